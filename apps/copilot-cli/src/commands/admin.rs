@@ -0,0 +1,173 @@
+//! Admin commands: compliance reports, access reviews and bulk user
+//! management
+
+use crate::AdminCommands;
+use anyhow::Result;
+use colored::Colorize;
+use copilot_sdk::{AccessReview, AccessReviewRequest, CopilotClient, UserImportReport};
+use std::path::PathBuf;
+use tabled::{Table, Tabled};
+
+pub async fn run(
+    api_url: &str,
+    api_key: Option<&str>,
+    cmd: AdminCommands,
+    format: &str,
+) -> Result<()> {
+    let client = CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .build()?;
+
+    match cmd {
+        AdminCommands::ComplianceReport { period, output, format } => {
+            compliance_report(&client, period.as_deref(), output, &format).await
+        }
+        AdminCommands::AccessReviews { period } => {
+            let reviews = client.list_access_reviews(period.as_deref()).await?;
+            print_reviews(&reviews, format)
+        }
+        AdminCommands::ReviewAccess { user, decision, roles, note } => {
+            let review = client
+                .record_access_review(&AccessReviewRequest {
+                    subject: user,
+                    roles,
+                    decision,
+                    note,
+                })
+                .await?;
+            println!(
+                "{} access review of {}: {}",
+                "Recorded".green(),
+                review.subject.cyan(),
+                review.decision
+            );
+            Ok(())
+        }
+        AdminCommands::ExportUsers { output } => {
+            let csv = client.export_users().await?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, csv)?;
+                    println!("{} users to {}", "Exported".green(), path.display().to_string().cyan());
+                }
+                None => print!("{}", csv),
+            }
+            Ok(())
+        }
+        AdminCommands::ImportUsers { file } => {
+            let csv = std::fs::read_to_string(&file)?;
+            let report = client.import_users(csv).await?;
+            print_import(&report, format)
+        }
+    }
+}
+
+async fn compliance_report(
+    client: &CopilotClient,
+    period: Option<&str>,
+    output: Option<PathBuf>,
+    format: &str,
+) -> Result<()> {
+    let content = match format {
+        "json" => serde_json::to_string_pretty(&client.compliance_report(period).await?)?,
+        _ => client.compliance_report_markdown(period).await?,
+    };
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, content)?;
+            println!("{} to {}", "Exported".green(), path.display().to_string().cyan());
+        }
+        None => println!("{}", content),
+    }
+
+    Ok(())
+}
+
+fn print_reviews(reviews: &[AccessReview], format: &str) -> Result<()> {
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(reviews)?),
+        "yaml" => println!("{}", serde_yaml::to_string(reviews)?),
+        _ => {
+            if reviews.is_empty() {
+                println!("{}", "No access reviews in the period.".dimmed());
+                return Ok(());
+            }
+
+            #[derive(Tabled)]
+            struct ReviewRow {
+                #[tabled(rename = "Reviewed")]
+                reviewed_at: String,
+                #[tabled(rename = "Reviewer")]
+                reviewer: String,
+                #[tabled(rename = "User")]
+                subject: String,
+                #[tabled(rename = "Roles")]
+                roles: String,
+                #[tabled(rename = "Decision")]
+                decision: String,
+                #[tabled(rename = "Note")]
+                note: String,
+            }
+
+            let rows: Vec<ReviewRow> = reviews
+                .iter()
+                .map(|r| ReviewRow {
+                    reviewed_at: r.reviewed_at.clone(),
+                    reviewer: r.reviewer.clone(),
+                    subject: r.subject.clone(),
+                    roles: r.roles.join(", "),
+                    decision: r.decision.clone(),
+                    note: r.note.clone().unwrap_or_default(),
+                })
+                .collect();
+            println!("{}", Table::new(rows));
+        }
+    }
+
+    Ok(())
+}
+
+fn print_import(report: &UserImportReport, format: &str) -> Result<()> {
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(report)?),
+        "yaml" => println!("{}", serde_yaml::to_string(report)?),
+        _ => {
+            println!(
+                "{} {} created, {} updated, {} unchanged",
+                "Imported:".green(),
+                report.created,
+                report.updated,
+                report.unchanged
+            );
+            if report.failures.is_empty() {
+                return Ok(());
+            }
+
+            #[derive(Tabled)]
+            struct FailureRow {
+                #[tabled(rename = "Line")]
+                line: usize,
+                #[tabled(rename = "User")]
+                username: String,
+                #[tabled(rename = "Error")]
+                error: String,
+            }
+
+            println!("{} {} rows failed", "Failed:".red(), report.failures.len());
+            let rows: Vec<FailureRow> = report
+                .failures
+                .iter()
+                .map(|f| FailureRow {
+                    line: f.line,
+                    username: f.username.clone().unwrap_or_default(),
+                    error: f.error.clone(),
+                })
+                .collect();
+            println!("{}", Table::new(rows));
+        }
+    }
+
+    Ok(())
+}
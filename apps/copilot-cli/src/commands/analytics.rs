@@ -0,0 +1,111 @@
+//! Analytics commands: question topics and knowledge gaps
+
+use crate::AnalyticsCommands;
+use anyhow::Result;
+use colored::Colorize;
+use copilot_sdk::{CopilotClient, KnowledgeGapReport, QueryTopic};
+use tabled::{Table, Tabled};
+
+pub async fn run(
+    api_url: &str,
+    api_key: Option<&str>,
+    cmd: AnalyticsCommands,
+    format: &str,
+) -> Result<()> {
+    let client = CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .build()?;
+
+    match cmd {
+        AnalyticsCommands::Gaps { days, all } => {
+            let report = client.knowledge_gaps(Some(days)).await?;
+            print_report(&report, all, format)
+        }
+    }
+}
+
+fn print_report(report: &KnowledgeGapReport, all: bool, format: &str) -> Result<()> {
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(report)?),
+        "yaml" => println!("{}", serde_yaml::to_string(report)?),
+        _ => {
+            println!(
+                "{} questions since {}, {} unanswered",
+                report.total_queries,
+                report.since,
+                report.unanswered_queries.to_string().yellow()
+            );
+            if report.untopical_queries > 0 {
+                println!(
+                    "{}",
+                    format!(
+                        "{} questions have no topic: the tenant does not retain their text.",
+                        report.untopical_queries
+                    )
+                    .dimmed()
+                );
+            }
+
+            let topics = if all { &report.topics } else { &report.gaps };
+            if topics.is_empty() {
+                let none = if all {
+                    "No questions in the period."
+                } else {
+                    "No knowledge gaps found."
+                };
+                println!("{}", none.dimmed());
+                return Ok(());
+            }
+            println!("{}", print_topics(topics));
+
+            // Example questions tell writers what the missing content is
+            for topic in topics.iter().filter(|t| !t.examples.is_empty()) {
+                println!("\n{}", topic.label.cyan().bold());
+                for example in &topic.examples {
+                    println!("  - {}", example);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_topics(topics: &[QueryTopic]) -> Table {
+    #[derive(Tabled)]
+    struct TopicRow {
+        #[tabled(rename = "Topic")]
+        label: String,
+        #[tabled(rename = "Questions")]
+        queries: u64,
+        #[tabled(rename = "Unanswered")]
+        unanswered: String,
+        #[tabled(rename = "Low confidence")]
+        low_confidence: u64,
+        #[tabled(rename = "Uncited")]
+        uncited: u64,
+        #[tabled(rename = "Mean confidence")]
+        mean_confidence: String,
+    }
+
+    let rows: Vec<TopicRow> = topics
+        .iter()
+        .map(|t| TopicRow {
+            label: t.label.clone(),
+            queries: t.queries,
+            unanswered: format!(
+                "{} ({:.0}%)",
+                t.unanswered,
+                t.unanswered as f64 * 100.0 / t.queries.max(1) as f64
+            ),
+            low_confidence: t.low_confidence,
+            uncited: t.uncited,
+            mean_confidence: t
+                .mean_confidence
+                .map(|c| format!("{:.2}", c))
+                .unwrap_or_else(|| "-".to_string()),
+        })
+        .collect();
+    Table::new(rows)
+}
@@ -0,0 +1,285 @@
+//! Apply code edits proposed in chat (`copilot apply`)
+//!
+//! Fetches the unified diffs from a session's latest response, previews them
+//! and checks every hunk against the working tree. Files are only written when
+//! all edits apply cleanly; optionally the edits land on a new git branch and
+//! are committed.
+
+use crate::ApplyArgs;
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use copilot_sdk::{CopilotClient, FileEdit};
+use dialoguer::Confirm;
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// Result of checking one file edit against the working tree
+struct Planned {
+    edit: FileEdit,
+    /// New contents (`None` deletes the file), or why the edit conflicts
+    outcome: std::result::Result<Option<String>, String>,
+}
+
+#[derive(Debug, Serialize)]
+struct FileReport {
+    path: String,
+    change: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conflict: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    session_id: String,
+    files: Vec<FileReport>,
+    applied: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit: Option<String>,
+}
+
+pub async fn run(
+    api_url: &str,
+    api_key: Option<&str>,
+    args: ApplyArgs,
+    format: &str,
+) -> Result<()> {
+    let client = CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .build()?;
+
+    let proposed = client.proposed_edits(&args.session).await?;
+    if proposed.edits.is_empty() {
+        if format == "text" {
+            println!(
+                "{}",
+                format!(
+                    "No code edits found in the latest response of session {}",
+                    args.session
+                )
+                .dimmed()
+            );
+        }
+        return Ok(());
+    }
+
+    let plan: Vec<Planned> = proposed
+        .edits
+        .into_iter()
+        .map(|edit| {
+            let outcome = check(&args.dir, &edit);
+            Planned { edit, outcome }
+        })
+        .collect();
+    let conflicts = plan.iter().filter(|p| p.outcome.is_err()).count();
+
+    let mut report = Report {
+        session_id: proposed.session_id,
+        files: plan.iter().map(file_report).collect(),
+        applied: false,
+        branch: None,
+        commit: None,
+    };
+
+    if format == "text" {
+        for planned in &plan {
+            print_preview(planned);
+        }
+    }
+
+    if conflicts > 0 {
+        print_report(&report, format)?;
+        bail!(
+            "{} of {} file edits conflict with the working tree; nothing was changed",
+            conflicts,
+            plan.len()
+        );
+    }
+    if args.dry_run {
+        if format == "text" {
+            println!("{} All edits apply cleanly (dry run)", "✓".green());
+        }
+        return print_report(&report, format);
+    }
+
+    let paths: Vec<&str> = plan.iter().map(|p| p.edit.path.as_str()).collect();
+    let use_git = args.branch.is_some() || args.commit.is_some();
+    if use_git {
+        check_git(&args.dir, &paths)?;
+    }
+
+    if !args.yes {
+        let confirmed = Confirm::new()
+            .with_prompt(format!("Apply edits to {} file(s)?", plan.len()))
+            .default(true)
+            .interact()?;
+        if !confirmed {
+            println!("Aborted; nothing was changed");
+            return Ok(());
+        }
+    }
+
+    if let Some(branch) = &args.branch {
+        git(&args.dir, &["checkout", "-b", branch])?;
+        report.branch = Some(branch.clone());
+    }
+
+    for planned in &plan {
+        let target = args.dir.join(&planned.edit.path);
+        match &planned.outcome {
+            Ok(Some(contents)) => {
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&target, contents)
+                    .with_context(|| format!("Failed to write {}", target.display()))?;
+            }
+            Ok(None) => std::fs::remove_file(&target)
+                .with_context(|| format!("Failed to delete {}", target.display()))?,
+            Err(_) => unreachable!("conflicting edits are rejected above"),
+        }
+    }
+    report.applied = true;
+
+    if let Some(message) = &args.commit {
+        let message = if message.is_empty() {
+            format!("Apply edits from copilot session {}", report.session_id)
+        } else {
+            message.clone()
+        };
+        let mut add = vec!["add", "--all", "--"];
+        add.extend(&paths);
+        git(&args.dir, &add)?;
+        git(&args.dir, &["commit", "-q", "-m", &message])?;
+        report.commit = Some(git(&args.dir, &["rev-parse", "--short", "HEAD"])?);
+    }
+
+    if format == "text" {
+        println!("{} Applied edits to {} file(s)", "✓".green(), plan.len());
+        if let Some(branch) = &report.branch {
+            println!("  Branch: {}", branch.cyan());
+        }
+        if let Some(commit) = &report.commit {
+            println!("  Commit: {}", commit.cyan());
+        }
+    }
+    print_report(&report, format)
+}
+
+/// Check an edit against the file on disk
+fn check(dir: &Path, edit: &FileEdit) -> std::result::Result<Option<String>, String> {
+    let path = safe_path(&edit.path)?;
+    let target = dir.join(path);
+    let original = match std::fs::read_to_string(&target) {
+        Ok(text) => Some(text),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(format!("cannot read {}: {}", target.display(), e)),
+    };
+    edit.apply(original.as_deref())
+        .map_err(|conflict| match conflict.hunk {
+            Some(hunk) => format!("hunk {}: {}", hunk + 1, conflict.reason),
+            None => conflict.reason,
+        })
+}
+
+/// Only accept paths that stay inside the project directory
+fn safe_path(path: &str) -> std::result::Result<PathBuf, String> {
+    let path = PathBuf::from(path);
+    let inside = !path.as_os_str().is_empty()
+        && path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if inside {
+        Ok(path)
+    } else {
+        Err("path escapes the project directory".to_string())
+    }
+}
+
+fn file_report(planned: &Planned) -> FileReport {
+    let change = if planned.edit.created {
+        "created"
+    } else if planned.edit.deleted {
+        "deleted"
+    } else {
+        "modified"
+    };
+    FileReport {
+        path: planned.edit.path.clone(),
+        change,
+        conflict: planned.outcome.as_ref().err().cloned(),
+    }
+}
+
+fn print_preview(planned: &Planned) {
+    let edit = &planned.edit;
+    let status = match &planned.outcome {
+        Err(reason) => format!("CONFLICT: {}", reason).red().bold(),
+        Ok(_) if edit.created => "new file".green(),
+        Ok(_) if edit.deleted => "deleted".red(),
+        Ok(_) => "modified".yellow(),
+    };
+    println!("{} ({})", edit.path.bold(), status);
+
+    for hunk in &edit.hunks {
+        let header = format!(
+            "@@ -{},{} +{},{} @@ {}",
+            hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines, hunk.section
+        );
+        println!("{}", header.trim_end().cyan());
+        for line in &hunk.lines {
+            match line.chars().next() {
+                Some('+') => println!("{}", line.green()),
+                Some('-') => println!("{}", line.red()),
+                _ => println!("{}", line.dimmed()),
+            }
+        }
+    }
+    println!();
+}
+
+fn print_report(report: &Report, format: &str) -> Result<()> {
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(report)?),
+        "yaml" => println!("{}", serde_yaml::to_string(report)?),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Make sure git can branch and commit without picking up unrelated changes
+fn check_git(dir: &Path, paths: &[&str]) -> Result<()> {
+    git(dir, &["rev-parse", "--is-inside-work-tree"])
+        .context("--branch and --commit need a git working tree")?;
+
+    let mut status = vec!["status", "--porcelain", "--"];
+    status.extend(paths);
+    let dirty = git(dir, &status)?;
+    if !dirty.is_empty() {
+        bail!(
+            "These files have uncommitted changes; commit or stash them first:\n{}",
+            dirty
+        );
+    }
+    Ok(())
+}
+
+/// Run git in `dir`, returning its trimmed stdout
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,364 @@
+//! Platform backup and restore (`copilot server backup` / `copilot server restore`)
+//!
+//! A backup is a gzipped tarball holding a logical Postgres dump, the Redis
+//! keys that carry queue state and the context memory logs, plus a
+//! `manifest.json` that records the archive format version and the SHA-256 of
+//! every file. Restore checks the whole archive against its manifest before
+//! touching any data.
+
+use crate::{BackupArgs, RestoreArgs};
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use dialoguer::Confirm;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use walkdir::WalkDir;
+
+/// Newest archive layout this build reads and the one it writes
+const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const POSTGRES_DUMP: &str = "postgres.dump";
+const REDIS_DUMP: &str = "redis.json";
+const MEMORY_DIR: &str = "memory";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    format_version: u32,
+    created_at: DateTime<Utc>,
+    cli_version: String,
+    components: Vec<String>,
+    /// Archive-relative path to digest, for every file but the manifest
+    files: BTreeMap<String, FileDigest>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct FileDigest {
+    sha256: String,
+    bytes: u64,
+}
+
+/// A Redis key as captured by `DUMP`, restorable with `RESTORE`
+#[derive(Debug, Serialize, Deserialize)]
+struct RedisKey {
+    key: String,
+    /// Remaining time to live in milliseconds, 0 for none
+    ttl_ms: i64,
+    /// Base64 of the `DUMP` payload
+    dump: String,
+}
+
+/// Scratch directory removed when dropped
+struct Staging(PathBuf);
+
+impl Staging {
+    fn new(kind: &str) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("copilot-{}-{}", kind, uuid::Uuid::new_v4()));
+        fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+pub async fn backup(args: BackupArgs) -> Result<()> {
+    let sources = &args.sources;
+    if sources.database_url.is_none() && sources.redis_url.is_none() && sources.memory_dir.is_none() {
+        bail!("Nothing to back up: set --database-url, --redis-url or --memory-dir");
+    }
+
+    let output = args.output.unwrap_or_else(|| {
+        PathBuf::from(format!("copilot-backup-{}.tar.gz", Utc::now().format("%Y%m%d_%H%M%S")))
+    });
+    let staging = Staging::new("backup")?;
+    let mut components = Vec::new();
+
+    if let Some(url) = &sources.database_url {
+        println!("{} Postgres...", "Dumping".green());
+        run_tool(
+            Command::new("pg_dump")
+                .args(["--format=custom", "--no-owner", "--file"])
+                .arg(staging.0.join(POSTGRES_DUMP))
+                .arg(url),
+            "pg_dump",
+        )?;
+        components.push("postgres".to_string());
+    }
+
+    if let Some(url) = &sources.redis_url {
+        println!("{} Redis queues...", "Dumping".green());
+        let keys = dump_redis(url, &sources.redis_patterns).await?;
+        println!("  {} keys", keys.len());
+        fs::write(staging.0.join(REDIS_DUMP), serde_json::to_vec_pretty(&keys)?)?;
+        components.push("redis".to_string());
+    }
+
+    if let Some(dir) = &sources.memory_dir {
+        println!("{} context memory from {}...", "Copying".green(), dir.display());
+        let copied = copy_tree(dir, &staging.0.join(MEMORY_DIR))?;
+        println!("  {} files", copied);
+        components.push("memory".to_string());
+    }
+
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        created_at: Utc::now(),
+        cli_version: env!("CARGO_PKG_VERSION").to_string(),
+        components,
+        files: digest_files(&staging.0)?,
+    };
+    fs::write(staging.0.join(MANIFEST), serde_json::to_vec_pretty(&manifest)?)?;
+
+    run_tool(
+        Command::new("tar")
+            .arg("-czf")
+            .arg(&output)
+            .arg("-C")
+            .arg(&staging.0)
+            .arg("."),
+        "tar",
+    )?;
+
+    println!(
+        "{} Backup written to {} ({})",
+        "✓".green(),
+        output.display().to_string().cyan(),
+        manifest.components.join(", ")
+    );
+    Ok(())
+}
+
+pub async fn restore(args: RestoreArgs) -> Result<()> {
+    let staging = Staging::new("restore")?;
+    run_tool(
+        Command::new("tar")
+            .arg("-xzf")
+            .arg(&args.archive)
+            .arg("-C")
+            .arg(&staging.0),
+        "tar",
+    )?;
+
+    let manifest = verify_archive(&staging.0)?;
+    println!(
+        "{} Archive verified: format v{}, created {} by copilot {} ({})",
+        "✓".green(),
+        manifest.format_version,
+        manifest.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        manifest.cli_version,
+        manifest.components.join(", ")
+    );
+    if args.verify_only {
+        return Ok(());
+    }
+
+    if !args.yes
+        && !Confirm::new()
+            .with_prompt("Restoring overwrites the current database, queues and memory. Continue?")
+            .default(false)
+            .interact()?
+    {
+        println!("{}", "Restore cancelled".yellow());
+        return Ok(());
+    }
+
+    let sources = &args.sources;
+    for component in &manifest.components {
+        match component.as_str() {
+            "postgres" => {
+                let Some(url) = &sources.database_url else {
+                    skip(component, "--database-url");
+                    continue;
+                };
+                println!("{} Postgres...", "Restoring".green());
+                run_tool(
+                    Command::new("pg_restore")
+                        .args(["--clean", "--if-exists", "--no-owner", "--dbname"])
+                        .arg(url)
+                        .arg(staging.0.join(POSTGRES_DUMP)),
+                    "pg_restore",
+                )?;
+            }
+            "redis" => {
+                let Some(url) = &sources.redis_url else {
+                    skip(component, "--redis-url");
+                    continue;
+                };
+                println!("{} Redis queues...", "Restoring".green());
+                let keys: Vec<RedisKey> =
+                    serde_json::from_slice(&fs::read(staging.0.join(REDIS_DUMP))?)?;
+                restore_redis(url, &keys).await?;
+                println!("  {} keys", keys.len());
+            }
+            "memory" => {
+                let Some(dir) = &sources.memory_dir else {
+                    skip(component, "--memory-dir");
+                    continue;
+                };
+                println!("{} context memory to {}...", "Restoring".green(), dir.display());
+                let copied = copy_tree(&staging.0.join(MEMORY_DIR), dir)?;
+                println!("  {} files", copied);
+            }
+            other => println!("{} Unknown component {} skipped", "!".yellow(), other),
+        }
+    }
+
+    println!("{} Restore complete", "✓".green());
+    Ok(())
+}
+
+fn skip(component: &str, flag: &str) {
+    println!(
+        "{} Skipping {}: {} not set",
+        "!".yellow(),
+        component,
+        flag
+    );
+}
+
+fn run_tool(command: &mut Command, name: &str) -> Result<()> {
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to run {} (is it installed?)", name))?;
+    if !status.success() {
+        bail!("{} exited with {}", name, status);
+    }
+    Ok(())
+}
+
+async fn dump_redis(url: &str, patterns: &[String]) -> Result<Vec<RedisKey>> {
+    let client = redis::Client::open(url)?;
+    let mut con = client.get_multiplexed_async_connection().await?;
+
+    let mut names = BTreeSet::new();
+    for pattern in patterns {
+        let mut iter = con.scan_match::<_, String>(pattern).await?;
+        while let Some(key) = iter.next_item().await {
+            names.insert(key);
+        }
+    }
+
+    let mut keys = Vec::with_capacity(names.len());
+    for key in names {
+        let dump: Option<Vec<u8>> = redis::cmd("DUMP").arg(&key).query_async(&mut con).await?;
+        // Keys that expired since the scan are left out
+        let Some(dump) = dump else { continue };
+        let ttl: i64 = con.pttl(&key).await?;
+        keys.push(RedisKey {
+            key,
+            ttl_ms: ttl.max(0),
+            dump: BASE64.encode(dump),
+        });
+    }
+    Ok(keys)
+}
+
+async fn restore_redis(url: &str, keys: &[RedisKey]) -> Result<()> {
+    let client = redis::Client::open(url)?;
+    let mut con = client.get_multiplexed_async_connection().await?;
+
+    for key in keys {
+        let dump = BASE64
+            .decode(&key.dump)
+            .with_context(|| format!("Corrupt Redis dump for key {}", key.key))?;
+        redis::cmd("RESTORE")
+            .arg(&key.key)
+            .arg(key.ttl_ms)
+            .arg(dump)
+            .arg("REPLACE")
+            .query_async::<_, ()>(&mut con)
+            .await
+            .with_context(|| format!("Failed to restore Redis key {}", key.key))?;
+    }
+    Ok(())
+}
+
+/// Copy every file under `from` to the same relative path under `to`
+fn copy_tree(from: &Path, to: &Path) -> Result<usize> {
+    let mut copied = 0;
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let target = to.join(entry.path().strip_prefix(from)?);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(entry.path(), &target)
+            .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+/// Digest of every file under `root` except the manifest
+fn digest_files(root: &Path) -> Result<BTreeMap<String, FileDigest>> {
+    let mut files = BTreeMap::new();
+    for entry in WalkDir::new(root) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(root)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        if relative == MANIFEST {
+            continue;
+        }
+
+        let mut hasher = Sha256::new();
+        let bytes = std::io::copy(&mut File::open(entry.path())?, &mut hasher)?;
+        files.insert(
+            relative,
+            FileDigest {
+                sha256: format!("{:x}", hasher.finalize()),
+                bytes,
+            },
+        );
+    }
+    Ok(files)
+}
+
+/// Check an extracted archive against its manifest
+fn verify_archive(root: &Path) -> Result<Manifest> {
+    let manifest: Manifest = serde_json::from_slice(
+        &fs::read(root.join(MANIFEST)).context("Archive has no manifest.json")?,
+    )
+    .context("Archive manifest is malformed")?;
+
+    if manifest.format_version > FORMAT_VERSION {
+        bail!(
+            "Archive format v{} is newer than this copilot supports (v{}); upgrade the CLI",
+            manifest.format_version,
+            FORMAT_VERSION
+        );
+    }
+
+    let actual = digest_files(root)?;
+    for (path, expected) in &manifest.files {
+        match actual.get(path) {
+            None => bail!("Archive is missing {}", path),
+            Some(digest) if digest != expected => bail!("Checksum mismatch for {}", path),
+            Some(_) => {}
+        }
+    }
+    if let Some(extra) = actual.keys().find(|path| !manifest.files.contains_key(*path)) {
+        bail!("Archive contains {} which is not in its manifest", extra);
+    }
+
+    Ok(manifest)
+}
@@ -3,12 +3,17 @@
 //! This module provides the CLI `run` subcommand that invokes run_all_benchmarks()
 //! and writes benchmark results to the canonical output directories.
 
+use crate::output::{self, CommandOutput};
 use anyhow::Result;
 use colored::Colorize;
 use copilot_benchmarks::{
-    run_all_benchmarks_with_config, run_benchmark, BenchmarkConfig, BenchmarkIo,
-    MarkdownGenerator,
+    run_all_benchmarks_with_config, run_benchmark, BenchmarkConfig, BenchmarkIo, BenchmarkResult,
+    Determinism, Isolation, MachineIdentity, MarkdownGenerator, Provenance,
 };
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Run benchmarks subcommand
 #[derive(Debug, Clone)]
@@ -23,6 +28,16 @@ pub enum BenchmarkCommand {
         format: String,
         /// Skip writing results to disk
         no_write: bool,
+        /// Seed for a deterministic run
+        seed: Option<u64>,
+        /// Seconds a benchmark may run before it is stopped
+        timeout: u64,
+        /// Run each benchmark on its own thread
+        isolate: bool,
+        /// CPUs to pin isolated benchmarks to
+        pin_cpus: Vec<usize>,
+        /// Sign the combined results with the machine identity
+        sign: bool,
     },
     /// List available benchmarks
     List,
@@ -31,6 +46,13 @@ pub enum BenchmarkCommand {
         /// Target ID to show
         target_id: String,
     },
+    /// Verify a signed combined results file
+    Verify {
+        /// Combined results file
+        file: PathBuf,
+        /// Key ID the results must be signed by
+        key_id: Option<String>,
+    },
 }
 
 /// Execute the benchmark command
@@ -41,11 +63,24 @@ pub async fn run(cmd: BenchmarkCommand, format: &str) -> Result<()> {
             parallel,
             format: output_format,
             no_write,
+            seed,
+            timeout,
+            isolate,
+            pin_cpus,
+            sign,
         } => {
-            run_benchmarks(filter, parallel, &output_format, no_write).await
+            let isolation = if isolate || !pin_cpus.is_empty() {
+                Isolation::Thread { cpus: pin_cpus }
+            } else {
+                Isolation::Shared
+            };
+            let timeout = Duration::from_secs(timeout);
+            let identity_key = sign.then(MachineIdentity::default_path);
+            run_benchmarks(filter, parallel, &output_format, no_write, seed, timeout, isolation, identity_key).await
         }
         BenchmarkCommand::List => list_benchmarks(format),
         BenchmarkCommand::Show { target_id } => show_benchmark(&target_id, format).await,
+        BenchmarkCommand::Verify { file, key_id } => verify_results(&file, key_id.as_deref(), format),
     }
 }
 
@@ -54,8 +89,13 @@ async fn run_benchmarks(
     parallel: bool,
     format: &str,
     no_write: bool,
+    seed: Option<u64>,
+    timeout: Duration,
+    isolation: Isolation,
+    identity_key: Option<PathBuf>,
 ) -> Result<()> {
-    println!("{}", "Running benchmarks...".cyan().bold());
+    // Progress goes to stderr so JSON and YAML output stay parseable
+    eprintln!("{}", "Running benchmarks...".cyan().bold());
 
     let config = BenchmarkConfig {
         write_results: !no_write,
@@ -63,154 +103,186 @@ async fn run_benchmarks(
         parallel,
         max_parallel: 4,
         filter: filter.clone(),
+        determinism: seed.map_or_else(Determinism::system, Determinism::seeded),
+        target_timeout: timeout,
+        isolation: isolation.clone(),
+        identity_key: identity_key.clone(),
+        ..Default::default()
     };
 
+    // Ctrl-C stops the run, reporting the unfinished benchmarks as cancelled
+    let cancellation = config.cancellation.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancellation.cancel();
+        }
+    });
+
     if let Some(ref f) = filter {
-        println!("Filter: {}", f.yellow());
+        eprintln!("Filter: {}", f.yellow());
     }
     if parallel {
-        println!("Mode: {}", "parallel".green());
+        eprintln!("Mode: {}", "parallel".green());
+    }
+    if let Some(seed) = seed {
+        eprintln!("Seed: {}", seed.to_string().yellow());
+    }
+    if let Isolation::Thread { cpus } = &isolation {
+        if cpus.is_empty() {
+            eprintln!("Isolation: {}", "thread per benchmark".green());
+        } else {
+            eprintln!("Isolation: {} (CPUs {:?})", "thread per benchmark".green(), cpus);
+        }
+    }
+    if let Some(ref path) = identity_key {
+        let identity = MachineIdentity::load_or_create(path)?;
+        eprintln!("Signing key: {}", identity.key_id().yellow());
     }
 
     let start = std::time::Instant::now();
     let results = run_all_benchmarks_with_config(config).await;
-    let duration = start.elapsed();
+    let run = BenchmarkRun {
+        results,
+        duration: start.elapsed(),
+        output_dir: (!no_write).then(|| BenchmarkIo::new().output_dir().to_path_buf()),
+    };
+
+    output::emit(&run, format)
+}
+
+/// Output of `copilot run`: the results of every benchmark run
+#[derive(Serialize, JsonSchema)]
+#[serde(transparent)]
+pub struct BenchmarkRun {
+    results: Vec<BenchmarkResult>,
+    #[serde(skip)]
+    duration: Duration,
+    #[serde(skip)]
+    output_dir: Option<PathBuf>,
+}
 
-    // Display results
-    match format {
-        "json" => {
-            let json = serde_json::to_string_pretty(&results)?;
-            println!("{}", json);
+impl CommandOutput for BenchmarkRun {
+    fn print_text(&self) {
+        println!("\n{}", "Benchmark Results".green().bold());
+        println!("{}", "─".repeat(60));
+
+        let mut passed = 0;
+        let mut failed = 0;
+
+        for result in &self.results {
+            let status = if result.is_success() {
+                passed += 1;
+                "✅".to_string()
+            } else {
+                failed += 1;
+                "❌".to_string()
+            };
+
+            let duration_str = result
+                .duration_ms()
+                .map(|d| format!("{}ms", d))
+                .unwrap_or_else(|| "-".to_string());
+
+            println!(
+                "{} {} {}",
+                status,
+                result.target_id.cyan(),
+                duration_str.dimmed()
+            );
         }
-        _ => {
-            println!("\n{}", "Benchmark Results".green().bold());
-            println!("{}", "─".repeat(60));
-
-            let mut passed = 0;
-            let mut failed = 0;
-
-            for result in &results {
-                let status = if result.is_success() {
-                    passed += 1;
-                    "✅".to_string()
-                } else {
-                    failed += 1;
-                    "❌".to_string()
-                };
-
-                let duration_str = result
-                    .duration_ms()
-                    .map(|d| format!("{}ms", d))
-                    .unwrap_or_else(|| "-".to_string());
-
-                println!(
-                    "{} {} {}",
-                    status,
-                    result.target_id.cyan(),
-                    duration_str.dimmed()
-                );
-            }
 
-            println!("{}", "─".repeat(60));
+        println!("{}", "─".repeat(60));
+        println!(
+            "Total: {} | Passed: {} | Failed: {} | Duration: {:?}",
+            self.results.len().to_string().bold(),
+            passed.to_string().green(),
+            if failed > 0 {
+                failed.to_string().red()
+            } else {
+                failed.to_string().green()
+            },
+            self.duration
+        );
+
+        if let Some(dir) = &self.output_dir {
             println!(
-                "Total: {} | Passed: {} | Failed: {} | Duration: {:?}",
-                results.len().to_string().bold(),
-                passed.to_string().green(),
-                if failed > 0 {
-                    failed.to_string().red()
-                } else {
-                    failed.to_string().green()
-                },
-                duration
+                "\n{} {}",
+                "Results written to:".dimmed(),
+                dir.display()
             );
+        }
+    }
+}
+
+/// A benchmark target listed by `copilot benchmark list`
+#[derive(Serialize, JsonSchema)]
+pub struct BenchmarkTarget {
+    id: String,
+    description: Option<String>,
+}
+
+impl CommandOutput for Vec<BenchmarkTarget> {
+    fn print_text(&self) {
+        println!("{}", "Available Benchmarks".green().bold());
+        println!("{}", "─".repeat(60));
 
-            if !no_write {
-                let io = BenchmarkIo::new();
-                println!(
-                    "\n{} {}",
-                    "Results written to:".dimmed(),
-                    io.output_dir().display()
-                );
+        for target in self {
+            println!("  {} {}", "•".cyan(), target.id.bold());
+            if let Some(desc) = &target.description {
+                println!("    {}", desc.dimmed());
             }
         }
-    }
 
-    Ok(())
+        println!("{}", "─".repeat(60));
+        println!("Total: {} benchmarks", self.len().to_string().bold());
+    }
 }
 
 fn list_benchmarks(format: &str) -> Result<()> {
-    let targets = copilot_benchmarks::all_targets();
-
-    match format {
-        "json" => {
-            let list: Vec<serde_json::Value> = targets
-                .iter()
-                .map(|t| {
-                    serde_json::json!({
-                        "id": t.id(),
-                        "description": t.description()
-                    })
-                })
-                .collect();
-            println!("{}", serde_json::to_string_pretty(&list)?);
-        }
-        _ => {
-            println!("{}", "Available Benchmarks".green().bold());
-            println!("{}", "─".repeat(60));
-
-            for target in &targets {
-                println!("  {} {}", "•".cyan(), target.id().bold());
-                if let Some(desc) = target.description() {
-                    println!("    {}", desc.dimmed());
-                }
+    let targets: Vec<BenchmarkTarget> = copilot_benchmarks::all_targets()
+        .iter()
+        .map(|t| BenchmarkTarget {
+            id: t.id().to_string(),
+            description: t.description().map(String::from),
+        })
+        .collect();
+
+    output::emit(&targets, format)
+}
+
+impl CommandOutput for BenchmarkResult {
+    fn print_text(&self) {
+        println!("\n{}", "Result".green().bold());
+        println!("{}", "─".repeat(40));
+        println!("Target: {}", self.target_id.cyan());
+        println!(
+            "Status: {}",
+            if self.is_success() {
+                "✅ Passed".green()
+            } else {
+                "❌ Failed".red()
             }
+        );
+        println!("Timestamp: {}", self.timestamp);
 
-            println!("{}", "─".repeat(60));
-            println!("Total: {} benchmarks", targets.len().to_string().bold());
+        if let Some(duration) = self.duration_ms() {
+            println!("Duration: {}ms", duration);
         }
-    }
 
-    Ok(())
+        if let Some(error) = self.error() {
+            println!("Error: {}", error.red());
+        }
+
+        println!("\n{}", "Metrics:".bold());
+        println!("{}", serde_json::to_string_pretty(&self.metrics).unwrap_or_default());
+    }
 }
 
 async fn show_benchmark(target_id: &str, format: &str) -> Result<()> {
-    println!("Running benchmark: {}", target_id.cyan().bold());
-
-    let result = run_benchmark(target_id).await;
-
-    match result {
-        Some(result) => {
-            match format {
-                "json" => {
-                    println!("{}", serde_json::to_string_pretty(&result)?);
-                }
-                _ => {
-                    println!("\n{}", "Result".green().bold());
-                    println!("{}", "─".repeat(40));
-                    println!("Target: {}", result.target_id.cyan());
-                    println!(
-                        "Status: {}",
-                        if result.is_success() {
-                            "✅ Passed".green()
-                        } else {
-                            "❌ Failed".red()
-                        }
-                    );
-                    println!("Timestamp: {}", result.timestamp);
-
-                    if let Some(duration) = result.duration_ms() {
-                        println!("Duration: {}ms", duration);
-                    }
-
-                    if let Some(error) = result.error() {
-                        println!("Error: {}", error.red());
-                    }
-
-                    println!("\n{}", "Metrics:".bold());
-                    println!("{}", serde_json::to_string_pretty(&result.metrics)?);
-                }
-            }
-        }
+    eprintln!("Running benchmark: {}", target_id.cyan().bold());
+
+    match run_benchmark(target_id).await {
+        Some(result) => output::emit(&result, format),
         None => {
             eprintln!(
                 "{}: Benchmark target '{}' not found",
@@ -220,6 +292,55 @@ async fn show_benchmark(target_id: &str, format: &str) -> Result<()> {
             std::process::exit(1);
         }
     }
+}
+
+/// Output of `copilot benchmark verify`
+#[derive(Serialize, JsonSchema)]
+pub struct VerifiedResults {
+    verified: bool,
+    key_id: String,
+    provenance: Provenance,
+    /// Number of results in the file
+    results: usize,
+}
+
+impl CommandOutput for VerifiedResults {
+    fn print_text(&self) {
+        let provenance = &self.provenance;
+        let unknown = || "unknown".to_string();
+        println!("{} signed by key {}", "✅ Verified".green(), self.key_id.cyan());
+        println!("{}", "─".repeat(40));
+        println!("Results: {}", self.results);
+        println!(
+            "Commit: {}{}",
+            provenance.git_sha.clone().unwrap_or_else(unknown),
+            if provenance.git_dirty == Some(true) { " (dirty)" } else { "" }
+        );
+        println!("Rustc: {}", provenance.rustc_version.clone().unwrap_or_else(unknown));
+        println!(
+            "CPU: {} ({} cores, {}/{})",
+            provenance.cpu_model.clone().unwrap_or_else(unknown),
+            provenance.cpu_count,
+            provenance.os,
+            provenance.arch
+        );
+        println!("Cargo.lock: {}", provenance.lockfile_sha256.clone().unwrap_or_else(unknown));
+        println!("Collected: {}", provenance.collected_at);
+    }
+}
 
-    Ok(())
+fn verify_results(file: &Path, key_id: Option<&str>, format: &str) -> Result<()> {
+    let signed = BenchmarkIo::read_signed(file)?;
+    let signature = match key_id {
+        Some(key_id) => signed.verify_signed_by(key_id)?,
+        None => signed.verify()?,
+    };
+
+    let verified = VerifiedResults {
+        verified: true,
+        key_id: signature.key_id.clone(),
+        provenance: signed.provenance.clone(),
+        results: signed.results.len(),
+    };
+    output::emit(&verified, format)
 }
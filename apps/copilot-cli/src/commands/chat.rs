@@ -2,7 +2,7 @@
 
 use anyhow::Result;
 use colored::Colorize;
-use copilot_sdk::CopilotClient;
+use copilot_sdk::{CopilotClient, MessagePart};
 use dialoguer::{theme::ColorfulTheme, Input};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::time::Duration;
@@ -113,7 +113,21 @@ async fn send_message(client: &CopilotClient, session_id: &str, message: &str) -
 
     // Print metadata if available
     if let Some(usage) = &response.usage {
-        println!("{}", format!("[{} tokens]", usage.total_tokens).dimmed());
+        let footer = match &response.session_usage {
+            Some(session) => format!(
+                "[{} tokens, ${:.4} | session: {} tokens, ${:.4}]",
+                usage.total_tokens, usage.cost_usd, session.total_tokens, session.cost_usd
+            ),
+            None => format!("[{} tokens, ${:.4}]", usage.total_tokens, usage.cost_usd),
+        };
+        println!("{}", footer.dimmed());
+    }
+    if let Some(downsize) = &response.context_downsize {
+        let note = format!(
+            "[context cut from {} to {} tokens to fit the {}-token window of {}]",
+            downsize.tokens_before, downsize.tokens_after, downsize.window, downsize.model
+        );
+        println!("{}", note.yellow().dimmed());
     }
 
     Ok(())
@@ -141,15 +155,50 @@ async fn show_history(client: &CopilotClient, session_id: &str) -> Result<()> {
         let role_str = match msg.role.as_str() {
             "user" => "You".green(),
             "assistant" => "Assistant".cyan(),
+            "tool" => "Tool".yellow(),
             _ => msg.role.normal(),
         };
         println!("{}: {}", role_str.bold(), msg.content);
+        print_parts(&msg.parts);
         println!();
     }
 
     Ok(())
 }
 
+/// Print the tool calls, results and sandbox output of a message, indented
+/// under it
+pub(crate) fn print_parts(parts: &[MessagePart]) {
+    for part in parts {
+        let tool = part.tool.as_deref().unwrap_or("?");
+        match part.kind.as_str() {
+            "tool_call" => {
+                let arguments = part.arguments.as_ref().map(|args| args.to_string()).unwrap_or_default();
+                println!("  {} {} {}", "→".yellow(), tool.yellow().bold(), arguments.dimmed());
+            }
+            "tool_result" => match &part.error {
+                Some(error) => println!("  {} {} {}", "✗".red(), tool.red().bold(), error.red()),
+                None => {
+                    let output = part.output.as_ref().map(|output| output.to_string()).unwrap_or_default();
+                    let cached = if part.cached { " (cached)" } else { "" };
+                    println!("  {} {}{} {}", "←".green(), tool.green().bold(), cached.dimmed(), output.dimmed());
+                }
+            },
+            "sandbox_output" => {
+                let exit_code = part.exit_code.map_or("?".to_string(), |code| code.to_string());
+                println!("  {} exit {}", "$".magenta(), exit_code);
+                for line in part.stdout.as_deref().unwrap_or_default().lines() {
+                    println!("    {} {}", "|".dimmed(), line);
+                }
+                for line in part.stderr.as_deref().unwrap_or_default().lines() {
+                    println!("    {} {}", "!".red(), line.red());
+                }
+            }
+            other => println!("  [{}]", other.dimmed()),
+        }
+    }
+}
+
 async fn export_session(client: &CopilotClient, session_id: &str) -> Result<()> {
     let history = client.get_history(session_id).await?;
     let filename = format!("conversation_{}.json", session_id);
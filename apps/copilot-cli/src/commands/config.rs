@@ -1,9 +1,11 @@
 //! Configuration management commands
 
+use crate::output::{self, CommandOutput};
 use crate::ConfigCommands;
 use anyhow::Result;
 use colored::Colorize;
 use dialoguer::Confirm;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -52,48 +54,89 @@ impl CliConfig {
     }
 }
 
-pub async fn run(cmd: ConfigCommands) -> Result<()> {
+pub async fn run(cmd: ConfigCommands, format: &str) -> Result<()> {
     match cmd {
-        ConfigCommands::Show => show_config().await,
+        ConfigCommands::Show => show_config(format).await,
         ConfigCommands::Set { key, value } => set_config(&key, &value).await,
-        ConfigCommands::Get { key } => get_config(&key).await,
+        ConfigCommands::Get { key } => get_config(&key, format).await,
         ConfigCommands::Reset { force } => reset_config(force).await,
         ConfigCommands::Edit => edit_config().await,
     }
 }
 
-async fn show_config() -> Result<()> {
-    let config = CliConfig::load()?;
-    let path = CliConfig::config_path()?;
+/// Output of `copilot config show`; the API key itself is never shown
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ConfigView {
+    pub path: PathBuf,
+    pub api_url: Option<String>,
+    pub api_key_set: bool,
+    pub default_model: Option<String>,
+    pub output_format: Option<String>,
+    pub timeout_seconds: Option<u64>,
+    pub custom: HashMap<String, String>,
+}
 
-    println!("{}: {}", "Config file".bold(), path.display());
-    println!();
+impl CommandOutput for ConfigView {
+    fn print_text(&self) {
+        println!("{}: {}", "Config file".bold(), self.path.display());
+        println!();
 
-    if let Some(url) = &config.api_url {
-        println!("{}: {}", "api_url".cyan(), url);
-    }
-    if config.api_key.is_some() {
-        println!("{}: {}", "api_key".cyan(), "[set]".dimmed());
-    }
-    if let Some(model) = &config.default_model {
-        println!("{}: {}", "default_model".cyan(), model);
-    }
-    if let Some(format) = &config.output_format {
-        println!("{}: {}", "output_format".cyan(), format);
-    }
-    if let Some(timeout) = config.timeout_seconds {
-        println!("{}: {}s", "timeout_seconds".cyan(), timeout);
+        if let Some(url) = &self.api_url {
+            println!("{}: {}", "api_url".cyan(), url);
+        }
+        if self.api_key_set {
+            println!("{}: {}", "api_key".cyan(), "[set]".dimmed());
+        }
+        if let Some(model) = &self.default_model {
+            println!("{}: {}", "default_model".cyan(), model);
+        }
+        if let Some(format) = &self.output_format {
+            println!("{}: {}", "output_format".cyan(), format);
+        }
+        if let Some(timeout) = self.timeout_seconds {
+            println!("{}: {}s", "timeout_seconds".cyan(), timeout);
+        }
+
+        if !self.custom.is_empty() {
+            println!();
+            println!("{}", "Custom settings:".bold());
+            for (key, value) in &self.custom {
+                println!("  {}: {}", key.cyan(), value);
+            }
+        }
     }
+}
 
-    if !config.custom.is_empty() {
-        println!();
-        println!("{}", "Custom settings:".bold());
-        for (key, value) in &config.custom {
-            println!("  {}: {}", key.cyan(), value);
+/// Output of `copilot config get`
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ConfigValue {
+    pub key: String,
+    /// The value, or null when unset; the API key is redacted
+    pub value: Option<String>,
+}
+
+impl CommandOutput for ConfigValue {
+    fn print_text(&self) {
+        match &self.value {
+            Some(v) => println!("{}", v),
+            None => println!("{}", "(not set)".dimmed()),
         }
     }
+}
 
-    Ok(())
+async fn show_config(format: &str) -> Result<()> {
+    let config = CliConfig::load()?;
+    let view = ConfigView {
+        path: CliConfig::config_path()?,
+        api_url: config.api_url,
+        api_key_set: config.api_key.is_some(),
+        default_model: config.default_model,
+        output_format: config.output_format,
+        timeout_seconds: config.timeout_seconds,
+        custom: config.custom,
+    };
+
+    output::emit(&view, format)
 }
 
 async fn set_config(key: &str, value: &str) -> Result<()> {
@@ -118,7 +161,7 @@ async fn set_config(key: &str, value: &str) -> Result<()> {
     Ok(())
 }
 
-async fn get_config(key: &str) -> Result<()> {
+async fn get_config(key: &str, format: &str) -> Result<()> {
     let config = CliConfig::load()?;
 
     let value = match key {
@@ -130,12 +173,7 @@ async fn get_config(key: &str) -> Result<()> {
         _ => config.custom.get(key).cloned(),
     };
 
-    match value {
-        Some(v) => println!("{}", v),
-        None => println!("{}", "(not set)".dimmed()),
-    }
-
-    Ok(())
+    output::emit(&ConfigValue { key: key.to_string(), value }, format)
 }
 
 async fn reset_config(force: bool) -> Result<()> {
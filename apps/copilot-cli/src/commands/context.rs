@@ -1,10 +1,17 @@
 //! Context management commands
 
-use crate::ContextCommands;
+use crate::output::{self, CommandOutput};
+use crate::watch::{self, Change, Watchable};
+use crate::{ContextCommands, ContextFilterArgs};
 use anyhow::Result;
 use colored::Colorize;
-use copilot_sdk::CopilotClient;
+use copilot_sdk::{
+    BootstrapJob, BulkContextJob, BulkContextOperation, ContextSearchFilter, ContextSearchResult, CopilotClient,
+};
 use dialoguer::Confirm;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::path::PathBuf;
+use std::time::Duration;
 use tabled::{Table, Tabled};
 
 pub async fn run(
@@ -22,7 +29,56 @@ pub async fn run(
         ContextCommands::Add { path, tag } => add_context(&client, &path, tag).await,
         ContextCommands::List { tag } => list_context(&client, tag, format).await,
         ContextCommands::Clear { tag, force } => clear_context(&client, tag, force).await,
-        ContextCommands::Search { query, limit } => search_context(&client, &query, limit, format).await,
+        ContextCommands::Search { query, limit, filter, watch } => {
+            let filter = filter.into();
+            match watch.interval() {
+                Some(interval) => {
+                    watch::watch(interval, format, || async {
+                        Ok(client.search_context_filtered(&query, limit, &filter).await?)
+                    })
+                    .await
+                }
+                None => search_context(&client, &query, limit, &filter, format).await,
+            }
+        }
+        ContextCommands::Bulk {
+            operation,
+            filter,
+            add,
+            remove,
+            output,
+            no_wait,
+            force,
+        } => {
+            let operation = match operation.as_str() {
+                "delete" => BulkContextOperation::Delete,
+                "retag" => BulkContextOperation::Retag { add, remove },
+                "reembed" => BulkContextOperation::Reembed,
+                _ => BulkContextOperation::Export,
+            };
+            let options = BulkOptions { output, no_wait, force };
+            bulk_context(&client, operation, &filter.into(), options, format).await
+        }
+        ContextCommands::Bootstrap { export, source, no_wait } => {
+            bootstrap_corpus(&client, &export, &source, no_wait, format).await
+        }
+        ContextCommands::Warm { filter } => warm_embeddings(&client, &filter.into(), format).await,
+        ContextCommands::Rehydrate { filter } => rehydrate_context(&client, &filter.into(), format).await,
+        ContextCommands::CacheStats => embedding_cache_stats(&client, format).await,
+    }
+}
+
+impl From<ContextFilterArgs> for ContextSearchFilter {
+    fn from(args: ContextFilterArgs) -> Self {
+        Self {
+            tags: args.tag,
+            source_prefix: args.source,
+            language: args.language,
+            content_type: args.doc_type,
+            since: args.since,
+            until: args.until,
+            as_of: args.as_of,
+        }
     }
 }
 
@@ -151,34 +207,296 @@ async fn search_context(
     client: &CopilotClient,
     query: &str,
     limit: usize,
+    filter: &ContextSearchFilter,
     format: &str,
 ) -> Result<()> {
-    let results = client.search_context(query, limit).await?;
+    let results = client.search_context_filtered(query, limit, filter).await?;
 
-    match format {
-        "json" => {
-            println!("{}", serde_json::to_string_pretty(&results)?);
+    output::emit(&results, format)
+}
+
+impl CommandOutput for Vec<ContextSearchResult> {
+    fn print_text(&self) {
+        if self.is_empty() {
+            println!("{}", "No matching context found.".dimmed());
+            return;
         }
-        "yaml" => {
-            println!("{}", serde_yaml::to_string(&results)?);
+
+        println!("{} {} results:\n", "Found".green(), self.len());
+
+        for (i, result) in self.iter().enumerate() {
+            println!(
+                "{}. {} (score: {:.2})",
+                i + 1,
+                result.source.as_ref().unwrap_or(&"unknown".to_string()).cyan(),
+                result.score
+            );
+            println!("   {}", truncate(&result.snippet, 100).dimmed());
+            println!();
+        }
+    }
+}
+
+impl Watchable for Vec<ContextSearchResult> {
+    fn changes(&self, previous: &Self) -> Vec<Change> {
+        let describe = |rank: usize, result: &ContextSearchResult| {
+            let source = result.source.as_deref().unwrap_or("unknown");
+            format!("#{} {} (score: {:.2})", rank + 1, source, result.score)
+        };
+        let mut changes = Vec::new();
+
+        for (rank, result) in self.iter().enumerate() {
+            match previous.iter().position(|p| p.id == result.id) {
+                None => changes.push(Change::Added(describe(rank, result))),
+                Some(before) if before != rank || (previous[before].score - result.score).abs() >= 0.005 => {
+                    changes.push(Change::Changed(format!(
+                        "{} (was #{}, score {:.2})",
+                        describe(rank, result),
+                        before + 1,
+                        previous[before].score
+                    )));
+                }
+                Some(_) => {}
+            }
+        }
+        for (rank, result) in previous.iter().enumerate() {
+            if !self.iter().any(|r| r.id == result.id) {
+                changes.push(Change::Removed(describe(rank, result)));
+            }
+        }
+        changes
+    }
+}
+
+struct BulkOptions {
+    output: Option<PathBuf>,
+    no_wait: bool,
+    force: bool,
+}
+
+async fn bulk_context(
+    client: &CopilotClient,
+    operation: BulkContextOperation,
+    filter: &ContextSearchFilter,
+    options: BulkOptions,
+    format: &str,
+) -> Result<()> {
+    if operation == BulkContextOperation::Delete && !options.force {
+        let confirmed = Confirm::new()
+            .with_prompt("Delete all context matching the filter?")
+            .default(false)
+            .interact()?;
+
+        if !confirmed {
+            println!("{}", "Cancelled.".yellow());
+            return Ok(());
+        }
+    }
+
+    let job = client.bulk_context(&operation, filter).await?;
+    if options.no_wait {
+        match format {
+            "json" => println!("{}", serde_json::to_string_pretty(&job)?),
+            _ => println!("{}: {}", "Job ID".bold(), job.id),
         }
+        return Ok(());
+    }
+
+    let job = wait_for_bulk_job(client, &job.id).await?;
+    if job.status == "failed" {
+        anyhow::bail!(
+            "Bulk {} failed: {}",
+            job.operation,
+            job.error.unwrap_or_default()
+        );
+    }
+
+    if operation == BulkContextOperation::Export {
+        let items = client.export_bulk_context_job(&job.id).await?;
+        match &options.output {
+            Some(path) => {
+                std::fs::write(path, items)?;
+                eprintln!("{} {} items to {}", "Exported".green(), job.processed, path.display());
+            }
+            None => print!("{}", items),
+        }
+        return Ok(());
+    }
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&job)?),
+        "yaml" => println!("{}", serde_yaml::to_string(&job)?),
         _ => {
-            if results.is_empty() {
-                println!("{}", "No matching context found.".dimmed());
-                return Ok(());
+            println!(
+                "{} {} of {} matching items",
+                format!("Bulk {}:", job.operation).green(),
+                job.processed - job.failed,
+                job.matched
+            );
+            if job.failed > 0 {
+                println!("{} {} items failed", "Warning:".yellow(), job.failed);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn wait_for_bulk_job(client: &CopilotClient, job_id: &str) -> Result<BulkContextJob> {
+    let pb = ProgressBar::new(0);
+    pb.set_style(ProgressStyle::default_bar().template("{bar:40.cyan/blue} {pos}/{len} {msg}")?);
+
+    loop {
+        let job = client.get_bulk_context_job(job_id).await?;
+        pb.set_length(job.matched as u64);
+        pb.set_position(job.processed as u64);
+        pb.set_message(job.operation.clone());
+
+        if job.is_finished() {
+            pb.finish_and_clear();
+            return Ok(job);
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+async fn bootstrap_corpus(
+    client: &CopilotClient,
+    export: &std::path::Path,
+    source: &str,
+    no_wait: bool,
+    format: &str,
+) -> Result<()> {
+    let job = client.bootstrap_corpus(export, Some(source)).await?;
+    if no_wait {
+        match format {
+            "json" => println!("{}", serde_json::to_string_pretty(&job)?),
+            _ => println!("{}: {}", "Job ID".bold(), job.id),
+        }
+        return Ok(());
+    }
+
+    let pb = ProgressBar::new(job.total_pages as u64);
+    pb.set_style(ProgressStyle::default_bar().template("{bar:40.cyan/blue} {pos}/{len} pages")?);
+    let job = loop {
+        let job = client.get_bootstrap_job(&job.id).await?;
+        pb.set_position(job.report.pages as u64);
+        if job.is_finished() {
+            pb.finish_and_clear();
+            break job;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    };
+    if job.status == "failed" {
+        anyhow::bail!("Bootstrap failed: {}", job.error.unwrap_or_default());
+    }
+
+    output::emit(&job, format)
+}
+
+impl CommandOutput for BootstrapJob {
+    fn print_text(&self) {
+        let report = &self.report;
+        println!(
+            "{} {} of {} {} pages ({} chunks) in {:.1}s",
+            "Bootstrapped".green(),
+            report.ingested,
+            self.total_pages,
+            self.format,
+            report.chunks,
+            report.processing_time_ms as f64 / 1000.0
+        );
+        println!("{}: {:.1}%", "Coverage".bold(), report.coverage * 100.0);
+        println!("{}: {:.1}%", "Summarized".bold(), report.summary_coverage * 100.0);
+        if report.empty + report.skipped > 0 {
+            println!(
+                "{}",
+                format!("{} pages without text, {} skipped by ingestion rules", report.empty, report.skipped).dimmed()
+            );
+        }
+
+        if !report.spaces.is_empty() {
+            #[derive(Tabled)]
+            struct SpaceRow {
+                #[tabled(rename = "Space")]
+                space: String,
+                #[tabled(rename = "Pages")]
+                pages: usize,
+                #[tabled(rename = "Ingested")]
+                ingested: usize,
+                #[tabled(rename = "Summarized")]
+                summarized: usize,
+                #[tabled(rename = "Chunks")]
+                chunks: usize,
             }
 
-            println!("{} {} results:\n", "Found".green(), results.len());
-
-            for (i, result) in results.iter().enumerate() {
-                println!(
-                    "{}. {} (score: {:.2})",
-                    i + 1,
-                    result.source.as_ref().unwrap_or(&"unknown".to_string()).cyan(),
-                    result.score
-                );
-                println!("   {}", truncate(&result.snippet, 100).dimmed());
-                println!();
+            let rows = report.spaces.iter().map(|s| SpaceRow {
+                space: s.space.clone(),
+                pages: s.pages,
+                ingested: s.ingested,
+                summarized: s.summarized,
+                chunks: s.chunks,
+            });
+            println!();
+            println!("{}", Table::new(rows));
+        }
+
+        if report.failed > 0 {
+            println!();
+            println!("{} {} pages failed", "Warning:".yellow(), report.failed);
+            for failure in &report.failures {
+                println!("  {} {}: {}", "•".red(), failure.page, failure.error.dimmed());
+            }
+        }
+    }
+}
+
+async fn warm_embeddings(client: &CopilotClient, filter: &ContextSearchFilter, format: &str) -> Result<()> {
+    let warmup = client.warm_embedding_cache(filter).await?;
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&warmup)?),
+        "yaml" => println!("{}", serde_yaml::to_string(&warmup)?),
+        _ => {
+            println!(
+                "{} {} items ({} embedded, {} already cached)",
+                "Warmed".green(),
+                warmup.texts,
+                warmup.embedded,
+                warmup.already_cached
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn rehydrate_context(client: &CopilotClient, filter: &ContextSearchFilter, format: &str) -> Result<()> {
+    let warmup = client.warm_up_context(filter).await?;
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&warmup)?),
+        "yaml" => println!("{}", serde_yaml::to_string(&warmup)?),
+        _ => println!("{} {} archived items", "Rehydrated".green(), warmup.rehydrated),
+    }
+
+    Ok(())
+}
+
+async fn embedding_cache_stats(client: &CopilotClient, format: &str) -> Result<()> {
+    let stats = client.embedding_cache_stats().await?;
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&stats)?),
+        "yaml" => println!("{}", serde_yaml::to_string(&stats)?),
+        _ => {
+            println!("{}: {:.1}%", "Hit Rate".bold(), stats.hit_rate * 100.0);
+            println!("{}: {}", "Memory Hits".bold(), stats.memory_hits);
+            println!("{}: {}", "Shared Hits".bold(), stats.shared_hits);
+            println!("{}: {}", "Misses".bold(), stats.misses);
+            println!("{}: {}", "Entries".bold(), stats.entries);
+            if stats.shared_errors > 0 {
+                println!("{} {} shared cache errors", "Warning:".yellow(), stats.shared_errors);
             }
         }
     }
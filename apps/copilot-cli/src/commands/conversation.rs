@@ -3,7 +3,7 @@
 use crate::ConversationCommands;
 use anyhow::Result;
 use colored::Colorize;
-use copilot_sdk::CopilotClient;
+use copilot_sdk::{CopilotClient, HandoffRequest, MessagePart};
 use dialoguer::Confirm;
 use tabled::{Table, Tabled};
 
@@ -31,6 +31,10 @@ pub async fn run(
         ConversationCommands::Export { id, output, format: export_format } => {
             export_conversation(&client, &id, output, &export_format).await
         }
+        ConversationCommands::Handoff { id, targets, notes, dry_run } => {
+            let request = HandoffRequest { targets, notes, dry_run };
+            hand_off_conversation(&client, &id, &request, format).await
+        }
     }
 }
 
@@ -100,9 +104,11 @@ async fn show_conversation(client: &CopilotClient, id: &str, format: &str) -> Re
                 let role = match msg.role.as_str() {
                     "user" => "You".green(),
                     "assistant" => "Assistant".cyan(),
+                    "tool" => "Tool".yellow(),
                     _ => msg.role.normal(),
                 };
                 println!("{}: {}", role.bold(), msg.content);
+                super::chat::print_parts(&msg.parts);
                 println!();
             }
         }
@@ -146,8 +152,17 @@ async fn export_conversation(
             md.push_str("---\n\n");
 
             for msg in conversation.messages {
-                let role = if msg.role == "user" { "**You**" } else { "**Assistant**" };
-                md.push_str(&format!("{}\n\n{}\n\n---\n\n", role, msg.content));
+                let role = match msg.role.as_str() {
+                    "user" => "**You**",
+                    "tool" => "**Tool**",
+                    _ => "**Assistant**",
+                };
+                md.push_str(&format!("{}\n\n{}\n\n", role, msg.content));
+                for part in &msg.parts {
+                    md.push_str(&part_markdown(part));
+                    md.push('\n');
+                }
+                md.push_str("---\n\n");
             }
             md
         }
@@ -166,3 +181,78 @@ async fn export_conversation(
 
     Ok(())
 }
+
+/// A tool call, result or sandbox output as a Markdown block
+fn part_markdown(part: &MessagePart) -> String {
+    let tool = part.tool.as_deref().unwrap_or("?");
+    let json = |value: &Option<serde_json::Value>| {
+        value
+            .as_ref()
+            .map(|value| serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string()))
+            .unwrap_or_else(|| "null".to_string())
+    };
+    match part.kind.as_str() {
+        "tool_call" => format!(
+            "**Tool call** `{}` ({})\n\n```json\n{}\n```\n",
+            tool,
+            part.call_id,
+            json(&part.arguments)
+        ),
+        "tool_result" => match &part.error {
+            Some(error) => format!("**Tool error** `{}`: {}\n", tool, error),
+            None => format!(
+                "**Tool result** `{}`{}\n\n```json\n{}\n```\n",
+                tool,
+                if part.cached { " (cached)" } else { "" },
+                json(&part.output)
+            ),
+        },
+        "sandbox_output" => {
+            let mut md = match part.exit_code {
+                Some(code) => format!("**Sandbox output** (exit code {})\n", code),
+                None => "**Sandbox output**\n".to_string(),
+            };
+            for (stream, text) in [("stdout", &part.stdout), ("stderr", &part.stderr)] {
+                if let Some(text) = text.as_deref().filter(|text| !text.is_empty()) {
+                    md.push_str(&format!("\n{}:\n\n```\n{}\n```\n", stream, text.trim_end()));
+                }
+            }
+            md
+        }
+        other => format!("*{}*\n", other),
+    }
+}
+
+async fn hand_off_conversation(
+    client: &CopilotClient,
+    id: &str,
+    request: &HandoffRequest,
+    format: &str,
+) -> Result<()> {
+    let handoff = client.hand_off_session(id, request).await?;
+
+    match format {
+        "json" => {
+            println!("{}", serde_json::to_string_pretty(&handoff)?);
+        }
+        "yaml" => {
+            println!("{}", serde_yaml::to_string(&handoff)?);
+        }
+        _ => {
+            println!("{}", handoff.document);
+            if request.dry_run {
+                println!("{}", "Dry run, nothing was posted.".dimmed());
+            }
+            for delivery in &handoff.deliveries {
+                if delivery.delivered {
+                    println!("{} to {}", "Handed off".green(), delivery.endpoint_id);
+                } else {
+                    let error = delivery.error.as_deref().unwrap_or("delivery failed");
+                    println!("{} to {}: {}", "Not handed off".red(), delivery.endpoint_id, error);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,578 @@
+//! Editor integration daemon (`copilot daemon`)
+//!
+//! Speaks JSON-RPC 2.0 with LSP base-protocol framing
+//! (`Content-Length: <n>\r\n\r\n<json>`) over stdio or a Unix socket, so
+//! editor plugins can reach the agent without embedding HTTP logic.
+//!
+//! Methods:
+//! - `initialize`: set the workspace root; returns server info and methods
+//! - `copilot/ask`: inline question, optionally about a file or selection
+//! - `copilot/complete`: completion at a cursor, with workspace file context
+//! - `copilot/workflows`: list workflows
+//! - `copilot/runWorkflow`, `copilot/workflowStatus`: trigger and poll workflows
+//! - `shutdown`, followed by the `exit` notification
+//!
+//! Requests on a connection are handled concurrently, so responses may arrive
+//! in a different order than the requests were sent.
+
+use anyhow::{bail, Context, Result};
+use copilot_sdk::CopilotClient;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::sync::mpsc;
+
+/// Methods advertised from `initialize`
+const METHODS: &[&str] = &[
+    "initialize",
+    "shutdown",
+    "copilot/ask",
+    "copilot/complete",
+    "copilot/workflows",
+    "copilot/runWorkflow",
+    "copilot/workflowStatus",
+];
+
+/// Largest message accepted from an editor
+const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Document text kept before and after the cursor for completions
+const MAX_PREFIX_CHARS: usize = 6_000;
+const MAX_SUFFIX_CHARS: usize = 2_000;
+
+/// Workspace files added to completion prompts
+const MAX_CONTEXT_FILES: usize = 4;
+const MAX_CONTEXT_FILE_CHARS: usize = 4_000;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The agent API call behind a method failed
+const BACKEND_ERROR: i64 = -32000;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<copilot_sdk::CopilotError> for RpcError {
+    fn from(e: copilot_sdk::CopilotError) -> Self {
+        Self::new(BACKEND_ERROR, e.to_string())
+    }
+}
+
+type RpcResult = std::result::Result<Value, RpcError>;
+
+/// A file the editor has open, sent along so unsaved changes are used
+#[derive(Debug, Deserialize)]
+struct DocumentParam {
+    path: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InitializeParams {
+    #[serde(default)]
+    workspace_root: Option<PathBuf>,
+    #[serde(default)]
+    client_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AskParams {
+    question: String,
+    /// Continue an existing session instead of asking statelessly
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    document: Option<DocumentParam>,
+    /// Selected text the question is about
+    #[serde(default)]
+    selection: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompleteParams {
+    document: DocumentParam,
+    /// Zero-based cursor position
+    line: usize,
+    character: usize,
+    /// Other open documents to use as context
+    #[serde(default)]
+    open_documents: Vec<DocumentParam>,
+    #[serde(default)]
+    model: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RunWorkflowParams {
+    workflow_id: String,
+    #[serde(default)]
+    inputs: HashMap<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkflowStatusParams {
+    execution_id: String,
+}
+
+/// State shared by the requests of one editor connection
+struct Connection {
+    client: CopilotClient,
+    workspace_root: Mutex<Option<PathBuf>>,
+}
+
+pub async fn run(api_url: &str, api_key: Option<&str>, socket: Option<PathBuf>) -> Result<()> {
+    let client = CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .build()?;
+
+    match socket {
+        Some(path) => serve_socket(client, &path).await,
+        None => {
+            // Status goes to stderr; stdout carries the protocol
+            eprintln!("copilot daemon: serving JSON-RPC on stdio");
+            serve(
+                client,
+                BufReader::new(tokio::io::stdin()),
+                tokio::io::stdout(),
+            )
+            .await
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn serve_socket(client: CopilotClient, path: &Path) -> Result<()> {
+    use tokio::net::UnixListener;
+
+    if path.exists() {
+        // Left behind by a daemon that did not shut down cleanly
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    }
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+    eprintln!("copilot daemon: listening on {}", path.display());
+
+    let result = loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = match accepted {
+                    Ok(connection) => connection,
+                    Err(e) => break Err(e.into()),
+                };
+                let client = client.clone();
+                tokio::spawn(async move {
+                    let (reader, writer) = stream.into_split();
+                    if let Err(e) = serve(client, BufReader::new(reader), writer).await {
+                        eprintln!("copilot daemon: connection closed: {}", e);
+                    }
+                });
+            }
+            _ = tokio::signal::ctrl_c() => break Ok(()),
+        }
+    };
+
+    let _ = std::fs::remove_file(path);
+    result
+}
+
+#[cfg(not(unix))]
+async fn serve_socket(_client: CopilotClient, _path: &Path) -> Result<()> {
+    bail!("--socket is only supported on Unix; use stdio instead")
+}
+
+/// Serve one editor connection until it sends `exit` or closes the stream
+async fn serve<R, W>(client: CopilotClient, mut reader: R, writer: W) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (tx, rx) = mpsc::unbounded_channel::<Value>();
+    let writer = tokio::spawn(write_messages(writer, rx));
+
+    let connection = Arc::new(Connection {
+        client,
+        workspace_root: Mutex::new(None),
+    });
+
+    while let Some(body) = read_message(&mut reader).await? {
+        let message: Value = match serde_json::from_slice(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                let _ = tx.send(error_response(
+                    Value::Null,
+                    RpcError::new(PARSE_ERROR, e.to_string()),
+                ));
+                continue;
+            }
+        };
+
+        let id = message.get("id").cloned();
+        let Some(method) = message
+            .get("method")
+            .and_then(Value::as_str)
+            .map(String::from)
+        else {
+            if let Some(id) = id {
+                let _ = tx.send(error_response(
+                    id,
+                    RpcError::new(INVALID_REQUEST, "missing method"),
+                ));
+            }
+            continue;
+        };
+        if method == "exit" {
+            break;
+        }
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        // Later requests depend on the workspace root, so finish this first
+        if method == "initialize" {
+            let result = connection.handle(&method, params).await;
+            respond(&tx, id, result);
+            continue;
+        }
+
+        let connection = connection.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let result = connection.handle(&method, params).await;
+            respond(&tx, id, result);
+        });
+    }
+
+    drop(tx);
+    // Wait for in-flight responses to be written
+    writer.await??;
+    Ok(())
+}
+
+/// Send the result of a request; notifications (no `id`) get no response
+fn respond(tx: &mpsc::UnboundedSender<Value>, id: Option<Value>, result: RpcResult) {
+    let Some(id) = id else {
+        return;
+    };
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => error_response(id, e),
+    };
+    let _ = tx.send(response);
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+/// Read one `Content-Length` framed message; `None` at end of stream
+async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            // Tolerate blank lines between messages
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .context("Invalid Content-Length")?,
+                );
+            }
+        }
+    }
+
+    let length = length.unwrap_or_default();
+    if length > MAX_MESSAGE_BYTES {
+        bail!(
+            "Message of {} bytes exceeds the {} byte limit",
+            length,
+            MAX_MESSAGE_BYTES
+        );
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(body))
+}
+
+async fn write_messages<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    mut rx: mpsc::UnboundedReceiver<Value>,
+) -> Result<()> {
+    while let Some(message) = rx.recv().await {
+        let body = serde_json::to_vec(&message)?;
+        writer
+            .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+            .await?;
+        writer.write_all(&body).await?;
+        writer.flush().await?;
+    }
+    Ok(())
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+impl Connection {
+    async fn handle(&self, method: &str, params: Value) -> RpcResult {
+        match method {
+            "initialize" => self.initialize(parse_params(params)?),
+            "shutdown" => Ok(Value::Null),
+            "copilot/ask" => self.ask(parse_params(params)?).await,
+            "copilot/complete" => self.complete(parse_params(params)?).await,
+            "copilot/workflows" => Ok(json!(self.client.list_workflows().await?)),
+            "copilot/runWorkflow" => {
+                let params: RunWorkflowParams = parse_params(params)?;
+                let execution = self
+                    .client
+                    .start_workflow(&params.workflow_id, params.inputs)
+                    .await?;
+                Ok(json!(execution))
+            }
+            "copilot/workflowStatus" => {
+                let params: WorkflowStatusParams = parse_params(params)?;
+                Ok(json!(
+                    self.client
+                        .get_workflow_status(&params.execution_id)
+                        .await?
+                ))
+            }
+            // Other notifications, e.g. `initialized` or `$/cancelRequest`
+            _ if method.starts_with('$') || method == "initialized" => Ok(Value::Null),
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method {}", method),
+            )),
+        }
+    }
+
+    fn initialize(&self, params: InitializeParams) -> RpcResult {
+        if let Some(client_name) = &params.client_name {
+            eprintln!("copilot daemon: {} connected", client_name);
+        }
+        *self.workspace_root.lock().expect("workspace root poisoned") = params.workspace_root;
+
+        Ok(json!({
+            "server_info": { "name": "copilot", "version": env!("CARGO_PKG_VERSION") },
+            "capabilities": { "methods": METHODS },
+        }))
+    }
+
+    async fn ask(&self, params: AskParams) -> RpcResult {
+        let mut context = String::new();
+        if let Some(document) = &params.document {
+            let text = self.document_text(document)?;
+            context.push_str(&format!("File {}:\n```\n{}\n```\n", document.path, text));
+        }
+        if let Some(selection) = params.selection.as_deref().filter(|s| !s.is_empty()) {
+            context.push_str(&format!("Selected code:\n```\n{}\n```\n", selection));
+        }
+
+        let response = match &params.session_id {
+            Some(session_id) => {
+                let message = if context.is_empty() {
+                    params.question.clone()
+                } else {
+                    format!("{}\n{}", context, params.question)
+                };
+                self.client.send_message(session_id, message).await?
+            }
+            None => {
+                let context = (!context.is_empty()).then_some(context.as_str());
+                self.client
+                    .ask(params.question.clone(), context, params.model.clone())
+                    .await?
+            }
+        };
+
+        Ok(json!({
+            "answer": response.content,
+            "session_id": response.conversation_id,
+        }))
+    }
+
+    async fn complete(&self, params: CompleteParams) -> RpcResult {
+        let text = self.document_text(&params.document)?;
+        let cursor = cursor_offset(&text, params.line, params.character);
+        let (before, after) = text.split_at(cursor);
+        let prefix = tail_chars(before, MAX_PREFIX_CHARS);
+        let suffix = head_chars(after, MAX_SUFFIX_CHARS);
+
+        let related = self.related_files(&params.document.path, &params.open_documents);
+        let mut prompt = String::new();
+        if !related.is_empty() {
+            prompt.push_str("Related workspace files:\n\n");
+            for (path, contents) in &related {
+                prompt.push_str(&format!("File {}:\n```\n{}\n```\n\n", path, contents));
+            }
+        }
+        prompt.push_str(&format!(
+            "Complete the code at <CURSOR> in {}. Reply with only the text to insert at the \
+             cursor, without explanations or code fences.\n\n```\n{}<CURSOR>{}\n```",
+            params.document.path, prefix, suffix
+        ));
+
+        let response = self.client.ask(prompt, None, params.model).await?;
+        Ok(json!({
+            "completion": strip_fences(&response.content),
+            "context_files": related.iter().map(|(path, _)| path).collect::<Vec<_>>(),
+        }))
+    }
+
+    /// Text of a document: what the editor sent, or the file on disk
+    fn document_text(&self, document: &DocumentParam) -> std::result::Result<String, RpcError> {
+        if let Some(text) = &document.text {
+            return Ok(text.clone());
+        }
+        let path = self.resolve(&document.path);
+        std::fs::read_to_string(&path).map_err(|e| {
+            RpcError::new(
+                INVALID_PARAMS,
+                format!("Cannot read {}: {}", path.display(), e),
+            )
+        })
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        let path = PathBuf::from(path);
+        match &*self.workspace_root.lock().expect("workspace root poisoned") {
+            Some(root) if path.is_relative() => root.join(path),
+            _ => path,
+        }
+    }
+
+    /// Open documents first, then files next to the current one with the same
+    /// extension
+    fn related_files(&self, current: &str, open: &[DocumentParam]) -> Vec<(String, String)> {
+        let mut files: Vec<(String, String)> = open
+            .iter()
+            .filter(|doc| doc.path != current)
+            .filter_map(|doc| Some((doc.path.clone(), self.document_text(doc).ok()?)))
+            .take(MAX_CONTEXT_FILES)
+            .collect();
+
+        let current_path = self.resolve(current);
+        if let (Some(dir), Some(extension)) = (current_path.parent(), current_path.extension()) {
+            let mut siblings: Vec<PathBuf> = std::fs::read_dir(dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file() && path.extension() == Some(extension))
+                .filter(|path| *path != current_path)
+                .collect();
+            siblings.sort();
+
+            for path in siblings {
+                if files.len() >= MAX_CONTEXT_FILES {
+                    break;
+                }
+                let display = self.display_path(&path);
+                if files.iter().any(|(p, _)| *p == display) {
+                    continue;
+                }
+                if let Ok(contents) = std::fs::read_to_string(&path) {
+                    files.push((display, contents));
+                }
+            }
+        }
+
+        for (_, contents) in &mut files {
+            *contents = head_chars(contents, MAX_CONTEXT_FILE_CHARS).to_string();
+        }
+        files
+    }
+
+    fn display_path(&self, path: &Path) -> String {
+        let root = self.workspace_root.lock().expect("workspace root poisoned");
+        root.as_deref()
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+}
+
+/// Byte offset of a zero-based line/character position, clamped to the text
+fn cursor_offset(text: &str, line: usize, character: usize) -> usize {
+    let mut offset = 0;
+    for (index, content) in text.split_inclusive('\n').enumerate() {
+        if index == line {
+            let content = content.trim_end_matches(['\r', '\n']);
+            return offset
+                + content
+                    .char_indices()
+                    .nth(character)
+                    .map(|(i, _)| i)
+                    .unwrap_or(content.len());
+        }
+        offset += content.len();
+    }
+    text.len()
+}
+
+fn tail_chars(text: &str, max: usize) -> &str {
+    let skip = text.chars().count().saturating_sub(max);
+    match text.char_indices().nth(skip) {
+        Some((start, _)) => &text[start..],
+        None => text,
+    }
+}
+
+fn head_chars(text: &str, max: usize) -> &str {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// Models sometimes wrap completions in a code fence despite being asked not to
+fn strip_fences(text: &str) -> String {
+    let trimmed = text.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return text.to_string();
+    };
+    let body = rest.split_once('\n').map(|(_, body)| body).unwrap_or("");
+    body.strip_suffix("```")
+        .unwrap_or(body)
+        .trim_end_matches('\n')
+        .to_string()
+}
@@ -0,0 +1,541 @@
+//! Self-diagnostic command (`copilot doctor`)
+//!
+//! Checks the CLI and server configuration, probes every service the platform
+//! depends on (the API server, Postgres, Redis, NATS, the vector store and the
+//! LLM providers) and runs a small ingest, search and chat round trip against
+//! the API. Every check that does not pass says what to do about it.
+
+use crate::config::CliConfig;
+use crate::output::{self, CommandOutput};
+use crate::DoctorArgs;
+use anyhow::{anyhow, bail, Result};
+use colored::Colorize;
+use copilot_sdk::CopilotClient;
+use reqwest::Url;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::TcpStream;
+
+/// JWT secret shipped in `config/default.toml`
+const DEFAULT_JWT_SECRET: &str = "your-secret-key-change-in-production";
+
+/// Outcome of one check; `skip` when the service is not configured or the
+/// check depends on one that failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+/// One check run by `copilot doctor`
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DoctorCheck {
+    /// `config`, `connectivity` or `smoke_test`
+    pub group: String,
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do when the check did not pass
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+/// Output of `copilot doctor`
+#[derive(Debug, Default, Serialize, JsonSchema)]
+pub struct DoctorReport {
+    pub checks: Vec<DoctorCheck>,
+}
+
+impl DoctorReport {
+    fn count(&self, status: CheckStatus) -> usize {
+        self.checks.iter().filter(|check| check.status == status).count()
+    }
+
+    fn status_of(&self, name: &str) -> Option<CheckStatus> {
+        self.checks.iter().find(|check| check.name == name).map(|check| check.status)
+    }
+
+    fn push(&mut self, group: &str, name: &str, status: CheckStatus, detail: impl Into<String>) -> &mut DoctorCheck {
+        self.checks.push(DoctorCheck {
+            group: group.to_string(),
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+            remediation: None,
+            latency_ms: None,
+        });
+        self.checks.last_mut().expect("just pushed")
+    }
+}
+
+impl DoctorCheck {
+    fn fix(&mut self, remediation: impl Into<String>) -> &mut Self {
+        self.remediation = Some(remediation.into());
+        self
+    }
+
+    fn took(&mut self, latency: Duration) -> &mut Self {
+        self.latency_ms = Some(latency.as_millis() as u64);
+        self
+    }
+}
+
+impl CommandOutput for DoctorReport {
+    fn print_text(&self) {
+        let mut group = "";
+        for check in &self.checks {
+            if check.group != group {
+                group = &check.group;
+                output::section(match group {
+                    "config" => "Configuration",
+                    "connectivity" => "Connectivity",
+                    _ => "Smoke test",
+                });
+            }
+            let icon = match check.status {
+                CheckStatus::Pass => "✓".green(),
+                CheckStatus::Warn => "!".yellow(),
+                CheckStatus::Fail => "✗".red(),
+                CheckStatus::Skip => "-".dimmed(),
+            };
+            let latency = check.latency_ms.map(|ms| format!(" ({})", output::format_duration(ms))).unwrap_or_default();
+            println!("  {} {}: {}{}", icon, check.name.bold(), check.detail, latency.dimmed());
+            if let Some(remediation) = &check.remediation {
+                println!("      {} {}", "→".cyan(), remediation);
+            }
+        }
+
+        println!();
+        println!(
+            "{} passed, {} warnings, {} failed, {} skipped",
+            self.count(CheckStatus::Pass).to_string().green(),
+            self.count(CheckStatus::Warn).to_string().yellow(),
+            self.count(CheckStatus::Fail).to_string().red(),
+            self.count(CheckStatus::Skip),
+        );
+    }
+}
+
+pub async fn run(api_url: &str, api_key: Option<&str>, args: DoctorArgs, format: &str) -> Result<()> {
+    let timeout = Duration::from_secs(args.timeout.max(1));
+    let mut report = DoctorReport::default();
+
+    let server_config = check_config(&mut report, api_url, api_key, &args);
+    check_connectivity(&mut report, api_url, &args, server_config.as_ref(), timeout).await;
+    if args.skip_smoke_test {
+        report.push("smoke_test", "round trip", CheckStatus::Skip, "skipped with --skip-smoke-test");
+    } else {
+        smoke_test(&mut report, api_url, api_key, timeout).await;
+    }
+
+    output::emit(&report, format)?;
+
+    let failed = report.count(CheckStatus::Fail);
+    if failed > 0 {
+        bail!("{} of {} checks failed", failed, report.checks.len());
+    }
+    Ok(())
+}
+
+/// Validate the CLI settings and the server configuration file, returning
+/// the latter when it parses
+fn check_config(report: &mut DoctorReport, api_url: &str, api_key: Option<&str>, args: &DoctorArgs) -> Option<toml::Value> {
+    let cli_path = CliConfig::config_path()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| "the CLI config file".to_string());
+    match CliConfig::load() {
+        Ok(_) => {
+            report.push("config", "cli config", CheckStatus::Pass, format!("{} is valid", cli_path));
+        }
+        Err(e) => {
+            report
+                .push("config", "cli config", CheckStatus::Fail, format!("{} is invalid: {}", cli_path, e))
+                .fix(format!("Fix or remove {}, or run `copilot config reset`", cli_path));
+        }
+    }
+
+    match Url::parse(api_url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {
+            report.push("config", "api url", CheckStatus::Pass, api_url);
+        }
+        _ => {
+            report
+                .push("config", "api url", CheckStatus::Fail, format!("'{}' is not an http(s) URL", api_url))
+                .fix("Set --api-url or COPILOT_API_URL to the server's base URL, e.g. http://localhost:8080");
+        }
+    }
+
+    if api_key.is_some() {
+        report.push("config", "api key", CheckStatus::Pass, "set");
+    } else {
+        report
+            .push("config", "api key", CheckStatus::Warn, "not set; authenticated endpoints will be rejected")
+            .fix("Set --api-key or COPILOT_API_KEY, or run `copilot config set api_key <key>`");
+    }
+
+    let path = args.config.display().to_string();
+    let content = match std::fs::read_to_string(&args.config) {
+        Ok(content) => content,
+        Err(e) => {
+            report
+                .push("config", "server config", CheckStatus::Skip, format!("cannot read {}: {}", path, e))
+                .fix("Pass --config or set CONFIG_PATH to the server's configuration file");
+            return None;
+        }
+    };
+    let config: toml::Value = match toml::from_str(&content) {
+        Ok(config) => config,
+        Err(e) => {
+            report
+                .push("config", "server config", CheckStatus::Fail, format!("{} is not valid TOML: {}", path, e))
+                .fix(format!("Fix the syntax error in {}", path));
+            return None;
+        }
+    };
+
+    let mut problems = Vec::new();
+    let mut fixes = Vec::new();
+    if let Some(provider) = lookup(&config, "llm.default_provider") {
+        if config.get("llm").and_then(|llm| llm.get(provider)).is_none() {
+            problems.push(format!("llm.default_provider '{}' has no [llm.{}] section", provider, provider));
+            fixes.push(format!("Add an [llm.{}] section or change llm.default_provider", provider));
+        }
+    }
+    if lookup(&config, "auth.jwt_secret") == Some(DEFAULT_JWT_SECRET) && std::env::var("JWT_SECRET").is_err() {
+        problems.push("auth.jwt_secret is the shipped default".to_string());
+        fixes.push("Set JWT_SECRET to a random value, e.g. `openssl rand -base64 32`".to_string());
+    }
+
+    if problems.is_empty() {
+        report.push("config", "server config", CheckStatus::Pass, format!("{} is valid", path));
+    } else {
+        report
+            .push("config", "server config", CheckStatus::Warn, format!("{}: {}", path, problems.join("; ")))
+            .fix(fixes.join("; "));
+    }
+    Some(config)
+}
+
+async fn check_connectivity(
+    report: &mut DoctorReport,
+    api_url: &str,
+    args: &DoctorArgs,
+    config: Option<&toml::Value>,
+    timeout: Duration,
+) {
+    let client = CopilotClient::builder().base_url(api_url).timeout(timeout).build();
+    let (result, latency) = timed(timeout, async { Ok(client?.health_check(false).await?) }).await;
+    match result {
+        Ok(health) => {
+            report
+                .push("connectivity", "api server", CheckStatus::Pass, format!("{} (v{})", health.status, health.version))
+                .took(latency);
+        }
+        Err(e) => {
+            report
+                .push("connectivity", "api server", CheckStatus::Fail, format!("unreachable: {}", e))
+                .fix(format!(
+                    "Start the server with `copilot server start`, or point --api-url at a running one (now {})",
+                    api_url
+                ));
+        }
+    }
+
+    let database_url = setting(&args.database_url, config, "database.url");
+    probe(report, "postgres", database_url, "DATABASE_URL", timeout, |url| async move {
+        let (host, port) = host_port(&url, 5432)?;
+        TcpStream::connect((host.as_str(), port)).await?;
+        Ok(format!("accepting connections at {}:{}", host, port))
+    })
+    .await;
+
+    let redis_url = setting(&args.redis_url, config, "redis.url");
+    probe(report, "redis", redis_url, "REDIS_URL", timeout, |url| async move {
+        let client = redis::Client::open(url.as_str())?;
+        let mut con = client.get_multiplexed_async_connection().await?;
+        let pong: String = redis::cmd("PING").query_async(&mut con).await?;
+        Ok(format!("{} from {}", pong, redact(&url)))
+    })
+    .await;
+
+    let nats_url = setting(&args.nats_url, config, "nats.url");
+    probe(report, "nats", nats_url, "NATS_URL", timeout, |url| async move {
+        let (host, port) = host_port(&url, 4222)?;
+        let stream = TcpStream::connect((host.as_str(), port)).await?;
+        // A NATS server greets every connection with its INFO line
+        let mut greeting = String::new();
+        BufReader::new(stream).read_line(&mut greeting).await?;
+        if !greeting.starts_with("INFO") {
+            bail!("{}:{} did not answer like a NATS server", host, port);
+        }
+        Ok(format!("server at {}:{}", host, port))
+    })
+    .await;
+
+    let vector_store_url = args.vector_store_url.clone().or_else(|| {
+        let provider = config.and_then(|config| lookup(config, "vector_db.provider"))?;
+        setting(&None, config, &format!("vector_db.{}.url", provider))
+    });
+    probe(report, "vector store", vector_store_url, "VECTOR_STORE_URL", timeout, |url| async move {
+        let response = reqwest::get(url.as_str()).await?;
+        if !response.status().is_success() {
+            bail!("{} answered {}", url, response.status());
+        }
+        Ok(format!("{} answered {}", url, response.status()))
+    })
+    .await;
+
+    check_llm_providers(report, config, timeout).await;
+}
+
+/// Probe the default LLM provider and its fallbacks with their configured keys;
+/// only the default provider failing fails the check
+async fn check_llm_providers(report: &mut DoctorReport, config: Option<&toml::Value>, timeout: Duration) {
+    let default = std::env::var("LLM_DEFAULT_PROVIDER")
+        .ok()
+        .or_else(|| config.and_then(|config| lookup(config, "llm.default_provider")).map(String::from))
+        .unwrap_or_else(|| "openai".to_string());
+    let fallbacks: Vec<String> = match std::env::var("LLM_FALLBACK_PROVIDERS") {
+        Ok(list) => list.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect(),
+        Err(_) => config
+            .and_then(|config| config.get("llm")?.get("fallback_providers")?.as_array().cloned())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|p| p.as_str().map(String::from))
+            .collect(),
+    };
+
+    for (provider, required) in std::iter::once((default, true)).chain(fallbacks.into_iter().map(|p| (p, false))) {
+        let name = format!("llm {}", provider);
+        let unhealthy = if required { CheckStatus::Fail } else { CheckStatus::Warn };
+        let env_var = format!("{}_API_KEY", provider.to_uppercase());
+        let key = std::env::var(&env_var)
+            .ok()
+            .or_else(|| setting(&None, config, &format!("llm.{}.api_key", provider)))
+            .filter(|key| !key.is_empty() && !key.contains("your-"));
+        let Some(key) = key else {
+            report
+                .push("connectivity", &name, unhealthy, "no API key configured")
+                .fix(format!("Set {} or llm.{}.api_key in the server config", env_var, provider));
+            continue;
+        };
+
+        let request = match provider.as_str() {
+            "openai" => reqwest::Client::new().get("https://api.openai.com/v1/models").bearer_auth(&key),
+            "anthropic" => reqwest::Client::new()
+                .get("https://api.anthropic.com/v1/models")
+                .header("x-api-key", &key)
+                .header("anthropic-version", "2023-06-01"),
+            "google" => reqwest::Client::new()
+                .get("https://generativelanguage.googleapis.com/v1beta/models")
+                .query(&[("key", &key)]),
+            _ => {
+                report.push("connectivity", &name, CheckStatus::Skip, "API key set; no probe for this provider");
+                continue;
+            }
+        };
+
+        let (result, latency) = timed(timeout, async { Ok(request.send().await?) }).await;
+        match result {
+            Ok(response) if response.status().is_success() => {
+                report.push("connectivity", &name, CheckStatus::Pass, "API key accepted").took(latency);
+            }
+            Ok(response) if matches!(response.status().as_u16(), 401 | 403) => {
+                report
+                    .push("connectivity", &name, unhealthy, format!("API key rejected ({})", response.status()))
+                    .fix(format!("Replace {} with a valid key for {}", env_var, provider));
+            }
+            Ok(response) => {
+                report
+                    .push("connectivity", &name, unhealthy, format!("answered {}", response.status()))
+                    .fix(format!("Check {}'s status page; the server falls back to other providers meanwhile", provider));
+            }
+            Err(e) => {
+                report
+                    .push("connectivity", &name, unhealthy, format!("unreachable: {}", e))
+                    .fix("Check outbound network access and proxy settings (HTTPS_PROXY) from this host");
+            }
+        }
+    }
+}
+
+/// Store a uniquely tagged document, find it through search, ask the model a
+/// question and clean up after
+async fn smoke_test(report: &mut DoctorReport, api_url: &str, api_key: Option<&str>, timeout: Duration) {
+    if report.status_of("api server") != Some(CheckStatus::Pass) {
+        report.push("smoke_test", "round trip", CheckStatus::Skip, "the API server is unreachable");
+        return;
+    }
+    if api_key.is_none() {
+        report
+            .push("smoke_test", "round trip", CheckStatus::Skip, "needs an API key")
+            .fix("Set --api-key or COPILOT_API_KEY to run the smoke test");
+        return;
+    }
+    let client = match CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .timeout(timeout)
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            report.push("smoke_test", "round trip", CheckStatus::Fail, e.to_string());
+            return;
+        }
+    };
+
+    let run_id = uuid::Uuid::new_v4().simple().to_string();
+    let tag = format!("copilot-doctor-{}", run_id);
+    let source = format!("copilot-doctor/{}.md", run_id);
+    let marker = format!("doctor{}", &run_id[..12]);
+    let content = format!(
+        "# Copilot doctor\n\nThis document was written by `copilot doctor` to check ingestion. \
+         Its verification phrase is {}.",
+        marker
+    );
+
+    let (result, latency) = timed(timeout, async { Ok(client.add_context(&source, &content, vec![tag.clone()]).await?) }).await;
+    match result {
+        Ok(item) => {
+            report.push("smoke_test", "ingestion", CheckStatus::Pass, format!("stored {}", item.id)).took(latency);
+        }
+        Err(e) => {
+            report
+                .push("smoke_test", "ingestion", CheckStatus::Fail, e.to_string())
+                .fix("Check that the API key may write context and that Postgres and the vector store pass above");
+        }
+    }
+
+    if report.status_of("ingestion") == Some(CheckStatus::Pass) {
+        let (result, latency) = timed(timeout, async { Ok(client.search_context(&marker, 5).await?) }).await;
+        match result {
+            Ok(results) => match results.iter().position(|r| r.source.as_deref() == Some(source.as_str())) {
+                Some(rank) => {
+                    report
+                        .push("smoke_test", "retrieval", CheckStatus::Pass, format!("found at rank {} of {}", rank + 1, results.len()))
+                        .took(latency);
+                }
+                None => {
+                    report
+                        .push("smoke_test", "retrieval", CheckStatus::Fail, "the stored document was not found")
+                        .fix("Check the embedding provider and vector store; if ingestion is queued, rerun in a few seconds");
+                }
+            },
+            Err(e) => {
+                report
+                    .push("smoke_test", "retrieval", CheckStatus::Fail, e.to_string())
+                    .fix("Check the vector store and embedding provider connectivity above");
+            }
+        }
+    } else {
+        report.push("smoke_test", "retrieval", CheckStatus::Skip, "ingestion failed");
+    }
+
+    let (result, latency) = timed(timeout, async { Ok(client.chat("Reply with the single word OK.", None).await?) }).await;
+    match result {
+        Ok(reply) if !reply.content.trim().is_empty() => {
+            let model = reply.model.clone().unwrap_or_else(|| "default model".to_string());
+            report.push("smoke_test", "chat", CheckStatus::Pass, format!("{} replied", model)).took(latency);
+            let _ = client.delete_conversation(&reply.conversation_id).await;
+        }
+        Ok(reply) => {
+            report
+                .push("smoke_test", "chat", CheckStatus::Fail, "the model returned an empty reply")
+                .fix("Check the server logs for the LLM provider's response");
+            let _ = client.delete_conversation(&reply.conversation_id).await;
+        }
+        Err(e) => {
+            report
+                .push("smoke_test", "chat", CheckStatus::Fail, e.to_string())
+                .fix("Check the LLM provider checks above and the server logs; raise --timeout for slow models");
+        }
+    }
+
+    if report.status_of("ingestion") == Some(CheckStatus::Pass) {
+        if let Err(e) = client.clear_context(Some(tag.clone())).await {
+            report
+                .push("smoke_test", "cleanup", CheckStatus::Warn, format!("could not remove the test document: {}", e))
+                .fix(format!("Remove it with `copilot context clear --tag {}`", tag));
+        }
+    }
+}
+
+/// Run one connectivity probe against the URL of a configured service
+async fn probe<F, Fut>(
+    report: &mut DoctorReport,
+    name: &str,
+    url: Option<String>,
+    env_var: &str,
+    timeout: Duration,
+    check: F,
+) where
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let Some(url) = url else {
+        report
+            .push("connectivity", name, CheckStatus::Skip, "not configured")
+            .fix(format!("Set {} or pass it on the command line to check {}", env_var, name));
+        return;
+    };
+    let shown = redact(&url);
+    let (result, latency) = timed(timeout, check(url)).await;
+    match result {
+        Ok(detail) => {
+            report.push("connectivity", name, CheckStatus::Pass, detail).took(latency);
+        }
+        Err(e) => {
+            report
+                .push("connectivity", name, CheckStatus::Fail, format!("{}: {}", shown, e))
+                .fix(format!("Check that {} is running and reachable from this host, or correct {}", name, env_var));
+        }
+    }
+}
+
+/// Run `check` with a deadline, timing it
+async fn timed<T>(timeout: Duration, check: impl Future<Output = Result<T>>) -> (Result<T>, Duration) {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(timeout, check).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow!("timed out after {}s", timeout.as_secs())),
+    };
+    (result, started.elapsed())
+}
+
+/// A flag's value, or else the non-empty string at `path` in the server config
+fn setting(flag: &Option<String>, config: Option<&toml::Value>, path: &str) -> Option<String> {
+    flag.clone()
+        .or_else(|| config.and_then(|config| lookup(config, path)).map(String::from))
+        .filter(|value| !value.is_empty())
+}
+
+/// The string at a dotted `path` in the server config
+fn lookup<'a>(config: &'a toml::Value, path: &str) -> Option<&'a str> {
+    path.split('.').try_fold(config, |value, key| value.get(key))?.as_str()
+}
+
+fn host_port(url: &str, default_port: u16) -> Result<(String, u16)> {
+    let parsed = Url::parse(url)?;
+    let host = parsed.host_str().ok_or_else(|| anyhow!("no host in URL"))?;
+    Ok((host.to_string(), parsed.port().unwrap_or(default_port)))
+}
+
+/// `url` without its password, for display
+fn redact(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut parsed) if parsed.password().is_some() => {
+            let _ = parsed.set_password(Some("***"));
+            parsed.to_string()
+        }
+        _ => url.to_string(),
+    }
+}
@@ -1,58 +1,96 @@
 //! Health check command
 
+use crate::output::{self, CommandOutput};
+use crate::watch::{self, Change, Watchable};
 use anyhow::Result;
 use colored::Colorize;
-use copilot_sdk::CopilotClient;
+use copilot_sdk::{CopilotClient, HealthResponse};
+use std::time::Duration;
 
-pub async fn run(api_url: &str, detailed: bool, format: &str) -> Result<()> {
+pub async fn run(api_url: &str, detailed: bool, watch: Option<Duration>, format: &str) -> Result<()> {
     let client = CopilotClient::builder()
         .base_url(api_url)
         .build()?;
 
+    if let Some(interval) = watch {
+        return watch::watch(interval, format, || async { Ok(client.health_check(detailed).await?) }).await;
+    }
+
     let health = client.health_check(detailed).await?;
 
-    match format {
-        "json" => {
-            println!("{}", serde_json::to_string_pretty(&health)?);
+    output::emit(&health, format)
+}
+
+impl CommandOutput for HealthResponse {
+    fn print_text(&self) {
+        let status_str = match self.status.as_str() {
+            "healthy" => "Healthy".green(),
+            "degraded" => "Degraded".yellow(),
+            _ => "Unhealthy".red(),
+        };
+
+        println!("{}: {}", "Status".bold(), status_str);
+        println!("{}: {}", "Version".bold(), self.version);
+
+        // Services and uptime are only reported for detailed checks
+        if !self.services.is_empty() {
+            println!();
+            println!("{}", "Services:".bold());
+            for (name, svc_health) in &self.services {
+                let status_icon = match svc_health.status.as_str() {
+                    "healthy" => "✓".green(),
+                    "degraded" => "!".yellow(),
+                    _ => "✗".red(),
+                };
+                let latency_str = svc_health
+                    .latency_ms
+                    .map(|ms| format!(" ({}ms)", ms))
+                    .unwrap_or_default();
+                println!("  {} {}: {}{}", status_icon, name, svc_health.status, latency_str);
+            }
         }
-        "yaml" => {
-            println!("{}", serde_yaml::to_string(&health)?);
+
+        if let Some(uptime) = self.uptime {
+            println!();
+            println!("{}: {}s", "Uptime".bold(), uptime);
+        }
+    }
+}
+
+impl Watchable for HealthResponse {
+    fn changes(&self, previous: &Self) -> Vec<Change> {
+        let mut changes = Vec::new();
+        if self.status != previous.status {
+            changes.push(Change::Changed(format!("Status: {} → {}", previous.status, self.status)));
+        }
+        if self.version != previous.version {
+            changes.push(Change::Changed(format!("Version: {} → {}", previous.version, self.version)));
         }
-        _ => {
-            let status_str = match health.status.as_str() {
-                "healthy" => "Healthy".green(),
-                "degraded" => "Degraded".yellow(),
-                _ => "Unhealthy".red(),
-            };
-
-            println!("{}: {}", "Status".bold(), status_str);
-            println!("{}: {}", "Version".bold(), health.version);
-
-            if detailed {
-                if !health.services.is_empty() {
-                    println!();
-                    println!("{}", "Services:".bold());
-                    for (name, svc_health) in &health.services {
-                        let status_icon = match svc_health.status.as_str() {
-                            "healthy" => "✓".green(),
-                            "degraded" => "!".yellow(),
-                            _ => "✗".red(),
-                        };
-                        let latency_str = svc_health
-                            .latency_ms
-                            .map(|ms| format!(" ({}ms)", ms))
-                            .unwrap_or_default();
-                        println!("  {} {}: {}{}", status_icon, name, svc_health.status, latency_str);
-                    }
-                }
 
-                if let Some(uptime) = health.uptime {
-                    println!();
-                    println!("{}: {}s", "Uptime".bold(), uptime);
+        let mut names: Vec<&String> = self.services.keys().chain(previous.services.keys()).collect();
+        names.sort();
+        names.dedup();
+        for name in names {
+            match (previous.services.get(name), self.services.get(name)) {
+                (None, Some(service)) => changes.push(Change::Added(format!("{}: {}", name, service.status))),
+                (Some(service), None) => changes.push(Change::Removed(format!("{}: {}", name, service.status))),
+                (Some(before), Some(after)) if before.status != after.status => {
+                    let message = after.message.as_deref().map(|m| format!(" ({})", m)).unwrap_or_default();
+                    changes.push(Change::Changed(format!(
+                        "{}: {} → {}{}",
+                        name, before.status, after.status, message
+                    )));
                 }
+                _ => {}
             }
         }
-    }
 
-    Ok(())
+        // A restart shows up as uptime going backwards
+        if let (Some(before), Some(after)) = (previous.uptime, self.uptime) {
+            if after < before {
+                changes.push(Change::Changed(format!("Restarted (uptime {}s)", after)));
+            }
+        }
+        changes
+    }
 }
@@ -2,10 +2,31 @@
 
 use anyhow::Result;
 use colored::Colorize;
+use copilot_sdk::{CopilotClient, IngestionJob};
 use dialoguer::{Confirm, Input};
-use std::path::Path;
-
-pub async fn run(path: &str, template: &str) -> Result<()> {
+use indicatif::{ProgressBar, ProgressStyle};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Templates accepted by `copilot init --template`
+pub const TEMPLATES: &[&str] = &[
+    "default",
+    "minimal",
+    "rust",
+    "python",
+    "typescript",
+    "rust-service",
+    "python-ml",
+    "node-web",
+];
+
+pub async fn run(
+    path: &str,
+    template: &str,
+    ingest: bool,
+    api_url: &str,
+    api_key: Option<&str>,
+) -> Result<()> {
     let project_path = path;
     let project_dir = Path::new(&project_path);
 
@@ -50,21 +71,36 @@ pub async fn run(path: &str, template: &str) -> Result<()> {
     );
 
     // Create configuration files based on template
-    match template_name {
-        "rust" => create_rust_template(project_dir, &project_name)?,
-        "python" => create_python_template(project_dir, &project_name)?,
-        "typescript" => create_typescript_template(project_dir, &project_name)?,
-        "minimal" => create_minimal_template(project_dir, &project_name)?,
+    let preset = preset(template_name);
+    match (template_name, preset) {
+        (_, Some(preset)) => create_preset_template(project_dir, &project_name, preset)?,
+        ("rust", _) => create_rust_template(project_dir, &project_name)?,
+        ("python", _) => create_python_template(project_dir, &project_name)?,
+        ("typescript", _) => create_typescript_template(project_dir, &project_name)?,
+        ("minimal", _) => create_minimal_template(project_dir, &project_name)?,
         _ => create_default_template(project_dir, &project_name)?,
     }
 
     println!();
     println!("{} Project initialized successfully!", "✓".green());
+
+    if let Some(preset) = preset {
+        if ingest {
+            println!();
+            ingest_sources(project_dir, &project_name, preset, api_url, api_key).await?;
+        }
+    }
+
     println!();
     println!("{}", "Next steps:".bold());
     println!("  1. cd {}", project_path);
     println!("  2. Review and update .copilot/config.toml");
-    println!("  3. Run 'copilot chat' to start using the assistant");
+    if preset.is_some() && !ingest {
+        println!("  3. Re-run with --ingest to index README, docs/ and src/");
+        println!("  4. Run 'copilot chat' to start using the assistant");
+    } else {
+        println!("  3. Run 'copilot chat' to start using the assistant");
+    }
 
     Ok(())
 }
@@ -129,7 +165,11 @@ memory_limit = "512m"
     std::fs::write(copilot_dir.join("config.toml"), config)?;
     println!("  {} .copilot/config.toml", "Created".green());
 
-    // Gitignore additions
+    update_gitignore(dir)
+}
+
+/// Keep local Copilot state out of version control
+fn update_gitignore(dir: &Path) -> Result<()> {
     let gitignore_content = r#"
 # Copilot
 .copilot/cache/
@@ -309,3 +349,421 @@ model = "gpt-4"
 
     Ok(())
 }
+
+/// Where `copilot init --ingest` looks for documents
+struct IngestionSource {
+    /// File or directory, relative to the project root
+    path: &'static str,
+    /// Extensions to pick up inside a directory; empty means every file
+    extensions: &'static [&'static str],
+}
+
+/// Scaffolding for a common project type
+struct Preset {
+    name: &'static str,
+    description: &'static str,
+    system_prompt: &'static str,
+    include: &'static [&'static str],
+    exclude: &'static [&'static str],
+    sources: &'static [IngestionSource],
+    /// Starter workflows as (file name, YAML)
+    workflows: &'static [(&'static str, &'static str)],
+}
+
+const PRESETS: &[Preset] = &[
+    Preset {
+        name: "rust-service",
+        description: "Rust backend service",
+        system_prompt: "You are an expert Rust engineer working on a backend service.\n\
+                        Prefer idiomatic, safe Rust and explain trade-offs around async, error handling and performance.",
+        include: &["src/**/*.rs", "tests/**/*.rs", "benches/**/*.rs", "*.md", "docs/**/*.md", "Cargo.toml"],
+        exclude: &["target/", ".git/", "*.lock"],
+        sources: &[
+            IngestionSource { path: "README.md", extensions: &[] },
+            IngestionSource { path: "docs", extensions: &["md"] },
+            IngestionSource { path: "src", extensions: &["rs"] },
+        ],
+        workflows: &[
+            (
+                "rust-review.yaml",
+                r#"name: rust-review
+description: Lint, test and review Rust changes
+
+steps:
+  - id: fmt-check
+    type: command
+    command: cargo fmt --check
+
+  - id: clippy
+    type: command
+    command: cargo clippy --all-targets --all-features -- -D warnings
+
+  - id: test
+    type: command
+    command: cargo test
+
+  - id: ai-review
+    type: llm
+    prompt: |
+      Review the following Rust code changes for:
+      - Error handling and panics in request paths
+      - Blocking calls inside async code
+      - Memory safety and unnecessary cloning
+      - Idiomatic Rust usage
+
+      {{changes}}
+"#,
+            ),
+            (
+                "api-docs.yaml",
+                r#"name: api-docs
+description: Draft documentation for public service endpoints
+
+steps:
+  - id: doc-build
+    type: command
+    command: cargo doc --no-deps
+    continue_on_error: true
+
+  - id: draft
+    type: llm
+    prompt: |
+      Using the project context, write or update documentation for the
+      service's HTTP endpoints: paths, request and response bodies, error
+      codes and authentication requirements.
+"#,
+            ),
+        ],
+    },
+    Preset {
+        name: "python-ml",
+        description: "Python machine learning project",
+        system_prompt: "You are an experienced ML engineer working in Python.\n\
+                        Help with data pipelines, model training and evaluation, and keep experiments reproducible.",
+        include: &["src/**/*.py", "*.py", "notebooks/**/*.ipynb", "*.md", "docs/**/*.md", "pyproject.toml", "requirements*.txt"],
+        exclude: &[".venv/", "venv/", "__pycache__/", ".git/", "data/", "models/", "*.ckpt", "*.pt"],
+        sources: &[
+            IngestionSource { path: "README.md", extensions: &[] },
+            IngestionSource { path: "docs", extensions: &["md", "rst"] },
+            IngestionSource { path: "src", extensions: &["py"] },
+        ],
+        workflows: &[
+            (
+                "python-review.yaml",
+                r#"name: python-review
+description: Lint, type-check, test and review Python changes
+
+steps:
+  - id: ruff-check
+    type: command
+    command: ruff check .
+
+  - id: mypy
+    type: command
+    command: mypy .
+    continue_on_error: true
+
+  - id: pytest
+    type: command
+    command: pytest
+
+  - id: ai-review
+    type: llm
+    prompt: |
+      Review the following Python changes for:
+      - Data leakage between training and evaluation splits
+      - Non-deterministic behaviour (unseeded randomness)
+      - Type hints and error handling
+      - Performance of data loading and vectorized operations
+
+      {{changes}}
+"#,
+            ),
+            (
+                "experiment-report.yaml",
+                r#"name: experiment-report
+description: Summarize an experiment run
+
+steps:
+  - id: metrics
+    type: command
+    command: cat metrics.json
+    continue_on_error: true
+
+  - id: report
+    type: llm
+    prompt: |
+      Summarize the experiment: configuration, reported metrics
+      ({{steps.metrics.output}}), how it compares to the previous baseline
+      and suggested next experiments.
+"#,
+            ),
+        ],
+    },
+    Preset {
+        name: "node-web",
+        description: "Node.js web application",
+        system_prompt: "You are a senior full-stack engineer working on a Node.js web application.\n\
+                        Favor type-safe, accessible and secure code, and mention browser and runtime compatibility concerns.",
+        include: &["src/**/*.ts", "src/**/*.tsx", "src/**/*.js", "src/**/*.jsx", "*.md", "docs/**/*.md", "package.json", "tsconfig.json"],
+        exclude: &["node_modules/", "dist/", "build/", ".next/", ".git/", "*.lock", "package-lock.json"],
+        sources: &[
+            IngestionSource { path: "README.md", extensions: &[] },
+            IngestionSource { path: "docs", extensions: &["md", "mdx"] },
+            IngestionSource { path: "src", extensions: &["ts", "tsx", "js", "jsx"] },
+        ],
+        workflows: &[
+            (
+                "web-review.yaml",
+                r#"name: web-review
+description: Type-check, lint, test and review web changes
+
+steps:
+  - id: typecheck
+    type: command
+    command: npx tsc --noEmit
+
+  - id: lint
+    type: command
+    command: npx eslint .
+
+  - id: test
+    type: command
+    command: npm test
+
+  - id: ai-review
+    type: llm
+    prompt: |
+      Review the following changes for:
+      - XSS, CSRF and unsafe handling of user input
+      - Accessibility of new UI
+      - Component state and effect handling
+      - Bundle size impact of new dependencies
+
+      {{changes}}
+"#,
+            ),
+            (
+                "release-notes.yaml",
+                r#"name: release-notes
+description: Draft release notes from recent commits
+
+steps:
+  - id: log
+    type: command
+    command: git log --oneline -n 50
+
+  - id: notes
+    type: llm
+    prompt: |
+      Write user-facing release notes from these commits, grouped into
+      features, fixes and breaking changes:
+
+      {{steps.log.output}}
+"#,
+            ),
+        ],
+    },
+];
+
+fn preset(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|p| p.name == name)
+}
+
+fn toml_list(items: &[&str]) -> String {
+    items
+        .iter()
+        .map(|item| format!("    \"{}\",\n", item))
+        .collect()
+}
+
+fn create_preset_template(dir: &Path, name: &str, preset: &Preset) -> Result<()> {
+    let copilot_dir = dir.join(".copilot");
+    std::fs::create_dir_all(copilot_dir.join("workflows"))?;
+
+    let mut sources = String::new();
+    for source in preset.sources {
+        sources.push_str(&format!(
+            "\n[[ingestion.sources]]\npath = \"{}\"\n",
+            source.path
+        ));
+        if !source.extensions.is_empty() {
+            let extensions: Vec<String> = source
+                .extensions
+                .iter()
+                .map(|e| format!("\"{}\"", e))
+                .collect();
+            sources.push_str(&format!("extensions = [{}]\n", extensions.join(", ")));
+        }
+    }
+
+    let workflows: String = preset
+        .workflows
+        .iter()
+        .map(|(file, _)| format!("    \".copilot/workflows/{}\",\n", file))
+        .collect();
+
+    let config = format!(
+        r#"# Copilot Configuration
+# Project: {name} ({description})
+
+[project]
+name = "{name}"
+template = "{template}"
+description = ""
+
+[assistant]
+# Default model to use
+model = "gpt-4"
+
+# System prompt customization
+system_prompt = """
+{system_prompt}
+"""
+
+[context]
+# Files and patterns to include in context
+include = [
+{include}]
+
+# Files and patterns to exclude
+exclude = [
+{exclude}]
+
+# Documents indexed by `copilot init --ingest`
+[ingestion]
+source = "{name}"
+{sources}
+[workflows]
+# Enable built-in workflows
+code_review = true
+documentation = true
+testing = true
+
+# Starter workflows for this project type
+files = [
+{workflows}]
+
+[sandbox]
+# Sandbox settings for code execution
+enabled = true
+timeout = 30
+memory_limit = "512m"
+"#,
+        description = preset.description,
+        template = preset.name,
+        system_prompt = preset.system_prompt,
+        include = toml_list(preset.include),
+        exclude = toml_list(preset.exclude),
+    );
+
+    std::fs::write(copilot_dir.join("config.toml"), config)?;
+    println!("  {} .copilot/config.toml", "Created".green());
+
+    for (file, workflow) in preset.workflows {
+        std::fs::write(copilot_dir.join("workflows").join(file), workflow)?;
+        println!("  {} .copilot/workflows/{}", "Created".green(), file);
+    }
+
+    update_gitignore(dir)
+}
+
+/// Files matched by a preset's ingestion sources, skipping excluded directories
+fn source_files(dir: &Path, preset: &Preset) -> Vec<PathBuf> {
+    let excluded: Vec<&str> = preset
+        .exclude
+        .iter()
+        .filter_map(|pattern| pattern.strip_suffix('/'))
+        .collect();
+
+    let mut files = Vec::new();
+    for source in preset.sources {
+        let root = dir.join(source.path);
+        if root.is_file() {
+            files.push(root);
+            continue;
+        }
+
+        for entry in walkdir::WalkDir::new(&root)
+            .into_iter()
+            .filter_entry(|e| {
+                let name = e.file_name().to_string_lossy();
+                !name.starts_with('.') && !excluded.contains(&name.as_ref())
+            })
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let matches = source.extensions.is_empty()
+                || entry
+                    .path()
+                    .extension()
+                    .map(|ext| source.extensions.contains(&ext.to_string_lossy().as_ref()))
+                    .unwrap_or(false);
+            if matches {
+                files.push(entry.into_path());
+            }
+        }
+    }
+    files
+}
+
+/// Run the initial ingestion pass over the preset's sources
+async fn ingest_sources(
+    dir: &Path,
+    source: &str,
+    preset: &Preset,
+    api_url: &str,
+    api_key: Option<&str>,
+) -> Result<()> {
+    let files = source_files(dir, preset);
+    if files.is_empty() {
+        println!("{}", "No documents found to ingest.".dimmed());
+        return Ok(());
+    }
+
+    let client = CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .build()?;
+
+    let pb = ProgressBar::new(files.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.cyan} Ingesting [{bar:30.cyan}] {pos}/{len} {msg}")?
+            .progress_chars("=> "),
+    );
+
+    let mut failed = 0;
+    for file in &files {
+        let relative = file.strip_prefix(dir).unwrap_or(file);
+        pb.set_message(relative.display().to_string());
+        let outcome = match client.ingest_file(file, Some(source)).await {
+            Ok(job) => wait_for_ingestion(&client, job).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = outcome {
+            failed += 1;
+            pb.println(format!("  {} {}: {}", "✗".red(), relative.display(), e));
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    println!("{} {} documents", "Ingested".green(), files.len() - failed);
+    if failed > 0 {
+        println!("{} {} documents failed to ingest", "!".yellow(), failed);
+    }
+    Ok(())
+}
+
+/// Poll an accepted ingestion job until the server finishes with it
+async fn wait_for_ingestion(client: &CopilotClient, mut job: IngestionJob) -> Result<()> {
+    while !job.is_finished() {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        job = client.get_ingestion_job(&job.id).await?;
+    }
+    if job.status == "failed" {
+        anyhow::bail!(job.error.unwrap_or_default());
+    }
+    Ok(())
+}
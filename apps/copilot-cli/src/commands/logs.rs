@@ -0,0 +1,127 @@
+//! Log analysis commands
+
+use crate::LogsCommands;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use copilot_sdk::{CopilotClient, LogAnalysisReport, LogAnalysisRequest};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::Read;
+use std::time::Duration;
+use tabled::{Table, Tabled};
+
+pub async fn run(
+    api_url: &str,
+    api_key: Option<&str>,
+    cmd: LogsCommands,
+    format: &str,
+) -> Result<()> {
+    let client = CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .build()?;
+
+    match cmd {
+        LogsCommands::Analyze { file, top, no_summary } => {
+            analyze(&client, file.as_deref(), top, !no_summary, format).await
+        }
+    }
+}
+
+async fn analyze(
+    client: &CopilotClient,
+    file: Option<&str>,
+    top: usize,
+    summarize: bool,
+    format: &str,
+) -> Result<()> {
+    let logs = match file {
+        None | Some("-") => {
+            let mut logs = String::new();
+            std::io::stdin()
+                .read_to_string(&mut logs)
+                .context("Failed to read logs from standard input")?;
+            logs
+        }
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read log file {}", path))?,
+    };
+
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
+            .template("{spinner:.cyan} {msg}")?,
+    );
+    spinner.set_message("Analyzing logs...");
+    spinner.enable_steady_tick(Duration::from_millis(80));
+
+    let request = LogAnalysisRequest {
+        logs,
+        top_patterns: Some(top),
+        summarize: Some(summarize),
+    };
+    let report = client.analyze_logs(&request).await;
+    spinner.finish_and_clear();
+    let report = report?;
+
+    match format {
+        "json" => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        "yaml" => {
+            println!("{}", serde_yaml::to_string(&report)?);
+        }
+        _ => print_report(&report),
+    }
+
+    Ok(())
+}
+
+fn print_report(report: &LogAnalysisReport) {
+    println!(
+        "{} lines in {} patterns",
+        report.total_lines.to_string().bold(),
+        report.pattern_count.to_string().bold()
+    );
+    println!();
+
+    #[derive(Tabled)]
+    struct PatternRow {
+        #[tabled(rename = "Count")]
+        count: usize,
+        #[tabled(rename = "Share")]
+        share: String,
+        #[tabled(rename = "Level")]
+        level: String,
+        #[tabled(rename = "Pattern")]
+        template: String,
+    }
+
+    let rows: Vec<PatternRow> = report
+        .patterns
+        .iter()
+        .map(|p| PatternRow {
+            count: p.count,
+            share: format!("{:.1}%", p.share * 100.0),
+            level: p.level.clone().unwrap_or_else(|| "-".to_string()),
+            template: p.template.clone(),
+        })
+        .collect();
+    println!("{}", Table::new(rows));
+
+    if !report.findings.is_empty() {
+        println!();
+        println!("{}", "Findings".bold());
+        for finding in &report.findings {
+            let kind = match finding.kind.as_str() {
+                "error_pattern" => finding.kind.red(),
+                _ => finding.kind.yellow(),
+            };
+            println!("  {} {} ({})", kind, finding.template, finding.detail.dimmed());
+        }
+    }
+
+    println!();
+    println!("{}", "Summary".bold());
+    println!("{}", report.summary);
+}
@@ -1,15 +1,31 @@
 //! CLI command implementations
 
+pub mod admin;
+pub mod analytics;
+pub mod apply;
 pub mod ask;
+pub mod backup;
 pub mod benchmark;
 pub mod chat;
 pub mod completions;
 pub mod config;
 pub mod context;
 pub mod conversation;
+pub mod daemon;
+pub mod doctor;
 pub mod health;
 pub mod init;
+pub mod logs;
+pub mod plugin;
+pub mod preferences;
+pub mod quarantine;
+pub mod replay;
 pub mod sandbox;
+pub mod schedule;
+pub mod schema;
 pub mod server;
+pub mod shell;
+pub mod top;
 pub mod version;
+pub mod webhook;
 pub mod workflow;
@@ -0,0 +1,283 @@
+//! External plugin subcommands
+//!
+//! Any executable named `copilot-<name>` on `PATH` or in the plugin directory
+//! (`~/.config/copilot/plugins`) can be run as `copilot <name> [args...]`.
+//!
+//! Plugins receive a JSON [`PluginRequest`] on stdin carrying their arguments
+//! and the resolved CLI configuration, which is also exported as `COPILOT_*`
+//! environment variables. A plugin may answer with a JSON [`PluginResponse`]
+//! on stdout, which the CLI renders in the selected output format; any other
+//! output is passed through unchanged. Stderr is inherited.
+
+use crate::config::CliConfig;
+use crate::PluginCommands;
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Executable name prefix for plugins
+pub const PLUGIN_PREFIX: &str = "copilot-";
+
+/// Version of the stdin/stdout JSON protocol
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Configuration handed to a plugin, after applying flags, environment and
+/// the config file
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedConfig {
+    pub api_url: String,
+    pub api_key: Option<String>,
+    pub output_format: String,
+    pub default_model: Option<String>,
+    pub timeout_seconds: Option<u64>,
+    pub custom: HashMap<String, String>,
+}
+
+impl ResolvedConfig {
+    pub fn resolve(api_url: &str, api_key: Option<&str>, format: &str) -> Self {
+        let file = CliConfig::load().unwrap_or_default();
+        let (api_url, api_key) = file.with_overrides(Some(api_url), api_key);
+        Self {
+            api_url,
+            api_key,
+            output_format: format.to_string(),
+            default_model: file.default_model,
+            timeout_seconds: file.timeout_seconds,
+            custom: file.custom,
+        }
+    }
+}
+
+/// Sent to the plugin on stdin
+#[derive(Debug, Serialize)]
+pub struct PluginRequest<'a> {
+    pub protocol_version: u32,
+    pub cli_version: &'a str,
+    /// Plugin name, without the `copilot-` prefix
+    pub command: &'a str,
+    pub args: &'a [String],
+    pub config: &'a ResolvedConfig,
+}
+
+/// Optional structured reply on the plugin's stdout
+#[derive(Debug, Deserialize)]
+pub struct PluginResponse {
+    pub success: bool,
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// A discovered plugin executable
+#[derive(Debug, Clone, Serialize)]
+pub struct Plugin {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Directory searched for plugins in addition to `PATH`
+pub fn plugin_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("copilot").join("plugins"))
+}
+
+/// Find all plugins; earlier directories shadow later ones
+pub fn discover() -> Vec<Plugin> {
+    let mut found: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for dir in search_dirs() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = plugin_name(&path) else {
+                continue;
+            };
+            if is_executable(&path) {
+                found.entry(name).or_insert(path);
+            }
+        }
+    }
+
+    found
+        .into_iter()
+        .map(|(name, path)| Plugin { name, path })
+        .collect()
+}
+
+/// Look up a single plugin by name
+pub fn find(name: &str) -> Option<Plugin> {
+    let file_name = format!("{}{}{}", PLUGIN_PREFIX, name, std::env::consts::EXE_SUFFIX);
+    search_dirs()
+        .into_iter()
+        .map(|dir| dir.join(&file_name))
+        .find(|path| is_executable(path))
+        .map(|path| Plugin {
+            name: name.to_string(),
+            path,
+        })
+}
+
+fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = plugin_dir().into_iter().collect();
+    if let Some(path) = std::env::var_os("PATH") {
+        dirs.extend(std::env::split_paths(&path));
+    }
+    dirs
+}
+
+fn plugin_name(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    let stem = file_name
+        .strip_suffix(std::env::consts::EXE_SUFFIX)
+        .unwrap_or(file_name);
+    let name = stem.strip_prefix(PLUGIN_PREFIX)?;
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+pub async fn run(cmd: PluginCommands, format: &str) -> Result<()> {
+    match cmd {
+        PluginCommands::List => list_plugins(format),
+    }
+}
+
+fn list_plugins(format: &str) -> Result<()> {
+    let plugins = discover();
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&plugins)?),
+        "yaml" => println!("{}", serde_yaml::to_string(&plugins)?),
+        _ => {
+            if plugins.is_empty() {
+                println!("{}", "No plugins found.".dimmed());
+                if let Some(dir) = plugin_dir() {
+                    println!(
+                        "Install executables named {}<name> on PATH or in {}",
+                        PLUGIN_PREFIX,
+                        dir.display()
+                    );
+                }
+                return Ok(());
+            }
+
+            println!("{}", "Plugins:".bold());
+            for plugin in &plugins {
+                println!(
+                    "  {} {}",
+                    plugin.name.cyan(),
+                    plugin.path.display().to_string().dimmed()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Run `copilot <name> [args...]` through the matching plugin
+pub async fn run_external(args: Vec<String>, config: ResolvedConfig) -> Result<()> {
+    let Some((name, args)) = args.split_first() else {
+        bail!("No subcommand given");
+    };
+    let Some(plugin) = find(name) else {
+        bail!(
+            "Unknown command '{}'. No plugin named {}{} was found; run 'copilot plugin list' to see installed plugins",
+            name,
+            PLUGIN_PREFIX,
+            name
+        );
+    };
+
+    let request = PluginRequest {
+        protocol_version: PROTOCOL_VERSION,
+        cli_version: env!("CARGO_PKG_VERSION"),
+        command: name,
+        args,
+        config: &config,
+    };
+    let input = serde_json::to_vec(&request)?;
+
+    let mut command = Command::new(&plugin.path);
+    command
+        .args(args)
+        .env("COPILOT_PLUGIN_PROTOCOL", PROTOCOL_VERSION.to_string())
+        .env("COPILOT_API_URL", &config.api_url)
+        .env("COPILOT_OUTPUT_FORMAT", &config.output_format)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit());
+    if let Some(key) = &config.api_key {
+        command.env("COPILOT_API_KEY", key);
+    }
+
+    let output = tokio::task::spawn_blocking(move || -> Result<std::process::Output> {
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("Failed to start plugin {}", plugin.path.display()))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // Plugins that ignore stdin may exit before reading it
+            let _ = stdin.write_all(&input);
+        }
+        Ok(child.wait_with_output()?)
+    })
+    .await??;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match serde_json::from_str::<PluginResponse>(stdout.trim()) {
+        Ok(response) => render_response(name, response, &config.output_format)?,
+        Err(_) => print!("{}", stdout),
+    }
+
+    if !output.status.success() {
+        match output.status.code() {
+            Some(code) => bail!("Plugin '{}' exited with status {}", name, code),
+            None => bail!("Plugin '{}' was terminated by a signal", name),
+        }
+    }
+    Ok(())
+}
+
+fn render_response(name: &str, response: PluginResponse, format: &str) -> Result<()> {
+    if let Some(data) = &response.data {
+        match format {
+            "json" => println!("{}", serde_json::to_string_pretty(data)?),
+            "yaml" => println!("{}", serde_yaml::to_string(data)?),
+            _ => match data {
+                serde_json::Value::String(text) => println!("{}", text),
+                other => println!("{}", serde_json::to_string_pretty(other)?),
+            },
+        }
+    }
+    // Keep structured output parseable
+    if let Some(message) = response.message.as_ref().filter(|_| format == "text") {
+        println!("{}", message);
+    }
+
+    if !response.success {
+        bail!(
+            "Plugin '{}' failed: {}",
+            name,
+            response.error.as_deref().unwrap_or("no error message")
+        );
+    }
+    Ok(())
+}
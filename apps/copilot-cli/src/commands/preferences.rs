@@ -0,0 +1,200 @@
+//! User preference commands
+
+use crate::PreferenceCommands;
+use anyhow::{bail, Result};
+use colored::Colorize;
+use copilot_sdk::{CopilotClient, PreferenceSuggestion, UserPreferences, Verbosity};
+use dialoguer::Confirm;
+use tabled::{Table, Tabled};
+
+pub async fn run(
+    api_url: &str,
+    api_key: Option<&str>,
+    cmd: PreferenceCommands,
+    format: &str,
+) -> Result<()> {
+    let client = CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .build()?;
+
+    match cmd {
+        PreferenceCommands::Show => show_preferences(&client, format).await,
+        PreferenceCommands::Set {
+            verbosity,
+            language,
+            time_range,
+            favorite_services,
+            learning,
+        } => {
+            let mut preferences = client.get_preferences().await?.preferences;
+            if let Some(verbosity) = verbosity {
+                preferences.verbosity = Some(parse_verbosity(&verbosity)?);
+            }
+            if let Some(language) = language {
+                preferences.language = Some(language);
+            }
+            if let Some(time_range) = time_range {
+                preferences.default_time_range = Some(time_range);
+            }
+            if !favorite_services.is_empty() {
+                preferences.favorite_services = favorite_services;
+            }
+            if let Some(learning) = learning {
+                preferences.learning = learning;
+            }
+
+            let preferences = client.update_preferences(&preferences).await?;
+            if format == "text" {
+                println!("{}", "Preferences updated.".green());
+            }
+            print_preferences(&preferences, format)
+        }
+        PreferenceCommands::Confirm { id } => {
+            let preferences = client.confirm_preference(&id).await?;
+            if format == "text" {
+                println!("{} preference {}", "Confirmed".green(), id);
+            }
+            print_preferences(&preferences, format)
+        }
+        PreferenceCommands::Dismiss { id } => {
+            client.dismiss_preference(&id).await?;
+            println!("{} preference {}", "Dismissed".yellow(), id);
+            Ok(())
+        }
+        PreferenceCommands::Delete { force } => {
+            if !force {
+                let confirmed = Confirm::new()
+                    .with_prompt("Forget all your preferences and suggestions?")
+                    .default(false)
+                    .interact()?;
+
+                if !confirmed {
+                    println!("{}", "Cancelled.".yellow());
+                    return Ok(());
+                }
+            }
+
+            client.delete_preferences().await?;
+            println!("{}", "Preferences deleted.".green());
+            Ok(())
+        }
+    }
+}
+
+fn parse_verbosity(value: &str) -> Result<Verbosity> {
+    match value {
+        "brief" => Ok(Verbosity::Brief),
+        "normal" => Ok(Verbosity::Normal),
+        "detailed" => Ok(Verbosity::Detailed),
+        other => bail!("Unknown verbosity '{}'; use brief, normal or detailed", other),
+    }
+}
+
+async fn show_preferences(client: &CopilotClient, format: &str) -> Result<()> {
+    let response = client.get_preferences().await?;
+
+    match format {
+        "json" => {
+            println!("{}", serde_json::to_string_pretty(&response)?);
+        }
+        "yaml" => {
+            println!("{}", serde_yaml::to_string(&response)?);
+        }
+        _ => {
+            print_preferences(&response.preferences, format)?;
+            print_suggestions(&response.suggestions);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_preferences(preferences: &UserPreferences, format: &str) -> Result<()> {
+    match format {
+        "json" => {
+            println!("{}", serde_json::to_string_pretty(preferences)?);
+        }
+        "yaml" => {
+            println!("{}", serde_yaml::to_string(preferences)?);
+        }
+        _ => {
+            let unset = || "-".dimmed().to_string();
+            let verbosity = match preferences.verbosity {
+                Some(Verbosity::Brief) => "brief".to_string(),
+                Some(Verbosity::Normal) => "normal".to_string(),
+                Some(Verbosity::Detailed) => "detailed".to_string(),
+                None => unset(),
+            };
+            let services = if preferences.favorite_services.is_empty() {
+                unset()
+            } else {
+                preferences.favorite_services.join(", ")
+            };
+
+            println!("{}: {}", "Verbosity".bold(), verbosity);
+            println!("{}: {}", "Language".bold(), preferences.language.clone().unwrap_or_else(unset));
+            println!(
+                "{}: {}",
+                "Default Time Range".bold(),
+                preferences.default_time_range.clone().unwrap_or_else(unset)
+            );
+            println!("{}: {}", "Favorite Services".bold(), services);
+            println!(
+                "{}: {}",
+                "Learning".bold(),
+                if preferences.learning { "on".green() } else { "off".yellow() }
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn print_suggestions(suggestions: &[PreferenceSuggestion]) {
+    if suggestions.is_empty() {
+        return;
+    }
+
+    #[derive(Tabled)]
+    struct SuggestionRow {
+        #[tabled(rename = "ID")]
+        id: String,
+        #[tabled(rename = "Preference")]
+        key: String,
+        #[tabled(rename = "Value")]
+        value: String,
+        #[tabled(rename = "Seen In")]
+        evidence: String,
+    }
+
+    let rows: Vec<SuggestionRow> = suggestions
+        .iter()
+        .map(|s| SuggestionRow {
+            id: s.id.clone(),
+            key: serde_json::to_value(s.key)
+                .ok()
+                .and_then(|v| v.as_str().map(String::from))
+                .unwrap_or_default(),
+            value: s.value.clone(),
+            evidence: truncate(&s.evidence, 60),
+        })
+        .collect();
+
+    println!();
+    println!("{}", "Learned preferences awaiting confirmation:".bold());
+    println!("{}", Table::new(rows));
+    println!(
+        "{}",
+        "Confirm with `copilot preferences confirm <id>` or dismiss with `copilot preferences dismiss <id>`."
+            .dimmed()
+    );
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        format!("{}...", text.chars().take(max_chars).collect::<String>())
+    }
+}
@@ -0,0 +1,140 @@
+//! Quarantine review commands
+
+use crate::QuarantineCommands;
+use anyhow::Result;
+use colored::Colorize;
+use copilot_sdk::{CopilotClient, QuarantinedDocument, SafetyCategory, SafetyFinding};
+use dialoguer::Confirm;
+use tabled::{Table, Tabled};
+
+pub async fn run(
+    api_url: &str,
+    api_key: Option<&str>,
+    cmd: QuarantineCommands,
+    format: &str,
+) -> Result<()> {
+    let client = CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .build()?;
+
+    match cmd {
+        QuarantineCommands::List => list_quarantine(&client, format).await,
+        QuarantineCommands::Show { id } => {
+            let document = client.get_quarantined_document(&id).await?;
+            print_document(&document, format)
+        }
+        QuarantineCommands::Release { id, force } => {
+            if !force && !confirm(&client, &id, "Store these chunks in the knowledge base?").await? {
+                println!("{}", "Cancelled.".yellow());
+                return Ok(());
+            }
+
+            let document = client.release_quarantined_document(&id).await?;
+            println!(
+                "{} {} chunks of {}",
+                "Released".green(),
+                document.chunks.len(),
+                document.document_id
+            );
+            Ok(())
+        }
+        QuarantineCommands::Discard { id, force } => {
+            if !force && !confirm(&client, &id, "Drop these chunks for good?").await? {
+                println!("{}", "Cancelled.".yellow());
+                return Ok(());
+            }
+
+            client.discard_quarantined_document(&id).await?;
+            println!("{} {}", "Discarded".yellow(), id);
+            Ok(())
+        }
+    }
+}
+
+/// Show what was flagged before asking
+async fn confirm(client: &CopilotClient, id: &str, prompt: &str) -> Result<bool> {
+    let document = client.get_quarantined_document(id).await?;
+    print_findings(&document.findings);
+    Ok(Confirm::new().with_prompt(prompt).default(false).interact()?)
+}
+
+async fn list_quarantine(client: &CopilotClient, format: &str) -> Result<()> {
+    let documents = client.list_quarantine().await?;
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&documents)?),
+        "yaml" => println!("{}", serde_yaml::to_string(&documents)?),
+        _ => {
+            if documents.is_empty() {
+                println!("{}", "Nothing is quarantined.".dimmed());
+                return Ok(());
+            }
+
+            #[derive(Tabled)]
+            struct DocumentRow {
+                #[tabled(rename = "Document")]
+                id: String,
+                #[tabled(rename = "Source")]
+                source: String,
+                #[tabled(rename = "Findings")]
+                findings: String,
+                #[tabled(rename = "Chunks")]
+                chunks: usize,
+                #[tabled(rename = "Quarantined")]
+                quarantined_at: String,
+            }
+
+            let rows: Vec<DocumentRow> = documents
+                .iter()
+                .map(|d| DocumentRow {
+                    id: d.document_id.clone(),
+                    source: d.document.clone().unwrap_or_else(|| d.source.clone()),
+                    findings: d.findings.iter().map(|f| f.rule.as_str()).collect::<Vec<_>>().join(", "),
+                    chunks: d.chunks.len(),
+                    quarantined_at: d.quarantined_at.clone(),
+                })
+                .collect();
+            println!("{}", Table::new(rows));
+        }
+    }
+
+    Ok(())
+}
+
+fn print_document(document: &QuarantinedDocument, format: &str) -> Result<()> {
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(document)?),
+        "yaml" => println!("{}", serde_yaml::to_string(document)?),
+        _ => {
+            println!("{}: {}", "Document".bold(), document.document_id);
+            println!("{}: {}", "Source".bold(), document.source);
+            if let Some(name) = &document.document {
+                println!("{}: {}", "Name".bold(), name);
+            }
+            println!("{}: {}", "Quarantined".bold(), document.quarantined_at);
+            print_findings(&document.findings);
+
+            for chunk in &document.chunks {
+                println!();
+                println!("{}", format!("--- {} ---", chunk.id).dimmed());
+                println!("{}", chunk.content);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_findings(findings: &[SafetyFinding]) {
+    println!();
+    for finding in findings {
+        let category = match finding.category {
+            SafetyCategory::Secret => "secret".red(),
+            SafetyCategory::Malware => "malware".red(),
+            SafetyCategory::PromptInjection => "prompt injection".yellow(),
+        };
+        println!("  {} {} {}", category, finding.rule.bold(), finding.excerpt.dimmed());
+    }
+    println!();
+}
@@ -0,0 +1,258 @@
+//! Replay captured chat turns for debugging
+
+use crate::ReplayCommands;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use copilot_sdk::{CopilotClient, ReplayCapture, Usage};
+use serde::Serialize;
+use std::path::Path;
+use std::time::Instant;
+use tabled::{Table, Tabled};
+
+pub async fn run(
+    api_url: &str,
+    api_key: Option<&str>,
+    cmd: ReplayCommands,
+    format: &str,
+) -> Result<()> {
+    let client = CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .build()?;
+
+    match cmd {
+        ReplayCommands::List { limit } => list_replays(&client, limit, format).await,
+        ReplayCommands::Show { capture } => {
+            let capture = load_capture(&client, &capture).await?;
+            print_capture(&capture, format)
+        }
+        ReplayCommands::Run { capture, target, target_api_key } => {
+            let capture = load_capture(&client, &capture).await?;
+            let target = CopilotClient::builder()
+                .base_url(&target)
+                .api_key(target_api_key)
+                .build()?;
+            replay(&target, &capture, format).await
+        }
+    }
+}
+
+/// A capture file, e.g. copied from the server's capture directory, or the
+/// ID of a capture to fetch
+async fn load_capture(client: &CopilotClient, capture: &str) -> Result<ReplayCapture> {
+    let path = Path::new(capture);
+    if path.is_file() {
+        let json = std::fs::read_to_string(path).with_context(|| format!("reading {}", capture))?;
+        return serde_json::from_str(&json).with_context(|| format!("{} is not a replay capture", capture));
+    }
+    Ok(client.get_replay(capture).await?)
+}
+
+async fn list_replays(client: &CopilotClient, limit: usize, format: &str) -> Result<()> {
+    let replays = client.list_replays(limit).await?;
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&replays)?),
+        "yaml" => println!("{}", serde_yaml::to_string(&replays)?),
+        _ => {
+            if replays.is_empty() {
+                println!("{}", "No captures.".dimmed());
+                return Ok(());
+            }
+
+            #[derive(Tabled)]
+            struct ReplayRow {
+                #[tabled(rename = "ID")]
+                id: String,
+                #[tabled(rename = "Captured")]
+                captured_at: String,
+                #[tabled(rename = "Model")]
+                model: String,
+                #[tabled(rename = "Chars")]
+                message_chars: usize,
+                #[tabled(rename = "Outcome")]
+                outcome: String,
+                #[tabled(rename = "Duration")]
+                duration: String,
+            }
+
+            let rows: Vec<ReplayRow> = replays
+                .iter()
+                .map(|r| ReplayRow {
+                    id: r.id.clone(),
+                    captured_at: r.captured_at.clone(),
+                    model: r.model.clone().unwrap_or_else(|| "-".to_string()),
+                    message_chars: r.message_chars,
+                    outcome: if r.succeeded { "ok".to_string() } else { "error".to_string() },
+                    duration: format!("{} ms", r.duration_ms),
+                })
+                .collect();
+            println!("{}", Table::new(rows));
+        }
+    }
+
+    Ok(())
+}
+
+fn print_capture(capture: &ReplayCapture, format: &str) -> Result<()> {
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(capture)?),
+        "yaml" => println!("{}", serde_yaml::to_string(capture)?),
+        _ => {
+            println!("{}: {}", "Capture".bold(), capture.id);
+            println!("{}: {}", "Captured".bold(), capture.captured_at);
+            println!("{}: {}", "Session".bold(), capture.session_id);
+            if let Some(model) = &capture.request.model {
+                println!("{}: {}", "Model".bold(), model);
+            }
+            println!("{}: {} ms", "Duration".bold(), capture.duration_ms);
+            for (key, value) in &capture.request.metadata {
+                println!("{}: {}", key.bold(), value);
+            }
+
+            println!();
+            println!("{}", "--- request ---".dimmed());
+            println!("{}", capture.request.message);
+            println!();
+            match (&capture.response, &capture.error) {
+                (Some(response), _) => {
+                    println!("{}", "--- response ---".dimmed());
+                    println!("{}", response.content);
+                    println!();
+                    println!("{}", usage_line(&response.usage).dimmed());
+                }
+                (None, Some(error)) => println!("{} {}", "Failed:".red(), error),
+                (None, None) => {}
+            }
+
+            if !capture.retrieval.is_empty() {
+                println!();
+                println!("{}", "Retrieved context:".bold());
+                for entry in &capture.retrieval {
+                    println!(
+                        "  {:.3}  {} {}",
+                        entry.score,
+                        entry.source,
+                        format!("({} tokens)", entry.token_count).dimmed()
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Outcome of re-sending a capture
+#[derive(Debug, Serialize)]
+struct ReplayOutcome {
+    capture_id: String,
+    session_id: String,
+    duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<Usage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Whether the reply matches the captured one
+    matches: bool,
+}
+
+/// Send the captured request to `target` in a new session and compare the
+/// reply with the captured one
+async fn replay(target: &CopilotClient, capture: &ReplayCapture, format: &str) -> Result<()> {
+    let session = target
+        .create_session(capture.request.model.clone())
+        .await
+        .context("creating a session on the target")?;
+
+    let started = Instant::now();
+    let result = target.send_session_message(&session.id, &capture.request).await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    let captured = capture.response.as_ref().map(|r| r.content.as_str());
+    let outcome = match result {
+        Ok(response) => ReplayOutcome {
+            capture_id: capture.id.clone(),
+            session_id: session.id,
+            duration_ms,
+            matches: captured == Some(response.content.as_str()),
+            content: Some(response.content),
+            model: response.model,
+            usage: response.usage,
+            error: None,
+        },
+        Err(e) => ReplayOutcome {
+            capture_id: capture.id.clone(),
+            session_id: session.id,
+            duration_ms,
+            matches: captured.is_none(),
+            content: None,
+            model: None,
+            usage: None,
+            error: Some(e.to_string()),
+        },
+    };
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&outcome)?),
+        "yaml" => println!("{}", serde_yaml::to_string(&outcome)?),
+        _ => print_comparison(capture, &outcome),
+    }
+
+    Ok(())
+}
+
+fn print_comparison(capture: &ReplayCapture, outcome: &ReplayOutcome) {
+    let verdict = if outcome.matches {
+        "same outcome as captured".green()
+    } else {
+        "differs from the capture".yellow()
+    };
+    println!("{} {} in session {}: {}", "Replayed".bold(), capture.id, outcome.session_id, verdict);
+    println!(
+        "{}: {} ms captured, {} ms replayed",
+        "Duration".bold(),
+        capture.duration_ms,
+        outcome.duration_ms
+    );
+    let captured_model = capture.response.as_ref().and_then(|r| r.model.as_deref());
+    if captured_model != outcome.model.as_deref() {
+        println!(
+            "{}: {} captured, {} replayed",
+            "Model".bold(),
+            captured_model.unwrap_or("-"),
+            outcome.model.as_deref().unwrap_or("-")
+        );
+    }
+    if let (Some(captured), Some(replayed)) = (capture.response.as_ref(), outcome.usage.as_ref()) {
+        println!("{}: {}", "Captured".bold(), usage_line(&captured.usage).dimmed());
+        println!("{}: {}", "Replayed".bold(), usage_line(replayed).dimmed());
+    }
+
+    if outcome.matches {
+        return;
+    }
+    println!();
+    println!("{}", "--- captured ---".dimmed());
+    match (&capture.response, &capture.error) {
+        (Some(response), _) => println!("{}", response.content),
+        (None, error) => println!("{} {}", "Failed:".red(), error.as_deref().unwrap_or("unknown error")),
+    }
+    println!();
+    println!("{}", "--- replayed ---".dimmed());
+    match (&outcome.content, &outcome.error) {
+        (Some(content), _) => println!("{}", content),
+        (None, error) => println!("{} {}", "Failed:".red(), error.as_deref().unwrap_or("unknown error")),
+    }
+}
+
+fn usage_line(usage: &Usage) -> String {
+    format!(
+        "{} prompt + {} completion tokens, ${:.4}",
+        usage.prompt_tokens, usage.completion_tokens, usage.cost_usd
+    )
+}
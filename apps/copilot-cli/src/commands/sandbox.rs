@@ -1,9 +1,10 @@
 //! Sandbox execution commands
 
+use crate::output::{self, CommandOutput};
 use crate::SandboxCommands;
 use anyhow::Result;
 use colored::Colorize;
-use copilot_sdk::CopilotClient;
+use copilot_sdk::{CopilotClient, ExecutionResult, Sandbox};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::time::Duration;
 use tabled::{Table, Tabled};
@@ -63,44 +64,7 @@ async fn run_code(
 
     spinner.finish_and_clear();
 
-    match format {
-        "json" => {
-            println!("{}", serde_json::to_string_pretty(&result)?);
-        }
-        "yaml" => {
-            println!("{}", serde_yaml::to_string(&result)?);
-        }
-        _ => {
-            if result.success {
-                println!("{}", "✓ Execution successful".green());
-            } else {
-                println!("{}", "✗ Execution failed".red());
-            }
-
-            println!();
-
-            if !result.stdout.is_empty() {
-                println!("{}", "Output:".bold());
-                println!("{}", result.stdout);
-            }
-
-            if !result.stderr.is_empty() {
-                println!();
-                println!("{}", "Errors:".bold().red());
-                println!("{}", result.stderr);
-            }
-
-            println!();
-            println!(
-                "{}",
-                format!(
-                    "[Exit code: {} | Duration: {}ms]",
-                    result.exit_code, result.duration_ms
-                )
-                .dimmed()
-            );
-        }
-    }
+    output::emit(&result, format)?;
 
     if !result.success {
         std::process::exit(result.exit_code);
@@ -112,83 +76,100 @@ async fn run_code(
 async fn list_sandboxes(client: &CopilotClient, format: &str) -> Result<()> {
     let sandboxes = client.list_sandboxes().await?;
 
-    match format {
-        "json" => {
-            println!("{}", serde_json::to_string_pretty(&sandboxes)?);
-        }
-        "yaml" => {
-            println!("{}", serde_yaml::to_string(&sandboxes)?);
-        }
-        _ => {
-            if sandboxes.is_empty() {
-                println!("{}", "No active sandboxes.".dimmed());
-                return Ok(());
-            }
-
-            #[derive(Tabled)]
-            struct SandboxRow {
-                #[tabled(rename = "ID")]
-                id: String,
-                #[tabled(rename = "Template")]
-                template: String,
-                #[tabled(rename = "Status")]
-                status: String,
-                #[tabled(rename = "Created")]
-                created: String,
-            }
-
-            let rows: Vec<SandboxRow> = sandboxes
-                .iter()
-                .map(|s| SandboxRow {
-                    id: s.id[..8.min(s.id.len())].to_string(),
-                    template: s.template.clone(),
-                    status: s.status.clone(),
-                    created: s.created_at.clone(),
-                })
-                .collect();
-
-            let table = Table::new(rows).to_string();
-            println!("{}", table);
-        }
-    }
-
-    Ok(())
+    output::emit(&sandboxes, format)
 }
 
 async fn sandbox_status(client: &CopilotClient, id: &str, format: &str) -> Result<()> {
     let sandbox = client.get_sandbox(id).await?;
 
-    match format {
-        "json" => {
-            println!("{}", serde_json::to_string_pretty(&sandbox)?);
+    output::emit(&sandbox, format)
+}
+
+async fn destroy_sandbox(client: &CopilotClient, id: &str) -> Result<()> {
+    client.destroy_sandbox(id).await?;
+    println!("{} sandbox {}", "Destroyed".green(), id);
+    Ok(())
+}
+
+impl CommandOutput for ExecutionResult {
+    fn print_text(&self) {
+        if self.success {
+            println!("{}", "✓ Execution successful".green());
+        } else {
+            println!("{}", "✗ Execution failed".red());
         }
-        "yaml" => {
-            println!("{}", serde_yaml::to_string(&sandbox)?);
+
+        println!();
+
+        if !self.stdout.is_empty() {
+            println!("{}", "Output:".bold());
+            println!("{}", self.stdout);
         }
-        _ => {
-            let status_color = match sandbox.status.as_str() {
-                "running" => sandbox.status.green(),
-                "paused" => sandbox.status.yellow(),
-                "stopped" | "error" => sandbox.status.red(),
-                _ => sandbox.status.normal(),
-            };
-
-            println!("{}: {}", "ID".bold(), sandbox.id);
-            println!("{}: {}", "Template".bold(), sandbox.template);
-            println!("{}: {}", "Status".bold(), status_color);
-            println!("{}: {}", "Created".bold(), sandbox.created_at);
-
-            if let Some(last_activity) = sandbox.last_activity {
-                println!("{}: {}", "Last Activity".bold(), last_activity);
-            }
+
+        if !self.stderr.is_empty() {
+            println!();
+            println!("{}", "Errors:".bold().red());
+            println!("{}", self.stderr);
         }
+
+        println!();
+        println!(
+            "{}",
+            format!("[Exit code: {} | Duration: {}ms]", self.exit_code, self.duration_ms).dimmed()
+        );
     }
+}
 
-    Ok(())
+impl CommandOutput for Vec<Sandbox> {
+    fn print_text(&self) {
+        if self.is_empty() {
+            println!("{}", "No active sandboxes.".dimmed());
+            return;
+        }
+
+        #[derive(Tabled)]
+        struct SandboxRow {
+            #[tabled(rename = "ID")]
+            id: String,
+            #[tabled(rename = "Template")]
+            template: String,
+            #[tabled(rename = "Status")]
+            status: String,
+            #[tabled(rename = "Created")]
+            created: String,
+        }
+
+        let rows: Vec<SandboxRow> = self
+            .iter()
+            .map(|s| SandboxRow {
+                id: s.id[..8.min(s.id.len())].to_string(),
+                template: s.template.clone(),
+                status: s.status.clone(),
+                created: s.created_at.clone(),
+            })
+            .collect();
+
+        let table = Table::new(rows).to_string();
+        println!("{}", table);
+    }
 }
 
-async fn destroy_sandbox(client: &CopilotClient, id: &str) -> Result<()> {
-    client.destroy_sandbox(id).await?;
-    println!("{} sandbox {}", "Destroyed".green(), id);
-    Ok(())
+impl CommandOutput for Sandbox {
+    fn print_text(&self) {
+        let status_color = match self.status.as_str() {
+            "running" => self.status.green(),
+            "paused" => self.status.yellow(),
+            "stopped" | "error" => self.status.red(),
+            _ => self.status.normal(),
+        };
+
+        println!("{}: {}", "ID".bold(), self.id);
+        println!("{}: {}", "Template".bold(), self.template);
+        println!("{}: {}", "Status".bold(), status_color);
+        println!("{}: {}", "Created".bold(), self.created_at);
+
+        if let Some(last_activity) = &self.last_activity {
+            println!("{}: {}", "Last Activity".bold(), last_activity);
+        }
+    }
 }
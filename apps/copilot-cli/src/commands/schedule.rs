@@ -0,0 +1,173 @@
+//! Workflow schedule commands
+
+use crate::ScheduleCommands;
+use anyhow::Result;
+use colored::Colorize;
+use copilot_sdk::{CopilotClient, WorkflowSchedule};
+use serde_json::{json, Value};
+use tabled::{Table, Tabled};
+
+pub async fn run(
+    api_url: &str,
+    api_key: Option<&str>,
+    cmd: ScheduleCommands,
+    format: &str,
+) -> Result<()> {
+    let client = CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .build()?;
+
+    match cmd {
+        ScheduleCommands::List => list_schedules(&client, format).await,
+        ScheduleCommands::Show { id } => {
+            let schedule = client.get_schedule(&id).await?;
+            print_schedule(&schedule, format)
+        }
+        ScheduleCommands::Pause { id } => {
+            let schedule = client.pause_schedule(&id).await?;
+            if format == "text" {
+                println!("{} schedule {}", "Paused".yellow(), schedule.id);
+                return Ok(());
+            }
+            print_schedule(&schedule, format)
+        }
+        ScheduleCommands::Resume { id } => {
+            let schedule = client.resume_schedule(&id).await?;
+            if format == "text" {
+                let next = schedule.next_execution.as_deref().unwrap_or("never");
+                println!("{} schedule {} (next run: {})", "Resumed".green(), schedule.id, next);
+                return Ok(());
+            }
+            print_schedule(&schedule, format)
+        }
+        ScheduleCommands::Preview { cron, count } => preview(&client, &cron, count, format).await,
+    }
+}
+
+async fn list_schedules(client: &CopilotClient, format: &str) -> Result<()> {
+    let schedules = client.list_schedules().await?;
+
+    match format {
+        "json" => {
+            println!("{}", serde_json::to_string_pretty(&schedules)?);
+        }
+        "yaml" => {
+            println!("{}", serde_yaml::to_string(&schedules)?);
+        }
+        _ => {
+            if schedules.is_empty() {
+                println!("{}", "No schedules found.".dimmed());
+                return Ok(());
+            }
+
+            #[derive(Tabled)]
+            struct ScheduleRow {
+                #[tabled(rename = "ID")]
+                id: String,
+                #[tabled(rename = "Workflow")]
+                workflow: String,
+                #[tabled(rename = "Schedule")]
+                schedule: String,
+                #[tabled(rename = "Status")]
+                status: String,
+                #[tabled(rename = "Next Run")]
+                next: String,
+            }
+
+            let rows: Vec<ScheduleRow> = schedules
+                .iter()
+                .map(|s| ScheduleRow {
+                    id: s.id.clone(),
+                    workflow: s.workflow_id.clone(),
+                    schedule: describe(&s.schedule),
+                    status: if s.enabled { "active" } else { "paused" }.to_string(),
+                    next: s.next_execution.clone().unwrap_or_else(|| "-".to_string()),
+                })
+                .collect();
+
+            let table = Table::new(rows).to_string();
+            println!("{}", table);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_schedule(schedule: &WorkflowSchedule, format: &str) -> Result<()> {
+    match format {
+        "json" => {
+            println!("{}", serde_json::to_string_pretty(schedule)?);
+        }
+        "yaml" => {
+            println!("{}", serde_yaml::to_string(schedule)?);
+        }
+        _ => {
+            let status = if schedule.enabled { "active".green() } else { "paused".yellow() };
+
+            println!("{}: {}", "ID".bold(), schedule.id);
+            println!("{}: {}", "Workflow".bold(), schedule.workflow_id);
+            println!("{}: {}", "Schedule".bold(), describe(&schedule.schedule));
+            println!("{}: {}", "Status".bold(), status);
+            println!("{}: {}", "Missed Runs".bold(), describe_missed_runs(&schedule.missed_runs));
+            if let Some(paused_at) = &schedule.paused_at {
+                println!("{}: {}", "Paused At".bold(), paused_at);
+            }
+            if let Some(last) = &schedule.last_execution {
+                println!("{}: {}", "Last Run".bold(), last);
+            }
+            if let Some(next) = &schedule.next_execution {
+                println!("{}: {}", "Next Run".bold(), next);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn preview(client: &CopilotClient, cron: &str, count: usize, format: &str) -> Result<()> {
+    let schedule = json!({ "type": "cron", "expression": cron });
+    let preview = client.preview_schedule(&schedule, count).await?;
+
+    match format {
+        "json" => {
+            println!("{}", serde_json::to_string_pretty(&preview)?);
+        }
+        "yaml" => {
+            println!("{}", serde_yaml::to_string(&preview)?);
+        }
+        _ => {
+            if preview.fire_times.is_empty() {
+                println!("{}", format!("`{}` never fires.", cron).yellow());
+                return Ok(());
+            }
+            println!("{} {}", "Next runs of".bold(), cron.bold());
+            for time in &preview.fire_times {
+                println!("  {}", time);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// One-line description of a schedule specification
+fn describe(schedule: &Value) -> String {
+    let field = |name: &str| schedule.get(name).cloned().unwrap_or(Value::Null);
+    match schedule.get("type").and_then(Value::as_str) {
+        Some("cron") => format!("cron {}", field("expression").as_str().unwrap_or_default()),
+        Some("interval") => format!("every {}s", field("interval_seconds")),
+        Some("once") => format!("once at {}", field("at").as_str().unwrap_or_default()),
+        Some(kind) => kind.to_string(),
+        None => schedule.to_string(),
+    }
+}
+
+fn describe_missed_runs(policy: &Value) -> String {
+    match policy.get("policy").and_then(Value::as_str) {
+        Some("catch_up") => format!("catch up, at most {}", policy.get("max_runs").cloned().unwrap_or(Value::Null)),
+        Some("run_once") => "run once".to_string(),
+        Some(other) => other.to_string(),
+        None => policy.to_string(),
+    }
+}
@@ -0,0 +1,100 @@
+//! Output schema command
+//!
+//! Publishes the JSON Schema of every command whose output goes through
+//! [`output::emit`](crate::output::emit), so scripts can validate what
+//! `--format json` and `--format yaml` print.
+
+use super::{benchmark, config, doctor, version};
+use crate::output::{self, CommandOutput};
+use anyhow::Result;
+use colored::Colorize;
+use copilot_benchmarks::BenchmarkResult;
+use copilot_sdk::{BootstrapJob, ContextSearchResult, ExecutionResult, HealthResponse, Sandbox};
+use schemars::schema::RootSchema;
+use schemars::{schema_for, JsonSchema};
+use serde::Serialize;
+use std::path::Path;
+
+fn schema<T: CommandOutput>() -> RootSchema {
+    schema_for!(T)
+}
+
+/// Commands with typed output and the schemas of what they print
+fn output_schemas() -> Vec<(&'static str, RootSchema)> {
+    vec![
+        ("version", schema::<version::VersionInfo>()),
+        ("health", schema::<HealthResponse>()),
+        ("doctor", schema::<doctor::DoctorReport>()),
+        ("config show", schema::<config::ConfigView>()),
+        ("config get", schema::<config::ConfigValue>()),
+        ("context search", schema::<Vec<ContextSearchResult>>()),
+        ("context bootstrap", schema::<BootstrapJob>()),
+        ("sandbox run", schema::<ExecutionResult>()),
+        ("sandbox list", schema::<Vec<Sandbox>>()),
+        ("sandbox status", schema::<Sandbox>()),
+        ("run", schema::<benchmark::BenchmarkRun>()),
+        ("benchmark run", schema::<benchmark::BenchmarkRun>()),
+        ("benchmark list", schema::<Vec<benchmark::BenchmarkTarget>>()),
+        ("benchmark show", schema::<BenchmarkResult>()),
+        ("benchmark verify", schema::<benchmark::VerifiedResults>()),
+        ("schema", schema::<Vec<SchemaEntry>>()),
+    ]
+}
+
+/// File a command's schema is written to by `--out-dir`
+fn schema_file(command: &str) -> String {
+    format!("{}.schema.json", command.replace(' ', "-"))
+}
+
+/// A command listed by `copilot schema`
+#[derive(Serialize, JsonSchema)]
+pub struct SchemaEntry {
+    command: String,
+    file: String,
+}
+
+impl CommandOutput for Vec<SchemaEntry> {
+    fn print_text(&self) {
+        println!("{}", "Commands with typed output".green().bold());
+        for entry in self {
+            println!("  {} {}", "•".cyan(), entry.command.bold());
+        }
+        println!();
+        println!("{}", "Print one with `copilot schema <command>`".dimmed());
+    }
+}
+
+pub fn run(command: &str, out_dir: Option<&Path>, format: &str) -> Result<()> {
+    let schemas = output_schemas();
+
+    if let Some(dir) = out_dir {
+        std::fs::create_dir_all(dir)?;
+        for (command, schema) in &schemas {
+            let json = serde_json::to_string_pretty(schema)? + "\n";
+            std::fs::write(dir.join(schema_file(command)), json)?;
+        }
+        output::success(&format!("Wrote {} schemas to {}", schemas.len(), dir.display()));
+        return Ok(());
+    }
+
+    if command.is_empty() {
+        let entries: Vec<SchemaEntry> = schemas
+            .iter()
+            .map(|(command, _)| SchemaEntry {
+                command: command.to_string(),
+                file: schema_file(command),
+            })
+            .collect();
+        return output::emit(&entries, format);
+    }
+
+    let (_, schema) = schemas
+        .iter()
+        .find(|(name, _)| *name == command)
+        .ok_or_else(|| anyhow::anyhow!("No output schema for '{}'; run `copilot schema` to list them", command))?;
+    match format {
+        "yaml" => print!("{}", serde_yaml::to_string(schema)?),
+        _ => println!("{}", serde_json::to_string_pretty(schema)?),
+    }
+    Ok(())
+}
@@ -1,5 +1,6 @@
 //! Server management commands
 
+use super::backup;
 use crate::ServerCommands;
 use anyhow::Result;
 use colored::Colorize;
@@ -13,6 +14,8 @@ pub async fn run(cmd: ServerCommands) -> Result<()> {
         ServerCommands::Stop => stop_server().await,
         ServerCommands::Status => show_status().await,
         ServerCommands::Logs { follow, lines } => show_logs(follow, lines).await,
+        ServerCommands::Backup(args) => backup::backup(args).await,
+        ServerCommands::Restore(args) => backup::restore(args).await,
     }
 }
 
@@ -0,0 +1,604 @@
+//! Shell command suggestions (`copilot do`)
+//!
+//! Asks the agent to turn a task into shell commands, shows each command with
+//! an explanation and a risk rating, and runs it only after confirmation,
+//! either locally or in a sandbox. Command output is sent back into the same
+//! conversation so the agent can summarize the result or suggest a fix.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use copilot_sdk::CopilotClient;
+use dialoguer::Confirm;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Output kept per stream when reporting back to the agent
+const MAX_REPORTED_OUTPUT: usize = 4_000;
+
+/// Exit code recorded for a command that timed out, as `timeout(1)` uses
+const TIMED_OUT_EXIT_CODE: i32 = 124;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Risk {
+    #[default]
+    Low,
+    Medium,
+    High,
+}
+
+impl Risk {
+    fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "high" | "critical" | "dangerous" => Risk::High,
+            "medium" | "moderate" => Risk::Medium,
+            _ => Risk::Low,
+        }
+    }
+
+    fn label(self) -> colored::ColoredString {
+        match self {
+            Risk::Low => "low risk".green(),
+            Risk::Medium => "medium risk".yellow(),
+            Risk::High => "HIGH RISK".red().bold(),
+        }
+    }
+}
+
+/// A command proposed by the agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProposedCommand {
+    command: String,
+    #[serde(default)]
+    explanation: String,
+    /// Rating given by the agent
+    #[serde(default)]
+    risk: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Proposal {
+    #[serde(default)]
+    summary: String,
+    commands: Vec<ProposedCommand>,
+}
+
+/// A proposed command after local risk checks
+#[derive(Debug, Serialize)]
+struct PlannedCommand {
+    command: String,
+    explanation: String,
+    risk: Risk,
+    /// Why the local checks raised the rating, if they did
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+struct CommandOutcome {
+    command: String,
+    risk: Risk,
+    executed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+    stdout: String,
+    stderr: String,
+    duration_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    session_id: String,
+    task: String,
+    summary: String,
+    sandboxed: bool,
+    results: Vec<CommandOutcome>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    follow_up: Option<String>,
+}
+
+/// Patterns that make a command destructive or hard to undo
+const HIGH_RISK: &[(&str, &str)] = &[
+    ("rm -rf", "recursively deletes files"),
+    ("rm -fr", "recursively deletes files"),
+    ("rm -r", "recursively deletes files"),
+    ("mkfs", "formats a filesystem"),
+    ("dd if=", "writes raw data to a device or file"),
+    ("> /dev/", "writes to a device"),
+    (":(){", "fork bomb"),
+    ("chmod -r 777", "makes files world-writable"),
+    ("| sh", "pipes downloaded content into a shell"),
+    ("| bash", "pipes downloaded content into a shell"),
+    ("sudo ", "runs with elevated privileges"),
+    ("shutdown", "stops the machine"),
+    ("reboot", "restarts the machine"),
+    ("git push --force", "rewrites remote history"),
+    ("git push -f", "rewrites remote history"),
+    ("git reset --hard", "discards local changes"),
+    ("git clean -f", "deletes untracked files"),
+    ("drop table", "drops a database table"),
+    ("drop database", "drops a database"),
+    ("truncate table", "deletes table contents"),
+];
+
+/// Patterns that change state but are usually recoverable
+const MEDIUM_RISK: &[(&str, &str)] = &[
+    ("rm ", "deletes files"),
+    ("mv ", "moves or renames files"),
+    ("chmod ", "changes permissions"),
+    ("chown ", "changes ownership"),
+    ("kill ", "stops processes"),
+    ("pkill ", "stops processes"),
+    ("git push", "publishes commits"),
+    ("git commit", "creates commits"),
+    ("git checkout", "switches branches or discards changes"),
+    ("publish", "publishes a package"),
+    ("docker rm", "removes containers or images"),
+    ("docker system prune", "removes containers or images"),
+    ("install", "installs software"),
+    ("curl ", "makes network requests"),
+    ("wget ", "downloads files"),
+    (" > ", "overwrites a file"),
+];
+
+/// Local risk assessment, independent of what the agent claims
+fn assess(command: &str) -> (Risk, Vec<&'static str>) {
+    let normalized = normalize(command);
+
+    for (level, patterns) in [(Risk::High, HIGH_RISK), (Risk::Medium, MEDIUM_RISK)] {
+        let mut reasons: Vec<&'static str> = patterns
+            .iter()
+            .filter(|(pattern, _)| contains_word(&normalized, pattern))
+            .map(|(_, reason)| *reason)
+            .collect();
+        if !reasons.is_empty() {
+            reasons.dedup();
+            return (level, reasons);
+        }
+    }
+    (Risk::Low, Vec::new())
+}
+
+/// Lowercase `command` with single spaces between words and around pipes, so
+/// `curl ...|sh` reads like `curl ... | sh`
+fn normalize(command: &str) -> String {
+    let mut spaced = String::with_capacity(command.len());
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '|' {
+            spaced.push_str(" |");
+            // Keep `||` together
+            while let Some(c) = chars.next_if_eq(&'|') {
+                spaced.push(c);
+            }
+            spaced.push(' ');
+        } else {
+            spaced.push(c);
+        }
+    }
+    spaced
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Whether `pattern` occurs in `command`, starting at a word boundary when the
+/// pattern starts with a word (so `rm ` does not match `confirm `)
+fn contains_word(command: &str, pattern: &str) -> bool {
+    let starts_with_word = pattern.starts_with(|c: char| c.is_alphanumeric());
+    command.match_indices(pattern).any(|(i, _)| {
+        if !starts_with_word {
+            return true;
+        }
+        match command[..i].chars().next_back() {
+            None => true,
+            Some(c) => c.is_whitespace() || ";|&(`".contains(c),
+        }
+    })
+}
+
+fn system_context() -> String {
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+    let cwd = std::env::current_dir()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+    format!(
+        "OS: {}\nShell: {}\nWorking directory: {}",
+        std::env::consts::OS,
+        shell,
+        cwd
+    )
+}
+
+fn proposal_prompt(task: &str, sandbox: bool) -> String {
+    let target = if sandbox {
+        "an isolated Linux sandbox with bash".to_string()
+    } else {
+        system_context()
+    };
+    format!(
+        "Propose shell commands to accomplish this task:\n\n{task}\n\n\
+         They will run on:\n{target}\n\n\
+         Reply with JSON only, in this exact shape:\n\
+         {{\"summary\": \"<one sentence>\", \"commands\": [\
+         {{\"command\": \"<shell command>\", \"explanation\": \"<what it does>\", \
+         \"risk\": \"low|medium|high\"}}]}}\n\n\
+         Prefer the fewest, least destructive commands. Rate as high anything \
+         that deletes data, needs elevated privileges or cannot be undone."
+    )
+}
+
+/// Extract the JSON object from a reply that may be wrapped in prose or a code fence
+fn parse_proposal(content: &str) -> Result<Proposal> {
+    let (Some(start), Some(end)) = (content.find('{'), content.rfind('}')) else {
+        bail!(
+            "The agent did not propose any commands:\n{}",
+            content.trim()
+        );
+    };
+    serde_json::from_str(&content[start..=end])
+        .map_err(|e| anyhow::anyhow!("Could not parse the agent's proposal: {}", e))
+}
+
+fn plan(proposal: Proposal) -> Vec<PlannedCommand> {
+    proposal
+        .commands
+        .into_iter()
+        .filter(|c| !c.command.trim().is_empty())
+        .map(|c| {
+            let (local, warnings) = assess(&c.command);
+            let risk = Risk::parse(&c.risk).max(local);
+            PlannedCommand {
+                command: c.command.trim().to_string(),
+                explanation: c.explanation,
+                risk,
+                // Only worth showing when they raise the agent's rating
+                warnings: if local > Risk::parse(&c.risk) {
+                    warnings
+                } else {
+                    Vec::new()
+                },
+            }
+        })
+        .collect()
+}
+
+fn spinner(message: &str) -> Result<ProgressBar> {
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
+            .template("{spinner:.cyan} {msg}")?,
+    );
+    spinner.set_message(message.to_string());
+    spinner.enable_steady_tick(Duration::from_millis(80));
+    Ok(spinner)
+}
+
+/// The plan goes to stderr for structured formats so stdout stays parseable
+fn print_plan(summary: &str, commands: &[PlannedCommand], text: bool) {
+    let mut out = String::new();
+    if !summary.is_empty() {
+        out.push_str(&format!("{}\n\n", summary));
+    }
+    for (i, planned) in commands.iter().enumerate() {
+        out.push_str(&format!(
+            "{} {}  {}\n",
+            format!("[{}]", i + 1).bold(),
+            planned.command.cyan(),
+            format!("({})", planned.risk.label()).dimmed()
+        ));
+        if !planned.explanation.is_empty() {
+            out.push_str(&format!("    {}\n", planned.explanation.dimmed()));
+        }
+        for warning in &planned.warnings {
+            out.push_str(&format!("    {} {}\n", "!".yellow(), warning.yellow()));
+        }
+    }
+
+    if text {
+        println!("{}", out);
+    } else {
+        eprintln!("{}", out);
+    }
+}
+
+fn confirm(planned: &PlannedCommand, sandbox: bool) -> Result<bool> {
+    let target = if sandbox { "in the sandbox" } else { "locally" };
+    let prompt = match planned.risk {
+        Risk::High => format!(
+            "{} Run `{}` {}?",
+            "This command is high risk.".red().bold(),
+            planned.command,
+            target
+        ),
+        _ => format!("Run `{}` {}?", planned.command, target),
+    };
+    Ok(Confirm::new()
+        .with_prompt(prompt)
+        .default(planned.risk == Risk::Low)
+        .interact()?)
+}
+
+async fn execute(
+    client: &CopilotClient,
+    command: &str,
+    sandbox: bool,
+    timeout: u64,
+) -> Result<(i32, String, String, u64)> {
+    if sandbox {
+        let result = client.execute_code(command, "bash", timeout).await?;
+        return Ok((
+            result.exit_code,
+            result.stdout,
+            result.stderr,
+            result.duration_ms,
+        ));
+    }
+
+    let started = Instant::now();
+    let mut process = if cfg!(windows) {
+        let mut process = tokio::process::Command::new("cmd");
+        process.arg("/C");
+        process
+    } else {
+        let mut process = tokio::process::Command::new("sh");
+        process.arg("-c");
+        process
+    };
+    process.arg(command).kill_on_drop(true);
+
+    let output = match tokio::time::timeout(Duration::from_secs(timeout), process.output()).await {
+        Ok(output) => output?,
+        Err(_) => {
+            return Ok((
+                TIMED_OUT_EXIT_CODE,
+                String::new(),
+                format!("Timed out after {}s", timeout),
+                started.elapsed().as_millis() as u64,
+            ))
+        }
+    };
+    Ok((
+        output.status.code().unwrap_or(-1),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+        started.elapsed().as_millis() as u64,
+    ))
+}
+
+fn truncate(output: &str) -> &str {
+    if output.len() <= MAX_REPORTED_OUTPUT {
+        return output;
+    }
+    let mut end = MAX_REPORTED_OUTPUT;
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    &output[..end]
+}
+
+fn results_message(results: &[CommandOutcome]) -> String {
+    let mut message = String::from(
+        "Here is what happened with the proposed commands. Briefly summarize the \
+         outcome and, if something failed, suggest the next step.\n",
+    );
+    for result in results {
+        message.push_str(&format!("\n$ {}\n", result.command));
+        if !result.executed {
+            message.push_str("(skipped by the user)\n");
+            continue;
+        }
+        message.push_str(&format!("exit code: {}\n", result.exit_code.unwrap_or(-1)));
+        if !result.stdout.is_empty() {
+            message.push_str(&format!("stdout:\n{}\n", truncate(&result.stdout)));
+        }
+        if !result.stderr.is_empty() {
+            message.push_str(&format!("stderr:\n{}\n", truncate(&result.stderr)));
+        }
+    }
+    message
+}
+
+pub async fn run(
+    api_url: &str,
+    api_key: Option<&str>,
+    task: &str,
+    sandbox: bool,
+    timeout: u64,
+    model: &str,
+    format: &str,
+) -> Result<()> {
+    let client = CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .build()?;
+    let text = format == "text";
+
+    let model = (!model.is_empty() && model != "default").then(|| model.to_string());
+    let session = client.create_session(model).await?;
+
+    let progress = spinner("Asking for commands...")?;
+    let response = client
+        .send_message(&session.id, proposal_prompt(task, sandbox))
+        .await;
+    progress.finish_and_clear();
+    let proposal = parse_proposal(&response?.content)?;
+    let summary = proposal.summary.clone();
+    let commands = plan(proposal);
+
+    if commands.is_empty() {
+        println!("{}", "The agent did not propose any commands.".yellow());
+        return Ok(());
+    }
+    print_plan(&summary, &commands, text);
+
+    let mut results = Vec::new();
+    for planned in &commands {
+        let mut outcome = CommandOutcome {
+            command: planned.command.clone(),
+            risk: planned.risk,
+            executed: false,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            duration_ms: 0,
+        };
+
+        if !confirm(planned, sandbox)? {
+            if text {
+                println!("  {}", "Skipped".dimmed());
+            }
+            results.push(outcome);
+            continue;
+        }
+
+        let (exit_code, stdout, stderr, duration_ms) =
+            execute(&client, &planned.command, sandbox, timeout).await?;
+        outcome.executed = true;
+        outcome.exit_code = Some(exit_code);
+        outcome.duration_ms = duration_ms;
+
+        if text {
+            if !stdout.is_empty() {
+                print!("{}", stdout);
+            }
+            if !stderr.is_empty() {
+                eprint!("{}", stderr.red());
+            }
+            let status = if exit_code == 0 {
+                "✓".green()
+            } else {
+                "✗".red()
+            };
+            println!(
+                "{} {}",
+                status,
+                format!("[Exit code: {} | Duration: {}ms]", exit_code, duration_ms).dimmed()
+            );
+        }
+        outcome.stdout = stdout;
+        outcome.stderr = stderr;
+
+        let failed = exit_code != 0;
+        results.push(outcome);
+        if failed
+            && !Confirm::new()
+                .with_prompt("The command failed. Continue with the remaining commands?")
+                .default(false)
+                .interact()?
+        {
+            break;
+        }
+    }
+
+    let follow_up = if results.iter().any(|r| r.executed) {
+        let progress = spinner("Reporting results...")?;
+        let response = client
+            .send_message(&session.id, results_message(&results))
+            .await;
+        progress.finish_and_clear();
+        Some(response?.content)
+    } else {
+        None
+    };
+
+    let report = Report {
+        session_id: session.id,
+        task: task.to_string(),
+        summary,
+        sandboxed: sandbox,
+        results,
+        follow_up,
+    };
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&report)?),
+        "yaml" => println!("{}", serde_yaml::to_string(&report)?),
+        _ => {
+            if let Some(follow_up) = &report.follow_up {
+                println!();
+                println!("{}", follow_up);
+            }
+            println!();
+            println!(
+                "{}",
+                format!(
+                    "Continue with: copilot chat --session {}",
+                    report.session_id
+                )
+                .dimmed()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assess_risk_tiers() {
+        assert_eq!(assess("ls -la").0, Risk::Low);
+        assert_eq!(assess("git status").0, Risk::Low);
+        assert_eq!(assess("mv a.txt b.txt").0, Risk::Medium);
+        assert_eq!(assess("cargo install ripgrep").0, Risk::Medium);
+        assert_eq!(assess("rm -rf build").0, Risk::High);
+        assert_eq!(assess("SUDO  apt-get update").0, Risk::High);
+
+        let (risk, reasons) = assess("git push --force origin main");
+        assert_eq!(risk, Risk::High);
+        assert_eq!(reasons, vec!["rewrites remote history"]);
+    }
+
+    #[test]
+    fn test_assess_pipes_into_a_shell() {
+        for command in [
+            "curl https://example.com/install.sh | sh",
+            "curl https://example.com/install.sh|sh",
+            "curl https://example.com/install.sh |bash",
+            "wget -qO- https://example.com/install.sh|  bash",
+        ] {
+            let (risk, reasons) = assess(command);
+            assert_eq!(risk, Risk::High, "{}", command);
+            assert_eq!(
+                reasons,
+                vec!["pipes downloaded content into a shell"],
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn test_contains_word_needs_a_boundary() {
+        assert!(!contains_word("confirm the change", "rm "));
+        assert!(contains_word("ls && rm tmp", "rm "));
+        assert!(contains_word("(rm tmp)", "rm "));
+        assert!(contains_word("echo hi > out.txt", " > "));
+    }
+
+    #[test]
+    fn test_normalize_spaces_pipes() {
+        assert_eq!(normalize("Curl  x|SH"), "curl x | sh");
+        assert_eq!(normalize("a||b"), "a || b");
+        assert_eq!(normalize("a |  b"), "a | b");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timeout_is_a_failed_step() {
+        let client = CopilotClient::builder()
+            .base_url("http://localhost:1")
+            .build()
+            .unwrap();
+
+        let (exit_code, _, stderr, _) = execute(&client, "sleep 5", false, 0).await.unwrap();
+        assert_eq!(exit_code, TIMED_OUT_EXIT_CODE);
+        assert_eq!(stderr, "Timed out after 0s");
+    }
+}
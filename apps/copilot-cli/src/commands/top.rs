@@ -0,0 +1,312 @@
+//! Live server dashboard (`copilot top`)
+//!
+//! Streams activity snapshots from the server's dashboard endpoint and renders
+//! request rates, task queue depths, application gauges (active workflows,
+//! sandboxes, ...) and recent server errors. The stream is reopened
+//! automatically if the connection drops.
+
+use anyhow::Result;
+use copilot_sdk::{CopilotClient, DashboardSnapshot};
+use futures::StreamExt;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Sparkline, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Delay before reopening a dropped stream
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// Task priority lanes, highest first
+const PRIORITIES: [&str; 4] = ["critical", "high", "normal", "low"];
+
+enum Update {
+    Snapshot(Box<DashboardSnapshot>),
+    Disconnected(String),
+    Key(KeyCode, KeyModifiers),
+}
+
+struct Dashboard {
+    api_url: String,
+    snapshot: Option<DashboardSnapshot>,
+    /// Set while the stream is down
+    disconnected: Option<String>,
+}
+
+pub async fn run(api_url: &str, api_key: Option<&str>, interval_ms: u64) -> Result<()> {
+    let client = CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .build()?;
+
+    // Fail fast on a bad URL or key instead of opening an empty dashboard
+    let first = client.dashboard().await?;
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    spawn_stream(client, Duration::from_millis(interval_ms), tx.clone());
+    spawn_input(tx);
+
+    let dashboard = Dashboard {
+        api_url: api_url.to_string(),
+        snapshot: Some(first),
+        disconnected: None,
+    };
+
+    let terminal = ratatui::init();
+    let result = dashboard.run(terminal, rx).await;
+    ratatui::restore();
+    result
+}
+
+/// Forward snapshots to the UI, reconnecting whenever the stream ends
+fn spawn_stream(client: CopilotClient, interval: Duration, tx: mpsc::UnboundedSender<Update>) {
+    tokio::spawn(async move {
+        loop {
+            let reason = match client.dashboard_stream(interval).await {
+                Ok(mut stream) => loop {
+                    match stream.next().await {
+                        Some(Ok(snapshot)) => {
+                            if tx.send(Update::Snapshot(Box::new(snapshot))).is_err() {
+                                return;
+                            }
+                        }
+                        Some(Err(e)) => break e.to_string(),
+                        None => break "stream closed by server".to_string(),
+                    }
+                },
+                Err(e) => e.to_string(),
+            };
+
+            if tx.send(Update::Disconnected(reason)).is_err() {
+                return;
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+/// Read key presses on a blocking thread
+fn spawn_input(tx: mpsc::UnboundedSender<Update>) {
+    std::thread::spawn(move || loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                if tx.send(Update::Key(key.code, key.modifiers)).is_err() {
+                    return;
+                }
+            }
+            Ok(_) => {}
+            Err(_) => return,
+        }
+    });
+}
+
+impl Dashboard {
+    async fn run(
+        mut self,
+        mut terminal: DefaultTerminal,
+        mut rx: mpsc::UnboundedReceiver<Update>,
+    ) -> Result<()> {
+        terminal.draw(|frame| self.draw(frame))?;
+
+        while let Some(update) = rx.recv().await {
+            match update {
+                Update::Snapshot(snapshot) => {
+                    self.snapshot = Some(*snapshot);
+                    self.disconnected = None;
+                }
+                Update::Disconnected(reason) => self.disconnected = Some(reason),
+                Update::Key(KeyCode::Char('q') | KeyCode::Esc, _) => break,
+                Update::Key(KeyCode::Char('c'), modifiers)
+                    if modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    break
+                }
+                Update::Key(..) => {}
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+        }
+
+        Ok(())
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, requests, panels, errors, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(7),
+            Constraint::Length(8),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(self.header(), header);
+        frame.render_widget(
+            Line::from(" q quit ").style(Style::new().add_modifier(Modifier::DIM)),
+            footer,
+        );
+
+        let Some(snapshot) = &self.snapshot else {
+            return;
+        };
+        self.draw_requests(frame, snapshot, requests);
+
+        let [tasks, gauges] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(panels);
+        frame.render_widget(task_panel(snapshot), tasks);
+        frame.render_widget(gauge_panel(snapshot), gauges);
+        frame.render_widget(error_table(snapshot), errors);
+    }
+
+    fn header(&self) -> Line<'_> {
+        let status = match &self.disconnected {
+            None => Span::from("● live").green(),
+            Some(reason) => Span::from(format!("● reconnecting ({})", reason)).red(),
+        };
+        let uptime = self
+            .snapshot
+            .as_ref()
+            .map(|s| format!("up {}", format_uptime(s.uptime_secs)))
+            .unwrap_or_default();
+
+        Line::from(vec![
+            Span::from(" copilot top ").bold(),
+            Span::from(format!("{}  {}  ", self.api_url, uptime)),
+            status,
+        ])
+    }
+
+    fn draw_requests(&self, frame: &mut Frame, snapshot: &DashboardSnapshot, area: Rect) {
+        let [counters, rate] =
+            Layout::horizontal([Constraint::Length(28), Constraint::Min(10)]).areas(area);
+
+        let error_style = if snapshot.errors_total > 0 {
+            Style::new().fg(Color::Red)
+        } else {
+            Style::new()
+        };
+        let lines = vec![
+            stat_line("req/s", format!("{:.1}", snapshot.requests_per_sec)),
+            stat_line("total", snapshot.requests_total.to_string()),
+            stat_line("in flight", snapshot.in_flight.to_string()),
+            stat_line("errors", snapshot.errors_total.to_string()).style(error_style),
+        ];
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Requests ")),
+            counters,
+        );
+
+        // Show the most recent seconds that fit in the panel
+        let width = rate.width.saturating_sub(2) as usize;
+        let history = &snapshot.request_rate_history;
+        let visible = &history[history.len().saturating_sub(width)..];
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::bordered().title(" Requests per second (last minute) "))
+                .data(visible)
+                .style(Style::new().fg(Color::Cyan)),
+            rate,
+        );
+    }
+}
+
+fn stat_line(label: &str, value: String) -> Line<'static> {
+    Line::from(vec![
+        Span::from(format!(" {:<10}", label)).add_modifier(Modifier::DIM),
+        Span::from(value).bold(),
+    ])
+}
+
+fn task_panel(snapshot: &DashboardSnapshot) -> Paragraph<'static> {
+    let tasks = &snapshot.tasks;
+    let by_priority: Vec<String> = PRIORITIES
+        .iter()
+        .map(|p| {
+            let count = tasks.queued_by_priority.get(*p).copied().unwrap_or(0);
+            format!("{} {}", p, count)
+        })
+        .collect();
+
+    let lines = vec![
+        stat_line("queued", tasks.queued.to_string()),
+        Line::from(format!("   {}", by_priority.join("  "))).add_modifier(Modifier::DIM),
+        stat_line("running", tasks.running.to_string()),
+        stat_line("completed", tasks.completed.to_string()),
+        stat_line("failed", tasks.failed.to_string()),
+        stat_line("cancelled", tasks.cancelled.to_string()),
+    ];
+    Paragraph::new(lines).block(Block::bordered().title(" Task queue "))
+}
+
+fn gauge_panel(snapshot: &DashboardSnapshot) -> Paragraph<'static> {
+    let mut gauges: Vec<_> = snapshot.gauges.iter().collect();
+    gauges.sort_by(|a, b| a.0.cmp(b.0));
+
+    let lines: Vec<Line> = if gauges.is_empty() {
+        vec![Line::from(" no gauges reported").add_modifier(Modifier::DIM)]
+    } else {
+        gauges
+            .into_iter()
+            .map(|(name, value)| {
+                let value = if value.fract() == 0.0 {
+                    format!("{}", *value as i64)
+                } else {
+                    format!("{:.2}", value)
+                };
+                Line::from(vec![
+                    Span::from(format!(" {:<22}", name.replace('_', " "))).add_modifier(Modifier::DIM),
+                    Span::from(value).bold(),
+                ])
+            })
+            .collect()
+    };
+    Paragraph::new(lines).block(Block::bordered().title(" Workflows, sandboxes & queues "))
+}
+
+fn error_table(snapshot: &DashboardSnapshot) -> Table<'static> {
+    let rows = snapshot.recent_errors.iter().map(|error| {
+        let time = chrono::DateTime::parse_from_rfc3339(&error.timestamp)
+            .map(|t| t.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+            .unwrap_or_else(|_| error.timestamp.clone());
+        Row::new(vec![
+            Cell::from(time),
+            Cell::from(error.status.to_string()).red(),
+            Cell::from(error.method.clone()),
+            Cell::from(error.path.clone()),
+            Cell::from(format!("{}ms", error.duration_ms)),
+        ])
+    });
+
+    Table::new(
+        rows,
+        [
+            Constraint::Length(9),
+            Constraint::Length(6),
+            Constraint::Length(7),
+            Constraint::Min(20),
+            Constraint::Length(9),
+        ],
+    )
+    .header(
+        Row::new(["time", "status", "method", "path", "duration"])
+            .style(Style::new().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::bordered().title(format!(
+        " Recent errors ({} total) ",
+        snapshot.errors_total
+    )))
+}
+
+fn format_uptime(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs % 86_400 / 3_600, secs % 3_600 / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m {}s", minutes, secs % 60)
+    }
+}
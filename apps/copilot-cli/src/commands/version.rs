@@ -1,7 +1,9 @@
 //! Version information command
 
+use crate::output::{self, CommandOutput};
 use anyhow::Result;
 use colored::Colorize;
+use schemars::JsonSchema;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -12,41 +14,15 @@ pub async fn run(all: bool, format: &str) -> Result<()> {
         build_date: option_env!("BUILD_DATE").map(String::from),
         git_commit: option_env!("GIT_COMMIT").map(String::from),
         rust_version: option_env!("RUST_VERSION").map(String::from),
+        all,
     };
 
-    match format {
-        "json" => {
-            println!("{}", serde_json::to_string_pretty(&version_info)?);
-        }
-        "yaml" => {
-            println!("{}", serde_yaml::to_string(&version_info)?);
-        }
-        _ => {
-            println!("{} {}", "copilot".cyan().bold(), VERSION.green());
-
-            if all {
-                println!();
-                println!("{}", "Components:".bold());
-                println!("  SDK: {}", version_info.sdk_version.green());
-
-                if let Some(date) = &version_info.build_date {
-                    println!("  Build Date: {}", date);
-                }
-                if let Some(commit) = &version_info.git_commit {
-                    println!("  Git Commit: {}", &commit[..7.min(commit.len())]);
-                }
-                if let Some(rust) = &version_info.rust_version {
-                    println!("  Rust: {}", rust);
-                }
-            }
-        }
-    }
-
-    Ok(())
+    output::emit(&version_info, format)
 }
 
-#[derive(serde::Serialize)]
-struct VersionInfo {
+/// Output of `copilot version`
+#[derive(serde::Serialize, JsonSchema)]
+pub struct VersionInfo {
     cli_version: String,
     sdk_version: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -55,4 +31,29 @@ struct VersionInfo {
     git_commit: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     rust_version: Option<String>,
+    /// Show all component versions in the text rendering
+    #[serde(skip)]
+    all: bool,
+}
+
+impl CommandOutput for VersionInfo {
+    fn print_text(&self) {
+        println!("{} {}", "copilot".cyan().bold(), self.cli_version.green());
+
+        if self.all {
+            println!();
+            println!("{}", "Components:".bold());
+            println!("  SDK: {}", self.sdk_version.green());
+
+            if let Some(date) = &self.build_date {
+                println!("  Build Date: {}", date);
+            }
+            if let Some(commit) = &self.git_commit {
+                println!("  Git Commit: {}", &commit[..7.min(commit.len())]);
+            }
+            if let Some(rust) = &self.rust_version {
+                println!("  Rust: {}", rust);
+            }
+        }
+    }
 }
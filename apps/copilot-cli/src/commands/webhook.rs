@@ -0,0 +1,457 @@
+//! Webhook endpoint management commands
+//!
+//! Besides the outbound endpoints managed through the API, `init` scaffolds
+//! inbound endpoints as files for the server's INBOUND_WEBHOOKS directory,
+//! and `test` posts a signed sample payload to one to check the wiring.
+
+use crate::WebhookCommands;
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use copilot_sdk::{
+    CopilotClient, CreateWebhookRequest, WebhookEndpoint, WebhookEndpointUpdate, WebhookSecret, WebhookStats,
+};
+use copilot_webhook::{generate_webhook_secret, InboundWebhookConfig, WebhookSigner};
+use dialoguer::Confirm;
+use serde_json::json;
+use std::path::Path;
+use tabled::{Table, Tabled};
+
+pub async fn run(
+    api_url: &str,
+    api_key: Option<&str>,
+    cmd: WebhookCommands,
+    format: &str,
+) -> Result<()> {
+    let client = CopilotClient::builder()
+        .base_url(api_url)
+        .api_key(api_key.map(String::from))
+        .build()?;
+
+    match cmd {
+        WebhookCommands::List => list_webhooks(&client, format).await,
+        WebhookCommands::Create { name, url, events, headers, schema_version } => {
+            let headers = headers
+                .iter()
+                .map(|header| {
+                    header
+                        .split_once('=')
+                        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                        .ok_or_else(|| anyhow!("Header {} is not NAME=VALUE", header))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let secret = client
+                .create_webhook(&CreateWebhookRequest { name, url, events, headers, schema_version })
+                .await?;
+            print_secret(&secret, "Registered", format)
+        }
+        WebhookCommands::Show { id } => {
+            let endpoint = client.get_webhook(&id).await?;
+            let stats = client.get_webhook_stats(&id).await?;
+            print_endpoint(&endpoint, &stats, format)
+        }
+        WebhookCommands::Update {
+            id,
+            name,
+            url,
+            events,
+            all_events,
+            enable,
+            disable,
+            schema_version,
+            unpin_schema,
+        } => {
+            let update = WebhookEndpointUpdate {
+                name,
+                url,
+                events: if all_events {
+                    Some(Vec::new())
+                } else if events.is_empty() {
+                    None
+                } else {
+                    Some(events)
+                },
+                enabled: match (enable, disable) {
+                    (true, _) => Some(true),
+                    (_, true) => Some(false),
+                    _ => None,
+                },
+                headers: None,
+                schema_version: if unpin_schema { Some(None) } else { schema_version.map(Some) },
+            };
+            let endpoint = client.update_webhook(&id, &update).await?;
+            println!("{} {} ({})", "Updated".green(), endpoint.id, describe_events(&endpoint.events));
+            Ok(())
+        }
+        WebhookCommands::Delete { id, force } => {
+            if !force {
+                let endpoint = client.get_webhook(&id).await?;
+                let prompt = format!("Stop delivering events to {}?", endpoint.url);
+                if !Confirm::new().with_prompt(prompt).default(false).interact()? {
+                    println!("{}", "Cancelled.".yellow());
+                    return Ok(());
+                }
+            }
+
+            client.delete_webhook(&id).await?;
+            println!("{} {}", "Removed".yellow(), id);
+            Ok(())
+        }
+        WebhookCommands::RotateSecret { id, grace_hours } => {
+            let secret = client.rotate_webhook_secret(&id, grace_hours).await?;
+            print_secret(&secret, "Rotated the secret of", format)
+        }
+        WebhookCommands::Schemas { version: Some(version) } => {
+            let schemas = client.get_webhook_schemas(version).await?;
+            match format {
+                "yaml" => println!("{}", serde_yaml::to_string(&schemas)?),
+                _ => println!("{}", serde_json::to_string_pretty(&schemas)?),
+            }
+            Ok(())
+        }
+        WebhookCommands::Schemas { version: None } => list_schema_versions(&client, format).await,
+        WebhookCommands::Init { provider, name, tenant, dir, force } => {
+            init_inbound(api_url, &provider, name.as_deref(), tenant.as_deref(), &dir, force, format)
+        }
+        WebhookCommands::Test { file, url } => test_inbound(api_url, &file, url, format).await,
+        WebhookCommands::Ping { id } => {
+            let result = client.ping_webhook(&id).await?;
+            match format {
+                "json" => println!("{}", serde_json::to_string_pretty(&result)?),
+                "yaml" => println!("{}", serde_yaml::to_string(&result)?),
+                _ => {
+                    let status = result
+                        .status_code
+                        .map(|code| format!("HTTP {}", code))
+                        .or(result.error.clone())
+                        .unwrap_or_default();
+                    if result.delivered {
+                        println!("{} {} in {}ms", "Delivered".green(), status, result.duration_ms);
+                    } else {
+                        println!("{} {} in {}ms", "Failed".red(), status, result.duration_ms);
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn list_webhooks(client: &CopilotClient, format: &str) -> Result<()> {
+    let endpoints = client.list_webhooks().await?;
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&endpoints)?),
+        "yaml" => println!("{}", serde_yaml::to_string(&endpoints)?),
+        _ => {
+            if endpoints.is_empty() {
+                println!("{}", "No webhook endpoints.".dimmed());
+                return Ok(());
+            }
+
+            #[derive(Tabled)]
+            struct EndpointRow {
+                #[tabled(rename = "ID")]
+                id: String,
+                #[tabled(rename = "Name")]
+                name: String,
+                #[tabled(rename = "URL")]
+                url: String,
+                #[tabled(rename = "Events")]
+                events: String,
+                #[tabled(rename = "Enabled")]
+                enabled: bool,
+            }
+
+            let rows: Vec<EndpointRow> = endpoints
+                .iter()
+                .map(|e| EndpointRow {
+                    id: e.id.clone(),
+                    name: e.name.clone(),
+                    url: e.url.clone(),
+                    events: describe_events(&e.events),
+                    enabled: e.enabled,
+                })
+                .collect();
+            println!("{}", Table::new(rows));
+        }
+    }
+
+    Ok(())
+}
+
+async fn list_schema_versions(client: &CopilotClient, format: &str) -> Result<()> {
+    let versions = client.list_webhook_schema_versions().await?;
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&versions)?),
+        "yaml" => println!("{}", serde_yaml::to_string(&versions)?),
+        _ => {
+            #[derive(Tabled)]
+            struct VersionRow {
+                #[tabled(rename = "Version")]
+                version: String,
+                #[tabled(rename = "Changes")]
+                description: String,
+                #[tabled(rename = "Objects")]
+                objects: String,
+            }
+
+            let rows: Vec<VersionRow> = versions
+                .iter()
+                .map(|v| VersionRow {
+                    version: if v.current { format!("{} (current)", v.version) } else { v.version.to_string() },
+                    description: v.description.clone(),
+                    objects: v.changed_objects.join(", "),
+                })
+                .collect();
+            println!("{}", Table::new(rows));
+        }
+    }
+
+    Ok(())
+}
+
+/// Sample payload of a provider, and the headers it is sent with
+fn sample_delivery(provider: &str) -> (serde_json::Value, Vec<(&'static str, &'static str)>) {
+    match provider {
+        "github" => (
+            json!({
+                "zen": "Keep it logically awesome.",
+                "hook_id": 1,
+                "repository": { "full_name": "octo-org/octo-repo" },
+                "sender": { "login": "octocat" },
+            }),
+            vec![("X-GitHub-Event", "ping")],
+        ),
+        "stripe" => (
+            json!({
+                "id": "evt_test_webhook",
+                "type": "payment_intent.succeeded",
+                "livemode": false,
+                "data": { "object": { "id": "pi_test", "object": "payment_intent" } },
+            }),
+            Vec::new(),
+        ),
+        _ => (
+            json!({
+                "event": "copilot.webhook.test",
+                "message": "Sample payload sent by copilot webhook test",
+            }),
+            Vec::new(),
+        ),
+    }
+}
+
+/// Where a provider is pointed at an endpoint, for the printed instructions
+fn provider_setup(provider: &str) -> Option<&'static str> {
+    match provider {
+        "github" => Some("In the repository's Settings > Webhooks, add the URL with content type application/json"),
+        "stripe" => Some("In the Stripe dashboard's Developers > Webhooks, add the URL as an endpoint"),
+        _ => None,
+    }
+}
+
+fn inbound_url(api_url: &str, config: &InboundWebhookConfig) -> String {
+    format!("{}/webhooks/{}", api_url.trim_end_matches('/'), config.id)
+}
+
+fn init_inbound(
+    api_url: &str,
+    provider: &str,
+    name: Option<&str>,
+    tenant: Option<&str>,
+    dir: &Path,
+    force: bool,
+    format: &str,
+) -> Result<()> {
+    let provider = provider.trim().to_lowercase();
+    if provider.is_empty() || !provider.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        bail!("Provider {:?} should be a name like github or stripe", provider);
+    }
+    let path = dir.join(format!("{}.yaml", provider));
+    if path.exists() && !force {
+        bail!("{} already exists; pass --force to replace it", path.display());
+    }
+
+    let mut config = InboundWebhookConfig::new(name.unwrap_or(&provider), &provider, &generate_webhook_secret());
+    if let Some(tenant) = tenant {
+        config = config.with_tenant(tenant);
+    }
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    std::fs::write(&path, serde_yaml::to_string(&config)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let url = inbound_url(api_url, &config);
+    match format {
+        "json" => println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({ "file": path, "url": url, "endpoint": config }))?
+        ),
+        "yaml" => println!("{}", serde_yaml::to_string(&json!({ "file": path, "url": url, "endpoint": config }))?),
+        _ => {
+            println!("{} {} endpoint {} in {}", "Scaffolded".green(), provider, config.id, path.display());
+            println!("{}: {}", "URL".bold(), url);
+            println!("{}: {}", "Secret".bold(), config.secret);
+            println!("{}: {}", "Signature header".bold(), config.signature_header);
+            println!();
+            println!("{}", "To receive and verify deliveries:".bold());
+            println!("  1. Start the server with INBOUND_WEBHOOKS={}", dir.display());
+            let mut step = 2;
+            if let Some(setup) = provider_setup(&provider) {
+                println!("  {}. {}", step, setup);
+                step += 1;
+            }
+            println!(
+                "  {}. Sign each body as {}: t=<unix time>,sha256=<hex HMAC-SHA256 of \"<t>.<body>\" with the secret>;",
+                step, config.signature_header
+            );
+            println!("     signatures older than 5 minutes are refused");
+            if provider_setup(&provider).is_some() {
+                println!(
+                    "     {} signs deliveries its own way, which is not checked; restrict the endpoint to its",
+                    provider
+                );
+                println!("     addresses with allowed_ips in {}", path.display());
+            }
+            println!("  {}. Check the wiring: copilot webhook test {}", step + 1, path.display());
+            println!("{}", "The secret is stored in the file; keep it out of version control.".yellow());
+        }
+    }
+
+    Ok(())
+}
+
+async fn test_inbound(api_url: &str, file: &Path, url: Option<String>, format: &str) -> Result<()> {
+    let yaml = std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let config: InboundWebhookConfig =
+        serde_yaml::from_str(&yaml).with_context(|| format!("{} is not an inbound endpoint", file.display()))?;
+    let url = url.unwrap_or_else(|| inbound_url(api_url, &config));
+
+    let (payload, headers) = sample_delivery(&config.source);
+    let body = serde_json::to_vec(&payload)?;
+    let (signature, _) = WebhookSigner::new(&config.secret).sign_now(&body);
+    let mut request = reqwest::Client::new()
+        .post(&url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(config.signature_header.as_str(), signature);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+
+    let started = std::time::Instant::now();
+    let response = request.send().await.with_context(|| format!("Failed to reach {}", url))?;
+    let duration_ms = started.elapsed().as_millis();
+    let status = response.status();
+    let hint = match status.as_u16() {
+        200..=299 => "accepted by the inbound pipeline",
+        401 => "the signature was refused; does the server have the same secret?",
+        403 => "the endpoint is disabled or this address is not in its allowed_ips",
+        404 => "the server does not know the endpoint; is its file in INBOUND_WEBHOOKS?",
+        413 => "the sample is larger than the endpoint's max_payload_bytes",
+        429 => "the endpoint's rate limit is exhausted; retry later",
+        _ => "unexpected response",
+    };
+
+    match format {
+        "json" | "yaml" => {
+            let result = json!({
+                "url": url,
+                "status_code": status.as_u16(),
+                "delivered": status.is_success(),
+                "duration_ms": duration_ms,
+                "payload": payload,
+            });
+            if format == "json" {
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            } else {
+                println!("{}", serde_yaml::to_string(&result)?);
+            }
+        }
+        _ if status.is_success() => println!("{} HTTP {} in {}ms: {}", "Delivered".green(), status, duration_ms, hint),
+        _ => println!("{} HTTP {} in {}ms: {}", "Failed".red(), status, duration_ms, hint),
+    }
+
+    if !status.is_success() {
+        return Err(anyhow!("Test delivery to {} failed with HTTP {}", url, status));
+    }
+    Ok(())
+}
+
+fn describe_events(events: &[String]) -> String {
+    if events.is_empty() {
+        "all events".to_string()
+    } else {
+        events.join(", ")
+    }
+}
+
+fn print_secret(secret: &WebhookSecret, action: &str, format: &str) -> Result<()> {
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(secret)?),
+        "yaml" => println!("{}", serde_yaml::to_string(secret)?),
+        _ => {
+            println!("{} {} ({})", action.green(), secret.endpoint.id, secret.endpoint.url);
+            println!("{}: {}", "Secret".bold(), secret.secret);
+            if let Some(expires_at) = &secret.endpoint.previous_secret_expires_at {
+                println!("{}", format!("The previous secret stays valid until {}", expires_at).dimmed());
+            }
+            println!("{}", "Store the secret now; it is not shown again.".yellow());
+        }
+    }
+
+    Ok(())
+}
+
+fn print_endpoint(endpoint: &WebhookEndpoint, stats: &WebhookStats, format: &str) -> Result<()> {
+    match format {
+        "json" => println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({ "endpoint": endpoint, "stats": stats }))?
+        ),
+        "yaml" => println!(
+            "{}",
+            serde_yaml::to_string(&serde_json::json!({ "endpoint": endpoint, "stats": stats }))?
+        ),
+        _ => {
+            println!("{}: {}", "Endpoint".bold(), endpoint.id);
+            println!("{}: {}", "Name".bold(), endpoint.name);
+            println!("{}: {}", "URL".bold(), endpoint.url);
+            println!("{}: {}", "Events".bold(), describe_events(&endpoint.events));
+            println!("{}: {}", "Enabled".bold(), endpoint.enabled);
+            if let Some(version) = endpoint.schema_version {
+                println!("{}: {}", "Schema version".bold(), version);
+            }
+            if let Some(expires_at) = &endpoint.previous_secret_expires_at {
+                println!("{}: {}", "Previous secret valid until".bold(), expires_at);
+            }
+
+            println!();
+            println!(
+                "{}: {} delivered, {} failed ({:.1}% success)",
+                "Deliveries".bold(),
+                stats.delivered,
+                stats.failed,
+                stats.success_rate * 100.0
+            );
+            println!(
+                "{}: {:.0}ms avg, {:.0}ms p95",
+                "Latency".bold(),
+                stats.avg_latency_ms,
+                stats.p95_latency_ms
+            );
+            if let Some(error) = &stats.last_error {
+                println!("{}: {}", "Last error".bold(), error.red());
+            }
+            for by_type in &stats.by_event_type {
+                println!(
+                    "  {} {} ({:.1}% success)",
+                    by_type.event_type,
+                    by_type.count,
+                    by_type.success_rate * 100.0
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
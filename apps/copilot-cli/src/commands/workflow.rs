@@ -1,11 +1,13 @@
 //! Workflow management commands
 
 use crate::WorkflowCommands;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::Colorize;
-use copilot_sdk::CopilotClient;
+use copilot_sdk::{CopilotClient, CopilotError, ParameterError, RunEvent};
+use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::collections::HashMap;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 use tabled::{Table, Tabled};
 
@@ -23,11 +25,33 @@ pub async fn run(
     match cmd {
         WorkflowCommands::List => list_workflows(&client, format).await,
         WorkflowCommands::Show { id } => show_workflow(&client, &id, format).await,
-        WorkflowCommands::Run { workflow, param, wait } => {
-            run_workflow(&client, &workflow, param, wait, format).await
+        WorkflowCommands::Run {
+            workflow,
+            param,
+            param_file,
+            template,
+            dry_run,
+            wait,
+            watch,
+        } => {
+            let params = Params::load(param_file, param)?;
+            let follow = Follow::from_flags(wait, watch);
+            if template {
+                run_template(&client, &workflow, params, dry_run, follow, format).await
+            } else {
+                run_workflow(&client, &workflow, params.values.into_iter().collect(), follow, format).await
+            }
+        }
+        WorkflowCommands::Templates { category } => {
+            list_templates(&client, category.as_deref(), format).await
         }
-        WorkflowCommands::Status { execution_id } => {
-            workflow_status(&client, &execution_id, format).await
+        WorkflowCommands::Params { template } => show_parameters(&client, &template, format).await,
+        WorkflowCommands::Status { execution_id, watch } => {
+            if watch {
+                watch_execution(&client, &execution_id, format).await
+            } else {
+                workflow_status(&client, &execution_id, format).await
+            }
         }
         WorkflowCommands::Cancel { execution_id } => {
             cancel_workflow(&client, &execution_id).await
@@ -112,29 +136,107 @@ async fn show_workflow(client: &CopilotClient, id: &str, format: &str) -> Result
     Ok(())
 }
 
+/// Input parameters and where each came from
+struct Params {
+    values: Map<String, Value>,
+    file: Option<String>,
+    from_file: HashSet<String>,
+}
+
+impl Params {
+    /// Parameters from `file`, overridden by `key=value` pairs
+    fn load(file: Option<String>, pairs: Vec<String>) -> Result<Self> {
+        let mut values = match &file {
+            Some(file) => read_param_file(file)?,
+            None => Map::new(),
+        };
+        let mut from_file: HashSet<String> = values.keys().cloned().collect();
+
+        for pair in pairs {
+            if let Some((key, value)) = pair.split_once('=') {
+                // Try to parse as JSON, fall back to string
+                let json_value = serde_json::from_str(value)
+                    .unwrap_or_else(|_| Value::String(value.to_string()));
+                from_file.remove(key);
+                values.insert(key.to_string(), json_value);
+            }
+        }
+
+        Ok(Self {
+            values,
+            file,
+            from_file,
+        })
+    }
+
+    /// Where the rejected parameter was given
+    fn locate(&self, error: &ParameterError) -> String {
+        match &self.file {
+            Some(file) if self.from_file.contains(&error.field) => format!("{}: {}", file, error.field),
+            _ if self.values.contains_key(&error.field) => format!("--param {}", error.field),
+            _ => error.field.clone(),
+        }
+    }
+}
+
+/// Read a JSON or YAML mapping of parameter names to values
+fn read_param_file(file: &str) -> Result<Map<String, Value>> {
+    let content = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read parameter file {}", file))?;
+
+    let value: Value = if file.ends_with(".yaml") || file.ends_with(".yml") {
+        serde_yaml::from_str(&content).with_context(|| format!("{} is not valid YAML", file))?
+    } else {
+        serde_json::from_str(&content).with_context(|| format!("{} is not valid JSON", file))?
+    };
+
+    match value {
+        Value::Object(values) => Ok(values),
+        _ => anyhow::bail!("{} must map parameter names to values", file),
+    }
+}
+
+/// What to do once a run has started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Follow {
+    /// Print the execution ID and return
+    Detach,
+    /// Show a spinner until the run finishes
+    Wait,
+    /// Print each step event until the run finishes
+    Watch,
+}
+
+impl Follow {
+    fn from_flags(wait: bool, watch: bool) -> Self {
+        match (wait, watch) {
+            (_, true) => Self::Watch,
+            (true, false) => Self::Wait,
+            (false, false) => Self::Detach,
+        }
+    }
+
+    async fn run(self, client: &CopilotClient, execution_id: &str, format: &str) -> Result<()> {
+        match self {
+            Self::Detach => Ok(()),
+            Self::Wait => wait_for_execution(client, execution_id).await,
+            Self::Watch => watch_execution(client, execution_id, format).await,
+        }
+    }
+}
+
 async fn run_workflow(
     client: &CopilotClient,
     workflow: &str,
-    params: Vec<String>,
-    wait: bool,
+    input: HashMap<String, Value>,
+    follow: Follow,
     format: &str,
 ) -> Result<()> {
-    // Parse parameters
-    let mut input: HashMap<String, serde_json::Value> = HashMap::new();
-    for param in params {
-        if let Some((key, value)) = param.split_once('=') {
-            // Try to parse as JSON, fall back to string
-            let json_value = serde_json::from_str(value)
-                .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
-            input.insert(key.to_string(), json_value);
-        }
-    }
-
     println!("{} workflow {}...", "Starting".green(), workflow.cyan());
 
     let execution = client.start_workflow(workflow, input).await?;
 
-    if !wait {
+    if follow == Follow::Detach {
         match format {
             "json" => {
                 println!("{}", serde_json::to_string_pretty(&execution)?);
@@ -152,7 +254,66 @@ async fn run_workflow(
         return Ok(());
     }
 
-    // Wait for completion
+    follow.run(client, &execution.id, format).await
+}
+
+async fn run_template(
+    client: &CopilotClient,
+    template: &str,
+    params: Params,
+    dry_run: bool,
+    follow: Follow,
+    format: &str,
+) -> Result<()> {
+    if !dry_run {
+        println!("{} template {}...", "Starting".green(), template.cyan());
+    }
+
+    let run = match client.run_template(template, &params.values, dry_run).await {
+        Ok(run) => run,
+        Err(CopilotError::InvalidParameters(errors)) => {
+            println!("{} Invalid parameters for template {}:", "✗".red(), template);
+            for error in &errors {
+                println!("  - {}: {}", params.locate(error).bold(), error.message);
+            }
+            anyhow::bail!("Validation failed");
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let Some(execution_id) = run.execution_id.clone() else {
+        match format {
+            "json" => println!("{}", serde_json::to_string_pretty(&run)?),
+            "yaml" => println!("{}", serde_yaml::to_string(&run)?),
+            _ => {
+                println!("{} Parameters are valid!", "✓".green());
+                println!("{}", serde_json::to_string_pretty(&run.params)?);
+            }
+        }
+        return Ok(());
+    };
+
+    if follow == Follow::Detach {
+        match format {
+            "json" => {
+                println!("{}", serde_json::to_string_pretty(&run)?);
+            }
+            _ => {
+                println!("{}: {}", "Execution ID".bold(), execution_id);
+                println!();
+                println!(
+                    "{}",
+                    "Use 'copilot workflow status <execution_id>' to check progress.".dimmed()
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    follow.run(client, &execution_id, format).await
+}
+
+async fn wait_for_execution(client: &CopilotClient, execution_id: &str) -> Result<()> {
     let pb = ProgressBar::new_spinner();
     pb.set_style(
         ProgressStyle::default_spinner()
@@ -162,7 +323,7 @@ async fn run_workflow(
     pb.enable_steady_tick(Duration::from_millis(100));
 
     loop {
-        let status = client.get_workflow_status(&execution.id).await?;
+        let status = client.get_workflow_status(execution_id).await?;
         pb.set_message(format!("Status: {} - Step: {}", status.status, status.current_step));
 
         match status.status.as_str() {
@@ -190,6 +351,174 @@ async fn run_workflow(
     Ok(())
 }
 
+/// Print the events of a run as the server streams them, one JSON object
+/// per line with `--format json`, until the run finishes
+async fn watch_execution(client: &CopilotClient, execution_id: &str, format: &str) -> Result<()> {
+    let mut events = client.watch_run(execution_id).await?;
+
+    while let Some(event) = events.next().await {
+        let event = event?;
+        if format == "json" {
+            println!("{}", serde_json::to_string(&event)?);
+        } else {
+            println!("{}", describe_event(&event));
+        }
+
+        if event.is_last() {
+            return match event.status.as_str() {
+                "failed" => anyhow::bail!("Workflow execution failed"),
+                _ => Ok(()),
+            };
+        }
+    }
+
+    anyhow::bail!("Event stream of run {} ended before the run finished", execution_id)
+}
+
+/// One line describing a run event
+fn describe_event(event: &RunEvent) -> String {
+    let step = event.step_id.as_deref().unwrap_or("?").bold();
+    let error = event.error.as_deref().unwrap_or("unknown error");
+    match event.kind.as_deref() {
+        None => format!(
+            "{} run {}: {}, {} steps done, {} failed",
+            "Watching".cyan(),
+            event.execution_id,
+            event.status,
+            event.completed_steps,
+            event.failed_steps
+        ),
+        Some("started") => format!("{} run {}", "Started".cyan(), event.execution_id),
+        Some("step_started") => format!("{} {} started", "▶".cyan(), step),
+        Some("step_retried") => format!(
+            "{} {} failed attempt {}, retrying: {}",
+            "↻".yellow(),
+            step,
+            event.attempt.unwrap_or(1),
+            error
+        ),
+        Some("step_completed") => match &event.output_summary {
+            Some(summary) => format!("{} {} completed: {}", "✓".green(), step, summary.dimmed()),
+            None => format!("{} {} completed", "✓".green(), step),
+        },
+        Some("step_failed") => format!("{} {} failed: {}", "✗".red(), step, error),
+        Some("completed") => format!("{}", "Completed!".green()),
+        Some("failed") => format!("{}: {}", "Failed!".red(), error),
+        Some("cancelled") => format!("{}", "Cancelled".yellow()),
+        Some(kind) => format!("{} {}", kind, event.status),
+    }
+}
+
+async fn list_templates(client: &CopilotClient, category: Option<&str>, format: &str) -> Result<()> {
+    let templates = client.list_templates(category).await?;
+
+    match format {
+        "json" => {
+            println!("{}", serde_json::to_string_pretty(&templates)?);
+        }
+        "yaml" => {
+            println!("{}", serde_yaml::to_string(&templates)?);
+        }
+        _ => {
+            if templates.is_empty() {
+                println!("{}", "No templates found".yellow());
+                return Ok(());
+            }
+
+            #[derive(Tabled)]
+            struct TemplateRow {
+                #[tabled(rename = "ID")]
+                id: String,
+                #[tabled(rename = "Name")]
+                name: String,
+                #[tabled(rename = "Category")]
+                category: String,
+                #[tabled(rename = "Required")]
+                required: String,
+                #[tabled(rename = "Description")]
+                description: String,
+            }
+
+            let rows: Vec<TemplateRow> = templates
+                .iter()
+                .map(|t| TemplateRow {
+                    id: t.id.clone(),
+                    name: t.name.clone(),
+                    category: t.category.clone(),
+                    required: t.required_parameters.join(", "),
+                    description: t.description.clone(),
+                })
+                .collect();
+
+            println!("{}", Table::new(rows));
+            println!();
+            println!("Run {} to see a template's parameters", "copilot workflow params <ID>".cyan());
+        }
+    }
+
+    Ok(())
+}
+
+async fn show_parameters(client: &CopilotClient, template: &str, format: &str) -> Result<()> {
+    let schema = client.template_parameters(template).await?;
+
+    match format {
+        "json" => {
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+        }
+        "yaml" => {
+            println!("{}", serde_yaml::to_string(&schema)?);
+        }
+        _ => {
+            println!("{}: {}", "Template".bold(), schema.name);
+            println!("{}: {}", "Description".bold(), schema.description);
+            println!();
+
+            #[derive(Tabled)]
+            struct ParameterRow {
+                #[tabled(rename = "Name")]
+                name: String,
+                #[tabled(rename = "Type")]
+                param_type: String,
+                #[tabled(rename = "Required")]
+                required: String,
+                #[tabled(rename = "Default")]
+                default: String,
+                #[tabled(rename = "Description")]
+                description: String,
+            }
+
+            let rows: Vec<ParameterRow> = schema
+                .parameters
+                .iter()
+                .map(|p| ParameterRow {
+                    name: p.name.clone(),
+                    param_type: type_name(&p.param_type),
+                    required: if p.required { "yes" } else { "no" }.to_string(),
+                    default: p.default_value.as_ref().map(Value::to_string).unwrap_or_default(),
+                    description: p.description.clone().unwrap_or_default(),
+                })
+                .collect();
+
+            println!("{}", Table::new(rows));
+        }
+    }
+
+    Ok(())
+}
+
+/// `select (a|b)` for select parameters, the type name otherwise
+fn type_name(param_type: &Value) -> String {
+    if let Some(options) = param_type.pointer("/select/options").and_then(Value::as_array) {
+        let values: Vec<&str> = options
+            .iter()
+            .filter_map(|o| o.get("value").and_then(Value::as_str))
+            .collect();
+        return format!("select ({})", values.join("|"));
+    }
+    param_type.as_str().unwrap_or("unknown").to_string()
+}
+
 async fn workflow_status(client: &CopilotClient, execution_id: &str, format: &str) -> Result<()> {
     let status = client.get_workflow_status(execution_id).await?;
 
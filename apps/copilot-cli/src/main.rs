@@ -5,8 +5,9 @@
 mod commands;
 mod config;
 mod output;
+mod watch;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use colored::Colorize;
 use std::process::ExitCode;
 
@@ -86,14 +87,43 @@ enum Commands {
         model: String,
     },
 
+    /// Ask for shell commands that accomplish a task and run them after confirmation
+    Do {
+        /// What you want to get done
+        task: String,
+
+        /// Run the commands in a sandbox instead of locally
+        #[arg(long)]
+        sandbox: bool,
+
+        /// Per-command timeout in seconds
+        #[arg(short, long, default_value = "120")]
+        timeout: u64,
+
+        /// Model to use
+        #[arg(long, default_value = "default")]
+        model: String,
+    },
+
+    /// Preview and apply code edits proposed in a chat session
+    Apply(ApplyArgs),
+
     /// Manage conversations
     #[command(subcommand)]
     Conversation(ConversationCommands),
 
+    /// View and manage what the assistant remembers about your preferences
+    #[command(subcommand)]
+    Preferences(PreferenceCommands),
+
     /// Manage and execute workflows
     #[command(subcommand)]
     Workflow(WorkflowCommands),
 
+    /// Pause, resume and preview workflow schedules
+    #[command(subcommand)]
+    Schedule(ScheduleCommands),
+
     /// Execute code in a sandboxed environment
     #[command(subcommand)]
     Sandbox(SandboxCommands),
@@ -102,6 +132,31 @@ enum Commands {
     #[command(subcommand)]
     Context(ContextCommands),
 
+    /// Review ingested documents held for secrets, malware or prompt injection
+    #[command(subcommand)]
+    Quarantine(QuarantineCommands),
+
+    /// Inspect captured chat turns and replay them against a local build
+    #[command(subcommand)]
+    Replay(ReplayCommands),
+
+    /// Manage webhook endpoints and their event subscriptions, and scaffold
+    /// inbound ones
+    #[command(subcommand)]
+    Webhook(WebhookCommands),
+
+    /// Compliance evidence and access reviews (admin only)
+    #[command(subcommand)]
+    Admin(AdminCommands),
+
+    /// Question topics and knowledge gaps (admin only)
+    #[command(subcommand)]
+    Analytics(AnalyticsCommands),
+
+    /// Analyze logs
+    #[command(subcommand)]
+    Logs(LogsCommands),
+
     /// Configuration management
     #[command(subcommand)]
     Config(ConfigCommands),
@@ -115,6 +170,26 @@ enum Commands {
         /// Include detailed component health
         #[arg(short, long)]
         detailed: bool,
+        #[command(flatten)]
+        watch: watch::WatchArgs,
+    },
+
+    /// Check configuration and connectivity, then run an end-to-end smoke
+    /// test, with remediation steps for every failure
+    Doctor(DoctorArgs),
+
+    /// Show a live dashboard of server activity
+    Top {
+        /// Refresh interval in milliseconds
+        #[arg(short, long, default_value = "1000")]
+        interval: u64,
+    },
+
+    /// Serve JSON-RPC for editor integrations over stdio or a Unix socket
+    Daemon {
+        /// Listen on this Unix socket instead of stdio
+        #[arg(long)]
+        socket: Option<std::path::PathBuf>,
     },
 
     /// Display version information
@@ -124,6 +199,16 @@ enum Commands {
         all: bool,
     },
 
+    /// List the commands with typed output, or print the JSON Schema of one
+    Schema {
+        /// Command whose output schema to print, e.g. `sandbox list`
+        command: Vec<String>,
+
+        /// Write every command's schema to this directory
+        #[arg(long, conflicts_with = "command")]
+        out_dir: Option<std::path::PathBuf>,
+    },
+
     /// Initialize a new project with CoPilot
     Init {
         /// Project directory
@@ -131,8 +216,17 @@ enum Commands {
         path: String,
 
         /// Project template
-        #[arg(short, long, default_value = "default")]
+        #[arg(
+            short,
+            long,
+            default_value = "default",
+            value_parser = clap::builder::PossibleValuesParser::new(commands::init::TEMPLATES)
+        )]
         template: String,
+
+        /// Ingest the template's sources (README, docs/, src/) after scaffolding
+        #[arg(long)]
+        ingest: bool,
     },
 
     /// Generate shell completions
@@ -142,6 +236,10 @@ enum Commands {
         shell: String,
     },
 
+    /// Manage CLI plugins
+    #[command(subcommand)]
+    Plugin(PluginCommands),
+
     /// Run benchmarks and performance tests
     #[command(subcommand)]
     Benchmark(BenchmarkCommands),
@@ -159,7 +257,98 @@ enum Commands {
         /// Skip writing results to disk
         #[arg(long)]
         no_write: bool,
+
+        /// Run deterministically with this seed (frozen clock, zero timings)
+        #[arg(long, env = "COPILOT_SEED")]
+        seed: Option<u64>,
+
+        /// Stop a benchmark after this many seconds and report it as timed out
+        #[arg(long, default_value = "300")]
+        timeout: u64,
+
+        /// Run each benchmark on its own thread and runtime
+        #[arg(long)]
+        isolate: bool,
+
+        /// Pin isolated benchmark threads to these CPUs in turn (Linux only;
+        /// implies --isolate)
+        #[arg(long, value_delimiter = ',')]
+        pin_cpus: Vec<usize>,
+
+        /// Sign the combined results with this machine's identity key
+        /// (COPILOT_BENCH_IDENTITY_KEY or ~/.copilot/benchmark_identity.pk8)
+        #[arg(long)]
+        sign: bool,
     },
+
+    /// Run a `copilot-<name>` plugin
+    #[command(external_subcommand)]
+    External(Vec<String>),
+}
+
+#[derive(Args)]
+struct ApplyArgs {
+    /// Session whose latest response contains the edits
+    #[arg(short, long)]
+    session: String,
+
+    /// Directory the edited paths are relative to
+    #[arg(short, long, default_value = ".")]
+    dir: std::path::PathBuf,
+
+    /// Only preview the edits and check them for conflicts
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Apply without asking for confirmation
+    #[arg(short, long)]
+    yes: bool,
+
+    /// Create and switch to this git branch before applying
+    #[arg(short, long)]
+    branch: Option<String>,
+
+    /// Commit the edited files, optionally with a custom message
+    #[arg(short = 'm', long, num_args = 0..=1, default_missing_value = "")]
+    commit: Option<String>,
+}
+
+#[derive(Args)]
+struct DoctorArgs {
+    /// Server configuration file to validate and read service URLs from
+    #[arg(long, env = "CONFIG_PATH", default_value = "config/default.toml")]
+    config: std::path::PathBuf,
+
+    /// Postgres connection URL (defaults to `database.url` in the config)
+    #[arg(long, env = "DATABASE_URL")]
+    database_url: Option<String>,
+
+    /// Redis connection URL (defaults to `redis.url` in the config)
+    #[arg(long, env = "REDIS_URL")]
+    redis_url: Option<String>,
+
+    /// NATS server URL (defaults to `nats.url` in the config)
+    #[arg(long, env = "NATS_URL")]
+    nats_url: Option<String>,
+
+    /// Health endpoint of the vector store, e.g. `http://qdrant:6333/healthz`
+    /// (defaults to the configured `vector_db` provider's URL)
+    #[arg(long, env = "VECTOR_STORE_URL")]
+    vector_store_url: Option<String>,
+
+    /// Only check configuration and connectivity
+    #[arg(long)]
+    skip_smoke_test: bool,
+
+    /// Seconds each check may take
+    #[arg(short, long, default_value = "15")]
+    timeout: u64,
+}
+
+#[derive(Subcommand)]
+enum PluginCommands {
+    /// List installed plugins
+    List,
 }
 
 #[derive(Subcommand)]
@@ -177,6 +366,28 @@ enum BenchmarkCommands {
         /// Skip writing results to disk
         #[arg(long)]
         no_write: bool,
+
+        /// Run deterministically with this seed (frozen clock, zero timings)
+        #[arg(long, env = "COPILOT_SEED")]
+        seed: Option<u64>,
+
+        /// Stop a benchmark after this many seconds and report it as timed out
+        #[arg(long, default_value = "300")]
+        timeout: u64,
+
+        /// Run each benchmark on its own thread and runtime
+        #[arg(long)]
+        isolate: bool,
+
+        /// Pin isolated benchmark threads to these CPUs in turn (Linux only;
+        /// implies --isolate)
+        #[arg(long, value_delimiter = ',')]
+        pin_cpus: Vec<usize>,
+
+        /// Sign the combined results with this machine's identity key
+        /// (COPILOT_BENCH_IDENTITY_KEY or ~/.copilot/benchmark_identity.pk8)
+        #[arg(long)]
+        sign: bool,
     },
     /// List available benchmarks
     List,
@@ -185,6 +396,15 @@ enum BenchmarkCommands {
         /// Benchmark target ID
         target_id: String,
     },
+    /// Verify the signature of a combined results file and show its provenance
+    Verify {
+        /// Combined results file
+        file: std::path::PathBuf,
+
+        /// Only accept results signed by this key ID
+        #[arg(long)]
+        key_id: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -219,6 +439,20 @@ enum ConversationCommands {
         #[arg(short, long, default_value = "json")]
         format: String,
     },
+    /// Hand a conversation over to humans via incident channels or tickets
+    Handoff {
+        /// Conversation ID
+        id: String,
+        /// Handoff endpoints to post to (e.g. incident, ticket); all by default
+        #[arg(short, long = "target")]
+        targets: Vec<String>,
+        /// Notes for the people taking over
+        #[arg(short, long)]
+        notes: Option<String>,
+        /// Print the handoff document without posting it
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -234,17 +468,44 @@ enum WorkflowCommands {
     Run {
         /// Workflow ID or file path
         workflow: String,
-        /// Input parameters (key=value)
+        /// Input parameters (key=value), overriding --param-file
         #[arg(short, long)]
         param: Vec<String>,
+        /// JSON or YAML file of input parameters
+        #[arg(long)]
+        param_file: Option<String>,
+        /// Run the workflow template with this ID, validating the parameters
+        #[arg(short, long)]
+        template: bool,
+        /// Only validate the parameters of a template run
+        #[arg(long, requires = "template")]
+        dry_run: bool,
         /// Wait for completion
         #[arg(short, long)]
         wait: bool,
+        /// Wait for completion, printing each step as it starts, retries,
+        /// completes or fails
+        #[arg(long)]
+        watch: bool,
+    },
+    /// List workflow templates
+    Templates {
+        /// Only list templates in this category, e.g. DevOps
+        #[arg(short, long)]
+        category: Option<String>,
+    },
+    /// Show the parameters of a workflow template
+    Params {
+        /// Template ID
+        template: String,
     },
     /// Check workflow execution status
     Status {
         /// Execution ID
         execution_id: String,
+        /// Follow the run until it finishes, printing each step event
+        #[arg(long)]
+        watch: bool,
     },
     /// Cancel a running workflow
     Cancel {
@@ -258,6 +519,75 @@ enum WorkflowCommands {
     },
 }
 
+#[derive(Subcommand)]
+enum PreferenceCommands {
+    /// Show your preferences and the learned ones awaiting confirmation
+    Show,
+    /// Change your preferences
+    Set {
+        /// Answer length: brief, normal or detailed
+        #[arg(long)]
+        verbosity: Option<String>,
+        /// Language to answer in
+        #[arg(long)]
+        language: Option<String>,
+        /// Time range to use when a question gives none, e.g. 24h or 7d
+        #[arg(long)]
+        time_range: Option<String>,
+        /// Services you work on most (replaces the current list)
+        #[arg(long = "favorite-service")]
+        favorite_services: Vec<String>,
+        /// Whether preferences may be learned from your messages
+        #[arg(long)]
+        learning: Option<bool>,
+    },
+    /// Accept a learned preference
+    Confirm {
+        /// Suggestion ID
+        id: String,
+    },
+    /// Reject a learned preference so it is not suggested again
+    Dismiss {
+        /// Suggestion ID
+        id: String,
+    },
+    /// Forget all your preferences and suggestions
+    Delete {
+        /// Skip confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScheduleCommands {
+    /// List workflow schedules
+    List,
+    /// Show schedule details
+    Show {
+        /// Schedule ID
+        id: String,
+    },
+    /// Stop a schedule from firing
+    Pause {
+        /// Schedule ID
+        id: String,
+    },
+    /// Resume a paused schedule from its next fire time
+    Resume {
+        /// Schedule ID
+        id: String,
+    },
+    /// Show the next fire times of a cron expression
+    Preview {
+        /// Cron expression, e.g. "*/15 9-17 * * 1-5"
+        cron: String,
+        /// Number of fire times
+        #[arg(short = 'n', long, default_value = "5")]
+        count: usize,
+    },
+}
+
 #[derive(Subcommand)]
 enum SandboxCommands {
     /// Execute code in a sandbox
@@ -321,6 +651,327 @@ enum ContextCommands {
         /// Maximum results
         #[arg(short, long, default_value = "10")]
         limit: usize,
+        #[command(flatten)]
+        filter: ContextFilterArgs,
+        #[command(flatten)]
+        watch: watch::WatchArgs,
+    },
+    /// Delete, retag, re-embed or export every item matching a filter
+    Bulk {
+        /// Operation to run
+        #[arg(value_parser = ["delete", "retag", "reembed", "export"])]
+        operation: String,
+        #[command(flatten)]
+        filter: ContextFilterArgs,
+        /// Tag to add when retagging (repeatable)
+        #[arg(long)]
+        add: Vec<String>,
+        /// Tag to remove when retagging (repeatable)
+        #[arg(long)]
+        remove: Vec<String>,
+        /// File to write exported items to (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+        /// Print the job ID instead of waiting for the job to finish
+        #[arg(long)]
+        no_wait: bool,
+        /// Skip confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Ingest a wiki export, with page summaries, and report its coverage
+    Bootstrap {
+        /// Confluence space export (zip or entities.xml) or zip of markdown files
+        export: std::path::PathBuf,
+        /// Source label attached to stored chunks
+        #[arg(long, default_value = "wiki")]
+        source: String,
+        /// Print the job ID instead of waiting for the job to finish
+        #[arg(long)]
+        no_wait: bool,
+    },
+    /// Embed and cache every item matching a filter ahead of queries
+    Warm {
+        #[command(flatten)]
+        filter: ContextFilterArgs,
+    },
+    /// Bring archived items matching a filter back into memory ahead of queries
+    Rehydrate {
+        #[command(flatten)]
+        filter: ContextFilterArgs,
+    },
+    /// Show embedding cache hit rates
+    CacheStats,
+}
+
+/// Conditions context items must meet; unset conditions match everything
+#[derive(Args)]
+struct ContextFilterArgs {
+    /// Only items carrying this tag (repeatable; all must match)
+    #[arg(short, long)]
+    tag: Vec<String>,
+    /// Only items whose source starts with this prefix
+    #[arg(long)]
+    source: Option<String>,
+    /// Only items in this language
+    #[arg(long)]
+    language: Option<String>,
+    /// Only items of this document type
+    #[arg(long = "type")]
+    doc_type: Option<String>,
+    /// Only items ingested at or after this time (RFC 3339)
+    #[arg(long)]
+    since: Option<String>,
+    /// Only items ingested before this time (RFC 3339)
+    #[arg(long)]
+    until: Option<String>,
+    /// View the corpus as it was at this time (RFC 3339), with only the
+    /// document versions current then
+    #[arg(long)]
+    as_of: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum QuarantineCommands {
+    /// List documents held for review
+    List,
+    /// Show a document's findings and held chunks
+    Show {
+        /// Document ID
+        id: String,
+    },
+    /// Store a reviewed document's held chunks in the knowledge base
+    Release {
+        /// Document ID
+        id: String,
+        /// Skip confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Drop a document's held chunks
+    Discard {
+        /// Document ID
+        id: String,
+        /// Skip confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReplayCommands {
+    /// List captured chat turns, newest first
+    List {
+        /// Maximum number to show
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+    },
+    /// Show a capture's request, reply and retrieved context
+    Show {
+        /// Capture ID, or a capture file from the server's capture directory
+        capture: String,
+    },
+    /// Send a captured request to a server, e.g. a local build, and compare
+    /// the reply with the captured one
+    Run {
+        /// Capture ID, or a capture file from the server's capture directory
+        capture: String,
+        /// Server to replay against
+        #[arg(short, long, default_value = "http://localhost:8080")]
+        target: String,
+        /// API key for the target server
+        #[arg(long, env = "COPILOT_REPLAY_API_KEY")]
+        target_api_key: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AnalyticsCommands {
+    /// Show the topics users ask about that go unanswered: answered with
+    /// low confidence or without sources
+    Gaps {
+        /// Days of questions to report on
+        #[arg(short, long, default_value = "30")]
+        days: u32,
+        /// Show every topic, not only the gaps
+        #[arg(short, long)]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AdminCommands {
+    /// Export the tenant's compliance report: audit trail, roles and
+    /// permissions, retention settings and access reviews
+    ComplianceReport {
+        /// Period: YYYY, YYYY-Qn, YYYY-MM or YYYY-MM-DD..YYYY-MM-DD (the
+        /// last 90 days by default)
+        #[arg(short, long)]
+        period: Option<String>,
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+        /// Report format
+        #[arg(short, long, default_value = "markdown", value_parser = ["markdown", "json"])]
+        format: String,
+    },
+    /// List access reviews
+    AccessReviews {
+        /// Period, as for compliance-report (the last 90 days by default)
+        #[arg(short, long)]
+        period: Option<String>,
+    },
+    /// Record that you reviewed a user's access
+    ReviewAccess {
+        /// User whose access was reviewed
+        user: String,
+        /// retain, reduce or revoke
+        #[arg(short, long, value_parser = ["retain", "reduce", "revoke"])]
+        decision: String,
+        /// Role the user held (repeatable)
+        #[arg(short, long = "role")]
+        roles: Vec<String>,
+        /// Note for the auditor
+        #[arg(short, long)]
+        note: Option<String>,
+    },
+    /// Export the tenant's users as CSV
+    ExportUsers {
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Create or update users from a CSV file with username, email, roles
+    /// (separated by ;), active, external_id and display_name columns
+    ImportUsers {
+        /// CSV file, e.g. one written by export-users
+        file: std::path::PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum WebhookCommands {
+    /// List the tenant's webhook endpoints
+    List,
+    /// Register an endpoint and print its signing secret
+    Create {
+        /// Display name
+        name: String,
+        /// URL deliveries are posted to
+        url: String,
+        /// Event type to subscribe to, e.g. agent_task_completed (repeatable;
+        /// all events when omitted)
+        #[arg(short, long = "event")]
+        events: Vec<String>,
+        /// Extra header sent with every delivery, as NAME=VALUE (repeatable)
+        #[arg(long = "header")]
+        headers: Vec<String>,
+        /// Pin deliveries to a payload schema version
+        #[arg(long)]
+        schema_version: Option<u32>,
+    },
+    /// Show an endpoint and its delivery statistics
+    Show {
+        /// Endpoint ID
+        id: String,
+    },
+    /// Change an endpoint's URL, subscriptions or state
+    Update {
+        /// Endpoint ID
+        id: String,
+        /// New display name
+        #[arg(long)]
+        name: Option<String>,
+        /// New URL
+        #[arg(long)]
+        url: Option<String>,
+        /// Replace the subscribed event types (repeatable)
+        #[arg(short, long = "event", conflicts_with = "all_events")]
+        events: Vec<String>,
+        /// Subscribe to all events
+        #[arg(long)]
+        all_events: bool,
+        /// Resume deliveries
+        #[arg(long, conflicts_with = "disable")]
+        enable: bool,
+        /// Pause deliveries
+        #[arg(long)]
+        disable: bool,
+        /// Pin deliveries to a payload schema version
+        #[arg(long, conflicts_with = "unpin_schema")]
+        schema_version: Option<u32>,
+        /// Deliver in the current payload schema version
+        #[arg(long)]
+        unpin_schema: bool,
+    },
+    /// Remove an endpoint
+    Delete {
+        /// Endpoint ID
+        id: String,
+        /// Skip confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Issue a new signing secret, signing with the old one too for a while
+    RotateSecret {
+        /// Endpoint ID
+        id: String,
+        /// Hours the old secret stays valid (default 24, 0 drops it now)
+        #[arg(long)]
+        grace_hours: Option<u32>,
+    },
+    /// Send a test delivery
+    Ping {
+        /// Endpoint ID
+        id: String,
+    },
+    /// List payload schema versions, or print the JSON Schemas of one
+    Schemas {
+        /// Schema version
+        version: Option<u32>,
+    },
+    /// Scaffold an inbound endpoint for a provider, with a new signing
+    /// secret, and print how to verify its deliveries
+    Init {
+        /// Provider sending the webhooks: github, stripe, or any other name
+        /// for plain JSON
+        provider: String,
+        /// Display name (default: the provider)
+        #[arg(long)]
+        name: Option<String>,
+        /// Tenant the endpoint's events belong to
+        #[arg(long)]
+        tenant: Option<String>,
+        /// Directory the endpoint file is written to; the server serves the
+        /// endpoints in the directory named by INBOUND_WEBHOOKS
+        #[arg(long, default_value = "webhooks")]
+        dir: std::path::PathBuf,
+        /// Overwrite an existing endpoint file
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Send a signed sample payload to an inbound endpoint
+    Test {
+        /// Endpoint file written by `webhook init`
+        file: std::path::PathBuf,
+        /// URL to post to (default: <api-url>/webhooks/<endpoint id>)
+        #[arg(long)]
+        url: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum LogsCommands {
+    /// Cluster log lines into patterns and summarize them
+    Analyze {
+        /// Log file; reads standard input when omitted or "-"
+        file: Option<String>,
+        /// Number of most frequent patterns to show
+        #[arg(short = 'n', long, default_value = "10")]
+        top: usize,
+        /// Skip the model summary
+        #[arg(long)]
+        no_summary: bool,
     },
 }
 
@@ -374,6 +1025,58 @@ enum ServerCommands {
         #[arg(short, long, default_value = "100")]
         lines: usize,
     },
+    /// Snapshot the database, Redis queues and context memory into one archive
+    Backup(BackupArgs),
+    /// Verify a backup archive and restore it
+    Restore(RestoreArgs),
+}
+
+/// Where the platform keeps the state a backup covers; components whose
+/// source is not configured are skipped
+#[derive(Args)]
+struct BackupSources {
+    /// Postgres connection URL
+    #[arg(long, env = "DATABASE_URL")]
+    database_url: Option<String>,
+
+    /// Redis connection URL
+    #[arg(long, env = "REDIS_URL")]
+    redis_url: Option<String>,
+
+    /// Redis key patterns holding queue state
+    #[arg(long = "redis-pattern", default_value = "copilot:queue:*")]
+    redis_patterns: Vec<String>,
+
+    /// Directory of context memory write-ahead logs and snapshots
+    #[arg(long, env = "COPILOT_MEMORY_DIR")]
+    memory_dir: Option<std::path::PathBuf>,
+}
+
+#[derive(Args)]
+struct BackupArgs {
+    /// Archive to write (defaults to copilot-backup-<timestamp>.tar.gz)
+    #[arg(short, long)]
+    output: Option<std::path::PathBuf>,
+
+    #[command(flatten)]
+    sources: BackupSources,
+}
+
+#[derive(Args)]
+struct RestoreArgs {
+    /// Archive written by `copilot server backup`
+    archive: std::path::PathBuf,
+
+    /// Only check the archive's integrity
+    #[arg(long)]
+    verify_only: bool,
+
+    /// Restore without asking for confirmation
+    #[arg(short, long)]
+    yes: bool,
+
+    #[command(flatten)]
+    sources: BackupSources,
 }
 
 #[tokio::main]
@@ -393,59 +1096,121 @@ async fn main() -> ExitCode {
         Commands::Ask { message, context, model } => {
             commands::ask::run(&cli.api_url, cli.api_key.as_deref(), &message, context.as_deref(), &model, &cli.format).await
         }
+        Commands::Do { task, sandbox, timeout, model } => {
+            commands::shell::run(&cli.api_url, cli.api_key.as_deref(), &task, sandbox, timeout, &model, &cli.format).await
+        }
+        Commands::Apply(args) => {
+            commands::apply::run(&cli.api_url, cli.api_key.as_deref(), args, &cli.format).await
+        }
         Commands::Conversation(cmd) => {
             commands::conversation::run(&cli.api_url, cli.api_key.as_deref(), cmd, &cli.format).await
         }
         Commands::Workflow(cmd) => {
             commands::workflow::run(&cli.api_url, cli.api_key.as_deref(), cmd, &cli.format).await
         }
+        Commands::Preferences(cmd) => {
+            commands::preferences::run(&cli.api_url, cli.api_key.as_deref(), cmd, &cli.format).await
+        }
+        Commands::Schedule(cmd) => {
+            commands::schedule::run(&cli.api_url, cli.api_key.as_deref(), cmd, &cli.format).await
+        }
         Commands::Sandbox(cmd) => {
             commands::sandbox::run(&cli.api_url, cli.api_key.as_deref(), cmd, &cli.format).await
         }
         Commands::Context(cmd) => {
             commands::context::run(&cli.api_url, cli.api_key.as_deref(), cmd, &cli.format).await
         }
+        Commands::Quarantine(cmd) => {
+            commands::quarantine::run(&cli.api_url, cli.api_key.as_deref(), cmd, &cli.format).await
+        }
+        Commands::Replay(cmd) => {
+            commands::replay::run(&cli.api_url, cli.api_key.as_deref(), cmd, &cli.format).await
+        }
+        Commands::Webhook(cmd) => {
+            commands::webhook::run(&cli.api_url, cli.api_key.as_deref(), cmd, &cli.format).await
+        }
+        Commands::Admin(cmd) => {
+            commands::admin::run(&cli.api_url, cli.api_key.as_deref(), cmd, &cli.format).await
+        }
+        Commands::Analytics(cmd) => {
+            commands::analytics::run(&cli.api_url, cli.api_key.as_deref(), cmd, &cli.format).await
+        }
+        Commands::Logs(cmd) => {
+            commands::logs::run(&cli.api_url, cli.api_key.as_deref(), cmd, &cli.format).await
+        }
         Commands::Config(cmd) => {
-            commands::config::run(cmd).await
+            commands::config::run(cmd, &cli.format).await
         }
         Commands::Server(cmd) => {
             commands::server::run(cmd).await
         }
-        Commands::Health { detailed } => {
-            commands::health::run(&cli.api_url, detailed, &cli.format).await
+        Commands::Health { detailed, watch } => {
+            commands::health::run(&cli.api_url, detailed, watch.interval(), &cli.format).await
+        }
+        Commands::Doctor(args) => {
+            commands::doctor::run(&cli.api_url, cli.api_key.as_deref(), args, &cli.format).await
+        }
+        Commands::Top { interval } => {
+            commands::top::run(&cli.api_url, cli.api_key.as_deref(), interval).await
+        }
+        Commands::Daemon { socket } => {
+            commands::daemon::run(&cli.api_url, cli.api_key.as_deref(), socket).await
         }
         Commands::Version { all } => {
             commands::version::run(all, &cli.format).await
         }
-        Commands::Init { path, template } => {
-            commands::init::run(&path, &template).await
+        Commands::Schema { command, out_dir } => {
+            commands::schema::run(&command.join(" "), out_dir.as_deref(), &cli.format)
+        }
+        Commands::Init { path, template, ingest } => {
+            commands::init::run(&path, &template, ingest, &cli.api_url, cli.api_key.as_deref()).await
         }
         Commands::Completions { shell } => {
             commands::completions::run(&shell)
         }
+        Commands::Plugin(cmd) => {
+            commands::plugin::run(cmd, &cli.format).await
+        }
+        Commands::External(args) => {
+            let config = commands::plugin::ResolvedConfig::resolve(&cli.api_url, cli.api_key.as_deref(), &cli.format);
+            commands::plugin::run_external(args, config).await
+        }
         Commands::Benchmark(cmd) => {
             let benchmark_cmd = match cmd {
-                BenchmarkCommands::Run { filter, parallel, no_write } => {
+                BenchmarkCommands::Run { filter, parallel, no_write, seed, timeout, isolate, pin_cpus, sign } => {
                     commands::benchmark::BenchmarkCommand::Run {
                         filter,
                         parallel,
                         format: cli.format.clone(),
                         no_write,
+                        seed,
+                        timeout,
+                        isolate,
+                        pin_cpus,
+                        sign,
                     }
                 }
                 BenchmarkCommands::List => commands::benchmark::BenchmarkCommand::List,
                 BenchmarkCommands::Show { target_id } => {
                     commands::benchmark::BenchmarkCommand::Show { target_id }
                 }
+                BenchmarkCommands::Verify { file, key_id } => {
+                    commands::benchmark::BenchmarkCommand::Verify { file, key_id }
+                }
             };
             commands::benchmark::run(benchmark_cmd, &cli.format).await
         }
-        Commands::Run { filter, parallel, no_write } => {
+        Commands::Run { filter, parallel, no_write, seed, timeout, isolate, pin_cpus, sign } => {
             let benchmark_cmd = commands::benchmark::BenchmarkCommand::Run {
                 filter,
                 parallel,
                 format: cli.format.clone(),
                 no_write,
+                seed,
+                timeout,
+                isolate,
+                pin_cpus,
+                sign,
             };
             commands::benchmark::run(benchmark_cmd, &cli.format).await
         }
@@ -1,6 +1,11 @@
 //! Output formatting utilities
+//!
+//! Commands produce a typed [`CommandOutput`] and hand it to [`emit`], which
+//! renders it as text, JSON or YAML per `--format`. The JSON Schema of each
+//! command's output is published by `copilot schema`.
 
 use colored::Colorize;
+use schemars::JsonSchema;
 use serde::Serialize;
 
 /// Output format types
@@ -54,6 +59,21 @@ pub fn format_output<T: Serialize>(value: &T, format: OutputFormat) -> anyhow::R
     }
 }
 
+/// The typed output of a command
+pub trait CommandOutput: Serialize + JsonSchema {
+    /// Print the human-readable rendering
+    fn print_text(&self);
+}
+
+/// Print `output` in `format` (text, json or yaml)
+pub fn emit<T: CommandOutput>(output: &T, format: &str) -> anyhow::Result<()> {
+    match format.parse::<OutputFormat>().map_err(anyhow::Error::msg)? {
+        OutputFormat::Text => output.print_text(),
+        format => println!("{}", format_output(output, format)?),
+    }
+    Ok(())
+}
+
 /// Print a success message
 pub fn success(message: &str) {
     println!("{} {}", "✓".green(), message);
@@ -0,0 +1,135 @@
+//! Watch mode
+//!
+//! [`watch`] re-runs a command at an interval until Ctrl-C. The first run is
+//! rendered in full; later runs print only what changed since the previous
+//! one, so new search results or components flipping health stand out. With
+//! `--format json` every run is one line holding the output and its changes,
+//! with `--format yaml` one document.
+
+use crate::output::{self, CommandOutput, OutputFormat};
+use anyhow::Result;
+use clap::Args;
+use colored::Colorize;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::future::Future;
+use std::time::Duration;
+
+/// Arguments of commands that can be watched
+#[derive(Args, Debug, Clone)]
+pub struct WatchArgs {
+    /// Re-run until interrupted, showing what changed between runs
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Interval between runs in milliseconds
+    #[arg(long, default_value = "2000", requires = "watch")]
+    pub interval: u64,
+}
+
+impl WatchArgs {
+    /// The interval to watch at, if watching
+    pub fn interval(&self) -> Option<Duration> {
+        self.watch.then(|| Duration::from_millis(self.interval))
+    }
+}
+
+/// A change between two runs of a watched command
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(tag = "kind", content = "description", rename_all = "snake_case")]
+pub enum Change {
+    Added(String),
+    Removed(String),
+    Changed(String),
+}
+
+impl Change {
+    fn print(&self) {
+        match self {
+            Change::Added(what) => println!("  {} {}", "+".green(), what),
+            Change::Removed(what) => println!("  {} {}", "-".red(), what),
+            Change::Changed(what) => println!("  {} {}", "~".yellow(), what),
+        }
+    }
+}
+
+/// Output that can describe how it changed since a previous run
+pub trait Watchable: CommandOutput {
+    fn changes(&self, previous: &Self) -> Vec<Change>;
+}
+
+/// One run of a watched command, as printed in JSON and YAML
+#[derive(Serialize)]
+struct WatchEvent<'a, T> {
+    at: chrono::DateTime<chrono::Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<&'a T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    changes: Vec<Change>,
+}
+
+/// Run `poll` every `interval` until Ctrl-C, printing each run's changes.
+/// A failing run is reported and watching continues, so an outage and the
+/// recovery from it both show up
+pub async fn watch<T, F, Fut>(interval: Duration, format: &str, mut poll: F) -> Result<()>
+where
+    T: Watchable,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let format = format.parse::<OutputFormat>().map_err(anyhow::Error::msg)?;
+    let mut previous: Option<Result<T, String>> = None;
+
+    loop {
+        let current = poll().await.map_err(|e| e.to_string());
+        let changes = match (&previous, &current) {
+            (Some(Ok(previous)), Ok(current)) => current.changes(previous),
+            (Some(Ok(_)), Err(e)) => vec![Change::Changed(format!("Failing: {}", e))],
+            (Some(Err(_)), Ok(_)) => vec![Change::Changed("Recovered".to_string())],
+            (Some(Err(previous)), Err(e)) if previous != e => vec![Change::Changed(format!("Failing: {}", e))],
+            _ => Vec::new(),
+        };
+        let first = previous.is_none();
+
+        match format {
+            OutputFormat::Text => print_text(&current, &changes, first),
+            OutputFormat::Json | OutputFormat::Yaml | OutputFormat::Markdown => {
+                let event = WatchEvent {
+                    at: chrono::Utc::now(),
+                    output: current.as_ref().ok(),
+                    error: current.as_ref().err().cloned(),
+                    changes,
+                };
+                if format == OutputFormat::Yaml {
+                    print!("---\n{}", serde_yaml::to_string(&event)?);
+                } else {
+                    println!("{}", serde_json::to_string(&event)?);
+                }
+            }
+        }
+        previous = Some(current);
+
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+fn print_text<T: CommandOutput>(current: &Result<T, String>, changes: &[Change], first: bool) {
+    let time = chrono::Local::now().format("%H:%M:%S");
+    if first {
+        match current {
+            Ok(output) => output.print_text(),
+            Err(e) => output::error(e),
+        }
+        println!();
+        output::dimmed(&format!("[{}] Watching for changes, Ctrl-C to stop", time));
+    } else if !changes.is_empty() {
+        println!("{}", format!("[{}]", time).bold());
+        for change in changes {
+            change.print();
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Access control for the operator endpoints under `/debug`
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use copilot_api::rest::{extract_token, validate_token_with_keys};
+use copilot_security::SigningKeys;
+use std::sync::Arc;
+
+/// How operator endpoints check tokens: against the rotating signing key a
+/// token names, if configured, or else the JWT secret
+#[derive(Clone)]
+pub struct AdminAuth {
+    pub jwt_secret: String,
+    pub signing_keys: Option<SigningKeys>,
+}
+
+impl AdminAuth {
+    pub fn new(jwt_secret: impl Into<String>) -> Self {
+        Self {
+            jwt_secret: jwt_secret.into(),
+            signing_keys: None,
+        }
+    }
+}
+
+/// Reject callers without an `admin` scoped token
+pub async fn require_admin(State(auth): State<Arc<AdminAuth>>, req: Request, next: Next) -> Response {
+    let claims = match extract_token(req.headers())
+        .and_then(|token| validate_token_with_keys(&token, &auth.jwt_secret, auth.signing_keys.as_ref()))
+    {
+        Ok(claims) => claims,
+        Err(e) => return e.into_response(),
+    };
+    if let Err(e) = claims.require_admin() {
+        return e.into_response();
+    }
+    next.run(req).await
+}
@@ -0,0 +1,516 @@
+//! Request prioritization and load shedding
+//!
+//! Requests are classified as interactive (chat, reads), batch (model
+//! comparisons, workflow runs, CI gates) or background (agent tasks,
+//! ingestion, speculative context prefetches) and share a pool of in-flight
+//! slots. Lower classes may only use part of the pool and give way to queued
+//! higher classes, so when the server saturates background work is shed
+//! first and chat keeps responding. A request that cannot be queued, or
+//! waits longer than its class allows, is rejected with `429 Too Many
+//! Requests` and `Retry-After`.
+//!
+//! Health checks, metrics, profiling and long-lived streams (server-sent
+//! events, WebSocket upgrades) bypass admission so they never hold a slot.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use copilot_api::error::ErrorResponse;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::warn;
+
+/// Priority class of a request, highest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestClass {
+    /// A user is waiting on the response
+    Interactive,
+    /// Multi-step work started on demand
+    Batch,
+    /// Queued or bulk work that can be retried later
+    Background,
+}
+
+impl RequestClass {
+    pub const ALL: [RequestClass; 3] = [
+        RequestClass::Interactive,
+        RequestClass::Batch,
+        RequestClass::Background,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestClass::Interactive => "interactive",
+            RequestClass::Batch => "batch",
+            RequestClass::Background => "background",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Class of a request, or `None` when it bypasses admission
+    pub fn classify(method: &Method, path: &str, upgrade: bool) -> Option<Self> {
+        let path = path.trim_end_matches('/');
+        if upgrade
+            || matches!(path, "" | "/health" | "/metrics")
+            || path.starts_with("/debug/")
+            || path.ends_with("/events")
+        {
+            return None;
+        }
+        if *method != Method::POST {
+            return Some(RequestClass::Interactive);
+        }
+        if path.ends_with("/tasks") || path.ends_with("/ingest") || path.ends_with("/prefetch") {
+            Some(RequestClass::Background)
+        } else if path.ends_with("/comparisons") || path.ends_with("/workflows") || path.contains("/gates/") {
+            Some(RequestClass::Batch)
+        } else {
+            Some(RequestClass::Interactive)
+        }
+    }
+}
+
+/// Limits for one request class
+#[derive(Debug, Clone)]
+pub struct ClassLimits {
+    /// Fraction of the in-flight pool the class may use
+    pub share: f64,
+    /// Requests that may wait for a slot before new ones are shed
+    pub max_queued: usize,
+    /// Longest a request waits for a slot
+    pub queue_timeout: Duration,
+}
+
+/// Admission control configuration
+#[derive(Debug, Clone)]
+pub struct AdmissionConfig {
+    /// Requests processed at once across all classes
+    pub max_in_flight: usize,
+    pub interactive: ClassLimits,
+    pub batch: ClassLimits,
+    pub background: ClassLimits,
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight: 512,
+            interactive: ClassLimits {
+                share: 1.0,
+                max_queued: 256,
+                queue_timeout: Duration::from_secs(10),
+            },
+            batch: ClassLimits {
+                share: 0.75,
+                max_queued: 64,
+                queue_timeout: Duration::from_secs(5),
+            },
+            background: ClassLimits {
+                share: 0.5,
+                max_queued: 32,
+                queue_timeout: Duration::from_secs(2),
+            },
+        }
+    }
+}
+
+impl AdmissionConfig {
+    /// Default class limits with a different pool size
+    pub fn with_max_in_flight(max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight,
+            ..Self::default()
+        }
+    }
+
+    fn limits(&self, class: RequestClass) -> &ClassLimits {
+        match class {
+            RequestClass::Interactive => &self.interactive,
+            RequestClass::Batch => &self.batch,
+            RequestClass::Background => &self.background,
+        }
+    }
+
+    /// Slots the class may occupy, at least one
+    fn capacity(&self, class: RequestClass) -> usize {
+        ((self.max_in_flight as f64 * self.limits(class).share).floor() as usize).max(1)
+    }
+}
+
+/// Why a request was shed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShedReason {
+    /// The class queue was full
+    QueueFull,
+    /// No slot freed up within the queue timeout
+    Timeout,
+}
+
+impl ShedReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ShedReason::QueueFull => "queue_full",
+            ShedReason::Timeout => "timeout",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ClassCounters {
+    admitted: AtomicU64,
+    shed_queue_full: AtomicU64,
+    shed_timeout: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct PoolState {
+    in_flight: [usize; 3],
+    queued: [usize; 3],
+}
+
+impl PoolState {
+    fn total_in_flight(&self) -> usize {
+        self.in_flight.iter().sum()
+    }
+}
+
+/// Shared pool of in-flight slots
+#[derive(Debug)]
+pub struct AdmissionController {
+    config: AdmissionConfig,
+    state: Mutex<PoolState>,
+    released: Notify,
+    counters: [ClassCounters; 3],
+}
+
+/// A held slot, released on drop
+#[derive(Debug)]
+pub struct AdmissionPermit {
+    controller: Arc<AdmissionController>,
+    class: RequestClass,
+}
+
+impl Drop for AdmissionPermit {
+    fn drop(&mut self) {
+        self.controller.lock().in_flight[self.class.index()] -= 1;
+        self.controller.released.notify_waiters();
+    }
+}
+
+/// A request waiting for a slot, removed from the queue on drop (also when
+/// the client goes away)
+struct QueuedRequest<'a> {
+    controller: &'a AdmissionController,
+    class: RequestClass,
+}
+
+impl Drop for QueuedRequest<'_> {
+    fn drop(&mut self) {
+        self.controller.lock().queued[self.class.index()] -= 1;
+        // Lower classes may have been waiting on this request
+        self.controller.released.notify_waiters();
+    }
+}
+
+impl AdmissionController {
+    pub fn new(config: AdmissionConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(PoolState::default()),
+            released: Notify::new(),
+            counters: Default::default(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PoolState> {
+        self.state.lock().expect("admission state poisoned")
+    }
+
+    /// Whether `class` may take a slot now: it is under its share of the pool
+    /// and no higher class is waiting
+    fn can_admit(&self, state: &PoolState, class: RequestClass) -> bool {
+        state.total_in_flight() < self.config.capacity(class)
+            && state.queued[..class.index()].iter().all(|queued| *queued == 0)
+    }
+
+    /// Wait for a slot for `class`, or the reason the request is shed
+    pub async fn acquire(self: &Arc<Self>, class: RequestClass) -> Result<AdmissionPermit, ShedReason> {
+        let limits = self.config.limits(class);
+        let counters = &self.counters[class.index()];
+        let deadline = tokio::time::Instant::now() + limits.queue_timeout;
+        let mut waiting: Option<QueuedRequest<'_>> = None;
+
+        let result = loop {
+            let released = self.released.notified();
+            tokio::pin!(released);
+            {
+                let mut state = self.lock();
+                if self.can_admit(&state, class) {
+                    state.in_flight[class.index()] += 1;
+                    break Ok(());
+                }
+                if waiting.is_none() {
+                    if state.queued[class.index()] >= limits.max_queued {
+                        break Err(ShedReason::QueueFull);
+                    }
+                    state.queued[class.index()] += 1;
+                    waiting = Some(QueuedRequest { controller: self, class });
+                }
+                // Register before unlocking so a release in between is not missed
+                released.as_mut().enable();
+            }
+            if tokio::time::timeout_at(deadline, released).await.is_err() {
+                break Err(ShedReason::Timeout);
+            }
+        };
+        drop(waiting);
+
+        match result {
+            Ok(()) => {
+                counters.admitted.fetch_add(1, Ordering::Relaxed);
+                Ok(AdmissionPermit {
+                    controller: self.clone(),
+                    class,
+                })
+            }
+            Err(reason) => {
+                match reason {
+                    ShedReason::QueueFull => &counters.shed_queue_full,
+                    ShedReason::Timeout => &counters.shed_timeout,
+                }
+                .fetch_add(1, Ordering::Relaxed);
+                Err(reason)
+            }
+        }
+    }
+
+    /// Requests shed for `class` so far
+    pub fn shed_count(&self, class: RequestClass) -> u64 {
+        let counters = &self.counters[class.index()];
+        counters.shed_queue_full.load(Ordering::Relaxed) + counters.shed_timeout.load(Ordering::Relaxed)
+    }
+
+    /// Seconds a shed client of `class` should wait before retrying
+    fn retry_after_secs(&self, class: RequestClass) -> u64 {
+        self.config.limits(class).queue_timeout.as_secs().max(1)
+    }
+
+    /// Render the counters and gauges in Prometheus text format
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        let (in_flight, queued) = {
+            let state = self.lock();
+            (state.in_flight, state.queued)
+        };
+
+        let _ = writeln!(out, "# HELP {prefix}_admission_admitted_total Requests admitted");
+        let _ = writeln!(out, "# TYPE {prefix}_admission_admitted_total counter");
+        for class in RequestClass::ALL {
+            let value = self.counters[class.index()].admitted.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{prefix}_admission_admitted_total{{class=\"{}\"}} {value}", class.as_str());
+        }
+
+        let _ = writeln!(out, "# HELP {prefix}_admission_shed_total Requests rejected under load");
+        let _ = writeln!(out, "# TYPE {prefix}_admission_shed_total counter");
+        for class in RequestClass::ALL {
+            let counters = &self.counters[class.index()];
+            for (reason, counter) in [
+                (ShedReason::QueueFull, &counters.shed_queue_full),
+                (ShedReason::Timeout, &counters.shed_timeout),
+            ] {
+                let _ = writeln!(
+                    out,
+                    "{prefix}_admission_shed_total{{class=\"{}\",reason=\"{}\"}} {}",
+                    class.as_str(),
+                    reason.as_str(),
+                    counter.load(Ordering::Relaxed)
+                );
+            }
+        }
+
+        for (name, help, values) in [
+            ("in_flight", "Requests being processed", in_flight),
+            ("queued", "Requests waiting for a slot", queued),
+        ] {
+            let _ = writeln!(out, "# HELP {prefix}_admission_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_admission_{name} gauge");
+            for class in RequestClass::ALL {
+                let _ = writeln!(
+                    out,
+                    "{prefix}_admission_{name}{{class=\"{}\"}} {}",
+                    class.as_str(),
+                    values[class.index()]
+                );
+            }
+        }
+        out
+    }
+}
+
+/// Wrap a router with admission control
+pub fn apply(router: Router, controller: Arc<AdmissionController>) -> Router {
+    router.layer(middleware::from_fn_with_state(controller, admit))
+}
+
+async fn admit(State(controller): State<Arc<AdmissionController>>, req: Request, next: Next) -> Response {
+    let upgrade = req.headers().contains_key(header::UPGRADE);
+    let Some(class) = RequestClass::classify(req.method(), req.uri().path(), upgrade) else {
+        return next.run(req).await;
+    };
+
+    match controller.acquire(class).await {
+        Ok(permit) => {
+            let response = next.run(req).await;
+            drop(permit);
+            response
+        }
+        Err(reason) => {
+            warn!(
+                class = class.as_str(),
+                reason = reason.as_str(),
+                shed_total = controller.shed_count(class),
+                path = %req.uri().path(),
+                "Shedding request"
+            );
+            let retry_after = controller.retry_after_secs(class);
+            let body = ErrorResponse {
+                code: "OVERLOADED".to_string(),
+                message: format!("Server is overloaded; retry {} work later", class.as_str()),
+                details: None,
+            };
+            let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(body)).into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::post};
+    use tower::ServiceExt;
+
+    fn config(max_in_flight: usize, max_queued: usize) -> AdmissionConfig {
+        let limits = |share| ClassLimits {
+            share,
+            max_queued,
+            queue_timeout: Duration::from_millis(200),
+        };
+        AdmissionConfig {
+            max_in_flight,
+            interactive: limits(1.0),
+            batch: limits(0.75),
+            background: limits(0.5),
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        let classify = |method: Method, path: &str| RequestClass::classify(&method, path, false);
+        assert_eq!(classify(Method::POST, "/api/v1/sessions/1/messages"), Some(RequestClass::Interactive));
+        assert_eq!(classify(Method::GET, "/api/v1/tasks"), Some(RequestClass::Interactive));
+        assert_eq!(classify(Method::POST, "/api/v1/tasks"), Some(RequestClass::Background));
+        assert_eq!(classify(Method::POST, "/api/v1/ingest"), Some(RequestClass::Background));
+        assert_eq!(
+            classify(Method::POST, "/api/v1/sessions/s1/prefetch"),
+            Some(RequestClass::Background)
+        );
+        assert_eq!(classify(Method::POST, "/api/v1/workflows"), Some(RequestClass::Batch));
+        assert_eq!(classify(Method::POST, "/api/v1/gates/github"), Some(RequestClass::Batch));
+        assert_eq!(classify(Method::GET, "/health"), None);
+        assert_eq!(classify(Method::GET, "/api/v1/tasks/1/events"), None);
+        assert_eq!(classify(Method::GET, "/debug/pprof/profile"), None);
+        assert_eq!(RequestClass::classify(&Method::GET, "/api/ws", true), None);
+    }
+
+    #[tokio::test]
+    async fn test_lower_classes_are_shed_first() {
+        let controller = Arc::new(AdmissionController::new(config(4, 0)));
+
+        // Background may use half the pool, batch three quarters
+        let mut held = vec![
+            controller.acquire(RequestClass::Background).await.unwrap(),
+            controller.acquire(RequestClass::Background).await.unwrap(),
+        ];
+        assert_eq!(
+            controller.acquire(RequestClass::Background).await.unwrap_err(),
+            ShedReason::QueueFull
+        );
+        held.push(controller.acquire(RequestClass::Batch).await.unwrap());
+        assert!(controller.acquire(RequestClass::Batch).await.is_err());
+        held.push(controller.acquire(RequestClass::Interactive).await.unwrap());
+        assert!(controller.acquire(RequestClass::Interactive).await.is_err());
+
+        drop(held);
+        assert!(controller.acquire(RequestClass::Background).await.is_ok());
+        assert_eq!(controller.shed_count(RequestClass::Background), 1);
+        assert_eq!(controller.shed_count(RequestClass::Interactive), 1);
+        assert!(controller
+            .render_prometheus("copilot")
+            .contains("copilot_admission_shed_total{class=\"batch\",reason=\"queue_full\"} 1"));
+    }
+
+    #[tokio::test]
+    async fn test_queued_requests_wait_for_a_slot() {
+        let controller = Arc::new(AdmissionController::new(config(1, 1)));
+        let permit = controller.acquire(RequestClass::Interactive).await.unwrap();
+
+        let waiter = tokio::spawn({
+            let controller = controller.clone();
+            async move { controller.acquire(RequestClass::Interactive).await.map(|_| ()) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        // The queue holds one request
+        assert_eq!(
+            controller.acquire(RequestClass::Interactive).await.unwrap_err(),
+            ShedReason::QueueFull
+        );
+        drop(permit);
+        assert!(waiter.await.unwrap().is_ok());
+
+        let _permit = controller.acquire(RequestClass::Interactive).await.unwrap();
+        assert_eq!(
+            controller.acquire(RequestClass::Interactive).await.unwrap_err(),
+            ShedReason::Timeout
+        );
+
+        // A client that goes away while queued frees its place in the queue
+        let abandoned = tokio::spawn({
+            let controller = controller.clone();
+            async move { controller.acquire(RequestClass::Interactive).await.map(|_| ()) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        abandoned.abort();
+        let _ = abandoned.await;
+        assert!(controller
+            .render_prometheus("copilot")
+            .contains("copilot_admission_queued{class=\"interactive\"} 0"));
+    }
+
+    #[tokio::test]
+    async fn test_shed_response() {
+        let controller = Arc::new(AdmissionController::new(config(2, 0)));
+        let _held = controller.acquire(RequestClass::Interactive).await.unwrap();
+        let app = apply(Router::new().route("/api/v1/tasks", post(|| async { "queued" })), controller);
+
+        let response = app
+            .oneshot(Request::post("/api/v1/tasks").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+    }
+}
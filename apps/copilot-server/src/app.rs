@@ -1,15 +1,26 @@
 //! Application state and initialization
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::info;
 
-use copilot_core::CoPilotEngine;
-use copilot_conversation::ConversationManager;
+use copilot_core::{CoPilotEngine, FairScheduler, ResidencyLog};
+use copilot_conversation::{
+    CodePolicyLog, CodePolicyStage, ConversationManager, GroundednessMonitor, PostProcessor, TokenBudgetConfig,
+    TokenBudgets,
+};
 use copilot_nlp::NlpEngineImpl;
-use copilot_context::{ContextEngineImpl, ContextEngineConfig};
+use copilot_context::{
+    AccessFence, ContextArchive, ContextEngine, ContextEngineImpl, ContextEngineConfig, ContextPrefetcher, FanOutConfig,
+    FanOutEngine, ResidencyFence,
+};
+use copilot_ingestion::{
+    ContextEngineSink, ImapConfig, ImapConnector, IngestionPipeline, IngestionRules, PipelineConfig, RulesStage,
+};
 
 use crate::cli::Args;
+use crate::pipeline;
 use crate::server::Server;
 
 /// Shared application state
@@ -21,11 +32,35 @@ pub struct AppState {
     pub conversation_manager: Arc<ConversationManager>,
     /// JWT secret for authentication
     pub jwt_secret: String,
+    /// Cross-region context access refused, if tenants' data is kept in
+    /// regions
+    pub residency_log: Option<Arc<ResidencyLog>>,
+    /// Keeps context items and sources with ACLs from callers they do not
+    /// admit
+    pub access_fence: Arc<AccessFence>,
+    /// Responses annotated or blocked by tenants' code policies, if any are
+    /// configured
+    pub code_policy_log: Option<Arc<CodePolicyLog>>,
+    /// Archive tier of the context engine, if inactive items are archived
+    pub context_archive: Option<Arc<ContextArchive>>,
 }
 
 impl AppState {
     /// Create a new application state with all dependencies
-    pub async fn new() -> Result<Self> {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn new(
+        jwt_secret: Option<String>,
+        mut post_processor: PostProcessor,
+        groundedness: Option<Arc<GroundednessMonitor>>,
+        fan_out: Option<FanOutConfig>,
+        llm_scheduler: Option<Arc<FairScheduler>>,
+        residency_log: Option<Arc<ResidencyLog>>,
+        code_policy: Option<CodePolicyStage>,
+        token_budgets: Option<TokenBudgetConfig>,
+        pipeline: Option<&pipeline::PipelineConfig>,
+        context_archive: Option<Arc<ContextArchive>>,
+        context_wal_dir: Option<&std::path::Path>,
+    ) -> Result<Self> {
         info!("Initializing application components");
 
         // Initialize core engine
@@ -35,23 +70,93 @@ impl AppState {
         let nlp_engine = Arc::new(NlpEngineImpl::new());
 
         // Initialize context engine
-        let context_engine = ContextEngineImpl::new(ContextEngineConfig::default())
+        let mut context_engine = ContextEngineImpl::new(ContextEngineConfig::default())
             .map_err(|e| anyhow::anyhow!("Failed to create context engine: {}", e))?;
-        let context_engine = Arc::new(context_engine);
+        if let Some(archive) = &context_archive {
+            context_engine = context_engine.with_archive(archive.clone());
+        }
+        if let Some(dir) = context_wal_dir {
+            info!("Logging context changes to {}", dir.display());
+            context_engine = context_engine
+                .with_write_ahead_log(dir)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to restore context from {}: {}", dir.display(), e))?;
+        }
+        let mut context_engine: Arc<dyn ContextEngine> = Arc::new(context_engine);
+        if let Some(config) = fan_out {
+            info!("Fanning multi-part questions out into up to {} sub-queries", config.max_sub_queries);
+            context_engine = Arc::new(FanOutEngine::with_config(context_engine, config));
+        }
+        if let Some(log) = &residency_log {
+            info!("Keeping tenants' context in their home regions");
+            context_engine = Arc::new(ResidencyFence::new(context_engine, log.clone()));
+        }
+        let access_fence = Arc::new(AccessFence::new(context_engine));
+        let context_engine: Arc<dyn ContextEngine> = access_fence.clone();
+        let mut prefetcher = None;
+        if let Some(pipeline) = pipeline {
+            let mut context = ContextPrefetcher::new(context_engine.clone());
+            if let Some(reranker) = pipeline.reranker()? {
+                info!("Reranking retrieved context with {}", reranker.name());
+                context = context.with_reranker(reranker);
+            }
+            if let Some(compressor) = pipeline.prompt_compressor()? {
+                info!("Compressing retrieved context");
+                context = context.with_prompt_compressor(compressor);
+            }
+            prefetcher = Some(Arc::new(context));
+        }
 
-        // Initialize conversation manager
-        let conversation_manager = Arc::new(
-            ConversationManager::new(nlp_engine, context_engine)
-        );
+        // Initialize conversation manager; code policies run last so they
+        // see the code as returned
+        let mut code_policy_log = None;
+        if let Some(stage) = code_policy {
+            info!("Checking generated code against tenants' code policies");
+            code_policy_log = Some(stage.log().clone());
+            post_processor = post_processor.with_stage(Arc::new(stage));
+        }
+        if !post_processor.is_empty() {
+            info!("Post-processing responses with stages: {:?}", post_processor.stage_names());
+        }
+        let mut conversation_manager =
+            ConversationManager::new(nlp_engine, context_engine).with_post_processor(post_processor);
+        if let Some(prefetcher) = prefetcher {
+            conversation_manager = conversation_manager.with_prefetcher(prefetcher);
+        }
+        if let Some(pipeline) = pipeline {
+            info!(
+                "Answering with pipeline profiles for {} personas and {} tenants",
+                pipeline.personas.len(),
+                pipeline.tenants.len()
+            );
+            conversation_manager = conversation_manager.with_profiles(Arc::new(pipeline.profiles()));
+        }
+        if let Some(monitor) = groundedness {
+            info!("Scoring answer groundedness");
+            conversation_manager = conversation_manager.with_groundedness(monitor);
+        }
+        if let Some(scheduler) = llm_scheduler {
+            info!("Sharing {} model call slots between tenants", scheduler.capacity());
+            conversation_manager = conversation_manager.with_llm_scheduler(scheduler);
+        }
+        if let Some(config) = token_budgets {
+            info!("Limiting conversations to daily token budgets");
+            conversation_manager = conversation_manager.with_token_budgets(Arc::new(TokenBudgets::new(config)));
+        }
+        let conversation_manager = Arc::new(conversation_manager);
 
-        // JWT secret (should come from config in production)
-        let jwt_secret = std::env::var("JWT_SECRET")
-            .unwrap_or_else(|_| "default-dev-secret-change-in-production".to_string());
+        // Required in prod (see Args::validation_report)
+        let jwt_secret = jwt_secret
+            .unwrap_or_else(|| "default-dev-secret-change-in-production".to_string());
 
         Ok(Self {
             engine,
             conversation_manager,
             jwt_secret,
+            residency_log,
+            access_fence,
+            code_policy_log,
+            context_archive,
         })
     }
 }
@@ -65,12 +170,45 @@ pub struct App {
 impl App {
     /// Build the application with all dependencies
     pub async fn build(args: Args) -> Result<Self> {
-        // Validate arguments
-        args.validate()
-            .context("Invalid command line arguments")?;
+        // The pipeline configuration, if given, replaces the settings it
+        // covers (see Args::validation_report)
+        let pipeline = args.pipeline()?;
+        let (post_processor, groundedness, fan_out, code_policy, token_budgets) = match &pipeline {
+            Some(pipeline) => {
+                info!("Configuring the answering pipeline from PIPELINE_CONFIG");
+                (
+                    pipeline.post_processor()?,
+                    pipeline.groundedness(),
+                    pipeline.fan_out(),
+                    pipeline.code_policy(),
+                    pipeline.token_budgets(),
+                )
+            }
+            None => (args.post_processor()?, args.groundedness(), args.fan_out(), args.code_policy()?, args.token_budgets()?),
+        };
 
         // Initialize application state
-        let state = AppState::new().await?;
+        let state = AppState::new(
+            args.jwt_secret.clone(),
+            post_processor,
+            groundedness,
+            fan_out,
+            args.llm_scheduler(),
+            args.residency().map(|_| Arc::new(ResidencyLog::new())),
+            code_policy.map(CodePolicyStage::new),
+            token_budgets,
+            pipeline.as_ref(),
+            args.context_archive(),
+            args.context_wal_dir.as_deref(),
+        )
+        .await?;
+        let acls = args.context_acls().map_err(|e| anyhow::anyhow!("Invalid CONTEXT_ACLS: {}", e))?;
+        if !acls.is_empty() {
+            info!("Restricting {} context sources by ACL", acls.len());
+        }
+        for (prefix, acl) in acls {
+            state.access_fence.set_source_acl(prefix, acl);
+        }
 
         Ok(Self { args, state })
     }
@@ -80,12 +218,36 @@ impl App {
         info!("Starting server");
         info!("HTTP port: {}", self.args.port);
 
+        if let Some(url) = &self.args.imap_url {
+            self.start_imap_connector(url)?;
+        }
+
         // Create and run server
         let server = Server::new(self.args, self.state)?;
         server.run().await?;
 
         Ok(())
     }
+
+    /// Poll a mailbox in the background, storing its mail as context
+    fn start_imap_connector(&self, url: &str) -> Result<()> {
+        let config = ImapConfig::from_url(url)?
+            .with_poll_interval(Duration::from_secs(self.args.imap_poll_interval.max(1)));
+        let engine = self.state.conversation_manager.context_engine();
+        let mut pipeline = IngestionPipeline::with_defaults(PipelineConfig::default())?;
+        if let Some(path) = &self.args.ingestion_rules {
+            let rules = IngestionRules::load(path)?;
+            rules.spawn_reload(Duration::from_secs(10));
+            info!("Applying ingestion rules from {}", path.display());
+            pipeline.insert_stage(0, Arc::new(RulesStage::new(rules).with_authority(engine.clone())));
+        }
+        let sink = ContextEngineSink::new(engine, config.source());
+        info!("Ingesting mail from {}", config.source());
+
+        let connector = ImapConnector::new(config, Arc::new(pipeline), Arc::new(sink));
+        tokio::spawn(async move { connector.run().await });
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -94,7 +256,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_app_state_creation() {
-        let result = AppState::new().await;
+        let result = AppState::new(None, PostProcessor::new(), None, None, None, None, None, None, None, None, None).await;
         assert!(result.is_ok());
     }
 }
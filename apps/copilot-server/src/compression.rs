@@ -0,0 +1,257 @@
+//! Request body limits and HTTP compression
+//!
+//! Request bodies encoded with gzip, deflate or zstd are decompressed as they
+//! stream in, and responses are compressed according to `Accept-Encoding`.
+//! The body size limit applies to the decompressed request, so a small
+//! compressed payload cannot expand past it.
+//!
+//! Byte counters are recorded on both sides of the codecs, giving the wire
+//! (compressed) and raw (decompressed) sizes of each direction.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::header,
+    middleware::{self, Next},
+    response::Response,
+    Router,
+};
+use futures::StreamExt;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tower::ServiceBuilder;
+use tower_http::{
+    compression::CompressionLayer,
+    decompression::{DecompressionBody, RequestDecompressionLayer},
+    limit::RequestBodyLimitLayer,
+};
+
+/// Compressed vs raw byte counts for request and response bodies
+#[derive(Debug, Default)]
+pub struct BodyMetrics {
+    request_wire_bytes: AtomicU64,
+    request_raw_bytes: AtomicU64,
+    response_wire_bytes: AtomicU64,
+    response_raw_bytes: AtomicU64,
+    compressed_requests: AtomicU64,
+    compressed_responses: AtomicU64,
+}
+
+impl BodyMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request_wire_bytes(&self) -> u64 {
+        self.request_wire_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn request_raw_bytes(&self) -> u64 {
+        self.request_raw_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn response_wire_bytes(&self) -> u64 {
+        self.response_wire_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn response_raw_bytes(&self) -> u64 {
+        self.response_raw_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Render the counters in Prometheus text format
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        let counters = [
+            (
+                "request_wire_bytes_total",
+                "Request body bytes as received",
+                self.request_wire_bytes(),
+            ),
+            (
+                "request_raw_bytes_total",
+                "Request body bytes after decompression",
+                self.request_raw_bytes(),
+            ),
+            (
+                "response_raw_bytes_total",
+                "Response body bytes before compression",
+                self.response_raw_bytes(),
+            ),
+            (
+                "response_wire_bytes_total",
+                "Response body bytes as sent",
+                self.response_wire_bytes(),
+            ),
+            (
+                "compressed_requests_total",
+                "Requests received with a Content-Encoding",
+                self.compressed_requests.load(Ordering::Relaxed),
+            ),
+            (
+                "compressed_responses_total",
+                "Responses sent with a Content-Encoding",
+                self.compressed_responses.load(Ordering::Relaxed),
+            ),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {prefix}_http_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_http_{name} counter");
+            let _ = writeln!(out, "{prefix}_http_{name} {value}");
+        }
+        out
+    }
+}
+
+/// Wrap a router with body limits, compression and byte accounting
+pub fn apply(router: Router, max_body_size: usize, metrics: Arc<BodyMetrics>) -> Router {
+    router.layer(
+        ServiceBuilder::new()
+            .layer(middleware::from_fn_with_state(
+                metrics.clone(),
+                count_wire_bytes,
+            ))
+            .layer(CompressionLayer::new().gzip(true).deflate(true).zstd(true))
+            .layer(
+                RequestDecompressionLayer::new()
+                    .gzip(true)
+                    .deflate(true)
+                    .zstd(true),
+            )
+            .map_request(|req: Request<DecompressionBody<Body>>| req.map(Body::new))
+            .layer(middleware::from_fn_with_state(metrics, count_raw_bytes))
+            .layer(RequestBodyLimitLayer::new(max_body_size)),
+    )
+}
+
+/// Count bodies as they cross the network
+async fn count_wire_bytes(
+    State(metrics): State<Arc<BodyMetrics>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if req.headers().contains_key(header::CONTENT_ENCODING) {
+        metrics.compressed_requests.fetch_add(1, Ordering::Relaxed);
+    }
+    let req = count_request(req, &metrics, |m| &m.request_wire_bytes);
+
+    let response = next.run(req).await;
+    if response.headers().contains_key(header::CONTENT_ENCODING) {
+        metrics.compressed_responses.fetch_add(1, Ordering::Relaxed);
+    }
+    count_response(response, &metrics, |m| &m.response_wire_bytes)
+}
+
+/// Count bodies as the handlers see them
+async fn count_raw_bytes(
+    State(metrics): State<Arc<BodyMetrics>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let req = count_request(req, &metrics, |m| &m.request_raw_bytes);
+    let response = next.run(req).await;
+    count_response(response, &metrics, |m| &m.response_raw_bytes)
+}
+
+fn count_request(
+    req: Request,
+    metrics: &Arc<BodyMetrics>,
+    counter: fn(&BodyMetrics) -> &AtomicU64,
+) -> Request {
+    let (parts, body) = req.into_parts();
+    Request::from_parts(parts, counted(body, metrics.clone(), counter))
+}
+
+fn count_response(
+    response: Response,
+    metrics: &Arc<BodyMetrics>,
+    counter: fn(&BodyMetrics) -> &AtomicU64,
+) -> Response {
+    let (parts, body) = response.into_parts();
+    Response::from_parts(parts, counted(body, metrics.clone(), counter))
+}
+
+/// Wrap a body so each data frame is added to a counter as it streams
+fn counted(body: Body, metrics: Arc<BodyMetrics>, counter: fn(&BodyMetrics) -> &AtomicU64) -> Body {
+    Body::from_stream(body.into_data_stream().inspect(move |chunk| {
+        if let Ok(bytes) = chunk {
+            counter(&metrics).fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+    use std::io::{Read, Write as _};
+    use tower::ServiceExt;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn echo_app(max_body_size: usize, metrics: Arc<BodyMetrics>) -> Router {
+        let router = Router::new().route("/echo", post(|body: String| async move { body }));
+        apply(router, max_body_size, metrics)
+    }
+
+    #[tokio::test]
+    async fn test_compressed_round_trip() {
+        let metrics = Arc::new(BodyMetrics::new());
+        let payload = "compress me ".repeat(200);
+        let compressed = gzip(payload.as_bytes());
+
+        let response = echo_app(1024 * 1024, metrics.clone())
+            .oneshot(
+                Request::post("/echo")
+                    .header(header::CONTENT_ENCODING, "gzip")
+                    .header(header::ACCEPT_ENCODING, "gzip")
+                    .body(Body::from(compressed.clone()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let mut decoded = String::new();
+        GzDecoder::new(&body[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, payload);
+
+        assert_eq!(metrics.request_wire_bytes(), compressed.len() as u64);
+        assert_eq!(metrics.request_raw_bytes(), payload.len() as u64);
+        assert_eq!(metrics.response_raw_bytes(), payload.len() as u64);
+        assert_eq!(metrics.response_wire_bytes(), body.len() as u64);
+        assert!(metrics
+            .render_prometheus("copilot")
+            .contains("copilot_http_compressed_responses_total 1"));
+    }
+
+    #[tokio::test]
+    async fn test_limit_applies_to_decompressed_size() {
+        let payload = "x".repeat(4096);
+        let compressed = gzip(payload.as_bytes());
+        assert!(compressed.len() < 1024);
+
+        let response = echo_app(1024, Arc::new(BodyMetrics::new()))
+            .oneshot(
+                Request::post("/echo")
+                    .header(header::CONTENT_ENCODING, "gzip")
+                    .body(Body::from(compressed))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,100 @@
+//! Fault injection for test environments (`--fault-injection`)
+//!
+//! The rules given on the command line are installed in the global
+//! [`FaultInjector`] at startup, and can be changed while the server runs:
+//!
+//! - `GET /debug/faults` returns the rules and the faults injected so far
+//! - `PUT /debug/faults` replaces the rules with a JSON array of rules
+//! - `DELETE /debug/faults` removes every rule
+//!
+//! Every endpoint requires a bearer token with the `admin` scope.
+
+use axum::{http::StatusCode, middleware, routing::get, Json, Router};
+use copilot_api::ApiError;
+use copilot_core::fault::{FaultRule, FaultSpecError};
+use copilot_core::FaultInjector;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::admin::{require_admin, AdminAuth};
+
+/// Install the rules of a `--fault-injection` specification
+pub fn install(spec: &str) -> Result<(), FaultSpecError> {
+    let rules = FaultRule::parse_list(spec)?;
+    warn!(rules = rules.len(), "Fault injection enabled; do not use in production");
+    FaultInjector::global().set_rules(rules);
+    Ok(())
+}
+
+/// Fault injection endpoints, nested under `/debug`
+pub fn router(auth: AdminAuth) -> Router {
+    Router::new()
+        .route("/faults", get(list).put(replace).delete(clear))
+        .layer(middleware::from_fn_with_state(Arc::new(auth), require_admin))
+}
+
+async fn list() -> Json<Value> {
+    let faults = FaultInjector::global();
+    Json(json!({ "rules": faults.rules(), "injected": faults.stats() }))
+}
+
+async fn replace(Json(rules): Json<Vec<FaultRule>>) -> Result<Json<Value>, ApiError> {
+    for rule in &rules {
+        rule.validate().map_err(|e| ApiError::InvalidInput(e.to_string()))?;
+    }
+    warn!(rules = rules.len(), "Fault injection rules replaced");
+    FaultInjector::global().set_rules(rules);
+    Ok(list().await)
+}
+
+async fn clear() -> StatusCode {
+    warn!("Fault injection rules removed");
+    FaultInjector::global().clear();
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header, Request};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_rules_can_be_replaced_at_runtime() {
+        let claims = json!({ "sub": "ops", "exp": 4_000_000_000u64, "iat": 0, "scope": "admin" });
+        let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(b"secret")).unwrap();
+        let send = |method: &str, body: &str| {
+            let req = Request::builder()
+                .method(method)
+                .uri("/faults")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            router(AdminAuth::new("secret")).oneshot(req)
+        };
+
+        let response = send("PUT", r#"[{"target": "router/*", "error_rate": 0.5}]"#).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["rules"][0]["target"], "router/*");
+        assert_eq!(body["rules"][0]["latency_rate"], 1.0);
+        assert!(FaultInjector::global().is_enabled());
+
+        let invalid = send("PUT", r#"[{"target": "router/*", "drop_rate": 2}]"#).await.unwrap();
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+
+        assert_eq!(send("DELETE", "").await.unwrap().status(), StatusCode::NO_CONTENT);
+        assert!(!FaultInjector::global().is_enabled());
+
+        let anonymous = router(AdminAuth::new("secret"))
+            .oneshot(Request::get("/faults").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -1,5 +1,12 @@
+mod admin;
+mod admission;
 mod app;
 mod cli;
+mod compression;
+mod faults;
+mod pipeline;
+#[cfg(feature = "profiling")]
+mod profiling;
 mod server;
 mod telemetry;
 
@@ -7,7 +14,7 @@ use anyhow::Result;
 use clap::Parser;
 use tracing::{info, error};
 
-use crate::cli::Args;
+use crate::cli::{Args, SECRET_ENV_VARS};
 use crate::app::App;
 use crate::telemetry::init_telemetry;
 
@@ -16,8 +23,18 @@ async fn main() -> Result<()> {
     // Load environment variables from .env file if it exists
     dotenv::dotenv().ok();
 
+    // Secrets mounted as files (JWT_SECRET_FILE, ...) become the variables
+    // the arguments are read from
+    let mut report = copilot_core::export_secret_files(SECRET_ENV_VARS);
+
     // Parse command line arguments
     let args = Args::parse();
+    report.merge(args.validation_report());
+
+    if args.check_config {
+        println!("{}", report);
+        std::process::exit(if report.is_ok() { 0 } else { 1 });
+    }
 
     // Initialize telemetry (logging, tracing, metrics)
     let _guards = init_telemetry(&args)?;
@@ -26,6 +43,18 @@ async fn main() -> Result<()> {
     info!("Version: {}", env!("CARGO_PKG_VERSION"));
     info!("Environment: {}", args.env);
 
+    // Report every bad setting at once, before binding any port
+    if !report.is_ok() {
+        for issue in &report.issues {
+            error!(setting = %issue.setting, "{}", issue);
+        }
+        anyhow::bail!("{}", report);
+    }
+
+    if let Some(spec) = &args.fault_injection {
+        faults::install(spec)?;
+    }
+
     // Build and run the application
     let result = run_application(args).await;
 
@@ -58,4 +87,256 @@ mod tests {
         use clap::CommandFactory;
         Args::command().debug_assert()
     }
+
+    #[test]
+    fn validation_report_lists_every_problem() {
+        let args = Args::parse_from([
+            "copilot-server",
+            "--env",
+            "prod",
+            "--slack-bot-token",
+            "xoxb-1",
+            "--notify-webhook-url",
+            "ftp://example.com",
+            "--handoff-webhooks",
+            "incident=https://hooks.example.com/incident,ticket",
+            "--fault-injection",
+            "router/*:error=10%",
+        ]);
+        let settings: Vec<String> = args
+            .validation_report()
+            .issues
+            .into_iter()
+            .map(|issue| issue.setting)
+            .collect();
+        assert_eq!(
+            settings,
+            ["JWT_SECRET", "NOTIFY_WEBHOOK_URL", "HANDOFF_WEBHOOKS", "FAULT_INJECTION", "SLACK_SIGNING_SECRET"]
+        );
+
+        let args = Args::parse_from(["copilot-server", "--fault-injection", "router/*:error=2"]);
+        assert_eq!(args.validation_report().issues.len(), 1);
+    }
+
+    #[test]
+    fn validation_report_checks_dependencies() {
+        let args = Args::parse_from([
+            "copilot-server",
+            "--redis-url",
+            "http://cache:6379",
+            "--nats-url",
+            "nats://bus:4222",
+            "--required-dependencies",
+            "postgres,nats,kafka",
+        ]);
+        let settings: Vec<String> = args
+            .validation_report()
+            .issues
+            .into_iter()
+            .map(|issue| issue.setting)
+            .collect();
+        assert_eq!(settings, ["REDIS_URL", "DATABASE_URL", "REQUIRED_DEPENDENCIES"]);
+    }
+
+    #[test]
+    fn validation_report_checks_request_timeouts() {
+        let args = Args::parse_from(["copilot-server", "--request-timeout", "300"]);
+        let report = args.validation_report();
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].setting, "REQUEST_TIMEOUT");
+
+        let args = Args::parse_from([
+            "copilot-server",
+            "--request-timeout",
+            "300",
+            "--max-request-timeout",
+            "600",
+        ]);
+        assert!(args.validation_report().issues.is_empty());
+    }
+
+    #[test]
+    fn validation_report_checks_fairness_settings() {
+        let args = Args::parse_from(["copilot-server", "--fairness-weights", "free=1,pro=6"]);
+        assert!(args.validation_report().issues.is_empty());
+        assert_eq!(args.fairness_weights().weight("pro"), 6.0);
+        assert!(args.llm_scheduler().is_none());
+
+        let args = Args::parse_from(["copilot-server", "--fairness-weights", "pro"]);
+        let report = args.validation_report();
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].setting, "FAIRNESS_WEIGHTS");
+
+        let args = Args::parse_from([
+            "copilot-server",
+            "--llm-concurrency",
+            "8",
+            "--llm-queue-timeout",
+            "0",
+        ]);
+        let report = args.validation_report();
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].setting, "LLM_QUEUE_TIMEOUT");
+    }
+
+    #[test]
+    fn validation_report_checks_context_acls() {
+        let args = Args::parse_from([
+            "copilot-server",
+            "--context-acls",
+            "hr/=role:admin,group:hr;finance/=group:finance",
+        ]);
+        assert!(args.validation_report().issues.is_empty());
+        let acls = args.context_acls().unwrap();
+        assert_eq!(acls[0].0, "hr/");
+        assert_eq!(acls[0].1.to_string(), "role:admin,group:hr");
+        assert_eq!(acls[1].1.groups, vec!["finance"]);
+
+        let args = Args::parse_from(["copilot-server", "--context-acls", "hr/=team:hr"]);
+        let report = args.validation_report();
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].setting, "CONTEXT_ACLS");
+    }
+
+    #[test]
+    fn validation_report_checks_residency_settings() {
+        let args = Args::parse_from(["copilot-server"]);
+        assert!(args.residency().is_none());
+
+        let args = Args::parse_from([
+            "copilot-server",
+            "--data-region",
+            "us",
+            "--tenant-regions",
+            "acme=eu:strict,globex=eu",
+            "--region-object-stores",
+            "eu=s3://copilot-eu?region=eu-west-1",
+            "--region-database-urls",
+            "eu=postgres://db.eu.internal/copilot",
+            "--object-store-access-key-id",
+            "id",
+            "--object-store-secret-access-key",
+            "secret",
+        ]);
+        assert!(args.validation_report().issues.is_empty());
+        let residency = args.residency().unwrap();
+        assert_eq!(residency.for_tenant("acme").region, "eu");
+        assert_eq!(residency.for_tenant("initech").region, "us");
+
+        let args = Args::parse_from([
+            "copilot-server",
+            "--tenant-regions",
+            "acme=eu:sometimes",
+            "--region-object-stores",
+            "eu=file:///var/objects",
+            "--region-database-urls",
+            "eu=redis://cache",
+        ]);
+        let report = args.validation_report();
+        let settings: Vec<_> = report.issues.iter().map(|issue| issue.setting.as_str()).collect();
+        assert_eq!(
+            settings,
+            vec![
+                "REGION_OBJECT_STORES",
+                "OBJECT_STORE_ACCESS_KEY_ID",
+                "OBJECT_STORE_SECRET_ACCESS_KEY",
+                "REGION_DATABASE_URLS",
+                "TENANT_REGIONS",
+            ]
+        );
+    }
+
+    #[test]
+    fn tenant_prompt_logging_overrides_the_default() {
+        let args = Args::parse_from([
+            "copilot-server",
+            "--prompt-logging",
+            "hashed",
+            "--tenant-prompt-logging",
+            "acme=off",
+        ]);
+        assert!(args.validation_report().issues.is_empty());
+        let policy = args.prompt_log_policy();
+        assert_eq!(policy.for_tenant("acme"), copilot_core::PromptLogging::Off);
+        assert_eq!(policy.for_tenant("globex"), copilot_core::PromptLogging::Hashed);
+
+        let args = Args::parse_from(["copilot-server", "--tenant-prompt-logging", "acme=verbose"]);
+        let settings: Vec<String> = args
+            .validation_report()
+            .issues
+            .into_iter()
+            .map(|issue| issue.setting)
+            .collect();
+        assert_eq!(settings, ["TENANT_PROMPT_LOGGING"]);
+    }
+
+    #[test]
+    fn validation_report_checks_replay_capture() {
+        let args = Args::parse_from(["copilot-server", "--replay-sample-rate", "5"]);
+        assert!(args.validation_report().issues.is_empty());
+
+        let args = Args::parse_from([
+            "copilot-server",
+            "--replay-capture-dir",
+            "/var/lib/copilot/replays",
+            "--replay-sample-rate",
+            "5",
+        ]);
+        let settings: Vec<String> = args
+            .validation_report()
+            .issues
+            .into_iter()
+            .map(|issue| issue.setting)
+            .collect();
+        assert_eq!(settings, ["REPLAY_SAMPLE_RATE"]);
+    }
+
+    #[test]
+    fn validation_report_checks_code_policy() {
+        let dir = std::env::temp_dir().join(format!("copilot-code-policy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let valid = dir.join("valid.json");
+        std::fs::write(&valid, r#"{"tenants": {"acme": {"action": "block", "disallowed_packages": ["left-pad"]}}}"#)
+            .unwrap();
+        let invalid = dir.join("invalid.json");
+        std::fs::write(&invalid, r#"{"tenants": {"acme": {"action": "delete"}}}"#).unwrap();
+
+        let args = Args::parse_from(["copilot-server", "--code-policy", valid.to_str().unwrap()]);
+        assert!(args.validation_report().issues.is_empty());
+        let config = args.code_policy().unwrap().unwrap();
+        assert_eq!(config.for_tenant(Some("acme")).unwrap().disallowed_packages, ["left-pad"]);
+
+        let args = Args::parse_from(["copilot-server", "--code-policy", invalid.to_str().unwrap()]);
+        let settings: Vec<String> = args
+            .validation_report()
+            .issues
+            .into_iter()
+            .map(|issue| issue.setting)
+            .collect();
+        assert_eq!(settings, ["CODE_POLICY"]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn gate_workflows_are_scoped_by_directory() {
+        let dir = std::env::temp_dir().join(format!("copilot-gate-workflows-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("acme")).unwrap();
+        let workflow = |name: &str| format!("id: {name}\nname: {name}\ndescription: Checks\nsteps: []\n");
+        std::fs::write(dir.join("shared.yaml"), workflow("shared-checks")).unwrap();
+        std::fs::write(dir.join("acme").join("release.yaml"), workflow("release-checks")).unwrap();
+
+        let args = Args::parse_from(["copilot-server", "--gate-workflows", dir.to_str().unwrap()]);
+        assert!(args.validation_report().issues.is_empty());
+        let workflows: Vec<(Option<String>, String)> = args
+            .gate_workflows()
+            .unwrap()
+            .into_iter()
+            .map(|(tenant, workflow)| (tenant, workflow.name))
+            .collect();
+        assert_eq!(
+            workflows,
+            [(None, "shared-checks".to_string()), (Some("acme".to_string()), "release-checks".to_string())]
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
@@ -0,0 +1,498 @@
+//! Declarative configuration of the agent pipeline
+//!
+//! One YAML file, named by `PIPELINE_CONFIG`, describes how questions are
+//! answered: retrieval fan-out, reranking and compression, the guardrails
+//! answers pass, and the prompt template, model routing, retrieval sources,
+//! token budget and code policy of each persona and tenant. It replaces the
+//! separate `POST_PROCESSING`, `CODE_POLICY`, `TOKEN_BUDGETS`,
+//! `GROUNDEDNESS_REVIEW_THRESHOLD` and `CONTEXT_FAN_OUT` settings, which may
+//! not be set alongside it.
+//!
+//! ```yaml
+//! retrieval:
+//!   fan_out: 3
+//!   reranker: { provider: cohere, api_key_env: COHERE_API_KEY, max_documents: 50 }
+//!   compression: { ratio: 0.6, min_words: 40 }
+//! guardrails:
+//!   groundedness_review_threshold: 0.5
+//!   post_processing: [{ stage: markdown_lint }, { stage: citations }]
+//! budgets:
+//!   economy_models: [claude-3-haiku]
+//! defaults:
+//!   prompt_template: "{system_prompt}\n\nCite the sources you use."
+//!   models: [claude-3-sonnet]
+//!   retrieval: { max_items: 8 }
+//! personas:
+//!   sre-assistant:
+//!     retrieval: { sources: [runbooks, incidents], min_score: 0.2 }
+//! tenants:
+//!   acme:
+//!     models: [claude-3-haiku]
+//!     daily_tokens: 200000
+//!     code_policy: { action: block, disallowed_packages: [left-pad] }
+//! ```
+//!
+//! A session's profile is the defaults, overlaid with its persona's and then
+//! its tenant's. The file is checked as a whole at startup and by
+//! `--check-config`, and every problem is reported with where it is in the
+//! file, e.g. `tenants.acme.retrieval.min_score: must be between 0 and 1`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use copilot_context::reranking::CohereReranker;
+use copilot_context::{
+    CalibrationParams, FanOutConfig, PromptCompressionConfig, PromptCompressor, Reranker, RerankerConfig,
+};
+use copilot_conversation::{
+    CodePolicy, CodePolicyConfig, GroundednessMonitor, OverlapScorer, PipelineProfile, PipelineProfiles,
+    PostProcessingConfig, PostProcessor, ReferenceSnippet, RetrievalOverrides, RetrievalProfile, StageConfig,
+    TokenBudgetConfig,
+};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The pipeline configuration file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    #[serde(default)]
+    pub retrieval: RetrievalSection,
+    #[serde(default)]
+    pub guardrails: GuardrailsSection,
+    #[serde(default)]
+    pub budgets: BudgetsSection,
+    /// Profile of every session, before its persona's and tenant's
+    #[serde(default)]
+    pub defaults: ProfileSection,
+    #[serde(default)]
+    pub personas: BTreeMap<String, ProfileSection>,
+    #[serde(default)]
+    pub tenants: BTreeMap<String, ProfileSection>,
+}
+
+/// How context is retrieved for every question
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetrievalSection {
+    /// Most sub-queries a multi-part question is split into
+    #[serde(default)]
+    pub fan_out: Option<usize>,
+    #[serde(default)]
+    pub reranker: Option<RerankerSection>,
+    #[serde(default)]
+    pub compression: Option<PromptCompressionConfig>,
+}
+
+/// Reranking services
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RerankerKind {
+    Cohere,
+}
+
+/// The reranker retrieved context passes through
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RerankerSection {
+    pub provider: RerankerKind,
+    /// Environment variable holding the service's API key
+    pub api_key_env: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub max_documents: Option<usize>,
+    #[serde(default)]
+    pub score_threshold: Option<f32>,
+    #[serde(default)]
+    pub latency_budget_ms: Option<u64>,
+    /// Calibration fitted for the model, as saved by `CalibrationParams`
+    #[serde(default)]
+    pub calibration: Option<PathBuf>,
+}
+
+/// Checks every answer passes
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GuardrailsSection {
+    /// Answers scoring below this groundedness (0-1) are queued for review
+    #[serde(default)]
+    pub groundedness_review_threshold: Option<f64>,
+    /// Post-processing stages responses pass through, in order
+    #[serde(default)]
+    pub post_processing: Vec<StageConfig>,
+    /// Code generated code is compared against by tenants' code policies
+    #[serde(default)]
+    pub code_references: Vec<ReferenceSnippet>,
+}
+
+/// Settings shared by every token budget
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BudgetsSection {
+    /// Models to fall back to near a budget, besides the session's own
+    #[serde(default)]
+    pub economy_models: Vec<String>,
+    #[serde(default)]
+    pub compress_above: Option<f64>,
+    #[serde(default)]
+    pub minimal_above: Option<f64>,
+}
+
+/// Retrieved context a profile keeps
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetrievalProfileSection {
+    #[serde(default)]
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub max_items: Option<usize>,
+    #[serde(default)]
+    pub min_score: Option<f64>,
+}
+
+/// Settings of the defaults, a persona or a tenant
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileSection {
+    /// System prompt; `{system_prompt}` is replaced with the persona's own
+    #[serde(default)]
+    pub prompt_template: Option<String>,
+    /// Models to answer with, preferred first
+    #[serde(default)]
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub retrieval: RetrievalProfileSection,
+    /// Daily token budget per conversation
+    #[serde(default)]
+    pub daily_tokens: Option<usize>,
+    /// License and package policy for generated code; not for personas
+    #[serde(default)]
+    pub code_policy: Option<CodePolicy>,
+}
+
+impl ProfileSection {
+    fn profile(&self) -> PipelineProfile {
+        PipelineProfile {
+            prompt_template: self.prompt_template.clone(),
+            models: self.models.clone(),
+            retrieval: RetrievalProfile {
+                sources: self.retrieval.sources.clone(),
+                limits: RetrievalOverrides {
+                    max_items: self.retrieval.max_items,
+                    min_score: self.retrieval.min_score,
+                },
+            },
+        }
+    }
+
+    fn validate(&self, path: &str, errors: &mut Vec<String>) {
+        if self.prompt_template.as_deref().is_some_and(|template| template.trim().is_empty()) {
+            errors.push(format!("{}.prompt_template: must not be empty", path));
+        }
+        if self.models.iter().any(|model| model.trim().is_empty()) {
+            errors.push(format!("{}.models: model names must not be empty", path));
+        }
+        if self.retrieval.sources.iter().any(|source| source.trim().is_empty()) {
+            errors.push(format!("{}.retrieval.sources: sources must not be empty", path));
+        }
+        if self.retrieval.max_items == Some(0) {
+            errors.push(format!("{}.retrieval.max_items: must be at least 1", path));
+        }
+        if self.retrieval.min_score.is_some_and(|score| !(0.0..=1.0).contains(&score)) {
+            errors.push(format!("{}.retrieval.min_score: must be between 0 and 1", path));
+        }
+        if self.daily_tokens == Some(0) {
+            errors.push(format!("{}.daily_tokens: must be at least 1", path));
+        }
+        if let Some(policy) = &self.code_policy {
+            if !(0.0..=1.0).contains(&policy.similarity_threshold) {
+                errors.push(format!("{}.code_policy.similarity_threshold: must be between 0 and 1", path));
+            }
+        }
+    }
+}
+
+impl PipelineConfig {
+    /// Read and check a configuration file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let config: Self = serde_yaml::from_str(&text).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+        let errors = config.validate();
+        if !errors.is_empty() {
+            bail!("{}: {}", path.display(), errors.join("; "));
+        }
+        Ok(config)
+    }
+
+    /// Every problem with the configuration, each prefixed with where it is
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if self.retrieval.fan_out.is_some_and(|max| max < 2) {
+            errors.push("retrieval.fan_out: must be at least 2".to_string());
+        }
+        if let Some(compression) = &self.retrieval.compression {
+            if let Err(e) = compression.validate() {
+                errors.push(format!("retrieval.compression: {}", e));
+            }
+        }
+        if let Some(reranker) = &self.retrieval.reranker {
+            if std::env::var(&reranker.api_key_env).map_or(true, |key| key.is_empty()) {
+                errors.push(format!("retrieval.reranker.api_key_env: {} is not set", reranker.api_key_env));
+            }
+            if reranker.max_documents == Some(0) {
+                errors.push("retrieval.reranker.max_documents: must be at least 1".to_string());
+            }
+            if let Some(path) = &reranker.calibration {
+                if let Err(e) = CalibrationParams::load(path) {
+                    errors.push(format!("retrieval.reranker.calibration: {}", e));
+                }
+            }
+        }
+
+        if let Some(threshold) = self.guardrails.groundedness_review_threshold {
+            if !(0.0..=1.0).contains(&threshold) {
+                errors.push("guardrails.groundedness_review_threshold: must be between 0 and 1".to_string());
+            }
+        }
+        for (i, stage) in self.guardrails.post_processing.iter().enumerate() {
+            let config = PostProcessingConfig {
+                stages: vec![stage.clone()],
+            };
+            if let Err(e) = PostProcessor::from_config(&config) {
+                errors.push(format!("guardrails.post_processing[{}]: {}", i, e));
+            }
+        }
+
+        if let Err(e) = self.budget_config().validate() {
+            errors.push(format!("budgets: {}", e));
+        }
+
+        self.defaults.validate("defaults", &mut errors);
+        for (name, persona) in &self.personas {
+            let path = format!("personas.{}", name);
+            persona.validate(&path, &mut errors);
+            if persona.code_policy.is_some() {
+                errors.push(format!("{}.code_policy: code policies apply per tenant; set it under defaults or tenants", path));
+            }
+        }
+        for (name, tenant) in &self.tenants {
+            tenant.validate(&format!("tenants.{}", name), &mut errors);
+        }
+        errors
+    }
+
+    /// Sub-query fan-out settings, if enabled
+    pub fn fan_out(&self) -> Option<FanOutConfig> {
+        self.retrieval.fan_out.map(|max_sub_queries| FanOutConfig {
+            max_sub_queries,
+            ..FanOutConfig::default()
+        })
+    }
+
+    /// The reranker retrieved context passes through, if one is configured
+    pub fn reranker(&self) -> Result<Option<Arc<dyn Reranker>>> {
+        let Some(section) = &self.retrieval.reranker else {
+            return Ok(None);
+        };
+        let api_key = std::env::var(&section.api_key_env)
+            .with_context(|| format!("retrieval.reranker.api_key_env: {} is not set", section.api_key_env))?;
+
+        let mut config = RerankerConfig::default();
+        if let Some(model) = &section.model {
+            config = config.with_model(model);
+        }
+        if let Some(endpoint) = &section.endpoint {
+            config = config.with_api_endpoint(endpoint);
+        }
+        if let Some(max_documents) = section.max_documents {
+            config = config.with_max_documents(max_documents);
+        }
+        if let Some(threshold) = section.score_threshold {
+            config = config.with_score_threshold(threshold);
+        }
+        if let Some(budget_ms) = section.latency_budget_ms {
+            config = config.with_latency_budget_ms(budget_ms);
+        }
+        if let Some(path) = &section.calibration {
+            config = config.with_calibration(CalibrationParams::load(path)?.calibration);
+        }
+        Ok(Some(match section.provider {
+            RerankerKind::Cohere => Arc::new(CohereReranker::new(api_key, config)),
+        }))
+    }
+
+    /// The compressor retrieved context passes through, if enabled
+    pub fn prompt_compressor(&self) -> Result<Option<Arc<PromptCompressor>>> {
+        self.retrieval
+            .compression
+            .clone()
+            .map(|config| PromptCompressor::new(config).map(Arc::new))
+            .transpose()
+            .map_err(|e| anyhow!("retrieval.compression: {}", e))
+    }
+
+    /// Groundedness monitor scoring answers by their overlap with the
+    /// retrieved context, if a review threshold is set
+    pub fn groundedness(&self) -> Option<Arc<GroundednessMonitor>> {
+        self.guardrails.groundedness_review_threshold.map(|threshold| {
+            Arc::new(GroundednessMonitor::new(Arc::new(OverlapScorer::new())).with_review_threshold(threshold))
+        })
+    }
+
+    /// Response post-processing chain
+    pub fn post_processor(&self) -> copilot_conversation::Result<PostProcessor> {
+        PostProcessor::from_config(&PostProcessingConfig {
+            stages: self.guardrails.post_processing.clone(),
+        })
+    }
+
+    /// Code policies of the defaults and tenants, if any are set
+    pub fn code_policy(&self) -> Option<CodePolicyConfig> {
+        let tenants: HashMap<String, CodePolicy> = self
+            .tenants
+            .iter()
+            .filter_map(|(name, tenant)| Some((name.clone(), tenant.code_policy.clone()?)))
+            .collect();
+        if self.defaults.code_policy.is_none() && tenants.is_empty() {
+            return None;
+        }
+        Some(CodePolicyConfig {
+            default: self.defaults.code_policy.clone(),
+            tenants,
+            references: self.guardrails.code_references.clone(),
+        })
+    }
+
+    fn budget_config(&self) -> TokenBudgetConfig {
+        let daily_tokens = |sections: &BTreeMap<String, ProfileSection>| {
+            sections
+                .iter()
+                .filter_map(|(name, section)| Some((name.clone(), section.daily_tokens?)))
+                .collect()
+        };
+        let defaults = TokenBudgetConfig::default();
+        TokenBudgetConfig {
+            default_daily_tokens: self.defaults.daily_tokens,
+            tenants: daily_tokens(&self.tenants),
+            personas: daily_tokens(&self.personas),
+            economy_models: self.budgets.economy_models.clone(),
+            compress_above: self.budgets.compress_above.unwrap_or(defaults.compress_above),
+            minimal_above: self.budgets.minimal_above.unwrap_or(defaults.minimal_above),
+        }
+    }
+
+    /// Daily token budgets of the defaults, personas and tenants, if any are
+    /// set
+    pub fn token_budgets(&self) -> Option<TokenBudgetConfig> {
+        let config = self.budget_config();
+        let limited = config.default_daily_tokens.is_some() || !config.tenants.is_empty() || !config.personas.is_empty();
+        limited.then_some(config)
+    }
+
+    /// Prompt, model and retrieval profiles of the defaults, personas and
+    /// tenants
+    pub fn profiles(&self) -> PipelineProfiles {
+        PipelineProfiles {
+            defaults: self.defaults.profile(),
+            personas: self.personas.iter().map(|(name, persona)| (name.clone(), persona.profile())).collect(),
+            tenants: self.tenants.iter().map(|(name, tenant)| (name.clone(), tenant.profile())).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+retrieval:
+  fan_out: 3
+  compression: { ratio: 0.6, min_words: 40 }
+guardrails:
+  groundedness_review_threshold: 0.5
+  post_processing: [{ stage: markdown_lint }]
+budgets:
+  economy_models: [claude-3-haiku]
+defaults:
+  prompt_template: "{system_prompt}\n\nCite the sources you use."
+  models: [claude-3-sonnet]
+  retrieval: { max_items: 8 }
+personas:
+  sre-assistant:
+    retrieval: { sources: [runbooks, incidents], min_score: 0.2 }
+    daily_tokens: 100000
+tenants:
+  acme:
+    models: [claude-3-haiku]
+    daily_tokens: 200000
+    code_policy: { action: block, disallowed_packages: [left-pad] }
+"#;
+
+    #[test]
+    fn test_config_maps_onto_module_settings() {
+        let config: PipelineConfig = serde_yaml::from_str(CONFIG).unwrap();
+        assert!(config.validate().is_empty(), "{:?}", config.validate());
+
+        assert_eq!(config.fan_out().unwrap().max_sub_queries, 3);
+        assert!(config.prompt_compressor().unwrap().is_some());
+        assert!(config.reranker().unwrap().is_none());
+        assert!(config.groundedness().is_some());
+        assert_eq!(config.post_processor().unwrap().stage_names().len(), 1);
+
+        let budgets = config.token_budgets().unwrap();
+        assert_eq!(budgets.daily_tokens(Some("acme"), None), Some(200_000));
+        assert_eq!(budgets.daily_tokens(None, Some("sre-assistant")), Some(100_000));
+        assert_eq!(budgets.economy_models, vec!["claude-3-haiku".to_string()]);
+        let code_policy = config.code_policy().unwrap();
+        assert!(code_policy.for_tenant(Some("acme")).is_some());
+        assert!(code_policy.for_tenant(Some("globex")).is_none());
+
+        let profile = config.profiles().resolve(Some("acme"), Some("sre-assistant"));
+        assert_eq!(profile.models, vec!["claude-3-haiku".to_string()]);
+        assert_eq!(profile.retrieval.sources, vec!["runbooks".to_string(), "incidents".to_string()]);
+        assert_eq!(profile.retrieval.limits.max_items, Some(8));
+        assert_eq!(profile.retrieval.limits.min_score, Some(0.2));
+    }
+
+    #[test]
+    fn test_problems_are_reported_with_their_paths() {
+        let config: PipelineConfig = serde_yaml::from_str(
+            r#"
+retrieval:
+  fan_out: 0
+  reranker: { provider: cohere, api_key_env: PIPELINE_TEST_UNSET_KEY }
+guardrails:
+  groundedness_review_threshold: 1.5
+budgets:
+  compress_above: 0.9
+  minimal_above: 0.5
+personas:
+  analyst:
+    code_policy: { action: block }
+tenants:
+  acme:
+    retrieval: { max_items: 0, min_score: 2 }
+"#,
+        )
+        .unwrap();
+        let errors = config.validate();
+        for path in [
+            "retrieval.fan_out",
+            "retrieval.reranker.api_key_env",
+            "guardrails.groundedness_review_threshold",
+            "budgets",
+            "personas.analyst.code_policy",
+            "tenants.acme.retrieval.max_items",
+            "tenants.acme.retrieval.min_score",
+        ] {
+            assert!(errors.iter().any(|e| e.starts_with(path)), "no error for {} in {:?}", path, errors);
+        }
+
+        let unknown = serde_yaml::from_str::<PipelineConfig>("tenants:\n  acme:\n    model: claude-3-haiku\n");
+        assert!(unknown.unwrap_err().to_string().contains("unknown field `model`"));
+    }
+}
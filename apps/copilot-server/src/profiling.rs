@@ -0,0 +1,564 @@
+//! CPU profiling endpoints (`profiling` feature)
+//!
+//! [`ProfilingLayer`] times every tracing span while a capture is running
+//! and attributes the time a span spends entered, minus its entered
+//! children, to its stack of span names. Since instrumented futures are
+//! entered for the duration of each poll, this is the CPU time of the
+//! worker threads broken down by request, handler and engine call.
+//!
+//! - `GET /debug/pprof/profile?seconds=30` returns a gzipped pprof profile,
+//!   readable by `go tool pprof` and accepted by Pyroscope's ingest API
+//! - `GET /debug/pprof/flamegraph?seconds=30` returns collapsed stacks for
+//!   `flamegraph.pl`, `inferno-flamegraph`, speedscope or Pyroscope
+//!   (`format=folded`)
+//! - `GET /debug/runtime` returns Tokio worker, task and queue metrics
+//!
+//! Only spans passing the log filter are recorded, so run with
+//! `RUST_LOG=debug` (or a narrower directive) for finer-grained stacks.
+//! Every endpoint requires a bearer token with the `admin` scope.
+
+use axum::{
+    extract::Query,
+    http::header,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use copilot_api::ApiError;
+use flate2::{write::GzEncoder, Compression};
+use prost::Message;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{span, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::admin::{require_admin, AdminAuth};
+
+const DEFAULT_SECONDS: u64 = 30;
+const MAX_SECONDS: u64 = 300;
+
+/// A stack of spans, root first, as `(target, name)`
+type Stack = Vec<(&'static str, &'static str)>;
+
+/// Collects span timings while a capture is running
+#[derive(Default)]
+pub struct SpanProfiler {
+    capturing: AtomicBool,
+    stacks: Mutex<HashMap<Stack, u64>>,
+}
+
+impl SpanProfiler {
+    /// The profiler fed by [`ProfilingLayer`]
+    pub fn global() -> &'static SpanProfiler {
+        static PROFILER: OnceLock<SpanProfiler> = OnceLock::new();
+        PROFILER.get_or_init(SpanProfiler::default)
+    }
+
+    fn is_capturing(&self) -> bool {
+        self.capturing.load(Ordering::Relaxed)
+    }
+
+    fn stacks(&self) -> MutexGuard<'_, HashMap<Stack, u64>> {
+        self.stacks.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn record(&self, stack: Stack, nanos: u64) {
+        if nanos > 0 {
+            *self.stacks().entry(stack).or_default() += nanos;
+        }
+    }
+
+    /// Record span timings for `duration`
+    ///
+    /// Returns `None` when another capture is already running.
+    pub async fn capture(&self, duration: Duration) -> Option<Profile> {
+        if self
+            .capturing
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+            .is_err()
+        {
+            return None;
+        }
+        let started = SystemTime::now();
+        self.stacks().clear();
+
+        // Stop on cancellation too, so a dropped request frees the profiler
+        struct Stop<'a>(&'a AtomicBool);
+        impl Drop for Stop<'_> {
+            fn drop(&mut self) {
+                self.0.store(false, Ordering::Release);
+            }
+        }
+        let stop = Stop(&self.capturing);
+        tokio::time::sleep(duration).await;
+        drop(stop);
+
+        let mut stacks: Vec<_> = std::mem::take(&mut *self.stacks()).into_iter().collect();
+        stacks.sort();
+        Some(Profile {
+            started,
+            duration,
+            stacks,
+        })
+    }
+}
+
+/// Span timings collected during one capture
+#[derive(Debug, Clone)]
+pub struct Profile {
+    started: SystemTime,
+    duration: Duration,
+    /// Self time in nanoseconds per stack
+    stacks: Vec<(Stack, u64)>,
+}
+
+impl Profile {
+    /// Collapsed stacks (`root;child;leaf <microseconds>` per line)
+    pub fn folded(&self) -> String {
+        let mut out = String::new();
+        for (stack, nanos) in &self.stacks {
+            let micros = nanos / 1_000;
+            if micros == 0 {
+                continue;
+            }
+            let frames: Vec<_> = stack.iter().map(|frame| frame_name(*frame)).collect();
+            let _ = writeln!(out, "{} {}", frames.join(";"), micros);
+        }
+        out
+    }
+
+    /// Gzipped pprof protobuf with a single `cpu`/`nanoseconds` sample type
+    pub fn pprof(&self) -> std::io::Result<Vec<u8>> {
+        let mut builder = PprofBuilder::default();
+        let cpu = builder.value_type("cpu", "nanoseconds");
+        let samples = self
+            .stacks
+            .iter()
+            .map(|(stack, nanos)| pprof::Sample {
+                // pprof lists locations leaf first
+                location_id: stack.iter().rev().map(|frame| builder.location(*frame)).collect(),
+                value: vec![*nanos as i64],
+            })
+            .collect();
+
+        let profile = pprof::Profile {
+            sample_type: vec![cpu.clone()],
+            sample: samples,
+            location: builder.locations,
+            function: builder.functions,
+            string_table: builder.strings,
+            time_nanos: self
+                .started
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_nanos() as i64),
+            duration_nanos: self.duration.as_nanos() as i64,
+            period_type: Some(cpu),
+            period: 1,
+        };
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&profile.encode_to_vec())?;
+        encoder.finish()
+    }
+}
+
+fn frame_name((target, name): (&str, &str)) -> String {
+    format!("{}::{}", target, name)
+}
+
+/// Interns strings, functions and locations for a pprof profile
+struct PprofBuilder {
+    strings: Vec<String>,
+    string_ids: HashMap<String, i64>,
+    functions: Vec<pprof::Function>,
+    locations: Vec<pprof::Location>,
+    location_ids: HashMap<(&'static str, &'static str), u64>,
+}
+
+impl Default for PprofBuilder {
+    fn default() -> Self {
+        // The string table must start with the empty string
+        Self {
+            strings: vec![String::new()],
+            string_ids: HashMap::from([(String::new(), 0)]),
+            functions: Vec::new(),
+            locations: Vec::new(),
+            location_ids: HashMap::new(),
+        }
+    }
+}
+
+impl PprofBuilder {
+    fn string(&mut self, value: &str) -> i64 {
+        if let Some(id) = self.string_ids.get(value) {
+            return *id;
+        }
+        let id = self.strings.len() as i64;
+        self.strings.push(value.to_string());
+        self.string_ids.insert(value.to_string(), id);
+        id
+    }
+
+    fn value_type(&mut self, r#type: &str, unit: &str) -> pprof::ValueType {
+        pprof::ValueType {
+            r#type: self.string(r#type),
+            unit: self.string(unit),
+        }
+    }
+
+    /// Location of a frame; each frame is its own function
+    fn location(&mut self, frame: (&'static str, &'static str)) -> u64 {
+        if let Some(id) = self.location_ids.get(&frame) {
+            return *id;
+        }
+        let id = self.locations.len() as u64 + 1;
+        let name = self.string(&frame_name(frame));
+        let filename = self.string(frame.0);
+        self.functions.push(pprof::Function {
+            id,
+            name,
+            system_name: name,
+            filename,
+        });
+        self.locations.push(pprof::Location {
+            id,
+            line: vec![pprof::Line {
+                function_id: id,
+                line: 0,
+            }],
+        });
+        self.location_ids.insert(frame, id);
+        id
+    }
+}
+
+/// The subset of pprof's `profile.proto` the profiler writes
+mod pprof {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Profile {
+        #[prost(message, repeated, tag = "1")]
+        pub sample_type: Vec<ValueType>,
+        #[prost(message, repeated, tag = "2")]
+        pub sample: Vec<Sample>,
+        #[prost(message, repeated, tag = "4")]
+        pub location: Vec<Location>,
+        #[prost(message, repeated, tag = "5")]
+        pub function: Vec<Function>,
+        #[prost(string, repeated, tag = "6")]
+        pub string_table: Vec<String>,
+        #[prost(int64, tag = "9")]
+        pub time_nanos: i64,
+        #[prost(int64, tag = "10")]
+        pub duration_nanos: i64,
+        #[prost(message, optional, tag = "11")]
+        pub period_type: Option<ValueType>,
+        #[prost(int64, tag = "12")]
+        pub period: i64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ValueType {
+        #[prost(int64, tag = "1")]
+        pub r#type: i64,
+        #[prost(int64, tag = "2")]
+        pub unit: i64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Sample {
+        #[prost(uint64, repeated, tag = "1")]
+        pub location_id: Vec<u64>,
+        #[prost(int64, repeated, tag = "2")]
+        pub value: Vec<i64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Location {
+        #[prost(uint64, tag = "1")]
+        pub id: u64,
+        #[prost(message, repeated, tag = "4")]
+        pub line: Vec<Line>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Line {
+        #[prost(uint64, tag = "1")]
+        pub function_id: u64,
+        #[prost(int64, tag = "2")]
+        pub line: i64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Function {
+        #[prost(uint64, tag = "1")]
+        pub id: u64,
+        #[prost(int64, tag = "2")]
+        pub name: i64,
+        #[prost(int64, tag = "3")]
+        pub system_name: i64,
+        #[prost(int64, tag = "4")]
+        pub filename: i64,
+    }
+}
+
+/// Time a span has been entered for, stored in its extensions
+#[derive(Default)]
+struct Busy {
+    entered_at: Option<Instant>,
+    children: Duration,
+}
+
+/// Tracing layer feeding [`SpanProfiler::global`]
+pub struct ProfilingLayer {
+    profiler: &'static SpanProfiler,
+}
+
+impl ProfilingLayer {
+    pub fn global() -> Self {
+        Self {
+            profiler: SpanProfiler::global(),
+        }
+    }
+}
+
+impl<S> Layer<S> for ProfilingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if !self.profiler.is_capturing() {
+            return;
+        }
+        let Some(span) = ctx.span(id) else { return };
+        let mut extensions = span.extensions_mut();
+        let busy = Busy {
+            entered_at: Some(Instant::now()),
+            children: Duration::ZERO,
+        };
+        match extensions.get_mut::<Busy>() {
+            Some(existing) => *existing = busy,
+            None => extensions.insert(busy),
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let (elapsed, children) = {
+            let mut extensions = span.extensions_mut();
+            let Some(busy) = extensions.get_mut::<Busy>() else { return };
+            let Some(entered_at) = busy.entered_at.take() else { return };
+            (entered_at.elapsed(), std::mem::take(&mut busy.children))
+        };
+
+        // Charge the time to the parent's children if the parent is entered,
+        // so it only counts its own (self) time
+        if let Some(parent) = span.parent() {
+            if let Some(busy) = parent.extensions_mut().get_mut::<Busy>() {
+                if busy.entered_at.is_some() {
+                    busy.children += elapsed;
+                }
+            }
+        }
+
+        if self.profiler.is_capturing() {
+            let stack = span
+                .scope()
+                .from_root()
+                .map(|span| (span.metadata().target(), span.metadata().name()))
+                .collect();
+            self.profiler
+                .record(stack, elapsed.saturating_sub(children).as_nanos() as u64);
+        }
+    }
+}
+
+/// Capture length (`?seconds=`)
+#[derive(Debug, Deserialize)]
+pub struct CaptureQuery {
+    pub seconds: Option<u64>,
+}
+
+impl CaptureQuery {
+    fn duration(&self) -> Result<Duration, ApiError> {
+        match self.seconds.unwrap_or(DEFAULT_SECONDS) {
+            seconds @ 1..=MAX_SECONDS => Ok(Duration::from_secs(seconds)),
+            _ => Err(ApiError::InvalidInput(format!(
+                "seconds must be between 1 and {}",
+                MAX_SECONDS
+            ))),
+        }
+    }
+}
+
+/// Profiling and runtime endpoints, nested under `/debug`
+pub fn router(auth: AdminAuth) -> Router {
+    Router::new()
+        .route("/pprof/profile", get(profile))
+        .route("/pprof/flamegraph", get(flamegraph))
+        .route("/runtime", get(runtime))
+        .layer(middleware::from_fn_with_state(Arc::new(auth), require_admin))
+}
+
+async fn capture(query: &CaptureQuery) -> Result<Profile, ApiError> {
+    let duration = query.duration()?;
+    tracing::info!(seconds = duration.as_secs(), "Capturing CPU profile");
+    SpanProfiler::global()
+        .capture(duration)
+        .await
+        .ok_or_else(|| ApiError::ServiceUnavailable("A profile is already being captured".into()))
+}
+
+async fn profile(Query(query): Query<CaptureQuery>) -> Result<Response, ApiError> {
+    let body = capture(&query)
+        .await?
+        .pprof()
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"profile.pb.gz\""),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+async fn flamegraph(Query(query): Query<CaptureQuery>) -> Result<Response, ApiError> {
+    let profile = capture(&query).await?;
+    Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], profile.folded()).into_response())
+}
+
+/// Tokio runtime metrics: workers, alive tasks and queue depth
+async fn runtime() -> Json<serde_json::Value> {
+    let metrics = tokio::runtime::Handle::current().metrics();
+    let workers: Vec<_> = (0..metrics.num_workers())
+        .map(|worker| {
+            json!({
+                "worker": worker,
+                "busy_ms": metrics.worker_total_busy_duration(worker).as_millis() as u64,
+                "park_count": metrics.worker_park_count(worker),
+            })
+        })
+        .collect();
+    Json(json!({
+        "num_workers": metrics.num_workers(),
+        "num_alive_tasks": metrics.num_alive_tasks(),
+        "global_queue_depth": metrics.global_queue_depth(),
+        "workers": workers,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use tower::ServiceExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn frames(stack: &[&'static str]) -> Stack {
+        stack.iter().map(|name| ("copilot", *name)).collect()
+    }
+
+    #[test]
+    fn test_folded_and_pprof_output() {
+        let profile = Profile {
+            started: SystemTime::now(),
+            duration: Duration::from_secs(1),
+            stacks: vec![
+                (frames(&["request"]), 2_000_000),
+                (frames(&["request", "retrieve"]), 5_000_000),
+                (frames(&["request", "tiny"]), 10),
+            ],
+        };
+        assert_eq!(
+            profile.folded(),
+            "copilot::request 2000\ncopilot::request;copilot::retrieve 5000\n"
+        );
+
+        let gzipped = profile.pprof().unwrap();
+        let mut decoded = Vec::new();
+        std::io::Read::read_to_end(&mut flate2::read::GzDecoder::new(&gzipped[..]), &mut decoded).unwrap();
+        let pprof = pprof::Profile::decode(&decoded[..]).unwrap();
+        assert_eq!(pprof.string_table[0], "");
+        assert_eq!(pprof.sample.len(), 3);
+        assert_eq!(pprof.location.len(), 3);
+        // Leaf first: retrieve, then request
+        assert_eq!(pprof.sample[1].location_id, vec![2, 1]);
+        assert_eq!(pprof.sample[1].value, vec![5_000_000]);
+        let name = pprof.function[1].name as usize;
+        assert_eq!(pprof.string_table[name], "copilot::retrieve");
+    }
+
+    #[tokio::test]
+    async fn test_capture_records_span_self_time() {
+        let profiler: &'static SpanProfiler = Box::leak(Box::default());
+        let subscriber = tracing_subscriber::registry().with(ProfilingLayer { profiler });
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        let capture = profiler.capture(Duration::from_millis(50));
+        let work = async {
+            tokio::task::yield_now().await;
+            let outer = tracing::info_span!("outer");
+            let _outer = outer.enter();
+            std::thread::sleep(Duration::from_millis(5));
+            let inner = tracing::info_span!("inner");
+            let _inner = inner.enter();
+            std::thread::sleep(Duration::from_millis(5));
+        };
+        let (profile, ()) = tokio::join!(capture, work);
+        let profile = profile.unwrap();
+
+        let self_time = |name: &str| {
+            profile
+                .stacks
+                .iter()
+                .find(|(stack, _)| stack.last().map(|frame| frame.1) == Some(name))
+                .map(|(_, nanos)| Duration::from_nanos(*nanos))
+                .unwrap()
+        };
+        assert!(self_time("inner") >= Duration::from_millis(5));
+        // The inner span's time is not counted again for the outer span
+        assert!(self_time("outer") >= Duration::from_millis(5));
+        assert!(self_time("outer") < Duration::from_millis(10));
+        assert!(!profiler.is_capturing());
+    }
+
+    #[tokio::test]
+    async fn test_endpoints_require_admin_scope() {
+        let token = |scope: &str| {
+            let claims = json!({ "sub": "ops", "exp": 4_000_000_000u64, "iat": 0, "scope": scope });
+            encode(&Header::default(), &claims, &EncodingKey::from_secret(b"secret")).unwrap()
+        };
+        let get = |token: Option<String>| {
+            let mut req = axum::http::Request::get("/runtime");
+            if let Some(token) = token {
+                req = req.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            router(AdminAuth::new("secret")).oneshot(req.body(Body::empty()).unwrap())
+        };
+
+        assert_eq!(get(None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(get(Some(token("read"))).await.unwrap().status(), StatusCode::FORBIDDEN);
+        assert_eq!(get(Some(token("read admin"))).await.unwrap().status(), StatusCode::OK);
+
+        let bad = router(AdminAuth::new("secret"))
+            .oneshot(
+                axum::http::Request::get("/pprof/flamegraph?seconds=0")
+                    .header(header::AUTHORIZATION, format!("Bearer {}", token("admin")))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(bad.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -5,21 +5,60 @@ use axum::{
     Router,
     routing::get,
     response::Json,
-    http::StatusCode,
+    http::{header, StatusCode},
 };
 use serde_json::json;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tower_http::{
     trace::TraceLayer,
     cors::CorsLayer,
 };
-use tracing::info;
+use tracing::{info, warn};
 
+use copilot_adapters::ObservatoryClient;
 use copilot_api::create_router;
+use copilot_api::{
+    ApiLimits, BenchmarkOutcome, BenchmarkSuite, ContextAccessAudit, EmailAccountMailer, GateService,
+    ManualRunService, ObjectArchiveStore, ReplayRecorder, RequestTimeouts, RoutePolicy,
+};
+use copilot_api::event_webhooks::forward_workflow_events;
 use copilot_api::AppState as ApiAppState;
+use copilot_benchmarks::{run_all_benchmarks_with_config, BenchmarkConfig};
+use copilot_context::ContextEngine;
+use copilot_conversation::{ConversationError, PersonaRegistry, Result as ConversationResult, SandboxRunner};
+use copilot_core::SandboxPolicy;
+use copilot_e2b::{E2BAgent, E2BConfig, E2BError};
+use copilot_infra::{
+    check_for_url, create_lazy_pool, Criticality, DependencyMonitor, LocalObjectStore, ObjectStorage, ObjectStore,
+    PgDeliveryQueue, PgPoolConfig, S3Config, S3ObjectStore,
+};
+use copilot_ingestion::TrustedSigners;
+use copilot_security::{KeyRotationConfig, SigningAlgorithm, SigningKeys};
+use copilot_slack::{SlackApp, SlackClient};
+use copilot_workflow::execution::DefaultStepExecutor;
+use copilot_workflow::templates::InMemoryTemplateRepository;
+use copilot_workflow::{ApprovalGate, PersonaLookup, RolePersona, TemplateLibrary, WorkflowEngine};
+use copilot_webhook::{
+    create_webhook_router, DeliveryQueue, DeliveryWorker, InboundWebhookProcessor, InboundWebhookState,
+    NotificationSubscriptions, RetryConfig, SmtpConfig, SmtpNotifier, TaskNotifier, WebhookDispatcher,
+    WebhookEndpoint, WebhookEventType, WebhookHandlerRegistry, TASK_EVENT_TYPES,
+};
 
+use crate::admin::AdminAuth;
+use crate::admission::{self, AdmissionConfig, AdmissionController};
 use crate::app::AppState;
-use crate::cli::Args;
+use crate::cli::{region_entries, Args, DEPENDENCIES};
+use crate::compression::{self, BodyMetrics};
+
+/// What serves in place of each of [`DEPENDENCIES`] while it is down
+const DEPENDENCY_FALLBACKS: [&str; 4] = [
+    "state kept in memory; webhooks delivered inline",
+    "no shared semantic cache; caches are per instance",
+    "events delivered in process only",
+    "context served from the in-process index",
+];
 
 pub struct Server {
     args: Args,
@@ -34,8 +73,21 @@ impl Server {
     pub async fn run(self) -> Result<()> {
         let addr = SocketAddr::from(([0, 0, 0, 0], self.args.port));
 
+        // Probe the backing services before taking traffic; the server
+        // starts even when some are down and picks them up as they return
+        let dependencies = self.build_dependency_monitor();
+        if !dependencies.is_empty() {
+            let report = dependencies.probe_all().await;
+            info!("Dependencies probed, server is {}", report.status());
+            dependencies
+                .clone()
+                .spawn(Duration::from_secs(self.args.dependency_probe_interval.max(1)));
+        }
+
+        self.spawn_session_cleanup();
+
         // Build HTTP router
-        let app = self.build_http_router();
+        let app = self.build_http_router(dependencies);
 
         info!("HTTP server listening on {}", addr);
 
@@ -43,32 +95,628 @@ impl Server {
             .await
             .context("Failed to bind HTTP server")?;
 
-        axum::serve(listener, app.into_make_service())
+        // Peer addresses are needed for inbound webhook IP allowlists
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
             .await
             .context("HTTP server error")?;
 
         Ok(())
     }
 
-    fn build_http_router(&self) -> Router {
+    fn build_http_router(&self, dependencies: Arc<DependencyMonitor>) -> Router {
+        let signing_keys = self.build_signing_keys();
+
         // Create API app state
         let api_state = ApiAppState::new(
             self.state.engine.clone(),
             self.state.conversation_manager.clone(),
             self.state.jwt_secret.clone(),
+        )
+        .with_prompt_logging(self.args.prompt_log_policy())
+        .with_request_timeouts(RequestTimeouts {
+            default: Duration::from_secs(self.args.request_timeout),
+            max: Duration::from_secs(self.args.max_request_timeout),
+        })
+        .with_limits(ApiLimits::default().with_fairness_weights(self.args.fairness_weights()));
+        let api_state = match (self.args.residency(), &self.state.residency_log) {
+            (Some(residency), Some(log)) => {
+                info!(
+                    "Data residency enabled: default region {}, {} tenants pinned",
+                    residency.default_region,
+                    residency.tenants.len()
+                );
+                api_state.with_residency(residency).with_residency_log(log.clone())
+            }
+            _ => api_state,
+        };
+        let api_state = match &self.state.code_policy_log {
+            Some(log) => api_state.with_code_policy_log(log.clone()),
+            None => api_state,
+        };
+        let api_state = match &signing_keys {
+            Some(keys) => api_state.with_signing_keys(keys.clone()),
+            None => api_state,
+        };
+        let api_state = match self.build_route_policy() {
+            Some(policy) => api_state.with_route_policy(policy),
+            None => api_state,
+        };
+        let api_state = match &self.args.replay_capture_dir {
+            Some(dir) => {
+                info!(
+                    "Capturing {}% of chat turns for replay in {}",
+                    self.args.replay_sample_rate * 100.0,
+                    dir.display()
+                );
+                api_state.with_replays(Arc::new(
+                    ReplayRecorder::new(dir, self.args.replay_sample_rate)
+                        .with_max_captures(self.args.replay_max_captures),
+                ))
+            }
+            None => api_state,
+        };
+        let webhooks = self.build_event_webhooks();
+        let api_state = api_state
+            .with_notifier(self.build_notifier(webhooks.clone()))
+            .with_webhooks(webhooks.clone());
+        let api_state = match self.build_account_mailer() {
+            Some(mailer) => api_state.with_account_mailer(mailer),
+            None => api_state,
+        };
+        let api_state = match TrustedSigners::from_entries(&self.args.trusted_signers) {
+            Ok(signers) if !signers.is_empty() => {
+                info!("Signed ingestion enabled for {} trusted sources", signers.signers().len());
+                api_state.with_trusted_signers(signers)
+            }
+            Ok(_) => api_state,
+            Err(e) => {
+                warn!("Ignoring TRUSTED_SIGNERS: {}", e);
+                api_state
+            }
+        };
+        let api_state = match self.attach_object_storage(api_state.clone()) {
+            Ok(with_storage) => with_storage,
+            Err(e) => {
+                warn!("Object storage disabled: {}", e);
+                api_state
+            }
+        };
+        let api_state = match self.build_handoff_webhooks() {
+            Some(dispatcher) => api_state.with_handoff_webhooks(dispatcher),
+            None => api_state,
+        };
+        let observatory = ObservatoryClient::with_prometheus(&self.args.prometheus_url)
+            .with_loki(&self.args.loki_url);
+        let observatory = match (&self.args.grafana_url, &self.args.grafana_token) {
+            (Some(url), Some(token)) => {
+                info!("Generated dashboards can be pushed to Grafana at {}", url);
+                observatory.with_grafana(url, token)
+            }
+            _ => observatory,
+        };
+        let observatory = Arc::new(observatory);
+
+        // Manual runs of the built-in templates, such as "explain this spike",
+        // read the same observatory and the conversations' context engine
+        let approval_gate = Arc::new(ApprovalGate::new());
+        let executor = DefaultStepExecutor::new()
+            .with_approval_gate(approval_gate.clone())
+            .with_observatory(observatory.clone())
+            .with_context_engine(self.state.conversation_manager.context_engine())
+            .with_personas(Arc::new(ConversationPersonas(
+                self.state.conversation_manager.persona_registry(),
+            )));
+        let engine = WorkflowEngine::with_executor(Arc::new(executor)).with_approval_gate(approval_gate);
+        forward_workflow_events(&engine, webhooks);
+        let gates = self.build_gates(engine.clone());
+        let runs = ManualRunService::new(
+            engine,
+            TemplateLibrary::new(Arc::new(InMemoryTemplateRepository::with_builtins())),
         );
+        let api_state = api_state
+            .with_observatory(observatory)
+            .with_runs(Arc::new(runs))
+            .with_gates(gates)
+            .with_dependencies(dependencies.clone());
+        self.state
+            .access_fence
+            .set_auditor(Arc::new(ContextAccessAudit::new(api_state.audit.clone())));
+        if let Some(api_key) = &self.args.e2b_api_key {
+            info!("Running conversations' code in E2B sandboxes");
+            self.state
+                .conversation_manager
+                .set_sandbox_runner(Arc::new(E2BSandboxes(E2BConfig::with_api_key(api_key))));
+        }
+
+        let query_analytics = api_state.query_analytics.clone();
+        let conversation_analytics = api_state.conversation_analytics.clone();
+        let context_pipeline = api_state.context_pipeline.clone();
 
         // Create API router from copilot-api crate
         let api_router = create_router(api_state);
 
+        let body_metrics = Arc::new(BodyMetrics::new());
+        let admission = Arc::new(AdmissionController::new(AdmissionConfig::with_max_in_flight(
+            self.args.max_concurrent_requests,
+        )));
+        let metrics = body_metrics.clone();
+        let admission_metrics = admission.clone();
+        let conversations = self.state.conversation_manager.clone();
+        let context_archive = self.state.context_archive.clone();
+        let inbound_webhooks = self.build_inbound_webhooks();
+        let inbound_metrics = inbound_webhooks.clone();
+        let jwks = signing_keys.clone();
+
         // Combine routes
-        Router::new()
+        let router = Router::new()
             .route("/", get(root))
             .route("/health", get(health_check))
-            .nest("/api", api_router)
+            .route(
+                "/.well-known/jwks.json",
+                get(move || async move {
+                    let keys = jwks.map(|keys| keys.jwks().keys).unwrap_or_default();
+                    ([(header::CACHE_CONTROL, "public, max-age=300")], Json(json!({ "keys": keys })))
+                }),
+            )
+            .route(
+                "/readyz",
+                get(move || async move {
+                    let report = dependencies.report();
+                    let status = if report.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+                    (status, Json(report))
+                }),
+            )
+            .route(
+                "/metrics",
+                get(move || async move {
+                    metrics.render_prometheus("copilot")
+                        + &admission_metrics.render_prometheus("copilot")
+                        + &conversations.prefetch_stats().render_prometheus("copilot")
+                        + &conversations.stream_stats().render_prometheus("copilot")
+                        + &conversations.freshness_stats().render_prometheus("copilot")
+                        + &conversations.context_fit_stats().render_prometheus("copilot")
+                        + &query_analytics.stats().render_prometheus("copilot")
+                        + &conversation_analytics.stats().render_prometheus("copilot")
+                        + &context_pipeline.stats().render_prometheus("copilot")
+                        + &context_archive
+                            .map(|archive| archive.stats().render_prometheus("copilot"))
+                            .unwrap_or_default()
+                        + &conversations
+                            .groundedness()
+                            .map(|monitor| monitor.stats().render_prometheus("copilot"))
+                            .unwrap_or_default()
+                        + &conversations
+                            .model_chain()
+                            .map(|chain| chain.stats().render_prometheus("copilot"))
+                            .unwrap_or_default()
+                        + &conversations
+                            .llm_scheduler()
+                            .map(|scheduler| scheduler.render_prometheus("copilot"))
+                            .unwrap_or_default()
+                        + &inbound_metrics
+                            .map(|inbound| inbound.render_prometheus("copilot"))
+                            .unwrap_or_default()
+                }),
+            )
+            .nest("/api", api_router);
+
+        // Operator endpoints
+        let admin_auth = AdminAuth {
+            signing_keys,
+            ..AdminAuth::new(self.state.jwt_secret.clone())
+        };
+        let mut debug: Option<Router> = None;
+        #[cfg(feature = "profiling")]
+        {
+            info!("Profiling endpoints enabled at /debug (admin scope required)");
+            debug = Some(crate::profiling::router(admin_auth.clone()));
+        }
+        if self.args.fault_injection.is_some() {
+            info!("Fault injection endpoints enabled at /debug/faults (admin scope required)");
+            let faults = crate::faults::router(admin_auth.clone());
+            debug = Some(match debug {
+                Some(debug) => debug.merge(faults),
+                None => faults,
+            });
+        }
+        let router = match debug {
+            Some(debug) => router.nest("/debug", debug),
+            None => router,
+        };
+        let router = match (&self.args.slack_bot_token, &self.args.slack_signing_secret) {
+            (Some(token), Some(secret)) => {
+                let slack = SlackApp::new(
+                    Arc::new(SlackClient::new(token)),
+                    self.state.conversation_manager.clone(),
+                    secret,
+                );
+                info!("Slack app enabled at /slack");
+                router.nest("/slack", copilot_slack::router(Arc::new(slack)))
+            }
+            _ => router,
+        };
+        let router = match inbound_webhooks {
+            Some(inbound) => router.merge(create_webhook_router(inbound)),
+            None => router,
+        };
+
+        // Shed before bodies are decompressed
+        let router = compression::apply(router, self.args.max_body_size, body_metrics);
+        admission::apply(router, admission)
             .layer(TraceLayer::new_for_http())
             .layer(CorsLayer::permissive())
     }
+
+    /// Inbound webhook endpoints from INBOUND_WEBHOOKS, if set; received
+    /// webhooks are logged
+    fn build_inbound_webhooks(&self) -> Option<Arc<InboundWebhookState>> {
+        self.args.inbound_webhooks.as_ref()?;
+        // Validated in Args::validate
+        let configs = self.args.inbound_webhooks().unwrap_or_default();
+        let (sender, receiver) = tokio::sync::mpsc::channel(1000);
+        let state = InboundWebhookState::new(Arc::new(WebhookHandlerRegistry::new()), sender)
+            .with_trusted_proxies(self.args.inbound_webhook_trusted_proxies);
+        info!("Inbound webhooks enabled at /webhooks, {} endpoints", configs.len());
+        for config in configs {
+            state.register_config(config);
+        }
+
+        let processor = InboundWebhookProcessor::new(receiver).with_callback(|webhook| {
+            info!(
+                webhook_id = %webhook.id,
+                source = %webhook.source,
+                status = ?webhook.status,
+                "Inbound webhook received"
+            );
+        });
+        tokio::spawn(processor.run());
+        Some(Arc::new(state))
+    }
+
+    /// Monitor for the configured backing services; those not listed in
+    /// REQUIRED_DEPENDENCIES run on their fallback while down instead of
+    /// failing readiness
+    fn build_dependency_monitor(&self) -> Arc<DependencyMonitor> {
+        let mut monitor = DependencyMonitor::new();
+        let configured = DEPENDENCIES.iter().zip(DEPENDENCY_FALLBACKS).zip(self.args.dependency_urls());
+        for ((name, fallback), (setting, url)) in configured {
+            let Some(url) = url else {
+                continue;
+            };
+            let criticality = if self.args.required_dependencies.iter().any(|required| required == name) {
+                Criticality::Required
+            } else {
+                Criticality::optional(fallback)
+            };
+            match check_for_url(url) {
+                Ok(check) => monitor = monitor.with_dependency(*name, check, criticality),
+                Err(e) => warn!("Not probing {}: {}", setting, e),
+            }
+        }
+        for (region, url) in region_entries(&self.args.region_database_urls) {
+            let name = format!("postgres-{}", region);
+            match check_for_url(url) {
+                Ok(check) => {
+                    monitor = monitor.with_dependency(name, check, Criticality::optional(DEPENDENCY_FALLBACKS[0]))
+                }
+                Err(e) => warn!("Not probing {}: {}", name, e),
+            }
+        }
+        Arc::new(monitor)
+    }
+
+    /// Dispatcher for event webhooks: the configured task notification
+    /// endpoint and the endpoints tenants register through the API
+    ///
+    /// With a database configured, deliveries go through the shared
+    /// Postgres queue and every replica runs a worker draining it.
+    fn build_event_webhooks(&self) -> Arc<WebhookDispatcher> {
+        let (deliveries, mut delivered) = tokio::sync::mpsc::channel(256);
+        tokio::spawn(async move { while delivered.recv().await.is_some() {} });
+        let mut dispatcher = WebhookDispatcher::new(deliveries, RetryConfig::default());
+        if let Some(url) = &self.args.notify_webhook_url {
+            dispatcher.register_endpoint(
+                WebhookEndpoint::new("task-notifications", url, &self.args.notify_webhook_secret)
+                    .with_events(TASK_EVENT_TYPES.to_vec()),
+            );
+            info!("Task notifications will be posted to {}", url);
+        }
+
+        let queue = self.build_delivery_queue();
+        if let Some(queue) = &queue {
+            dispatcher = dispatcher.with_queue(queue.clone());
+        }
+        let dispatcher = Arc::new(dispatcher);
+        if let Some(queue) = queue {
+            let worker = DeliveryWorker::new(queue, dispatcher.clone());
+            tokio::spawn(async move { worker.run().await });
+        }
+        dispatcher
+    }
+
+    /// Webhook delivery queue shared by the replicas through DATABASE_URL
+    fn build_delivery_queue(&self) -> Option<Arc<dyn DeliveryQueue>> {
+        let url = self.args.database_url.as_ref()?;
+        match create_lazy_pool(&PgPoolConfig::new(url.as_str()).with_min_connections(0)) {
+            Ok(pool) => {
+                info!("Webhook deliveries will be queued in Postgres");
+                Some(Arc::new(PgDeliveryQueue::new(pool)))
+            }
+            Err(e) => {
+                warn!("Delivering webhooks inline, no delivery queue: {}", e);
+                None
+            }
+        }
+    }
+
+    /// CI gates running the configured gate workflows on `engine`, and the
+    /// benchmark suite if enabled
+    fn build_gates(&self, engine: WorkflowEngine) -> Arc<GateService> {
+        let gates = GateService::new(engine);
+        // Validated in Args::validate
+        let workflows = self.args.gate_workflows().unwrap_or_default();
+        if !workflows.is_empty() {
+            info!("CI gates can run {} workflows", workflows.len());
+        }
+        for (tenant, workflow) in workflows {
+            match tenant {
+                Some(tenant) => gates.register_tenant_workflow(&tenant, workflow),
+                None => gates.register_workflow(workflow),
+            }
+        }
+        if self.args.gate_benchmarks {
+            info!("CI gates can run the benchmark suite");
+            gates.set_benchmark_suite(Arc::new(CanonicalBenchmarks));
+        }
+        Arc::new(gates)
+    }
+
+    /// Task notifier for the event webhooks and the configured SMTP server
+    fn build_notifier(&self, webhooks: Arc<WebhookDispatcher>) -> Arc<TaskNotifier> {
+        let mut notifier =
+            TaskNotifier::new(Arc::new(NotificationSubscriptions::default())).with_webhooks(webhooks);
+
+        if let Some(url) = &self.args.smtp_url {
+            // Validated in Args::validate
+            if let Ok(config) = SmtpConfig::from_url(url, &self.args.smtp_from) {
+                info!("Task notification emails will be sent via {}:{}", config.host, config.port);
+                notifier = notifier.with_email(Arc::new(SmtpNotifier::new(config)));
+            }
+        }
+
+        Arc::new(notifier)
+    }
+
+    /// Rotating JWT signing keys of the configured algorithm, rotated on
+    /// schedule in the background
+    fn build_signing_keys(&self) -> Option<SigningKeys> {
+        // Validated in Args::validate
+        let algorithm: SigningAlgorithm = self.args.jwt_signing_algorithm.as_ref()?.parse().ok()?;
+        let config = KeyRotationConfig {
+            algorithm,
+            rotation_interval_secs: self.args.jwt_key_rotation_hours as i64 * 3600,
+            overlap_secs: self.args.jwt_key_overlap_hours as i64 * 3600,
+        };
+        match SigningKeys::generate(config) {
+            Ok(keys) => {
+                info!(
+                    "Signing tokens with {} keys rotated every {}h, published at /.well-known/jwks.json",
+                    algorithm, self.args.jwt_key_rotation_hours
+                );
+                keys.spawn_rotation(Duration::from_secs(60));
+                Some(keys)
+            }
+            Err(e) => {
+                warn!("Signing tokens with JWT_SECRET: failed to generate signing key: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Route policy of the configured file, reloaded when the file changes
+    fn build_route_policy(&self) -> Option<RoutePolicy> {
+        let path = self.args.route_policy.as_ref()?;
+        match RoutePolicy::load(path) {
+            Ok(policy) => {
+                info!(
+                    "Enforcing route policy from {} ({} rules)",
+                    path.display(),
+                    policy.config().routes.len()
+                );
+                policy.spawn_reload(Duration::from_secs(10));
+                Some(policy)
+            }
+            Err(e) => {
+                warn!("Route policy not loaded: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Mailer of email verification and password reset links through the
+    /// configured SMTP server, linking to the public URL
+    fn build_account_mailer(&self) -> Option<Arc<EmailAccountMailer>> {
+        // Validated in Args::validate
+        let config = SmtpConfig::from_url(self.args.smtp_url.as_ref()?, &self.args.smtp_from).ok()?;
+        let public_url = self
+            .args
+            .public_url
+            .clone()
+            .unwrap_or_else(|| format!("http://localhost:{}", self.args.port));
+        info!("Email verification and password reset links will point to {}", public_url);
+        Some(Arc::new(EmailAccountMailer::new(Arc::new(SmtpNotifier::new(config)), public_url)))
+    }
+
+    /// Keep attachments, sandbox artifacts and export bundles in the
+    /// configured object stores, expiring them hourly
+    fn attach_object_storage(&self, mut api_state: ApiAppState) -> Result<ApiAppState> {
+        if let Some(url) = &self.args.object_store {
+            // Validated in Args::validate
+            let store: Arc<dyn ObjectStore> = match url.strip_prefix("file://") {
+                Some(root) => {
+                    let public_url = self
+                        .args
+                        .public_url
+                        .clone()
+                        .unwrap_or_else(|| format!("http://localhost:{}", self.args.port));
+                    let base_url = format!("{}/api/objects", public_url.trim_end_matches('/'));
+                    let store = Arc::new(LocalObjectStore::new(root, base_url, self.state.jwt_secret.as_bytes())?);
+                    info!("Keeping objects in {}", root);
+                    api_state = api_state.with_local_objects(store.clone());
+                    store
+                }
+                None => {
+                    let bucket = url.trim_start_matches("s3://").trim_end_matches('/');
+                    info!("Keeping objects in S3 bucket {}", bucket);
+                    Arc::new(S3ObjectStore::new(self.s3_config(bucket, &self.args.object_store_region))?)
+                }
+            };
+            if let Some(archive) = &self.state.context_archive {
+                archive.attach(Arc::new(ObjectArchiveStore::new(store.clone())));
+                self.spawn_context_maintenance();
+            }
+            api_state = api_state.with_object_storage(Self::object_storage(store));
+        }
+
+        for (region, url) in region_entries(&self.args.region_object_stores) {
+            let url = url.trim_start_matches("s3://");
+            let (bucket, bucket_region) = match url.split_once("?region=") {
+                Some((bucket, bucket_region)) => (bucket, bucket_region),
+                None => (url, self.args.object_store_region.as_str()),
+            };
+            let bucket = bucket.trim_end_matches('/');
+            let store = S3ObjectStore::new(self.s3_config(bucket, bucket_region))?;
+            info!("Keeping objects of tenants homed in {} in S3 bucket {}", region, bucket);
+            api_state = api_state.with_regional_object_storage(region, Self::object_storage(Arc::new(store)));
+        }
+        Ok(api_state)
+    }
+
+    /// Run context maintenance hourly, archiving the items inactive for
+    /// longer than CONTEXT_ARCHIVE_AFTER_DAYS
+    fn spawn_context_maintenance(&self) {
+        info!(
+            "Archiving context inactive for {} days to object storage",
+            self.args.context_archive_after_days.unwrap_or_default()
+        );
+        let engine = self.state.conversation_manager.context_engine();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+            loop {
+                interval.tick().await;
+                match engine.maintenance().await {
+                    Ok(report) if report.items_archived > 0 => {
+                        info!("Archived {} inactive context items", report.items_archived)
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Context maintenance failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Remove expired sessions and their per-session state every minute
+    fn spawn_session_cleanup(&self) {
+        let conversations = self.state.conversation_manager.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                conversations.cleanup_expired_sessions().await;
+            }
+        });
+    }
+
+    fn s3_config(&self, bucket: &str, region: &str) -> S3Config {
+        let access_key_id = self.args.object_store_access_key_id.clone().unwrap_or_default();
+        let secret_access_key = self.args.object_store_secret_access_key.clone().unwrap_or_default();
+        match &self.args.object_store_endpoint {
+            Some(endpoint) => S3Config::minio(endpoint, bucket, access_key_id, secret_access_key).with_region(region),
+            None => S3Config::aws(bucket, region, access_key_id, secret_access_key),
+        }
+    }
+
+    fn object_storage(store: Arc<dyn ObjectStore>) -> Arc<ObjectStorage> {
+        let storage = Arc::new(ObjectStorage::new(store));
+        storage.clone().spawn_lifecycle(Duration::from_secs(60 * 60));
+        storage
+    }
+
+    /// Dispatcher for the incident channels and ticket systems conversations
+    /// are handed off to, each registered under its configured name
+    fn build_handoff_webhooks(&self) -> Option<Arc<WebhookDispatcher>> {
+        if self.args.handoff_webhooks.is_empty() {
+            return None;
+        }
+        let (deliveries, mut delivered) = tokio::sync::mpsc::channel(256);
+        tokio::spawn(async move { while delivered.recv().await.is_some() {} });
+        let dispatcher = WebhookDispatcher::new(deliveries, RetryConfig::default());
+        // Validated in Args::validate
+        for (name, url) in self.args.handoff_webhooks.iter().filter_map(|entry| entry.split_once('=')) {
+            let mut endpoint = WebhookEndpoint::new(name.trim(), url, &self.args.notify_webhook_secret)
+                .with_events(vec![WebhookEventType::ConversationHandoff]);
+            endpoint.id = name.trim().to_string();
+            dispatcher.register_endpoint(endpoint);
+            info!("Conversations can be handed off to {} at {}", name.trim(), url);
+        }
+        Some(Arc::new(dispatcher))
+    }
+}
+
+/// The benchmark targets of copilot-benchmarks, run in process without
+/// writing results to disk
+struct CanonicalBenchmarks;
+
+#[async_trait::async_trait]
+impl BenchmarkSuite for CanonicalBenchmarks {
+    async fn run(&self, filter: &str) -> Vec<BenchmarkOutcome> {
+        let config = BenchmarkConfig {
+            write_results: false,
+            generate_summary: false,
+            filter: Some(filter.to_string()),
+            ..BenchmarkConfig::default()
+        };
+        run_all_benchmarks_with_config(config)
+            .await
+            .into_iter()
+            .map(|result| BenchmarkOutcome {
+                success: result.is_success(),
+                duration_ms: result.duration_ms(),
+                error: result.error().map(str::to_string),
+                target_id: result.target_id,
+            })
+            .collect()
+    }
+}
+
+/// Personas of the conversation manager, for agent roles that name one
+struct ConversationPersonas(Arc<tokio::sync::RwLock<PersonaRegistry>>);
+
+#[async_trait::async_trait]
+impl PersonaLookup for ConversationPersonas {
+    async fn persona(&self, persona_id: &str) -> Option<RolePersona> {
+        self.0.read().await.get(persona_id).map(|persona| RolePersona {
+            system_prompt: persona.system_prompt.clone(),
+            allowed_tools: persona.allowed_tools.clone(),
+        })
+    }
+}
+
+/// Runs conversations' code in an E2B sandbox created for each execution
+/// under the session's policy
+struct E2BSandboxes(E2BConfig);
+
+#[async_trait::async_trait]
+impl SandboxRunner for E2BSandboxes {
+    async fn run(&self, runtime: &str, code: &str, policy: SandboxPolicy) -> ConversationResult<serde_json::Value> {
+        let tool_error = |e: E2BError| ConversationError::ToolError(e.to_string());
+        let mut agent = E2BAgent::new(self.0.clone().sandbox_policy(policy)).await.map_err(tool_error)?;
+        let execution = agent.execute_code_with_runtime(code, runtime).await;
+        if let Err(e) = agent.cleanup().await {
+            warn!("Failed to clean up E2B sandbox: {}", e);
+        }
+        Ok(json!(execution.map_err(tool_error)?))
+    }
 }
 
 // Route handlers
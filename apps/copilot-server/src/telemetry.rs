@@ -21,11 +21,18 @@ pub fn init_telemetry(args: &Args) -> Result<TelemetryGuards> {
         .or_else(|_| EnvFilter::try_new(&args.log_level))
         .context("Failed to create environment filter")?;
 
+    // Span timings for the /debug/pprof endpoints
+    #[cfg(feature = "profiling")]
+    let profiler = Some(crate::profiling::ProfilingLayer::global());
+    #[cfg(not(feature = "profiling"))]
+    let profiler = None::<tracing_subscriber::layer::Identity>;
+
     // Create subscriber with formatting layer
     if args.json_logs {
         // JSON formatting for production
         tracing_subscriber::registry()
             .with(env_filter)
+            .with(profiler)
             .with(
                 fmt::layer()
                     .json()
@@ -36,6 +43,7 @@ pub fn init_telemetry(args: &Args) -> Result<TelemetryGuards> {
         // Pretty formatting for development
         tracing_subscriber::registry()
             .with(env_filter)
+            .with(profiler)
             .with(
                 fmt::layer()
                     .pretty()
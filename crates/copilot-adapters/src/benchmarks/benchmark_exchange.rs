@@ -127,7 +127,7 @@ impl BenchmarkExchangeClient {
             base_url: base_url.into(),
             cache_dir: cache_dir.into(),
             client: Client::new(),
-            circuit_breaker: CircuitBreaker::default(),
+            circuit_breaker: CircuitBreaker::default().with_name("benchmark-exchange"),
         }
     }
 
@@ -141,7 +141,7 @@ impl BenchmarkExchangeClient {
         debug!("Sending {} request to {}", method, url);
 
         let response = with_retry(3, || async {
-            self.circuit_breaker.call(|| async {
+            self.circuit_breaker.call_endpoint(path, || async {
                 let mut request = self.client.request(method.clone(), &url);
                 if let Some(body) = body {
                     request = request.json(body);
@@ -1,6 +1,9 @@
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use copilot_core::fault::{Fault, FaultInjector};
+use copilot_core::Deadline;
 use tracing::{debug, warn};
 
 use crate::{AdapterError, AdapterResult};
@@ -42,6 +45,8 @@ struct CircuitBreakerState {
 pub struct CircuitBreaker {
     config: CircuitBreakerConfig,
     state: Arc<Mutex<CircuitBreakerState>>,
+    name: String,
+    faults: Arc<FaultInjector>,
 }
 
 impl CircuitBreaker {
@@ -55,6 +60,91 @@ impl CircuitBreaker {
                 last_failure_time: None,
                 half_open_requests: 0,
             })),
+            name: "adapter".to_string(),
+            faults: FaultInjector::global(),
+        }
+    }
+
+    /// Name of the protected service, the first part of fault injection
+    /// targets
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Take injected faults from `faults` instead of the global injector
+    pub fn with_faults(mut self, faults: Arc<FaultInjector>) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    /// Like [`CircuitBreaker::call`], for a request to `endpoint` that is
+    /// subject to the faults injected into `<name><endpoint>`
+    ///
+    /// Inside a request [`Deadline`] the call is abandoned when the deadline
+    /// passes. Like a cancellation, that does not count towards opening the
+    /// circuit.
+    pub async fn call_endpoint<F, Fut, T>(&self, endpoint: &str, f: F) -> AdapterResult<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = AdapterResult<T>>,
+    {
+        let target = format!("{}{}", self.name, endpoint);
+        let Some(deadline) = Deadline::current() else {
+            return self.call(|| self.inject(&target, f)).await;
+        };
+        let exceeded = || AdapterError::Timeout(format!("request deadline reached before {} answered", target));
+        if deadline.is_expired() {
+            return Err(exceeded());
+        }
+
+        self.admit().await?;
+        match tokio::time::timeout_at(deadline.at(), self.inject(&target, f)).await {
+            Ok(result) => {
+                self.record(&result).await;
+                result
+            }
+            Err(_) => {
+                debug!(target = %target, "Request deadline reached");
+                self.release().await;
+                Err(exceeded())
+            }
+        }
+    }
+
+    /// Like [`CircuitBreaker::call_endpoint`], abandoning the request as soon
+    /// as `cancel` fires
+    ///
+    /// The in-flight request is dropped, which closes its connection. A
+    /// cancelled call is not a failure of the service, so it does not count
+    /// towards opening the circuit.
+    pub async fn call_cancellable<F, Fut, T>(
+        &self,
+        endpoint: &str,
+        cancel: &CancellationToken,
+        f: F,
+    ) -> AdapterResult<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = AdapterResult<T>>,
+    {
+        let target = format!("{}{}", self.name, endpoint);
+        let cancelled = || AdapterError::Cancelled(format!("request to {} was cancelled", target));
+        if cancel.is_cancelled() {
+            return Err(cancelled());
+        }
+
+        self.admit().await?;
+        tokio::select! {
+            result = self.inject(&target, f) => {
+                self.record(&result).await;
+                result
+            }
+            _ = cancel.cancelled() => {
+                debug!(target = %target, "Request cancelled");
+                self.release().await;
+                Err(cancelled())
+            }
         }
     }
 
@@ -63,43 +153,77 @@ impl CircuitBreaker {
         F: FnOnce() -> Fut,
         Fut: std::future::Future<Output = AdapterResult<T>>,
     {
-        // Check if we can proceed
-        {
-            let mut state = self.state.lock().await;
-
-            match state.state {
-                CircuitState::Open => {
-                    // Check if timeout has elapsed
-                    if let Some(last_failure) = state.last_failure_time {
-                        if last_failure.elapsed() >= self.config.timeout {
-                            debug!("Circuit breaker transitioning to half-open state");
-                            state.state = CircuitState::HalfOpen;
-                            state.half_open_requests = 0;
-                            state.success_count = 0;
-                        } else {
-                            warn!("Circuit breaker is open, rejecting request");
-                            return Err(AdapterError::CircuitBreakerOpen);
-                        }
-                    }
-                }
-                CircuitState::HalfOpen => {
-                    if state.half_open_requests >= self.config.half_open_max_requests {
-                        warn!("Circuit breaker half-open limit reached, rejecting request");
+        self.admit().await?;
+
+        // Execute the function
+        let result = f().await;
+
+        self.record(&result).await;
+        result
+    }
+
+    /// Run `f` unless a fault is injected into `target`
+    async fn inject<F, Fut, T>(&self, target: &str, f: F) -> AdapterResult<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = AdapterResult<T>>,
+    {
+        let injection = self.faults.roll(target);
+        if let Some(delay) = injection.delay {
+            tokio::time::sleep(delay).await;
+        }
+        match injection.fault {
+            Some(Fault::Error) => Err(AdapterError::ServiceUnavailable(format!(
+                "{} at {}",
+                Fault::Error,
+                target
+            ))),
+            Some(Fault::Drop) => Err(AdapterError::ConnectionError(format!(
+                "{} at {}",
+                Fault::Drop,
+                target
+            ))),
+            None => f().await,
+        }
+    }
+
+    /// Check if we can proceed
+    async fn admit(&self) -> AdapterResult<()> {
+        let mut state = self.state.lock().await;
+
+        match state.state {
+            CircuitState::Open => {
+                // Check if timeout has elapsed
+                if let Some(last_failure) = state.last_failure_time {
+                    if last_failure.elapsed() >= self.config.timeout {
+                        debug!("Circuit breaker transitioning to half-open state");
+                        state.state = CircuitState::HalfOpen;
+                        state.half_open_requests = 0;
+                        state.success_count = 0;
+                    } else {
+                        warn!("Circuit breaker is open, rejecting request");
                         return Err(AdapterError::CircuitBreakerOpen);
                     }
-                    state.half_open_requests += 1;
                 }
-                CircuitState::Closed => {
-                    // Allow the request
+            }
+            CircuitState::HalfOpen => {
+                if state.half_open_requests >= self.config.half_open_max_requests {
+                    warn!("Circuit breaker half-open limit reached, rejecting request");
+                    return Err(AdapterError::CircuitBreakerOpen);
                 }
+                state.half_open_requests += 1;
+            }
+            CircuitState::Closed => {
+                // Allow the request
             }
         }
 
-        // Execute the function
-        let result = f().await;
+        Ok(())
+    }
 
-        // Update state based on result
-        match &result {
+    /// Update state based on result
+    async fn record<T>(&self, result: &AdapterResult<T>) {
+        match result {
             Ok(_) => {
                 self.record_success().await;
             }
@@ -107,8 +231,14 @@ impl CircuitBreaker {
                 self.record_failure().await;
             }
         }
+    }
 
-        result
+    /// Give back the half-open slot of a call that never completed
+    async fn release(&self) {
+        let mut state = self.state.lock().await;
+        if state.state == CircuitState::HalfOpen {
+            state.half_open_requests = state.half_open_requests.saturating_sub(1);
+        }
     }
 
     pub async fn record_success(&self) {
@@ -276,4 +406,95 @@ mod tests {
 
         assert_eq!(cb.get_state().await, CircuitState::Closed);
     }
+
+    #[tokio::test]
+    async fn test_injected_faults_open_the_circuit() {
+        use crate::retry::with_retry;
+        use copilot_core::{Determinism, FaultRule};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let faults = Arc::new(
+            FaultInjector::new(&Determinism::seeded(1))
+                .with_rules(vec![FaultRule::new("router/api/*").with_error_rate(1.0)]),
+        );
+        let cb = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 3,
+            ..Default::default()
+        })
+        .with_name("router")
+        .with_faults(faults.clone());
+        let calls = AtomicUsize::new(0);
+
+        let result = with_retry(3, || async {
+            cb.call_endpoint("/api/v1/route", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, AdapterError>(())
+            })
+            .await
+        })
+        .await;
+
+        assert!(matches!(result, Err(AdapterError::ServiceUnavailable(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(faults.stats().errors, 3);
+        assert_eq!(cb.get_state().await, CircuitState::Open);
+        assert!(matches!(
+            cb.call_endpoint("/api/v1/route", || async { Ok::<_, AdapterError>(()) }).await,
+            Err(AdapterError::CircuitBreakerOpen)
+        ));
+
+        // Endpoints without a matching rule are untouched
+        faults.set_rules(vec![FaultRule::new("router/api/*").with_drop_rate(1.0)]);
+        cb.reset().await;
+        cb.call_endpoint("/health", || async { Ok::<_, AdapterError>(()) }).await.unwrap();
+        assert!(matches!(
+            cb.call_endpoint("/api/v1/route", || async { Ok::<_, AdapterError>(()) }).await,
+            Err(AdapterError::ConnectionError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_calls_do_not_open_the_circuit() {
+        let cb = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            ..Default::default()
+        })
+        .with_name("router");
+        let cancel = CancellationToken::new();
+
+        let call = cb.call_cancellable("/api/v1/route", &cancel, || async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok::<_, AdapterError>(())
+        });
+        let (result, _) = tokio::join!(call, async { cancel.cancel() });
+
+        assert!(matches!(result, Err(AdapterError::Cancelled(_))));
+        assert_eq!(cb.get_state().await, CircuitState::Closed);
+        assert!(matches!(
+            cb.call_cancellable("/api/v1/route", &cancel, || async { Ok::<_, AdapterError>(()) }).await,
+            Err(AdapterError::Cancelled(_))
+        ));
+        cb.call_cancellable("/api/v1/route", &CancellationToken::new(), || async { Ok::<_, AdapterError>(()) })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_calls_stop_at_the_request_deadline() {
+        let cb = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            ..Default::default()
+        })
+        .with_name("router");
+
+        let result = Deadline::after(Duration::from_millis(20))
+            .scope(cb.call_endpoint("/api/v1/route", || async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok::<_, AdapterError>(())
+            }))
+            .await;
+
+        assert!(matches!(result, Err(AdapterError::Timeout(_))));
+        assert_eq!(cb.get_state().await, CircuitState::Closed);
+    }
 }
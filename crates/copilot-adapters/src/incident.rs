@@ -23,7 +23,7 @@ impl IncidentClient {
         Self {
             base_url: base_url.into(),
             client: Client::new(),
-            circuit_breaker: CircuitBreaker::default(),
+            circuit_breaker: CircuitBreaker::default().with_name("incident"),
         }
     }
 
@@ -37,7 +37,7 @@ impl IncidentClient {
         debug!("Sending {} request to {}", method, url);
 
         let response = with_retry(3, || async {
-            self.circuit_breaker.call(|| async {
+            self.circuit_breaker.call_endpoint(path, || async {
                 let mut request = self.client.request(method.clone(), &url);
 
                 if let Some(body) = body {
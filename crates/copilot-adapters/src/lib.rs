@@ -83,6 +83,9 @@ pub enum AdapterError {
     #[error("Service unavailable: {0}")]
     ServiceUnavailable(String),
 
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
@@ -165,7 +165,7 @@ impl AutoOptimizerClient {
         Self {
             base_url: base_url.into(),
             client: Client::new(),
-            circuit_breaker: CircuitBreaker::default(),
+            circuit_breaker: CircuitBreaker::default().with_name("auto-optimizer"),
         }
     }
 
@@ -179,7 +179,7 @@ impl AutoOptimizerClient {
         debug!("Sending {} request to {}", method, url);
 
         let response = with_retry(3, || async {
-            self.circuit_breaker.call(|| async {
+            self.circuit_breaker.call_endpoint(path, || async {
                 let mut request = self.client.request(method.clone(), &url);
                 if let Some(body) = body {
                     request = request.json(body);
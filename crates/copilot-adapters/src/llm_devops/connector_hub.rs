@@ -128,7 +128,7 @@ impl ConnectorHubClient {
         Self {
             base_url: base_url.into(),
             client: Client::new(),
-            circuit_breaker: CircuitBreaker::default(),
+            circuit_breaker: CircuitBreaker::default().with_name("connector-hub"),
         }
     }
 
@@ -142,7 +142,7 @@ impl ConnectorHubClient {
         debug!("Sending {} request to {}", method, url);
 
         let response = with_retry(3, || async {
-            self.circuit_breaker.call(|| async {
+            self.circuit_breaker.call_endpoint(path, || async {
                 let mut request = self.client.request(method.clone(), &url);
                 if let Some(body) = body {
                     request = request.json(body);
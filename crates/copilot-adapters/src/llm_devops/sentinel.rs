@@ -154,7 +154,7 @@ impl SentinelClient {
         Self {
             base_url: base_url.into(),
             client: Client::new(),
-            circuit_breaker: CircuitBreaker::default(),
+            circuit_breaker: CircuitBreaker::default().with_name("sentinel"),
         }
     }
 
@@ -168,7 +168,7 @@ impl SentinelClient {
         debug!("Sending {} request to {}", method, url);
 
         let response = with_retry(3, || async {
-            self.circuit_breaker.call(|| async {
+            self.circuit_breaker.call_endpoint(path, || async {
                 let mut request = self.client.request(method.clone(), &url);
                 if let Some(body) = body {
                     request = request.json(body);
@@ -270,7 +270,7 @@ impl SentinelAdapter for SentinelClient {
     }
 
     async fn report_incident(&self, incident: SecurityIncident) -> AdapterResult<SecurityIncident> {
-        warn!("Reporting security incident: {} ({})", incident.incident_type, incident.severity);
+        warn!("Reporting security incident: {} ({:?})", incident.incident_type, incident.severity);
         self.send_request(reqwest::Method::POST, "/incidents", Some(&incident)).await
     }
 }
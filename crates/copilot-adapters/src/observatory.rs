@@ -7,6 +7,7 @@ use crate::{
     traits::{
         ModuleAdapter, ObservatoryAdapter, MetricsQuery, MetricsResponse,
         LogsQuery, LogsResponse, TracesQuery, TracesResponse,
+        DashboardPush, DashboardPushResponse,
     },
     circuit_breaker::CircuitBreaker,
     retry::with_retry,
@@ -16,6 +17,8 @@ pub struct ObservatoryClient {
     prometheus_url: String,
     loki_url: String,
     jaeger_url: String,
+    /// Grafana URL and API token, if dashboards can be pushed
+    grafana: Option<(String, String)>,
     client: Client,
     circuit_breaker: CircuitBreaker,
 }
@@ -30,8 +33,9 @@ impl ObservatoryClient {
             prometheus_url: prometheus_url.into(),
             loki_url: loki_url.into(),
             jaeger_url: jaeger_url.into(),
+            grafana: None,
             client: Client::new(),
-            circuit_breaker: CircuitBreaker::default(),
+            circuit_breaker: CircuitBreaker::default().with_name("observatory"),
         }
     }
 
@@ -41,17 +45,31 @@ impl ObservatoryClient {
             prometheus_url: url.clone(),
             loki_url: url.clone(),
             jaeger_url: url,
+            grafana: None,
             client: Client::new(),
-            circuit_breaker: CircuitBreaker::default(),
+            circuit_breaker: CircuitBreaker::default().with_name("observatory"),
         }
     }
 
+    /// Query logs from the Loki at `loki_url`
+    pub fn with_loki(mut self, loki_url: impl Into<String>) -> Self {
+        self.loki_url = loki_url.into();
+        self
+    }
+
+    /// Push dashboards to the Grafana at `grafana_url`, authenticating with
+    /// a service account token
+    pub fn with_grafana(mut self, grafana_url: impl Into<String>, api_token: impl Into<String>) -> Self {
+        self.grafana = Some((grafana_url.into().trim_end_matches('/').to_string(), api_token.into()));
+        self
+    }
+
     async fn query_prometheus(&self, query: &str, start: i64, end: i64, step: &str) -> AdapterResult<MetricsResponse> {
         let url = format!("{}/api/v1/query_range", self.prometheus_url);
         debug!("Querying Prometheus: {}", query);
 
         let response = with_retry(3, || async {
-            self.circuit_breaker.call(|| async {
+            self.circuit_breaker.call_endpoint("/api/v1/query_range", || async {
                 self.client
                     .get(&url)
                     .query(&[
@@ -83,7 +101,7 @@ impl ObservatoryClient {
         debug!("Querying Loki: {}", query);
 
         let response = with_retry(3, || async {
-            self.circuit_breaker.call(|| async {
+            self.circuit_breaker.call_endpoint("/loki/api/v1/query_range", || async {
                 self.client
                     .get(&url)
                     .query(&[
@@ -110,12 +128,44 @@ impl ObservatoryClient {
             .map_err(|e| AdapterError::SerializationError(e.to_string()))
     }
 
+    async fn query_loki_metrics(&self, query: &str, start: i64, end: i64, step: &str) -> AdapterResult<MetricsResponse> {
+        let url = format!("{}/loki/api/v1/query_range", self.loki_url);
+        debug!("Querying Loki metrics: {}", query);
+
+        let response = with_retry(3, || async {
+            self.circuit_breaker.call_endpoint("/loki/api/v1/query_range", || async {
+                self.client
+                    .get(&url)
+                    .query(&[
+                        ("query", query),
+                        ("start", &start.to_string()),
+                        ("end", &end.to_string()),
+                        ("step", step),
+                    ])
+                    .send()
+                    .await
+                    .map_err(|e| AdapterError::RequestFailed(e.to_string()))
+            }).await
+        }).await?;
+
+        if !response.status().is_success() {
+            let error_msg = format!("Loki metric query failed with status: {}", response.status());
+            error!("{}", error_msg);
+            return Err(AdapterError::RequestFailed(error_msg));
+        }
+
+        response
+            .json::<MetricsResponse>()
+            .await
+            .map_err(|e| AdapterError::SerializationError(e.to_string()))
+    }
+
     async fn query_jaeger(&self, params: &TracesQuery) -> AdapterResult<TracesResponse> {
         let url = format!("{}/api/traces", self.jaeger_url);
         debug!("Querying Jaeger for traces");
 
         let response = with_retry(3, || async {
-            self.circuit_breaker.call(|| async {
+            self.circuit_breaker.call_endpoint("/api/traces", || async {
                 self.client
                     .get(&url)
                     .json(&params)
@@ -246,6 +296,19 @@ impl ObservatoryAdapter for ObservatoryClient {
         Ok(result)
     }
 
+    async fn query_log_metrics(&self, query: MetricsQuery) -> AdapterResult<MetricsResponse> {
+        info!("Querying log metrics: {}", query.query);
+
+        let start = query.start_time.timestamp_nanos_opt().unwrap_or(0);
+        let end = query.end_time.timestamp_nanos_opt().unwrap_or(0);
+        let step = query.step.as_deref().unwrap_or("15s");
+
+        let result = self.query_loki_metrics(&query.query, start, end, step).await?;
+
+        info!("Log metrics query returned {} results", result.data.result.len());
+        Ok(result)
+    }
+
     async fn query_traces(&self, query: TracesQuery) -> AdapterResult<TracesResponse> {
         info!("Querying traces for service: {:?}", query.service_name);
 
@@ -254,6 +317,37 @@ impl ObservatoryAdapter for ObservatoryClient {
         info!("Traces query returned {} traces", result.traces.len());
         Ok(result)
     }
+
+    async fn push_dashboard(&self, push: DashboardPush) -> AdapterResult<DashboardPushResponse> {
+        let (grafana_url, token) = self.grafana.as_ref()
+            .ok_or_else(|| AdapterError::ServiceUnavailable("Grafana is not configured".to_string()))?;
+        let url = format!("{}/api/dashboards/db", grafana_url);
+        info!("Pushing dashboard to Grafana: {}", push.dashboard["title"]);
+
+        // Not retried: a retry after a timeout could save a second version
+        let response = self.circuit_breaker.call_endpoint("/api/dashboards/db", || async {
+            self.client
+                .post(&url)
+                .bearer_auth(token)
+                .json(&push)
+                .send()
+                .await
+                .map_err(|e| AdapterError::RequestFailed(e.to_string()))
+        }).await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            let error_msg = format!("Grafana dashboard push failed with status {}: {}", status, body);
+            error!("{}", error_msg);
+            return Err(AdapterError::RequestFailed(error_msg));
+        }
+
+        response
+            .json::<DashboardPushResponse>()
+            .await
+            .map_err(|e| AdapterError::SerializationError(e.to_string()))
+    }
 }
 
 #[cfg(test)]
@@ -279,4 +373,40 @@ mod tests {
         assert_eq!(caps.name, "LLM-Observatory");
         assert!(caps.features.contains(&"metrics_collection".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_push_dashboard_requires_grafana() {
+        let client = ObservatoryClient::with_prometheus("http://prometheus:9090");
+        let push = DashboardPush {
+            dashboard: serde_json::json!({"title": "checkout"}),
+            folder_uid: None,
+            overwrite: false,
+            message: None,
+        };
+        assert!(matches!(
+            client.push_dashboard(push.clone()).await,
+            Err(AdapterError::ServiceUnavailable(_))
+        ));
+
+        let client = client.with_grafana("http://grafana:3000/", "token");
+        assert_eq!(client.grafana.as_ref().unwrap().0, "http://grafana:3000");
+    }
+
+    #[test]
+    fn test_parses_range_query_response() {
+        let client = ObservatoryClient::with_prometheus("http://prometheus:9090").with_loki("http://loki:3100");
+        assert_eq!(client.loki_url, "http://loki:3100");
+
+        // Prometheus and Loki both answer matrix queries in this shape
+        let response: MetricsResponse = serde_json::from_value(serde_json::json!({
+            "status": "success",
+            "data": {
+                "resultType": "matrix",
+                "result": [{"metric": {"service": "checkout"}, "values": [[1700000000, "0.7"]]}]
+            }
+        }))
+        .unwrap();
+        assert_eq!(response.data.result_type, "matrix");
+        assert_eq!(response.data.result[0].values[0], (1_700_000_000.0, "0.7".to_string()));
+    }
 }
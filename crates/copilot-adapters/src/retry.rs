@@ -1,6 +1,7 @@
 use std::time::Duration;
 use tracing::{debug, warn};
 use rand::Rng;
+use copilot_core::Deadline;
 
 use crate::{AdapterError, AdapterResult};
 
@@ -76,6 +77,11 @@ impl RetryPolicy {
         for attempt in 0..self.max_attempts {
             if attempt > 0 {
                 let backoff = self.calculate_backoff(attempt - 1);
+                // No point backing off past the request deadline
+                if Deadline::too_close(backoff) {
+                    debug!("Request deadline too close for another attempt");
+                    break;
+                }
                 debug!(
                     "Retry attempt {}/{}, backing off for {:?}",
                     attempt + 1,
@@ -142,6 +148,7 @@ fn is_retryable(error: &AdapterError) -> bool {
         AdapterError::CircuitBreakerOpen => false,
         AdapterError::SerializationError(_) => false,
         AdapterError::InvalidResponse(_) => false,
+        AdapterError::Cancelled(_) => false,
         AdapterError::Unknown(_) => true,
     }
 }
@@ -23,7 +23,7 @@ impl TestBenchClient {
         Self {
             endpoint: endpoint.into(),
             client: None,
-            circuit_breaker: CircuitBreaker::default(),
+            circuit_breaker: CircuitBreaker::default().with_name("testbench"),
         }
     }
 
@@ -63,7 +63,7 @@ impl TestBenchClient {
 
         let client = reqwest::Client::new();
         let response = with_retry(3, || async {
-            self.circuit_breaker.call(|| async {
+            self.circuit_breaker.call_endpoint(path, || async {
                 client
                     .post(&url)
                     .json(request)
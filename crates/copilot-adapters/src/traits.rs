@@ -91,6 +91,7 @@ pub struct MetricsResponse {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetricsData {
+    #[serde(alias = "resultType")]
     pub result_type: String,
     pub result: Vec<MetricResult>,
 }
@@ -156,11 +157,37 @@ pub struct Span {
     pub tags: HashMap<String, String>,
 }
 
+/// A Grafana dashboard to create or update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardPush {
+    /// Dashboard JSON model
+    pub dashboard: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder_uid: Option<String>,
+    /// Replace a dashboard with the same uid or title
+    pub overwrite: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Where Grafana saved a pushed dashboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardPushResponse {
+    pub uid: String,
+    pub url: String,
+    pub version: u64,
+    pub status: String,
+}
+
 #[async_trait]
 pub trait ObservatoryAdapter: Send + Sync {
     async fn query_metrics(&self, query: MetricsQuery) -> AdapterResult<MetricsResponse>;
     async fn query_logs(&self, query: LogsQuery) -> AdapterResult<LogsResponse>;
+    /// Runs a LogQL metric query, such as `count_over_time`, over a range
+    async fn query_log_metrics(&self, query: MetricsQuery) -> AdapterResult<MetricsResponse>;
     async fn query_traces(&self, query: TracesQuery) -> AdapterResult<TracesResponse>;
+    async fn push_dashboard(&self, push: DashboardPush) -> AdapterResult<DashboardPushResponse>;
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,68 @@
+//! Auditing of context access control
+//!
+//! [`ContextAccessAudit`] records each context item an
+//! [`copilot_context::AccessFence`] keeps from a caller as an
+//! `AccessDenied` audit event, so attempts to reach confidential documents
+//! show up in compliance reports and access reviews.
+
+use async_trait::async_trait;
+use copilot_context::{AccessAuditor, AccessDenial};
+use copilot_security::{AuditEvent, AuditEventType, AuditLogger, AuditOutcome};
+use std::sync::Arc;
+
+/// Sends context access denials to an audit logger
+pub struct ContextAccessAudit {
+    audit: Arc<dyn AuditLogger>,
+}
+
+impl ContextAccessAudit {
+    pub fn new(audit: Arc<dyn AuditLogger>) -> Self {
+        Self { audit }
+    }
+}
+
+#[async_trait]
+impl AccessAuditor for ContextAccessAudit {
+    async fn denied(&self, denial: &AccessDenial) {
+        let mut event = AuditEvent::new(AuditEventType::AccessDenied, "context.retrieve")
+            .with_actor(&denial.principal.user_id, "user")
+            .with_resource("context_item", &denial.item_id.to_string())
+            .with_outcome(AuditOutcome::Failure)
+            .with_metadata("operation", denial.operation.as_str())
+            .with_metadata("source", denial.source.as_str());
+        if let Some(tenant_id) = &denial.principal.tenant_id {
+            event = event.with_tenant_id(tenant_id);
+        }
+        self.audit.log(event).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_context::AccessOperation;
+    use copilot_core::Principal;
+    use copilot_security::InMemoryAuditLogger;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_denials_are_audited() {
+        let logger = Arc::new(InMemoryAuditLogger::new());
+        let audit = ContextAccessAudit::new(logger.clone());
+        let denial = AccessDenial {
+            principal: Principal::new("bob").with_tenant("acme"),
+            operation: AccessOperation::Retrieve,
+            item_id: Uuid::new_v4(),
+            source: "hr/bands.md".to_string(),
+        };
+        audit.denied(&denial).await;
+
+        let events = logger.get_events().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, AuditEventType::AccessDenied);
+        assert_eq!(events[0].actor_id.as_deref(), Some("bob"));
+        assert_eq!(events[0].tenant_id.as_deref(), Some("acme"));
+        assert_eq!(events[0].resource_id, Some(denial.item_id.to_string()));
+        assert_eq!(events[0].metadata["source"], "hr/bands.md");
+    }
+}
@@ -0,0 +1,129 @@
+//! Email delivery of verification and password reset links
+//!
+//! [`EmailAccountMailer`] renders the links [`AuthService`] mails users and
+//! sends them with the notification subsystem's [`EmailSender`], e.g. the
+//! SMTP notifier task notifications go through.
+//!
+//! [`AuthService`]: copilot_security::AuthService
+
+use async_trait::async_trait;
+use copilot_security::{AccountEmail, AccountMailer, AccountTokenPurpose, SecurityError};
+use copilot_webhook::{EmailMessage, EmailSender, MessageTemplate};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Mails account links that open pages of the web app at `base_url`
+pub struct EmailAccountMailer {
+    sender: Arc<dyn EmailSender>,
+    base_url: String,
+    templates: HashMap<AccountTokenPurpose, MessageTemplate>,
+}
+
+impl EmailAccountMailer {
+    pub fn new(sender: Arc<dyn EmailSender>, base_url: impl Into<String>) -> Self {
+        let templates = HashMap::from([
+            (
+                AccountTokenPurpose::VerifyEmail,
+                MessageTemplate::new(
+                    "Verify your email address",
+                    "Hi {{username}},\n\nConfirm this is your email address by opening:\n\n{{link}}\n\nThe link works once and expires at {{expires_at}}. If you didn't create an account, ignore this email.",
+                ),
+            ),
+            (
+                AccountTokenPurpose::ResetPassword,
+                MessageTemplate::new(
+                    "Reset your password",
+                    "Hi {{username}},\n\nChoose a new password by opening:\n\n{{link}}\n\nThe link works once and expires at {{expires_at}}. If you didn't ask to reset your password, ignore this email; your password is unchanged.",
+                ),
+            ),
+        ]);
+
+        Self {
+            sender,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            templates,
+        }
+    }
+
+    /// Replace the message sent for `purpose`; it may use the `username`,
+    /// `link` and `expires_at` placeholders
+    pub fn with_template(mut self, purpose: AccountTokenPurpose, template: MessageTemplate) -> Self {
+        self.templates.insert(purpose, template);
+        self
+    }
+
+    /// The page of the web app that redeems a token
+    fn link(&self, email: &AccountEmail) -> String {
+        let page = match email.purpose {
+            AccountTokenPurpose::VerifyEmail => "verify-email",
+            AccountTokenPurpose::ResetPassword => "reset-password",
+        };
+        format!("{}/{}?token={}", self.base_url, page, email.token)
+    }
+}
+
+#[async_trait]
+impl AccountMailer for EmailAccountMailer {
+    async fn send(&self, email: &AccountEmail) -> copilot_security::Result<()> {
+        let template = &self.templates[&email.purpose];
+        let vars = HashMap::from([
+            ("username", email.username.clone()),
+            ("link", self.link(email)),
+            ("expires_at", email.expires_at.format("%Y-%m-%d %H:%M UTC").to_string()),
+        ]);
+        let message = template.render_with(&vars);
+
+        self.sender
+            .send(&EmailMessage {
+                to: email.to.clone(),
+                subject: message.subject,
+                body: message.body,
+            })
+            .await
+            .map_err(|e| SecurityError::Internal(format!("Failed to send {} email: {}", email.purpose.as_str(), e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use std::sync::Mutex;
+
+    struct RecordingSender(Mutex<Vec<EmailMessage>>);
+
+    #[async_trait]
+    impl EmailSender for RecordingSender {
+        async fn send(&self, message: &EmailMessage) -> copilot_webhook::Result<()> {
+            self.0.lock().unwrap().push(message.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mails_link_to_web_app() {
+        let sender = Arc::new(RecordingSender(Mutex::new(Vec::new())));
+        let mailer = EmailAccountMailer::new(sender.clone(), "https://copilot.example.com/");
+
+        mailer
+            .send(&AccountEmail {
+                purpose: AccountTokenPurpose::ResetPassword,
+                user_id: "user-1".to_string(),
+                username: "alice".to_string(),
+                to: "alice@example.com".to_string(),
+                token: "abc.def".to_string(),
+                expires_at: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+            })
+            .await
+            .unwrap();
+
+        let sent = sender.0.lock().unwrap();
+        assert_eq!(sent[0].to, "alice@example.com");
+        assert_eq!(sent[0].subject, "Reset your password");
+        assert!(sent[0].body.starts_with("Hi alice,"));
+        assert!(sent[0]
+            .body
+            .contains("https://copilot.example.com/reset-password?token=abc.def"));
+        assert!(sent[0].body.contains("2026-01-02 03:04 UTC"));
+    }
+}
@@ -0,0 +1,153 @@
+//! Query and conversation analytics
+//!
+//! [`QueryAnalyticsObserver`] records the outcome of every answered question
+//! in a [`QueryAnalytics`], keeping question text only as far as the
+//! tenant's prompt logging mode allows, so the knowledge gaps report can
+//! show documentation teams which topics go unanswered.
+//! [`ConversationAnalyticsObserver`] follows the same answers per
+//! conversation in a [`ConversationAnalytics`], for the intent and
+//! resolution report. [`ContextPipelineObserver`] adds each stage the
+//! context pipeline ran to the [`ContextPipelineMetrics`].
+
+use copilot_context::{ContextStageObserver, StageRecord};
+use copilot_conversation::{AnswerObserver, AnswerOutcome};
+use copilot_core::PromptLogPolicy;
+use copilot_observability::{
+    ContextPipelineMetrics, ConversationAnalytics, QueryAnalytics, QueryOutcome, StageSample,
+};
+use std::sync::Arc;
+
+/// Records answer outcomes in query analytics
+pub struct QueryAnalyticsObserver {
+    analytics: Arc<QueryAnalytics>,
+    prompt_logging: PromptLogPolicy,
+}
+
+impl QueryAnalyticsObserver {
+    pub fn new(analytics: Arc<QueryAnalytics>, prompt_logging: PromptLogPolicy) -> Self {
+        Self {
+            analytics,
+            prompt_logging,
+        }
+    }
+}
+
+impl AnswerObserver for QueryAnalyticsObserver {
+    fn observe(&self, outcome: &AnswerOutcome) {
+        let tenant_id = outcome.tenant_id.as_deref().unwrap_or_default();
+        let mut query = QueryOutcome::new(&outcome.query).with_citations(outcome.citations);
+        if let Some(tenant_id) = &outcome.tenant_id {
+            query = query.with_tenant(tenant_id);
+        }
+        if let Some(confidence) = outcome.confidence {
+            query = query.with_confidence(confidence);
+        }
+        self.analytics.record(query, self.prompt_logging.for_tenant(tenant_id));
+    }
+}
+
+/// Records each answered turn in conversation analytics
+pub struct ConversationAnalyticsObserver {
+    analytics: Arc<ConversationAnalytics>,
+    prompt_logging: PromptLogPolicy,
+}
+
+impl ConversationAnalyticsObserver {
+    pub fn new(analytics: Arc<ConversationAnalytics>, prompt_logging: PromptLogPolicy) -> Self {
+        Self {
+            analytics,
+            prompt_logging,
+        }
+    }
+}
+
+impl AnswerObserver for ConversationAnalyticsObserver {
+    fn observe(&self, outcome: &AnswerOutcome) {
+        let tenant_id = outcome.tenant_id.as_deref();
+        self.analytics.record_turn(
+            &outcome.session_id,
+            tenant_id,
+            outcome.intent.as_deref(),
+            &outcome.query,
+            self.prompt_logging.for_tenant(tenant_id.unwrap_or_default()),
+        );
+    }
+}
+
+/// Records context pipeline stages in per-stage metrics
+pub struct ContextPipelineObserver {
+    metrics: Arc<ContextPipelineMetrics>,
+}
+
+impl ContextPipelineObserver {
+    pub fn new(metrics: Arc<ContextPipelineMetrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl ContextStageObserver for ContextPipelineObserver {
+    fn record(&self, record: &StageRecord) {
+        self.metrics.record(
+            record.stage.as_str(),
+            StageSample {
+                latency_ms: record.latency.as_secs_f64() * 1000.0,
+                candidates_in: record.candidates_in as u64,
+                candidates_out: record.candidates_out as u64,
+                tokens_saved: record.tokens_saved() as u64,
+                cache_hit: record.cache_hit,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use copilot_core::PromptLogging;
+
+    #[test]
+    fn test_outcomes_are_recorded_as_tenants_allow() {
+        let analytics = Arc::new(QueryAnalytics::new());
+        let policy = PromptLogPolicy::default().with_tenant("private", PromptLogging::Off);
+        let observer = QueryAnalyticsObserver::new(analytics.clone(), policy);
+        let outcome = |tenant_id: &str| AnswerOutcome {
+            session_id: "s1".to_string(),
+            tenant_id: Some(tenant_id.to_string()),
+            query: "How do I request a new laptop?".to_string(),
+            intent: None,
+            confidence: Some(0.3),
+            citations: 1,
+            cached: false,
+            experiment: None,
+            context_downsize: None,
+        };
+        observer.observe(&outcome("acme"));
+        observer.observe(&outcome("private"));
+
+        let since = Utc::now() - Duration::hours(1);
+        let report = analytics.report(Some("acme"), since);
+        assert_eq!((report.total_queries, report.unanswered_queries), (1, 1));
+        assert_eq!(report.gaps[0].examples, vec!["How do I request a new laptop?"]);
+        assert_eq!(analytics.report(Some("private"), since).total_queries, 0);
+    }
+
+    #[test]
+    fn test_context_stages_are_recorded() {
+        use copilot_context::ContextStage;
+        use std::time::Duration;
+
+        let metrics = Arc::new(ContextPipelineMetrics::new());
+        let observer = ContextPipelineObserver::new(metrics.clone());
+        observer.record(
+            &StageRecord::new(ContextStage::Compression, Duration::from_millis(4))
+                .with_candidates(3, 3)
+                .with_tokens(800, 500),
+        );
+
+        let stats = metrics.stats();
+        let compression = stats.stage("compression").unwrap();
+        assert_eq!((compression.runs, compression.tokens_saved), (1, 300));
+        assert_eq!(compression.max_latency_ms, 4.0);
+    }
+}
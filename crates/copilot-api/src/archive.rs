@@ -0,0 +1,69 @@
+//! Context archive in object storage
+//!
+//! [`ObjectArchiveStore`] keeps the content of archived context items in the
+//! server's object store, under the archive's key prefix. Object lifecycle
+//! rules only expire attachments, artifacts and exports, so archived content
+//! stays until its item is rehydrated or removed.
+
+use async_trait::async_trait;
+use copilot_context::{ArchiveStore, ContextError};
+use copilot_infra::{InfraError, ObjectStore};
+use std::sync::Arc;
+
+/// Keeps archived context in an object store
+pub struct ObjectArchiveStore {
+    store: Arc<dyn ObjectStore>,
+}
+
+impl ObjectArchiveStore {
+    pub fn new(store: Arc<dyn ObjectStore>) -> Self {
+        Self { store }
+    }
+}
+
+fn storage_error(e: InfraError) -> ContextError {
+    ContextError::StorageError(e.to_string())
+}
+
+#[async_trait]
+impl ArchiveStore for ObjectArchiveStore {
+    async fn put(&self, key: &str, content: Vec<u8>) -> copilot_context::Result<()> {
+        self.store
+            .put(key, content, "text/plain; charset=utf-8")
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> copilot_context::Result<Option<Vec<u8>>> {
+        match self.store.get(key).await {
+            Ok(content) => Ok(Some(content)),
+            Err(InfraError::NotFound(_)) => Ok(None),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> copilot_context::Result<()> {
+        self.store.delete(key).await.map_err(storage_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_infra::LocalObjectStore;
+
+    #[tokio::test]
+    async fn test_archived_content_round_trips() {
+        let root = std::env::temp_dir().join(format!("copilot-archive-{}", uuid::Uuid::new_v4()));
+        let objects = Arc::new(LocalObjectStore::new(root, "http://localhost/api/objects", b"key").unwrap());
+        let archive = ObjectArchiveStore::new(objects);
+
+        let key = "context-archive/5f0c6f1e-2b1d-4c8e-9a63-0f6b8f1c2d3e";
+        archive.put(key, b"Restart the billing queue".to_vec()).await.unwrap();
+        assert_eq!(archive.get(key).await.unwrap().unwrap(), b"Restart the billing queue");
+
+        archive.delete(key).await.unwrap();
+        assert!(archive.get(key).await.unwrap().is_none());
+    }
+}
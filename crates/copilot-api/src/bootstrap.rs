@@ -0,0 +1,227 @@
+//! Cold-start corpus bootstrap jobs
+//!
+//! Ingests a whole wiki export (a Confluence space export or a zip of
+//! markdown) as a background job, so a new deployment reaches useful
+//! retrieval quality in one step. Every page is chunked, summarized and
+//! stored through the same safety scanning as uploads, and the job ends with
+//! a [`BootstrapReport`] of how much of the export was covered, per space.
+
+use crate::error::{ApiError, Result};
+use crate::ingestion::{ingestion_error, IngestionService};
+use chrono::{DateTime, Utc};
+use copilot_ingestion::{
+    BootstrapReport, CorpusBootstrap, ExtractiveSummarizer, IngestionPipeline, PipelineConfig, SummaryStage,
+    WikiExport, WikiFormat,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tracing::info;
+use uuid::Uuid;
+
+/// Finished jobs retained for status queries
+const RETAINED_JOBS: usize = 100;
+
+/// Pages ingested between progress updates
+const PROGRESS_INTERVAL: usize = 10;
+
+/// Largest export accepted
+pub const MAX_EXPORT_BYTES: usize = 512 * 1024 * 1024;
+
+/// State of a bootstrap job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BootstrapJobStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// A wiki export being ingested
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapJob {
+    pub id: Uuid,
+    pub tenant_id: String,
+    /// Source label attached to stored chunks
+    pub source: String,
+    pub format: WikiFormat,
+    pub status: BootstrapJobStatus,
+    /// Pages in the export
+    pub total_pages: usize,
+    /// Coverage so far; final once the job completes
+    pub report: BootstrapReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Runs bootstrap jobs and tracks them
+pub struct BootstrapService {
+    ingestion: Arc<IngestionService>,
+    jobs: RwLock<HashMap<Uuid, BootstrapJob>>,
+    finished: RwLock<VecDeque<Uuid>>,
+}
+
+impl BootstrapService {
+    pub fn new(ingestion: Arc<IngestionService>) -> Self {
+        Self {
+            ingestion,
+            jobs: RwLock::new(HashMap::new()),
+            finished: RwLock::new(VecDeque::new()),
+        }
+    }
+
+    /// Parse `export` and ingest its pages in the background
+    pub fn submit(self: &Arc<Self>, tenant_id: &str, source: &str, export: &[u8]) -> Result<BootstrapJob> {
+        let export = WikiExport::parse(export).map_err(ingestion_error)?;
+        if export.pages.is_empty() {
+            return Err(ApiError::InvalidInput("Wiki export has no pages".to_string()));
+        }
+
+        let job = BootstrapJob {
+            id: Uuid::new_v4(),
+            tenant_id: tenant_id.to_string(),
+            source: source.to_string(),
+            format: export.format,
+            status: BootstrapJobStatus::Running,
+            total_pages: export.pages.len(),
+            report: BootstrapReport::default(),
+            error: None,
+            created_at: Utc::now(),
+            finished_at: None,
+        };
+        self.jobs.write().expect("bootstrap jobs poisoned").insert(job.id, job.clone());
+
+        let service = Arc::clone(self);
+        let id = job.id;
+        let (tenant_id, source) = (tenant_id.to_string(), source.to_string());
+        tokio::spawn(async move { service.run(id, &tenant_id, &source, export).await });
+        Ok(job)
+    }
+
+    /// Get a job, scoped to its tenant
+    pub fn get(&self, tenant_id: &str, id: Uuid) -> Option<BootstrapJob> {
+        self.jobs
+            .read()
+            .expect("bootstrap jobs poisoned")
+            .get(&id)
+            .filter(|job| job.tenant_id == tenant_id)
+            .cloned()
+    }
+
+    async fn run(&self, id: Uuid, tenant_id: &str, source: &str, export: WikiExport) {
+        let mut pipeline = match IngestionPipeline::with_defaults(PipelineConfig::default()) {
+            Ok(pipeline) => pipeline,
+            Err(e) => return self.finish(id, Some(e.to_string())),
+        };
+        pipeline.insert_stage(1, Arc::new(SummaryStage::new(Arc::new(ExtractiveSummarizer::new()))));
+        let bootstrap = CorpusBootstrap::new(Arc::new(pipeline), self.ingestion.sink(tenant_id, source));
+
+        let started = Instant::now();
+        let mut report = BootstrapReport::default();
+        for (done, page) in export.pages.iter().enumerate() {
+            bootstrap.ingest_page(page, source, &mut report).await;
+            if (done + 1) % PROGRESS_INTERVAL == 0 {
+                let progress = report.clone();
+                self.update(id, |job| job.report = progress);
+            }
+        }
+        report.finish(started.elapsed().as_millis() as u64);
+        info!(
+            "Bootstrap job {} ingested {} of {} pages ({} chunks, {} failed)",
+            id, report.ingested, report.pages, report.chunks, report.failed
+        );
+
+        self.update(id, |job| job.report = report);
+        self.finish(id, None);
+    }
+
+    fn update(&self, id: Uuid, f: impl FnOnce(&mut BootstrapJob)) {
+        if let Some(job) = self.jobs.write().expect("bootstrap jobs poisoned").get_mut(&id) {
+            f(job);
+        }
+    }
+
+    fn finish(&self, id: Uuid, error: Option<String>) {
+        let mut jobs = self.jobs.write().expect("bootstrap jobs poisoned");
+        let Some(job) = jobs.get_mut(&id) else {
+            return;
+        };
+        job.status = if error.is_some() { BootstrapJobStatus::Failed } else { BootstrapJobStatus::Completed };
+        job.error = error;
+        job.finished_at = Some(Utc::now());
+
+        let mut finished = self.finished.write().expect("bootstrap jobs poisoned");
+        finished.push_back(id);
+        while finished.len() > RETAINED_JOBS {
+            if let Some(old) = finished.pop_front() {
+                jobs.remove(&old);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_context::{ContextEngine, ContextEngineConfig, ContextEngineImpl, ContextFilter};
+    use std::time::Duration;
+
+    const ENTITIES: &str = r#"<hibernate-generic datetime="2026-09-30 10:00:00">
+<object class="Space" package="com.atlassian.confluence.spaces">
+<id name="id">1</id>
+<property name="name"><![CDATA[Support]]></property>
+</object>
+<object class="Page" package="com.atlassian.confluence.pages">
+<id name="id">10</id>
+<property name="title"><![CDATA[Refunds]]></property>
+<property name="space" class="Space" package="com.atlassian.confluence.spaces"><id name="id">1</id></property>
+</object>
+<object class="BodyContent" package="com.atlassian.confluence.core">
+<id name="id">20</id>
+<property name="body"><![CDATA[<p>Refunds are issued within five days. Partial refunds need approval.</p>]]></property>
+<property name="content" class="Page" package="com.atlassian.confluence.pages"><id name="id">10</id></property>
+</object>
+</hibernate-generic>"#;
+
+    async fn wait(service: &BootstrapService, id: Uuid) -> BootstrapJob {
+        for _ in 0..100 {
+            let job = service.get("acme", id).unwrap();
+            if job.status != BootstrapJobStatus::Running {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("bootstrap job {} did not finish", id);
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_from_confluence_export() {
+        let engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let service = Arc::new(BootstrapService::new(Arc::new(IngestionService::new(engine.clone()))));
+
+        let job = service.submit("acme", "confluence", ENTITIES.as_bytes()).unwrap();
+        assert_eq!((job.format, job.total_pages), (WikiFormat::Confluence, 1));
+        let job = wait(&service, job.id).await;
+        assert_eq!(job.status, BootstrapJobStatus::Completed);
+        assert_eq!((job.report.ingested, job.report.summarized), (1, 1));
+        assert_eq!(job.report.spaces[0].space, "Support");
+        assert!(service.get("other-tenant", job.id).is_none());
+
+        let stored = engine.list_matching(&ContextFilter::new().with_tag("wiki")).await.unwrap();
+        assert_eq!(stored.len(), job.report.chunks);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unknown_exports() {
+        let engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let service = Arc::new(BootstrapService::new(Arc::new(IngestionService::new(engine))));
+        assert!(matches!(
+            service.submit("acme", "wiki", b"not a wiki"),
+            Err(ApiError::InvalidInput(_))
+        ));
+    }
+}
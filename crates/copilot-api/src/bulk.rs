@@ -0,0 +1,322 @@
+//! Bulk context operations
+//!
+//! Deletes, retags, re-embeds or exports every context item matching a
+//! [`ContextFilter`] as a background job. The matching items are selected
+//! once when the job starts and then processed in batches, so clients can
+//! poll the job's progress instead of scripting one call per item.
+//! Exported items are kept with the job until it is evicted.
+
+use crate::error::{ApiError, Result};
+use chrono::{DateTime, Utc};
+use copilot_context::{ContextEngine, ContextFilter, MemoryItem};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Finished jobs retained for status queries and export downloads
+const RETAINED_JOBS: usize = 100;
+
+/// Items processed between progress updates
+const BATCH_SIZE: usize = 100;
+
+/// What to do with each matching item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum BulkOperation {
+    Delete,
+    Retag {
+        #[serde(default)]
+        add: Vec<String>,
+        #[serde(default)]
+        remove: Vec<String>,
+    },
+    /// Recompute token counts and compressed forms from the content
+    Reembed,
+    Export,
+}
+
+/// A bulk operation over the items matching a filter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkRequest {
+    #[serde(default)]
+    pub filter: ContextFilter,
+    #[serde(flatten)]
+    pub operation: BulkOperation,
+}
+
+impl BulkRequest {
+    fn validate(&self) -> Result<()> {
+        match &self.operation {
+            BulkOperation::Delete if self.filter.is_empty() => Err(ApiError::InvalidInput(
+                "Bulk delete needs a filter; clear the context to delete everything".to_string(),
+            )),
+            BulkOperation::Retag { add, remove } if add.is_empty() && remove.is_empty() => {
+                Err(ApiError::InvalidInput("Retag needs tags to add or remove".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// State of a bulk job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkJobStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// A bulk operation and its progress
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkJob {
+    pub id: Uuid,
+    pub tenant_id: String,
+    #[serde(flatten)]
+    pub request: BulkRequest,
+    pub status: BulkJobStatus,
+    /// Items the filter selected
+    pub matched: usize,
+    /// Items handled so far, including failures
+    pub processed: usize,
+    /// Items the operation failed on
+    pub failed: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl BulkJob {
+    /// Fraction of matched items handled, 1.0 once nothing is left
+    pub fn progress(&self) -> f64 {
+        if self.matched == 0 {
+            return if self.status == BulkJobStatus::Running { 0.0 } else { 1.0 };
+        }
+        self.processed as f64 / self.matched as f64
+    }
+}
+
+/// Runs bulk jobs against the context engine and tracks them
+pub struct BulkService {
+    engine: Arc<dyn ContextEngine>,
+    jobs: RwLock<HashMap<Uuid, BulkJob>>,
+    exports: RwLock<HashMap<Uuid, Vec<MemoryItem>>>,
+    finished: RwLock<VecDeque<Uuid>>,
+}
+
+impl BulkService {
+    pub fn new(engine: Arc<dyn ContextEngine>) -> Self {
+        Self {
+            engine,
+            jobs: RwLock::new(HashMap::new()),
+            exports: RwLock::new(HashMap::new()),
+            finished: RwLock::new(VecDeque::new()),
+        }
+    }
+
+    /// Validate `request` and start it in the background
+    pub fn submit(self: &Arc<Self>, tenant_id: &str, request: BulkRequest) -> Result<BulkJob> {
+        request.validate()?;
+
+        let job = BulkJob {
+            id: Uuid::new_v4(),
+            tenant_id: tenant_id.to_string(),
+            request,
+            status: BulkJobStatus::Running,
+            matched: 0,
+            processed: 0,
+            failed: 0,
+            error: None,
+            created_at: Utc::now(),
+            finished_at: None,
+        };
+        self.jobs.write().expect("bulk jobs poisoned").insert(job.id, job.clone());
+
+        let service = Arc::clone(self);
+        let id = job.id;
+        tokio::spawn(async move { service.run(id).await });
+        Ok(job)
+    }
+
+    /// Get a job, scoped to its tenant
+    pub fn get(&self, tenant_id: &str, id: Uuid) -> Option<BulkJob> {
+        self.jobs
+            .read()
+            .expect("bulk jobs poisoned")
+            .get(&id)
+            .filter(|job| job.tenant_id == tenant_id)
+            .cloned()
+    }
+
+    /// Items exported by a completed export job
+    pub fn export(&self, tenant_id: &str, id: Uuid) -> Option<Vec<MemoryItem>> {
+        self.get(tenant_id, id)?;
+        self.exports.read().expect("bulk exports poisoned").get(&id).cloned()
+    }
+
+    async fn run(&self, id: Uuid) {
+        let Some(request) = self.jobs.read().expect("bulk jobs poisoned").get(&id).map(|j| j.request.clone()) else {
+            return;
+        };
+
+        let items = match self.engine.list_matching(&request.filter).await {
+            Ok(items) => items,
+            Err(e) => return self.finish(id, Some(e.to_string())),
+        };
+        self.update(id, |job| job.matched = items.len());
+        info!("Bulk {:?} job {} matched {} items", request.operation, id, items.len());
+
+        if request.operation == BulkOperation::Export {
+            let count = items.len();
+            self.exports.write().expect("bulk exports poisoned").insert(id, items);
+            self.update(id, |job| job.processed = count);
+            return self.finish(id, None);
+        }
+
+        for batch in items.chunks(BATCH_SIZE) {
+            let mut failed = 0;
+            for item in batch {
+                let item_id = &item.metadata.id;
+                let outcome = match &request.operation {
+                    BulkOperation::Delete => self.engine.remove(item_id).await,
+                    BulkOperation::Retag { add, remove } => self.engine.retag(item_id, add, remove).await,
+                    BulkOperation::Reembed => self.engine.reindex(item_id).await,
+                    BulkOperation::Export => Ok(()),
+                };
+                if let Err(e) = outcome {
+                    warn!("Bulk job {} failed on item {}: {}", id, item_id, e);
+                    failed += 1;
+                }
+            }
+            self.update(id, |job| {
+                job.processed += batch.len();
+                job.failed += failed;
+            });
+            tokio::task::yield_now().await;
+        }
+        self.finish(id, None);
+    }
+
+    fn update(&self, id: Uuid, f: impl FnOnce(&mut BulkJob)) {
+        if let Some(job) = self.jobs.write().expect("bulk jobs poisoned").get_mut(&id) {
+            f(job);
+        }
+    }
+
+    fn finish(&self, id: Uuid, error: Option<String>) {
+        let mut jobs = self.jobs.write().expect("bulk jobs poisoned");
+        let Some(job) = jobs.get_mut(&id) else {
+            return;
+        };
+        job.status = if error.is_some() { BulkJobStatus::Failed } else { BulkJobStatus::Completed };
+        job.error = error;
+        job.finished_at = Some(Utc::now());
+
+        let mut finished = self.finished.write().expect("bulk jobs poisoned");
+        finished.push_back(id);
+        while finished.len() > RETAINED_JOBS {
+            if let Some(old) = finished.pop_front() {
+                jobs.remove(&old);
+                self.exports.write().expect("bulk exports poisoned").remove(&old);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_context::{ContextEngineConfig, ContextEngineImpl, MemoryMetadata};
+    use std::time::Duration;
+
+    async fn service() -> Arc<BulkService> {
+        let engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        for i in 0..5 {
+            let source = if i < 3 { "file:///repo/src" } else { "https://wiki" };
+            let metadata = MemoryMetadata::new("code", format!("{}/{}", source, i)).with_tags(vec!["draft".to_string()]);
+            engine.store(format!("item {}", i), metadata, 0.5).await.unwrap();
+        }
+        Arc::new(BulkService::new(engine))
+    }
+
+    async fn wait(service: &BulkService, id: Uuid) -> BulkJob {
+        for _ in 0..100 {
+            let job = service.get("acme", id).unwrap();
+            if job.status != BulkJobStatus::Running {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("bulk job {} did not finish", id);
+    }
+
+    fn request(filter: ContextFilter, operation: BulkOperation) -> BulkRequest {
+        BulkRequest { filter, operation }
+    }
+
+    #[tokio::test]
+    async fn test_retag_then_delete_by_filter() {
+        let service = service().await;
+        let repo = ContextFilter::new().with_source_prefix("file:///repo/");
+
+        let retag = BulkOperation::Retag {
+            add: vec!["reviewed".to_string()],
+            remove: vec!["draft".to_string()],
+        };
+        let job = service.submit("acme", request(repo.clone(), retag)).unwrap();
+        let job = wait(&service, job.id).await;
+        assert_eq!((job.matched, job.processed, job.failed), (3, 3, 0));
+        assert_eq!(job.progress(), 1.0);
+
+        let reviewed = ContextFilter::new().with_tag("reviewed");
+        assert_eq!(service.engine.list_matching(&reviewed).await.unwrap().len(), 3);
+
+        let job = service.submit("acme", request(reviewed, BulkOperation::Delete)).unwrap();
+        assert_eq!(wait(&service, job.id).await.processed, 3);
+        assert_eq!(service.engine.list_matching(&ContextFilter::new()).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_export_is_kept_with_the_job() {
+        let service = service().await;
+        let wiki = ContextFilter::new().with_source_prefix("https://");
+
+        let job = service.submit("acme", request(wiki, BulkOperation::Export)).unwrap();
+        assert_eq!(wait(&service, job.id).await.status, BulkJobStatus::Completed);
+
+        let items = service.export("acme", job.id).unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(service.export("other-tenant", job.id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unbounded_delete_and_empty_retag() {
+        let service = service().await;
+        assert!(matches!(
+            service.submit("acme", request(ContextFilter::new(), BulkOperation::Delete)),
+            Err(ApiError::InvalidInput(_))
+        ));
+        let retag = BulkOperation::Retag { add: vec![], remove: vec![] };
+        assert!(matches!(
+            service.submit("acme", request(ContextFilter::new(), retag)),
+            Err(ApiError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_request_deserialization() {
+        let request: BulkRequest = serde_json::from_str(
+            r#"{"operation": "retag", "add": ["a"], "filter": {"tags": ["b"]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            request.operation,
+            BulkOperation::Retag { add: vec!["a".to_string()], remove: vec![] }
+        );
+        assert_eq!(request.filter.tags, vec!["b"]);
+    }
+}
@@ -5,6 +5,7 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use copilot_workflow::ParameterError;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -35,6 +36,17 @@ pub enum ApiError {
     #[error("Rate limit exceeded")]
     RateLimitExceeded,
 
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    #[error("{message}")]
+    TokenBudgetExhausted {
+        message: String,
+        daily_tokens: usize,
+        available: usize,
+        resets_at: chrono::DateTime<chrono::Utc>,
+    },
+
     #[error("WebSocket error: {0}")]
     WebSocketError(String),
 
@@ -46,6 +58,12 @@ pub enum ApiError {
 
     #[error("Workflow error: {0}")]
     WorkflowError(String),
+
+    #[error(
+        "Invalid parameters: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    InvalidParameters(Vec<ParameterError>),
 }
 
 impl ApiError {
@@ -59,10 +77,13 @@ impl ApiError {
             ApiError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
             ApiError::RateLimitExceeded => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::QuotaExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::TokenBudgetExhausted { .. } => StatusCode::TOO_MANY_REQUESTS,
             ApiError::WebSocketError(_) => StatusCode::BAD_REQUEST,
             ApiError::GrpcError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::ConversationError(_) => StatusCode::BAD_REQUEST,
             ApiError::WorkflowError(_) => StatusCode::BAD_REQUEST,
+            ApiError::InvalidParameters(_) => StatusCode::UNPROCESSABLE_ENTITY,
         }
     }
 
@@ -76,10 +97,31 @@ impl ApiError {
             ApiError::InternalError(_) => "INTERNAL_ERROR",
             ApiError::ServiceUnavailable(_) => "SERVICE_UNAVAILABLE",
             ApiError::RateLimitExceeded => "RATE_LIMIT_EXCEEDED",
+            ApiError::QuotaExceeded(_) => "QUOTA_EXCEEDED",
+            ApiError::TokenBudgetExhausted { .. } => "TOKEN_BUDGET_EXHAUSTED",
             ApiError::WebSocketError(_) => "WEBSOCKET_ERROR",
             ApiError::GrpcError(_) => "GRPC_ERROR",
             ApiError::ConversationError(_) => "CONVERSATION_ERROR",
             ApiError::WorkflowError(_) => "WORKFLOW_ERROR",
+            ApiError::InvalidParameters(_) => "INVALID_PARAMETERS",
+        }
+    }
+
+    /// Structured details for the response body, e.g. the offending fields
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            ApiError::InvalidParameters(errors) => serde_json::to_value(errors).ok(),
+            ApiError::TokenBudgetExhausted {
+                daily_tokens,
+                available,
+                resets_at,
+                ..
+            } => Some(serde_json::json!({
+                "daily_tokens": daily_tokens,
+                "available": available,
+                "resets_at": resets_at,
+            })),
+            _ => None,
         }
     }
 }
@@ -102,7 +144,7 @@ impl IntoResponse for ApiError {
         let body = ErrorResponse {
             code: self.error_code().to_string(),
             message: self.to_string(),
-            details: None,
+            details: self.details(),
         };
 
         (status, Json(body)).into_response()
@@ -121,6 +163,52 @@ impl From<serde_json::Error> for ApiError {
     }
 }
 
+impl From<copilot_conversation::ConversationError> for ApiError {
+    fn from(err: copilot_conversation::ConversationError) -> Self {
+        use copilot_conversation::ConversationError as E;
+        match err {
+            E::SessionNotFound(_) | E::PersonaNotFound(_) => ApiError::NotFound(err.to_string()),
+            E::PreferenceNotFound(_) | E::ExperimentNotFound(_) => ApiError::NotFound(err.to_string()),
+            E::StreamNotFound(_) | E::StreamNotResumable(_) => ApiError::NotFound(err.to_string()),
+            E::InvalidPersona(_) | E::InvalidPreference(_) | E::InvalidExperiment(_) => {
+                ApiError::InvalidInput(err.to_string())
+            }
+            E::ToolNotAllowed { .. } | E::ToolNotAllowedInSession { .. } => {
+                ApiError::AuthorizationFailed(err.to_string())
+            }
+            E::Overloaded(_) | E::ModelUnavailable { .. } | E::ToolUnavailable(_) => {
+                ApiError::ServiceUnavailable(err.to_string())
+            }
+            E::ModelRateLimited { .. } => ApiError::RateLimitExceeded,
+            E::TokenBudgetExhausted {
+                daily_tokens,
+                available,
+                resets_at,
+                ..
+            } => ApiError::TokenBudgetExhausted {
+                message: err.to_string(),
+                daily_tokens,
+                available,
+                resets_at,
+            },
+            _ => ApiError::ConversationError(err.to_string()),
+        }
+    }
+}
+
+impl From<copilot_security::SecurityError> for ApiError {
+    fn from(err: copilot_security::SecurityError) -> Self {
+        match err.status_code() {
+            401 => ApiError::AuthenticationFailed(err.to_string()),
+            403 => ApiError::AuthorizationFailed(err.to_string()),
+            404 => ApiError::NotFound(err.to_string()),
+            429 => ApiError::RateLimitExceeded,
+            400 | 409 => ApiError::InvalidInput(err.to_string()),
+            _ => ApiError::InternalError(err.to_string()),
+        }
+    }
+}
+
 #[cfg(feature = "grpc")]
 impl From<tonic::Status> for ApiError {
     fn from(status: tonic::Status) -> Self {
@@ -148,6 +236,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_conversation_error_mapping() {
+        use copilot_conversation::ConversationError;
+
+        let err: ApiError = ConversationError::PersonaNotFound("x".into()).into();
+        assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+
+        let err: ApiError = ConversationError::ToolNotAllowed {
+            tool: "t".into(),
+            persona: "p".into(),
+        }
+        .into();
+        assert_eq!(err.status_code(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_invalid_parameters_list_the_fields() {
+        let err = ApiError::InvalidParameters(vec![
+            ParameterError::new("environment", "Missing required parameter: environment"),
+            ParameterError::new("replicas", "Parameter replicas must be a number"),
+        ]);
+        assert_eq!(err.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            err.to_string(),
+            "Invalid parameters: environment: Missing required parameter: environment; \
+             replicas: Parameter replicas must be a number"
+        );
+        assert_eq!(err.details().unwrap()[1]["field"], "replicas");
+    }
+
+    #[test]
+    fn test_exhausted_token_budget_says_when_it_resets() {
+        use copilot_conversation::ConversationError;
+
+        let resets_at = chrono::Utc::now() + chrono::Duration::hours(1);
+        let err: ApiError = ConversationError::TokenBudgetExhausted {
+            conversation: "s1".into(),
+            daily_tokens: 1000,
+            available: 3,
+            resets_at,
+        }
+        .into();
+        assert_eq!(err.status_code(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(err.error_code(), "TOKEN_BUDGET_EXHAUSTED");
+        let details = err.details().unwrap();
+        assert_eq!(details["available"], 3);
+        assert_eq!(details["resets_at"], serde_json::to_value(resets_at).unwrap());
+    }
+
     #[test]
     fn test_error_codes() {
         assert_eq!(
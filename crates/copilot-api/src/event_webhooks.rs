@@ -0,0 +1,286 @@
+//! Subsystem events delivered over the event webhooks
+//!
+//! Workflow executions, approval gates and sandbox managers publish their
+//! lifecycle events on broadcast channels; quota managers and SLA monitors
+//! call alert handlers. The `forward_*` functions subscribe to a subsystem
+//! and dispatch each event, converted to a [`WebhookEvent`], to the tenants'
+//! matching endpoints.
+
+use chrono::Utc;
+use copilot_e2b::sandbox::SandboxManager;
+use copilot_e2b::{SandboxEvent, SandboxEventKind};
+use copilot_observability::{SlaCheckResult, SlaMonitor};
+use copilot_tenant::{QuotaAlert, QuotaAlertLevel, QuotaManager};
+use copilot_webhook::{
+    ApprovalEventData, QuotaEventData, SandboxEventData, SlaEventData, WebhookDispatcher, WebhookEvent,
+    WebhookEventData, WebhookEventType, WorkflowEventData,
+};
+use copilot_workflow::{ApprovalRequest, WorkflowEngine, WorkflowEvent, WorkflowEventKind};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+/// Webhook event for a workflow lifecycle event; `None` for events only
+/// streamed to run watchers, such as steps starting or retrying
+pub fn workflow_event(event: &WorkflowEvent) -> Option<WebhookEvent> {
+    let event_type = match event.kind {
+        WorkflowEventKind::Started => WebhookEventType::WorkflowStarted,
+        WorkflowEventKind::StepCompleted => WebhookEventType::WorkflowStepCompleted,
+        WorkflowEventKind::StepFailed => WebhookEventType::WorkflowStepFailed,
+        WorkflowEventKind::Completed => WebhookEventType::WorkflowCompleted,
+        WorkflowEventKind::Failed => WebhookEventType::WorkflowFailed,
+        WorkflowEventKind::StepStarted | WorkflowEventKind::StepRetried | WorkflowEventKind::Cancelled => {
+            return None
+        }
+    };
+    let state = &event.state;
+    let error = match event.kind {
+        WorkflowEventKind::StepFailed => event
+            .step_id
+            .as_ref()
+            .and_then(|step| state.step_results.get(step))
+            .and_then(|result| result.error.clone()),
+        _ => state.error.clone(),
+    };
+
+    let webhook_event = WebhookEvent::new(
+        event_type,
+        WebhookEventData::Workflow(WorkflowEventData {
+            id: state.execution_id.clone(),
+            workflow_id: state.workflow_id.clone(),
+            name: event.name.clone(),
+            status: name_of(&state.status),
+            current_step: event.step_id.clone(),
+            started_at: state.started_at.unwrap_or_else(Utc::now),
+            completed_at: state.completed_at,
+            error,
+            output: None,
+        }),
+    );
+    Some(match &event.tenant_id {
+        Some(tenant_id) => webhook_event.with_tenant(tenant_id),
+        None => webhook_event,
+    })
+}
+
+/// Webhook event for an approval requested by a workflow step
+pub fn approval_event(request: &ApprovalRequest) -> WebhookEvent {
+    WebhookEvent::new(
+        WebhookEventType::WorkflowApprovalRequested,
+        WebhookEventData::Approval(ApprovalEventData {
+            id: request.id.clone(),
+            workflow_id: request.workflow_id.clone(),
+            step_id: request.step_id.clone(),
+            title: request.title.clone(),
+            description: request.description.clone(),
+            requester: request.requester.clone(),
+            group: request.policy.group.clone(),
+            timeout_secs: request.timeout_secs,
+            created_at: request.created_at,
+        }),
+    )
+}
+
+/// Webhook event for a sandbox created or failing to be created
+pub fn sandbox_event(event: &SandboxEvent) -> WebhookEvent {
+    let (event_type, status) = match event.kind {
+        SandboxEventKind::Created => (WebhookEventType::SandboxCreated, "running"),
+        SandboxEventKind::Failed => (WebhookEventType::SandboxFailed, "error"),
+    };
+    WebhookEvent::new(
+        event_type,
+        WebhookEventData::Sandbox(SandboxEventData {
+            id: event.sandbox_id.clone(),
+            template: event.template.to_string(),
+            status: status.to_string(),
+            error: event.error.clone(),
+            created_at: event.timestamp,
+        }),
+    )
+}
+
+/// Webhook event for a tenant's usage crossing a quota threshold
+pub fn quota_event(alert: &QuotaAlert) -> WebhookEvent {
+    let event_type = match alert.level {
+        QuotaAlertLevel::Warning => WebhookEventType::QuotaWarning,
+        QuotaAlertLevel::Exceeded => WebhookEventType::QuotaExceeded,
+    };
+    WebhookEvent::new(
+        event_type,
+        WebhookEventData::Quota(QuotaEventData {
+            tenant_id: alert.tenant_id.clone(),
+            quota_type: alert.usage.quota_type.as_str().to_string(),
+            current: alert.usage.current,
+            limit: alert.usage.limit,
+            percentage: alert.usage.percentage,
+            threshold: alert.threshold,
+        }),
+    )
+    .with_tenant(&alert.tenant_id)
+}
+
+/// Webhook event for an SLA check, when it found the target breached
+pub fn sla_event(result: &SlaCheckResult) -> Option<WebhookEvent> {
+    if !result.is_violation() {
+        return None;
+    }
+    Some(WebhookEvent::new(
+        WebhookEventType::SlaBreached,
+        WebhookEventData::Sla(SlaEventData {
+            metric: result.metric.as_str().to_string(),
+            target: result.target,
+            current: result.current,
+            status: result.status.as_str().to_string(),
+            message: result.message.clone(),
+            period_start: result.period_start,
+            period_end: result.period_end,
+        }),
+    ))
+}
+
+/// Deliver the engine's workflow events and its approval gate's requests
+pub fn forward_workflow_events(engine: &WorkflowEngine, dispatcher: Arc<WebhookDispatcher>) {
+    forward(engine.subscribe(), dispatcher.clone(), workflow_event);
+    forward(engine.approval_gate().subscribe(), dispatcher, |request| Some(approval_event(request)));
+}
+
+/// Deliver the manager's sandbox events
+pub fn forward_sandbox_events(manager: &SandboxManager, dispatcher: Arc<WebhookDispatcher>) {
+    forward(manager.subscribe(), dispatcher, |event| Some(sandbox_event(event)));
+}
+
+/// Deliver the manager's quota threshold crossings
+pub fn forward_quota_alerts(quotas: &QuotaManager, dispatcher: Arc<WebhookDispatcher>) {
+    quotas.on_alert(move |alert| dispatch(dispatcher.clone(), quota_event(alert)));
+}
+
+/// Deliver the monitor's SLA breaches
+pub fn forward_sla_breaches(monitor: &SlaMonitor, dispatcher: Arc<WebhookDispatcher>) {
+    monitor.on_alert(move |result| {
+        if let Some(event) = sla_event(result) {
+            dispatch(dispatcher.clone(), event);
+        }
+    });
+}
+
+/// Dispatch every event received on `events` that has a webhook event
+/// until the sender is dropped
+fn forward<T: Clone + Send + 'static>(
+    mut events: broadcast::Receiver<T>,
+    dispatcher: Arc<WebhookDispatcher>,
+    convert: fn(&T) -> Option<WebhookEvent>,
+) {
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    let Some(event) = convert(&event) else {
+                        continue;
+                    };
+                    if let Err(e) = dispatcher.dispatch(event).await {
+                        warn!(error = %e, "Failed to dispatch event webhook");
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    warn!(missed, "Event webhooks fell behind, events were dropped");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Dispatch from a synchronous alert handler
+fn dispatch(dispatcher: Arc<WebhookDispatcher>, event: WebhookEvent) {
+    match tokio::runtime::Handle::try_current() {
+        Ok(runtime) => {
+            runtime.spawn(async move {
+                if let Err(e) = dispatcher.dispatch(event).await {
+                    warn!(error = %e, "Failed to dispatch event webhook");
+                }
+            });
+        }
+        Err(_) => warn!(event_type = event.event_type.as_str(), "No runtime to dispatch event webhook on"),
+    }
+}
+
+/// Serialized name of a unit enum variant, e.g. `running`
+fn name_of<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_tenant::{Tenant, TenantTier};
+    use copilot_webhook::{RetryConfig, WebhookEndpoint};
+    use copilot_workflow::{StepAction, StepType, WorkflowDefinition, WorkflowStatus, WorkflowStep};
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_workflow_events_match_tenant_endpoints() {
+        let (tx, _rx) = mpsc::channel(16);
+        let dispatcher = WebhookDispatcher::new(tx, RetryConfig::default());
+        dispatcher.register_endpoint(
+            WebhookEndpoint::new("ops", "https://example.com/hook", "secret")
+                .with_tenant("tenant-1")
+                .with_events(vec![WebhookEventType::WorkflowStarted, WebhookEventType::WorkflowCompleted]),
+        );
+
+        let engine = WorkflowEngine::new();
+        let mut events = engine.subscribe();
+        let mut workflow = WorkflowDefinition::new("Nightly", "Nightly job").add_step(
+            WorkflowStep::new("wait", StepType::Action, StepAction::Wait { duration_secs: 0 }).with_id("wait"),
+        );
+        workflow.metadata.insert("tenant_id".to_string(), serde_json::json!("tenant-1"));
+        let execution_id = engine.execute_workflow(workflow).await.unwrap();
+
+        let started = workflow_event(&events.recv().await.unwrap()).unwrap();
+        assert_eq!(started.event_type, WebhookEventType::WorkflowStarted);
+        assert_eq!(dispatcher.matching_endpoints(&started).len(), 1);
+        assert!(workflow_event(&events.recv().await.unwrap()).is_none());
+        let step_completed = workflow_event(&events.recv().await.unwrap()).unwrap();
+        assert_eq!(step_completed.event_type, WebhookEventType::WorkflowStepCompleted);
+        assert!(dispatcher.matching_endpoints(&step_completed).is_empty());
+        let completed = workflow_event(&events.recv().await.unwrap()).unwrap();
+        assert_eq!(completed.event_type, WebhookEventType::WorkflowCompleted);
+        match &completed.data {
+            WebhookEventData::Workflow(data) => {
+                assert_eq!(data.id, execution_id);
+                assert_eq!(data.status, "completed");
+                assert!(data.completed_at.is_some());
+            }
+            other => panic!("unexpected data {:?}", other),
+        }
+        assert_eq!(engine.get_status(&execution_id).await.unwrap().status, WorkflowStatus::Completed);
+    }
+
+    #[test]
+    fn test_quota_alerts_become_tenant_events() {
+        let quotas = QuotaManager::new();
+        let tenant = Tenant::new("Acme", "acme", "owner", TenantTier::Free);
+        quotas.register_tenant(&tenant);
+        quotas.set_custom_limit(&tenant.id, copilot_tenant::QuotaType::ApiCalls, 10);
+
+        let alerts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = alerts.clone();
+        quotas.on_alert(move |alert| seen.lock().unwrap().push(quota_event(alert)));
+        quotas.increment(&tenant.id, copilot_tenant::QuotaType::ApiCalls, 10).unwrap();
+
+        let alerts = alerts.lock().unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].event_type, WebhookEventType::QuotaExceeded);
+        assert_eq!(alerts[0].tenant_id.as_deref(), Some(tenant.id.as_str()));
+        match &alerts[0].data {
+            WebhookEventData::Quota(data) => {
+                assert_eq!(data.quota_type, "api_calls");
+                assert_eq!(data.current, 10);
+            }
+            other => panic!("unexpected data {:?}", other),
+        }
+    }
+}
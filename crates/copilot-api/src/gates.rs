@@ -0,0 +1,660 @@
+//! CI gates for GitHub check runs
+//!
+//! A GitHub Actions job posts its check-run context together with a gate
+//! target: a registered workflow (by name) or a benchmark filter. The gate
+//! runs to completion and answers with a [`GateVerdict`] whose top-level
+//! fields (`name`, `head_sha`, `status`, `conclusion`, `output`, ...) follow
+//! GitHub's "create a check run" body, so the job can report it as a status
+//! check as-is. The individual results the conclusion was derived from are
+//! listed under `checks`.
+//!
+//! Gate workflows are either shared by every tenant or registered for one
+//! tenant, and a tenant only sees its own and the shared ones.
+
+use crate::error::{ApiError, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use copilot_workflow::{StepState, WorkflowDefinition, WorkflowEngine, WorkflowStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How often a running workflow is polled for completion
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Where a gate was requested from (a subset of the GitHub Actions context)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRunContext {
+    /// `owner/repo`
+    pub repository: String,
+    /// Commit the check run is reported on
+    pub head_sha: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pull_request: Option<u64>,
+    /// Actions run that requested the gate (`github.run_id`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<u64>,
+    /// Name of the check run; derived from the target when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check_name: Option<String>,
+    /// Link shown as "Details" on the check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details_url: Option<String>,
+}
+
+/// What a gate runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GateTarget {
+    /// A workflow registered with [`GateService::register_workflow`] or
+    /// [`GateService::register_tenant_workflow`]
+    Workflow { name: String },
+    /// Every benchmark whose ID starts with `filter`
+    Benchmarks {
+        filter: String,
+        /// Fail benchmarks that succeed but take longer than this
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_duration_ms: Option<u64>,
+    },
+}
+
+impl GateTarget {
+    fn default_check_name(&self) -> String {
+        match self {
+            GateTarget::Workflow { name } => format!("copilot/workflow/{}", name),
+            GateTarget::Benchmarks { filter, .. } if filter.is_empty() => {
+                "copilot/benchmarks".to_string()
+            }
+            GateTarget::Benchmarks { filter, .. } => format!("copilot/benchmarks/{}", filter),
+        }
+    }
+}
+
+/// Request to run a gate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GateRequest {
+    pub context: CheckRunContext,
+    pub target: GateTarget,
+    /// Give up after this many seconds (workflows only); defaults to the
+    /// workflow's own timeout, then the service default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Check-run conclusion, as GitHub names it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckConclusion {
+    Success,
+    Failure,
+    Neutral,
+    Skipped,
+    TimedOut,
+    ActionRequired,
+}
+
+impl CheckConclusion {
+    fn label(self) -> &'static str {
+        match self {
+            CheckConclusion::Success => "passed",
+            CheckConclusion::Failure => "failed",
+            CheckConclusion::Neutral => "neutral",
+            CheckConclusion::Skipped => "skipped",
+            CheckConclusion::TimedOut => "timed out",
+            CheckConclusion::ActionRequired => "waiting for approval",
+        }
+    }
+}
+
+/// One workflow step or benchmark that contributed to a verdict
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GateCheck {
+    pub name: String,
+    pub conclusion: CheckConclusion,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Error or threshold that decided a non-successful conclusion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Check-run `output` object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckOutput {
+    pub title: String,
+    /// Markdown
+    pub summary: String,
+    /// Markdown table of the individual checks
+    pub text: String,
+}
+
+/// Outcome of a gate, ready to be reported as a GitHub check run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GateVerdict {
+    pub name: String,
+    pub head_sha: String,
+    /// Always `completed`; the gate answers once it has finished
+    pub status: String,
+    pub conclusion: CheckConclusion,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details_url: Option<String>,
+    /// Gate run ID, for correlating the check run with server logs
+    pub external_id: String,
+    pub output: CheckOutput,
+    pub checks: Vec<GateCheck>,
+}
+
+/// Result of one benchmark target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkOutcome {
+    pub target_id: String,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Runs benchmark targets for benchmark gates
+#[async_trait]
+pub trait BenchmarkSuite: Send + Sync {
+    /// Run every target whose ID starts with `filter`
+    async fn run(&self, filter: &str) -> Vec<BenchmarkOutcome>;
+}
+
+/// Runs gates against registered workflows and the benchmark suite
+pub struct GateService {
+    engine: WorkflowEngine,
+    /// Keyed by owning tenant (`None` for shared workflows) and name
+    workflows: RwLock<HashMap<(Option<String>, String), WorkflowDefinition>>,
+    benchmarks: RwLock<Option<Arc<dyn BenchmarkSuite>>>,
+    default_timeout: Duration,
+}
+
+impl Default for GateService {
+    fn default() -> Self {
+        Self::new(WorkflowEngine::new())
+    }
+}
+
+impl GateService {
+    /// Create a gate service that executes workflows on `engine`
+    pub fn new(engine: WorkflowEngine) -> Self {
+        Self {
+            engine,
+            workflows: RwLock::new(HashMap::new()),
+            benchmarks: RwLock::new(None),
+            default_timeout: Duration::from_secs(600),
+        }
+    }
+
+    /// Time limit for workflows without their own timeout
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = timeout;
+        self
+    }
+
+    /// Make `definition` available to every tenant's gates under its name
+    pub fn register_workflow(&self, definition: WorkflowDefinition) {
+        self.workflows
+            .write()
+            .unwrap()
+            .insert((None, definition.name.clone()), definition);
+    }
+
+    /// Make `definition` available to `tenant_id`'s gates under its name,
+    /// in place of a shared workflow of the same name
+    pub fn register_tenant_workflow(&self, tenant_id: &str, definition: WorkflowDefinition) {
+        self.workflows.write().unwrap().insert(
+            (Some(tenant_id.to_string()), definition.name.clone()),
+            definition,
+        );
+    }
+
+    /// Names of the workflows `tenant_id`'s gates can run
+    pub fn workflow_names(&self, tenant_id: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .workflows
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|(owner, _)| owner.as_deref().map_or(true, |owner| owner == tenant_id))
+            .map(|(_, name)| name.clone())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// The tenant's own workflow called `name`, else the shared one
+    fn workflow(&self, tenant_id: &str, name: &str) -> Option<WorkflowDefinition> {
+        let workflows = self.workflows.read().unwrap();
+        workflows
+            .get(&(Some(tenant_id.to_string()), name.to_string()))
+            .or_else(|| workflows.get(&(None, name.to_string())))
+            .cloned()
+    }
+
+    /// Set the suite benchmark gates run against
+    pub fn set_benchmark_suite(&self, suite: Arc<dyn BenchmarkSuite>) {
+        *self.benchmarks.write().unwrap() = Some(suite);
+    }
+
+    /// Run the gate for `tenant_id` and wait for its verdict
+    pub async fn evaluate(&self, tenant_id: &str, request: GateRequest) -> Result<GateVerdict> {
+        if request.context.head_sha.trim().is_empty() {
+            return Err(ApiError::InvalidInput("head_sha is required".to_string()));
+        }
+
+        let started_at = Utc::now();
+        let (conclusion, checks, title) = match &request.target {
+            GateTarget::Workflow { name } => {
+                self.run_workflow(tenant_id, name, request.timeout_secs)
+                    .await?
+            }
+            GateTarget::Benchmarks {
+                filter,
+                max_duration_ms,
+            } => self.run_benchmarks(filter, *max_duration_ms).await?,
+        };
+
+        let verdict = GateVerdict {
+            name: request
+                .context
+                .check_name
+                .clone()
+                .unwrap_or_else(|| request.target.default_check_name()),
+            head_sha: request.context.head_sha.clone(),
+            status: "completed".to_string(),
+            conclusion,
+            started_at,
+            completed_at: Utc::now(),
+            details_url: request.context.details_url.clone(),
+            external_id: Uuid::new_v4().to_string(),
+            output: CheckOutput {
+                title,
+                summary: summary(&checks),
+                text: checks_table(&checks),
+            },
+            checks,
+        };
+        info!(
+            tenant_id = %tenant_id,
+            repository = %request.context.repository,
+            head_sha = %verdict.head_sha,
+            check = %verdict.name,
+            conclusion = ?verdict.conclusion,
+            "Gate finished"
+        );
+        Ok(verdict)
+    }
+
+    async fn run_workflow(
+        &self,
+        tenant_id: &str,
+        name: &str,
+        timeout_secs: Option<u64>,
+    ) -> Result<(CheckConclusion, Vec<GateCheck>, String)> {
+        let definition = self
+            .workflow(tenant_id, name)
+            .ok_or_else(|| ApiError::NotFound(format!("Gate workflow {}", name)))?;
+        let timeout = timeout_secs
+            .or(definition.timeout_secs)
+            .map(Duration::from_secs)
+            .unwrap_or(self.default_timeout);
+
+        let execution_id = self
+            .engine
+            .execute_workflow(definition.clone())
+            .await
+            .map_err(|e| ApiError::WorkflowError(e.to_string()))?;
+
+        let deadline = Instant::now() + timeout;
+        let (state, timed_out) = loop {
+            let state = self
+                .engine
+                .get_status(&execution_id)
+                .await
+                .map_err(|e| ApiError::WorkflowError(e.to_string()))?;
+            // A paused workflow waits on an approval, which can take longer
+            // than a CI job should block for
+            if state.is_terminal() || state.status == WorkflowStatus::Paused {
+                break (state, false);
+            }
+            if Instant::now() >= deadline {
+                if let Err(e) = self.engine.cancel_workflow(&execution_id).await {
+                    warn!(execution_id = %execution_id, error = %e, "Failed to cancel timed out gate workflow");
+                }
+                break (state, true);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        };
+
+        let checks: Vec<GateCheck> = definition
+            .steps
+            .iter()
+            .map(|step| {
+                let result = state.step_results.get(&step.id);
+                let conclusion = match result.map(|r| &r.state) {
+                    Some(StepState::Completed) => CheckConclusion::Success,
+                    Some(StepState::Failed) => CheckConclusion::Failure,
+                    Some(StepState::Skipped) => CheckConclusion::Skipped,
+                    Some(StepState::WaitingApproval) => CheckConclusion::ActionRequired,
+                    _ if timed_out => CheckConclusion::TimedOut,
+                    _ => CheckConclusion::Neutral,
+                };
+                GateCheck {
+                    name: step.name.clone(),
+                    conclusion,
+                    duration_ms: result.and_then(|r| {
+                        r.completed_at
+                            .map(|end| (end - r.started_at).num_milliseconds().max(0) as u64)
+                    }),
+                    message: result.and_then(|r| r.error.clone()),
+                }
+            })
+            .collect();
+
+        let (conclusion, outcome) = if timed_out {
+            (
+                CheckConclusion::TimedOut,
+                format!("timed out after {}s", timeout.as_secs()),
+            )
+        } else {
+            match state.status {
+                WorkflowStatus::Completed => (CheckConclusion::Success, "passed".to_string()),
+                WorkflowStatus::Paused => (
+                    CheckConclusion::ActionRequired,
+                    "is waiting for approval".to_string(),
+                ),
+                WorkflowStatus::Cancelled => {
+                    (CheckConclusion::Failure, "was cancelled".to_string())
+                }
+                _ => (CheckConclusion::Failure, "failed".to_string()),
+            }
+        };
+        Ok((conclusion, checks, format!("Workflow {} {}", name, outcome)))
+    }
+
+    async fn run_benchmarks(
+        &self,
+        filter: &str,
+        max_duration_ms: Option<u64>,
+    ) -> Result<(CheckConclusion, Vec<GateCheck>, String)> {
+        let suite = self.benchmarks.read().unwrap().clone().ok_or_else(|| {
+            ApiError::ServiceUnavailable("No benchmark suite configured".to_string())
+        })?;
+
+        let checks: Vec<GateCheck> = suite
+            .run(filter)
+            .await
+            .into_iter()
+            .map(|outcome| {
+                let too_slow = match (outcome.duration_ms, max_duration_ms) {
+                    (Some(duration), Some(max)) if duration > max => {
+                        Some(format!("took {}ms, limit is {}ms", duration, max))
+                    }
+                    _ => None,
+                };
+                let (conclusion, message) = match (outcome.success, too_slow) {
+                    (false, _) => (CheckConclusion::Failure, outcome.error),
+                    (true, Some(too_slow)) => (CheckConclusion::Failure, Some(too_slow)),
+                    (true, None) => (CheckConclusion::Success, None),
+                };
+                GateCheck {
+                    name: outcome.target_id,
+                    conclusion,
+                    duration_ms: outcome.duration_ms,
+                    message,
+                }
+            })
+            .collect();
+
+        if checks.is_empty() {
+            return Ok((
+                CheckConclusion::Neutral,
+                checks,
+                format!("No benchmarks match \"{}\"", filter),
+            ));
+        }
+        let passed = checks
+            .iter()
+            .filter(|c| c.conclusion == CheckConclusion::Success)
+            .count();
+        let conclusion = if passed == checks.len() {
+            CheckConclusion::Success
+        } else {
+            CheckConclusion::Failure
+        };
+        let title = format!("{} of {} benchmarks passed", passed, checks.len());
+        Ok((conclusion, checks, title))
+    }
+}
+
+/// One line per conclusion, e.g. "**3** passed, **1** failed"
+fn summary(checks: &[GateCheck]) -> String {
+    let mut counts: Vec<(CheckConclusion, usize)> = Vec::new();
+    for check in checks {
+        match counts.iter_mut().find(|(c, _)| *c == check.conclusion) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((check.conclusion, 1)),
+        }
+    }
+    if counts.is_empty() {
+        return "Nothing was run.".to_string();
+    }
+    counts
+        .iter()
+        .map(|(conclusion, n)| format!("**{}** {}", n, conclusion.label()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn checks_table(checks: &[GateCheck]) -> String {
+    let mut text = String::from("| Check | Result | Duration | Details |\n|---|---|---|---|\n");
+    for check in checks {
+        let duration = check
+            .duration_ms
+            .map(|ms| format!("{}ms", ms))
+            .unwrap_or_default();
+        let details = check
+            .message
+            .as_deref()
+            .unwrap_or("")
+            .replace('|', "\\|")
+            .replace('\n', " ");
+        let _ = writeln!(
+            text,
+            "| {} | {} | {} | {} |",
+            check.name,
+            check.conclusion.label(),
+            duration,
+            details
+        );
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_workflow::{StepAction, StepType, WorkflowStep};
+
+    fn context() -> CheckRunContext {
+        CheckRunContext {
+            repository: "acme/widgets".to_string(),
+            head_sha: "0123abc".to_string(),
+            head_branch: Some("main".to_string()),
+            pull_request: None,
+            run_id: Some(42),
+            check_name: None,
+            details_url: None,
+        }
+    }
+
+    struct FixedSuite(Vec<BenchmarkOutcome>);
+
+    #[async_trait]
+    impl BenchmarkSuite for FixedSuite {
+        async fn run(&self, filter: &str) -> Vec<BenchmarkOutcome> {
+            self.0
+                .iter()
+                .filter(|o| o.target_id.starts_with(filter))
+                .cloned()
+                .collect()
+        }
+    }
+
+    fn outcome(id: &str, success: bool, duration_ms: u64) -> BenchmarkOutcome {
+        BenchmarkOutcome {
+            target_id: id.to_string(),
+            success,
+            duration_ms: Some(duration_ms),
+            error: (!success).then(|| "assertion failed".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_workflow_gate_succeeds() {
+        let gates = GateService::default();
+        gates.register_workflow(
+            WorkflowDefinition::new("release-checks", "Pre-release checks").add_step(
+                WorkflowStep::new(
+                    "lint",
+                    StepType::Action,
+                    StepAction::Wait { duration_secs: 0 },
+                ),
+            ),
+        );
+
+        let verdict = gates
+            .evaluate(
+                "acme",
+                GateRequest {
+                    context: context(),
+                    target: GateTarget::Workflow {
+                        name: "release-checks".to_string(),
+                    },
+                    timeout_secs: Some(10),
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(verdict.conclusion, CheckConclusion::Success);
+        assert_eq!(verdict.name, "copilot/workflow/release-checks");
+        assert_eq!(verdict.head_sha, "0123abc");
+        assert_eq!(verdict.checks.len(), 1);
+        assert_eq!(verdict.checks[0].conclusion, CheckConclusion::Success);
+
+        let missing = gates
+            .evaluate(
+                "acme",
+                GateRequest {
+                    context: context(),
+                    target: GateTarget::Workflow {
+                        name: "nope".to_string(),
+                    },
+                    timeout_secs: None,
+                },
+            )
+            .await;
+        assert!(matches!(missing, Err(ApiError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_gate_applies_filter_and_threshold() {
+        let gates = GateService::default();
+        let request = |filter: &str| GateRequest {
+            context: CheckRunContext {
+                check_name: Some("perf".to_string()),
+                ..context()
+            },
+            target: GateTarget::Benchmarks {
+                filter: filter.to_string(),
+                max_duration_ms: Some(100),
+            },
+            timeout_secs: None,
+        };
+        assert!(matches!(
+            gates.evaluate("acme", request("nlp")).await,
+            Err(ApiError::ServiceUnavailable(_))
+        ));
+
+        gates.set_benchmark_suite(Arc::new(FixedSuite(vec![
+            outcome("nlp::intent", true, 20),
+            outcome("nlp::entities", true, 250),
+            outcome("context::search", false, 10),
+        ])));
+
+        let verdict = gates.evaluate("acme", request("nlp")).await.unwrap();
+        assert_eq!(verdict.name, "perf");
+        assert_eq!(verdict.conclusion, CheckConclusion::Failure);
+        assert_eq!(verdict.output.title, "1 of 2 benchmarks passed");
+        assert_eq!(verdict.output.summary, "**1** passed, **1** failed");
+        assert_eq!(
+            verdict.checks[1].message.as_deref(),
+            Some("took 250ms, limit is 100ms")
+        );
+
+        let verdict = gates.evaluate("acme", request("ingestion")).await.unwrap();
+        assert_eq!(verdict.conclusion, CheckConclusion::Neutral);
+
+        let json = serde_json::to_value(
+            gates
+                .evaluate("acme", request("nlp::intent"))
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["status"], "completed");
+        assert_eq!(json["conclusion"], "success");
+        assert!(json["output"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("| nlp::intent | passed | 20ms |"));
+    }
+
+    #[tokio::test]
+    async fn test_tenant_workflows_are_scoped() {
+        let gates = GateService::default();
+        let workflow = |name: &str| {
+            WorkflowDefinition::new(name, "Checks").add_step(WorkflowStep::new(
+                "lint",
+                StepType::Action,
+                StepAction::Wait { duration_secs: 0 },
+            ))
+        };
+        gates.register_workflow(workflow("shared-checks"));
+        gates.register_tenant_workflow("acme", workflow("acme-checks"));
+
+        assert_eq!(
+            gates.workflow_names("acme"),
+            vec!["acme-checks", "shared-checks"]
+        );
+        assert_eq!(gates.workflow_names("globex"), vec!["shared-checks"]);
+
+        let request = || GateRequest {
+            context: context(),
+            target: GateTarget::Workflow {
+                name: "acme-checks".to_string(),
+            },
+            timeout_secs: Some(10),
+        };
+        let verdict = gates.evaluate("acme", request()).await.unwrap();
+        assert_eq!(verdict.conclusion, CheckConclusion::Success);
+        assert!(matches!(
+            gates.evaluate("globex", request()).await,
+            Err(ApiError::NotFound(_))
+        ));
+    }
+}
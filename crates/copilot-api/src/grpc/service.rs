@@ -62,10 +62,13 @@ impl CoPilotServiceImpl {
             ApiError::InternalError(msg) => Status::internal(msg),
             ApiError::ServiceUnavailable(msg) => Status::unavailable(msg),
             ApiError::RateLimitExceeded => Status::resource_exhausted("Rate limit exceeded"),
+            ApiError::QuotaExceeded(msg) => Status::resource_exhausted(msg),
+            ApiError::TokenBudgetExhausted { message, .. } => Status::resource_exhausted(message),
             ApiError::WebSocketError(msg) => Status::internal(msg),
             ApiError::GrpcError(msg) => Status::internal(msg),
             ApiError::ConversationError(msg) => Status::failed_precondition(msg),
             ApiError::WorkflowError(msg) => Status::failed_precondition(msg),
+            error @ ApiError::InvalidParameters(_) => Status::invalid_argument(error.to_string()),
         }
     }
 }
@@ -0,0 +1,476 @@
+//! Admin impersonation for troubleshooting
+//!
+//! A user grants consent for a limited time; while it lasts, an admin of
+//! the same tenant can start an impersonation by giving a reason. The admin
+//! receives a short-lived token for the user carrying an RFC 8693 `act`
+//! claim that names them, the reason and the impersonation. The token has
+//! no scopes of its own, so it can't reach admin endpoints, start another
+//! impersonation or grant consent.
+//!
+//! Every request made with the token is checked against the impersonation,
+//! so it stops working when the impersonation is ended or the consent is
+//! revoked, even before it expires. Each request is written to the audit
+//! log, and responses carry [`IMPERSONATED_BY_HEADER`] so clients can show
+//! that someone else is acting as the user.
+
+use crate::error::{ApiError, Result};
+use crate::types::Claims;
+use chrono::{DateTime, Duration, Utc};
+use copilot_security::{AuditEvent, AuditEventType, AuditLogger, AuditOutcome, SigningKeys, TracingAuditLogger};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+/// Response header naming the admin acting as the user
+pub const IMPERSONATED_BY_HEADER: &str = "x-impersonated-by";
+
+/// Response header with the ID of the impersonation a request was made in
+pub const IMPERSONATION_ID_HEADER: &str = "x-impersonation-id";
+
+/// Response header with the time an impersonation token expires (RFC 3339)
+pub const IMPERSONATION_EXPIRES_HEADER: &str = "x-impersonation-expires";
+
+/// Consent lasts this long unless the user asks for less
+const DEFAULT_CONSENT_HOURS: u32 = 24;
+const MAX_CONSENT_HOURS: u32 = 7 * 24;
+
+/// Impersonation tokens last this long unless the admin asks for less
+const DEFAULT_IMPERSONATION_MINUTES: u32 = 15;
+const MAX_IMPERSONATION_MINUTES: u32 = 60;
+
+/// The `act` claim of an impersonation token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Actor {
+    /// The admin acting as the token's subject
+    pub sub: String,
+    pub reason: String,
+    pub impersonation_id: Uuid,
+}
+
+/// A user's consent to be impersonated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpersonationConsent {
+    pub tenant_id: String,
+    pub user_id: String,
+    pub granted_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Consent request from the user being impersonated
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConsentRequest {
+    /// How long admins may impersonate the user; 24 hours by default, at
+    /// most a week
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hours: Option<u32>,
+}
+
+/// An admin's request to act as a user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpersonationRequest {
+    pub user_id: String,
+    /// Why the admin needs to act as the user; recorded with every action
+    pub reason: String,
+    /// Lifetime of the token; 15 minutes by default, at most an hour
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minutes: Option<u32>,
+}
+
+/// An impersonation of a user by an admin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Impersonation {
+    pub id: Uuid,
+    pub tenant_id: String,
+    pub admin: String,
+    pub user_id: String,
+    pub reason: String,
+    pub started_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Requests made with the impersonation token
+    pub actions: u64,
+}
+
+impl Impersonation {
+    fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.ended_at.is_none() && now < self.expires_at
+    }
+}
+
+/// A started impersonation and the token to act as the user with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpersonationToken {
+    pub token: String,
+    pub impersonation: Impersonation,
+}
+
+/// Claims of an impersonation token
+#[derive(Debug, Serialize)]
+struct ImpersonationClaims<'a> {
+    sub: &'a str,
+    tenant_id: &'a str,
+    exp: usize,
+    iat: usize,
+    act: &'a Actor,
+}
+
+/// Consents, impersonations and the audit trail of impersonated actions
+pub struct ImpersonationService {
+    consents: RwLock<HashMap<(String, String), ImpersonationConsent>>,
+    impersonations: RwLock<HashMap<Uuid, Impersonation>>,
+    audit: Arc<dyn AuditLogger>,
+}
+
+impl Default for ImpersonationService {
+    fn default() -> Self {
+        Self::new(Arc::new(TracingAuditLogger))
+    }
+}
+
+impl ImpersonationService {
+    /// Record impersonations and impersonated actions with `audit`
+    pub fn new(audit: Arc<dyn AuditLogger>) -> Self {
+        Self {
+            consents: RwLock::new(HashMap::new()),
+            impersonations: RwLock::new(HashMap::new()),
+            audit,
+        }
+    }
+
+    /// Let admins of the caller's tenant impersonate them
+    pub fn grant_consent(&self, claims: &Claims, req: &ConsentRequest) -> Result<ImpersonationConsent> {
+        if claims.impersonator().is_some() {
+            return Err(ApiError::AuthorizationFailed(
+                "Consent can't be granted while impersonated".to_string(),
+            ));
+        }
+        let hours = req.hours.unwrap_or(DEFAULT_CONSENT_HOURS);
+        if hours == 0 || hours > MAX_CONSENT_HOURS {
+            return Err(ApiError::InvalidInput(format!(
+                "Consent must last between 1 and {} hours",
+                MAX_CONSENT_HOURS
+            )));
+        }
+
+        let now = Utc::now();
+        let consent = ImpersonationConsent {
+            tenant_id: claims.tenant_id().to_string(),
+            user_id: claims.sub.clone(),
+            granted_at: now,
+            expires_at: now + Duration::hours(hours as i64),
+        };
+        self.consents
+            .write()
+            .expect("impersonation consents poisoned")
+            .insert((consent.tenant_id.clone(), consent.user_id.clone()), consent.clone());
+        Ok(consent)
+    }
+
+    /// The caller's active consent, if any
+    pub fn consent(&self, claims: &Claims) -> Option<ImpersonationConsent> {
+        self.active_consent(claims.tenant_id(), &claims.sub)
+    }
+
+    /// Withdraw the caller's consent, ending impersonations of them
+    pub async fn revoke_consent(&self, claims: &Claims) -> Result<bool> {
+        if claims.impersonator().is_some() {
+            return Err(ApiError::AuthorizationFailed(
+                "Consent can't be revoked while impersonated".to_string(),
+            ));
+        }
+        let key = (claims.tenant_id().to_string(), claims.sub.clone());
+        let revoked = self
+            .consents
+            .write()
+            .expect("impersonation consents poisoned")
+            .remove(&key)
+            .is_some();
+
+        let now = Utc::now();
+        let ended: Vec<Impersonation> = self
+            .impersonations
+            .write()
+            .expect("impersonations poisoned")
+            .values_mut()
+            .filter(|i| i.tenant_id == key.0 && i.user_id == key.1 && i.is_active(now))
+            .map(|i| {
+                i.ended_at = Some(now);
+                i.clone()
+            })
+            .collect();
+        for impersonation in &ended {
+            self.audit_ended(impersonation, &claims.sub, "consent revoked").await;
+        }
+        Ok(revoked)
+    }
+
+    fn active_consent(&self, tenant_id: &str, user_id: &str) -> Option<ImpersonationConsent> {
+        self.consents
+            .read()
+            .expect("impersonation consents poisoned")
+            .get(&(tenant_id.to_string(), user_id.to_string()))
+            .filter(|consent| Utc::now() < consent.expires_at)
+            .cloned()
+    }
+
+    /// Start impersonating a consenting user of the admin's tenant, signing
+    /// the token with the current key of `keys` if given, or else `secret`
+    pub async fn start(
+        &self,
+        claims: &Claims,
+        req: &ImpersonationRequest,
+        secret: &str,
+        keys: Option<&SigningKeys>,
+    ) -> Result<ImpersonationToken> {
+        claims.require_admin()?;
+        if claims.impersonator().is_some() {
+            return Err(ApiError::AuthorizationFailed(
+                "Impersonations can't be started while impersonating".to_string(),
+            ));
+        }
+        let reason = req.reason.trim();
+        if reason.is_empty() {
+            return Err(ApiError::InvalidInput("A reason is required to impersonate a user".to_string()));
+        }
+        if req.user_id == claims.sub {
+            return Err(ApiError::InvalidInput("Admins can't impersonate themselves".to_string()));
+        }
+        let minutes = req.minutes.unwrap_or(DEFAULT_IMPERSONATION_MINUTES);
+        if minutes == 0 || minutes > MAX_IMPERSONATION_MINUTES {
+            return Err(ApiError::InvalidInput(format!(
+                "Impersonations must last between 1 and {} minutes",
+                MAX_IMPERSONATION_MINUTES
+            )));
+        }
+
+        let tenant_id = claims.tenant_id();
+        let consent = self.active_consent(tenant_id, &req.user_id).ok_or_else(|| {
+            ApiError::AuthorizationFailed(format!("{} has not consented to be impersonated", req.user_id))
+        })?;
+
+        let now = Utc::now();
+        let impersonation = Impersonation {
+            id: Uuid::new_v4(),
+            tenant_id: tenant_id.to_string(),
+            admin: claims.sub.clone(),
+            user_id: req.user_id.clone(),
+            reason: reason.to_string(),
+            started_at: now,
+            // Never outlive the consent
+            expires_at: (now + Duration::minutes(minutes as i64)).min(consent.expires_at),
+            ended_at: None,
+            actions: 0,
+        };
+        let actor = Actor {
+            sub: impersonation.admin.clone(),
+            reason: impersonation.reason.clone(),
+            impersonation_id: impersonation.id,
+        };
+        let token_claims = ImpersonationClaims {
+            sub: &impersonation.user_id,
+            tenant_id: &impersonation.tenant_id,
+            exp: impersonation.expires_at.timestamp() as usize,
+            iat: now.timestamp() as usize,
+            act: &actor,
+        };
+        let token = match keys {
+            Some(keys) => keys.sign(&token_claims).map_err(|e| e.to_string()),
+            None => encode(&Header::default(), &token_claims, &EncodingKey::from_secret(secret.as_bytes()))
+                .map_err(|e| e.to_string()),
+        }
+        .map_err(|e| ApiError::InternalError(format!("Failed to sign impersonation token: {}", e)))?;
+
+        self.impersonations
+            .write()
+            .expect("impersonations poisoned")
+            .insert(impersonation.id, impersonation.clone());
+        self.audit
+            .log(
+                self.event(AuditEventType::ImpersonationStarted, "impersonate", &impersonation)
+                    .with_outcome(AuditOutcome::Success)
+                    .with_metadata("expires_at", impersonation.expires_at.to_rfc3339()),
+            )
+            .await;
+        Ok(ImpersonationToken { token, impersonation })
+    }
+
+    /// The impersonation a token's `act` claim names, if it is still active
+    pub fn check(&self, actor: &Actor) -> Result<Impersonation> {
+        let impersonations = self.impersonations.read().expect("impersonations poisoned");
+        let impersonation = impersonations
+            .get(&actor.impersonation_id)
+            .filter(|i| i.admin == actor.sub && i.is_active(Utc::now()))
+            .ok_or_else(|| ApiError::AuthenticationFailed("Impersonation has ended".to_string()))?;
+        Ok(impersonation.clone())
+    }
+
+    /// Audit a request made while impersonating
+    pub async fn record(
+        &self,
+        impersonation: &Impersonation,
+        method: &str,
+        path: &str,
+        status: u16,
+        request_id: Option<&str>,
+    ) {
+        if let Some(i) = self
+            .impersonations
+            .write()
+            .expect("impersonations poisoned")
+            .get_mut(&impersonation.id)
+        {
+            i.actions += 1;
+        }
+
+        let outcome = if status < 400 { AuditOutcome::Success } else { AuditOutcome::Failure };
+        let mut event = self
+            .event(AuditEventType::ImpersonatedAction, &format!("{} {}", method, path), impersonation)
+            .with_outcome(outcome)
+            .with_metadata("status", status);
+        if let Some(request_id) = request_id {
+            event = event.with_request_id(request_id);
+        }
+        self.audit.log(event).await;
+    }
+
+    /// Impersonations in a tenant, newest first
+    pub fn list(&self, tenant_id: &str) -> Vec<Impersonation> {
+        let mut impersonations: Vec<Impersonation> = self
+            .impersonations
+            .read()
+            .expect("impersonations poisoned")
+            .values()
+            .filter(|i| i.tenant_id == tenant_id)
+            .cloned()
+            .collect();
+        impersonations.sort_by_key(|i| std::cmp::Reverse(i.started_at));
+        impersonations
+    }
+
+    /// End an active impersonation in a tenant, invalidating its token
+    pub async fn end(&self, tenant_id: &str, id: Uuid, ended_by: &str) -> Option<Impersonation> {
+        let now = Utc::now();
+        let impersonation = {
+            let mut impersonations = self.impersonations.write().expect("impersonations poisoned");
+            let impersonation = impersonations
+                .get_mut(&id)
+                .filter(|i| i.tenant_id == tenant_id && i.is_active(now))?;
+            impersonation.ended_at = Some(now);
+            impersonation.clone()
+        };
+        self.audit_ended(&impersonation, ended_by, "ended").await;
+        Some(impersonation)
+    }
+
+    async fn audit_ended(&self, impersonation: &Impersonation, ended_by: &str, why: &str) {
+        self.audit
+            .log(
+                self.event(AuditEventType::ImpersonationEnded, "end_impersonation", impersonation)
+                    .with_outcome(AuditOutcome::Success)
+                    .with_metadata("ended_by", ended_by)
+                    .with_metadata("actions", impersonation.actions)
+                    .with_description(why),
+            )
+            .await;
+    }
+
+    fn event(&self, event_type: AuditEventType, action: &str, impersonation: &Impersonation) -> AuditEvent {
+        AuditEvent::new(event_type, action)
+            .with_actor(&impersonation.admin, "user")
+            .with_resource("user", &impersonation.user_id)
+            .with_tenant_id(&impersonation.tenant_id)
+            .with_metadata("impersonation_id", impersonation.id.to_string())
+            .with_metadata("reason", impersonation.reason.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rest::middleware::validate_token;
+    use copilot_security::InMemoryAuditLogger;
+
+    fn claims(sub: &str, scope: &str) -> Claims {
+        serde_json::from_value(serde_json::json!({
+            "sub": sub, "exp": 0, "iat": 0, "tenant_id": "acme", "scope": scope
+        }))
+        .unwrap()
+    }
+
+    fn request(reason: &str) -> ImpersonationRequest {
+        ImpersonationRequest {
+            user_id: "alice".to_string(),
+            reason: reason.to_string(),
+            minutes: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_impersonation_requires_consent_and_reason() {
+        let service = ImpersonationService::default();
+        let admin = claims("root", "admin");
+
+        assert!(matches!(
+            service.start(&admin, &request("ticket 42"), "secret", None).await,
+            Err(ApiError::AuthorizationFailed(_))
+        ));
+        service.grant_consent(&claims("alice", ""), &ConsentRequest::default()).unwrap();
+        assert!(matches!(
+            service.start(&admin, &request("  "), "secret", None).await,
+            Err(ApiError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            service.start(&claims("bob", ""), &request("ticket 42"), "secret", None).await,
+            Err(ApiError::AuthorizationFailed(_))
+        ));
+
+        let started = service.start(&admin, &request("ticket 42"), "secret", None).await.unwrap();
+        let token = validate_token(&started.token, "secret").unwrap();
+        assert_eq!((token.sub.as_str(), token.tenant_id()), ("alice", "acme"));
+        assert!(!token.has_scope("admin"));
+        let actor = token.impersonator().unwrap();
+        assert_eq!((actor.sub.as_str(), actor.reason.as_str()), ("root", "ticket 42"));
+
+        // Impersonation tokens can't be used to start another impersonation
+        assert!(service.start(&token, &request("again"), "secret", None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ending_impersonation_invalidates_token_and_is_audited() {
+        let audit = Arc::new(InMemoryAuditLogger::new());
+        let service = ImpersonationService::new(audit.clone());
+        service.grant_consent(&claims("alice", ""), &ConsentRequest::default()).unwrap();
+        let started = service.start(&claims("root", "admin"), &request("ticket 42"), "secret", None).await.unwrap();
+        let actor = validate_token(&started.token, "secret").unwrap().impersonator().unwrap();
+
+        let impersonation = service.check(&actor).unwrap();
+        service
+            .record(&impersonation, "GET", "/api/v1/sessions", 200, Some("req-1"))
+            .await;
+        assert_eq!(service.list("acme")[0].actions, 1);
+        assert!(service.list("other").is_empty());
+
+        // Revoking consent ends the impersonation
+        assert!(service.revoke_consent(&claims("alice", "")).await.unwrap());
+        assert!(service.check(&actor).is_err());
+        assert!(service.end("acme", impersonation.id, "root").await.is_none());
+
+        let events = audit.get_events().await;
+        let types: Vec<_> = events.iter().map(|e| e.event_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                AuditEventType::ImpersonationStarted,
+                AuditEventType::ImpersonatedAction,
+                AuditEventType::ImpersonationEnded,
+            ]
+        );
+        assert_eq!(events[1].actor_id.as_deref(), Some("root"));
+        assert_eq!(events[1].resource_id.as_deref(), Some("alice"));
+        assert_eq!(events[1].request_id.as_deref(), Some("req-1"));
+        assert_eq!(events[1].metadata["reason"], "ticket 42");
+    }
+}
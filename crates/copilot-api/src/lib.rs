@@ -4,6 +4,47 @@
 //! - REST API endpoints
 //! - WebSocket connections for real-time communication
 //! - gRPC services for high-performance RPC
+//! - A priority task queue for long-running agent jobs
+//! - Streaming document ingestion, with flagged content quarantined for review
+//!   and unsigned content labeled untrusted once trusted signers are set
+//! - Per-tenant concurrent ingestion limits and daily ingestion byte quotas
+//! - Bulk delete, retag, re-embed and export of context items by filter
+//! - One-step bootstrap of the knowledge base from a wiki export, with a
+//!   coverage report
+//! - Admin-editable authority weights of context sources
+//! - Embedding cache statistics and warmup
+//! - Context archived to object storage, warmed up back into memory on request
+//! - Grafana dashboards generated from natural language asks
+//! - Alert rules generated from natural language and back-tested on recent data
+//! - Log pattern clustering with model-written summaries
+//! - Webhook and email notifications when tasks and ingestion jobs finish
+//! - Tenant-managed webhook endpoints with secret rotation, test pings and
+//!   delivery statistics
+//! - Published webhook payload schemas, with endpoints pinnable to a version
+//! - Workflow, sandbox, quota and SLA events forwarded to the event webhooks
+//! - Workflow and benchmark gates reported as GitHub check runs
+//! - Listing workflow templates and running them with server-side parameter
+//!   validation
+//! - Step-by-step event streams of workflow runs
+//! - Pausing, resuming and previewing workflow schedules
+//! - Live server statistics for the `copilot top` dashboard
+//! - Per-tenant rate limit and quota headers on every response
+//! - Consented, time-boxed admin impersonation with every action audited
+//! - Presigned, content-addressed uploads and downloads of chat attachments
+//!   and sandbox artifacts, and export bundles kept in object storage
+//! - Compliance reports and access reviews for SOC 2 style audits
+//! - Context retrieval restricted by item and source ACLs, with denials
+//!   audited
+//! - Decisions of tenants' license and package policies on generated code
+//! - Query topics and a knowledge gaps report of questions answered with low
+//!   confidence or without sources
+//! - SCIM 2.0 provisioning of users and role groups, and bulk CSV import and
+//!   export of users
+//! - Email verification and password reset links mailed through the
+//!   notification subsystem
+//! - Tokens signed with rotating RS256/EdDSA keys, published as a JWKS
+//! - A reloadable table of the scopes each route requires, enforced by the
+//!   REST middleware and checked for uncovered routes at startup
 //!
 //! # Features
 //!
@@ -11,7 +52,22 @@
 //! - `websocket` - Enable WebSocket support (enabled by default)
 //! - `grpc` - Enable gRPC services (enabled by default)
 
+pub mod access;
+pub mod accounts;
+pub mod analytics;
+pub mod archive;
+pub mod bootstrap;
+pub mod bulk;
 pub mod error;
+pub mod event_webhooks;
+pub mod gates;
+pub mod impersonation;
+pub mod ingestion;
+pub mod limits;
+pub mod replay;
+pub mod route_policy;
+pub mod runs;
+pub mod schedules;
 
 #[cfg(feature = "rest")]
 pub mod rest;
@@ -22,18 +78,58 @@ pub mod websocket;
 #[cfg(feature = "grpc")]
 pub mod grpc;
 
+pub mod stats;
+pub mod tasks;
 pub mod types;
 
 // Re-export commonly used types
+pub use access::ContextAccessAudit;
+pub use accounts::EmailAccountMailer;
+pub use analytics::{ContextPipelineObserver, ConversationAnalyticsObserver, QueryAnalyticsObserver};
+pub use archive::ObjectArchiveStore;
+pub use bootstrap::{BootstrapJob, BootstrapJobStatus, BootstrapService};
+pub use bulk::{BulkJob, BulkJobStatus, BulkOperation, BulkRequest, BulkService};
 pub use error::{ApiError, Result};
+pub use gates::{
+    BenchmarkOutcome, BenchmarkSuite, CheckConclusion, CheckRunContext, GateRequest, GateService,
+    GateTarget, GateVerdict,
+};
+pub use impersonation::{
+    ConsentRequest, Impersonation, ImpersonationConsent, ImpersonationRequest, ImpersonationService,
+    ImpersonationToken,
+};
+pub use limits::ApiLimits;
+pub use replay::{CapturedResponse, ReplayCapture, ReplayRecorder, ReplaySummary};
+pub use route_policy::{RouteInfo, RoutePolicy, RoutePolicyConfig, RoutePolicyReport, RouteRule};
+pub use runs::{ManualRun, ManualRunRequest, ManualRunService, ParameterSchema, RunEvent, TemplateSummary};
+pub use schedules::{SchedulePreview, SchedulePreviewRequest, ScheduleService};
+pub use stats::{DashboardSnapshot, RecentError, ServerStats};
+pub use tasks::{
+    TaskEvent, TaskHandler, TaskInfo, TaskPriority, TaskQueue, TaskQueueConfig, TaskQueueStats,
+    TaskStatus,
+};
 pub use types::*;
 
 #[cfg(feature = "rest")]
 pub use rest::router::create_router;
 
 use std::sync::Arc;
-use copilot_core::CoPilotEngine;
-use copilot_conversation::ConversationManager;
+use std::time::Duration;
+use copilot_adapters::ObservatoryAdapter;
+use copilot_context::CachedEmbeddingProvider;
+use copilot_core::{CoPilotEngine, PromptLogPolicy, RegionRouter, ResidencyLog, ResidencyPolicy};
+use copilot_infra::{DependencyMonitor, LocalObjectStore, ObjectStorage};
+use copilot_conversation::{CodePolicyLog, CompositeAnswerObserver, ConversationManager};
+use copilot_ingestion::TrustedSigners;
+use copilot_nlp::{AlertRuleGenerator, DashboardGenerator};
+use copilot_observability::{ContextPipelineMetrics, ConversationAnalytics, QueryAnalytics};
+use copilot_security::{
+    AccountMailer, AuditLogger, AuthService, AuthServiceConfig, ComplianceReporter, CompositeAuditLogger,
+    InMemoryAuditLogger, InMemoryTokenBlacklist, InMemoryUserStore, JwtConfig, SigningKeys, TracingAuditLogger,
+    UserProvisioning, UserStore,
+};
+use copilot_webhook::{TaskNotifier, WebhookDispatcher};
+use ingestion::IngestionService;
 
 /// Application state shared across all API handlers
 #[derive(Clone)]
@@ -44,6 +140,135 @@ pub struct AppState {
     pub conversation_manager: Arc<ConversationManager>,
     /// JWT secret for authentication
     pub jwt_secret: String,
+    /// Rotating keys that sign the tokens this server issues and verify
+    /// tokens naming them, if configured; other tokens are checked against
+    /// the secret
+    pub signing_keys: Option<SigningKeys>,
+    /// Queue for long-running agent tasks
+    pub task_queue: TaskQueue,
+    /// Streaming document ingestion
+    pub ingestion: Arc<IngestionService>,
+    /// Bulk operations on context items
+    pub bulk: Arc<BulkService>,
+    /// Knowledge base bootstraps from wiki exports
+    pub bootstrap: Arc<BootstrapService>,
+    /// Request counters and application gauges
+    pub stats: Arc<ServerStats>,
+    /// Per-tenant retention of prompt and response text in server logs
+    pub prompt_logging: PromptLogPolicy,
+    /// Home regions of tenants' data
+    pub residency: ResidencyPolicy,
+    /// Cross-region access refused by the residency policy
+    pub residency_log: Arc<ResidencyLog>,
+    /// Responses annotated or blocked by tenants' code policies, if enabled
+    pub code_policy_log: Option<Arc<CodePolicyLog>>,
+    /// Completion and failure notifications, if configured
+    pub notifier: Option<Arc<TaskNotifier>>,
+    /// CI gates for GitHub check runs
+    pub gates: Arc<GateService>,
+    /// Per-tenant rate limits and quotas
+    pub limits: Arc<ApiLimits>,
+    /// Manual runs of workflow templates
+    pub runs: Arc<ManualRunService>,
+    /// Workflow schedules
+    pub schedules: Arc<ScheduleService>,
+    /// Grafana dashboard generation from natural language
+    pub dashboard_generator: Arc<DashboardGenerator>,
+    /// Alert rule generation from natural language
+    pub alert_generator: Arc<AlertRuleGenerator>,
+    /// Observability stack alert rules are back-tested against and
+    /// dashboards are pushed to, if configured
+    pub observatory: Option<Arc<dyn ObservatoryAdapter>>,
+    /// Incident channels and ticket systems conversations are handed off
+    /// to, if configured
+    pub handoff_webhooks: Option<Arc<WebhookDispatcher>>,
+    /// Event webhooks tenants manage through the API, if configured
+    pub webhooks: Option<Arc<WebhookDispatcher>>,
+    /// Embedding cache shared with ingestion and query-time search, if
+    /// configured
+    pub embedding_cache: Option<Arc<CachedEmbeddingProvider>>,
+    /// Admin impersonation of consenting users
+    pub impersonation: Arc<ImpersonationService>,
+    /// Audit trail of security-relevant actions
+    pub audit: Arc<dyn AuditLogger>,
+    /// Compliance reports built from the audit trail and access reviews
+    pub compliance: Arc<ComplianceReporter>,
+    /// Object storage for attachments, sandbox artifacts and export
+    /// bundles by region, if configured
+    pub object_storage: RegionRouter<ObjectStorage>,
+    /// Local object store whose presigned URLs this server answers, if
+    /// objects are kept on local disk
+    pub local_objects: Option<Arc<LocalObjectStore>>,
+    /// Backing services probed for readiness, if any are configured
+    pub dependencies: Option<Arc<DependencyMonitor>>,
+    /// Deadlines given to API requests
+    pub request_timeouts: RequestTimeouts,
+    /// Scopes required by routes, checked before their handlers run
+    pub route_policy: RoutePolicy,
+    /// Capture of sampled chat turns for replay, if enabled
+    pub replays: Option<Arc<ReplayRecorder>>,
+    /// Questions asked and how well they were answered
+    pub query_analytics: Arc<QueryAnalytics>,
+    /// Intents, resolution and escalation of conversations
+    pub conversation_analytics: Arc<ConversationAnalytics>,
+    /// Latency, candidates and tokens saved per context pipeline stage
+    pub context_pipeline: Arc<ContextPipelineMetrics>,
+    /// SCIM and CSV provisioning of the platform's users
+    pub users: Arc<UserProvisioning>,
+    /// Email verification and password resets of the provisioned users
+    pub accounts: Arc<AuthService>,
+}
+
+/// Account flows of the users in `store`, with tokens signed by a key
+/// derived from the JWT secret
+fn account_service(
+    jwt_secret: &str,
+    store: Arc<dyn UserStore>,
+    audit: Arc<dyn AuditLogger>,
+    mailer: Option<Arc<dyn AccountMailer>>,
+) -> Arc<AuthService> {
+    let config = AuthServiceConfig {
+        jwt_config: JwtConfig {
+            secret: jwt_secret.to_string(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let service = AuthService::new(config, store, Arc::new(InMemoryTokenBlacklist::new()), audit)
+        .expect("default password policy is valid");
+    Arc::new(match mailer {
+        Some(mailer) => service.with_mailer(mailer),
+        None => service,
+    })
+}
+
+/// Audit events kept queryable for compliance reports by default
+pub const DEFAULT_AUDIT_CAPACITY: usize = 100_000;
+
+/// How long an API request may take before retrieval, reranking, model and
+/// adapter calls start cutting their work short
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestTimeouts {
+    /// Deadline of requests that do not ask for one
+    pub default: Duration,
+    /// Longest deadline a client may ask for with `X-Request-Timeout`
+    pub max: Duration,
+}
+
+impl Default for RequestTimeouts {
+    fn default() -> Self {
+        Self {
+            default: Duration::from_secs(30),
+            max: Duration::from_secs(120),
+        }
+    }
+}
+
+impl RequestTimeouts {
+    /// Deadline for a request that asked for `requested`
+    pub fn budget(&self, requested: Option<Duration>) -> Duration {
+        requested.map_or(self.default, |requested| requested.min(self.max))
+    }
 }
 
 impl AppState {
@@ -53,11 +278,314 @@ impl AppState {
         conversation_manager: Arc<ConversationManager>,
         jwt_secret: String,
     ) -> Self {
-        Self {
+        let task_queue = TaskQueue::default();
+        let handler = Arc::new(tasks::ConversationTaskHandler::new(conversation_manager.clone()));
+        task_queue.register_handler("code_generation", handler.clone());
+        task_queue.register_handler("analysis", handler);
+        let ingestion = Arc::new(IngestionService::new(conversation_manager.context_engine()));
+        let bulk = Arc::new(BulkService::new(conversation_manager.context_engine()));
+        let bootstrap = Arc::new(BootstrapService::new(ingestion.clone()));
+        let audit: Arc<dyn AuditLogger> = Arc::new(
+            CompositeAuditLogger::new()
+                .add_logger(TracingAuditLogger)
+                .add_logger(InMemoryAuditLogger::new().with_capacity(DEFAULT_AUDIT_CAPACITY)),
+        );
+        let query_analytics = Arc::new(QueryAnalytics::new());
+        let users: Arc<dyn UserStore> = Arc::new(InMemoryUserStore::new());
+        let accounts = account_service(&jwt_secret, users.clone(), audit.clone(), None);
+
+        let state = Self {
             engine,
             conversation_manager,
             jwt_secret,
-        }
+            signing_keys: None,
+            task_queue,
+            ingestion,
+            bulk,
+            bootstrap,
+            stats: Arc::new(ServerStats::new()),
+            prompt_logging: PromptLogPolicy::default(),
+            residency: ResidencyPolicy::default(),
+            residency_log: Arc::new(ResidencyLog::new()),
+            code_policy_log: None,
+            notifier: None,
+            gates: Arc::new(GateService::default()),
+            limits: Arc::new(ApiLimits::default()),
+            runs: Arc::new(ManualRunService::default()),
+            schedules: Arc::new(ScheduleService::default()),
+            dashboard_generator: Arc::new(DashboardGenerator::new()),
+            alert_generator: Arc::new(AlertRuleGenerator::new()),
+            observatory: None,
+            handoff_webhooks: None,
+            webhooks: None,
+            embedding_cache: None,
+            impersonation: Arc::new(ImpersonationService::new(audit.clone())),
+            compliance: Arc::new(ComplianceReporter::new(audit.clone())),
+            users: Arc::new(UserProvisioning::new(users, audit.clone())),
+            accounts,
+            audit,
+            object_storage: RegionRouter::new(),
+            local_objects: None,
+            dependencies: None,
+            request_timeouts: RequestTimeouts::default(),
+            route_policy: RoutePolicy::default(),
+            replays: None,
+            query_analytics,
+            conversation_analytics: Arc::new(ConversationAnalytics::new()),
+            context_pipeline: Arc::new(ContextPipelineMetrics::new()),
+        };
+        state.observe_answers();
+        state.observe_context_stages();
+        state
+    }
+
+    /// Record the outcome of the conversation manager's answers in the
+    /// query and conversation analytics, under the current prompt logging
+    /// policy
+    fn observe_answers(&self) {
+        let observer = CompositeAnswerObserver::new()
+            .add_observer(Arc::new(QueryAnalyticsObserver::new(
+                self.query_analytics.clone(),
+                self.prompt_logging.clone(),
+            )))
+            .add_observer(Arc::new(ConversationAnalyticsObserver::new(
+                self.conversation_analytics.clone(),
+                self.prompt_logging.clone(),
+            )));
+        self.conversation_manager.set_answer_observer(Arc::new(observer));
+    }
+
+    /// Record the stages of the conversation manager's context pipeline in
+    /// the context pipeline metrics
+    fn observe_context_stages(&self) {
+        let observer = ContextPipelineObserver::new(self.context_pipeline.clone());
+        self.conversation_manager.set_stage_observer(Arc::new(observer));
+    }
+
+    /// Sign tokens with the current key of `keys` and publish the keys at
+    /// `/.well-known/jwks.json`
+    pub fn with_signing_keys(mut self, keys: SigningKeys) -> Self {
+        self.signing_keys = Some(keys);
+        self
+    }
+
+    /// Replace the task queue (e.g. to apply custom concurrency limits)
+    pub fn with_task_queue(mut self, task_queue: TaskQueue) -> Self {
+        self.task_queue = task_queue;
+        self
+    }
+
+    /// Share server statistics with the embedding application, which can
+    /// publish its own gauges through them
+    pub fn with_stats(mut self, stats: Arc<ServerStats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Replace the prompt logging policy (e.g. to honour tenant privacy settings)
+    pub fn with_prompt_logging(mut self, prompt_logging: PromptLogPolicy) -> Self {
+        self.prompt_logging = prompt_logging;
+        self.observe_answers();
+        self
+    }
+
+    /// Record answered questions in `analytics`, e.g. one shared with a
+    /// dashboard service
+    pub fn with_query_analytics(mut self, analytics: Arc<QueryAnalytics>) -> Self {
+        self.query_analytics = analytics;
+        self.observe_answers();
+        self
+    }
+
+    /// Follow conversations in `analytics`, e.g. one shared with a
+    /// dashboard service
+    pub fn with_conversation_analytics(mut self, analytics: Arc<ConversationAnalytics>) -> Self {
+        self.conversation_analytics = analytics;
+        self.observe_answers();
+        self
+    }
+
+    /// Keep tenants' data in their home regions
+    pub fn with_residency(mut self, residency: ResidencyPolicy) -> Self {
+        self.ingestion.set_residency(residency.clone());
+        self.residency = residency;
+        self
+    }
+
+    /// Record residency violations in `log`, e.g. the one the context
+    /// engine's residency fence records to
+    pub fn with_residency_log(mut self, log: Arc<ResidencyLog>) -> Self {
+        self.residency_log = log;
+        self
+    }
+
+    /// Report the decisions the conversation manager's code policy stage
+    /// records in `log`
+    pub fn with_code_policy_log(mut self, log: Arc<CodePolicyLog>) -> Self {
+        self.code_policy_log = Some(log);
+        self
+    }
+
+    /// Notify subscribers when agent tasks and ingestion jobs finish
+    pub fn with_notifier(mut self, notifier: Arc<TaskNotifier>) -> Self {
+        self.task_queue.set_notifier(notifier.clone());
+        self.ingestion.set_notifier(notifier.clone());
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Check uploads' signatures against `signers` and label their chunks
+    /// verified or untrusted
+    pub fn with_trusted_signers(self, signers: TrustedSigners) -> Self {
+        self.ingestion.set_signers(signers);
+        self
+    }
+
+    /// Replace the gate service (e.g. to share the host's workflow engine)
+    pub fn with_gates(mut self, gates: Arc<GateService>) -> Self {
+        self.gates = gates;
+        self
+    }
+
+    /// Replace the manual run service (e.g. to share the host's templates
+    /// and workflow engine)
+    pub fn with_runs(mut self, runs: Arc<ManualRunService>) -> Self {
+        self.runs = runs;
+        self
+    }
+
+    /// Replace the schedule service (e.g. to manage the host's running
+    /// scheduler)
+    pub fn with_schedules(mut self, schedules: Arc<ScheduleService>) -> Self {
+        self.schedules = schedules;
+        self
+    }
+
+    /// Replace the dashboard generator (e.g. to recognize the deployment's
+    /// services or target its Prometheus datasource)
+    pub fn with_dashboard_generator(mut self, generator: DashboardGenerator) -> Self {
+        self.dashboard_generator = Arc::new(generator);
+        self
+    }
+
+    /// Replace the alert rule generator (e.g. to recognize the deployment's
+    /// services)
+    pub fn with_alert_generator(mut self, generator: AlertRuleGenerator) -> Self {
+        self.alert_generator = Arc::new(generator);
+        self
+    }
+
+    /// Back-test alert rules and push generated dashboards through `observatory`
+    pub fn with_observatory(mut self, observatory: Arc<dyn ObservatoryAdapter>) -> Self {
+        self.observatory = Some(observatory);
+        self
+    }
+
+    /// Post conversation handoffs to the endpoints registered on `dispatcher`
+    pub fn with_handoff_webhooks(mut self, dispatcher: Arc<WebhookDispatcher>) -> Self {
+        self.handoff_webhooks = Some(dispatcher);
+        self
+    }
+
+    /// Let tenants manage their event webhook endpoints on `dispatcher`
+    pub fn with_webhooks(mut self, dispatcher: Arc<WebhookDispatcher>) -> Self {
+        self.webhooks = Some(dispatcher);
+        self
+    }
+
+    /// Report and warm the embedding cache the host shares between
+    /// ingestion and queries
+    pub fn with_embedding_cache(mut self, cache: Arc<CachedEmbeddingProvider>) -> Self {
+        self.embedding_cache = Some(cache);
+        self
+    }
+
+    /// Replace the impersonation service (e.g. to send its audit trail to
+    /// the host's audit logger)
+    pub fn with_impersonation(mut self, impersonation: Arc<ImpersonationService>) -> Self {
+        self.impersonation = impersonation;
+        self
+    }
+
+    /// Write the audit trail, including impersonations, to `audit`;
+    /// compliance reports are built from what `audit` can query
+    pub fn with_audit_logger(mut self, audit: Arc<dyn AuditLogger>) -> Self {
+        let reviews = self.compliance.access_reviews().clone();
+        self.impersonation = Arc::new(ImpersonationService::new(audit.clone()));
+        self.compliance = Arc::new(ComplianceReporter::new(audit.clone()).with_access_reviews(reviews));
+        self.users = Arc::new(UserProvisioning::new(self.users.store().clone(), audit.clone()));
+        self.audit = audit;
+        self.rebuild_accounts()
+    }
+
+    /// Provision users into `store`, e.g. the host's user database
+    pub fn with_user_store(mut self, store: Arc<dyn UserStore>) -> Self {
+        self.users = Arc::new(UserProvisioning::new(store, self.audit.clone()));
+        self.rebuild_accounts()
+    }
+
+    /// Mail email verification and password reset links with `mailer`;
+    /// without one, users can't ask for either
+    pub fn with_account_mailer(mut self, mailer: Arc<dyn AccountMailer>) -> Self {
+        self.accounts = account_service(&self.jwt_secret, self.users.store().clone(), self.audit.clone(), Some(mailer));
+        self
+    }
+
+    /// Rebuild the account flows on the current user store and audit logger
+    fn rebuild_accounts(mut self) -> Self {
+        let mailer = self.accounts.mailer().cloned();
+        self.accounts = account_service(&self.jwt_secret, self.users.store().clone(), self.audit.clone(), mailer);
+        self
+    }
+
+    /// Keep attachments, sandbox artifacts and export bundles in `storage`
+    pub fn with_object_storage(mut self, storage: Arc<ObjectStorage>) -> Self {
+        self.object_storage = self.object_storage.with_default(storage);
+        self
+    }
+
+    /// Keep the objects of tenants homed in `region` in `storage`
+    pub fn with_regional_object_storage(mut self, region: &str, storage: Arc<ObjectStorage>) -> Self {
+        self.object_storage = self.object_storage.with_region(region, storage);
+        self
+    }
+
+    /// Serve the presigned URLs of `store` under `/objects`; needed when
+    /// the object storage is a local directory
+    pub fn with_local_objects(mut self, store: Arc<LocalObjectStore>) -> Self {
+        self.local_objects = Some(store);
+        self
+    }
+
+    /// Report the availability of `monitor`'s dependencies from the
+    /// readiness check
+    pub fn with_dependencies(mut self, monitor: Arc<DependencyMonitor>) -> Self {
+        self.dependencies = Some(monitor);
+        self
+    }
+
+    /// Replace the default and maximum request deadlines
+    pub fn with_request_timeouts(mut self, timeouts: RequestTimeouts) -> Self {
+        self.request_timeouts = timeouts;
+        self
+    }
+
+    /// Require the scopes of `policy`'s rules to use the routes they match
+    pub fn with_route_policy(mut self, policy: RoutePolicy) -> Self {
+        self.route_policy = policy;
+        self
+    }
+
+    /// Capture sampled chat turns with `recorder` for later replay
+    pub fn with_replays(mut self, recorder: Arc<ReplayRecorder>) -> Self {
+        self.replays = Some(recorder);
+        self
+    }
+
+    /// Replace the rate limits and quotas (e.g. to meter tenant quotas)
+    pub fn with_limits(mut self, limits: ApiLimits) -> Self {
+        self.limits = Arc::new(limits);
+        self
     }
 }
 
@@ -70,4 +598,12 @@ mod tests {
         // Test would require actual engine and manager instances
         // This is a placeholder for future tests
     }
+
+    #[test]
+    fn test_request_timeouts_cap_the_requested_budget() {
+        let timeouts = RequestTimeouts::default();
+        assert_eq!(timeouts.budget(None), Duration::from_secs(30));
+        assert_eq!(timeouts.budget(Some(Duration::from_secs(2))), Duration::from_secs(2));
+        assert_eq!(timeouts.budget(Some(Duration::from_secs(600))), Duration::from_secs(120));
+    }
 }
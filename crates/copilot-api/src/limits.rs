@@ -0,0 +1,258 @@
+//! Per-tenant rate limits and quotas
+//!
+//! Every authenticated request is checked against its tenant's rate limit
+//! tier and counted against the tenant's API call quota. The outcome is
+//! reported on the response as `X-RateLimit-*` and `X-Quota-*` headers so
+//! clients can throttle themselves before they are rejected.
+
+use crate::{error::ApiError, types::Claims};
+use axum::{
+    http::{HeaderName, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use copilot_core::{FairnessWeights, TenantShare};
+use copilot_security::{RateLimitKey, RateLimitManager, RateLimitResult, RateLimitTier, SecurityError};
+use copilot_tenant::{QuotaManager, QuotaType, TenantError};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Response headers as name/value pairs
+pub type LimitHeaders = Vec<(String, String)>;
+
+/// A request rejected by a rate limit or quota
+#[derive(Debug)]
+pub struct LimitRejection {
+    pub error: ApiError,
+    /// Headers explaining the rejection (`Retry-After`, exhausted quota)
+    pub headers: LimitHeaders,
+}
+
+impl IntoResponse for LimitRejection {
+    fn into_response(self) -> Response {
+        with_headers(self.error.into_response(), self.headers)
+    }
+}
+
+/// Rate limits and quotas applied to API requests
+#[derive(Clone)]
+pub struct ApiLimits {
+    rate_limits: RateLimitManager,
+    quotas: Option<Arc<QuotaManager>>,
+    default_tier: RateLimitTier,
+    fairness: FairnessWeights,
+}
+
+impl Default for ApiLimits {
+    fn default() -> Self {
+        Self::new(RateLimitManager::default_config())
+    }
+}
+
+impl ApiLimits {
+    /// Rate limit requests with `rate_limits`; quotas are not enforced
+    pub fn new(rate_limits: RateLimitManager) -> Self {
+        Self {
+            rate_limits,
+            quotas: None,
+            default_tier: RateLimitTier::Standard,
+            fairness: FairnessWeights::default(),
+        }
+    }
+
+    /// Count requests against tenant quotas and report their usage
+    pub fn with_quotas(mut self, quotas: Arc<QuotaManager>) -> Self {
+        self.quotas = Some(quotas);
+        self
+    }
+
+    /// Tier for tokens without a `tier` claim (standard by default)
+    pub fn with_default_tier(mut self, tier: RateLimitTier) -> Self {
+        self.default_tier = tier;
+        self
+    }
+
+    /// Weights of each tier in queues for shared resources
+    pub fn with_fairness_weights(mut self, weights: FairnessWeights) -> Self {
+        self.fairness = weights;
+        self
+    }
+
+    /// The caller's share of shared resources, weighted by its tier
+    pub fn share(&self, tenant_id: &str, tier: RateLimitTier) -> TenantShare {
+        TenantShare::new(tenant_id, self.fairness.weight(tier.as_str()))
+    }
+
+    /// Rate limit tier of the caller (`tier` claim)
+    pub fn tier(&self, claims: &Claims) -> RateLimitTier {
+        claims
+            .additional
+            .get("tier")
+            .and_then(|v| v.as_str())
+            .map(RateLimitTier::from_str)
+            .unwrap_or(self.default_tier)
+    }
+
+    /// Admit a request from `tenant_id`
+    ///
+    /// Returns the rate limit headers for the response, or the rejection
+    /// with the headers that explain it.
+    pub async fn admit(&self, tenant_id: &str, tier: RateLimitTier) -> Result<LimitHeaders, LimitRejection> {
+        let key = RateLimitKey::User(tenant_id.to_string());
+        let headers = match self.rate_limits.check_limit(&key, &tier).await {
+            // Unlimited tiers and disabled limiting have nothing to report
+            Ok(result) if result.limit == u32::MAX => Vec::new(),
+            Ok(result) => self.rate_limits.get_headers(&result),
+            Err(SecurityError::RateLimitExceeded { retry_after_secs }) => {
+                warn!(tenant_id = %tenant_id, retry_after_secs, "Rate limit exceeded");
+                let limit = tier.requests_per_minute().unwrap_or(u32::MAX);
+                let result = RateLimitResult::exceeded(limit, retry_after_secs);
+                return Err(LimitRejection {
+                    error: ApiError::RateLimitExceeded,
+                    headers: self.rate_limits.get_headers(&result),
+                });
+            }
+            Err(e) => {
+                return Err(LimitRejection {
+                    error: ApiError::InternalError(e.to_string()),
+                    headers: Vec::new(),
+                })
+            }
+        };
+
+        // Tenants without registered quotas are not metered
+        if let Some(quotas) = &self.quotas {
+            if let Err(TenantError::QuotaExceeded(message)) =
+                quotas.increment(tenant_id, QuotaType::ApiCalls, 1)
+            {
+                warn!(tenant_id = %tenant_id, "{}", message);
+                let mut rejected = headers;
+                rejected.extend(self.quota_headers(tenant_id));
+                return Err(LimitRejection {
+                    error: ApiError::QuotaExceeded(message),
+                    headers: rejected,
+                });
+            }
+        }
+
+        Ok(headers)
+    }
+
+    /// Usage headers for the tenant's periodic quotas (API calls, tokens)
+    pub fn quota_headers(&self, tenant_id: &str) -> LimitHeaders {
+        let Some(quotas) = &self.quotas else {
+            return Vec::new();
+        };
+        let mut usage: Vec<_> = quotas
+            .get_all_usage(tenant_id)
+            .into_values()
+            .filter(|usage| usage.quota_type.is_periodic())
+            .collect();
+        usage.sort_by_key(|usage| usage.quota_type.as_str());
+        usage.iter().flat_map(|usage| usage.headers()).collect()
+    }
+}
+
+/// Add `headers` to a response, skipping any that are not valid header values
+pub fn with_headers(mut response: Response, headers: LimitHeaders) -> Response {
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use copilot_security::RateLimitConfig;
+    use copilot_tenant::{Tenant, TenantTier};
+
+    fn claims(additional: serde_json::Value) -> Claims {
+        Claims {
+            sub: "user-1".to_string(),
+            exp: 0,
+            iat: 0,
+            additional,
+        }
+    }
+
+    #[test]
+    fn test_tier_from_claims() {
+        let limits = ApiLimits::default();
+        assert_eq!(limits.tier(&claims(serde_json::json!({}))), RateLimitTier::Standard);
+        assert_eq!(
+            limits.tier(&claims(serde_json::json!({ "tier": "enterprise" }))),
+            RateLimitTier::Enterprise
+        );
+    }
+
+    #[test]
+    fn test_share_weighted_by_tier() {
+        let limits = ApiLimits::default().with_fairness_weights("pro=5".parse().unwrap());
+        assert_eq!(limits.share("acme", RateLimitTier::Pro).weight, 5.0);
+        assert_eq!(limits.share("acme", RateLimitTier::Free).weight, 1.0);
+        assert_eq!(limits.share("acme", RateLimitTier::Free).tenant_id, "acme");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_headers() {
+        let limits = ApiLimits::default();
+        let headers = limits.admit("tenant-a", RateLimitTier::Anonymous).await.unwrap();
+        assert!(headers.contains(&("X-RateLimit-Limit".to_string(), "10".to_string())));
+        assert!(headers.contains(&("X-RateLimit-Remaining".to_string(), "4".to_string())));
+
+        for _ in 0..4 {
+            limits.admit("tenant-a", RateLimitTier::Anonymous).await.unwrap();
+        }
+        let rejected = limits
+            .admit("tenant-a", RateLimitTier::Anonymous)
+            .await
+            .unwrap_err()
+            .into_response();
+        assert_eq!(rejected.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(rejected.headers()["x-ratelimit-remaining"], "0");
+        assert!(rejected.headers().contains_key("retry-after"));
+
+        // Other tenants have their own bucket; unlimited tiers report nothing
+        assert!(limits.admit("tenant-b", RateLimitTier::Anonymous).await.is_ok());
+        assert!(limits.admit("tenant-a", RateLimitTier::Unlimited).await.unwrap().is_empty());
+
+        let disabled = ApiLimits::new(RateLimitManager::new(RateLimitConfig {
+            enabled: false,
+            ..Default::default()
+        }));
+        assert!(disabled.admit("tenant-a", RateLimitTier::Anonymous).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_quota_headers_and_exhaustion() {
+        let tenant = Tenant::new("Acme", "acme", "owner", TenantTier::Free);
+        let quotas = Arc::new(QuotaManager::new());
+        quotas.register_tenant(&tenant);
+        quotas.set_custom_limit(&tenant.id, QuotaType::ApiCalls, 2);
+        let limits = ApiLimits::default().with_quotas(quotas);
+
+        limits.admit(&tenant.id, RateLimitTier::Pro).await.unwrap();
+        let headers = limits.quota_headers(&tenant.id);
+        assert!(headers.contains(&("X-Quota-Api-Calls-Limit".to_string(), "2".to_string())));
+        assert!(headers.contains(&("X-Quota-Api-Calls-Remaining".to_string(), "1".to_string())));
+        assert!(headers.iter().any(|(name, _)| name == "X-Quota-Tokens-Used"));
+
+        limits.admit(&tenant.id, RateLimitTier::Pro).await.unwrap();
+        let rejected = limits.admit(&tenant.id, RateLimitTier::Pro).await.unwrap_err();
+        assert!(matches!(rejected.error, ApiError::QuotaExceeded(_)));
+        let rejected = rejected.into_response();
+        assert_eq!(rejected.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(rejected.headers()["x-quota-api-calls-remaining"], "0");
+        assert!(rejected.headers().contains_key("x-ratelimit-limit"));
+
+        // Unregistered tenants are not metered
+        assert!(limits.admit("unknown", RateLimitTier::Pro).await.is_ok());
+        assert!(limits.quota_headers("unknown").is_empty());
+    }
+}
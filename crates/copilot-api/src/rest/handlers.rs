@@ -1,19 +1,67 @@
 //! Request handlers for REST API endpoints
 
 use crate::{
+    bootstrap::BootstrapJob,
+    bulk::{BulkJob, BulkRequest},
     error::{ApiError, Result},
+    gates::{GateRequest, GateTarget, GateVerdict},
+    impersonation::{ConsentRequest, Impersonation, ImpersonationConsent, ImpersonationRequest, ImpersonationToken},
+    ingestion::{ingestion_error, IngestionJob, IngestionService},
+    replay::{CapturedResponse, ReplayCapture, ReplaySummary},
+    runs::{ManualRun, ManualRunRequest, ParameterSchema, RunEvent, TemplateSummary},
+    schedules::{SchedulePreview, SchedulePreviewRequest},
+    stats::DashboardSnapshot,
+    tasks::{TaskEvent, TaskInfo},
     types::*,
     AppState,
 };
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    Json,
+    extract::{FromRequest, Multipart, Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    response::{IntoResponse, Response},
+    Extension, Json,
 };
 use chrono::Utc;
+use copilot_adapters::traits::{DashboardPush, MetricsQuery};
+use copilot_context::{
+    CachedEmbeddingProvider, ContextFilter, ContextWindowDiff, ContextWindowSnapshot, EmbeddingCacheStats,
+    EmbeddingWarmup, PrefetchOutcome, PrefetchStats,
+};
+use copilot_core::{PromptLogging, ResidencyOperation, ResidencyReport};
+use copilot_infra::storage::{content_key, sha256_hex};
+use copilot_infra::{
+    InfraError, LocalObjectStore, ObjectClass, ObjectStorage, ObjectStore, PresignMethod, PresignedQuery, PresignedUrl,
+};
+use copilot_ingestion::signing::{SIGNATURE_HEADER, SIGNER_HEADER};
+use copilot_ingestion::{QuarantinedDocument, SignatureClaim};
+use copilot_nlp::logs::LOG_SUMMARY_PROMPT;
+use copilot_nlp::{AlertBacktest, AlertDraft, LogClusterer, QueryLanguage};
+use copilot_observability::{ConversationReport, KnowledgeGapReport, Resolution};
+use copilot_conversation::resume::parse_event_id;
+use copilot_conversation::streaming::ChunkType;
+use copilot_conversation::{
+    BudgetStatus, CodePolicyDecision, EventStream, Experiment, ExperimentResults, ExperimentSpec, FallbackStats, GroundednessMonitor, WorkingNote, WorkingMemoryView, GroundednessReview, GroundednessStats, ModelComparison, ModelPreferenceStats, Persona,
+    StreamStats, ToolPolicy, ToolResult, UserPreferences,
+};
+use copilot_security::{
+    describe_retention, service_provider_config, AccessReview, AuditEvent, AuditEventType, AuditOutcome,
+    ComplianceReport, ReportPeriod, RetentionSetting, ScimError, ScimGroup, ScimListQuery, ScimPatchRequest, ScimUser,
+    UserImportReport,
+};
+use copilot_webhook::{
+    validate_url, DeliveryStatus, EndpointDeliveryStats, EndpointUpdate, HandoffEventData, NotificationChannel,
+    NotificationPreferences, PayloadSchemas, SchemaVersionInfo, TaskNotifier, WebhookDispatcher, WebhookEndpoint, WebhookError, WebhookEvent,
+    WebhookEventData, WebhookEventType,
+};
+use copilot_workflow::ScheduledWorkflow;
+use jsonwebtoken::jwk::JwkSet;
+use futures::stream::{self, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
 use std::sync::Arc;
-use tracing::{debug, error, info};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 /// Health check handler
@@ -27,39 +75,67 @@ pub async fn health_check() -> Result<Json<HealthResponse>> {
 }
 
 /// Readiness check handler
-pub async fn readiness_check(State(state): State<Arc<AppState>>) -> Result<Json<HealthResponse>> {
+///
+/// Answers 503 while a required dependency is down; optional dependencies
+/// that are down only mark the server as degraded.
+pub async fn readiness_check(
+    State(state): State<Arc<AppState>>,
+) -> Result<(StatusCode, Json<HealthResponse>)> {
     debug!("Readiness check requested");
 
-    // TODO: Check if dependencies are ready (database, external services, etc.)
+    let (status, code) = match &state.dependencies {
+        Some(monitor) => {
+            let report = monitor.report();
+            let code = if report.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+            (report.status(), code)
+        }
+        None => ("ready", StatusCode::OK),
+    };
 
-    Ok(Json(HealthResponse {
-        status: "ready".to_string(),
-        version: env!("CARGO_PKG_VERSION").to_string(),
-        uptime: 0,
-    }))
+    Ok((
+        code,
+        Json(HealthResponse {
+            status: status.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime: 0,
+        }),
+    ))
 }
 
 /// Create a new session
 pub async fn create_session(
     State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
     Json(req): Json<CreateSessionRequest>,
 ) -> Result<(StatusCode, Json<ApiResponse<SessionResponse>>)> {
     info!("Creating new session: {:?}", req.name);
 
-    let session_id = Uuid::new_v4().to_string();
-    let now = Utc::now();
+    let session = state
+        .conversation_manager
+        .create_session(req.persona_id.as_deref(), None)
+        .await?;
+    state
+        .conversation_manager
+        .set_session_owner(&session.id, claims.tenant_id(), &claims.sub)
+        .await?;
+    if req.allowed_tools.is_some() || req.sandbox.is_some() {
+        state
+            .conversation_manager
+            .set_tool_policy(&session.id, req.allowed_tools, req.sandbox)
+            .await?;
+    }
 
-    // TODO: Actually create session using conversation manager
-    // For now, return a mock response
     let response = SessionResponse {
-        id: session_id.clone(),
+        id: session.id.clone(),
         name: req.name,
-        created_at: now,
-        last_activity: now,
+        created_at: session.created_at,
+        last_activity: session.last_accessed,
+        persona_id: session.persona_id,
         metadata: req.metadata,
+        usage: Default::default(),
     };
 
-    info!("Session created: {}", session_id);
+    info!("Session created: {}", session.id);
     Ok((StatusCode::CREATED, Json(ApiResponse::success(response))))
 }
 
@@ -78,7 +154,9 @@ pub async fn get_session(
         name: Some("Example Session".to_string()),
         created_at: now,
         last_activity: now,
+        persona_id: None,
         metadata: serde_json::json!({}),
+        usage: state.conversation_manager.session_usage(&id).await,
     };
 
     Ok(Json(ApiResponse::success(response)))
@@ -91,7 +169,7 @@ pub async fn delete_session(
 ) -> Result<StatusCode> {
     info!("Deleting session: {}", id);
 
-    // TODO: Delete session using conversation manager
+    state.conversation_manager.delete_session(&id).await;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -125,6 +203,311 @@ pub async fn send_message(
     Ok((StatusCode::CREATED, Json(ApiResponse::success(response))))
 }
 
+/// Send a chat turn in a session and get the assistant's reply
+pub async fn chat_in_session(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+    Json(req): Json<SessionChatRequest>,
+) -> Result<Json<ApiResponse<SessionChatResponse>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    let prompt_logging = state.prompt_logging.for_tenant(claims.tenant_id());
+    if prompt_logging.records_prompts() {
+        info!(
+            "Chat turn in session {}: {} characters{}",
+            session_id,
+            req.message.len(),
+            logged_content(prompt_logging, &req.message)
+        );
+    }
+    audit_prompt(&state, &claims, &session_id, "session.chat", &req.message, prompt_logging).await;
+
+    // Only tenants that allow retaining prompt text are captured
+    let replays = state
+        .replays
+        .as_ref()
+        .filter(|replays| prompt_logging == PromptLogging::Full && replays.sample());
+    let captured_request = replays.map(|_| req.clone());
+    let started = std::time::Instant::now();
+
+    let result = state
+        .conversation_manager
+        .process_message(copilot_conversation::manager::MessageRequest {
+            session_id: session_id.clone(),
+            message: req.message,
+            metadata: req.metadata,
+            model: req.model,
+        })
+        .await;
+
+    if let (Some(replays), Some(request)) = (replays, captured_request) {
+        // The capture belongs to the session's tenant, even when an admin chats in it
+        let tenant_id = match state.conversation_manager.session_owner(&session_id).await {
+            Some((tenant_id, _)) => tenant_id,
+            None => claims.tenant_id().to_string(),
+        };
+        replays.record(ReplayCapture {
+            id: Uuid::new_v4(),
+            captured_at: Utc::now(),
+            tenant_id,
+            retrieval: state
+                .conversation_manager
+                .context_window_snapshots(&session_id)
+                .pop()
+                .map(|snapshot| snapshot.entries)
+                .unwrap_or_default(),
+            session_id,
+            request,
+            response: result.as_ref().ok().map(|response| CapturedResponse {
+                content: response.response.clone(),
+                model: response.model.clone(),
+                usage: response.usage,
+            }),
+            error: result.as_ref().err().map(ToString::to_string),
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+    }
+    let response = result?;
+
+    if prompt_logging.records_prompts() {
+        info!(
+            "Chat reply in session {}: {} tokens{}",
+            response.session_id,
+            response.usage.total_tokens,
+            logged_content(prompt_logging, &response.response)
+        );
+    }
+
+    Ok(Json(ApiResponse::success(SessionChatResponse {
+        content: response.response,
+        conversation_id: response.session_id,
+        usage: response.usage,
+        session_usage: response.session_usage,
+        model: response.model,
+        preference_suggestions: response.preference_suggestions,
+        context_downsize: response.context_downsize,
+    })))
+}
+
+/// Stream the assistant's reply to a chat turn as server-sent events
+///
+/// When the client disconnects, axum drops the event stream on its next
+/// write, which cancels the generation and records the partial reply; the
+/// short keep-alive makes that write happen even while generation is quiet.
+pub async fn stream_chat_in_session(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+    Json(req): Json<SessionChatRequest>,
+) -> Result<Response> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    info!(
+        "Streaming chat turn in session {}: {} characters",
+        session_id,
+        req.message.len()
+    );
+
+    let (stream_id, events) = state
+        .conversation_manager
+        .start_resumable_stream(copilot_conversation::manager::MessageRequest {
+            session_id,
+            message: req.message,
+            metadata: req.metadata,
+            model: req.model,
+        })
+        .await?;
+
+    Ok(sse_stream(&stream_id, events))
+}
+
+/// Query parameters for resuming a chat stream
+#[derive(Debug, Deserialize)]
+pub struct ResumeStreamQuery {
+    /// Index of the last event received; `Last-Event-ID` takes precedence
+    pub after: Option<usize>,
+}
+
+/// Reconnect to a chat stream, continuing after the event named by the
+/// `Last-Event-ID` header (or `after`) without generating the reply again
+pub async fn resume_chat_stream(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path((session_id, stream_id)): Path<(String, String)>,
+    Query(query): Query<ResumeStreamQuery>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    let after = match headers.get(LAST_EVENT_ID).map(|id| id.to_str().map(parse_event_id)) {
+        Some(Ok(Some((id, index)))) if id == stream_id => Some(index),
+        Some(_) => return Err(ApiError::InvalidInput(format!("Last-Event-ID is not an event of stream {}", stream_id))),
+        None => query.after,
+    };
+    info!("Resuming stream {} of session {} after {:?}", stream_id, session_id, after);
+
+    let events = state.conversation_manager.resume_stream(&session_id, &stream_id, after)?;
+    Ok(sse_stream(&stream_id, events))
+}
+
+/// Header naming the last SSE event a reconnecting client received
+const LAST_EVENT_ID: &str = "last-event-id";
+
+/// Header carrying the ID of a resumable stream
+pub const STREAM_ID_HEADER: &str = "x-stream-id";
+
+/// Send stream events as SSE, each with the ID to resume after it
+fn sse_stream(stream_id: &str, events: EventStream) -> Response {
+    let events = events.map(|event| {
+        let id = event.id();
+        let sse = match event.chunk.chunk_type {
+            _ if event.chunk.is_final => Event::default().event("done").json_data(&event.chunk),
+            ChunkType::Error => Ok(Event::default().event("error").data(event.chunk.content)),
+            _ => Event::default().event("token").json_data(&event.chunk),
+        };
+        Ok::<_, Infallible>(sse.unwrap_or_else(|_| Event::default().event("error")).id(id))
+    });
+
+    (
+        [(STREAM_ID_HEADER, stream_id.to_string())],
+        Sse::new(events).keep_alive(KeepAlive::new().interval(STREAM_KEEP_ALIVE)),
+    )
+        .into_response()
+}
+
+/// Keep-alive interval of chat streams, bounding how long a disconnected
+/// client goes unnoticed
+const STREAM_KEEP_ALIVE: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Streamed replies started, completed and cancelled
+pub async fn get_stream_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<StreamStats>>> {
+    claims.require_admin()?;
+    Ok(Json(ApiResponse::success(state.conversation_manager.stream_stats())))
+}
+
+/// Health and failover counters of the model fallback chain's providers
+pub async fn get_model_fallback_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<FallbackStats>>> {
+    claims.require_admin()?;
+    let chain = state
+        .conversation_manager
+        .model_chain()
+        .ok_or_else(|| ApiError::NotFound("No model fallback chain is configured".to_string()))?;
+    Ok(Json(ApiResponse::success(chain.stats())))
+}
+
+/// Answer a message with two models and return both replies side by side
+pub async fn compare_models(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+    Json(req): Json<CompareModelsRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<ModelComparison>>)> {
+    let prompt_logging = state.prompt_logging.for_tenant(claims.tenant_id());
+    if prompt_logging.records_prompts() {
+        info!(
+            "Comparison in session {}: {} characters{}",
+            session_id,
+            req.message.len(),
+            logged_content(prompt_logging, &req.message)
+        );
+    }
+    audit_prompt(&state, &claims, &session_id, "session.compare_models", &req.message, prompt_logging).await;
+
+    let comparison = state
+        .conversation_manager
+        .compare_models(
+            copilot_conversation::manager::MessageRequest {
+                session_id,
+                message: req.message,
+                metadata: req.metadata,
+                model: None,
+            },
+            req.models,
+        )
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(comparison))))
+}
+
+/// List the model comparisons run in a session
+pub async fn list_comparisons(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+) -> Result<Json<ApiResponse<Vec<ModelComparison>>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    let comparisons = state.conversation_manager.comparisons(&session_id).await;
+    Ok(Json(ApiResponse::success(comparisons)))
+}
+
+/// Record which answer of a comparison the user preferred
+pub async fn vote_comparison(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path((session_id, comparison_id)): Path<(String, String)>,
+    Json(req): Json<ComparisonVoteRequest>,
+) -> Result<Json<ApiResponse<ModelComparison>>> {
+    let comparison = state
+        .conversation_manager
+        .vote_comparison(&session_id, &comparison_id, &req.preferred_model)
+        .await?;
+    info!(
+        "Model preference from {} in tenant {}: {} (comparison {})",
+        claims.sub,
+        claims.tenant_id(),
+        req.preferred_model,
+        comparison_id
+    );
+    Ok(Json(ApiResponse::success(comparison)))
+}
+
+/// Preference vote counts per model across all comparisons
+pub async fn model_preference_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<std::collections::HashMap<String, ModelPreferenceStats>>>> {
+    claims.require_admin()?;
+    let stats = state.conversation_manager.model_preference_stats().await;
+    Ok(Json(ApiResponse::success(stats)))
+}
+
+/// Suffix for a log line carrying prompt or response text, as far as the
+/// tenant's prompt logging mode allows
+fn logged_content(mode: PromptLogging, content: &str) -> String {
+    mode.redact(content)
+        .map(|content| format!(": {:?}", content))
+        .unwrap_or_default()
+}
+
+/// Audit a prompt sent to a session, keeping as much of its text as the
+/// tenant's prompt logging mode allows
+async fn audit_prompt(
+    state: &AppState,
+    claims: &Claims,
+    session_id: &str,
+    action: &str,
+    prompt: &str,
+    mode: PromptLogging,
+) {
+    if !mode.records_prompts() {
+        return;
+    }
+    state
+        .audit
+        .log(
+            AuditEvent::new(AuditEventType::ResourceCreated, action)
+                .with_actor(&claims.sub, "user")
+                .with_tenant_id(claims.tenant_id())
+                .with_resource("session", session_id)
+                .with_content("prompt", prompt, mode),
+        )
+        .await;
+}
+
 /// Query parameters for getting messages
 #[derive(Debug, Deserialize)]
 pub struct GetMessagesQuery {
@@ -161,70 +544,3002 @@ pub async fn get_messages(
     Ok(Json(ApiResponse::success(response)))
 }
 
-/// Create a new workflow
-pub async fn create_workflow(
+/// Query parameters for the context window diff
+#[derive(Debug, Deserialize)]
+pub struct ContextDiffQuery {
+    /// Earlier turn to compare (defaults to the turn before `to`)
+    pub from: Option<u64>,
+    /// Later turn to compare (defaults to the latest turn)
+    pub to: Option<u64>,
+}
+
+/// Get what changed in the assembled context window between two turns
+pub async fn get_context_diff(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateWorkflowRequest>,
-) -> Result<(StatusCode, Json<ApiResponse<WorkflowResponse>>)> {
-    info!("Creating workflow: {}", req.name);
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+    Query(query): Query<ContextDiffQuery>,
+) -> Result<Json<ApiResponse<ContextWindowDiff>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    debug!(
+        "Getting context diff for session {}: from={:?}, to={:?}",
+        session_id, query.from, query.to
+    );
 
-    let workflow_id = Uuid::new_v4().to_string();
-    let now = Utc::now();
+    let diff = state
+        .conversation_manager
+        .context_window_diff(&session_id, query.from, query.to)
+        .ok_or_else(|| {
+            ApiError::NotFound(format!(
+                "No context window snapshots to compare for session {}",
+                session_id
+            ))
+        })?;
 
-    // TODO: Create workflow using CoPilot engine
-    // For now, return a mock response
-    let response = WorkflowResponse {
-        id: workflow_id.clone(),
-        name: req.name,
-        status: WorkflowStatus::Pending,
-        created_at: now,
-        updated_at: now,
-        result: None,
-        error: None,
-    };
+    Ok(Json(ApiResponse::success(diff)))
+}
 
-    info!("Workflow created: {}", workflow_id);
-    Ok((StatusCode::CREATED, Json(ApiResponse::success(response))))
+/// List the context window snapshots captured for a session
+pub async fn list_context_snapshots(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+) -> Result<Json<ApiResponse<Vec<ContextWindowSnapshot>>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    debug!("Listing context snapshots for session {}", session_id);
+
+    let snapshots = state.conversation_manager.context_window_snapshots(&session_id);
+    Ok(Json(ApiResponse::success(snapshots)))
 }
 
-/// Get workflow status
-pub async fn get_workflow_status(
+/// Query parameters for a context search
+#[derive(Debug, Deserialize)]
+pub struct ContextSearchQuery {
+    /// Search text
+    pub q: String,
+    /// Maximum results
+    #[serde(default = "default_search_limit")]
+    pub limit: usize,
+    /// Comma-separated tags every result must carry
+    pub tags: Option<String>,
+    /// Source URI prefix
+    pub source: Option<String>,
+    /// Content language
+    pub language: Option<String>,
+    /// Document type
+    #[serde(rename = "type")]
+    pub content_type: Option<String>,
+    /// Earliest ingestion time, inclusive
+    pub since: Option<chrono::DateTime<Utc>>,
+    /// Latest ingestion time, exclusive
+    pub until: Option<chrono::DateTime<Utc>>,
+    /// Search the corpus as it was at this time, including only the
+    /// document versions current then
+    pub as_of: Option<chrono::DateTime<Utc>>,
+}
+
+fn default_search_limit() -> usize {
+    10
+}
+
+impl ContextSearchQuery {
+    fn filter(&self) -> ContextFilter {
+        ContextFilter {
+            tags: self
+                .tags
+                .iter()
+                .flat_map(|tags| tags.split(','))
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(String::from)
+                .collect(),
+            source_prefix: self.source.clone(),
+            language: self.language.clone(),
+            content_type: self.content_type.clone(),
+            ingested_after: self.since,
+            ingested_before: self.until,
+            as_of: self.as_of,
+        }
+    }
+}
+
+/// Search stored context, narrowed by tags, source, language, document type
+/// and ingestion time before retrieval, optionally as of a past time
+pub async fn search_context(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ContextSearchQuery>,
+) -> Result<Json<ApiResponse<Vec<ContextSearchHit>>>> {
+    let filter = query.filter();
+    debug!("Searching context for {:?} with {:?}", query.q, filter);
+
+    if let (Some(since), Some(until)) = (query.since, query.until) {
+        if since >= until {
+            return Err(ApiError::InvalidInput(
+                "`since` must be earlier than `until`".to_string(),
+            ));
+        }
+    }
+
+    let result = state
+        .conversation_manager
+        .context_engine()
+        .retrieve_filtered(&query.q, &filter)
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+
+    let hits = result
+        .selected
+        .iter()
+        .take(query.limit)
+        .map(|scored| ContextSearchHit {
+            id: scored.item.metadata.id.to_string(),
+            snippet: scored.item.content.chars().take(SNIPPET_CHARS).collect(),
+            source: Some(scored.item.metadata.source.clone()),
+            score: scored.score as f32,
+        })
+        .collect();
+    Ok(Json(ApiResponse::success(hits)))
+}
+
+/// Characters of content returned with each search hit
+const SNIPPET_CHARS: usize = 200;
+
+/// Partial message sent while the user is still typing
+#[derive(Debug, Deserialize)]
+pub struct PrefetchContextRequest {
+    /// Message text typed so far
+    pub query: String,
+}
+
+/// Warm the context for a message the user is still typing
+pub async fn prefetch_context(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+    Json(req): Json<PrefetchContextRequest>,
+) -> Result<Json<ApiResponse<PrefetchOutcome>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    debug!("Prefetching context for session {}", session_id);
+
+    let outcome = state
+        .conversation_manager
+        .prefetch_context(&session_id, &req.query)
+        .await?;
+    Ok(Json(ApiResponse::success(outcome)))
+}
+
+/// Get how often prefetched context was used and the latency it saved
+pub async fn get_prefetch_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<PrefetchStats>>> {
+    claims.require_admin()?;
+    Ok(Json(ApiResponse::success(state.conversation_manager.prefetch_stats())))
+}
+
+fn embedding_cache(state: &AppState) -> Result<&Arc<CachedEmbeddingProvider>> {
+    state
+        .embedding_cache
+        .as_ref()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Embedding cache is not configured".to_string()))
+}
+
+/// Get embedding cache hit rates
+pub async fn get_embedding_cache_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<EmbeddingCacheStats>>> {
+    claims.require_admin()?;
+    Ok(Json(ApiResponse::success(embedding_cache(&state)?.stats())))
+}
+
+/// Embed and cache the context items matching a filter ahead of queries
+/// (admin only)
+pub async fn warm_embedding_cache(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(filter): Json<ContextFilter>,
+) -> Result<Json<ApiResponse<EmbeddingWarmup>>> {
+    claims.require_admin()?;
+    let cache = embedding_cache(&state)?;
+    let items = state
+        .conversation_manager
+        .context_engine()
+        .list_matching(&filter)
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    let texts: Vec<&str> = items.iter().map(|item| item.content.as_str()).collect();
+    let warmup = cache
+        .warm(&texts)
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    info!(
+        "Embedding cache warmed: {} embedded, {} already cached",
+        warmup.embedded, warmup.already_cached
+    );
+    Ok(Json(ApiResponse::success(warmup)))
+}
+
+/// Bring the archived context items matching a filter back into memory
+/// ahead of queries (admin only)
+pub async fn warm_up_context(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(filter): Json<ContextFilter>,
+) -> Result<Json<ApiResponse<ContextWarmup>>> {
+    claims.require_admin()?;
+    let rehydrated = state
+        .conversation_manager
+        .context_engine()
+        .warm_up(&filter)
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    info!("Context warmed up: {} archived items rehydrated", rehydrated);
+    Ok(Json(ApiResponse::success(ContextWarmup { rehydrated })))
+}
+
+fn groundedness(state: &AppState) -> Result<&Arc<GroundednessMonitor>> {
+    state
+        .conversation_manager
+        .groundedness()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Groundedness scoring is not configured".to_string()))
+}
+
+/// Get how well answers are grounded in their context (admin only)
+pub async fn get_groundedness_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<GroundednessStats>>> {
+    claims.require_admin()?;
+    Ok(Json(ApiResponse::success(groundedness(&state)?.stats())))
+}
+
+/// List poorly grounded answers awaiting review (admin only)
+pub async fn list_groundedness_reviews(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<Vec<GroundednessReview>>>> {
+    claims.require_admin()?;
+    Ok(Json(ApiResponse::success(groundedness(&state)?.reviews())))
+}
+
+/// Take a reviewed answer off the review queue (admin only)
+pub async fn resolve_groundedness_review(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    claims.require_admin()?;
+    groundedness(&state)?
+        .resolve(id)
+        .ok_or_else(|| ApiError::NotFound(format!("Answer {} is not awaiting review", id)))?;
+    info!("{} resolved groundedness review {}", claims.sub, id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Query parameters for the knowledge gaps report
+#[derive(Debug, Deserialize)]
+pub struct KnowledgeGapsQuery {
+    /// Days of questions to report on
+    #[serde(default = "default_knowledge_gap_days")]
+    pub days: i64,
+}
+
+fn default_knowledge_gap_days() -> i64 {
+    30
+}
+
+/// Topics the tenant's users ask about and the ones answered with low
+/// confidence or without sources (admin only)
+pub async fn get_knowledge_gaps(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<KnowledgeGapsQuery>,
+) -> Result<Json<ApiResponse<KnowledgeGapReport>>> {
+    claims.require_admin()?;
+    let since = Utc::now() - chrono::Duration::days(query.days.clamp(1, 365));
+    let report = state.query_analytics.report(Some(claims.tenant_id()), since);
+    Ok(Json(ApiResponse::success(report)))
+}
+
+/// Intent distribution, resolution and escalation rates of the tenant's
+/// recent conversations (admin only)
+pub async fn get_conversation_analytics(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<KnowledgeGapsQuery>,
+) -> Result<Json<ApiResponse<ConversationReport>>> {
+    claims.require_admin()?;
+    let since = Utc::now() - chrono::Duration::days(query.days.clamp(1, 365));
+    let report = state.conversation_analytics.report(Some(claims.tenant_id()), since);
+    Ok(Json(ApiResponse::success(report)))
+}
+
+/// Record whether a conversation resolved the user's goal
+pub async fn confirm_resolution(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+    Json(req): Json<ResolutionRequest>,
+) -> Result<Json<ApiResponse<Resolution>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    if !state.conversation_analytics.confirm_resolution(&session_id, req.resolved) {
+        return Err(ApiError::NotFound(format!("No answered turns in session {}", session_id)));
+    }
+    info!("{} marked session {} resolved: {}", claims.sub, session_id, req.resolved);
+    let resolution = state
+        .conversation_analytics
+        .resolution(&session_id)
+        .ok_or_else(|| ApiError::NotFound(format!("No answered turns in session {}", session_id)))?;
+    Ok(Json(ApiResponse::success(resolution)))
+}
+
+/// Record whether a session's answers helped, counted in the results of
+/// the experiment the session takes part in
+pub async fn submit_session_feedback(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+    Json(req): Json<FeedbackRequest>,
+) -> Result<StatusCode> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    let assignment = state.conversation_manager.experiments().record_feedback(&session_id, req.helpful);
+    if let Some(assignment) = assignment {
+        info!(
+            "{} rated session {} helpful: {} (experiment {}, variant {})",
+            claims.sub, session_id, req.helpful, assignment.experiment_id, assignment.variant
+        );
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List A/B experiments (admin only)
+pub async fn list_experiments(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<Vec<Experiment>>>> {
+    claims.require_admin()?;
+    Ok(Json(ApiResponse::success(state.conversation_manager.experiments().list())))
+}
+
+/// Start an A/B experiment (admin only); only the caller's tenant may be
+/// enrolled
+pub async fn create_experiment(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(spec): Json<ExperimentSpec>,
+) -> Result<(StatusCode, Json<ApiResponse<Experiment>>)> {
+    claims.require_admin()?;
+    if spec.tenants.iter().any(|tenant| tenant != claims.tenant_id()) {
+        return Err(ApiError::AuthorizationFailed(
+            "Experiments can only enroll the caller's tenant".to_string(),
+        ));
+    }
+    let experiment = state.conversation_manager.experiments().create(spec)?;
+    info!("{} started experiment {}", claims.sub, experiment.id);
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(experiment))))
+}
+
+/// An experiment's metrics per variant and significance tests against the
+/// control (admin only)
+pub async fn get_experiment_results(
     State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
     Path(id): Path<String>,
-) -> Result<Json<ApiResponse<WorkflowResponse>>> {
-    debug!("Getting workflow status: {}", id);
+) -> Result<Json<ApiResponse<ExperimentResults>>> {
+    claims.require_admin()?;
+    let results = state
+        .conversation_manager
+        .experiments()
+        .results(&id)
+        .ok_or_else(|| ApiError::NotFound(format!("Experiment not found: {}", id)))?;
+    Ok(Json(ApiResponse::success(results)))
+}
 
-    // TODO: Fetch workflow status from CoPilot engine
-    // For now, return a mock response
-    let now = Utc::now();
-    let response = WorkflowResponse {
-        id: id.clone(),
-        name: "Example Workflow".to_string(),
-        status: WorkflowStatus::Running,
-        created_at: now,
-        updated_at: now,
-        result: None,
-        error: None,
-    };
+/// Stop assigning sessions to an experiment (admin only)
+pub async fn stop_experiment(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<Experiment>>> {
+    claims.require_admin()?;
+    let experiment = state.conversation_manager.experiments().stop(&id)?;
+    info!("{} stopped experiment {}", claims.sub, id);
+    Ok(Json(ApiResponse::success(experiment)))
+}
 
-    Ok(Json(ApiResponse::success(response)))
+/// Enroll the caller's tenant in an experiment (admin only)
+pub async fn enroll_in_experiment(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<Experiment>>> {
+    claims.require_admin()?;
+    let experiment = state
+        .conversation_manager
+        .experiments()
+        .set_enrollment(&id, claims.tenant_id(), true)?;
+    info!("{} enrolled tenant {} in experiment {}", claims.sub, claims.tenant_id(), id);
+    Ok(Json(ApiResponse::success(experiment)))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Withdraw the caller's tenant from an experiment (admin only)
+pub async fn withdraw_from_experiment(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<Experiment>>> {
+    claims.require_admin()?;
+    let experiment = state
+        .conversation_manager
+        .experiments()
+        .set_enrollment(&id, claims.tenant_id(), false)?;
+    info!("{} withdrew tenant {} from experiment {}", claims.sub, claims.tenant_id(), id);
+    Ok(Json(ApiResponse::success(experiment)))
+}
 
-    #[tokio::test]
-    async fn test_health_check() {
-        let result = health_check().await;
-        assert!(result.is_ok());
-        let health = result.unwrap().0;
-        assert_eq!(health.status, "healthy");
+/// Query parameters for the residency report
+#[derive(Debug, Deserialize)]
+pub struct ResidencyReportQuery {
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+/// Get the tenant's home region and the cross-region access refused by
+/// its residency policy (admin only)
+pub async fn get_residency_report(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ResidencyReportQuery>,
+) -> Result<Json<ApiResponse<ResidencyReport>>> {
+    claims.require_admin()?;
+    let residency = state.residency.for_tenant(claims.tenant_id());
+    Ok(Json(ApiResponse::success(state.residency_log.report(&residency, query.limit))))
+}
+
+/// Query parameters for listing code policy decisions
+#[derive(Debug, Deserialize)]
+pub struct CodePolicyDecisionsQuery {
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+/// List responses the tenant's code policy annotated or blocked, newest
+/// first (admin only)
+pub async fn list_code_policy_decisions(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<CodePolicyDecisionsQuery>,
+) -> Result<Json<ApiResponse<Vec<CodePolicyDecision>>>> {
+    claims.require_admin()?;
+    let log = state
+        .code_policy_log
+        .as_ref()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Code policies are not enabled".to_string()))?;
+    Ok(Json(ApiResponse::success(log.recent(claims.tenant_id(), query.limit))))
+}
+
+/// Query parameters for the compliance report
+#[derive(Debug, Deserialize)]
+pub struct ComplianceReportQuery {
+    /// `YYYY`, `YYYY-Qn`, `YYYY-MM` or `YYYY-MM-DD..YYYY-MM-DD`; the last 90
+    /// days by default
+    #[serde(default)]
+    pub period: Option<String>,
+    /// `json` (default) or `markdown`
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+/// Get the tenant's compliance report over a period, as JSON or markdown
+/// (admin only)
+pub async fn get_compliance_report(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ComplianceReportQuery>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    let markdown = match query.format.as_deref() {
+        None | Some("json") => false,
+        Some("markdown" | "md") => true,
+        Some(other) => {
+            return Err(ApiError::InvalidInput(format!("Unknown report format '{}': expected json or markdown", other)))
+        }
+    };
+    let period = report_period(query.period.as_deref())?;
+
+    let tenant_id = claims.tenant_id();
+    let retention = retention_settings(&state, tenant_id);
+    let report = state.compliance.generate(tenant_id, period, retention, &claims.sub).await;
+    state
+        .audit
+        .log(
+            AuditEvent::new(AuditEventType::DataExported, "compliance_report")
+                .with_actor(&claims.sub, "user")
+                .with_tenant_id(tenant_id)
+                .with_resource("compliance_report", &report.period.label)
+                .with_outcome(AuditOutcome::Success),
+        )
+        .await;
+    info!("{} generated the {} compliance report of {}", claims.sub, report.period.label, tenant_id);
+
+    if markdown {
+        Ok(([(header::CONTENT_TYPE, "text/markdown; charset=utf-8")], report.to_markdown()).into_response())
+    } else {
+        Ok(Json(ApiResponse::<ComplianceReport>::success(report)).into_response())
     }
+}
 
-    #[test]
-    fn test_default_limit() {
-        assert_eq!(default_limit(), 50);
+/// The requested report period, or the last 90 days
+fn report_period(period: Option<&str>) -> Result<ReportPeriod> {
+    match period {
+        Some(period) => period.parse().map_err(ApiError::InvalidInput),
+        None => Ok(ReportPeriod::trailing_days(90, Utc::now())),
+    }
+}
+
+/// How long the tenant's data is kept, as configured on this server
+fn retention_settings(state: &AppState, tenant_id: &str) -> Vec<RetentionSetting> {
+    let mut settings = vec![RetentionSetting::new(
+        "prompt_logging",
+        state.prompt_logging.for_tenant(tenant_id).as_str(),
+        "How prompt and response text is kept in server logs",
+    )];
+    let residency = state.residency.for_tenant(tenant_id);
+    if let Ok(Some(storage)) = state.object_storage.route(&residency) {
+        for rule in storage.lifecycle().rules() {
+            settings.push(RetentionSetting::new(
+                format!("objects:{}", rule.prefix),
+                describe_retention(rule.expire_after),
+                format!("Objects under {} expire from object storage", rule.prefix),
+            ));
+        }
+    }
+    if let Some(replays) = &state.replays {
+        settings.push(RetentionSetting::new(
+            "replay_captures",
+            format!("{} captures", replays.max_captures()),
+            format!(
+                "{:.0}% of chat turns are captured with secrets redacted; the oldest captures are removed first",
+                replays.sample_rate() * 100.0
+            ),
+        ));
+    }
+    settings
+}
+
+/// Query parameters for listing access reviews
+#[derive(Debug, Deserialize)]
+pub struct ListAccessReviewsQuery {
+    /// Period as for the compliance report; the last 90 days by default
+    #[serde(default)]
+    pub period: Option<String>,
+}
+
+/// List the tenant's access reviews in a period (admin only)
+pub async fn list_access_reviews(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ListAccessReviewsQuery>,
+) -> Result<Json<ApiResponse<Vec<AccessReview>>>> {
+    claims.require_admin()?;
+    let period = report_period(query.period.as_deref())?;
+    let reviews = state.compliance.access_reviews().list(claims.tenant_id(), &period);
+    Ok(Json(ApiResponse::success(reviews)))
+}
+
+/// Record a review of a user's access, signed off by the caller (admin only)
+pub async fn record_access_review(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<AccessReviewRequest>,
+) -> Result<Json<ApiResponse<AccessReview>>> {
+    claims.require_admin()?;
+    if req.subject.trim().is_empty() {
+        return Err(ApiError::InvalidInput("subject must not be empty".to_string()));
+    }
+    let mut review = AccessReview::new(claims.tenant_id(), &claims.sub, req.subject, req.roles, req.decision);
+    review.note = req.note;
+    state
+        .audit
+        .log(
+            AuditEvent::new(AuditEventType::AccessReviewed, "review_access")
+                .with_actor(&claims.sub, "user")
+                .with_tenant_id(claims.tenant_id())
+                .with_resource("user", &review.subject)
+                .with_metadata("decision", review.decision.as_str())
+                .with_outcome(AuditOutcome::Success),
+        )
+        .await;
+    state.compliance.access_reviews().record(review.clone());
+    info!("{} reviewed the access of {}: {}", claims.sub, review.subject, review.decision.as_str());
+    Ok(Json(ApiResponse::success(review)))
+}
+
+/// Media type of SCIM requests and responses
+const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+
+/// A SCIM response: the resource, or a SCIM error with the matching status
+fn scim_response<T: Serialize>(status: StatusCode, result: copilot_security::Result<T>) -> Response {
+    let content_type = [(header::CONTENT_TYPE, SCIM_CONTENT_TYPE)];
+    match result {
+        Ok(body) => (status, content_type, Json(body)).into_response(),
+        Err(err) => {
+            let status = StatusCode::from_u16(err.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            (status, content_type, Json(ScimError::from(&err))).into_response()
+        }
+    }
+}
+
+/// What the SCIM endpoints support
+pub async fn scim_service_provider_config(Extension(claims): Extension<Claims>) -> Result<Response> {
+    claims.require_admin()?;
+    Ok(scim_response(StatusCode::OK, Ok(service_provider_config())))
+}
+
+/// List the tenant's users (admin only)
+pub async fn scim_list_users(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ScimListQuery>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    Ok(scim_response(StatusCode::OK, state.users.list_users(claims.tenant_id(), &query).await))
+}
+
+/// Provision a user into the tenant (admin only)
+pub async fn scim_create_user(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(user): Json<ScimUser>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    let result = state.users.create_user(claims.tenant_id(), user, &claims.sub).await;
+    Ok(scim_response(StatusCode::CREATED, result))
+}
+
+/// Get one of the tenant's users (admin only)
+pub async fn scim_get_user(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    Ok(scim_response(StatusCode::OK, state.users.get_user(claims.tenant_id(), &id).await))
+}
+
+/// Replace a user's attributes (admin only)
+pub async fn scim_replace_user(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(user): Json<ScimUser>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    let result = state.users.replace_user(claims.tenant_id(), &id, user, &claims.sub).await;
+    Ok(scim_response(StatusCode::OK, result))
+}
+
+/// Change some of a user's attributes, e.g. deactivate them (admin only)
+pub async fn scim_patch_user(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(patch): Json<ScimPatchRequest>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    let result = state.users.patch_user(claims.tenant_id(), &id, patch, &claims.sub).await;
+    Ok(scim_response(StatusCode::OK, result))
+}
+
+/// Deprovision a user (admin only)
+pub async fn scim_delete_user(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    match state.users.delete_user(claims.tenant_id(), &id, &claims.sub).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT.into_response()),
+        Err(err) => Ok(scim_response::<()>(StatusCode::NO_CONTENT, Err(err))),
+    }
+}
+
+/// List the role groups and their members in the tenant (admin only)
+pub async fn scim_list_groups(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ScimListQuery>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    Ok(scim_response(StatusCode::OK, state.users.list_groups(claims.tenant_id(), &query).await))
+}
+
+/// Push a group named after a role, setting its members (admin only)
+pub async fn scim_create_group(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(group): Json<ScimGroup>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    let result = state.users.create_group(claims.tenant_id(), group, &claims.sub).await;
+    Ok(scim_response(StatusCode::CREATED, result))
+}
+
+/// Get a role group and its members (admin only)
+pub async fn scim_get_group(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    Ok(scim_response(StatusCode::OK, state.users.get_group(claims.tenant_id(), &id).await))
+}
+
+/// Set the members of a role group (admin only)
+pub async fn scim_replace_group(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(group): Json<ScimGroup>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    let result = state.users.replace_group(claims.tenant_id(), &id, group, &claims.sub).await;
+    Ok(scim_response(StatusCode::OK, result))
+}
+
+/// Add or remove members of a role group (admin only)
+pub async fn scim_patch_group(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(patch): Json<ScimPatchRequest>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    let result = state.users.patch_group(claims.tenant_id(), &id, patch, &claims.sub).await;
+    Ok(scim_response(StatusCode::OK, result))
+}
+
+/// Roles cannot be deleted: deleting a role group revokes the role from
+/// all its members (admin only)
+pub async fn scim_delete_group(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    let tenant_id = claims.tenant_id();
+    let result = match state.users.get_group(tenant_id, &id).await {
+        Ok(group) => {
+            let empty = ScimGroup { members: Vec::new(), ..group };
+            state.users.replace_group(tenant_id, &id, empty, &claims.sub).await
+        }
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok(_) => Ok(StatusCode::NO_CONTENT.into_response()),
+        Err(err) => Ok(scim_response::<()>(StatusCode::NO_CONTENT, Err(err))),
+    }
+}
+
+/// Export the tenant's users as CSV (admin only)
+pub async fn export_users(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Response> {
+    claims.require_admin()?;
+    let tenant_id = claims.tenant_id();
+    let csv = state.users.export_csv(tenant_id).await?;
+    state
+        .audit
+        .log(
+            AuditEvent::new(AuditEventType::DataExported, "export_users")
+                .with_actor(&claims.sub, "user")
+                .with_tenant_id(tenant_id)
+                .with_resource("users", tenant_id)
+                .with_outcome(AuditOutcome::Success),
+        )
+        .await;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}-users.csv\"", tenant_id)),
+        ],
+        csv,
+    )
+        .into_response())
+}
+
+/// Create or update the tenant's users from a CSV body (admin only)
+pub async fn import_users(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    body: String,
+) -> Result<Json<ApiResponse<UserImportReport>>> {
+    claims.require_admin()?;
+    let report = state.users.import_csv(claims.tenant_id(), &body, &claims.sub).await?;
+    Ok(Json(ApiResponse::success(report)))
+}
+
+/// Public keys that verify the tokens this server signs, as a JWKS (no
+/// authentication); empty unless rotating signing keys are configured
+pub async fn get_jwks(State(state): State<Arc<AppState>>) -> Response {
+    let jwks = state
+        .signing_keys
+        .as_ref()
+        .map(|keys| keys.jwks())
+        .unwrap_or(JwkSet { keys: Vec::new() });
+    // Verifiers may cache the set for less than the overlap window
+    ([(header::CACHE_CONTROL, "public, max-age=300")], Json(jwks)).into_response()
+}
+
+/// Email the caller a link to verify their address
+pub async fn request_email_verification(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode> {
+    require_account_mailer(&state)?;
+    state.accounts.request_email_verification(&claims.sub).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Verify an email address with the token mailed to it (no authentication)
+pub async fn verify_email(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<EmailVerificationRequest>,
+) -> Result<StatusCode> {
+    state.accounts.verify_email(&request.token).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Email a password reset link to the account registered to an address
+/// (no authentication); accepted whether or not there is one
+pub async fn request_password_reset(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<PasswordResetRequest>,
+) -> Result<StatusCode> {
+    require_account_mailer(&state)?;
+    state.accounts.request_password_reset(&request.email, None).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Set a new password with a password reset token (no authentication)
+pub async fn reset_password(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<PasswordResetConfirmation>,
+) -> Result<StatusCode> {
+    state
+        .accounts
+        .reset_password(&request.token, &request.new_password, None)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn require_account_mailer(state: &AppState) -> Result<()> {
+    match state.accounts.mailer() {
+        Some(_) => Ok(()),
+        None => Err(ApiError::ServiceUnavailable(
+            "Email delivery is not configured".to_string(),
+        )),
+    }
+}
+
+/// Get the code edits proposed as unified diffs in the latest response
+pub async fn get_proposed_edits(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+) -> Result<Json<ApiResponse<ProposedEditsResponse>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    debug!("Getting proposed edits for session {}", session_id);
+
+    let edits = state.conversation_manager.proposed_edits(&session_id).await?;
+    Ok(Json(ApiResponse::success(ProposedEditsResponse { session_id, edits })))
+}
+
+/// Get a session's messages, with the tool calls made in it
+pub async fn get_session_history(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+) -> Result<Json<ApiResponse<Vec<HistoryMessage>>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    debug!("Getting history for session {}", session_id);
+
+    let messages = state.conversation_manager.session_history(&session_id).await?;
+    Ok(Json(ApiResponse::success(messages.into_iter().map(HistoryMessage::from).collect())))
+}
+
+/// Package a session into a handoff document and post it to incident
+/// channels or ticket systems
+pub async fn hand_off_session(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+    Json(req): Json<HandoffRequest>,
+) -> Result<Json<ApiResponse<HandoffResponse>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    let bundle = state
+        .conversation_manager
+        .handoff_bundle(&session_id)
+        .await?
+        .with_notes(req.notes);
+    let document = bundle.to_markdown();
+    if req.dry_run {
+        return Ok(Json(ApiResponse::success(HandoffResponse { bundle, document, deliveries: Vec::new() })));
+    }
+
+    let dispatcher = state
+        .handoff_webhooks
+        .as_ref()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Handoff webhooks are not configured".to_string()))?;
+    let event = WebhookEvent::new(
+        WebhookEventType::ConversationHandoff,
+        WebhookEventData::Handoff(HandoffEventData {
+            conversation_id: session_id.clone(),
+            title: bundle.title.clone(),
+            summary: bundle.summary.clone(),
+            document: document.clone(),
+            bundle: serde_json::to_value(&bundle).map_err(|e| ApiError::InternalError(e.to_string()))?,
+            requested_by: Some(claims.sub.clone()),
+            created_at: Utc::now(),
+        }),
+    )
+    .with_tenant(claims.tenant_id());
+
+    let mut deliveries = Vec::new();
+    if req.targets.is_empty() {
+        let delivered = dispatcher
+            .dispatch(event)
+            .await
+            .map_err(|e| ApiError::InternalError(e.to_string()))?;
+        deliveries.extend(delivered.iter().map(|delivery| HandoffDelivery {
+            endpoint_id: delivery.endpoint_id.clone(),
+            delivered: delivery.status == DeliveryStatus::Delivered,
+            error: None,
+        }));
+        if deliveries.is_empty() {
+            return Err(ApiError::ServiceUnavailable("No endpoint is subscribed to handoffs".to_string()));
+        }
+    } else {
+        for target in &req.targets {
+            // Other tenants' endpoints are as unknown as missing ones
+            let visible = dispatcher
+                .get_endpoint(target)
+                .is_some_and(|endpoint| endpoint.tenant_id.as_deref().map_or(true, |t| t == claims.tenant_id()));
+            if !visible {
+                return Err(ApiError::InvalidInput(format!("Unknown handoff target {}", target)));
+            }
+            deliveries.push(match dispatcher.dispatch_to(target, event.clone()).await {
+                Ok(delivery) => HandoffDelivery {
+                    endpoint_id: target.clone(),
+                    delivered: delivery.status == DeliveryStatus::Delivered,
+                    error: None,
+                },
+                Err(e) => HandoffDelivery { endpoint_id: target.clone(), delivered: false, error: Some(e.to_string()) },
+            });
+        }
+    }
+    state.conversation_analytics.record_escalation(&session_id);
+    info!(
+        "{} handed off session {} to {} endpoints",
+        claims.sub,
+        session_id,
+        deliveries.iter().filter(|d| d.delivered).count()
+    );
+
+    Ok(Json(ApiResponse::success(HandoffResponse { bundle, document, deliveries })))
+}
+
+/// Hours a rotated-out webhook secret keeps signing deliveries by default
+const DEFAULT_SECRET_GRACE_HOURS: u32 = 24;
+
+/// Longest grace period of a rotated-out webhook secret
+const MAX_SECRET_GRACE_HOURS: u32 = 168;
+
+fn webhooks(state: &AppState) -> Result<&Arc<WebhookDispatcher>> {
+    state
+        .webhooks
+        .as_ref()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Webhooks are not configured".to_string()))
+}
+
+fn webhook_error(error: WebhookError) -> ApiError {
+    match error {
+        WebhookError::NotFound(id) => ApiError::NotFound(format!("Webhook {} not found", id)),
+        WebhookError::InvalidUrl(_) | WebhookError::UnknownSchemaVersion(_) => {
+            ApiError::InvalidInput(error.to_string())
+        }
+        other => ApiError::InternalError(other.to_string()),
+    }
+}
+
+/// An endpoint of the caller's tenant; other tenants' endpoints are as
+/// unknown as missing ones
+fn tenant_webhook(dispatcher: &WebhookDispatcher, claims: &Claims, id: &str) -> Result<WebhookEndpoint> {
+    dispatcher
+        .get_endpoint(id)
+        .filter(|endpoint| endpoint.tenant_id.as_deref() == Some(claims.tenant_id()))
+        .ok_or_else(|| ApiError::NotFound(format!("Webhook {} not found", id)))
+}
+
+/// List the tenant's webhook endpoints (admin only)
+pub async fn list_webhooks(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<Vec<WebhookEndpointInfo>>>> {
+    claims.require_admin()?;
+    let mut endpoints = webhooks(&state)?.list_tenant_endpoints(claims.tenant_id());
+    endpoints.sort_by_key(|endpoint| endpoint.created_at);
+    Ok(Json(ApiResponse::success(endpoints.iter().map(WebhookEndpointInfo::from).collect())))
+}
+
+/// Register a webhook endpoint; its signing secret is only returned now
+/// (admin only)
+pub async fn create_webhook(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<CreateWebhookRequest>,
+) -> Result<Json<ApiResponse<WebhookSecret>>> {
+    claims.require_admin()?;
+    let dispatcher = webhooks(&state)?;
+    if req.name.trim().is_empty() {
+        return Err(ApiError::InvalidInput("Webhook name must not be empty".to_string()));
+    }
+    validate_url(&req.url).map_err(webhook_error)?;
+    if let Some(version) = req.schema_version {
+        dispatcher.schema_registry().check_version(version).map_err(webhook_error)?;
+    }
+
+    let secret = copilot_webhook::generate_webhook_secret();
+    let mut endpoint = WebhookEndpoint::new(req.name.trim(), &req.url, &secret)
+        .with_events(req.events)
+        .with_headers(req.headers)
+        .with_tenant(claims.tenant_id());
+    endpoint.schema_version = req.schema_version;
+    let info = WebhookEndpointInfo::from(&endpoint);
+    dispatcher.register_endpoint(endpoint);
+    info!("{} registered webhook {} for {}", claims.sub, info.id, info.url);
+
+    Ok(Json(ApiResponse::success(WebhookSecret { endpoint: info, secret })))
+}
+
+/// Get a webhook endpoint (admin only)
+pub async fn get_webhook(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<WebhookEndpointInfo>>> {
+    claims.require_admin()?;
+    let endpoint = tenant_webhook(webhooks(&state)?, &claims, &id)?;
+    Ok(Json(ApiResponse::success(WebhookEndpointInfo::from(&endpoint))))
+}
+
+/// Change a webhook endpoint's name, URL, subscribed events, headers or
+/// state (admin only)
+pub async fn update_webhook(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(update): Json<EndpointUpdate>,
+) -> Result<Json<ApiResponse<WebhookEndpointInfo>>> {
+    claims.require_admin()?;
+    let dispatcher = webhooks(&state)?;
+    tenant_webhook(dispatcher, &claims, &id)?;
+    let endpoint = dispatcher.update_endpoint(&id, update).map_err(webhook_error)?;
+    info!("{} updated webhook {}", claims.sub, id);
+    Ok(Json(ApiResponse::success(WebhookEndpointInfo::from(&endpoint))))
+}
+
+/// Remove a webhook endpoint (admin only)
+pub async fn delete_webhook(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    claims.require_admin()?;
+    let dispatcher = webhooks(&state)?;
+    tenant_webhook(dispatcher, &claims, &id)?;
+    dispatcher.unregister_endpoint(&id);
+    info!("{} removed webhook {}", claims.sub, id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Give a webhook endpoint a new signing secret; deliveries are signed with
+/// both secrets until the grace period ends (admin only)
+pub async fn rotate_webhook_secret(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(req): Json<RotateWebhookSecretRequest>,
+) -> Result<Json<ApiResponse<WebhookSecret>>> {
+    claims.require_admin()?;
+    let dispatcher = webhooks(&state)?;
+    tenant_webhook(dispatcher, &claims, &id)?;
+    let grace_hours = req.grace_hours.unwrap_or(DEFAULT_SECRET_GRACE_HOURS);
+    if grace_hours > MAX_SECRET_GRACE_HOURS {
+        return Err(ApiError::InvalidInput(format!(
+            "grace_hours must be at most {}",
+            MAX_SECRET_GRACE_HOURS
+        )));
+    }
+    let endpoint = dispatcher
+        .rotate_secret(&id, chrono::Duration::hours(grace_hours as i64))
+        .map_err(webhook_error)?;
+    info!("{} rotated the secret of webhook {} ({}h grace)", claims.sub, id, grace_hours);
+
+    Ok(Json(ApiResponse::success(WebhookSecret {
+        endpoint: WebhookEndpointInfo::from(&endpoint),
+        secret: endpoint.secret.clone(),
+    })))
+}
+
+/// Send a `webhook.ping` test delivery to an endpoint (admin only)
+pub async fn ping_webhook(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<WebhookPingResult>>> {
+    claims.require_admin()?;
+    let dispatcher = webhooks(&state)?;
+    tenant_webhook(dispatcher, &claims, &id)?;
+    let delivery = dispatcher.ping(&id).await.map_err(webhook_error)?;
+    let attempt = delivery.last_attempt();
+    let result = WebhookPingResult {
+        endpoint_id: id,
+        delivered: delivery.is_success(),
+        status_code: attempt.and_then(|a| a.status_code),
+        duration_ms: delivery.total_duration_ms(),
+        error: attempt.and_then(|a| a.error_message.clone()),
+    };
+    Ok(Json(ApiResponse::success(result)))
+}
+
+/// Get a webhook endpoint's delivery success and latency, overall and by
+/// event type (admin only)
+pub async fn get_webhook_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<EndpointDeliveryStats>>> {
+    claims.require_admin()?;
+    let dispatcher = webhooks(&state)?;
+    tenant_webhook(dispatcher, &claims, &id)?;
+    Ok(Json(ApiResponse::success(dispatcher.endpoint_stats(&id))))
+}
+
+/// List the payload schema versions webhook endpoints can be pinned to
+pub async fn list_webhook_schema_versions(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<Vec<SchemaVersionInfo>>>> {
+    Ok(Json(ApiResponse::success(webhooks(&state)?.schema_registry().versions())))
+}
+
+/// Get the JSON Schemas of the webhook envelope and payloads in a version
+pub async fn get_webhook_schemas(
+    State(state): State<Arc<AppState>>,
+    Path(version): Path<u32>,
+) -> Result<Json<ApiResponse<PayloadSchemas>>> {
+    let schemas = webhooks(&state)?
+        .schema_registry()
+        .schemas(version)
+        .ok_or_else(|| ApiError::NotFound(format!("Payload schema version {} not found", version)))?;
+    Ok(Json(ApiResponse::success(schemas)))
+}
+
+/// Get the tools and sandbox capabilities a session may use
+pub async fn get_tool_policy(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+) -> Result<Json<ApiResponse<ToolPolicy>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    let policy = state.conversation_manager.tool_policy(&session_id).await?;
+    Ok(Json(ApiResponse::success(policy)))
+}
+
+/// Set a session's tool allowlist and sandbox policy
+///
+/// Only the session's owner or an admin of its tenant may change it.
+/// Returns the effective policy, which never exceeds the persona's.
+pub async fn update_tool_policy(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+    Json(req): Json<UpdateToolPolicyRequest>,
+) -> Result<Json<ApiResponse<ToolPolicy>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    let policy = state
+        .conversation_manager
+        .set_tool_policy(&session_id, req.allowed_tools, req.sandbox)
+        .await?;
+    Ok(Json(ApiResponse::success(policy)))
+}
+
+/// Run code in a session's sandbox
+///
+/// The sandbox is created under the session's sandbox policy, and the
+/// session must be allowed the sandbox tool.
+pub async fn execute_in_sandbox(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+    Json(req): Json<ExecuteCodeRequest>,
+) -> Result<Json<ApiResponse<ToolResult>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    let result = state
+        .conversation_manager
+        .execute_code(&session_id, &req.runtime, &req.code, req.force_refresh)
+        .await?;
+    Ok(Json(ApiResponse::success(result)))
+}
+
+/// Fail unless the caller owns the session or is an admin of its tenant
+async fn require_session_owner(state: &AppState, claims: &Claims, session_id: &str) -> Result<()> {
+    match state.conversation_manager.session_owner(session_id).await {
+        Some((tenant_id, _)) if tenant_id != claims.tenant_id() => {
+            Err(ApiError::NotFound(format!("Session {} not found", session_id)))
+        }
+        Some((_, user_id)) if user_id == claims.sub => Ok(()),
+        _ => claims.require_admin(),
+    }
+}
+
+/// Query parameters for a session's working memory
+#[derive(Debug, Deserialize)]
+pub struct WorkingMemoryQuery {
+    /// Include notes that are not shown to the user
+    #[serde(default)]
+    pub include_hidden: bool,
+}
+
+/// Get a session's working notes; only those visible to the user unless
+/// `include_hidden` is set
+///
+/// Only the session's owner or an admin of its tenant may read them.
+pub async fn get_working_memory(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+    Query(query): Query<WorkingMemoryQuery>,
+) -> Result<Json<ApiResponse<WorkingMemoryView>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    let view = state.conversation_manager.working_memory().view(&session_id, query.include_hidden);
+    Ok(Json(ApiResponse::success(view)))
+}
+
+/// Where a session stands against its daily token budget
+pub async fn get_session_budget(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+) -> Result<Json<ApiResponse<BudgetStatus>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    let status = state
+        .conversation_manager
+        .budget_status(&session_id)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Session {} has no token budget", session_id)))?;
+    Ok(Json(ApiResponse::success(status)))
+}
+
+/// Add or replace a note in a session's working memory
+pub async fn write_working_note(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path((session_id, key)): Path<(String, String)>,
+    Json(req): Json<WriteWorkingNoteRequest>,
+) -> Result<Json<ApiResponse<WorkingNote>>> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    let mut note = WorkingNote::new(key, req.kind, req.content);
+    note.written_by = req.written_by;
+    note.visible = req.visible;
+    let note = state.conversation_manager.write_working_note(&session_id, note).await?;
+    Ok(Json(ApiResponse::success(note)))
+}
+
+/// Remove a note from a session's working memory
+pub async fn delete_working_note(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path((session_id, key)): Path<(String, String)>,
+) -> Result<StatusCode> {
+    require_session_owner(&state, &claims, &session_id).await?;
+    if !state.conversation_manager.working_memory().remove(&session_id, &key) {
+        return Err(ApiError::NotFound(format!("WorkingMemory entry {} not found", key)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn notifier(state: &AppState) -> Result<&Arc<TaskNotifier>> {
+    state
+        .notifier
+        .as_ref()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Notifications are not configured".to_string()))
+}
+
+/// Get the notification preferences that apply to the caller
+pub async fn get_notification_preferences(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<NotificationPreferences>>> {
+    let preferences = notifier(&state)?
+        .subscriptions()
+        .resolve(claims.tenant_id(), Some(&claims.sub));
+    Ok(Json(ApiResponse::success(preferences)))
+}
+
+/// Set the caller's own notification preferences
+pub async fn update_notification_preferences(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(preferences): Json<NotificationPreferences>,
+) -> Result<Json<ApiResponse<NotificationPreferences>>> {
+    if preferences.email.is_none()
+        && preferences.channels.contains(&NotificationChannel::Email)
+    {
+        return Err(ApiError::InvalidInput(
+            "An email address is required for email notifications".to_string(),
+        ));
+    }
+    notifier(&state)?
+        .subscriptions()
+        .set_user(claims.tenant_id(), &claims.sub, preferences.clone());
+    Ok(Json(ApiResponse::success(preferences)))
+}
+
+/// Drop the caller's own notification preferences so the tenant's apply
+pub async fn reset_notification_preferences(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<NotificationPreferences>>> {
+    let subscriptions = notifier(&state)?.subscriptions();
+    subscriptions.clear_user(claims.tenant_id(), &claims.sub);
+    let preferences = subscriptions.resolve(claims.tenant_id(), Some(&claims.sub));
+    Ok(Json(ApiResponse::success(preferences)))
+}
+
+/// Get the caller's preferences and the learned ones awaiting confirmation
+pub async fn get_user_preferences(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<UserPreferencesResponse>>> {
+    let preferences = state.conversation_manager.preferences();
+    Ok(Json(ApiResponse::success(UserPreferencesResponse {
+        preferences: preferences.get(claims.tenant_id(), &claims.sub),
+        suggestions: preferences.pending(claims.tenant_id(), &claims.sub),
+    })))
+}
+
+/// Replace the caller's preferences
+pub async fn update_user_preferences(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(preferences): Json<UserPreferences>,
+) -> Result<Json<ApiResponse<UserPreferences>>> {
+    let preferences = state
+        .conversation_manager
+        .preferences()
+        .set(claims.tenant_id(), &claims.sub, preferences)?;
+    info!("{} updated their preferences", claims.sub);
+    Ok(Json(ApiResponse::success(preferences)))
+}
+
+/// Forget everything remembered about the caller's preferences
+pub async fn delete_user_preferences(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode> {
+    state
+        .conversation_manager
+        .preferences()
+        .delete(claims.tenant_id(), &claims.sub);
+    info!("{} deleted their preferences", claims.sub);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Accept a learned preference
+pub async fn confirm_preference_suggestion(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<UserPreferences>>> {
+    let preferences = state
+        .conversation_manager
+        .preferences()
+        .confirm(claims.tenant_id(), &claims.sub, &id)?;
+    Ok(Json(ApiResponse::success(preferences)))
+}
+
+/// Reject a learned preference so it is not suggested again
+pub async fn dismiss_preference_suggestion(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    state
+        .conversation_manager
+        .preferences()
+        .dismiss(claims.tenant_id(), &claims.sub, &id)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List available personas
+pub async fn list_personas(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<Vec<Persona>>>> {
+    let registry = state.conversation_manager.persona_registry();
+    let personas = registry.read().await.list();
+    Ok(Json(ApiResponse::success(personas)))
+}
+
+/// Get a persona by ID
+pub async fn get_persona(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<Persona>>> {
+    let registry = state.conversation_manager.persona_registry();
+    let persona = registry
+        .read()
+        .await
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| ApiError::NotFound(format!("Persona {} not found", id)))?;
+    Ok(Json(ApiResponse::success(persona)))
+}
+
+/// Create a custom persona (admin only; personas are shared by all tenants)
+pub async fn create_persona(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(persona): Json<Persona>,
+) -> Result<(StatusCode, Json<ApiResponse<Persona>>)> {
+    claims.require_admin()?;
+    info!("Creating persona: {}", persona.id);
+
+    let registry = state.conversation_manager.persona_registry();
+    let mut registry = registry.write().await;
+    if registry.get(&persona.id).is_some() {
+        return Err(ApiError::InvalidInput(format!(
+            "Persona {} already exists",
+            persona.id
+        )));
+    }
+    let persona = registry.upsert(persona)?;
+
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(persona))))
+}
+
+/// Create or replace a custom persona (admin only)
+pub async fn update_persona(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(mut persona): Json<Persona>,
+) -> Result<Json<ApiResponse<Persona>>> {
+    claims.require_admin()?;
+    info!("Updating persona: {}", id);
+
+    persona.id = id;
+    let registry = state.conversation_manager.persona_registry();
+    let persona = registry.write().await.upsert(persona)?;
+
+    Ok(Json(ApiResponse::success(persona)))
+}
+
+/// Delete a custom persona (admin only)
+pub async fn delete_persona(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    claims.require_admin()?;
+    info!("Deleting persona: {}", id);
+
+    let registry = state.conversation_manager.persona_registry();
+    registry.write().await.remove(&id)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Create a new workflow
+pub async fn create_workflow(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateWorkflowRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<WorkflowResponse>>)> {
+    info!("Creating workflow: {}", req.name);
+
+    let workflow_id = Uuid::new_v4().to_string();
+    let now = Utc::now();
+
+    // TODO: Create workflow using CoPilot engine
+    // For now, return a mock response
+    let response = WorkflowResponse {
+        id: workflow_id.clone(),
+        name: req.name,
+        status: WorkflowStatus::Pending,
+        created_at: now,
+        updated_at: now,
+        result: None,
+        error: None,
+    };
+
+    info!("Workflow created: {}", workflow_id);
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(response))))
+}
+
+/// Get workflow status
+pub async fn get_workflow_status(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<WorkflowResponse>>> {
+    debug!("Getting workflow status: {}", id);
+
+    // TODO: Fetch workflow status from CoPilot engine
+    // For now, return a mock response
+    let now = Utc::now();
+    let response = WorkflowResponse {
+        id: id.clone(),
+        name: "Example Workflow".to_string(),
+        status: WorkflowStatus::Running,
+        created_at: now,
+        updated_at: now,
+        result: None,
+        error: None,
+    };
+
+    Ok(Json(ApiResponse::success(response)))
+}
+
+/// Submit a long-running agent task
+pub async fn submit_task(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<SubmitTaskRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<TaskInfo>>)> {
+    info!("Submitting {} task for tenant {}", req.kind, claims.tenant_id());
+
+    let task = state
+        .task_queue
+        .submit(claims.tenant_id(), Some(&claims.sub), &req.kind, req.priority, req.payload)?;
+
+    Ok((StatusCode::ACCEPTED, Json(ApiResponse::success(task))))
+}
+
+/// List the caller's tasks
+pub async fn list_tasks(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<Vec<TaskInfo>>>> {
+    Ok(Json(ApiResponse::success(state.task_queue.list(claims.tenant_id()))))
+}
+
+/// Get task status
+pub async fn get_task(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<TaskInfo>>> {
+    debug!("Getting task: {}", id);
+
+    let task = state
+        .task_queue
+        .get(claims.tenant_id(), id)
+        .ok_or_else(|| ApiError::NotFound(format!("Task {} not found", id)))?;
+
+    Ok(Json(ApiResponse::success(task)))
+}
+
+/// Cancel a queued or running task
+pub async fn cancel_task(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<TaskInfo>>> {
+    info!("Cancelling task: {}", id);
+
+    let task = state.task_queue.cancel(claims.tenant_id(), id)?;
+    Ok(Json(ApiResponse::success(task)))
+}
+
+/// Stream task progress as server-sent events until the task finishes
+pub async fn task_events(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    // Subscribe before taking the snapshot so no transition is missed
+    let receiver = state.task_queue.subscribe();
+    let current = state
+        .task_queue
+        .get(claims.tenant_id(), id)
+        .ok_or_else(|| ApiError::NotFound(format!("Task {} not found", id)))?;
+
+    let initial = TaskEvent {
+        task_id: current.id,
+        status: current.status,
+        progress: current.progress,
+        message: current.message,
+        timestamp: Utc::now(),
+    };
+
+    let events = stream::unfold(
+        (Some(initial), receiver, false),
+        move |(pending, mut receiver, finished)| async move {
+            if finished {
+                return None;
+            }
+            let event = match pending {
+                Some(event) => event,
+                None => loop {
+                    match receiver.recv().await {
+                        Ok(event) if event.task_id == id => break event,
+                        Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    }
+                },
+            };
+
+            let done = event.status.is_terminal();
+            let sse = Event::default()
+                .event("progress")
+                .json_data(&event)
+                .unwrap_or_else(|_| Event::default().event("error"));
+            Some((Ok(sse), (None, receiver, done)))
+        },
+    );
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Query parameters for document uploads
+#[derive(Debug, Deserialize)]
+pub struct IngestQuery {
+    /// Filename for raw (non-multipart) uploads
+    pub filename: Option<String>,
+    /// Source label attached to stored chunks
+    #[serde(default = "default_ingest_source")]
+    pub source: String,
+}
+
+fn default_ingest_source() -> String {
+    "upload".to_string()
+}
+
+/// Stream uploaded documents into the ingestion pipeline.
+///
+/// Accepts either `multipart/form-data` (one document per file field) or a
+/// raw, optionally chunked, text body. Bytes are ingested as they arrive.
+/// Trusted sources sign raw uploads with the `X-Copilot-Signer` and
+/// `X-Copilot-Signature` headers, and multipart uploads with the same
+/// headers on each file field.
+///
+/// Responds `202 Accepted` with the job as soon as the upload is admitted;
+/// the body is ingested in the background, so poll the job for its outcome.
+///
+/// Uploads beyond the tenant's concurrent ingestion limit wait for a
+/// running one to finish; uploads past its daily byte budget are rejected
+/// with `QUOTA_EXCEEDED`.
+pub async fn ingest_documents(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<IngestQuery>,
+    request: Request,
+) -> Result<(StatusCode, Json<ApiResponse<IngestionJob>>)> {
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("text/plain")
+        .to_string();
+    let content_length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    let service = state.ingestion.clone();
+    let job_id = service.start_job(claims.tenant_id(), Some(&claims.sub));
+    let permit = match service.admit(job_id, content_length).await {
+        Ok(permit) => permit,
+        Err(e) => {
+            warn!("Ingestion job {} rejected: {}", job_id, e);
+            service.fail(job_id, &e.to_string());
+            return Err(e);
+        }
+    };
+    info!("Ingestion job {} started ({})", job_id, content_type);
+
+    let job = service
+        .get(claims.tenant_id(), job_id)
+        .ok_or_else(|| ApiError::InternalError("Ingestion job lost".to_string()))?;
+    tokio::spawn(async move {
+        let _permit = permit;
+        let result = if content_type.starts_with("multipart/form-data") {
+            match Multipart::from_request(request, &state).await {
+                Ok(multipart) => ingest_multipart(&service, job_id, &query.source, multipart).await,
+                Err(e) => Err(ApiError::InvalidInput(e.to_string())),
+            }
+        } else {
+            let filename = query.filename.as_deref();
+            let signature = signature_claim(request.headers());
+            let stream = request.into_body().into_data_stream();
+            stream_document(&service, job_id, filename, &content_type, &query.source, signature.as_ref(), stream).await
+        };
+
+        match result {
+            Ok(()) => {
+                service.complete(job_id);
+            }
+            Err(e) => {
+                error!("Ingestion job {} failed: {}", job_id, e);
+                service.fail(job_id, &e.to_string());
+            }
+        }
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(ApiResponse::success(job))))
+}
+
+/// Get an ingestion job
+pub async fn get_ingestion_job(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<IngestionJob>>> {
+    let job = state
+        .ingestion
+        .get(claims.tenant_id(), id)
+        .ok_or_else(|| ApiError::NotFound(format!("Ingestion job {} not found", id)))?;
+    Ok(Json(ApiResponse::success(job)))
+}
+
+/// Query parameters for wiki export bootstraps
+#[derive(Debug, Deserialize)]
+pub struct BootstrapQuery {
+    /// Source label attached to stored chunks
+    #[serde(default = "default_bootstrap_source")]
+    pub source: String,
+}
+
+fn default_bootstrap_source() -> String {
+    "wiki".to_string()
+}
+
+/// Bootstrap the knowledge base from a wiki export (admin only)
+///
+/// The body is a Confluence space export (zip or `entities.xml`) or a zip
+/// of markdown files. Pages are ingested, summarized and indexed in the
+/// background; poll the job for its coverage report.
+pub async fn bootstrap_corpus(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<BootstrapQuery>,
+    body: axum::body::Bytes,
+) -> Result<(StatusCode, Json<ApiResponse<BootstrapJob>>)> {
+    claims.require_admin()?;
+    let job = state.bootstrap.submit(claims.tenant_id(), &query.source, &body)?;
+    info!(
+        "{} started bootstrap job {} ({:?} export, {} pages)",
+        claims.sub, job.id, job.format, job.total_pages
+    );
+    Ok((StatusCode::ACCEPTED, Json(ApiResponse::success(job))))
+}
+
+/// Get a bootstrap job and its coverage report
+pub async fn get_bootstrap_job(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<BootstrapJob>>> {
+    let job = state
+        .bootstrap
+        .get(claims.tenant_id(), id)
+        .ok_or_else(|| ApiError::NotFound(format!("Bootstrap job {} not found", id)))?;
+    Ok(Json(ApiResponse::success(job)))
+}
+
+/// Query parameters for listing replay captures
+#[derive(Debug, Deserialize)]
+pub struct ListReplaysQuery {
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+/// List the tenant's captured chat turns, newest first (admin only)
+pub async fn list_replays(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ListReplaysQuery>,
+) -> Result<Json<ApiResponse<Vec<ReplaySummary>>>> {
+    claims.require_admin()?;
+    let replays = replay_recorder(&state)?;
+    Ok(Json(ApiResponse::success(replays.list(claims.tenant_id(), query.limit).await?)))
+}
+
+/// Get a captured chat turn to replay (admin only)
+pub async fn get_replay(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<ReplayCapture>>> {
+    claims.require_admin()?;
+    let capture = replay_recorder(&state)?
+        .get(claims.tenant_id(), id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Replay capture {} not found", id)))?;
+    Ok(Json(ApiResponse::success(capture)))
+}
+
+fn replay_recorder(state: &AppState) -> Result<&crate::ReplayRecorder> {
+    state
+        .replays
+        .as_deref()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Replay capture is not enabled".to_string()))
+}
+
+/// List documents held in quarantine for review (admin only)
+pub async fn list_quarantined_documents(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<Vec<QuarantinedDocument>>>> {
+    claims.require_admin()?;
+    Ok(Json(ApiResponse::success(state.ingestion.quarantined(claims.tenant_id()))))
+}
+
+/// Get a quarantined document with its findings and held chunks (admin only)
+pub async fn get_quarantined_document(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<QuarantinedDocument>>> {
+    claims.require_admin()?;
+    let document = state
+        .ingestion
+        .quarantined_document(claims.tenant_id(), &id)
+        .ok_or_else(|| ApiError::NotFound(format!("Document {} is not quarantined", id)))?;
+    Ok(Json(ApiResponse::success(document)))
+}
+
+/// Store a reviewed document's held chunks (admin only)
+pub async fn release_quarantined_document(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<QuarantinedDocument>>> {
+    claims.require_admin()?;
+    let document = state
+        .ingestion
+        .release(claims.tenant_id(), &id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Document {} is not quarantined", id)))?;
+    info!("{} released {} quarantined chunks of {}", claims.sub, document.chunks.len(), id);
+    Ok(Json(ApiResponse::success(document)))
+}
+
+/// Drop a quarantined document's held chunks (admin only)
+pub async fn discard_quarantined_document(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    claims.require_admin()?;
+    state
+        .ingestion
+        .discard(claims.tenant_id(), &id)
+        .ok_or_else(|| ApiError::NotFound(format!("Document {} is not quarantined", id)))?;
+    info!("{} discarded quarantined document {}", claims.sub, id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Start a bulk delete, retag, re-embed or export of matching context items
+///
+/// The context engine is shared by all tenants, so this needs the platform
+/// scope rather than a tenant's admin scope.
+pub async fn submit_bulk_context_job(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<BulkRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<BulkJob>>)> {
+    claims.require_scope(PLATFORM_SCOPE)?;
+    let job = state.bulk.submit(claims.tenant_id(), req)?;
+    info!("Bulk context job {} started: {:?}", job.id, job.request.operation);
+    Ok((StatusCode::ACCEPTED, Json(ApiResponse::success(job))))
+}
+
+/// Get a bulk context job and its progress
+pub async fn get_bulk_context_job(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<BulkJob>>> {
+    let job = state
+        .bulk
+        .get(claims.tenant_id(), id)
+        .ok_or_else(|| ApiError::NotFound(format!("Bulk job {} not found", id)))?;
+    Ok(Json(ApiResponse::success(job)))
+}
+
+/// Download the items of a finished export job as newline-delimited JSON
+pub async fn export_bulk_context_job(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<([(header::HeaderName, &'static str); 1], String)> {
+    let body = bulk_export_ndjson(&state, claims.tenant_id(), id)?;
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], body))
+}
+
+/// Store the items of a finished export job as an export bundle and get a
+/// link to download it
+pub async fn store_bulk_context_export(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<ExportBundle>>> {
+    let storage = object_storage(&state, claims.tenant_id(), ResidencyOperation::Write)?;
+    let body = bulk_export_ndjson(&state, claims.tenant_id(), id)?;
+    let object = storage
+        .put(ObjectClass::ExportBundle, claims.tenant_id(), body.into_bytes(), "application/x-ndjson")
+        .await
+        .map_err(storage_error)?;
+    let download = storage.presign_download(&object.key).map_err(storage_error)?;
+    info!("{} stored export of bulk job {} as {}", claims.sub, id, object.key);
+    Ok(Json(ApiResponse::success(ExportBundle { object, download })))
+}
+
+fn bulk_export_ndjson(state: &AppState, tenant_id: &str, id: Uuid) -> Result<String> {
+    let items = state
+        .bulk
+        .export(tenant_id, id)
+        .ok_or_else(|| ApiError::NotFound(format!("No export for bulk job {}", id)))?;
+
+    let mut body = String::new();
+    for item in &items {
+        let line = serde_json::to_string(item).map_err(|e| ApiError::InternalError(e.to_string()))?;
+        body.push_str(&line);
+        body.push('\n');
+    }
+    Ok(body)
+}
+
+/// Object storage of the tenant's home region, refusing to `operation` on
+/// a store in another region when the tenant's data must stay in its own
+fn object_storage<'a>(
+    state: &'a AppState,
+    tenant_id: &str,
+    operation: ResidencyOperation,
+) -> Result<&'a Arc<ObjectStorage>> {
+    let residency = state.residency.for_tenant(tenant_id);
+    match state.object_storage.route(&residency) {
+        Ok(Some(storage)) => Ok(storage),
+        Ok(None) => Err(ApiError::ServiceUnavailable("Object storage is not configured".to_string())),
+        Err(out_of_region) => {
+            state
+                .residency_log
+                .record(residency.violation(operation, "object storage", Some(&state.residency.default_region)));
+            Err(ApiError::ServiceUnavailable(out_of_region.to_string()))
+        }
+    }
+}
+
+fn storage_error(error: InfraError) -> ApiError {
+    match error {
+        InfraError::NotFound(what) => ApiError::NotFound(format!("{} not found", what)),
+        other => ApiError::InternalError(other.to_string()),
+    }
+}
+
+/// Get a URL to upload an attachment or sandbox artifact to, unless the
+/// same content is already stored
+pub async fn create_file_upload(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<FileUploadRequest>,
+) -> Result<Json<ApiResponse<FileUpload>>> {
+    let storage = object_storage(&state, claims.tenant_id(), ResidencyOperation::Write)?;
+    if req.class == ObjectClass::ExportBundle {
+        return Err(ApiError::InvalidInput("Export bundles are created by export jobs".to_string()));
+    }
+    let sha256 = req.sha256.to_ascii_lowercase();
+    let key = content_key(req.class, claims.tenant_id(), &sha256)
+        .map_err(|e| ApiError::InvalidInput(e.to_string()))?;
+
+    let already_stored = storage.store().head(&key).await.map_err(storage_error)?.is_some();
+    let upload = if already_stored {
+        None
+    } else {
+        let (_, mut url) = storage
+            .presign_upload(req.class, claims.tenant_id(), &sha256)
+            .map_err(storage_error)?;
+        url.headers.push((header::CONTENT_TYPE.to_string(), req.content_type.clone()));
+        Some(url)
+    };
+    Ok(Json(ApiResponse::success(FileUpload {
+        key,
+        sha256,
+        already_stored,
+        upload,
+    })))
+}
+
+/// Get a URL to download a stored attachment, sandbox artifact or export
+/// bundle
+pub async fn get_file_download(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path((class, sha256)): Path<(ObjectClass, String)>,
+) -> Result<Json<ApiResponse<PresignedUrl>>> {
+    let storage = object_storage(&state, claims.tenant_id(), ResidencyOperation::Read)?;
+    let key = content_key(class, claims.tenant_id(), &sha256).map_err(|e| ApiError::InvalidInput(e.to_string()))?;
+    if storage.store().head(&key).await.map_err(storage_error)?.is_none() {
+        return Err(ApiError::NotFound(format!("No {} with digest {}", class.prefix(), sha256)));
+    }
+    Ok(Json(ApiResponse::success(storage.presign_download(&key).map_err(storage_error)?)))
+}
+
+fn local_objects(state: &AppState) -> Result<&Arc<LocalObjectStore>> {
+    state
+        .local_objects
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("Objects are not served by this server".to_string()))
+}
+
+/// Download an object through a presigned URL of the local object store
+pub async fn get_object(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+    Query(query): Query<PresignedQuery>,
+) -> Result<(HeaderMap, Vec<u8>)> {
+    let store = local_objects(&state)?;
+    store
+        .verify(&key, PresignMethod::Get, &query, Utc::now())
+        .map_err(|e| ApiError::AuthorizationFailed(e.to_string()))?;
+    let data = store.get(&key).await.map_err(storage_error)?;
+
+    let mut headers = HeaderMap::new();
+    let content_type = store.head(&key).await.ok().flatten().and_then(|meta| meta.content_type);
+    if let Some(value) = content_type.and_then(|value| value.parse().ok()) {
+        headers.insert(header::CONTENT_TYPE, value);
+    }
+    Ok((headers, data))
+}
+
+/// Upload an object through a presigned URL of the local object store
+pub async fn put_object(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+    Query(query): Query<PresignedQuery>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<StatusCode> {
+    let store = local_objects(&state)?;
+    store
+        .verify(&key, PresignMethod::Put, &query, Utc::now())
+        .map_err(|e| ApiError::AuthorizationFailed(e.to_string()))?;
+    if let Some(expected) = &query.sha256 {
+        if !sha256_hex(&body).eq_ignore_ascii_case(expected) {
+            return Err(ApiError::InvalidInput("Body does not match the presigned digest".to_string()));
+        }
+    }
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("application/octet-stream");
+    store.put(&key, body.to_vec(), content_type).await.map_err(storage_error)?;
+    Ok(StatusCode::OK)
+}
+
+/// Query parameters naming a source prefix
+#[derive(Debug, Deserialize)]
+pub struct SourceQuery {
+    pub source: String,
+}
+
+fn source_weights(state: &AppState) -> Vec<SourceWeight> {
+    state
+        .conversation_manager
+        .context_engine()
+        .source_weights()
+        .into_iter()
+        .map(|(source, weight)| SourceWeight { source, weight })
+        .collect()
+}
+
+/// Get the caller's consent to be impersonated by admins
+pub async fn get_impersonation_consent(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<ImpersonationConsent>>> {
+    let consent = state
+        .impersonation
+        .consent(&claims)
+        .ok_or_else(|| ApiError::NotFound("No impersonation consent is active".to_string()))?;
+    Ok(Json(ApiResponse::success(consent)))
+}
+
+/// Let admins of the caller's tenant impersonate them for a while
+pub async fn grant_impersonation_consent(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<ConsentRequest>,
+) -> Result<Json<ApiResponse<ImpersonationConsent>>> {
+    let consent = state.impersonation.grant_consent(&claims, &req)?;
+    info!("{} consented to impersonation until {}", claims.sub, consent.expires_at);
+    Ok(Json(ApiResponse::success(consent)))
+}
+
+/// Withdraw the caller's consent, ending impersonations of them
+pub async fn revoke_impersonation_consent(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode> {
+    if !state.impersonation.revoke_consent(&claims).await? {
+        return Err(ApiError::NotFound("No impersonation consent is active".to_string()));
+    }
+    info!("{} revoked impersonation consent", claims.sub);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Start acting as a consenting user, with a reason (admin only)
+pub async fn start_impersonation(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<ImpersonationRequest>,
+) -> Result<Json<ApiResponse<ImpersonationToken>>> {
+    let started = state
+        .impersonation
+        .start(&claims, &req, &state.jwt_secret, state.signing_keys.as_ref())
+        .await?;
+    warn!(
+        "{} is impersonating {} until {}: {}",
+        claims.sub, req.user_id, started.impersonation.expires_at, started.impersonation.reason
+    );
+    Ok(Json(ApiResponse::success(started)))
+}
+
+/// List impersonations in the caller's tenant, newest first (admin only)
+pub async fn list_impersonations(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<Vec<Impersonation>>>> {
+    claims.require_admin()?;
+    Ok(Json(ApiResponse::success(state.impersonation.list(claims.tenant_id()))))
+}
+
+/// End an impersonation, invalidating its token (admin only)
+pub async fn end_impersonation(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    claims.require_admin()?;
+    state
+        .impersonation
+        .end(claims.tenant_id(), id, &claims.sub)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Impersonation {} is not active", id)))?;
+    info!("{} ended impersonation {}", claims.sub, id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List the authority weights of context sources (admin only)
+pub async fn list_source_weights(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<Vec<SourceWeight>>>> {
+    claims.require_admin()?;
+    Ok(Json(ApiResponse::success(source_weights(&state))))
+}
+
+/// Set the authority weight of a context source (admin only); the next
+/// retrieval uses it
+pub async fn set_source_weight(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<SourceWeight>,
+) -> Result<Json<ApiResponse<Vec<SourceWeight>>>> {
+    claims.require_admin()?;
+    state
+        .conversation_manager
+        .context_engine()
+        .set_source_weight(&req.source, req.weight)
+        .map_err(|e| ApiError::InvalidInput(e.to_string()))?;
+    info!("{} set authority of {} to {}", claims.sub, req.source, req.weight);
+    Ok(Json(ApiResponse::success(source_weights(&state))))
+}
+
+/// Return a context source to the neutral weight (admin only)
+pub async fn remove_source_weight(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<SourceQuery>,
+) -> Result<Json<ApiResponse<Vec<SourceWeight>>>> {
+    claims.require_admin()?;
+    if !state.conversation_manager.context_engine().remove_source_weight(&query.source) {
+        return Err(ApiError::NotFound(format!("No weight for source {}", query.source)));
+    }
+    info!("{} removed the authority weight of {}", claims.sub, query.source);
+    Ok(Json(ApiResponse::success(source_weights(&state))))
+}
+
+/// Run a workflow or benchmark gate for a GitHub check run
+///
+/// Responds once the gate has finished; a failing gate is still a
+/// successful request whose verdict has a `failure` conclusion. Workflow
+/// gates run the caller's tenant's gate workflows; benchmark gates exercise
+/// the whole server and are admin only.
+pub async fn run_github_gate(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<GateRequest>,
+) -> Result<Json<ApiResponse<GateVerdict>>> {
+    if matches!(req.target, GateTarget::Benchmarks { .. }) {
+        claims.require_admin()?;
+    }
+    info!(
+        "{} running gate for {}@{}",
+        claims.sub, req.context.repository, req.context.head_sha
+    );
+    let verdict = state.gates.evaluate(claims.tenant_id(), req).await?;
+    Ok(Json(ApiResponse::success(verdict)))
+}
+
+/// Query parameters for listing workflow templates
+#[derive(Debug, Deserialize)]
+pub struct TemplateListQuery {
+    pub category: Option<String>,
+}
+
+/// List the workflow templates that can be run
+pub async fn list_templates(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TemplateListQuery>,
+) -> Result<Json<ApiResponse<Vec<TemplateSummary>>>> {
+    let templates = state.runs.list(query.category.as_deref()).await?;
+    Ok(Json(ApiResponse::success(templates)))
+}
+
+/// Get the parameters a template is run with
+pub async fn get_template_parameters(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<ParameterSchema>>> {
+    let schema = state.runs.schema(&id).await?;
+    Ok(Json(ApiResponse::success(schema)))
+}
+
+/// Validate parameters and start a template run for the caller's tenant
+///
+/// Invalid parameters are rejected with `422` and one entry per offending
+/// field under `details`. Dry runs only validate.
+pub async fn run_template(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(req): Json<ManualRunRequest>,
+) -> Result<Json<ApiResponse<ManualRun>>> {
+    info!("{} running template {} (dry run: {})", claims.sub, id, req.dry_run);
+    let run = state.runs.run(claims.tenant_id(), &id, req).await?;
+    Ok(Json(ApiResponse::success(run)))
+}
+
+/// Stream the steps of a run of the caller's tenant as server-sent events
+/// until it finishes
+///
+/// The first event is a snapshot of the run; each following one is a step
+/// starting, being retried, completing or failing, or the run ending.
+pub async fn run_events(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    let (snapshot, receiver) = state.runs.watch(claims.tenant_id(), &id).await?;
+
+    let events = stream::unfold(
+        (Some(snapshot), receiver, false),
+        move |(pending, mut receiver, finished)| {
+            let id = id.clone();
+            async move {
+                if finished {
+                    return None;
+                }
+                let event = match pending {
+                    Some(event) => event,
+                    None => loop {
+                        match receiver.recv().await {
+                            Ok(event) if event.state.execution_id == id => break RunEvent::from(&event),
+                            Ok(_) => continue,
+                            Err(RecvError::Lagged(missed)) => {
+                                warn!(run = %id, missed, "Run event stream fell behind");
+                                continue;
+                            }
+                            Err(RecvError::Closed) => return None,
+                        }
+                    },
+                };
+
+                let done = event.is_last();
+                let name = if event.kind.is_some() { "step" } else { "snapshot" };
+                let sse = Event::default()
+                    .event(name)
+                    .json_data(&event)
+                    .unwrap_or_else(|_| Event::default().event("error"));
+                Some((Ok(sse), (None, receiver, done)))
+            }
+        },
+    );
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// List the workflow schedules of the caller's tenant
+pub async fn list_schedules(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<Vec<ScheduledWorkflow>>>> {
+    let schedules = state.schedules.list(claims.tenant_id()).await?;
+    Ok(Json(ApiResponse::success(schedules)))
+}
+
+/// Get a workflow schedule of the caller's tenant
+pub async fn get_schedule(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<ScheduledWorkflow>>> {
+    let schedule = state.schedules.get(&id, claims.tenant_id()).await?;
+    Ok(Json(ApiResponse::success(schedule)))
+}
+
+/// Pause a workflow schedule of the caller's tenant
+pub async fn pause_schedule(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<ScheduledWorkflow>>> {
+    info!("{} pausing schedule {}", claims.sub, id);
+    let schedule = state.schedules.pause(&id, claims.tenant_id()).await?;
+    Ok(Json(ApiResponse::success(schedule)))
+}
+
+/// Resume a paused workflow schedule from its next fire time, with the
+/// same authorization as pausing it
+pub async fn resume_schedule(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<ScheduledWorkflow>>> {
+    info!("{} resuming schedule {}", claims.sub, id);
+    let schedule = state.schedules.resume(&id, claims.tenant_id()).await?;
+    Ok(Json(ApiResponse::success(schedule)))
+}
+
+/// Preview the next fire times of a schedule
+///
+/// Schedules that cannot fire, such as malformed cron expressions, are
+/// rejected with `400`.
+pub async fn preview_schedule(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SchedulePreviewRequest>,
+) -> Result<Json<ApiResponse<SchedulePreview>>> {
+    let preview = state.schedules.preview(&req)?;
+    Ok(Json(ApiResponse::success(preview)))
+}
+
+/// Query parameters for the dashboard stream
+#[derive(Debug, Deserialize)]
+pub struct DashboardStreamQuery {
+    /// Milliseconds between snapshots
+    #[serde(default = "default_dashboard_interval")]
+    pub interval_ms: u64,
+}
+
+fn default_dashboard_interval() -> u64 {
+    1_000
+}
+
+/// Get a snapshot of server activity (admin only; it spans all tenants)
+pub async fn get_dashboard(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<DashboardSnapshot>>> {
+    claims.require_admin()?;
+    let snapshot = state.stats.snapshot(state.task_queue.stats());
+    Ok(Json(ApiResponse::success(snapshot)))
+}
+
+/// Stream server activity snapshots as server-sent events (admin only)
+pub async fn dashboard_events(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<DashboardStreamQuery>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    claims.require_admin()?;
+    let period = std::time::Duration::from_millis(query.interval_ms.clamp(250, 60_000));
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    let events = stream::unfold((state, interval), |(state, mut interval)| async move {
+        interval.tick().await;
+        let snapshot = state.stats.snapshot(state.task_queue.stats());
+        let sse = Event::default()
+            .event("snapshot")
+            .json_data(&snapshot)
+            .unwrap_or_else(|_| Event::default().event("error"));
+        Some((Ok(sse), (state, interval)))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Generate a Grafana dashboard from a natural language ask, optionally
+/// saving it to the configured Grafana
+pub async fn generate_dashboard(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<DashboardRequest>,
+) -> Result<Json<ApiResponse<GeneratedDashboard>>> {
+    let dashboard = state
+        .dashboard_generator
+        .generate(&req.ask)
+        .map_err(|e| ApiError::InvalidInput(e.to_string()))?;
+    let dashboard = serde_json::to_value(&dashboard).map_err(|e| ApiError::InternalError(e.to_string()))?;
+
+    let pushed = if req.push {
+        let observatory = state
+            .observatory
+            .as_ref()
+            .ok_or_else(|| ApiError::ServiceUnavailable("Grafana is not configured".to_string()))?;
+        let push = DashboardPush {
+            dashboard: dashboard.clone(),
+            folder_uid: req.folder_uid.clone(),
+            overwrite: false,
+            message: Some(format!("Generated from: {}", req.ask)),
+        };
+        let pushed = observatory
+            .push_dashboard(push)
+            .await
+            .map_err(|e| ApiError::ServiceUnavailable(e.to_string()))?;
+        info!("{} pushed generated dashboard {} to Grafana", claims.sub, pushed.uid);
+        Some(pushed)
+    } else {
+        None
+    };
+
+    Ok(Json(ApiResponse::success(GeneratedDashboard { dashboard, pushed })))
+}
+
+/// Default back-test window: one week
+const DEFAULT_ALERT_LOOKBACK_HOURS: u32 = 168;
+
+/// Most points a back-test asks a range query for, per series
+const MAX_BACKTEST_POINTS: i64 = 10_000;
+
+/// Generate an alert rule from a natural language description and back-test it
+pub async fn generate_alert_rule(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<AlertRuleRequest>,
+) -> Result<Json<ApiResponse<GeneratedAlertRule>>> {
+    let draft = state
+        .alert_generator
+        .generate(&req.description)
+        .map_err(|e| ApiError::InvalidInput(e.to_string()))?;
+    info!("{} generated alert rule {}", claims.sub, draft.rule.alert);
+
+    let (backtest, backtest_error) = if req.backtest {
+        let lookback_hours = req.lookback_hours.unwrap_or(DEFAULT_ALERT_LOOKBACK_HOURS).max(1);
+        match backtest_alert_rule(&state, &draft, lookback_hours).await {
+            Ok(backtest) => (Some(backtest), None),
+            Err(e) => {
+                debug!("Back-test of alert rule {} failed: {}", draft.rule.alert, e);
+                (None, Some(e.to_string()))
+            }
+        }
+    } else {
+        (None, None)
+    };
+
+    Ok(Json(ApiResponse::success(GeneratedAlertRule {
+        language: draft.language,
+        rule: draft.rule,
+        backtest,
+        backtest_error,
+    })))
+}
+
+/// Replays an alert rule over the last `lookback_hours` of data.
+async fn backtest_alert_rule(state: &AppState, draft: &AlertDraft, lookback_hours: u32) -> Result<AlertBacktest> {
+    let observatory = state
+        .observatory
+        .as_ref()
+        .ok_or_else(|| ApiError::ServiceUnavailable("No observability stack is configured".to_string()))?;
+    let for_secs = draft
+        .rule
+        .for_secs()
+        .ok_or_else(|| ApiError::InvalidInput(format!("Invalid for duration: {}", draft.rule.for_duration)))?;
+
+    let window_secs = i64::from(lookback_hours) * 3_600;
+    let step_secs = (window_secs / MAX_BACKTEST_POINTS).max(60);
+    let end_time = Utc::now();
+    // A comparison filters the result to the points where the rule held
+    let query = MetricsQuery {
+        query: draft.rule.expr.clone(),
+        start_time: end_time - chrono::Duration::seconds(window_secs),
+        end_time,
+        step: Some(format!("{}s", step_secs)),
+    };
+    let response = match draft.language {
+        QueryLanguage::LogQL => observatory.query_log_metrics(query).await,
+        _ => observatory.query_metrics(query).await,
+    }
+    .map_err(|e| ApiError::ServiceUnavailable(e.to_string()))?;
+
+    let series: Vec<Vec<i64>> = response
+        .data
+        .result
+        .iter()
+        .map(|result| result.values.iter().map(|(timestamp, _)| *timestamp as i64).collect())
+        .collect();
+    Ok(AlertBacktest::evaluate(&series, for_secs, step_secs, window_secs))
+}
+
+/// Largest log upload accepted for analysis
+pub(crate) const MAX_LOG_ANALYSIS_BYTES: usize = 16 * 1024 * 1024;
+
+/// Cluster log lines into patterns, flag anomalies and summarize them
+pub async fn analyze_logs(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<LogAnalysisRequest>,
+) -> Result<Json<ApiResponse<LogAnalysisReport>>> {
+    let mut clusterer = LogClusterer::new();
+    clusterer.add_lines(req.logs.lines());
+    if clusterer.line_count() == 0 {
+        return Err(ApiError::InvalidInput("No log lines to analyze".to_string()));
+    }
+    let analysis = clusterer.analysis(req.top_patterns.unwrap_or(10));
+    info!(
+        "{} analyzed {} log lines in {} patterns",
+        claims.sub, analysis.total_lines, analysis.pattern_count
+    );
+
+    let summary = if req.summarize {
+        let prompt = format!("{}\n\n{}", LOG_SUMMARY_PROMPT, analysis.summary_task());
+        let manager = &state.conversation_manager;
+        let summary = match manager.create_session(None, None).await {
+            Ok(session) => manager.generate_response(&session.id, &prompt).await,
+            Err(e) => Err(e),
+        };
+        match summary {
+            Ok(summary) if !summary.trim().is_empty() => summary,
+            Ok(_) => analysis.describe(),
+            Err(e) => {
+                warn!("Log summary failed, using the plain description: {}", e);
+                analysis.describe()
+            }
+        }
+    } else {
+        analysis.describe()
+    };
+
+    Ok(Json(ApiResponse::success(LogAnalysisReport { analysis, summary })))
+}
+
+async fn ingest_multipart(
+    service: &IngestionService,
+    job_id: Uuid,
+    source: &str,
+    mut multipart: Multipart,
+) -> Result<()> {
+    let mut documents = 0;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError::InvalidInput(e.to_string()))?
+    {
+        // Only file fields are documents; ignore plain form values
+        let Some(filename) = field.file_name().map(str::to_string) else {
+            continue;
+        };
+        let content_type = field.content_type().unwrap_or("text/plain").to_string();
+        let signature = signature_claim(field.headers());
+        stream_document(service, job_id, Some(&filename), &content_type, source, signature.as_ref(), field).await?;
+        documents += 1;
+    }
+
+    if documents == 0 {
+        return Err(ApiError::InvalidInput("No file fields in upload".to_string()));
+    }
+    Ok(())
+}
+
+/// The signature a trusted source attached to an upload, if any
+fn signature_claim(headers: &HeaderMap) -> Option<SignatureClaim> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    Some(SignatureClaim::new(header(SIGNER_HEADER)?, header(SIGNATURE_HEADER)?))
+}
+
+async fn stream_document<S, B, E>(
+    service: &IngestionService,
+    job_id: Uuid,
+    filename: Option<&str>,
+    content_type: &str,
+    source: &str,
+    signature: Option<&SignatureClaim>,
+    stream: S,
+) -> Result<()>
+where
+    S: Stream<Item = std::result::Result<B, E>>,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+    let mut ingestor = service.ingestor(job_id, filename, content_type, source, signature)?;
+    let mut counted = 0;
+
+    futures::pin_mut!(stream);
+    while let Some(piece) = stream.next().await {
+        let piece = piece.map_err(|e| ApiError::InvalidInput(format!("Upload interrupted: {}", e)))?;
+        let piece = piece.as_ref();
+        service.charge(job_id, piece.len() as u64)?;
+        let chunks = ingestor.push(piece).await.map_err(ingestion_error)?;
+        counted += chunks;
+        service.record_progress(job_id, piece.len() as u64, chunks);
+    }
+
+    let summary = ingestor.finish().await.map_err(ingestion_error)?;
+    service.record_document(job_id, summary, counted);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_state() -> Arc<AppState> {
+        let context_engine = Arc::new(
+            copilot_context::ContextEngineImpl::new(copilot_context::ContextEngineConfig::default()).unwrap(),
+        );
+        let manager = Arc::new(copilot_conversation::ConversationManager::new(
+            Arc::new(copilot_nlp::NlpEngineImpl::default()),
+            context_engine,
+        ));
+        Arc::new(AppState::new(Arc::new(copilot_core::CoPilotEngine::new()), manager, "secret".to_string()))
+    }
+
+    fn claims(scope: &str) -> Claims {
+        Claims {
+            sub: "user-1".to_string(),
+            exp: usize::MAX,
+            iat: 0,
+            additional: serde_json::json!({ "tenant_id": "acme", "scope": scope }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_check() {
+        let result = health_check().await;
+        assert!(result.is_ok());
+        let health = result.unwrap().0;
+        assert_eq!(health.status, "healthy");
+    }
+
+    #[tokio::test]
+    async fn test_bulk_jobs_need_the_platform_scope() {
+        let state = test_state();
+        let request = || crate::bulk::BulkRequest {
+            filter: ContextFilter {
+                source_prefix: Some("h".to_string()),
+                ..Default::default()
+            },
+            operation: crate::bulk::BulkOperation::Delete,
+        };
+
+        let denied = submit_bulk_context_job(State(state.clone()), Extension(claims("read")), Json(request())).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = submit_bulk_context_job(State(state.clone()), Extension(claims("admin")), Json(request())).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+
+        let (status, _) = submit_bulk_context_job(State(state), Extension(claims("platform")), Json(request()))
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::ACCEPTED);
+    }
+
+    #[tokio::test]
+    async fn test_persona_changes_need_admin() {
+        let state = test_state();
+        let persona = || Persona::new("support", "Support", "Answer as the support desk");
+
+        let denied = create_persona(State(state.clone()), Extension(claims("read")), Json(persona())).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = delete_persona(State(state.clone()), Extension(claims("read")), Path("support".to_string()));
+        assert_eq!(denied.await.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+
+        let (status, _) = create_persona(State(state), Extension(claims("admin")), Json(persona())).await.unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_prompts_are_audited_by_logging_mode() {
+        let logger = Arc::new(copilot_security::InMemoryAuditLogger::new());
+        let state = Arc::try_unwrap(test_state())
+            .ok()
+            .unwrap()
+            .with_audit_logger(logger.clone())
+            .with_prompt_logging(
+                copilot_core::PromptLogPolicy::new(PromptLogging::Hashed).with_tenant("globex", PromptLogging::Off),
+            );
+        let mode = |tenant: &str| state.prompt_logging.for_tenant(tenant);
+
+        audit_prompt(&state, &claims("read"), "s1", "session.chat", "rotate the prod keys", mode("acme")).await;
+        audit_prompt(&state, &claims("read"), "s1", "session.chat", "rotate the prod keys", mode("globex")).await;
+
+        let events = logger.get_events().await;
+        assert_eq!(events.len(), 1);
+        assert!(events[0].metadata["prompt"].as_str().unwrap().starts_with("sha256:"));
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_gates_need_admin() {
+        let state = test_state();
+        let request = || GateRequest {
+            context: crate::gates::CheckRunContext {
+                repository: "acme/widgets".to_string(),
+                head_sha: "0123abc".to_string(),
+                head_branch: None,
+                pull_request: None,
+                run_id: None,
+                check_name: None,
+                details_url: None,
+            },
+            target: GateTarget::Benchmarks { filter: String::new(), max_duration_ms: None },
+            timeout_secs: None,
+        };
+
+        let denied = run_github_gate(State(state.clone()), Extension(claims("read")), Json(request())).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+
+        // No suite is configured in tests
+        let unavailable = run_github_gate(State(state), Extension(claims("admin")), Json(request())).await;
+        assert_eq!(unavailable.err().unwrap().into_response().status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_session_sandbox_settings_need_the_owner() {
+        let state = test_state();
+        let manager = &state.conversation_manager;
+        let session = manager.create_session(Some("code-reviewer"), None).await.unwrap();
+        manager.set_session_owner(&session.id, "acme", "user-2").await.unwrap();
+        let widen = || {
+            Json(UpdateToolPolicyRequest {
+                allowed_tools: None,
+                sandbox: Some(copilot_core::SandboxPolicy::unrestricted()),
+            })
+        };
+
+        let denied = update_tool_policy(
+            State(state.clone()),
+            Extension(claims("read")),
+            Path(session.id.clone()),
+            widen(),
+        )
+        .await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let run = || {
+            Json(ExecuteCodeRequest {
+                code: "print(1)".to_string(),
+                runtime: "python".to_string(),
+                force_refresh: false,
+            })
+        };
+        let denied =
+            execute_in_sandbox(State(state.clone()), Extension(claims("read")), Path(session.id.clone()), run()).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+
+        let updated = update_tool_policy(
+            State(state.clone()),
+            Extension(claims("admin")),
+            Path(session.id.clone()),
+            widen(),
+        )
+        .await;
+        assert!(updated.is_ok());
+
+        // No sandbox runner is configured in tests
+        manager.set_session_owner(&session.id, "acme", "user-1").await.unwrap();
+        let unavailable =
+            execute_in_sandbox(State(state.clone()), Extension(claims("read")), Path(session.id.clone()), run()).await;
+        assert_eq!(unavailable.err().unwrap().into_response().status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        manager.set_session_owner(&session.id, "globex", "user-1").await.unwrap();
+        let hidden = update_tool_policy(State(state), Extension(claims("admin")), Path(session.id), widen()).await;
+        assert_eq!(hidden.err().unwrap().into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_chat_needs_the_session_owner() {
+        let state = test_state();
+        let manager = &state.conversation_manager;
+        let session = manager.create_session(Some("code-reviewer"), None).await.unwrap();
+        manager.set_session_owner(&session.id, "acme", "user-2").await.unwrap();
+        let turn = || {
+            Json(SessionChatRequest {
+                message: "hello".to_string(),
+                metadata: Default::default(),
+                model: None,
+            })
+        };
+
+        let denied =
+            chat_in_session(State(state.clone()), Extension(claims("read")), Path(session.id.clone()), turn()).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied =
+            stream_chat_in_session(State(state.clone()), Extension(claims("read")), Path(session.id.clone()), turn())
+                .await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = resume_chat_stream(
+            State(state.clone()),
+            Extension(claims("read")),
+            Path((session.id.clone(), "stream-1".to_string())),
+            Query(ResumeStreamQuery { after: None }),
+            HeaderMap::new(),
+        )
+        .await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = get_stream_stats(State(state.clone()), Extension(claims("read"))).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+
+        manager.set_session_owner(&session.id, "globex", "user-1").await.unwrap();
+        let hidden = chat_in_session(State(state), Extension(claims("admin")), Path(session.id), turn()).await;
+        assert_eq!(hidden.err().unwrap().into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_session_reads_need_the_owner() {
+        let state = test_state();
+        let manager = &state.conversation_manager;
+        let session = manager.create_session(Some("code-reviewer"), None).await.unwrap();
+        manager.set_session_owner(&session.id, "acme", "user-2").await.unwrap();
+        let caller = || Extension(claims("read"));
+        let id = || Path(session.id.clone());
+
+        let denied = get_session_history(State(state.clone()), caller(), id()).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = get_context_diff(
+            State(state.clone()),
+            caller(),
+            id(),
+            Query(ContextDiffQuery { from: None, to: None }),
+        )
+        .await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = list_context_snapshots(State(state.clone()), caller(), id()).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = get_proposed_edits(State(state.clone()), caller(), id()).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = list_comparisons(State(state.clone()), caller(), id()).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = get_tool_policy(State(state.clone()), caller(), id()).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let typing = Json(PrefetchContextRequest { query: "deploy".to_string() });
+        let denied = prefetch_context(State(state.clone()), caller(), id(), typing).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = hand_off_session(State(state.clone()), caller(), id(), Json(HandoffRequest::default())).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = get_session_budget(State(state.clone()), caller(), id()).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied =
+            confirm_resolution(State(state.clone()), caller(), id(), Json(ResolutionRequest { resolved: true })).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let rating = Json(FeedbackRequest { helpful: false });
+        let denied = submit_session_feedback(State(state.clone()), caller(), id(), rating).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+
+        let history = get_session_history(State(state.clone()), Extension(claims("admin")), id()).await;
+        assert!(history.is_ok());
+        manager.set_session_owner(&session.id, "globex", "user-1").await.unwrap();
+        let hidden = get_session_history(State(state), Extension(claims("admin")), id()).await;
+        assert_eq!(hidden.err().unwrap().into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_stats_need_an_admin() {
+        let state = test_state();
+
+        let denied = model_preference_stats(State(state.clone()), Extension(claims("read"))).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = get_prefetch_stats(State(state.clone()), Extension(claims("read"))).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = get_embedding_cache_stats(State(state.clone()), Extension(claims("read"))).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = get_model_fallback_stats(State(state.clone()), Extension(claims("read"))).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+
+        let stats = model_preference_stats(State(state), Extension(claims("admin"))).await;
+        assert!(stats.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_working_memory_needs_the_owner() {
+        let state = test_state();
+        let manager = &state.conversation_manager;
+        let session = manager.create_session(Some("code-reviewer"), None).await.unwrap();
+        manager.set_session_owner(&session.id, "acme", "user-2").await.unwrap();
+        let all = || Query(WorkingMemoryQuery { include_hidden: true });
+
+        let denied =
+            get_working_memory(State(state.clone()), Extension(claims("read")), Path(session.id.clone()), all()).await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = delete_working_note(
+            State(state.clone()),
+            Extension(claims("read")),
+            Path((session.id.clone(), "plan".to_string())),
+        )
+        .await;
+        assert_eq!(denied.err().unwrap().into_response().status(), StatusCode::FORBIDDEN);
+
+        let read =
+            get_working_memory(State(state.clone()), Extension(claims("admin")), Path(session.id.clone()), all()).await;
+        assert!(read.is_ok());
+
+        manager.set_session_owner(&session.id, "globex", "user-1").await.unwrap();
+        let hidden = get_working_memory(State(state), Extension(claims("admin")), Path(session.id), all()).await;
+        assert_eq!(hidden.err().unwrap().into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_default_limit() {
+        assert_eq!(default_limit(), 50);
+    }
+
+    #[test]
+    fn test_dashboard_stream_query_default() {
+        let query: DashboardStreamQuery = serde_json::from_str("{}").unwrap();
+        assert_eq!(query.interval_ms, 1_000);
+    }
+
+    #[test]
+    fn test_context_search_query_filter() {
+        let uri = "/context/search?q=cache&tags=backend,%20core,&source=file:///repo/&type=code&since=2026-01-01T00:00:00Z&as_of=2026-03-03T00:00:00Z"
+            .parse()
+            .unwrap();
+        let Query(query) = Query::<ContextSearchQuery>::try_from_uri(&uri).unwrap();
+        assert_eq!(query.limit, 10);
+
+        let filter = query.filter();
+        assert_eq!(filter.tags, vec!["backend", "core"]);
+        assert_eq!(filter.source_prefix.as_deref(), Some("file:///repo/"));
+        assert_eq!(filter.content_type.as_deref(), Some("code"));
+        assert!(filter.ingested_after.is_some());
+        assert_eq!(filter.as_of.unwrap().to_rfc3339(), "2026-03-03T00:00:00+00:00");
+        assert!(filter.language.is_none());
+    }
+
+    #[test]
+    fn test_presigned_object_query() {
+        let root = std::env::temp_dir().join(format!("copilot-api-objects-{}", Uuid::new_v4()));
+        let store = LocalObjectStore::new(root, "http://localhost:8080/api/objects", b"key").unwrap();
+        let url = store
+            .presign(
+                "attachments/acme/report.pdf",
+                &copilot_infra::PresignRequest::put(std::time::Duration::from_secs(60)).with_content_sha256("ab12"),
+            )
+            .unwrap();
+
+        let uri = url.url.trim_start_matches("http://localhost:8080/api").parse().unwrap();
+        let Query(query) = Query::<PresignedQuery>::try_from_uri(&uri).unwrap();
+        assert_eq!(query.sha256.as_deref(), Some("ab12"));
+        store
+            .verify("attachments/acme/report.pdf", PresignMethod::Put, &query, Utc::now())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_context_diff_query_deserialization() {
+        let query: ContextDiffQuery = serde_json::from_str(r#"{"from": 3}"#).unwrap();
+        assert_eq!(query.from, Some(3));
+        assert_eq!(query.to, None);
     }
 
     #[test]
@@ -233,4 +3548,11 @@ mod tests {
         assert_eq!(query.limit, 100);
         assert_eq!(query.cursor, None);
     }
+
+    #[test]
+    fn test_logged_content_follows_mode() {
+        assert_eq!(logged_content(PromptLogging::Full, "hi"), ": \"hi\"");
+        assert!(logged_content(PromptLogging::Hashed, "hi").starts_with(": \"sha256:"));
+        assert_eq!(logged_content(PromptLogging::MetadataOnly, "hi"), "");
+    }
 }
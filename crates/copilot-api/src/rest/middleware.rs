@@ -1,20 +1,25 @@
 //! Middleware for REST API
 
-use crate::{error::ApiError, types::Claims, AppState};
+use crate::{
+    error::ApiError,
+    impersonation::{IMPERSONATED_BY_HEADER, IMPERSONATION_EXPIRES_HEADER, IMPERSONATION_ID_HEADER},
+    limits::with_headers,
+    route_policy::RoutePolicy,
+    types::Claims,
+    AppState,
+};
 use axum::{
     body::Body,
-    extract::{Request, State},
-    http::{header, HeaderMap, StatusCode},
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
 };
-use governor::{
-    clock::DefaultClock,
-    state::{InMemoryState, NotKeyed},
-    Quota, RateLimiter,
-};
-use jsonwebtoken::{decode, DecodingKey, Validation};
-use std::{num::NonZeroU32, sync::Arc};
+use copilot_core::{Deadline, DEADLINE_HEADER};
+use copilot_security::SigningKeys;
+use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
+use sha2::{Digest, Sha256};
+use std::{sync::Arc, time::Instant};
 use tracing::{debug, warn};
 use uuid::Uuid;
 
@@ -30,7 +35,7 @@ pub async fn auth_middleware(
     let token = extract_token(headers)?;
 
     // Validate JWT token
-    let claims = validate_token(&token, &state.jwt_secret)?;
+    let claims = validate_token_with_keys(&token, &state.jwt_secret, state.signing_keys.as_ref())?;
 
     // Add claims to request extensions for use in handlers
     req.extensions_mut().insert(claims);
@@ -39,7 +44,7 @@ pub async fn auth_middleware(
 }
 
 /// Extract JWT token from Authorization header
-fn extract_token(headers: &HeaderMap) -> Result<String, ApiError> {
+pub fn extract_token(headers: &HeaderMap) -> Result<String, ApiError> {
     let auth_header = headers
         .get(header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
@@ -55,7 +60,7 @@ fn extract_token(headers: &HeaderMap) -> Result<String, ApiError> {
 }
 
 /// Validate JWT token
-fn validate_token(token: &str, secret: &str) -> Result<Claims, ApiError> {
+pub fn validate_token(token: &str, secret: &str) -> Result<Claims, ApiError> {
     let validation = Validation::default();
     let decoding_key = DecodingKey::from_secret(secret.as_bytes());
 
@@ -64,27 +69,182 @@ fn validate_token(token: &str, secret: &str) -> Result<Claims, ApiError> {
         .map_err(|e| ApiError::AuthenticationFailed(format!("Invalid token: {}", e)))
 }
 
-/// Rate limiting middleware
+/// Validate a JWT naming one of the rotating signing `keys` in its `kid`
+/// header against that key, and any other against `secret`
+pub fn validate_token_with_keys(
+    token: &str,
+    secret: &str,
+    keys: Option<&SigningKeys>,
+) -> Result<Claims, ApiError> {
+    let Some(keys) = keys else {
+        return validate_token(token, secret);
+    };
+    match decode_header(token) {
+        Ok(header) if header.kid.is_some() => keys
+            .verify(token, &Validation::default())
+            .map_err(|e| ApiError::AuthenticationFailed(format!("Invalid token: {}", e))),
+        _ => validate_token(token, secret),
+    }
+}
+
+/// Route authorization middleware
 ///
-/// Implements per-IP rate limiting using the governor crate
-pub async fn rate_limit_middleware(req: Request, next: Next) -> Result<Response, ApiError> {
-    // Create a simple in-memory rate limiter
-    // In production, you'd want to use a distributed rate limiter (Redis, etc.)
-    lazy_static::lazy_static! {
-        static ref LIMITER: RateLimiter<NotKeyed, InMemoryState, DefaultClock> = {
-            // Allow 100 requests per minute
-            let quota = Quota::per_minute(NonZeroU32::new(100).unwrap());
-            RateLimiter::direct(quota)
-        };
+/// Checks the caller's token against the rule of the matched route in the
+/// [`RoutePolicy`], so every route is held to the same table instead
+/// of checks scattered across handlers.
+pub async fn route_policy_middleware(
+    State(policy): State<RoutePolicy>,
+    req: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if let Some(route) = req.extensions().get::<MatchedPath>() {
+        policy.authorize(req.method(), route.as_str(), req.extensions().get::<Claims>())?;
     }
+    Ok(next.run(req).await)
+}
 
-    match LIMITER.check() {
-        Ok(_) => Ok(next.run(req).await),
-        Err(_) => {
-            warn!("Rate limit exceeded");
-            Err(ApiError::RateLimitExceeded)
+/// Impersonation middleware
+///
+/// Rejects impersonation tokens whose impersonation has ended, audits each
+/// request made with one, and flags the response with the impersonating
+/// admin (see [`crate::impersonation`]).
+pub async fn impersonation_middleware(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let Some(actor) = req.extensions().get::<Claims>().and_then(Claims::impersonator) else {
+        return Ok(next.run(req).await);
+    };
+    let impersonation = state.impersonation.check(&actor)?;
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+
+    let mut response = next.run(req).await;
+    let request_id = response
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    state
+        .impersonation
+        .record(&impersonation, &method, &path, response.status().as_u16(), request_id.as_deref())
+        .await;
+
+    let headers = response.headers_mut();
+    for (name, value) in [
+        (IMPERSONATED_BY_HEADER, impersonation.admin.clone()),
+        (IMPERSONATION_ID_HEADER, impersonation.id.to_string()),
+        (IMPERSONATION_EXPIRES_HEADER, impersonation.expires_at.to_rfc3339()),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
         }
     }
+    Ok(response)
+}
+
+/// Rate limiting middleware
+///
+/// Applies the tenant's rate limit and API call quota (see
+/// [`crate::limits`]) and reports them through `X-RateLimit-*` and
+/// `X-Quota-*` headers on the response, including rejections. The request
+/// then runs as the tenant's [`copilot_core::TenantShare`], weighted by tier.
+pub async fn rate_limit_middleware(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(claims) = req.extensions().get::<Claims>() else {
+        return next.run(req).await;
+    };
+    let tenant_id = claims.tenant_id().to_string();
+    let tier = state.limits.tier(claims);
+
+    let mut headers = match state.limits.admit(&tenant_id, tier).await {
+        Ok(headers) => headers,
+        Err(rejected) => return rejected.into_response(),
+    };
+
+    // Shared resources (model calls, sandboxes, embeddings) are queued
+    // fairly between tenants by tier
+    let share = state.limits.share(&tenant_id, tier);
+    let response = share.scope(next.run(req)).await;
+    // Read after the handler so usage it recorded (e.g. tokens) is included
+    headers.extend(state.limits.quota_headers(&tenant_id));
+    with_headers(response, headers)
+}
+
+/// Data residency middleware
+///
+/// Runs the request as the tenant's [`copilot_core::DataResidency`], so
+/// context it stores is tagged with the tenant's region and a `strict`
+/// tenant does not retrieve context from other regions.
+pub async fn residency_middleware(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(claims) = req.extensions().get::<Claims>() else {
+        return next.run(req).await;
+    };
+    let residency = state.residency.for_tenant(claims.tenant_id());
+    residency.scope(next.run(req)).await
+}
+
+/// Access control middleware
+///
+/// Runs the request as the caller's [`copilot_core::Principal`], taken from
+/// the roles and groups of its token, so context items and sources with
+/// ACLs are only retrieved by callers they admit.
+pub async fn access_middleware(req: Request, next: Next) -> Response {
+    let Some(claims) = req.extensions().get::<Claims>() else {
+        return next.run(req).await;
+    };
+    let principal = claims.auth_context().principal();
+    principal.scope(next.run(req)).await
+}
+
+/// Request deadline middleware
+///
+/// Runs the request inside a [`Deadline`] taken from the `X-Request-Timeout`
+/// header (capped at the server maximum) or the server default, so slow
+/// retrieval, reranking, model and adapter calls are cut short instead of
+/// holding the request open.
+pub async fn deadline_middleware(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let requested = req
+        .headers()
+        .get(DEADLINE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(Deadline::parse_timeout);
+    let budget = state.request_timeouts.budget(requested);
+
+    Deadline::after(budget).scope(next.run(req)).await
+}
+
+/// Request statistics middleware
+///
+/// Feeds request counts, rates and server errors to [`crate::ServerStats`]
+pub async fn stats_middleware(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    let started = Instant::now();
+
+    state.stats.request_started();
+    let response = next.run(req).await;
+    state
+        .stats
+        .request_finished(&method, &path, response.status().as_u16(), started.elapsed());
+
+    response
 }
 
 /// Request ID middleware
@@ -122,6 +282,85 @@ pub async fn request_id_middleware(mut req: Request, next: Next) -> Response {
 #[derive(Debug, Clone)]
 pub struct RequestId(pub String);
 
+/// Largest response body buffered to compute an ETag
+const MAX_ETAG_BODY_SIZE: usize = 8 * 1024 * 1024;
+
+/// ETag middleware for read endpoints
+///
+/// Computes an ETag from the body of successful `GET`/`HEAD` responses and
+/// answers `304 Not Modified` when it matches the request's `If-None-Match`.
+/// Streaming responses (server-sent events) are passed through untouched.
+pub async fn etag_middleware(req: Request, next: Next) -> Response {
+    if req.method() != axum::http::Method::GET && req.method() != axum::http::Method::HEAD {
+        return next.run(req).await;
+    }
+
+    let if_none_match = req
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let response = next.run(req).await;
+    if response.status() != StatusCode::OK || response.headers().contains_key(header::ETAG) {
+        return response;
+    }
+    let is_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/event-stream"));
+    if is_stream {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_ETAG_BODY_SIZE).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to buffer response for ETag: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let etag = compute_etag(&bytes);
+    let etag_value = header::HeaderValue::from_str(&etag).expect("hex ETag is a valid header");
+    parts.headers.insert(header::ETAG, etag_value.clone());
+    parts.headers.insert(
+        header::CACHE_CONTROL,
+        header::HeaderValue::from_static("private, no-cache"),
+    );
+
+    if if_none_match.is_some_and(|value| etag_matches(&value, &etag)) {
+        debug!("ETag {} matched, returning 304", etag);
+        let mut not_modified = Response::new(Body::empty());
+        *not_modified.status_mut() = StatusCode::NOT_MODIFIED;
+        for name in [header::ETAG, header::CACHE_CONTROL, header::VARY] {
+            if let Some(value) = parts.headers.get(&name) {
+                not_modified.headers_mut().insert(name, value.clone());
+            }
+        }
+        return not_modified;
+    }
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// Strong ETag for a response body
+fn compute_etag(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    format!("\"{}\"", hex::encode(&digest[..8]))
+}
+
+/// Whether an `If-None-Match` header matches `etag` (weak comparison)
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
 /// Error handling middleware
 ///
 /// Converts errors into proper HTTP responses
@@ -162,6 +401,23 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_tokens_naming_a_signing_key_are_checked_against_it() {
+        use copilot_security::KeyRotationConfig;
+        use jsonwebtoken::{encode, EncodingKey, Header};
+
+        let keys = SigningKeys::generate(KeyRotationConfig::default()).unwrap();
+        let claims = serde_json::json!({ "sub": "alice", "exp": 4_102_444_800u64, "iat": 0 });
+        let signed = keys.sign(&claims).unwrap();
+        let legacy = encode(&Header::default(), &claims, &EncodingKey::from_secret(b"secret")).unwrap();
+
+        assert_eq!(validate_token_with_keys(&signed, "secret", Some(&keys)).unwrap().sub, "alice");
+        assert_eq!(validate_token_with_keys(&legacy, "secret", Some(&keys)).unwrap().sub, "alice");
+        assert!(validate_token_with_keys(&signed, "secret", None).is_err());
+        let other = SigningKeys::generate(KeyRotationConfig::default()).unwrap();
+        assert!(validate_token_with_keys(&signed, "secret", Some(&other)).is_err());
+    }
+
     #[test]
     fn test_extract_token_invalid_format() {
         let mut headers = HeaderMap::new();
@@ -174,6 +430,87 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_etag_matching() {
+        let etag = compute_etag(b"{\"items\":[]}");
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+        assert_eq!(etag, compute_etag(b"{\"items\":[]}"));
+
+        assert!(etag_matches(&etag, &etag));
+        assert!(etag_matches(&format!("\"other\", W/{}", etag), &etag));
+        assert!(etag_matches("*", &etag));
+        assert!(!etag_matches("\"other\"", &etag));
+    }
+
+    #[tokio::test]
+    async fn test_route_policy_checks_the_matched_route() {
+        use axum::{routing::get, Router};
+        use tower::ServiceExt;
+
+        let policy = RoutePolicy::new(
+            serde_json::from_value(serde_json::json!({"routes": [{"path": "/api/v1/users/:id", "scope": "admin"}]}))
+                .unwrap(),
+        )
+        .unwrap();
+        let as_caller = |scope: &'static str| {
+            axum::middleware::from_fn(move |mut req: Request, next: Next| async move {
+                req.extensions_mut().insert(Claims {
+                    sub: "alice".to_string(),
+                    exp: 4_102_444_800,
+                    iat: 0,
+                    additional: serde_json::json!({ "scope": scope }),
+                });
+                next.run(req).await
+            })
+        };
+        let app = |scope| {
+            let api = Router::new()
+                .route("/users/:id", get(|| async { "user" }))
+                .route("/sessions", get(|| async { "sessions" }))
+                .layer(axum::middleware::from_fn_with_state(policy.clone(), route_policy_middleware))
+                .layer(as_caller(scope));
+            Router::new().nest("/api/v1", api)
+        };
+        let get = |scope, uri: &str| app(scope).oneshot(Request::get(uri).body(Body::empty()).unwrap());
+
+        assert_eq!(get("user", "/api/v1/users/42").await.unwrap().status(), StatusCode::FORBIDDEN);
+        assert_eq!(get("admin", "/api/v1/users/42").await.unwrap().status(), StatusCode::OK);
+        assert_eq!(get("user", "/api/v1/sessions").await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_etag_middleware_not_modified() {
+        use axum::{routing::get, Router};
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route("/items", get(|| async { "[1,2,3]" }))
+            .layer(axum::middleware::from_fn(etag_middleware));
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/items").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG].clone();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/items")
+                    .header(header::IF_NONE_MATCH, etag.clone())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag);
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert!(body.is_empty());
+    }
+
     #[test]
     fn test_request_id_type() {
         let request_id = RequestId("test-id".to_string());
@@ -2,12 +2,13 @@
 
 use crate::{
     rest::{handlers, middleware},
-    AppState,
+    AppState, RouteInfo,
 };
 use axum::{
+    extract::DefaultBodyLimit,
     http::{header, HeaderValue, Method},
     middleware as axum_middleware,
-    routing::{get, post, delete},
+    routing::{delete, get, post, put, MethodRouter},
     Router,
 };
 use std::{sync::Arc, time::Duration};
@@ -21,42 +22,333 @@ use tower_http::{
 pub fn create_router(state: AppState) -> Router {
     let state = Arc::new(state);
 
+    // Read endpoints polled by clients answer conditional requests
+    let etag = axum_middleware::from_fn(middleware::etag_middleware);
+
     // Create the API v1 router
-    let api_v1 = Router::new()
+    let api_v1 = Routes::new()
         // Session routes
         .route("/sessions", post(handlers::create_session))
-        .route("/sessions/:id", get(handlers::get_session))
-        .route("/sessions/:id", delete(handlers::delete_session))
+        .route(
+            "/sessions/:id",
+            get(handlers::get_session).layer(etag.clone()).delete(handlers::delete_session),
+        )
+        .route(
+            "/sessions/:id/context",
+            get(handlers::list_context_snapshots).layer(etag.clone()),
+        )
+        .route("/sessions/:id/context-diff", get(handlers::get_context_diff))
+        .route("/sessions/:id/prefetch", post(handlers::prefetch_context))
+        .route("/prefetch/stats", get(handlers::get_prefetch_stats))
+        .route("/context/search", get(handlers::search_context))
+        .route("/sessions/:id/messages", post(handlers::chat_in_session))
+        .route("/sessions/:id/messages/stream", post(handlers::stream_chat_in_session))
+        .route("/sessions/:id/messages/stream/:stream_id", get(handlers::resume_chat_stream))
+        .route("/streams/stats", get(handlers::get_stream_stats))
+        .route("/models/fallback", get(handlers::get_model_fallback_stats))
+        .route("/sessions/:id/history", get(handlers::get_session_history))
+        .route("/sessions/:id/edits", get(handlers::get_proposed_edits))
+        .route("/sessions/:id/handoff", post(handlers::hand_off_session))
+        .route("/sessions/:id/resolution", post(handlers::confirm_resolution))
+        .route("/sessions/:id/feedback", post(handlers::submit_session_feedback))
+        .route(
+            "/sessions/:id/tool-policy",
+            get(handlers::get_tool_policy).put(handlers::update_tool_policy),
+        )
+        .route("/sessions/:id/sandbox", post(handlers::execute_in_sandbox))
+        .route("/sessions/:id/working-memory", get(handlers::get_working_memory))
+        .route("/sessions/:id/budget", get(handlers::get_session_budget))
+        .route(
+            "/sessions/:id/working-memory/:key",
+            put(handlers::write_working_note).delete(handlers::delete_working_note),
+        )
+        .route(
+            "/sessions/:id/comparisons",
+            get(handlers::list_comparisons).post(handlers::compare_models),
+        )
+        .route(
+            "/sessions/:id/comparisons/:comparison_id/vote",
+            post(handlers::vote_comparison),
+        )
+        .route("/model-preferences", get(handlers::model_preference_stats))
+        // Persona routes
+        .route("/personas", get(handlers::list_personas).post(handlers::create_persona))
+        .route(
+            "/personas/:id",
+            get(handlers::get_persona)
+                .put(handlers::update_persona)
+                .delete(handlers::delete_persona),
+        )
         // Message routes
         .route("/messages", post(handlers::send_message))
-        .route("/messages/:session_id", get(handlers::get_messages))
+        .route("/messages/:session_id", get(handlers::get_messages).layer(etag.clone()))
+        // Agent task routes
+        .route("/tasks", get(handlers::list_tasks).post(handlers::submit_task))
+        .route("/tasks/:id", get(handlers::get_task))
+        .route("/tasks/:id/cancel", post(handlers::cancel_task))
+        .route("/tasks/:id/events", get(handlers::task_events))
+        // Ingestion routes (the streaming ingestor enforces its own size limit)
+        .route(
+            "/ingest",
+            post(handlers::ingest_documents).layer(DefaultBodyLimit::disable()),
+        )
+        .route("/ingest/jobs/:id", get(handlers::get_ingestion_job))
+        .route(
+            "/ingest/bootstrap",
+            post(handlers::bootstrap_corpus).layer(DefaultBodyLimit::max(crate::bootstrap::MAX_EXPORT_BYTES)),
+        )
+        .route("/ingest/bootstrap/:id", get(handlers::get_bootstrap_job))
+        .route("/ingest/quarantine", get(handlers::list_quarantined_documents))
+        .route(
+            "/ingest/quarantine/:id",
+            get(handlers::get_quarantined_document).delete(handlers::discard_quarantined_document),
+        )
+        .route("/ingest/quarantine/:id/release", post(handlers::release_quarantined_document))
+        .route(
+            "/impersonation/consent",
+            get(handlers::get_impersonation_consent)
+                .put(handlers::grant_impersonation_consent)
+                .delete(handlers::revoke_impersonation_consent),
+        )
+        .route(
+            "/admin/impersonations",
+            get(handlers::list_impersonations).post(handlers::start_impersonation),
+        )
+        .route("/admin/impersonations/:id", delete(handlers::end_impersonation))
+        .route("/admin/compliance-report", get(handlers::get_compliance_report))
+        .route(
+            "/admin/access-reviews",
+            get(handlers::list_access_reviews).post(handlers::record_access_review),
+        )
+        .route("/auth/email-verification/request", post(handlers::request_email_verification))
+        .route("/admin/users/export", get(handlers::export_users))
+        .route("/admin/users/import", post(handlers::import_users))
+        // SCIM 2.0 provisioning routes
+        .route("/scim/v2/ServiceProviderConfig", get(handlers::scim_service_provider_config))
+        .route("/scim/v2/Users", get(handlers::scim_list_users).post(handlers::scim_create_user))
+        .route(
+            "/scim/v2/Users/:id",
+            get(handlers::scim_get_user)
+                .put(handlers::scim_replace_user)
+                .patch(handlers::scim_patch_user)
+                .delete(handlers::scim_delete_user),
+        )
+        .route("/scim/v2/Groups", get(handlers::scim_list_groups).post(handlers::scim_create_group))
+        .route(
+            "/scim/v2/Groups/:id",
+            get(handlers::scim_get_group)
+                .put(handlers::scim_replace_group)
+                .patch(handlers::scim_patch_group)
+                .delete(handlers::scim_delete_group),
+        )
+        .route(
+            "/admin/source-weights",
+            get(handlers::list_source_weights)
+                .put(handlers::set_source_weight)
+                .delete(handlers::remove_source_weight),
+        )
+        // Webhook routes
+        .route("/webhooks", get(handlers::list_webhooks).post(handlers::create_webhook))
+        .route(
+            "/webhooks/:id",
+            get(handlers::get_webhook)
+                .put(handlers::update_webhook)
+                .delete(handlers::delete_webhook),
+        )
+        .route("/webhooks/:id/secret", post(handlers::rotate_webhook_secret))
+        .route("/webhooks/:id/ping", post(handlers::ping_webhook))
+        .route("/webhooks/:id/stats", get(handlers::get_webhook_stats))
+        .route("/webhook-schemas", get(handlers::list_webhook_schema_versions))
+        .route("/webhook-schemas/:version", get(handlers::get_webhook_schemas).layer(etag.clone()))
+        .route("/embeddings/cache", get(handlers::get_embedding_cache_stats))
+        .route("/embeddings/cache/warmup", post(handlers::warm_embedding_cache))
+        .route("/groundedness/stats", get(handlers::get_groundedness_stats))
+        .route("/groundedness/reviews", get(handlers::list_groundedness_reviews))
+        .route("/groundedness/reviews/:id", delete(handlers::resolve_groundedness_review))
+        .route("/experiments", get(handlers::list_experiments).post(handlers::create_experiment))
+        .route("/experiments/:id", get(handlers::get_experiment_results))
+        .route("/experiments/:id/stop", post(handlers::stop_experiment))
+        .route(
+            "/experiments/:id/enrollment",
+            put(handlers::enroll_in_experiment).delete(handlers::withdraw_from_experiment),
+        )
+        .route("/governance/residency", get(handlers::get_residency_report))
+        .route("/governance/code-policy", get(handlers::list_code_policy_decisions))
+        .route("/replays", get(handlers::list_replays))
+        .route("/replays/:id", get(handlers::get_replay))
+        .route("/context/bulk", post(handlers::submit_bulk_context_job))
+        .route("/context/bulk/jobs/:id", get(handlers::get_bulk_context_job))
+        .route("/context/bulk/jobs/:id/export", get(handlers::export_bulk_context_job))
+        .route("/context/bulk/jobs/:id/bundle", post(handlers::store_bulk_context_export))
+        .route("/context/archive/warmup", post(handlers::warm_up_context))
+        // Object storage routes
+        .route("/files", post(handlers::create_file_upload))
+        .route("/files/:class/:sha256", get(handlers::get_file_download))
         // Workflow routes
         .route("/workflows", post(handlers::create_workflow))
         .route("/workflows/:id", get(handlers::get_workflow_status))
+        .route("/templates", get(handlers::list_templates))
+        .route("/templates/:id/parameters", get(handlers::get_template_parameters))
+        .route("/templates/:id/runs", post(handlers::run_template))
+        .route("/runs/:id/events", get(handlers::run_events))
+        // Schedule routes
+        .route("/schedules", get(handlers::list_schedules))
+        .route("/schedules/preview", post(handlers::preview_schedule))
+        .route("/schedules/:id", get(handlers::get_schedule))
+        .route("/schedules/:id/pause", post(handlers::pause_schedule))
+        .route("/schedules/:id/resume", post(handlers::resume_schedule))
+        // CI gate routes
+        .route("/gates/github", post(handlers::run_github_gate))
+        // Preference routes
+        .route(
+            "/preferences",
+            get(handlers::get_user_preferences)
+                .put(handlers::update_user_preferences)
+                .delete(handlers::delete_user_preferences),
+        )
+        .route(
+            "/preferences/suggestions/:id",
+            delete(handlers::dismiss_preference_suggestion),
+        )
+        .route(
+            "/preferences/suggestions/:id/confirm",
+            post(handlers::confirm_preference_suggestion),
+        )
+        // Notification routes
+        .route(
+            "/notifications/preferences",
+            get(handlers::get_notification_preferences)
+                .put(handlers::update_notification_preferences)
+                .delete(handlers::reset_notification_preferences),
+        )
+        // Dashboard routes
+        .route("/dashboard", get(handlers::get_dashboard))
+        .route("/dashboard/events", get(handlers::dashboard_events))
+        // Query analytics routes
+        .route("/analytics/knowledge-gaps", get(handlers::get_knowledge_gaps))
+        .route("/analytics/conversations", get(handlers::get_conversation_analytics))
+        // Grafana dashboard routes
+        .route("/dashboards/generate", post(handlers::generate_dashboard))
+        // Alert rule routes
+        .route("/alerts/generate", post(handlers::generate_alert_rule))
+        // Log analysis routes
+        .route(
+            "/logs/analyze",
+            post(handlers::analyze_logs).layer(DefaultBodyLimit::max(handlers::MAX_LOG_ANALYSIS_BYTES)),
+        );
+    let mut routes = api_v1.infos("/api/v1", true);
+    let api_v1 = api_v1
+        .router
         .layer(
             ServiceBuilder::new()
+                .layer(axum_middleware::from_fn_with_state(
+                    state.clone(),
+                    middleware::stats_middleware,
+                ))
                 .layer(axum_middleware::from_fn_with_state(
                     state.clone(),
                     middleware::auth_middleware,
                 ))
-                .layer(axum_middleware::from_fn(middleware::rate_limit_middleware))
+                .layer(axum_middleware::from_fn_with_state(
+                    state.clone(),
+                    middleware::impersonation_middleware,
+                ))
+                .layer(axum_middleware::from_fn_with_state(
+                    state.route_policy.clone(),
+                    middleware::route_policy_middleware,
+                ))
+                .layer(axum_middleware::from_fn_with_state(
+                    state.clone(),
+                    middleware::rate_limit_middleware,
+                ))
+                .layer(axum_middleware::from_fn_with_state(
+                    state.clone(),
+                    middleware::residency_middleware,
+                ))
+                .layer(axum_middleware::from_fn(middleware::access_middleware))
+                .layer(axum_middleware::from_fn_with_state(
+                    state.clone(),
+                    middleware::deadline_middleware,
+                ))
                 .layer(axum_middleware::from_fn(middleware::request_id_middleware)),
         );
 
+    // Account token routes (the token or email is the credential)
+    let account_routes = Routes::new()
+        .route("/auth/email-verification", post(handlers::verify_email))
+        .route("/auth/password-reset/request", post(handlers::request_password_reset))
+        .route("/auth/password-reset", post(handlers::reset_password));
+
     // Health check routes (no authentication required)
-    let health_routes = Router::new()
+    let health_routes = Routes::new()
         .route("/health", get(handlers::health_check))
         .route("/ready", get(handlers::readiness_check));
 
+    // Token verification keys (no authentication required)
+    let jwks_routes = Routes::new().route("/.well-known/jwks.json", get(handlers::get_jwks));
+
+    // Presigned URLs of a local object store carry their own signature
+    let object_routes = Routes::new().route(
+        "/objects/*key",
+        get(handlers::get_object)
+            .put(handlers::put_object)
+            .layer(DefaultBodyLimit::disable()),
+    );
+
+    // Report the routes the policy table leaves uncovered
+    routes.extend(account_routes.infos("/api/v1", false));
+    for public in [&health_routes, &jwks_routes, &object_routes] {
+        routes.extend(public.infos("", false));
+    }
+    if state.route_policy.is_empty() {
+        tracing::warn!(routes = routes.len(), "No route policy configured; routes only require authentication");
+    } else {
+        state.route_policy.report(&routes).log();
+    }
+
     // Combine all routes
     Router::new()
-        .nest("/api/v1", api_v1)
-        .merge(health_routes)
+        .nest("/api/v1", api_v1.merge(account_routes.router))
+        .merge(health_routes.router)
+        .merge(jwks_routes.router)
+        .merge(object_routes.router)
         .layer(cors_layer())
         .layer(TraceLayer::new_for_http())
         .with_state(state)
 }
 
+/// A router that remembers the paths of its routes
+struct Routes {
+    router: Router<Arc<AppState>>,
+    paths: Vec<String>,
+}
+
+impl Routes {
+    fn new() -> Self {
+        Self {
+            router: Router::new(),
+            paths: Vec::new(),
+        }
+    }
+
+    fn route(mut self, path: &str, method_router: MethodRouter<Arc<AppState>>) -> Self {
+        self.paths.push(path.to_string());
+        self.router = self.router.route(path, method_router);
+        self
+    }
+
+    /// The routes as served under `prefix`
+    fn infos(&self, prefix: &str, authenticated: bool) -> Vec<RouteInfo> {
+        self.paths
+            .iter()
+            .map(|path| RouteInfo {
+                path: format!("{}{}", prefix, path),
+                authenticated,
+            })
+            .collect()
+    }
+}
+
 /// Configure CORS layer
 fn cors_layer() -> CorsLayer {
     CorsLayer::new()
@@ -78,6 +370,7 @@ fn cors_layer() -> CorsLayer {
             header::ACCEPT,
             header::CONTENT_TYPE,
             header::HeaderName::from_static("x-request-id"),
+            header::HeaderName::from_static(copilot_core::DEADLINE_HEADER),
         ])
         .allow_credentials(true)
         .max_age(Duration::from_secs(3600))
@@ -0,0 +1,349 @@
+//! Authorization policy of API routes
+//!
+//! A [`RoutePolicy`] is a table of rules naming the scope a token must grant
+//! to use a route, loaded from a JSON file at startup:
+//!
+//! ```json
+//! {"routes": [
+//!   {"path": "/api/v1/users/*", "scope": "admin"},
+//!   {"path": "/api/v1/workflows", "methods": ["POST"], "scope": "workflows:write"},
+//!   {"path": "/health", "public": true}
+//! ]}
+//! ```
+//!
+//! The REST middleware checks every authenticated request against the rule
+//! of its matched route before the handler runs. The most specific rule
+//! wins: an exact path before a `/*` prefix, a longer prefix before a
+//! shorter one, and a rule naming the method before one that doesn't.
+//! [`RoutePolicy::reload`] swaps in an edited file without a restart, and a
+//! [`RoutePolicyReport`] lists at startup the routes no rule covers, so a new
+//! route isn't exposed to every authenticated caller by accident.
+
+use crate::{error::ApiError, types::Claims, Result};
+use axum::http::Method;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+/// What callers need to use the routes a rule matches
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteRule {
+    /// Route path as registered, e.g. `/api/v1/users/:id`; a trailing `/*`
+    /// matches the prefix and every route under it
+    pub path: String,
+    /// Methods the rule applies to; empty applies to all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub methods: Vec<String>,
+    /// Scope the token must grant; unset only requires authentication
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// The route is meant to be reachable without a token
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub public: bool,
+}
+
+impl RouteRule {
+    fn matches(&self, method: Option<&Method>, route: &str) -> bool {
+        let path = match self.path.strip_suffix("/*") {
+            Some(prefix) => route == prefix || route.starts_with(&format!("{}/", prefix)),
+            None => route == self.path,
+        };
+        let method = match method {
+            Some(method) => self.methods.is_empty() || self.methods.iter().any(|m| m.eq_ignore_ascii_case(method.as_str())),
+            None => true,
+        };
+        path && method
+    }
+
+    /// Order of precedence among rules matching the same route
+    fn specificity(&self) -> (bool, usize, bool) {
+        (!self.path.ends_with("/*"), self.path.len(), !self.methods.is_empty())
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        if !self.path.starts_with('/') {
+            return Err(format!("Route path {:?} must start with /", self.path));
+        }
+        if let Some(method) = self.methods.iter().find(|m| Method::from_bytes(m.as_bytes()).is_err()) {
+            return Err(format!("Invalid method {:?} for {}", method, self.path));
+        }
+        if self.public && self.scope.is_some() {
+            return Err(format!("Public route {} cannot require a scope", self.path));
+        }
+        if self.scope.as_deref().is_some_and(|scope| scope.trim().is_empty()) {
+            return Err(format!("Empty scope for {}", self.path));
+        }
+        Ok(())
+    }
+}
+
+/// The rules of a policy file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutePolicyConfig {
+    #[serde(default)]
+    pub routes: Vec<RouteRule>,
+}
+
+impl RoutePolicyConfig {
+    /// Read a configuration from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let invalid = |e: String| ApiError::InvalidInput(format!("Route policy {}: {}", path.display(), e));
+        let text = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let config: Self = serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+        config.validate().map_err(invalid)?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        self.routes.iter().try_for_each(RouteRule::validate)
+    }
+
+    /// The most specific rule matching `route`
+    pub fn rule(&self, method: &Method, route: &str) -> Option<&RouteRule> {
+        self.routes
+            .iter()
+            .filter(|rule| rule.matches(Some(method), route))
+            .max_by_key(|rule| rule.specificity())
+    }
+}
+
+/// A route the router serves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteInfo {
+    pub path: String,
+    /// Whether the route sits behind authentication, and so the policy
+    pub authenticated: bool,
+}
+
+/// How well a policy covers the routes of a router
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoutePolicyReport {
+    /// Routes no rule covers: authenticated routes open to any caller with
+    /// a token, and public routes not declared public
+    pub unprotected: Vec<RouteInfo>,
+    /// Public routes a rule requires a scope for, which can't be enforced
+    pub unenforced: Vec<String>,
+    /// Rule paths matching no route, e.g. typos or removed routes
+    pub unused_rules: Vec<String>,
+}
+
+impl RoutePolicyReport {
+    /// Log the report, warning about each gap
+    pub fn log(&self) {
+        for route in &self.unprotected {
+            if route.authenticated {
+                warn!(route = %route.path, "Route has no policy rule; any authenticated caller may use it");
+            } else {
+                warn!(route = %route.path, "Public route has no policy rule; declare it public if intended");
+            }
+        }
+        for route in &self.unenforced {
+            warn!(route = %route, "Route policy requires a scope for a public route, which is not enforced");
+        }
+        for path in &self.unused_rules {
+            warn!(rule = %path, "Route policy rule matches no route");
+        }
+        if self.unprotected.is_empty() && self.unenforced.is_empty() && self.unused_rules.is_empty() {
+            info!("Route policy covers every route");
+        }
+    }
+}
+
+/// The route authorization table, shared by the server and reloadable
+#[derive(Debug, Clone, Default)]
+pub struct RoutePolicy {
+    config: Arc<RwLock<Arc<RoutePolicyConfig>>>,
+    source: Option<PathBuf>,
+}
+
+impl RoutePolicy {
+    /// A policy of `config`'s rules
+    pub fn new(config: RoutePolicyConfig) -> Result<Self> {
+        config.validate().map_err(ApiError::InvalidInput)?;
+        Ok(Self {
+            config: Arc::new(RwLock::new(Arc::new(config))),
+            source: None,
+        })
+    }
+
+    /// A policy read from a JSON file, which [`reload`](Self::reload) re-reads
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let config = RoutePolicyConfig::load(&path)?;
+        Ok(Self {
+            config: Arc::new(RwLock::new(Arc::new(config))),
+            source: Some(path),
+        })
+    }
+
+    /// The rules in force
+    pub fn config(&self) -> Arc<RoutePolicyConfig> {
+        self.config.read().expect("route policy poisoned").clone()
+    }
+
+    /// Whether the policy has any rules; without rules only authentication
+    /// is enforced
+    pub fn is_empty(&self) -> bool {
+        self.config().routes.is_empty()
+    }
+
+    /// Re-read the policy file; on error the rules in force stay
+    pub fn reload(&self) -> Result<()> {
+        let Some(path) = &self.source else {
+            return Ok(());
+        };
+        let config = RoutePolicyConfig::load(path)?;
+        info!(rules = config.routes.len(), "Reloaded route policy from {}", path.display());
+        *self.config.write().expect("route policy poisoned") = Arc::new(config);
+        Ok(())
+    }
+
+    /// Reload the policy file whenever it changes, checking every
+    /// `check_every`
+    pub fn spawn_reload(&self, check_every: Duration) -> Option<tokio::task::JoinHandle<()>> {
+        let path = self.source.clone()?;
+        let policy = self.clone();
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        Some(tokio::spawn(async move {
+            let mut seen: Option<SystemTime> = modified(&path);
+            let mut ticks = tokio::time::interval(check_every);
+            loop {
+                ticks.tick().await;
+                let current = modified(&path);
+                if current == seen {
+                    continue;
+                }
+                seen = current;
+                if let Err(e) = policy.reload() {
+                    warn!(error = %e, "Keeping the route policy in force");
+                }
+            }
+        }))
+    }
+
+    /// Check that `claims` may use `route`, the matched path of a request
+    pub fn authorize(&self, method: &Method, route: &str, claims: Option<&Claims>) -> Result<()> {
+        let config = self.config();
+        let Some(scope) = config.rule(method, route).and_then(|rule| rule.scope.as_deref()) else {
+            return Ok(());
+        };
+        match claims {
+            Some(claims) if claims.has_scope(scope) => Ok(()),
+            Some(_) => Err(ApiError::AuthorizationFailed(format!("Requires the {} scope", scope))),
+            None => Err(ApiError::AuthenticationFailed("Missing authorization header".into())),
+        }
+    }
+
+    /// How well the policy covers `routes`
+    pub fn report(&self, routes: &[RouteInfo]) -> RoutePolicyReport {
+        let config = self.config();
+        let mut report = RoutePolicyReport::default();
+        for route in routes {
+            let rules: Vec<&RouteRule> = config.routes.iter().filter(|r| r.matches(None, &route.path)).collect();
+            if rules.is_empty() {
+                report.unprotected.push(route.clone());
+            } else if !route.authenticated && rules.iter().any(|r| r.scope.is_some()) {
+                report.unenforced.push(route.path.clone());
+            } else if !route.authenticated && !rules.iter().any(|r| r.public) {
+                report.unprotected.push(route.clone());
+            }
+        }
+        report.unused_rules = config
+            .routes
+            .iter()
+            .filter(|rule| !routes.iter().any(|route| rule.matches(None, &route.path)))
+            .map(|rule| rule.path.clone())
+            .collect();
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn claims(scope: &str) -> Claims {
+        Claims {
+            sub: "user-1".to_string(),
+            exp: 4_000_000_000,
+            iat: 0,
+            additional: json!({ "scope": scope }),
+        }
+    }
+
+    fn policy() -> RoutePolicy {
+        RoutePolicy::new(
+            serde_json::from_value(json!({"routes": [
+                {"path": "/api/v1/users/*", "scope": "admin"},
+                {"path": "/api/v1/users/:id", "methods": ["GET"], "scope": "users:read"},
+                {"path": "/api/v1/sessions"},
+                {"path": "/health", "public": true},
+                {"path": "/api/v1/removed"}
+            ]}))
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_most_specific_rule_decides() {
+        let policy = policy();
+        let reader = claims("users:read");
+        let admin = claims("admin");
+
+        assert!(policy.authorize(&Method::GET, "/api/v1/users/:id", Some(&reader)).is_ok());
+        assert!(matches!(
+            policy.authorize(&Method::DELETE, "/api/v1/users/:id", Some(&reader)),
+            Err(ApiError::AuthorizationFailed(_))
+        ));
+        assert!(policy.authorize(&Method::DELETE, "/api/v1/users/:id", Some(&admin)).is_ok());
+        assert!(policy.authorize(&Method::GET, "/api/v1/users", Some(&reader)).is_err());
+        // Routes without a scope only need a token
+        assert!(policy.authorize(&Method::POST, "/api/v1/sessions", Some(&reader)).is_ok());
+        assert!(policy.authorize(&Method::GET, "/api/v1/usersettings", Some(&reader)).is_ok());
+    }
+
+    #[test]
+    fn test_report_lists_gaps() {
+        let routes = [
+            ("/api/v1/users/:id", true),
+            ("/api/v1/sessions", true),
+            ("/api/v1/workflows", true),
+            ("/health", false),
+            ("/ready", false),
+        ]
+        .map(|(path, authenticated)| RouteInfo {
+            path: path.to_string(),
+            authenticated,
+        });
+
+        let report = policy().report(&routes);
+        let unprotected: Vec<&str> = report.unprotected.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(unprotected, vec!["/api/v1/workflows", "/ready"]);
+        assert!(report.unenforced.is_empty());
+        assert_eq!(report.unused_rules, vec!["/api/v1/removed"]);
+    }
+
+    #[test]
+    fn test_invalid_rules_are_refused_and_reload_keeps_rules_in_force() {
+        let invalid = serde_json::from_value(json!({"routes": [{"path": "/health", "public": true, "scope": "admin"}]}));
+        assert!(RoutePolicy::new(invalid.unwrap()).is_err());
+
+        let path = std::env::temp_dir().join(format!("route-policy-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, r#"{"routes": [{"path": "/api/v1/*", "scope": "admin"}]}"#).unwrap();
+        let policy = RoutePolicy::load(&path).unwrap();
+        assert!(policy.authorize(&Method::GET, "/api/v1/sessions", Some(&claims("user"))).is_err());
+
+        std::fs::write(&path, r#"{"routes": [{"path": "/api/v1/*", "methods": ["FETCH ME"]}]}"#).unwrap();
+        assert!(policy.reload().is_err());
+        assert_eq!(policy.config().routes[0].scope.as_deref(), Some("admin"));
+
+        std::fs::write(&path, r#"{"routes": [{"path": "/api/v1/*", "scope": "user"}]}"#).unwrap();
+        policy.reload().unwrap();
+        assert!(policy.authorize(&Method::GET, "/api/v1/sessions", Some(&claims("user"))).is_ok());
+        std::fs::remove_file(path).unwrap();
+    }
+}
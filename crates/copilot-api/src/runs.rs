@@ -0,0 +1,417 @@
+//! Manual workflow runs from templates
+//!
+//! The server UI and the CLI fetch a template's parameter schema, let the
+//! user fill it in and submit the values to start a run. Values are
+//! validated here against the template's [`TemplateParameter`]s, and every
+//! offending field is reported so forms can mark each one. A dry run only
+//! validates.
+//!
+//! Started runs can be watched: [`ManualRunService::watch`] returns the
+//! run's current state as a [`RunEvent`] and the engine's events after it,
+//! which the API streams step by step to the CLI's `--watch` and the UI.
+
+use crate::error::{ApiError, Result};
+use chrono::{DateTime, Utc};
+use copilot_workflow::templates::InMemoryTemplateRepository;
+use copilot_workflow::{
+    TemplateDocs, TemplateLibrary, TemplateParameter, WorkflowEngine, WorkflowError, WorkflowEvent,
+    WorkflowEventKind, WorkflowState, WorkflowStatus, WorkflowTemplate,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::info;
+
+/// Parameters a template is run with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParameterSchema {
+    pub template_id: String,
+    pub name: String,
+    pub description: String,
+    pub parameters: Vec<TemplateParameter>,
+}
+
+/// A template as listed for picking one to run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateSummary {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub category: String,
+    pub tags: Vec<String>,
+    pub icon: Option<String>,
+    pub version: String,
+    /// Parameters a run must be given values for
+    pub required_parameters: Vec<String>,
+    pub docs: Option<TemplateDocs>,
+    pub usage_count: u64,
+}
+
+impl From<WorkflowTemplate> for TemplateSummary {
+    fn from(template: WorkflowTemplate) -> Self {
+        Self {
+            required_parameters: template
+                .parameters
+                .iter()
+                .filter(|p| p.required)
+                .map(|p| p.name.clone())
+                .collect(),
+            id: template.id,
+            name: template.name,
+            description: template.description,
+            category: template.category,
+            tags: template.tags,
+            icon: template.icon,
+            version: template.version,
+            docs: template.docs,
+            usage_count: template.usage_count,
+        }
+    }
+}
+
+/// Request to run a template
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ManualRunRequest {
+    /// Values by parameter name
+    #[serde(default)]
+    pub params: serde_json::Map<String, serde_json::Value>,
+    /// Validate the values without starting a run
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// A validated, and unless dry run, started template run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManualRun {
+    pub template_id: String,
+    /// Workflow execution, absent for dry runs
+    pub execution_id: Option<String>,
+    /// Values the run uses, with defaults filled in
+    pub params: serde_json::Value,
+}
+
+/// Something that happened to a run, as streamed to watchers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunEvent {
+    pub execution_id: String,
+    /// What happened; absent for the snapshot a stream starts with
+    pub kind: Option<WorkflowEventKind>,
+    pub step_id: Option<String>,
+    /// Attempt of the step that failed, for retries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempt: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Short rendering of the step's outputs, for completed steps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_summary: Option<String>,
+    /// Status of the run after the event
+    pub status: WorkflowStatus,
+    /// Steps running after the event, in name order
+    pub running_steps: Vec<String>,
+    pub completed_steps: usize,
+    pub failed_steps: usize,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl RunEvent {
+    /// Snapshot of a run's current state
+    pub fn snapshot(state: &WorkflowState) -> Self {
+        let mut running_steps: Vec<String> = state.running_steps.iter().cloned().collect();
+        running_steps.sort();
+        Self {
+            execution_id: state.execution_id.clone(),
+            kind: None,
+            step_id: None,
+            attempt: None,
+            error: state.error.clone(),
+            output_summary: None,
+            status: state.status.clone(),
+            running_steps,
+            completed_steps: state.completed_steps.len(),
+            failed_steps: state.failed_steps.len(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Whether no event follows this one
+    pub fn is_last(&self) -> bool {
+        match self.kind {
+            Some(kind) => kind.is_terminal(),
+            None => matches!(
+                self.status,
+                WorkflowStatus::Completed | WorkflowStatus::Failed | WorkflowStatus::Cancelled
+            ),
+        }
+    }
+}
+
+impl From<&WorkflowEvent> for RunEvent {
+    fn from(event: &WorkflowEvent) -> Self {
+        Self {
+            kind: Some(event.kind),
+            step_id: event.step_id.clone(),
+            attempt: event.attempt,
+            error: event.error.clone().or_else(|| match event.kind {
+                WorkflowEventKind::Failed => event.state.error.clone(),
+                _ => None,
+            }),
+            output_summary: event.output_summary.clone(),
+            ..Self::snapshot(&event.state)
+        }
+    }
+}
+
+/// Starts workflow runs from templates with user-supplied parameters
+pub struct ManualRunService {
+    engine: WorkflowEngine,
+    templates: TemplateLibrary,
+}
+
+impl Default for ManualRunService {
+    fn default() -> Self {
+        Self::new(
+            WorkflowEngine::new(),
+            TemplateLibrary::new(Arc::new(InMemoryTemplateRepository::with_builtins())),
+        )
+    }
+}
+
+impl ManualRunService {
+    /// Create a service running templates from `templates` on `engine`
+    pub fn new(engine: WorkflowEngine, templates: TemplateLibrary) -> Self {
+        Self { engine, templates }
+    }
+
+    pub fn templates(&self) -> &TemplateLibrary {
+        &self.templates
+    }
+
+    /// Templates that can be run, optionally of one category, ordered by
+    /// category and name
+    pub async fn list(&self, category: Option<&str>) -> Result<Vec<TemplateSummary>> {
+        let templates = match category {
+            Some(category) => self.templates.list_by_category(category).await,
+            None => self.templates.list().await,
+        }
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+
+        let mut summaries: Vec<TemplateSummary> = templates.into_iter().map(TemplateSummary::from).collect();
+        summaries.sort_by(|a, b| (&a.category, &a.name).cmp(&(&b.category, &b.name)));
+        Ok(summaries)
+    }
+
+    /// Parameter schema of the template `template_id`
+    pub async fn schema(&self, template_id: &str) -> Result<ParameterSchema> {
+        let template = self.template(template_id).await?;
+        Ok(ParameterSchema {
+            template_id: template.id,
+            name: template.name,
+            description: template.description,
+            parameters: template.parameters,
+        })
+    }
+
+    /// Validate `request` against the template and start a run for
+    /// `tenant_id` unless it is a dry run
+    pub async fn run(&self, tenant_id: &str, template_id: &str, request: ManualRunRequest) -> Result<ManualRun> {
+        let template = self.template(template_id).await?;
+        let params = serde_json::Value::Object(request.params);
+
+        let errors = template.check_params(&params);
+        if !errors.is_empty() {
+            return Err(ApiError::InvalidParameters(errors));
+        }
+        let resolved = template.resolve_params(&params);
+        if request.dry_run {
+            return Ok(ManualRun {
+                template_id: template.id,
+                execution_id: None,
+                params: resolved,
+            });
+        }
+
+        let mut definition = self
+            .templates
+            .instantiate(&template.id, params)
+            .await
+            .map_err(|e| ApiError::WorkflowError(e.to_string()))?;
+        definition.metadata.insert("tenant_id".to_string(), serde_json::json!(tenant_id));
+        let execution_id = self
+            .engine
+            .execute_workflow(definition)
+            .await
+            .map_err(|e| ApiError::WorkflowError(e.to_string()))?;
+
+        info!(template_id = %template.id, execution_id = %execution_id, "Started manual run");
+        Ok(ManualRun {
+            template_id: template.id,
+            execution_id: Some(execution_id),
+            params: resolved,
+        })
+    }
+
+    /// Current state of run `execution_id` of `tenant_id` and the engine's
+    /// events after it; the events of other runs are on the same channel
+    /// and are left to the caller to skip
+    pub async fn watch(
+        &self,
+        tenant_id: &str,
+        execution_id: &str,
+    ) -> Result<(RunEvent, broadcast::Receiver<WorkflowEvent>)> {
+        let not_found = || ApiError::NotFound(format!("Run {} not found", execution_id));
+        let run_error = |e: WorkflowError| match e {
+            WorkflowError::NotFound(_) => not_found(),
+            e => ApiError::WorkflowError(e.to_string()),
+        };
+        let owner = self.engine.execution_tenant(execution_id).await.map_err(run_error)?;
+        if owner.as_deref() != Some(tenant_id) {
+            return Err(not_found());
+        }
+        let (state, events) = self.engine.watch(execution_id).await.map_err(run_error)?;
+        Ok((RunEvent::snapshot(&state), events))
+    }
+
+    async fn template(&self, template_id: &str) -> Result<WorkflowTemplate> {
+        self.templates
+            .get(template_id)
+            .await
+            .map_err(|e| ApiError::InternalError(e.to_string()))?
+            .ok_or_else(|| ApiError::NotFound(format!("Template {} not found", template_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_workflow::templates::SelectOption;
+    use copilot_workflow::{ParameterType, TemplateBuilders};
+
+    async fn service() -> (ManualRunService, String) {
+        let service = ManualRunService::default();
+        let template = TemplateBuilders::sequential_workflow("Deploy", "Deploy a service", vec!["deploy"])
+            .with_parameter(TemplateParameter {
+                name: "environment".to_string(),
+                label: "Environment".to_string(),
+                description: None,
+                param_type: ParameterType::Select {
+                    options: vec![SelectOption {
+                        value: "staging".to_string(),
+                        label: "Staging".to_string(),
+                    }],
+                },
+                required: true,
+                default_value: None,
+                validation: None,
+            });
+        let template = service.templates().create(template).await.unwrap();
+        (service, template.id)
+    }
+
+    #[tokio::test]
+    async fn test_list_includes_the_builtin_templates() {
+        let (service, id) = service().await;
+
+        let all = service.list(None).await.unwrap();
+        assert!(all.iter().any(|t| t.id == id));
+        let devops = service.list(Some("DevOps")).await.unwrap();
+        assert_eq!(devops.len(), 5);
+        let deploy = devops.iter().find(|t| t.id == TemplateBuilders::DEPLOY_WITH_APPROVAL_ID).unwrap();
+        assert_eq!(deploy.required_parameters, vec!["service", "version"]);
+        assert!(deploy.docs.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_schema_lists_the_parameters() {
+        let (service, id) = service().await;
+
+        let schema = service.schema(&id).await.unwrap();
+        assert!(schema.parameters.iter().any(|p| p.name == "environment"));
+        assert!(matches!(service.schema("missing").await, Err(ApiError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_run_validates_before_starting() {
+        let (service, id) = service().await;
+
+        let request = ManualRunRequest {
+            params: serde_json::from_value(serde_json::json!({
+                "environment": "prod",
+                "workflow_name": "x"
+            }))
+            .unwrap(),
+            dry_run: false,
+        };
+        match service.run("acme", &id, request).await {
+            Err(ApiError::InvalidParameters(errors)) => {
+                let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+                assert_eq!(fields, vec!["workflow_name", "environment"]);
+            }
+            other => panic!("expected invalid parameters, got {:?}", other.map(|r| r.execution_id)),
+        }
+
+        let mut request = ManualRunRequest {
+            params: serde_json::from_value(serde_json::json!({
+                "environment": "staging",
+                "workflow_name": "Deploy staging"
+            }))
+            .unwrap(),
+            dry_run: true,
+        };
+        let dry_run = service.run("acme", &id, request.clone()).await.unwrap();
+        assert!(dry_run.execution_id.is_none());
+        assert_eq!(dry_run.params["step_1_name"], "deploy");
+
+        request.dry_run = false;
+        let run = service.run("acme", &id, request).await.unwrap();
+        let status = service.engine.get_status(&run.execution_id.unwrap()).await.unwrap();
+        assert_eq!(status.status, WorkflowStatus::Running);
+    }
+
+    #[tokio::test]
+    async fn test_watch_streams_the_run_step_by_step() {
+        let (service, id) = service().await;
+        assert!(matches!(service.watch("acme", "missing").await, Err(ApiError::NotFound(_))));
+
+        let mut events = service.engine.subscribe();
+        let request = ManualRunRequest {
+            params: serde_json::from_value(serde_json::json!({
+                "environment": "staging",
+                "workflow_name": "Deploy staging"
+            }))
+            .unwrap(),
+            dry_run: false,
+        };
+        let execution_id = service.run("acme", &id, request).await.unwrap().execution_id.unwrap();
+        assert!(matches!(service.watch("globex", &execution_id).await, Err(ApiError::NotFound(_))));
+
+        let mut kinds = Vec::new();
+        loop {
+            let event = RunEvent::from(&events.recv().await.unwrap());
+            assert_eq!(event.execution_id, execution_id);
+            kinds.push(event.kind.unwrap());
+            if event.is_last() {
+                assert_eq!(event.status, WorkflowStatus::Completed);
+                assert_eq!(event.completed_steps, 1);
+                break;
+            }
+            if event.kind == Some(WorkflowEventKind::StepCompleted) {
+                assert_eq!(event.output_summary.as_deref(), Some("custom_result={}"));
+            }
+        }
+        assert_eq!(
+            kinds,
+            vec![
+                WorkflowEventKind::Started,
+                WorkflowEventKind::StepStarted,
+                WorkflowEventKind::StepCompleted,
+                WorkflowEventKind::Completed
+            ]
+        );
+
+        // Watching a finished run yields only its final state
+        let (snapshot, _) = service.watch("acme", &execution_id).await.unwrap();
+        assert!(snapshot.kind.is_none() && snapshot.is_last());
+        assert_eq!(snapshot.completed_steps, 1);
+    }
+}
@@ -0,0 +1,169 @@
+//! Workflow schedules
+//!
+//! Lists schedules, pauses and resumes them, and previews the fire times of
+//! a schedule so cron expressions can be checked before they are saved.
+//! Callers only see their own tenant's schedules, admins included.
+
+use crate::error::{ApiError, Result};
+use chrono::{DateTime, Utc};
+use copilot_workflow::scheduling::InMemoryScheduleRepository;
+use copilot_workflow::{Schedule, ScheduledWorkflow, WorkflowError, WorkflowScheduler};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Most fire times a preview returns
+const MAX_PREVIEW: usize = 100;
+
+/// Request to preview a schedule's fire times
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulePreviewRequest {
+    pub schedule: Schedule,
+    /// Number of fire times, at most 100
+    #[serde(default = "default_preview_count")]
+    pub count: usize,
+}
+
+fn default_preview_count() -> usize {
+    5
+}
+
+/// Upcoming fire times of a schedule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulePreview {
+    pub fire_times: Vec<DateTime<Utc>>,
+}
+
+/// Pauses, resumes and previews workflow schedules
+pub struct ScheduleService {
+    scheduler: Arc<WorkflowScheduler>,
+}
+
+impl Default for ScheduleService {
+    /// Schedules kept in memory and never fired; hosts running a scheduler
+    /// pass it to [`ScheduleService::new`]
+    fn default() -> Self {
+        let (sender, _receiver) = mpsc::channel(1);
+        Self::new(Arc::new(WorkflowScheduler::new(
+            Arc::new(InMemoryScheduleRepository::new()),
+            sender,
+        )))
+    }
+}
+
+impl ScheduleService {
+    pub fn new(scheduler: Arc<WorkflowScheduler>) -> Self {
+        Self { scheduler }
+    }
+
+    pub fn scheduler(&self) -> &WorkflowScheduler {
+        &self.scheduler
+    }
+
+    /// Schedules of `tenant_id`
+    pub async fn list(&self, tenant_id: &str) -> Result<Vec<ScheduledWorkflow>> {
+        let schedules = self.scheduler.list().await.map_err(schedule_error)?;
+        Ok(schedules
+            .into_iter()
+            .filter(|schedule| schedule.tenant_id.as_deref() == Some(tenant_id))
+            .collect())
+    }
+
+    /// Schedule `id`, as not found unless it belongs to `tenant_id`
+    pub async fn get(&self, id: &str, tenant_id: &str) -> Result<ScheduledWorkflow> {
+        let schedule = self.scheduler.get(id).await.map_err(schedule_error)?;
+        if schedule.tenant_id.as_deref() != Some(tenant_id) {
+            return Err(ApiError::NotFound(format!("Schedule {} not found", id)));
+        }
+        Ok(schedule)
+    }
+
+    /// Pause schedule `id` of `tenant_id`
+    pub async fn pause(&self, id: &str, tenant_id: &str) -> Result<ScheduledWorkflow> {
+        self.get(id, tenant_id).await?;
+        self.scheduler.pause(id).await.map_err(schedule_error)
+    }
+
+    /// Resume schedule `id` of `tenant_id`
+    pub async fn resume(&self, id: &str, tenant_id: &str) -> Result<ScheduledWorkflow> {
+        self.get(id, tenant_id).await?;
+        self.scheduler.resume(id).await.map_err(schedule_error)
+    }
+
+    /// Next fire times of `request.schedule`, rejecting schedules that
+    /// cannot fire
+    pub fn preview(&self, request: &SchedulePreviewRequest) -> Result<SchedulePreview> {
+        request.schedule.validate().map_err(schedule_error)?;
+        let count = request.count.clamp(1, MAX_PREVIEW);
+        Ok(SchedulePreview {
+            fire_times: request.schedule.preview(Utc::now(), count),
+        })
+    }
+}
+
+fn schedule_error(error: WorkflowError) -> ApiError {
+    match error {
+        WorkflowError::NotFound(id) => ApiError::NotFound(format!("Schedule {} not found", id)),
+        WorkflowError::InvalidDefinition(reason) => ApiError::InvalidInput(reason),
+        other => ApiError::WorkflowError(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pause_resume_and_preview() {
+        let service = ScheduleService::default();
+        let schedule = ScheduledWorkflow::new(
+            "wf-1",
+            Schedule::Cron {
+                expression: "0 9 * * 1-5".to_string(),
+            },
+        )
+        .with_tenant("acme");
+        let schedule = service.scheduler().create(schedule).await.unwrap();
+
+        assert!(!service.pause(&schedule.id, "acme").await.unwrap().enabled);
+        assert!(service.resume(&schedule.id, "acme").await.unwrap().enabled);
+        assert!(matches!(service.pause("missing", "acme").await, Err(ApiError::NotFound(_))));
+
+        let preview = service
+            .preview(&SchedulePreviewRequest {
+                schedule: schedule.schedule,
+                count: 3,
+            })
+            .unwrap();
+        assert_eq!(preview.fire_times.len(), 3);
+
+        let invalid = SchedulePreviewRequest {
+            schedule: Schedule::Cron {
+                expression: "0 25 * * *".to_string(),
+            },
+            count: 3,
+        };
+        assert!(matches!(service.preview(&invalid), Err(ApiError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_tenants_change_only_their_schedules() {
+        let service = ScheduleService::default();
+        let daily = || Schedule::Cron {
+            expression: "0 9 * * *".to_string(),
+        };
+        let owned = ScheduledWorkflow::new("wf-1", daily()).with_tenant("acme");
+        let owned = service.scheduler().create(owned).await.unwrap();
+        let global = service.scheduler().create(ScheduledWorkflow::new("wf-2", daily())).await.unwrap();
+
+        assert!(!service.pause(&owned.id, "acme").await.unwrap().enabled);
+        assert!(matches!(service.resume(&owned.id, "globex").await, Err(ApiError::NotFound(_))));
+        assert!(matches!(service.get(&owned.id, "globex").await, Err(ApiError::NotFound(_))));
+        assert!(matches!(service.pause(&global.id, "acme").await, Err(ApiError::NotFound(_))));
+
+        let listed = service.list("acme").await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, owned.id);
+        assert!(service.list("globex").await.unwrap().is_empty());
+    }
+}
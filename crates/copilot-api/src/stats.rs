@@ -0,0 +1,245 @@
+//! Live server statistics
+//!
+//! [`ServerStats`] counts API requests and keeps a short history of failed
+//! ones. Together with the task queue counts and any gauges registered by the
+//! embedding application (active workflows, sandboxes, delivery queues, ...)
+//! it forms the [`DashboardSnapshot`] served to `copilot top`.
+
+use crate::tasks::TaskQueueStats;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Seconds of per-second request counts kept for the rate history
+const RATE_WINDOW_SECS: usize = 60;
+
+/// A request that failed with a server error
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentError {
+    pub timestamp: DateTime<Utc>,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub duration_ms: u64,
+}
+
+/// Point-in-time view of the server for dashboards
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardSnapshot {
+    pub timestamp: DateTime<Utc>,
+    pub uptime_secs: u64,
+    pub requests_total: u64,
+    pub errors_total: u64,
+    pub in_flight: u64,
+    /// Requests per second over the last minute
+    pub requests_per_sec: f64,
+    /// Requests completed in each of the last seconds, oldest first
+    pub request_rate_history: Vec<u64>,
+    pub tasks: TaskQueueStats,
+    /// Application gauges such as `workflows_active` or `sandboxes_active`
+    pub gauges: BTreeMap<String, f64>,
+    /// Most recent server errors, newest first
+    pub recent_errors: Vec<RecentError>,
+}
+
+/// Per-second request counts covering the last [`RATE_WINDOW_SECS`]
+struct RateWindow {
+    /// Second (since start) that the newest bucket belongs to
+    newest_second: u64,
+    /// Oldest first
+    buckets: VecDeque<u64>,
+}
+
+impl RateWindow {
+    fn new() -> Self {
+        Self {
+            newest_second: 0,
+            buckets: VecDeque::from(vec![0; RATE_WINDOW_SECS]),
+        }
+    }
+
+    /// Slide the window so its newest bucket is `second`
+    fn advance(&mut self, second: u64) {
+        if second <= self.newest_second {
+            return;
+        }
+        let shift = (second - self.newest_second).min(RATE_WINDOW_SECS as u64);
+        for _ in 0..shift {
+            self.buckets.pop_front();
+            self.buckets.push_back(0);
+        }
+        self.newest_second = second;
+    }
+
+    fn record(&mut self, second: u64) {
+        self.advance(second);
+        if let Some(bucket) = self.buckets.back_mut() {
+            *bucket += 1;
+        }
+    }
+}
+
+/// Request counters shared by the REST middleware and the dashboard handlers
+pub struct ServerStats {
+    started: Instant,
+    requests_total: AtomicU64,
+    errors_total: AtomicU64,
+    in_flight: AtomicU64,
+    rate: Mutex<RateWindow>,
+    recent_errors: Mutex<VecDeque<RecentError>>,
+    gauges: Mutex<BTreeMap<String, f64>>,
+    max_recent_errors: usize,
+}
+
+impl ServerStats {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            requests_total: AtomicU64::new(0),
+            errors_total: AtomicU64::new(0),
+            in_flight: AtomicU64::new(0),
+            rate: Mutex::new(RateWindow::new()),
+            recent_errors: Mutex::new(VecDeque::new()),
+            gauges: Mutex::new(BTreeMap::new()),
+            max_recent_errors: 50,
+        }
+    }
+
+    /// Set how many recent errors are kept
+    pub fn with_max_recent_errors(mut self, max: usize) -> Self {
+        self.max_recent_errors = max;
+        self
+    }
+
+    /// Mark a request as started
+    pub fn request_started(&self) {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a finished request; 5xx responses are kept as recent errors
+    pub fn request_finished(&self, method: &str, path: &str, status: u16, duration: Duration) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.requests_total.fetch_add(1, Ordering::Relaxed);
+        self.rate
+            .lock()
+            .expect("rate window poisoned")
+            .record(self.started.elapsed().as_secs());
+
+        if status >= 500 {
+            self.errors_total.fetch_add(1, Ordering::Relaxed);
+            let mut errors = self.recent_errors.lock().expect("recent errors poisoned");
+            errors.push_front(RecentError {
+                timestamp: Utc::now(),
+                method: method.to_string(),
+                path: path.to_string(),
+                status,
+                duration_ms: duration.as_millis() as u64,
+            });
+            errors.truncate(self.max_recent_errors);
+        }
+    }
+
+    /// Set an application gauge, replacing its previous value
+    pub fn set_gauge(&self, name: impl Into<String>, value: f64) {
+        self.gauges
+            .lock()
+            .expect("gauges poisoned")
+            .insert(name.into(), value);
+    }
+
+    /// Remove an application gauge
+    pub fn clear_gauge(&self, name: &str) {
+        self.gauges.lock().expect("gauges poisoned").remove(name);
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Snapshot the counters together with the task queue state
+    pub fn snapshot(&self, tasks: TaskQueueStats) -> DashboardSnapshot {
+        let elapsed = self.started.elapsed().as_secs();
+        let history: Vec<u64> = {
+            let mut rate = self.rate.lock().expect("rate window poisoned");
+            rate.advance(elapsed);
+            rate.buckets.iter().copied().collect()
+        };
+        // Only count the seconds the server has actually been up
+        let observed = (elapsed as usize + 1).min(RATE_WINDOW_SECS);
+        let recent: u64 = history.iter().rev().take(observed).sum();
+
+        DashboardSnapshot {
+            timestamp: Utc::now(),
+            uptime_secs: elapsed,
+            requests_total: self.requests_total.load(Ordering::Relaxed),
+            errors_total: self.errors_total.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            requests_per_sec: recent as f64 / observed as f64,
+            request_rate_history: history,
+            tasks,
+            gauges: self.gauges.lock().expect("gauges poisoned").clone(),
+            recent_errors: self
+                .recent_errors
+                .lock()
+                .expect("recent errors poisoned")
+                .iter()
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+impl Default for ServerStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_counts_and_recent_errors() {
+        let stats = ServerStats::new().with_max_recent_errors(2);
+        for (path, status) in [("/a", 200), ("/b", 500), ("/c", 503), ("/d", 502)] {
+            stats.request_started();
+            stats.request_finished("GET", path, status, Duration::from_millis(5));
+        }
+        stats.request_started();
+        stats.set_gauge("workflows_active", 3.0);
+
+        let snapshot = stats.snapshot(TaskQueueStats::default());
+        assert_eq!(snapshot.requests_total, 4);
+        assert_eq!(snapshot.errors_total, 3);
+        assert_eq!(snapshot.in_flight, 1);
+        assert_eq!(snapshot.request_rate_history.len(), RATE_WINDOW_SECS);
+        assert_eq!(snapshot.request_rate_history.iter().sum::<u64>(), 4);
+        assert!(snapshot.requests_per_sec > 0.0);
+        assert_eq!(snapshot.gauges["workflows_active"], 3.0);
+
+        let paths: Vec<_> = snapshot.recent_errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["/d", "/c"]);
+    }
+
+    #[test]
+    fn test_rate_window_slides() {
+        let mut window = RateWindow::new();
+        window.record(0);
+        window.record(59);
+        assert_eq!(window.buckets.iter().sum::<u64>(), 2);
+        assert_eq!(window.buckets[0], 1);
+
+        window.record(60);
+        // Second 0 has left the window
+        assert_eq!(window.buckets.iter().sum::<u64>(), 2);
+        assert_eq!(window.buckets[RATE_WINDOW_SECS - 1], 1);
+
+        window.advance(1_000);
+        assert_eq!(window.buckets.iter().sum::<u64>(), 0);
+        assert_eq!(window.newest_second, 1_000);
+    }
+}
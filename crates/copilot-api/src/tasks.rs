@@ -0,0 +1,842 @@
+//! Agent task queue
+//!
+//! Long-running agent jobs (code generation, analysis, ...) are submitted to a
+//! [`TaskQueue`] instead of being executed inline in request handlers. Tasks
+//! wait in priority lanes and are started when both the global and the
+//! per-tenant concurrency caps allow it. Progress is published as
+//! [`TaskEvent`]s (served to clients over SSE) and tasks can be cancelled
+//! while queued or running. Finished and failed tasks are reported to the
+//! [`TaskNotifier`], when one is set. A task runs as the [`Principal`] that
+//! submitted it, so it retrieves only the context its submitter may read.
+
+use crate::error::{ApiError, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use copilot_conversation::ConversationManager;
+use copilot_core::Principal;
+use copilot_webhook::{TaskKind, TaskNotification, TaskNotifier, TaskOutcome};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Priority lane of a task; higher lanes are always drained first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Critical,
+}
+
+impl TaskPriority {
+    const LANES: usize = 4;
+
+    /// Every priority, lowest first
+    pub const ALL: [TaskPriority; 4] = [
+        TaskPriority::Low,
+        TaskPriority::Normal,
+        TaskPriority::High,
+        TaskPriority::Critical,
+    ];
+
+    fn lane(self) -> usize {
+        self as usize
+    }
+}
+
+/// Lifecycle state of a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl TaskStatus {
+    /// Whether the task has finished (successfully or not)
+    pub fn is_terminal(self) -> bool {
+        matches!(self, TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Cancelled)
+    }
+}
+
+/// Snapshot of a task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskInfo {
+    pub id: Uuid,
+    pub tenant_id: String,
+    /// User who submitted the task
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submitted_by: Option<String>,
+    /// Handler kind (e.g. "code_generation")
+    pub kind: String,
+    pub priority: TaskPriority,
+    pub status: TaskStatus,
+    /// Progress in `[0, 1]`
+    pub progress: f32,
+    /// Latest progress message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Progress or state change of a task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskEvent {
+    pub task_id: Uuid,
+    pub status: TaskStatus,
+    pub progress: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Task queue configuration
+#[derive(Debug, Clone)]
+pub struct TaskQueueConfig {
+    /// Maximum tasks running at once across all tenants
+    pub max_concurrent: usize,
+    /// Maximum tasks running at once per tenant, unless overridden
+    pub default_tenant_limit: usize,
+    /// Per-tenant concurrency overrides
+    pub tenant_limits: HashMap<String, usize>,
+    /// Maximum queued tasks per tenant
+    pub max_queued_per_tenant: usize,
+    /// Finished tasks retained for status queries
+    pub retained_finished: usize,
+    /// Capacity of the progress event channel
+    pub event_capacity: usize,
+}
+
+impl Default for TaskQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 32,
+            default_tenant_limit: 4,
+            tenant_limits: HashMap::new(),
+            max_queued_per_tenant: 100,
+            retained_finished: 1_000,
+            event_capacity: 1_024,
+        }
+    }
+}
+
+impl TaskQueueConfig {
+    pub fn with_max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = max_concurrent;
+        self
+    }
+
+    pub fn with_default_tenant_limit(mut self, limit: usize) -> Self {
+        self.default_tenant_limit = limit;
+        self
+    }
+
+    pub fn with_tenant_limit(mut self, tenant_id: impl Into<String>, limit: usize) -> Self {
+        self.tenant_limits.insert(tenant_id.into(), limit);
+        self
+    }
+
+    fn tenant_limit(&self, tenant_id: &str) -> usize {
+        self.tenant_limits
+            .get(tenant_id)
+            .copied()
+            .unwrap_or(self.default_tenant_limit)
+    }
+}
+
+/// Execution context handed to a [`TaskHandler`]
+#[derive(Clone)]
+pub struct TaskContext {
+    pub task_id: Uuid,
+    pub tenant_id: String,
+    pub payload: serde_json::Value,
+    cancel: CancellationToken,
+    queue: TaskQueue,
+}
+
+impl TaskContext {
+    /// Report progress (clamped to `[0, 1]`) with an optional message
+    pub fn report_progress(&self, progress: f32, message: Option<String>) {
+        self.queue.update_progress(self.task_id, progress, message);
+    }
+
+    /// Whether cancellation has been requested
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Resolves when cancellation is requested
+    pub async fn cancelled(&self) {
+        self.cancel.cancelled().await
+    }
+}
+
+/// Executes tasks of one kind
+#[async_trait]
+pub trait TaskHandler: Send + Sync {
+    async fn run(&self, ctx: TaskContext) -> anyhow::Result<serde_json::Value>;
+}
+
+/// Queue depth and retained task counts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskQueueStats {
+    pub queued: usize,
+    pub queued_by_priority: BTreeMap<TaskPriority, usize>,
+    pub running: usize,
+    /// Finished tasks still retained for status queries
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+}
+
+struct TaskEntry {
+    info: TaskInfo,
+    payload: serde_json::Value,
+    cancel: CancellationToken,
+    principal: Option<Principal>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    tasks: HashMap<Uuid, TaskEntry>,
+    lanes: [VecDeque<Uuid>; TaskPriority::LANES],
+    running: usize,
+    running_per_tenant: HashMap<String, usize>,
+    queued_per_tenant: HashMap<String, usize>,
+    finished: VecDeque<Uuid>,
+}
+
+struct Inner {
+    config: TaskQueueConfig,
+    handlers: RwLock<HashMap<String, Arc<dyn TaskHandler>>>,
+    state: Mutex<QueueState>,
+    events: broadcast::Sender<TaskEvent>,
+    notifier: RwLock<Option<Arc<TaskNotifier>>>,
+}
+
+/// Priority task queue with per-tenant concurrency caps
+#[derive(Clone)]
+pub struct TaskQueue {
+    inner: Arc<Inner>,
+}
+
+impl TaskQueue {
+    pub fn new(config: TaskQueueConfig) -> Self {
+        let (events, _) = broadcast::channel(config.event_capacity.max(1));
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                handlers: RwLock::new(HashMap::new()),
+                state: Mutex::new(QueueState::default()),
+                events,
+                notifier: RwLock::new(None),
+            }),
+        }
+    }
+
+    /// Register the handler for a task kind
+    pub fn register_handler(&self, kind: impl Into<String>, handler: Arc<dyn TaskHandler>) {
+        self.inner
+            .handlers
+            .write()
+            .expect("handler registry poisoned")
+            .insert(kind.into(), handler);
+    }
+
+    /// Report completed and failed tasks through `notifier`
+    pub fn set_notifier(&self, notifier: Arc<TaskNotifier>) {
+        *self.inner.notifier.write().expect("task notifier poisoned") = Some(notifier);
+    }
+
+    /// Registered task kinds, sorted
+    pub fn kinds(&self) -> Vec<String> {
+        let mut kinds: Vec<_> = self
+            .inner
+            .handlers
+            .read()
+            .expect("handler registry poisoned")
+            .keys()
+            .cloned()
+            .collect();
+        kinds.sort();
+        kinds
+    }
+
+    /// Enqueue a task, to run as the current principal, and start it if
+    /// capacity allows
+    pub fn submit(
+        &self,
+        tenant_id: &str,
+        user_id: Option<&str>,
+        kind: &str,
+        priority: TaskPriority,
+        payload: serde_json::Value,
+    ) -> Result<TaskInfo> {
+        if !self.inner.handlers.read().expect("handler registry poisoned").contains_key(kind) {
+            return Err(ApiError::InvalidInput(format!("Unknown task kind: {}", kind)));
+        }
+
+        let info = {
+            let mut state = self.lock();
+            let queued = state.queued_per_tenant.get(tenant_id).copied().unwrap_or(0);
+            if queued >= self.inner.config.max_queued_per_tenant {
+                return Err(ApiError::RateLimitExceeded);
+            }
+
+            let info = TaskInfo {
+                id: Uuid::new_v4(),
+                tenant_id: tenant_id.to_string(),
+                submitted_by: user_id.map(str::to_string),
+                kind: kind.to_string(),
+                priority,
+                status: TaskStatus::Queued,
+                progress: 0.0,
+                message: None,
+                result: None,
+                error: None,
+                created_at: Utc::now(),
+                started_at: None,
+                finished_at: None,
+            };
+
+            state.lanes[priority.lane()].push_back(info.id);
+            *state.queued_per_tenant.entry(tenant_id.to_string()).or_default() += 1;
+            state.tasks.insert(
+                info.id,
+                TaskEntry {
+                    info: info.clone(),
+                    payload,
+                    cancel: CancellationToken::new(),
+                    principal: Principal::current(),
+                },
+            );
+            info
+        };
+
+        info!(task_id = %info.id, tenant_id, kind, ?priority, "Task queued");
+        self.emit(&info);
+        self.dispatch();
+        Ok(info)
+    }
+
+    /// Get a task, scoped to its tenant
+    pub fn get(&self, tenant_id: &str, task_id: Uuid) -> Option<TaskInfo> {
+        self.lock()
+            .tasks
+            .get(&task_id)
+            .filter(|t| t.info.tenant_id == tenant_id)
+            .map(|t| t.info.clone())
+    }
+
+    /// All retained tasks for a tenant, newest first
+    pub fn list(&self, tenant_id: &str) -> Vec<TaskInfo> {
+        let mut tasks: Vec<_> = self
+            .lock()
+            .tasks
+            .values()
+            .filter(|t| t.info.tenant_id == tenant_id)
+            .map(|t| t.info.clone())
+            .collect();
+        tasks.sort_by_key(|t| std::cmp::Reverse(t.created_at));
+        tasks
+    }
+
+    /// Cancel a queued or running task
+    pub fn cancel(&self, tenant_id: &str, task_id: Uuid) -> Result<TaskInfo> {
+        let mut state = self.lock();
+        let entry = state
+            .tasks
+            .get_mut(&task_id)
+            .filter(|t| t.info.tenant_id == tenant_id)
+            .ok_or_else(|| ApiError::NotFound(format!("Task {} not found", task_id)))?;
+
+        match entry.info.status {
+            TaskStatus::Queued => {
+                let lane = entry.info.priority.lane();
+                entry.info.status = TaskStatus::Cancelled;
+                entry.info.finished_at = Some(Utc::now());
+                let info = entry.info.clone();
+
+                state.lanes[lane].retain(|id| *id != task_id);
+                Self::decrement(&mut state.queued_per_tenant, tenant_id);
+                self.retain_finished(&mut state, task_id);
+                drop(state);
+
+                info!(%task_id, "Queued task cancelled");
+                self.emit(&info);
+                Ok(info)
+            }
+            TaskStatus::Running => {
+                // The runner observes the token, records the cancellation and
+                // frees the concurrency slot
+                entry.cancel.cancel();
+                Ok(entry.info.clone())
+            }
+            _ => Err(ApiError::InvalidInput(format!(
+                "Task {} has already finished",
+                task_id
+            ))),
+        }
+    }
+
+    /// Counts across all tenants, for operational dashboards
+    pub fn stats(&self) -> TaskQueueStats {
+        let state = self.lock();
+        let mut stats = TaskQueueStats {
+            queued_by_priority: TaskPriority::ALL
+                .into_iter()
+                .map(|priority| (priority, state.lanes[priority.lane()].len()))
+                .collect(),
+            running: state.running,
+            ..Default::default()
+        };
+        stats.queued = stats.queued_by_priority.values().sum();
+        for entry in state.tasks.values() {
+            match entry.info.status {
+                TaskStatus::Completed => stats.completed += 1,
+                TaskStatus::Failed => stats.failed += 1,
+                TaskStatus::Cancelled => stats.cancelled += 1,
+                TaskStatus::Queued | TaskStatus::Running => {}
+            }
+        }
+        stats
+    }
+
+    /// Subscribe to task events for all tasks
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.inner.events.subscribe()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.inner.state.lock().expect("task queue state poisoned")
+    }
+
+    fn emit(&self, info: &TaskInfo) {
+        // No receivers is fine; events are best-effort
+        let _ = self.inner.events.send(TaskEvent {
+            task_id: info.id,
+            status: info.status,
+            progress: info.progress,
+            message: info.message.clone(),
+            timestamp: Utc::now(),
+        });
+    }
+
+    fn decrement(counts: &mut HashMap<String, usize>, tenant_id: &str) {
+        if let Some(count) = counts.get_mut(tenant_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                counts.remove(tenant_id);
+            }
+        }
+    }
+
+    fn retain_finished(&self, state: &mut QueueState, task_id: Uuid) {
+        state.finished.push_back(task_id);
+        while state.finished.len() > self.inner.config.retained_finished {
+            if let Some(old) = state.finished.pop_front() {
+                state.tasks.remove(&old);
+            }
+        }
+    }
+
+    fn update_progress(&self, task_id: Uuid, progress: f32, message: Option<String>) {
+        let info = {
+            let mut state = self.lock();
+            let Some(entry) = state.tasks.get_mut(&task_id) else {
+                return;
+            };
+            if entry.info.status != TaskStatus::Running {
+                return;
+            }
+            entry.info.progress = progress.clamp(0.0, 1.0);
+            entry.info.message = message;
+            entry.info.clone()
+        };
+        self.emit(&info);
+    }
+
+    /// Start as many queued tasks as the concurrency caps allow
+    fn dispatch(&self) {
+        let handlers = self.inner.handlers.read().expect("handler registry poisoned").clone();
+        let mut started = Vec::new();
+
+        {
+            let mut state = self.lock();
+            let state = &mut *state;
+
+            for lane in (0..TaskPriority::LANES).rev() {
+                let mut index = 0;
+                while index < state.lanes[lane].len() {
+                    if state.running >= self.inner.config.max_concurrent {
+                        break;
+                    }
+
+                    let task_id = state.lanes[lane][index];
+                    let entry = state.tasks.get_mut(&task_id).expect("queued task exists");
+                    let tenant_id = entry.info.tenant_id.clone();
+                    let running = state.running_per_tenant.get(&tenant_id).copied().unwrap_or(0);
+                    if running >= self.inner.config.tenant_limit(&tenant_id) {
+                        // Skip over saturated tenants so they don't block others
+                        index += 1;
+                        continue;
+                    }
+
+                    state.lanes[lane].remove(index);
+                    entry.info.status = TaskStatus::Running;
+                    entry.info.started_at = Some(Utc::now());
+                    state.running += 1;
+                    *state.running_per_tenant.entry(tenant_id.clone()).or_default() += 1;
+                    Self::decrement(&mut state.queued_per_tenant, &tenant_id);
+
+                    let handler = handlers.get(&entry.info.kind).cloned();
+                    let ctx = TaskContext {
+                        task_id,
+                        tenant_id,
+                        payload: entry.payload.clone(),
+                        cancel: entry.cancel.clone(),
+                        queue: self.clone(),
+                    };
+                    started.push((entry.info.clone(), handler, ctx, entry.principal.clone()));
+                }
+            }
+        }
+
+        for (info, handler, ctx, principal) in started {
+            debug!(task_id = %info.id, "Task started");
+            self.emit(&info);
+
+            let queue = self.clone();
+            tokio::spawn(async move {
+                let task_id = ctx.task_id;
+                let cancel = ctx.cancel.clone();
+                let outcome = match handler {
+                    Some(handler) => {
+                        let run = async {
+                            match principal {
+                                Some(principal) => principal.scope(handler.run(ctx)).await,
+                                None => handler.run(ctx).await,
+                            }
+                        };
+                        tokio::select! {
+                            result = run => Some(result),
+                            _ = cancel.cancelled() => None,
+                        }
+                    }
+                    None => Some(Err(anyhow::anyhow!("No handler registered for task kind"))),
+                };
+                queue.finish(task_id, outcome);
+            });
+        }
+    }
+
+    /// Record a task's outcome (`None` means cancelled) and free its slot
+    fn finish(&self, task_id: Uuid, outcome: Option<anyhow::Result<serde_json::Value>>) {
+        let info = {
+            let mut state = self.lock();
+            let Some(entry) = state.tasks.get_mut(&task_id) else {
+                return;
+            };
+
+            match outcome {
+                Some(Ok(value)) => {
+                    entry.info.status = TaskStatus::Completed;
+                    entry.info.progress = 1.0;
+                    entry.info.result = Some(value);
+                }
+                Some(Err(e)) => {
+                    warn!(%task_id, error = %e, "Task failed");
+                    entry.info.status = TaskStatus::Failed;
+                    entry.info.error = Some(e.to_string());
+                }
+                None => {
+                    info!(%task_id, "Running task cancelled");
+                    entry.info.status = TaskStatus::Cancelled;
+                }
+            }
+            entry.info.finished_at = Some(Utc::now());
+            let info = entry.info.clone();
+
+            state.running = state.running.saturating_sub(1);
+            Self::decrement(&mut state.running_per_tenant, &info.tenant_id);
+            self.retain_finished(&mut state, task_id);
+            info
+        };
+
+        self.emit(&info);
+        self.notify(&info);
+        self.dispatch();
+    }
+
+    /// Send a completion or failure notification in the background
+    fn notify(&self, info: &TaskInfo) {
+        let outcome = match info.status {
+            TaskStatus::Completed => TaskOutcome::Completed,
+            TaskStatus::Failed => TaskOutcome::Failed,
+            _ => return,
+        };
+        let Some(notifier) = self.inner.notifier.read().expect("task notifier poisoned").clone() else {
+            return;
+        };
+        let notification =
+            TaskNotification::new(TaskKind::AgentTask, info.id.to_string(), &info.kind, outcome, &info.tenant_id)
+                .with_user(info.submitted_by.as_deref())
+                .with_error(info.error.as_deref())
+                .with_times(info.started_at, info.finished_at.unwrap_or_else(Utc::now));
+        tokio::spawn(async move {
+            notifier.notify(&notification).await;
+        });
+    }
+}
+
+impl Default for TaskQueue {
+    fn default() -> Self {
+        Self::new(TaskQueueConfig::default())
+    }
+}
+
+/// Runs a prompt against a conversation session.
+///
+/// Expects a payload of `{"session_id": "...", "prompt": "..."}` and returns
+/// `{"response": "..."}`.
+pub struct ConversationTaskHandler {
+    manager: Arc<ConversationManager>,
+}
+
+impl ConversationTaskHandler {
+    pub fn new(manager: Arc<ConversationManager>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl TaskHandler for ConversationTaskHandler {
+    async fn run(&self, ctx: TaskContext) -> anyhow::Result<serde_json::Value> {
+        let session_id = ctx.payload["session_id"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("payload.session_id is required"))?;
+        let prompt = ctx.payload["prompt"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("payload.prompt is required"))?;
+
+        ctx.report_progress(0.1, Some("Generating response".to_string()));
+        let response = self.manager.generate_response(session_id, prompt).await?;
+
+        Ok(serde_json::json!({ "response": response }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::Semaphore;
+
+    /// Blocks each task until a permit is released, recording start order
+    struct GatedHandler {
+        gate: Arc<Semaphore>,
+        started: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl TaskHandler for GatedHandler {
+        async fn run(&self, ctx: TaskContext) -> anyhow::Result<serde_json::Value> {
+            let label = ctx.payload["label"].as_str().unwrap_or_default().to_string();
+            self.started.lock().unwrap().push(label.clone());
+            ctx.report_progress(0.5, Some("halfway".to_string()));
+            self.gate.acquire().await?.forget();
+            Ok(serde_json::json!({ "label": label }))
+        }
+    }
+
+    fn queue(config: TaskQueueConfig) -> (TaskQueue, Arc<Semaphore>, Arc<Mutex<Vec<String>>>) {
+        let queue = TaskQueue::new(config);
+        let gate = Arc::new(Semaphore::new(0));
+        let started = Arc::new(Mutex::new(Vec::new()));
+        queue.register_handler(
+            "job",
+            Arc::new(GatedHandler {
+                gate: gate.clone(),
+                started: started.clone(),
+            }),
+        );
+        (queue, gate, started)
+    }
+
+    async fn wait_for(queue: &TaskQueue, tenant: &str, id: Uuid, status: TaskStatus) -> TaskInfo {
+        for _ in 0..200 {
+            if let Some(info) = queue.get(tenant, id).filter(|t| t.status == status) {
+                return info;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("task {} never reached {:?}", id, status);
+    }
+
+    fn payload(label: &str) -> serde_json::Value {
+        serde_json::json!({ "label": label })
+    }
+
+    #[tokio::test]
+    async fn test_priority_lanes_and_tenant_cap() {
+        let (queue, gate, started) = queue(
+            TaskQueueConfig::default()
+                .with_default_tenant_limit(1)
+                .with_tenant_limit("big", 2),
+        );
+
+        let first = queue.submit("acme", None, "job", TaskPriority::Normal, payload("first")).unwrap();
+        let low = queue.submit("acme", None, "job", TaskPriority::Low, payload("low")).unwrap();
+        let high = queue.submit("acme", None, "job", TaskPriority::High, payload("high")).unwrap();
+        // Another tenant is not blocked by acme's saturated slot
+        let other = queue.submit("big", None, "job", TaskPriority::Low, payload("other")).unwrap();
+
+        wait_for(&queue, "acme", first.id, TaskStatus::Running).await;
+        wait_for(&queue, "big", other.id, TaskStatus::Running).await;
+        assert_eq!(queue.get("acme", high.id).unwrap().status, TaskStatus::Queued);
+        let stats = queue.stats();
+        assert_eq!((stats.queued, stats.running), (2, 2));
+        assert_eq!(stats.queued_by_priority[&TaskPriority::High], 1);
+
+        gate.add_permits(1);
+        // The high-priority task jumps ahead of the earlier low-priority one
+        wait_for(&queue, "acme", high.id, TaskStatus::Running).await;
+        assert_eq!(queue.get("acme", low.id).unwrap().status, TaskStatus::Queued);
+
+        gate.add_permits(3);
+        let done = wait_for(&queue, "acme", low.id, TaskStatus::Completed).await;
+        assert_eq!(done.result, Some(serde_json::json!({ "label": "low" })));
+        assert_eq!(started.lock().unwrap()[..2], ["first".to_string(), "other".to_string()]);
+        assert_eq!(started.lock().unwrap()[2..], ["high".to_string(), "low".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_queued_and_running() {
+        let (queue, _gate, _) = queue(TaskQueueConfig::default().with_default_tenant_limit(1));
+
+        let running = queue.submit("acme", None, "job", TaskPriority::Normal, payload("a")).unwrap();
+        let queued = queue.submit("acme", None, "job", TaskPriority::Normal, payload("b")).unwrap();
+        wait_for(&queue, "acme", running.id, TaskStatus::Running).await;
+
+        let cancelled = queue.cancel("acme", queued.id).unwrap();
+        assert_eq!(cancelled.status, TaskStatus::Cancelled);
+
+        queue.cancel("acme", running.id).unwrap();
+        wait_for(&queue, "acme", running.id, TaskStatus::Cancelled).await;
+
+        assert!(queue.cancel("acme", running.id).is_err());
+        // Tasks are invisible to other tenants
+        assert!(matches!(queue.cancel("evil", queued.id), Err(ApiError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_progress_events_and_validation() {
+        let (queue, gate, _) = queue(TaskQueueConfig::default());
+        let mut events = queue.subscribe();
+
+        assert!(queue.submit("acme", None, "unknown", TaskPriority::Normal, payload("x")).is_err());
+
+        let task = queue.submit("acme", None, "job", TaskPriority::Normal, payload("x")).unwrap();
+        gate.add_permits(1);
+
+        let mut statuses = Vec::new();
+        while let Ok(event) = tokio::time::timeout(Duration::from_secs(1), events.recv()).await {
+            let event = event.unwrap();
+            assert_eq!(event.task_id, task.id);
+            statuses.push((event.status, event.progress));
+            if event.status.is_terminal() {
+                break;
+            }
+        }
+
+        assert_eq!(
+            statuses,
+            vec![
+                (TaskStatus::Queued, 0.0),
+                (TaskStatus::Running, 0.0),
+                (TaskStatus::Running, 0.5),
+                (TaskStatus::Completed, 1.0),
+            ]
+        );
+    }
+
+    struct WhoAmI;
+
+    #[async_trait]
+    impl TaskHandler for WhoAmI {
+        async fn run(&self, _ctx: TaskContext) -> anyhow::Result<serde_json::Value> {
+            Ok(serde_json::json!(Principal::current().map(|p| p.user_id)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tasks_run_as_their_submitter() {
+        let queue = TaskQueue::new(TaskQueueConfig::default());
+        queue.register_handler("whoami", Arc::new(WhoAmI));
+
+        let submit = || queue.submit("acme", Some("bob"), "whoami", TaskPriority::Normal, payload("x"));
+        let scoped = Principal::new("bob").scope(async { submit() }).await.unwrap();
+        let unscoped = submit().unwrap();
+
+        let done = wait_for(&queue, "acme", scoped.id, TaskStatus::Completed).await;
+        assert_eq!(done.result, Some(serde_json::json!("bob")));
+        let done = wait_for(&queue, "acme", unscoped.id, TaskStatus::Completed).await;
+        assert_eq!(done.result, Some(serde_json::Value::Null));
+    }
+
+    struct RecordingSender(Mutex<Vec<copilot_webhook::EmailMessage>>);
+
+    #[async_trait]
+    impl copilot_webhook::EmailSender for RecordingSender {
+        async fn send(&self, message: &copilot_webhook::EmailMessage) -> copilot_webhook::Result<()> {
+            self.0.lock().unwrap().push(message.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_finished_task_notifies_submitter() {
+        let (queue, gate, _) = queue(TaskQueueConfig::default());
+        let subscriptions = Arc::new(copilot_webhook::NotificationSubscriptions::default());
+        subscriptions.set_user(
+            "acme",
+            "alice",
+            copilot_webhook::NotificationPreferences::none().with_email("alice@example.com"),
+        );
+        let sender = Arc::new(RecordingSender(Mutex::new(Vec::new())));
+        queue.set_notifier(Arc::new(TaskNotifier::new(subscriptions).with_email(sender.clone())));
+
+        let task = queue.submit("acme", Some("alice"), "job", TaskPriority::Normal, payload("x")).unwrap();
+        assert_eq!(task.submitted_by.as_deref(), Some("alice"));
+        gate.add_permits(1);
+        wait_for(&queue, "acme", task.id, TaskStatus::Completed).await;
+
+        for _ in 0..200 {
+            if !sender.0.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let sent = sender.0.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "alice@example.com");
+        assert!(sent[0].body.contains(&task.id.to_string()));
+    }
+}
@@ -1,6 +1,16 @@
 //! Common types used across the API
 
 use chrono::{DateTime, Utc};
+use copilot_adapters::traits::DashboardPushResponse;
+use copilot_core::SandboxPolicy;
+use copilot_infra::{ObjectClass, PresignedUrl, StoredObject};
+use copilot_security::ReviewDecision;
+use copilot_nlp::{AlertBacktest, AlertRule, LogAnalysis, QueryLanguage};
+use copilot_conversation::{
+    ContextDownsize, ConversationMessage, HandoffBundle, MessagePart, NoteKind, PreferenceSuggestion, TokenUsage,
+    UserPreferences,
+};
+use copilot_webhook::{WebhookEndpoint, WebhookEventType};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -10,6 +20,15 @@ pub struct CreateSessionRequest {
     /// Optional session name
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    /// Persona to assign to the session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona_id: Option<String>,
+    /// Tools the session may call, within the persona's allowlist
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_tools: Option<Vec<String>>,
+    /// Sandbox capabilities for the session, within the persona's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxPolicy>,
     /// Session metadata
     #[serde(default)]
     pub metadata: serde_json::Value,
@@ -27,8 +46,130 @@ pub struct SessionResponse {
     pub created_at: DateTime<Utc>,
     /// Last activity timestamp
     pub last_activity: DateTime<Utc>,
+    /// Persona assigned to the session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona_id: Option<String>,
     /// Session metadata
     pub metadata: serde_json::Value,
+    /// Tokens and cost of the session so far
+    #[serde(default)]
+    pub usage: TokenUsage,
+}
+
+/// Chat turn within a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionChatRequest {
+    /// User message
+    pub message: String,
+    /// Optional message metadata
+    #[serde(default)]
+    pub metadata: std::collections::HashMap<String, String>,
+    /// Model to answer this message with instead of the session's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// Assistant reply to a chat turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionChatResponse {
+    /// Assistant response content
+    pub content: String,
+    /// Session the turn belongs to
+    pub conversation_id: String,
+    /// Tokens and cost of this turn
+    pub usage: TokenUsage,
+    /// Running tokens and cost of the session
+    pub session_usage: TokenUsage,
+    /// Model that answered, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Preferences learned from this turn, awaiting the user's confirmation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preference_suggestions: Vec<PreferenceSuggestion>,
+    /// How the retrieved context was cut to fit the model, if it was
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_downsize: Option<ContextDownsize>,
+}
+
+/// The caller's preferences and the learned ones awaiting confirmation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserPreferencesResponse {
+    /// Confirmed preferences, applied to the caller's sessions
+    pub preferences: UserPreferences,
+    /// Learned preferences to confirm or dismiss
+    pub suggestions: Vec<PreferenceSuggestion>,
+}
+
+/// Session-level tool allowlist and sandbox policy
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateToolPolicyRequest {
+    /// Tools the session may call; omit to use the persona's allowlist
+    #[serde(default)]
+    pub allowed_tools: Option<Vec<String>>,
+    /// Sandbox capabilities; omit to use the persona's
+    #[serde(default)]
+    pub sandbox: Option<SandboxPolicy>,
+}
+
+/// Code to run in a session's sandbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecuteCodeRequest {
+    pub code: String,
+    /// Runtime to run the code with ("python", "node" or "bash")
+    #[serde(default = "default_runtime")]
+    pub runtime: String,
+    /// Run the code even if an identical run's result is still cached
+    #[serde(default)]
+    pub force_refresh: bool,
+}
+
+fn default_runtime() -> String {
+    "python".to_string()
+}
+
+/// Note to write to a session's working memory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteWorkingNoteRequest {
+    #[serde(default)]
+    pub kind: NoteKind,
+    pub content: String,
+    /// Tool or workflow step writing the note
+    #[serde(default)]
+    pub written_by: Option<String>,
+    /// Whether the note may be shown to the user
+    #[serde(default)]
+    pub visible: bool,
+}
+
+/// Request to answer one message with two models side by side
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompareModelsRequest {
+    /// User message
+    pub message: String,
+    /// The two models to compare
+    pub models: [String; 2],
+    /// Optional message metadata
+    #[serde(default)]
+    pub metadata: std::collections::HashMap<String, String>,
+}
+
+/// Preference vote on a model comparison
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparisonVoteRequest {
+    /// Model whose answer the user preferred
+    pub preferred_model: String,
+}
+
+/// The user's word on whether a conversation resolved their goal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolutionRequest {
+    pub resolved: bool,
+}
+
+/// The user's rating of a session's answers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedbackRequest {
+    pub helpful: bool,
 }
 
 /// Message send request
@@ -83,6 +224,337 @@ pub struct GetMessagesResponse {
     pub next_cursor: Option<String>,
 }
 
+/// A message of a session's history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryMessage {
+    /// `user`, `assistant`, `system` or `tool`
+    pub role: String,
+    pub content: String,
+    pub timestamp: DateTime<Utc>,
+    /// Tokens and cost of producing the message (assistant messages)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+    /// Model that produced the message (assistant messages)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Tool calls, their results and sandbox output (tool messages)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<MessagePart>,
+}
+
+impl From<ConversationMessage> for HistoryMessage {
+    fn from(message: ConversationMessage) -> Self {
+        Self {
+            role: message.role.as_str().to_string(),
+            content: message.content,
+            timestamp: message.timestamp,
+            usage: message.usage,
+            model: message.model,
+            parts: message.parts,
+        }
+    }
+}
+
+/// Code edits proposed in a session's latest response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposedEditsResponse {
+    /// Session identifier
+    pub session_id: String,
+    /// One entry per edited file, in the order they were proposed
+    pub edits: Vec<copilot_conversation::FileEdit>,
+}
+
+/// Request to hand a session over to humans
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HandoffRequest {
+    /// Handoff endpoints to post to, e.g. `incident` or `ticket`; empty posts
+    /// to every endpoint subscribed to handoffs
+    #[serde(default)]
+    pub targets: Vec<String>,
+    /// Notes for the people taking over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Build the handoff document without posting it
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Outcome of posting a handoff to one endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandoffDelivery {
+    pub endpoint_id: String,
+    pub delivered: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A session's handoff bundle and where it was posted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandoffResponse {
+    pub bundle: HandoffBundle,
+    /// The bundle as a Markdown document
+    pub document: String,
+    /// Empty for dry runs
+    pub deliveries: Vec<HandoffDelivery>,
+}
+
+/// Request for a URL to upload a file to object storage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileUploadRequest {
+    /// `attachment` or `sandbox_artifact`
+    pub class: ObjectClass,
+    /// Hex SHA-256 of the file; the upload is rejected if the body differs
+    pub sha256: String,
+    pub content_type: String,
+}
+
+/// Where to upload a file, unless the same content is already stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileUpload {
+    pub key: String,
+    pub sha256: String,
+    pub already_stored: bool,
+    /// Absent when the content is already stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload: Option<PresignedUrl>,
+}
+
+/// An export bundle kept in object storage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportBundle {
+    pub object: StoredObject,
+    pub download: PresignedUrl,
+}
+
+/// Request to record a review of a user's access
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessReviewRequest {
+    /// User whose access was reviewed
+    pub subject: String,
+    /// Roles the user held when reviewed
+    #[serde(default)]
+    pub roles: Vec<String>,
+    pub decision: ReviewDecision,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Request to verify an email address with the token mailed to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailVerificationRequest {
+    pub token: String,
+}
+
+/// Request for a password reset link
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+/// Request to set a new password with a password reset token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordResetConfirmation {
+    pub token: String,
+    pub new_password: String,
+}
+
+/// Request to register a webhook endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub name: String,
+    pub url: String,
+    /// Events to subscribe to; empty subscribes to all
+    #[serde(default)]
+    pub events: Vec<WebhookEventType>,
+    /// Extra headers sent with every delivery
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    /// Payload schema version to pin deliveries to; the current one when
+    /// omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+}
+
+/// A webhook endpoint, without its secrets or header values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpointInfo {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    /// Subscribed events; empty means all
+    pub events: Vec<WebhookEventType>,
+    pub enabled: bool,
+    /// Names of the extra headers sent with every delivery
+    pub header_names: Vec<String>,
+    /// Pinned payload schema version; deliveries use the current one when
+    /// unpinned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+    /// Until when deliveries are also signed with the previous secret
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_secret_expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<&WebhookEndpoint> for WebhookEndpointInfo {
+    fn from(endpoint: &WebhookEndpoint) -> Self {
+        Self {
+            id: endpoint.id.clone(),
+            name: endpoint.name.clone(),
+            url: endpoint.url.clone(),
+            events: endpoint.events.clone(),
+            enabled: endpoint.enabled,
+            header_names: endpoint.headers.iter().map(|(name, _)| name.clone()).collect(),
+            schema_version: endpoint.schema_version,
+            previous_secret_expires_at: endpoint
+                .active_previous_secret()
+                .and(endpoint.previous_secret_expires_at),
+            created_at: endpoint.created_at,
+            updated_at: endpoint.updated_at,
+        }
+    }
+}
+
+/// A webhook endpoint's signing secret, only shown when it is created or
+/// rotated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookSecret {
+    pub endpoint: WebhookEndpointInfo,
+    pub secret: String,
+}
+
+/// Request to rotate a webhook endpoint's signing secret
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RotateWebhookSecretRequest {
+    /// Hours deliveries stay signed with the old secret too; 24 by default,
+    /// at most a week, 0 to drop it immediately
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grace_hours: Option<u32>,
+}
+
+/// Outcome of a test delivery to a webhook endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPingResult {
+    pub endpoint_id: String,
+    pub delivered: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<u16>,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// One match of a context search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextSearchHit {
+    /// Context item identifier
+    pub id: String,
+    /// Beginning of the item's content
+    pub snippet: String,
+    /// Where the item came from
+    pub source: Option<String>,
+    /// Retrieval score
+    pub score: f32,
+}
+
+/// What a warmup of archived context did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextWarmup {
+    /// Archived items brought back into memory
+    pub rehydrated: usize,
+}
+
+/// Authority weight of a context source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceWeight {
+    /// Prefix of the sources the weight applies to, e.g. `https://docs.example.com/`
+    pub source: String,
+    /// Multiplier of the items' retrieval scores; 1.0 is neutral
+    pub weight: f64,
+}
+
+/// Request to generate a Grafana dashboard from a natural language ask
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardRequest {
+    /// What the dashboard should show, e.g. "checkout latency and error rate"
+    pub ask: String,
+    /// Save the dashboard to the configured Grafana
+    #[serde(default)]
+    pub push: bool,
+    /// Grafana folder to save the dashboard in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder_uid: Option<String>,
+}
+
+/// A generated Grafana dashboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedDashboard {
+    /// Grafana dashboard JSON model
+    pub dashboard: serde_json::Value,
+    /// Where Grafana saved the dashboard, if it was pushed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pushed: Option<DashboardPushResponse>,
+}
+
+/// Request to generate an alert rule from a natural language description
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRuleRequest {
+    /// When the alert should fire, e.g. "checkout latency above 500ms for 10 minutes"
+    pub description: String,
+    /// Replay the rule over recent data to estimate how often it fires
+    #[serde(default = "default_backtest")]
+    pub backtest: bool,
+    /// How far back to replay the rule, in hours (default 168, one week)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lookback_hours: Option<u32>,
+}
+
+fn default_backtest() -> bool {
+    true
+}
+
+/// A generated alert rule, to review before applying it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedAlertRule {
+    /// `PromQL` for a Prometheus rule, `LogQL` for a Loki rule
+    pub language: QueryLanguage,
+    /// The rule, in Prometheus rule-file format
+    pub rule: AlertRule,
+    /// How often the rule would have fired, if it was back-tested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backtest: Option<AlertBacktest>,
+    /// Why the back-test could not run, if it failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backtest_error: Option<String>,
+}
+
+/// Request to cluster and summarize log lines
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogAnalysisRequest {
+    /// Log text, one entry per line
+    pub logs: String,
+    /// How many of the most frequent patterns to report (default 10)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_patterns: Option<usize>,
+    /// Have the model summarize the patterns and anomalies
+    #[serde(default = "default_summarize")]
+    pub summarize: bool,
+}
+
+fn default_summarize() -> bool {
+    true
+}
+
+/// Log patterns, anomalies and their summary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogAnalysisReport {
+    #[serde(flatten)]
+    pub analysis: LogAnalysis,
+    /// Model summary, or a plain description if the model was not asked or failed
+    pub summary: String,
+}
+
 /// Workflow creation request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateWorkflowRequest {
@@ -206,6 +678,99 @@ pub struct Claims {
     pub additional: serde_json::Value,
 }
 
+/// Scope a token needs to use administrative endpoints
+pub const ADMIN_SCOPE: &str = "admin";
+
+/// Scope a token needs to change data shared by every tenant, such as the
+/// context corpus; a tenant's `admin` scope does not grant it
+pub const PLATFORM_SCOPE: &str = "platform";
+
+impl Claims {
+    /// Tenant the caller belongs to (`tenant_id` claim, falling back to the subject)
+    pub fn tenant_id(&self) -> &str {
+        self.additional
+            .get("tenant_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or(&self.sub)
+    }
+
+    /// Whether the token grants `scope` (space-delimited `scope` claim or a
+    /// `scopes` array)
+    pub fn has_scope(&self, scope: &str) -> bool {
+        let delimited = self
+            .additional
+            .get("scope")
+            .and_then(|v| v.as_str())
+            .is_some_and(|scopes| scopes.split_whitespace().any(|s| s == scope));
+        let listed = self
+            .additional
+            .get("scopes")
+            .and_then(|v| v.as_array())
+            .is_some_and(|scopes| scopes.iter().any(|s| s.as_str() == Some(scope)));
+        delimited || listed
+    }
+
+    /// Roles granted by the token (`roles` array)
+    pub fn roles(&self) -> Vec<String> {
+        self.string_list("roles")
+    }
+
+    /// Groups the caller belongs to (`groups` array)
+    pub fn groups(&self) -> Vec<String> {
+        self.string_list("groups")
+    }
+
+    fn string_list(&self, claim: &str) -> Vec<String> {
+        self.additional
+            .get(claim)
+            .and_then(|v| v.as_array())
+            .map(|values| values.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    }
+
+    /// The caller's authorization context
+    pub fn auth_context(&self) -> copilot_security::AuthContext {
+        copilot_security::AuthContext::from_role_strings(self.sub.clone(), &self.roles())
+            .with_tenant(self.tenant_id().to_string())
+            .with_groups(self.groups())
+    }
+
+    /// The admin acting as the subject, if this is an impersonation token
+    /// (`act` claim)
+    pub fn impersonator(&self) -> Option<crate::impersonation::Actor> {
+        self.additional
+            .get("act")
+            .and_then(|act| serde_json::from_value(act.clone()).ok())
+    }
+
+    /// Fail unless the token grants the `admin` scope
+    pub fn require_admin(&self) -> Result<(), crate::ApiError> {
+        self.require_scope(ADMIN_SCOPE)
+    }
+
+    /// Fail unless the token grants `scope`
+    pub fn require_scope(&self, scope: &str) -> Result<(), crate::ApiError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(crate::ApiError::AuthorizationFailed(format!("Requires the {} scope", scope)))
+        }
+    }
+}
+
+/// Agent task submission request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitTaskRequest {
+    /// Task kind (e.g. "code_generation", "analysis")
+    pub kind: String,
+    /// Priority lane
+    #[serde(default)]
+    pub priority: crate::tasks::TaskPriority,
+    /// Handler-specific input
+    #[serde(default)]
+    pub payload: serde_json::Value,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -226,6 +791,46 @@ mod tests {
         assert_eq!(response.error, Some("test error".to_string()));
     }
 
+    #[test]
+    fn test_claims_tenant_id() {
+        let claims: Claims = serde_json::from_value(serde_json::json!({
+            "sub": "user-1", "exp": 0, "iat": 0, "tenant_id": "acme"
+        }))
+        .unwrap();
+        assert_eq!(claims.tenant_id(), "acme");
+
+        let claims: Claims =
+            serde_json::from_value(serde_json::json!({ "sub": "user-1", "exp": 0, "iat": 0 })).unwrap();
+        assert_eq!(claims.tenant_id(), "user-1");
+
+        let claims: Claims = serde_json::from_value(serde_json::json!({
+            "sub": "user-1", "exp": 0, "iat": 0, "roles": ["admin", "auditor"], "groups": ["finance"]
+        }))
+        .unwrap();
+        let principal = claims.auth_context().principal();
+        assert_eq!(principal.roles, vec!["admin"]);
+        assert_eq!(principal.groups, vec!["finance"]);
+        assert_eq!(principal.tenant_id.as_deref(), Some("user-1"));
+    }
+
+    #[test]
+    fn test_claims_has_scope() {
+        let claims = |extra: serde_json::Value| -> Claims {
+            let mut value = serde_json::json!({ "sub": "user-1", "exp": 0, "iat": 0 });
+            value.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+            serde_json::from_value(value).unwrap()
+        };
+        assert!(claims(serde_json::json!({ "scope": "read admin" })).has_scope("admin"));
+        assert!(claims(serde_json::json!({ "scopes": ["admin"] })).has_scope("admin"));
+        assert!(!claims(serde_json::json!({ "scope": "administrator" })).has_scope("admin"));
+        assert!(!claims(serde_json::json!({})).has_scope("admin"));
+        assert!(claims(serde_json::json!({ "scope": "admin" })).require_admin().is_ok());
+        assert!(matches!(
+            claims(serde_json::json!({ "scope": "read" })).require_admin(),
+            Err(crate::ApiError::AuthorizationFailed(_))
+        ));
+    }
+
     #[test]
     fn test_message_role_serialization() {
         let role = MessageRole::User;
@@ -13,8 +13,13 @@ use futures::{
     stream::{SplitSink, SplitStream, StreamExt},
 };
 use serde::{Deserialize, Serialize};
+use copilot_conversation::manager::MessageRequest;
+use copilot_conversation::resume::parse_event_id;
+use copilot_conversation::streaming::ChunkType;
+use copilot_conversation::EventStream;
 use std::{sync::Arc, time::Duration};
 use tokio::{sync::mpsc, time::interval};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
@@ -41,6 +46,15 @@ pub enum WebSocketMessage {
         message_id: String,
         chunk: String,
         finished: bool,
+        /// Sent back in `resume_stream` after a reconnect to continue after
+        /// this chunk
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        resume_token: Option<String>,
+    },
+    /// Client continues a reply interrupted by a disconnect
+    ResumeStream {
+        session_id: String,
+        resume_token: String,
     },
     /// Client requests workflow execution
     ExecuteWorkflow {
@@ -89,6 +103,12 @@ impl WebSocketSession {
     }
 }
 
+/// Messages queued for a client before producers wait for it to catch up
+///
+/// Streamed replies are generated only as fast as the client reads them, so
+/// a slow client slows its own generation instead of growing a queue.
+const OUTBOUND_BUFFER: usize = 64;
+
 /// Handle WebSocket upgrade
 pub async fn handle_websocket(
     ws: WebSocketUpgrade,
@@ -106,37 +126,50 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     let (sender, receiver) = socket.split();
 
     // Create channels for message passing
-    let (tx, rx) = mpsc::unbounded_channel();
+    let (tx, rx) = mpsc::channel(OUTBOUND_BUFFER);
+
+    // Cancelled when the connection closes, stopping in-flight replies
+    let cancel = CancellationToken::new();
 
     // Spawn sender task
-    let send_task = tokio::spawn(handle_sender(sender, rx));
+    let mut send_task = tokio::spawn(handle_sender(sender, rx));
 
     // Spawn receiver task
-    let recv_task = tokio::spawn(handle_receiver(receiver, tx.clone(), state.clone()));
+    let mut recv_task = tokio::spawn(handle_receiver(
+        receiver,
+        tx.clone(),
+        state.clone(),
+        cancel.clone(),
+    ));
 
     // Spawn heartbeat task
-    let heartbeat_task = tokio::spawn(heartbeat(tx.clone()));
+    let mut heartbeat_task = tokio::spawn(heartbeat(tx.clone()));
 
     // Wait for any task to complete
     tokio::select! {
-        _ = send_task => {
+        _ = &mut send_task => {
             info!("WebSocket sender task completed");
         }
-        _ = recv_task => {
+        _ = &mut recv_task => {
             info!("WebSocket receiver task completed");
         }
-        _ = heartbeat_task => {
+        _ = &mut heartbeat_task => {
             info!("WebSocket heartbeat task completed");
         }
     }
 
+    cancel.cancel();
+    send_task.abort();
+    recv_task.abort();
+    heartbeat_task.abort();
+
     info!("WebSocket connection closed: {}", session.id);
 }
 
 /// Handle sending messages to the client
 async fn handle_sender(
     mut sender: SplitSink<WebSocket, Message>,
-    mut rx: mpsc::UnboundedReceiver<WebSocketMessage>,
+    mut rx: mpsc::Receiver<WebSocketMessage>,
 ) {
     while let Some(msg) = rx.recv().await {
         let json = match serde_json::to_string(&msg) {
@@ -157,8 +190,9 @@ async fn handle_sender(
 /// Handle receiving messages from the client
 async fn handle_receiver(
     mut receiver: SplitStream<WebSocket>,
-    tx: mpsc::UnboundedSender<WebSocketMessage>,
+    tx: mpsc::Sender<WebSocketMessage>,
     state: Arc<AppState>,
+    cancel: CancellationToken,
 ) {
     while let Some(msg) = receiver.next().await {
         let msg = match msg {
@@ -171,13 +205,13 @@ async fn handle_receiver(
 
         match msg {
             Message::Text(text) => {
-                if let Err(e) = handle_text_message(&text, &tx, &state).await {
+                if let Err(e) = handle_text_message(&text, &tx, &state, &cancel).await {
                     error!("Error handling message: {}", e);
                     let error_msg = WebSocketMessage::Error {
                         code: "PROCESSING_ERROR".to_string(),
                         message: e.to_string(),
                     };
-                    let _ = tx.send(error_msg);
+                    let _ = tx.send(error_msg).await;
                 }
             }
             Message::Binary(data) => {
@@ -201,8 +235,9 @@ async fn handle_receiver(
 /// Handle text messages
 async fn handle_text_message(
     text: &str,
-    tx: &mpsc::UnboundedSender<WebSocketMessage>,
+    tx: &mpsc::Sender<WebSocketMessage>,
     state: &Arc<AppState>,
+    cancel: &CancellationToken,
 ) -> Result<(), ApiError> {
     let msg: WebSocketMessage = serde_json::from_str(text)
         .map_err(|e| ApiError::InvalidInput(format!("Invalid JSON: {}", e)))?;
@@ -215,17 +250,35 @@ async fn handle_text_message(
             content,
             metadata,
         } => {
-            // TODO: Process message using CoPilot engine
-            // For now, echo back
-            let response = WebSocketMessage::MessageResponse {
-                message_id: Uuid::new_v4().to_string(),
-                session_id: session_id.clone(),
-                content: format!("Echo: {}", content),
-                role: "assistant".to_string(),
-                timestamp: chrono::Utc::now().to_rfc3339(),
+            let metadata = metadata
+                .and_then(|value| serde_json::from_value(value).ok())
+                .unwrap_or_default();
+            let request = MessageRequest {
+                session_id,
+                message: content,
+                metadata,
+                model: None,
             };
-            tx.send(response)
-                .map_err(|e| ApiError::WebSocketError(e.to_string()))?;
+
+            // Replies run in their own task so the connection keeps reading
+            // (and noticing a close) while a turn waits or streams
+            tokio::spawn(stream_reply(
+                Arc::clone(state),
+                request,
+                tx.clone(),
+                cancel.child_token(),
+            ));
+        }
+        WebSocketMessage::ResumeStream {
+            session_id,
+            resume_token,
+        } => {
+            let (stream_id, after) = parse_event_id(&resume_token)
+                .ok_or_else(|| ApiError::InvalidInput(format!("Invalid resume token: {}", resume_token)))?;
+            let events = state
+                .conversation_manager
+                .resume_stream(&session_id, stream_id, Some(after))?;
+            tokio::spawn(send_reply(session_id, events, tx.clone(), cancel.child_token()));
         }
         WebSocketMessage::ExecuteWorkflow { workflow_id, input } => {
             // TODO: Execute workflow
@@ -235,11 +288,13 @@ async fn handle_text_message(
                 progress: Some(0.0),
             };
             tx.send(response)
+                .await
                 .map_err(|e| ApiError::WebSocketError(e.to_string()))?;
         }
         WebSocketMessage::Ping { timestamp } => {
             let response = WebSocketMessage::Pong { timestamp };
             tx.send(response)
+                .await
                 .map_err(|e| ApiError::WebSocketError(e.to_string()))?;
         }
         _ => {
@@ -250,8 +305,97 @@ async fn handle_text_message(
     Ok(())
 }
 
+/// Answer a message, waiting for the session's earlier turns, and send the
+/// reply to the client chunk by chunk, then in full
+///
+/// Returning early detaches the client from the reply's stream; unless it
+/// resumes the stream within the resume grace period, generation is then
+/// cancelled and the partial reply recorded. That happens when the
+/// connection closes or the client stops reading.
+async fn stream_reply(
+    state: Arc<AppState>,
+    request: MessageRequest,
+    tx: mpsc::Sender<WebSocketMessage>,
+    cancel: CancellationToken,
+) {
+    let session_id = request.session_id.clone();
+    let started = tokio::select! {
+        _ = cancel.cancelled() => return,
+        started = state.conversation_manager.start_resumable_stream(request) => started,
+    };
+    match started {
+        Ok((_, events)) => send_reply(session_id, events, tx, cancel).await,
+        Err(e) => {
+            let _ = tx
+                .send(WebSocketMessage::Error {
+                    code: "PROCESSING_ERROR".to_string(),
+                    message: e.to_string(),
+                })
+                .await;
+        }
+    }
+}
+
+/// Send a reply stream's chunks to the client, then the reply in full; a
+/// resumed reply's full message holds only the chunks sent after resuming
+async fn send_reply(
+    session_id: String,
+    mut events: EventStream,
+    tx: mpsc::Sender<WebSocketMessage>,
+    cancel: CancellationToken,
+) {
+    let mut message_id = None;
+    let mut content = String::new();
+
+    loop {
+        let event = tokio::select! {
+            _ = cancel.cancelled() => {
+                debug!("Connection closed, detaching from reply {:?}", message_id);
+                return;
+            }
+            event = events.next() => event,
+        };
+        let Some(event) = event else { break };
+        let resume_token = event.id();
+        let message_id = message_id.get_or_insert_with(|| event.stream_id.clone()).clone();
+
+        let message = match event.chunk.chunk_type {
+            ChunkType::Error => WebSocketMessage::Error {
+                code: "STREAM_ERROR".to_string(),
+                message: event.chunk.content,
+            },
+            _ => {
+                content.push_str(&event.chunk.content);
+                WebSocketMessage::StreamChunk {
+                    message_id,
+                    chunk: event.chunk.content,
+                    finished: event.chunk.is_final,
+                    resume_token: Some(resume_token),
+                }
+            }
+        };
+
+        let sent = tokio::select! {
+            _ = cancel.cancelled() => return,
+            sent = tx.send(message) => sent,
+        };
+        if sent.is_err() {
+            return;
+        }
+    }
+
+    let response = WebSocketMessage::MessageResponse {
+        message_id: message_id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+        session_id,
+        content,
+        role: "assistant".to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+    let _ = tx.send(response).await;
+}
+
 /// Heartbeat task to keep connection alive
-async fn heartbeat(tx: mpsc::UnboundedSender<WebSocketMessage>) {
+async fn heartbeat(tx: mpsc::Sender<WebSocketMessage>) {
     let mut interval = interval(Duration::from_secs(30));
 
     loop {
@@ -260,7 +404,7 @@ async fn heartbeat(tx: mpsc::UnboundedSender<WebSocketMessage>) {
         let timestamp = chrono::Utc::now().timestamp() as u64;
         let ping = WebSocketMessage::Ping { timestamp };
 
-        if tx.send(ping).is_err() {
+        if tx.send(ping).await.is_err() {
             debug!("Failed to send heartbeat, connection likely closed");
             break;
         }
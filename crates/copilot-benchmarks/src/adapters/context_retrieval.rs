@@ -3,7 +3,8 @@
 //! Exposes context retrieval operations as benchmark targets.
 
 use async_trait::async_trait;
-use std::time::Instant;
+use crate::corpus::{CorpusConfig, CorpusGenerator, CorpusKind};
+use crate::determinism::stopwatch;
 use crate::result::BenchmarkResult;
 use crate::traits::BenchTarget;
 
@@ -43,13 +44,13 @@ impl BenchTarget for SimpleRetrievalBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         let query = "Show me the authentication service configuration";
         let mut retrieval_results = Vec::new();
 
         for &k in &self.k_values {
-            let retrieval_start = Instant::now();
+            let retrieval_start = stopwatch();
 
             // Simulate context retrieval
             let contexts = simulate_context_retrieval(query, k).await;
@@ -119,25 +120,22 @@ impl BenchTarget for LargeCorpusRetrievalBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
-
-        // Simulate building a large corpus
-        let corpus_build_start = Instant::now();
-        let _corpus = simulate_corpus_build(self.corpus_size);
+        let start = stopwatch();
+
+        // Build a synthetic corpus and draw queries from it
+        let corpus_build_start = stopwatch();
+        let generator = CorpusGenerator::new(
+            CorpusConfig::new(CorpusKind::Prose, self.corpus_size).with_queries(5),
+        );
+        let _corpus: Vec<_> = generator.documents().collect();
         let corpus_build_time = corpus_build_start.elapsed();
 
         // Run retrieval queries
-        let queries = vec![
-            "authentication configuration",
-            "database connection settings",
-            "API rate limiting rules",
-            "error handling patterns",
-            "logging configuration",
-        ];
+        let queries: Vec<String> = generator.queries().map(|(query, _)| query.text).collect();
 
         let mut query_times = Vec::new();
         for query in &queries {
-            let query_start = Instant::now();
+            let query_start = stopwatch();
             let _ = simulate_context_retrieval(query, 10).await;
             query_times.push(query_start.elapsed().as_micros());
         }
@@ -174,12 +172,6 @@ async fn simulate_context_retrieval(query: &str, k: usize) -> Vec<String> {
         .collect()
 }
 
-fn simulate_corpus_build(size: usize) -> Vec<String> {
-    (0..size)
-        .map(|i| format!("Document {} with content about various topics including configuration, settings, and patterns.", i))
-        .collect()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -3,7 +3,7 @@
 //! Exposes conversation management operations as benchmark targets.
 
 use async_trait::async_trait;
-use std::time::Instant;
+use crate::determinism::stopwatch;
 use crate::result::BenchmarkResult;
 use crate::traits::BenchTarget;
 
@@ -43,7 +43,7 @@ impl BenchTarget for SimpleResponseBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         let prompts = vec![
             "What is the current CPU usage?",
@@ -57,7 +57,7 @@ impl BenchTarget for SimpleResponseBenchmark {
 
         for _ in 0..self.iterations {
             for prompt in &prompts {
-                let response_start = Instant::now();
+                let response_start = stopwatch();
                 let (response, tokens) = simulate_response_generation(prompt, &[]).await;
                 response_times.push(response_start.elapsed().as_micros());
                 total_tokens += tokens;
@@ -121,7 +121,7 @@ impl BenchTarget for MultiTurnBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         let turn_sequence = vec![
             "Show me the service health",
@@ -135,7 +135,7 @@ impl BenchTarget for MultiTurnBenchmark {
         let mut total_context_growth = 0;
 
         for _ in 0..self.conversations {
-            let conv_start = Instant::now();
+            let conv_start = stopwatch();
             let mut context = Vec::new();
 
             for (turn_idx, prompt) in turn_sequence.iter().take(self.turns).enumerate() {
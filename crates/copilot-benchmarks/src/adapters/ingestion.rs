@@ -3,7 +3,7 @@
 //! Exposes document ingestion operations as benchmark targets.
 
 use async_trait::async_trait;
-use std::time::Instant;
+use crate::determinism::stopwatch;
 use crate::result::BenchmarkResult;
 use crate::traits::BenchTarget;
 
@@ -45,14 +45,14 @@ impl BenchTarget for DocumentIngestionBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         let mut ingestion_times = Vec::new();
         let mut total_bytes_processed = 0;
         let mut total_chunks_created = 0;
 
         for doc_idx in 0..self.document_count {
-            let doc_start = Instant::now();
+            let doc_start = stopwatch();
 
             // Generate mock document content
             let content = generate_mock_document(self.avg_document_size);
@@ -119,14 +119,14 @@ impl BenchTarget for ChunkProcessingBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         // Generate a large document to chunk
         let document = generate_mock_document(50000);
         let mut chunk_results = Vec::new();
 
         for &chunk_size in &self.chunk_sizes {
-            let chunk_start = Instant::now();
+            let chunk_start = stopwatch();
 
             let chunks = simulate_text_chunking(&document, chunk_size);
             let chunk_duration = chunk_start.elapsed();
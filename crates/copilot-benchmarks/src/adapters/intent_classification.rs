@@ -3,7 +3,7 @@
 //! Exposes NLP intent classification operations as benchmark targets.
 
 use async_trait::async_trait;
-use std::time::Instant;
+use crate::determinism::stopwatch;
 use crate::result::BenchmarkResult;
 use crate::traits::BenchTarget;
 
@@ -48,7 +48,7 @@ impl BenchTarget for SimpleIntentBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         // Simulate intent classification for simple queries
         let queries = vec![
@@ -121,7 +121,7 @@ impl BenchTarget for ComplexIntentBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         let complex_queries = vec![
             "Compare the CPU and memory usage between auth-service and api-gateway in us-east-1 and eu-west-1 for the last 24 hours and show me any anomalies",
@@ -185,13 +185,13 @@ impl BenchTarget for BatchIntentBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         let query = "Show me metrics";
         let mut batch_results = Vec::new();
 
         for &batch_size in &self.batch_sizes {
-            let batch_start = Instant::now();
+            let batch_start = stopwatch();
             for _ in 0..batch_size {
                 let _ = simulate_intent_classification(query);
             }
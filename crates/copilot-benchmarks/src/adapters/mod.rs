@@ -4,6 +4,7 @@
 //! as benchmark targets through the canonical BenchTarget trait.
 
 pub mod intent_classification;
+pub mod nlp_accuracy;
 pub mod context_retrieval;
 pub mod conversation;
 pub mod workflow;
@@ -25,6 +26,9 @@ pub fn all_targets() -> Vec<BoxedBenchTarget> {
     targets.push(Box::new(intent_classification::ComplexIntentBenchmark::new()));
     targets.push(Box::new(intent_classification::BatchIntentBenchmark::new()));
 
+    // NLP accuracy benchmarks
+    targets.push(Box::new(nlp_accuracy::NlpAccuracyBenchmark::new()));
+
     // Context Retrieval benchmarks
     targets.push(Box::new(context_retrieval::SimpleRetrievalBenchmark::new()));
     targets.push(Box::new(context_retrieval::LargeCorpusRetrievalBenchmark::new()));
@@ -40,6 +44,8 @@ pub fn all_targets() -> Vec<BoxedBenchTarget> {
     // Sandbox Execution benchmarks
     targets.push(Box::new(sandbox_execution::PythonExecutionBenchmark::new()));
     targets.push(Box::new(sandbox_execution::NodeExecutionBenchmark::new()));
+    targets.push(Box::new(sandbox_execution::SandboxStartupBenchmark::new()));
+    targets.push(Box::new(sandbox_execution::SandboxConcurrencyBenchmark::new()));
 
     // Ingestion benchmarks
     targets.push(Box::new(ingestion::DocumentIngestionBenchmark::new()));
@@ -0,0 +1,291 @@
+//! NLP Accuracy Benchmark Adapters
+//!
+//! Scores the intent classifier and entity extractor against the labeled
+//! queries bundled in `data/nlp_labeled.json`, reporting precision, recall
+//! and F1 per intent and per entity type.
+//!
+//! An entity counts as found when the extractor returns an entity of the
+//! labeled type whose normalized value matches the label, ignoring case.
+
+use async_trait::async_trait;
+use copilot_nlp::{EntityExtractor, EntityType, IntentClassifier, IntentType};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use crate::determinism::stopwatch;
+use crate::result::BenchmarkResult;
+use crate::traits::BenchTarget;
+
+/// Labeled queries the benchmark scores against
+const DATASET: &str = include_str!("../../data/nlp_labeled.json");
+
+#[derive(Debug, Deserialize)]
+struct LabeledQuery {
+    query: String,
+    intent: IntentType,
+    #[serde(default)]
+    entities: Vec<LabeledEntity>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LabeledEntity {
+    #[serde(rename = "type")]
+    entity_type: EntityType,
+    value: String,
+}
+
+/// Hit counts for a single label
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LabelCounts {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+}
+
+impl LabelCounts {
+    pub fn precision(&self) -> f64 {
+        ratio(self.true_positives, self.true_positives + self.false_positives)
+    }
+
+    pub fn recall(&self) -> f64 {
+        ratio(self.true_positives, self.true_positives + self.false_negatives)
+    }
+
+    pub fn f1(&self) -> f64 {
+        let (precision, recall) = (self.precision(), self.recall());
+        if precision + recall == 0.0 {
+            0.0
+        } else {
+            2.0 * precision * recall / (precision + recall)
+        }
+    }
+
+    /// Number of labeled occurrences
+    pub fn support(&self) -> usize {
+        self.true_positives + self.false_negatives
+    }
+
+    fn to_json(self) -> serde_json::Value {
+        serde_json::json!({
+            "precision": round(self.precision()),
+            "recall": round(self.recall()),
+            "f1": round(self.f1()),
+            "support": self.support()
+        })
+    }
+}
+
+/// Precision, recall and F1 per label over a set of predictions
+#[derive(Debug, Clone, Default)]
+pub struct Scoreboard {
+    labels: BTreeMap<String, LabelCounts>,
+}
+
+impl Scoreboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Score one query's predictions against its labels. Items are
+    /// `(label, value)` pairs and match only when both are equal.
+    pub fn record(&mut self, expected: &BTreeSet<(String, String)>, predicted: &BTreeSet<(String, String)>) {
+        for item in expected {
+            let counts = self.labels.entry(item.0.clone()).or_default();
+            if predicted.contains(item) {
+                counts.true_positives += 1;
+            } else {
+                counts.false_negatives += 1;
+            }
+        }
+        for item in predicted.difference(expected) {
+            self.labels.entry(item.0.clone()).or_default().false_positives += 1;
+        }
+    }
+
+    pub fn label(&self, label: &str) -> Option<LabelCounts> {
+        self.labels.get(label).copied()
+    }
+
+    /// Counts summed over every label
+    pub fn micro(&self) -> LabelCounts {
+        self.labels.values().fold(LabelCounts::default(), |total, counts| LabelCounts {
+            true_positives: total.true_positives + counts.true_positives,
+            false_positives: total.false_positives + counts.false_positives,
+            false_negatives: total.false_negatives + counts.false_negatives,
+        })
+    }
+
+    /// Unweighted mean of the per-label F1 scores
+    pub fn macro_f1(&self) -> f64 {
+        if self.labels.is_empty() {
+            return 0.0;
+        }
+        self.labels.values().map(LabelCounts::f1).sum::<f64>() / self.labels.len() as f64
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let per_label: serde_json::Map<String, serde_json::Value> = self
+            .labels
+            .iter()
+            .map(|(label, counts)| (label.clone(), counts.to_json()))
+            .collect();
+        serde_json::json!({
+            "micro": self.micro().to_json(),
+            "macro_f1": round(self.macro_f1()),
+            "per_label": per_label
+        })
+    }
+}
+
+/// Benchmark for intent and entity accuracy on the bundled labeled queries
+pub struct NlpAccuracyBenchmark {
+    id: String,
+}
+
+impl NlpAccuracyBenchmark {
+    pub fn new() -> Self {
+        Self {
+            id: "nlp::accuracy::labeled".to_string(),
+        }
+    }
+}
+
+impl Default for NlpAccuracyBenchmark {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl BenchTarget for NlpAccuracyBenchmark {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Scores intent classification and entity extraction against a labeled query set")
+    }
+
+    fn expected_duration_ms(&self) -> Option<(u64, u64)> {
+        Some((1, 500))
+    }
+
+    async fn run(&self) -> BenchmarkResult {
+        let dataset: Vec<LabeledQuery> = match serde_json::from_str(DATASET) {
+            Ok(dataset) => dataset,
+            Err(e) => return BenchmarkResult::failure(&self.id, format!("Invalid labeled dataset: {}", e)),
+        };
+
+        let start = stopwatch();
+        let classifier = IntentClassifier::new();
+        let extractor = EntityExtractor::new();
+        let mut intents = Scoreboard::new();
+        let mut entities = Scoreboard::new();
+
+        for labeled in &dataset {
+            let predicted = classifier.classify(&labeled.query).intent_type;
+            intents.record(
+                &BTreeSet::from([(label(&labeled.intent), String::new())]),
+                &BTreeSet::from([(label(&predicted), String::new())]),
+            );
+
+            let expected = labeled
+                .entities
+                .iter()
+                .map(|e| (label(&e.entity_type), normalize(&e.value)))
+                .collect();
+            let predicted = extractor
+                .extract(&labeled.query)
+                .iter()
+                .map(|e| (label(&e.entity_type), normalize(&e.normalized_value)))
+                .collect();
+            entities.record(&expected, &predicted);
+        }
+
+        let duration = start.elapsed();
+        let intent_accuracy = ratio(intents.micro().true_positives, dataset.len());
+
+        let mut intent_metrics = intents.to_json();
+        intent_metrics["accuracy"] = serde_json::json!(round(intent_accuracy));
+
+        BenchmarkResult::new(
+            &self.id,
+            serde_json::json!({
+                "success": true,
+                "duration_ms": duration.as_millis() as u64,
+                "queries": dataset.len(),
+                "intent": intent_metrics,
+                "entity": entities.to_json()
+            }),
+        )
+    }
+}
+
+fn label(value: &impl std::fmt::Debug) -> String {
+    format!("{:?}", value)
+}
+
+fn normalize(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10_000.0).round() / 10_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(items: &[(&str, &str)]) -> BTreeSet<(String, String)> {
+        items.iter().map(|(l, v)| (l.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_scoreboard_counts() {
+        let mut board = Scoreboard::new();
+        board.record(
+            &items(&[("Metric", "cpu"), ("Service", "auth-service")]),
+            &items(&[("Metric", "cpu"), ("Metric", "memory")]),
+        );
+        board.record(&items(&[("Metric", "latency")]), &items(&[("Metric", "latency")]));
+
+        let metric = board.label("Metric").unwrap();
+        assert_eq!(metric.true_positives, 2);
+        assert_eq!(metric.false_positives, 1);
+        assert_eq!(metric.support(), 2);
+        assert!((metric.precision() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(metric.recall(), 1.0);
+
+        let service = board.label("Service").unwrap();
+        assert_eq!(service.f1(), 0.0);
+        assert_eq!(board.micro().support(), 3);
+    }
+
+    #[test]
+    fn test_dataset_parses() {
+        let dataset: Vec<LabeledQuery> = serde_json::from_str(DATASET).unwrap();
+        assert!(!dataset.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_nlp_accuracy_benchmark() {
+        let benchmark = NlpAccuracyBenchmark::new();
+        assert_eq!(benchmark.id(), "nlp::accuracy::labeled");
+
+        let result = benchmark.run().await;
+        assert!(result.is_success());
+        assert!(result.metrics["intent"]["per_label"]["QueryMetrics"]["f1"].is_number());
+        assert!(result.metrics["entity"]["per_label"]["Service"]["recall"].is_number());
+
+        let accuracy = result.metrics["intent"]["accuracy"].as_f64().unwrap();
+        assert!((0.0..=1.0).contains(&accuracy));
+    }
+}
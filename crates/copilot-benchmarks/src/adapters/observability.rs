@@ -3,7 +3,7 @@
 //! Exposes telemetry and observability operations as benchmark targets.
 
 use async_trait::async_trait;
-use std::time::Instant;
+use crate::determinism::stopwatch;
 use crate::result::BenchmarkResult;
 use crate::traits::BenchTarget;
 
@@ -45,13 +45,13 @@ impl BenchTarget for MetricsCollectionBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         let mut total_metrics_recorded = 0;
         let mut collection_times = Vec::new();
 
         for _ in 0..self.iterations {
-            let iter_start = Instant::now();
+            let iter_start = stopwatch();
 
             // Simulate recording various metric types
             for i in 0..self.metric_count {
@@ -119,13 +119,13 @@ impl BenchTarget for TracingBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         let mut total_spans_created = 0;
         let mut trace_times = Vec::new();
 
         for trace_idx in 0..self.traces_count {
-            let trace_start = Instant::now();
+            let trace_start = stopwatch();
 
             // Simulate creating a trace with nested spans
             let spans = simulate_trace_creation(trace_idx, self.span_depth).await;
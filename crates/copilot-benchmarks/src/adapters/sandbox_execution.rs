@@ -1,16 +1,28 @@
 //! Sandbox Execution Benchmark Adapters
 //!
-//! Exposes E2B sandbox execution operations as benchmark targets.
+//! Exposes E2B sandbox execution operations as benchmark targets. Besides
+//! per-runtime execution time, they measure cold-start against warm-pool
+//! startup latency and throughput under concurrency with a bounded warm
+//! pool, and estimate the dollar cost of each run from the sandbox seconds
+//! it used, so pool sizing can be decided from the numbers.
 
 use async_trait::async_trait;
-use std::time::Instant;
+use crate::determinism::{self, stopwatch};
 use crate::result::BenchmarkResult;
 use crate::traits::BenchTarget;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+/// Price of a second of sandbox time in US dollars, as metered for tenants
+/// (compute seconds)
+pub const DEFAULT_COST_PER_SANDBOX_SECOND_USD: f64 = 0.0001;
 
 /// Benchmark for Python code execution in sandbox
 pub struct PythonExecutionBenchmark {
     id: String,
     iterations: usize,
+    cost_per_sandbox_second: f64,
 }
 
 impl PythonExecutionBenchmark {
@@ -18,8 +30,15 @@ impl PythonExecutionBenchmark {
         Self {
             id: "sandbox::python::execution".to_string(),
             iterations: 10,
+            cost_per_sandbox_second: DEFAULT_COST_PER_SANDBOX_SECOND_USD,
         }
     }
+
+    /// Price cost estimates at `usd` per sandbox second
+    pub fn with_cost_per_sandbox_second(mut self, usd: f64) -> Self {
+        self.cost_per_sandbox_second = usd;
+        self
+    }
 }
 
 impl Default for PythonExecutionBenchmark {
@@ -43,7 +62,7 @@ impl BenchTarget for PythonExecutionBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         let python_snippets = vec![
             "print('Hello, World!')",
@@ -57,9 +76,9 @@ impl BenchTarget for PythonExecutionBenchmark {
 
         for _ in 0..self.iterations {
             for code in &python_snippets {
-                let exec_start = Instant::now();
+                let exec_start = stopwatch();
                 let (stdout, _stderr, exit_code) = simulate_sandbox_execution(code, "python").await;
-                execution_times.push(exec_start.elapsed().as_millis());
+                execution_times.push(exec_start.elapsed());
 
                 total_stdout_bytes += stdout.len();
                 std::hint::black_box(exit_code);
@@ -69,17 +88,17 @@ impl BenchTarget for PythonExecutionBenchmark {
         let total_duration = start.elapsed();
         let total_executions = self.iterations * python_snippets.len();
 
-        BenchmarkResult::new(
-            &self.id,
-            serde_json::json!({
-                "success": true,
-                "duration_ms": total_duration.as_millis() as u64,
-                "total_executions": total_executions,
-                "avg_execution_ms": execution_times.iter().sum::<u128>() as f64 / execution_times.len() as f64,
-                "total_stdout_bytes": total_stdout_bytes,
-                "runtime": "python"
-            }),
-        )
+        let mut metrics = serde_json::json!({
+            "success": true,
+            "duration_ms": total_duration.as_millis() as u64,
+            "total_executions": total_executions,
+            "avg_execution_ms": LatencyStats::of(&execution_times).avg_ms,
+            "total_stdout_bytes": total_stdout_bytes,
+            "runtime": "python"
+        });
+        add_cost_metrics(&mut metrics, &execution_times, self.cost_per_sandbox_second);
+
+        BenchmarkResult::new(&self.id, metrics)
     }
 }
 
@@ -87,6 +106,7 @@ impl BenchTarget for PythonExecutionBenchmark {
 pub struct NodeExecutionBenchmark {
     id: String,
     iterations: usize,
+    cost_per_sandbox_second: f64,
 }
 
 impl NodeExecutionBenchmark {
@@ -94,8 +114,15 @@ impl NodeExecutionBenchmark {
         Self {
             id: "sandbox::nodejs::execution".to_string(),
             iterations: 10,
+            cost_per_sandbox_second: DEFAULT_COST_PER_SANDBOX_SECOND_USD,
         }
     }
+
+    /// Price cost estimates at `usd` per sandbox second
+    pub fn with_cost_per_sandbox_second(mut self, usd: f64) -> Self {
+        self.cost_per_sandbox_second = usd;
+        self
+    }
 }
 
 impl Default for NodeExecutionBenchmark {
@@ -119,7 +146,7 @@ impl BenchTarget for NodeExecutionBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         let node_snippets = vec![
             "console.log('Hello, World!')",
@@ -133,9 +160,9 @@ impl BenchTarget for NodeExecutionBenchmark {
 
         for _ in 0..self.iterations {
             for code in &node_snippets {
-                let exec_start = Instant::now();
+                let exec_start = stopwatch();
                 let (stdout, _stderr, exit_code) = simulate_sandbox_execution(code, "nodejs").await;
-                execution_times.push(exec_start.elapsed().as_millis());
+                execution_times.push(exec_start.elapsed());
 
                 total_stdout_bytes += stdout.len();
                 std::hint::black_box(exit_code);
@@ -145,21 +172,358 @@ impl BenchTarget for NodeExecutionBenchmark {
         let total_duration = start.elapsed();
         let total_executions = self.iterations * node_snippets.len();
 
-        BenchmarkResult::new(
-            &self.id,
-            serde_json::json!({
-                "success": true,
-                "duration_ms": total_duration.as_millis() as u64,
-                "total_executions": total_executions,
-                "avg_execution_ms": execution_times.iter().sum::<u128>() as f64 / execution_times.len() as f64,
-                "total_stdout_bytes": total_stdout_bytes,
-                "runtime": "nodejs"
-            }),
-        )
+        let mut metrics = serde_json::json!({
+            "success": true,
+            "duration_ms": total_duration.as_millis() as u64,
+            "total_executions": total_executions,
+            "avg_execution_ms": LatencyStats::of(&execution_times).avg_ms,
+            "total_stdout_bytes": total_stdout_bytes,
+            "runtime": "nodejs"
+        });
+        add_cost_metrics(&mut metrics, &execution_times, self.cost_per_sandbox_second);
+
+        BenchmarkResult::new(&self.id, metrics)
+    }
+}
+
+/// Benchmark of sandbox startup: booting a new sandbox for each run against
+/// taking one from a pool of warm sandboxes
+pub struct SandboxStartupBenchmark {
+    id: String,
+    runs: usize,
+    cost_per_sandbox_second: f64,
+}
+
+impl SandboxStartupBenchmark {
+    pub fn new() -> Self {
+        Self {
+            id: "sandbox::startup".to_string(),
+            runs: 5,
+            cost_per_sandbox_second: DEFAULT_COST_PER_SANDBOX_SECOND_USD,
+        }
+    }
+
+    /// Start this many sandboxes of each kind
+    pub fn with_runs(mut self, runs: usize) -> Self {
+        self.runs = runs.max(1);
+        self
+    }
+
+    /// Price cost estimates at `usd` per sandbox second
+    pub fn with_cost_per_sandbox_second(mut self, usd: f64) -> Self {
+        self.cost_per_sandbox_second = usd;
+        self
     }
 }
 
-// Simulation function
+impl Default for SandboxStartupBenchmark {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl BenchTarget for SandboxStartupBenchmark {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Compares cold-start and warm-pool sandbox startup latency and cost")
+    }
+
+    fn expected_duration_ms(&self) -> Option<(u64, u64)> {
+        Some((50, 2000))
+    }
+
+    fn exclusive(&self) -> bool {
+        true
+    }
+
+    async fn run(&self) -> BenchmarkResult {
+        let start = stopwatch();
+        let code = "print('ready')";
+
+        let mut cold = Vec::with_capacity(self.runs);
+        let mut cold_runs = Vec::with_capacity(self.runs);
+        for _ in 0..self.runs {
+            let run = stopwatch();
+            let boot = stopwatch();
+            simulate_cold_start().await;
+            cold.push(boot.elapsed());
+            simulate_sandbox_execution(code, "python").await;
+            cold_runs.push(run.elapsed());
+        }
+
+        let pool = SimulatedPool::new(self.runs);
+        let mut warm = Vec::with_capacity(self.runs);
+        let mut warm_runs = Vec::with_capacity(self.runs);
+        for _ in 0..self.runs {
+            let run = stopwatch();
+            let acquire = stopwatch();
+            pool.acquire().await;
+            warm.push(acquire.elapsed());
+            simulate_sandbox_execution(code, "python").await;
+            pool.release();
+            warm_runs.push(run.elapsed());
+        }
+
+        let cold = LatencyStats::of(&cold);
+        let warm = LatencyStats::of(&warm);
+        let mut metrics = serde_json::json!({
+            "success": true,
+            "duration_ms": start.elapsed().as_millis() as u64,
+            "runs": self.runs,
+            "cold_start": cold.to_json(),
+            "warm_pool": warm.to_json(),
+            "warm_speedup": (warm.avg_ms > 0.0).then(|| cold.avg_ms / warm.avg_ms),
+            "cold_cost_per_run_usd": cost_per_run(&cold_runs, self.cost_per_sandbox_second),
+            "warm_cost_per_run_usd": cost_per_run(&warm_runs, self.cost_per_sandbox_second),
+        });
+        let all_runs: Vec<Duration> = cold_runs.into_iter().chain(warm_runs).collect();
+        add_cost_metrics(&mut metrics, &all_runs, self.cost_per_sandbox_second);
+
+        BenchmarkResult::new(&self.id, metrics)
+    }
+}
+
+/// Benchmark of execution throughput at increasing concurrency, drawing
+/// sandboxes from a warm pool of fixed size and cold-starting the rest
+pub struct SandboxConcurrencyBenchmark {
+    id: String,
+    pool_size: usize,
+    concurrency_levels: Vec<usize>,
+    runs_per_level: usize,
+    cost_per_sandbox_second: f64,
+}
+
+impl SandboxConcurrencyBenchmark {
+    pub fn new() -> Self {
+        Self {
+            id: "sandbox::concurrency".to_string(),
+            pool_size: 4,
+            concurrency_levels: vec![1, 4, 16],
+            runs_per_level: 32,
+            cost_per_sandbox_second: DEFAULT_COST_PER_SANDBOX_SECOND_USD,
+        }
+    }
+
+    /// Keep this many warm sandboxes
+    pub fn with_pool_size(mut self, pool_size: usize) -> Self {
+        self.pool_size = pool_size;
+        self
+    }
+
+    /// Measure at each of these numbers of concurrent executions
+    pub fn with_concurrency_levels(mut self, levels: Vec<usize>) -> Self {
+        self.concurrency_levels = levels.into_iter().filter(|&level| level > 0).collect();
+        self
+    }
+
+    /// Run this many executions at each concurrency level
+    pub fn with_runs_per_level(mut self, runs: usize) -> Self {
+        self.runs_per_level = runs.max(1);
+        self
+    }
+
+    /// Price cost estimates at `usd` per sandbox second
+    pub fn with_cost_per_sandbox_second(mut self, usd: f64) -> Self {
+        self.cost_per_sandbox_second = usd;
+        self
+    }
+
+    /// Run the executions of one level, returning each run's latency and
+    /// how many runs cold-started a sandbox
+    async fn run_level(&self, concurrency: usize) -> (Vec<Duration>, usize) {
+        let pool = Arc::new(SimulatedPool::new(self.pool_size));
+        let permits = Arc::new(tokio::sync::Semaphore::new(concurrency));
+        let determinism = determinism::current();
+        let mut runs = JoinSet::new();
+
+        for i in 0..self.runs_per_level {
+            let pool = pool.clone();
+            let permits = permits.clone();
+            let run = async move {
+                let _permit = permits.acquire_owned().await.expect("semaphore closed");
+                let started = stopwatch();
+                let was_warm = pool.acquire().await;
+                simulate_sandbox_execution(&format!("print({})", i), "python").await;
+                tokio::time::sleep(SIMULATED_EXECUTION).await;
+                pool.release();
+                (started.elapsed(), !was_warm)
+            };
+            runs.spawn(determinism::scope(determinism.clone(), run));
+        }
+
+        let mut latencies = Vec::with_capacity(self.runs_per_level);
+        let mut cold_starts = 0;
+        while let Some(run) = runs.join_next().await {
+            let (latency, cold) = run.expect("benchmark run panicked");
+            latencies.push(latency);
+            cold_starts += cold as usize;
+        }
+        (latencies, cold_starts)
+    }
+}
+
+impl Default for SandboxConcurrencyBenchmark {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl BenchTarget for SandboxConcurrencyBenchmark {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Measures sandbox execution throughput and cost under concurrency with a warm pool")
+    }
+
+    fn expected_duration_ms(&self) -> Option<(u64, u64)> {
+        Some((100, 5000))
+    }
+
+    fn exclusive(&self) -> bool {
+        true
+    }
+
+    async fn run(&self) -> BenchmarkResult {
+        let start = stopwatch();
+        let mut levels = Vec::with_capacity(self.concurrency_levels.len());
+        let mut all_runs = Vec::new();
+
+        for &concurrency in &self.concurrency_levels {
+            let level_start = stopwatch();
+            let (latencies, cold_starts) = self.run_level(concurrency).await;
+            let elapsed = level_start.elapsed().as_secs_f64();
+            let latency = LatencyStats::of(&latencies);
+            levels.push(serde_json::json!({
+                "concurrency": concurrency,
+                "runs": latencies.len(),
+                "throughput_per_sec": if elapsed > 0.0 { latencies.len() as f64 / elapsed } else { 0.0 },
+                "avg_latency_ms": latency.avg_ms,
+                "p95_latency_ms": latency.p95_ms,
+                "cold_starts": cold_starts,
+                "cost_per_run_usd": cost_per_run(&latencies, self.cost_per_sandbox_second),
+            }));
+            all_runs.extend(latencies);
+        }
+
+        let mut metrics = serde_json::json!({
+            "success": true,
+            "duration_ms": start.elapsed().as_millis() as u64,
+            "pool_size": self.pool_size,
+            "levels": levels,
+        });
+        add_cost_metrics(&mut metrics, &all_runs, self.cost_per_sandbox_second);
+
+        BenchmarkResult::new(&self.id, metrics)
+    }
+}
+
+/// Average and percentiles of a set of latencies, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct LatencyStats {
+    avg_ms: f64,
+    p50_ms: f64,
+    p95_ms: f64,
+}
+
+impl LatencyStats {
+    fn of(latencies: &[Duration]) -> Self {
+        if latencies.is_empty() {
+            return Self::default();
+        }
+        let mut ms: Vec<f64> = latencies.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        ms.sort_by(|a, b| a.total_cmp(b));
+        let percentile = |p: f64| ms[((ms.len() - 1) as f64 * p).round() as usize];
+        Self {
+            avg_ms: ms.iter().sum::<f64>() / ms.len() as f64,
+            p50_ms: percentile(0.5),
+            p95_ms: percentile(0.95),
+        }
+    }
+
+    fn to_json(self) -> serde_json::Value {
+        serde_json::json!({ "avg_ms": self.avg_ms, "p50_ms": self.p50_ms, "p95_ms": self.p95_ms })
+    }
+}
+
+/// Estimated cost of a run, from the average sandbox time of `runs`
+fn cost_per_run(runs: &[Duration], cost_per_sandbox_second: f64) -> f64 {
+    if runs.is_empty() {
+        return 0.0;
+    }
+    sandbox_seconds(runs) / runs.len() as f64 * cost_per_sandbox_second
+}
+
+fn sandbox_seconds(runs: &[Duration]) -> f64 {
+    runs.iter().map(Duration::as_secs_f64).sum()
+}
+
+/// Add the sandbox seconds `runs` used and their estimated cost to `metrics`
+fn add_cost_metrics(metrics: &mut serde_json::Value, runs: &[Duration], cost_per_sandbox_second: f64) {
+    let seconds = sandbox_seconds(runs);
+    metrics["sandbox_seconds"] = serde_json::json!(seconds);
+    metrics["cost_per_sandbox_second_usd"] = serde_json::json!(cost_per_sandbox_second);
+    metrics["estimated_cost_usd"] = serde_json::json!(seconds * cost_per_sandbox_second);
+    metrics["cost_per_run_usd"] = serde_json::json!(cost_per_run(runs, cost_per_sandbox_second));
+}
+
+// Simulation functions
+
+/// Time to boot a sandbox from its template
+const SIMULATED_COLD_START: Duration = Duration::from_millis(20);
+
+/// Time a concurrent execution holds its sandbox
+const SIMULATED_EXECUTION: Duration = Duration::from_millis(2);
+
+async fn simulate_cold_start() {
+    tokio::time::sleep(SIMULATED_COLD_START).await;
+}
+
+/// A pool of warm sandboxes: a run takes one if any is idle, or else boots
+/// a new one, and hands it back afterwards up to the pool's size
+struct SimulatedPool {
+    idle: Mutex<usize>,
+    size: usize,
+}
+
+impl SimulatedPool {
+    /// A pool with all `size` sandboxes warmed up
+    fn new(size: usize) -> Self {
+        Self {
+            idle: Mutex::new(size),
+            size,
+        }
+    }
+
+    /// Take a sandbox, returning whether it was warm
+    async fn acquire(&self) -> bool {
+        let warm = {
+            let mut idle = self.idle.lock().unwrap();
+            let warm = *idle > 0;
+            *idle = idle.saturating_sub(1);
+            warm
+        };
+        if warm {
+            tokio::task::yield_now().await;
+        } else {
+            simulate_cold_start().await;
+        }
+        warm
+    }
+
+    /// Hand a sandbox back; cold-started sandboxes are kept while the pool
+    /// has room, like the warm ones
+    fn release(&self) {
+        let mut idle = self.idle.lock().unwrap();
+        *idle = (*idle + 1).min(self.size);
+    }
+}
 
 async fn simulate_sandbox_execution(code: &str, runtime: &str) -> (String, String, i32) {
     // Simulate async sandbox execution
@@ -195,4 +559,49 @@ mod tests {
         let result = benchmark.run().await;
         assert!(result.is_success());
     }
+
+    #[tokio::test]
+    async fn test_startup_benchmark_compares_cold_and_warm() {
+        let result = SandboxStartupBenchmark::new().with_runs(3).run().await;
+        assert!(result.is_success());
+
+        let cold = result.metrics["cold_start"]["avg_ms"].as_f64().unwrap();
+        let warm = result.metrics["warm_pool"]["avg_ms"].as_f64().unwrap();
+        assert!(cold >= SIMULATED_COLD_START.as_millis() as f64);
+        assert!(warm < cold);
+        assert!(
+            result.metrics["cold_cost_per_run_usd"].as_f64().unwrap()
+                > result.metrics["warm_cost_per_run_usd"].as_f64().unwrap()
+        );
+        assert!(result.metrics["estimated_cost_usd"].as_f64().unwrap() > 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_beyond_the_pool_cold_starts() {
+        let result = SandboxConcurrencyBenchmark::new()
+            .with_pool_size(2)
+            .with_concurrency_levels(vec![1, 4])
+            .with_runs_per_level(8)
+            .run()
+            .await;
+        assert!(result.is_success());
+
+        let levels = result.metrics["levels"].as_array().unwrap();
+        assert_eq!(levels.len(), 2);
+        assert_eq!(levels[0]["cold_starts"], 0);
+        assert!(levels[1]["cold_starts"].as_u64().unwrap() > 0);
+        assert!(levels[1]["throughput_per_sec"].as_f64().unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_cost_is_priced_from_sandbox_seconds() {
+        let runs = [Duration::from_secs(2), Duration::from_secs(4)];
+        let mut metrics = serde_json::json!({});
+        add_cost_metrics(&mut metrics, &runs, 0.5);
+
+        assert_eq!(metrics["sandbox_seconds"], 6.0);
+        assert_eq!(metrics["estimated_cost_usd"], 3.0);
+        assert_eq!(metrics["cost_per_run_usd"], 1.5);
+        assert_eq!(LatencyStats::of(&runs).p95_ms, 4000.0);
+    }
 }
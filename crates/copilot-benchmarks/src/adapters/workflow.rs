@@ -3,7 +3,7 @@
 //! Exposes workflow orchestration operations as benchmark targets.
 
 use async_trait::async_trait;
-use std::time::Instant;
+use crate::determinism::stopwatch;
 use crate::result::BenchmarkResult;
 use crate::traits::BenchTarget;
 
@@ -55,7 +55,7 @@ impl BenchTarget for WorkflowExecutionBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         let step_count = match self.workflow_complexity {
             WorkflowComplexity::Simple => 5,
@@ -68,7 +68,7 @@ impl BenchTarget for WorkflowExecutionBenchmark {
         let mut parallel_groups = 0;
 
         for step in 0..step_count {
-            let step_start = Instant::now();
+            let step_start = stopwatch();
 
             // Simulate step execution
             let is_parallel = step % 3 == 0 && step > 0;
@@ -136,14 +136,14 @@ impl BenchTarget for WorkflowValidationBenchmark {
     }
 
     async fn run(&self) -> BenchmarkResult {
-        let start = Instant::now();
+        let start = stopwatch();
 
         // Simulate validating various workflow definitions
         let workflow_sizes = vec![5, 10, 20, 50, 100];
         let mut validation_results = Vec::new();
 
         for size in &workflow_sizes {
-            let validation_start = Instant::now();
+            let validation_start = stopwatch();
 
             // Simulate workflow validation
             let _is_valid = simulate_workflow_validation(*size);
@@ -0,0 +1,396 @@
+//! Synthetic benchmark corpora
+//!
+//! Generates documents, queries and graded relevance labels so retrieval
+//! benchmarks can run at any scale without shipping fixtures. Every document
+//! and query is derived from the seed and its own index, so a corpus is the
+//! same on every run, can be streamed without holding it in memory, and any
+//! single document can be regenerated on its own.
+//!
+//! Documents are grouped into clusters that share a topic phrase, and each
+//! document carries a unique anchor token. A query names one document's
+//! anchor and its cluster's topic: that document is highly relevant
+//! (grade 2) and the rest of its cluster is relevant (grade 1).
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Vocabulary and layout of generated documents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorpusKind {
+    /// Source code functions
+    Code,
+    /// Service log lines
+    Logs,
+    /// Natural language paragraphs
+    Prose,
+}
+
+/// Shape of a generated corpus
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorpusConfig {
+    pub kind: CorpusKind,
+    pub documents: usize,
+    pub queries: usize,
+    /// Approximate length of each document in words
+    pub words_per_document: usize,
+    /// Documents sharing a topic, and so relevant to the same queries
+    pub cluster_size: usize,
+    pub seed: u64,
+}
+
+impl Default for CorpusConfig {
+    fn default() -> Self {
+        Self {
+            kind: CorpusKind::Prose,
+            documents: 10_000,
+            queries: 100,
+            words_per_document: 64,
+            cluster_size: 5,
+            seed: 42,
+        }
+    }
+}
+
+impl CorpusConfig {
+    pub fn new(kind: CorpusKind, documents: usize) -> Self {
+        Self {
+            kind,
+            documents,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_queries(mut self, queries: usize) -> Self {
+        self.queries = queries;
+        self
+    }
+
+    pub fn with_words_per_document(mut self, words: usize) -> Self {
+        self.words_per_document = words;
+        self
+    }
+
+    pub fn with_cluster_size(mut self, cluster_size: usize) -> Self {
+        self.cluster_size = cluster_size.max(1);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    fn clusters(&self) -> usize {
+        self.documents.div_ceil(self.cluster_size.max(1))
+    }
+}
+
+/// A generated document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyntheticDocument {
+    pub id: String,
+    pub text: String,
+}
+
+/// A generated query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyntheticQuery {
+    pub id: String,
+    pub text: String,
+}
+
+/// How relevant a document is to a query: 2 for the document the query
+/// names, 1 for the rest of its cluster
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelevanceLabel {
+    pub query_id: String,
+    pub document_id: String,
+    pub grade: u8,
+}
+
+/// Counts of what [`CorpusGenerator::write_to`] wrote
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorpusStats {
+    pub documents: usize,
+    pub queries: usize,
+    pub labels: usize,
+}
+
+/// Generates a synthetic corpus from a [`CorpusConfig`]
+pub struct CorpusGenerator {
+    config: CorpusConfig,
+}
+
+const CODE_WORDS: &[&str] = &[
+    "user", "session", "cache", "token", "request", "config", "handler", "buffer", "index",
+    "query", "retry", "batch", "stream", "schema", "client", "pool", "event", "metric",
+];
+const CODE_TYPES: &[&str] = &["String", "u64", "bool", "Vec<u8>", "Option<Id>", "Duration", "Result<()>"];
+const LOG_SERVICES: &[&str] = &["auth", "billing", "checkout", "gateway", "inventory", "search", "payments"];
+const LOG_LEVELS: &[&str] = &["INFO", "INFO", "INFO", "DEBUG", "WARN", "ERROR"];
+const LOG_MESSAGES: &[&str] = &[
+    "request completed", "cache miss", "connection reset", "retrying upstream call",
+    "slow query detected", "token refreshed", "queue depth high", "health check passed",
+];
+const PROSE_WORDS: &[&str] = &[
+    "the", "a", "system", "team", "during", "incident", "latency", "rollout", "release",
+    "observed", "because", "after", "traffic", "capacity", "customers", "reported", "service",
+    "migration", "was", "and", "with", "improved", "regional", "failover", "dashboard", "alert",
+];
+const TOPIC_WORDS: &[&str] = &[
+    "ledger", "quota", "replica", "tenant", "webhook", "shard", "invoice", "catalog", "ingest",
+    "cursor", "lease", "backfill", "throttle", "snapshot", "routing", "settlement", "audit",
+    "embedding", "manifest", "compaction",
+];
+
+impl CorpusGenerator {
+    pub fn new(config: CorpusConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &CorpusConfig {
+        &self.config
+    }
+
+    /// The document at `index`
+    pub fn document(&self, index: usize) -> SyntheticDocument {
+        let mut rng = self.rng(DOCUMENT_STREAM, index);
+        let topic = self.topic(index / self.config.cluster_size.max(1));
+        let anchor = self.anchor(index);
+        let text = match self.config.kind {
+            CorpusKind::Code => self.code(&mut rng, &topic, &anchor),
+            CorpusKind::Logs => self.logs(&mut rng, &topic, &anchor),
+            CorpusKind::Prose => self.prose(&mut rng, &topic, &anchor),
+        };
+        SyntheticDocument {
+            id: document_id(index),
+            text,
+        }
+    }
+
+    /// Every document, generated as iterated
+    pub fn documents(&self) -> impl Iterator<Item = SyntheticDocument> + '_ {
+        (0..self.config.documents).map(|i| self.document(i))
+    }
+
+    /// The query at `index` and the documents relevant to it
+    pub fn query(&self, index: usize) -> (SyntheticQuery, Vec<RelevanceLabel>) {
+        let id = format!("q{:06}", index);
+        if self.config.documents == 0 {
+            return (SyntheticQuery { id, text: String::new() }, Vec::new());
+        }
+
+        let mut rng = self.rng(QUERY_STREAM, index);
+        let cluster_size = self.config.cluster_size.max(1);
+        let cluster = rng.gen_range(0..self.config.clusters());
+        let members = cluster * cluster_size..((cluster + 1) * cluster_size).min(self.config.documents);
+        let target = rng.gen_range(members.clone());
+
+        let topic = self.topic(cluster);
+        let anchor = self.anchor(target);
+        let text = match self.config.kind {
+            CorpusKind::Code => format!("where is {} defined in the {} module", anchor, topic.replace(' ', "_")),
+            CorpusKind::Logs => format!("show {} logs for request {}", topic.replace(' ', "-"), anchor),
+            CorpusKind::Prose => format!("what happened to {} during the {} work", anchor, topic),
+        };
+
+        let labels = members
+            .map(|member| RelevanceLabel {
+                query_id: id.clone(),
+                document_id: document_id(member),
+                grade: if member == target { 2 } else { 1 },
+            })
+            .collect();
+        (SyntheticQuery { id, text }, labels)
+    }
+
+    /// Every query with its labels, generated as iterated
+    pub fn queries(&self) -> impl Iterator<Item = (SyntheticQuery, Vec<RelevanceLabel>)> + '_ {
+        (0..self.config.queries).map(|i| self.query(i))
+    }
+
+    /// Write `documents.jsonl`, `queries.jsonl` and `qrels.tsv` (query id,
+    /// document id, grade) into `dir`, streaming so memory stays flat
+    pub fn write_to(&self, dir: &Path) -> std::io::Result<CorpusStats> {
+        std::fs::create_dir_all(dir)?;
+        let mut stats = CorpusStats::default();
+
+        let mut documents = BufWriter::new(File::create(dir.join("documents.jsonl"))?);
+        for document in self.documents() {
+            serde_json::to_writer(&mut documents, &document)?;
+            documents.write_all(b"\n")?;
+            stats.documents += 1;
+        }
+        documents.flush()?;
+
+        let mut queries = BufWriter::new(File::create(dir.join("queries.jsonl"))?);
+        let mut qrels = BufWriter::new(File::create(dir.join("qrels.tsv"))?);
+        for (query, labels) in self.queries() {
+            serde_json::to_writer(&mut queries, &query)?;
+            queries.write_all(b"\n")?;
+            stats.queries += 1;
+            for label in labels {
+                writeln!(qrels, "{}\t{}\t{}", label.query_id, label.document_id, label.grade)?;
+                stats.labels += 1;
+            }
+        }
+        queries.flush()?;
+        qrels.flush()?;
+
+        Ok(stats)
+    }
+
+    fn rng(&self, stream: u64, index: usize) -> StdRng {
+        StdRng::seed_from_u64(splitmix64(self.config.seed ^ splitmix64(stream ^ index as u64)))
+    }
+
+    /// The topic phrase shared by a cluster's documents
+    fn topic(&self, cluster: usize) -> String {
+        let mut rng = self.rng(TOPIC_STREAM, cluster);
+        let words: Vec<&str> = TOPIC_WORDS.choose_multiple(&mut rng, 2).copied().collect();
+        words.join(" ")
+    }
+
+    /// A token unique to the document at `index`
+    fn anchor(&self, index: usize) -> String {
+        // Xor with a fixed mask keeps anchors distinct while hiding the index
+        let token = (index as u64) ^ (splitmix64(self.config.seed) & 0xffff_ffff);
+        match self.config.kind {
+            CorpusKind::Code => format!("fn_{:08x}", token),
+            CorpusKind::Logs => format!("req-{:08x}", token),
+            CorpusKind::Prose => format!("project-{:08x}", token),
+        }
+    }
+
+    fn code(&self, rng: &mut StdRng, topic: &str, anchor: &str) -> String {
+        let module = topic.replace(' ', "_");
+        let mut text = format!("mod {} {{\n    pub fn {}(", module, anchor);
+        let mut words = 4;
+        while words < self.config.words_per_document {
+            let a = pick(rng, CODE_WORDS);
+            let b = pick(rng, CODE_WORDS);
+            match rng.gen_range(0..3) {
+                0 => text.push_str(&format!("\n        let {}_{} = {}.{}()?;", a, b, a, b)),
+                1 => text.push_str(&format!("\n        // {} the {} {}", pick(rng, CODE_WORDS), a, b)),
+                _ => text.push_str(&format!("\n        {}: {},", a, pick(rng, CODE_TYPES))),
+            }
+            words += 4;
+        }
+        text.push_str(&format!("\n    ) {{ {}::run() }}\n}}", module));
+        text
+    }
+
+    fn logs(&self, rng: &mut StdRng, topic: &str, anchor: &str) -> String {
+        let service = topic.replace(' ', "-");
+        let mut lines = Vec::new();
+        let mut words = 0;
+        while words < self.config.words_per_document.max(1) {
+            let second = rng.gen_range(0..60);
+            lines.push(format!(
+                "2024-01-01T00:00:{:02}Z {} {}-{}: {} request_id={} latency_ms={}",
+                second,
+                pick(rng, LOG_LEVELS),
+                pick(rng, LOG_SERVICES),
+                service,
+                pick(rng, LOG_MESSAGES),
+                anchor,
+                rng.gen_range(1..2000)
+            ));
+            words += 8;
+        }
+        lines.join("\n")
+    }
+
+    fn prose(&self, rng: &mut StdRng, topic: &str, anchor: &str) -> String {
+        let mut words: Vec<&str> = (0..self.config.words_per_document.saturating_sub(4))
+            .map(|_| pick(rng, PROSE_WORDS))
+            .collect();
+        let at = rng.gen_range(0..=words.len());
+        words.insert(at, topic);
+        words.insert(at, "the");
+        let at = rng.gen_range(0..=words.len());
+        words.insert(at, anchor);
+        let mut text = words.join(" ");
+        text.push('.');
+        text
+    }
+}
+
+const DOCUMENT_STREAM: u64 = 0x646f_6373;
+const QUERY_STREAM: u64 = 0x7175_6572;
+const TOPIC_STREAM: u64 = 0x746f_7063;
+
+fn document_id(index: usize) -> String {
+    format!("doc{:07}", index)
+}
+
+fn pick<'a>(rng: &mut StdRng, words: &[&'a str]) -> &'a str {
+    words[rng.gen_range(0..words.len())]
+}
+
+/// SplitMix64 finalizer, spreading nearby seeds far apart
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corpus_is_reproducible() {
+        let config = CorpusConfig::new(CorpusKind::Code, 50).with_queries(10);
+        let first = CorpusGenerator::new(config.clone());
+        let second = CorpusGenerator::new(config.clone());
+
+        assert_eq!(first.document(17), second.document(17));
+        assert_eq!(first.query(3), second.query(3));
+        assert_ne!(
+            first.document(17),
+            CorpusGenerator::new(config.with_seed(7)).document(17)
+        );
+        assert_eq!(first.documents().count(), 50);
+    }
+
+    #[test]
+    fn test_queries_name_their_relevant_documents() {
+        for kind in [CorpusKind::Code, CorpusKind::Logs, CorpusKind::Prose] {
+            let generator = CorpusGenerator::new(
+                CorpusConfig::new(kind, 23).with_queries(20).with_cluster_size(4),
+            );
+            for (query, labels) in generator.queries() {
+                let target = labels.iter().find(|l| l.grade == 2).unwrap();
+                let index: usize = target.document_id[3..].parse().unwrap();
+                let anchor = generator.anchor(index);
+
+                assert!(query.text.contains(&anchor), "{:?}", query);
+                assert!(generator.document(index).text.contains(&anchor));
+                assert!(labels.len() <= 4);
+                assert_eq!(labels.iter().filter(|l| l.grade == 2).count(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_to_streams_files() {
+        let dir = std::env::temp_dir().join(format!("copilot-corpus-{}", uuid::Uuid::new_v4()));
+        let generator = CorpusGenerator::new(CorpusConfig::new(CorpusKind::Logs, 12).with_queries(3));
+
+        let stats = generator.write_to(&dir).unwrap();
+        assert_eq!(stats.documents, 12);
+        assert_eq!(stats.queries, 3);
+
+        let qrels = std::fs::read_to_string(dir.join("qrels.tsv")).unwrap();
+        assert_eq!(qrels.lines().count(), stats.labels);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
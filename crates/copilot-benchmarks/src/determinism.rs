@@ -0,0 +1,36 @@
+//! Clock for benchmark runs
+//!
+//! [`run_all_benchmarks_with_config`](crate::run_all_benchmarks_with_config)
+//! runs every target with the configured [`Determinism`] in scope. Targets
+//! time themselves with [`stopwatch`], and results and reports are stamped
+//! with [`now`], so a seeded run (see `COPILOT_SEED`) writes identical
+//! results and summaries every time: its clock is frozen, timings read zero
+//! and every timestamp is the clock's epoch.
+
+use chrono::{DateTime, Utc};
+use copilot_core::{Determinism, Stopwatch};
+use std::future::Future;
+
+tokio::task_local! {
+    static CURRENT: Determinism;
+}
+
+/// The determinism of the running benchmark, the system outside a run
+pub fn current() -> Determinism {
+    CURRENT.try_with(Determinism::clone).unwrap_or_default()
+}
+
+/// Current time on the run's clock
+pub fn now() -> DateTime<Utc> {
+    current().now()
+}
+
+/// Start timing on the run's clock
+pub fn stopwatch() -> Stopwatch {
+    current().stopwatch()
+}
+
+/// Run `future` with `determinism` in scope
+pub async fn scope<F: Future>(determinism: Determinism, future: F) -> F::Output {
+    CURRENT.scope(determinism, future).await
+}
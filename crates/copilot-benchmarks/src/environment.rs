@@ -0,0 +1,244 @@
+//! Machine a benchmark ran on
+//!
+//! Every [`BenchmarkResult`](crate::BenchmarkResult) records the
+//! [`Environment`] it was measured in: OS, CPU count and model, memory and,
+//! inside a container, the CPU and memory limits of its cgroup. A laptop,
+//! a CI runner and a two-CPU container produce very different timings for
+//! the same commit, so the markdown summary groups results by environment
+//! and compares targets across environments side by side instead of
+//! averaging them together.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Root of the cgroup filesystem on Linux
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// cgroup v1 reports "no limit" as a huge page-aligned number; anything
+/// above this is treated as unlimited
+const UNLIMITED_MEMORY: u64 = 1 << 60;
+
+/// CPU and memory limits of the container a benchmark ran in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ContainerLimits {
+    /// cgroup version the limits were read from (1 or 2)
+    pub cgroup_version: u8,
+    /// CPUs the container may use, from its CFS quota
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_limit: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_limit_bytes: Option<u64>,
+}
+
+impl ContainerLimits {
+    /// Limits of the cgroup mounted at `root`; `None` when there is no
+    /// cgroup filesystem or it sets no limits
+    pub fn detect_in(root: &Path) -> Option<Self> {
+        let read = |file: &str| std::fs::read_to_string(root.join(file)).ok();
+
+        let limits = if let Some(cpu_max) = read("cpu.max") {
+            Self {
+                cgroup_version: 2,
+                cpu_limit: parse_cpu_max(&cpu_max),
+                memory_limit_bytes: read("memory.max").and_then(|max| parse_memory_limit(&max)),
+            }
+        } else {
+            let quota = read("cpu/cpu.cfs_quota_us");
+            let period = read("cpu/cpu.cfs_period_us");
+            let memory = read("memory/memory.limit_in_bytes");
+            if quota.is_none() && memory.is_none() {
+                return None;
+            }
+            Self {
+                cgroup_version: 1,
+                cpu_limit: quota.zip(period).and_then(|(quota, period)| cpu_quota(quota.trim(), period.trim())),
+                memory_limit_bytes: memory.and_then(|limit| parse_memory_limit(&limit)),
+            }
+        };
+        (limits.cpu_limit.is_some() || limits.memory_limit_bytes.is_some()).then_some(limits)
+    }
+}
+
+/// CPUs allowed by a cgroup v2 `cpu.max` ("200000 100000", or "max 100000"
+/// when unlimited)
+fn parse_cpu_max(cpu_max: &str) -> Option<f64> {
+    let mut fields = cpu_max.split_whitespace();
+    cpu_quota(fields.next()?, fields.next().unwrap_or("100000"))
+}
+
+fn cpu_quota(quota: &str, period: &str) -> Option<f64> {
+    let quota: f64 = quota.parse().ok().filter(|quota: &f64| *quota > 0.0)?;
+    let period: f64 = period.parse().ok().filter(|period: &f64| *period > 0.0)?;
+    Some(quota / period)
+}
+
+/// A memory limit in bytes ("max" or a huge number when unlimited)
+fn parse_memory_limit(limit: &str) -> Option<u64> {
+    limit.trim().parse().ok().filter(|limit| *limit < UNLIMITED_MEMORY)
+}
+
+/// The machine, and container if any, a benchmark ran in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Environment {
+    pub os: String,
+    /// Distribution or release, e.g. `Ubuntu 22.04.4 LTS` or `14.4`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os_version: Option<String>,
+    pub arch: String,
+    /// CPUs available to the process
+    pub cpu_count: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_model: Option<String>,
+    /// Physical memory of the machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_bytes: Option<u64>,
+    /// Limits of the container the process ran in, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<ContainerLimits>,
+}
+
+impl Environment {
+    /// The environment of this process, detected once
+    pub fn current() -> Self {
+        static CURRENT: OnceLock<Environment> = OnceLock::new();
+        CURRENT.get_or_init(Self::detect).clone()
+    }
+
+    /// Detect the environment; facts that can't be determined are left unset
+    pub fn detect() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            os_version: os_version(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpu_count: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            cpu_model: crate::provenance::cpu_model(),
+            memory_bytes: memory_bytes(),
+            container: if cfg!(target_os = "linux") {
+                ContainerLimits::detect_in(Path::new(CGROUP_ROOT))
+            } else {
+                None
+            },
+        }
+    }
+
+    /// One line describing the environment, e.g. `linux x86_64, 8 CPUs
+    /// (AMD EPYC 7763), 15.6 GiB, container limited to 2 CPUs / 4.0 GiB`;
+    /// results are grouped by it
+    pub fn label(&self) -> String {
+        let mut label = self.os.clone();
+        if let Some(version) = &self.os_version {
+            label.push_str(&format!(" {}", version));
+        }
+        label.push_str(&format!(" {}, {} CPUs", self.arch, self.cpu_count));
+        if let Some(model) = &self.cpu_model {
+            label.push_str(&format!(" ({})", model));
+        }
+        if let Some(memory) = self.memory_bytes {
+            label.push_str(&format!(", {}", gib(memory)));
+        }
+        if let Some(container) = &self.container {
+            let limits: Vec<String> = container
+                .cpu_limit
+                .map(|cpus| format!("{} CPUs", cpus))
+                .into_iter()
+                .chain(container.memory_limit_bytes.map(gib))
+                .collect();
+            label.push_str(&format!(", container limited to {}", limits.join(" / ")));
+        }
+        label
+    }
+}
+
+fn gib(bytes: u64) -> String {
+    format!("{:.1} GiB", bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+}
+
+fn os_version() -> Option<String> {
+    if cfg!(target_os = "linux") {
+        std::fs::read_to_string("/etc/os-release").ok()?.lines().find_map(|line| {
+            let value = line.strip_prefix("PRETTY_NAME=")?;
+            Some(value.trim_matches('"').to_string())
+        })
+    } else if cfg!(target_os = "macos") {
+        let output = Command::new("sw_vers").arg("-productVersion").output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
+fn memory_bytes() -> Option<u64> {
+    if cfg!(target_os = "linux") {
+        std::fs::read_to_string("/proc/meminfo").ok()?.lines().find_map(|line| {
+            let kib = line.strip_prefix("MemTotal:")?.trim().strip_suffix("kB")?;
+            kib.trim().parse::<u64>().ok().map(|kib| kib * 1024)
+        })
+    } else if cfg!(target_os = "macos") {
+        let output = Command::new("sysctl").args(["-n", "hw.memsize"]).output().ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_limits_from_cgroups() {
+        let root = std::env::temp_dir().join(format!("bench_cgroup_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        assert_eq!(ContainerLimits::detect_in(&root), None);
+
+        // cgroup v2 without limits, then with two CPUs and 4 GiB
+        std::fs::write(root.join("cpu.max"), "max 100000\n").unwrap();
+        std::fs::write(root.join("memory.max"), "max\n").unwrap();
+        assert_eq!(ContainerLimits::detect_in(&root), None);
+        std::fs::write(root.join("cpu.max"), "200000 100000\n").unwrap();
+        std::fs::write(root.join("memory.max"), "4294967296\n").unwrap();
+        let limits = ContainerLimits::detect_in(&root).unwrap();
+        assert_eq!(limits.cgroup_version, 2);
+        assert_eq!(limits.cpu_limit, Some(2.0));
+        assert_eq!(limits.memory_limit_bytes, Some(4 * 1024 * 1024 * 1024));
+        std::fs::remove_dir_all(&root).unwrap();
+
+        // cgroup v1 with a half-CPU quota and no memory limit
+        std::fs::create_dir_all(root.join("cpu")).unwrap();
+        std::fs::create_dir_all(root.join("memory")).unwrap();
+        std::fs::write(root.join("cpu/cpu.cfs_quota_us"), "50000\n").unwrap();
+        std::fs::write(root.join("cpu/cpu.cfs_period_us"), "100000\n").unwrap();
+        std::fs::write(root.join("memory/memory.limit_in_bytes"), "9223372036854771712\n").unwrap();
+        let limits = ContainerLimits::detect_in(&root).unwrap();
+        assert_eq!((limits.cgroup_version, limits.cpu_limit, limits.memory_limit_bytes), (1, Some(0.5), None));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_label_describes_machine_and_container() {
+        let environment = Environment {
+            os: "linux".to_string(),
+            os_version: None,
+            arch: "x86_64".to_string(),
+            cpu_count: 8,
+            cpu_model: Some("AMD EPYC 7763".to_string()),
+            memory_bytes: Some(16 * 1024 * 1024 * 1024),
+            container: Some(ContainerLimits {
+                cgroup_version: 2,
+                cpu_limit: Some(2.0),
+                memory_limit_bytes: Some(4 * 1024 * 1024 * 1024),
+            }),
+        };
+        assert_eq!(
+            environment.label(),
+            "linux x86_64, 8 CPUs (AMD EPYC 7763), 16.0 GiB, container limited to 2 CPUs / 4.0 GiB"
+        );
+        assert_eq!(Environment::current().os, std::env::consts::OS);
+    }
+}
@@ -3,6 +3,7 @@
 //! This module handles reading and writing benchmark results to the
 //! canonical output directories.
 
+use crate::provenance::{MachineIdentity, Provenance, SignedResults};
 use crate::result::BenchmarkResult;
 use std::fs::{self, File};
 use std::io::{BufReader, BufWriter, Write};
@@ -23,6 +24,9 @@ pub enum IoError {
 
     #[error("Invalid file format: {0}")]
     InvalidFormat(String),
+
+    #[error("Signature error: {0}")]
+    Signature(String),
 }
 
 /// Result type for I/O operations
@@ -102,14 +106,31 @@ impl BenchmarkIo {
         results.iter().map(|r| self.write_result(r)).collect()
     }
 
-    /// Write all results to a single combined file
+    /// Write all results to a single combined file with the provenance of
+    /// the run
     pub fn write_combined(&self, results: &[BenchmarkResult], filename: &str) -> IoResult<PathBuf> {
+        self.write_signed(&SignedResults::new(results.to_vec(), Provenance::collect()), filename)
+    }
+
+    /// Write all results to a single combined file with the provenance of
+    /// the run, signed by `identity`
+    pub fn write_combined_signed(
+        &self,
+        results: &[BenchmarkResult],
+        filename: &str,
+        identity: &MachineIdentity,
+    ) -> IoResult<PathBuf> {
+        let signed = identity.sign(SignedResults::new(results.to_vec(), Provenance::collect()))?;
+        self.write_signed(&signed, filename)
+    }
+
+    fn write_signed(&self, signed: &SignedResults, filename: &str) -> IoResult<PathBuf> {
         self.ensure_directories()?;
 
         let path = self.output_dir.join(filename);
         let file = File::create(&path)?;
         let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, results)?;
+        serde_json::to_writer_pretty(writer, signed)?;
 
         Ok(path)
     }
@@ -155,8 +176,23 @@ impl BenchmarkIo {
         let path = self.output_dir.join(filename);
         let file = File::open(&path)?;
         let reader = BufReader::new(file);
-        let results = serde_json::from_reader(reader)?;
-        Ok(results)
+        // Files written before provenance was recorded hold just the results
+        let combined: serde_json::Value = serde_json::from_reader(reader)?;
+        if combined.is_array() {
+            return Ok(serde_json::from_value(combined)?);
+        }
+        Ok(serde_json::from_value::<SignedResults>(combined)?.results)
+    }
+
+    /// Read a combined results file with its provenance and signature
+    pub fn read_signed(path: impl AsRef<Path>) -> IoResult<SignedResults> {
+        let file = File::open(path.as_ref())?;
+        let reader = BufReader::new(file);
+        let combined: serde_json::Value = serde_json::from_reader(reader)?;
+        if combined.is_array() {
+            return Err(IoError::InvalidFormat("Results file has no provenance".to_string()));
+        }
+        Ok(serde_json::from_value(combined)?)
     }
 
     /// Clean up old result files, keeping only the most recent N
@@ -233,4 +269,22 @@ mod tests {
         let read_result = io.read_result(&path).unwrap();
         assert_eq!(read_result.target_id, result.target_id);
     }
+
+    #[test]
+    fn test_combined_results_are_signed_with_provenance() {
+        let io = temp_io();
+        let (identity, _) = MachineIdentity::generate().unwrap();
+        let results = vec![BenchmarkResult::success("test::target", 100)];
+
+        let path = io.write_combined_signed(&results, "combined.json", &identity).unwrap();
+        let signed = BenchmarkIo::read_signed(&path).unwrap();
+        signed.verify_signed_by(&identity.key_id()).unwrap();
+        assert_eq!(signed.provenance.cpu_count, Provenance::collect().cpu_count);
+        assert_eq!(io.read_combined("combined.json").unwrap()[0].target_id, "test::target");
+
+        // Bare result arrays of earlier runs still read
+        std::fs::write(io.output_dir().join("old.json"), serde_json::to_string(&results).unwrap()).unwrap();
+        assert_eq!(io.read_combined("old.json").unwrap().len(), 1);
+        assert!(BenchmarkIo::read_signed(io.output_dir().join("old.json")).is_err());
+    }
 }
@@ -0,0 +1,200 @@
+//! Isolation of benchmark targets
+//!
+//! [`run_target`] runs one target under a timeout and the run's
+//! cancellation, so a hung target is reported as failed instead of stalling
+//! the run. With [`Isolation::Shared`] targets are tasks on the caller's
+//! runtime and a timed out target is aborted at its next await. With
+//! [`Isolation::Thread`] each target gets its own thread and runtime,
+//! optionally pinned to a CPU, so targets don't compete for runtime workers
+//! and a target that blocks its thread is abandoned rather than waited for.
+
+use crate::determinism;
+use crate::result::BenchmarkResult;
+use crate::traits::BenchTarget;
+use copilot_core::Determinism;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// How targets are isolated from each other
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Isolation {
+    /// Targets run as tasks on the caller's runtime
+    #[default]
+    Shared,
+    /// Each target runs on its own thread with a single-threaded runtime,
+    /// pinned to the CPUs listed in turn (Linux only; empty leaves threads
+    /// unpinned)
+    Thread { cpus: Vec<usize> },
+}
+
+/// Run `target` with `isolation`, stopping it after `timeout` or when
+/// `cancellation` fires; `slot` picks the CPU a pinned thread runs on
+pub async fn run_target(
+    target: Arc<dyn BenchTarget>,
+    isolation: &Isolation,
+    slot: usize,
+    timeout: Duration,
+    cancellation: &CancellationToken,
+) -> BenchmarkResult {
+    let id = target.id().to_string();
+    if cancellation.is_cancelled() {
+        return BenchmarkResult::cancelled(&id);
+    }
+    let timeout = target.timeout().unwrap_or(timeout);
+    let determinism = determinism::current();
+
+    let run = async {
+        match isolation {
+            Isolation::Shared => run_shared(target, determinism).await,
+            Isolation::Thread { cpus } => {
+                let cpu = (!cpus.is_empty()).then(|| cpus[slot % cpus.len()]);
+                run_on_thread(target, determinism, cpu).await
+            }
+        }
+    };
+    tokio::select! {
+        result = tokio::time::timeout(timeout, run) => {
+            result.unwrap_or_else(|_| BenchmarkResult::timed_out(&id, timeout))
+        }
+        _ = cancellation.cancelled() => BenchmarkResult::cancelled(&id),
+    }
+}
+
+/// Aborts a task when dropped, e.g. when its run times out
+struct AbortOnDrop<T>(tokio::task::JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+async fn run_shared(target: Arc<dyn BenchTarget>, determinism: Determinism) -> BenchmarkResult {
+    let id = target.id().to_string();
+    let mut task = AbortOnDrop(tokio::spawn(determinism::scope(determinism, async move {
+        target.run().await
+    })));
+    (&mut task.0)
+        .await
+        .unwrap_or_else(|e| BenchmarkResult::failure(id, format!("Benchmark panicked: {}", e)))
+}
+
+async fn run_on_thread(target: Arc<dyn BenchTarget>, determinism: Determinism, cpu: Option<usize>) -> BenchmarkResult {
+    let id = target.id().to_string();
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    let thread = std::thread::Builder::new()
+        .name(format!("bench-{}", id))
+        .spawn(move || {
+            let pinned = cpu.filter(|&cpu| {
+                let pinned = pin_to_cpu(cpu);
+                if !pinned {
+                    eprintln!("Warning: Failed to pin benchmark {} to CPU {}", target.id(), cpu);
+                }
+                pinned
+            });
+            let result = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(runtime) => {
+                    let mut result = runtime.block_on(determinism::scope(determinism, target.run()));
+                    if let Some(cpu) = pinned {
+                        result.metrics["pinned_cpu"] = serde_json::json!(cpu);
+                    }
+                    result
+                }
+                Err(e) => BenchmarkResult::failure(target.id(), format!("Failed to start runtime: {}", e)),
+            };
+            let _ = sender.send(result);
+        });
+
+    if let Err(e) = thread {
+        return BenchmarkResult::failure(id, format!("Failed to start benchmark thread: {}", e));
+    }
+    receiver
+        .await
+        .unwrap_or_else(|_| BenchmarkResult::failure(id, "Benchmark panicked"))
+}
+
+/// Pin the calling thread to `cpu`, returning whether it worked
+#[cfg(target_os = "linux")]
+fn pin_to_cpu(cpu: usize) -> bool {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return false;
+    }
+    // SAFETY: the set is a plain bitmask initialized before use, and pid 0
+    // names the calling thread
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to_cpu(_cpu: usize) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    struct Hung;
+
+    #[async_trait]
+    impl BenchTarget for Hung {
+        fn id(&self) -> &str {
+            "test::hung"
+        }
+
+        async fn run(&self) -> BenchmarkResult {
+            std::future::pending().await
+        }
+    }
+
+    struct Blocking;
+
+    #[async_trait]
+    impl BenchTarget for Blocking {
+        fn id(&self) -> &str {
+            "test::blocking"
+        }
+
+        fn timeout(&self) -> Option<Duration> {
+            Some(Duration::from_millis(20))
+        }
+
+        async fn run(&self) -> BenchmarkResult {
+            std::thread::sleep(Duration::from_millis(500));
+            BenchmarkResult::success("test::blocking", 500)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hung_targets_time_out() {
+        let cancellation = CancellationToken::new();
+        let result = run_target(Arc::new(Hung), &Isolation::Shared, 0, Duration::from_millis(20), &cancellation).await;
+        assert!(!result.is_success());
+        assert_eq!(result.metrics["timed_out"], true);
+
+        // A target blocking its thread is abandoned at its own timeout
+        let isolation = Isolation::Thread { cpus: vec![0] };
+        let result = run_target(Arc::new(Blocking), &isolation, 0, Duration::from_secs(60), &cancellation).await;
+        assert_eq!(result.metrics["timed_out"], true);
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_stops_targets() {
+        let cancellation = CancellationToken::new();
+        let cancel = cancellation.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            cancel.cancel();
+        });
+
+        let result = run_target(Arc::new(Hung), &Isolation::Shared, 0, Duration::from_secs(60), &cancellation).await;
+        assert_eq!(result.metrics["cancelled"], true);
+        let result = run_target(Arc::new(Hung), &Isolation::Shared, 0, Duration::from_secs(60), &cancellation).await;
+        assert_eq!(result.error(), Some("Cancelled"));
+    }
+}
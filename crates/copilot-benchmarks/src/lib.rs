@@ -19,9 +19,15 @@
 //! ├── traits.rs       (BenchTarget trait)
 //! ├── markdown.rs     (Markdown report generation)
 //! ├── io.rs           (File I/O for results)
+//! ├── determinism.rs  (Run clock for timings and timestamps)
+//! ├── isolation.rs    (Timeouts, cancellation and thread isolation of targets)
+//! ├── provenance.rs   (Provenance and signing of combined results)
+//! ├── environment.rs  (Machine and container limits results were measured in)
+//! ├── corpus.rs       (Synthetic documents, queries and relevance labels)
 //! ├── adapters/       (Benchmark target implementations)
 //! │   ├── mod.rs
 //! │   ├── intent_classification.rs
+//! │   ├── nlp_accuracy.rs
 //! │   ├── context_retrieval.rs
 //! │   ├── conversation.rs
 //! │   ├── workflow.rs
@@ -50,6 +56,11 @@
 
 pub mod result;
 pub mod traits;
+pub mod determinism;
+pub mod isolation;
+pub mod provenance;
+pub mod environment;
+pub mod corpus;
 pub mod markdown;
 pub mod io;
 pub mod adapters;
@@ -59,7 +70,17 @@ pub use result::BenchmarkResult;
 pub use traits::{BenchTarget, BoxedBenchTarget};
 pub use markdown::{MarkdownGenerator, MarkdownConfig};
 pub use io::{BenchmarkIo, IoError, IoResult};
+pub use corpus::{CorpusConfig, CorpusGenerator, CorpusKind};
 pub use adapters::all_targets;
+pub use isolation::Isolation;
+pub use provenance::{MachineIdentity, Provenance, ResultSignature, SignedResults};
+pub use environment::{ContainerLimits, Environment};
+pub use copilot_core::Determinism;
+pub use tokio_util::sync::CancellationToken;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Configuration for running benchmarks
 #[derive(Debug, Clone)]
@@ -74,6 +95,19 @@ pub struct BenchmarkConfig {
     pub max_parallel: usize,
     /// Filter to run only specific targets (by ID prefix)
     pub filter: Option<String>,
+    /// Clock for timings and timestamps; seeded from `COPILOT_SEED` when set
+    pub determinism: Determinism,
+    /// Time a target may run before it is stopped and reported as timed
+    /// out, unless the target sets its own
+    pub target_timeout: Duration,
+    /// How targets are isolated from each other
+    pub isolation: Isolation,
+    /// Stops the run: targets still running or not yet started are reported
+    /// as cancelled
+    pub cancellation: CancellationToken,
+    /// Sign the combined results with the machine identity key stored here,
+    /// created on first use
+    pub identity_key: Option<PathBuf>,
 }
 
 impl Default for BenchmarkConfig {
@@ -84,6 +118,11 @@ impl Default for BenchmarkConfig {
             parallel: false,
             max_parallel: 4,
             filter: None,
+            determinism: Determinism::from_env(),
+            target_timeout: Duration::from_secs(300),
+            isolation: Isolation::Shared,
+            cancellation: CancellationToken::new(),
+            identity_key: None,
         }
     }
 }
@@ -107,8 +146,11 @@ pub async fn run_all_benchmarks() -> Vec<BenchmarkResult> {
 
 /// Run all benchmarks with custom configuration
 pub async fn run_all_benchmarks_with_config(config: BenchmarkConfig) -> Vec<BenchmarkResult> {
+    determinism::scope(config.determinism.clone(), run_targets(config)).await
+}
+
+async fn run_targets(config: BenchmarkConfig) -> Vec<BenchmarkResult> {
     let targets = adapters::all_targets();
-    let mut results = Vec::with_capacity(targets.len());
 
     // Filter targets if filter is specified
     let targets: Vec<_> = if let Some(ref filter) = config.filter {
@@ -120,24 +162,7 @@ pub async fn run_all_benchmarks_with_config(config: BenchmarkConfig) -> Vec<Benc
         targets
     };
 
-    if config.parallel {
-        // Run benchmarks in parallel with limited concurrency
-        use futures::stream::{self, StreamExt};
-
-        let results_stream = stream::iter(targets)
-            .map(|target| async move {
-                target.run().await
-            })
-            .buffer_unordered(config.max_parallel);
-
-        results = results_stream.collect().await;
-    } else {
-        // Run benchmarks sequentially
-        for target in targets {
-            let result = target.run().await;
-            results.push(result);
-        }
-    }
+    let results = execute_targets(targets, &config).await;
 
     // Write results if configured
     if config.write_results {
@@ -146,8 +171,13 @@ pub async fn run_all_benchmarks_with_config(config: BenchmarkConfig) -> Vec<Benc
             eprintln!("Warning: Failed to write benchmark results: {}", e);
         }
 
-        // Write combined results
-        if let Err(e) = io.write_combined(&results, "latest_results.json") {
+        // Write combined results with their provenance, signed if configured
+        let written = match &config.identity_key {
+            Some(path) => MachineIdentity::load_or_create(path)
+                .and_then(|identity| io.write_combined_signed(&results, "latest_results.json", &identity)),
+            None => io.write_combined(&results, "latest_results.json"),
+        };
+        if let Err(e) = written {
             eprintln!("Warning: Failed to write combined results: {}", e);
         }
     }
@@ -166,28 +196,83 @@ pub async fn run_all_benchmarks_with_config(config: BenchmarkConfig) -> Vec<Benc
     results
 }
 
+/// Run `targets`, each under its timeout and the run's isolation
+async fn execute_targets(targets: Vec<BoxedBenchTarget>, config: &BenchmarkConfig) -> Vec<BenchmarkResult> {
+    let targets: Vec<(usize, Arc<dyn BenchTarget>)> = targets.into_iter().map(Arc::from).enumerate().collect();
+    let run = |(slot, target): (usize, Arc<dyn BenchTarget>)| async move {
+        let result = isolation::run_target(
+            target,
+            &config.isolation,
+            slot,
+            config.target_timeout,
+            &config.cancellation,
+        )
+        .await;
+        (slot, result)
+    };
+
+    let mut results = Vec::with_capacity(targets.len());
+    if config.parallel {
+        // Run benchmarks in parallel with limited concurrency, then the
+        // exclusive ones on their own
+        let (exclusive, shared): (Vec<_>, Vec<_>) = targets.into_iter().partition(|(_, t)| t.exclusive());
+        let permits = Arc::new(tokio::sync::Semaphore::new(config.max_parallel.max(1)));
+        let mut runs = tokio::task::JoinSet::new();
+        for (slot, target) in shared {
+            let config = config.clone();
+            let permits = permits.clone();
+            // Spawned tasks don't inherit the run's clock
+            runs.spawn(determinism::scope(determinism::current(), async move {
+                let _permit = permits.acquire_owned().await;
+                let result = isolation::run_target(
+                    target,
+                    &config.isolation,
+                    slot,
+                    config.target_timeout,
+                    &config.cancellation,
+                )
+                .await;
+                (slot, result)
+            }));
+        }
+        while let Some(run) = runs.join_next().await {
+            results.push(run.expect("run_target reports panicking targets as failures"));
+        }
+        for target in exclusive {
+            results.push(run(target).await);
+        }
+        if config.determinism.is_deterministic() {
+            // Keep registry order so seeded runs repeat
+            results.sort_by_key(|(slot, _)| *slot);
+        }
+    } else {
+        // Run benchmarks sequentially
+        for target in targets {
+            results.push(run(target).await);
+        }
+    }
+
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
 /// Run a specific benchmark by target ID
 pub async fn run_benchmark(target_id: &str) -> Option<BenchmarkResult> {
     let targets = adapters::all_targets();
 
     for target in targets {
         if target.id() == target_id {
-            return Some(target.run().await);
+            return Some(determinism::scope(Determinism::from_env(), target.run()).await);
         }
     }
 
     None
 }
 
-/// Get information about all registered benchmark targets
-pub fn list_targets() -> Vec<(&'static str, Option<&'static str>)> {
+/// Get the ID and description of every registered benchmark target
+pub fn list_targets() -> Vec<(String, Option<String>)> {
     adapters::all_targets()
         .into_iter()
-        .map(|t| {
-            // We need to get static references, so we'll use id() directly
-            // For description, we return None as we can't get static refs easily
-            (t.id(), t.description())
-        })
+        .map(|t| (t.id().to_string(), t.description().map(str::to_string)))
         .collect()
 }
 
@@ -208,6 +293,8 @@ mod tests {
             parallel: false,
             max_parallel: 4,
             filter: Some("nlp".to_string()), // Only run NLP benchmarks for speed
+            determinism: Determinism::system(),
+            ..Default::default()
         };
 
         let results = run_all_benchmarks_with_config(config).await;
@@ -218,6 +305,81 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_seeded_runs_are_identical() {
+        let run = || async {
+            let config = BenchmarkConfig {
+                write_results: false,
+                generate_summary: false,
+                parallel: true,
+                filter: Some("nlp".to_string()),
+                determinism: Determinism::seeded(7),
+                ..Default::default()
+            };
+            let results = run_all_benchmarks_with_config(config).await;
+            let summary = determinism::scope(Determinism::seeded(7), async {
+                MarkdownGenerator::new().generate(&results)
+            })
+            .await;
+            (serde_json::to_string(&results).unwrap(), summary)
+        };
+
+        let (first_results, first_summary) = run().await;
+        let (second_results, second_summary) = run().await;
+        assert_eq!(first_results, second_results);
+        assert_eq!(first_summary, second_summary);
+        assert!(first_results.contains("2024-01-01T00:00:00Z"));
+    }
+
+    #[tokio::test]
+    async fn test_hung_target_does_not_stall_parallel_run() {
+        use async_trait::async_trait;
+
+        struct Target {
+            id: &'static str,
+            hangs: bool,
+            exclusive: bool,
+        }
+
+        #[async_trait]
+        impl BenchTarget for Target {
+            fn id(&self) -> &str {
+                self.id
+            }
+
+            fn exclusive(&self) -> bool {
+                self.exclusive
+            }
+
+            async fn run(&self) -> BenchmarkResult {
+                if self.hangs {
+                    std::future::pending::<()>().await;
+                }
+                BenchmarkResult::success(self.id, 1)
+            }
+        }
+
+        let targets: Vec<BoxedBenchTarget> = vec![
+            Box::new(Target { id: "a", hangs: false, exclusive: true }),
+            Box::new(Target { id: "b", hangs: true, exclusive: false }),
+            Box::new(Target { id: "c", hangs: false, exclusive: false }),
+        ];
+        let config = BenchmarkConfig {
+            write_results: false,
+            generate_summary: false,
+            parallel: true,
+            determinism: Determinism::seeded(7),
+            target_timeout: Duration::from_millis(50),
+            ..Default::default()
+        };
+
+        let results = execute_targets(targets, &config).await;
+        let ids: Vec<&str> = results.iter().map(|r| r.target_id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert!(results[0].is_success() && results[2].is_success());
+        assert_eq!(results[1].metrics["timed_out"], true);
+    }
+
     #[tokio::test]
     async fn test_run_single_benchmark() {
         let result = run_benchmark("nlp::intent::simple").await;
@@ -243,5 +405,6 @@ mod tests {
         for (id, _desc) in &targets {
             assert!(!id.is_empty());
         }
+        assert!(targets.iter().any(|(_, description)| description.is_some()));
     }
 }
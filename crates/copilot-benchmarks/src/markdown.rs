@@ -1,10 +1,17 @@
 //! Markdown report generation
 //!
 //! This module generates markdown summary reports from benchmark results.
+//! Results measured in more than one environment (see
+//! [`Environment`](crate::environment::Environment)) are summarized per
+//! environment, and targets measured in several are compared side by side
+//! rather than averaged together.
 
 use crate::result::BenchmarkResult;
 use chrono::{DateTime, Utc};
 
+/// Group of results without a recorded environment
+const UNKNOWN_ENVIRONMENT: &str = "unknown environment";
+
 /// Configuration for markdown report generation
 #[derive(Debug, Clone)]
 pub struct MarkdownConfig {
@@ -61,23 +68,55 @@ impl MarkdownGenerator {
         md.push_str(&format!("# {}\n\n", self.config.title));
 
         if self.config.include_timestamp {
-            let now: DateTime<Utc> = Utc::now();
+            let now: DateTime<Utc> = crate::determinism::now();
             md.push_str(&format!(
                 "**Generated:** {}\n\n",
                 now.format("%Y-%m-%d %H:%M:%S UTC")
             ));
         }
 
-        // Summary statistics
+        let groups = group_by_environment(results);
+
+        // Summary statistics; durations are only summarized within an
+        // environment
         md.push_str("## Summary\n\n");
-        md.push_str(&self.generate_summary(results));
+        match groups.as_slice() {
+            [(environment, _)] => {
+                md.push_str(&self.generate_summary(results));
+                md.push_str(&format!("- **Environment:** {}\n", environment));
+            }
+            _ => {
+                md.push_str(&self.generate_counts(results));
+                if !groups.is_empty() {
+                    md.push_str(&format!(
+                        "- **Environments:** {} (durations are summarized per environment)\n",
+                        groups.len()
+                    ));
+                }
+            }
+        }
         md.push('\n');
 
         // Results table
-        if self.config.use_tables && !results.is_empty() {
+        if self.config.use_tables && groups.len() == 1 {
             md.push_str("## Results\n\n");
             md.push_str(&self.generate_results_table(results));
             md.push('\n');
+        } else if groups.len() > 1 {
+            for (environment, group) in &groups {
+                md.push_str(&format!("## Environment: {}\n\n", environment));
+                md.push_str(&self.generate_durations(group));
+                if self.config.use_tables {
+                    md.push('\n');
+                    md.push_str(&self.generate_results_table(group));
+                }
+                md.push('\n');
+            }
+            if let Some(comparison) = self.generate_comparison(&groups) {
+                md.push_str("## Comparison Across Environments\n\n");
+                md.push_str(&comparison);
+                md.push('\n');
+            }
         }
 
         // Detailed results
@@ -98,22 +137,17 @@ impl MarkdownGenerator {
 
     /// Generate summary statistics
     fn generate_summary(&self, results: &[BenchmarkResult]) -> String {
+        let mut summary = self.generate_counts(results);
+        summary.push_str(&self.generate_durations(results));
+        summary
+    }
+
+    /// Generate pass/fail counts
+    fn generate_counts(&self, results: &[BenchmarkResult]) -> String {
         let total = results.len();
         let successful = results.iter().filter(|r| r.is_success()).count();
         let failed = total - successful;
 
-        let avg_duration: Option<f64> = {
-            let durations: Vec<u64> = results.iter().filter_map(|r| r.duration_ms()).collect();
-            if durations.is_empty() {
-                None
-            } else {
-                Some(durations.iter().sum::<u64>() as f64 / durations.len() as f64)
-            }
-        };
-
-        let min_duration = results.iter().filter_map(|r| r.duration_ms()).min();
-        let max_duration = results.iter().filter_map(|r| r.duration_ms()).max();
-
         let mut summary = String::new();
 
         summary.push_str(&format!("- **Total Benchmarks:** {}\n", total));
@@ -128,6 +162,25 @@ impl MarkdownGenerator {
         ));
         summary.push_str(&format!("- **Failed:** {}\n", failed));
 
+        summary
+    }
+
+    /// Generate duration statistics
+    fn generate_durations(&self, results: &[BenchmarkResult]) -> String {
+        let avg_duration: Option<f64> = {
+            let durations: Vec<u64> = results.iter().filter_map(|r| r.duration_ms()).collect();
+            if durations.is_empty() {
+                None
+            } else {
+                Some(durations.iter().sum::<u64>() as f64 / durations.len() as f64)
+            }
+        };
+
+        let min_duration = results.iter().filter_map(|r| r.duration_ms()).min();
+        let max_duration = results.iter().filter_map(|r| r.duration_ms()).max();
+
+        let mut summary = String::new();
+
         if let Some(avg) = avg_duration {
             summary.push_str(&format!("- **Average Duration:** {:.2} ms\n", avg));
         }
@@ -171,6 +224,69 @@ impl MarkdownGenerator {
         table
     }
 
+    /// Generate a table of the targets measured in several environments,
+    /// one column per environment with the duration relative to the first
+    /// environment that measured it
+    fn generate_comparison(&self, groups: &[(String, Vec<BenchmarkResult>)]) -> Option<String> {
+        let mut targets: Vec<&str> = Vec::new();
+        for (_, group) in groups {
+            for result in group {
+                if !targets.contains(&result.target_id.as_str()) {
+                    targets.push(&result.target_id);
+                }
+            }
+        }
+        let duration = |group: &[BenchmarkResult], target: &str| {
+            group
+                .iter()
+                .find(|r| r.target_id == target && r.is_success())
+                .and_then(|r| r.duration_ms())
+        };
+        targets.retain(|target| groups.iter().filter(|(_, group)| duration(group, target).is_some()).count() > 1);
+        if targets.is_empty() {
+            return None;
+        }
+
+        let mut table = String::new();
+        table.push_str("| Target |");
+        for (index, _) in groups.iter().enumerate() {
+            table.push_str(&format!(" Env {} (ms) |", index + 1));
+        }
+        table.push_str("\n|--------|");
+        table.push_str(&"------------|".repeat(groups.len()));
+        table.push('\n');
+
+        for target in targets {
+            table.push_str(&format!("| {} |", target));
+            let mut baseline = None;
+            for (_, group) in groups {
+                match duration(group, target) {
+                    Some(ms) => {
+                        let base = *baseline.get_or_insert(ms);
+                        if base > 0 && ms != base {
+                            table.push_str(&format!(" {} ({:.2}×) |", ms, ms as f64 / base as f64));
+                        } else {
+                            table.push_str(&format!(" {} |", ms));
+                        }
+                    }
+                    None => table.push_str(" - |"),
+                }
+            }
+            table.push('\n');
+        }
+
+        table.push('\n');
+        for (index, (environment, _)) in groups.iter().enumerate() {
+            table.push_str(&format!("- **Env {}:** {}\n", index + 1, environment));
+        }
+        table.push_str(
+            "\n*Timings from different environments reflect their CPU and memory as much as the code; \
+             compare them with the environments in mind.*\n",
+        );
+
+        Some(table)
+    }
+
     /// Generate detailed result for a single benchmark
     fn generate_detail(&self, result: &BenchmarkResult) -> String {
         let mut detail = String::new();
@@ -218,6 +334,24 @@ impl MarkdownGenerator {
     }
 }
 
+/// Results grouped by the label of their environment, in order of first
+/// appearance
+fn group_by_environment(results: &[BenchmarkResult]) -> Vec<(String, Vec<BenchmarkResult>)> {
+    let mut groups: Vec<(String, Vec<BenchmarkResult>)> = Vec::new();
+    for result in results {
+        let label = result
+            .environment
+            .as_ref()
+            .map(|environment| environment.label())
+            .unwrap_or_else(|| UNKNOWN_ENVIRONMENT.to_string());
+        match groups.iter_mut().find(|(existing, _)| *existing == label) {
+            Some((_, group)) => group.push(result.clone()),
+            None => groups.push((label, vec![result.clone()])),
+        }
+    }
+    groups
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -265,4 +399,31 @@ mod tests {
         assert!(md.contains("# Custom Report"));
         assert!(!md.contains("## Details"));
     }
+
+    #[test]
+    fn test_results_are_compared_across_environments() {
+        let gen = MarkdownGenerator::new();
+        let laptop = BenchmarkResult::success("test::a", 100);
+        let mut container = BenchmarkResult::success("test::a", 250);
+        if let Some(environment) = container.environment.as_mut() {
+            environment.cpu_count += 1;
+        }
+        let mut legacy = BenchmarkResult::success("test::b", 10);
+        legacy.environment = None;
+
+        let md = gen.generate(&[laptop.clone(), container, legacy]);
+        assert!(md.contains("- **Environments:** 3"));
+        assert!(!md.contains("**Average Duration:** 120"));
+        assert!(md.contains(&format!("## Environment: {}", laptop.environment.unwrap().label())));
+        assert!(md.contains("## Environment: unknown environment"));
+        assert!(md.contains("## Comparison Across Environments"));
+        assert!(md.contains("| test::a | 100 | 250 (2.50×) | - |"));
+        assert!(!md.contains("| test::b |"));
+
+        // A single environment keeps the plain summary
+        let md = gen.generate(&[BenchmarkResult::success("test::a", 100)]);
+        assert!(md.contains("- **Environment:** "));
+        assert!(md.contains("## Results"));
+        assert!(!md.contains("## Comparison Across Environments"));
+    }
 }
@@ -0,0 +1,303 @@
+//! Provenance and signing of benchmark results
+//!
+//! The combined results file embeds the [`Provenance`] of the run (git
+//! commit and whether the tree was dirty, rustc version, CPU model and a
+//! hash of `Cargo.lock`) and, when the run has a [`MachineIdentity`], an
+//! Ed25519 signature over results and provenance. Results published to
+//! benchmark-exchange can then be checked with [`SignedResults::verify`]
+//! and reproduced on the same commit, toolchain and dependencies.
+
+use crate::io::{IoError, IoResult};
+use crate::result::BenchmarkResult;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Environment variable naming the machine identity key file
+pub const IDENTITY_KEY_ENV: &str = "COPILOT_BENCH_IDENTITY_KEY";
+
+/// Signature algorithm of signed results
+pub const SIGNATURE_ALGORITHM: &str = "ed25519";
+
+/// Where and with what a benchmark run was built and run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Provenance {
+    /// Commit the run was built from
+    pub git_sha: Option<String>,
+    /// Whether the working tree had uncommitted changes
+    pub git_dirty: Option<bool>,
+    /// Output of `rustc --version`
+    pub rustc_version: Option<String>,
+    pub cpu_model: Option<String>,
+    pub cpu_count: usize,
+    pub os: String,
+    pub arch: String,
+    /// SHA-256 of `Cargo.lock`, pinning the dependency versions
+    pub lockfile_sha256: Option<String>,
+    pub collected_at: DateTime<Utc>,
+}
+
+impl Provenance {
+    /// Provenance of the working directory's checkout
+    pub fn collect() -> Self {
+        Self::collect_in(Path::new("."))
+    }
+
+    /// Provenance of the checkout containing `dir`; facts that can't be
+    /// determined are left unset
+    pub fn collect_in(dir: &Path) -> Self {
+        let git_sha = command_output(dir, "git", &["rev-parse", "HEAD"]);
+        let git_dirty = git_sha
+            .as_ref()
+            .and_then(|_| command_output(dir, "git", &["status", "--porcelain"]))
+            .map(|status| !status.is_empty());
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+
+        Self {
+            git_sha,
+            git_dirty,
+            rustc_version: command_output(dir, &rustc, &["--version"]),
+            cpu_model: cpu_model(),
+            cpu_count: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            lockfile_sha256: find_lockfile(dir)
+                .and_then(|path| std::fs::read(path).ok())
+                .map(|lockfile| format!("{:x}", Sha256::digest(&lockfile))),
+            collected_at: crate::determinism::now(),
+        }
+    }
+}
+
+/// Trimmed stdout of a command run in `dir`, if it succeeded
+fn command_output(dir: &Path, program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).current_dir(dir).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub(crate) fn cpu_model() -> Option<String> {
+    if cfg!(target_os = "linux") {
+        std::fs::read_to_string("/proc/cpuinfo").ok()?.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "model name").then(|| value.trim().to_string())
+        })
+    } else if cfg!(target_os = "macos") {
+        command_output(Path::new("."), "sysctl", &["-n", "machdep.cpu.brand_string"])
+    } else {
+        None
+    }
+}
+
+/// `Cargo.lock` in `dir` or the nearest directory above it
+fn find_lockfile(dir: &Path) -> Option<PathBuf> {
+    let dir = dir.canonicalize().ok()?;
+    dir.ancestors().map(|d| d.join("Cargo.lock")).find(|path| path.is_file())
+}
+
+/// Signature of a results file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ResultSignature {
+    pub algorithm: String,
+    /// Fingerprint of the signing key
+    pub key_id: String,
+    /// Base64 public key that verifies the signature
+    pub public_key: String,
+    /// Base64 signature over the results and provenance
+    pub value: String,
+}
+
+/// A combined results file: the results of a run, its provenance and,
+/// if signed, the signature over both
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SignedResults {
+    pub provenance: Provenance,
+    pub results: Vec<BenchmarkResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ResultSignature>,
+}
+
+impl SignedResults {
+    /// Unsigned results with their provenance
+    pub fn new(results: Vec<BenchmarkResult>, provenance: Provenance) -> Self {
+        Self {
+            provenance,
+            results,
+            signature: None,
+        }
+    }
+
+    /// The bytes the signature covers
+    fn signed_bytes(&self) -> IoResult<Vec<u8>> {
+        Ok(serde_json::to_vec(&serde_json::json!({
+            "provenance": self.provenance,
+            "results": self.results,
+        }))?)
+    }
+
+    /// Check the signature against the public key it names
+    pub fn verify(&self) -> IoResult<&ResultSignature> {
+        let signature = self
+            .signature
+            .as_ref()
+            .ok_or_else(|| IoError::Signature("Results are not signed".to_string()))?;
+        if signature.algorithm != SIGNATURE_ALGORITHM {
+            return Err(IoError::Signature(format!("Unsupported algorithm {}", signature.algorithm)));
+        }
+        let public_key = BASE64
+            .decode(&signature.public_key)
+            .map_err(|e| IoError::Signature(format!("Invalid public key: {}", e)))?;
+        if key_id(&public_key) != signature.key_id {
+            return Err(IoError::Signature("Key ID does not match the public key".to_string()));
+        }
+        let value = BASE64
+            .decode(&signature.value)
+            .map_err(|e| IoError::Signature(format!("Invalid signature: {}", e)))?;
+        UnparsedPublicKey::new(&ED25519, &public_key)
+            .verify(&self.signed_bytes()?, &value)
+            .map_err(|_| IoError::Signature("Signature does not match the results".to_string()))?;
+        Ok(signature)
+    }
+
+    /// Check the signature and that it was made by the key `trusted_key_id`
+    pub fn verify_signed_by(&self, trusted_key_id: &str) -> IoResult<&ResultSignature> {
+        let signature = self.verify()?;
+        if signature.key_id != trusted_key_id {
+            return Err(IoError::Signature(format!("Signed by untrusted key {}", signature.key_id)));
+        }
+        Ok(signature)
+    }
+}
+
+/// The key a machine signs its benchmark results with
+pub struct MachineIdentity {
+    key_pair: Ed25519KeyPair,
+}
+
+impl std::fmt::Debug for MachineIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MachineIdentity").field("key_id", &self.key_id()).finish()
+    }
+}
+
+impl MachineIdentity {
+    /// A new identity and its PKCS#8 encoding
+    pub fn generate() -> IoResult<(Self, Vec<u8>)> {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| IoError::Signature("Failed to generate identity key".to_string()))?;
+        let identity = Self::from_pkcs8(pkcs8.as_ref())?;
+        Ok((identity, pkcs8.as_ref().to_vec()))
+    }
+
+    pub fn from_pkcs8(pkcs8: &[u8]) -> IoResult<Self> {
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8)
+            .map_err(|e| IoError::Signature(format!("Invalid identity key: {}", e)))?;
+        Ok(Self { key_pair })
+    }
+
+    /// The key file `COPILOT_BENCH_IDENTITY_KEY` names, or else
+    /// `~/.copilot/benchmark_identity.pk8`
+    pub fn default_path() -> PathBuf {
+        if let Ok(path) = std::env::var(IDENTITY_KEY_ENV) {
+            return PathBuf::from(path);
+        }
+        let home = std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        home.join(".copilot").join("benchmark_identity.pk8")
+    }
+
+    /// The identity stored at `path`, generating and storing one (readable
+    /// only by its owner) if there is none yet
+    pub fn load_or_create(path: &Path) -> IoResult<Self> {
+        if path.exists() {
+            return Self::from_pkcs8(&std::fs::read(path)?);
+        }
+        let (identity, pkcs8) = Self::generate()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        std::io::Write::write_all(&mut options.open(path)?, &pkcs8)?;
+        Ok(identity)
+    }
+
+    /// Fingerprint of the public key
+    pub fn key_id(&self) -> String {
+        key_id(self.key_pair.public_key().as_ref())
+    }
+
+    /// Base64 public key
+    pub fn public_key(&self) -> String {
+        BASE64.encode(self.key_pair.public_key().as_ref())
+    }
+
+    /// Sign `results`, replacing any previous signature
+    pub fn sign(&self, mut results: SignedResults) -> IoResult<SignedResults> {
+        let signature = self.key_pair.sign(&results.signed_bytes()?);
+        results.signature = Some(ResultSignature {
+            algorithm: SIGNATURE_ALGORITHM.to_string(),
+            key_id: self.key_id(),
+            public_key: self.public_key(),
+            value: BASE64.encode(signature.as_ref()),
+        });
+        Ok(results)
+    }
+}
+
+/// First 16 hex digits of the SHA-256 of a public key
+fn key_id(public_key: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(public_key))[..16].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_results_verify_until_tampered() {
+        let (identity, _) = MachineIdentity::generate().unwrap();
+        let provenance = Provenance::collect();
+        assert_eq!(provenance.os, std::env::consts::OS);
+
+        let results = vec![BenchmarkResult::success("test::target", 100)];
+        let signed = identity.sign(SignedResults::new(results, provenance)).unwrap();
+
+        // Signatures survive the trip through the results file
+        let signed: SignedResults = serde_json::from_str(&serde_json::to_string_pretty(&signed).unwrap()).unwrap();
+        assert!(signed.verify().is_ok());
+        assert!(signed.verify_signed_by(&identity.key_id()).is_ok());
+        assert!(signed.verify_signed_by("0000000000000000").is_err());
+
+        let mut tampered = signed.clone();
+        tampered.results[0].metrics["duration_ms"] = serde_json::json!(1);
+        assert!(tampered.verify().is_err());
+        let mut tampered = signed.clone();
+        tampered.provenance.git_dirty = Some(!tampered.provenance.git_dirty.unwrap_or(false));
+        assert!(tampered.verify().is_err());
+        assert!(SignedResults::new(vec![], Provenance::collect()).verify().is_err());
+    }
+
+    #[test]
+    fn test_identity_is_created_once() {
+        let path = std::env::temp_dir()
+            .join(format!("bench_identity_{}", uuid::Uuid::new_v4()))
+            .join("identity.pk8");
+        let created = MachineIdentity::load_or_create(&path).unwrap();
+        let loaded = MachineIdentity::load_or_create(&path).unwrap();
+        assert_eq!(created.key_id(), loaded.key_id());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -3,14 +3,19 @@
 //! This module defines the standardized BenchmarkResult struct used across
 //! all benchmark targets in the LLM-CoPilot-Agent repository.
 
+use crate::environment::Environment;
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-/// Canonical BenchmarkResult struct with exactly the required fields:
+/// Canonical BenchmarkResult struct with the required fields:
 /// - target_id: String - Identifier for the benchmark target
 /// - metrics: serde_json::Value - Flexible JSON metrics payload
 /// - timestamp: chrono::DateTime<chrono::Utc> - When the benchmark was run
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// and the optional environment it was measured in, absent from results
+/// written before environments were recorded
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct BenchmarkResult {
     /// Unique identifier for the benchmark target
     pub target_id: String,
@@ -28,15 +33,20 @@ pub struct BenchmarkResult {
 
     /// UTC timestamp when the benchmark was executed
     pub timestamp: DateTime<Utc>,
+
+    /// Machine and container limits the benchmark ran under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Environment>,
 }
 
 impl BenchmarkResult {
-    /// Create a new BenchmarkResult with the current timestamp
+    /// Create a new BenchmarkResult stamped with the run's clock
     pub fn new(target_id: impl Into<String>, metrics: serde_json::Value) -> Self {
         Self {
             target_id: target_id.into(),
             metrics,
-            timestamp: Utc::now(),
+            timestamp: crate::determinism::now(),
+            environment: Some(Environment::current()),
         }
     }
 
@@ -50,6 +60,7 @@ impl BenchmarkResult {
             target_id: target_id.into(),
             metrics,
             timestamp,
+            environment: Some(Environment::current()),
         }
     }
 
@@ -75,6 +86,30 @@ impl BenchmarkResult {
         )
     }
 
+    /// Create a failure result for a target stopped at its timeout
+    pub fn timed_out(target_id: impl Into<String>, timeout: std::time::Duration) -> Self {
+        Self::new(
+            target_id,
+            serde_json::json!({
+                "success": false,
+                "error": format!("Timed out after {}ms", timeout.as_millis()),
+                "timed_out": true
+            }),
+        )
+    }
+
+    /// Create a failure result for a target stopped by cancelling the run
+    pub fn cancelled(target_id: impl Into<String>) -> Self {
+        Self::new(
+            target_id,
+            serde_json::json!({
+                "success": false,
+                "error": "Cancelled",
+                "cancelled": true
+            }),
+        )
+    }
+
     /// Check if the benchmark was successful
     pub fn is_success(&self) -> bool {
         self.metrics
@@ -135,5 +170,13 @@ mod tests {
 
         assert_eq!(deserialized.target_id, result.target_id);
         assert_eq!(deserialized.duration_ms(), result.duration_ms());
+        assert_eq!(deserialized.environment, Some(Environment::current()));
+
+        // Results written before environments were recorded still load
+        let legacy: BenchmarkResult = serde_json::from_str(
+            r#"{"target_id": "test_target", "metrics": {}, "timestamp": "2024-01-01T00:00:00Z"}"#,
+        )
+        .unwrap();
+        assert!(legacy.environment.is_none());
     }
 }
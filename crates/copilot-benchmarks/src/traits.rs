@@ -5,6 +5,7 @@
 
 use async_trait::async_trait;
 use crate::result::BenchmarkResult;
+use std::time::Duration;
 
 /// Canonical BenchTarget trait for benchmark targets
 ///
@@ -40,6 +41,19 @@ pub trait BenchTarget: Send + Sync {
     fn expected_duration_ms(&self) -> Option<(u64, u64)> {
         None
     }
+
+    /// Optional: Returns how long the target may run before it is stopped,
+    /// overriding the run's `target_timeout`
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Optional: Whether the target must run alone, e.g. because it measures
+    /// latency or throughput that other targets would skew; parallel runs
+    /// run exclusive targets one at a time after the others
+    fn exclusive(&self) -> bool {
+        false
+    }
 }
 
 /// Box type alias for benchmark targets
@@ -0,0 +1,105 @@
+//! Retrieval latency under concurrent writes
+//!
+//! Compares retrieval latency percentiles for a single-lock store (one shard)
+//! against sharded stores while writer threads continuously insert and remove
+//! items, mimicking retrieval during ingestion. "Point" is a lookup by ID and
+//! "scan" is the full-store snapshot the context engine takes per query.
+//!
+//! Run with: cargo bench -p copilot-context --bench memory_store_contention
+
+use copilot_context::{MemoryItem, MemoryMetadata, MemoryTier, ShardedMemoryStore};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const PREFILL_ITEMS: usize = 5_000;
+const WRITER_THREADS: usize = 4;
+const POINT_READS: usize = 100_000;
+const SCANS: usize = 500;
+
+fn item() -> MemoryItem {
+    MemoryItem::new(
+        "benchmark content for contention testing".to_string(),
+        MemoryMetadata::new("document", "benchmark"),
+        0.5,
+        8,
+    )
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let idx = ((sorted.len() as f64 * p).ceil() as usize).saturating_sub(1);
+    sorted[idx.min(sorted.len() - 1)]
+}
+
+fn run(shards: usize) {
+    let store = Arc::new(ShardedMemoryStore::with_shards(MemoryTier::ShortTerm, shards));
+    let ids: Vec<_> = (0..PREFILL_ITEMS)
+        .map(|_| {
+            let item = item();
+            let id = item.metadata.id;
+            store.insert(item);
+            id
+        })
+        .collect();
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let writers: Vec<_> = (0..WRITER_THREADS)
+        .map(|_| {
+            let store = store.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut writes = 0u64;
+                while !stop.load(Ordering::Relaxed) {
+                    // Insert then remove so the store size stays stable
+                    let item = item();
+                    let id = item.metadata.id;
+                    store.insert(item);
+                    store.take(&id);
+                    writes += 2;
+                }
+                writes
+            })
+        })
+        .collect();
+
+    let mut point = Vec::with_capacity(POINT_READS);
+    for i in 0..POINT_READS {
+        // Stride through the prefilled IDs with a prime step
+        let id = &ids[(i * 7_919) % ids.len()];
+        let start = Instant::now();
+        std::hint::black_box(store.get(id));
+        point.push(start.elapsed());
+    }
+
+    let mut scan = Vec::with_capacity(SCANS);
+    for _ in 0..SCANS {
+        let start = Instant::now();
+        std::hint::black_box(store.items());
+        scan.push(start.elapsed());
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    let writes: u64 = writers.into_iter().map(|w| w.join().unwrap()).sum();
+
+    point.sort();
+    scan.sort();
+    println!(
+        "{:>6} | {:>10.2?} | {:>10.2?} | {:>10.2?} | {:>10.2?} | {:>10}",
+        shards,
+        percentile(&point, 0.50),
+        percentile(&point, 0.99),
+        percentile(&scan, 0.50),
+        percentile(&scan, 0.99),
+        writes,
+    );
+}
+
+fn main() {
+    println!(
+        "{:>6} | {:>10} | {:>10} | {:>10} | {:>10} | {:>10}",
+        "shards", "point p50", "point p99", "scan p50", "scan p99", "writes"
+    );
+    for shards in [1, 4, 16, 64] {
+        run(shards);
+    }
+}
@@ -0,0 +1,343 @@
+//! Access control on context
+//!
+//! [`AccessFence`] wraps a context engine so confidential items never reach
+//! callers who may not read them. An item may carry an [`Acl`] in its
+//! [`ACL_KEY`] metadata field, and sources may be given one by prefix (the
+//! longest matching prefix applies); an item is admitted only if the
+//! current [`Principal`] passes both. Items with neither are open to
+//! everyone.
+//!
+//! Every item kept from a caller is an [`AccessDenial`], passed to the
+//! fence's [`AccessAuditor`] if one is set. Calls made outside a principal
+//! scope (maintenance, tests) pass through unfenced.
+
+use async_trait::async_trait;
+use copilot_core::{Acl, Principal, ACL_KEY};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    engine::{CompressionStats, ContextEngine, EngineStats, MaintenanceReport},
+    filter::ContextFilter,
+    memory::{MemoryItem, MemoryMetadata, MemoryTier},
+    retrieval::RetrievalResult,
+    Result,
+};
+
+/// ACL an item was stored with, if any; a malformed ACL admits no one
+pub fn item_acl(metadata: &MemoryMetadata) -> Option<Acl> {
+    let value = metadata.custom.get(ACL_KEY)?;
+    Some(serde_json::from_value(value.clone()).unwrap_or_else(|e| {
+        warn!("Item {} has a malformed ACL: {}", metadata.id, e);
+        Acl::new()
+    }))
+}
+
+/// What a caller was kept from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessOperation {
+    Retrieve,
+    List,
+}
+
+impl AccessOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccessOperation::Retrieve => "retrieve",
+            AccessOperation::List => "list",
+        }
+    }
+}
+
+/// A context item kept from a caller by its ACL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessDenial {
+    pub principal: Principal,
+    pub operation: AccessOperation,
+    pub item_id: Uuid,
+    pub source: String,
+}
+
+/// Records denials, e.g. to an audit log
+#[async_trait]
+pub trait AccessAuditor: Send + Sync {
+    async fn denied(&self, denial: &AccessDenial);
+}
+
+/// Context engine that only returns items the caller may read
+pub struct AccessFence {
+    inner: Arc<dyn ContextEngine>,
+    source_acls: RwLock<BTreeMap<String, Acl>>,
+    auditor: RwLock<Option<Arc<dyn AccessAuditor>>>,
+}
+
+impl AccessFence {
+    pub fn new(inner: Arc<dyn ContextEngine>) -> Self {
+        Self {
+            inner,
+            source_acls: RwLock::new(BTreeMap::new()),
+            auditor: RwLock::new(None),
+        }
+    }
+
+    /// Restrict items whose source starts with `prefix` to `acl`
+    pub fn with_source_acl(self, prefix: impl Into<String>, acl: Acl) -> Self {
+        self.set_source_acl(prefix, acl);
+        self
+    }
+
+    pub fn set_source_acl(&self, prefix: impl Into<String>, acl: Acl) {
+        self.source_acls.write().insert(prefix.into(), acl);
+    }
+
+    /// Lift the ACL of a source prefix, returning whether it had one
+    pub fn remove_source_acl(&self, prefix: &str) -> bool {
+        self.source_acls.write().remove(prefix).is_some()
+    }
+
+    /// ACLs by source prefix
+    pub fn source_acls(&self) -> BTreeMap<String, Acl> {
+        self.source_acls.read().clone()
+    }
+
+    /// Send denials to `auditor`
+    pub fn set_auditor(&self, auditor: Arc<dyn AccessAuditor>) {
+        *self.auditor.write() = Some(auditor);
+    }
+
+    /// Whether `principal` may read `item`
+    pub fn permits(&self, principal: &Principal, item: &MemoryItem) -> bool {
+        let source_acls = self.source_acls.read();
+        let source_acl = source_acls
+            .iter()
+            .filter(|(prefix, _)| item.metadata.source.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, acl)| acl);
+        source_acl.map_or(true, |acl| acl.permits(principal))
+            && item_acl(&item.metadata).map_or(true, |acl| acl.permits(principal))
+    }
+
+    fn deny(principal: &Principal, operation: AccessOperation, item: &MemoryItem) -> AccessDenial {
+        AccessDenial {
+            principal: principal.clone(),
+            operation,
+            item_id: item.metadata.id,
+            source: item.metadata.source.clone(),
+        }
+    }
+
+    async fn audit(&self, denials: Vec<AccessDenial>) {
+        if denials.is_empty() {
+            return;
+        }
+        warn!(
+            "Kept {} context items from {}",
+            denials.len(),
+            denials[0].principal.user_id
+        );
+        let auditor = self.auditor.read().clone();
+        if let Some(auditor) = auditor {
+            for denial in &denials {
+                auditor.denied(denial).await;
+            }
+        }
+    }
+
+    async fn fence(&self, mut result: RetrievalResult) -> RetrievalResult {
+        let Some(principal) = Principal::current() else {
+            return result;
+        };
+        let mut denials = Vec::new();
+        result.selected.retain(|scored| {
+            let permitted = self.permits(&principal, &scored.item);
+            if !permitted {
+                denials.push(Self::deny(&principal, AccessOperation::Retrieve, &scored.item));
+            }
+            permitted
+        });
+        // Rejected items never reached the caller, so they are dropped
+        // without counting as denials
+        result.rejected.retain(|scored| self.permits(&principal, &scored.item));
+        let kept: HashSet<Uuid> = result
+            .selected
+            .iter()
+            .chain(&result.rejected)
+            .map(|scored| scored.item.metadata.id)
+            .collect();
+        if kept.len() < result.candidate_ids.len() {
+            result.candidate_ids.retain(|id| kept.contains(id));
+            result.total_tokens = result.selected.iter().map(|s| s.item.token_count).sum();
+        }
+        self.audit(denials).await;
+        result
+    }
+}
+
+#[async_trait]
+impl ContextEngine for AccessFence {
+    async fn store(&self, content: String, metadata: MemoryMetadata, importance: f64) -> Result<Uuid> {
+        self.inner.store(content, metadata, importance).await
+    }
+
+    async fn retrieve(&self, query: &str) -> Result<RetrievalResult> {
+        let result = self.inner.retrieve(query).await?;
+        Ok(self.fence(result).await)
+    }
+
+    async fn retrieve_filtered(&self, query: &str, filter: &ContextFilter) -> Result<RetrievalResult> {
+        let result = self.inner.retrieve_filtered(query, filter).await?;
+        Ok(self.fence(result).await)
+    }
+
+    async fn compress(&self) -> Result<CompressionStats> {
+        self.inner.compress().await
+    }
+
+    async fn stats(&self) -> Result<EngineStats> {
+        self.inner.stats().await
+    }
+
+    async fn promote(&self, id: &Uuid, tier: MemoryTier) -> Result<()> {
+        self.inner.promote(id, tier).await
+    }
+
+    async fn demote(&self, id: &Uuid, tier: MemoryTier) -> Result<()> {
+        self.inner.demote(id, tier).await
+    }
+
+    async fn remove(&self, id: &Uuid) -> Result<()> {
+        self.inner.remove(id).await
+    }
+
+    async fn list_matching(&self, filter: &ContextFilter) -> Result<Vec<MemoryItem>> {
+        let mut items = self.inner.list_matching(filter).await?;
+        if let Some(principal) = Principal::current() {
+            let mut denials = Vec::new();
+            items.retain(|item| {
+                let permitted = self.permits(&principal, item);
+                if !permitted {
+                    denials.push(Self::deny(&principal, AccessOperation::List, item));
+                }
+                permitted
+            });
+            self.audit(denials).await;
+        }
+        Ok(items)
+    }
+
+    async fn retag(&self, id: &Uuid, add: &[String], remove: &[String]) -> Result<()> {
+        self.inner.retag(id, add, remove).await
+    }
+
+    async fn reindex(&self, id: &Uuid) -> Result<()> {
+        self.inner.reindex(id).await
+    }
+
+    fn source_weights(&self) -> BTreeMap<String, f64> {
+        self.inner.source_weights()
+    }
+
+    fn set_source_weight(&self, prefix: &str, weight: f64) -> Result<()> {
+        self.inner.set_source_weight(prefix, weight)
+    }
+
+    fn remove_source_weight(&self, prefix: &str) -> bool {
+        self.inner.remove_source_weight(prefix)
+    }
+
+    async fn clear(&self) -> Result<()> {
+        self.inner.clear().await
+    }
+
+    async fn maintenance(&self) -> Result<MaintenanceReport> {
+        self.inner.maintenance().await
+    }
+
+    async fn warm_up(&self, filter: &ContextFilter) -> Result<usize> {
+        self.inner.warm_up(filter).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{ContextEngineConfig, ContextEngineImpl};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingAuditor(Mutex<Vec<AccessDenial>>);
+
+    #[async_trait]
+    impl AccessAuditor for RecordingAuditor {
+        async fn denied(&self, denial: &AccessDenial) {
+            self.0.lock().unwrap().push(denial.clone());
+        }
+    }
+
+    fn with_acl(source: &str, acl: &Acl) -> MemoryMetadata {
+        let mut metadata = MemoryMetadata::new("doc", source);
+        metadata
+            .custom
+            .insert(ACL_KEY.to_string(), serde_json::to_value(acl).unwrap());
+        metadata
+    }
+
+    #[tokio::test]
+    async fn test_callers_only_retrieve_items_they_may_read() {
+        let engine: Arc<dyn ContextEngine> = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let fence = AccessFence::new(engine).with_source_acl("hr/", Acl::new().with_role("admin"));
+        let auditor = Arc::new(RecordingAuditor::default());
+        fence.set_auditor(auditor.clone());
+
+        let content = |what: &str| format!("quarterly salary review process {}", what);
+        fence
+            .store(content("overview"), MemoryMetadata::new("doc", "wiki/salaries.md"), 0.5)
+            .await
+            .unwrap();
+        fence
+            .store(content("budget"), with_acl("finance/budget.md", &Acl::new().with_group("finance")), 0.5)
+            .await
+            .unwrap();
+        fence
+            .store(content("bands"), MemoryMetadata::new("doc", "hr/bands.md"), 0.5)
+            .await
+            .unwrap();
+
+        let sources = |result: RetrievalResult| {
+            let mut sources: Vec<String> = result.selected.iter().map(|s| s.item.metadata.source.clone()).collect();
+            sources.sort();
+            sources
+        };
+
+        let analyst = Principal::new("bob").with_roles(["user"]).with_groups(["finance"]);
+        let result = analyst.clone().scope(fence.retrieve("salary review")).await.unwrap();
+        assert_eq!(result.total_tokens, result.selected.iter().map(|s| s.item.token_count).sum::<usize>());
+        assert_eq!(sources(result), vec!["finance/budget.md", "wiki/salaries.md"]);
+
+        let admin = Principal::new("alice").with_roles(["admin"]);
+        let result = admin.scope(fence.retrieve("salary review")).await.unwrap();
+        assert_eq!(sources(result), vec!["hr/bands.md", "wiki/salaries.md"]);
+
+        {
+            let denials = auditor.0.lock().unwrap();
+            assert_eq!(denials.len(), 2);
+            assert_eq!(denials[0].principal.user_id, "bob");
+            assert_eq!(denials[0].source, "hr/bands.md");
+            assert_eq!(denials[1].source, "finance/budget.md");
+        }
+
+        // Listing is fenced too, and unscoped calls see everything
+        let listed = analyst.scope(fence.list_matching(&ContextFilter::default())).await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(auditor.0.lock().unwrap().last().unwrap().operation, AccessOperation::List);
+        assert_eq!(fence.retrieve("salary review").await.unwrap().selected.len(), 3);
+
+        assert!(fence.remove_source_acl("hr/"));
+        assert!(fence.source_acls().is_empty());
+    }
+}
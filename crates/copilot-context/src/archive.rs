@@ -0,0 +1,321 @@
+//! Archive tier backed by object storage
+//!
+//! Items nobody has retrieved for a long time still cost memory in the
+//! long-term tier. When a [`ContextArchive`] is set on the engine,
+//! maintenance moves items inactive for longer than
+//! [`ArchiveConfig::inactive_after`] to [`MemoryTier::Archive`](crate::MemoryTier):
+//! their content goes to an [`ArchiveStore`], usually object storage, and
+//! only their metadata and a sketch of their terms stay in memory. The
+//! engine matches items on their terms rather than on embeddings, so the
+//! sketch is what lets retrieval still find archived items.
+//!
+//! A retrieval that selects an archived item returns it with the
+//! [`CONTENT_LOADING`] placeholder as its content and rehydrates it in the
+//! background, back into short-term memory, so the next retrieval has the
+//! real content. Callers that know what they will need, e.g. before an
+//! incident review, warm items up ahead of time with
+//! [`ContextEngine::warm_up`](crate::ContextEngine::warm_up).
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{ContextError, Result};
+
+/// Content of archived items until they are rehydrated
+pub const CONTENT_LOADING: &str = "[content loading from archive]";
+
+/// Where the content of archived items is kept
+#[async_trait]
+pub trait ArchiveStore: Send + Sync {
+    async fn put(&self, key: &str, content: Vec<u8>) -> Result<()>;
+
+    /// The content stored under `key`, if any
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    async fn delete(&self, key: &str) -> Result<()>;
+}
+
+/// Archive store keeping content in memory, for tests and development
+#[derive(Default)]
+pub struct InMemoryArchiveStore {
+    objects: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl InMemoryArchiveStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.lock().is_empty()
+    }
+}
+
+#[async_trait]
+impl ArchiveStore for InMemoryArchiveStore {
+    async fn put(&self, key: &str, content: Vec<u8>) -> Result<()> {
+        self.objects.lock().insert(key.to_string(), content);
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.objects.lock().get(key).cloned())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.objects.lock().remove(key);
+        Ok(())
+    }
+}
+
+/// When items are archived and where their content goes
+#[derive(Debug, Clone)]
+pub struct ArchiveConfig {
+    /// How long an item goes without being accessed before it is archived
+    pub inactive_after: Duration,
+    /// Prefix of the keys archived content is stored under
+    pub key_prefix: String,
+    /// Most distinct terms kept in memory per archived item
+    pub max_terms: usize,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            inactive_after: Duration::from_secs(30 * 24 * 60 * 60),
+            key_prefix: "context-archive/".to_string(),
+            max_terms: 64,
+        }
+    }
+}
+
+/// How much has been archived and rehydrated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveStats {
+    /// Items moved to the archive
+    pub archived: u64,
+    /// Items brought back from the archive
+    pub rehydrated: u64,
+    /// Rehydrations that failed, e.g. because the store was unreachable
+    pub rehydration_failures: u64,
+    /// Retrievals that returned an archived item's placeholder
+    pub placeholder_hits: u64,
+}
+
+impl ArchiveStats {
+    /// Render the counters in the Prometheus text exposition format
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        let metrics = [
+            ("archived_total", "Context items moved to the archive", self.archived),
+            ("rehydrated_total", "Context items brought back from the archive", self.rehydrated),
+            (
+                "rehydration_failures_total",
+                "Rehydrations of archived context items that failed",
+                self.rehydration_failures,
+            ),
+            (
+                "placeholder_hits_total",
+                "Retrievals that returned an archived item still loading",
+                self.placeholder_hits,
+            ),
+        ];
+        for (name, help, value) in metrics {
+            let _ = writeln!(out, "# HELP {prefix}_context_archive_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_context_archive_{name} counter");
+            let _ = writeln!(out, "{prefix}_context_archive_{name} {value}");
+        }
+        out
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    archived: AtomicU64,
+    rehydrated: AtomicU64,
+    rehydration_failures: AtomicU64,
+    placeholder_hits: AtomicU64,
+}
+
+/// The archive tier of a context engine
+///
+/// The store may be attached after the engine is built, e.g. once the
+/// server has connected to object storage; until then nothing is archived.
+pub struct ContextArchive {
+    config: ArchiveConfig,
+    store: OnceLock<Arc<dyn ArchiveStore>>,
+    /// Items being rehydrated, so concurrent hits fetch them once
+    rehydrating: Mutex<HashSet<Uuid>>,
+    counters: Counters,
+}
+
+impl ContextArchive {
+    /// An archive without a store yet
+    pub fn new(config: ArchiveConfig) -> Self {
+        Self {
+            config,
+            store: OnceLock::new(),
+            rehydrating: Mutex::new(HashSet::new()),
+            counters: Counters::default(),
+        }
+    }
+
+    /// An archive keeping content in `store`
+    pub fn with_store(config: ArchiveConfig, store: Arc<dyn ArchiveStore>) -> Self {
+        let archive = Self::new(config);
+        archive.attach(store);
+        archive
+    }
+
+    /// Keep archived content in `store`; false if a store was attached
+    /// already
+    pub fn attach(&self, store: Arc<dyn ArchiveStore>) -> bool {
+        self.store.set(store).is_ok()
+    }
+
+    /// Whether a store is attached, i.e. items can be archived
+    pub fn is_enabled(&self) -> bool {
+        self.store.get().is_some()
+    }
+
+    pub fn config(&self) -> &ArchiveConfig {
+        &self.config
+    }
+
+    /// Key the content of item `id` is stored under
+    pub fn key(&self, id: &Uuid) -> String {
+        format!("{}{}", self.config.key_prefix, id)
+    }
+
+    fn store(&self) -> Result<&Arc<dyn ArchiveStore>> {
+        self.store
+            .get()
+            .ok_or_else(|| ContextError::StorageError("no archive store is attached".to_string()))
+    }
+
+    /// Store the content of item `id`
+    pub(crate) async fn put(&self, id: &Uuid, content: &str) -> Result<()> {
+        self.store()?.put(&self.key(id), content.as_bytes().to_vec()).await?;
+        self.counters.archived.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Fetch the content of item `id`
+    pub(crate) async fn get(&self, id: &Uuid) -> Result<String> {
+        let key = self.key(id);
+        let content = self
+            .store()?
+            .get(&key)
+            .await?
+            .ok_or_else(|| ContextError::StorageError(format!("archived content {} is missing", key)))?;
+        String::from_utf8(content)
+            .map_err(|e| ContextError::StorageError(format!("archived content {} is not UTF-8: {}", key, e)))
+    }
+
+    /// Delete the stored content of item `id`
+    pub(crate) async fn delete(&self, id: &Uuid) -> Result<()> {
+        self.store()?.delete(&self.key(id)).await
+    }
+
+    /// Mark item `id` as being rehydrated; false if it already is
+    pub(crate) fn begin_rehydration(&self, id: &Uuid) -> bool {
+        self.rehydrating.lock().insert(*id)
+    }
+
+    /// Count how a rehydration of item `id` ended: `Ok(true)` if the item
+    /// came back, `Ok(false)` if there was nothing to do
+    pub(crate) fn end_rehydration(&self, id: &Uuid, result: &Result<bool>) {
+        self.rehydrating.lock().remove(id);
+        let counter = match result {
+            Ok(true) => &self.counters.rehydrated,
+            Ok(false) => return,
+            Err(_) => &self.counters.rehydration_failures,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_placeholder_hit(&self) {
+        self.counters.placeholder_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The distinct terms of `content` kept in memory for matching, most
+    /// frequent first
+    pub fn term_sketch(&self, content: &str) -> String {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for word in content
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.chars().count() >= 3)
+        {
+            *counts.entry(word.to_lowercase()).or_default() += 1;
+        }
+        let mut terms: Vec<(String, usize)> = counts.into_iter().collect();
+        terms.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+        terms
+            .into_iter()
+            .take(self.config.max_terms)
+            .map(|(term, _)| term)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn stats(&self) -> ArchiveStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        ArchiveStats {
+            archived: load(&self.counters.archived),
+            rehydrated: load(&self.counters.rehydrated),
+            rehydration_failures: load(&self.counters.rehydration_failures),
+            placeholder_hits: load(&self.counters.placeholder_hits),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_content_round_trips_through_the_store() {
+        let archive = ContextArchive::new(ArchiveConfig::default());
+        let id = Uuid::new_v4();
+        assert!(!archive.is_enabled());
+        assert!(archive.put(&id, "runbook").await.is_err());
+
+        let store = Arc::new(InMemoryArchiveStore::new());
+        assert!(archive.attach(store.clone()));
+        assert!(!archive.attach(store.clone()));
+        archive.put(&id, "Restart the billing pods, then the billing queue").await.unwrap();
+        assert_eq!(store.len(), 1);
+        assert!(archive.key(&id).starts_with("context-archive/"));
+        assert_eq!(archive.get(&id).await.unwrap(), "Restart the billing pods, then the billing queue");
+
+        archive.delete(&id).await.unwrap();
+        assert!(matches!(archive.get(&id).await, Err(ContextError::StorageError(_))));
+
+        assert!(archive.begin_rehydration(&id));
+        assert!(!archive.begin_rehydration(&id));
+        archive.end_rehydration(&id, &Ok(true));
+        assert!(archive.begin_rehydration(&id));
+        assert_eq!(archive.stats().rehydrated, 1);
+    }
+
+    #[test]
+    fn test_term_sketch_keeps_the_most_frequent_terms() {
+        let config = ArchiveConfig {
+            max_terms: 2,
+            ..Default::default()
+        };
+        let archive = ContextArchive::new(config);
+        assert_eq!(archive.term_sketch("Billing pods: restart the billing queue, then billing pods"), "billing pods");
+    }
+}
@@ -0,0 +1,98 @@
+//! Per-source authority weights
+//!
+//! Administrators rank ingestion sources by how much they trust them, e.g.
+//! official docs above the wiki above chat transcripts. A weight applies to
+//! every item whose source starts with its prefix (the longest matching
+//! prefix wins) and multiplies the item's retrieval score; sources without a
+//! weight keep a neutral 1.0. Weights are read at scoring time, so a change
+//! affects the next retrieval.
+
+use crate::{ContextError, Result};
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+
+/// Weight of sources nobody has ranked
+pub const NEUTRAL_WEIGHT: f64 = 1.0;
+
+/// Largest weight a source can be given
+pub const MAX_WEIGHT: f64 = 10.0;
+
+/// Authority weights by source prefix
+#[derive(Debug, Default)]
+pub struct SourceAuthority {
+    weights: RwLock<BTreeMap<String, f64>>,
+}
+
+impl SourceAuthority {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Weight items from sources starting with `prefix`; 0.0 hides them
+    pub fn set(&self, prefix: impl Into<String>, weight: f64) -> Result<()> {
+        let prefix = prefix.into();
+        if prefix.is_empty() {
+            return Err(ContextError::RetrievalFailed("Source prefix must not be empty".to_string()));
+        }
+        if !(0.0..=MAX_WEIGHT).contains(&weight) {
+            return Err(ContextError::RetrievalFailed(format!(
+                "Authority weight must be in [0, {}], got {}",
+                MAX_WEIGHT, weight
+            )));
+        }
+        self.weights.write().insert(prefix, weight);
+        Ok(())
+    }
+
+    /// Return the prefix to the neutral weight; false if it had none
+    pub fn remove(&self, prefix: &str) -> bool {
+        self.weights.write().remove(prefix).is_some()
+    }
+
+    /// Every configured weight, by prefix
+    pub fn weights(&self) -> BTreeMap<String, f64> {
+        self.weights.read().clone()
+    }
+
+    /// Weight of an item from `source`
+    pub fn weight_for(&self, source: &str) -> f64 {
+        self.weights
+            .read()
+            .iter()
+            .filter(|(prefix, _)| source.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(NEUTRAL_WEIGHT, |(_, weight)| *weight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let authority = SourceAuthority::new();
+        authority.set("https://docs.example.com/", 2.0).unwrap();
+        authority.set("https://docs.example.com/archive/", 0.5).unwrap();
+        authority.set("slack://", 0.3).unwrap();
+
+        assert_eq!(authority.weight_for("https://docs.example.com/guide"), 2.0);
+        assert_eq!(authority.weight_for("https://docs.example.com/archive/v1"), 0.5);
+        assert_eq!(authority.weight_for("slack://C024BE91L"), 0.3);
+        assert_eq!(authority.weight_for("file:///repo/README.md"), NEUTRAL_WEIGHT);
+
+        assert!(authority.remove("slack://"));
+        assert_eq!(authority.weight_for("slack://C024BE91L"), NEUTRAL_WEIGHT);
+        assert!(!authority.remove("slack://"));
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_weights() {
+        let authority = SourceAuthority::new();
+        assert!(authority.set("wiki://", -1.0).is_err());
+        assert!(authority.set("wiki://", MAX_WEIGHT + 1.0).is_err());
+        assert!(authority.set("wiki://", f64::NAN).is_err());
+        assert!(authority.set("", 2.0).is_err());
+        assert!(authority.weights().is_empty());
+    }
+}
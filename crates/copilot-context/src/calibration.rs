@@ -0,0 +1,371 @@
+//! Reranker score calibration
+//!
+//! Raw cross-encoder scores are on whatever scale the model happens to
+//! produce: logits for one model, probabilities for another, and min-max
+//! normalization only rescales them within a single result list. A
+//! [`ScoreCalibration`] maps a model's raw scores to the probability that
+//! the document is relevant, so a `score_threshold` in
+//! [`RerankerConfig`](crate::reranking::RerankerConfig) means the same
+//! thing whichever model produced the scores.
+//!
+//! Calibrations are fitted on a labeled set, either from raw scores
+//! ([`ScoreCalibration::fit`]) or by scoring labeled query-document pairs
+//! with the reranker itself
+//! ([`CrossEncoderReranker::calibrate`](crate::reranking::CrossEncoderReranker::calibrate)),
+//! and persisted as JSON with [`CalibrationParams`].
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::{ContextError, Result};
+
+/// How raw scores are mapped to probabilities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CalibrationMethod {
+    /// A sigmoid fitted to the scores; suits small labeled sets
+    Platt,
+    /// A monotonic step function; needs more labels but assumes no shape
+    Isotonic,
+}
+
+/// A raw reranker score with its relevance label
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LabeledScore {
+    pub score: f32,
+    pub relevant: bool,
+}
+
+impl LabeledScore {
+    pub fn new(score: f32, relevant: bool) -> Self {
+        Self { score, relevant }
+    }
+}
+
+/// A query-document pair with its relevance label
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LabeledPair {
+    pub query: String,
+    pub document: String,
+    pub relevant: bool,
+}
+
+impl LabeledPair {
+    pub fn new(query: impl Into<String>, document: impl Into<String>, relevant: bool) -> Self {
+        Self {
+            query: query.into(),
+            document: document.into(),
+            relevant,
+        }
+    }
+}
+
+/// A fitted mapping from raw scores to relevance probabilities
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum ScoreCalibration {
+    /// `p = 1 / (1 + exp(a * score + b))`
+    Platt { a: f64, b: f64 },
+    /// Probabilities at increasing raw scores, interpolated linearly
+    /// between points and held constant outside them
+    Isotonic { points: Vec<(f32, f32)> },
+}
+
+impl ScoreCalibration {
+    /// Fit a calibration with `method` on labeled scores
+    pub fn fit(method: CalibrationMethod, samples: &[LabeledScore]) -> Result<Self> {
+        let positives = samples.iter().filter(|s| s.relevant).count();
+        if positives == 0 || positives == samples.len() {
+            return Err(ContextError::InvalidCalibration(
+                "labeled set needs both relevant and non-relevant examples".to_string(),
+            ));
+        }
+        if samples.iter().any(|s| !s.score.is_finite()) {
+            return Err(ContextError::InvalidCalibration("labeled set has non-finite scores".to_string()));
+        }
+
+        Ok(match method {
+            CalibrationMethod::Platt => fit_platt(samples),
+            CalibrationMethod::Isotonic => fit_isotonic(samples),
+        })
+    }
+
+    pub fn method(&self) -> CalibrationMethod {
+        match self {
+            ScoreCalibration::Platt { .. } => CalibrationMethod::Platt,
+            ScoreCalibration::Isotonic { .. } => CalibrationMethod::Isotonic,
+        }
+    }
+
+    /// Probability that a document with raw score `score` is relevant
+    pub fn apply(&self, score: f32) -> f32 {
+        match self {
+            ScoreCalibration::Platt { a, b } => sigmoid(-(a * score as f64 + b)) as f32,
+            ScoreCalibration::Isotonic { points } => interpolate(points, score),
+        }
+    }
+
+    /// Mean squared error of the calibrated probabilities on `samples`;
+    /// lower is better
+    pub fn brier_score(&self, samples: &[LabeledScore]) -> f32 {
+        if samples.is_empty() {
+            return 0.0;
+        }
+        let total: f32 = samples
+            .iter()
+            .map(|s| {
+                let target = if s.relevant { 1.0 } else { 0.0 };
+                (self.apply(s.score) - target).powi(2)
+            })
+            .sum();
+        total / samples.len() as f32
+    }
+}
+
+fn sigmoid(x: f64) -> f64 {
+    if x >= 0.0 {
+        1.0 / (1.0 + (-x).exp())
+    } else {
+        let e = x.exp();
+        e / (1.0 + e)
+    }
+}
+
+/// Platt scaling with the smoothed targets and Newton iterations of
+/// Lin, Lin and Weng's "A note on Platt's probabilistic outputs"
+fn fit_platt(samples: &[LabeledScore]) -> ScoreCalibration {
+    let positives = samples.iter().filter(|s| s.relevant).count() as f64;
+    let negatives = samples.len() as f64 - positives;
+    let high = (positives + 1.0) / (positives + 2.0);
+    let low = 1.0 / (negatives + 2.0);
+    let targets: Vec<f64> = samples.iter().map(|s| if s.relevant { high } else { low }).collect();
+
+    // Negative log-likelihood of the smoothed targets under (a, b)
+    let loss = |a: f64, b: f64| -> f64 {
+        samples
+            .iter()
+            .zip(&targets)
+            .map(|(s, t)| {
+                let f = a * s.score as f64 + b;
+                if f >= 0.0 {
+                    t * f + (1.0 + (-f).exp()).ln()
+                } else {
+                    (t - 1.0) * f + (1.0 + f.exp()).ln()
+                }
+            })
+            .sum()
+    };
+
+    const SIGMA: f64 = 1e-12;
+    let mut a = 0.0;
+    let mut b = ((negatives + 1.0) / (positives + 1.0)).ln();
+    let mut current = loss(a, b);
+
+    for _ in 0..100 {
+        let (mut h11, mut h22, mut h21, mut g1, mut g2) = (SIGMA, SIGMA, 0.0, 0.0, 0.0);
+        for (s, t) in samples.iter().zip(&targets) {
+            let x = s.score as f64;
+            let p = sigmoid(-(a * x + b));
+            let q = 1.0 - p;
+            let d2 = p * q;
+            h11 += x * x * d2;
+            h22 += d2;
+            h21 += x * d2;
+            let d1 = t - p;
+            g1 += x * d1;
+            g2 += d1;
+        }
+        if g1.abs() < 1e-5 && g2.abs() < 1e-5 {
+            break;
+        }
+
+        let det = h11 * h22 - h21 * h21;
+        let da = -(h22 * g1 - h21 * g2) / det;
+        let db = -(-h21 * g1 + h11 * g2) / det;
+        let gd = g1 * da + g2 * db;
+
+        // Backtracking line search
+        let mut step = 1.0;
+        while step >= 1e-10 {
+            let (na, nb) = (a + step * da, b + step * db);
+            let next = loss(na, nb);
+            if next < current + 1e-4 * step * gd {
+                a = na;
+                b = nb;
+                current = next;
+                break;
+            }
+            step /= 2.0;
+        }
+        if step < 1e-10 {
+            break;
+        }
+    }
+
+    ScoreCalibration::Platt { a, b }
+}
+
+/// Isotonic regression by pool adjacent violators
+fn fit_isotonic(samples: &[LabeledScore]) -> ScoreCalibration {
+    let mut sorted: Vec<&LabeledScore> = samples.iter().collect();
+    sorted.sort_by(|x, y| x.score.total_cmp(&y.score));
+
+    // Blocks of (lowest score, highest score, label sum, count)
+    let mut blocks: Vec<(f32, f32, f64, f64)> = Vec::new();
+    for sample in sorted {
+        let label = if sample.relevant { 1.0 } else { 0.0 };
+        match blocks.last_mut() {
+            // Equal scores must share a probability
+            Some(last) if last.1 == sample.score => {
+                last.2 += label;
+                last.3 += 1.0;
+            }
+            _ => blocks.push((sample.score, sample.score, label, 1.0)),
+        }
+        while blocks.len() > 1 {
+            let (prev, last) = (blocks[blocks.len() - 2], blocks[blocks.len() - 1]);
+            if prev.2 / prev.3 <= last.2 / last.3 {
+                break;
+            }
+            blocks.pop();
+            *blocks.last_mut().unwrap() = (prev.0, last.1, prev.2 + last.2, prev.3 + last.3);
+        }
+    }
+
+    let mut points = Vec::with_capacity(blocks.len() * 2);
+    for (low, high, sum, count) in blocks {
+        let value = (sum / count) as f32;
+        points.push((low, value));
+        if high > low {
+            points.push((high, value));
+        }
+    }
+    ScoreCalibration::Isotonic { points }
+}
+
+fn interpolate(points: &[(f32, f32)], score: f32) -> f32 {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return score;
+    };
+    if score <= first.0 {
+        return first.1;
+    }
+    if score >= last.0 {
+        return last.1;
+    }
+    let upper = points.partition_point(|(x, _)| *x <= score);
+    let (x0, y0) = points[upper - 1];
+    let (x1, y1) = points[upper];
+    y0 + (y1 - y0) * (score - x0) / (x1 - x0)
+}
+
+/// A calibration persisted for one reranker model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationParams {
+    /// Model whose raw scores the calibration maps
+    pub model_name: String,
+    pub calibration: ScoreCalibration,
+    /// Labeled examples the calibration was fitted on
+    pub samples: usize,
+    /// Brier score on those examples
+    pub brier_score: f32,
+    pub fitted_at: DateTime<Utc>,
+}
+
+impl CalibrationParams {
+    /// Fit a calibration for `model_name` on labeled scores
+    pub fn fit(model_name: impl Into<String>, method: CalibrationMethod, samples: &[LabeledScore]) -> Result<Self> {
+        let calibration = ScoreCalibration::fit(method, samples)?;
+        Ok(Self {
+            model_name: model_name.into(),
+            brier_score: calibration.brier_score(samples),
+            samples: samples.len(),
+            calibration,
+            fitted_at: Utc::now(),
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| ContextError::StorageError(format!("Failed to read {}: {}", path.display(), e)))?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| ContextError::StorageError(format!("Failed to write {}: {}", path.display(), e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Relevance rises with the score: everything above 2.0 is relevant,
+    /// with some noise around it
+    fn labeled() -> Vec<LabeledScore> {
+        (0..40)
+            .map(|i| {
+                let score = i as f32 / 10.0;
+                let relevant = if (1.6..2.4).contains(&score) { i % 2 == 0 } else { score >= 2.0 };
+                LabeledScore::new(score, relevant)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_platt_calibration_is_monotonic_probability() {
+        let samples = labeled();
+        let calibration = ScoreCalibration::fit(CalibrationMethod::Platt, &samples).unwrap();
+        let ScoreCalibration::Platt { a, .. } = calibration else {
+            panic!("expected Platt calibration");
+        };
+        assert!(a < 0.0);
+
+        assert!(calibration.apply(0.0) < 0.1);
+        assert!(calibration.apply(3.9) > 0.9);
+        assert!((calibration.apply(2.0) - 0.5).abs() < 0.15);
+        assert!(calibration.brier_score(&samples) < 0.1);
+    }
+
+    #[test]
+    fn test_isotonic_calibration() {
+        let samples = vec![
+            LabeledScore::new(0.1, false),
+            LabeledScore::new(0.2, true),
+            LabeledScore::new(0.3, false),
+            LabeledScore::new(0.8, true),
+            LabeledScore::new(0.9, true),
+        ];
+        let calibration = ScoreCalibration::fit(CalibrationMethod::Isotonic, &samples).unwrap();
+
+        assert_eq!(calibration.apply(0.0), 0.0);
+        // 0.2 and 0.3 violate the order and pool to 0.5
+        assert_eq!(calibration.apply(0.25), 0.5);
+        assert_eq!(calibration.apply(0.55), 0.75);
+        assert_eq!(calibration.apply(5.0), 1.0);
+    }
+
+    #[test]
+    fn test_fit_needs_both_labels() {
+        let samples = vec![LabeledScore::new(0.4, true), LabeledScore::new(0.9, true)];
+        assert!(matches!(
+            ScoreCalibration::fit(CalibrationMethod::Platt, &samples),
+            Err(ContextError::InvalidCalibration(_))
+        ));
+    }
+
+    #[test]
+    fn test_params_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("calibration.json");
+        let params = CalibrationParams::fit("cross-encoder/test", CalibrationMethod::Isotonic, &labeled()).unwrap();
+
+        params.save(&path).unwrap();
+        assert_eq!(CalibrationParams::load(&path).unwrap(), params);
+    }
+}
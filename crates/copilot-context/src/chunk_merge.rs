@@ -0,0 +1,256 @@
+//! Merging of adjacent retrieved chunks
+//!
+//! A long document is stored as chunks, and a question about it often
+//! retrieves several neighbouring ones. Given to the model side by side they
+//! repeat the text the chunker overlapped and read as unrelated snippets.
+//! [`ChunkMerger`] splices chunks of the same document version that touch or
+//! overlap, going by the offsets recorded at ingestion, into one passage
+//! before the window is budgeted, so the budget is spent on distinct text
+//! and the passage is cited once. The text two chunks share is found by
+//! matching rather than taken from the offsets, which chunkers may count in
+//! bytes and which trimming shifts.
+//!
+//! A merged passage keeps the metadata and id of its best-scoring chunk,
+//! takes that chunk's score, and lists every chunk it covers under
+//! [`MERGED_KEY`].
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::freshness::VERSION_KEY;
+use crate::retrieval::ScoredItem;
+
+/// Custom metadata key holding a chunk's start character offset in its
+/// document
+pub const START_OFFSET_KEY: &str = "start_offset";
+
+/// Custom metadata key holding a chunk's end character offset, exclusive
+pub const END_OFFSET_KEY: &str = "end_offset";
+
+/// Custom metadata key listing the ids of the chunks a passage was merged
+/// from
+pub const MERGED_KEY: &str = "merged_chunks";
+
+/// Limits of merged passages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkMergeConfig {
+    /// Longest merged passage in characters; 0 disables merging
+    pub max_span_chars: usize,
+    /// Characters allowed between chunks that still count as adjacent, such
+    /// as the blank line a paragraph chunker drops
+    pub max_gap_chars: usize,
+}
+
+impl Default for ChunkMergeConfig {
+    fn default() -> Self {
+        Self {
+            max_span_chars: 4000,
+            max_gap_chars: 2,
+        }
+    }
+}
+
+/// Offsets of a chunk in its document, if recorded
+fn offsets(scored: &ScoredItem) -> Option<(String, usize, usize)> {
+    let custom = &scored.item.metadata.custom;
+    let version = custom.get(VERSION_KEY)?.as_str()?.to_string();
+    let start = custom.get(START_OFFSET_KEY)?.as_u64()? as usize;
+    let end = custom.get(END_OFFSET_KEY)?.as_u64()? as usize;
+    (start <= end).then_some((version, start, end))
+}
+
+/// A passage being assembled from chunks
+struct Passage {
+    /// Best-scoring chunk, which represents the passage
+    best: ScoredItem,
+    content: String,
+    start: usize,
+    end: usize,
+    /// Characters and tokens of the chunks spliced in, overlap included
+    chars: usize,
+    tokens: usize,
+    ids: Vec<String>,
+}
+
+impl Passage {
+    fn new(scored: ScoredItem, start: usize, end: usize) -> Self {
+        Self {
+            content: scored.item.content.clone(),
+            chars: scored.item.content.chars().count(),
+            tokens: scored.item.token_count,
+            ids: vec![scored.item.metadata.id.to_string()],
+            best: scored,
+            start,
+            end,
+        }
+    }
+
+    /// Append `next`, which starts at or after the passage, skipping the
+    /// text both cover
+    fn splice(&mut self, next: ScoredItem, start: usize, end: usize) {
+        let shared = shared_prefix(&self.content, &next.item.content, self.end.saturating_sub(start));
+        if start > self.end {
+            self.content.push('\n');
+        }
+        self.content.push_str(&next.item.content[shared..]);
+        self.end = end;
+        self.chars += next.item.content.chars().count();
+        self.tokens += next.item.token_count;
+        self.ids.push(next.item.metadata.id.to_string());
+        if next.score > self.best.score {
+            self.best = next;
+        }
+    }
+
+    fn finish(self) -> ScoredItem {
+        let Passage { mut best, content, start, end, chars, tokens, ids } = self;
+        if ids.len() == 1 {
+            return best;
+        }
+        let item = &mut best.item;
+        // Scale the chunks' token counts to the text left after splicing
+        item.token_count = (tokens * content.chars().count()).div_ceil(chars.max(1));
+        item.content = content;
+        item.compressed_content = None;
+        let custom = &mut item.metadata.custom;
+        custom.insert(START_OFFSET_KEY.to_string(), start.into());
+        custom.insert(END_OFFSET_KEY.to_string(), end.into());
+        custom.insert(MERGED_KEY.to_string(), ids.into());
+        best
+    }
+}
+
+/// Length in bytes of the longest prefix of `next`, at most `max_chars`
+/// characters, that `content` ends with
+fn shared_prefix(content: &str, next: &str, max_chars: usize) -> usize {
+    next.char_indices()
+        .map(|(i, c)| i + c.len_utf8())
+        .take(max_chars)
+        .filter(|&len| content.ends_with(&next[..len]))
+        .last()
+        .unwrap_or(0)
+}
+
+/// Splices adjacent chunks of the same document into passages
+#[derive(Debug, Clone, Default)]
+pub struct ChunkMerger {
+    config: ChunkMergeConfig,
+}
+
+impl ChunkMerger {
+    pub fn new(config: ChunkMergeConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &ChunkMergeConfig {
+        &self.config
+    }
+
+    /// Merge adjacent chunks among `items`; items without offsets are kept
+    /// as they are. Passages come back in no particular order.
+    pub fn merge(&self, items: Vec<ScoredItem>) -> Vec<ScoredItem> {
+        if self.config.max_span_chars == 0 {
+            return items;
+        }
+
+        let mut merged = Vec::with_capacity(items.len());
+        let mut documents: HashMap<String, Vec<(ScoredItem, usize, usize)>> = HashMap::new();
+        for scored in items {
+            match offsets(&scored) {
+                Some((version, start, end)) => documents.entry(version).or_default().push((scored, start, end)),
+                None => merged.push(scored),
+            }
+        }
+
+        for mut chunks in documents.into_values() {
+            chunks.sort_by_key(|(_, start, end)| (*start, *end));
+            let mut chunks = chunks.into_iter();
+            let Some((first, start, end)) = chunks.next() else {
+                continue;
+            };
+            let mut passage = Passage::new(first, start, end);
+            for (next, start, end) in chunks {
+                let adjacent = start <= passage.end + self.config.max_gap_chars;
+                let fits = end.max(passage.end) - passage.start <= self.config.max_span_chars;
+                if adjacent && fits && end <= passage.end {
+                    // Already covered by the passage
+                    passage.ids.push(next.item.metadata.id.to_string());
+                    if next.score > passage.best.score {
+                        passage.best = next;
+                    }
+                } else if adjacent && fits {
+                    passage.splice(next, start, end);
+                } else {
+                    merged.push(passage.finish());
+                    passage = Passage::new(next, start, end);
+                }
+            }
+            merged.push(passage.finish());
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{MemoryItem, MemoryMetadata};
+
+    fn chunk(version: &str, text: &str, start: usize, score: f64) -> ScoredItem {
+        let mut metadata = MemoryMetadata::new("document", "docs/runbook.md");
+        metadata.add_custom(VERSION_KEY.to_string(), version.into());
+        metadata.add_custom(START_OFFSET_KEY.to_string(), start.into());
+        metadata.add_custom(END_OFFSET_KEY.to_string(), (start + text.chars().count()).into());
+        let item = MemoryItem::new(text.to_string(), metadata, 0.5, text.split_whitespace().count());
+        ScoredItem { item, score, stale: None }
+    }
+
+    #[test]
+    fn test_adjacent_and_overlapping_chunks_are_spliced() {
+        let merger = ChunkMerger::default();
+        let a = chunk("v1", "Drain the node first.", 0, 0.4);
+        // Overlaps the first chunk by "first."
+        let b = chunk("v1", "first. Then cordon it.", 15, 0.9);
+        assert_eq!(shared_prefix("Drain the node first.", "first. Then", 6), "first.".len());
+        assert_eq!(shared_prefix("Drain the node first.", "first. Then", 3), 0);
+        // After a blank line the chunker dropped
+        let c = chunk("v1", "Finally reboot.", 39, 0.5);
+        let ids = [&a, &b, &c].map(|s| s.item.metadata.id.to_string());
+        let other_version = chunk("v2", "Drain the node first.", 0, 0.3);
+
+        let mut merged = merger.merge(vec![c, a, other_version, b]);
+        merged.sort_by(|x, y| y.score.total_cmp(&x.score));
+        assert_eq!(merged.len(), 2);
+
+        let passage = &merged[0];
+        assert_eq!(passage.item.content, "Drain the node first. Then cordon it.\nFinally reboot.");
+        assert_eq!(passage.score, 0.9);
+        assert_eq!(passage.item.metadata.id.to_string(), ids[1]);
+        assert_eq!(passage.item.metadata.custom[MERGED_KEY], serde_json::json!(ids));
+        assert_eq!(passage.item.metadata.custom[END_OFFSET_KEY], 54);
+        // 10 tokens counted over 58 characters, 53 of which are left
+        assert_eq!(passage.item.token_count, 10);
+        assert_eq!(merged[1].item.content, "Drain the node first.");
+    }
+
+    #[test]
+    fn test_passages_stop_at_gaps_and_the_span_limit() {
+        let merger = ChunkMerger::new(ChunkMergeConfig { max_span_chars: 30, max_gap_chars: 0 });
+        let items = vec![
+            chunk("v1", "0123456789", 0, 0.5),
+            chunk("v1", "abcdefghij", 10, 0.5),
+            chunk("v1", "ABCDEFGHIJ", 20, 0.5),
+            // Would make the passage longer than 30 characters
+            chunk("v1", "klmnopqrst", 30, 0.5),
+            // Not adjacent to anything
+            chunk("v1", "uvwxyz", 45, 0.5),
+        ];
+        let mut contents: Vec<String> = merger.merge(items).into_iter().map(|s| s.item.content).collect();
+        contents.sort();
+        assert_eq!(contents, vec!["0123456789abcdefghijABCDEFGHIJ", "klmnopqrst", "uvwxyz"]);
+
+        let disabled = ChunkMerger::new(ChunkMergeConfig { max_span_chars: 0, ..Default::default() });
+        let items = vec![chunk("v1", "0123456789", 0, 0.5), chunk("v1", "abcdefghij", 10, 0.5)];
+        assert_eq!(disabled.merge(items).len(), 2);
+    }
+}
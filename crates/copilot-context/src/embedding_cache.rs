@@ -0,0 +1,562 @@
+//! Shared embedding cache
+//!
+//! [`CachedEmbeddingProvider`] wraps an embedding provider and keeps the
+//! vectors it returns, keyed by the model and the SHA-256 of the text, so
+//! content that has not changed is never embedded twice. Ingestion and
+//! query-time search share one instance, which means a chunk embedded while
+//! ingesting is already cached when a search re-embeds it, and re-ingesting
+//! an unchanged document costs no embedding calls.
+//!
+//! Vectors are kept in a bounded in-process tier in front of an optional
+//! shared tier ([`EmbeddingTier`]), usually Redis via [`CacheTier`], which
+//! lets several server instances reuse each other's embeddings.
+//!
+//! Misses of background work (ingestion through [`CachedEmbeddingProvider::background`]
+//! and warmups) can go to a separate provider, usually the background queue
+//! of an [`EmbeddingScheduler`](crate::EmbeddingScheduler), so they never
+//! hold up queries.
+
+use async_trait::async_trait;
+use copilot_core::cache::Cache as SharedCache;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+use crate::{
+    hybrid_search::{Embedding, EmbeddingProvider},
+    ContextError, Result,
+};
+
+/// SHA-256 of a text, hex encoded; the same hash ingestion records for
+/// each chunk
+pub fn content_hash(text: &str) -> String {
+    hex::encode(Sha256::digest(text.as_bytes()))
+}
+
+/// Cache key of a text's embedding under `model`
+pub fn cache_key(model: &str, content_hash: &str) -> String {
+    format!("embedding:{}:{}", model, content_hash)
+}
+
+/// Shared tier behind the in-process cache
+#[async_trait]
+pub trait EmbeddingTier: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<Embedding>>;
+
+    async fn put(&self, key: &str, embedding: &Embedding) -> Result<()>;
+}
+
+/// An [`EmbeddingTier`] backed by any shared cache, e.g. Redis
+pub struct CacheTier<C> {
+    cache: C,
+}
+
+impl<C> CacheTier<C> {
+    pub fn new(cache: C) -> Self {
+        Self { cache }
+    }
+}
+
+#[async_trait]
+impl<C: SharedCache> EmbeddingTier for CacheTier<C> {
+    async fn get(&self, key: &str) -> Result<Option<Embedding>> {
+        self.cache
+            .get(key)
+            .await
+            .map_err(|e| ContextError::StorageError(e.to_string()))
+    }
+
+    async fn put(&self, key: &str, embedding: &Embedding) -> Result<()> {
+        self.cache
+            .set(key, embedding)
+            .await
+            .map_err(|e| ContextError::StorageError(e.to_string()))
+    }
+}
+
+/// Embedding cache configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingCacheConfig {
+    /// Name of the embedding model; part of every key, so switching models
+    /// never serves vectors from the old one
+    pub model: String,
+    /// Embeddings kept in process
+    pub capacity: usize,
+}
+
+impl Default for EmbeddingCacheConfig {
+    fn default() -> Self {
+        Self {
+            model: "default".to_string(),
+            capacity: 10_000,
+        }
+    }
+}
+
+/// Embedding cache effectiveness counters
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingCacheStats {
+    /// Lookups answered from the in-process tier
+    pub memory_hits: u64,
+    /// Lookups answered from the shared tier
+    pub shared_hits: u64,
+    /// Lookups that had to call the embedding model
+    pub misses: u64,
+    /// `(memory_hits + shared_hits) / lookups`
+    pub hit_rate: f64,
+    /// Failed reads and writes of the shared tier
+    pub shared_errors: u64,
+    /// Embeddings held in process
+    pub entries: usize,
+}
+
+impl EmbeddingCacheStats {
+    /// Render the counters in the Prometheus text exposition format
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        for (name, kind, help, value) in [
+            ("memory_hits_total", "counter", "Embeddings served from the in-process cache", self.memory_hits as f64),
+            ("shared_hits_total", "counter", "Embeddings served from the shared cache", self.shared_hits as f64),
+            ("misses_total", "counter", "Embeddings computed by the model", self.misses as f64),
+            ("hit_rate", "gauge", "Share of embedding lookups served from cache", self.hit_rate),
+            ("shared_errors_total", "counter", "Failed shared embedding cache operations", self.shared_errors as f64),
+            ("entries", "gauge", "Embeddings held in process", self.entries as f64),
+        ] {
+            let _ = writeln!(out, "# HELP {prefix}_embedding_cache_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_embedding_cache_{name} {kind}");
+            let _ = writeln!(out, "{prefix}_embedding_cache_{name} {value}");
+        }
+        out
+    }
+}
+
+/// Result of warming the cache
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingWarmup {
+    /// Texts looked at
+    pub texts: usize,
+    /// Texts that were already cached
+    pub already_cached: usize,
+    /// Texts embedded and cached
+    pub embedded: usize,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    memory_hits: AtomicU64,
+    shared_hits: AtomicU64,
+    misses: AtomicU64,
+    shared_errors: AtomicU64,
+}
+
+#[derive(Default)]
+struct MemoryTier {
+    entries: HashMap<String, Embedding>,
+    /// Insertion order, oldest first, for eviction
+    order: VecDeque<String>,
+}
+
+/// Embedding provider decorator that caches by content hash
+pub struct CachedEmbeddingProvider {
+    inner: Arc<dyn EmbeddingProvider>,
+    background: Option<Arc<dyn EmbeddingProvider>>,
+    shared: Option<Arc<dyn EmbeddingTier>>,
+    config: EmbeddingCacheConfig,
+    memory: Mutex<MemoryTier>,
+    counters: Counters,
+}
+
+impl CachedEmbeddingProvider {
+    pub fn new(inner: Arc<dyn EmbeddingProvider>, config: EmbeddingCacheConfig) -> Self {
+        Self {
+            inner,
+            background: None,
+            shared: None,
+            config,
+            memory: Mutex::new(MemoryTier::default()),
+            counters: Counters::default(),
+        }
+    }
+
+    /// Look up and store embeddings in `tier` behind the in-process cache
+    pub fn with_shared_tier(mut self, tier: Arc<dyn EmbeddingTier>) -> Self {
+        self.shared = Some(tier);
+        self
+    }
+
+    /// Embed misses of background work with `provider` instead
+    pub fn with_background_provider(mut self, provider: Arc<dyn EmbeddingProvider>) -> Self {
+        self.background = Some(provider);
+        self
+    }
+
+    /// This cache as a provider for background work such as ingestion
+    pub fn background(self: &Arc<Self>) -> BackgroundEmbeddings {
+        BackgroundEmbeddings { cache: self.clone() }
+    }
+
+    /// Name of the embedding model the cache is keyed by
+    pub fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    /// Cached embedding of a text with this content hash, if any
+    pub async fn cached(&self, content_hash: &str) -> Option<Embedding> {
+        let key = cache_key(&self.config.model, content_hash);
+        if let Some(embedding) = self.memory.lock().entries.get(&key).cloned() {
+            self.counters.memory_hits.fetch_add(1, Ordering::Relaxed);
+            return Some(embedding);
+        }
+
+        let shared = self.shared.as_ref()?;
+        match shared.get(&key).await {
+            Ok(Some(embedding)) => {
+                self.counters.shared_hits.fetch_add(1, Ordering::Relaxed);
+                self.remember(key, embedding.clone());
+                Some(embedding)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                self.counters.shared_errors.fetch_add(1, Ordering::Relaxed);
+                warn!("Shared embedding cache read failed: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Embed the texts that are not cached yet, so later calls for them hit
+    pub async fn warm(&self, texts: &[&str]) -> Result<EmbeddingWarmup> {
+        let mut hashes: Vec<String> = Vec::new();
+        let mut missing: Vec<&str> = Vec::new();
+        let mut already_cached = 0;
+        for text in texts {
+            let hash = content_hash(text);
+            if hashes.contains(&hash) {
+                continue;
+            }
+            if self.is_cached(&hash).await {
+                already_cached += 1;
+            } else {
+                missing.push(text);
+            }
+            hashes.push(hash);
+        }
+
+        if !missing.is_empty() {
+            self.embed_batch_with(self.background_provider(), &missing).await?;
+        }
+        debug!(
+            "Warmed embedding cache: {} embedded, {} already cached",
+            missing.len(),
+            already_cached
+        );
+        Ok(EmbeddingWarmup {
+            texts: texts.len(),
+            already_cached,
+            embedded: missing.len(),
+        })
+    }
+
+    /// Hits, misses and size of the cache so far
+    pub fn stats(&self) -> EmbeddingCacheStats {
+        let memory_hits = self.counters.memory_hits.load(Ordering::Relaxed);
+        let shared_hits = self.counters.shared_hits.load(Ordering::Relaxed);
+        let misses = self.counters.misses.load(Ordering::Relaxed);
+        let lookups = memory_hits + shared_hits + misses;
+        EmbeddingCacheStats {
+            memory_hits,
+            shared_hits,
+            misses,
+            hit_rate: if lookups == 0 {
+                0.0
+            } else {
+                (memory_hits + shared_hits) as f64 / lookups as f64
+            },
+            shared_errors: self.counters.shared_errors.load(Ordering::Relaxed),
+            entries: self.memory.lock().entries.len(),
+        }
+    }
+
+    fn background_provider(&self) -> &dyn EmbeddingProvider {
+        self.background.as_deref().unwrap_or(self.inner.as_ref())
+    }
+
+    /// Look up `texts`, embedding the misses with `provider`
+    async fn embed_batch_with(&self, provider: &dyn EmbeddingProvider, texts: &[&str]) -> Result<Vec<Embedding>> {
+        let mut embeddings: Vec<Option<Embedding>> = Vec::with_capacity(texts.len());
+        // Uncached texts by hash, with every position they appear at
+        let mut missing: Vec<(String, Vec<usize>)> = Vec::new();
+        for (i, text) in texts.iter().enumerate() {
+            let hash = content_hash(text);
+            if let Some((_, positions)) = missing.iter_mut().find(|(h, _)| *h == hash) {
+                self.counters.memory_hits.fetch_add(1, Ordering::Relaxed);
+                positions.push(i);
+                embeddings.push(None);
+                continue;
+            }
+            let cached = self.cached(&hash).await;
+            if cached.is_none() {
+                missing.push((hash, vec![i]));
+            }
+            embeddings.push(cached);
+        }
+
+        if !missing.is_empty() {
+            self.counters
+                .misses
+                .fetch_add(missing.len() as u64, Ordering::Relaxed);
+            let batch: Vec<&str> = missing.iter().map(|(_, positions)| texts[positions[0]]).collect();
+            let computed = provider.embed_batch(&batch).await?;
+            if computed.len() != batch.len() {
+                return Err(ContextError::RetrievalFailed(format!(
+                    "Embedding provider returned {} embeddings for {} texts",
+                    computed.len(),
+                    batch.len()
+                )));
+            }
+            for ((hash, positions), embedding) in missing.into_iter().zip(computed) {
+                self.store(&hash, &embedding).await;
+                for i in positions {
+                    embeddings[i] = Some(embedding.clone());
+                }
+            }
+        }
+
+        Ok(embeddings.into_iter().map(|e| e.unwrap_or_default()).collect())
+    }
+
+    /// Whether a text with this hash is cached, without counting a lookup
+    async fn is_cached(&self, content_hash: &str) -> bool {
+        let key = cache_key(&self.config.model, content_hash);
+        if self.memory.lock().entries.contains_key(&key) {
+            return true;
+        }
+        match &self.shared {
+            Some(shared) => matches!(shared.get(&key).await, Ok(Some(_))),
+            None => false,
+        }
+    }
+
+    async fn store(&self, content_hash: &str, embedding: &Embedding) {
+        let key = cache_key(&self.config.model, content_hash);
+        if let Some(shared) = &self.shared {
+            if let Err(e) = shared.put(&key, embedding).await {
+                self.counters.shared_errors.fetch_add(1, Ordering::Relaxed);
+                warn!("Shared embedding cache write failed: {}", e);
+            }
+        }
+        self.remember(key, embedding.clone());
+    }
+
+    fn remember(&self, key: String, embedding: Embedding) {
+        let mut memory = self.memory.lock();
+        if memory.entries.insert(key.clone(), embedding).is_none() {
+            memory.order.push_back(key);
+        }
+        while memory.entries.len() > self.config.capacity {
+            match memory.order.pop_front() {
+                Some(oldest) => {
+                    memory.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for CachedEmbeddingProvider {
+    async fn embed(&self, text: &str) -> Result<Embedding> {
+        let hash = content_hash(text);
+        if let Some(embedding) = self.cached(&hash).await {
+            return Ok(embedding);
+        }
+
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
+        let embedding = self.inner.embed(text).await?;
+        self.store(&hash, &embedding).await;
+        Ok(embedding)
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        self.embed_batch_with(self.inner.as_ref(), texts).await
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+}
+
+/// A [`CachedEmbeddingProvider`] whose misses go to its background provider
+#[derive(Clone)]
+pub struct BackgroundEmbeddings {
+    cache: Arc<CachedEmbeddingProvider>,
+}
+
+#[async_trait]
+impl EmbeddingProvider for BackgroundEmbeddings {
+    async fn embed(&self, text: &str) -> Result<Embedding> {
+        let mut embeddings = self.embed_batch(&[text]).await?;
+        embeddings
+            .pop()
+            .ok_or_else(|| ContextError::RetrievalFailed("No embedding returned".to_string()))
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        self.cache
+            .embed_batch_with(self.cache.background_provider(), texts)
+            .await
+    }
+
+    fn dimension(&self) -> usize {
+        self.cache.dimension()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_search::MockEmbeddingProvider;
+
+    /// Counts the texts it is asked to embed
+    struct CountingProvider {
+        inner: MockEmbeddingProvider,
+        calls: AtomicU64,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for CountingProvider {
+        async fn embed(&self, text: &str) -> Result<Embedding> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            self.inner.embed(text).await
+        }
+
+        async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+            self.calls.fetch_add(texts.len() as u64, Ordering::Relaxed);
+            self.inner.embed_batch(texts).await
+        }
+
+        fn dimension(&self) -> usize {
+            self.inner.dimension()
+        }
+    }
+
+    fn counting() -> Arc<CountingProvider> {
+        Arc::new(CountingProvider {
+            inner: MockEmbeddingProvider::new(8),
+            calls: AtomicU64::new(0),
+        })
+    }
+
+    #[derive(Default)]
+    struct MapTier {
+        entries: Mutex<HashMap<String, Embedding>>,
+    }
+
+    #[async_trait]
+    impl EmbeddingTier for MapTier {
+        async fn get(&self, key: &str) -> Result<Option<Embedding>> {
+            Ok(self.entries.lock().get(key).cloned())
+        }
+
+        async fn put(&self, key: &str, embedding: &Embedding) -> Result<()> {
+            self.entries.lock().insert(key.to_string(), embedding.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_content_is_embedded_once() {
+        let provider = counting();
+        let cache = CachedEmbeddingProvider::new(provider.clone(), EmbeddingCacheConfig::default());
+
+        let ingested = cache.embed_batch(&["alpha", "beta", "alpha"]).await.unwrap();
+        assert_eq!(ingested[0], ingested[2]);
+        let queried = cache.embed("beta").await.unwrap();
+        assert_eq!(queried, ingested[1]);
+        assert_eq!(provider.calls.load(Ordering::Relaxed), 2);
+
+        let stats = cache.stats();
+        assert_eq!((stats.memory_hits, stats.misses, stats.entries), (2, 2, 2));
+        assert!(stats.render_prometheus("copilot").contains("copilot_embedding_cache_misses_total 2"));
+        assert!(cache.cached(&content_hash("alpha")).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_shared_tier_and_model_keys() {
+        let tier: Arc<MapTier> = Arc::new(MapTier::default());
+        let first = CachedEmbeddingProvider::new(counting(), EmbeddingCacheConfig::default())
+            .with_shared_tier(tier.clone());
+        first.embed("gamma").await.unwrap();
+
+        // Another instance with an empty process cache reuses the vector
+        let provider = counting();
+        let second = CachedEmbeddingProvider::new(provider.clone(), EmbeddingCacheConfig::default())
+            .with_shared_tier(tier.clone());
+        second.embed("gamma").await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::Relaxed), 0);
+        assert_eq!(second.stats().shared_hits, 1);
+
+        // A different model never sees the old vectors
+        let other_model = CachedEmbeddingProvider::new(
+            provider.clone(),
+            EmbeddingCacheConfig {
+                model: "other".to_string(),
+                ..Default::default()
+            },
+        )
+        .with_shared_tier(tier);
+        other_model.embed("gamma").await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_warm_and_eviction() {
+        let provider = counting();
+        let cache = CachedEmbeddingProvider::new(
+            provider.clone(),
+            EmbeddingCacheConfig {
+                capacity: 2,
+                ..Default::default()
+            },
+        );
+
+        let warmup = cache.warm(&["a", "b", "a"]).await.unwrap();
+        assert_eq!(
+            warmup,
+            EmbeddingWarmup {
+                texts: 3,
+                already_cached: 0,
+                embedded: 2
+            }
+        );
+        assert_eq!(cache.warm(&["b"]).await.unwrap().already_cached, 1);
+
+        cache.embed("c").await.unwrap();
+        assert_eq!(cache.stats().entries, 2);
+        assert!(cache.cached(&content_hash("a")).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_background_misses_use_background_provider() {
+        let queries = counting();
+        let ingestion = counting();
+        let cache = Arc::new(
+            CachedEmbeddingProvider::new(queries.clone(), EmbeddingCacheConfig::default())
+                .with_background_provider(ingestion.clone()),
+        );
+
+        cache.background().embed_batch(&["a", "b"]).await.unwrap();
+        cache.warm(&["c"]).await.unwrap();
+        assert_eq!(ingestion.calls.load(Ordering::Relaxed), 3);
+
+        // Queries reuse what background work embedded
+        cache.embed_batch(&["a", "d"]).await.unwrap();
+        assert_eq!(queries.calls.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,369 @@
+//! Embedding provider migrations
+//!
+//! Switching embedding models means re-embedding every document, and vectors
+//! from the two models cannot be compared. [`EmbeddingMigration`] keeps the
+//! old and the new index side by side while that happens:
+//!
+//! - writes go to both indexes, so new content is embedded with both models
+//! - [`EmbeddingMigration::backfill`] re-embeds, a batch at a time, the
+//!   documents only the old index has
+//! - searches hit the new index; when it holds fewer results than asked for,
+//!   documents it has not been backfilled with yet are filled in from the old
+//!   index, and when it fails the old index answers the whole query
+//!
+//! [`MigrationProgress`] reports coverage and fallbacks, and says when
+//! cutover is safe: every document is in the new index and the last
+//! [`MigrationConfig::clean_queries_required`] searches were answered without
+//! the old one. [`EmbeddingMigration::complete`] then hands back the new index.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::{
+    filter::ContextFilter,
+    hybrid_search::{HybridSearchEngine, HybridSearchResult},
+    memory::MemoryMetadata,
+    Result,
+};
+
+/// Migration configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MigrationConfig {
+    /// Consecutive searches the new index must answer on its own, once fully
+    /// backfilled, before cutover is safe
+    pub clean_queries_required: u64,
+    /// Documents re-embedded per [`EmbeddingMigration::backfill`] call
+    pub backfill_batch: usize,
+}
+
+impl Default for MigrationConfig {
+    fn default() -> Self {
+        Self {
+            clean_queries_required: 100,
+            backfill_batch: 64,
+        }
+    }
+}
+
+/// How far a migration has come
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MigrationProgress {
+    /// Documents in the old index
+    pub old_documents: usize,
+    /// Documents in the new index
+    pub new_documents: usize,
+    /// Documents still to be re-embedded with the new model
+    pub pending_backfill: usize,
+    /// Share of the old index's documents the new index holds
+    pub coverage: f64,
+    /// Searches served during the migration
+    pub queries: u64,
+    /// Searches partly answered from the old index
+    pub fallback_queries: u64,
+    /// Searches the new index failed, answered from the old index
+    pub new_index_errors: u64,
+    /// Searches in a row the new index answered on its own
+    pub clean_streak: u64,
+    /// Whether [`EmbeddingMigration::complete`] will succeed
+    pub safe_to_cutover: bool,
+}
+
+impl MigrationProgress {
+    /// Render the progress in the Prometheus text exposition format
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        for (name, kind, help, value) in [
+            ("pending_backfill", "gauge", "Documents not yet embedded with the new model", self.pending_backfill as f64),
+            ("coverage", "gauge", "Share of documents embedded with the new model", self.coverage),
+            ("queries_total", "counter", "Searches served during the migration", self.queries as f64),
+            ("fallback_queries_total", "counter", "Searches partly answered from the old index", self.fallback_queries as f64),
+            ("new_index_errors_total", "counter", "Searches the new index failed", self.new_index_errors as f64),
+            ("safe_to_cutover", "gauge", "Whether cutover to the new index is safe", self.safe_to_cutover as u8 as f64),
+        ] {
+            let _ = writeln!(out, "# HELP {prefix}_embedding_migration_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_embedding_migration_{name} {kind}");
+            let _ = writeln!(out, "{prefix}_embedding_migration_{name} {value}");
+        }
+        out
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    queries: AtomicU64,
+    fallback_queries: AtomicU64,
+    new_index_errors: AtomicU64,
+    clean_streak: AtomicU64,
+}
+
+/// Dual-write, dual-read migration between two embedding indexes
+///
+/// The locks are always taken old index first, so writes, searches and
+/// backfills never deadlock.
+pub struct EmbeddingMigration {
+    old: Mutex<HybridSearchEngine>,
+    new: Mutex<HybridSearchEngine>,
+    config: MigrationConfig,
+    counters: Counters,
+}
+
+impl EmbeddingMigration {
+    /// Migrate from `old` to `new`, usually an empty index over the new
+    /// model's provider
+    pub fn new(old: HybridSearchEngine, new: HybridSearchEngine) -> Self {
+        Self::with_config(old, new, MigrationConfig::default())
+    }
+
+    pub fn with_config(old: HybridSearchEngine, new: HybridSearchEngine, config: MigrationConfig) -> Self {
+        Self {
+            old: Mutex::new(old),
+            new: Mutex::new(new),
+            config,
+            counters: Counters::default(),
+        }
+    }
+
+    pub fn config(&self) -> &MigrationConfig {
+        &self.config
+    }
+
+    /// Index a document with both models
+    ///
+    /// The old index stays the source of truth until cutover: its failures
+    /// fail the write, while a failure of the new index only leaves the
+    /// document to the backfill.
+    pub async fn index(&self, doc_id: &str, content: &str) -> Result<()> {
+        self.write(doc_id, content, None).await
+    }
+
+    /// Index a document with both models, along with the metadata search
+    /// filters match
+    pub async fn index_with_metadata(
+        &self,
+        doc_id: &str,
+        content: &str,
+        metadata: MemoryMetadata,
+        ingested_at: DateTime<Utc>,
+    ) -> Result<()> {
+        self.write(doc_id, content, Some((metadata, ingested_at))).await
+    }
+
+    async fn write(&self, doc_id: &str, content: &str, metadata: Option<(MemoryMetadata, DateTime<Utc>)>) -> Result<()> {
+        let mut old = self.old.lock().await;
+        let mut new = self.new.lock().await;
+        index_into(&mut old, doc_id, content, metadata.clone()).await?;
+        if let Err(e) = index_into(&mut new, doc_id, content, metadata).await {
+            warn!(doc_id = %doc_id, "Embedding with the new model failed, leaving it to the backfill: {}", e);
+            new.remove(doc_id);
+        }
+        Ok(())
+    }
+
+    /// Remove a document from both indexes
+    pub async fn remove(&self, doc_id: &str) {
+        let mut old = self.old.lock().await;
+        let mut new = self.new.lock().await;
+        old.remove(doc_id);
+        new.remove(doc_id);
+    }
+
+    /// Re-embed the next batch of documents the new index lacks, returning
+    /// how many were added
+    ///
+    /// Call it until it returns 0. A failed embedding stops the batch and is
+    /// returned; the documents added before it stay added.
+    pub async fn backfill(&self) -> Result<usize> {
+        let old = self.old.lock().await;
+        let mut new = self.new.lock().await;
+        let pending: Vec<&str> = old
+            .doc_ids()
+            .filter(|doc_id| !new.contains(doc_id))
+            .take(self.config.backfill_batch.max(1))
+            .collect();
+
+        let mut added = 0;
+        for doc_id in pending {
+            let content = old.get_content(doc_id).cloned().unwrap_or_default();
+            index_into(&mut new, doc_id, &content, old.get_metadata(doc_id).cloned()).await?;
+            added += 1;
+        }
+
+        if added > 0 {
+            debug!(added, remaining = pending_backfill(&old, &new), "Backfilled embeddings with the new model");
+        }
+        Ok(added)
+    }
+
+    /// Search using hybrid retrieval
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<HybridSearchResult>> {
+        self.search_filtered(query, limit, &ContextFilter::default()).await
+    }
+
+    /// Search the documents matching `filter`, new index first
+    ///
+    /// Results filled in from the old index come after the new index's, as
+    /// the two models' scores cannot be compared.
+    pub async fn search_filtered(
+        &self,
+        query: &str,
+        limit: usize,
+        filter: &ContextFilter,
+    ) -> Result<Vec<HybridSearchResult>> {
+        let mut old = self.old.lock().await;
+        let mut new = self.new.lock().await;
+        self.counters.queries.fetch_add(1, Ordering::Relaxed);
+
+        let mut results = match new.search_filtered(query, limit, filter).await {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("New embedding index failed, answering from the old one: {}", e);
+                self.counters.new_index_errors.fetch_add(1, Ordering::Relaxed);
+                self.counters.clean_streak.store(0, Ordering::Relaxed);
+                return old.search_filtered(query, limit, filter).await;
+            }
+        };
+
+        if results.len() < limit && pending_backfill(&old, &new) > 0 {
+            let fallback: Vec<_> = old
+                .search_filtered(query, limit, filter)
+                .await?
+                .into_iter()
+                .filter(|result| !new.contains(&result.doc_id))
+                .take(limit - results.len())
+                .collect();
+            if !fallback.is_empty() {
+                self.counters.fallback_queries.fetch_add(1, Ordering::Relaxed);
+                self.counters.clean_streak.store(0, Ordering::Relaxed);
+                results.extend(fallback);
+                return Ok(results);
+            }
+        }
+
+        let streak = self.counters.clean_streak.fetch_add(1, Ordering::Relaxed) + 1;
+        if streak == self.config.clean_queries_required && pending_backfill(&old, &new) == 0 {
+            info!(queries = streak, "Embedding migration is safe to cut over");
+        }
+        Ok(results)
+    }
+
+    /// How far the migration has come
+    pub async fn progress(&self) -> MigrationProgress {
+        let old = self.old.lock().await;
+        let new = self.new.lock().await;
+        let pending = pending_backfill(&old, &new);
+        let clean_streak = self.counters.clean_streak.load(Ordering::Relaxed);
+        MigrationProgress {
+            old_documents: old.len(),
+            new_documents: new.len(),
+            pending_backfill: pending,
+            coverage: if old.is_empty() { 1.0 } else { (old.len() - pending) as f64 / old.len() as f64 },
+            queries: self.counters.queries.load(Ordering::Relaxed),
+            fallback_queries: self.counters.fallback_queries.load(Ordering::Relaxed),
+            new_index_errors: self.counters.new_index_errors.load(Ordering::Relaxed),
+            clean_streak,
+            safe_to_cutover: pending == 0 && clean_streak >= self.config.clean_queries_required,
+        }
+    }
+
+    /// Finish the migration, returning the new index
+    ///
+    /// Until cutover is safe this hands the migration back untouched;
+    /// [`progress`](Self::progress) tells what is left.
+    pub async fn complete(self) -> std::result::Result<HybridSearchEngine, Box<Self>> {
+        let progress = self.progress().await;
+        if !progress.safe_to_cutover {
+            return Err(Box::new(self));
+        }
+        info!(documents = progress.new_documents, "Cut over to the new embedding index");
+        Ok(self.new.into_inner())
+    }
+
+    /// Abandon the migration, returning the old index
+    pub fn abort(self) -> HybridSearchEngine {
+        self.old.into_inner()
+    }
+}
+
+async fn index_into(
+    engine: &mut HybridSearchEngine,
+    doc_id: &str,
+    content: &str,
+    metadata: Option<(MemoryMetadata, DateTime<Utc>)>,
+) -> Result<()> {
+    match metadata {
+        Some((metadata, ingested_at)) => engine.index_with_metadata(doc_id, content, metadata, ingested_at).await,
+        None => engine.index(doc_id, content).await,
+    }
+}
+
+/// Documents of `old` that `new` does not hold yet
+fn pending_backfill(old: &HybridSearchEngine, new: &HybridSearchEngine) -> usize {
+    old.doc_ids().filter(|doc_id| !new.contains(doc_id)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_search::{HybridSearchConfig, MockEmbeddingProvider};
+    use std::sync::Arc;
+
+    fn engine(dimension: usize) -> HybridSearchEngine {
+        HybridSearchEngine::new(HybridSearchConfig::default(), Arc::new(MockEmbeddingProvider::new(dimension)))
+    }
+
+    #[tokio::test]
+    async fn test_searches_fall_back_to_old_index_until_backfilled() {
+        let mut old = engine(8);
+        old.index("doc1", "rust programming language").await.unwrap();
+        old.index("doc2", "rust borrow checker").await.unwrap();
+        let config = MigrationConfig {
+            clean_queries_required: 2,
+            backfill_batch: 1,
+        };
+        let migration = EmbeddingMigration::with_config(old, engine(16), config);
+
+        migration.index("doc3", "rust async runtime").await.unwrap();
+        let progress = migration.progress().await;
+        assert_eq!((progress.old_documents, progress.new_documents, progress.pending_backfill), (3, 1, 2));
+
+        let results = migration.search("rust", 10).await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].doc_id, "doc3");
+        assert_eq!(migration.progress().await.fallback_queries, 1);
+
+        assert_eq!(migration.backfill().await.unwrap(), 1);
+        assert_eq!(migration.backfill().await.unwrap(), 1);
+        assert_eq!(migration.backfill().await.unwrap(), 0);
+
+        assert_eq!(migration.search("rust", 10).await.unwrap().len(), 3);
+        let progress = migration.progress().await;
+        assert_eq!(progress.coverage, 1.0);
+        assert_eq!(progress.fallback_queries, 1);
+        assert!(!progress.safe_to_cutover);
+    }
+
+    #[tokio::test]
+    async fn test_cutover_waits_for_clean_searches() {
+        let mut old = engine(8);
+        old.index("doc1", "rust programming language").await.unwrap();
+        let config = MigrationConfig {
+            clean_queries_required: 2,
+            ..Default::default()
+        };
+        let migration = EmbeddingMigration::with_config(old, engine(16), config);
+        migration.backfill().await.unwrap();
+        migration.search("rust", 5).await.unwrap();
+
+        let migration = migration.complete().await.err().unwrap();
+
+        migration.search("rust", 5).await.unwrap();
+        assert!(migration.progress().await.safe_to_cutover);
+        let new = migration.complete().await.ok().unwrap();
+        assert!(new.contains("doc1"));
+    }
+}
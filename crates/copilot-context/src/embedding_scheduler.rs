@@ -0,0 +1,683 @@
+//! Batch embedding scheduler
+//!
+//! [`EmbeddingScheduler`] sits between callers and an embedding provider.
+//! Texts from concurrent callers are queued and sent to the provider in
+//! batches, within the provider's requests- and tokens-per-minute limits.
+//! Rate limit responses ([`ContextError::RateLimited`]) are retried with
+//! exponential backoff.
+//!
+//! Every text is queued at a [`EmbeddingPriority`]. Interactive texts (search
+//! queries) are always batched before background ones (ingestion), one
+//! batch slot and a share of the rate budget are kept free for them, so a
+//! large import does not slow down the user waiting on an answer.
+//!
+//! Within a priority, texts are batched in weighted fair order between
+//! tenants (see [`copilot_core::fairness`]), costed by their tokens, so one
+//! tenant's bulk import does not hold back another tenant's.
+
+use async_trait::async_trait;
+use copilot_core::fairness::{render_queue_stats, FairQueue, TenantQueueStats, TenantShare};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+use crate::{
+    hybrid_search::{Embedding, EmbeddingProvider},
+    ContextError, Result,
+};
+
+/// Window the per-minute limits are measured over
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Who is waiting on an embedding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingPriority {
+    /// A user is waiting, e.g. on a search
+    Interactive,
+    /// Nobody is waiting, e.g. ingestion
+    Background,
+}
+
+/// Embedding scheduler configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingSchedulerConfig {
+    /// Most texts sent in one request
+    pub max_batch_size: usize,
+    /// Most (estimated) tokens sent in one request
+    pub max_batch_tokens: usize,
+    /// Requests in flight at once
+    pub max_concurrent_batches: usize,
+    /// Provider request limit, if any
+    pub requests_per_minute: Option<usize>,
+    /// Provider token limit, if any
+    pub tokens_per_minute: Option<usize>,
+    /// Share of the per-minute limits background work may not use
+    pub interactive_reserve: f64,
+    /// Retries of a rate-limited request before giving up
+    pub max_retries: usize,
+    /// Backoff before the first retry; doubles on each one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for EmbeddingSchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 64,
+            max_batch_tokens: 8_000,
+            max_concurrent_batches: 4,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            interactive_reserve: 0.2,
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl EmbeddingSchedulerConfig {
+    /// Respect a provider's requests- and tokens-per-minute limits
+    pub fn with_rate_limits(mut self, requests_per_minute: usize, tokens_per_minute: usize) -> Self {
+        self.requests_per_minute = Some(requests_per_minute);
+        self.tokens_per_minute = Some(tokens_per_minute);
+        self
+    }
+
+    pub fn with_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.max(1);
+        self
+    }
+
+    pub fn with_concurrency(mut self, max_concurrent_batches: usize) -> Self {
+        self.max_concurrent_batches = max_concurrent_batches.max(1);
+        self
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    fn backoff(&self, retry: usize) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry as u32))
+            .min(self.max_backoff)
+    }
+}
+
+/// Rough token count of a text; providers tokenize differently, and the
+/// budget only needs to be close
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4).max(1)
+}
+
+/// Embedding scheduler counters
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingSchedulerStats {
+    /// Interactive texts waiting for a batch
+    pub queued_interactive: usize,
+    /// Background texts waiting for a batch
+    pub queued_background: usize,
+    /// Requests sent to the provider, retries included
+    pub batches: u64,
+    /// Texts embedded
+    pub embedded: u64,
+    /// Rate limit responses from the provider
+    pub rate_limited: u64,
+    /// Times a batch waited for the per-minute budget
+    pub budget_waits: u64,
+    /// Texts that could not be embedded
+    pub failed: u64,
+    /// Queueing per tenant, both priorities together
+    #[serde(default)]
+    pub tenants: Vec<TenantQueueStats>,
+}
+
+impl EmbeddingSchedulerStats {
+    /// Render the counters in the Prometheus text exposition format
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        for (name, kind, help, value) in [
+            ("queued_interactive", "gauge", "Interactive texts waiting to be embedded", self.queued_interactive as f64),
+            ("queued_background", "gauge", "Background texts waiting to be embedded", self.queued_background as f64),
+            ("batches_total", "counter", "Embedding requests sent to the provider", self.batches as f64),
+            ("embedded_total", "counter", "Texts embedded", self.embedded as f64),
+            ("rate_limited_total", "counter", "Rate limit responses from the embedding provider", self.rate_limited as f64),
+            ("budget_waits_total", "counter", "Batches held back by the per-minute budget", self.budget_waits as f64),
+            ("failed_total", "counter", "Texts that could not be embedded", self.failed as f64),
+        ] {
+            let _ = writeln!(out, "# HELP {prefix}_embedding_scheduler_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_embedding_scheduler_{name} {kind}");
+            let _ = writeln!(out, "{prefix}_embedding_scheduler_{name} {value}");
+        }
+        out.push_str(&render_queue_stats(prefix, "embedding", &self.tenants));
+        out
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    batches: AtomicU64,
+    embedded: AtomicU64,
+    rate_limited: AtomicU64,
+    budget_waits: AtomicU64,
+    failed: AtomicU64,
+}
+
+struct Job {
+    text: String,
+    tokens: usize,
+    reply: oneshot::Sender<Result<Embedding>>,
+}
+
+/// Requests sent in the last minute, for the per-minute limits
+#[derive(Default)]
+struct RateBudget {
+    sent: VecDeque<(Instant, usize)>,
+    /// Set by a rate limit response; nothing is sent before then
+    paused_until: Option<Instant>,
+}
+
+impl RateBudget {
+    /// Reserve a request of `tokens` now, or say how long to wait first.
+    /// `share` is the part of the limits the caller may use.
+    fn reserve(
+        &mut self,
+        now: Instant,
+        tokens: usize,
+        config: &EmbeddingSchedulerConfig,
+        share: f64,
+    ) -> Option<Duration> {
+        if let Some(until) = self.paused_until {
+            if until > now {
+                return Some(until - now);
+            }
+            self.paused_until = None;
+        }
+        while self.sent.front().is_some_and(|(at, _)| now.duration_since(*at) >= RATE_WINDOW) {
+            self.sent.pop_front();
+        }
+
+        let until_oldest_expires = || {
+            self.sent
+                .front()
+                .map(|(at, _)| RATE_WINDOW.saturating_sub(now.duration_since(*at)))
+                .unwrap_or_default()
+        };
+        let scaled = |limit: usize| ((limit as f64 * share).floor() as usize).max(1);
+        if let Some(limit) = config.requests_per_minute {
+            if self.sent.len() >= scaled(limit) {
+                return Some(until_oldest_expires());
+            }
+        }
+        if let Some(limit) = config.tokens_per_minute {
+            let used: usize = self.sent.iter().map(|(_, tokens)| tokens).sum();
+            // A batch over the whole budget still goes once the window is empty
+            if !self.sent.is_empty() && used + tokens > scaled(limit) {
+                return Some(until_oldest_expires());
+            }
+        }
+
+        self.sent.push_back((now, tokens));
+        None
+    }
+}
+
+#[derive(Default)]
+struct Queues {
+    interactive: FairQueue<Job>,
+    background: FairQueue<Job>,
+    in_flight: usize,
+}
+
+struct Inner {
+    provider: Arc<dyn EmbeddingProvider>,
+    config: EmbeddingSchedulerConfig,
+    queues: Mutex<Queues>,
+    budget: Mutex<RateBudget>,
+    counters: Counters,
+}
+
+/// Batches and rate-limits embedding calls, interactive ones first
+#[derive(Clone)]
+pub struct EmbeddingScheduler {
+    inner: Arc<Inner>,
+}
+
+impl EmbeddingScheduler {
+    pub fn new(provider: Arc<dyn EmbeddingProvider>, config: EmbeddingSchedulerConfig) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                provider,
+                config,
+                queues: Mutex::new(Queues::default()),
+                budget: Mutex::new(RateBudget::default()),
+                counters: Counters::default(),
+            }),
+        }
+    }
+
+    /// Embed `texts`, batched with other callers' texts at `priority`
+    pub async fn embed_with_priority(
+        &self,
+        texts: &[&str],
+        priority: EmbeddingPriority,
+    ) -> Result<Vec<Embedding>> {
+        let share = TenantShare::current_or_unattributed();
+        let mut replies = Vec::with_capacity(texts.len());
+        {
+            let mut queues = self.inner.queues.lock();
+            let queue = match priority {
+                EmbeddingPriority::Interactive => &mut queues.interactive,
+                EmbeddingPriority::Background => &mut queues.background,
+            };
+            for text in texts {
+                let (reply, receiver) = oneshot::channel();
+                let tokens = estimate_tokens(text);
+                queue.push(
+                    &share,
+                    tokens as f64,
+                    Job {
+                        text: text.to_string(),
+                        tokens,
+                        reply,
+                    },
+                );
+                replies.push(receiver);
+            }
+        }
+        self.dispatch();
+
+        let mut embeddings = Vec::with_capacity(replies.len());
+        for reply in replies {
+            let embedding = reply
+                .await
+                .map_err(|_| ContextError::RetrievalFailed("Embedding scheduler dropped a request".to_string()))??;
+            embeddings.push(embedding);
+        }
+        Ok(embeddings)
+    }
+
+    /// An [`EmbeddingProvider`] whose calls are scheduled at `priority`
+    pub fn provider(&self, priority: EmbeddingPriority) -> ScheduledEmbeddings {
+        ScheduledEmbeddings {
+            scheduler: self.clone(),
+            priority,
+        }
+    }
+
+    /// Queue lengths and counters so far
+    pub fn stats(&self) -> EmbeddingSchedulerStats {
+        let (queued_interactive, queued_background, tenants) = {
+            let queues = self.inner.queues.lock();
+            let mut tenants = queues.interactive.stats();
+            for background in queues.background.stats() {
+                match tenants.iter_mut().find(|t| t.tenant_id == background.tenant_id) {
+                    Some(tenant) => tenant.merge(&background),
+                    None => tenants.push(background),
+                }
+            }
+            tenants.sort_by(|a, b| a.tenant_id.cmp(&b.tenant_id));
+            (queues.interactive.len(), queues.background.len(), tenants)
+        };
+        let counters = &self.inner.counters;
+        EmbeddingSchedulerStats {
+            queued_interactive,
+            queued_background,
+            batches: counters.batches.load(Ordering::Relaxed),
+            embedded: counters.embedded.load(Ordering::Relaxed),
+            rate_limited: counters.rate_limited.load(Ordering::Relaxed),
+            budget_waits: counters.budget_waits.load(Ordering::Relaxed),
+            failed: counters.failed.load(Ordering::Relaxed),
+            tenants,
+        }
+    }
+
+    /// Start batches for queued texts while there are free slots
+    fn dispatch(&self) {
+        let config = &self.inner.config;
+        // One slot stays free for interactive batches when there is more than one
+        let reserved = usize::from(config.max_concurrent_batches > 1);
+
+        loop {
+            let (batch, priority) = {
+                let mut queues = self.inner.queues.lock();
+                if queues.in_flight >= config.max_concurrent_batches {
+                    return;
+                }
+                let (queue, priority) = if !queues.interactive.is_empty() {
+                    (&mut queues.interactive, EmbeddingPriority::Interactive)
+                } else if queues.in_flight + reserved < config.max_concurrent_batches {
+                    (&mut queues.background, EmbeddingPriority::Background)
+                } else {
+                    return;
+                };
+
+                let mut batch: Vec<Job> = Vec::new();
+                let mut tokens = 0;
+                while let Some(job) = queue.peek() {
+                    if !batch.is_empty()
+                        && (batch.len() >= config.max_batch_size || tokens + job.tokens > config.max_batch_tokens)
+                    {
+                        break;
+                    }
+                    tokens += job.tokens;
+                    batch.extend(queue.pop().map(|queued| queued.item));
+                }
+                if batch.is_empty() {
+                    return;
+                }
+                queues.in_flight += 1;
+                (batch, priority)
+            };
+
+            let scheduler = self.clone();
+            tokio::spawn(async move {
+                scheduler.run_batch(batch, priority).await;
+                scheduler.inner.queues.lock().in_flight -= 1;
+                scheduler.dispatch();
+            });
+        }
+    }
+
+    async fn run_batch(&self, batch: Vec<Job>, priority: EmbeddingPriority) {
+        let tokens: usize = batch.iter().map(|job| job.tokens).sum();
+        let texts: Vec<&str> = batch.iter().map(|job| job.text.as_str()).collect();
+        let counters = &self.inner.counters;
+
+        let mut retries = 0;
+        let result = loop {
+            self.wait_for_budget(tokens, priority).await;
+            counters.batches.fetch_add(1, Ordering::Relaxed);
+            match self.inner.provider.embed_batch(&texts).await {
+                Err(ContextError::RateLimited { retry_after }) if retries < self.inner.config.max_retries => {
+                    counters.rate_limited.fetch_add(1, Ordering::Relaxed);
+                    let backoff = retry_after.unwrap_or_else(|| self.inner.config.backoff(retries));
+                    retries += 1;
+                    warn!(
+                        "Embedding provider rate limited, retry {} in {:?}",
+                        retries, backoff
+                    );
+                    // Hold back every batch, not just this one
+                    let until = Instant::now() + backoff;
+                    let mut budget = self.inner.budget.lock();
+                    if budget.paused_until.map_or(true, |paused| paused < until) {
+                        budget.paused_until = Some(until);
+                    }
+                }
+                Ok(embeddings) if embeddings.len() != texts.len() => {
+                    break Err(ContextError::RetrievalFailed(format!(
+                        "Embedding provider returned {} embeddings for {} texts",
+                        embeddings.len(),
+                        texts.len()
+                    )));
+                }
+                result => break result,
+            }
+        };
+
+        match result {
+            Ok(embeddings) => {
+                counters.embedded.fetch_add(batch.len() as u64, Ordering::Relaxed);
+                debug!(texts = batch.len(), tokens, ?priority, "Embedded batch");
+                for (job, embedding) in batch.into_iter().zip(embeddings) {
+                    let _ = job.reply.send(Ok(embedding));
+                }
+            }
+            Err(e) => {
+                if matches!(e, ContextError::RateLimited { .. }) {
+                    counters.rate_limited.fetch_add(1, Ordering::Relaxed);
+                }
+                counters.failed.fetch_add(batch.len() as u64, Ordering::Relaxed);
+                warn!(texts = batch.len(), "Embedding batch failed: {}", e);
+                for job in batch {
+                    let error = match &e {
+                        ContextError::RateLimited { retry_after } => ContextError::RateLimited {
+                            retry_after: *retry_after,
+                        },
+                        other => ContextError::RetrievalFailed(other.to_string()),
+                    };
+                    let _ = job.reply.send(Err(error));
+                }
+            }
+        }
+    }
+
+    async fn wait_for_budget(&self, tokens: usize, priority: EmbeddingPriority) {
+        let config = &self.inner.config;
+        let share = match priority {
+            EmbeddingPriority::Interactive => 1.0,
+            EmbeddingPriority::Background => 1.0 - config.interactive_reserve.clamp(0.0, 1.0),
+        };
+        loop {
+            let wait = self.inner.budget.lock().reserve(Instant::now(), tokens, config, share);
+            match wait {
+                None => return,
+                Some(wait) => {
+                    self.inner.counters.budget_waits.fetch_add(1, Ordering::Relaxed);
+                    tokio::time::sleep(wait.max(Duration::from_millis(1))).await;
+                }
+            }
+        }
+    }
+}
+
+/// An [`EmbeddingProvider`] that goes through an [`EmbeddingScheduler`]
+#[derive(Clone)]
+pub struct ScheduledEmbeddings {
+    scheduler: EmbeddingScheduler,
+    priority: EmbeddingPriority,
+}
+
+#[async_trait]
+impl EmbeddingProvider for ScheduledEmbeddings {
+    async fn embed(&self, text: &str) -> Result<Embedding> {
+        let mut embeddings = self.embed_batch(&[text]).await?;
+        embeddings
+            .pop()
+            .ok_or_else(|| ContextError::RetrievalFailed("No embedding returned".to_string()))
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        self.scheduler.embed_with_priority(texts, self.priority).await
+    }
+
+    fn dimension(&self) -> usize {
+        self.scheduler.inner.provider.dimension()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockEmbeddingProvider;
+
+    /// Records the first text of each batch, rate limiting the first
+    /// `rate_limited` calls
+    struct RecordingProvider {
+        inner: MockEmbeddingProvider,
+        batches: Mutex<Vec<(String, usize)>>,
+        rate_limited: Mutex<usize>,
+        delay: Duration,
+    }
+
+    impl RecordingProvider {
+        fn new(rate_limited: usize, delay: Duration) -> Arc<Self> {
+            Arc::new(Self {
+                inner: MockEmbeddingProvider::new(4),
+                batches: Mutex::new(Vec::new()),
+                rate_limited: Mutex::new(rate_limited),
+                delay,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for RecordingProvider {
+        async fn embed(&self, text: &str) -> Result<Embedding> {
+            self.inner.embed(text).await
+        }
+
+        async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+            let limited = {
+                let mut remaining = self.rate_limited.lock();
+                let limited = *remaining > 0;
+                *remaining = remaining.saturating_sub(1);
+                limited
+            };
+            if limited {
+                return Err(ContextError::RateLimited {
+                    retry_after: Some(Duration::from_millis(1)),
+                });
+            }
+            self.batches.lock().push((texts[0].to_string(), texts.len()));
+            tokio::time::sleep(self.delay).await;
+            self.inner.embed_batch(texts).await
+        }
+
+        fn dimension(&self) -> usize {
+            4
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batches_queued_texts() {
+        let provider = RecordingProvider::new(0, Duration::ZERO);
+        let scheduler = EmbeddingScheduler::new(
+            provider.clone(),
+            EmbeddingSchedulerConfig::default().with_batch_size(4),
+        );
+
+        let texts: Vec<String> = (0..10).map(|i| format!("chunk {}", i)).collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let embeddings = scheduler
+            .embed_with_priority(&texts, EmbeddingPriority::Background)
+            .await
+            .unwrap();
+
+        assert_eq!(embeddings.len(), 10);
+        let mut sizes: Vec<usize> = provider.batches.lock().iter().map(|(_, size)| *size).collect();
+        sizes.sort_unstable_by(|a, b| b.cmp(a));
+        assert_eq!(sizes, vec![4, 4, 2]);
+        assert_eq!(scheduler.stats().embedded, 10);
+    }
+
+    #[tokio::test]
+    async fn test_retries_rate_limited_batches() {
+        let provider = RecordingProvider::new(2, Duration::ZERO);
+        let scheduler = EmbeddingScheduler::new(provider, EmbeddingSchedulerConfig::default());
+
+        let embedding = scheduler
+            .provider(EmbeddingPriority::Interactive)
+            .embed("latency p99")
+            .await
+            .unwrap();
+        assert_eq!(embedding.len(), 4);
+
+        let stats = scheduler.stats();
+        assert_eq!(stats.rate_limited, 2);
+        assert_eq!(stats.batches, 3);
+
+        // Gives up after the configured retries
+        let provider = RecordingProvider::new(10, Duration::ZERO);
+        let config = EmbeddingSchedulerConfig {
+            max_retries: 1,
+            ..Default::default()
+        };
+        let scheduler = EmbeddingScheduler::new(provider, config);
+        let err = scheduler
+            .embed_with_priority(&["latency p99"], EmbeddingPriority::Interactive)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ContextError::RateLimited { .. }));
+        assert_eq!(scheduler.stats().failed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_interactive_texts_jump_the_queue() {
+        let provider = RecordingProvider::new(0, Duration::from_millis(20));
+        let scheduler = EmbeddingScheduler::new(
+            provider.clone(),
+            EmbeddingSchedulerConfig::default().with_batch_size(2).with_concurrency(1),
+        );
+
+        let background = scheduler.provider(EmbeddingPriority::Background);
+        let import = tokio::spawn(async move {
+            let texts: Vec<String> = (0..8).map(|i| format!("chunk {}", i)).collect();
+            let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+            background.embed_batch(&texts).await.unwrap();
+        });
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        scheduler
+            .embed_with_priority(&["what broke checkout?"], EmbeddingPriority::Interactive)
+            .await
+            .unwrap();
+        import.await.unwrap();
+
+        // Only the background batch already in flight went first
+        let order: Vec<String> = provider.batches.lock().iter().map(|(first, _)| first.clone()).collect();
+        assert_eq!(order[0], "chunk 0");
+        assert_eq!(order[1], "what broke checkout?");
+        assert_eq!(order.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_tenants_share_background_batches() {
+        let provider = RecordingProvider::new(0, Duration::from_millis(20));
+        let scheduler = EmbeddingScheduler::new(
+            provider.clone(),
+            EmbeddingSchedulerConfig::default().with_batch_size(2).with_concurrency(1),
+        );
+
+        let background = scheduler.provider(EmbeddingPriority::Background);
+        let import = tokio::spawn(TenantShare::new("noisy", 1.0).scope(async move {
+            let texts: Vec<String> = (0..8).map(|i| format!("chunk {}", i)).collect();
+            let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+            background.embed_batch(&texts).await.unwrap();
+        }));
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        TenantShare::new("quiet", 1.0)
+            .scope(scheduler.embed_with_priority(&["tls"], EmbeddingPriority::Background))
+            .await
+            .unwrap();
+        import.await.unwrap();
+
+        // The quiet tenant's text is not stuck behind the whole import
+        let order: Vec<String> = provider.batches.lock().iter().map(|(first, _)| first.clone()).collect();
+        assert_eq!(order[1], "tls");
+
+        let stats = scheduler.stats();
+        let tenants: Vec<(&str, u64)> = stats.tenants.iter().map(|t| (t.tenant_id.as_str(), t.granted)).collect();
+        assert_eq!(tenants, [("noisy", 8), ("quiet", 1)]);
+        assert!(stats
+            .render_prometheus("copilot")
+            .contains("copilot_fair_queue_granted_total{resource=\"embedding\",tenant=\"quiet\"} 1"));
+    }
+
+    #[test]
+    fn test_rate_budget_reserves_share_for_interactive() {
+        let config = EmbeddingSchedulerConfig::default().with_rate_limits(10, 100);
+        let mut budget = RateBudget::default();
+        let now = Instant::now();
+
+        // Background may use 80 of the 100 tokens per minute
+        assert!(budget.reserve(now, 60, &config, 0.8).is_none());
+        let wait = budget.reserve(now, 30, &config, 0.8).unwrap();
+        assert_eq!(wait, RATE_WINDOW);
+        assert!(budget.reserve(now, 30, &config, 1.0).is_none());
+
+        // The window frees up a minute after the first request
+        let later = now + RATE_WINDOW;
+        assert!(budget.reserve(later, 60, &config, 0.8).is_none());
+    }
+}
@@ -1,18 +1,29 @@
 //! Context Engine Implementation
 //!
 //! Main engine for managing multi-tier context storage, retrieval, and compression.
+//! With a [`ContextArchive`] set, maintenance also moves long-inactive items
+//! to the archive tier (see [`crate::archive`]).
 
 use async_trait::async_trait;
+use copilot_core::{Clock, SystemClock};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
 use std::sync::Arc;
 use tiktoken_rs::{get_bpe_from_model, CoreBPE};
+use tracing::warn;
 use uuid::Uuid;
 
 use crate::{
+    archive::{ContextArchive, CONTENT_LOADING},
+    authority::SourceAuthority,
     compression::{CompressionConfig, Compressor, TokenBudgetManager},
-    memory::{ImportanceScorer, InMemoryStore, MemoryItem, MemoryMetadata, MemoryStore, MemoryTier},
+    filter::ContextFilter,
+    memory::{ImportanceScorer, MemoryItem, MemoryMetadata, MemoryStore, MemoryTier},
     retrieval::{ContextWindow, RetrievalConfig, RetrievalResult},
+    sharded::ShardedMemoryStore,
+    wal::{WalConfig, WalMemoryStore},
     ContextError, Result,
 };
 
@@ -69,6 +80,9 @@ pub trait ContextEngine: Send + Sync {
     /// Retrieve relevant context within token budget
     async fn retrieve(&self, query: &str) -> Result<RetrievalResult>;
 
+    /// Retrieve relevant context among the items matching `filter`
+    async fn retrieve_filtered(&self, query: &str, filter: &ContextFilter) -> Result<RetrievalResult>;
+
     /// Compress context when approaching limits
     async fn compress(&self) -> Result<CompressionStats>;
 
@@ -84,24 +98,52 @@ pub trait ContextEngine: Send + Sync {
     /// Remove item from storage
     async fn remove(&self, id: &Uuid) -> Result<()>;
 
+    /// Stored items matching `filter`
+    async fn list_matching(&self, filter: &ContextFilter) -> Result<Vec<MemoryItem>>;
+
+    /// Add and remove tags on an item
+    async fn retag(&self, id: &Uuid, add: &[String], remove: &[String]) -> Result<()>;
+
+    /// Recompute an item's derived data (token count, compressed form)
+    /// from its content, e.g. after the tokenizer model changed
+    async fn reindex(&self, id: &Uuid) -> Result<()>;
+
+    /// Authority weights by source prefix
+    fn source_weights(&self) -> BTreeMap<String, f64>;
+
+    /// Weight retrieval scores of items from sources starting with `prefix`
+    fn set_source_weight(&self, prefix: &str, weight: f64) -> Result<()>;
+
+    /// Return a source prefix to the neutral weight; false if it had none
+    fn remove_source_weight(&self, prefix: &str) -> bool;
+
     /// Clear all context
     async fn clear(&self) -> Result<()>;
 
-    /// Run maintenance (tier management, compression, eviction)
+    /// Run maintenance (tier management, archiving, compression, eviction)
     async fn maintenance(&self) -> Result<MaintenanceReport>;
+
+    /// Bring the archived items matching `filter` back into memory ahead of
+    /// their retrieval, returning how many were rehydrated
+    async fn warm_up(&self, filter: &ContextFilter) -> Result<usize>;
 }
 
 /// Implementation of the context engine
 pub struct ContextEngineImpl {
     config: ContextEngineConfig,
-    short_term: Arc<tokio::sync::RwLock<InMemoryStore>>,
-    medium_term: Arc<tokio::sync::RwLock<InMemoryStore>>,
-    long_term: Arc<tokio::sync::RwLock<InMemoryStore>>,
+    short_term: Arc<dyn MemoryStore>,
+    medium_term: Arc<dyn MemoryStore>,
+    long_term: Arc<dyn MemoryStore>,
+    /// Archived items: metadata and search terms, content in the archive
+    archived: Arc<dyn MemoryStore>,
+    archive: Option<Arc<ContextArchive>>,
     budget_manager: Arc<tokio::sync::RwLock<TokenBudgetManager>>,
     compressor: Compressor,
     context_window: ContextWindow,
     tokenizer: CoreBPE,
     item_index: Arc<DashMap<Uuid, MemoryTier>>, // Quick lookup for item location
+    clock: Arc<dyn Clock>,
+    authority: Arc<SourceAuthority>,
 }
 
 impl ContextEngineImpl {
@@ -112,38 +154,94 @@ impl ContextEngineImpl {
 
         let budget_manager = TokenBudgetManager::new(config.max_tokens, config.target_utilization);
         let compressor = Compressor::new(config.compression.clone())?;
-        let context_window = ContextWindow::new(config.retrieval.clone())?;
+        let authority = Arc::new(SourceAuthority::new());
+        let context_window = ContextWindow::new(config.retrieval.clone())?.with_authority(authority.clone());
 
         Ok(Self {
             config,
-            short_term: Arc::new(tokio::sync::RwLock::new(InMemoryStore::new(
-                MemoryTier::ShortTerm,
-            ))),
-            medium_term: Arc::new(tokio::sync::RwLock::new(InMemoryStore::new(
-                MemoryTier::MediumTerm,
-            ))),
-            long_term: Arc::new(tokio::sync::RwLock::new(InMemoryStore::new(
-                MemoryTier::LongTerm,
-            ))),
+            short_term: Arc::new(ShardedMemoryStore::new(MemoryTier::ShortTerm)),
+            medium_term: Arc::new(ShardedMemoryStore::new(MemoryTier::MediumTerm)),
+            long_term: Arc::new(ShardedMemoryStore::new(MemoryTier::LongTerm)),
+            archived: Arc::new(ShardedMemoryStore::new(MemoryTier::Archive)),
+            archive: None,
             budget_manager: Arc::new(tokio::sync::RwLock::new(budget_manager)),
             compressor,
             context_window,
             tokenizer,
             item_index: Arc::new(DashMap::new()),
+            clock: Arc::new(SystemClock),
+            authority,
         })
     }
 
+    /// Stamp stored items and score retrievals with `clock`
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.context_window = self.context_window.with_clock(clock.clone());
+        self.clock = clock;
+        self
+    }
+
+    /// Share source authority weights, e.g. loaded from the admin's
+    /// saved configuration
+    pub fn with_authority(mut self, authority: Arc<SourceAuthority>) -> Self {
+        self.context_window = self.context_window.with_authority(authority.clone());
+        self.authority = authority;
+        self
+    }
+
+    /// Archive long-inactive items during maintenance and rehydrate them
+    /// when retrieved
+    pub fn with_archive(mut self, archive: Arc<ContextArchive>) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Log each tier's mutations to a write-ahead log in `dir`, first
+    /// rebuilding the tiers from the logs a previous run left there
+    pub async fn with_write_ahead_log(mut self, dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        self.short_term = Self::logged_tier(dir, MemoryTier::ShortTerm).await?;
+        self.medium_term = Self::logged_tier(dir, MemoryTier::MediumTerm).await?;
+        self.long_term = Self::logged_tier(dir, MemoryTier::LongTerm).await?;
+        self.archived = Self::logged_tier(dir, MemoryTier::Archive).await?;
+
+        let mut budget = self.budget_manager.write().await;
+        for tier in [MemoryTier::ShortTerm, MemoryTier::MediumTerm, MemoryTier::LongTerm, MemoryTier::Archive] {
+            for item in self.get_store(tier).list().await? {
+                self.item_index.insert(item.metadata.id, tier);
+                // Archived content is not held in memory
+                if tier != MemoryTier::Archive {
+                    budget.add_tokens(item.token_count)?;
+                }
+            }
+        }
+        drop(budget);
+        Ok(self)
+    }
+
+    async fn logged_tier(dir: &Path, tier: MemoryTier) -> Result<Arc<dyn MemoryStore>> {
+        let name = match tier {
+            MemoryTier::ShortTerm => "short_term",
+            MemoryTier::MediumTerm => "medium_term",
+            MemoryTier::LongTerm => "long_term",
+            MemoryTier::Archive => "archive",
+        };
+        let config = WalConfig::new(dir.join(format!("{}.wal", name)));
+        Ok(Arc::new(WalMemoryStore::open(ShardedMemoryStore::new(tier), config).await?))
+    }
+
     /// Count tokens in text
     fn count_tokens(&self, text: &str) -> usize {
         self.tokenizer.encode_with_special_tokens(text).len()
     }
 
     /// Get the appropriate store for a tier
-    fn get_store(&self, tier: MemoryTier) -> Arc<tokio::sync::RwLock<InMemoryStore>> {
+    fn get_store(&self, tier: MemoryTier) -> Arc<dyn MemoryStore> {
         match tier {
             MemoryTier::ShortTerm => self.short_term.clone(),
             MemoryTier::MediumTerm => self.medium_term.clone(),
             MemoryTier::LongTerm => self.long_term.clone(),
+            MemoryTier::Archive => self.archived.clone(),
         }
     }
 
@@ -158,19 +256,74 @@ impl ContextEngineImpl {
         }
     }
 
-    /// Collect all items from all tiers
-    async fn collect_all_items(&self) -> Result<Vec<MemoryItem>> {
-        let mut all_items = Vec::new();
+    /// Collect the items matching `filter` from all tiers; archived items
+    /// carry their search terms as content
+    async fn collect_matching_items(&self, filter: &ContextFilter) -> Result<Vec<MemoryItem>> {
+        let mut items = Vec::new();
 
-        let short = self.short_term.read().await.list().await?;
-        let medium = self.medium_term.read().await.list().await?;
-        let long = self.long_term.read().await.list().await?;
+        items.extend(self.short_term.list_matching(filter).await?);
+        items.extend(self.medium_term.list_matching(filter).await?);
+        items.extend(self.long_term.list_matching(filter).await?);
+        items.extend(self.archived.list_matching(filter).await?);
+        filter.retain_versions_as_of(&mut items);
+
+        Ok(items)
+    }
 
-        all_items.extend(short);
-        all_items.extend(medium);
-        all_items.extend(long);
+    /// What a rehydration needs of the engine, if it has an archive
+    fn rehydration(&self) -> Option<Rehydration> {
+        Some(Rehydration {
+            archive: self.archive.clone()?,
+            archived: self.archived.clone(),
+            short_term: self.short_term.clone(),
+            item_index: self.item_index.clone(),
+            budget_manager: self.budget_manager.clone(),
+            clock: self.clock.clone(),
+        })
+    }
 
-        Ok(all_items)
+    /// The archive, if it has a store to archive to
+    fn enabled_archive(&self) -> Option<&ContextArchive> {
+        self.archive.as_deref().filter(|archive| archive.is_enabled())
+    }
+
+    /// Archive the items not accessed for longer than the archive's
+    /// `inactive_after`, returning how many were archived
+    async fn archive_inactive(&self) -> Result<usize> {
+        let Some(archive) = self.enabled_archive() else {
+            return Ok(0);
+        };
+        let Ok(inactive_after) = chrono::Duration::from_std(archive.config().inactive_after) else {
+            return Ok(0);
+        };
+        let cutoff = self.clock.now() - inactive_after;
+        let mut archived = 0;
+        for tier in [MemoryTier::ShortTerm, MemoryTier::MediumTerm, MemoryTier::LongTerm] {
+            for item in self.get_store(tier).list().await? {
+                if item.last_accessed <= cutoff && self.archive_item(archive, &item).await? {
+                    archived += 1;
+                }
+            }
+        }
+        Ok(archived)
+    }
+
+    /// Move an item's content to the archive, keeping its metadata and
+    /// search terms in memory; false if it was removed meanwhile
+    async fn archive_item(&self, archive: &ContextArchive, item: &MemoryItem) -> Result<bool> {
+        let id = item.metadata.id;
+        archive.put(&id, &item.content).await?;
+        let Some(mut stub) = self.get_store(item.tier).take(&id).await? else {
+            archive.delete(&id).await?;
+            return Ok(false);
+        };
+        stub.content = archive.term_sketch(&item.content);
+        stub.compressed_content = None;
+        stub.tier = MemoryTier::Archive;
+        self.archived.store(stub).await?;
+        self.item_index.insert(id, MemoryTier::Archive);
+        self.budget_manager.write().await.remove_tokens(item.token_count);
+        Ok(true)
     }
 
     /// Manage tiers automatically (promote/demote based on access patterns)
@@ -179,8 +332,7 @@ impl ContextEngineImpl {
 
         // Check each tier for promotion/demotion candidates
         for tier in [MemoryTier::ShortTerm, MemoryTier::MediumTerm, MemoryTier::LongTerm] {
-            let store = self.get_store(tier);
-            let items = store.read().await.list().await?;
+            let items = self.get_store(tier).list().await?;
 
             for item in items {
                 if let Some(new_tier) = item.should_promote() {
@@ -201,20 +353,15 @@ impl ContextEngineImpl {
         let from_store = self.get_store(from);
         let to_store = self.get_store(to);
 
-        // Get item from source tier
+        // Take item from source tier
         let mut item = from_store
-            .read()
-            .await
-            .retrieve(id)
+            .take(id)
             .await?
             .ok_or_else(|| ContextError::ItemNotFound(id.to_string()))?;
 
-        // Update tier
+        // Update tier and move to new tier
         item.tier = to;
-
-        // Move to new tier
-        to_store.write().await.store(item).await?;
-        from_store.write().await.remove(id).await?;
+        to_store.store(item).await?;
 
         // Update index
         self.item_index.insert(*id, to);
@@ -228,32 +375,44 @@ impl ContextEngineImpl {
 
         // Evict from short-term first
         if tokens_freed < tokens_needed {
-            let freed = self.short_term
-                .write()
-                .await
-                .evict(self.short_term.read().await.total_tokens().await? - tokens_needed)
-                .await?
-                .iter()
-                .map(|item| item.token_count)
-                .sum::<usize>();
-            tokens_freed += freed;
+            let target = self.short_term.total_tokens().await?.saturating_sub(tokens_needed);
+            tokens_freed += self.evict_from(self.short_term.as_ref(), target).await?;
         }
 
         // Then medium-term if needed
         if tokens_freed < tokens_needed {
-            let freed = self.medium_term
-                .write()
-                .await
-                .evict(self.medium_term.read().await.total_tokens().await? - (tokens_needed - tokens_freed))
+            let target = self
+                .medium_term
+                .total_tokens()
                 .await?
-                .iter()
-                .map(|item| item.token_count)
-                .sum::<usize>();
-            tokens_freed += freed;
+                .saturating_sub(tokens_needed - tokens_freed);
+            tokens_freed += self.evict_from(self.medium_term.as_ref(), target).await?;
         }
 
         Ok(tokens_freed)
     }
+
+    /// Evict from one tier down to `target_tokens`, returning tokens freed
+    async fn evict_from(&self, store: &dyn MemoryStore, target_tokens: usize) -> Result<usize> {
+        let evicted = store.evict(target_tokens).await?;
+        for item in &evicted {
+            self.item_index.remove(&item.metadata.id);
+        }
+        Ok(evicted.iter().map(|item| item.token_count).sum())
+    }
+
+    /// Apply `f` to a stored item, unless it is no longer in `tier`
+    async fn modify_item<F>(&self, tier: MemoryTier, id: &Uuid, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut MemoryItem) + Send,
+    {
+        let store = self.get_store(tier);
+        if let Some(mut item) = store.retrieve(id).await? {
+            f(&mut item);
+            store.update(item).await?;
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -291,12 +450,11 @@ impl ContextEngine for ContextEngineImpl {
         let tier = self.select_tier(importance);
 
         // Create memory item
-        let item = MemoryItem::new(content, metadata, importance, token_count);
+        let item = MemoryItem::new_at(content, metadata, importance, token_count, self.clock.now());
         let id = item.metadata.id;
 
         // Store in appropriate tier
-        let store = self.get_store(tier);
-        store.write().await.store(item).await?;
+        self.get_store(tier).store(item).await?;
 
         // Update index
         self.item_index.insert(id, tier);
@@ -305,23 +463,43 @@ impl ContextEngine for ContextEngineImpl {
     }
 
     async fn retrieve(&self, query: &str) -> Result<RetrievalResult> {
-        // Collect all items
-        let all_items = self.collect_all_items().await?;
+        self.retrieve_filtered(query, &ContextFilter::default()).await
+    }
+
+    async fn retrieve_filtered(&self, query: &str, filter: &ContextFilter) -> Result<RetrievalResult> {
+        // Collect only the items the filter admits
+        let candidates = self.collect_matching_items(filter).await?;
 
         // Use context window to retrieve relevant items
-        let result = self.context_window.retrieve_optimized(query, all_items)?;
+        let mut result = self.context_window.retrieve_optimized(query, candidates)?;
+
+        // Archived items are returned loading while their content is fetched
+        // for the next retrieval
+        for scored in result.selected.iter_mut().chain(result.rejected.iter_mut()) {
+            if scored.item.tier == MemoryTier::Archive {
+                scored.item.content = CONTENT_LOADING.to_string();
+            }
+        }
+        if let Some(rehydration) = self.rehydration() {
+            for scored in result.selected.iter().filter(|scored| scored.item.tier == MemoryTier::Archive) {
+                rehydration.archive.record_placeholder_hit();
+                let (rehydration, id) = (rehydration.clone(), scored.item.metadata.id);
+                tokio::spawn(async move {
+                    if let Err(e) = rehydration.run(&id).await {
+                        warn!("Failed to rehydrate context item {}: {}", id, e);
+                    }
+                });
+            }
+        }
 
         // Update access statistics for retrieved items
+        let now = self.clock.now();
         for scored in &result.selected {
-            if let Some(tier) = self.item_index.get(&scored.item.metadata.id) {
-                let store = self.get_store(*tier);
-                let mut store_write = store.write().await;
-
-                if let Some(mut item) = store_write.retrieve(&scored.item.metadata.id).await? {
-                    item.record_access();
-                    store_write.update(item).await?;
-                }
-            }
+            let id = scored.item.metadata.id;
+            let Some(tier) = self.item_index.get(&id).map(|tier| *tier) else {
+                continue;
+            };
+            self.modify_item(tier, &id, |item| item.record_access_at(now)).await?;
         }
 
         Ok(result)
@@ -333,7 +511,7 @@ impl ContextEngine for ContextEngineImpl {
         // Compress items in each tier
         for tier in [MemoryTier::ShortTerm, MemoryTier::MediumTerm, MemoryTier::LongTerm] {
             let store = self.get_store(tier);
-            let items = store.read().await.list().await?;
+            let items = store.list().await?;
 
             for item in items {
                 if item.compressed_content.is_some() {
@@ -347,7 +525,7 @@ impl ContextEngine for ContextEngineImpl {
                     let mut updated_item = item.clone();
                     updated_item.compressed_content = Some(compressed);
 
-                    store.write().await.update(updated_item).await?;
+                    store.update(updated_item).await?;
 
                     stats.items_compressed += 1;
                     stats.tokens_saved += item.token_count - compressed_tokens;
@@ -365,14 +543,15 @@ impl ContextEngine for ContextEngineImpl {
     }
 
     async fn stats(&self) -> Result<EngineStats> {
-        let short_tokens = self.short_term.read().await.total_tokens().await?;
-        let medium_tokens = self.medium_term.read().await.total_tokens().await?;
-        let long_tokens = self.long_term.read().await.total_tokens().await?;
+        let short_tokens = self.short_term.total_tokens().await?;
+        let medium_tokens = self.medium_term.total_tokens().await?;
+        let long_tokens = self.long_term.total_tokens().await?;
         let total_tokens = short_tokens + medium_tokens + long_tokens;
 
-        let short_items = self.short_term.read().await.list().await?.len();
-        let medium_items = self.medium_term.read().await.list().await?.len();
-        let long_items = self.long_term.read().await.list().await?.len();
+        let short_items = self.short_term.count().await?;
+        let medium_items = self.medium_term.count().await?;
+        let long_items = self.long_term.count().await?;
+        let archived_items = self.archived.count().await?;
 
         let budget = self.budget_manager.read().await;
 
@@ -385,19 +564,40 @@ impl ContextEngine for ContextEngineImpl {
             short_term_items: short_items,
             medium_term_items: medium_items,
             long_term_items: long_items,
+            archived_items,
             utilization: budget.utilization(),
             within_budget: budget.is_within_budget(),
         })
     }
 
     async fn promote(&self, id: &Uuid, tier: MemoryTier) -> Result<()> {
-        if let Some(current_tier) = self.item_index.get(id) {
-            if *current_tier != tier {
-                self.move_item(id, *current_tier, tier).await?;
+        let current_tier = *self
+            .item_index
+            .get(id)
+            .ok_or_else(|| ContextError::ItemNotFound(id.to_string()))?;
+        if current_tier == tier {
+            return Ok(());
+        }
+        let no_archive = || ContextError::InvalidTier("no archive store is attached".to_string());
+        match (current_tier, tier) {
+            (_, MemoryTier::Archive) => {
+                let archive = self.enabled_archive().ok_or_else(no_archive)?;
+                let item = self
+                    .get_store(current_tier)
+                    .retrieve(id)
+                    .await?
+                    .ok_or_else(|| ContextError::ItemNotFound(id.to_string()))?;
+                self.archive_item(archive, &item).await?;
+                Ok(())
             }
-            Ok(())
-        } else {
-            Err(ContextError::ItemNotFound(id.to_string()))
+            (MemoryTier::Archive, tier) => {
+                self.rehydration().ok_or_else(no_archive)?.run(id).await?;
+                if tier != MemoryTier::ShortTerm {
+                    self.move_item(id, MemoryTier::ShortTerm, tier).await?;
+                }
+                Ok(())
+            }
+            (current_tier, tier) => self.move_item(id, current_tier, tier).await,
         }
     }
 
@@ -407,12 +607,14 @@ impl ContextEngine for ContextEngineImpl {
 
     async fn remove(&self, id: &Uuid) -> Result<()> {
         if let Some((_, tier)) = self.item_index.remove(id) {
-            let store = self.get_store(tier);
-            let item = store.read().await.retrieve(id).await?;
-
-            if let Some(item) = item {
-                self.budget_manager.write().await.remove_tokens(item.token_count);
-                store.write().await.remove(id).await?;
+            match self.get_store(tier).take(id).await? {
+                Some(_) if tier == MemoryTier::Archive => {
+                    if let Some(archive) = &self.archive {
+                        archive.delete(id).await?;
+                    }
+                }
+                Some(item) => self.budget_manager.write().await.remove_tokens(item.token_count),
+                None => {}
             }
 
             Ok(())
@@ -421,10 +623,88 @@ impl ContextEngine for ContextEngineImpl {
         }
     }
 
+    async fn list_matching(&self, filter: &ContextFilter) -> Result<Vec<MemoryItem>> {
+        let mut items = self.collect_matching_items(filter).await?;
+        for item in items.iter_mut().filter(|item| item.tier == MemoryTier::Archive) {
+            item.content = CONTENT_LOADING.to_string();
+        }
+        Ok(items)
+    }
+
+    async fn retag(&self, id: &Uuid, add: &[String], remove: &[String]) -> Result<()> {
+        let tier = *self
+            .item_index
+            .get(id)
+            .ok_or_else(|| ContextError::ItemNotFound(id.to_string()))?;
+        self.modify_item(tier, id, |item| {
+            let tags = &mut item.metadata.tags;
+            tags.retain(|tag| !remove.contains(tag));
+            for tag in add {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
+        })
+        .await
+    }
+
+    async fn reindex(&self, id: &Uuid) -> Result<()> {
+        let mut tier = *self
+            .item_index
+            .get(id)
+            .ok_or_else(|| ContextError::ItemNotFound(id.to_string()))?;
+        if tier == MemoryTier::Archive {
+            // Derived data comes from the content, which is in the archive
+            self.promote(id, MemoryTier::ShortTerm).await?;
+            tier = MemoryTier::ShortTerm;
+        }
+        let item = self
+            .get_store(tier)
+            .retrieve(id)
+            .await?
+            .ok_or_else(|| ContextError::ItemNotFound(id.to_string()))?;
+        let tokens = self.count_tokens(&item.content);
+
+        let mut budget = self.budget_manager.write().await;
+        budget.remove_tokens(item.token_count);
+        if let Err(e) = budget.add_tokens(tokens) {
+            // The old count fit before, so restoring it cannot fail
+            let _ = budget.add_tokens(item.token_count);
+            return Err(e);
+        }
+        drop(budget);
+
+        self.modify_item(tier, id, |item| {
+            item.token_count = tokens;
+            item.compressed_content = None;
+        })
+        .await
+    }
+
+    fn source_weights(&self) -> BTreeMap<String, f64> {
+        self.authority.weights()
+    }
+
+    fn set_source_weight(&self, prefix: &str, weight: f64) -> Result<()> {
+        self.authority.set(prefix, weight)
+    }
+
+    fn remove_source_weight(&self, prefix: &str) -> bool {
+        self.authority.remove(prefix)
+    }
+
     async fn clear(&self) -> Result<()> {
-        self.short_term.write().await.clear().await?;
-        self.medium_term.write().await.clear().await?;
-        self.long_term.write().await.clear().await?;
+        self.short_term.clear().await?;
+        self.medium_term.clear().await?;
+        self.long_term.clear().await?;
+        if let Some(archive) = &self.archive {
+            for item in self.archived.list().await? {
+                if let Err(e) = archive.delete(&item.metadata.id).await {
+                    warn!("Failed to delete archived content of {}: {}", item.metadata.id, e);
+                }
+            }
+        }
+        self.archived.clear().await?;
         self.item_index.clear();
 
         let mut budget = self.budget_manager.write().await;
@@ -443,6 +723,9 @@ impl ContextEngine for ContextEngineImpl {
             report.demotions = tier_stats.demotions;
         }
 
+        // Archiving of long-inactive items
+        report.items_archived = self.archive_inactive().await?;
+
         // Compression
         let budget = self.budget_manager.read().await;
         if budget.utilization() >= self.config.auto_compress_threshold {
@@ -463,6 +746,67 @@ impl ContextEngine for ContextEngineImpl {
 
         Ok(report)
     }
+
+    async fn warm_up(&self, filter: &ContextFilter) -> Result<usize> {
+        let Some(rehydration) = self.rehydration() else {
+            return Ok(0);
+        };
+        let mut rehydrated = 0;
+        for item in self.archived.list_matching(filter).await? {
+            match rehydration.run(&item.metadata.id).await {
+                Ok(true) => rehydrated += 1,
+                Ok(false) => {}
+                Err(e) => warn!("Failed to rehydrate context item {}: {}", item.metadata.id, e),
+            }
+        }
+        Ok(rehydrated)
+    }
+}
+
+/// What a rehydration needs of the engine, so it can run in the background
+#[derive(Clone)]
+struct Rehydration {
+    archive: Arc<ContextArchive>,
+    archived: Arc<dyn MemoryStore>,
+    short_term: Arc<dyn MemoryStore>,
+    item_index: Arc<DashMap<Uuid, MemoryTier>>,
+    budget_manager: Arc<tokio::sync::RwLock<TokenBudgetManager>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Rehydration {
+    /// Bring an archived item back into short-term memory; false if it is
+    /// not archived or already on its way back
+    async fn run(&self, id: &Uuid) -> Result<bool> {
+        if !self.archive.begin_rehydration(id) {
+            return Ok(false);
+        }
+        let result = self.restore(id).await;
+        self.archive.end_rehydration(id, &result);
+        result
+    }
+
+    async fn restore(&self, id: &Uuid) -> Result<bool> {
+        let Some(stub) = self.archived.retrieve(id).await? else {
+            return Ok(false);
+        };
+        let content = self.archive.get(id).await?;
+        self.budget_manager.write().await.add_tokens(stub.token_count)?;
+        let Some(mut item) = self.archived.take(id).await? else {
+            // Removed while its content was fetched
+            self.budget_manager.write().await.remove_tokens(stub.token_count);
+            return Ok(false);
+        };
+        item.content = content;
+        item.tier = MemoryTier::ShortTerm;
+        item.record_access_at(self.clock.now());
+        self.short_term.store(item).await?;
+        self.item_index.insert(*id, MemoryTier::ShortTerm);
+        if let Err(e) = self.archive.delete(id).await {
+            warn!("Failed to delete archived content of {}: {}", id, e);
+        }
+        Ok(true)
+    }
 }
 
 /// Statistics about the context engine
@@ -476,6 +820,9 @@ pub struct EngineStats {
     pub short_term_items: usize,
     pub medium_term_items: usize,
     pub long_term_items: usize,
+    /// Archived items, not counted in `total_items`
+    #[serde(default)]
+    pub archived_items: usize,
     pub utilization: f64,
     pub within_budget: bool,
 }
@@ -502,6 +849,8 @@ pub struct MaintenanceReport {
     pub items_compressed: usize,
     pub tokens_saved: usize,
     pub items_evicted: usize,
+    #[serde(default)]
+    pub items_archived: usize,
 }
 
 #[cfg(test)]
@@ -530,6 +879,176 @@ mod tests {
         assert!(!result.selected.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_retrieve_filtered() {
+        let engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+
+        let code = MemoryMetadata::new("code", "file:///repo/src/cache.rs").with_tags(vec!["backend".to_string()]);
+        let notes = MemoryMetadata::new("document", "https://wiki/cache");
+        engine.store("cache eviction policy for the backend".to_string(), code, 0.8).await.unwrap();
+        engine.store("cache eviction policy notes".to_string(), notes, 0.8).await.unwrap();
+
+        let filter = ContextFilter::new().with_tag("backend").with_content_type("code");
+        let result = engine.retrieve_filtered("cache eviction policy", &filter).await.unwrap();
+        assert_eq!(result.selected.len(), 1);
+        assert_eq!(result.selected[0].item.metadata.source, "file:///repo/src/cache.rs");
+
+        let filter = ContextFilter::new().with_source_prefix("https://");
+        let result = engine.retrieve_filtered("cache eviction policy", &filter).await.unwrap();
+        assert_eq!(result.selected.len(), 1);
+        assert_eq!(result.selected[0].item.metadata.content_type, "document");
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_as_of() {
+        use crate::freshness::{DOCUMENT_KEY, VERSION_KEY};
+        use copilot_core::FrozenClock;
+
+        let clock = Arc::new(FrozenClock::at_epoch());
+        let engine = ContextEngineImpl::new(ContextEngineConfig::default())
+            .unwrap()
+            .with_clock(clock.clone());
+        let runbook = |version: &str| {
+            let mut metadata = MemoryMetadata::new("document", "wiki");
+            metadata.add_custom(DOCUMENT_KEY.to_string(), serde_json::json!("checkout-runbook"));
+            metadata.add_custom(VERSION_KEY.to_string(), serde_json::json!(version));
+            metadata
+        };
+        engine.store("checkout runbook: restart the pods".to_string(), runbook("v1"), 0.8).await.unwrap();
+        clock.advance(chrono::Duration::days(7));
+        let last_tuesday = clock.now();
+        clock.advance(chrono::Duration::days(1));
+        engine.store("checkout runbook: fail over to the standby".to_string(), runbook("v2"), 0.8).await.unwrap();
+
+        let filter = ContextFilter::new().with_as_of(last_tuesday);
+        let result = engine.retrieve_filtered("checkout runbook", &filter).await.unwrap();
+        assert_eq!(result.selected.len(), 1);
+        assert!(result.selected[0].item.content.contains("restart the pods"));
+        assert!(result.selected[0].stale.is_none());
+
+        // Without a time, both versions are retrieved and the old one is flagged
+        let result = engine.retrieve("checkout runbook").await.unwrap();
+        assert_eq!(result.selected.len(), 2);
+        assert_eq!(result.stale().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_adjacent_chunks_are_retrieved_as_one_passage() {
+        use crate::chunk_merge::{END_OFFSET_KEY, MERGED_KEY, START_OFFSET_KEY};
+        use crate::freshness::VERSION_KEY;
+
+        let engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+        let chunks = [
+            ("Failover runbook: promote the standby database.", 0),
+            ("standby database. Then repoint the failover DNS record.", 30),
+        ];
+        for (text, start) in chunks {
+            let mut metadata = MemoryMetadata::new("document", "wiki/failover.md");
+            metadata.add_custom(VERSION_KEY.to_string(), serde_json::json!("doc-1"));
+            metadata.add_custom(START_OFFSET_KEY.to_string(), serde_json::json!(start));
+            metadata.add_custom(END_OFFSET_KEY.to_string(), serde_json::json!(start + text.len()));
+            engine.store(text.to_string(), metadata, 0.8).await.unwrap();
+        }
+
+        let result = engine.retrieve("failover runbook standby database").await.unwrap();
+        assert_eq!(result.selected.len(), 1);
+        let passage = &result.selected[0].item;
+        assert_eq!(
+            passage.content,
+            "Failover runbook: promote the standby database. Then repoint the failover DNS record."
+        );
+        assert_eq!(passage.metadata.custom[MERGED_KEY].as_array().unwrap().len(), 2);
+        assert_eq!(result.total_tokens, passage.token_count);
+    }
+
+    #[tokio::test]
+    async fn test_retag_and_reindex() {
+        let engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+        let metadata = MemoryMetadata::new("code", "file:///a.rs").with_tags(vec!["old".to_string()]);
+        let id = engine.store("fn main() {}".to_string(), metadata, 0.5).await.unwrap();
+
+        engine.retag(&id, &["new".to_string()], &["old".to_string()]).await.unwrap();
+        let items = engine.list_matching(&ContextFilter::new().with_tag("new")).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].metadata.tags, vec!["new"]);
+        assert!(engine.list_matching(&ContextFilter::new().with_tag("old")).await.unwrap().is_empty());
+
+        let tokens = engine.stats().await.unwrap().total_tokens;
+        engine.reindex(&id).await.unwrap();
+        assert_eq!(engine.stats().await.unwrap().total_tokens, tokens);
+        assert!(matches!(
+            engine.reindex(&Uuid::new_v4()).await,
+            Err(ContextError::ItemNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_source_weights_reorder_retrieval() {
+        let engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+        let content = "Deploy with helm upgrade --install".to_string();
+        engine.store(content.clone(), MemoryMetadata::new("doc", "https://docs.example.com/deploy"), 0.5).await.unwrap();
+        engine.store(content, MemoryMetadata::new("chat", "slack://C024BE91L"), 0.5).await.unwrap();
+
+        let first_source = |result: RetrievalResult| result.selected[0].item.metadata.source.clone();
+
+        engine.set_source_weight("slack://", 3.0).unwrap();
+        assert_eq!(first_source(engine.retrieve("helm deploy").await.unwrap()), "slack://C024BE91L");
+
+        engine.set_source_weight("https://docs.example.com/", 5.0).unwrap();
+        assert_eq!(first_source(engine.retrieve("helm deploy").await.unwrap()), "https://docs.example.com/deploy");
+        assert_eq!(engine.source_weights().len(), 2);
+
+        assert!(engine.set_source_weight("wiki://", -1.0).is_err());
+        assert!(engine.remove_source_weight("slack://"));
+    }
+
+    #[tokio::test]
+    async fn test_inactive_items_are_archived_and_rehydrated() {
+        use crate::archive::{ArchiveConfig, InMemoryArchiveStore};
+        use copilot_core::FrozenClock;
+
+        let clock = Arc::new(FrozenClock::at_epoch());
+        let store = Arc::new(InMemoryArchiveStore::new());
+        let archive = Arc::new(ContextArchive::with_store(ArchiveConfig::default(), store.clone()));
+        let engine = ContextEngineImpl::new(ContextEngineConfig::default())
+            .unwrap()
+            .with_clock(clock.clone())
+            .with_archive(archive.clone());
+        let runbook = MemoryMetadata::new("document", "wiki/billing").with_tags(vec!["billing".to_string()]);
+        let content = "Billing runbook: restart the billing queue workers".to_string();
+        let id = engine.store(content, runbook, 0.8).await.unwrap();
+        clock.advance(chrono::Duration::days(20));
+        let deploy = MemoryMetadata::new("document", "wiki/deploy");
+        engine.store("Deploy the frontend with helm".to_string(), deploy, 0.8).await.unwrap();
+        clock.advance(chrono::Duration::days(11));
+
+        let report = engine.maintenance().await.unwrap();
+        assert_eq!(report.items_archived, 1);
+        assert_eq!(store.len(), 1);
+        let stats = engine.stats().await.unwrap();
+        assert_eq!((stats.total_items, stats.archived_items), (1, 1));
+
+        // A hit returns the placeholder and brings the content back
+        let result = engine.retrieve("billing queue workers").await.unwrap();
+        assert_eq!(result.selected[0].item.content, CONTENT_LOADING);
+        for _ in 0..100 {
+            if *engine.item_index.get(&id).unwrap() == MemoryTier::ShortTerm {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        let result = engine.retrieve("billing queue workers").await.unwrap();
+        assert!(result.selected[0].item.content.starts_with("Billing runbook"));
+        assert!(store.is_empty());
+
+        // Warming up brings archived items back before they are retrieved
+        engine.promote(&id, MemoryTier::Archive).await.unwrap();
+        assert_eq!(engine.warm_up(&ContextFilter::new().with_tag("billing")).await.unwrap(), 1);
+        assert_eq!(*engine.item_index.get(&id).unwrap(), MemoryTier::ShortTerm);
+        let stats = archive.stats();
+        assert_eq!((stats.archived, stats.rehydrated, stats.placeholder_hits), (2, 2, 1));
+    }
+
     #[tokio::test]
     async fn test_tier_selection() {
         let config = ContextEngineConfig::default();
@@ -596,4 +1115,37 @@ mod tests {
         assert_eq!(stats_after.total_items, 0);
         assert_eq!(stats_after.total_tokens, 0);
     }
+
+    #[tokio::test]
+    async fn test_write_ahead_log_restores_tiers() {
+        let dir = tempfile::tempdir().unwrap();
+        let (kept, removed) = {
+            let engine = ContextEngineImpl::new(ContextEngineConfig::default())
+                .unwrap()
+                .with_write_ahead_log(dir.path())
+                .await
+                .unwrap();
+            let kept = engine
+                .store("Restart the billing workers".to_string(), MemoryMetadata::new("test", "test"), 0.8)
+                .await
+                .unwrap();
+            let removed = engine
+                .store("Scratch note".to_string(), MemoryMetadata::new("test", "test"), 0.2)
+                .await
+                .unwrap();
+            engine.remove(&removed).await.unwrap();
+            (kept, removed)
+        };
+
+        let engine = ContextEngineImpl::new(ContextEngineConfig::default())
+            .unwrap()
+            .with_write_ahead_log(dir.path())
+            .await
+            .unwrap();
+        assert_eq!(*engine.item_index.get(&kept).unwrap(), MemoryTier::LongTerm);
+        assert!(engine.item_index.get(&removed).is_none());
+        let stats = engine.stats().await.unwrap();
+        assert_eq!(stats.total_items, 1);
+        assert!(engine.budget_manager.read().await.utilization() > 0.0);
+    }
 }
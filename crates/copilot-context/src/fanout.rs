@@ -0,0 +1,589 @@
+//! Multi-query fan-out for compound questions
+//!
+//! [`FanOutEngine`] wraps a context engine and splits multi-part questions
+//! ("how do I rotate the signing keys, and what breaks if I don't?") into
+//! sub-queries with a [`QueryDecomposer`]. Each sub-query is retrieved
+//! concurrently, items found by several sub-queries are kept once, and the
+//! token budget is shared out in proportion to the relevance each sub-query
+//! found, so one part of the question cannot crowd out the others. Budget a
+//! sub-query leaves unused goes to the best remaining items.
+//!
+//! Single-part questions go straight to the wrapped engine. Sub-queries
+//! still running when the request [`Deadline`] passes are dropped and the
+//! context is assembled from the ones that finished.
+
+use async_trait::async_trait;
+use copilot_core::Deadline;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::{
+    engine::{CompressionStats, ContextEngine, EngineStats, MaintenanceReport},
+    filter::ContextFilter,
+    memory::{MemoryItem, MemoryMetadata, MemoryTier},
+    retrieval::{RetrievalResult, ScoredItem},
+    ContextError, Result,
+};
+
+/// Words that open a new question or request after "and"
+const CLAUSE_OPENERS: &[&str] = &[
+    "how", "what", "why", "where", "when", "which", "who", "is", "are", "does", "do", "can", "should", "show",
+    "list", "explain", "describe", "compare",
+];
+
+/// Fan-out configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FanOutConfig {
+    /// Most sub-queries retrieved per question; further parts are folded
+    /// into the last one
+    pub max_sub_queries: usize,
+    /// Parts shorter than this many words are folded into the part before
+    pub min_sub_query_words: usize,
+}
+
+impl Default for FanOutConfig {
+    fn default() -> Self {
+        Self {
+            max_sub_queries: 4,
+            min_sub_query_words: 3,
+        }
+    }
+}
+
+/// Splits a question into independently retrievable sub-queries
+pub trait QueryDecomposer: Send + Sync {
+    /// Sub-queries of `query`; a single element when it has one part
+    fn decompose(&self, query: &str) -> Vec<String>;
+}
+
+/// Splits on question marks, semicolons, line breaks and list items, and on
+/// conjunctions that join complete clauses
+pub struct HeuristicDecomposer {
+    min_words: usize,
+}
+
+impl HeuristicDecomposer {
+    pub fn new(min_words: usize) -> Self {
+        Self {
+            min_words: min_words.max(1),
+        }
+    }
+
+    /// Split a sentence at ", and", "as well as" and "and <question word>"
+    fn split_clauses(sentence: &str) -> Vec<String> {
+        let words: Vec<&str> = sentence.split_whitespace().collect();
+        let mut parts = Vec::new();
+        let mut current: Vec<&str> = Vec::new();
+        let mut i = 0;
+        while i < words.len() {
+            let word = words[i];
+            let lower = word.to_lowercase();
+            let next = words.get(i + 1).map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase());
+            let after_comma = current.last().is_some_and(|last| last.ends_with(','));
+            let split = match lower.as_str() {
+                "and" | "also" | "then" => {
+                    after_comma || next.as_deref().is_some_and(|next| CLAUSE_OPENERS.contains(&next))
+                }
+                "as" => words.get(i + 1).is_some_and(|w| w.eq_ignore_ascii_case("well"))
+                    && words.get(i + 2).is_some_and(|w| w.eq_ignore_ascii_case("as")),
+                _ => false,
+            };
+            if split && !current.is_empty() {
+                parts.push(current.join(" "));
+                current.clear();
+                i += match lower.as_str() {
+                    "as" => 3,
+                    _ if next.as_deref().is_some_and(|n| n == "also" || n == "then") => 2,
+                    _ => 1,
+                };
+                continue;
+            }
+            current.push(word);
+            i += 1;
+        }
+        if !current.is_empty() {
+            parts.push(current.join(" "));
+        }
+        parts
+    }
+}
+
+impl Default for HeuristicDecomposer {
+    fn default() -> Self {
+        Self::new(FanOutConfig::default().min_sub_query_words)
+    }
+}
+
+impl QueryDecomposer for HeuristicDecomposer {
+    fn decompose(&self, query: &str) -> Vec<String> {
+        let mut parts: Vec<String> = Vec::new();
+        for sentence in query.split(['?', ';', '\n']) {
+            let sentence = strip_list_marker(sentence.trim());
+            for clause in Self::split_clauses(sentence) {
+                let clause = clause.trim_matches(|c: char| c.is_whitespace() || ",.:".contains(c)).to_string();
+                if clause.is_empty() {
+                    continue;
+                }
+                match parts.last_mut() {
+                    Some(last) if clause.split_whitespace().count() < self.min_words => {
+                        last.push(' ');
+                        last.push_str(&clause);
+                    }
+                    _ => parts.push(clause),
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        parts.retain(|part| seen.insert(part.to_lowercase()));
+        if parts.len() <= 1 {
+            return vec![query.trim().to_string()];
+        }
+        parts
+    }
+}
+
+/// Drop a leading "1.", "2)", "-" or "*"
+fn strip_list_marker(line: &str) -> &str {
+    let rest = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    let rest = if rest.len() < line.len() {
+        rest.strip_prefix(['.', ')']).unwrap_or(line)
+    } else {
+        rest.strip_prefix(['-', '*']).unwrap_or(rest)
+    };
+    rest.trim_start()
+}
+
+/// What one sub-query contributed to a fanned-out retrieval
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubQueryReport {
+    pub query: String,
+    /// Tokens allotted to the sub-query's items
+    pub budget: usize,
+    /// Items selected for the sub-query
+    pub selected: usize,
+    /// Tokens of the selected items, including any taken from leftover
+    /// budget
+    pub tokens: usize,
+}
+
+/// A retrieval merged from several sub-queries
+#[derive(Debug, Clone)]
+pub struct FanOutResult {
+    pub result: RetrievalResult,
+    pub sub_queries: Vec<SubQueryReport>,
+    /// Items retrieved by more than one sub-query, kept once
+    pub duplicates: usize,
+}
+
+/// Context engine decorator that fans compound questions out into
+/// sub-queries
+pub struct FanOutEngine {
+    inner: Arc<dyn ContextEngine>,
+    decomposer: Arc<dyn QueryDecomposer>,
+    config: FanOutConfig,
+}
+
+impl FanOutEngine {
+    pub fn new(inner: Arc<dyn ContextEngine>) -> Self {
+        Self::with_config(inner, FanOutConfig::default())
+    }
+
+    pub fn with_config(inner: Arc<dyn ContextEngine>, config: FanOutConfig) -> Self {
+        Self {
+            inner,
+            decomposer: Arc::new(HeuristicDecomposer::new(config.min_sub_query_words)),
+            config,
+        }
+    }
+
+    /// Split questions with `decomposer` instead of the heuristics
+    pub fn with_decomposer(mut self, decomposer: Arc<dyn QueryDecomposer>) -> Self {
+        self.decomposer = decomposer;
+        self
+    }
+
+    /// The wrapped engine
+    pub fn inner(&self) -> Arc<dyn ContextEngine> {
+        Arc::clone(&self.inner)
+    }
+
+    /// Sub-queries `query` is retrieved with
+    pub fn sub_queries(&self, query: &str) -> Vec<String> {
+        let mut parts = self.decomposer.decompose(query);
+        let max = self.config.max_sub_queries.max(1);
+        if parts.len() > max {
+            let tail = parts.split_off(max - 1).join("; ");
+            parts.push(tail);
+        }
+        parts
+    }
+
+    /// Retrieve each sub-query of `query` concurrently and merge the results
+    pub async fn retrieve_fanned_out(&self, query: &str, filter: &ContextFilter) -> Result<FanOutResult> {
+        let sub_queries = self.sub_queries(query);
+        if sub_queries.len() <= 1 {
+            let result = self.inner.retrieve_filtered(query, filter).await?;
+            let report = SubQueryReport {
+                query: query.to_string(),
+                budget: result.target_tokens,
+                selected: result.selected.len(),
+                tokens: result.total_tokens,
+            };
+            return Ok(FanOutResult {
+                result,
+                sub_queries: vec![report],
+                duplicates: 0,
+            });
+        }
+
+        let deadline = Deadline::current();
+        let mut tasks = JoinSet::new();
+        for (index, sub_query) in sub_queries.iter().enumerate() {
+            let inner = Arc::clone(&self.inner);
+            let sub_query = sub_query.clone();
+            let filter = filter.clone();
+            let retrieve = async move { (index, inner.retrieve_filtered(&sub_query, &filter).await) };
+            match deadline {
+                Some(deadline) => tasks.spawn(deadline.scope(retrieve)),
+                None => tasks.spawn(retrieve),
+            };
+        }
+        let mut results: Vec<Option<RetrievalResult>> = vec![None; sub_queries.len()];
+        loop {
+            let Ok(joined) = Deadline::run(tasks.join_next()).await else {
+                warn!(pending = tasks.len(), "Request deadline reached, dropping unfinished sub-queries");
+                tasks.abort_all();
+                break;
+            };
+            let Some(joined) = joined else {
+                break;
+            };
+            let (index, result) =
+                joined.map_err(|e| ContextError::RetrievalFailed(format!("Sub-query retrieval panicked: {}", e)))?;
+            results[index] = Some(result?);
+        }
+        let (sub_queries, results): (Vec<String>, Vec<RetrievalResult>) = sub_queries
+            .into_iter()
+            .zip(results)
+            .filter_map(|(sub_query, result)| Some((sub_query, result?)))
+            .unzip();
+
+        let merged = merge(&sub_queries, results);
+        debug!(
+            sub_queries = merged.sub_queries.len(),
+            duplicates = merged.duplicates,
+            selected = merged.result.selected.len(),
+            "Fanned out retrieval"
+        );
+        Ok(merged)
+    }
+}
+
+/// Deduplicate the sub-query results and share the token budget out
+fn merge(sub_queries: &[String], results: Vec<RetrievalResult>) -> FanOutResult {
+    let target_tokens = results.iter().map(|r| r.target_tokens).max().unwrap_or(0);
+    let max_tokens = results.iter().map(|r| r.max_tokens).max().unwrap_or(0);
+
+    // Each item belongs to the sub-query that scored it highest
+    let mut owners: HashMap<Uuid, (usize, ScoredItem)> = HashMap::new();
+    let mut rejected: HashMap<Uuid, ScoredItem> = HashMap::new();
+    let mut candidate_ids = Vec::new();
+    let mut seen_candidates = HashSet::new();
+    let mut duplicates = 0;
+    for (index, result) in results.into_iter().enumerate() {
+        for scored in result.selected {
+            match owners.get(&scored.item.metadata.id) {
+                Some((_, existing)) => {
+                    duplicates += 1;
+                    if scored.score > existing.score {
+                        owners.insert(scored.item.metadata.id, (index, scored));
+                    }
+                }
+                None => {
+                    owners.insert(scored.item.metadata.id, (index, scored));
+                }
+            }
+        }
+        for scored in result.rejected {
+            rejected.entry(scored.item.metadata.id).or_insert(scored);
+        }
+        candidate_ids.extend(result.candidate_ids.into_iter().filter(|id| seen_candidates.insert(*id)));
+    }
+
+    let mut pools: Vec<Vec<ScoredItem>> = vec![Vec::new(); sub_queries.len()];
+    for (index, scored) in owners.into_values() {
+        pools[index].push(scored);
+    }
+    for pool in &mut pools {
+        pool.sort_by(by_score_desc);
+    }
+
+    // Budgets proportional to the relevance each sub-query found
+    let weights: Vec<f64> = pools.iter().map(|pool| pool.iter().map(|s| s.score).sum()).collect();
+    let total_weight: f64 = weights.iter().sum();
+    let budgets: Vec<usize> = weights
+        .iter()
+        .map(|weight| {
+            if total_weight > 0.0 {
+                (target_tokens as f64 * weight / total_weight) as usize
+            } else {
+                0
+            }
+        })
+        .collect();
+
+    let mut reports: Vec<SubQueryReport> = sub_queries
+        .iter()
+        .zip(&budgets)
+        .map(|(query, budget)| SubQueryReport {
+            query: query.clone(),
+            budget: *budget,
+            selected: 0,
+            tokens: 0,
+        })
+        .collect();
+    let mut selected = Vec::new();
+    let mut leftover = Vec::new();
+    for (index, pool) in pools.into_iter().enumerate() {
+        for scored in pool {
+            let tokens = scored.item.token_count;
+            if reports[index].tokens + tokens <= budgets[index] {
+                reports[index].tokens += tokens;
+                reports[index].selected += 1;
+                selected.push(scored);
+            } else {
+                leftover.push((index, scored));
+            }
+        }
+    }
+
+    // Unused budget goes to the best remaining items
+    let mut total_tokens: usize = selected.iter().map(|s| s.item.token_count).sum();
+    leftover.sort_by(|a, b| by_score_desc(&a.1, &b.1));
+    for (index, scored) in leftover {
+        let tokens = scored.item.token_count;
+        if total_tokens + tokens <= target_tokens {
+            total_tokens += tokens;
+            reports[index].tokens += tokens;
+            reports[index].selected += 1;
+            selected.push(scored);
+        } else {
+            rejected.insert(scored.item.metadata.id, scored);
+        }
+    }
+    selected.sort_by(by_score_desc);
+    for scored in &selected {
+        rejected.remove(&scored.item.metadata.id);
+    }
+    let mut rejected: Vec<ScoredItem> = rejected.into_values().collect();
+    rejected.sort_by(by_score_desc);
+
+    FanOutResult {
+        result: RetrievalResult {
+            selected,
+            rejected,
+            candidate_ids,
+            total_tokens,
+            target_tokens,
+            max_tokens,
+        },
+        sub_queries: reports,
+        duplicates,
+    }
+}
+
+fn by_score_desc(a: &ScoredItem, b: &ScoredItem) -> Ordering {
+    b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal)
+}
+
+#[async_trait]
+impl ContextEngine for FanOutEngine {
+    async fn store(&self, content: String, metadata: MemoryMetadata, importance: f64) -> Result<Uuid> {
+        self.inner.store(content, metadata, importance).await
+    }
+
+    async fn retrieve(&self, query: &str) -> Result<RetrievalResult> {
+        self.retrieve_filtered(query, &ContextFilter::default()).await
+    }
+
+    async fn retrieve_filtered(&self, query: &str, filter: &ContextFilter) -> Result<RetrievalResult> {
+        Ok(self.retrieve_fanned_out(query, filter).await?.result)
+    }
+
+    async fn compress(&self) -> Result<CompressionStats> {
+        self.inner.compress().await
+    }
+
+    async fn stats(&self) -> Result<EngineStats> {
+        self.inner.stats().await
+    }
+
+    async fn promote(&self, id: &Uuid, tier: MemoryTier) -> Result<()> {
+        self.inner.promote(id, tier).await
+    }
+
+    async fn demote(&self, id: &Uuid, tier: MemoryTier) -> Result<()> {
+        self.inner.demote(id, tier).await
+    }
+
+    async fn remove(&self, id: &Uuid) -> Result<()> {
+        self.inner.remove(id).await
+    }
+
+    async fn list_matching(&self, filter: &ContextFilter) -> Result<Vec<MemoryItem>> {
+        self.inner.list_matching(filter).await
+    }
+
+    async fn retag(&self, id: &Uuid, add: &[String], remove: &[String]) -> Result<()> {
+        self.inner.retag(id, add, remove).await
+    }
+
+    async fn reindex(&self, id: &Uuid) -> Result<()> {
+        self.inner.reindex(id).await
+    }
+
+    fn source_weights(&self) -> BTreeMap<String, f64> {
+        self.inner.source_weights()
+    }
+
+    fn set_source_weight(&self, prefix: &str, weight: f64) -> Result<()> {
+        self.inner.set_source_weight(prefix, weight)
+    }
+
+    fn remove_source_weight(&self, prefix: &str) -> bool {
+        self.inner.remove_source_weight(prefix)
+    }
+
+    async fn clear(&self) -> Result<()> {
+        self.inner.clear().await
+    }
+
+    async fn maintenance(&self) -> Result<MaintenanceReport> {
+        self.inner.maintenance().await
+    }
+
+    async fn warm_up(&self, filter: &ContextFilter) -> Result<usize> {
+        self.inner.warm_up(filter).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{ContextEngineConfig, ContextEngineImpl};
+
+    #[test]
+    fn test_decomposition() {
+        let decomposer = HeuristicDecomposer::default();
+
+        assert_eq!(
+            decomposer.decompose("How do I rotate the signing keys, and what breaks if I skip it?"),
+            vec!["How do I rotate the signing keys", "what breaks if I skip it"]
+        );
+        assert_eq!(
+            decomposer.decompose("Compare the staging and production rollout steps"),
+            vec!["Compare the staging and production rollout steps"]
+        );
+        assert_eq!(
+            decomposer.decompose("1. list the failing checks\n2. explain the flaky test policy\n3. who owns billing"),
+            vec!["list the failing checks", "explain the flaky test policy", "who owns billing"]
+        );
+        assert_eq!(
+            decomposer.decompose("show the helm values as well as the ingress rules; and why?"),
+            vec!["show the helm values", "the ingress rules and why"]
+        );
+        assert_eq!(decomposer.decompose("what is the SLA? What is the SLA?"), vec!["what is the SLA? What is the SLA?"]);
+    }
+
+    #[test]
+    fn test_sub_queries_are_capped() {
+        let engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let fan_out = FanOutEngine::with_config(
+            engine,
+            FanOutConfig {
+                max_sub_queries: 2,
+                ..FanOutConfig::default()
+            },
+        );
+        assert_eq!(
+            fan_out.sub_queries("what is the rollback plan? who approves the release? when is the freeze?"),
+            vec!["what is the rollback plan", "who approves the release; when is the freeze"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_retrieves_every_part() {
+        let engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let metadata = || MemoryMetadata::new("doc", "runbooks");
+        for content in [
+            "signing keys rotation procedure for the release pipeline",
+            "signing keys are rotated every ninety days",
+            "database failover runbook for the primary cluster",
+        ] {
+            engine.store(content.to_string(), metadata(), 0.5).await.unwrap();
+        }
+
+        let fan_out = FanOutEngine::new(engine.clone());
+        let merged = fan_out
+            .retrieve_fanned_out(
+                "how are signing keys rotated, and where is the database failover runbook",
+                &ContextFilter::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(merged.sub_queries.len(), 2);
+        assert!(merged.sub_queries.iter().all(|report| report.selected > 0));
+        let ids: HashSet<_> = merged.result.selected.iter().map(|s| s.item.metadata.id).collect();
+        assert_eq!(ids.len(), merged.result.selected.len());
+        assert!(merged.result.total_tokens <= merged.result.target_tokens);
+        assert_eq!(merged.result.candidate_ids.len(), 3);
+
+        // One-part questions pass through
+        let passthrough = fan_out.retrieve_fanned_out("database failover", &ContextFilter::default()).await.unwrap();
+        assert_eq!(passthrough.sub_queries.len(), 1);
+        assert_eq!(passthrough.duplicates, 0);
+    }
+
+    #[test]
+    fn test_merge_deduplicates_and_fills_leftover_budget() {
+        let item = |content: &str, tokens: usize, score: f64| ScoredItem {
+            item: MemoryItem::new(content.to_string(), MemoryMetadata::new("doc", "test"), 0.5, tokens),
+            score,
+            stale: None,
+        };
+        let shared = item("shared", 10, 0.9);
+        let mut shared_again = shared.clone();
+        shared_again.score = 0.5;
+        let big = item("big", 80, 0.8);
+        let small = item("small", 10, 0.4);
+        let result = |selected: Vec<ScoredItem>| RetrievalResult {
+            candidate_ids: selected.iter().map(|s| s.item.metadata.id).collect(),
+            selected,
+            rejected: Vec::new(),
+            total_tokens: 0,
+            target_tokens: 100,
+            max_tokens: 200,
+        };
+
+        let merged = merge(
+            &["first".to_string(), "second".to_string()],
+            vec![result(vec![shared.clone(), big.clone()]), result(vec![shared_again, small.clone()])],
+        );
+        assert_eq!(merged.duplicates, 1);
+        assert_eq!(merged.result.candidate_ids.len(), 3);
+        // `big` overflows the first sub-query's 80-token share and is taken
+        // from the budget the second left unused
+        let selected: Vec<_> = merged.result.selected.iter().map(|s| s.item.content.as_str()).collect();
+        assert_eq!(selected, vec!["shared", "big", "small"]);
+        assert_eq!(merged.result.total_tokens, 100);
+        assert_eq!(merged.sub_queries[0].selected, 2);
+        assert_eq!(merged.sub_queries[1].selected, 1);
+    }
+}
@@ -0,0 +1,226 @@
+//! Structured context filters
+//!
+//! A [`ContextFilter`] narrows retrieval to items with given tags, source,
+//! language, document type or ingestion time. Filters are applied while
+//! candidates are gathered from the memory stores and the search index, so
+//! only matching items are scored and a selective filter makes retrieval
+//! cheaper rather than dropping results after the fact.
+//!
+//! A filter can also look at the corpus as it was at a past time: with
+//! `as_of` set, only items ingested by then are considered, and of each
+//! versioned document (see [`crate::freshness`]) only the version that was
+//! current then.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::freshness::LatestVersions;
+use crate::memory::{MemoryItem, MemoryMetadata};
+
+/// Custom metadata key holding an item's language
+pub const LANGUAGE_KEY: &str = "language";
+
+/// Conditions an item must meet to be retrieved; unset conditions match
+/// everything
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContextFilter {
+    /// Tags the item must all carry
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// Prefix of the item's source, e.g. `file:///repo/src/`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_prefix: Option<String>,
+
+    /// Language recorded in the item's metadata, ignoring case
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    /// Document type, matched against the item's content type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+
+    /// Earliest ingestion time, inclusive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ingested_after: Option<DateTime<Utc>>,
+
+    /// Latest ingestion time, exclusive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ingested_before: Option<DateTime<Utc>>,
+
+    /// Time to view the corpus as of, inclusive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub as_of: Option<DateTime<Utc>>,
+}
+
+impl ContextFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn with_source_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.source_prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Only items ingested in `[after, before)`; either bound may be open
+    pub fn ingested_between(mut self, after: Option<DateTime<Utc>>, before: Option<DateTime<Utc>>) -> Self {
+        self.ingested_after = after;
+        self.ingested_before = before;
+        self
+    }
+
+    /// View the corpus as it was at `time`
+    pub fn with_as_of(mut self, time: DateTime<Utc>) -> Self {
+        self.as_of = Some(time);
+        self
+    }
+
+    /// Whether the filter matches every item
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn matches(&self, item: &MemoryItem) -> bool {
+        self.matches_metadata(&item.metadata, item.created_at)
+    }
+
+    /// Whether an item with `metadata`, ingested at `ingested_at`, matches
+    pub fn matches_metadata(&self, metadata: &MemoryMetadata, ingested_at: DateTime<Utc>) -> bool {
+        if !self.tags.iter().all(|tag| metadata.tags.contains(tag)) {
+            return false;
+        }
+        if let Some(prefix) = &self.source_prefix {
+            if !metadata.source.starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+        if let Some(language) = &self.language {
+            let item_language = metadata.custom.get(LANGUAGE_KEY).and_then(|v| v.as_str());
+            if !item_language.is_some_and(|l| l.eq_ignore_ascii_case(language)) {
+                return false;
+            }
+        }
+        if let Some(content_type) = &self.content_type {
+            if &metadata.content_type != content_type {
+                return false;
+            }
+        }
+        if self.ingested_after.is_some_and(|after| ingested_at < after) {
+            return false;
+        }
+        if self.ingested_before.is_some_and(|before| ingested_at >= before) {
+            return false;
+        }
+        if self.as_of.is_some_and(|as_of| ingested_at > as_of) {
+            return false;
+        }
+        true
+    }
+
+    /// With `as_of` set, drop the items of document versions that a later
+    /// version had replaced by then; `items` must all match the filter
+    pub fn retain_versions_as_of(&self, items: &mut Vec<MemoryItem>) {
+        if self.as_of.is_none() {
+            return;
+        }
+        let latest = LatestVersions::of(items);
+        items.retain(|item| latest.is_latest(item));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn item(source: &str, tags: &[&str], language: Option<&str>) -> MemoryItem {
+        let mut metadata = MemoryMetadata::new("code", source)
+            .with_tags(tags.iter().map(|t| t.to_string()).collect());
+        if let Some(language) = language {
+            metadata.add_custom(LANGUAGE_KEY.to_string(), serde_json::json!(language));
+        }
+        MemoryItem::new("fn main() {}".to_string(), metadata, 0.5, 4)
+    }
+
+    #[test]
+    fn test_empty_filter_matches_everything() {
+        assert!(ContextFilter::new().is_empty());
+        assert!(ContextFilter::new().matches(&item("file:///a.rs", &[], None)));
+    }
+
+    #[test]
+    fn test_filter_conditions() {
+        let rust = item("file:///repo/src/main.rs", &["backend", "core"], Some("Rust"));
+        let docs = item("https://docs.example.com/guide", &["backend"], None);
+
+        let filter = ContextFilter::new()
+            .with_tag("backend")
+            .with_tag("core")
+            .with_source_prefix("file:///repo/")
+            .with_language("rust")
+            .with_content_type("code");
+        assert!(filter.matches(&rust));
+        assert!(!filter.matches(&docs));
+
+        assert!(ContextFilter::new().with_tag("backend").matches(&docs));
+        assert!(!ContextFilter::new().with_language("python").matches(&rust));
+        assert!(!ContextFilter::new().with_content_type("document").matches(&rust));
+    }
+
+    #[test]
+    fn test_ingestion_time_range() {
+        let stored = item("file:///a.rs", &[], None);
+        let at = stored.created_at;
+
+        let around = ContextFilter::new().ingested_between(Some(at - Duration::hours(1)), Some(at + Duration::hours(1)));
+        assert!(around.matches(&stored));
+        assert!(ContextFilter::new().ingested_between(Some(at), None).matches(&stored));
+        assert!(!ContextFilter::new().ingested_between(None, Some(at)).matches(&stored));
+    }
+
+    #[test]
+    fn test_versions_as_of() {
+        use crate::freshness::{DOCUMENT_KEY, VERSION_KEY};
+
+        let start = Utc::now() - Duration::days(30);
+        let version = |version: &str, days: i64| {
+            let mut metadata = MemoryMetadata::new("document", "wiki");
+            metadata.add_custom(DOCUMENT_KEY.to_string(), serde_json::json!("runbook"));
+            metadata.add_custom(VERSION_KEY.to_string(), serde_json::json!(version));
+            MemoryItem::new_at(version.to_string(), metadata, 0.5, 4, start + Duration::days(days))
+        };
+        let unversioned = MemoryItem::new_at("notes".to_string(), MemoryMetadata::new("document", "wiki"), 0.5, 4, start);
+        let items = [version("v1", 0), version("v2", 10), version("v3", 20), unversioned];
+
+        let as_of = |days: i64| {
+            let filter = ContextFilter::new().with_as_of(start + Duration::days(days));
+            let mut matching: Vec<MemoryItem> = items.iter().filter(|i| filter.matches(i)).cloned().collect();
+            filter.retain_versions_as_of(&mut matching);
+            matching.into_iter().map(|i| i.content).collect::<Vec<_>>()
+        };
+        assert_eq!(as_of(5), vec!["v1", "notes"]);
+        assert_eq!(as_of(10), vec!["v2", "notes"]);
+        assert_eq!(as_of(25), vec!["v3", "notes"]);
+
+        // Without a time every version is kept
+        let mut all = items.to_vec();
+        ContextFilter::new().retain_versions_as_of(&mut all);
+        assert_eq!(all.len(), 4);
+    }
+}
@@ -0,0 +1,295 @@
+//! Stale content detection
+//!
+//! The [`FreshnessEvaluator`] flags selected context items that were
+//! ingested longer ago than a configurable age, or that a newer version of
+//! the same document has replaced. Flags travel with the items in the
+//! [`RetrievalResult`], so responses can warn that context may be outdated
+//! and [`FreshnessCounters`] can track how often stale context is used.
+//!
+//! Items belong to the same document when they share a source and a
+//! [`DOCUMENT_KEY`] value; the chunks stored by one ingestion share a
+//! [`VERSION_KEY`] value, so they never supersede each other.
+
+use crate::memory::MemoryItem;
+use crate::retrieval::RetrievalResult;
+use chrono::{DateTime, Duration, Utc};
+use copilot_core::{Clock, SystemClock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Custom metadata key naming the document an item was ingested from
+pub const DOCUMENT_KEY: &str = "document";
+
+/// Custom metadata key identifying one ingestion of a document
+pub const VERSION_KEY: &str = "document_id";
+
+/// When retrieved items count as stale
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FreshnessConfig {
+    /// Items ingested more than this many days ago are outdated; `None`
+    /// never flags items by age
+    pub max_age_days: Option<u32>,
+
+    /// Flag items whose document has been ingested again since
+    pub flag_superseded: bool,
+}
+
+impl Default for FreshnessConfig {
+    fn default() -> Self {
+        Self {
+            max_age_days: Some(365),
+            flag_superseded: true,
+        }
+    }
+}
+
+/// Why a retrieved item may be outdated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Staleness {
+    /// Ingested longer ago than the configured maximum age
+    Outdated { age_days: i64 },
+    /// A newer version of the same document was ingested since
+    Superseded {
+        newer_version: String,
+        ingested_at: DateTime<Utc>,
+    },
+}
+
+impl Staleness {
+    /// Warning to show next to content drawn from the item
+    pub fn warning(&self, source: &str) -> String {
+        match self {
+            Self::Outdated { age_days } => {
+                format!("{} may be outdated: it was ingested {} days ago", source, age_days)
+            }
+            Self::Superseded { ingested_at, .. } => format!(
+                "{} may be outdated: a newer version was ingested on {}",
+                source,
+                ingested_at.format("%Y-%m-%d")
+            ),
+        }
+    }
+}
+
+/// Newest ingestion of each document among a set of items
+#[derive(Debug, Default)]
+pub struct LatestVersions {
+    documents: HashMap<(String, String), (String, DateTime<Utc>)>,
+}
+
+impl LatestVersions {
+    pub fn of(items: &[MemoryItem]) -> Self {
+        let mut documents: HashMap<(String, String), (String, DateTime<Utc>)> = HashMap::new();
+        for item in items {
+            let (Some(document), Some(version)) = (document_of(item), version_of(item)) else {
+                continue;
+            };
+            let key = (item.metadata.source.clone(), document.to_string());
+            match documents.get(&key) {
+                Some((_, latest)) if *latest >= item.created_at => {}
+                _ => {
+                    documents.insert(key, (version.to_string(), item.created_at));
+                }
+            }
+        }
+        Self { documents }
+    }
+
+    /// Whether `item` belongs to the newest version of its document, or to
+    /// no versioned document at all
+    pub fn is_latest(&self, item: &MemoryItem) -> bool {
+        let (Some(document), Some(version)) = (document_of(item), version_of(item)) else {
+            return true;
+        };
+        self.documents
+            .get(&(item.metadata.source.clone(), document.to_string()))
+            .map_or(true, |(newest, _)| newest == version)
+    }
+}
+
+/// Flags retrieved items that may be outdated
+pub struct FreshnessEvaluator {
+    config: FreshnessConfig,
+    clock: Arc<dyn Clock>,
+}
+
+impl FreshnessEvaluator {
+    pub fn new(config: FreshnessConfig) -> Self {
+        Self {
+            config,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Measure item age against `clock`
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Why `item` may be outdated, given the newest version of each document
+    pub fn evaluate(&self, item: &MemoryItem, latest: &LatestVersions) -> Option<Staleness> {
+        if self.config.flag_superseded {
+            if let (Some(document), Some(version)) = (document_of(item), version_of(item)) {
+                let key = (item.metadata.source.clone(), document.to_string());
+                if let Some((newest, ingested_at)) = latest.documents.get(&key) {
+                    if newest != version && *ingested_at > item.created_at {
+                        return Some(Staleness::Superseded {
+                            newer_version: newest.clone(),
+                            ingested_at: *ingested_at,
+                        });
+                    }
+                }
+            }
+        }
+
+        let max_age = Duration::days(i64::from(self.config.max_age_days?));
+        let age = self.clock.now() - item.created_at;
+        (age > max_age).then(|| Staleness::Outdated { age_days: age.num_days() })
+    }
+
+    /// Flag the selected items of `result`
+    pub fn annotate(&self, result: &mut RetrievalResult, latest: &LatestVersions) {
+        for scored in &mut result.selected {
+            scored.stale = self.evaluate(&scored.item, latest);
+        }
+    }
+}
+
+fn document_of(item: &MemoryItem) -> Option<&str> {
+    item.metadata.custom.get(DOCUMENT_KEY).and_then(|v| v.as_str())
+}
+
+fn version_of(item: &MemoryItem) -> Option<&str> {
+    item.metadata.custom.get(VERSION_KEY).and_then(|v| v.as_str())
+}
+
+/// Snapshot of stale context usage
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FreshnessStats {
+    /// Context windows used
+    pub windows: u64,
+    /// Windows with at least one stale item
+    pub windows_with_stale: u64,
+    /// Stale items used, by reason
+    pub outdated_items: u64,
+    pub superseded_items: u64,
+}
+
+impl FreshnessStats {
+    /// Prometheus text exposition of the counters
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        for (name, help, value) in [
+            ("context_windows_total", "Context windows used", self.windows),
+            ("context_windows_stale_total", "Context windows with stale items", self.windows_with_stale),
+            ("context_outdated_items_total", "Items used past their maximum age", self.outdated_items),
+            ("context_superseded_items_total", "Items used after a newer version was ingested", self.superseded_items),
+        ] {
+            let _ = writeln!(out, "# HELP {}_{} {}", prefix, name, help);
+            let _ = writeln!(out, "# TYPE {}_{} counter", prefix, name);
+            let _ = writeln!(out, "{}_{} {}", prefix, name, value);
+        }
+        out
+    }
+}
+
+/// Counts context windows and the stale items they use
+#[derive(Debug, Default)]
+pub struct FreshnessCounters {
+    windows: AtomicU64,
+    windows_with_stale: AtomicU64,
+    outdated_items: AtomicU64,
+    superseded_items: AtomicU64,
+}
+
+impl FreshnessCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the stale items of a window about to be used
+    pub fn record(&self, result: &RetrievalResult) {
+        let mut stale = 0;
+        for staleness in result.selected.iter().filter_map(|scored| scored.stale.as_ref()) {
+            stale += 1;
+            match staleness {
+                Staleness::Outdated { .. } => self.outdated_items.fetch_add(1, Ordering::Relaxed),
+                Staleness::Superseded { .. } => self.superseded_items.fetch_add(1, Ordering::Relaxed),
+            };
+        }
+        self.windows.fetch_add(1, Ordering::Relaxed);
+        if stale > 0 {
+            self.windows_with_stale.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn stats(&self) -> FreshnessStats {
+        FreshnessStats {
+            windows: self.windows.load(Ordering::Relaxed),
+            windows_with_stale: self.windows_with_stale.load(Ordering::Relaxed),
+            outdated_items: self.outdated_items.load(Ordering::Relaxed),
+            superseded_items: self.superseded_items.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryMetadata;
+    use copilot_core::FrozenClock;
+
+    fn chunk(document: &str, version: &str, ingested_at: DateTime<Utc>) -> MemoryItem {
+        let mut metadata = MemoryMetadata::new("document", "wiki");
+        metadata.add_custom(DOCUMENT_KEY.to_string(), serde_json::json!(document));
+        metadata.add_custom(VERSION_KEY.to_string(), serde_json::json!(version));
+        MemoryItem::new_at("Deploy with helm".to_string(), metadata, 0.5, 4, ingested_at)
+    }
+
+    #[test]
+    fn test_superseded_versions_are_flagged() {
+        let clock = Arc::new(FrozenClock::at_epoch());
+        let evaluator = FreshnessEvaluator::new(FreshnessConfig::default()).with_clock(clock.clone());
+        let old = chunk("deploy.md", "v1", FrozenClock::EPOCH);
+        let old_sibling = chunk("deploy.md", "v1", FrozenClock::EPOCH);
+        let new = chunk("deploy.md", "v2", FrozenClock::EPOCH + Duration::days(3));
+        let other = chunk("rollback.md", "v1", FrozenClock::EPOCH);
+        clock.advance(Duration::days(5));
+
+        let latest = LatestVersions::of(&[old.clone(), old_sibling.clone(), new.clone(), other.clone()]);
+        assert!(matches!(
+            evaluator.evaluate(&old, &latest),
+            Some(Staleness::Superseded { ref newer_version, .. }) if newer_version == "v2"
+        ));
+        assert_eq!(evaluator.evaluate(&new, &latest), None);
+        assert_eq!(evaluator.evaluate(&other, &latest), None);
+
+        // Chunks of the same ingestion do not supersede each other
+        let latest = LatestVersions::of(&[old.clone(), old_sibling]);
+        assert_eq!(evaluator.evaluate(&old, &latest), None);
+    }
+
+    #[test]
+    fn test_old_items_are_outdated() {
+        let clock = Arc::new(FrozenClock::at_epoch());
+        let config = FreshnessConfig {
+            max_age_days: Some(30),
+            flag_superseded: true,
+        };
+        let evaluator = FreshnessEvaluator::new(config).with_clock(clock.clone());
+        let item = chunk("deploy.md", "v1", FrozenClock::EPOCH);
+        let latest = LatestVersions::default();
+
+        clock.advance(Duration::days(30));
+        assert_eq!(evaluator.evaluate(&item, &latest), None);
+        clock.advance(Duration::days(10));
+        let staleness = evaluator.evaluate(&item, &latest).unwrap();
+        assert_eq!(staleness, Staleness::Outdated { age_days: 40 });
+        assert_eq!(staleness.warning("wiki"), "wiki may be outdated: it was ingested 40 days ago");
+    }
+}
@@ -0,0 +1,305 @@
+//! Hierarchical retrieval over document summaries
+//!
+//! In a large corpus many chunks look alike once cut from their documents.
+//! [`HierarchicalIndex`] keeps a parent node per document, holding the
+//! summary and keywords generated when it was ingested, above the
+//! document's chunks. A search first matches the query against the parent
+//! nodes, then ranks only the chunks of the best matching documents, so a
+//! chunk of an unrelated document that happens to share words with the
+//! query is not considered. When those documents hold fewer chunks than
+//! asked for, the rest come from a search over every other chunk.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use crate::{
+    hybrid_search::{EmbeddingProvider, HybridSearchConfig, HybridSearchEngine},
+    Result,
+};
+
+/// Summary of a whole document, generated at ingestion
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentSummary {
+    /// A few sentences on what the document covers
+    pub summary: String,
+    /// Terms the document is about
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+}
+
+impl DocumentSummary {
+    pub fn new(summary: impl Into<String>) -> Self {
+        Self {
+            summary: summary.into(),
+            keywords: Vec::new(),
+        }
+    }
+
+    pub fn with_keywords(mut self, keywords: Vec<String>) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.summary.trim().is_empty() && self.keywords.is_empty()
+    }
+
+    /// Text of the parent node, which searches match against
+    pub fn text(&self) -> String {
+        if self.keywords.is_empty() {
+            return self.summary.clone();
+        }
+        format!("{}\nKeywords: {}", self.summary, self.keywords.join(", "))
+    }
+}
+
+/// Hierarchical retrieval configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HierarchicalConfig {
+    /// Documents whose chunks are ranked, best matching summaries first
+    pub documents_per_query: usize,
+    /// Fill results the matched documents cannot from every other chunk
+    pub fill_from_all_chunks: bool,
+}
+
+impl Default for HierarchicalConfig {
+    fn default() -> Self {
+        Self {
+            documents_per_query: 5,
+            fill_from_all_chunks: true,
+        }
+    }
+}
+
+/// A chunk found by hierarchical retrieval
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HierarchicalResult {
+    pub chunk_id: String,
+    pub document_id: String,
+    /// Score of the chunk among the chunks it was ranked with
+    pub score: f32,
+    /// Score of the document's summary; `None` for chunks filled in from
+    /// outside the matched documents
+    pub document_score: Option<f32>,
+}
+
+struct Nodes {
+    /// One node per summarized document, keyed by document ID
+    parents: HybridSearchEngine,
+    chunks: HybridSearchEngine,
+    children: HashMap<String, Vec<String>>,
+    parent_of: HashMap<String, String>,
+}
+
+/// Document summaries over their chunks
+pub struct HierarchicalIndex {
+    config: HierarchicalConfig,
+    nodes: Mutex<Nodes>,
+}
+
+impl HierarchicalIndex {
+    pub fn new(
+        config: HierarchicalConfig,
+        search: HybridSearchConfig,
+        embedding_provider: Arc<dyn EmbeddingProvider>,
+    ) -> Self {
+        Self {
+            config,
+            nodes: Mutex::new(Nodes {
+                parents: HybridSearchEngine::new(search.clone(), embedding_provider.clone()),
+                chunks: HybridSearchEngine::new(search, embedding_provider),
+                children: HashMap::new(),
+                parent_of: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn config(&self) -> &HierarchicalConfig {
+        &self.config
+    }
+
+    /// Index a document's summary and its chunks as `(id, content)`,
+    /// replacing what was indexed for it before
+    ///
+    /// A document with an empty summary gets no parent node; its chunks are
+    /// only found when filling results.
+    pub async fn index_document(
+        &self,
+        document_id: &str,
+        summary: &DocumentSummary,
+        chunks: &[(String, String)],
+    ) -> Result<()> {
+        let mut nodes = self.nodes.lock().await;
+        nodes.remove(document_id);
+
+        if !summary.is_empty() {
+            nodes.parents.index(document_id, &summary.text()).await?;
+        }
+        for (chunk_id, content) in chunks {
+            nodes.chunks.index(chunk_id, content).await?;
+            nodes.parent_of.insert(chunk_id.clone(), document_id.to_string());
+        }
+        nodes
+            .children
+            .insert(document_id.to_string(), chunks.iter().map(|(id, _)| id.clone()).collect());
+
+        debug!(document_id = %document_id, chunks = chunks.len(), "Indexed document hierarchically");
+        Ok(())
+    }
+
+    /// Remove a document and its chunks; false if it was not indexed
+    pub async fn remove_document(&self, document_id: &str) -> bool {
+        self.nodes.lock().await.remove(document_id)
+    }
+
+    /// Number of documents indexed
+    pub async fn document_count(&self) -> usize {
+        self.nodes.lock().await.children.len()
+    }
+
+    /// Summary text of a document, if it has a parent node
+    pub async fn summary_text(&self, document_id: &str) -> Option<String> {
+        self.nodes.lock().await.parents.get_content(document_id).cloned()
+    }
+
+    /// Chunks for `query`: the best chunks of the documents whose summaries
+    /// match best, then, when configured, the best of the rest
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<HierarchicalResult>> {
+        let mut guard = self.nodes.lock().await;
+        let nodes = &mut *guard;
+
+        let documents: HashMap<String, f32> = nodes
+            .parents
+            .search(query, self.config.documents_per_query)
+            .await?
+            .into_iter()
+            .map(|result| (result.doc_id, result.score))
+            .collect();
+        let candidates: HashSet<&str> = documents
+            .keys()
+            .filter_map(|document_id| nodes.children.get(document_id))
+            .flatten()
+            .map(String::as_str)
+            .collect();
+
+        let parent_of = &nodes.parent_of;
+        let result = |chunk_id: String, score: f32, document_score: Option<f32>| HierarchicalResult {
+            document_id: parent_of.get(&chunk_id).cloned().unwrap_or_default(),
+            chunk_id,
+            score,
+            document_score,
+        };
+
+        let mut results: Vec<HierarchicalResult> = nodes
+            .chunks
+            .search_matching(query, limit, |chunk_id| candidates.contains(chunk_id))
+            .await?
+            .into_iter()
+            .map(|hit| {
+                let document_score = parent_of.get(&hit.doc_id).and_then(|document| documents.get(document)).copied();
+                result(hit.doc_id, hit.score, document_score)
+            })
+            .collect();
+
+        if self.config.fill_from_all_chunks && results.len() < limit {
+            let rest = nodes
+                .chunks
+                .search_matching(query, limit - results.len(), |chunk_id| !candidates.contains(chunk_id))
+                .await?;
+            results.extend(rest.into_iter().map(|hit| result(hit.doc_id, hit.score, None)));
+        }
+
+        debug!(
+            documents = documents.len(),
+            candidates = candidates.len(),
+            results = results.len(),
+            "Hierarchical search completed"
+        );
+        Ok(results)
+    }
+}
+
+impl Nodes {
+    fn remove(&mut self, document_id: &str) -> bool {
+        self.parents.remove(document_id);
+        let Some(children) = self.children.remove(document_id) else {
+            return false;
+        };
+        for chunk_id in children {
+            self.chunks.remove(&chunk_id);
+            self.parent_of.remove(&chunk_id);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_search::MockEmbeddingProvider;
+
+    fn chunks(document_id: &str, contents: &[&str]) -> Vec<(String, String)> {
+        contents
+            .iter()
+            .enumerate()
+            .map(|(i, content)| (format!("{}_{}", document_id, i), content.to_string()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_summaries_narrow_chunks_to_matching_documents() {
+        let config = HierarchicalConfig {
+            documents_per_query: 1,
+            fill_from_all_chunks: false,
+        };
+        let index = HierarchicalIndex::new(config, HybridSearchConfig::default(), Arc::new(MockEmbeddingProvider::new(32)));
+
+        let billing = DocumentSummary::new("How invoices are generated and retried for failed payments")
+            .with_keywords(vec!["billing".into(), "invoices".into()]);
+        index
+            .index_document("billing", &billing, &chunks("billing", &["Retry failed payments after a timeout", "Invoices close monthly"]))
+            .await
+            .unwrap();
+        let network = DocumentSummary::new("Load balancer and DNS configuration for the edge network");
+        index
+            .index_document("network", &network, &chunks("network", &["Retry the health check after a timeout"]))
+            .await
+            .unwrap();
+
+        let results = index.search("billing invoices retry after timeout", 5).await.unwrap();
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| r.document_id == "billing" && r.document_score.is_some()));
+
+        assert!(index.remove_document("billing").await);
+        assert!(!index.remove_document("billing").await);
+        assert_eq!(index.document_count().await, 1);
+        assert!(index.summary_text("billing").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_results_are_filled_from_other_documents() {
+        let index = HierarchicalIndex::new(
+            HierarchicalConfig {
+                documents_per_query: 1,
+                ..Default::default()
+            },
+            HybridSearchConfig::default(),
+            Arc::new(MockEmbeddingProvider::new(32)),
+        );
+        let summary = DocumentSummary::new("Rotating signing keys");
+        index.index_document("keys", &summary, &chunks("keys", &["Rotate signing keys weekly"])).await.unwrap();
+        index
+            .index_document("notes", &DocumentSummary::default(), &chunks("notes", &["Signing keys live in the vault"]))
+            .await
+            .unwrap();
+
+        let results = index.search("signing keys", 2).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].chunk_id, "keys_0");
+        assert_eq!(results[1].chunk_id, "notes_0");
+        assert_eq!(results[1].document_score, None);
+    }
+}
@@ -3,8 +3,9 @@
 //! Provides advanced search capabilities that combine dense (vector) and sparse
 //! (keyword/BM25) retrieval methods for improved accuracy.
 
-use crate::{ContextError, MemoryItem, Result};
+use crate::{ContextError, ContextFilter, MemoryItem, MemoryMetadata, Result};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -237,6 +238,11 @@ impl BM25Scorer {
 
     /// Score a query against all documents
     pub fn score(&mut self, query: &str) -> Vec<(String, f32)> {
+        self.score_matching(query, |_| true)
+    }
+
+    /// Score a query against the documents `admits` accepts
+    pub fn score_matching(&mut self, query: &str, admits: impl Fn(&str) -> bool) -> Vec<(String, f32)> {
         let query_tokens = tokenize(query);
         let mut scores: HashMap<String, f32> = HashMap::new();
 
@@ -245,6 +251,9 @@ impl BM25Scorer {
 
             if let Some(postings) = self.inverted_index.get(&term) {
                 for (doc_id, tf) in postings {
+                    if !admits(doc_id) {
+                        continue;
+                    }
                     let doc_length = *self.doc_lengths.get(doc_id).unwrap_or(&1) as f32;
                     let tf = *tf as f32;
 
@@ -331,6 +340,8 @@ pub struct HybridSearchEngine {
     doc_embeddings: HashMap<String, Embedding>,
     /// Document contents for retrieval
     doc_contents: HashMap<String, String>,
+    /// Document metadata and ingestion time, for filtered search
+    doc_metadata: HashMap<String, (MemoryMetadata, DateTime<Utc>)>,
 }
 
 impl HybridSearchEngine {
@@ -342,6 +353,7 @@ impl HybridSearchEngine {
             bm25_scorer,
             doc_embeddings: HashMap::new(),
             doc_contents: HashMap::new(),
+            doc_metadata: HashMap::new(),
         }
     }
 
@@ -361,6 +373,19 @@ impl HybridSearchEngine {
         Ok(())
     }
 
+    /// Index a document along with the metadata search filters match
+    pub async fn index_with_metadata(
+        &mut self,
+        doc_id: &str,
+        content: &str,
+        metadata: MemoryMetadata,
+        ingested_at: DateTime<Utc>,
+    ) -> Result<()> {
+        self.index(doc_id, content).await?;
+        self.doc_metadata.insert(doc_id.to_string(), (metadata, ingested_at));
+        Ok(())
+    }
+
     /// Index multiple documents
     pub async fn index_batch(&mut self, documents: Vec<(&str, &str)>) -> Result<()> {
         for (doc_id, content) in documents {
@@ -374,17 +399,62 @@ impl HybridSearchEngine {
         self.bm25_scorer.remove(doc_id);
         self.doc_embeddings.remove(doc_id);
         self.doc_contents.remove(doc_id);
+        self.doc_metadata.remove(doc_id);
     }
 
     /// Search using hybrid retrieval
     pub async fn search(&mut self, query: &str, limit: usize) -> Result<Vec<HybridSearchResult>> {
+        self.search_filtered(query, limit, &ContextFilter::default()).await
+    }
+
+    /// Search the documents matching `filter`
+    ///
+    /// Both retrievers skip documents the filter rejects before scoring, so
+    /// the candidate pool is drawn from matching documents only. Documents
+    /// indexed without metadata match only an empty filter.
+    pub async fn search_filtered(
+        &mut self,
+        query: &str,
+        limit: usize,
+        filter: &ContextFilter,
+    ) -> Result<Vec<HybridSearchResult>> {
+        let doc_metadata = &self.doc_metadata;
+        let admits = |doc_id: &str| {
+            filter.is_empty()
+                || doc_metadata
+                    .get(doc_id)
+                    .is_some_and(|(metadata, at)| filter.matches_metadata(metadata, *at))
+        };
+
         // Get vector search results
-        let vector_results = self.vector_search(query).await?;
+        let vector_results = self.vector_search(query, &admits).await?;
 
         // Get keyword search results
-        let keyword_results = self.bm25_scorer.score(query);
+        let keyword_results = self.bm25_scorer.score_matching(query, admits);
+
+        Ok(self.fuse(query, limit, vector_results, keyword_results))
+    }
+
+    /// Search the documents `admits` accepts, by ID
+    pub async fn search_matching(
+        &mut self,
+        query: &str,
+        limit: usize,
+        admits: impl Fn(&str) -> bool,
+    ) -> Result<Vec<HybridSearchResult>> {
+        let vector_results = self.vector_search(query, &admits).await?;
+        let keyword_results = self.bm25_scorer.score_matching(query, admits);
+        Ok(self.fuse(query, limit, vector_results, keyword_results))
+    }
 
-        // Fuse results
+    /// Fuse both retrievers' results and keep the top `limit`
+    fn fuse(
+        &self,
+        query: &str,
+        limit: usize,
+        vector_results: Vec<(String, f32)>,
+        keyword_results: Vec<(String, f32)>,
+    ) -> Vec<HybridSearchResult> {
         let fused = if self.config.use_rrf {
             self.reciprocal_rank_fusion(vector_results, keyword_results)
         } else {
@@ -400,16 +470,17 @@ impl HybridSearchEngine {
             "Hybrid search completed"
         );
 
-        Ok(results)
+        results
     }
 
     /// Vector similarity search
-    async fn vector_search(&self, query: &str) -> Result<Vec<(String, f32)>> {
+    async fn vector_search(&self, query: &str, admits: impl Fn(&str) -> bool) -> Result<Vec<(String, f32)>> {
         let query_embedding = self.embedding_provider.embed(query).await?;
 
         let mut results: Vec<(String, f32)> = self
             .doc_embeddings
             .iter()
+            .filter(|(doc_id, _)| admits(doc_id))
             .map(|(doc_id, doc_embedding)| {
                 let similarity = self.config.similarity_metric.calculate(&query_embedding, doc_embedding);
                 (doc_id.clone(), similarity)
@@ -516,10 +587,41 @@ impl HybridSearchEngine {
         results
     }
 
+    /// Reweight vector and keyword results in fusion
+    pub fn set_fusion_weights(&mut self, vector_weight: f32, keyword_weight: f32) {
+        self.config.vector_weight = vector_weight;
+        self.config.keyword_weight = keyword_weight;
+    }
+
     /// Get document content by ID
     pub fn get_content(&self, doc_id: &str) -> Option<&String> {
         self.doc_contents.get(doc_id)
     }
+
+    /// Metadata and ingestion time a document was indexed with, if any
+    pub fn get_metadata(&self, doc_id: &str) -> Option<&(MemoryMetadata, DateTime<Utc>)> {
+        self.doc_metadata.get(doc_id)
+    }
+
+    /// Whether a document is indexed
+    pub fn contains(&self, doc_id: &str) -> bool {
+        self.doc_contents.contains_key(doc_id)
+    }
+
+    /// IDs of the indexed documents, in no particular order
+    pub fn doc_ids(&self) -> impl Iterator<Item = &str> {
+        self.doc_contents.keys().map(String::as_str)
+    }
+
+    /// Number of indexed documents
+    pub fn len(&self) -> usize {
+        self.doc_contents.len()
+    }
+
+    /// Whether no documents are indexed
+    pub fn is_empty(&self) -> bool {
+        self.doc_contents.is_empty()
+    }
 }
 
 #[cfg(test)]
@@ -592,4 +694,36 @@ mod tests {
         // First result should be doc1
         assert_eq!(results[0].doc_id, "doc1");
     }
+
+    #[tokio::test]
+    async fn test_filtered_search_only_scores_matching_documents() {
+        let provider = Arc::new(MockEmbeddingProvider::new(128));
+        let mut engine = HybridSearchEngine::new(HybridSearchConfig::default(), provider);
+        let now = Utc::now();
+
+        let metadata = |source: &str, tag: &str| {
+            MemoryMetadata::new("code", source).with_tags(vec![tag.to_string()])
+        };
+        engine
+            .index_with_metadata("doc1", "rust programming language", metadata("file:///repo/a.rs", "core"), now)
+            .await
+            .unwrap();
+        engine
+            .index_with_metadata("doc2", "rust programming guide", metadata("https://docs/rust", "docs"), now)
+            .await
+            .unwrap();
+        engine.index("doc3", "rust programming tips").await.unwrap();
+
+        let filter = ContextFilter::new().with_tag("docs");
+        let results = engine.search_filtered("rust programming", 10, &filter).await.unwrap();
+        let ids: Vec<_> = results.iter().map(|r| r.doc_id.as_str()).collect();
+        assert_eq!(ids, vec!["doc2"]);
+
+        let filter = ContextFilter::new().with_source_prefix("file:///repo/");
+        let results = engine.search_filtered("rust", 10, &filter).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].doc_id, "doc1");
+
+        assert_eq!(engine.search("rust programming", 10).await.unwrap().len(), 3);
+    }
 }
@@ -3,17 +3,62 @@
 //! This crate provides multi-tier context management with intelligent retrieval,
 //! compression, and token budget management for LLM interactions.
 
+pub mod access;
+pub mod archive;
+pub mod authority;
+pub mod calibration;
+pub mod chunk_merge;
 pub mod compression;
+pub mod embedding_cache;
+pub mod embedding_migration;
+pub mod embedding_scheduler;
 pub mod engine;
+pub mod fanout;
+pub mod filter;
+pub mod freshness;
+pub mod hierarchical;
 pub mod hybrid_search;
 pub mod memory;
+pub mod opensearch;
+pub mod pipeline_trace;
+pub mod prefetch;
+pub mod prompt_compression;
+pub mod provenance;
 pub mod reranking;
+pub mod residency;
 pub mod retrieval;
+pub mod scratchpad;
+pub mod sharded;
+pub mod tuning;
+pub mod wal;
+pub mod window_diff;
 
 // Re-exports
+pub use access::{item_acl, AccessAuditor, AccessDenial, AccessFence, AccessOperation};
+pub use archive::{
+    ArchiveConfig, ArchiveStats, ArchiveStore, ContextArchive, InMemoryArchiveStore, CONTENT_LOADING,
+};
+pub use authority::SourceAuthority;
+pub use calibration::{CalibrationMethod, CalibrationParams, LabeledPair, LabeledScore, ScoreCalibration};
+pub use chunk_merge::{ChunkMergeConfig, ChunkMerger};
+pub use embedding_cache::{
+    BackgroundEmbeddings, CacheTier, CachedEmbeddingProvider, EmbeddingCacheConfig, EmbeddingCacheStats, EmbeddingTier,
+    EmbeddingWarmup,
+};
+pub use embedding_migration::{EmbeddingMigration, MigrationConfig, MigrationProgress};
+pub use embedding_scheduler::{
+    EmbeddingPriority, EmbeddingScheduler, EmbeddingSchedulerConfig, EmbeddingSchedulerStats,
+    ScheduledEmbeddings,
+};
 pub use engine::{ContextEngine, ContextEngineImpl, ContextEngineConfig};
+pub use fanout::{FanOutConfig, FanOutEngine, FanOutResult, HeuristicDecomposer, QueryDecomposer, SubQueryReport};
+pub use filter::ContextFilter;
+pub use hierarchical::{DocumentSummary, HierarchicalConfig, HierarchicalIndex, HierarchicalResult};
+pub use freshness::{
+    FreshnessConfig, FreshnessCounters, FreshnessEvaluator, FreshnessStats, Staleness,
+};
 pub use memory::{MemoryTier, MemoryItem, MemoryStore, MemoryMetadata};
-pub use retrieval::{RelevanceScorer, ContextWindow, RetrievalConfig};
+pub use retrieval::{RelevanceScorer, ContextWindow, RetrievalConfig, SearchParams};
 pub use compression::{CompressionStrategy, CompressionConfig, Compressor};
 pub use hybrid_search::{
     HybridSearchEngine, HybridSearchConfig, HybridSearchResult,
@@ -22,7 +67,28 @@ pub use hybrid_search::{
 };
 pub use reranking::{
     Reranker, RerankerConfig, CrossEncoderReranker,
-    RerankerResult, RerankerProvider,
+    RerankerResult, RerankerProvider, EnsembleConfig, FusionStrategy,
+};
+pub use opensearch::{
+    BulkReport, OpenSearchBackend, OpenSearchConfig, SearchAuth, SearchDocument, SearchFlavor, SearchHit,
+};
+pub use pipeline_trace::{ContextStage, ContextStageObserver, StageRecord};
+pub use prefetch::{ContextPrefetcher, PrefetchConfig, PrefetchOutcome, PrefetchStats};
+pub use prompt_compression::{
+    HeuristicTokenScorer, PromptCompressionConfig, PromptCompressionStats, PromptCompressor, TokenScorer,
+};
+pub use provenance::Trust;
+pub use residency::{item_region, ResidencyFence};
+pub use scratchpad::{Scratchpad, ScratchpadEntry};
+pub use sharded::ShardedMemoryStore;
+pub use tuning::{
+    AutoTuner, AutoTunerConfig, EvalMetrics, HybridRetriever, LoggedQuery, QueryLog, SearchSpace, Signal,
+    TunableRetriever, TuningObjective, TuningProposal,
+};
+pub use wal::{WalConfig, WalEntry, WalMemoryStore, WalOperation, WriteAheadLog};
+pub use window_diff::{
+    ContextWindowDiff, ContextWindowSnapshot, ContextWindowTracker, DropReason, DroppedEntry,
+    WindowEntry,
 };
 
 /// Error types for context operations
@@ -51,6 +117,14 @@ pub enum ContextError {
 
     #[error("Core error: {0}")]
     CoreError(String),
+
+    /// The embedding provider answered 429; providers return this so the
+    /// scheduler can back off and retry
+    #[error("Rate limited by provider")]
+    RateLimited { retry_after: Option<std::time::Duration> },
+
+    #[error("Invalid calibration: {0}")]
+    InvalidCalibration(String),
 }
 
 pub type Result<T> = std::result::Result<T, ContextError>;
@@ -5,10 +5,12 @@
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+use crate::filter::ContextFilter;
 use crate::{ContextError, Result};
 
 /// Memory tier enumeration
@@ -28,6 +30,11 @@ pub enum MemoryTier {
     /// Retention: Days to weeks
     /// Capacity: ~140K tokens
     LongTerm,
+
+    /// Archived memory (inactive for a long time, content in object storage)
+    /// Retention: Until removed
+    /// Capacity: none held locally; only metadata and search terms
+    Archive,
 }
 
 impl MemoryTier {
@@ -37,6 +44,7 @@ impl MemoryTier {
             MemoryTier::ShortTerm => 10_000,
             MemoryTier::MediumTerm => 50_000,
             MemoryTier::LongTerm => 140_000,
+            MemoryTier::Archive => 0,
         }
     }
 
@@ -46,6 +54,7 @@ impl MemoryTier {
             MemoryTier::ShortTerm => 0.1,    // Fast decay
             MemoryTier::MediumTerm => 0.05,  // Medium decay
             MemoryTier::LongTerm => 0.01,    // Slow decay
+            MemoryTier::Archive => 0.01,     // Kept as archived
         }
     }
 
@@ -55,6 +64,8 @@ impl MemoryTier {
             MemoryTier::ShortTerm => 0.3,
             MemoryTier::MediumTerm => 0.5,
             MemoryTier::LongTerm => 0.7,
+            // Items are archived for inactivity, never for their importance
+            MemoryTier::Archive => 1.0,
         }
     }
 }
@@ -138,7 +149,17 @@ impl MemoryItem {
         importance: f64,
         token_count: usize,
     ) -> Self {
-        let now = Utc::now();
+        Self::new_at(content, metadata, importance, token_count, Utc::now())
+    }
+
+    /// Create an item stored at `now`
+    pub fn new_at(
+        content: String,
+        metadata: MemoryMetadata,
+        importance: f64,
+        token_count: usize,
+        now: DateTime<Utc>,
+    ) -> Self {
         let tier = Self::select_tier(importance);
 
         Self {
@@ -167,14 +188,23 @@ impl MemoryItem {
 
     /// Update access statistics
     pub fn record_access(&mut self) {
-        self.last_accessed = Utc::now();
+        self.record_access_at(Utc::now());
+    }
+
+    /// Update access statistics for an access at `now`
+    pub fn record_access_at(&mut self, now: DateTime<Utc>) {
+        self.last_accessed = now;
         self.access_count += 1;
     }
 
     /// Calculate current importance with time decay
     pub fn current_importance(&self) -> f64 {
-        let age_seconds = (Utc::now() - self.created_at).num_seconds() as f64;
-        let recency_seconds = (Utc::now() - self.last_accessed).num_seconds() as f64;
+        self.importance_at(Utc::now())
+    }
+
+    /// Importance with time decay as of `now`
+    pub fn importance_at(&self, now: DateTime<Utc>) -> f64 {
+        let recency_seconds = (now - self.last_accessed).num_seconds() as f64;
 
         let decay_rate = self.tier.decay_rate();
         let time_factor = (-decay_rate * recency_seconds / 3600.0).exp(); // Decay per hour
@@ -204,7 +234,7 @@ impl MemoryItem {
                     None
                 }
             }
-            MemoryTier::LongTerm => None,
+            MemoryTier::LongTerm | MemoryTier::Archive => None,
         }
     }
 
@@ -227,7 +257,7 @@ impl MemoryItem {
                     None
                 }
             }
-            MemoryTier::ShortTerm => None,
+            MemoryTier::ShortTerm | MemoryTier::Archive => None,
         }
     }
 
@@ -317,10 +347,13 @@ impl ImportanceScorer {
 }
 
 /// Trait for tier-specific storage backends
+///
+/// Every operation takes `&self`, so implementations guard their own state
+/// and a store can be shared behind an `Arc` by the engine's tiers.
 #[async_trait]
 pub trait MemoryStore: Send + Sync {
     /// Store a memory item
-    async fn store(&mut self, item: MemoryItem) -> Result<()>;
+    async fn store(&self, item: MemoryItem) -> Result<()>;
 
     /// Retrieve a memory item by ID
     async fn retrieve(&self, id: &Uuid) -> Result<Option<MemoryItem>>;
@@ -328,35 +361,50 @@ pub trait MemoryStore: Send + Sync {
     /// List all items in the store
     async fn list(&self) -> Result<Vec<MemoryItem>>;
 
+    /// List the items matching `filter`
+    async fn list_matching(&self, filter: &ContextFilter) -> Result<Vec<MemoryItem>> {
+        Ok(self.list().await?.into_iter().filter(|item| filter.matches(item)).collect())
+    }
+
+    /// Remove an item, returning it if present
+    async fn take(&self, id: &Uuid) -> Result<Option<MemoryItem>>;
+
     /// Remove an item by ID
-    async fn remove(&mut self, id: &Uuid) -> Result<()>;
+    async fn remove(&self, id: &Uuid) -> Result<()> {
+        self.take(id).await.map(|_| ())
+    }
 
     /// Update an existing item
-    async fn update(&mut self, item: MemoryItem) -> Result<()>;
+    async fn update(&self, item: MemoryItem) -> Result<()>;
 
     /// Get total token count in store
     async fn total_tokens(&self) -> Result<usize>;
 
+    /// Get the number of items in store
+    async fn count(&self) -> Result<usize> {
+        Ok(self.list().await?.len())
+    }
+
     /// Clear all items from store
-    async fn clear(&mut self) -> Result<()>;
+    async fn clear(&self) -> Result<()>;
 
     /// Get items by tier
     async fn get_by_tier(&self, tier: MemoryTier) -> Result<Vec<MemoryItem>>;
 
     /// Evict items to free up space
-    async fn evict(&mut self, target_tokens: usize) -> Result<Vec<MemoryItem>>;
+    async fn evict(&self, target_tokens: usize) -> Result<Vec<MemoryItem>>;
 }
 
 /// In-memory implementation of MemoryStore
 pub struct InMemoryStore {
-    items: HashMap<Uuid, MemoryItem>,
+    items: RwLock<HashMap<Uuid, MemoryItem>>,
     tier: MemoryTier,
 }
 
 impl InMemoryStore {
     pub fn new(tier: MemoryTier) -> Self {
         Self {
-            items: HashMap::new(),
+            items: RwLock::new(HashMap::new()),
             tier,
         }
     }
@@ -364,55 +412,64 @@ impl InMemoryStore {
 
 #[async_trait]
 impl MemoryStore for InMemoryStore {
-    async fn store(&mut self, item: MemoryItem) -> Result<()> {
-        self.items.insert(item.metadata.id, item);
+    async fn store(&self, item: MemoryItem) -> Result<()> {
+        self.items.write().insert(item.metadata.id, item);
         Ok(())
     }
 
     async fn retrieve(&self, id: &Uuid) -> Result<Option<MemoryItem>> {
-        Ok(self.items.get(id).cloned())
+        Ok(self.items.read().get(id).cloned())
     }
 
     async fn list(&self) -> Result<Vec<MemoryItem>> {
-        Ok(self.items.values().cloned().collect())
+        Ok(self.items.read().values().cloned().collect())
     }
 
-    async fn remove(&mut self, id: &Uuid) -> Result<()> {
-        self.items.remove(id);
-        Ok(())
+    async fn list_matching(&self, filter: &ContextFilter) -> Result<Vec<MemoryItem>> {
+        Ok(self.items.read().values().filter(|item| filter.matches(item)).cloned().collect())
+    }
+
+    async fn take(&self, id: &Uuid) -> Result<Option<MemoryItem>> {
+        Ok(self.items.write().remove(id))
     }
 
-    async fn update(&mut self, item: MemoryItem) -> Result<()> {
-        self.items.insert(item.metadata.id, item);
+    async fn update(&self, item: MemoryItem) -> Result<()> {
+        self.items.write().insert(item.metadata.id, item);
         Ok(())
     }
 
     async fn total_tokens(&self) -> Result<usize> {
-        Ok(self.items.values().map(|item| item.token_count).sum())
+        Ok(self.items.read().values().map(|item| item.token_count).sum())
+    }
+
+    async fn count(&self) -> Result<usize> {
+        Ok(self.items.read().len())
     }
 
-    async fn clear(&mut self) -> Result<()> {
-        self.items.clear();
+    async fn clear(&self) -> Result<()> {
+        self.items.write().clear();
         Ok(())
     }
 
     async fn get_by_tier(&self, tier: MemoryTier) -> Result<Vec<MemoryItem>> {
         Ok(self
             .items
+            .read()
             .values()
             .filter(|item| item.tier == tier)
             .cloned()
             .collect())
     }
 
-    async fn evict(&mut self, target_tokens: usize) -> Result<Vec<MemoryItem>> {
-        let current_tokens = self.total_tokens().await?;
+    async fn evict(&self, target_tokens: usize) -> Result<Vec<MemoryItem>> {
+        let mut items = self.items.write();
+        let current_tokens: usize = items.values().map(|item| item.token_count).sum();
         if current_tokens <= target_tokens {
             return Ok(Vec::new());
         }
 
-        let mut items: Vec<_> = self.items.values().cloned().collect();
-        items.sort_by(|a, b| {
+        let mut candidates: Vec<_> = items.values().cloned().collect();
+        candidates.sort_by(|a, b| {
             a.current_importance()
                 .partial_cmp(&b.current_importance())
                 .unwrap_or(std::cmp::Ordering::Equal)
@@ -422,12 +479,12 @@ impl MemoryStore for InMemoryStore {
         let mut freed_tokens = 0;
         let tokens_to_free = current_tokens - target_tokens;
 
-        for item in items {
+        for item in candidates {
             if freed_tokens >= tokens_to_free {
                 break;
             }
             freed_tokens += item.token_count;
-            self.items.remove(&item.metadata.id);
+            items.remove(&item.metadata.id);
             evicted.push(item);
         }
 
@@ -475,7 +532,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_in_memory_store() {
-        let mut store = InMemoryStore::new(MemoryTier::ShortTerm);
+        let store = InMemoryStore::new(MemoryTier::ShortTerm);
         let item = MemoryItem::new(
             "test content".to_string(),
             MemoryMetadata::new("test", "test"),
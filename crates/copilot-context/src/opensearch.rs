@@ -0,0 +1,777 @@
+//! OpenSearch / Elasticsearch retrieval backend
+//!
+//! [`OpenSearchBackend`] keeps searchable documents in an existing OpenSearch
+//! or Elasticsearch cluster instead of the in-process
+//! [`HybridSearchEngine`](crate::HybridSearchEngine). It creates the index
+//! with a text field for BM25 and a vector field for kNN, bulk-indexes
+//! documents (embedding them with the configured provider when they arrive
+//! without a vector), and answers each query with one hybrid query that
+//! adds a BM25 match and a kNN clause, boosted by the fusion weights.
+//! [`ContextFilter`]s become filter clauses, so the cluster only scores
+//! matching documents.
+//!
+//! The two engines differ in the vector mapping and the kNN syntax; see
+//! [`SearchFlavor`].
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::{
+    filter::{ContextFilter, LANGUAGE_KEY},
+    hybrid_search::{Embedding, EmbeddingProvider},
+    memory::MemoryMetadata,
+    retrieval::SearchParams,
+    tuning::TunableRetriever,
+    ContextError, Result,
+};
+
+/// Which search engine the cluster runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchFlavor {
+    /// OpenSearch 2.x with the k-NN plugin: `knn_vector` fields and a `knn`
+    /// query clause
+    OpenSearch,
+    /// Elasticsearch 8.x: `dense_vector` fields and a top-level `knn`
+    /// section
+    Elasticsearch,
+}
+
+/// Credentials for the cluster
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SearchAuth {
+    #[default]
+    None,
+    Basic { username: String, password: String },
+    ApiKey { key: String },
+}
+
+impl fmt::Debug for SearchAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchAuth::None => write!(f, "None"),
+            SearchAuth::Basic { username, .. } => write!(f, "Basic({}, <redacted>)", username),
+            SearchAuth::ApiKey { .. } => write!(f, "ApiKey(<redacted>)"),
+        }
+    }
+}
+
+/// Cluster connection and index settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenSearchConfig {
+    /// Cluster URL, e.g. `https://search.internal:9200`
+    pub url: String,
+    pub index: String,
+    pub flavor: SearchFlavor,
+    pub auth: SearchAuth,
+    /// Vector dimension; must match the embedding provider
+    pub dimension: usize,
+    pub shards: u32,
+    pub replicas: u32,
+    /// Boost of the kNN clause in hybrid scores
+    pub vector_weight: f32,
+    /// Boost of the BM25 clause in hybrid scores
+    pub keyword_weight: f32,
+    /// Nearest-neighbour candidates per shard, as a multiple of the limit
+    pub candidate_factor: usize,
+    /// Documents per `_bulk` request
+    pub bulk_batch_size: usize,
+    pub timeout: Duration,
+}
+
+impl OpenSearchConfig {
+    pub fn new(url: impl Into<String>, index: impl Into<String>, flavor: SearchFlavor, dimension: usize) -> Self {
+        Self {
+            url: url.into().trim_end_matches('/').to_string(),
+            index: index.into(),
+            flavor,
+            auth: SearchAuth::None,
+            dimension,
+            shards: 1,
+            replicas: 1,
+            vector_weight: 0.7,
+            keyword_weight: 0.3,
+            candidate_factor: 10,
+            bulk_batch_size: 500,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_auth(mut self, auth: SearchAuth) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    pub fn with_weights(mut self, vector_weight: f32, keyword_weight: f32) -> Self {
+        self.vector_weight = vector_weight;
+        self.keyword_weight = keyword_weight;
+        self
+    }
+
+    pub fn with_shards(mut self, shards: u32, replicas: u32) -> Self {
+        self.shards = shards.max(1);
+        self.replicas = replicas;
+        self
+    }
+
+    pub fn with_bulk_batch_size(mut self, size: usize) -> Self {
+        self.bulk_batch_size = size.max(1);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// A document to index
+#[derive(Debug, Clone)]
+pub struct SearchDocument {
+    pub id: String,
+    pub content: String,
+    pub metadata: MemoryMetadata,
+    pub ingested_at: DateTime<Utc>,
+    /// Precomputed embedding; documents without one are embedded on
+    /// indexing
+    pub embedding: Option<Embedding>,
+}
+
+impl SearchDocument {
+    pub fn new(id: impl Into<String>, content: impl Into<String>, metadata: MemoryMetadata) -> Self {
+        Self {
+            id: id.into(),
+            content: content.into(),
+            metadata,
+            ingested_at: Utc::now(),
+            embedding: None,
+        }
+    }
+
+    pub fn with_embedding(mut self, embedding: Embedding) -> Self {
+        self.embedding = Some(embedding);
+        self
+    }
+
+    pub fn ingested_at(mut self, at: DateTime<Utc>) -> Self {
+        self.ingested_at = at;
+        self
+    }
+
+    fn source(&self, embedding: &Embedding) -> Value {
+        let language = self
+            .metadata
+            .custom
+            .get(LANGUAGE_KEY)
+            .and_then(|v| v.as_str())
+            .map(str::to_lowercase);
+        json!({
+            "content": self.content,
+            "embedding": embedding,
+            "source": self.metadata.source,
+            "content_type": self.metadata.content_type,
+            "tags": self.metadata.tags,
+            "language": language,
+            "ingested_at": self.ingested_at.to_rfc3339(),
+            "metadata": self.metadata,
+        })
+    }
+}
+
+/// A document matching a query
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub doc_id: String,
+    /// Hybrid score: boosted BM25 plus boosted vector similarity
+    pub score: f32,
+    pub content: String,
+    /// Metadata the document was indexed with, if it could be read back
+    pub metadata: Option<MemoryMetadata>,
+}
+
+/// Outcome of a `_bulk` request
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BulkReport {
+    /// Documents indexed or deleted
+    pub succeeded: usize,
+    /// Document IDs the cluster rejected, with its reason
+    pub failed: Vec<(String, String)>,
+}
+
+impl BulkReport {
+    fn merge(&mut self, other: BulkReport) {
+        self.succeeded += other.succeeded;
+        self.failed.extend(other.failed);
+    }
+}
+
+/// Retrieval backend on an OpenSearch or Elasticsearch cluster
+pub struct OpenSearchBackend {
+    config: OpenSearchConfig,
+    client: reqwest::Client,
+    provider: Arc<dyn EmbeddingProvider>,
+}
+
+impl OpenSearchBackend {
+    pub fn new(config: OpenSearchConfig, provider: Arc<dyn EmbeddingProvider>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(|e| ContextError::StorageError(format!("Failed to build search client: {}", e)))?;
+        Ok(Self {
+            config,
+            client,
+            provider,
+        })
+    }
+
+    pub fn config(&self) -> &OpenSearchConfig {
+        &self.config
+    }
+
+    /// Settings and mappings the index is created with
+    pub fn index_definition(&self) -> Value {
+        let embedding = match self.config.flavor {
+            SearchFlavor::OpenSearch => json!({
+                "type": "knn_vector",
+                "dimension": self.config.dimension,
+                "method": { "name": "hnsw", "space_type": "cosinesimil", "engine": "lucene" },
+            }),
+            SearchFlavor::Elasticsearch => json!({
+                "type": "dense_vector",
+                "dims": self.config.dimension,
+                "index": true,
+                "similarity": "cosine",
+            }),
+        };
+        let mut settings = json!({
+            "number_of_shards": self.config.shards,
+            "number_of_replicas": self.config.replicas,
+        });
+        if self.config.flavor == SearchFlavor::OpenSearch {
+            settings["knn"] = json!(true);
+        }
+        json!({
+            "settings": { "index": settings },
+            "mappings": {
+                "properties": {
+                    "content": { "type": "text" },
+                    "embedding": embedding,
+                    "source": { "type": "keyword" },
+                    "content_type": { "type": "keyword" },
+                    "tags": { "type": "keyword" },
+                    "language": { "type": "keyword" },
+                    "ingested_at": { "type": "date" },
+                    "metadata": { "type": "object", "enabled": false },
+                }
+            }
+        })
+    }
+
+    /// Create the index unless it exists; true when it was created
+    pub async fn ensure_index(&self) -> Result<bool> {
+        let response = self
+            .request(reqwest::Method::PUT, &self.config.index)
+            .json(&self.index_definition())
+            .send()
+            .await
+            .map_err(|e| self.transport_error(e))?;
+        let status = response.status();
+        if status.is_success() {
+            info!(index = %self.config.index, "Created search index");
+            return Ok(true);
+        }
+        let body = response.text().await.unwrap_or_default();
+        if body.contains("resource_already_exists_exception") {
+            return Ok(false);
+        }
+        Err(self.status_error("create index", status, &body))
+    }
+
+    /// Delete the index and everything in it
+    pub async fn delete_index(&self) -> Result<()> {
+        let response = self
+            .request(reqwest::Method::DELETE, &self.config.index)
+            .send()
+            .await
+            .map_err(|e| self.transport_error(e))?;
+        self.check(response, "delete index").await.map(|_| ())
+    }
+
+    /// Make recent writes visible to search
+    pub async fn refresh(&self) -> Result<()> {
+        let response = self
+            .request(reqwest::Method::POST, &format!("{}/_refresh", self.config.index))
+            .send()
+            .await
+            .map_err(|e| self.transport_error(e))?;
+        self.check(response, "refresh").await.map(|_| ())
+    }
+
+    /// Index a document without metadata
+    pub async fn index(&self, doc_id: &str, content: &str) -> Result<()> {
+        let report = self
+            .index_documents(vec![SearchDocument::new(doc_id, content, MemoryMetadata::new("document", ""))])
+            .await?;
+        match report.failed.into_iter().next() {
+            Some((id, reason)) => Err(ContextError::StorageError(format!("Failed to index {}: {}", id, reason))),
+            None => Ok(()),
+        }
+    }
+
+    /// Index documents in `_bulk` batches, embedding those without a vector
+    pub async fn index_documents(&self, documents: Vec<SearchDocument>) -> Result<BulkReport> {
+        let mut report = BulkReport::default();
+        for batch in documents.chunks(self.config.bulk_batch_size) {
+            let missing: Vec<&str> = batch
+                .iter()
+                .filter(|doc| doc.embedding.is_none())
+                .map(|doc| doc.content.as_str())
+                .collect();
+            let mut embedded = self.provider.embed_batch(&missing).await?.into_iter();
+
+            let mut body = String::new();
+            for doc in batch {
+                let embedding = match &doc.embedding {
+                    Some(embedding) => embedding.clone(),
+                    None => embedded.next().ok_or_else(|| {
+                        ContextError::StorageError("Embedding provider returned too few embeddings".to_string())
+                    })?,
+                };
+                body.push_str(&json!({ "index": { "_index": self.config.index, "_id": doc.id } }).to_string());
+                body.push('\n');
+                body.push_str(&doc.source(&embedding).to_string());
+                body.push('\n');
+            }
+            report.merge(self.bulk(body).await?);
+        }
+        debug!(
+            index = %self.config.index,
+            succeeded = report.succeeded,
+            failed = report.failed.len(),
+            "Bulk indexed documents"
+        );
+        Ok(report)
+    }
+
+    /// Delete documents by ID
+    pub async fn remove(&self, doc_ids: &[String]) -> Result<BulkReport> {
+        let mut report = BulkReport::default();
+        for batch in doc_ids.chunks(self.config.bulk_batch_size) {
+            let mut body = String::new();
+            for id in batch {
+                body.push_str(&json!({ "delete": { "_index": self.config.index, "_id": id } }).to_string());
+                body.push('\n');
+            }
+            report.merge(self.bulk(body).await?);
+        }
+        Ok(report)
+    }
+
+    /// Search with hybrid BM25 and kNN scoring
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        self.search_filtered(query, limit, &ContextFilter::default()).await
+    }
+
+    /// Search the documents matching `filter`
+    pub async fn search_filtered(&self, query: &str, limit: usize, filter: &ContextFilter) -> Result<Vec<SearchHit>> {
+        let weights = (self.config.vector_weight, self.config.keyword_weight);
+        self.search_weighted(query, limit, filter, weights).await
+    }
+
+    /// Hybrid query DSL for `query`, whose embedding is `embedding`
+    pub fn hybrid_query(
+        &self,
+        query: &str,
+        embedding: &Embedding,
+        limit: usize,
+        filter: &ContextFilter,
+        (vector_weight, keyword_weight): (f32, f32),
+    ) -> Value {
+        let filters = filter_clauses(filter);
+        let candidates = limit.max(1) * self.config.candidate_factor.max(1);
+        let text = json!({ "match": { "content": { "query": query, "boost": keyword_weight } } });
+        match self.config.flavor {
+            SearchFlavor::OpenSearch => {
+                let mut knn = json!({ "vector": embedding, "k": candidates, "boost": vector_weight });
+                if !filters.is_empty() {
+                    knn["filter"] = json!({ "bool": { "filter": filters } });
+                }
+                json!({
+                    "size": limit,
+                    "_source": { "excludes": ["embedding"] },
+                    "query": {
+                        "bool": {
+                            "should": [text, { "knn": { "embedding": knn } }],
+                            "minimum_should_match": 1,
+                            "filter": filters,
+                        }
+                    }
+                })
+            }
+            SearchFlavor::Elasticsearch => json!({
+                "size": limit,
+                "_source": { "excludes": ["embedding"] },
+                "query": { "bool": { "must": [text], "filter": filters } },
+                "knn": {
+                    "field": "embedding",
+                    "query_vector": embedding,
+                    "k": limit,
+                    "num_candidates": candidates,
+                    "boost": vector_weight,
+                    "filter": filters,
+                },
+            }),
+        }
+    }
+
+    async fn search_weighted(
+        &self,
+        query: &str,
+        limit: usize,
+        filter: &ContextFilter,
+        weights: (f32, f32),
+    ) -> Result<Vec<SearchHit>> {
+        let embedding = self.provider.embed(query).await?;
+        let body = self.hybrid_query(query, &embedding, limit, filter, weights);
+        let response = self
+            .request(reqwest::Method::POST, &format!("{}/_search", self.config.index))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| self.transport_error(e))?;
+        let response = self.check(response, "search").await?;
+
+        let hits = response["hits"]["hits"].as_array().cloned().unwrap_or_default();
+        Ok(hits
+            .into_iter()
+            .filter_map(|hit| {
+                let source = &hit["_source"];
+                Some(SearchHit {
+                    doc_id: hit["_id"].as_str()?.to_string(),
+                    score: hit["_score"].as_f64().unwrap_or(0.0) as f32,
+                    content: source["content"].as_str().unwrap_or_default().to_string(),
+                    metadata: serde_json::from_value(source["metadata"].clone()).ok(),
+                })
+            })
+            .collect())
+    }
+
+    async fn bulk(&self, body: String) -> Result<BulkReport> {
+        let response = self
+            .request(reqwest::Method::POST, "_bulk")
+            .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| self.transport_error(e))?;
+        let response = self.check(response, "bulk").await?;
+
+        let mut report = BulkReport::default();
+        for item in response["items"].as_array().into_iter().flatten() {
+            let Some(result) = item.as_object().and_then(|item| item.values().next()) else {
+                continue;
+            };
+            let id = result["_id"].as_str().unwrap_or_default().to_string();
+            match result.get("error") {
+                Some(error) => {
+                    let reason = error["reason"].as_str().map(str::to_string).unwrap_or_else(|| error.to_string());
+                    report.failed.push((id, reason));
+                }
+                None => report.succeeded += 1,
+            }
+        }
+        if !report.failed.is_empty() {
+            warn!(
+                index = %self.config.index,
+                failed = report.failed.len(),
+                "Search cluster rejected documents"
+            );
+        }
+        Ok(report)
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, format!("{}/{}", self.config.url, path));
+        match &self.config.auth {
+            SearchAuth::None => request,
+            SearchAuth::Basic { username, password } => request.basic_auth(username, Some(password)),
+            SearchAuth::ApiKey { key } => request.header(reqwest::header::AUTHORIZATION, format!("ApiKey {}", key)),
+        }
+    }
+
+    async fn check(&self, response: reqwest::Response, action: &str) -> Result<Value> {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(self.status_error(action, status, &body));
+        }
+        serde_json::from_str(&body).map_err(ContextError::from)
+    }
+
+    fn status_error(&self, action: &str, status: reqwest::StatusCode, body: &str) -> ContextError {
+        ContextError::StorageError(format!(
+            "Search cluster {} on {} failed with {}: {}",
+            action, self.config.index, status, body
+        ))
+    }
+
+    fn transport_error(&self, error: reqwest::Error) -> ContextError {
+        ContextError::StorageError(format!("Search cluster {} unreachable: {}", self.config.url, error))
+    }
+}
+
+/// Filter clauses equivalent to [`ContextFilter::matches_metadata`]
+fn filter_clauses(filter: &ContextFilter) -> Vec<Value> {
+    let mut clauses: Vec<Value> = filter.tags.iter().map(|tag| json!({ "term": { "tags": tag } })).collect();
+    if let Some(prefix) = &filter.source_prefix {
+        clauses.push(json!({ "prefix": { "source": prefix } }));
+    }
+    if let Some(language) = &filter.language {
+        clauses.push(json!({ "term": { "language": language.to_lowercase() } }));
+    }
+    if let Some(content_type) = &filter.content_type {
+        clauses.push(json!({ "term": { "content_type": content_type } }));
+    }
+    if filter.ingested_after.is_some() || filter.ingested_before.is_some() {
+        let mut range = serde_json::Map::new();
+        if let Some(after) = filter.ingested_after {
+            range.insert("gte".to_string(), json!(after.to_rfc3339()));
+        }
+        if let Some(before) = filter.ingested_before {
+            range.insert("lt".to_string(), json!(before.to_rfc3339()));
+        }
+        clauses.push(json!({ "range": { "ingested_at": range } }));
+    }
+    if let Some(as_of) = filter.as_of {
+        clauses.push(json!({ "range": { "ingested_at": { "lte": as_of.to_rfc3339() } } }));
+    }
+    clauses
+}
+
+/// Replays use the parameters' fusion weights and result count; there is no
+/// reranking step, so the rerank threshold is ignored
+#[async_trait]
+impl TunableRetriever for OpenSearchBackend {
+    async fn retrieve(&self, query: &str, params: &SearchParams) -> Result<Vec<String>> {
+        let weights = (params.vector_weight, params.keyword_weight());
+        let hits = self
+            .search_weighted(query, params.top_k, &ContextFilter::default(), weights)
+            .await?;
+        Ok(hits.into_iter().map(|hit| hit.doc_id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_search::MockEmbeddingProvider;
+    use parking_lot::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[derive(Debug, Clone)]
+    struct Recorded {
+        request_line: String,
+        headers: String,
+        body: String,
+    }
+
+    /// Answers each connection with the next canned response, recording
+    /// the requests
+    async fn fake_cluster(responses: Vec<(u16, Value)>) -> (String, Arc<Mutex<Vec<Recorded>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let log = recorded.clone();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let (head_len, content_length) = loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buffer.extend_from_slice(&chunk[..n]);
+                    if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                        let head = String::from_utf8_lossy(&buffer[..end]).to_lowercase();
+                        let length = head
+                            .lines()
+                            .find_map(|line| line.strip_prefix("content-length:"))
+                            .and_then(|v| v.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        break (end + 4, length);
+                    }
+                };
+                while buffer.len() < head_len + content_length {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buffer.extend_from_slice(&chunk[..n]);
+                }
+                let head = String::from_utf8_lossy(&buffer[..head_len]).to_string();
+                let (request_line, headers) = head.split_once("\r\n").unwrap();
+                log.lock().push(Recorded {
+                    request_line: request_line.to_string(),
+                    headers: headers.to_lowercase(),
+                    body: String::from_utf8_lossy(&buffer[head_len..]).to_string(),
+                });
+
+                let body = body.to_string();
+                let response = format!(
+                    "HTTP/1.1 {} X\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, recorded)
+    }
+
+    fn backend(url: &str, flavor: SearchFlavor) -> OpenSearchBackend {
+        let config = OpenSearchConfig::new(url, "context", flavor, 8).with_auth(SearchAuth::ApiKey {
+            key: "secret-key".to_string(),
+        });
+        OpenSearchBackend::new(config, Arc::new(MockEmbeddingProvider::new(8))).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_index_management() {
+        let (url, recorded) = fake_cluster(vec![
+            (200, json!({ "acknowledged": true })),
+            (400, json!({ "error": { "type": "resource_already_exists_exception" } })),
+            (401, json!({ "error": "unauthorized" })),
+        ])
+        .await;
+        let backend = backend(&url, SearchFlavor::OpenSearch);
+
+        assert!(backend.ensure_index().await.unwrap());
+        assert!(!backend.ensure_index().await.unwrap());
+        let err = backend.ensure_index().await.unwrap_err();
+        assert!(err.to_string().contains("401"));
+
+        let requests = recorded.lock().clone();
+        assert_eq!(requests[0].request_line, "PUT /context HTTP/1.1");
+        assert!(requests[0].headers.contains("authorization: apikey secret-key"));
+        let definition: Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(definition["settings"]["index"]["knn"], json!(true));
+        assert_eq!(definition["mappings"]["properties"]["embedding"]["type"], "knn_vector");
+        assert_eq!(definition["mappings"]["properties"]["embedding"]["dimension"], 8);
+
+        let elastic = backend.index_definition();
+        assert_eq!(elastic["mappings"]["properties"]["content"]["type"], "text");
+        let elastic = OpenSearchBackend::new(
+            OpenSearchConfig::new(&url, "context", SearchFlavor::Elasticsearch, 8),
+            Arc::new(MockEmbeddingProvider::new(8)),
+        )
+        .unwrap()
+        .index_definition();
+        assert_eq!(elastic["mappings"]["properties"]["embedding"]["type"], "dense_vector");
+        assert!(elastic["settings"]["index"].get("knn").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bulk_indexing_reports_rejections() {
+        let (url, recorded) = fake_cluster(vec![(
+            200,
+            json!({
+                "errors": true,
+                "items": [
+                    { "index": { "_id": "doc-1", "status": 201 } },
+                    { "index": { "_id": "doc-2", "status": 400, "error": { "reason": "mapper_parsing_exception" } } },
+                ]
+            }),
+        )])
+        .await;
+        let backend = backend(&url, SearchFlavor::OpenSearch);
+
+        let metadata = MemoryMetadata::new("document", "https://wiki/runbooks")
+            .with_tags(vec!["ops".to_string()]);
+        let report = backend
+            .index_documents(vec![
+                SearchDocument::new("doc-1", "rotate the signing keys", metadata.clone()),
+                SearchDocument::new("doc-2", "failover runbook", metadata).with_embedding(vec![0.5; 8]),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(report.succeeded, 1);
+        assert_eq!(report.failed, vec![("doc-2".to_string(), "mapper_parsing_exception".to_string())]);
+
+        let request = recorded.lock()[0].clone();
+        assert_eq!(request.request_line, "POST /_bulk HTTP/1.1");
+        assert!(request.headers.contains("content-type: application/x-ndjson"));
+        let lines: Vec<Value> = request.body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], json!({ "index": { "_index": "context", "_id": "doc-1" } }));
+        assert_eq!(lines[1]["tags"], json!(["ops"]));
+        assert_eq!(lines[1]["embedding"].as_array().unwrap().len(), 8);
+        assert_eq!(lines[3]["embedding"], json!(vec![0.5; 8]));
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search() {
+        let metadata = MemoryMetadata::new("document", "https://wiki/runbooks");
+        let (url, recorded) = fake_cluster(vec![(
+            200,
+            json!({
+                "hits": { "hits": [
+                    { "_id": "doc-2", "_score": 3.5, "_source": { "content": "failover runbook", "metadata": metadata } },
+                    { "_id": "doc-1", "_score": 1.25, "_source": { "content": "signing keys" } },
+                ] }
+            }),
+        )])
+        .await;
+        let backend = backend(&url, SearchFlavor::Elasticsearch);
+
+        let filter = ContextFilter::new().with_tag("ops").with_language("EN");
+        let hits = backend.search_filtered("database failover", 5, &filter).await.unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].doc_id, "doc-2");
+        assert_eq!(hits[0].score, 3.5);
+        assert_eq!(hits[0].metadata.as_ref().unwrap().source, "https://wiki/runbooks");
+        assert!(hits[1].metadata.is_none());
+
+        let request = recorded.lock()[0].clone();
+        assert_eq!(request.request_line, "POST /context/_search HTTP/1.1");
+        let body: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body["size"], 5);
+        assert_eq!(body["query"]["bool"]["must"][0]["match"]["content"]["query"], "database failover");
+        assert_eq!(body["knn"]["num_candidates"], 50);
+        let filters = json!([{ "term": { "tags": "ops" } }, { "term": { "language": "en" } }]);
+        assert_eq!(body["query"]["bool"]["filter"], filters);
+        assert_eq!(body["knn"]["filter"], filters);
+    }
+
+    #[test]
+    fn test_opensearch_query_dsl() {
+        let backend = backend("http://localhost:9200", SearchFlavor::OpenSearch);
+        let after = Utc::now();
+        let filter = ContextFilter::new()
+            .with_source_prefix("file:///repo/")
+            .ingested_between(Some(after), None);
+
+        let query = backend.hybrid_query("rollback", &vec![0.0; 8], 3, &filter, (0.2, 0.8));
+        let should = &query["query"]["bool"]["should"];
+        assert_eq!(should[0]["match"]["content"]["boost"].as_f64().unwrap() as f32, 0.8);
+        assert_eq!(should[1]["knn"]["embedding"]["k"], 30);
+        assert_eq!(should[1]["knn"]["embedding"]["boost"].as_f64().unwrap() as f32, 0.2);
+        assert_eq!(query["query"]["bool"]["minimum_should_match"], 1);
+        assert_eq!(
+            query["query"]["bool"]["filter"],
+            json!([
+                { "prefix": { "source": "file:///repo/" } },
+                { "range": { "ingested_at": { "gte": after.to_rfc3339() } } },
+            ])
+        );
+
+        let unfiltered = backend.hybrid_query("rollback", &vec![0.0; 8], 3, &ContextFilter::new(), (0.5, 0.5));
+        assert!(unfiltered["query"]["bool"]["should"][1]["knn"]["embedding"].get("filter").is_none());
+        assert!(format!("{:?}", backend.config()).contains("<redacted>"));
+    }
+}
@@ -0,0 +1,156 @@
+//! Per-stage tracing of the context pipeline
+//!
+//! Building the context of a message runs through retrieval, reranking,
+//! compression and prompt assembly. Each stage runs in a tracing span of its
+//! own (`context.retrieve`, `context.rerank`, `context.compress`,
+//! `context.assemble`) and reports a [`StageRecord`] to the
+//! [`ContextStageObserver`] set on the prefetcher and the conversation
+//! manager: how long it took, how many candidates went in and came out, the
+//! tokens before and after it, and whether a cache answered it. Observers
+//! aggregate the records into metrics, so operators can see where context
+//! latency and the token budget go.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{field::Empty, Span};
+
+/// A stage of the context pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStage {
+    /// Candidates fetched from the engine and fitted to the token budget
+    Retrieval,
+    /// Selected items reordered by the reranker
+    Rerank,
+    /// Selected items compressed to save tokens
+    Compression,
+    /// Selected items assembled into the prompt
+    Assembly,
+}
+
+impl ContextStage {
+    pub const ALL: [ContextStage; 4] = [
+        ContextStage::Retrieval,
+        ContextStage::Rerank,
+        ContextStage::Compression,
+        ContextStage::Assembly,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContextStage::Retrieval => "retrieval",
+            ContextStage::Rerank => "rerank",
+            ContextStage::Compression => "compression",
+            ContextStage::Assembly => "assembly",
+        }
+    }
+
+    /// A span for one run of the stage; its figures are filled in by
+    /// [`StageRecord::trace`]
+    pub fn span(&self) -> Span {
+        macro_rules! stage_span {
+            ($name:literal) => {
+                tracing::debug_span!(
+                    $name,
+                    latency_ms = Empty,
+                    candidates_in = Empty,
+                    candidates_out = Empty,
+                    tokens_in = Empty,
+                    tokens_out = Empty,
+                    cache_hit = Empty
+                )
+            };
+        }
+        match self {
+            ContextStage::Retrieval => stage_span!("context.retrieve"),
+            ContextStage::Rerank => stage_span!("context.rerank"),
+            ContextStage::Compression => stage_span!("context.compress"),
+            ContextStage::Assembly => stage_span!("context.assemble"),
+        }
+    }
+}
+
+/// What one run of a stage did
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageRecord {
+    pub stage: ContextStage,
+    pub latency: Duration,
+    pub candidates_in: usize,
+    pub candidates_out: usize,
+    pub tokens_in: usize,
+    pub tokens_out: usize,
+    /// Whether a cache answered the stage, for stages served from one
+    pub cache_hit: Option<bool>,
+}
+
+impl StageRecord {
+    pub fn new(stage: ContextStage, latency: Duration) -> Self {
+        Self {
+            stage,
+            latency,
+            candidates_in: 0,
+            candidates_out: 0,
+            tokens_in: 0,
+            tokens_out: 0,
+            cache_hit: None,
+        }
+    }
+
+    pub fn with_candidates(mut self, candidates_in: usize, candidates_out: usize) -> Self {
+        self.candidates_in = candidates_in;
+        self.candidates_out = candidates_out;
+        self
+    }
+
+    pub fn with_tokens(mut self, tokens_in: usize, tokens_out: usize) -> Self {
+        self.tokens_in = tokens_in;
+        self.tokens_out = tokens_out;
+        self
+    }
+
+    pub fn with_cache_hit(mut self, hit: bool) -> Self {
+        self.cache_hit = Some(hit);
+        self
+    }
+
+    /// Tokens the stage removed from the context
+    pub fn tokens_saved(&self) -> usize {
+        self.tokens_in.saturating_sub(self.tokens_out)
+    }
+
+    /// Fill in the figures of the stage's span
+    pub fn trace(&self, span: &Span) {
+        span.record("latency_ms", self.latency.as_secs_f64() * 1000.0);
+        span.record("candidates_in", self.candidates_in);
+        span.record("candidates_out", self.candidates_out);
+        span.record("tokens_in", self.tokens_in);
+        span.record("tokens_out", self.tokens_out);
+        if let Some(hit) = self.cache_hit {
+            span.record("cache_hit", hit);
+        }
+    }
+}
+
+/// Receives a record of every context pipeline stage run
+pub trait ContextStageObserver: Send + Sync {
+    fn record(&self, record: &StageRecord);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_record_counts_tokens_saved() {
+        let record = StageRecord::new(ContextStage::Compression, Duration::from_millis(3))
+            .with_candidates(4, 4)
+            .with_tokens(1200, 700);
+        assert_eq!(record.tokens_saved(), 500);
+        assert_eq!(record.cache_hit, None);
+
+        let record = StageRecord::new(ContextStage::Retrieval, Duration::ZERO).with_tokens(10, 40);
+        assert_eq!(record.tokens_saved(), 0);
+        assert_eq!(ContextStage::Assembly.as_str(), "assembly");
+    }
+}
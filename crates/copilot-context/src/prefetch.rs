@@ -0,0 +1,686 @@
+//! Speculative context prefetching
+//!
+//! [`ContextPrefetcher`] wraps a context engine and keeps the retrieval
+//! results of recent queries. Clients send partial queries while the user is
+//! typing; each one is retrieved (and reranked, when a reranker is set) ahead
+//! of time, so by the time the message is sent its context is usually
+//! already assembled. Any write to the underlying engine invalidates the
+//! cached results. Results are cached per [`Principal::access_key`], so
+//! callers only share results with callers allowed to read the same items.
+//!
+//! With a [`PromptCompressor`] set, the selected items are compressed after
+//! reranking, and cached compressed.
+//!
+//! Reranking gives way to the request [`Deadline`]: when it runs out the
+//! items keep their retrieval order, and the result is not cached.
+//!
+//! [`PrefetchStats`] counts hits and misses of the final retrievals along
+//! with their latencies, which shows how much the speculative work saves.
+//! Retrieval, reranking and compression each run in a span of their own and
+//! are reported to the [`ContextStageObserver`], if one is set.
+
+use async_trait::async_trait;
+use copilot_core::{Deadline, Principal};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, Instrument, Span};
+use uuid::Uuid;
+
+use crate::{
+    engine::{CompressionStats, ContextEngine, EngineStats, MaintenanceReport},
+    filter::ContextFilter,
+    memory::{MemoryItem, MemoryMetadata, MemoryTier},
+    pipeline_trace::{ContextStage, ContextStageObserver, StageRecord},
+    prompt_compression::PromptCompressor,
+    reranking::{RerankDocument, Reranker},
+    retrieval::RetrievalResult,
+    Result,
+};
+
+/// Prefetch configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefetchConfig {
+    /// Shortest partial query worth prefetching, in characters
+    pub min_query_chars: usize,
+    /// Retrieval results kept
+    pub capacity: usize,
+    /// How long a prefetched result stays usable
+    pub ttl: Duration,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self {
+            min_query_chars: 3,
+            capacity: 256,
+            ttl: Duration::from_secs(30),
+        }
+    }
+}
+
+/// What a prefetch request did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrefetchOutcome {
+    /// The query was retrieved and cached
+    Warmed,
+    /// A result for the query was already cached
+    AlreadyWarm,
+    /// The query was too short to be worth retrieving
+    TooShort,
+}
+
+/// Prefetch effectiveness counters
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PrefetchStats {
+    /// Speculative retrievals run
+    pub prefetches: u64,
+    /// Prefetch requests that needed no work
+    pub prefetches_skipped: u64,
+    /// Retrievals answered from prefetched results
+    pub hits: u64,
+    /// Retrievals that went to the context engine
+    pub misses: u64,
+    /// `hits / (hits + misses)`
+    pub hit_rate: f64,
+    /// Mean latency of retrievals answered from the cache
+    pub avg_hit_latency_ms: f64,
+    /// Mean latency of retrievals that went to the context engine
+    pub avg_miss_latency_ms: f64,
+}
+
+impl PrefetchStats {
+    /// Render the counters in the Prometheus text exposition format
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        for (name, kind, help, value) in [
+            ("prefetches_total", "counter", "Speculative context retrievals run", self.prefetches as f64),
+            (
+                "prefetches_skipped_total",
+                "counter",
+                "Prefetch requests that needed no work",
+                self.prefetches_skipped as f64,
+            ),
+            ("hits_total", "counter", "Retrievals answered from prefetched results", self.hits as f64),
+            ("misses_total", "counter", "Retrievals that went to the context engine", self.misses as f64),
+            ("hit_latency_ms", "gauge", "Mean latency of prefetch hits", self.avg_hit_latency_ms),
+            ("miss_latency_ms", "gauge", "Mean latency of prefetch misses", self.avg_miss_latency_ms),
+        ] {
+            let _ = writeln!(out, "# HELP {prefix}_context_prefetch_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_context_prefetch_{name} {kind}");
+            let _ = writeln!(out, "{prefix}_context_prefetch_{name} {value}");
+        }
+        out
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    prefetches: AtomicU64,
+    prefetches_skipped: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    hit_micros: AtomicU64,
+    miss_micros: AtomicU64,
+}
+
+struct CachedResult {
+    result: RetrievalResult,
+    stored_at: Instant,
+}
+
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<String, CachedResult>,
+    /// Insertion order, oldest first, for eviction
+    order: VecDeque<String>,
+    /// Bumped by every write to the engine; results retrieved under an older
+    /// generation are not stored
+    generation: u64,
+}
+
+/// Context engine decorator that serves prefetched retrievals
+pub struct ContextPrefetcher {
+    inner: Arc<dyn ContextEngine>,
+    reranker: Option<Arc<dyn Reranker>>,
+    compressor: Option<Arc<PromptCompressor>>,
+    config: PrefetchConfig,
+    cache: Mutex<Cache>,
+    counters: Counters,
+    observer: RwLock<Option<Arc<dyn ContextStageObserver>>>,
+}
+
+impl ContextPrefetcher {
+    pub fn new(inner: Arc<dyn ContextEngine>) -> Self {
+        Self::with_config(inner, PrefetchConfig::default())
+    }
+
+    pub fn with_config(inner: Arc<dyn ContextEngine>, config: PrefetchConfig) -> Self {
+        Self {
+            inner,
+            reranker: None,
+            compressor: None,
+            config,
+            cache: Mutex::new(Cache::default()),
+            counters: Counters::default(),
+            observer: RwLock::new(None),
+        }
+    }
+
+    /// Rerank retrieved items, which also warms the reranker's score cache
+    pub fn with_reranker(mut self, reranker: Arc<dyn Reranker>) -> Self {
+        self.reranker = Some(reranker);
+        self
+    }
+
+    /// Compress the selected items of every retrieval
+    pub fn with_prompt_compressor(mut self, compressor: Arc<PromptCompressor>) -> Self {
+        self.compressor = Some(compressor);
+        self
+    }
+
+    /// Report every retrieval, rerank and compression to `observer`
+    pub fn set_stage_observer(&self, observer: Arc<dyn ContextStageObserver>) {
+        *self.observer.write() = Some(observer);
+    }
+
+    /// The prompt compressor, if retrieved context is compressed
+    pub fn prompt_compressor(&self) -> Option<&Arc<PromptCompressor>> {
+        self.compressor.as_ref()
+    }
+
+    /// Version of the engine's contents, bumped by every write through the
+    /// prefetcher
+    pub fn corpus_version(&self) -> u64 {
+        self.cache.lock().generation
+    }
+
+    /// The wrapped engine
+    pub fn inner(&self) -> Arc<dyn ContextEngine> {
+        Arc::clone(&self.inner)
+    }
+
+    /// Retrieve a partial query ahead of time
+    pub async fn prefetch(&self, partial_query: &str) -> Result<PrefetchOutcome> {
+        let query = normalize(partial_query);
+        if query.chars().count() < self.config.min_query_chars {
+            self.counters.prefetches_skipped.fetch_add(1, Ordering::Relaxed);
+            return Ok(PrefetchOutcome::TooShort);
+        }
+        let key = cache_key(&query);
+        if self.cached(&key).is_some() {
+            self.counters.prefetches_skipped.fetch_add(1, Ordering::Relaxed);
+            return Ok(PrefetchOutcome::AlreadyWarm);
+        }
+
+        self.counters.prefetches.fetch_add(1, Ordering::Relaxed);
+        let result = self.retrieve_uncached(&query, &key, None).await?;
+        debug!("Prefetched context for {:?}: {} items", query, result.selected.len());
+        Ok(PrefetchOutcome::Warmed)
+    }
+
+    /// Current effectiveness counters
+    pub fn prefetch_stats(&self) -> PrefetchStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let hits = load(&self.counters.hits);
+        let misses = load(&self.counters.misses);
+        let mean_ms = |micros: u64, count: u64| {
+            if count == 0 {
+                0.0
+            } else {
+                micros as f64 / count as f64 / 1000.0
+            }
+        };
+
+        PrefetchStats {
+            prefetches: load(&self.counters.prefetches),
+            prefetches_skipped: load(&self.counters.prefetches_skipped),
+            hits,
+            misses,
+            hit_rate: if hits + misses == 0 {
+                0.0
+            } else {
+                hits as f64 / (hits + misses) as f64
+            },
+            avg_hit_latency_ms: mean_ms(load(&self.counters.hit_micros), hits),
+            avg_miss_latency_ms: mean_ms(load(&self.counters.miss_micros), misses),
+        }
+    }
+
+    fn cached(&self, key: &str) -> Option<RetrievalResult> {
+        let mut cache = self.cache.lock();
+        let expired = match cache.entries.get(key) {
+            Some(entry) if entry.stored_at.elapsed() <= self.config.ttl => return Some(entry.result.clone()),
+            Some(_) => true,
+            None => false,
+        };
+        if expired {
+            cache.entries.remove(key);
+            cache.order.retain(|cached| cached != key);
+        }
+        None
+    }
+
+    /// Retrieve (and rerank) from the engine and cache the result under
+    /// `key`; final retrievals report the retrieval stage as a cache miss,
+    /// speculative ones pass `None`
+    async fn retrieve_uncached(&self, query: &str, key: &str, cache_hit: Option<bool>) -> Result<RetrievalResult> {
+        let generation = self.cache.lock().generation;
+        let (result, complete) = self.run_stages(query, &ContextFilter::default(), cache_hit).await?;
+
+        let mut cache = self.cache.lock();
+        if complete && cache.generation == generation {
+            if cache.entries.contains_key(key) {
+                cache.order.retain(|cached| cached != key);
+            }
+            cache.order.push_back(key.to_string());
+            cache.entries.insert(
+                key.to_string(),
+                CachedResult {
+                    result: result.clone(),
+                    stored_at: Instant::now(),
+                },
+            );
+            while cache.order.len() > self.config.capacity {
+                if let Some(oldest) = cache.order.pop_front() {
+                    cache.entries.remove(&oldest);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Retrieve from the engine, then rerank and compress the selected
+    /// items, tracing each stage. Returns false along with the result if
+    /// reranking was cut short.
+    async fn run_stages(
+        &self,
+        query: &str,
+        filter: &ContextFilter,
+        cache_hit: Option<bool>,
+    ) -> Result<(RetrievalResult, bool)> {
+        let span = ContextStage::Retrieval.span();
+        let started = Instant::now();
+        let mut result = if filter.is_empty() {
+            self.inner.retrieve(query).instrument(span.clone()).await?
+        } else {
+            self.inner.retrieve_filtered(query, filter).instrument(span.clone()).await?
+        };
+        let candidate_tokens = result.selected.iter().chain(&result.rejected).map(|s| s.item.token_count).sum();
+        let mut record = StageRecord::new(ContextStage::Retrieval, started.elapsed())
+            .with_candidates(result.candidate_ids.len(), result.selected.len())
+            .with_tokens(candidate_tokens, result.total_tokens);
+        record.cache_hit = cache_hit;
+        self.observe(&span, record);
+
+        let complete = match &self.reranker {
+            Some(reranker) => {
+                let span = ContextStage::Rerank.span();
+                let started = Instant::now();
+                let complete = rerank(reranker.as_ref(), query, &mut result).instrument(span.clone()).await?;
+                let record = StageRecord::new(ContextStage::Rerank, started.elapsed())
+                    .with_candidates(result.selected.len(), result.selected.len())
+                    .with_tokens(result.total_tokens, result.total_tokens);
+                self.observe(&span, record);
+                complete
+            }
+            None => true,
+        };
+
+        if let Some(compressor) = &self.compressor {
+            let span = ContextStage::Compression.span();
+            let started = Instant::now();
+            let tokens_in = result.total_tokens;
+            span.in_scope(|| compressor.compress(query, &mut result));
+            let record = StageRecord::new(ContextStage::Compression, started.elapsed())
+                .with_candidates(result.selected.len(), result.selected.len())
+                .with_tokens(tokens_in, result.total_tokens);
+            self.observe(&span, record);
+        }
+        Ok((result, complete))
+    }
+
+    fn observe(&self, span: &Span, record: StageRecord) {
+        record.trace(span);
+        if let Some(observer) = self.observer.read().as_ref() {
+            observer.record(&record);
+        }
+    }
+
+    /// Drop every cached result after the engine's contents changed
+    fn invalidate(&self) {
+        let mut cache = self.cache.lock();
+        cache.generation += 1;
+        cache.entries.clear();
+        cache.order.clear();
+    }
+}
+
+/// Reorder the selected items by reranker score; items the reranker did not
+/// return keep their place after the reranked ones. Returns false, leaving
+/// the order untouched, if the request deadline passed first.
+async fn rerank(reranker: &dyn Reranker, query: &str, result: &mut RetrievalResult) -> Result<bool> {
+    let documents = result
+        .selected
+        .iter()
+        .map(|scored| {
+            RerankDocument::new(scored.item.metadata.id.to_string(), scored.item.get_content())
+                .with_score(scored.score as f32)
+        })
+        .collect();
+    let Ok(reranked) = Deadline::run(reranker.rerank(query, documents)).await else {
+        debug!("Request deadline reached, skipping rerank");
+        return Ok(false);
+    };
+    let ranks: HashMap<String, usize> = reranked?
+        .into_iter()
+        .map(|reranked| (reranked.id, reranked.new_rank))
+        .collect();
+
+    result.selected.sort_by_key(|scored| {
+        ranks
+            .get(&scored.item.metadata.id.to_string())
+            .copied()
+            .unwrap_or(usize::MAX)
+    });
+    Ok(true)
+}
+
+/// Trim and collapse whitespace so keystroke noise maps to the same query
+fn normalize(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cache key of a normalized query for the current principal
+fn cache_key(query: &str) -> String {
+    match Principal::current() {
+        Some(principal) => format!("{}\0{}", query, principal.access_key()),
+        None => query.to_string(),
+    }
+}
+
+#[async_trait]
+impl ContextEngine for ContextPrefetcher {
+    async fn store(&self, content: String, metadata: MemoryMetadata, importance: f64) -> Result<Uuid> {
+        let id = self.inner.store(content, metadata, importance).await?;
+        self.invalidate();
+        Ok(id)
+    }
+
+    async fn retrieve(&self, query: &str) -> Result<RetrievalResult> {
+        let started = Instant::now();
+        let query = normalize(query);
+        let key = cache_key(&query);
+        let (result, counter, micros) = match self.cached(&key) {
+            Some(result) => {
+                let record = StageRecord::new(ContextStage::Retrieval, started.elapsed())
+                    .with_candidates(result.candidate_ids.len(), result.selected.len())
+                    .with_tokens(result.total_tokens, result.total_tokens)
+                    .with_cache_hit(true);
+                self.observe(&ContextStage::Retrieval.span(), record);
+                (result, &self.counters.hits, &self.counters.hit_micros)
+            }
+            None => (
+                self.retrieve_uncached(&query, &key, Some(false)).await?,
+                &self.counters.misses,
+                &self.counters.miss_micros,
+            ),
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        micros.fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+        Ok(result)
+    }
+
+    /// Filtered retrievals bypass the prefetch cache, which holds only
+    /// unfiltered results
+    async fn retrieve_filtered(&self, query: &str, filter: &ContextFilter) -> Result<RetrievalResult> {
+        if filter.is_empty() {
+            return self.retrieve(query).await;
+        }
+        let query = normalize(query);
+        let (result, _) = self.run_stages(&query, filter, None).await?;
+        Ok(result)
+    }
+
+    async fn list_matching(&self, filter: &ContextFilter) -> Result<Vec<MemoryItem>> {
+        self.inner.list_matching(filter).await
+    }
+
+    async fn retag(&self, id: &Uuid, add: &[String], remove: &[String]) -> Result<()> {
+        self.inner.retag(id, add, remove).await?;
+        self.invalidate();
+        Ok(())
+    }
+
+    async fn reindex(&self, id: &Uuid) -> Result<()> {
+        self.inner.reindex(id).await?;
+        self.invalidate();
+        Ok(())
+    }
+
+    async fn compress(&self) -> Result<CompressionStats> {
+        let stats = self.inner.compress().await?;
+        self.invalidate();
+        Ok(stats)
+    }
+
+    async fn stats(&self) -> Result<EngineStats> {
+        self.inner.stats().await
+    }
+
+    async fn promote(&self, id: &Uuid, tier: MemoryTier) -> Result<()> {
+        self.inner.promote(id, tier).await?;
+        self.invalidate();
+        Ok(())
+    }
+
+    async fn demote(&self, id: &Uuid, tier: MemoryTier) -> Result<()> {
+        self.inner.demote(id, tier).await?;
+        self.invalidate();
+        Ok(())
+    }
+
+    async fn remove(&self, id: &Uuid) -> Result<()> {
+        self.inner.remove(id).await?;
+        self.invalidate();
+        Ok(())
+    }
+
+    fn source_weights(&self) -> BTreeMap<String, f64> {
+        self.inner.source_weights()
+    }
+
+    fn set_source_weight(&self, prefix: &str, weight: f64) -> Result<()> {
+        self.inner.set_source_weight(prefix, weight)?;
+        self.invalidate();
+        Ok(())
+    }
+
+    fn remove_source_weight(&self, prefix: &str) -> bool {
+        let removed = self.inner.remove_source_weight(prefix);
+        self.invalidate();
+        removed
+    }
+
+    async fn clear(&self) -> Result<()> {
+        self.inner.clear().await?;
+        self.invalidate();
+        Ok(())
+    }
+
+    async fn maintenance(&self) -> Result<MaintenanceReport> {
+        let report = self.inner.maintenance().await?;
+        self.invalidate();
+        Ok(report)
+    }
+
+    async fn warm_up(&self, filter: &ContextFilter) -> Result<usize> {
+        let rehydrated = self.inner.warm_up(filter).await?;
+        self.invalidate();
+        Ok(rehydrated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{ContextEngineConfig, ContextEngineImpl};
+    use crate::reranking::CrossEncoderReranker;
+
+    fn prefetcher() -> ContextPrefetcher {
+        let engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        ContextPrefetcher::new(engine).with_reranker(Arc::new(CrossEncoderReranker::mock()))
+    }
+
+    #[tokio::test]
+    async fn test_prefetched_query_is_a_hit() {
+        let prefetcher = prefetcher();
+        prefetcher
+            .store("Deploy with helm upgrade".to_string(), MemoryMetadata::new("doc", "runbook"), 0.8)
+            .await
+            .unwrap();
+
+        assert_eq!(prefetcher.prefetch("de").await.unwrap(), PrefetchOutcome::TooShort);
+        assert_eq!(prefetcher.prefetch("how to dep").await.unwrap(), PrefetchOutcome::Warmed);
+        assert_eq!(prefetcher.prefetch("how to deploy").await.unwrap(), PrefetchOutcome::Warmed);
+        assert_eq!(prefetcher.prefetch(" how to  deploy").await.unwrap(), PrefetchOutcome::AlreadyWarm);
+
+        let result = prefetcher.retrieve("how to deploy ").await.unwrap();
+        assert_eq!(result.selected.len(), 1);
+        prefetcher.retrieve("something else entirely").await.unwrap();
+
+        let stats = prefetcher.prefetch_stats();
+        assert_eq!(stats.prefetches, 2);
+        assert_eq!(stats.prefetches_skipped, 2);
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.hit_rate, 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_writes_invalidate_prefetched_results() {
+        let prefetcher = prefetcher();
+        prefetcher.prefetch("rollback steps").await.unwrap();
+
+        prefetcher
+            .store("Rollback steps: helm rollback".to_string(), MemoryMetadata::new("doc", "runbook"), 0.8)
+            .await
+            .unwrap();
+        let result = prefetcher.retrieve("rollback steps").await.unwrap();
+        assert_eq!(result.selected.len(), 1);
+
+        let stats = prefetcher.prefetch_stats();
+        assert_eq!(stats.hits, 0);
+        assert_eq!(stats.misses, 1);
+    }
+
+    #[tokio::test]
+    async fn test_expired_results_are_not_served() {
+        let engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let prefetcher = ContextPrefetcher::with_config(
+            engine,
+            PrefetchConfig {
+                ttl: Duration::ZERO,
+                ..Default::default()
+            },
+        );
+        prefetcher.prefetch("status page").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        prefetcher.retrieve("status page").await.unwrap();
+        assert_eq!(prefetcher.prefetch_stats().misses, 1);
+    }
+
+    #[tokio::test]
+    async fn test_results_are_cached_per_caller_access() {
+        let prefetcher = prefetcher();
+        let admin = Principal::new("alice").with_roles(["admin"]);
+        let user = Principal::new("bob").with_roles(["user"]);
+
+        admin.clone().scope(prefetcher.prefetch("incident review")).await.unwrap();
+        let warmed = Principal::new("carol").with_roles(["Admin"]).scope(prefetcher.prefetch("incident review"));
+        assert_eq!(warmed.await.unwrap(), PrefetchOutcome::AlreadyWarm);
+
+        user.scope(prefetcher.retrieve("incident review")).await.unwrap();
+        prefetcher.retrieve("incident review").await.unwrap();
+        assert_eq!(prefetcher.prefetch_stats().misses, 2);
+        admin.scope(prefetcher.retrieve("incident review")).await.unwrap();
+        assert_eq!(prefetcher.prefetch_stats().hits, 1);
+    }
+
+    struct SlowReranker;
+
+    #[async_trait]
+    impl Reranker for SlowReranker {
+        async fn rerank(
+            &self,
+            query: &str,
+            documents: Vec<RerankDocument>,
+        ) -> Result<Vec<crate::reranking::RerankerResult>> {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            CrossEncoderReranker::mock().rerank(query, documents).await
+        }
+
+        fn name(&self) -> &str {
+            "slow"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rerank_gives_way_to_deadline() {
+        let engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let prefetcher = ContextPrefetcher::new(engine).with_reranker(Arc::new(SlowReranker));
+        prefetcher
+            .store("Scale the deployment".to_string(), MemoryMetadata::new("doc", "runbook"), 0.8)
+            .await
+            .unwrap();
+
+        let result = Deadline::after(Duration::from_millis(20))
+            .scope(prefetcher.retrieve("scale deployment"))
+            .await
+            .unwrap();
+        assert_eq!(result.selected.len(), 1);
+
+        // The unreranked result was not cached
+        assert!(prefetcher.cached("scale deployment").is_none());
+    }
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<StageRecord>>);
+
+    impl ContextStageObserver for Recorder {
+        fn record(&self, record: &StageRecord) {
+            self.0.lock().push(record.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stages_are_reported_to_the_observer() {
+        let prefetcher = prefetcher();
+        let recorder = Arc::new(Recorder::default());
+        prefetcher.set_stage_observer(recorder.clone());
+        prefetcher
+            .store("Restart the ingestion worker".to_string(), MemoryMetadata::new("doc", "runbook"), 0.8)
+            .await
+            .unwrap();
+
+        prefetcher.retrieve("restart ingestion worker").await.unwrap();
+        prefetcher.retrieve("restart ingestion worker").await.unwrap();
+
+        let records = recorder.0.lock().clone();
+        let stages: Vec<_> = records.iter().map(|record| (record.stage, record.cache_hit)).collect();
+        assert_eq!(
+            stages,
+            vec![
+                (ContextStage::Retrieval, Some(false)),
+                (ContextStage::Rerank, None),
+                (ContextStage::Retrieval, Some(true)),
+            ]
+        );
+        assert_eq!((records[0].candidates_in, records[0].candidates_out), (1, 1));
+    }
+}
@@ -0,0 +1,403 @@
+//! Query-aware compression of retrieved context
+//!
+//! In the spirit of LLMLingua, [`PromptCompressor`] drops the words of each
+//! retrieved passage that carry the least information until the passage is
+//! down to the configured ratio, keeping the survivors in their original
+//! order. Words are scored by a [`TokenScorer`]: [`HeuristicTokenScorer`]
+//! favours rare words, numbers, identifiers and words of the query, and
+//! penalizes stopwords; a small scoring model can be plugged in instead.
+//! Fenced code blocks are kept verbatim. The compressed text is set as the
+//! item's `compressed_content`, so the original is kept.
+//!
+//! Compression trades answer quality for tokens; run the eval harness
+//! against a configuration with and without it before changing the ratio.
+
+use crate::{retrieval::RetrievalResult, ContextError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Words dropped first, regardless of their frequency
+const STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "been", "but", "by", "can",
+    "could", "did", "do", "does", "for", "from", "had", "has", "have", "how", "if", "in", "into", "is", "it", "its",
+    "just", "may", "might", "more", "most", "of", "on", "or", "our", "over", "so", "some", "such", "than", "that",
+    "the", "their", "then", "there", "these", "they", "this", "those", "to", "very", "was", "we", "were", "what",
+    "when", "which", "while", "will", "with", "would", "you", "your",
+];
+
+/// Prompt compression settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptCompressionConfig {
+    /// Fraction of each passage's words to keep, in (0, 1]
+    pub ratio: f64,
+    /// Passages with fewer words are left alone
+    pub min_words: usize,
+}
+
+impl Default for PromptCompressionConfig {
+    fn default() -> Self {
+        Self {
+            ratio: 0.5,
+            min_words: 40,
+        }
+    }
+}
+
+impl PromptCompressionConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.ratio > 0.0 && self.ratio <= 1.0) {
+            return Err(ContextError::CompressionFailed(
+                "Prompt compression ratio must be in (0, 1]".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Scores how much information each word of the retrieved passages carries
+pub trait TokenScorer: Send + Sync {
+    /// One score per word of each passage; higher scores are kept first
+    fn score(&self, query: &str, passages: &[Vec<&str>]) -> Vec<Vec<f64>>;
+}
+
+/// Scores words by their rarity across the retrieved passages, boosting
+/// query terms, numbers and identifiers
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicTokenScorer;
+
+impl TokenScorer for HeuristicTokenScorer {
+    fn score(&self, query: &str, passages: &[Vec<&str>]) -> Vec<Vec<f64>> {
+        let query_terms: HashSet<String> = query.split_whitespace().map(normalize).collect();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for word in passages.iter().flatten() {
+            *counts.entry(normalize(word)).or_default() += 1;
+        }
+        let total = counts.values().sum::<usize>().max(1) as f64;
+
+        passages
+            .iter()
+            .map(|words| {
+                words
+                    .iter()
+                    .map(|word| {
+                        let term = normalize(word);
+                        if term.is_empty() {
+                            return 0.0;
+                        }
+                        if STOPWORDS.contains(&term.as_str()) {
+                            return 0.05;
+                        }
+                        // Self-information of the word within the retrieved text
+                        let mut score = (total / counts[&term] as f64).ln();
+                        if query_terms.contains(&term) {
+                            score += 2.0;
+                        }
+                        if word.chars().any(|c| c.is_ascii_digit()) || is_identifier(word) {
+                            score += 1.0;
+                        }
+                        score
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Words and tokens before and after compression
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PromptCompressionStats {
+    pub passages: u64,
+    pub words_in: u64,
+    pub words_out: u64,
+    pub tokens_in: u64,
+    pub tokens_out: u64,
+}
+
+impl PromptCompressionStats {
+    /// Fraction of tokens kept, 1.0 before anything was compressed
+    pub fn achieved_ratio(&self) -> f64 {
+        if self.tokens_in == 0 {
+            1.0
+        } else {
+            self.tokens_out as f64 / self.tokens_in as f64
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    passages: AtomicU64,
+    words_in: AtomicU64,
+    words_out: AtomicU64,
+    tokens_in: AtomicU64,
+    tokens_out: AtomicU64,
+}
+
+/// Drops low-information words from retrieved context
+pub struct PromptCompressor {
+    config: PromptCompressionConfig,
+    scorer: Arc<dyn TokenScorer>,
+    counters: Counters,
+}
+
+impl PromptCompressor {
+    pub fn new(config: PromptCompressionConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            scorer: Arc::new(HeuristicTokenScorer),
+            counters: Counters::default(),
+        })
+    }
+
+    /// Score words with `scorer`, e.g. a small language model
+    pub fn with_scorer(mut self, scorer: Arc<dyn TokenScorer>) -> Self {
+        self.scorer = scorer;
+        self
+    }
+
+    pub fn config(&self) -> &PromptCompressionConfig {
+        &self.config
+    }
+
+    /// Compress the selected items of `result` for `query`, updating their
+    /// token counts and the result's total
+    pub fn compress(&self, query: &str, result: &mut RetrievalResult) {
+        let passages: Vec<Vec<Segment>> = result
+            .selected
+            .iter()
+            .map(|scored| segments(scored.item.get_content()))
+            .collect();
+        let words: Vec<Vec<&str>> = passages
+            .iter()
+            .map(|segments| segments.iter().flat_map(Segment::words).collect())
+            .collect();
+        let scores = self.scorer.score(query, &words);
+
+        let compressed: Vec<Option<(String, usize, usize)>> = passages
+            .iter()
+            .zip(words.iter().zip(&scores))
+            .map(|(segments, (words, scores))| {
+                if words.len() < self.config.min_words || self.config.ratio >= 1.0 {
+                    return None;
+                }
+                let keep = keep_mask(scores, (words.len() as f64 * self.config.ratio).ceil() as usize);
+                let kept = keep.iter().filter(|k| **k).count();
+                Some((render(segments, &keep), words.len(), kept))
+            })
+            .collect();
+
+        for (scored, compressed) in result.selected.iter_mut().zip(compressed) {
+            let Some((text, words, kept)) = compressed else {
+                continue;
+            };
+            let tokens = scored.item.token_count;
+            let compressed_tokens = (tokens as f64 * kept as f64 / words as f64).ceil() as usize;
+
+            self.counters.passages.fetch_add(1, Ordering::Relaxed);
+            self.counters.words_in.fetch_add(words as u64, Ordering::Relaxed);
+            self.counters.words_out.fetch_add(kept as u64, Ordering::Relaxed);
+            self.counters.tokens_in.fetch_add(tokens as u64, Ordering::Relaxed);
+            self.counters.tokens_out.fetch_add(compressed_tokens as u64, Ordering::Relaxed);
+
+            scored.item.compressed_content = Some(text);
+            scored.item.token_count = compressed_tokens;
+        }
+        result.total_tokens = result.selected.iter().map(|scored| scored.item.token_count).sum();
+    }
+
+    pub fn stats(&self) -> PromptCompressionStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        PromptCompressionStats {
+            passages: load(&self.counters.passages),
+            words_in: load(&self.counters.words_in),
+            words_out: load(&self.counters.words_out),
+            tokens_in: load(&self.counters.tokens_in),
+            tokens_out: load(&self.counters.tokens_out),
+        }
+    }
+}
+
+/// A run of prose lines, or a fenced code block kept as is
+enum Segment<'a> {
+    Prose(Vec<Vec<&'a str>>),
+    Code(&'a str),
+}
+
+impl<'a> Segment<'a> {
+    fn words(&self) -> Box<dyn Iterator<Item = &'a str> + '_> {
+        match self {
+            Segment::Prose(lines) => Box::new(lines.iter().flatten().copied()),
+            Segment::Code(_) => Box::new(std::iter::empty()),
+        }
+    }
+}
+
+fn segments(content: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut prose = Vec::new();
+    let mut code_start: Option<usize> = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let fence = line.trim_start().starts_with("```");
+        match code_start {
+            Some(start) if fence => {
+                segments.push(Segment::Code(content[start..offset + line.len()].trim_end()));
+                code_start = None;
+            }
+            Some(_) => {}
+            None if fence => {
+                if !prose.is_empty() {
+                    segments.push(Segment::Prose(std::mem::take(&mut prose)));
+                }
+                code_start = Some(offset);
+            }
+            None => prose.push(line.split_whitespace().collect()),
+        }
+        offset += line.len();
+    }
+    if let Some(start) = code_start {
+        segments.push(Segment::Code(content[start..].trim_end()));
+    }
+    if !prose.is_empty() {
+        segments.push(Segment::Prose(prose));
+    }
+    segments
+}
+
+/// Keep the `keep` best-scored words; ties go to the earlier word
+fn keep_mask(scores: &[f64], keep: usize) -> Vec<bool> {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]).then(a.cmp(b)));
+    let mut mask = vec![false; scores.len()];
+    for index in order.into_iter().take(keep) {
+        mask[index] = true;
+    }
+    mask
+}
+
+fn render(segments: &[Segment<'_>], keep: &[bool]) -> String {
+    let mut keep = keep.iter();
+    let mut blocks = Vec::new();
+    for segment in segments {
+        match segment {
+            Segment::Code(code) => blocks.push(code.to_string()),
+            Segment::Prose(lines) => {
+                let lines: Vec<String> = lines
+                    .iter()
+                    .map(|words| {
+                        words
+                            .iter()
+                            .filter(|_| keep.next().copied().unwrap_or(true))
+                            .copied()
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    })
+                    .filter(|line| !line.is_empty())
+                    .collect();
+                if !lines.is_empty() {
+                    blocks.push(lines.join("\n"));
+                }
+            }
+        }
+    }
+    blocks.join("\n")
+}
+
+/// Lowercase with surrounding punctuation removed
+fn normalize(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+}
+
+/// Whether `word` looks like code: snake_case, a path, a dotted name or
+/// camelCase
+fn is_identifier(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+    word.contains(['_', '/', '.', ':'])
+        || word.chars().skip(1).any(char::is_uppercase) && word.chars().any(char::is_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{MemoryItem, MemoryMetadata};
+    use crate::retrieval::ScoredItem;
+
+    fn result(contents: &[&str]) -> RetrievalResult {
+        let selected: Vec<ScoredItem> = contents
+            .iter()
+            .map(|content| ScoredItem {
+                item: MemoryItem::new(
+                    content.to_string(),
+                    MemoryMetadata::new("doc", "runbook"),
+                    0.5,
+                    content.split_whitespace().count() * 4 / 3,
+                ),
+                score: 1.0,
+                stale: None,
+            })
+            .collect();
+        RetrievalResult {
+            total_tokens: selected.iter().map(|s| s.item.token_count).sum(),
+            selected,
+            rejected: Vec::new(),
+            candidate_ids: Vec::new(),
+            target_tokens: 1000,
+            max_tokens: 2000,
+        }
+    }
+
+    #[test]
+    fn test_keeps_query_terms_and_identifiers() {
+        let passage = "When the checkout service is slow it is usually because the payments_db pool \
+                       has been exhausted and all of the requests are waiting for a connection to \
+                       be returned to the pool by one of the other workers that are running in the \
+                       cluster at the time, so you should raise max_connections to 200 first";
+        let mut retrieved = result(&[passage, "too short to compress"]);
+        let before = retrieved.total_tokens;
+        let compressor = PromptCompressor::new(PromptCompressionConfig {
+            ratio: 0.4,
+            min_words: 10,
+        })
+        .unwrap();
+        compressor.compress("why is checkout slow", &mut retrieved);
+
+        let compressed = retrieved.selected[0].item.get_content();
+        for word in ["checkout", "slow", "payments_db", "max_connections", "200"] {
+            assert!(compressed.contains(word), "{} missing from {:?}", word, compressed);
+        }
+        assert!(!compressed.split_whitespace().any(|w| w == "the"));
+        assert_eq!(retrieved.selected[0].item.content, passage);
+        assert_eq!(retrieved.selected[1].item.compressed_content, None);
+        assert!(retrieved.total_tokens < before);
+
+        let stats = compressor.stats();
+        assert_eq!(stats.passages, 1);
+        assert!((stats.words_out as f64 / stats.words_in as f64 - 0.4).abs() < 0.05);
+        assert!(stats.achieved_ratio() < 0.5);
+    }
+
+    #[test]
+    fn test_code_blocks_are_kept_verbatim() {
+        let passage = "Restart the deployment with the following command and then wait for the rollout\n\
+                       ```\nkubectl rollout restart deploy/checkout -n prod\n```\n\
+                       after which the pods should all be ready within a couple of minutes or so";
+        let mut retrieved = result(&[passage]);
+        PromptCompressor::new(PromptCompressionConfig { ratio: 0.3, min_words: 5 })
+            .unwrap()
+            .compress("restart checkout", &mut retrieved);
+
+        let compressed = retrieved.selected[0].item.get_content();
+        assert!(compressed.contains("```\nkubectl rollout restart deploy/checkout -n prod\n```"));
+        assert!(compressed.len() < passage.len());
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(PromptCompressor::new(PromptCompressionConfig { ratio: 0.0, min_words: 1 }).is_err());
+        assert!(PromptCompressor::new(PromptCompressionConfig { ratio: 1.5, min_words: 1 }).is_err());
+        assert!(PromptCompressor::new(PromptCompressionConfig::default()).is_ok());
+    }
+}
@@ -0,0 +1,75 @@
+//! Source verification labels
+//!
+//! When signed ingestion is enabled, every stored chunk records whether its
+//! payload carried a valid signature from a trusted source under
+//! [`TRUST_KEY`], and the signer under [`SIGNER_KEY`]. Retrieval demotes
+//! [`Trust::Untrusted`] items by [`RetrievalConfig::untrusted_weight`] and
+//! citations label their sources, so content slipped into the knowledge base
+//! without a signature can't pass as authoritative. Items stored without a
+//! label, e.g. before signing was enabled, are scored as before.
+//!
+//! [`RetrievalConfig::untrusted_weight`]: crate::RetrievalConfig::untrusted_weight
+
+use crate::memory::MemoryItem;
+use serde::{Deserialize, Serialize};
+
+/// Custom metadata key holding an item's [`Trust`]
+pub const TRUST_KEY: &str = "trust";
+
+/// Custom metadata key naming the source that signed an item's payload
+pub const SIGNER_KEY: &str = "signed_by";
+
+/// Whether an item's payload was verified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trust {
+    /// Signed by a trusted source and unmodified since
+    Verified,
+    /// Unsigned, signed by an unknown source, or tampered with
+    Untrusted,
+}
+
+impl Trust {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Verified => "verified",
+            Self::Untrusted => "untrusted",
+        }
+    }
+
+    /// The label an item carries, if it was ingested with signing enabled
+    pub fn of(item: &MemoryItem) -> Option<Self> {
+        item.metadata
+            .custom
+            .get(TRUST_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+
+    /// Whether an item is labeled untrusted; unlabeled items are not
+    pub fn is_untrusted(item: &MemoryItem) -> bool {
+        Self::of(item) == Some(Self::Untrusted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryMetadata;
+
+    #[test]
+    fn test_reads_label_from_metadata() {
+        let item = |trust: Option<&str>| {
+            let mut metadata = MemoryMetadata::new("document", "wiki");
+            if let Some(trust) = trust {
+                metadata.add_custom(TRUST_KEY.to_string(), serde_json::json!(trust));
+            }
+            MemoryItem::new("content".to_string(), metadata, 0.5, 1)
+        };
+
+        assert_eq!(Trust::of(&item(Some("verified"))), Some(Trust::Verified));
+        assert!(Trust::is_untrusted(&item(Some("untrusted"))));
+        assert_eq!(Trust::of(&item(None)), None);
+        assert!(!Trust::is_untrusted(&item(None)));
+        assert_eq!(Trust::of(&item(Some("bogus"))), None);
+    }
+}
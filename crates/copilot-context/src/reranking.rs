@@ -8,17 +8,26 @@
 //! - Cross-encoder based reranking
 //! - Diversity-aware reranking (MMR)
 //! - Score normalization and calibration
+//! - Ensembles fused by score averaging, Borda count or reciprocal rank
 //! - Batch processing for efficiency
+//! - Latency budgets, leaving documents not scored in time unranked
 //! - Async support for external model APIs
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio::time::Instant;
+use tracing::debug;
 
+use crate::calibration::{CalibrationMethod, CalibrationParams, LabeledPair, LabeledScore, ScoreCalibration};
 use crate::ContextError;
 
+/// Metadata key set to `false` on results the latency budget left unranked
+pub const RERANKED_KEY: &str = "reranked";
+
 /// Result type for reranking operations
 pub type Result<T> = std::result::Result<T, ContextError>;
 
@@ -82,10 +91,14 @@ pub struct RerankerConfig {
     pub max_documents: usize,
     /// Batch size for processing
     pub batch_size: usize,
-    /// Whether to normalize scores to [0, 1]
+    /// Whether to normalize scores to [0, 1]; ignored when calibrated
     pub normalize_scores: bool,
-    /// Minimum score threshold for results
+    /// Minimum score threshold for results; a relevance probability when
+    /// calibrated
     pub score_threshold: Option<f32>,
+    /// Calibration mapping the model's raw scores to relevance probabilities
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calibration: Option<ScoreCalibration>,
     /// Whether to use diversity-aware reranking (MMR)
     pub use_mmr: bool,
     /// Lambda parameter for MMR (0 = diversity, 1 = relevance)
@@ -96,6 +109,10 @@ pub struct RerankerConfig {
     pub api_endpoint: Option<String>,
     /// Timeout in milliseconds
     pub timeout_ms: u64,
+    /// Time allowed for scoring, in milliseconds; documents not scored in
+    /// time are returned unranked after the reranked ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_budget_ms: Option<u64>,
 }
 
 impl Default for RerankerConfig {
@@ -105,11 +122,13 @@ impl Default for RerankerConfig {
             batch_size: 32,
             normalize_scores: true,
             score_threshold: None,
+            calibration: None,
             use_mmr: false,
             mmr_lambda: 0.7,
             model_name: "cross-encoder/ms-marco-MiniLM-L-6-v2".to_string(),
             api_endpoint: None,
             timeout_ms: 30000,
+            latency_budget_ms: None,
         }
     }
 }
@@ -136,6 +155,16 @@ impl RerankerConfig {
         self
     }
 
+    pub fn with_calibration(mut self, calibration: ScoreCalibration) -> Self {
+        self.calibration = Some(calibration);
+        self
+    }
+
+    pub fn with_latency_budget_ms(mut self, budget_ms: u64) -> Self {
+        self.latency_budget_ms = Some(budget_ms);
+        self
+    }
+
     pub fn with_model(mut self, model: impl Into<String>) -> Self {
         self.model_name = model.into();
         self
@@ -262,22 +291,37 @@ impl CrossEncoderReranker {
         }
 
         // Limit documents
-        let docs: Vec<_> = documents
+        let mut docs: Vec<_> = documents
             .into_iter()
             .take(self.config.max_documents)
+            .enumerate()
             .collect();
 
+        // With a latency budget, score the most promising documents first
+        let deadline = self
+            .config
+            .latency_budget_ms
+            .map(|ms| Instant::now() + Duration::from_millis(ms));
+        if deadline.is_some() {
+            docs.sort_by(|a, b| {
+                let score = |doc: &RerankDocument| doc.original_score.unwrap_or(f32::NEG_INFINITY);
+                score(&b.1).total_cmp(&score(&a.1))
+            });
+        }
+
         // Score documents in batches
         let mut scored_docs = Vec::with_capacity(docs.len());
+        let mut unscored_docs = Vec::new();
+        let mut budget_expired = false;
 
-        for (batch_idx, batch) in docs.chunks(self.config.batch_size).enumerate() {
+        for batch in docs.chunks(self.config.batch_size) {
             // Check cache first
             let mut uncached_indices = Vec::new();
             let mut cached_scores = vec![None; batch.len()];
 
             {
                 let cache = self.cache.read().await;
-                for (i, doc) in batch.iter().enumerate() {
+                for (i, (_, doc)) in batch.iter().enumerate() {
                     let key = Self::cache_key(query, &doc.id);
                     if let Some(&score) = cache.get(&key) {
                         cached_scores[i] = Some(score);
@@ -287,34 +331,48 @@ impl CrossEncoderReranker {
                 }
             }
 
-            // Score uncached documents
-            if !uncached_indices.is_empty() {
+            // Score uncached documents, unless the budget ran out
+            if !uncached_indices.is_empty() && !budget_expired {
                 let uncached_contents: Vec<&str> = uncached_indices
                     .iter()
-                    .map(|&i| batch[i].content.as_str())
+                    .map(|&i| batch[i].1.content.as_str())
                     .collect();
 
-                let scores = self.provider.score_pairs(query, &uncached_contents).await?;
+                let scoring = self.provider.score_pairs(query, &uncached_contents);
+                let scores = match deadline {
+                    Some(deadline) => tokio::time::timeout_at(deadline, scoring).await.ok().transpose()?,
+                    None => Some(scoring.await?),
+                };
 
-                // Update cache
-                let mut cache = self.cache.write().await;
-                for (score_idx, &doc_idx) in uncached_indices.iter().enumerate() {
-                    let key = Self::cache_key(query, &batch[doc_idx].id);
-                    cache.insert(key, scores[score_idx]);
-                    cached_scores[doc_idx] = Some(scores[score_idx]);
+                match scores {
+                    Some(scores) => {
+                        // Update cache
+                        let mut cache = self.cache.write().await;
+                        for (score_idx, &doc_idx) in uncached_indices.iter().enumerate() {
+                            let key = Self::cache_key(query, &batch[doc_idx].1.id);
+                            cache.insert(key, scores[score_idx]);
+                            cached_scores[doc_idx] = Some(scores[score_idx]);
+                        }
+                    }
+                    None => budget_expired = true,
                 }
             }
 
             // Collect scored documents
-            for (i, doc) in batch.iter().enumerate() {
-                let global_idx = batch_idx * self.config.batch_size + i;
-                scored_docs.push((
-                    global_idx,
-                    doc.clone(),
-                    cached_scores[i].unwrap_or(0.0),
-                ));
+            for (score, (index, doc)) in cached_scores.into_iter().zip(batch) {
+                match score {
+                    Some(score) => scored_docs.push((*index, doc.clone(), self.calibrated(score))),
+                    None => unscored_docs.push((*index, doc.clone())),
+                }
             }
         }
+        if budget_expired {
+            debug!(
+                reranked = scored_docs.len(),
+                unranked = unscored_docs.len(),
+                "Reranker latency budget expired"
+            );
+        }
 
         // Apply MMR if enabled
         let results = if self.config.use_mmr {
@@ -323,8 +381,9 @@ impl CrossEncoderReranker {
             self.standard_rerank(scored_docs)
         };
 
-        // Normalize scores if configured
-        let mut results = if self.config.normalize_scores {
+        // Normalize scores if configured; calibrated scores are already
+        // comparable across queries
+        let mut results = if self.config.normalize_scores && self.config.calibration.is_none() {
             self.normalize_results(results)
         } else {
             results
@@ -335,9 +394,31 @@ impl CrossEncoderReranker {
             results.retain(|r| r.score >= threshold);
         }
 
+        // Documents the budget left unscored follow, in priority order
+        let reranked = results.len();
+        results.extend(unscored_docs.into_iter().enumerate().map(|(i, (original_rank, mut doc))| {
+            doc.metadata.insert(RERANKED_KEY.to_string(), serde_json::Value::Bool(false));
+            RerankerResult {
+                id: doc.id,
+                content: doc.content,
+                score: 0.0,
+                original_rank,
+                new_rank: reranked + i,
+                original_score: doc.original_score,
+                metadata: doc.metadata,
+            }
+        }));
+
         Ok(results)
     }
 
+    fn calibrated(&self, score: f32) -> f32 {
+        match &self.config.calibration {
+            Some(calibration) => calibration.apply(score),
+            None => score,
+        }
+    }
+
     /// Standard reranking by score
     fn standard_rerank(
         &self,
@@ -460,6 +541,26 @@ impl CrossEncoderReranker {
         results
     }
 
+    /// Fit a calibration for this reranker's model on labeled
+    /// query-document pairs, scoring them with the provider
+    pub async fn calibrate(&self, examples: &[LabeledPair], method: CalibrationMethod) -> Result<CalibrationParams> {
+        let mut by_query: HashMap<&str, Vec<&LabeledPair>> = HashMap::new();
+        for example in examples {
+            by_query.entry(example.query.as_str()).or_default().push(example);
+        }
+
+        let mut samples = Vec::with_capacity(examples.len());
+        for (query, pairs) in by_query {
+            for batch in pairs.chunks(self.config.batch_size.max(1)) {
+                let documents: Vec<&str> = batch.iter().map(|pair| pair.document.as_str()).collect();
+                let scores = self.provider.score_pairs(query, &documents).await?;
+                samples.extend(batch.iter().zip(scores).map(|(pair, score)| LabeledScore::new(score, pair.relevant)));
+            }
+        }
+
+        CalibrationParams::fit(self.provider.model_name(), method, &samples)
+    }
+
     /// Clear the score cache
     pub async fn clear_cache(&self) {
         self.cache.write().await.clear();
@@ -558,85 +659,228 @@ impl Reranker for CohereReranker {
     }
 }
 
+/// How an ensemble combines its rerankers' results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FusionStrategy {
+    /// Weighted average of the scores each reranker gave a document
+    WeightedAverage,
+    /// Weighted sum of Borda points: of n documents, the first ranked gets
+    /// n - 1, the last 0, and one a reranker dropped none
+    BordaCount,
+    /// Weighted sum of `1 / (rrf_k + rank + 1)`
+    ReciprocalRankFusion,
+}
+
+/// Rescaling of each reranker's scores before they are averaged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreNormalization {
+    None,
+    /// Scale to [0, 1]
+    MinMax,
+    /// Subtract the mean and divide by the standard deviation
+    ZScore,
+}
+
+/// Order of documents whose fused scores are equal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TieBreak {
+    /// Earlier in the input first
+    OriginalRank,
+    /// Higher retrieval score first, then earlier in the input
+    OriginalScore,
+    /// Lower document ID first
+    DocumentId,
+}
+
+/// Configuration for the ensemble reranker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnsembleConfig {
+    pub strategy: FusionStrategy,
+    /// Applied to each reranker's scores for weighted averaging; rank-based
+    /// strategies do not use scores
+    pub normalization: ScoreNormalization,
+    /// RRF constant k
+    pub rrf_k: usize,
+    pub tie_break: TieBreak,
+}
+
+impl Default for EnsembleConfig {
+    fn default() -> Self {
+        Self {
+            strategy: FusionStrategy::WeightedAverage,
+            normalization: ScoreNormalization::None,
+            rrf_k: 60,
+            tie_break: TieBreak::OriginalRank,
+        }
+    }
+}
+
+impl EnsembleConfig {
+    pub fn with_strategy(mut self, strategy: FusionStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_normalization(mut self, normalization: ScoreNormalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    pub fn with_rrf_k(mut self, k: usize) -> Self {
+        self.rrf_k = k;
+        self
+    }
+
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+}
+
 /// Ensemble reranker that combines multiple rerankers
 pub struct EnsembleReranker {
     rerankers: Vec<(Arc<dyn Reranker>, f32)>, // (reranker, weight)
+    config: EnsembleConfig,
 }
 
 impl EnsembleReranker {
     pub fn new() -> Self {
         Self {
             rerankers: Vec::new(),
+            config: EnsembleConfig::default(),
         }
     }
 
+    pub fn with_config(mut self, config: EnsembleConfig) -> Self {
+        self.config = config;
+        self
+    }
+
     pub fn add_reranker(mut self, reranker: Arc<dyn Reranker>, weight: f32) -> Self {
         self.rerankers.push((reranker, weight));
         self
     }
 
-    /// Combine scores from multiple rerankers using weighted average
+    pub fn config(&self) -> &EnsembleConfig {
+        &self.config
+    }
+
+    /// Scores of one reranker's results, rescaled per the configuration
+    fn normalized_scores(&self, results: &[RerankerResult]) -> Vec<f32> {
+        let scores: Vec<f32> = results.iter().map(|r| r.score).collect();
+        if scores.is_empty() {
+            return scores;
+        }
+        match self.config.normalization {
+            ScoreNormalization::None => scores,
+            ScoreNormalization::MinMax => {
+                let min = scores.iter().copied().fold(f32::INFINITY, f32::min);
+                let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                let range = max - min;
+                scores
+                    .iter()
+                    .map(|s| if range > f32::EPSILON { (s - min) / range } else { 1.0 })
+                    .collect()
+            }
+            ScoreNormalization::ZScore => {
+                let n = scores.len() as f32;
+                let mean = scores.iter().sum::<f32>() / n;
+                let std_dev = (scores.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / n).sqrt();
+                scores
+                    .iter()
+                    .map(|s| if std_dev > f32::EPSILON { (s - mean) / std_dev } else { 0.0 })
+                    .collect()
+            }
+        }
+    }
+
+    /// Fuse the results of each reranker, given with its weight, over the
+    /// input `documents`
     fn combine_scores(
         &self,
-        results_per_reranker: Vec<Vec<RerankerResult>>,
+        documents: &[RerankDocument],
+        results_per_reranker: Vec<(f32, Vec<RerankerResult>)>,
     ) -> Vec<RerankerResult> {
         if results_per_reranker.is_empty() {
             return Vec::new();
         }
 
-        // Collect all document IDs
-        let mut doc_scores: HashMap<String, (RerankDocument, f32, f32)> = HashMap::new();
-
-        for (reranker_idx, results) in results_per_reranker.into_iter().enumerate() {
-            let weight = self.rerankers.get(reranker_idx).map(|(_, w)| *w).unwrap_or(1.0);
-
-            for result in results {
-                let entry = doc_scores
-                    .entry(result.id.clone())
-                    .or_insert((
-                        RerankDocument {
-                            id: result.id.clone(),
-                            content: result.content.clone(),
-                            original_score: result.original_score,
-                            metadata: result.metadata.clone(),
-                        },
-                        0.0,
-                        0.0,
-                    ));
-                entry.1 += result.score * weight;
-                entry.2 += weight;
+        let positions: HashMap<&str, usize> = documents
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, doc)| (doc.id.as_str(), i))
+            .collect();
+
+        // (score sum, weight sum) per input position
+        let mut fused: HashMap<usize, (f32, f32)> = HashMap::new();
+        for (weight, results) in &results_per_reranker {
+            let scores = self.normalized_scores(results);
+            for (rank, (result, score)) in results.iter().zip(scores).enumerate() {
+                let Some(&position) = positions.get(result.id.as_str()) else {
+                    continue;
+                };
+                let contribution = match self.config.strategy {
+                    FusionStrategy::WeightedAverage => score,
+                    FusionStrategy::BordaCount => documents.len().saturating_sub(rank + 1) as f32,
+                    FusionStrategy::ReciprocalRankFusion => 1.0 / (self.config.rrf_k + rank + 1) as f32,
+                };
+                let entry = fused.entry(position).or_insert((0.0, 0.0));
+                entry.0 += contribution * weight;
+                entry.1 += weight;
             }
         }
 
-        // Compute weighted average and sort
-        let mut combined: Vec<_> = doc_scores
+        let mut combined: Vec<(usize, f32)> = fused
             .into_iter()
-            .map(|(id, (doc, score_sum, weight_sum))| {
-                let avg_score = if weight_sum > 0.0 {
-                    score_sum / weight_sum
-                } else {
-                    0.0
+            .map(|(position, (score_sum, weight_sum))| {
+                let score = match self.config.strategy {
+                    FusionStrategy::WeightedAverage if weight_sum > 0.0 => score_sum / weight_sum,
+                    FusionStrategy::WeightedAverage => 0.0,
+                    _ => score_sum,
                 };
-                (id, doc, avg_score)
+                (position, score)
             })
             .collect();
 
-        combined.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+        combined.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| self.tie_break(&documents[a.0], &documents[b.0]))
+                .then(a.0.cmp(&b.0))
+        });
 
         combined
             .into_iter()
             .enumerate()
-            .map(|(new_rank, (id, doc, score))| RerankerResult {
-                id,
-                content: doc.content,
-                score,
-                original_rank: 0, // Not meaningful for ensemble
-                new_rank,
-                original_score: doc.original_score,
-                metadata: doc.metadata,
+            .map(|(new_rank, (position, score))| {
+                let doc = &documents[position];
+                RerankerResult {
+                    id: doc.id.clone(),
+                    content: doc.content.clone(),
+                    score,
+                    original_rank: position,
+                    new_rank,
+                    original_score: doc.original_score,
+                    metadata: doc.metadata.clone(),
+                }
             })
             .collect()
     }
+
+    fn tie_break(&self, a: &RerankDocument, b: &RerankDocument) -> std::cmp::Ordering {
+        match self.config.tie_break {
+            TieBreak::OriginalRank => std::cmp::Ordering::Equal,
+            TieBreak::OriginalScore => {
+                let score = |doc: &RerankDocument| doc.original_score.unwrap_or(f32::NEG_INFINITY);
+                score(b).total_cmp(&score(a))
+            }
+            TieBreak::DocumentId => a.id.cmp(&b.id),
+        }
+    }
 }
 
 impl Default for EnsembleReranker {
@@ -658,12 +902,13 @@ impl Reranker for EnsembleReranker {
 
         // Run all rerankers in parallel
         let mut handles = Vec::new();
-        for (reranker, _) in &self.rerankers {
+        for (reranker, weight) in &self.rerankers {
             let reranker = reranker.clone();
             let query = query.to_string();
             let docs = documents.clone();
+            let weight = *weight;
             handles.push(tokio::spawn(async move {
-                reranker.rerank(&query, docs).await
+                reranker.rerank(&query, docs).await.map(|results| (weight, results))
             }));
         }
 
@@ -680,7 +925,7 @@ impl Reranker for EnsembleReranker {
             }
         }
 
-        Ok(self.combine_scores(results_per_reranker))
+        Ok(self.combine_scores(&documents, results_per_reranker))
     }
 
     fn name(&self) -> &str {
@@ -744,6 +989,72 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_calibrated_threshold() {
+        let reranker = CrossEncoderReranker::mock();
+        let examples = vec![
+            LabeledPair::new("rust borrow checker", "the rust borrow checker rejects this", true),
+            LabeledPair::new("rust borrow checker", "rust checker for borrow rules", true),
+            LabeledPair::new("rust borrow checker", "the rust book", false),
+            LabeledPair::new("rust borrow checker", "gardening tips", false),
+            LabeledPair::new("tokio runtime", "configuring the tokio runtime", true),
+            LabeledPair::new("tokio runtime", "tokio tutorial", false),
+            LabeledPair::new("tokio runtime", "baking bread", false),
+        ];
+        let params = reranker.calibrate(&examples, CalibrationMethod::Isotonic).await.unwrap();
+        assert_eq!(params.model_name, "mock-cross-encoder");
+        assert_eq!(params.samples, 7);
+
+        let config = RerankerConfig::default().with_calibration(params.calibration).with_score_threshold(0.5);
+        let reranker = CrossEncoderReranker::new(config, Arc::new(MockRerankerProvider::new()));
+        let docs = vec![
+            RerankDocument::new("doc1", "rust borrow checker errors explained"),
+            RerankDocument::new("doc2", "rust release notes"),
+        ];
+
+        // Min-max normalization would keep doc2 at 0.0 and lift doc1 to 1.0
+        // whatever their raw scores; calibrated, doc2 falls below 0.5
+        let results = reranker.rerank("rust borrow checker", docs).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "doc1");
+        assert_eq!(results[0].score, 1.0);
+    }
+
+    struct SlowProvider(Duration);
+
+    #[async_trait]
+    impl RerankerProvider for SlowProvider {
+        async fn score_pairs(&self, query: &str, documents: &[&str]) -> Result<Vec<f32>> {
+            tokio::time::sleep(self.0).await;
+            MockRerankerProvider::new().score_pairs(query, documents).await
+        }
+
+        fn model_name(&self) -> &str {
+            "slow"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_latency_budget_leaves_tail_unranked() {
+        let config = RerankerConfig::default().with_batch_size(1).with_latency_budget_ms(100);
+        let reranker = CrossEncoderReranker::new(config, Arc::new(SlowProvider(Duration::from_millis(40))));
+
+        let docs: Vec<_> = (0..6)
+            .map(|i| RerankDocument::new(format!("doc{}", i), "rust programming").with_score(i as f32))
+            .collect();
+        let results = reranker.rerank("rust programming", docs).await.unwrap();
+
+        assert_eq!(results.len(), 6);
+        let unranked: Vec<_> = results.iter().filter(|r| r.metadata.contains_key(RERANKED_KEY)).collect();
+        assert!(!unranked.is_empty() && unranked.len() < 6);
+        // The highest retrieval scores were reranked; the rest follow in priority order
+        assert_eq!(results[0].id, "doc5");
+        let tail: Vec<_> = unranked.iter().map(|r| r.original_rank).collect();
+        assert!(tail.windows(2).all(|pair| pair[0] > pair[1]));
+        assert_eq!(results.last().unwrap().id, "doc0");
+        assert!(results.iter().enumerate().all(|(rank, r)| r.new_rank == rank));
+    }
+
     #[tokio::test]
     async fn test_reranker_with_mmr() {
         let config = RerankerConfig::default().with_mmr(0.5);
@@ -848,6 +1159,99 @@ mod tests {
         assert_eq!(results.len(), 2);
     }
 
+    /// Returns the documents in a fixed order with fixed scores
+    struct FixedReranker(Vec<(&'static str, f32)>);
+
+    #[async_trait]
+    impl Reranker for FixedReranker {
+        async fn rerank(&self, _query: &str, documents: Vec<RerankDocument>) -> Result<Vec<RerankerResult>> {
+            Ok(self
+                .0
+                .iter()
+                .enumerate()
+                .map(|(new_rank, (id, score))| {
+                    let original_rank = documents.iter().position(|d| d.id == *id).unwrap();
+                    RerankerResult {
+                        id: id.to_string(),
+                        content: documents[original_rank].content.clone(),
+                        score: *score,
+                        original_rank,
+                        new_rank,
+                        original_score: None,
+                        metadata: HashMap::new(),
+                    }
+                })
+                .collect())
+        }
+
+        fn name(&self) -> &str {
+            "fixed"
+        }
+    }
+
+    fn fusion_ensemble(config: EnsembleConfig, second: Vec<(&'static str, f32)>) -> EnsembleReranker {
+        // One reranker scores on a wide scale, the other on a narrow one
+        EnsembleReranker::new()
+            .with_config(config)
+            .add_reranker(Arc::new(FixedReranker(vec![("a", 10.0), ("b", 9.0), ("c", 0.0)])), 1.0)
+            .add_reranker(Arc::new(FixedReranker(second)), 1.0)
+    }
+
+    fn fusion_docs() -> Vec<RerankDocument> {
+        vec![RerankDocument::new("a", "a"), RerankDocument::new("b", "b"), RerankDocument::new("c", "c")]
+    }
+
+    fn ids(results: &[RerankerResult]) -> Vec<&str> {
+        results.iter().map(|r| r.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_ensemble_fusion_strategies() {
+        let second = || vec![("b", 0.9), ("c", 0.8), ("a", 0.1)];
+
+        // Raw averaging lets the wide-scale reranker decide alone
+        let results = fusion_ensemble(EnsembleConfig::default(), second()).rerank("q", fusion_docs()).await.unwrap();
+        assert_eq!(ids(&results), vec!["a", "b", "c"]);
+
+        let config = EnsembleConfig::default().with_normalization(ScoreNormalization::MinMax);
+        let results = fusion_ensemble(config, second()).rerank("q", fusion_docs()).await.unwrap();
+        assert_eq!(ids(&results), vec!["b", "a", "c"]);
+
+        let config = EnsembleConfig::default().with_strategy(FusionStrategy::BordaCount);
+        let results = fusion_ensemble(config, second()).rerank("q", fusion_docs()).await.unwrap();
+        assert_eq!(ids(&results), vec!["b", "a", "c"]);
+        assert_eq!(results[0].score, 3.0);
+        assert_eq!(results[0].original_rank, 1);
+
+        let config = EnsembleConfig::default().with_strategy(FusionStrategy::ReciprocalRankFusion).with_rrf_k(0);
+        let results = fusion_ensemble(config, second()).rerank("q", fusion_docs()).await.unwrap();
+        assert_eq!(ids(&results), vec!["b", "a", "c"]);
+        assert!((results[0].score - 1.5).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_ensemble_tie_break() {
+        // Opposite rankings give every document the same Borda points
+        let reversed = || vec![("c", 0.9), ("b", 0.8), ("a", 0.1)];
+        let docs = vec![
+            RerankDocument::new("c", "c").with_score(0.2),
+            RerankDocument::new("b", "b").with_score(0.1),
+            RerankDocument::new("a", "a").with_score(0.9),
+        ];
+        let config = EnsembleConfig::default().with_strategy(FusionStrategy::BordaCount);
+
+        let results = fusion_ensemble(config.clone(), reversed()).rerank("q", docs.clone()).await.unwrap();
+        assert_eq!(ids(&results), vec!["c", "b", "a"]);
+
+        let config = config.with_tie_break(TieBreak::OriginalScore);
+        let results = fusion_ensemble(config.clone(), reversed()).rerank("q", docs.clone()).await.unwrap();
+        assert_eq!(ids(&results), vec!["a", "c", "b"]);
+
+        let config = config.with_tie_break(TieBreak::DocumentId);
+        let results = fusion_ensemble(config, reversed()).rerank("q", docs).await.unwrap();
+        assert_eq!(ids(&results), vec!["a", "b", "c"]);
+    }
+
     #[tokio::test]
     async fn test_reranker_config_builder() {
         let config = RerankerConfig::default()
@@ -0,0 +1,222 @@
+//! Residency fence for context
+//!
+//! [`ResidencyFence`] wraps a context engine for a multi-region deployment.
+//! Items stored on behalf of a tenant are tagged with the tenant's home
+//! region, and items from other regions are taken out of what a `strict`
+//! tenant retrieves or lists. Each item taken out is recorded in the
+//! [`ResidencyLog`] as a violation.
+//!
+//! The tenant is the task's [`DataResidency`]; calls made outside a
+//! residency scope (maintenance, tests) pass through unfenced.
+
+use async_trait::async_trait;
+use copilot_core::{DataResidency, ResidencyLog, ResidencyOperation, REGION_KEY};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    engine::{CompressionStats, ContextEngine, EngineStats, MaintenanceReport},
+    filter::ContextFilter,
+    memory::{MemoryItem, MemoryMetadata, MemoryTier},
+    retrieval::{RetrievalResult, ScoredItem},
+    Result,
+};
+
+/// Region an item was written in, if tagged
+pub fn item_region(item: &MemoryItem) -> Option<&str> {
+    item.metadata.custom.get(REGION_KEY).and_then(|v| v.as_str())
+}
+
+/// Context engine that keeps tenants' context in their region
+pub struct ResidencyFence {
+    inner: Arc<dyn ContextEngine>,
+    log: Arc<ResidencyLog>,
+}
+
+impl ResidencyFence {
+    pub fn new(inner: Arc<dyn ContextEngine>, log: Arc<ResidencyLog>) -> Self {
+        Self { inner, log }
+    }
+
+    pub fn log(&self) -> &Arc<ResidencyLog> {
+        &self.log
+    }
+
+    /// Whether `item` may be used by `residency`'s tenant, recording a
+    /// violation of `operation` if not
+    fn admit(&self, residency: &DataResidency, item: &MemoryItem, operation: ResidencyOperation) -> bool {
+        let region = item_region(item);
+        if residency.permits(region) {
+            return true;
+        }
+        self.log
+            .record(residency.violation(operation, item.metadata.source.clone(), region));
+        false
+    }
+
+    fn fence(&self, mut result: RetrievalResult) -> RetrievalResult {
+        let Some(residency) = DataResidency::current() else {
+            return result;
+        };
+        let before = result.selected.len() + result.rejected.len();
+        result
+            .selected
+            .retain(|scored| self.admit(&residency, &scored.item, ResidencyOperation::Retrieve));
+        // Rejected items never reached the tenant, so they are dropped
+        // without counting as violations
+        result.rejected.retain(|scored| residency.permits(item_region(&scored.item)));
+        if result.selected.len() + result.rejected.len() < before {
+            let kept: HashSet<Uuid> = result
+                .selected
+                .iter()
+                .chain(&result.rejected)
+                .map(|scored: &ScoredItem| scored.item.metadata.id)
+                .collect();
+            result.candidate_ids.retain(|id| kept.contains(id));
+            result.total_tokens = result.selected.iter().map(|s| s.item.token_count).sum();
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl ContextEngine for ResidencyFence {
+    async fn store(&self, content: String, mut metadata: MemoryMetadata, importance: f64) -> Result<Uuid> {
+        if let Some(residency) = DataResidency::current() {
+            metadata
+                .custom
+                .entry(REGION_KEY.to_string())
+                .or_insert_with(|| residency.region.into());
+        }
+        self.inner.store(content, metadata, importance).await
+    }
+
+    async fn retrieve(&self, query: &str) -> Result<RetrievalResult> {
+        Ok(self.fence(self.inner.retrieve(query).await?))
+    }
+
+    async fn retrieve_filtered(&self, query: &str, filter: &ContextFilter) -> Result<RetrievalResult> {
+        Ok(self.fence(self.inner.retrieve_filtered(query, filter).await?))
+    }
+
+    async fn compress(&self) -> Result<CompressionStats> {
+        self.inner.compress().await
+    }
+
+    async fn stats(&self) -> Result<EngineStats> {
+        self.inner.stats().await
+    }
+
+    async fn promote(&self, id: &Uuid, tier: MemoryTier) -> Result<()> {
+        self.inner.promote(id, tier).await
+    }
+
+    async fn demote(&self, id: &Uuid, tier: MemoryTier) -> Result<()> {
+        self.inner.demote(id, tier).await
+    }
+
+    async fn remove(&self, id: &Uuid) -> Result<()> {
+        self.inner.remove(id).await
+    }
+
+    async fn list_matching(&self, filter: &ContextFilter) -> Result<Vec<MemoryItem>> {
+        let mut items = self.inner.list_matching(filter).await?;
+        if let Some(residency) = DataResidency::current() {
+            items.retain(|item| self.admit(&residency, item, ResidencyOperation::Read));
+        }
+        Ok(items)
+    }
+
+    async fn retag(&self, id: &Uuid, add: &[String], remove: &[String]) -> Result<()> {
+        self.inner.retag(id, add, remove).await
+    }
+
+    async fn reindex(&self, id: &Uuid) -> Result<()> {
+        self.inner.reindex(id).await
+    }
+
+    fn source_weights(&self) -> BTreeMap<String, f64> {
+        self.inner.source_weights()
+    }
+
+    fn set_source_weight(&self, prefix: &str, weight: f64) -> Result<()> {
+        self.inner.set_source_weight(prefix, weight)
+    }
+
+    fn remove_source_weight(&self, prefix: &str) -> bool {
+        self.inner.remove_source_weight(prefix)
+    }
+
+    async fn clear(&self) -> Result<()> {
+        self.inner.clear().await
+    }
+
+    async fn maintenance(&self) -> Result<MaintenanceReport> {
+        self.inner.maintenance().await
+    }
+
+    async fn warm_up(&self, filter: &ContextFilter) -> Result<usize> {
+        self.inner.warm_up(filter).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{ContextEngineConfig, ContextEngineImpl};
+    use copilot_core::ResidencyPolicy;
+
+    #[tokio::test]
+    async fn test_strict_tenants_only_retrieve_their_region() {
+        let engine: Arc<dyn ContextEngine> = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let fence = ResidencyFence::new(engine, Arc::new(ResidencyLog::new()));
+        let policy = ResidencyPolicy::new("us")
+            .with_entries(&["acme=eu:strict", "globex=eu"])
+            .unwrap();
+
+        policy
+            .for_tenant("globex")
+            .scope(fence.store(
+                "certificate rotation runbook for the eu cluster".to_string(),
+                MemoryMetadata::new("doc", "eu/runbook.md"),
+                0.5,
+            ))
+            .await
+            .unwrap();
+        // Written before regions were configured
+        fence
+            .store(
+                "certificate rotation runbook for the us cluster".to_string(),
+                MemoryMetadata::new("doc", "us/runbook.md"),
+                0.5,
+            )
+            .await
+            .unwrap();
+
+        let acme = policy.for_tenant("acme");
+        let result = acme
+            .clone()
+            .scope(fence.retrieve("certificate rotation runbook"))
+            .await
+            .unwrap();
+        let sources: Vec<_> = result.selected.iter().map(|s| s.item.metadata.source.as_str()).collect();
+        assert_eq!(sources, vec!["eu/runbook.md"]);
+        assert_eq!(result.total_tokens, result.selected[0].item.token_count);
+        assert_eq!(item_region(&result.selected[0].item), Some("eu"));
+
+        let report = fence.log().report(&acme, 10);
+        assert_eq!(report.violations, 1);
+        assert_eq!(report.recent[0].resource, "us/runbook.md");
+        assert_eq!(report.recent[0].data_region, "us");
+
+        // Unrestricted tenants and unscoped calls see everything
+        let globex = policy.for_tenant("globex").scope(fence.retrieve("certificate rotation runbook"));
+        assert_eq!(globex.await.unwrap().selected.len(), 2);
+        assert_eq!(fence.retrieve("certificate rotation runbook").await.unwrap().selected.len(), 2);
+
+        let listed = acme.scope(fence.list_matching(&ContextFilter::default())).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(fence.log().total(), 2);
+    }
+}
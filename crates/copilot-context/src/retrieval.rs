@@ -3,10 +3,19 @@
 //! Provides intelligent retrieval of context items based on relevance,
 //! importance, and recency with token budget management.
 
+use crate::authority::SourceAuthority;
+use crate::chunk_merge::{ChunkMergeConfig, ChunkMerger};
+use crate::freshness::{FreshnessConfig, FreshnessEvaluator, LatestVersions, Staleness};
+use crate::hybrid_search::HybridSearchConfig;
+use crate::reranking::RerankerConfig;
+use crate::provenance::Trust;
 use crate::{ContextError, MemoryItem, Result};
+use copilot_core::{Clock, SystemClock};
 use serde::{Deserialize, Serialize};
 use std::collections::BinaryHeap;
 use std::cmp::Ordering;
+use std::sync::Arc;
+use uuid::Uuid;
 
 /// Configuration for context retrieval
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +40,66 @@ pub struct RetrievalConfig {
 
     /// Include compressed content
     pub allow_compressed: bool,
+
+    /// When selected items are flagged as possibly outdated
+    #[serde(default)]
+    pub freshness: FreshnessConfig,
+
+    /// Score multiplier for items whose payload failed signature
+    /// verification (0.0 - 1.0)
+    #[serde(default = "default_untrusted_weight")]
+    pub untrusted_weight: f64,
+
+    /// Result count, fusion weights and rerank threshold of hybrid search
+    #[serde(default)]
+    pub search: SearchParams,
+
+    /// How adjacent chunks of a document are merged before budgeting
+    #[serde(default)]
+    pub chunk_merge: ChunkMergeConfig,
+}
+
+fn default_untrusted_weight() -> f64 {
+    0.5
+}
+
+/// Hybrid search parameters, as searched over by the auto-tuner
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SearchParams {
+    /// Results returned per query
+    pub top_k: usize,
+    /// Weight of vector results in fusion (0.0 - 1.0); keyword results get
+    /// the rest
+    pub vector_weight: f32,
+    /// Reranker score below which results are dropped
+    pub rerank_threshold: Option<f32>,
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        Self {
+            top_k: 10,
+            vector_weight: 0.7,
+            rerank_threshold: None,
+        }
+    }
+}
+
+impl SearchParams {
+    pub fn keyword_weight(&self) -> f32 {
+        1.0 - self.vector_weight
+    }
+
+    /// Set the fusion weights of `config`
+    pub fn apply_to_hybrid(&self, config: &mut HybridSearchConfig) {
+        config.vector_weight = self.vector_weight;
+        config.keyword_weight = self.keyword_weight();
+    }
+
+    /// Set the score threshold of `config`
+    pub fn apply_to_reranker(&self, config: &mut RerankerConfig) {
+        config.score_threshold = self.rerank_threshold;
+    }
 }
 
 impl Default for RetrievalConfig {
@@ -43,6 +112,10 @@ impl Default for RetrievalConfig {
             recency_weight: 0.2,
             min_relevance: 0.3,
             allow_compressed: true,
+            freshness: FreshnessConfig::default(),
+            untrusted_weight: default_untrusted_weight(),
+            search: SearchParams::default(),
+            chunk_merge: ChunkMergeConfig::default(),
         }
     }
 }
@@ -63,6 +136,24 @@ impl RetrievalConfig {
             ));
         }
 
+        if !(0.0..=1.0).contains(&self.untrusted_weight) {
+            return Err(ContextError::RetrievalFailed(
+                "Untrusted weight must be in [0, 1]".to_string(),
+            ));
+        }
+
+        if self.search.top_k == 0 {
+            return Err(ContextError::RetrievalFailed(
+                "Search top_k must be at least 1".to_string(),
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.search.vector_weight) {
+            return Err(ContextError::RetrievalFailed(
+                "Search vector weight must be in [0, 1]".to_string(),
+            ));
+        }
+
         Ok(())
     }
 
@@ -74,11 +165,29 @@ impl RetrievalConfig {
 /// Relevance scorer for context items
 pub struct RelevanceScorer {
     config: RetrievalConfig,
+    clock: Arc<dyn Clock>,
+    authority: Arc<SourceAuthority>,
 }
 
 impl RelevanceScorer {
     pub fn new(config: RetrievalConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            clock: Arc::new(SystemClock),
+            authority: Arc::new(SourceAuthority::new()),
+        }
+    }
+
+    /// Measure recency and importance decay against `clock`
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Boost or demote items by the authority of their source
+    pub fn with_authority(mut self, authority: Arc<SourceAuthority>) -> Self {
+        self.authority = authority;
+        self
     }
 
     /// Calculate relevance score between query and content
@@ -117,22 +226,25 @@ impl RelevanceScorer {
 
     /// Calculate recency score (0.0 - 1.0)
     pub fn calculate_recency(&self, item: &MemoryItem) -> f64 {
-        let age_seconds = (chrono::Utc::now() - item.last_accessed).num_seconds() as f64;
+        let age_seconds = (self.clock.now() - item.last_accessed).num_seconds() as f64;
         let half_life_hours = 24.0; // Score halves every 24 hours
 
         let decay = (-age_seconds / (half_life_hours * 3600.0) * 0.693).exp();
         decay
     }
 
-    /// Calculate composite score for retrieval prioritization
+    /// Calculate composite score for retrieval prioritization, scaled by
+    /// the authority of the item's source and demoted if it is untrusted
     pub fn calculate_score(&self, query: &str, item: &MemoryItem) -> f64 {
         let relevance = self.calculate_relevance(query, item.get_content());
-        let importance = item.current_importance();
+        let importance = item.importance_at(self.clock.now());
         let recency = self.calculate_recency(item);
 
-        self.config.relevance_weight * relevance
+        let score = self.config.relevance_weight * relevance
             + self.config.importance_weight * importance
-            + self.config.recency_weight * recency
+            + self.config.recency_weight * recency;
+        let trust = if Trust::is_untrusted(item) { self.config.untrusted_weight } else { 1.0 };
+        score * self.authority.weight_for(&item.metadata.source) * trust
     }
 
     /// Filter items by minimum relevance
@@ -143,7 +255,7 @@ impl RelevanceScorer {
                 let relevance = self.calculate_relevance(query, item.get_content());
                 if relevance >= self.config.min_relevance {
                     let score = self.calculate_score(query, &item);
-                    Some(ScoredItem { item, score })
+                    Some(ScoredItem { item, score, stale: None })
                 } else {
                     None
                 }
@@ -157,6 +269,8 @@ impl RelevanceScorer {
 pub struct ScoredItem {
     pub item: MemoryItem,
     pub score: f64,
+    /// Why the item may be outdated, set on selected items
+    pub stale: Option<Staleness>,
 }
 
 impl PartialEq for ScoredItem {
@@ -186,21 +300,41 @@ impl Ord for ScoredItem {
 pub struct ContextWindow {
     config: RetrievalConfig,
     scorer: RelevanceScorer,
+    freshness: FreshnessEvaluator,
+    merger: ChunkMerger,
 }
 
 impl ContextWindow {
     pub fn new(config: RetrievalConfig) -> Result<Self> {
         config.validate()?;
         let scorer = RelevanceScorer::new(config.clone());
-        Ok(Self { config, scorer })
+        let freshness = FreshnessEvaluator::new(config.freshness.clone());
+        let merger = ChunkMerger::new(config.chunk_merge);
+        Ok(Self { config, scorer, freshness, merger })
+    }
+
+    /// Score items and measure their age against `clock` instead of the
+    /// system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.scorer = self.scorer.with_clock(clock.clone());
+        self.freshness = self.freshness.with_clock(clock);
+        self
+    }
+
+    /// Weight scores by source authority
+    pub fn with_authority(mut self, authority: Arc<SourceAuthority>) -> Self {
+        self.scorer = self.scorer.with_authority(authority);
+        self
     }
 
     /// Retrieve and prioritize items within token budget
     pub fn retrieve(&self, query: &str, items: Vec<MemoryItem>) -> Result<RetrievalResult> {
         let target_tokens = self.config.target_tokens();
+        let candidate_ids = items.iter().map(|item| item.metadata.id).collect();
+        let latest = LatestVersions::of(&items);
 
-        // Score and filter items
-        let mut scored_items = self.scorer.filter_relevant(query, items);
+        // Score and filter items, merging adjacent chunks before budgeting
+        let mut scored_items = self.merger.merge(self.scorer.filter_relevant(query, items));
 
         // Sort by score (descending)
         scored_items.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
@@ -221,21 +355,26 @@ impl ContextWindow {
             }
         }
 
-        Ok(RetrievalResult {
+        let mut result = RetrievalResult {
             selected,
             rejected,
+            candidate_ids,
             total_tokens: current_tokens,
             target_tokens,
             max_tokens: self.config.max_tokens,
-        })
+        };
+        self.freshness.annotate(&mut result, &latest);
+        Ok(result)
     }
 
     /// Retrieve with advanced prioritization (knapsack-like optimization)
     pub fn retrieve_optimized(&self, query: &str, items: Vec<MemoryItem>) -> Result<RetrievalResult> {
         let target_tokens = self.config.target_tokens();
+        let candidate_ids = items.iter().map(|item| item.metadata.id).collect();
+        let latest = LatestVersions::of(&items);
 
-        // Score and filter items
-        let scored_items = self.scorer.filter_relevant(query, items);
+        // Score and filter items, merging adjacent chunks before budgeting
+        let scored_items = self.merger.merge(self.scorer.filter_relevant(query, items));
 
         // Use priority queue for better selection
         let mut heap: BinaryHeap<ScoredItem> = scored_items.into_iter().collect();
@@ -261,13 +400,16 @@ impl ContextWindow {
 
         let total_tokens = selected.iter().map(|s| s.item.token_count).sum();
 
-        Ok(RetrievalResult {
+        let mut result = RetrievalResult {
             selected,
             rejected,
+            candidate_ids,
             total_tokens,
             target_tokens,
             max_tokens: self.config.max_tokens,
-        })
+        };
+        self.freshness.annotate(&mut result, &latest);
+        Ok(result)
     }
 
     /// Optimize selection for better token utilization
@@ -356,7 +498,7 @@ impl ContextWindow {
 }
 
 /// Result of context retrieval operation
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RetrievalResult {
     /// Items selected for context
     pub selected: Vec<ScoredItem>,
@@ -364,6 +506,9 @@ pub struct RetrievalResult {
     /// Items rejected (below threshold or out of budget)
     pub rejected: Vec<ScoredItem>,
 
+    /// IDs of every item considered for the window
+    pub candidate_ids: Vec<Uuid>,
+
     /// Total tokens in selected items
     pub total_tokens: usize,
 
@@ -389,6 +534,11 @@ impl RetrievalResult {
     pub fn is_within_budget(&self) -> bool {
         self.total_tokens <= self.max_tokens
     }
+
+    /// Selected items that may be outdated
+    pub fn stale(&self) -> impl Iterator<Item = &ScoredItem> {
+        self.selected.iter().filter(|scored| scored.stale.is_some())
+    }
 }
 
 #[cfg(test)]
@@ -431,6 +581,98 @@ mod tests {
         assert!(score1 > score2);
     }
 
+    #[test]
+    fn test_authority_scales_scores() {
+        let authority = Arc::new(SourceAuthority::new());
+        let scorer = RelevanceScorer::new(RetrievalConfig::default()).with_authority(authority.clone());
+        let item = |source: &str| {
+            MemoryItem::new("rust guide".to_string(), MemoryMetadata::new("doc", source), 0.5, 2)
+        };
+        let docs = item("https://docs.example.com/rust");
+        let chat = item("slack://C024BE91L");
+        assert_eq!(scorer.calculate_score("rust", &docs), scorer.calculate_score("rust", &chat));
+
+        authority.set("https://docs.example.com/", 2.0).unwrap();
+        authority.set("slack://", 0.5).unwrap();
+        let docs_score = scorer.calculate_score("rust", &docs);
+        let chat_score = scorer.calculate_score("rust", &chat);
+        assert!((docs_score - 4.0 * chat_score).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_untrusted_items_are_demoted() {
+        use crate::provenance::TRUST_KEY;
+
+        let scorer = RelevanceScorer::new(RetrievalConfig::default());
+        let item = |trust: Option<Trust>| {
+            let mut metadata = MemoryMetadata::new("doc", "wiki");
+            if let Some(trust) = trust {
+                metadata.add_custom(TRUST_KEY.to_string(), serde_json::json!(trust));
+            }
+            MemoryItem::new("rust guide".to_string(), metadata, 0.5, 2)
+        };
+
+        let unlabeled = scorer.calculate_score("rust", &item(None));
+        assert_eq!(scorer.calculate_score("rust", &item(Some(Trust::Verified))), unlabeled);
+        let untrusted = scorer.calculate_score("rust", &item(Some(Trust::Untrusted)));
+        assert!((untrusted - 0.5 * unlabeled).abs() < 1e-9);
+
+        let config = RetrievalConfig { untrusted_weight: 1.5, ..Default::default() };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_window_flags_superseded_items() {
+        use crate::freshness::{DOCUMENT_KEY, VERSION_KEY};
+
+        let version = |id: &str, hours: i64| {
+            let mut metadata = MemoryMetadata::new("document", "wiki");
+            metadata.add_custom(DOCUMENT_KEY.to_string(), serde_json::json!("deploy.md"));
+            metadata.add_custom(VERSION_KEY.to_string(), serde_json::json!(id));
+            let ingested_at = chrono::Utc::now() - chrono::Duration::hours(hours);
+            MemoryItem::new_at("rust deployment guide".to_string(), metadata, 0.8, 10, ingested_at)
+        };
+
+        let window = ContextWindow::new(RetrievalConfig::default()).unwrap();
+        let result = window.retrieve("rust deployment", vec![version("v1", 48), version("v2", 1)]).unwrap();
+        assert_eq!(result.selected.len(), 2);
+
+        let stale: Vec<_> = result.stale().collect();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].item.metadata.custom[VERSION_KEY], "v1");
+    }
+
+    #[test]
+    fn test_scores_are_reproducible_with_a_frozen_clock() {
+        use copilot_core::FrozenClock;
+
+        let score = |hours: i64| {
+            let clock = Arc::new(FrozenClock::at_epoch());
+            let scorer = RelevanceScorer::new(RetrievalConfig::default()).with_clock(clock.clone());
+            let item = MemoryItem::new_at(
+                "rust programming guide".to_string(),
+                MemoryMetadata::new("test", "test"),
+                0.8,
+                10,
+                FrozenClock::EPOCH,
+            );
+            clock.advance(chrono::Duration::hours(hours));
+            (
+                scorer.calculate_recency(&item),
+                scorer.calculate_score("rust programming", &item),
+            )
+        };
+
+        let (fresh_recency, fresh_score) = score(0);
+        assert_eq!(fresh_recency, 1.0);
+        let (day_old_recency, day_old_score) = score(24);
+        assert!((day_old_recency - 0.5).abs() < 0.001);
+        assert!(day_old_score < fresh_score);
+
+        // Same inputs, same bits, however long the test takes
+        assert_eq!(score(24).1.to_bits(), day_old_score.to_bits());
+    }
+
     #[test]
     fn test_context_window_retrieval() {
         let mut config = RetrievalConfig::default();
@@ -462,10 +704,12 @@ mod tests {
         let scored1 = ScoredItem {
             item: item1,
             score: 0.9,
+            stale: None,
         };
         let scored2 = ScoredItem {
             item: item2,
             score: 0.7,
+            stale: None,
         };
 
         let mut heap = BinaryHeap::new();
@@ -0,0 +1,137 @@
+//! Shared scratchpad for collaborating agents
+//!
+//! An append-only notebook that several agents (or turns of a single agent)
+//! can write to and read from while working on the same task. Entries are
+//! attributed to their author so later turns can see who contributed what.
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// A single note written to the scratchpad
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScratchpadEntry {
+    pub id: Uuid,
+    /// Who wrote the entry (e.g. an agent role name)
+    pub author: String,
+    /// Free-form content
+    pub content: String,
+    /// Optional label (e.g. "plan", "code", "review")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Thread-safe shared scratchpad; clones share the same entries
+#[derive(Debug, Clone, Default)]
+pub struct Scratchpad {
+    entries: Arc<RwLock<Vec<ScratchpadEntry>>>,
+}
+
+impl Scratchpad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an entry and return its ID
+    pub fn write(&self, author: impl Into<String>, content: impl Into<String>) -> Uuid {
+        self.write_labeled(author, content, None::<String>)
+    }
+
+    /// Append an entry with a label and return its ID
+    pub fn write_labeled(
+        &self,
+        author: impl Into<String>,
+        content: impl Into<String>,
+        label: Option<impl Into<String>>,
+    ) -> Uuid {
+        let entry = ScratchpadEntry {
+            id: Uuid::new_v4(),
+            author: author.into(),
+            content: content.into(),
+            label: label.map(Into::into),
+            created_at: Utc::now(),
+        };
+        let id = entry.id;
+        self.entries.write().push(entry);
+        id
+    }
+
+    /// All entries, oldest first
+    pub fn entries(&self) -> Vec<ScratchpadEntry> {
+        self.entries.read().clone()
+    }
+
+    /// Entries written by `author`
+    pub fn entries_by(&self, author: &str) -> Vec<ScratchpadEntry> {
+        self.entries
+            .read()
+            .iter()
+            .filter(|e| e.author == author)
+            .cloned()
+            .collect()
+    }
+
+    /// The most recent entry
+    pub fn latest(&self) -> Option<ScratchpadEntry> {
+        self.entries.read().last().cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.read().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.entries.write().clear();
+    }
+
+    /// Render the most recent `max_entries` as prompt-ready text
+    pub fn render(&self, max_entries: usize) -> String {
+        let entries = self.entries.read();
+        let start = entries.len().saturating_sub(max_entries);
+
+        entries[start..]
+            .iter()
+            .map(|e| match &e.label {
+                Some(label) => format!("[{}:{}] {}", e.author, label, e.content),
+                None => format!("[{}] {}", e.author, e.content),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_between_clones() {
+        let pad = Scratchpad::new();
+        let other = pad.clone();
+
+        pad.write("planner", "1. write code");
+        other.write_labeled("coder", "fn main() {}", Some("code"));
+
+        assert_eq!(pad.len(), 2);
+        assert_eq!(pad.entries_by("coder").len(), 1);
+        assert_eq!(pad.latest().unwrap().author, "coder");
+    }
+
+    #[test]
+    fn test_render_tail() {
+        let pad = Scratchpad::new();
+        pad.write("a", "one");
+        pad.write_labeled("b", "two", Some("review"));
+        pad.write("c", "three");
+
+        assert_eq!(pad.render(2), "[b:review] two\n[c] three");
+        assert_eq!(pad.render(10).lines().count(), 3);
+    }
+}
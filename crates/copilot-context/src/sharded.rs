@@ -0,0 +1,299 @@
+//! Sharded, concurrent-safe memory store
+//!
+//! Partitions items across independently locked shards so that writes only
+//! contend with reads that hash to the same shard. Unlike [`InMemoryStore`],
+//! which guards every item with one lock, large writes do not stall
+//! retrieval across the whole tier.
+//!
+//! [`InMemoryStore`]: crate::memory::InMemoryStore
+
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::filter::ContextFilter;
+use crate::memory::{MemoryItem, MemoryStore, MemoryTier};
+use crate::Result;
+
+/// Default number of shards
+pub const DEFAULT_SHARD_COUNT: usize = 16;
+
+/// Memory store partitioned across independently locked shards
+pub struct ShardedMemoryStore {
+    shards: Vec<RwLock<HashMap<Uuid, MemoryItem>>>,
+    tier: MemoryTier,
+}
+
+impl ShardedMemoryStore {
+    pub fn new(tier: MemoryTier) -> Self {
+        Self::with_shards(tier, DEFAULT_SHARD_COUNT)
+    }
+
+    /// Create a store with a specific number of shards (at least one)
+    pub fn with_shards(tier: MemoryTier, shard_count: usize) -> Self {
+        let shard_count = shard_count.max(1);
+        Self {
+            shards: (0..shard_count).map(|_| RwLock::new(HashMap::new())).collect(),
+            tier,
+        }
+    }
+
+    /// The tier this store holds
+    pub fn tier(&self) -> MemoryTier {
+        self.tier
+    }
+
+    /// Number of shards
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    fn shard(&self, id: &Uuid) -> &RwLock<HashMap<Uuid, MemoryItem>> {
+        let idx = (id.as_u128() % self.shards.len() as u128) as usize;
+        &self.shards[idx]
+    }
+
+    /// Insert or replace an item
+    pub fn insert(&self, item: MemoryItem) {
+        self.shard(&item.metadata.id).write().insert(item.metadata.id, item);
+    }
+
+    /// Get a copy of an item
+    pub fn get(&self, id: &Uuid) -> Option<MemoryItem> {
+        self.shard(id).read().get(id).cloned()
+    }
+
+    /// Check whether an item exists
+    pub fn contains(&self, id: &Uuid) -> bool {
+        self.shard(id).read().contains_key(id)
+    }
+
+    /// Remove an item, returning it if present
+    pub fn take(&self, id: &Uuid) -> Option<MemoryItem> {
+        self.shard(id).write().remove(id)
+    }
+
+    /// Mutate an item in place; returns false if it does not exist
+    pub fn modify<F>(&self, id: &Uuid, f: F) -> bool
+    where
+        F: FnOnce(&mut MemoryItem),
+    {
+        match self.shard(id).write().get_mut(id) {
+            Some(item) => {
+                f(item);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Snapshot of all items, locking one shard at a time
+    pub fn items(&self) -> Vec<MemoryItem> {
+        let mut items = Vec::new();
+        for shard in &self.shards {
+            items.extend(shard.read().values().cloned());
+        }
+        items
+    }
+
+    /// Snapshot of the items matching `filter`; others are never cloned
+    pub fn items_matching(&self, filter: &ContextFilter) -> Vec<MemoryItem> {
+        if filter.is_empty() {
+            return self.items();
+        }
+        let mut items = Vec::new();
+        for shard in &self.shards {
+            items.extend(shard.read().values().filter(|item| filter.matches(item)).cloned());
+        }
+        items
+    }
+
+    /// Number of items
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.read().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|s| s.read().is_empty())
+    }
+
+    /// Total token count across shards
+    pub fn tokens(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|s| s.read().values().map(|item| item.token_count).sum::<usize>())
+            .sum()
+    }
+
+    /// Remove all items
+    pub fn clear_all(&self) {
+        for shard in &self.shards {
+            shard.write().clear();
+        }
+    }
+
+    /// Items whose tier matches
+    pub fn items_in_tier(&self, tier: MemoryTier) -> Vec<MemoryItem> {
+        let mut items = Vec::new();
+        for shard in &self.shards {
+            items.extend(shard.read().values().filter(|item| item.tier == tier).cloned());
+        }
+        items
+    }
+
+    /// Evict the least important items until at most `target_tokens` remain
+    pub fn evict_to(&self, target_tokens: usize) -> Vec<MemoryItem> {
+        let current_tokens = self.tokens();
+        if current_tokens <= target_tokens {
+            return Vec::new();
+        }
+
+        let mut candidates = self.items();
+        candidates.sort_by(|a, b| {
+            a.current_importance()
+                .partial_cmp(&b.current_importance())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let tokens_to_free = current_tokens - target_tokens;
+        let mut freed_tokens = 0;
+        let mut evicted = Vec::new();
+
+        for candidate in candidates {
+            if freed_tokens >= tokens_to_free {
+                break;
+            }
+            // The item may have been removed concurrently since the snapshot
+            if let Some(item) = self.take(&candidate.metadata.id) {
+                freed_tokens += item.token_count;
+                evicted.push(item);
+            }
+        }
+
+        evicted
+    }
+}
+
+#[async_trait]
+impl MemoryStore for ShardedMemoryStore {
+    async fn store(&self, item: MemoryItem) -> Result<()> {
+        self.insert(item);
+        Ok(())
+    }
+
+    async fn retrieve(&self, id: &Uuid) -> Result<Option<MemoryItem>> {
+        Ok(self.get(id))
+    }
+
+    async fn list(&self) -> Result<Vec<MemoryItem>> {
+        Ok(self.items())
+    }
+
+    async fn list_matching(&self, filter: &ContextFilter) -> Result<Vec<MemoryItem>> {
+        Ok(self.items_matching(filter))
+    }
+
+    async fn take(&self, id: &Uuid) -> Result<Option<MemoryItem>> {
+        Ok(ShardedMemoryStore::take(self, id))
+    }
+
+    async fn update(&self, item: MemoryItem) -> Result<()> {
+        self.insert(item);
+        Ok(())
+    }
+
+    async fn total_tokens(&self) -> Result<usize> {
+        Ok(self.tokens())
+    }
+
+    async fn count(&self) -> Result<usize> {
+        Ok(self.len())
+    }
+
+    async fn clear(&self) -> Result<()> {
+        self.clear_all();
+        Ok(())
+    }
+
+    async fn get_by_tier(&self, tier: MemoryTier) -> Result<Vec<MemoryItem>> {
+        Ok(self.items_in_tier(tier))
+    }
+
+    async fn evict(&self, target_tokens: usize) -> Result<Vec<MemoryItem>> {
+        Ok(self.evict_to(target_tokens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryMetadata;
+    use std::sync::Arc;
+
+    fn item(tokens: usize, importance: f64) -> MemoryItem {
+        MemoryItem::new(
+            "content".to_string(),
+            MemoryMetadata::new("test", "test"),
+            importance,
+            tokens,
+        )
+    }
+
+    #[test]
+    fn test_insert_get_take() {
+        let store = ShardedMemoryStore::with_shards(MemoryTier::ShortTerm, 4);
+        let a = item(10, 0.4);
+        let id = a.metadata.id;
+
+        store.insert(a);
+        assert!(store.contains(&id));
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.tokens(), 10);
+
+        assert!(store.modify(&id, |item| item.record_access()));
+        assert_eq!(store.get(&id).unwrap().access_count, 1);
+
+        assert!(store.take(&id).is_some());
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_evict_lowest_importance_first() {
+        let store = ShardedMemoryStore::new(MemoryTier::ShortTerm);
+        let low = item(50, 0.1);
+        let high = item(50, 0.9);
+        let low_id = low.metadata.id;
+        store.insert(low);
+        store.insert(high);
+
+        let evicted = store.evict_to(50);
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].metadata.id, low_id);
+        assert_eq!(store.tokens(), 50);
+    }
+
+    #[test]
+    fn test_concurrent_writers_and_readers() {
+        let store = Arc::new(ShardedMemoryStore::new(MemoryTier::ShortTerm));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..250 {
+                        store.insert(item(1, 0.5));
+                        let _ = store.items();
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(store.len(), 2_000);
+        assert_eq!(store.tokens(), 2_000);
+    }
+}
@@ -0,0 +1,663 @@
+//! Metrics-driven tuning of retrieval parameters
+//!
+//! [`QueryLog`] keeps recent real queries together with the click and
+//! feedback signals users gave the documents shown for them; each signal
+//! becomes a graded relevance label. [`AutoTuner`] periodically replays the
+//! labelled queries through a [`TunableRetriever`] for every point of a
+//! [`SearchSpace`], scores the rankings with [`evaluate`], and proposes the
+//! best [`SearchParams`] when they beat the current ones by a margin. With
+//! `auto_apply` set, proposals are also written to the shared
+//! [`RetrievalConfig`].
+//!
+//! Documents nobody signalled count as not relevant, so the tuner favours
+//! parameters that rank what users engaged with first.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use copilot_core::{Clock, SystemClock};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    hybrid_search::HybridSearchEngine,
+    reranking::{RerankDocument, Reranker},
+    retrieval::{RetrievalConfig, SearchParams},
+    Result,
+};
+
+/// What a user did with a retrieved document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Signal {
+    /// Opened or cited the document
+    Click,
+    /// Marked the document as helpful
+    Helpful,
+    /// Marked the document as not helpful
+    NotHelpful,
+}
+
+impl Signal {
+    /// Relevance grade: 2 for helpful, 1 for a click, 0 for not helpful
+    pub fn grade(self) -> u8 {
+        match self {
+            Signal::Helpful => 2,
+            Signal::Click => 1,
+            Signal::NotHelpful => 0,
+        }
+    }
+
+    /// Explicit feedback outranks a click, and negative feedback outranks
+    /// positive
+    fn precedence(self) -> u8 {
+        match self {
+            Signal::Click => 0,
+            Signal::Helpful => 1,
+            Signal::NotHelpful => 2,
+        }
+    }
+}
+
+/// A query as it was served, with the signals received since
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggedQuery {
+    pub id: Uuid,
+    pub query: String,
+    /// Document IDs shown, best first
+    pub shown: Vec<String>,
+    /// Strongest signal per document
+    pub signals: HashMap<String, Signal>,
+    pub at: DateTime<Utc>,
+}
+
+impl LoggedQuery {
+    /// Relevance grade of each signalled document
+    pub fn labels(&self) -> HashMap<String, u8> {
+        self.signals
+            .iter()
+            .map(|(doc_id, signal)| (doc_id.clone(), signal.grade()))
+            .collect()
+    }
+
+    /// Whether any document was signalled as relevant
+    pub fn is_labelled(&self) -> bool {
+        self.signals.values().any(|signal| signal.grade() > 0)
+    }
+}
+
+/// Bounded log of recent queries and their feedback signals
+pub struct QueryLog {
+    capacity: usize,
+    entries: Mutex<VecDeque<LoggedQuery>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl QueryLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(VecDeque::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Timestamp queries with `clock`
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Record a served query; the oldest query is dropped when full
+    pub fn record(&self, query: &str, shown: Vec<String>) -> Uuid {
+        let id = Uuid::new_v4();
+        let mut entries = self.entries.lock();
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(LoggedQuery {
+            id,
+            query: query.to_string(),
+            shown,
+            signals: HashMap::new(),
+            at: self.clock.now(),
+        });
+        id
+    }
+
+    /// Attach a signal to a document of a logged query; false if the query
+    /// has left the log
+    pub fn signal(&self, query_id: &Uuid, doc_id: &str, signal: Signal) -> bool {
+        let mut entries = self.entries.lock();
+        let Some(entry) = entries.iter_mut().rev().find(|entry| entry.id == *query_id) else {
+            return false;
+        };
+        entry
+            .signals
+            .entry(doc_id.to_string())
+            .and_modify(|current| {
+                if signal.precedence() >= current.precedence() {
+                    *current = signal;
+                }
+            })
+            .or_insert(signal);
+        true
+    }
+
+    /// Labelled queries recorded at or after `since`
+    pub fn labelled_since(&self, since: DateTime<Utc>) -> Vec<LoggedQuery> {
+        self.entries
+            .lock()
+            .iter()
+            .filter(|entry| entry.at >= since && entry.is_labelled())
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+}
+
+/// Ranking quality averaged over queries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvalMetrics {
+    pub queries: usize,
+    /// Normalized discounted cumulative gain at `top_k`
+    pub ndcg: f64,
+    /// Mean reciprocal rank of the first relevant result
+    pub mrr: f64,
+    /// Share of relevant documents retrieved
+    pub recall: f64,
+    /// Share of retrieved documents that are relevant
+    pub precision: f64,
+}
+
+/// Metric the tuner maximizes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TuningObjective {
+    Ndcg,
+    Mrr,
+    Recall,
+    /// Mean of nDCG and precision, so ordering counts and padding the
+    /// results with irrelevant documents does not pay
+    #[default]
+    Balanced,
+}
+
+impl EvalMetrics {
+    pub fn score(&self, objective: TuningObjective) -> f64 {
+        match objective {
+            TuningObjective::Ndcg => self.ndcg,
+            TuningObjective::Mrr => self.mrr,
+            TuningObjective::Recall => self.recall,
+            TuningObjective::Balanced => (self.ndcg + self.precision) / 2.0,
+        }
+    }
+}
+
+/// Score rankings against graded labels; each run pairs the ranked document
+/// IDs with the labels of its query, and rankings are cut off at `top_k`
+pub fn evaluate(runs: &[(Vec<String>, HashMap<String, u8>)], top_k: usize) -> EvalMetrics {
+    let mut metrics = EvalMetrics::default();
+    for (ranked, labels) in runs {
+        let ranked = &ranked[..ranked.len().min(top_k)];
+        let grade = |doc_id: &String| labels.get(doc_id).copied().unwrap_or(0);
+        let gain = |grade: u8| f64::from((1u32 << grade) - 1);
+        let discount = |rank: usize| (rank as f64 + 2.0).log2();
+
+        let dcg: f64 = ranked.iter().enumerate().map(|(rank, doc_id)| gain(grade(doc_id)) / discount(rank)).sum();
+        let mut ideal: Vec<u8> = labels.values().copied().filter(|grade| *grade > 0).collect();
+        ideal.sort_unstable_by(|a, b| b.cmp(a));
+        let idcg: f64 = ideal.iter().take(top_k).enumerate().map(|(rank, grade)| gain(*grade) / discount(rank)).sum();
+
+        let hits = ranked.iter().filter(|doc_id| grade(doc_id) > 0).count();
+        metrics.queries += 1;
+        if idcg > 0.0 {
+            metrics.ndcg += dcg / idcg;
+            metrics.recall += hits as f64 / ideal.len() as f64;
+        }
+        if let Some(rank) = ranked.iter().position(|doc_id| grade(doc_id) > 0) {
+            metrics.mrr += 1.0 / (rank as f64 + 1.0);
+        }
+        if !ranked.is_empty() {
+            metrics.precision += hits as f64 / ranked.len() as f64;
+        }
+    }
+    if metrics.queries > 0 {
+        let n = metrics.queries as f64;
+        metrics.ndcg /= n;
+        metrics.mrr /= n;
+        metrics.recall /= n;
+        metrics.precision /= n;
+    }
+    metrics
+}
+
+/// A retriever that can rank with any [`SearchParams`], for replaying
+/// logged queries
+#[async_trait]
+pub trait TunableRetriever: Send + Sync {
+    /// Document IDs for `query`, best first, at most `params.top_k`
+    async fn retrieve(&self, query: &str, params: &SearchParams) -> Result<Vec<String>>;
+}
+
+/// [`TunableRetriever`] over a hybrid search index, optionally reranked
+///
+/// The rerank threshold only applies when a reranker is set.
+pub struct HybridRetriever {
+    engine: tokio::sync::Mutex<HybridSearchEngine>,
+    reranker: Option<Arc<dyn Reranker>>,
+    candidates: usize,
+}
+
+impl HybridRetriever {
+    pub fn new(engine: HybridSearchEngine) -> Self {
+        Self {
+            engine: tokio::sync::Mutex::new(engine),
+            reranker: None,
+            candidates: 50,
+        }
+    }
+
+    /// Rerank the top `candidates` search results with `reranker`
+    pub fn with_reranker(mut self, reranker: Arc<dyn Reranker>, candidates: usize) -> Self {
+        self.reranker = Some(reranker);
+        self.candidates = candidates.max(1);
+        self
+    }
+}
+
+#[async_trait]
+impl TunableRetriever for HybridRetriever {
+    async fn retrieve(&self, query: &str, params: &SearchParams) -> Result<Vec<String>> {
+        let mut engine = self.engine.lock().await;
+        engine.set_fusion_weights(params.vector_weight, params.keyword_weight());
+
+        let Some(reranker) = &self.reranker else {
+            let results = engine.search(query, params.top_k).await?;
+            return Ok(results.into_iter().map(|result| result.doc_id).collect());
+        };
+        let documents = engine
+            .search(query, self.candidates.max(params.top_k))
+            .await?
+            .into_iter()
+            .map(|result| {
+                let content = engine.get_content(&result.doc_id).cloned().unwrap_or_default();
+                RerankDocument::new(result.doc_id, content).with_score(result.score)
+            })
+            .collect();
+        drop(engine);
+
+        let threshold = params.rerank_threshold.unwrap_or(f32::NEG_INFINITY);
+        Ok(reranker
+            .rerank(query, documents)
+            .await?
+            .into_iter()
+            .filter(|result| result.score >= threshold)
+            .take(params.top_k)
+            .map(|result| result.id)
+            .collect())
+    }
+}
+
+/// Parameter values the tuner tries; every combination is evaluated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchSpace {
+    pub top_k: Vec<usize>,
+    pub vector_weight: Vec<f32>,
+    pub rerank_threshold: Vec<Option<f32>>,
+}
+
+impl Default for SearchSpace {
+    fn default() -> Self {
+        Self {
+            top_k: vec![3, 5, 10, 20],
+            vector_weight: vec![0.0, 0.3, 0.5, 0.7, 1.0],
+            rerank_threshold: vec![None, Some(0.3), Some(0.5), Some(0.7)],
+        }
+    }
+}
+
+impl SearchSpace {
+    pub fn candidates(&self) -> Vec<SearchParams> {
+        let mut candidates = Vec::new();
+        for &top_k in &self.top_k {
+            for &vector_weight in &self.vector_weight {
+                for &rerank_threshold in &self.rerank_threshold {
+                    candidates.push(SearchParams {
+                        top_k,
+                        vector_weight,
+                        rerank_threshold,
+                    });
+                }
+            }
+        }
+        candidates
+    }
+}
+
+/// Auto-tuner configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoTunerConfig {
+    /// Time between tuning runs
+    pub interval: Duration,
+    /// How far back logged queries are replayed
+    pub lookback: Duration,
+    /// Labelled queries needed before proposing anything
+    pub min_queries: usize,
+    /// Objective gain over the current parameters needed for a proposal
+    pub min_improvement: f64,
+    pub objective: TuningObjective,
+    pub space: SearchSpace,
+    /// Write proposals to the retrieval config instead of only reporting
+    /// them
+    pub auto_apply: bool,
+}
+
+impl Default for AutoTunerConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(6 * 3600),
+            lookback: Duration::from_secs(7 * 24 * 3600),
+            min_queries: 20,
+            min_improvement: 0.02,
+            objective: TuningObjective::default(),
+            space: SearchSpace::default(),
+            auto_apply: false,
+        }
+    }
+}
+
+/// Parameters found to beat the current ones on recent queries
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TuningProposal {
+    pub current: SearchParams,
+    pub proposed: SearchParams,
+    /// Metrics of the current parameters
+    pub baseline: EvalMetrics,
+    /// Metrics of the proposed parameters
+    pub metrics: EvalMetrics,
+    /// Objective gain of the proposed parameters
+    pub improvement: f64,
+    /// Whether the proposal was written to the retrieval config
+    pub applied: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Periodically searches for better retrieval parameters
+pub struct AutoTuner {
+    config: AutoTunerConfig,
+    log: Arc<QueryLog>,
+    retriever: Arc<dyn TunableRetriever>,
+    retrieval: Arc<RwLock<RetrievalConfig>>,
+    latest: Mutex<Option<TuningProposal>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl AutoTuner {
+    pub fn new(
+        config: AutoTunerConfig,
+        log: Arc<QueryLog>,
+        retriever: Arc<dyn TunableRetriever>,
+        retrieval: Arc<RwLock<RetrievalConfig>>,
+    ) -> Self {
+        Self {
+            config,
+            log,
+            retriever,
+            retrieval,
+            latest: Mutex::new(None),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Measure the lookback window against `clock`
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Replay `queries` with `params` and score the rankings
+    pub async fn evaluate_params(&self, queries: &[LoggedQuery], params: &SearchParams) -> Result<EvalMetrics> {
+        let mut runs = Vec::with_capacity(queries.len());
+        for query in queries {
+            let ranked = self.retriever.retrieve(&query.query, params).await?;
+            runs.push((ranked, query.labels()));
+        }
+        Ok(evaluate(&runs, params.top_k))
+    }
+
+    /// Search for better parameters on recent queries; `None` when there
+    /// are too few labelled queries or nothing beats the current parameters
+    /// by `min_improvement`
+    pub async fn run_once(&self) -> Result<Option<TuningProposal>> {
+        let now = self.clock.now();
+        let lookback = chrono::Duration::from_std(self.config.lookback).unwrap_or(chrono::Duration::MAX);
+        let since = now.checked_sub_signed(lookback).unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let queries = self.log.labelled_since(since);
+        if queries.len() < self.config.min_queries {
+            debug!(queries = queries.len(), "Too few labelled queries to tune retrieval");
+            return Ok(None);
+        }
+
+        let objective = self.config.objective;
+        let current = self.retrieval.read().search;
+        let baseline = self.evaluate_params(&queries, &current).await?;
+        let mut best: Option<(SearchParams, EvalMetrics)> = None;
+        for candidate in self.config.space.candidates() {
+            let metrics = self.evaluate_params(&queries, &candidate).await?;
+            if best.map_or(true, |(_, best)| metrics.score(objective) > best.score(objective)) {
+                best = Some((candidate, metrics));
+            }
+        }
+
+        let Some((proposed, metrics)) = best else {
+            return Ok(None);
+        };
+        let improvement = metrics.score(objective) - baseline.score(objective);
+        if improvement < self.config.min_improvement {
+            debug!(improvement, "No retrieval parameters beat the current ones");
+            return Ok(None);
+        }
+
+        let applied = self.config.auto_apply;
+        if applied {
+            self.retrieval.write().search = proposed;
+        }
+        info!(
+            queries = queries.len(),
+            top_k = proposed.top_k,
+            vector_weight = proposed.vector_weight,
+            rerank_threshold = ?proposed.rerank_threshold,
+            improvement,
+            applied,
+            "Proposed retrieval parameters"
+        );
+        let proposal = TuningProposal {
+            current,
+            proposed,
+            baseline,
+            metrics,
+            improvement,
+            applied,
+            created_at: now,
+        };
+        *self.latest.lock() = Some(proposal.clone());
+        Ok(Some(proposal))
+    }
+
+    /// The most recent proposal
+    pub fn latest(&self) -> Option<TuningProposal> {
+        self.latest.lock().clone()
+    }
+
+    /// Write the most recent proposal to the retrieval config, returning
+    /// the applied parameters
+    pub fn apply_latest(&self) -> Option<SearchParams> {
+        let mut latest = self.latest.lock();
+        let proposal = latest.as_mut()?;
+        self.retrieval.write().search = proposal.proposed;
+        proposal.applied = true;
+        Some(proposal.proposed)
+    }
+
+    /// Run [`run_once`](Self::run_once) every `interval`
+    pub fn spawn(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.config.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.run_once().await {
+                    warn!(error = %e, "Retrieval tuning failed");
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_search::{HybridSearchConfig, MockEmbeddingProvider};
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    /// Ranks by keyword order below a vector weight of 0.5 and by vector
+    /// order above it
+    struct SplitRetriever;
+
+    #[async_trait]
+    impl TunableRetriever for SplitRetriever {
+        async fn retrieve(&self, query: &str, params: &SearchParams) -> Result<Vec<String>> {
+            let ranked = if params.vector_weight < 0.5 {
+                ids(&[&format!("{query}-keyword"), "noise-1", "noise-2"])
+            } else {
+                ids(&["noise-1", "noise-2", &format!("{query}-keyword")])
+            };
+            Ok(ranked.into_iter().take(params.top_k).collect())
+        }
+    }
+
+    fn keyword_clicks(log: &QueryLog, queries: usize) {
+        for i in 0..queries {
+            let query = format!("q{i}");
+            let id = log.record(&query, ids(&["noise-1", "noise-2", &format!("{query}-keyword")]));
+            log.signal(&id, &format!("{query}-keyword"), Signal::Click);
+            log.signal(&id, "noise-1", Signal::NotHelpful);
+        }
+    }
+
+    #[test]
+    fn test_metrics() {
+        let labels = HashMap::from([("a".to_string(), 2), ("b".to_string(), 1), ("c".to_string(), 0)]);
+
+        let perfect = evaluate(&[(ids(&["a", "b"]), labels.clone())], 2);
+        assert!((perfect.ndcg - 1.0).abs() < 1e-9);
+        assert_eq!((perfect.mrr, perfect.recall, perfect.precision), (1.0, 1.0, 1.0));
+
+        let metrics = evaluate(&[(ids(&["c", "x", "b", "a"]), labels.clone())], 4);
+        assert_eq!(metrics.mrr, 1.0 / 3.0);
+        assert_eq!(metrics.precision, 0.5);
+        assert!(metrics.ndcg < 0.6);
+
+        // Results past top_k do not count
+        let cut = evaluate(&[(ids(&["c", "a"]), labels)], 1);
+        assert_eq!((cut.mrr, cut.recall, cut.precision), (0.0, 0.0, 0.0));
+        assert_eq!(evaluate(&[], 10), EvalMetrics::default());
+    }
+
+    #[test]
+    fn test_query_log_labels() {
+        let log = QueryLog::new(2);
+        let first = log.record("deploy rollback", ids(&["a", "b"]));
+        let second = log.record("deploy canary", ids(&["c"]));
+
+        log.signal(&first, "a", Signal::Helpful);
+        log.signal(&first, "a", Signal::Click);
+        log.signal(&first, "b", Signal::Click);
+        log.signal(&first, "b", Signal::NotHelpful);
+        log.signal(&second, "c", Signal::NotHelpful);
+        let labelled = log.labelled_since(DateTime::<Utc>::MIN_UTC);
+        assert_eq!(labelled.len(), 1);
+        assert_eq!(labelled[0].labels(), HashMap::from([("a".to_string(), 2), ("b".to_string(), 0)]));
+
+        log.record("deploy status", Vec::new());
+        assert_eq!(log.len(), 2);
+        assert!(!log.signal(&first, "a", Signal::Click));
+    }
+
+    #[tokio::test]
+    async fn test_tuner_proposes_and_applies() {
+        let log = Arc::new(QueryLog::new(100));
+        let retrieval = Arc::new(RwLock::new(RetrievalConfig::default()));
+        let config = AutoTunerConfig {
+            min_queries: 5,
+            ..AutoTunerConfig::default()
+        };
+        let tuner = AutoTuner::new(config.clone(), log.clone(), Arc::new(SplitRetriever), retrieval.clone());
+
+        keyword_clicks(&log, 4);
+        assert!(tuner.run_once().await.unwrap().is_none());
+
+        keyword_clicks(&log, 4);
+        let proposal = tuner.run_once().await.unwrap().unwrap();
+        assert!(proposal.proposed.vector_weight < 0.5);
+        assert_eq!(proposal.proposed.top_k, 3);
+        assert_eq!(proposal.metrics.mrr, 1.0);
+        assert!(proposal.baseline.mrr < 0.5);
+        assert!(!proposal.applied);
+        assert_eq!(retrieval.read().search, SearchParams::default());
+
+        assert_eq!(tuner.apply_latest(), Some(proposal.proposed));
+        assert_eq!(retrieval.read().search, proposal.proposed);
+        assert!(tuner.latest().unwrap().applied);
+        // Nothing beats the applied parameters
+        assert!(tuner.run_once().await.unwrap().is_none());
+
+        let retrieval = Arc::new(RwLock::new(RetrievalConfig::default()));
+        let config = AutoTunerConfig { auto_apply: true, ..config };
+        let tuner = AutoTuner::new(config, log, Arc::new(SplitRetriever), retrieval.clone());
+        assert!(tuner.run_once().await.unwrap().unwrap().applied);
+        assert!(retrieval.read().search.vector_weight < 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_retriever_uses_fusion_weights() {
+        async fn engine() -> HybridSearchEngine {
+            let provider = Arc::new(MockEmbeddingProvider::new(64));
+            let mut engine = HybridSearchEngine::new(HybridSearchConfig::default(), provider);
+            engine.index("doc1", "rotate the signing keys").await.unwrap();
+            engine.index("doc2", "rollback a failed deployment").await.unwrap();
+            engine.index("doc3", "scale the worker pool").await.unwrap();
+            engine
+        }
+
+        let mut params = SearchParams {
+            top_k: 1,
+            vector_weight: 0.0,
+            rerank_threshold: None,
+        };
+        let retriever = HybridRetriever::new(engine().await);
+        assert_eq!(retriever.retrieve("rollback deployment", &params).await.unwrap(), ids(&["doc2"]));
+
+        let reranked = HybridRetriever::new(engine().await)
+            .with_reranker(Arc::new(crate::reranking::CrossEncoderReranker::mock()), 10);
+        assert_eq!(reranked.retrieve("rollback deployment", &params).await.unwrap().len(), 1);
+        params.rerank_threshold = Some(1.01);
+        assert!(reranked.retrieve("rollback deployment", &params).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,641 @@
+//! Write-ahead log for memory mutations
+//!
+//! Records every mutation applied to a [`MemoryStore`] in an append-only,
+//! line-delimited JSON log. The log can be replayed to rebuild an in-memory
+//! tier after a crash and doubles as an audit trail of when context entered
+//! or left the system. Compaction rewrites the log as a snapshot of the live
+//! items so it does not grow without bound.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, MutexGuard, OwnedMutexGuard};
+use uuid::Uuid;
+
+use crate::filter::ContextFilter;
+use crate::memory::{MemoryItem, MemoryStore, MemoryTier};
+use crate::{ContextError, Result};
+
+/// Configuration for a write-ahead log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalConfig {
+    /// Path of the log file
+    pub path: PathBuf,
+
+    /// Number of entries appended since the last compaction that triggers
+    /// an automatic compaction (0 disables automatic compaction)
+    pub compaction_threshold: usize,
+
+    /// Flush appended entries to disk with fsync before acknowledging
+    pub sync_on_write: bool,
+}
+
+impl WalConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for WalConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("memory.wal"),
+            compaction_threshold: 10_000,
+            sync_on_write: true,
+        }
+    }
+}
+
+/// A single mutation recorded in the log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum WalOperation {
+    /// A new item was added
+    Add { item: MemoryItem },
+
+    /// An existing item was replaced
+    Update { item: MemoryItem },
+
+    /// An item was explicitly removed
+    Remove { id: Uuid },
+
+    /// Items were evicted to free up space
+    Evict { ids: Vec<Uuid> },
+
+    /// All items were cleared
+    Clear,
+}
+
+impl WalOperation {
+    /// Whether this operation touches the given item
+    pub fn affects(&self, id: &Uuid) -> bool {
+        match self {
+            WalOperation::Add { item } | WalOperation::Update { item } => item.metadata.id == *id,
+            WalOperation::Remove { id: removed } => removed == id,
+            WalOperation::Evict { ids } => ids.contains(id),
+            WalOperation::Clear => true,
+        }
+    }
+}
+
+/// An entry in the write-ahead log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalEntry {
+    /// Monotonically increasing sequence number
+    pub sequence: u64,
+
+    /// When the mutation was recorded
+    pub timestamp: DateTime<Utc>,
+
+    /// The mutation
+    #[serde(flatten)]
+    pub operation: WalOperation,
+}
+
+/// Append-only log of memory mutations backed by a file
+pub struct WriteAheadLog {
+    config: WalConfig,
+    writer: BufWriter<File>,
+    next_sequence: u64,
+    entries_since_compaction: usize,
+}
+
+impl WriteAheadLog {
+    /// Open (or create) the log at the configured path
+    pub fn open(config: WalConfig) -> Result<Self> {
+        if let Some(parent) = config.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent).map_err(storage_error)?;
+            }
+        }
+
+        truncate_torn_tail(&config.path)?;
+        let entries = read_entries(&config.path)?;
+        let next_sequence = entries.last().map(|e| e.sequence + 1).unwrap_or(0);
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)
+            .map_err(storage_error)?;
+
+        Ok(Self {
+            config,
+            writer: BufWriter::new(file),
+            next_sequence,
+            entries_since_compaction: entries.len(),
+        })
+    }
+
+    /// Append a mutation and return its sequence number
+    pub fn append(&mut self, operation: WalOperation) -> Result<u64> {
+        let entry = WalEntry {
+            sequence: self.next_sequence,
+            timestamp: Utc::now(),
+            operation,
+        };
+        self.write_entry(&entry)?;
+
+        self.next_sequence += 1;
+        self.entries_since_compaction += 1;
+        Ok(entry.sequence)
+    }
+
+    /// Read every entry currently in the log
+    pub fn entries(&self) -> Result<Vec<WalEntry>> {
+        read_entries(&self.config.path)
+    }
+
+    /// Entries recorded at or after the given time
+    pub fn entries_since(&self, since: DateTime<Utc>) -> Result<Vec<WalEntry>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter(|e| e.timestamp >= since)
+            .collect())
+    }
+
+    /// Audit history for a single item
+    pub fn history(&self, id: &Uuid) -> Result<Vec<WalEntry>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter(|e| e.operation.affects(id))
+            .collect())
+    }
+
+    /// Replay the log and return the live items keyed by ID
+    pub fn replay(&self) -> Result<HashMap<Uuid, MemoryItem>> {
+        let mut items = HashMap::new();
+
+        for entry in self.entries()? {
+            match entry.operation {
+                WalOperation::Add { item } | WalOperation::Update { item } => {
+                    items.insert(item.metadata.id, item);
+                }
+                WalOperation::Remove { id } => {
+                    items.remove(&id);
+                }
+                WalOperation::Evict { ids } => {
+                    for id in ids {
+                        items.remove(&id);
+                    }
+                }
+                WalOperation::Clear => items.clear(),
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Whether enough entries have accumulated to warrant compaction
+    pub fn needs_compaction(&self) -> bool {
+        self.config.compaction_threshold > 0
+            && self.entries_since_compaction >= self.config.compaction_threshold
+    }
+
+    /// Rewrite the log as one `Add` entry per live item.
+    ///
+    /// Compacted entries keep the item's creation time as their timestamp so
+    /// the audit trail still shows when the content first entered the system.
+    pub fn compact(&mut self, live_items: &[MemoryItem]) -> Result<()> {
+        self.writer.flush().map_err(storage_error)?;
+
+        let tmp_path = self.config.path.with_extension("wal.compact");
+        {
+            let tmp = File::create(&tmp_path).map_err(storage_error)?;
+            let mut tmp_writer = BufWriter::new(tmp);
+
+            let mut sorted: Vec<&MemoryItem> = live_items.iter().collect();
+            sorted.sort_by_key(|item| item.created_at);
+
+            for item in sorted {
+                let entry = WalEntry {
+                    sequence: self.next_sequence,
+                    timestamp: item.created_at,
+                    operation: WalOperation::Add { item: item.clone() },
+                };
+                serde_json::to_writer(&mut tmp_writer, &entry)?;
+                tmp_writer.write_all(b"\n").map_err(storage_error)?;
+                self.next_sequence += 1;
+            }
+
+            tmp_writer.flush().map_err(storage_error)?;
+            tmp_writer.get_ref().sync_all().map_err(storage_error)?;
+        }
+
+        fs::rename(&tmp_path, &self.config.path).map_err(storage_error)?;
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&self.config.path)
+            .map_err(storage_error)?;
+        self.writer = BufWriter::new(file);
+        self.entries_since_compaction = 0;
+
+        Ok(())
+    }
+
+    /// Path of the underlying log file
+    pub fn path(&self) -> &Path {
+        &self.config.path
+    }
+
+    fn write_entry(&mut self, entry: &WalEntry) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, entry)?;
+        self.writer.write_all(b"\n").map_err(storage_error)?;
+        self.writer.flush().map_err(storage_error)?;
+
+        if self.config.sync_on_write {
+            self.writer.get_ref().sync_data().map_err(storage_error)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn storage_error(e: std::io::Error) -> ContextError {
+    ContextError::StorageError(e.to_string())
+}
+
+/// Drop a partially written final line so new appends start on a clean line
+fn truncate_torn_tail(path: &Path) -> Result<()> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(storage_error(e)),
+    };
+
+    let valid_len = bytes
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map(|pos| pos + 1)
+        .unwrap_or(0);
+
+    if valid_len < bytes.len() {
+        tracing::warn!(
+            "Truncating {} torn bytes from WAL {}",
+            bytes.len() - valid_len,
+            path.display()
+        );
+        let file = OpenOptions::new().write(true).open(path).map_err(storage_error)?;
+        file.set_len(valid_len as u64).map_err(storage_error)?;
+    }
+
+    Ok(())
+}
+
+/// Read all entries from a log file, tolerating a torn final line
+fn read_entries(path: &Path) -> Result<Vec<WalEntry>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(storage_error(e)),
+    };
+
+    let lines: Vec<String> = BufReader::new(file)
+        .lines()
+        .collect::<std::io::Result<_>>()
+        .map_err(storage_error)?;
+    let last = lines.len().saturating_sub(1);
+
+    let mut entries = Vec::with_capacity(lines.len());
+    for (idx, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<WalEntry>(line) {
+            Ok(entry) => entries.push(entry),
+            // A crash mid-append can leave a partial last line; drop it
+            Err(e) if idx == last => {
+                tracing::warn!("Ignoring truncated WAL entry in {}: {}", path.display(), e);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// A [`MemoryStore`] wrapper that logs every mutation the wrapped store
+/// applied
+///
+/// Entries are appended only after the wrapped store succeeds, so a failed
+/// mutation is never replayed. Mutations hold the log's lock while they
+/// apply, so the log records them in the order the wrapped store saw them.
+/// Appends and compactions write and sync the file on a blocking thread
+/// rather than the async runtime's.
+pub struct WalMemoryStore<S: MemoryStore> {
+    inner: S,
+    wal: Arc<Mutex<WriteAheadLog>>,
+}
+
+impl<S: MemoryStore> WalMemoryStore<S> {
+    /// Wrap a store, replaying any existing log into it first
+    pub async fn open(inner: S, config: WalConfig) -> Result<Self> {
+        let wal = WriteAheadLog::open(config)?;
+
+        for item in wal.replay()?.into_values() {
+            inner.store(item).await?;
+        }
+
+        Ok(Self {
+            inner,
+            wal: Arc::new(Mutex::new(wal)),
+        })
+    }
+
+    /// Access the underlying log (e.g. for audits)
+    pub async fn wal(&self) -> MutexGuard<'_, WriteAheadLog> {
+        self.wal.lock().await
+    }
+
+    /// Access the wrapped store
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Compact the log down to the store's current contents
+    pub async fn compact(&self) -> Result<()> {
+        let wal = self.lock().await;
+        let items = self.inner.list().await?;
+        blocking(wal, move |wal| wal.compact(&items)).await?;
+        Ok(())
+    }
+
+    async fn lock(&self) -> OwnedMutexGuard<WriteAheadLog> {
+        self.wal.clone().lock_owned().await
+    }
+
+    /// Append `operation`, then compact the log if it is due, keeping the
+    /// log locked throughout
+    async fn append(
+        &self,
+        wal: OwnedMutexGuard<WriteAheadLog>,
+        operation: WalOperation,
+    ) -> Result<()> {
+        let wal = blocking(wal, move |wal| wal.append(operation)).await?;
+        if wal.needs_compaction() {
+            let items = self.inner.list().await?;
+            blocking(wal, move |wal| wal.compact(&items)).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Run `f` on the locked log on a blocking thread and hand the lock back
+async fn blocking<T>(
+    mut wal: OwnedMutexGuard<WriteAheadLog>,
+    f: impl FnOnce(&mut WriteAheadLog) -> Result<T> + Send + 'static,
+) -> Result<OwnedMutexGuard<WriteAheadLog>> {
+    tokio::task::spawn_blocking(move || f(&mut *wal).map(|_| wal))
+        .await
+        .map_err(|e| ContextError::StorageError(format!("WAL write failed: {}", e)))?
+}
+
+#[async_trait]
+impl<S: MemoryStore> MemoryStore for WalMemoryStore<S> {
+    async fn store(&self, item: MemoryItem) -> Result<()> {
+        let wal = self.lock().await;
+        self.inner.store(item.clone()).await?;
+        self.append(wal, WalOperation::Add { item }).await
+    }
+
+    async fn retrieve(&self, id: &Uuid) -> Result<Option<MemoryItem>> {
+        self.inner.retrieve(id).await
+    }
+
+    async fn list(&self) -> Result<Vec<MemoryItem>> {
+        self.inner.list().await
+    }
+
+    async fn list_matching(&self, filter: &ContextFilter) -> Result<Vec<MemoryItem>> {
+        self.inner.list_matching(filter).await
+    }
+
+    async fn take(&self, id: &Uuid) -> Result<Option<MemoryItem>> {
+        let wal = self.lock().await;
+        let item = self.inner.take(id).await?;
+        if item.is_some() {
+            self.append(wal, WalOperation::Remove { id: *id }).await?;
+        }
+        Ok(item)
+    }
+
+    async fn update(&self, item: MemoryItem) -> Result<()> {
+        let wal = self.lock().await;
+        self.inner.update(item.clone()).await?;
+        self.append(wal, WalOperation::Update { item }).await
+    }
+
+    async fn total_tokens(&self) -> Result<usize> {
+        self.inner.total_tokens().await
+    }
+
+    async fn count(&self) -> Result<usize> {
+        self.inner.count().await
+    }
+
+    async fn clear(&self) -> Result<()> {
+        let wal = self.lock().await;
+        self.inner.clear().await?;
+        self.append(wal, WalOperation::Clear).await
+    }
+
+    async fn get_by_tier(&self, tier: MemoryTier) -> Result<Vec<MemoryItem>> {
+        self.inner.get_by_tier(tier).await
+    }
+
+    async fn evict(&self, target_tokens: usize) -> Result<Vec<MemoryItem>> {
+        let wal = self.lock().await;
+        let evicted = self.inner.evict(target_tokens).await?;
+        if !evicted.is_empty() {
+            let ids = evicted.iter().map(|item| item.metadata.id).collect();
+            self.append(wal, WalOperation::Evict { ids }).await?;
+        }
+        Ok(evicted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{InMemoryStore, MemoryMetadata};
+
+    fn item(content: &str, tokens: usize) -> MemoryItem {
+        MemoryItem::new(
+            content.to_string(),
+            MemoryMetadata::new("test", "test"),
+            0.4,
+            tokens,
+        )
+    }
+
+    fn config(dir: &tempfile::TempDir) -> WalConfig {
+        WalConfig {
+            path: dir.path().join("short_term.wal"),
+            compaction_threshold: 0,
+            sync_on_write: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recover_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let kept = item("kept", 10);
+        let removed = item("removed", 10);
+        let kept_id = kept.metadata.id;
+
+        {
+            let store = WalMemoryStore::open(InMemoryStore::new(MemoryTier::ShortTerm), config(&dir))
+                .await
+                .unwrap();
+            store.store(kept.clone()).await.unwrap();
+            store.store(removed.clone()).await.unwrap();
+            store.remove(&removed.metadata.id).await.unwrap();
+
+            let mut updated = kept.clone();
+            updated.content = "kept and updated".to_string();
+            store.update(updated).await.unwrap();
+        }
+
+        let store = WalMemoryStore::open(InMemoryStore::new(MemoryTier::ShortTerm), config(&dir))
+            .await
+            .unwrap();
+        let items = store.list().await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].metadata.id, kept_id);
+        assert_eq!(items[0].content, "kept and updated");
+    }
+
+    /// A store that rejects every write
+    struct ReadOnlyStore;
+
+    #[async_trait]
+    impl MemoryStore for ReadOnlyStore {
+        async fn store(&self, _item: MemoryItem) -> Result<()> {
+            Err(ContextError::StorageError("read-only".to_string()))
+        }
+
+        async fn retrieve(&self, _id: &Uuid) -> Result<Option<MemoryItem>> {
+            Ok(None)
+        }
+
+        async fn list(&self) -> Result<Vec<MemoryItem>> {
+            Ok(Vec::new())
+        }
+
+        async fn take(&self, _id: &Uuid) -> Result<Option<MemoryItem>> {
+            Err(ContextError::StorageError("read-only".to_string()))
+        }
+
+        async fn update(&self, _item: MemoryItem) -> Result<()> {
+            Err(ContextError::StorageError("read-only".to_string()))
+        }
+
+        async fn total_tokens(&self) -> Result<usize> {
+            Ok(0)
+        }
+
+        async fn clear(&self) -> Result<()> {
+            Err(ContextError::StorageError("read-only".to_string()))
+        }
+
+        async fn get_by_tier(&self, _tier: MemoryTier) -> Result<Vec<MemoryItem>> {
+            Ok(Vec::new())
+        }
+
+        async fn evict(&self, _target_tokens: usize) -> Result<Vec<MemoryItem>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_mutations_are_not_logged() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = WalMemoryStore::open(ReadOnlyStore, config(&dir)).await.unwrap();
+        let rejected = item("rejected", 10);
+
+        assert!(store.store(rejected.clone()).await.is_err());
+        assert!(store.update(rejected.clone()).await.is_err());
+        assert!(store.remove(&rejected.metadata.id).await.is_err());
+        assert!(store.clear().await.is_err());
+
+        assert!(store.wal().await.entries().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_evictions_are_logged() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = WalMemoryStore::open(InMemoryStore::new(MemoryTier::ShortTerm), config(&dir))
+            .await
+            .unwrap();
+        store.store(item("a", 50)).await.unwrap();
+        store.store(item("b", 50)).await.unwrap();
+
+        let evicted = store.evict(50).await.unwrap();
+        assert_eq!(evicted.len(), 1);
+
+        let replayed = store.wal().await.replay().unwrap();
+        assert_eq!(replayed.len(), 1);
+        assert!(!replayed.contains_key(&evicted[0].metadata.id));
+    }
+
+    #[tokio::test]
+    async fn test_compaction_preserves_state_and_entry_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cfg = config(&dir);
+        cfg.compaction_threshold = 4;
+
+        let store = WalMemoryStore::open(InMemoryStore::new(MemoryTier::ShortTerm), cfg.clone())
+            .await
+            .unwrap();
+        let first = item("first", 10);
+        let first_id = first.metadata.id;
+        let created_at = first.created_at;
+
+        store.store(first.clone()).await.unwrap();
+        for _ in 0..3 {
+            store.update(first.clone()).await.unwrap();
+        }
+
+        // Threshold reached: the log now holds a single snapshot entry
+        let entries = store.wal().await.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].timestamp, created_at);
+
+        let history = store.wal().await.history(&first_id).unwrap();
+        assert_eq!(history.len(), 1);
+
+        drop(store);
+        let reopened = WriteAheadLog::open(cfg).unwrap();
+        assert!(reopened.replay().unwrap().contains_key(&first_id));
+    }
+
+    #[test]
+    fn test_truncated_tail_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = config(&dir);
+
+        let mut wal = WriteAheadLog::open(cfg.clone()).unwrap();
+        wal.append(WalOperation::Add { item: item("a", 1) }).unwrap();
+        drop(wal);
+
+        let mut file = OpenOptions::new().append(true).open(&cfg.path).unwrap();
+        file.write_all(b"{\"sequence\":1,\"timest").unwrap();
+
+        let mut wal = WriteAheadLog::open(cfg).unwrap();
+        assert_eq!(wal.entries().unwrap().len(), 1);
+
+        wal.append(WalOperation::Clear).unwrap();
+        assert_eq!(wal.entries().unwrap().len(), 2);
+    }
+}
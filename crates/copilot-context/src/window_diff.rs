@@ -0,0 +1,319 @@
+//! Context window diffs between conversation turns
+//!
+//! Captures a lightweight snapshot of each assembled context window and
+//! computes what changed from one turn to the next: which items were added,
+//! which were dropped (and why), and which switched to compressed content.
+//! This makes it possible to explain why the agent "forgot" something.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use uuid::Uuid;
+
+use crate::retrieval::{RetrievalResult, ScoredItem};
+
+/// Default number of snapshots retained per conversation
+pub const DEFAULT_MAX_SNAPSHOTS: usize = 20;
+
+/// An item as it appeared in an assembled context window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowEntry {
+    pub id: Uuid,
+    pub content_type: String,
+    pub source: String,
+    pub score: f64,
+    pub token_count: usize,
+    /// Whether the window used the compressed version of the content
+    pub compressed: bool,
+}
+
+impl From<&ScoredItem> for WindowEntry {
+    fn from(scored: &ScoredItem) -> Self {
+        Self {
+            id: scored.item.metadata.id,
+            content_type: scored.item.metadata.content_type.clone(),
+            source: scored.item.metadata.source.clone(),
+            score: scored.score,
+            token_count: scored.item.token_count,
+            compressed: scored.item.compressed_content.is_some(),
+        }
+    }
+}
+
+/// Snapshot of the context window assembled for a single turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextWindowSnapshot {
+    /// Turn number within the conversation (starting at 1)
+    pub turn: u64,
+    pub captured_at: DateTime<Utc>,
+    pub query: String,
+    /// Items included in the window
+    pub entries: Vec<WindowEntry>,
+    /// Items that were relevant but did not fit the token budget
+    pub over_budget: HashSet<Uuid>,
+    /// Every item that was considered for the window
+    pub candidates: HashSet<Uuid>,
+    pub total_tokens: usize,
+}
+
+impl ContextWindowSnapshot {
+    /// Capture a snapshot from a retrieval result
+    pub fn capture(turn: u64, query: impl Into<String>, result: &RetrievalResult) -> Self {
+        Self {
+            turn,
+            captured_at: Utc::now(),
+            query: query.into(),
+            entries: result.selected.iter().map(WindowEntry::from).collect(),
+            over_budget: result.rejected.iter().map(|s| s.item.metadata.id).collect(),
+            candidates: result.candidate_ids.iter().copied().collect(),
+            total_tokens: result.total_tokens,
+        }
+    }
+}
+
+/// Why an item left the context window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropReason {
+    /// Still relevant, but higher-scoring items used up the token budget
+    OutOfBudget,
+    /// Still stored, but scored below the relevance threshold for the new query
+    BelowRelevance,
+    /// No longer stored (evicted or deleted)
+    Removed,
+}
+
+/// An item that was in the previous window but not the next
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DroppedEntry {
+    #[serde(flatten)]
+    pub entry: WindowEntry,
+    pub reason: DropReason,
+}
+
+/// Changes between two context window snapshots
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextWindowDiff {
+    pub from_turn: u64,
+    pub to_turn: u64,
+    pub added: Vec<WindowEntry>,
+    pub dropped: Vec<DroppedEntry>,
+    /// Items present in both windows that switched to compressed content
+    pub compressed: Vec<WindowEntry>,
+    /// Number of items present in both windows
+    pub retained: usize,
+    /// Change in total tokens used by the window
+    pub token_delta: i64,
+}
+
+impl ContextWindowDiff {
+    /// Compute the diff from `previous` to `next`
+    pub fn between(previous: &ContextWindowSnapshot, next: &ContextWindowSnapshot) -> Self {
+        let before: HashMap<Uuid, &WindowEntry> =
+            previous.entries.iter().map(|e| (e.id, e)).collect();
+        let after: HashMap<Uuid, &WindowEntry> = next.entries.iter().map(|e| (e.id, e)).collect();
+
+        let mut added = Vec::new();
+        let mut compressed = Vec::new();
+        let mut retained = 0;
+
+        for entry in &next.entries {
+            match before.get(&entry.id) {
+                None => added.push(entry.clone()),
+                Some(prev) => {
+                    retained += 1;
+                    if !prev.compressed && entry.compressed {
+                        compressed.push(entry.clone());
+                    }
+                }
+            }
+        }
+
+        let dropped = previous
+            .entries
+            .iter()
+            .filter(|e| !after.contains_key(&e.id))
+            .map(|e| {
+                let reason = if next.over_budget.contains(&e.id) {
+                    DropReason::OutOfBudget
+                } else if next.candidates.contains(&e.id) {
+                    DropReason::BelowRelevance
+                } else {
+                    DropReason::Removed
+                };
+                DroppedEntry {
+                    entry: e.clone(),
+                    reason,
+                }
+            })
+            .collect();
+
+        Self {
+            from_turn: previous.turn,
+            to_turn: next.turn,
+            added,
+            dropped,
+            compressed,
+            retained,
+            token_delta: next.total_tokens as i64 - previous.total_tokens as i64,
+        }
+    }
+
+    /// Whether the window is unchanged
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.dropped.is_empty() && self.compressed.is_empty()
+    }
+}
+
+/// Keeps recent context window snapshots per conversation
+pub struct ContextWindowTracker {
+    snapshots: DashMap<String, VecDeque<ContextWindowSnapshot>>,
+    max_snapshots: usize,
+}
+
+impl ContextWindowTracker {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_MAX_SNAPSHOTS)
+    }
+
+    /// Create a tracker retaining at most `max_snapshots` per conversation
+    pub fn with_capacity(max_snapshots: usize) -> Self {
+        Self {
+            snapshots: DashMap::new(),
+            max_snapshots: max_snapshots.max(2),
+        }
+    }
+
+    /// Record the window assembled for the next turn, returning its turn number
+    pub fn record(&self, conversation_id: &str, query: &str, result: &RetrievalResult) -> u64 {
+        let mut history = self.snapshots.entry(conversation_id.to_string()).or_default();
+        let turn = history.back().map(|s| s.turn + 1).unwrap_or(1);
+
+        history.push_back(ContextWindowSnapshot::capture(turn, query, result));
+        while history.len() > self.max_snapshots {
+            history.pop_front();
+        }
+
+        turn
+    }
+
+    /// Snapshots currently retained for a conversation, oldest first
+    pub fn snapshots(&self, conversation_id: &str) -> Vec<ContextWindowSnapshot> {
+        self.snapshots
+            .get(conversation_id)
+            .map(|h| h.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Diff between two retained turns
+    pub fn diff(&self, conversation_id: &str, from_turn: u64, to_turn: u64) -> Option<ContextWindowDiff> {
+        let history = self.snapshots.get(conversation_id)?;
+        let from = history.iter().find(|s| s.turn == from_turn)?;
+        let to = history.iter().find(|s| s.turn == to_turn)?;
+        Some(ContextWindowDiff::between(from, to))
+    }
+
+    /// Diff between the two most recent turns
+    pub fn latest_diff(&self, conversation_id: &str) -> Option<ContextWindowDiff> {
+        let history = self.snapshots.get(conversation_id)?;
+        let len = history.len();
+        if len < 2 {
+            return None;
+        }
+        Some(ContextWindowDiff::between(&history[len - 2], &history[len - 1]))
+    }
+
+    /// Drop all snapshots for a conversation
+    pub fn clear(&self, conversation_id: &str) {
+        self.snapshots.remove(conversation_id);
+    }
+}
+
+impl Default for ContextWindowTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{MemoryItem, MemoryMetadata};
+
+    fn scored(tokens: usize) -> ScoredItem {
+        ScoredItem {
+            item: MemoryItem::new(
+                "content".to_string(),
+                MemoryMetadata::new("document", "test"),
+                0.5,
+                tokens,
+            ),
+            score: 0.5,
+            stale: None,
+        }
+    }
+
+    fn result(selected: &[&ScoredItem], rejected: &[&ScoredItem], candidates: &[&ScoredItem]) -> RetrievalResult {
+        RetrievalResult {
+            selected: selected.iter().map(|s| (*s).clone()).collect(),
+            rejected: rejected.iter().map(|s| (*s).clone()).collect(),
+            candidate_ids: candidates.iter().map(|s| s.item.metadata.id).collect(),
+            total_tokens: selected.iter().map(|s| s.item.token_count).sum(),
+            target_tokens: 1_000,
+            max_tokens: 1_000,
+        }
+    }
+
+    #[test]
+    fn test_drop_reasons() {
+        let (a, b, c, d) = (scored(10), scored(20), scored(30), scored(40));
+        let tracker = ContextWindowTracker::new();
+
+        tracker.record("conv", "first", &result(&[&a, &b, &c], &[], &[&a, &b, &c]));
+        // a: still selected; b: out of budget; c: filtered out; d: new
+        tracker.record("conv", "second", &result(&[&a, &d], &[&b], &[&a, &b, &c, &d]));
+
+        let diff = tracker.latest_diff("conv").unwrap();
+        assert_eq!((diff.from_turn, diff.to_turn), (1, 2));
+        assert_eq!(diff.retained, 1);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].id, d.item.metadata.id);
+        assert_eq!(diff.token_delta, 50 - 60);
+
+        let reason = |id: Uuid| diff.dropped.iter().find(|e| e.entry.id == id).unwrap().reason;
+        assert_eq!(reason(b.item.metadata.id), DropReason::OutOfBudget);
+        assert_eq!(reason(c.item.metadata.id), DropReason::BelowRelevance);
+    }
+
+    #[test]
+    fn test_removed_and_compressed() {
+        let a = scored(10);
+        let b = scored(10);
+        let mut a_compressed = a.clone();
+        a_compressed.item.compressed_content = Some("short".to_string());
+
+        let tracker = ContextWindowTracker::new();
+        tracker.record("conv", "q", &result(&[&a, &b], &[], &[&a, &b]));
+        tracker.record("conv", "q", &result(&[&a_compressed], &[], &[&a_compressed]));
+
+        let diff = tracker.diff("conv", 1, 2).unwrap();
+        assert_eq!(diff.compressed.len(), 1);
+        assert_eq!(diff.dropped.len(), 1);
+        assert_eq!(diff.dropped[0].reason, DropReason::Removed);
+    }
+
+    #[test]
+    fn test_retention_limit() {
+        let a = scored(10);
+        let tracker = ContextWindowTracker::with_capacity(3);
+        for _ in 0..5 {
+            tracker.record("conv", "q", &result(&[&a], &[], &[&a]));
+        }
+
+        let turns: Vec<u64> = tracker.snapshots("conv").iter().map(|s| s.turn).collect();
+        assert_eq!(turns, vec![3, 4, 5]);
+        assert!(tracker.diff("conv", 1, 5).is_none());
+        assert!(tracker.latest_diff("conv").unwrap().is_empty());
+    }
+}
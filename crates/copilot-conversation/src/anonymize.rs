@@ -0,0 +1,299 @@
+//! Transcript anonymization
+//!
+//! Replaces people's names, email addresses, IP addresses and identifiers
+//! in conversation messages with pseudonyms so transcripts can be shared
+//! for debugging or turned into eval cases. The same value always gets the
+//! same pseudonym, so a transcript still reads coherently, and the mapping
+//! back to the real values is a separate [`PseudonymMap`] that stays with
+//! whoever may see the originals.
+//!
+//! Names are found from a list of known names and from phrases that
+//! introduce one ("my name is", "I'm", "thanks,"); once seen, a name is
+//! replaced everywhere it appears.
+
+use crate::history::ConversationMessage;
+use crate::Result;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Original values and their pseudonyms, by kind
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PseudonymMap {
+    #[serde(default)]
+    pub names: BTreeMap<String, String>,
+    #[serde(default)]
+    pub emails: BTreeMap<String, String>,
+    #[serde(default)]
+    pub ips: BTreeMap<String, String>,
+    #[serde(default)]
+    pub ids: BTreeMap<String, String>,
+}
+
+impl PseudonymMap {
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Number of values mapped
+    pub fn len(&self) -> usize {
+        self.names.len() + self.emails.len() + self.ips.len() + self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The original value behind `pseudonym`
+    pub fn original(&self, pseudonym: &str) -> Option<&str> {
+        [&self.names, &self.emails, &self.ips, &self.ids]
+            .into_iter()
+            .flat_map(|map| map.iter())
+            .find(|(_, p)| p.as_str() == pseudonym)
+            .map(|(original, _)| original.as_str())
+    }
+}
+
+/// Replaces personal data in transcripts with consistent pseudonyms
+#[derive(Debug, Clone, Default)]
+pub struct Anonymizer {
+    map: PseudonymMap,
+}
+
+impl Anonymizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Continue from an earlier mapping so values keep their pseudonyms
+    /// across exports
+    pub fn with_mapping(mut self, map: PseudonymMap) -> Self {
+        self.map = map;
+        self
+    }
+
+    /// Names to replace wherever they appear, e.g. a tenant's user list
+    pub fn with_names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for name in names {
+            let name = name.into();
+            if !name.trim().is_empty() {
+                pseudonym(&mut self.map.names, name.trim(), |n| format!("Person {}", n));
+            }
+        }
+        self
+    }
+
+    pub fn mapping(&self) -> &PseudonymMap {
+        &self.map
+    }
+
+    pub fn into_mapping(self) -> PseudonymMap {
+        self.map
+    }
+
+    /// `text` with personal data replaced
+    pub fn anonymize(&mut self, text: &str) -> String {
+        let map = &mut self.map;
+
+        let text = email_pattern().replace_all(text, |caps: &Captures| {
+            pseudonym(&mut map.emails, &caps[0], |n| format!("user{}@example.com", n))
+        });
+        let text = uuid_pattern().replace_all(&text, |caps: &Captures| {
+            pseudonym(&mut map.ids, &caps[0].to_ascii_lowercase(), |n| {
+                format!("00000000-0000-4000-8000-{:012x}", n)
+            })
+        });
+        let text = ipv4_pattern().replace_all(&text, |caps: &Captures| {
+            if caps[0].split('.').any(|octet| octet.parse::<u8>().is_err()) {
+                return caps[0].to_string();
+            }
+            pseudonym(&mut map.ips, &caps[0], |n| {
+                format!("10.{}.{}.{}", (n >> 16) & 0xff, (n >> 8) & 0xff, n & 0xff)
+            })
+        });
+        let text = ipv6_pattern().replace_all(&text, |caps: &Captures| {
+            pseudonym(&mut map.ips, &caps[0].to_ascii_lowercase(), |n| format!("2001:db8::{:x}", n))
+        });
+        let text = id_pattern().replace_all(&text, |caps: &Captures| {
+            // Prefixed words without digits (auth-service) are not identifiers
+            if !caps[0].chars().any(|c| c.is_ascii_digit()) {
+                return caps[0].to_string();
+            }
+            pseudonym(&mut map.ids, &caps[0], |n| format!("id-{:04}", n))
+        });
+
+        for caps in introduced_name_pattern().captures_iter(&text) {
+            pseudonym(&mut map.names, &caps[1], |n| format!("Person {}", n));
+        }
+        match names_pattern(&map.names) {
+            Some(names) => names
+                .replace_all(&text, |caps: &Captures| map.names[&caps[0]].clone())
+                .into_owned(),
+            None => text.into_owned(),
+        }
+    }
+
+    /// Copies of `messages` with their content and metadata values
+    /// anonymized
+    pub fn anonymize_messages(&mut self, messages: &[ConversationMessage]) -> Vec<ConversationMessage> {
+        messages
+            .iter()
+            .map(|message| {
+                let mut message = message.clone();
+                message.content = self.anonymize(&message.content);
+                for value in message.metadata.values_mut() {
+                    *value = self.anonymize(value);
+                }
+                for part in &mut message.parts {
+                    part.map_text(&mut |text| self.anonymize(text));
+                }
+                message
+            })
+            .collect()
+    }
+}
+
+/// The pseudonym of `original`, allocating the next one with `make`
+fn pseudonym(map: &mut BTreeMap<String, String>, original: &str, make: impl Fn(usize) -> String) -> String {
+    if let Some(existing) = map.get(original) {
+        return existing.clone();
+    }
+    let pseudonym = make(map.len() + 1);
+    map.insert(original.to_string(), pseudonym.clone());
+    pseudonym
+}
+
+fn email_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap())
+}
+
+fn uuid_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b").unwrap()
+    })
+}
+
+fn ipv4_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\b(?:\d{1,3}\.){3}\d{1,3}\b").unwrap())
+}
+
+fn ipv6_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    // Full form, or compressed with `::`; bare `12:30:45` times do not match
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"(?i)\b(?:[0-9a-f]{1,4}:){7}[0-9a-f]{1,4}\b|\b(?:[0-9a-f]{1,4}:)+:(?:[0-9a-f]{1,4}(?::[0-9a-f]{1,4})*)?\b",
+        )
+        .unwrap()
+    })
+}
+
+fn id_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    // Prefixed identifiers such as usr_8f3k2j, acct-001234 or tok_live_4eC39H
+    PATTERN.get_or_init(|| Regex::new(r"\b[a-z]{2,8}(?:[_-][a-z]{2,8})?[_-][A-Za-z0-9]{6,}\b").unwrap())
+}
+
+fn introduced_name_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"(?:[Mm]y name is|I'm|I am|[Tt]his is|[Tt]hanks,|[Rr]egards,|[Cc]heers,)\s+([A-Z][a-z]+(?: [A-Z][a-z]+)?)\b",
+        )
+        .unwrap()
+    })
+}
+
+/// Whole-word alternation of the known names, longest first so a full
+/// name wins over a name it contains
+fn names_pattern(names: &BTreeMap<String, String>) -> Option<Regex> {
+    if names.is_empty() {
+        return None;
+    }
+    let mut names: Vec<&String> = names.keys().collect();
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    let alternation = names.iter().map(|name| regex::escape(name)).collect::<Vec<_>>().join("|");
+    Regex::new(&format!(r"\b(?:{})\b", alternation)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::MessageRole;
+    use crate::tool_transcript::MessagePart;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_pseudonyms_are_consistent() {
+        let mut anonymizer = Anonymizer::new();
+        let first = anonymizer.anonymize(
+            "Hi, I'm Dana Whitfield (dana@acme.io). Session 3f2b8c1e-9a4d-4e2f-b1c3-7d5e6f708192 \
+             fails from 172.16.4.20 for acct-009187 on auth-service at 12:30:45.",
+        );
+        assert_eq!(
+            first,
+            "Hi, I'm Person 1 (user1@example.com). Session 00000000-0000-4000-8000-000000000001 \
+             fails from 10.0.0.1 for id-0002 on auth-service at 12:30:45."
+        );
+
+        let second = anonymizer.anonymize("Dana Whitfield retried from 172.16.4.20 and fe80::1ff:fe23:4567:890a");
+        assert_eq!(second, "Person 1 retried from 10.0.0.1 and 2001:db8::2");
+        assert_eq!(anonymizer.mapping().original("user1@example.com"), Some("dana@acme.io"));
+    }
+
+    #[test]
+    fn test_known_names_and_saved_mapping() {
+        let mut anonymizer = Anonymizer::new().with_names(["Priya", "Priya Raman"]);
+        assert_eq!(
+            anonymizer.anonymize("Priya Raman asked Priya about 999.1.1.1"),
+            "Person 2 asked Person 1 about 999.1.1.1"
+        );
+
+        let path = std::env::temp_dir().join(format!("pseudonyms-{}.json", uuid::Uuid::new_v4()));
+        anonymizer.mapping().save(&path).unwrap();
+        let mut resumed = Anonymizer::new().with_mapping(PseudonymMap::load(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resumed.anonymize("Thanks, Priya"), "Thanks, Person 1");
+        assert_eq!(resumed.mapping().len(), 2);
+    }
+
+    #[test]
+    fn test_anonymize_messages() {
+        let message = ConversationMessage {
+            role: MessageRole::User,
+            content: "reach me at ops@corp.example".to_string(),
+            timestamp: chrono::Utc::now(),
+            token_count: 6,
+            metadata: HashMap::from([("reporter".to_string(), "ops@corp.example".to_string())]),
+            usage: None,
+            model: None,
+            parts: vec![MessagePart::ToolCall {
+                call_id: "call-1".to_string(),
+                tool: "email_send".to_string(),
+                arguments: serde_json::json!({"to": ["ops@corp.example"]}),
+            }],
+        };
+
+        let anonymized = Anonymizer::new().anonymize_messages(&[message]);
+        assert_eq!(anonymized[0].content, "reach me at user1@example.com");
+        assert_eq!(anonymized[0].metadata["reporter"], "user1@example.com");
+        let MessagePart::ToolCall { arguments, .. } = &anonymized[0].parts[0] else {
+            panic!("expected a tool call");
+        };
+        assert_eq!(arguments["to"][0], "user1@example.com");
+    }
+}
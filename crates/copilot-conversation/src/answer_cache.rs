@@ -0,0 +1,330 @@
+//! Caching of grounded answers
+//!
+//! Many users ask the same questions of the same documents. [`AnswerCache`]
+//! keeps each grounded answer with the embedding of its normalized question
+//! and the versions of the context items it was generated from, and serves
+//! it again for questions whose embeddings are close enough. Before an
+//! answer is served its sources are checked against the corpus: once any of
+//! them is re-ingested (a newer version of the document exists) or removed,
+//! the answer is dropped and regenerated. Served answers carry a note
+//! saying how current their sources are.
+//!
+//! Answers are shared only between callers asking in the same scope: the
+//! same tenant and persona, and principals that ACLs treat alike.
+
+use chrono::{DateTime, Utc};
+use copilot_context::freshness::{LatestVersions, DOCUMENT_KEY, VERSION_KEY};
+use copilot_context::retrieval::{RetrievalResult, ScoredItem};
+use copilot_context::{ContextEngine, ContextFilter, Embedding, EmbeddingProvider};
+use copilot_core::Principal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Answer cache limits
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AnswerCacheConfig {
+    /// Cosine similarity a question must reach to a cached one to be served
+    /// its answer
+    pub similarity_threshold: f32,
+    /// Answers kept; the oldest are evicted first
+    pub max_entries: usize,
+}
+
+impl Default for AnswerCacheConfig {
+    fn default() -> Self {
+        Self {
+            similarity_threshold: 0.95,
+            max_entries: 1000,
+        }
+    }
+}
+
+/// A context item an answer was generated from, as it was at the time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceVersion {
+    pub item_id: Uuid,
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// A cached answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedAnswer {
+    pub question: String,
+    pub answer: String,
+    pub sources: Vec<SourceVersion>,
+    /// Ingestion time of the newest source
+    pub sources_as_of: DateTime<Utc>,
+    pub cached_at: DateTime<Utc>,
+}
+
+impl CachedAnswer {
+    /// The answer with a note on how current its sources are
+    pub fn with_note(&self) -> String {
+        format!(
+            "{}\n\nNote: based on sources as of {}.",
+            self.answer,
+            self.sources_as_of.format("%Y-%m-%d %H:%M UTC")
+        )
+    }
+}
+
+/// Answer cache effectiveness
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnswerCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Answers dropped because a source changed
+    pub invalidations: u64,
+    pub entries: usize,
+}
+
+struct Entry {
+    id: u64,
+    scope: String,
+    embedding: Embedding,
+    answer: CachedAnswer,
+}
+
+/// Grounded answers keyed by question embedding and source versions
+pub struct AnswerCache {
+    embedder: Arc<dyn EmbeddingProvider>,
+    config: AnswerCacheConfig,
+    entries: Mutex<VecDeque<Entry>>,
+    next_id: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+/// Lowercased words of a question without surrounding punctuation
+fn normalize(question: &str) -> String {
+    question
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        dot / norm
+    } else {
+        0.0
+    }
+}
+
+impl AnswerCache {
+    pub fn new(embedder: Arc<dyn EmbeddingProvider>) -> Self {
+        Self::with_config(embedder, AnswerCacheConfig::default())
+    }
+
+    pub fn with_config(embedder: Arc<dyn EmbeddingProvider>, config: AnswerCacheConfig) -> Self {
+        Self {
+            embedder,
+            config,
+            entries: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &AnswerCacheConfig {
+        &self.config
+    }
+
+    /// Scope answers are shared in, for the current principal
+    pub fn scope(tenant_id: Option<&str>, persona_id: Option<&str>) -> String {
+        format!(
+            "{}\0{}\0{}",
+            tenant_id.unwrap_or_default(),
+            persona_id.unwrap_or_default(),
+            Principal::current().map(|p| p.access_key()).unwrap_or_default()
+        )
+    }
+
+    async fn embed(&self, question: &str) -> Option<Embedding> {
+        match self.embedder.embed(&normalize(question)).await {
+            Ok(embedding) => Some(embedding),
+            Err(e) => {
+                warn!("Could not embed question for the answer cache: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Answer to a question like `question` asked in `scope`, if its
+    /// sources are still current in `engine`
+    pub async fn get(&self, engine: &dyn ContextEngine, scope: &str, question: &str) -> Option<CachedAnswer> {
+        let found = match self.embed(question).await {
+            Some(embedding) => self
+                .lock()
+                .iter()
+                .filter(|entry| entry.scope == scope)
+                .map(|entry| (cosine(&embedding, &entry.embedding), entry))
+                .filter(|(similarity, _)| *similarity >= self.config.similarity_threshold)
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|(_, entry)| (entry.id, entry.answer.clone())),
+            None => None,
+        };
+        let Some((id, answer)) = found else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+
+        if !Self::is_current(engine, &answer.sources).await {
+            debug!("Sources of the cached answer to {:?} changed", answer.question);
+            self.lock().retain(|entry| entry.id != id);
+            self.invalidations.fetch_add(1, Ordering::Relaxed);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(answer)
+    }
+
+    /// Whether every source is still stored and is the newest version of
+    /// its document
+    async fn is_current(engine: &dyn ContextEngine, sources: &[SourceVersion]) -> bool {
+        let paths: BTreeSet<&str> = sources.iter().map(|s| s.source.as_str()).collect();
+        for path in paths {
+            let items = match engine.list_matching(&ContextFilter::new().with_source_prefix(path)).await {
+                Ok(items) => items,
+                Err(e) => {
+                    warn!("Could not check the sources of a cached answer: {}", e);
+                    return false;
+                }
+            };
+            let latest = LatestVersions::of(&items);
+            let current = sources.iter().filter(|s| s.source == path).all(|source| {
+                items
+                    .iter()
+                    .find(|item| item.metadata.id == source.item_id)
+                    .is_some_and(|item| latest.is_latest(item))
+            });
+            if !current {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Cache the answer to `question` generated from `context`; answers
+    /// without context are not grounded and are not cached
+    pub async fn insert(&self, scope: &str, question: &str, answer: &str, context: &RetrievalResult) {
+        if context.selected.is_empty() || self.config.max_entries == 0 {
+            return;
+        }
+        let Some(embedding) = self.embed(question).await else {
+            return;
+        };
+        let custom = |scored: &ScoredItem, key: &str| {
+            scored.item.metadata.custom.get(key).and_then(|v| v.as_str()).map(str::to_string)
+        };
+        let sources = context
+            .selected
+            .iter()
+            .map(|scored| SourceVersion {
+                item_id: scored.item.metadata.id,
+                source: scored.item.metadata.source.clone(),
+                document: custom(scored, DOCUMENT_KEY),
+                version: custom(scored, VERSION_KEY),
+            })
+            .collect();
+        let now = Utc::now();
+        let sources_as_of = context.selected.iter().map(|s| s.item.created_at).max().unwrap_or(now);
+
+        let mut entries = self.lock();
+        entries.retain(|entry| entry.scope != scope || entry.answer.question != question);
+        while entries.len() >= self.config.max_entries {
+            entries.pop_front();
+        }
+        entries.push_back(Entry {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            scope: scope.to_string(),
+            embedding,
+            answer: CachedAnswer {
+                question: question.to_string(),
+                answer: answer.to_string(),
+                sources,
+                sources_as_of,
+                cached_at: now,
+            },
+        });
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub fn stats(&self) -> AnswerCacheStats {
+        AnswerCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            entries: self.lock().len(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_context::{ContextEngineConfig, ContextEngineImpl, MemoryMetadata, MockEmbeddingProvider};
+
+    fn document(version: &str) -> MemoryMetadata {
+        let mut metadata = MemoryMetadata::new("document", "docs/refunds.md");
+        metadata.add_custom(DOCUMENT_KEY.to_string(), "refunds".into());
+        metadata.add_custom(VERSION_KEY.to_string(), version.into());
+        metadata
+    }
+
+    #[tokio::test]
+    async fn test_answers_are_served_until_a_source_is_reingested() {
+        let engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+        let content = "Refunds are issued within 14 days of a return";
+        engine.store(content.to_string(), document("v1"), 0.5).await.unwrap();
+        let cache = AnswerCache::new(Arc::new(MockEmbeddingProvider::new(32)));
+        let scope = AnswerCache::scope(Some("acme"), None);
+
+        let context = engine.retrieve("refunds issued").await.unwrap();
+        assert!(!context.selected.is_empty());
+        cache.insert(&scope, "How long do refunds take?", "Within 14 days.", &context).await;
+
+        // Served for the same question however it is written, and only in
+        // the same scope
+        let cached = cache.get(&engine, &scope, "how long do refunds take").await.unwrap();
+        assert_eq!(cached.sources[0].version.as_deref(), Some("v1"));
+        assert!(cached.with_note().starts_with("Within 14 days.\n\nNote: based on sources as of "));
+        assert!(cache.get(&engine, &AnswerCache::scope(Some("globex"), None), "How long do refunds take?").await.is_none());
+        assert!(cache.get(&engine, &scope, "Can I get a refund?").await.is_none());
+
+        // A new version of the cited document invalidates the answer
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        engine.store(content.replace("14", "30"), document("v2"), 0.5).await.unwrap();
+        assert!(cache.get(&engine, &scope, "How long do refunds take?").await.is_none());
+        assert_eq!(
+            cache.stats(),
+            AnswerCacheStats { hits: 1, misses: 3, invalidations: 1, entries: 0 }
+        );
+    }
+}
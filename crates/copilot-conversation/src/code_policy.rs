@@ -0,0 +1,586 @@
+//! License and package policy for generated code
+//!
+//! [`CodePolicyStage`] is a post-processing stage that checks the fenced
+//! code of each response against the policy of the session's tenant. It
+//! flags:
+//!
+//! - copyleft license text, such as a GPL header copied into a block;
+//! - blocks that closely match a reference snippet under a restrictive
+//!   license, by the share of their token shingles found in the snippet;
+//! - imports, dependencies and install commands naming a disallowed package.
+//!
+//! Depending on the policy, a response with findings is annotated with
+//! notes or withheld, and the decision is recorded in the
+//! [`CodePolicyLog`]. Tenants without a policy, and the default when none is
+//! configured, are not scanned.
+
+use crate::postprocess::{ResponseContext, ResponseStage};
+use crate::Result;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::{info, warn};
+
+/// Tokens per shingle when comparing code with reference snippets
+const SHINGLE_TOKENS: usize = 5;
+
+/// Decisions kept by default
+const DEFAULT_LOG_CAPACITY: usize = 1000;
+
+/// What to do with a response whose code breaks the policy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodePolicyAction {
+    /// Return the response with a note on each finding
+    #[default]
+    Annotate,
+    /// Withhold the response, saying why
+    Block,
+}
+
+/// A tenant's rules for generated code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodePolicy {
+    #[serde(default)]
+    pub action: CodePolicyAction,
+    /// Share (0-1) of a block's shingles found in a reference snippet above
+    /// which the block counts as a verbatim copy
+    #[serde(default = "default_similarity_threshold")]
+    pub similarity_threshold: f64,
+    /// Blocks shorter than this many non-blank lines are not compared with
+    /// reference snippets
+    #[serde(default = "default_min_block_lines")]
+    pub min_block_lines: usize,
+    /// Flag blocks that carry copyleft license text
+    #[serde(default = "default_true")]
+    pub flag_license_text: bool,
+    /// Packages generated code must not use, e.g. `left-pad` or `pycrypto`
+    #[serde(default)]
+    pub disallowed_packages: Vec<String>,
+}
+
+impl Default for CodePolicy {
+    fn default() -> Self {
+        Self {
+            action: CodePolicyAction::default(),
+            similarity_threshold: default_similarity_threshold(),
+            min_block_lines: default_min_block_lines(),
+            flag_license_text: true,
+            disallowed_packages: Vec::new(),
+        }
+    }
+}
+
+fn default_similarity_threshold() -> f64 {
+    0.8
+}
+
+fn default_min_block_lines() -> usize {
+    5
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Code under a license generated code must not reproduce
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceSnippet {
+    /// Where the code comes from, e.g. `readline/history.c`
+    pub name: String,
+    /// SPDX identifier, e.g. `GPL-3.0-or-later`
+    pub license: String,
+    pub code: String,
+}
+
+/// Code policies by tenant and the reference snippets they compare against
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CodePolicyConfig {
+    /// Policy of tenants without their own; unset leaves them unscanned
+    #[serde(default)]
+    pub default: Option<CodePolicy>,
+    #[serde(default)]
+    pub tenants: HashMap<String, CodePolicy>,
+    #[serde(default)]
+    pub references: Vec<ReferenceSnippet>,
+}
+
+impl CodePolicyConfig {
+    /// Read a configuration from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn for_tenant(&self, tenant_id: Option<&str>) -> Option<&CodePolicy> {
+        tenant_id
+            .and_then(|tenant_id| self.tenants.get(tenant_id))
+            .or(self.default.as_ref())
+    }
+}
+
+/// What a finding is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeFindingKind {
+    LicenseText,
+    VerbatimMatch,
+    DisallowedPackage,
+}
+
+/// A policy breach in one code block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeFinding {
+    pub kind: CodeFindingKind,
+    /// Position of the code block in the response, from 1
+    pub block: usize,
+    pub detail: String,
+    /// Share of the block found in the matched reference snippet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f64>,
+}
+
+/// What happened to a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodePolicyOutcome {
+    Allowed,
+    Annotated,
+    Blocked,
+}
+
+/// The policy's verdict on one response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodePolicyDecision {
+    pub session_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub outcome: CodePolicyOutcome,
+    pub findings: Vec<CodeFinding>,
+    pub decided_at: DateTime<Utc>,
+}
+
+/// Recent decisions on responses with findings
+#[derive(Debug)]
+pub struct CodePolicyLog {
+    decisions: Mutex<VecDeque<CodePolicyDecision>>,
+    capacity: usize,
+}
+
+impl Default for CodePolicyLog {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_LOG_CAPACITY)
+    }
+}
+
+impl CodePolicyLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            decisions: Mutex::new(VecDeque::new()),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn record(&self, decision: CodePolicyDecision) {
+        let mut decisions = self.decisions.lock().expect("code policy log poisoned");
+        if decisions.len() == self.capacity {
+            decisions.pop_front();
+        }
+        decisions.push_back(decision);
+    }
+
+    /// `tenant_id`'s decisions, newest first
+    pub fn recent(&self, tenant_id: &str, limit: usize) -> Vec<CodePolicyDecision> {
+        self.decisions
+            .lock()
+            .expect("code policy log poisoned")
+            .iter()
+            .rev()
+            .filter(|decision| decision.tenant_id.as_deref() == Some(tenant_id))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+/// A fenced code block of a response
+struct CodeBlock<'a> {
+    language: &'a str,
+    code: String,
+}
+
+fn code_blocks(response: &str) -> Vec<CodeBlock<'_>> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(&str, Vec<&str>)> = None;
+    for line in response.lines() {
+        if let Some(fence) = line.trim_start().strip_prefix("```") {
+            match open.take() {
+                Some((language, lines)) => blocks.push(CodeBlock { language, code: lines.join("\n") }),
+                None => open = Some((fence.trim(), Vec::new())),
+            }
+        } else if let Some((_, lines)) = &mut open {
+            lines.push(line);
+        }
+    }
+    if let Some((language, lines)) = open {
+        blocks.push(CodeBlock { language, code: lines.join("\n") });
+    }
+    blocks
+}
+
+fn shingles(code: &str) -> HashSet<u64> {
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    let token = TOKEN.get_or_init(|| Regex::new(r"[A-Za-z_][A-Za-z0-9_]*|\d+|\S").unwrap());
+    let tokens: Vec<&str> = token.find_iter(code).map(|m| m.as_str()).collect();
+    tokens
+        .windows(SHINGLE_TOKENS)
+        .map(|window| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            window.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect()
+}
+
+/// Packages a block imports, depends on or installs
+fn packages(block: &CodeBlock) -> Vec<String> {
+    static IMPORT: OnceLock<Regex> = OnceLock::new();
+    static INSTALL: OnceLock<Regex> = OnceLock::new();
+    static DEPENDENCY: OnceLock<Regex> = OnceLock::new();
+    let import = IMPORT.get_or_init(|| {
+        Regex::new(
+            r#"(?m)^\s*(?:(?:pub\s+)?use\s+([A-Za-z_]\w*)::|extern\s+crate\s+(\w+)|import\s+([\w.]+)|from\s+([\w.]+)\s+import\b)|require\(\s*['"]([^'"]+)['"]\s*\)|\bfrom\s+['"]([^'"]+)['"]"#,
+        )
+        .unwrap()
+    });
+    let install = INSTALL.get_or_init(|| {
+        Regex::new(r"(?m)\b(?:pip3?\s+install|npm\s+(?:install|i)|yarn\s+add|pnpm\s+add|cargo\s+add|go\s+get)\s+(.+)$")
+            .unwrap()
+    });
+    let dependency =
+        DEPENDENCY.get_or_init(|| Regex::new(r#"(?m)^\s*"?([A-Za-z0-9_.@/-]+)"?\s*[=:]\s*["{]"#).unwrap());
+
+    let mut found = Vec::new();
+    for caps in import.captures_iter(&block.code) {
+        if let Some(name) = caps.iter().skip(1).flatten().next() {
+            found.push(name.as_str().to_string());
+        }
+    }
+    for caps in install.captures_iter(&block.code) {
+        found.extend(
+            caps[1]
+                .split_whitespace()
+                .take_while(|arg| !matches!(*arg, "&&" | "|" | ";"))
+                .filter(|arg| !arg.starts_with('-'))
+                .map(str::to_string),
+        );
+    }
+    if matches!(block.language, "toml" | "json") {
+        found.extend(dependency.captures_iter(&block.code).map(|caps| caps[1].to_string()));
+    }
+    found
+}
+
+/// The package a name refers to: `requests` for `requests.adapters`,
+/// `@scope/pkg` for `@scope/pkg/sub`, `foo_bar` for `foo-bar==1.0`
+fn package_root(name: &str) -> String {
+    let name = name.trim_matches(['"', '\'']);
+    let name = name.split(['=', '<', '>', '~', '!', '[']).next().unwrap_or(name);
+    let root = match name.strip_prefix('@') {
+        Some(scoped) => {
+            let mut parts = scoped.split('/');
+            let scope = parts.next().unwrap_or_default();
+            let package = parts.next().unwrap_or_default().split('@').next().unwrap_or_default();
+            format!("@{}/{}", scope, package)
+        }
+        None => {
+            let name = name.split('@').next().unwrap_or(name);
+            // Go modules are named by their whole path
+            if name.contains('.') && name.contains('/') {
+                name.to_string()
+            } else {
+                name.split(['/', '.', ':']).next().unwrap_or(name).to_string()
+            }
+        }
+    };
+    root.to_ascii_lowercase().replace('-', "_")
+}
+
+/// Checks the code of responses against tenants' code policies
+pub struct CodePolicyStage {
+    config: CodePolicyConfig,
+    references: Vec<(usize, HashSet<u64>)>,
+    log: Arc<CodePolicyLog>,
+}
+
+impl CodePolicyStage {
+    pub fn new(config: CodePolicyConfig) -> Self {
+        let references = config
+            .references
+            .iter()
+            .enumerate()
+            .map(|(i, snippet)| (i, shingles(&snippet.code)))
+            .filter(|(_, shingles)| !shingles.is_empty())
+            .collect();
+        Self {
+            config,
+            references,
+            log: Arc::new(CodePolicyLog::new()),
+        }
+    }
+
+    /// Record decisions in `log`, e.g. one the API reports from
+    pub fn with_log(mut self, log: Arc<CodePolicyLog>) -> Self {
+        self.log = log;
+        self
+    }
+
+    pub fn log(&self) -> &Arc<CodePolicyLog> {
+        &self.log
+    }
+
+    /// Breaches of `policy` in `response`'s code blocks
+    pub fn scan(&self, response: &str, policy: &CodePolicy) -> Vec<CodeFinding> {
+        static LICENSE: OnceLock<Regex> = OnceLock::new();
+        let license = LICENSE.get_or_init(|| {
+            Regex::new(
+                r"(?i)GNU\s+(?:Lesser\s+|Library\s+|Affero\s+)?General\s+Public\s+License|SPDX-License-Identifier:\s*(?:A|L)?GPL[\w.+-]*|you\s+can\s+redistribute\s+it\s+and/or\s+modify",
+            )
+            .unwrap()
+        });
+        let disallowed: HashSet<String> = policy.disallowed_packages.iter().map(|p| package_root(p)).collect();
+
+        let mut findings = Vec::new();
+        for (i, block) in code_blocks(response).iter().enumerate() {
+            let number = i + 1;
+            if policy.flag_license_text {
+                if let Some(m) = license.find(&block.code) {
+                    findings.push(CodeFinding {
+                        kind: CodeFindingKind::LicenseText,
+                        block: number,
+                        detail: format!("carries copyleft license text (\"{}\")", m.as_str()),
+                        similarity: None,
+                    });
+                }
+            }
+
+            let lines = block.code.lines().filter(|line| !line.trim().is_empty()).count();
+            if lines >= policy.min_block_lines && !self.references.is_empty() {
+                let block_shingles = shingles(&block.code);
+                let best = self
+                    .references
+                    .iter()
+                    .map(|(i, reference)| {
+                        let shared = block_shingles.intersection(reference).count();
+                        (*i, shared as f64 / block_shingles.len().max(1) as f64)
+                    })
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+                if let Some((i, similarity)) = best.filter(|(_, similarity)| *similarity >= policy.similarity_threshold) {
+                    let snippet = &self.config.references[i];
+                    findings.push(CodeFinding {
+                        kind: CodeFindingKind::VerbatimMatch,
+                        block: number,
+                        detail: format!(
+                            "closely matches {} ({}, {:.0}% similar)",
+                            snippet.name,
+                            snippet.license,
+                            similarity * 100.0
+                        ),
+                        similarity: Some(similarity),
+                    });
+                }
+            }
+
+            let mut flagged = HashSet::new();
+            for package in packages(block) {
+                let root = package_root(&package);
+                if disallowed.contains(&root) && flagged.insert(root) {
+                    findings.push(CodeFinding {
+                        kind: CodeFindingKind::DisallowedPackage,
+                        block: number,
+                        detail: format!("uses disallowed package {}", package),
+                        similarity: None,
+                    });
+                }
+            }
+        }
+        findings
+    }
+}
+
+impl ResponseStage for CodePolicyStage {
+    fn name(&self) -> &str {
+        "code_policy"
+    }
+
+    fn process(&self, response: String, context: &ResponseContext) -> Result<String> {
+        let tenant_id = context.tenant_id.as_deref();
+        let Some(policy) = self.config.for_tenant(tenant_id) else {
+            return Ok(response);
+        };
+        let findings = self.scan(&response, policy);
+        if findings.is_empty() {
+            return Ok(response);
+        }
+
+        let notes: Vec<String> = findings
+            .iter()
+            .map(|finding| format!("- Code block {} {}.", finding.block, finding.detail))
+            .collect();
+        let (outcome, processed) = match policy.action {
+            CodePolicyAction::Annotate => (
+                CodePolicyOutcome::Annotated,
+                format!(
+                    "{}\n\nCode policy notes (check licensing and dependencies before using this code):\n{}",
+                    response,
+                    notes.join("\n")
+                ),
+            ),
+            CodePolicyAction::Block => (
+                CodePolicyOutcome::Blocked,
+                format!(
+                    "This response was withheld because its code breaks your organization's code policy:\n{}",
+                    notes.join("\n")
+                ),
+            ),
+        };
+
+        let tenant = tenant_id.unwrap_or("-");
+        match outcome {
+            CodePolicyOutcome::Blocked => {
+                warn!("Blocked response in session {} for tenant {}: {:?}", context.session_id, tenant, notes)
+            }
+            _ => info!("Annotated response in session {} for tenant {}: {:?}", context.session_id, tenant, notes),
+        }
+        self.log.record(CodePolicyDecision {
+            session_id: context.session_id.clone(),
+            tenant_id: tenant_id.map(str::to_string),
+            outcome,
+            findings,
+            decided_at: Utc::now(),
+        });
+        Ok(processed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const READLINE: &str = "int history_expand (char *hstring, char **output)
+{
+  register int j;
+  int i, r, l, passc, cc, modified, eindex, only_printing, dquote;
+  char *string;
+  /* The output string, and its length. */
+  int result_len;
+  char *result;
+  result_len = 256;
+  result = (char *)xmalloc (result_len);
+}";
+
+    fn stage(policy: CodePolicy) -> CodePolicyStage {
+        CodePolicyStage::new(CodePolicyConfig {
+            default: None,
+            tenants: HashMap::from([("acme".to_string(), policy)]),
+            references: vec![ReferenceSnippet {
+                name: "readline/histexpand.c".to_string(),
+                license: "GPL-3.0-or-later".to_string(),
+                code: READLINE.to_string(),
+            }],
+        })
+    }
+
+    fn context(tenant_id: Option<&str>) -> ResponseContext {
+        ResponseContext {
+            session_id: "s1".to_string(),
+            tenant_id: tenant_id.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_package_roots() {
+        assert_eq!(package_root("requests.adapters"), "requests");
+        assert_eq!(package_root("left-pad@1.3.0"), "left_pad");
+        assert_eq!(package_root("@babel/core/lib"), "@babel/core");
+        assert_eq!(package_root("PyCrypto==2.6"), "pycrypto");
+        assert_eq!(package_root("lodash/fp"), "lodash");
+    }
+
+    #[test]
+    fn test_annotates_verbatim_copies_and_disallowed_packages() {
+        let stage = stage(CodePolicy {
+            disallowed_packages: vec!["left-pad".to_string(), "pycrypto".to_string()],
+            ..Default::default()
+        });
+        let copied = READLINE.replace("256", "512");
+        let response = format!(
+            "Here you go:\n```c\n{}\n```\n```js\nconst pad = require('left-pad');\n```\n```bash\npip install requests PyCrypto==2.6\n```",
+            copied
+        );
+
+        let findings = stage.scan(&response, stage.config.for_tenant(Some("acme")).unwrap());
+        let kinds: Vec<_> = findings.iter().map(|f| (f.kind, f.block)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (CodeFindingKind::VerbatimMatch, 1),
+                (CodeFindingKind::DisallowedPackage, 2),
+                (CodeFindingKind::DisallowedPackage, 3),
+            ]
+        );
+        assert!(findings[0].similarity.unwrap() > 0.8);
+
+        let processed = stage.process(response.clone(), &context(Some("acme"))).unwrap();
+        assert!(processed.starts_with(&response));
+        assert!(processed.contains("- Code block 2 uses disallowed package left-pad."));
+
+        let decisions = stage.log().recent("acme", 10);
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].outcome, CodePolicyOutcome::Annotated);
+
+        // Tenants without a policy are not scanned
+        assert_eq!(stage.process(response.clone(), &context(Some("globex"))).unwrap(), response);
+    }
+
+    #[test]
+    fn test_blocks_license_text() {
+        let stage = stage(CodePolicy {
+            action: CodePolicyAction::Block,
+            ..Default::default()
+        });
+        let response = "```python\n# SPDX-License-Identifier: GPL-2.0-only\ndef f():\n    pass\n```\n```rust\nfn main() {}\n```";
+        let processed = stage.process(response.to_string(), &context(Some("acme"))).unwrap();
+        assert!(processed.starts_with("This response was withheld"));
+        assert!(processed.contains("Code block 1 carries copyleft license text"));
+        assert!(!processed.contains("def f()"));
+        assert_eq!(stage.log().recent("acme", 10)[0].outcome, CodePolicyOutcome::Blocked);
+
+        // Original code that only resembles a reference in passing is fine
+        let clean = "```c\nint main(void)\n{\n  int result_len = 0;\n  printf(\"%d\", result_len);\n  return 0;\n}\n```";
+        assert_eq!(stage.process(clean.to_string(), &context(Some("acme"))).unwrap(), clean);
+    }
+
+    #[test]
+    fn test_config_parsing() {
+        let config: CodePolicyConfig = serde_json::from_str(
+            r#"{
+                "default": {"disallowed_packages": ["left-pad"]},
+                "tenants": {"acme": {"action": "block", "similarity_threshold": 0.6}},
+                "references": [{"name": "x.c", "license": "GPL-2.0", "code": "int x;"}]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.for_tenant(Some("acme")).unwrap().action, CodePolicyAction::Block);
+        assert_eq!(config.for_tenant(Some("globex")).unwrap().disallowed_packages, vec!["left-pad"]);
+        assert_eq!(config.for_tenant(None).unwrap().min_block_lines, 5);
+    }
+}
@@ -0,0 +1,146 @@
+//! Side-by-side model comparisons
+//!
+//! A comparison sends one prompt to two models within a conversation and
+//! keeps both answers together with the user's preference, so model choices
+//! can be judged on real traffic.
+
+use crate::{usage::TokenUsage, ConversationError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// One model's answer in a comparison
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComparedResponse {
+    pub model: String,
+    pub response: String,
+    pub usage: TokenUsage,
+}
+
+/// A prompt answered by several models, and which answer the user preferred
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelComparison {
+    pub id: String,
+    pub session_id: String,
+    pub prompt: String,
+    pub responses: Vec<ComparedResponse>,
+    /// Model whose answer the user voted for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_model: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voted_at: Option<DateTime<Utc>>,
+}
+
+impl ModelComparison {
+    pub fn new(session_id: &str, prompt: &str, responses: Vec<ComparedResponse>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id: session_id.to_string(),
+            prompt: prompt.to_string(),
+            responses,
+            preferred_model: None,
+            created_at: Utc::now(),
+            voted_at: None,
+        }
+    }
+
+    /// Record a preference for one of the compared models; a later vote
+    /// replaces an earlier one
+    pub fn vote(&mut self, model: &str) -> Result<()> {
+        if !self.responses.iter().any(|r| r.model == model) {
+            return Err(ConversationError::InvalidMessage(format!(
+                "Model {} is not part of comparison {}",
+                model, self.id
+            )));
+        }
+        self.preferred_model = Some(model.to_string());
+        self.voted_at = Some(Utc::now());
+        Ok(())
+    }
+}
+
+/// How often a model was compared and preferred
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelPreferenceStats {
+    /// Comparisons the model took part in that received a vote
+    pub voted: usize,
+    /// Votes the model won
+    pub preferred: usize,
+}
+
+impl ModelPreferenceStats {
+    /// Share of voted comparisons the model won
+    pub fn win_rate(&self) -> f64 {
+        if self.voted == 0 {
+            0.0
+        } else {
+            self.preferred as f64 / self.voted as f64
+        }
+    }
+}
+
+/// Preference counts per model over voted comparisons
+pub fn preference_stats<'a>(
+    comparisons: impl IntoIterator<Item = &'a ModelComparison>,
+) -> HashMap<String, ModelPreferenceStats> {
+    let mut stats: HashMap<String, ModelPreferenceStats> = HashMap::new();
+    for comparison in comparisons {
+        let Some(preferred) = &comparison.preferred_model else {
+            continue;
+        };
+        for response in &comparison.responses {
+            let entry = stats.entry(response.model.clone()).or_default();
+            entry.voted += 1;
+            if &response.model == preferred {
+                entry.preferred += 1;
+            }
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comparison(models: [&str; 2]) -> ModelComparison {
+        ModelComparison::new(
+            "s1",
+            "Why is latency up?",
+            models
+                .iter()
+                .map(|model| ComparedResponse {
+                    model: model.to_string(),
+                    response: format!("{} says hi", model),
+                    usage: TokenUsage::default(),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_vote_must_name_a_compared_model() {
+        let mut c = comparison(["claude-3-opus", "gpt-4"]);
+        assert!(c.vote("claude-3-haiku").is_err());
+        assert!(c.preferred_model.is_none());
+
+        c.vote("gpt-4").unwrap();
+        assert_eq!(c.preferred_model.as_deref(), Some("gpt-4"));
+        assert!(c.voted_at.is_some());
+    }
+
+    #[test]
+    fn test_preference_stats() {
+        let mut first = comparison(["claude-3-opus", "gpt-4"]);
+        first.vote("claude-3-opus").unwrap();
+        let mut second = comparison(["claude-3-opus", "gpt-4o"]);
+        second.vote("gpt-4o").unwrap();
+        let unvoted = comparison(["claude-3-opus", "gpt-4"]);
+
+        let stats = preference_stats([&first, &second, &unvoted]);
+        assert_eq!(stats["claude-3-opus"], ModelPreferenceStats { voted: 2, preferred: 1 });
+        assert_eq!(stats["gpt-4"].win_rate(), 0.0);
+        assert_eq!(stats["gpt-4o"].win_rate(), 1.0);
+    }
+}
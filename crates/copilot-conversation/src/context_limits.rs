@@ -0,0 +1,340 @@
+//! Fitting retrieved context to the model's context window
+//!
+//! The context engine's token budget is configured once, but a turn may be
+//! answered by a model whose context window is smaller than that budget,
+//! e.g. after a per-message override or a failover to a local model.
+//! [`ContextLimits`] knows the windows of common models and, before the
+//! prompt is assembled, cuts the retrieved context down to what the window
+//! leaves after the rest of the prompt and the reply: it recompresses the
+//! selected passages and, when that is not enough, drops the lowest-scored
+//! ones. A model that still rejects the prompt as too long
+//! ([`ConversationError::TokenLimitExceeded`](crate::ConversationError))
+//! teaches the limits its real window, and the turn is cut again and
+//! retried instead of failing.
+//!
+//! Each cut is counted for `/metrics` and described by a
+//! [`ContextDownsize`] returned with the response, so callers can tell the
+//! answer was built from less context than usual.
+
+use copilot_context::retrieval::RetrievalResult;
+use copilot_context::{PromptCompressionConfig, PromptCompressor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tracing::info;
+
+/// Smallest share of each passage recompression keeps; beyond that,
+/// passages are dropped instead
+const MIN_COMPRESSION_RATIO: f64 = 0.1;
+
+/// How a turn's retrieved context was cut to fit the model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextDownsize {
+    pub model: String,
+    /// Context window of the model, in tokens
+    pub window: usize,
+    /// Tokens the retrieved context was cut to
+    pub budget: usize,
+    pub tokens_before: usize,
+    pub tokens_after: usize,
+    /// Passages dropped because recompressing them was not enough
+    pub items_dropped: usize,
+    /// Whether the model rejected the prompt before it was cut
+    #[serde(default)]
+    pub retried: bool,
+}
+
+impl ContextDownsize {
+    /// One line describing the cut, for response metadata
+    pub fn note(&self) -> String {
+        let mut note = format!(
+            "Context cut from {} to {} tokens to fit the {}-token window of {}",
+            self.tokens_before, self.tokens_after, self.window, self.model
+        );
+        if self.items_dropped > 0 {
+            let _ = write!(note, "; {} passages dropped", self.items_dropped);
+        }
+        note
+    }
+}
+
+/// How often context had to be cut to fit a model
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextFitStats {
+    /// Turns whose context was cut
+    pub downsizes: u64,
+    /// Turns retried after the model rejected their prompt as too long
+    pub retries: u64,
+    pub tokens_removed: u64,
+    pub items_dropped: u64,
+}
+
+impl ContextFitStats {
+    /// Render the counters in the Prometheus text exposition format
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        let metrics = [
+            ("context_downsizes_total", "Turns whose context was cut to fit the model", self.downsizes),
+            (
+                "context_length_retries_total",
+                "Turns retried after the model rejected their prompt as too long",
+                self.retries,
+            ),
+            ("context_downsize_tokens_removed_total", "Context tokens cut to fit the model", self.tokens_removed),
+            ("context_downsize_items_dropped_total", "Passages dropped to fit the model", self.items_dropped),
+        ];
+        for (name, help, value) in metrics {
+            let _ = writeln!(out, "# HELP {prefix}_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_{name} counter");
+            let _ = writeln!(out, "{prefix}_{name} {value}");
+        }
+        out
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    downsizes: AtomicU64,
+    retries: AtomicU64,
+    tokens_removed: AtomicU64,
+    items_dropped: AtomicU64,
+}
+
+/// Context windows of models, and the cuts made to fit them
+#[derive(Debug)]
+pub struct ContextLimits {
+    windows: HashMap<String, usize>,
+    /// Tokens kept free for the reply
+    reserve_tokens: usize,
+    /// Windows learned from models rejecting prompts, by model
+    learned: Mutex<HashMap<String, usize>>,
+    counters: Counters,
+}
+
+impl ContextLimits {
+    /// Limits knowing no model's window, keeping `reserve_tokens` free for
+    /// the reply
+    pub fn new(reserve_tokens: usize) -> Self {
+        Self {
+            windows: HashMap::new(),
+            reserve_tokens,
+            learned: Mutex::new(HashMap::new()),
+            counters: Counters::default(),
+        }
+    }
+
+    /// Set the context window of a model, in tokens
+    pub fn with_model(mut self, model: impl Into<String>, window: usize) -> Self {
+        self.windows.insert(model.into(), window);
+        self
+    }
+
+    pub fn reserve_tokens(&self) -> usize {
+        self.reserve_tokens
+    }
+
+    /// Context window of a model, matching dated variants such as
+    /// `claude-3-sonnet-20240229` to their `claude-3-sonnet` entry; the
+    /// smaller of the configured and the learned window
+    pub fn window(&self, model: &str) -> Option<usize> {
+        let configured = self.windows.get(model).copied().or_else(|| {
+            self.windows
+                .iter()
+                .filter(|(name, _)| model.starts_with(name.as_str()))
+                .max_by_key(|(name, _)| name.len())
+                .map(|(_, window)| *window)
+        });
+        let learned = self.learned.lock().unwrap_or_else(|e| e.into_inner()).get(model).copied();
+        match (configured, learned) {
+            (Some(configured), Some(learned)) => Some(configured.min(learned)),
+            (window, None) | (None, window) => window,
+        }
+    }
+
+    /// Remember that `model` rejected a prompt over `window` tokens
+    pub fn learn(&self, model: &str, window: usize) {
+        let mut learned = self.learned.lock().unwrap_or_else(|e| e.into_inner());
+        let known = learned.entry(model.to_string()).or_insert(window);
+        *known = (*known).min(window);
+    }
+
+    /// Cut `result` to what `model`'s window leaves after `overhead`
+    /// tokens of prompt and the reply; `None` if it already fits or the
+    /// window is unknown
+    pub fn fit(&self, model: &str, query: &str, overhead: usize, result: &mut RetrievalResult) -> Option<ContextDownsize> {
+        let window = self.window(model)?;
+        let budget = window.saturating_sub(self.reserve_tokens + overhead);
+        if result.total_tokens <= budget {
+            return None;
+        }
+        Some(self.shrink(model, window, budget, query, result, false))
+    }
+
+    /// Cut `result` again after `model` rejected a prompt as over its
+    /// `window`; token estimates were evidently off, so at least a quarter
+    /// of the context goes
+    pub fn refit(
+        &self,
+        model: &str,
+        window: usize,
+        query: &str,
+        overhead: usize,
+        result: &mut RetrievalResult,
+    ) -> ContextDownsize {
+        self.learn(model, window);
+        self.counters.retries.fetch_add(1, Ordering::Relaxed);
+        let window = self.window(model).unwrap_or(window);
+        let budget = window
+            .saturating_sub(self.reserve_tokens + overhead)
+            .min(result.total_tokens * 3 / 4);
+        self.shrink(model, window, budget, query, result, true)
+    }
+
+    fn shrink(
+        &self,
+        model: &str,
+        window: usize,
+        budget: usize,
+        query: &str,
+        result: &mut RetrievalResult,
+        retried: bool,
+    ) -> ContextDownsize {
+        let tokens_before = result.total_tokens;
+        let ratio = (budget as f64 / tokens_before.max(1) as f64).clamp(MIN_COMPRESSION_RATIO, 1.0);
+        let config = PromptCompressionConfig { ratio, min_words: 0 };
+        if let Ok(compressor) = PromptCompressor::new(config) {
+            compressor.compress(query, result);
+        }
+
+        // Drop the lowest-scored passages compression could not fit
+        let mut items_dropped = 0;
+        while result.total_tokens > budget {
+            let lowest = result
+                .selected
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.score.total_cmp(&b.score))
+                .map(|(index, _)| index);
+            let Some(lowest) = lowest else {
+                break;
+            };
+            let dropped = result.selected.remove(lowest);
+            result.total_tokens = result.total_tokens.saturating_sub(dropped.item.token_count);
+            result.rejected.push(dropped);
+            items_dropped += 1;
+        }
+        result.max_tokens = result.max_tokens.min(budget);
+        result.target_tokens = result.target_tokens.min(budget);
+
+        let downsize = ContextDownsize {
+            model: model.to_string(),
+            window,
+            budget,
+            tokens_before,
+            tokens_after: result.total_tokens,
+            items_dropped,
+            retried,
+        };
+        info!("{}", downsize.note());
+        self.counters.downsizes.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .tokens_removed
+            .fetch_add(tokens_before.saturating_sub(result.total_tokens) as u64, Ordering::Relaxed);
+        self.counters.items_dropped.fetch_add(items_dropped as u64, Ordering::Relaxed);
+        downsize
+    }
+
+    pub fn stats(&self) -> ContextFitStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        ContextFitStats {
+            downsizes: load(&self.counters.downsizes),
+            retries: load(&self.counters.retries),
+            tokens_removed: load(&self.counters.tokens_removed),
+            items_dropped: load(&self.counters.items_dropped),
+        }
+    }
+}
+
+impl Default for ContextLimits {
+    /// Windows of common models, keeping 4096 tokens free for the reply
+    fn default() -> Self {
+        Self::new(4_096)
+            .with_model("claude-3", 200_000)
+            .with_model("gpt-4", 8_192)
+            .with_model("gpt-4-turbo", 128_000)
+            .with_model("gpt-4o", 128_000)
+            .with_model("gpt-3.5-turbo", 16_385)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_context::retrieval::ScoredItem;
+    use copilot_context::{MemoryItem, MemoryMetadata};
+
+    fn result(passages: &[(f64, usize)]) -> RetrievalResult {
+        let selected: Vec<ScoredItem> = passages
+            .iter()
+            .map(|(score, tokens)| ScoredItem {
+                item: MemoryItem::new(
+                    "deploy the billing service with helm ".repeat(tokens / 6),
+                    MemoryMetadata::new("document", "wiki"),
+                    0.5,
+                    *tokens,
+                ),
+                score: *score,
+                stale: None,
+            })
+            .collect();
+        RetrievalResult {
+            total_tokens: selected.iter().map(|scored| scored.item.token_count).sum(),
+            selected,
+            rejected: Vec::new(),
+            candidate_ids: Vec::new(),
+            target_tokens: 160_000,
+            max_tokens: 200_000,
+        }
+    }
+
+    #[test]
+    fn test_window_lookup_and_learning() {
+        let limits = ContextLimits::default();
+        assert_eq!(limits.window("claude-3-sonnet-20240229"), Some(200_000));
+        assert_eq!(limits.window("gpt-4o-mini"), Some(128_000));
+        assert_eq!(limits.window("llama-3-8b"), None);
+
+        limits.learn("llama-3-8b", 8_000);
+        limits.learn("llama-3-8b", 9_000);
+        assert_eq!(limits.window("llama-3-8b"), Some(8_000));
+    }
+
+    #[test]
+    fn test_context_is_cut_to_the_window() {
+        let limits = ContextLimits::new(1_000).with_model("small", 4_000);
+        let mut fits = result(&[(0.9, 600)]);
+        assert!(limits.fit("small", "deploy", 500, &mut fits).is_none());
+
+        let mut context = result(&[(0.9, 3_000), (0.2, 3_000)]);
+        let downsize = limits.fit("small", "deploy", 500, &mut context).unwrap();
+        assert_eq!((downsize.window, downsize.budget, downsize.tokens_before), (4_000, 2_500, 6_000));
+        assert!(context.total_tokens <= 2_500);
+        assert_eq!(downsize.tokens_after, context.total_tokens);
+        assert!(!downsize.retried);
+
+        // A rejected prompt is cut further and the window remembered
+        let mut context = result(&[(0.9, 3_000), (0.2, 3_000)]);
+        let downsize = limits.refit("small", 3_000, "deploy", 500, &mut context);
+        assert!(downsize.retried && downsize.budget <= 1_500);
+        assert!(context.total_tokens <= 1_500);
+        assert_eq!(limits.window("small"), Some(3_000));
+
+        let stats = limits.stats();
+        assert_eq!((stats.downsizes, stats.retries), (2, 1));
+        assert!(stats
+            .render_prometheus("copilot")
+            .contains("copilot_context_length_retries_total 1"));
+    }
+}
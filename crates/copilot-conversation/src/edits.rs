@@ -0,0 +1,233 @@
+//! Code edits proposed in assistant responses
+//!
+//! When the assistant answers with unified diffs (usually inside ```` ```diff ````
+//! fences), [`extract_edits`] turns them into [`FileEdit`]s: one per file,
+//! anchored by path and hunk line numbers so clients such as `copilot apply`
+//! can preview them and detect conflicts with the local tree.
+
+use serde::{Deserialize, Serialize};
+
+/// One `@@ -a,b +c,d @@` section of a unified diff
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffHunk {
+    /// First line of the hunk in the original file (1-based)
+    pub old_start: usize,
+    pub old_lines: usize,
+    /// First line of the hunk in the edited file (1-based)
+    pub new_start: usize,
+    pub new_lines: usize,
+    /// Text after the closing `@@`, typically the enclosing function
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub section: String,
+    /// Hunk body, each line prefixed with `' '`, `'-'` or `'+'`
+    pub lines: Vec<String>,
+}
+
+/// Changes to a single file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileEdit {
+    /// Path relative to the project root
+    pub path: String,
+    /// The file does not exist yet
+    #[serde(default)]
+    pub created: bool,
+    /// The file is removed
+    #[serde(default)]
+    pub deleted: bool,
+    pub hunks: Vec<DiffHunk>,
+    /// The unified diff for this file as it appeared in the response
+    pub diff: String,
+}
+
+/// Extract every file edit from a response; later edits to the same file are
+/// kept as separate entries in the order they appear
+pub fn extract_edits(content: &str) -> Vec<FileEdit> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut edits = Vec::new();
+    let mut i = 0;
+
+    while i + 1 < lines.len() {
+        let (Some(old), Some(new)) = (
+            lines[i].strip_prefix("--- "),
+            lines[i + 1].strip_prefix("+++ "),
+        ) else {
+            i += 1;
+            continue;
+        };
+        let (old, new) = (diff_path(old), diff_path(new));
+        let start = i;
+        i += 2;
+
+        let mut hunks = Vec::new();
+        while let Some(header) = lines.get(i).and_then(|l| parse_hunk_header(l)) {
+            i += 1;
+            let (hunk, consumed) = read_hunk(header, &lines[i..]);
+            i += consumed;
+            hunks.push(hunk);
+        }
+
+        let path = match (&old, &new) {
+            (_, Some(new)) => new.clone(),
+            (Some(old), None) => old.clone(),
+            (None, None) => continue,
+        };
+        if hunks.is_empty() {
+            continue;
+        }
+
+        let mut diff = lines[start..i].join("\n").trim_end().to_string();
+        diff.push('\n');
+        edits.push(FileEdit {
+            path,
+            created: old.is_none(),
+            deleted: new.is_none(),
+            hunks,
+            diff,
+        });
+    }
+
+    edits
+}
+
+/// Strip the `a/`/`b/` prefix and any trailing timestamp; `None` for `/dev/null`
+fn diff_path(raw: &str) -> Option<String> {
+    let path = raw.split('\t').next().unwrap_or(raw).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// Hunk start lines; the counts are recomputed from the body
+struct HunkHeader {
+    old_start: usize,
+    new_start: usize,
+    section: String,
+}
+
+/// Parse `@@ -12,5 +12,7 @@ fn main()`
+fn parse_hunk_header(line: &str) -> Option<HunkHeader> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (ranges, section) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let (old_start, _) = parse_range(old)?;
+    let (new_start, _) = parse_range(new)?;
+    Some(HunkHeader {
+        old_start,
+        new_start,
+        section: section.trim().to_string(),
+    })
+}
+
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// Read a hunk body, returning it with the number of lines consumed
+///
+/// Models often miscount hunk lengths, so the body runs until the first line
+/// that cannot belong to it and the counts are recomputed from what was read.
+fn read_hunk(header: HunkHeader, lines: &[&str]) -> (DiffHunk, usize) {
+    let mut body = Vec::new();
+    let mut consumed = 0;
+    for line in lines {
+        match line.chars().next() {
+            Some(' ' | '-' | '+') if !line.starts_with("--- ") && !line.starts_with("+++ ") => {
+                body.push(line.to_string());
+            }
+            // Blank context lines frequently lose their leading space
+            None => body.push(" ".to_string()),
+            Some('\\') => {}
+            _ => break,
+        }
+        consumed += 1;
+    }
+
+    // A blank line after the hunk separates it from the surrounding prose
+    while body.last().is_some_and(|l| l == " ") {
+        body.pop();
+    }
+
+    let old_lines = body.iter().filter(|l| !l.starts_with('+')).count();
+    let new_lines = body.iter().filter(|l| !l.starts_with('-')).count();
+    let hunk = DiffHunk {
+        old_start: header.old_start,
+        old_lines,
+        new_start: header.new_start,
+        new_lines,
+        section: header.section,
+        lines: body,
+    };
+    (hunk, consumed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_edits_from_fenced_diffs() {
+        let response = "\
+Here is the fix:
+
+```diff
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,4 +1,4 @@ pub fn add
+ pub fn add(a: i32, b: i32) -> i32 {
+-    a - b
++    a + b
+ }
+
+```
+
+And a new test file:
+
+```diff
+--- /dev/null
++++ b/tests/add.rs
+@@ -0,0 +1,2 @@
++#[test]
++fn adds() { assert_eq!(demo::add(1, 2), 3); }
+```
+";
+        let edits = extract_edits(response);
+        assert_eq!(edits.len(), 2);
+
+        let lib = &edits[0];
+        assert_eq!(lib.path, "src/lib.rs");
+        assert!(!lib.created && !lib.deleted);
+        assert_eq!(lib.hunks.len(), 1);
+        let hunk = &lib.hunks[0];
+        assert_eq!((hunk.old_start, hunk.old_lines, hunk.new_lines), (1, 3, 3));
+        assert_eq!(hunk.section, "pub fn add");
+        assert_eq!(hunk.lines[1], "-    a - b");
+        assert!(lib
+            .diff
+            .starts_with("--- a/src/lib.rs\n+++ b/src/lib.rs\n@@"));
+
+        let test = &edits[1];
+        assert_eq!(test.path, "tests/add.rs");
+        assert!(test.created);
+        assert_eq!(test.hunks[0].new_lines, 2);
+    }
+
+    #[test]
+    fn test_deleted_file_and_plain_prose() {
+        assert!(extract_edits("No code changes needed.\n--- just a separator").is_empty());
+
+        let edits = extract_edits("--- a/old.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-gone\n");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].path, "old.txt");
+        assert!(edits[0].deleted);
+        assert_eq!(edits[0].hunks[0].lines, vec!["-gone".to_string()]);
+    }
+}
@@ -0,0 +1,697 @@
+//! End-to-end evaluation of agent answers
+//!
+//! An [`EvalSuite`] holds questions together with the criteria a good answer
+//! meets. Each question is answered by a full pipeline (retrieval and
+//! generation, see [`AnswerPipeline`]) and the answer is graded by a
+//! [`RubricJudge`], which asks a judge model whether every criterion holds.
+//! Judgments are cached, so re-running a suite only pays for answers that
+//! changed. [`EvalRunner::compare`] runs the suite against two
+//! configurations and reports the per-case score deltas, so prompt or
+//! retrieval changes (such as a new prompt compression ratio) can be
+//! checked before they ship.
+
+use crate::manager::MessageRequest;
+use crate::{ConversationError, ConversationManager, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+/// A criterion a good answer meets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalCriterion {
+    pub description: String,
+    /// Relative weight of the criterion in the case score
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+impl EvalCriterion {
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            weight: default_weight(),
+        }
+    }
+
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+/// A question and the criteria its answer is graded on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalCase {
+    pub id: String,
+    pub question: String,
+    pub criteria: Vec<EvalCriterion>,
+}
+
+impl EvalCase {
+    pub fn new(id: impl Into<String>, question: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            question: question.into(),
+            criteria: Vec::new(),
+        }
+    }
+
+    pub fn with_criterion(mut self, criterion: EvalCriterion) -> Self {
+        self.criteria.push(criterion);
+        self
+    }
+}
+
+/// A set of evaluation cases
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalSuite {
+    pub name: String,
+    pub cases: Vec<EvalCase>,
+    /// Score from which a case counts as passed
+    #[serde(default = "default_pass_threshold")]
+    pub pass_threshold: f64,
+}
+
+fn default_pass_threshold() -> f64 {
+    0.7
+}
+
+impl EvalSuite {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            cases: Vec::new(),
+            pass_threshold: default_pass_threshold(),
+        }
+    }
+
+    pub fn with_case(mut self, case: EvalCase) -> Self {
+        self.cases.push(case);
+        self
+    }
+
+    pub fn with_pass_threshold(mut self, threshold: f64) -> Self {
+        self.pass_threshold = threshold;
+        self
+    }
+
+    /// Parse a suite from JSON and validate it
+    pub fn from_json(json: &str) -> Result<Self> {
+        let suite: Self = serde_json::from_str(json)?;
+        suite.validate()?;
+        Ok(suite)
+    }
+
+    /// Check that case ids are unique and every case can be scored
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.pass_threshold) {
+            return Err(ConversationError::EvalError(format!(
+                "Pass threshold {} is outside 0..=1",
+                self.pass_threshold
+            )));
+        }
+        let mut seen = std::collections::HashSet::new();
+        for case in &self.cases {
+            if !seen.insert(case.id.as_str()) {
+                return Err(ConversationError::EvalError(format!("Duplicate case id {}", case.id)));
+            }
+            if case.criteria.is_empty() {
+                return Err(ConversationError::EvalError(format!("Case {} has no criteria", case.id)));
+            }
+            if case.criteria.iter().any(|c| c.weight.is_nan() || c.weight <= 0.0) {
+                return Err(ConversationError::EvalError(format!(
+                    "Case {} has a criterion without a positive weight",
+                    case.id
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Answers a question end to end, as a user of the agent would get it
+#[async_trait]
+pub trait AnswerPipeline: Send + Sync {
+    async fn answer(&self, question: &str) -> Result<String>;
+}
+
+#[async_trait]
+impl AnswerPipeline for ConversationManager {
+    /// Answers each question in a fresh session so cases do not share
+    /// conversation context
+    async fn answer(&self, question: &str) -> Result<String> {
+        let session = self.create_session(None, None).await?;
+        let response = self
+            .process_message(MessageRequest {
+                session_id: session.id,
+                message: question.to_string(),
+                metadata: HashMap::new(),
+                model: None,
+            })
+            .await?;
+        Ok(response.response)
+    }
+}
+
+/// The model grading answers
+#[async_trait]
+pub trait JudgeModel: Send + Sync {
+    /// Model name, part of the judgment cache key
+    fn model(&self) -> &str;
+
+    async fn complete(&self, prompt: &str) -> Result<String>;
+}
+
+/// The judge's verdict on one criterion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CriterionVerdict {
+    pub criterion: String,
+    pub passed: bool,
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// The judge's grading of one answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Judgment {
+    pub verdicts: Vec<CriterionVerdict>,
+    /// Weighted share of criteria met, 0..=1
+    pub score: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawVerdicts {
+    verdicts: Vec<RawVerdict>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawVerdict {
+    criterion: usize,
+    passed: bool,
+    #[serde(default)]
+    reason: String,
+}
+
+/// Grades answers against a case's criteria with a judge model, caching
+/// judgments by model, question, criteria and answer
+pub struct RubricJudge {
+    model: Box<dyn JudgeModel>,
+    cache: RwLock<HashMap<String, Judgment>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl RubricJudge {
+    pub fn new(model: impl JudgeModel + 'static) -> Self {
+        Self {
+            model: Box::new(model),
+            cache: RwLock::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Start from judgments saved by an earlier run (see [`Self::cached`])
+    pub fn with_cache(mut self, judgments: HashMap<String, Judgment>) -> Self {
+        self.cache = RwLock::new(judgments);
+        self
+    }
+
+    /// Cached judgments by key, for saving between runs
+    pub async fn cached(&self) -> HashMap<String, Judgment> {
+        self.cache.read().await.clone()
+    }
+
+    /// Cache hits and misses so far
+    pub fn cache_stats(&self) -> (usize, usize) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+
+    /// Grade `answer` to `case`
+    pub async fn judge(&self, case: &EvalCase, answer: &str) -> Result<Judgment> {
+        let key = self.cache_key(case, answer);
+        if let Some(judgment) = self.cache.read().await.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(judgment.clone());
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let response = self.model.complete(&Self::prompt(case, answer)).await?;
+        let judgment = Self::parse(case, &response)?;
+        debug!("Judged case {}: score {:.2}", case.id, judgment.score);
+
+        self.cache.write().await.insert(key, judgment.clone());
+        Ok(judgment)
+    }
+
+    fn cache_key(&self, case: &EvalCase, answer: &str) -> String {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.model.model().hash(&mut hasher);
+        case.question.hash(&mut hasher);
+        for criterion in &case.criteria {
+            criterion.description.hash(&mut hasher);
+            criterion.weight.to_bits().hash(&mut hasher);
+        }
+        answer.hash(&mut hasher);
+        format!("{:x}", hasher.finish())
+    }
+
+    /// The grading prompt sent to the judge model
+    pub fn prompt(case: &EvalCase, answer: &str) -> String {
+        let mut prompt = String::from(
+            "You are grading an observability assistant's answer. For each numbered \
+             criterion, decide whether the answer meets it.\n\n",
+        );
+        let _ = writeln!(prompt, "Question:\n{}\n", case.question);
+        let _ = writeln!(prompt, "Answer:\n{}\n", answer);
+        prompt.push_str("Criteria:\n");
+        for (i, criterion) in case.criteria.iter().enumerate() {
+            let _ = writeln!(prompt, "{}. {}", i + 1, criterion.description);
+        }
+        prompt.push_str(
+            "\nRespond with JSON only, in the form \
+             {\"verdicts\": [{\"criterion\": 1, \"passed\": true, \"reason\": \"...\"}]}, \
+             with one verdict per criterion.",
+        );
+        prompt
+    }
+
+    /// Read the judge's verdicts from its response; text around the JSON
+    /// object is ignored
+    pub fn parse(case: &EvalCase, response: &str) -> Result<Judgment> {
+        let json = match (response.find('{'), response.rfind('}')) {
+            (Some(start), Some(end)) if start < end => &response[start..=end],
+            _ => {
+                return Err(ConversationError::EvalError(format!(
+                    "Judge response for case {} contains no JSON",
+                    case.id
+                )))
+            }
+        };
+        let raw: RawVerdicts = serde_json::from_str(json).map_err(|e| {
+            ConversationError::EvalError(format!("Unreadable judge response for case {}: {}", case.id, e))
+        })?;
+
+        let mut verdicts = Vec::with_capacity(case.criteria.len());
+        let (mut met, mut total) = (0.0, 0.0);
+        for (i, criterion) in case.criteria.iter().enumerate() {
+            let verdict = raw.verdicts.iter().find(|v| v.criterion == i + 1).ok_or_else(|| {
+                ConversationError::EvalError(format!(
+                    "Judge gave no verdict on criterion {} of case {}",
+                    i + 1,
+                    case.id
+                ))
+            })?;
+            total += criterion.weight;
+            if verdict.passed {
+                met += criterion.weight;
+            }
+            verdicts.push(CriterionVerdict {
+                criterion: criterion.description.clone(),
+                passed: verdict.passed,
+                reason: verdict.reason.clone(),
+            });
+        }
+
+        Ok(Judgment {
+            verdicts,
+            score: if total > 0.0 { met / total } else { 0.0 },
+        })
+    }
+}
+
+/// Outcome of one case in a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseResult {
+    pub case_id: String,
+    /// The pipeline's answer, absent if it failed
+    pub answer: Option<String>,
+    /// Why the pipeline failed to answer
+    pub error: Option<String>,
+    pub judgment: Option<Judgment>,
+    pub score: f64,
+    pub passed: bool,
+}
+
+/// A suite run against one configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalRun {
+    pub suite: String,
+    pub config: String,
+    pub cases: Vec<CaseResult>,
+}
+
+impl EvalRun {
+    pub fn mean_score(&self) -> f64 {
+        if self.cases.is_empty() {
+            return 0.0;
+        }
+        self.cases.iter().map(|c| c.score).sum::<f64>() / self.cases.len() as f64
+    }
+
+    pub fn pass_rate(&self) -> f64 {
+        if self.cases.is_empty() {
+            return 0.0;
+        }
+        self.cases.iter().filter(|c| c.passed).count() as f64 / self.cases.len() as f64
+    }
+
+    pub fn case(&self, case_id: &str) -> Option<&CaseResult> {
+        self.cases.iter().find(|c| c.case_id == case_id)
+    }
+}
+
+/// Score change of one case between two configurations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseDelta {
+    pub case_id: String,
+    pub baseline_score: f64,
+    pub candidate_score: f64,
+    pub baseline_passed: bool,
+    pub candidate_passed: bool,
+}
+
+impl CaseDelta {
+    pub fn delta(&self) -> f64 {
+        self.candidate_score - self.baseline_score
+    }
+}
+
+/// Comparison of a suite run against a baseline and a candidate configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalReport {
+    pub baseline: EvalRun,
+    pub candidate: EvalRun,
+    pub cases: Vec<CaseDelta>,
+}
+
+impl EvalReport {
+    pub fn new(baseline: EvalRun, candidate: EvalRun) -> Self {
+        let cases = baseline
+            .cases
+            .iter()
+            .filter_map(|b| {
+                candidate.case(&b.case_id).map(|c| CaseDelta {
+                    case_id: b.case_id.clone(),
+                    baseline_score: b.score,
+                    candidate_score: c.score,
+                    baseline_passed: b.passed,
+                    candidate_passed: c.passed,
+                })
+            })
+            .collect();
+        Self {
+            baseline,
+            candidate,
+            cases,
+        }
+    }
+
+    /// Mean score change, candidate minus baseline
+    pub fn score_delta(&self) -> f64 {
+        self.candidate.mean_score() - self.baseline.mean_score()
+    }
+
+    /// Cases the candidate scores lower on
+    pub fn regressions(&self) -> Vec<&CaseDelta> {
+        self.cases.iter().filter(|c| c.delta() < 0.0).collect()
+    }
+
+    /// Cases the candidate scores higher on
+    pub fn improvements(&self) -> Vec<&CaseDelta> {
+        self.cases.iter().filter(|c| c.delta() > 0.0).collect()
+    }
+
+    /// Markdown summary with a row per case
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Eval: {}\n", self.baseline.suite);
+        let _ = writeln!(out, "| Config | Mean score | Pass rate |");
+        let _ = writeln!(out, "|--------|-----------:|----------:|");
+        for run in [&self.baseline, &self.candidate] {
+            let _ = writeln!(
+                out,
+                "| {} | {:.3} | {:.1}% |",
+                run.config,
+                run.mean_score(),
+                run.pass_rate() * 100.0
+            );
+        }
+        let _ = writeln!(
+            out,
+            "\n{} improved, {} regressed, mean score change {:+.3}\n",
+            self.improvements().len(),
+            self.regressions().len(),
+            self.score_delta()
+        );
+        let _ = writeln!(
+            out,
+            "| Case | {} | {} | Change |",
+            self.baseline.config, self.candidate.config
+        );
+        let _ = writeln!(out, "|------|---:|---:|---:|");
+        for case in &self.cases {
+            let _ = writeln!(
+                out,
+                "| {} | {:.3} | {:.3} | {:+.3} |",
+                case.case_id,
+                case.baseline_score,
+                case.candidate_score,
+                case.delta()
+            );
+        }
+        out
+    }
+}
+
+/// Runs evaluation suites through answer pipelines and grades the answers
+pub struct EvalRunner {
+    judge: RubricJudge,
+}
+
+impl EvalRunner {
+    pub fn new(judge: RubricJudge) -> Self {
+        Self { judge }
+    }
+
+    pub fn judge(&self) -> &RubricJudge {
+        &self.judge
+    }
+
+    /// Answer and grade every case of `suite` with `pipeline`
+    ///
+    /// A case the pipeline fails to answer scores 0; a judge failure fails
+    /// the run since its scores would not be comparable.
+    pub async fn run(&self, suite: &EvalSuite, config: &str, pipeline: &dyn AnswerPipeline) -> Result<EvalRun> {
+        suite.validate()?;
+        info!("Running eval suite {} with config {}", suite.name, config);
+
+        let mut cases = Vec::with_capacity(suite.cases.len());
+        for case in &suite.cases {
+            let result = match pipeline.answer(&case.question).await {
+                Ok(answer) => {
+                    let judgment = self.judge.judge(case, &answer).await?;
+                    CaseResult {
+                        case_id: case.id.clone(),
+                        score: judgment.score,
+                        passed: judgment.score >= suite.pass_threshold,
+                        answer: Some(answer),
+                        error: None,
+                        judgment: Some(judgment),
+                    }
+                }
+                Err(e) => CaseResult {
+                    case_id: case.id.clone(),
+                    answer: None,
+                    error: Some(e.to_string()),
+                    judgment: None,
+                    score: 0.0,
+                    passed: false,
+                },
+            };
+            cases.push(result);
+        }
+
+        Ok(EvalRun {
+            suite: suite.name.clone(),
+            config: config.to_string(),
+            cases,
+        })
+    }
+
+    /// Run `suite` against a baseline and a candidate configuration
+    pub async fn compare(
+        &self,
+        suite: &EvalSuite,
+        baseline: (&str, &dyn AnswerPipeline),
+        candidate: (&str, &dyn AnswerPipeline),
+    ) -> Result<EvalReport> {
+        let baseline = self.run(suite, baseline.0, baseline.1).await?;
+        let candidate = self.run(suite, candidate.0, candidate.1).await?;
+        let report = EvalReport::new(baseline, candidate);
+        info!(
+            "Eval suite {}: {} vs {}, mean score change {:+.3}",
+            suite.name,
+            report.baseline.config,
+            report.candidate.config,
+            report.score_delta()
+        );
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// Answers with a fixed prefix, failing questions containing "fail"
+    struct PrefixPipeline(&'static str);
+
+    #[async_trait]
+    impl AnswerPipeline for PrefixPipeline {
+        async fn answer(&self, question: &str) -> Result<String> {
+            if question.contains("fail") {
+                return Err(ConversationError::ContextError("retrieval down".to_string()));
+            }
+            Ok(format!("{} answer to {}", self.0, question))
+        }
+    }
+
+    /// Passes every criterion for answers marked "good", counting calls
+    struct KeywordJudge(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl JudgeModel for KeywordJudge {
+        fn model(&self) -> &str {
+            "keyword-judge"
+        }
+
+        async fn complete(&self, prompt: &str) -> Result<String> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let passed = prompt.contains("good answer");
+            let criteria = prompt.lines().filter(|l| l.starts_with(|c: char| c.is_ascii_digit())).count();
+            let verdicts: Vec<_> = (1..=criteria)
+                .map(|i| serde_json::json!({ "criterion": i, "passed": passed || i == 1 }))
+                .collect();
+            Ok(format!("Verdicts: {}", serde_json::json!({ "verdicts": verdicts })))
+        }
+    }
+
+    fn suite() -> EvalSuite {
+        let case = |id: &str, question: &str| {
+            EvalCase::new(id, question)
+                .with_criterion(EvalCriterion::new("Names the service"))
+                .with_criterion(EvalCriterion::new("Cites a metric").with_weight(3.0))
+        };
+        EvalSuite::new("smoke")
+            .with_case(case("latency", "Why is checkout slow?"))
+            .with_case(case("outage", "Why did payments fail?"))
+    }
+
+    #[test]
+    fn test_suite_validation() {
+        assert!(suite().validate().is_ok());
+        assert!(suite().with_case(EvalCase::new("latency", "again")).validate().is_err());
+        assert!(EvalSuite::new("empty").with_case(EvalCase::new("a", "q")).validate().is_err());
+
+        let parsed = EvalSuite::from_json(
+            r#"{"name": "s", "cases": [{"id": "a", "question": "q", "criteria": [{"description": "d"}]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.pass_threshold, 0.7);
+        assert_eq!(parsed.cases[0].criteria[0].weight, 1.0);
+    }
+
+    #[test]
+    fn test_parse_weights_verdicts() {
+        let case = &suite().cases[0];
+        let judgment = RubricJudge::parse(
+            case,
+            r#"```json {"verdicts": [{"criterion": 2, "passed": true}, {"criterion": 1, "passed": false, "reason": "no service"}]} ```"#,
+        )
+        .unwrap();
+        assert_eq!(judgment.score, 0.75);
+        assert_eq!(judgment.verdicts[0].reason, "no service");
+
+        assert!(RubricJudge::parse(case, r#"{"verdicts": [{"criterion": 1, "passed": true}]}"#).is_err());
+        assert!(RubricJudge::parse(case, "looks fine").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_compare_configurations_with_cached_judgments() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let runner = EvalRunner::new(RubricJudge::new(KeywordJudge(calls.clone())));
+        let suite = suite();
+
+        let report = runner
+            .compare(&suite, ("baseline", &PrefixPipeline("bad")), ("candidate", &PrefixPipeline("good")))
+            .await
+            .unwrap();
+
+        let outage = report.baseline.case("outage").unwrap();
+        assert_eq!(outage.score, 0.0);
+        assert!(outage.error.as_deref().unwrap().contains("retrieval down"));
+
+        let latency = &report.cases[0];
+        assert_eq!(latency.baseline_score, 0.25);
+        assert_eq!(latency.candidate_score, 1.0);
+        assert!(!latency.baseline_passed && latency.candidate_passed);
+        assert_eq!(report.improvements().len(), 1);
+        assert!(report.regressions().is_empty());
+        assert!(report.to_markdown().contains("| latency | 0.250 | 1.000 | +0.750 |"));
+
+        // Re-grading an unchanged answer is served from the cache
+        let rerun = runner.run(&suite, "candidate", &PrefixPipeline("good")).await.unwrap();
+        assert_eq!(rerun.mean_score(), report.candidate.mean_score());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(runner.judge().cache_stats(), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_conversation_manager_answers_cases() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+
+        let runner = EvalRunner::new(RubricJudge::new(KeywordJudge(Arc::new(AtomicUsize::new(0)))));
+        let run = runner.run(&suite(), "default", &manager).await.unwrap();
+        assert_eq!(run.cases.len(), 2);
+        assert!(run.cases.iter().all(|c| c.answer.is_some()));
+    }
+
+    #[tokio::test]
+    async fn test_compare_with_compressed_context() {
+        use copilot_context::{
+            ContextEngineConfig, ContextEngineImpl, ContextPrefetcher, PromptCompressionConfig, PromptCompressor,
+        };
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let baseline = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine.clone());
+        let compressor = Arc::new(PromptCompressor::new(PromptCompressionConfig::default()).unwrap());
+        let candidate = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine.clone())
+            .with_prefetcher(Arc::new(
+                ContextPrefetcher::new(context_engine).with_prompt_compressor(compressor),
+            ));
+
+        let runner = EvalRunner::new(RubricJudge::new(KeywordJudge(Arc::new(AtomicUsize::new(0)))));
+        let report = runner
+            .compare(&suite(), ("uncompressed", &baseline), ("compressed-0.5", &candidate))
+            .await
+            .unwrap();
+        assert_eq!(report.candidate.config, "compressed-0.5");
+        assert!(report.regressions().is_empty());
+    }
+}
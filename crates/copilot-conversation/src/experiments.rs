@@ -0,0 +1,761 @@
+//! A/B experiments over prompts, models and retrieval settings
+//!
+//! An [`Experiment`] splits conversations between [`Variant`]s, each of which
+//! may replace the system prompt, the model or how much retrieved context is
+//! kept. Only tenants enrolled in an experiment take part. A session is
+//! assigned a variant by hashing the experiment and session IDs, so every
+//! turn of a conversation, on any replica and after a restart, gets the same
+//! variant without the assignment being stored.
+//!
+//! [`Experiments`] records each answered turn's latency, cost and
+//! groundedness, and the user's feedback, per variant. Its
+//! [`results`](Experiments::results) compare every variant with the first,
+//! the control, and test whether each difference is significant.
+
+use crate::{ConversationError, Result};
+use chrono::{DateTime, Utc};
+use copilot_context::retrieval::RetrievalResult;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tracing::info;
+
+/// Sessions whose assignment is remembered for feedback; the oldest are
+/// forgotten first
+const MAX_TRACKED_SESSIONS: usize = 50_000;
+
+/// Placeholder in a variant's prompt replaced with the session's own prompt
+pub const SYSTEM_PROMPT_PLACEHOLDER: &str = "{system_prompt}";
+
+/// Changes a variant makes to the retrieved context
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetrievalOverrides {
+    /// Most context items kept, best scoring first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<usize>,
+    /// Items scoring below this are dropped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_score: Option<f64>,
+}
+
+impl RetrievalOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.max_items.is_none() && self.min_score.is_none()
+    }
+
+    /// Drop the selected items these overrides exclude, moving them to the
+    /// rejected items
+    pub fn apply(&self, result: &mut RetrievalResult) {
+        if self.is_empty() {
+            return;
+        }
+        result.selected.sort_by(|a, b| b.score.total_cmp(&a.score));
+        let mut kept = Vec::with_capacity(result.selected.len());
+        for scored in result.selected.drain(..) {
+            let below = self.min_score.is_some_and(|min| scored.score < min);
+            let over = self.max_items.is_some_and(|max| kept.len() >= max);
+            if below || over {
+                result.rejected.push(scored);
+            } else {
+                kept.push(scored);
+            }
+        }
+        result.total_tokens = kept.iter().map(|scored| scored.item.token_count).sum();
+        result.selected = kept;
+    }
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// One arm of an experiment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Variant {
+    pub name: String,
+    /// Share of sessions assigned, relative to the other variants' weights
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// System prompt; `{system_prompt}` is replaced with the session's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_template: Option<String>,
+    /// Model to answer with instead of the session's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "RetrievalOverrides::is_empty")]
+    pub retrieval: RetrievalOverrides,
+}
+
+impl Variant {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            weight: 1,
+            prompt_template: None,
+            model: None,
+            retrieval: RetrievalOverrides::default(),
+        }
+    }
+
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_prompt_template(mut self, template: impl Into<String>) -> Self {
+        self.prompt_template = Some(template.into());
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn with_retrieval(mut self, retrieval: RetrievalOverrides) -> Self {
+        self.retrieval = retrieval;
+        self
+    }
+
+    /// The system prompt for a session whose own prompt is `system_prompt`
+    pub fn system_prompt(&self, system_prompt: &str) -> String {
+        match &self.prompt_template {
+            Some(template) => template.replace(SYSTEM_PROMPT_PLACEHOLDER, system_prompt),
+            None => system_prompt.to_string(),
+        }
+    }
+}
+
+/// What an experiment tests and which variants it splits sessions between
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentSpec {
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    /// Variants, the control first
+    pub variants: Vec<Variant>,
+    /// Tenants taking part from the start
+    #[serde(default)]
+    pub tenants: Vec<String>,
+}
+
+impl ExperimentSpec {
+    fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| Err(ConversationError::InvalidExperiment(format!("{}: {}", self.id, reason)));
+        if self.id.trim().is_empty() {
+            return Err(ConversationError::InvalidExperiment("experiment ID is empty".to_string()));
+        }
+        if self.variants.len() < 2 {
+            return invalid("an experiment needs at least two variants");
+        }
+        if self.variants.iter().any(|variant| variant.name.trim().is_empty()) {
+            return invalid("variant names must not be empty");
+        }
+        for (i, variant) in self.variants.iter().enumerate() {
+            if self.variants[..i].iter().any(|other| other.name == variant.name) {
+                return invalid(&format!("variant {} is defined twice", variant.name));
+            }
+        }
+        if self.variants.iter().all(|variant| variant.weight == 0) {
+            return invalid("at least one variant needs a weight");
+        }
+        Ok(())
+    }
+}
+
+/// Whether an experiment still assigns sessions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExperimentStatus {
+    Running,
+    Stopped,
+}
+
+/// A running or finished experiment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Experiment {
+    pub id: String,
+    pub description: String,
+    pub variants: Vec<Variant>,
+    /// Tenants enrolled in the experiment
+    pub tenants: Vec<String>,
+    pub status: ExperimentStatus,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopped_at: Option<DateTime<Utc>>,
+}
+
+impl Experiment {
+    /// The variant a session is assigned
+    pub fn variant_for(&self, session_id: &str) -> &Variant {
+        let total: u64 = self.variants.iter().map(|variant| variant.weight as u64).sum();
+        let mut bucket = fnv1a(format!("{}:{}", self.id, session_id).as_bytes()) % total.max(1);
+        for variant in &self.variants {
+            if bucket < variant.weight as u64 {
+                return variant;
+            }
+            bucket -= variant.weight as u64;
+        }
+        &self.variants[0]
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status == ExperimentStatus::Running
+    }
+}
+
+/// FNV-1a, stable across platforms and releases so assignments survive
+/// restarts
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// The variant a session was assigned in an experiment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExperimentAssignment {
+    pub experiment_id: String,
+    pub variant: String,
+}
+
+/// Measurements of one answered turn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TurnMetrics {
+    pub latency_ms: f64,
+    pub cost_usd: f64,
+    /// Groundedness score, if answers are scored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub groundedness: Option<f64>,
+}
+
+/// Running count, sum and sum of squares of a metric
+#[derive(Debug, Clone, Copy, Default)]
+struct Sample {
+    count: u64,
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl Sample {
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.sum_sq += value * value;
+    }
+
+    fn summary(&self) -> Option<MetricSummary> {
+        if self.count == 0 {
+            return None;
+        }
+        let n = self.count as f64;
+        let mean = self.sum / n;
+        let variance = if self.count > 1 {
+            ((self.sum_sq - n * mean * mean) / (n - 1.0)).max(0.0)
+        } else {
+            0.0
+        };
+        Some(MetricSummary {
+            count: self.count,
+            mean,
+            std_dev: variance.sqrt(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct VariantCounters {
+    turns: u64,
+    positive_feedback: u64,
+    negative_feedback: u64,
+    latency_ms: Sample,
+    cost_usd: Sample,
+    groundedness: Sample,
+}
+
+/// Mean and spread of a metric over a variant's turns
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MetricSummary {
+    pub count: u64,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+/// What one variant's turns came to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VariantResults {
+    pub variant: String,
+    pub turns: u64,
+    pub positive_feedback: u64,
+    pub negative_feedback: u64,
+    /// Share of feedback that was positive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub positive_rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<MetricSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<MetricSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub groundedness: Option<MetricSummary>,
+}
+
+/// A metric variants are compared on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExperimentMetric {
+    PositiveFeedback,
+    LatencyMs,
+    CostUsd,
+    Groundedness,
+}
+
+/// A variant's difference from the control on one metric
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignificanceTest {
+    pub metric: ExperimentMetric,
+    pub variant: String,
+    pub control_value: f64,
+    pub variant_value: f64,
+    /// Variant minus control
+    pub difference: f64,
+    /// Two-sided p-value of the difference
+    pub p_value: f64,
+    pub significant: bool,
+}
+
+/// Per-variant metrics of an experiment and how each variant compares with
+/// the control
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentResults {
+    pub experiment: Experiment,
+    pub variants: Vec<VariantResults>,
+    /// Significance level the tests were judged at
+    pub alpha: f64,
+    pub tests: Vec<SignificanceTest>,
+}
+
+struct Tracked {
+    experiment: Experiment,
+    counters: HashMap<String, VariantCounters>,
+}
+
+#[derive(Default)]
+struct SessionAssignments {
+    by_session: HashMap<String, ExperimentAssignment>,
+    order: VecDeque<String>,
+}
+
+impl SessionAssignments {
+    fn insert(&mut self, session_id: &str, assignment: ExperimentAssignment) {
+        if self.by_session.insert(session_id.to_string(), assignment).is_none() {
+            self.order.push_back(session_id.to_string());
+            while self.order.len() > MAX_TRACKED_SESSIONS {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.by_session.remove(&oldest);
+                }
+            }
+        }
+    }
+}
+
+/// The experiments in progress and their measurements
+pub struct Experiments {
+    experiments: Mutex<Vec<Tracked>>,
+    sessions: Mutex<SessionAssignments>,
+    alpha: f64,
+}
+
+impl Default for Experiments {
+    fn default() -> Self {
+        Self {
+            experiments: Mutex::new(Vec::new()),
+            sessions: Mutex::new(SessionAssignments::default()),
+            alpha: 0.05,
+        }
+    }
+}
+
+impl Experiments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Significance level differences are judged at
+    pub fn with_alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Start an experiment
+    pub fn create(&self, spec: ExperimentSpec) -> Result<Experiment> {
+        spec.validate()?;
+        let mut experiments = self.experiments.lock().unwrap_or_else(|e| e.into_inner());
+        if experiments.iter().any(|tracked| tracked.experiment.id == spec.id) {
+            return Err(ConversationError::InvalidExperiment(format!("{} already exists", spec.id)));
+        }
+        let experiment = Experiment {
+            id: spec.id,
+            description: spec.description,
+            variants: spec.variants,
+            tenants: spec.tenants,
+            status: ExperimentStatus::Running,
+            created_at: Utc::now(),
+            stopped_at: None,
+        };
+        let counters = experiment
+            .variants
+            .iter()
+            .map(|variant| (variant.name.clone(), VariantCounters::default()))
+            .collect();
+        info!("Started experiment {} with {} variants", experiment.id, experiment.variants.len());
+        experiments.push(Tracked {
+            experiment: experiment.clone(),
+            counters,
+        });
+        Ok(experiment)
+    }
+
+    /// Experiments, oldest first
+    pub fn list(&self) -> Vec<Experiment> {
+        let experiments = self.experiments.lock().unwrap_or_else(|e| e.into_inner());
+        experiments.iter().map(|tracked| tracked.experiment.clone()).collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Experiment> {
+        let experiments = self.experiments.lock().unwrap_or_else(|e| e.into_inner());
+        experiments.iter().find(|tracked| tracked.experiment.id == id).map(|tracked| tracked.experiment.clone())
+    }
+
+    fn update<T>(&self, id: &str, f: impl FnOnce(&mut Experiment) -> T) -> Result<T> {
+        let mut experiments = self.experiments.lock().unwrap_or_else(|e| e.into_inner());
+        let tracked = experiments
+            .iter_mut()
+            .find(|tracked| tracked.experiment.id == id)
+            .ok_or_else(|| ConversationError::ExperimentNotFound(id.to_string()))?;
+        Ok(f(&mut tracked.experiment))
+    }
+
+    /// Enroll a tenant in an experiment, or withdraw it
+    pub fn set_enrollment(&self, id: &str, tenant_id: &str, enrolled: bool) -> Result<Experiment> {
+        self.update(id, |experiment| {
+            experiment.tenants.retain(|tenant| tenant != tenant_id);
+            if enrolled {
+                experiment.tenants.push(tenant_id.to_string());
+            }
+            experiment.clone()
+        })
+    }
+
+    /// Stop assigning sessions to an experiment; its results are kept
+    pub fn stop(&self, id: &str) -> Result<Experiment> {
+        let experiment = self.update(id, |experiment| {
+            if experiment.is_running() {
+                experiment.status = ExperimentStatus::Stopped;
+                experiment.stopped_at = Some(Utc::now());
+            }
+            experiment.clone()
+        })?;
+        info!("Stopped experiment {}", id);
+        Ok(experiment)
+    }
+
+    /// The variant a session takes part with: from the oldest running
+    /// experiment its tenant is enrolled in, if any
+    ///
+    /// A session takes part in one experiment at a time, so that variants of
+    /// different experiments never change the same turn.
+    pub fn assign(&self, tenant_id: Option<&str>, session_id: &str) -> Option<(ExperimentAssignment, Variant)> {
+        let tenant_id = tenant_id?;
+        let experiments = self.experiments.lock().unwrap_or_else(|e| e.into_inner());
+        let experiment = experiments
+            .iter()
+            .map(|tracked| &tracked.experiment)
+            .find(|experiment| experiment.is_running() && experiment.tenants.iter().any(|t| t == tenant_id))?;
+        let variant = experiment.variant_for(session_id).clone();
+        let assignment = ExperimentAssignment {
+            experiment_id: experiment.id.clone(),
+            variant: variant.name.clone(),
+        };
+        Some((assignment, variant))
+    }
+
+    /// Record an answered turn of a session in its variant
+    pub fn record_turn(&self, session_id: &str, assignment: &ExperimentAssignment, metrics: TurnMetrics) {
+        self.with_counters(assignment, |counters| {
+            counters.turns += 1;
+            counters.latency_ms.add(metrics.latency_ms);
+            counters.cost_usd.add(metrics.cost_usd);
+            if let Some(groundedness) = metrics.groundedness {
+                counters.groundedness.add(groundedness);
+            }
+        });
+        self.sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(session_id, assignment.clone());
+    }
+
+    /// Record the user's feedback on a session's answers in the variant the
+    /// session took part with; `None` if it took part in no experiment
+    pub fn record_feedback(&self, session_id: &str, helpful: bool) -> Option<ExperimentAssignment> {
+        let assignment = self
+            .sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .by_session
+            .get(session_id)
+            .cloned()?;
+        self.with_counters(&assignment, |counters| {
+            if helpful {
+                counters.positive_feedback += 1;
+            } else {
+                counters.negative_feedback += 1;
+            }
+        });
+        Some(assignment)
+    }
+
+    fn with_counters(&self, assignment: &ExperimentAssignment, f: impl FnOnce(&mut VariantCounters)) {
+        let mut experiments = self.experiments.lock().unwrap_or_else(|e| e.into_inner());
+        let counters = experiments
+            .iter_mut()
+            .find(|tracked| tracked.experiment.id == assignment.experiment_id)
+            .and_then(|tracked| tracked.counters.get_mut(&assignment.variant));
+        if let Some(counters) = counters {
+            f(counters);
+        }
+    }
+
+    /// Metrics of each variant of an experiment, and tests of each variant
+    /// against the control
+    pub fn results(&self, id: &str) -> Option<ExperimentResults> {
+        let experiments = self.experiments.lock().unwrap_or_else(|e| e.into_inner());
+        let tracked = experiments.iter().find(|tracked| tracked.experiment.id == id)?;
+        let counters: Vec<(&str, VariantCounters)> = tracked
+            .experiment
+            .variants
+            .iter()
+            .map(|variant| {
+                let counters = tracked.counters.get(&variant.name).copied().unwrap_or_default();
+                (variant.name.as_str(), counters)
+            })
+            .collect();
+
+        let variants = counters.iter().map(|(name, counters)| variant_results(name, counters)).collect();
+        let mut tests = Vec::new();
+        if let Some((_, control)) = counters.first() {
+            for (name, counters) in &counters[1..] {
+                tests.extend(compare(name, control, counters, self.alpha));
+            }
+        }
+        Some(ExperimentResults {
+            experiment: tracked.experiment.clone(),
+            variants,
+            alpha: self.alpha,
+            tests,
+        })
+    }
+}
+
+fn variant_results(name: &str, counters: &VariantCounters) -> VariantResults {
+    let feedback = counters.positive_feedback + counters.negative_feedback;
+    VariantResults {
+        variant: name.to_string(),
+        turns: counters.turns,
+        positive_feedback: counters.positive_feedback,
+        negative_feedback: counters.negative_feedback,
+        positive_rate: (feedback > 0).then(|| counters.positive_feedback as f64 / feedback as f64),
+        latency_ms: counters.latency_ms.summary(),
+        cost_usd: counters.cost_usd.summary(),
+        groundedness: counters.groundedness.summary(),
+    }
+}
+
+/// Tests of a variant against the control on every metric both measured
+fn compare(name: &str, control: &VariantCounters, variant: &VariantCounters, alpha: f64) -> Vec<SignificanceTest> {
+    let test = |metric, control_value: f64, variant_value: f64, p_value: f64| SignificanceTest {
+        metric,
+        variant: name.to_string(),
+        control_value,
+        variant_value,
+        difference: variant_value - control_value,
+        p_value,
+        significant: p_value < alpha,
+    };
+
+    let mut tests = Vec::new();
+    if let Some((p_control, p_variant, p_value)) = proportion_test(
+        control.positive_feedback,
+        control.positive_feedback + control.negative_feedback,
+        variant.positive_feedback,
+        variant.positive_feedback + variant.negative_feedback,
+    ) {
+        tests.push(test(ExperimentMetric::PositiveFeedback, p_control, p_variant, p_value));
+    }
+    for (metric, control, variant) in [
+        (ExperimentMetric::LatencyMs, &control.latency_ms, &variant.latency_ms),
+        (ExperimentMetric::CostUsd, &control.cost_usd, &variant.cost_usd),
+        (ExperimentMetric::Groundedness, &control.groundedness, &variant.groundedness),
+    ] {
+        if let (Some(control), Some(variant)) = (control.summary(), variant.summary()) {
+            if let Some(p_value) = mean_test(&control, &variant) {
+                tests.push(test(metric, control.mean, variant.mean, p_value));
+            }
+        }
+    }
+    tests
+}
+
+/// Two-proportion z-test: the two rates and the two-sided p-value
+fn proportion_test(successes_a: u64, n_a: u64, successes_b: u64, n_b: u64) -> Option<(f64, f64, f64)> {
+    if n_a == 0 || n_b == 0 {
+        return None;
+    }
+    let (n_a, n_b) = (n_a as f64, n_b as f64);
+    let p_a = successes_a as f64 / n_a;
+    let p_b = successes_b as f64 / n_b;
+    let pooled = (successes_a + successes_b) as f64 / (n_a + n_b);
+    let standard_error = (pooled * (1.0 - pooled) * (1.0 / n_a + 1.0 / n_b)).sqrt();
+    let p_value = if standard_error > 0.0 {
+        two_sided_p((p_b - p_a) / standard_error)
+    } else {
+        1.0
+    };
+    Some((p_a, p_b, p_value))
+}
+
+/// Welch's test of two means, with the normal approximation of the t
+/// distribution; needs two samples a side
+fn mean_test(a: &MetricSummary, b: &MetricSummary) -> Option<f64> {
+    if a.count < 2 || b.count < 2 {
+        return None;
+    }
+    let standard_error = (a.std_dev.powi(2) / a.count as f64 + b.std_dev.powi(2) / b.count as f64).sqrt();
+    if standard_error > 0.0 {
+        Some(two_sided_p((b.mean - a.mean) / standard_error))
+    } else if a.mean == b.mean {
+        Some(1.0)
+    } else {
+        Some(0.0)
+    }
+}
+
+fn two_sided_p(z: f64) -> f64 {
+    erfc(z.abs() / std::f64::consts::SQRT_2).clamp(0.0, 1.0)
+}
+
+/// Complementary error function, to within 1.2e-7 (Numerical Recipes'
+/// Chebyshev fit)
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98 + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let result = t * poly.exp();
+    if x >= 0.0 {
+        result
+    } else {
+        2.0 - result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(tenants: &[&str]) -> ExperimentSpec {
+        ExperimentSpec {
+            id: "prompt-v2".to_string(),
+            description: "Shorter system prompt".to_string(),
+            variants: vec![
+                Variant::new("control"),
+                Variant::new("concise").with_prompt_template("{system_prompt}\nAnswer in one paragraph."),
+            ],
+            tenants: tenants.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_assignment_is_deterministic_and_opt_in() {
+        let experiments = Experiments::new();
+        experiments.create(spec(&["acme"])).unwrap();
+        assert!(experiments.create(spec(&[])).is_err());
+
+        assert!(experiments.assign(Some("globex"), "s1").is_none());
+        assert!(experiments.assign(None, "s1").is_none());
+
+        let (first, _) = experiments.assign(Some("acme"), "s1").unwrap();
+        let (again, _) = experiments.assign(Some("acme"), "s1").unwrap();
+        assert_eq!(first, again);
+
+        let variants: Vec<String> = (0..200)
+            .filter_map(|i| experiments.assign(Some("acme"), &format!("session-{}", i)))
+            .map(|(assignment, _)| assignment.variant)
+            .collect();
+        let concise = variants.iter().filter(|v| *v == "concise").count();
+        assert!((60..140).contains(&concise), "{} of 200 sessions got the variant", concise);
+
+        experiments.set_enrollment("prompt-v2", "globex", true).unwrap();
+        assert!(experiments.assign(Some("globex"), "s1").is_some());
+        experiments.stop("prompt-v2").unwrap();
+        assert!(experiments.assign(Some("acme"), "s1").is_none());
+
+        let variant = Variant::new("v").with_prompt_template("{system_prompt}\nBe brief.");
+        assert_eq!(variant.system_prompt("You are helpful."), "You are helpful.\nBe brief.");
+    }
+
+    #[test]
+    fn test_results_flag_significant_differences() {
+        let experiments = Experiments::new();
+        experiments.create(spec(&["acme"])).unwrap();
+        let control = ExperimentAssignment {
+            experiment_id: "prompt-v2".to_string(),
+            variant: "control".to_string(),
+        };
+        let concise = ExperimentAssignment {
+            variant: "concise".to_string(),
+            ..control.clone()
+        };
+
+        for i in 0..200 {
+            let (session, assignment, latency, helpful) = if i % 2 == 0 {
+                (format!("c{}", i), &control, 900.0 + (i % 10) as f64, i % 4 == 0)
+            } else {
+                (format!("v{}", i), &concise, 400.0 + (i % 10) as f64, i % 10 != 1)
+            };
+            let metrics = TurnMetrics {
+                latency_ms: latency,
+                cost_usd: 0.01,
+                groundedness: None,
+            };
+            experiments.record_turn(&session, assignment, metrics);
+            assert_eq!(experiments.record_feedback(&session, helpful), Some(assignment.clone()));
+        }
+        assert_eq!(experiments.record_feedback("unknown", true), None);
+
+        let results = experiments.results("prompt-v2").unwrap();
+        assert_eq!(results.variants[0].turns, 100);
+        assert_eq!(results.variants[1].positive_rate, Some(0.8));
+
+        let test = |metric| results.tests.iter().find(|t| t.metric == metric).unwrap();
+        let feedback = test(ExperimentMetric::PositiveFeedback);
+        assert!(feedback.significant && feedback.difference > 0.0);
+        let latency = test(ExperimentMetric::LatencyMs);
+        assert!(latency.significant && latency.difference < 0.0);
+        let cost = test(ExperimentMetric::CostUsd);
+        assert!(!cost.significant);
+        assert!(results.tests.iter().all(|t| t.metric != ExperimentMetric::Groundedness));
+    }
+
+    #[test]
+    fn test_normal_p_values() {
+        assert!((erfc(0.0) - 1.0).abs() < 1e-6);
+        assert!((two_sided_p(1.96) - 0.05).abs() < 1e-3);
+        assert!((two_sided_p(-2.576) - 0.01).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,629 @@
+//! Groundedness scoring of generated answers
+//!
+//! After each answer, a [`GroundednessScorer`] measures how much of it is
+//! supported by the context it was generated from: the answer is split into
+//! claims and each claim is checked against the retrieved passages.
+//! [`OverlapScorer`] is a cheap lexical check that needs no model;
+//! [`JudgedScorer`] asks a [`JudgeModel`] whether each claim is entailed by a
+//! passage and caches its verdicts, so regenerated answers over the same
+//! context are not judged twice.
+//!
+//! The [`GroundednessMonitor`] records every score in [`GroundednessStats`],
+//! which are exported with the server's metrics, and queues answers scoring
+//! below its review threshold for a human to look at. Scoring never fails an
+//! answer; scorer errors are counted and logged.
+
+use crate::eval::JudgeModel;
+use crate::{ConversationError, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Answers kept in the review queue; the oldest are dropped first
+const MAX_REVIEWS: usize = 1_000;
+
+/// Words too common to show that a claim comes from a passage
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "are", "was", "were", "with", "that", "this", "from", "have", "has", "had", "not",
+    "but", "you", "your", "can", "will", "its", "they", "them", "then", "than", "there", "which", "what",
+    "when", "into", "also", "use", "using", "should", "would", "could", "about", "been", "our",
+];
+
+/// A retrieved passage an answer may draw on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextPassage {
+    pub source: String,
+    pub content: String,
+}
+
+impl ContextPassage {
+    pub fn new(source: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            content: content.into(),
+        }
+    }
+}
+
+/// Whether one claim of an answer is backed by the context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClaimSupport {
+    pub claim: String,
+    pub supported: bool,
+    /// Source of the passage backing the claim
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// How well an answer is grounded in its context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroundednessScore {
+    /// Share of claims backed by the context, 0..=1; 1 for answers without
+    /// checkable claims
+    pub score: f64,
+    pub claims: Vec<ClaimSupport>,
+    /// Scorer that produced the score
+    pub scorer: String,
+}
+
+impl GroundednessScore {
+    fn from_claims(scorer: &str, claims: Vec<ClaimSupport>) -> Self {
+        let score = if claims.is_empty() {
+            1.0
+        } else {
+            claims.iter().filter(|c| c.supported).count() as f64 / claims.len() as f64
+        };
+        Self {
+            score,
+            claims,
+            scorer: scorer.to_string(),
+        }
+    }
+}
+
+/// Measures how much of an answer its context supports
+#[async_trait]
+pub trait GroundednessScorer: Send + Sync {
+    /// Name recorded with each score
+    fn name(&self) -> &str;
+
+    async fn score(&self, answer: &str, passages: &[ContextPassage]) -> Result<GroundednessScore>;
+}
+
+/// Sentences of an answer worth checking; lines appended by the
+/// post-processor (source lists, staleness notes) are left out
+pub fn claims(answer: &str) -> Vec<String> {
+    let body = answer.split("\n\nSources:").next().unwrap_or_default();
+    let mut claims = Vec::new();
+    for line in body.lines().map(str::trim).filter(|l| !l.starts_with("Note:")) {
+        let mut rest = line;
+        while !rest.is_empty() {
+            let end = rest
+                .char_indices()
+                .find(|&(i, c)| {
+                    matches!(c, '.' | '!' | '?')
+                        && rest[i + c.len_utf8()..].chars().next().map_or(true, char::is_whitespace)
+                })
+                .map_or(rest.len(), |(i, c)| i + c.len_utf8());
+            let sentence = rest[..end].trim();
+            if content_words(sentence).len() >= 3 {
+                claims.push(sentence.to_string());
+            }
+            rest = rest[end..].trim_start();
+        }
+    }
+    claims
+}
+
+fn content_words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .filter(|w| !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+/// Counts a claim as supported when most of its content words appear in a
+/// single passage
+#[derive(Debug, Clone)]
+pub struct OverlapScorer {
+    min_overlap: f64,
+}
+
+impl Default for OverlapScorer {
+    fn default() -> Self {
+        Self { min_overlap: 0.6 }
+    }
+}
+
+impl OverlapScorer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Share of a claim's content words a passage must contain, 0..=1
+    pub fn with_min_overlap(mut self, min_overlap: f64) -> Self {
+        self.min_overlap = min_overlap.clamp(0.0, 1.0);
+        self
+    }
+}
+
+#[async_trait]
+impl GroundednessScorer for OverlapScorer {
+    fn name(&self) -> &str {
+        "overlap"
+    }
+
+    async fn score(&self, answer: &str, passages: &[ContextPassage]) -> Result<GroundednessScore> {
+        let passage_words: Vec<HashSet<String>> = passages.iter().map(|p| content_words(&p.content)).collect();
+        let claims = claims(answer)
+            .into_iter()
+            .map(|claim| {
+                let words = content_words(&claim);
+                let best = passage_words
+                    .iter()
+                    .enumerate()
+                    .map(|(i, passage)| (i, words.intersection(passage).count() as f64 / words.len() as f64))
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+                match best {
+                    Some((i, overlap)) if overlap >= self.min_overlap => ClaimSupport {
+                        claim,
+                        supported: true,
+                        source: Some(passages[i].source.clone()),
+                    },
+                    _ => ClaimSupport {
+                        claim,
+                        supported: false,
+                        source: None,
+                    },
+                }
+            })
+            .collect();
+        Ok(GroundednessScore::from_claims(self.name(), claims))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RawClaims {
+    claims: Vec<RawClaim>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawClaim {
+    claim: usize,
+    supported: bool,
+    #[serde(default)]
+    passage: Option<usize>,
+}
+
+/// Asks a judge model which claims the passages entail, caching scores by
+/// model, answer and passages
+pub struct JudgedScorer {
+    model: Box<dyn JudgeModel>,
+    cache: RwLock<HashMap<String, GroundednessScore>>,
+    order: Mutex<VecDeque<String>>,
+    capacity: usize,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl JudgedScorer {
+    pub fn new(model: impl JudgeModel + 'static) -> Self {
+        Self {
+            model: Box::new(model),
+            cache: RwLock::new(HashMap::new()),
+            order: Mutex::new(VecDeque::new()),
+            capacity: 10_000,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Keep at most `capacity` scores, dropping the oldest first
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Cache hits and misses so far
+    pub fn cache_stats(&self) -> (usize, usize) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+
+    fn cache_key(&self, answer: &str, passages: &[ContextPassage]) -> String {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.model.model().hash(&mut hasher);
+        answer.hash(&mut hasher);
+        for passage in passages {
+            passage.content.hash(&mut hasher);
+        }
+        format!("{:x}", hasher.finish())
+    }
+
+    /// The entailment prompt sent to the judge model
+    pub fn prompt(claims: &[String], passages: &[ContextPassage]) -> String {
+        let mut prompt = String::from(
+            "You are checking whether an assistant's answer is supported by the context it was \
+             given. For each numbered claim, decide whether one of the numbered passages entails it. \
+             Claims the passages do not state or imply are unsupported, even if they are true.\n\nPassages:\n",
+        );
+        for (i, passage) in passages.iter().enumerate() {
+            let _ = writeln!(prompt, "[{}] ({}) {}", i + 1, passage.source, passage.content);
+        }
+        prompt.push_str("\nClaims:\n");
+        for (i, claim) in claims.iter().enumerate() {
+            let _ = writeln!(prompt, "{}. {}", i + 1, claim);
+        }
+        prompt.push_str(
+            "\nRespond with JSON only, in the form \
+             {\"claims\": [{\"claim\": 1, \"supported\": true, \"passage\": 2}]}, \
+             with one entry per claim.",
+        );
+        prompt
+    }
+
+    /// Read the judge's verdicts; claims it skipped count as unsupported
+    pub fn parse(claims: Vec<String>, passages: &[ContextPassage], response: &str) -> Result<Vec<ClaimSupport>> {
+        let json = match (response.find('{'), response.rfind('}')) {
+            (Some(start), Some(end)) if start < end => &response[start..=end],
+            _ => {
+                return Err(ConversationError::EvalError(
+                    "Groundedness judge response contains no JSON".to_string(),
+                ))
+            }
+        };
+        let raw: RawClaims = serde_json::from_str(json).map_err(|e| {
+            ConversationError::EvalError(format!("Unreadable groundedness judge response: {}", e))
+        })?;
+
+        Ok(claims
+            .into_iter()
+            .enumerate()
+            .map(|(i, claim)| {
+                let verdict = raw.claims.iter().find(|c| c.claim == i + 1);
+                let supported = verdict.is_some_and(|v| v.supported);
+                let source = verdict
+                    .filter(|_| supported)
+                    .and_then(|v| v.passage)
+                    .and_then(|p| passages.get(p.wrapping_sub(1)))
+                    .map(|p| p.source.clone());
+                ClaimSupport { claim, supported, source }
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl GroundednessScorer for JudgedScorer {
+    fn name(&self) -> &str {
+        self.model.model()
+    }
+
+    async fn score(&self, answer: &str, passages: &[ContextPassage]) -> Result<GroundednessScore> {
+        let claims = claims(answer);
+        if claims.is_empty() {
+            return Ok(GroundednessScore::from_claims(self.name(), Vec::new()));
+        }
+
+        let key = self.cache_key(answer, passages);
+        if let Some(score) = self.cache.read().await.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(score.clone());
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let score = if passages.is_empty() {
+            // Nothing to entail the claims; no need to ask
+            let claims = claims
+                .into_iter()
+                .map(|claim| ClaimSupport { claim, supported: false, source: None })
+                .collect();
+            GroundednessScore::from_claims(self.name(), claims)
+        } else {
+            let response = self.model.complete(&Self::prompt(&claims, passages)).await?;
+            GroundednessScore::from_claims(self.name(), Self::parse(claims, passages, &response)?)
+        };
+
+        let mut cache = self.cache.write().await;
+        let mut order = self.order.lock().expect("groundedness cache poisoned");
+        if cache.insert(key.clone(), score.clone()).is_none() {
+            order.push_back(key);
+        }
+        while order.len() > self.capacity {
+            if let Some(oldest) = order.pop_front() {
+                cache.remove(&oldest);
+            }
+        }
+        Ok(score)
+    }
+}
+
+/// An answer to score, with the context it was generated from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroundedAnswer {
+    pub session_id: String,
+    pub query: String,
+    pub answer: String,
+    pub passages: Vec<ContextPassage>,
+}
+
+/// A low-grounded answer waiting for human review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroundednessReview {
+    pub id: Uuid,
+    pub session_id: String,
+    pub query: String,
+    pub answer: String,
+    pub score: GroundednessScore,
+    /// Sources of the context the answer was generated from
+    pub sources: Vec<String>,
+    pub flagged_at: DateTime<Utc>,
+}
+
+/// Snapshot of groundedness scoring
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GroundednessStats {
+    /// Answers scored
+    pub answers: u64,
+    /// Answers flagged for review
+    pub flagged: u64,
+    /// Answers the scorer failed on
+    pub failures: u64,
+    /// Mean score of the scored answers
+    pub mean_score: f64,
+    /// Answers waiting in the review queue
+    pub pending_reviews: usize,
+    /// Score below which answers are flagged
+    pub review_threshold: f64,
+}
+
+impl GroundednessStats {
+    /// Prometheus text exposition of the counters
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        for (name, help, kind, value) in [
+            ("groundedness_answers_total", "Answers scored for groundedness", "counter", self.answers as f64),
+            ("groundedness_flagged_total", "Answers flagged for human review", "counter", self.flagged as f64),
+            ("groundedness_failures_total", "Answers the groundedness scorer failed on", "counter", self.failures as f64),
+            ("groundedness_mean_score", "Mean groundedness score of scored answers", "gauge", self.mean_score),
+            ("groundedness_pending_reviews", "Low-grounded answers awaiting review", "gauge", self.pending_reviews as f64),
+        ] {
+            let _ = writeln!(out, "# HELP {}_{} {}", prefix, name, help);
+            let _ = writeln!(out, "# TYPE {}_{} {}", prefix, name, kind);
+            let _ = writeln!(out, "{}_{} {}", prefix, name, value);
+        }
+        out
+    }
+}
+
+/// Scores answers, records the scores and queues low-grounded answers for
+/// review
+pub struct GroundednessMonitor {
+    scorer: Arc<dyn GroundednessScorer>,
+    review_threshold: f64,
+    answers: AtomicU64,
+    flagged: AtomicU64,
+    failures: AtomicU64,
+    /// Sum of scores in millionths, so it can be kept atomically
+    score_sum: AtomicU64,
+    reviews: Mutex<VecDeque<GroundednessReview>>,
+}
+
+impl GroundednessMonitor {
+    /// Flag answers scoring below 0.5
+    pub fn new(scorer: Arc<dyn GroundednessScorer>) -> Self {
+        Self {
+            scorer,
+            review_threshold: 0.5,
+            answers: AtomicU64::new(0),
+            flagged: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            score_sum: AtomicU64::new(0),
+            reviews: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Flag answers scoring below `threshold`; 0 flags none
+    pub fn with_review_threshold(mut self, threshold: f64) -> Self {
+        self.review_threshold = threshold.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Score below which answers are flagged
+    pub fn review_threshold(&self) -> f64 {
+        self.review_threshold
+    }
+
+    /// Score an answer, queueing it for review if it is poorly grounded;
+    /// `None` if the scorer failed
+    pub async fn evaluate(&self, answer: GroundedAnswer) -> Option<GroundednessScore> {
+        let score = match self.scorer.score(&answer.answer, &answer.passages).await {
+            Ok(score) => score,
+            Err(e) => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                warn!("Groundedness scoring failed for session {}: {}", answer.session_id, e);
+                return None;
+            }
+        };
+
+        self.answers.fetch_add(1, Ordering::Relaxed);
+        self.score_sum
+            .fetch_add((score.score * 1_000_000.0).round() as u64, Ordering::Relaxed);
+        info!(
+            session_id = %answer.session_id,
+            scorer = %score.scorer,
+            score = score.score,
+            claims = score.claims.len(),
+            "Answer groundedness"
+        );
+
+        if score.score < self.review_threshold {
+            self.flagged.fetch_add(1, Ordering::Relaxed);
+            let mut sources: Vec<String> = Vec::new();
+            for passage in &answer.passages {
+                if !sources.contains(&passage.source) {
+                    sources.push(passage.source.clone());
+                }
+            }
+            let mut reviews = self.reviews.lock().expect("groundedness reviews poisoned");
+            reviews.push_back(GroundednessReview {
+                id: Uuid::new_v4(),
+                session_id: answer.session_id,
+                query: answer.query,
+                answer: answer.answer,
+                score: score.clone(),
+                sources,
+                flagged_at: Utc::now(),
+            });
+            while reviews.len() > MAX_REVIEWS {
+                reviews.pop_front();
+            }
+        }
+        Some(score)
+    }
+
+    /// Answers waiting for review, oldest first
+    pub fn reviews(&self) -> Vec<GroundednessReview> {
+        self.reviews.lock().expect("groundedness reviews poisoned").iter().cloned().collect()
+    }
+
+    /// Take a reviewed answer off the queue
+    pub fn resolve(&self, id: Uuid) -> Option<GroundednessReview> {
+        let mut reviews = self.reviews.lock().expect("groundedness reviews poisoned");
+        let index = reviews.iter().position(|r| r.id == id)?;
+        reviews.remove(index)
+    }
+
+    pub fn stats(&self) -> GroundednessStats {
+        let answers = self.answers.load(Ordering::Relaxed);
+        let score_sum = self.score_sum.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        GroundednessStats {
+            answers,
+            flagged: self.flagged.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            mean_score: if answers > 0 { score_sum / answers as f64 } else { 0.0 },
+            pending_reviews: self.reviews.lock().expect("groundedness reviews poisoned").len(),
+            review_threshold: self.review_threshold,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passages() -> Vec<ContextPassage> {
+        vec![
+            ContextPassage::new("runbook.md", "Restart the billing service with helm rollback when latency exceeds 500ms."),
+            ContextPassage::new("wiki/oncall", "Page the payments team for checkout errors."),
+        ]
+    }
+
+    #[test]
+    fn test_claims_skip_appended_notes_and_sources() {
+        let answer = "Roll back the billing service with helm. Then page the payments team! Ok.\n\n\
+                      Note: wiki may be outdated.\n\nSources:\n1. runbook.md";
+        assert_eq!(claims(answer), vec!["Roll back the billing service with helm.", "Then page the payments team!"]);
+        assert_eq!(claims("Version 1.2 fixed the helm chart rollout"), vec!["Version 1.2 fixed the helm chart rollout"]);
+    }
+
+    #[tokio::test]
+    async fn test_overlap_scorer() {
+        let answer = "Restart the billing service with a helm rollback. Scale the database to twelve replicas.";
+        let score = OverlapScorer::new().score(answer, &passages()).await.unwrap();
+        assert_eq!(score.score, 0.5);
+        assert_eq!(score.claims[0].source.as_deref(), Some("runbook.md"));
+        assert!(!score.claims[1].supported);
+
+        let ungrounded = OverlapScorer::new().score(answer, &[]).await.unwrap();
+        assert_eq!(ungrounded.score, 0.0);
+        assert_eq!(OverlapScorer::new().score("Done.", &[]).await.unwrap().score, 1.0);
+    }
+
+    /// Supports claims mentioning helm, counting calls
+    struct HelmJudge(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl JudgeModel for HelmJudge {
+        fn model(&self) -> &str {
+            "helm-judge"
+        }
+
+        async fn complete(&self, prompt: &str) -> Result<String> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let claims: Vec<_> = prompt
+                .split("\nClaims:\n")
+                .nth(1)
+                .unwrap()
+                .lines()
+                .take_while(|l| l.starts_with(|c: char| c.is_ascii_digit()))
+                .enumerate()
+                .map(|(i, line)| serde_json::json!({ "claim": i + 1, "supported": line.contains("helm"), "passage": 1 }))
+                .collect();
+            Ok(format!("```json\n{}\n```", serde_json::json!({ "claims": claims })))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_judged_scorer_caches_verdicts() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let scorer = JudgedScorer::new(HelmJudge(calls.clone())).with_cache_capacity(1);
+        let answer = "Restart the billing service with helm. Scale the database to twelve replicas.";
+
+        let score = scorer.score(answer, &passages()).await.unwrap();
+        assert_eq!((score.score, score.scorer.as_str()), (0.5, "helm-judge"));
+        assert_eq!(score.claims[0].source.as_deref(), Some("runbook.md"));
+        assert_eq!(score.claims[1].source, None);
+
+        assert_eq!(scorer.score(answer, &passages()).await.unwrap(), score);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(scorer.cache_stats(), (1, 1));
+
+        // The capacity of one evicts the first answer
+        scorer.score("Page the payments team about checkout errors.", &passages()).await.unwrap();
+        scorer.score(answer, &passages()).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        assert!(JudgedScorer::parse(vec!["claim".to_string()], &passages(), "no idea").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_monitor_flags_low_grounded_answers() {
+        let monitor = GroundednessMonitor::new(Arc::new(OverlapScorer::new())).with_review_threshold(0.6);
+        let answer = |text: &str| GroundedAnswer {
+            session_id: "s1".to_string(),
+            query: "billing is slow".to_string(),
+            answer: text.to_string(),
+            passages: passages(),
+        };
+
+        let grounded = monitor.evaluate(answer("Restart the billing service with a helm rollback.")).await.unwrap();
+        assert_eq!(grounded.score, 1.0);
+        assert!(monitor.reviews().is_empty());
+
+        monitor
+            .evaluate(answer("Restart the billing service with a helm rollback. Scale the database to twelve replicas."))
+            .await
+            .unwrap();
+        let reviews = monitor.reviews();
+        assert_eq!(reviews.len(), 1);
+        assert_eq!(reviews[0].sources, vec!["runbook.md", "wiki/oncall"]);
+
+        let stats = monitor.stats();
+        assert_eq!((stats.answers, stats.flagged, stats.pending_reviews), (2, 1, 1));
+        assert!((stats.mean_score - 0.75).abs() < 1e-9);
+        assert!(stats.render_prometheus("copilot").contains("copilot_groundedness_flagged_total 1"));
+
+        assert!(monitor.resolve(reviews[0].id).is_some());
+        assert!(monitor.resolve(reviews[0].id).is_none());
+        assert_eq!(monitor.stats().pending_reviews, 0);
+    }
+}
@@ -0,0 +1,424 @@
+//! Handoff bundles for escalating a conversation to humans
+//!
+//! When the agent cannot finish on its own, [`HandoffBundle::from_messages`]
+//! packages what the conversation established: a summary, the key findings,
+//! the sources cited, the commands that were run and the questions still
+//! open. [`HandoffBundle::to_markdown`] renders it as a document that can be
+//! posted to an incident channel or attached to a ticket.
+
+use crate::history::{ConversationMessage, MessageRole};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Instructions for summarizing a conversation for the humans taking over
+pub const HANDOFF_SUMMARY_PROMPT: &str = "Summarize this conversation for an engineer taking it \
+over. In a few sentences, say what the user is trying to do, what has been found and tried, and \
+where things stand. Do not repeat the transcript.";
+
+/// Phrases that mark a sentence of an assistant message as a finding
+const FINDING_CUES: &[&str] = &[
+    "root cause",
+    "caused by",
+    "due to",
+    "because",
+    "found",
+    "identified",
+    "confirmed",
+    "the issue is",
+    "the problem is",
+    "appears to",
+];
+
+/// Code fence languages whose lines are shell commands
+const SHELL_LANGUAGES: &[&str] = &["sh", "bash", "shell", "console", "zsh"];
+
+/// Findings kept in a bundle
+const MAX_FINDINGS: usize = 10;
+
+/// Messages of the transcript tail handed to the summarizer
+const SUMMARY_TRANSCRIPT_MESSAGES: usize = 20;
+
+/// Everything a human needs to take a conversation over
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandoffBundle {
+    pub conversation_id: String,
+    /// The user's first request, shortened
+    pub title: String,
+    pub summary: String,
+    /// Conclusions the assistant reached
+    pub findings: Vec<String>,
+    /// Sources the assistant cited
+    pub citations: Vec<String>,
+    /// Shell commands run or proposed during the conversation
+    pub commands: Vec<String>,
+    /// Questions nobody has answered yet
+    pub open_questions: Vec<String>,
+    /// Notes from whoever requested the handoff
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    pub message_count: usize,
+    pub started_at: DateTime<Utc>,
+    pub last_message_at: DateTime<Utc>,
+    /// Transcript tail used to write the summary
+    #[serde(skip)]
+    transcript: String,
+}
+
+impl HandoffBundle {
+    /// Build a bundle from a conversation's messages, oldest first; `None`
+    /// for a conversation without messages
+    ///
+    /// The summary starts out as [`HandoffBundle::describe`]; replace it with
+    /// [`HandoffBundle::with_summary`] once a model has written one.
+    pub fn from_messages(conversation_id: &str, messages: &[ConversationMessage]) -> Option<Self> {
+        let first = messages.first()?;
+        let last = messages.last()?;
+        let title = messages
+            .iter()
+            .find(|m| m.role == MessageRole::User)
+            .map(|m| shorten(first_line(&m.content), 80))
+            .unwrap_or_else(|| format!("Conversation {}", conversation_id));
+
+        let mut bundle = Self {
+            conversation_id: conversation_id.to_string(),
+            title,
+            summary: String::new(),
+            findings: Vec::new(),
+            citations: Vec::new(),
+            commands: Vec::new(),
+            open_questions: open_questions(messages),
+            notes: None,
+            message_count: messages.len(),
+            started_at: first.timestamp,
+            last_message_at: last.timestamp,
+            transcript: transcript(messages),
+        };
+
+        for message in messages {
+            if let Some(command) = message.metadata.get("command") {
+                push_unique(&mut bundle.commands, command.trim());
+            }
+            for command in shell_commands(&message.content) {
+                push_unique(&mut bundle.commands, &command);
+            }
+            if message.role != MessageRole::Assistant {
+                continue;
+            }
+            for citation in citations(&message.content) {
+                push_unique(&mut bundle.citations, &citation);
+            }
+            for finding in prose_sentences(&message.content) {
+                let lower = finding.to_lowercase();
+                if bundle.findings.len() < MAX_FINDINGS && FINDING_CUES.iter().any(|cue| lower.contains(cue)) {
+                    push_unique(&mut bundle.findings, &finding);
+                }
+            }
+        }
+
+        bundle.summary = bundle.describe();
+        Some(bundle)
+    }
+
+    pub fn with_summary(mut self, summary: impl Into<String>) -> Self {
+        self.summary = summary.into();
+        self
+    }
+
+    pub fn with_notes(mut self, notes: Option<String>) -> Self {
+        self.notes = notes.filter(|notes| !notes.trim().is_empty());
+        self
+    }
+
+    /// Plain summary, for when no model-written one is available
+    pub fn describe(&self) -> String {
+        format!(
+            "{} messages from {} to {} about \"{}\": {} findings, {} commands, {} open questions.",
+            self.message_count,
+            self.started_at.format("%Y-%m-%d %H:%M UTC"),
+            self.last_message_at.format("%Y-%m-%d %H:%M UTC"),
+            self.title,
+            self.findings.len(),
+            self.commands.len(),
+            self.open_questions.len()
+        )
+    }
+
+    /// Task handed to the model that writes the summary
+    pub fn summary_task(&self) -> String {
+        format!("{}\n\nTranscript:\n{}", HANDOFF_SUMMARY_PROMPT, self.transcript)
+    }
+
+    /// The bundle as a Markdown handoff document
+    pub fn to_markdown(&self) -> String {
+        let mut doc = format!("# Handoff: {}\n\n", self.title);
+        doc.push_str(&format!(
+            "Conversation `{}`, {} messages, {} to {}.\n\n## Summary\n\n{}\n",
+            self.conversation_id,
+            self.message_count,
+            self.started_at.format("%Y-%m-%d %H:%M UTC"),
+            self.last_message_at.format("%Y-%m-%d %H:%M UTC"),
+            self.summary.trim()
+        ));
+        if let Some(notes) = &self.notes {
+            doc.push_str(&format!("\n## Notes\n\n{}\n", notes.trim()));
+        }
+
+        doc.push_str("\n## Key findings\n\n");
+        push_list(&mut doc, &self.findings, |_, finding| format!("- {}", finding));
+
+        doc.push_str("\n## Commands executed\n\n");
+        if self.commands.is_empty() {
+            doc.push_str("None recorded.\n");
+        } else {
+            doc.push_str(&format!("```sh\n{}\n```\n", self.commands.join("\n")));
+        }
+
+        doc.push_str("\n## Citations\n\n");
+        push_list(&mut doc, &self.citations, |i, citation| format!("{}. {}", i + 1, citation));
+
+        doc.push_str("\n## Open questions\n\n");
+        push_list(&mut doc, &self.open_questions, |_, question| format!("- {}", question));
+        doc
+    }
+}
+
+fn push_list(doc: &mut String, items: &[String], line: impl Fn(usize, &str) -> String) {
+    if items.is_empty() {
+        doc.push_str("None recorded.\n");
+    }
+    for (i, item) in items.iter().enumerate() {
+        doc.push_str(&line(i, item));
+        doc.push('\n');
+    }
+}
+
+fn push_unique(items: &mut Vec<String>, item: &str) {
+    if !item.is_empty() && !items.iter().any(|existing| existing == item) {
+        items.push(item.to_string());
+    }
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default()
+}
+
+fn shorten(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(max_chars - 3).collect();
+    short.push_str("...");
+    short
+}
+
+/// Lines of shell code fences, and `$ `-prompted lines outside fences
+fn shell_commands(content: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut fence: Option<&str> = None;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if let Some(language) = trimmed.strip_prefix("```") {
+            fence = match fence {
+                Some(_) => None,
+                None => Some(language.trim()),
+            };
+            continue;
+        }
+        let command = match fence {
+            // Console sessions mix commands with their output
+            Some("console") | None => trimmed.strip_prefix("$ "),
+            Some(language) if SHELL_LANGUAGES.contains(&language) => {
+                Some(trimmed.strip_prefix("$ ").unwrap_or(trimmed))
+            }
+            Some(_) => None,
+        };
+        if let Some(command) = command.map(str::trim).filter(|c| !c.is_empty() && !c.starts_with('#')) {
+            commands.push(command.to_string());
+        }
+    }
+    commands
+}
+
+/// Entries of the `Sources:` lists appended to responses, and bare URLs
+fn citations(content: &str) -> Vec<String> {
+    let mut citations = Vec::new();
+    let mut in_sources = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed == "Sources:" {
+            in_sources = true;
+            continue;
+        }
+        if in_sources {
+            match trimmed.split_once(". ") {
+                Some((number, source)) if number.chars().all(|c| c.is_ascii_digit()) => {
+                    citations.push(source.trim().to_string());
+                    continue;
+                }
+                _ => in_sources = false,
+            }
+        }
+        for word in trimmed.split_whitespace() {
+            if word.starts_with("http://") || word.starts_with("https://") {
+                let url = word.trim_end_matches(['.', ',', ')', ']', '>', ';']);
+                citations.push(url.trim_start_matches('<').to_string());
+            }
+        }
+    }
+    citations
+}
+
+/// Sentences of the message outside code fences and source lists
+fn prose_sentences(content: &str) -> Vec<String> {
+    let mut prose = String::new();
+    let mut in_fence = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if trimmed == "Sources:" {
+            break;
+        }
+        if !in_fence {
+            prose.push_str(trimmed.trim_start_matches(['-', '*', '#', '>']).trim());
+            prose.push('\n');
+        }
+    }
+    sentences(&prose)
+}
+
+fn sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            if !current.trim().is_empty() {
+                sentences.push(current.trim().to_string());
+            }
+            current.clear();
+            continue;
+        }
+        current.push(c);
+        if matches!(c, '.' | '?' | '!') && chars.peek().map_or(true, |next| next.is_whitespace()) {
+            sentences.push(current.trim().to_string());
+            current.clear();
+        }
+    }
+    if !current.trim().is_empty() {
+        sentences.push(current.trim().to_string());
+    }
+    sentences
+}
+
+/// Questions in the last message nobody has replied to: the user's, when the
+/// assistant has not answered, or the assistant's, when the user has not
+fn open_questions(messages: &[ConversationMessage]) -> Vec<String> {
+    let Some(last) = messages.iter().rev().find(|m| matches!(m.role, MessageRole::User | MessageRole::Assistant)) else {
+        return Vec::new();
+    };
+    let mut questions = Vec::new();
+    for sentence in prose_sentences(&last.content) {
+        if sentence.ends_with('?') {
+            push_unique(&mut questions, &sentence);
+        }
+    }
+    questions
+}
+
+fn transcript(messages: &[ConversationMessage]) -> String {
+    let start = messages.len().saturating_sub(SUMMARY_TRANSCRIPT_MESSAGES);
+    messages[start..]
+        .iter()
+        .map(|m| {
+            let role = match m.role {
+                MessageRole::User => "User",
+                MessageRole::Assistant => "Assistant",
+                MessageRole::System => "System",
+                MessageRole::Tool => "Tool",
+            };
+            format!("{}: {}", role, shorten(m.content.trim(), 2000))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn message(role: MessageRole, content: &str) -> ConversationMessage {
+        ConversationMessage {
+            role,
+            content: content.to_string(),
+            timestamp: Utc::now(),
+            token_count: 0,
+            metadata: HashMap::new(),
+            usage: None,
+            model: None,
+            parts: Vec::new(),
+        }
+    }
+
+    fn conversation() -> Vec<ConversationMessage> {
+        vec![
+            message(MessageRole::User, "Checkout latency doubled after the 14:00 deploy\nAny idea why?"),
+            message(
+                MessageRole::Assistant,
+                "I checked the pods:\n```console\n$ kubectl get pods -n checkout\nNAME READY\n```\n\
+                 The root cause appears to be connection pool exhaustion. Latency is otherwise normal.\n\n\
+                 See https://wiki.example.com/pools.\n\nSources:\n1. runbook/checkout.md\n2. wiki/deploys",
+            ),
+            message(MessageRole::User, "$ kubectl rollout undo deploy/checkout\nThat did not help."),
+            message(
+                MessageRole::Assistant,
+                "The pool size is set by DB_POOL_SIZE. Was it changed in the deploy? Who owns the database?",
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_bundle_collects_findings_commands_and_citations() {
+        let bundle = HandoffBundle::from_messages("s1", &conversation()).unwrap();
+
+        assert_eq!(bundle.title, "Checkout latency doubled after the 14:00 deploy");
+        assert_eq!(
+            bundle.findings,
+            vec!["The root cause appears to be connection pool exhaustion."]
+        );
+        assert_eq!(
+            bundle.commands,
+            vec!["kubectl get pods -n checkout", "kubectl rollout undo deploy/checkout"]
+        );
+        assert_eq!(
+            bundle.citations,
+            vec!["https://wiki.example.com/pools", "runbook/checkout.md", "wiki/deploys"]
+        );
+        assert_eq!(
+            bundle.open_questions,
+            vec!["Was it changed in the deploy?", "Who owns the database?"]
+        );
+        assert_eq!(bundle.message_count, 4);
+        assert!(bundle.summary.starts_with("4 messages"));
+        assert!(bundle.summary_task().contains("User: Checkout latency doubled"));
+        assert!(HandoffBundle::from_messages("s1", &[]).is_none());
+    }
+
+    #[test]
+    fn test_markdown_document() {
+        let bundle = HandoffBundle::from_messages("s1", &conversation()[..1])
+            .unwrap()
+            .with_summary("Latency regression after a deploy, not investigated yet.")
+            .with_notes(Some("Paging the checkout on-call".to_string()));
+
+        let doc = bundle.to_markdown();
+        assert!(doc.starts_with("# Handoff: Checkout latency doubled after the 14:00 deploy\n"));
+        assert!(doc.contains("## Summary\n\nLatency regression after a deploy, not investigated yet.\n"));
+        assert!(doc.contains("## Notes\n\nPaging the checkout on-call\n"));
+        assert!(doc.contains("## Commands executed\n\nNone recorded.\n"));
+        assert!(doc.contains("## Open questions\n\n- Any idea why?\n"));
+    }
+}
@@ -1,6 +1,6 @@
 //! Conversation history management with search and export capabilities
 
-use crate::{Result, ConversationError};
+use crate::{anonymize::Anonymizer, tool_transcript::MessagePart, usage::TokenUsage, Result, ConversationError};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -15,6 +15,19 @@ pub enum MessageRole {
     Assistant,
     /// System message
     System,
+    /// Tool call made by the assistant and its result
+    Tool,
+}
+
+impl MessageRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+            MessageRole::System => "system",
+            MessageRole::Tool => "tool",
+        }
+    }
 }
 
 /// A single message in conversation history
@@ -31,6 +44,15 @@ pub struct ConversationMessage {
     /// Optional metadata
     #[serde(default)]
     pub metadata: HashMap<String, String>,
+    /// Tokens and cost of producing this message (assistant messages only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+    /// Model that produced this message (assistant messages only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Tool calls, their results and sandbox output (tool messages only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<MessagePart>,
 }
 
 /// Search query for conversation history
@@ -261,12 +283,35 @@ impl HistoryManager {
         info!("Exporting history for session {} as {:?}", session_id, format);
 
         let messages = self.history.get(session_id).cloned().unwrap_or_default();
+        self.render(&messages, format)
+    }
+
+    /// Export conversation history with personal data replaced by
+    /// `anonymizer`'s pseudonyms; the mapping back stays in the anonymizer
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session identifier
+    /// * `format` - Export format
+    /// * `anonymizer` - Pseudonyms to use, extended with any new values
+    pub async fn export_anonymized(
+        &self,
+        session_id: &str,
+        format: ExportFormat,
+        anonymizer: &mut Anonymizer,
+    ) -> Result<String> {
+        info!("Exporting anonymized history for session {} as {:?}", session_id, format);
+
+        let messages = self.history.get(session_id).cloned().unwrap_or_default();
+        self.render(&anonymizer.anonymize_messages(&messages), format)
+    }
 
+    fn render(&self, messages: &[ConversationMessage], format: ExportFormat) -> Result<String> {
         let output = match format {
-            ExportFormat::Json => self.export_as_json(&messages)?,
-            ExportFormat::Markdown => self.export_as_markdown(&messages),
-            ExportFormat::Text => self.export_as_text(&messages),
-            ExportFormat::Csv => self.export_as_csv(&messages),
+            ExportFormat::Json => self.export_as_json(messages)?,
+            ExportFormat::Markdown => self.export_as_markdown(messages),
+            ExportFormat::Text => self.export_as_text(messages),
+            ExportFormat::Csv => self.export_as_csv(messages),
         };
 
         Ok(output)
@@ -308,6 +353,7 @@ impl HistoryManager {
             user_messages: 0,
             assistant_messages: 0,
             system_messages: 0,
+            tool_messages: 0,
             total_tokens: 0,
             average_message_length: 0.0,
         };
@@ -319,6 +365,7 @@ impl HistoryManager {
                 MessageRole::User => stats.user_messages += 1,
                 MessageRole::Assistant => stats.assistant_messages += 1,
                 MessageRole::System => stats.system_messages += 1,
+                MessageRole::Tool => stats.tool_messages += 1,
             }
             stats.total_tokens += msg.token_count;
             total_length += msg.content.len();
@@ -386,14 +433,20 @@ impl HistoryManager {
                 MessageRole::User => "User",
                 MessageRole::Assistant => "Assistant",
                 MessageRole::System => "System",
+                MessageRole::Tool => "Tool",
             };
 
             output.push_str(&format!(
-                "## {} - {}\n\n{}\n\n---\n\n",
+                "## {} - {}\n\n{}\n\n",
                 role,
                 msg.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
                 msg.content
             ));
+            for part in &msg.parts {
+                output.push_str(&part.to_markdown());
+                output.push('\n');
+            }
+            output.push_str("---\n\n");
         }
 
         output
@@ -409,15 +462,21 @@ impl HistoryManager {
                 MessageRole::User => "USER",
                 MessageRole::Assistant => "ASSISTANT",
                 MessageRole::System => "SYSTEM",
+                MessageRole::Tool => "TOOL",
             };
 
             output.push_str(&format!(
-                "[{}] {} ({})\n{}\n\n",
+                "[{}] {} ({})\n{}\n",
                 msg.timestamp.format("%Y-%m-%d %H:%M:%S"),
                 role,
                 msg.token_count,
                 msg.content
             ));
+            for part in &msg.parts {
+                output.push_str(&part.to_text());
+                output.push('\n');
+            }
+            output.push('\n');
         }
 
         output
@@ -427,11 +486,7 @@ impl HistoryManager {
         let mut output = String::from("timestamp,role,content,token_count\n");
 
         for msg in messages {
-            let role = match msg.role {
-                MessageRole::User => "user",
-                MessageRole::Assistant => "assistant",
-                MessageRole::System => "system",
-            };
+            let role = msg.role.as_str();
 
             // Escape CSV content
             let content = msg.content.replace('"', "\"\"");
@@ -462,6 +517,7 @@ pub struct HistoryStatistics {
     pub user_messages: usize,
     pub assistant_messages: usize,
     pub system_messages: usize,
+    pub tool_messages: usize,
     pub total_tokens: usize,
     pub average_message_length: f64,
 }
@@ -481,6 +537,9 @@ mod tests {
             timestamp: Utc::now(),
             token_count: 3,
             metadata: HashMap::new(),
+            usage: None,
+            model: None,
+            parts: Vec::new(),
         };
 
         manager.append_message(session_id, message).await.unwrap();
@@ -503,6 +562,9 @@ mod tests {
                 timestamp: Utc::now(),
                 token_count: 5,
                 metadata: HashMap::new(),
+                usage: None,
+                model: None,
+                parts: Vec::new(),
             },
         ).await.unwrap();
 
@@ -532,6 +594,9 @@ mod tests {
                 timestamp: Utc::now(),
                 token_count: 2,
                 metadata: HashMap::new(),
+                usage: None,
+                model: None,
+                parts: Vec::new(),
             },
         ).await.unwrap();
 
@@ -3,19 +3,104 @@
 //! This crate provides conversation management capabilities including:
 //! - Multi-turn dialogue with context retention
 //! - Session management with token tracking
-//! - Response streaming with SSE support
+//! - Response streaming with SSE support, resumable after a client disconnects
 //! - Conversation history with search and export
 //! - Reference resolution for natural dialogue
+//! - Extraction of code edits proposed as unified diffs
+//! - Per-message token usage and cost accounting
+//! - Per-message model overrides and side-by-side model comparisons
+//! - A/B experiments over prompts, models and retrieval settings, with significance tests
+//! - Per-tenant and per-persona pipeline profiles for prompts, model routing and retrieval
+//! - Model fallback chains failing over between providers on outages and rate limits
+//! - Retrieved context cut to fit models whose context window is smaller than the budget
+//! - Per-conversation tool allowlists and sandbox policies
+//! - Memoized tool calls within a conversation, with per-tool TTLs
+//! - Tool call transcripts in conversation history, with sensitive arguments redacted
+//! - Token-budgeted working memory per conversation for the agent's notes
+//! - Daily token budgets per conversation, answering more cheaply near the budget
+//! - Reuse of a conversation's context window while its inputs and the corpus are unchanged
+//! - Cached grounded answers, invalidated when a cited source is re-ingested
+//! - Outcomes of answered questions, for finding what the corpus cannot answer
+//! - End-to-end answer evaluation with rubric-graded, cached judgments
+//! - Inline groundedness scoring of answers, with a review queue for weak ones
+//! - Transcript anonymization with consistent pseudonyms
+//! - One turn at a time per conversation under concurrent clients
+//! - Configurable post-processing stages for generated responses
+//! - Per-tenant license and package policies for generated code
+//! - Handoff bundles for escalating conversations to humans
+//! - Per-user preference memory, learned only with the user's confirmation
 
 pub mod manager;
 pub mod session;
 pub mod streaming;
+pub mod resume;
 pub mod history;
+pub mod persona;
+pub mod edits;
+pub mod usage;
+pub mod token_budget;
+pub mod comparison;
+pub mod experiments;
+pub mod model_fallback;
+pub mod context_limits;
+pub mod tool_policy;
+pub mod tool_cache;
+pub mod tool_transcript;
+pub mod working_memory;
+pub mod window_cache;
+pub mod answer_cache;
+pub mod outcome;
+pub mod eval;
+pub mod groundedness;
+pub mod anonymize;
+pub mod turn_lock;
+pub mod postprocess;
+pub mod code_policy;
+pub mod handoff;
+pub mod preferences;
+pub mod profiles;
 
 pub use manager::ConversationManager;
 pub use session::{Session, SessionManager, SessionState};
-pub use streaming::{StreamingResponse, StreamChunk};
+pub use streaming::{StreamCounters, StreamStats, StreamingResponse, StreamChunk};
+pub use resume::{EventStream, ResumableStreams, ResumeConfig, StreamEvent};
 pub use history::{HistoryManager, ConversationMessage, MessageRole};
+pub use persona::{Persona, PersonaRegistry, ModelPreferences};
+pub use edits::{extract_edits, DiffHunk, FileEdit};
+pub use usage::{ModelPricing, PricingTable, TokenUsage};
+pub use token_budget::{BudgetStatus, TokenBudgetConfig, TokenBudgets, TruncationStrategy};
+pub use tool_policy::{SandboxRunner, ToolDenial, ToolPolicy, SANDBOX_TOOL};
+pub use tool_cache::{ToolCache, ToolCacheConfig, ToolCacheStats, ToolResult};
+pub use tool_transcript::{MessagePart, ToolRedaction};
+pub use window_cache::{WindowCache, WindowCacheStats};
+pub use answer_cache::{AnswerCache, AnswerCacheConfig, AnswerCacheStats, CachedAnswer, SourceVersion};
+pub use outcome::{AnswerObserver, AnswerOutcome, CompositeAnswerObserver};
+pub use working_memory::{NoteKind, WorkingMemory, WorkingMemoryView, WorkingNote, DEFAULT_WORKING_MEMORY_BUDGET};
+pub use context_limits::{ContextDownsize, ContextFitStats, ContextLimits};
+pub use model_fallback::{FallbackConfig, FallbackStats, ModelFallbackChain, ProviderHealth, ProviderStats};
+pub use comparison::{preference_stats, ComparedResponse, ModelComparison, ModelPreferenceStats};
+pub use experiments::{
+    Experiment, ExperimentAssignment, ExperimentMetric, ExperimentResults, ExperimentSpec, ExperimentStatus, Experiments,
+    MetricSummary, RetrievalOverrides, SignificanceTest, TurnMetrics, Variant, VariantResults,
+};
+pub use anonymize::{Anonymizer, PseudonymMap};
+pub use turn_lock::{TurnGuard, TurnLocks};
+pub use handoff::{HandoffBundle, HANDOFF_SUMMARY_PROMPT};
+pub use profiles::{PipelineProfile, PipelineProfiles, RetrievalProfile};
+pub use preferences::{LearnedPreference, PreferenceStore, PreferenceSuggestion, UserPreferences, Verbosity};
+pub use groundedness::{
+    ClaimSupport, ContextPassage, GroundedAnswer, GroundednessMonitor, GroundednessReview, GroundednessScore,
+    GroundednessScorer, GroundednessStats, JudgedScorer, OverlapScorer,
+};
+pub use code_policy::{
+    CodeFinding, CodeFindingKind, CodePolicy, CodePolicyAction, CodePolicyConfig, CodePolicyDecision, CodePolicyLog,
+    CodePolicyOutcome, CodePolicyStage, ReferenceSnippet,
+};
+pub use postprocess::{PostProcessingConfig, PostProcessor, ResponseContext, ResponseStage, StageConfig};
+pub use eval::{
+    AnswerPipeline, EvalCase, EvalCriterion, EvalReport, EvalRun, EvalRunner, EvalSuite, JudgeModel,
+    Judgment, RubricJudge,
+};
 
 use thiserror::Error;
 
@@ -40,15 +125,80 @@ pub enum ConversationError {
     #[error("Streaming error: {0}")]
     StreamingError(String),
 
+    #[error("Stream not found: {0}")]
+    StreamNotFound(String),
+
+    #[error("Stream cannot be resumed: {0}")]
+    StreamNotResumable(String),
+
     #[error("Context error: {0}")]
     ContextError(String),
 
     #[error("NLP processing error: {0}")]
     NlpError(String),
 
+    #[error("Persona not found: {0}")]
+    PersonaNotFound(String),
+
+    #[error("Invalid persona: {0}")]
+    InvalidPersona(String),
+
+    #[error("Tool {tool} is not allowed for persona {persona}")]
+    ToolNotAllowed { tool: String, persona: String },
+
+    #[error("Tool {tool} is not allowed in session {session}")]
+    ToolNotAllowedInSession { tool: String, session: String },
+
+    #[error("Tool call failed: {0}")]
+    ToolError(String),
+
+    #[error("Tool {0} is not available")]
+    ToolUnavailable(String),
+
+    #[error("Preference suggestion not found: {0}")]
+    PreferenceNotFound(String),
+
+    #[error("Invalid preference: {0}")]
+    InvalidPreference(String),
+
+    #[error("Evaluation error: {0}")]
+    EvalError(String),
+
+    #[error("Post-processing error: {0}")]
+    PostProcessingError(String),
+
+    #[error("Model {model} unavailable: {reason}")]
+    ModelUnavailable { model: String, reason: String },
+
+    #[error("Model {model} rate limited")]
+    ModelRateLimited {
+        model: String,
+        retry_after: Option<std::time::Duration>,
+    },
+
+    #[error("Overloaded: {0}")]
+    Overloaded(#[from] copilot_core::QueueTimeout),
+
     #[error("Token limit exceeded: used {used}, limit {limit}")]
     TokenLimitExceeded { used: usize, limit: usize },
 
+    #[error("Token budget of conversation {conversation} exhausted ({available} of {daily_tokens} daily tokens left); resets at {resets_at}")]
+    TokenBudgetExhausted {
+        conversation: String,
+        daily_tokens: usize,
+        available: usize,
+        resets_at: chrono::DateTime<chrono::Utc>,
+    },
+
+    #[error("Invalid token budget: {0}")]
+    InvalidTokenBudget(String),
+
+    #[error("Experiment not found: {0}")]
+    ExperimentNotFound(String),
+
+    #[error("Invalid experiment: {0}")]
+    InvalidExperiment(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
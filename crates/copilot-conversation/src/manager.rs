@@ -1,18 +1,50 @@
 //! Conversation manager for handling multi-turn dialogue
 
 use crate::{
+    answer_cache::{AnswerCache, AnswerCacheStats},
+    comparison::{preference_stats, ComparedResponse, ModelComparison, ModelPreferenceStats},
+    context_limits::{ContextDownsize, ContextFitStats, ContextLimits},
+    edits::{extract_edits, FileEdit},
+    eval::JudgeModel,
+    experiments::{ExperimentAssignment, Experiments, TurnMetrics, Variant},
+    groundedness::{ContextPassage, GroundedAnswer, GroundednessMonitor},
+    handoff::HandoffBundle,
     history::{ConversationMessage, HistoryManager, MessageRole},
-    session::{SessionManager, SessionState},
-    streaming::StreamingResponse,
+    model_fallback::ModelFallbackChain,
+    outcome::{AnswerObserver, AnswerOutcome},
+    persona::{Persona, PersonaRegistry},
+    preferences::{PreferenceStore, PreferenceSuggestion, UserPreferences},
+    profiles::{PipelineProfile, PipelineProfiles},
+    session::{Session, SessionManager, SessionState},
+    postprocess::{PostProcessor, ResponseContext},
+    resume::{EventStream, ResumableStreams, ResumeConfig},
+    streaming::{StreamCounters, StreamStats, StreamingResponse},
+    token_budget::{BudgetStatus, TokenBudgets, TruncationStrategy},
+    tool_cache::{ToolCache, ToolResult},
+    tool_policy::{SandboxRunner, ToolDenial, ToolPolicy, SANDBOX_TOOL},
+    tool_transcript::{MessagePart, ToolRedaction},
+    turn_lock::TurnLocks,
+    usage::{PricingTable, TokenUsage},
+    window_cache::{WindowCache, WindowCacheStats},
+    working_memory::{WorkingMemory, WorkingNote},
     Result, ConversationError,
 };
 use async_trait::async_trait;
-use copilot_context::ContextEngine;
+use copilot_core::{FairPermit, FairScheduler, SandboxPolicy};
+use copilot_context::retrieval::RetrievalResult;
+use copilot_context::{
+    ContextEngine, ContextPrefetcher, ContextStage, ContextStageObserver, ContextWindowDiff,
+    ContextWindowSnapshot, ContextWindowTracker, FreshnessCounters, FreshnessStats, PrefetchOutcome,
+    PrefetchStats, StageRecord, Trust,
+};
 use copilot_nlp::NlpEngine;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::RwLock;
-use tracing::{debug, info, warn};
+use tracing::{debug, info, warn, Span};
 
 /// Request for processing a user message
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +56,9 @@ pub struct MessageRequest {
     /// Optional metadata
     #[serde(default)]
     pub metadata: std::collections::HashMap<String, String>,
+    /// Model to answer this message with instead of the session's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
 }
 
 /// Response containing the assistant's reply
@@ -40,6 +75,21 @@ pub struct MessageResponse {
     pub tokens_used: usize,
     /// Total tokens used in session
     pub total_tokens: usize,
+    /// Tokens and cost of this exchange
+    #[serde(default)]
+    pub usage: TokenUsage,
+    /// Running tokens and cost of the whole session
+    #[serde(default)]
+    pub session_usage: TokenUsage,
+    /// Model that answered, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Preferences learned from this message, for the user to confirm
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preference_suggestions: Vec<PreferenceSuggestion>,
+    /// How the retrieved context was cut to fit the model, if it was
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_downsize: Option<ContextDownsize>,
 }
 
 /// A resolved reference from the conversation
@@ -56,9 +106,36 @@ pub struct ResolvedReference {
 /// Main conversation manager
 pub struct ConversationManager {
     nlp_engine: Arc<dyn NlpEngine>,
+    /// The prefetcher, serving retrievals warmed while the user typed
     context_engine: Arc<dyn ContextEngine>,
+    prefetcher: Arc<ContextPrefetcher>,
     session_manager: Arc<RwLock<SessionManager>>,
     history_manager: Arc<RwLock<HistoryManager>>,
+    window_tracker: Arc<ContextWindowTracker>,
+    persona_registry: Arc<RwLock<PersonaRegistry>>,
+    preferences: Arc<PreferenceStore>,
+    pricing: PricingTable,
+    comparisons: Arc<RwLock<HashMap<String, Vec<ModelComparison>>>>,
+    stream_counters: Arc<StreamCounters>,
+    freshness_counters: Arc<FreshnessCounters>,
+    post_processor: PostProcessor,
+    groundedness: Option<Arc<GroundednessMonitor>>,
+    answer_cache: Option<Arc<AnswerCache>>,
+    answer_observer: std::sync::RwLock<Option<Arc<dyn AnswerObserver>>>,
+    stage_observer: std::sync::RwLock<Option<Arc<dyn ContextStageObserver>>>,
+    llm_slots: Option<Arc<FairScheduler>>,
+    tool_cache: Arc<ToolCache>,
+    tool_redaction: ToolRedaction,
+    sandbox_runner: std::sync::RwLock<Option<Arc<dyn SandboxRunner>>>,
+    working_memory: Arc<WorkingMemory>,
+    window_cache: WindowCache,
+    turn_locks: TurnLocks,
+    resumable_streams: ResumableStreams,
+    token_budgets: Option<Arc<TokenBudgets>>,
+    model_chain: Option<Arc<ModelFallbackChain>>,
+    context_limits: ContextLimits,
+    experiments: Arc<Experiments>,
+    profiles: Option<Arc<PipelineProfiles>>,
 }
 
 impl ConversationManager {
@@ -69,14 +146,327 @@ impl ConversationManager {
     /// * `nlp_engine` - NLP engine for language processing
     /// * `context_engine` - Context engine for maintaining conversation context
     pub fn new(nlp_engine: Arc<dyn NlpEngine>, context_engine: Arc<dyn ContextEngine>) -> Self {
+        let prefetcher = Arc::new(ContextPrefetcher::new(context_engine));
         Self {
             nlp_engine,
-            context_engine,
+            context_engine: prefetcher.clone(),
+            prefetcher,
             session_manager: Arc::new(RwLock::new(SessionManager::new())),
             history_manager: Arc::new(RwLock::new(HistoryManager::new())),
+            window_tracker: Arc::new(ContextWindowTracker::new()),
+            persona_registry: Arc::new(RwLock::new(PersonaRegistry::with_builtins())),
+            preferences: Arc::new(PreferenceStore::new()),
+            pricing: PricingTable::default(),
+            comparisons: Arc::new(RwLock::new(HashMap::new())),
+            stream_counters: Arc::new(StreamCounters::new()),
+            freshness_counters: Arc::new(FreshnessCounters::new()),
+            post_processor: PostProcessor::new(),
+            groundedness: None,
+            answer_cache: None,
+            answer_observer: std::sync::RwLock::new(None),
+            stage_observer: std::sync::RwLock::new(None),
+            llm_slots: None,
+            tool_cache: Arc::new(ToolCache::default()),
+            tool_redaction: ToolRedaction::default(),
+            sandbox_runner: std::sync::RwLock::new(None),
+            working_memory: Arc::new(WorkingMemory::default()),
+            window_cache: WindowCache::new(),
+            turn_locks: TurnLocks::new(),
+            resumable_streams: ResumableStreams::default(),
+            token_budgets: None,
+            model_chain: None,
+            context_limits: ContextLimits::default(),
+            experiments: Arc::new(Experiments::new()),
+            profiles: None,
+        }
+    }
+
+    /// Replace the context prefetcher (e.g. to add a reranker or change its
+    /// cache size); it must wrap the engine the manager was created with
+    pub fn with_prefetcher(mut self, prefetcher: Arc<ContextPrefetcher>) -> Self {
+        self.context_engine = prefetcher.clone();
+        self.prefetcher = prefetcher;
+        self
+    }
+
+    /// Replace the model prices used to cost each message
+    pub fn with_pricing(mut self, pricing: PricingTable) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// Replace the store of users' preferences
+    pub fn with_preferences(mut self, preferences: Arc<PreferenceStore>) -> Self {
+        self.preferences = preferences;
+        self
+    }
+
+    /// Run generated responses through `post_processor` before they are
+    /// recorded and returned
+    pub fn with_post_processor(mut self, post_processor: PostProcessor) -> Self {
+        self.post_processor = post_processor;
+        self
+    }
+
+    /// Score each generated response against its retrieved context and
+    /// queue poorly grounded ones for review
+    pub fn with_groundedness(mut self, monitor: Arc<GroundednessMonitor>) -> Self {
+        self.groundedness = Some(monitor);
+        self
+    }
+
+    /// Serve grounded answers from `cache` to repeated questions while
+    /// their sources are unchanged
+    pub fn with_answer_cache(mut self, cache: Arc<AnswerCache>) -> Self {
+        self.answer_cache = Some(cache);
+        self
+    }
+
+    /// Answer cache effectiveness, if answers are cached
+    pub fn answer_cache_stats(&self) -> Option<AnswerCacheStats> {
+        self.answer_cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Send the outcome of each answer to `observer`
+    pub fn set_answer_observer(&self, observer: Arc<dyn AnswerObserver>) {
+        *self.answer_observer.write().unwrap_or_else(|e| e.into_inner()) = Some(observer);
+    }
+
+    /// Report every stage of the context pipeline, from retrieval to
+    /// prompt assembly, to `observer`
+    pub fn set_stage_observer(&self, observer: Arc<dyn ContextStageObserver>) {
+        self.prefetcher.set_stage_observer(observer.clone());
+        *self.stage_observer.write().unwrap_or_else(|e| e.into_inner()) = Some(observer);
+    }
+
+    fn observe_stage(&self, span: &Span, record: StageRecord) {
+        record.trace(span);
+        let observer = self.stage_observer.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(observer) = observer {
+            observer.record(&record);
+        }
+    }
+
+    /// Replace the A/B experiments sessions of enrolled tenants take part in
+    pub fn with_experiments(mut self, experiments: Arc<Experiments>) -> Self {
+        self.experiments = experiments;
+        self
+    }
+
+    /// The A/B experiments in progress
+    pub fn experiments(&self) -> &Arc<Experiments> {
+        &self.experiments
+    }
+
+    /// The experiment variant a session takes part with, if its tenant is
+    /// enrolled in a running experiment
+    pub async fn session_variant(&self, session_id: &str) -> Option<(ExperimentAssignment, Variant)> {
+        let tenant_id = self.session_tenant(session_id).await;
+        self.experiments.assign(tenant_id.as_deref(), session_id)
+    }
+
+    /// Answer turns by the prompt, model and retrieval settings of each
+    /// session's tenant and persona in `profiles`
+    pub fn with_profiles(mut self, profiles: Arc<PipelineProfiles>) -> Self {
+        self.profiles = Some(profiles);
+        self
+    }
+
+    /// The pipeline profile a session's turns are answered by, if profiles
+    /// are configured
+    pub async fn session_profile(&self, session_id: &str) -> Option<PipelineProfile> {
+        let profiles = self.profiles.as_ref()?;
+        let tenant_id = self.session_tenant(session_id).await;
+        let persona = self.session_persona(session_id).await;
+        Some(profiles.resolve(tenant_id.as_deref(), persona.as_ref().map(|p| p.id.as_str())))
+    }
+
+    async fn session_tenant(&self, session_id: &str) -> Option<String> {
+        self.session_manager
+            .write()
+            .await
+            .get_session(session_id)
+            .and_then(|session| session.tenant_id.clone())
+    }
+
+    /// Share model calls between tenants through `scheduler`, by the
+    /// current [`copilot_core::TenantShare`]
+    pub fn with_llm_scheduler(mut self, scheduler: Arc<FairScheduler>) -> Self {
+        self.llm_slots = Some(scheduler);
+        self
+    }
+
+    /// Replace the cache of tool results, e.g. to change tools' TTLs
+    pub fn with_tool_cache(mut self, cache: Arc<ToolCache>) -> Self {
+        self.tool_cache = cache;
+        self
+    }
+
+    /// Replace the policy redacting tool arguments recorded in history
+    pub fn with_tool_redaction(mut self, redaction: ToolRedaction) -> Self {
+        self.tool_redaction = redaction;
+        self
+    }
+
+    /// The cache of tool results
+    pub fn tool_cache(&self) -> &Arc<ToolCache> {
+        &self.tool_cache
+    }
+
+    /// Replace the conversations' scratchpads, e.g. to change their budget
+    pub fn with_working_memory(mut self, working_memory: Arc<WorkingMemory>) -> Self {
+        self.working_memory = working_memory;
+        self
+    }
+
+    /// Keep streams resumable for `config.grace` after their client goes away
+    pub fn with_resume_config(mut self, config: ResumeConfig) -> Self {
+        self.resumable_streams = ResumableStreams::new(config);
+        self
+    }
+
+    /// Generate responses with the models of `chain`, failing over between
+    /// its providers
+    pub fn with_model_chain(mut self, chain: Arc<ModelFallbackChain>) -> Self {
+        self.model_chain = Some(chain);
+        self
+    }
+
+    /// The model fallback chain, if responses are generated by one
+    pub fn model_chain(&self) -> Option<&Arc<ModelFallbackChain>> {
+        self.model_chain.as_ref()
+    }
+
+    /// Replace the context windows retrieved context is cut to fit
+    pub fn with_context_limits(mut self, limits: ContextLimits) -> Self {
+        self.context_limits = limits;
+        self
+    }
+
+    /// How often retrieved context was cut to fit the answering model
+    pub fn context_fit_stats(&self) -> ContextFitStats {
+        self.context_limits.stats()
+    }
+
+    /// Limit conversations to daily token budgets, answering them more
+    /// cheaply as they near their budget
+    pub fn with_token_budgets(mut self, budgets: Arc<TokenBudgets>) -> Self {
+        self.token_budgets = Some(budgets);
+        self
+    }
+
+    /// The token budgets, if conversations have any
+    pub fn token_budgets(&self) -> Option<&Arc<TokenBudgets>> {
+        self.token_budgets.as_ref()
+    }
+
+    /// Where a session stands against its daily token budget, if it has one
+    pub async fn budget_status(&self, session_id: &str) -> Option<BudgetStatus> {
+        let (budgets, daily_tokens) = self.session_budget(session_id).await?;
+        Some(budgets.status(session_id, daily_tokens))
+    }
+
+    /// The token budgets and the session's daily budget under them: the
+    /// smaller of its tenant's and persona's
+    async fn session_budget(&self, session_id: &str) -> Option<(&Arc<TokenBudgets>, usize)> {
+        let budgets = self.token_budgets.as_ref()?;
+        let tenant_id = self
+            .session_manager
+            .write()
+            .await
+            .get_session(session_id)
+            .and_then(|session| session.tenant_id.clone());
+        let persona = self.session_persona(session_id).await;
+        let daily_tokens = budgets
+            .config()
+            .daily_tokens(tenant_id.as_deref(), persona.as_ref().map(|p| p.id.as_str()))?;
+        Some((budgets, daily_tokens))
+    }
+
+    /// Check that the session's budget covers a turn of `message`, and how
+    /// to truncate the turn's prompt
+    async fn check_budget(&self, session_id: &str, message: &str) -> Result<TruncationStrategy> {
+        match self.session_budget(session_id).await {
+            Some((budgets, daily_tokens)) => {
+                Ok(budgets.check(session_id, daily_tokens, self.estimate_tokens(message))?.strategy)
+            }
+            None => Ok(TruncationStrategy::Standard),
+        }
+    }
+
+    /// Take the tokens of a turn from the session's budget
+    async fn charge_budget(&self, session_id: &str, tokens: usize) {
+        if let Some((budgets, daily_tokens)) = self.session_budget(session_id).await {
+            budgets.charge(session_id, daily_tokens, tokens);
+        }
+    }
+
+    /// Model to answer a turn with: the requested one, the cheapest the
+    /// session may use when near its budget, its experiment variant's, its
+    /// profile's, otherwise the session's own
+    async fn turn_model(&self, request: &MessageRequest, strategy: TruncationStrategy) -> Option<String> {
+        if let Some(model) = &request.model {
+            return Some(model.clone());
+        }
+        let profile_models = self
+            .session_profile(&request.session_id)
+            .await
+            .map(|profile| profile.models)
+            .unwrap_or_default();
+        if strategy != TruncationStrategy::Standard {
+            if let Some(budgets) = &self.token_budgets {
+                let mut candidates = self
+                    .session_persona(&request.session_id)
+                    .await
+                    .map(|persona| persona.model.preferred_models)
+                    .unwrap_or_default();
+                candidates.extend(profile_models.iter().cloned());
+                if let Some(model) = budgets.cheapest_model(&self.pricing, &candidates) {
+                    debug!("Session {} is near its token budget, answering with {}", request.session_id, model);
+                    return Some(model);
+                }
+            }
+        }
+        if let Some(model) = self.session_variant(&request.session_id).await.and_then(|(_, variant)| variant.model) {
+            return Some(model);
+        }
+        if let Some(model) = profile_models.into_iter().next() {
+            return Some(model);
+        }
+        self.session_model(&request.session_id).await
+    }
+
+    /// The working notes tools and workflow steps keep per conversation
+    pub fn working_memory(&self) -> &Arc<WorkingMemory> {
+        &self.working_memory
+    }
+
+    /// Write a note to an existing session's working memory
+    pub async fn write_working_note(&self, session_id: &str, note: WorkingNote) -> Result<WorkingNote> {
+        if self.session_manager.write().await.get_session(session_id).is_none() {
+            return Err(ConversationError::SessionNotFound(session_id.to_string()));
+        }
+        self.working_memory.write(session_id, note)
+    }
+
+    /// The model call scheduler, if model calls are queued
+    pub fn llm_scheduler(&self) -> Option<&Arc<FairScheduler>> {
+        self.llm_slots.as_ref()
+    }
+
+    /// Wait for a model call slot, if model calls are queued
+    async fn llm_slot(&self) -> Result<Option<FairPermit>> {
+        match &self.llm_slots {
+            Some(scheduler) => Ok(Some(scheduler.acquire(1.0).await?)),
+            None => Ok(None),
         }
     }
 
+    /// The groundedness monitor, if scoring is enabled
+    pub fn groundedness(&self) -> Option<&Arc<GroundednessMonitor>> {
+        self.groundedness.as_ref()
+    }
+
     /// Process a user message
     ///
     /// This is the main entry point for handling user messages. It:
@@ -86,12 +476,138 @@ impl ConversationManager {
     /// 4. Generates response
     /// 5. Updates history
     ///
+    /// Messages in the same session are processed one at a time, in arrival
+    /// order.
+    ///
     /// # Arguments
     ///
     /// * `request` - The message request to process
     pub async fn process_message(&self, request: MessageRequest) -> Result<MessageResponse> {
         info!("Processing message for session: {}", request.session_id);
 
+        let _turn = self.turn_locks.acquire(&request.session_id).await;
+        let strategy = self.check_budget(&request.session_id, &request.message).await?;
+        let (resolved_refs, enhanced_message) = self.begin_turn(&request).await?;
+        let preference_suggestions = self.learn_preferences(&request).await;
+
+        let model = self.turn_model(&request, strategy).await;
+        let (response, usage, context_downsize) = self
+            .answer(&request, &enhanced_message, model.clone(), HashMap::new())
+            .await?;
+
+        // Update session token count
+        let total_tokens = usage.total_tokens;
+
+        let mut session_mgr = self.session_manager.write().await;
+        session_mgr.update_session(&request.session_id, total_tokens).await?;
+        let session = session_mgr.get_session(&request.session_id).unwrap();
+        let session_total_tokens = session.total_tokens;
+        drop(session_mgr);
+        let session_usage = self.session_usage(&request.session_id).await;
+
+        Ok(MessageResponse {
+            session_id: request.session_id,
+            response,
+            resolved_references: resolved_refs,
+            tokens_used: total_tokens,
+            total_tokens: session_total_tokens,
+            usage,
+            session_usage,
+            model,
+            preference_suggestions,
+            context_downsize,
+        })
+    }
+
+    /// Send one message to two models and keep both answers side by side
+    ///
+    /// Both answers are added to the session history, tagged with the
+    /// comparison id, and the comparison is kept for a later preference vote.
+    pub async fn compare_models(
+        &self,
+        request: MessageRequest,
+        models: [String; 2],
+    ) -> Result<ModelComparison> {
+        info!(
+            "Comparing {} and {} in session: {}",
+            models[0], models[1], request.session_id
+        );
+        if models[0] == models[1] {
+            return Err(ConversationError::InvalidMessage(
+                "A comparison needs two different models".to_string(),
+            ));
+        }
+
+        let _turn = self.turn_locks.acquire(&request.session_id).await;
+        self.check_budget(&request.session_id, &request.message).await?;
+        let (_, enhanced_message) = self.begin_turn(&request).await?;
+
+        let mut comparison = ModelComparison::new(&request.session_id, &request.message, Vec::new());
+        for model in models {
+            let metadata = HashMap::from([("comparison_id".to_string(), comparison.id.clone())]);
+            let (response, usage, _) = self
+                .answer(&request, &enhanced_message, Some(model.clone()), metadata)
+                .await?;
+            comparison.responses.push(ComparedResponse { model, response, usage });
+        }
+
+        let total_tokens = comparison.responses.iter().map(|r| r.usage.total_tokens).sum();
+        self.session_manager
+            .write()
+            .await
+            .update_session(&request.session_id, total_tokens)
+            .await?;
+
+        self.comparisons
+            .write()
+            .await
+            .entry(request.session_id)
+            .or_default()
+            .push(comparison.clone());
+        Ok(comparison)
+    }
+
+    /// Record which model's answer the user preferred in a comparison
+    pub async fn vote_comparison(
+        &self,
+        session_id: &str,
+        comparison_id: &str,
+        preferred_model: &str,
+    ) -> Result<ModelComparison> {
+        let mut comparisons = self.comparisons.write().await;
+        let comparison = comparisons
+            .get_mut(session_id)
+            .and_then(|list| list.iter_mut().find(|c| c.id == comparison_id))
+            .ok_or_else(|| {
+                ConversationError::InvalidMessage(format!("Comparison not found: {}", comparison_id))
+            })?;
+        comparison.vote(preferred_model)?;
+        info!(
+            "Comparison {} in session {}: preferred {}",
+            comparison_id, session_id, preferred_model
+        );
+        Ok(comparison.clone())
+    }
+
+    /// Comparisons run in a session, oldest first
+    pub async fn comparisons(&self, session_id: &str) -> Vec<ModelComparison> {
+        self.comparisons
+            .read()
+            .await
+            .get(session_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Preference counts per model across all sessions' voted comparisons
+    pub async fn model_preference_stats(&self) -> HashMap<String, ModelPreferenceStats> {
+        let comparisons = self.comparisons.read().await;
+        preference_stats(comparisons.values().flatten())
+    }
+
+    /// Validate the session, resolve references and record the user message;
+    /// returns the resolved references and the message enhanced with them
+    async fn begin_turn(&self, request: &MessageRequest) -> Result<(Vec<ResolvedReference>, String)> {
         // Get or create session
         let mut session_mgr = self.session_manager.write().await;
         let session = session_mgr
@@ -100,7 +616,7 @@ impl ConversationManager {
 
         // Check if session is expired
         if session.state == SessionState::Expired {
-            return Err(ConversationError::SessionExpired(request.session_id));
+            return Err(ConversationError::SessionExpired(request.session_id.clone()));
         }
 
         drop(session_mgr);
@@ -122,13 +638,46 @@ impl ConversationManager {
                 timestamp: chrono::Utc::now(),
                 token_count: self.estimate_tokens(&request.message),
                 metadata: request.metadata.clone(),
+                usage: None,
+                model: None,
+                parts: Vec::new(),
             },
         ).await?;
         drop(history_mgr);
 
-        // Generate response
-        let response = self.generate_response(&request.session_id, &enhanced_message).await?;
+        Ok((resolved_refs, enhanced_message))
+    }
+
+    /// Generate a reply with `model` and record it in the history, noting
+    /// in its metadata when the context was cut to fit the model
+    async fn answer(
+        &self,
+        request: &MessageRequest,
+        enhanced_message: &str,
+        model: Option<String>,
+        mut metadata: HashMap<String, String>,
+    ) -> Result<(String, TokenUsage, Option<ContextDownsize>)> {
+        let started = Instant::now();
+        let (response, outcome) = self
+            .respond(&request.session_id, enhanced_message, model.as_deref())
+            .await?;
+        let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+        self.observe(&outcome);
         let response_tokens = self.estimate_tokens(&response);
+        let message_tokens = self.estimate_tokens(&request.message);
+        let usage = self.pricing.usage(model.as_deref(), message_tokens, response_tokens);
+        self.charge_budget(&request.session_id, usage.total_tokens).await;
+        if let Some(assignment) = &outcome.experiment {
+            let metrics = TurnMetrics {
+                latency_ms,
+                cost_usd: usage.cost_usd,
+                groundedness: outcome.confidence,
+            };
+            self.experiments.record_turn(&request.session_id, assignment, metrics);
+        }
+        if let Some(downsize) = &outcome.context_downsize {
+            metadata.insert("context_downsized".to_string(), downsize.note());
+        }
 
         // Add assistant message to history
         let mut history_mgr = self.history_manager.write().await;
@@ -139,27 +688,34 @@ impl ConversationManager {
                 content: response.clone(),
                 timestamp: chrono::Utc::now(),
                 token_count: response_tokens,
-                metadata: std::collections::HashMap::new(),
+                metadata,
+                usage: Some(usage),
+                model,
+                parts: Vec::new(),
             },
         ).await?;
-        drop(history_mgr);
 
-        // Update session token count
-        let message_tokens = self.estimate_tokens(&request.message);
-        let total_tokens = message_tokens + response_tokens;
+        Ok((response, usage, outcome.context_downsize))
+    }
 
-        let mut session_mgr = self.session_manager.write().await;
-        session_mgr.update_session(&request.session_id, total_tokens).await?;
-        let session = session_mgr.get_session(&request.session_id).unwrap();
-        let session_total_tokens = session.total_tokens;
+    /// Total tokens and cost of a session's messages so far
+    pub async fn session_usage(&self, session_id: &str) -> TokenUsage {
+        let history_mgr = self.history_manager.read().await;
+        history_mgr
+            .get_all_messages(session_id)
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|m| m.usage)
+            .sum()
+    }
 
-        Ok(MessageResponse {
-            session_id: request.session_id,
-            response,
-            resolved_references: resolved_refs,
-            tokens_used: total_tokens,
-            total_tokens: session_total_tokens,
-        })
+    /// Model a session's messages are priced at: its persona's first
+    /// preference, otherwise the pricing table's default
+    async fn session_model(&self, session_id: &str) -> Option<String> {
+        self.session_persona(session_id)
+            .await
+            .and_then(|persona| persona.model.preferred_models.first().cloned())
     }
 
     /// Generate an assistant response
@@ -169,16 +725,61 @@ impl ConversationManager {
     /// * `session_id` - The session identifier
     /// * `message` - The enhanced message with resolved references
     pub async fn generate_response(&self, session_id: &str, message: &str) -> Result<String> {
+        let (response, outcome) = self.respond(session_id, message, None).await?;
+        self.observe(&outcome);
+        Ok(response)
+    }
+
+    fn observe(&self, outcome: &AnswerOutcome) {
+        let observer = self.answer_observer.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(observer) = observer {
+            observer.observe(outcome);
+        }
+    }
+
+    /// Generate a response with `model`, or the chain's active model, and
+    /// sum up how it was answered
+    async fn respond(&self, session_id: &str, message: &str, model: Option<&str>) -> Result<(String, AnswerOutcome)> {
         debug!("Generating response for session: {}", session_id);
 
+        // Sessions near their token budget get a shorter, compressed prompt
+        let strategy = match self.budget_status(session_id).await {
+            Some(status) => status.strategy,
+            None => TruncationStrategy::Standard,
+        };
+
         // Get conversation history for context
         let history_mgr = self.history_manager.read().await;
-        let history = history_mgr.get_history(session_id, 0, 10).await?;
+        let history = history_mgr.get_history(session_id, 0, strategy.history_messages()).await?;
         drop(history_mgr);
 
         // Build context from history
         let context = self.build_context_from_history(&history);
 
+        // Serve a cached answer to the same question while its sources are
+        // unchanged
+        let persona = self.session_persona(session_id).await;
+        let tenant_id = self.session_owner(session_id).await.map(|(tenant_id, _)| tenant_id);
+        let answer_scope = AnswerCache::scope(tenant_id.as_deref(), persona.as_ref().map(|p| p.id.as_str()));
+        if let Some(cache) = &self.answer_cache {
+            if let Some(cached) = cache.get(self.context_engine.as_ref(), &answer_scope, message).await {
+                debug!("Serving a cached answer in session {}", session_id);
+                let sources: HashSet<&str> = cached.sources.iter().map(|s| s.source.as_str()).collect();
+                let outcome = AnswerOutcome {
+                    session_id: session_id.to_string(),
+                    tenant_id,
+                    query: message.to_string(),
+                    intent: None,
+                    confidence: None,
+                    citations: sources.len(),
+                    cached: true,
+                    experiment: None,
+                    context_downsize: None,
+                };
+                return Ok((cached.with_note(), outcome));
+            }
+        }
+
         // Use NLP engine to analyze intent
         let intent = self.nlp_engine
             .classify_intent(message)
@@ -187,24 +788,234 @@ impl ConversationManager {
 
         debug!("Detected intent: {:?}", intent);
 
-        // Use context engine to enhance understanding
-        let context_data = self.context_engine
-            .retrieve(session_id)
-            .await
-            .map_err(|e| ConversationError::ContextError(e.to_string()))?;
+        // Reuse the session's previous window when its inputs and the corpus
+        // are unchanged
+        let inputs = WindowCache::inputs_hash(message, persona.as_ref().map(|p| p.id.as_str()));
+        let corpus_version = self.prefetcher.corpus_version();
+        let started = Instant::now();
+        let mut context_data = match self.window_cache.get(session_id, inputs, corpus_version) {
+            Some(window) => {
+                debug!("Reusing the context window of session {}", session_id);
+                let record = StageRecord::new(ContextStage::Retrieval, started.elapsed())
+                    .with_candidates(window.candidate_ids.len(), window.selected.len())
+                    .with_tokens(window.total_tokens, window.total_tokens)
+                    .with_cache_hit(true);
+                self.observe_stage(&ContextStage::Retrieval.span(), record);
+                window
+            }
+            None => {
+                // Use context engine to enhance understanding; a query prefetched
+                // while the user typed is served without another retrieval
+                let mut context_data = self.context_engine
+                    .retrieve(message)
+                    .await
+                    .map_err(|e| ConversationError::ContextError(e.to_string()))?;
 
-        // Generate response based on intent and context
-        // In a real implementation, this would call an LLM
-        let response = format!(
-            "I understand you're asking about: {:?}. Based on our conversation context, I can help with that.",
-            intent
+                // Restrict context to the sources the session's persona may use
+                if let Some(persona) = &persona {
+                    persona.filter_context(&mut context_data);
+                }
+                self.window_cache.insert(session_id, inputs, corpus_version, context_data.clone());
+                context_data
+            }
+        };
+
+        if let Some(compressor) = self.token_budgets.as_ref().and_then(|budgets| budgets.compressor(strategy)) {
+            let span = ContextStage::Compression.span();
+            let started = Instant::now();
+            let tokens_in = context_data.total_tokens;
+            span.in_scope(|| compressor.compress(message, &mut context_data));
+            let record = StageRecord::new(ContextStage::Compression, started.elapsed())
+                .with_candidates(context_data.selected.len(), context_data.selected.len())
+                .with_tokens(tokens_in, context_data.total_tokens);
+            self.observe_stage(&span, record);
+        }
+
+        // The session's profile, then its experiment variant, if any, set the
+        // retrieval settings and prompt
+        let profile = self.session_profile(session_id).await;
+        if let Some(profile) = &profile {
+            profile.retrieval.apply(&mut context_data);
+        }
+        let experiment = self.session_variant(session_id).await;
+        if let Some((_, variant)) = &experiment {
+            variant.retrieval.apply(&mut context_data);
+        }
+        let mut system_prompt = self.system_prompt(session_id).await.unwrap_or_default();
+        if let Some(profile) = &profile {
+            system_prompt = profile.system_prompt(&system_prompt);
+        }
+        if let Some((_, variant)) = &experiment {
+            system_prompt = variant.system_prompt(&system_prompt);
+        }
+
+        // Cut the context to what the answering model's window leaves after
+        // the rest of the prompt, when it is smaller than the budget
+        let model = model
+            .map(String::from)
+            .or_else(|| self.model_chain.as_ref().map(|chain| chain.model().to_string()));
+        let overhead =
+            self.estimate_tokens(&system_prompt) + self.estimate_tokens(&context) + self.estimate_tokens(message);
+        let mut context_downsize = match &model {
+            Some(model) => self.fit_context(model, message, overhead, &mut context_data, None),
+            None => None,
+        };
+
+        // Remember what the window looked like so turns can be diffed later
+        let turn = self.window_tracker.record(session_id, message, &context_data);
+        debug!(
+            "Context window for turn {}: {} items, {} tokens",
+            turn,
+            context_data.selected.len(),
+            context_data.total_tokens
         );
 
-        Ok(response)
+        // Count stale context and warn that it may be outdated
+        self.freshness_counters.record(&context_data);
+        let warnings: Vec<String> = context_data
+            .stale()
+            .filter_map(|scored| scored.stale.as_ref().map(|stale| stale.warning(&scored.item.metadata.source)))
+            .collect();
+
+        // Generate response based on intent and context
+        let slot = self.llm_slot().await?;
+        let mut response = match &self.model_chain {
+            Some(chain) => {
+                let prompt = self.assemble_prompt(&system_prompt, &context_data, &context, message);
+                match chain.complete(&prompt).await {
+                    // The model's window is smaller than known: learn it, cut
+                    // the context again and retry once
+                    Err(ConversationError::TokenLimitExceeded { limit, .. }) => {
+                        let model = model.unwrap_or_else(|| chain.model().to_string());
+                        warn!("Model {} rejected the prompt as over {} tokens, cutting the context", model, limit);
+                        context_downsize = self.fit_context(&model, message, overhead, &mut context_data, Some(limit));
+                        let prompt = self.assemble_prompt(&system_prompt, &context_data, &context, message);
+                        chain.complete(&prompt).await?
+                    }
+                    result => result?,
+                }
+            }
+            None => format!(
+                "I understand you're asking about: {:?}. Based on our conversation context, I can help with that.",
+                intent
+            ),
+        };
+        for warning in warnings {
+            response.push_str(&format!("\n\nNote: {}.", warning));
+        }
+
+        let response_context = ResponseContext {
+            session_id: session_id.to_string(),
+            tenant_id,
+            query: message.to_string(),
+            sources: context_data.selected.iter().map(|scored| scored.item.metadata.source.clone()).collect(),
+            untrusted_sources: context_data
+                .selected
+                .iter()
+                .filter(|scored| Trust::is_untrusted(&scored.item))
+                .map(|scored| scored.item.metadata.source.clone())
+                .collect(),
+        };
+        drop(slot);
+        let response = self.post_processor.process(response, &response_context);
+
+        // Score the answer against the context it was generated from
+        let mut grounded = true;
+        let mut confidence = None;
+        if let Some(monitor) = &self.groundedness {
+            let score = monitor
+                .evaluate(GroundedAnswer {
+                    session_id: session_id.to_string(),
+                    query: message.to_string(),
+                    answer: response.clone(),
+                    passages: context_data
+                        .selected
+                        .iter()
+                        .map(|scored| ContextPassage::new(&scored.item.metadata.source, &scored.item.content))
+                        .collect(),
+                })
+                .await;
+            confidence = score.map(|score| score.score);
+            grounded = confidence.is_some_and(|score| score >= monitor.review_threshold());
+        }
+
+        // Only answers backed by their context are worth serving again
+        if let Some(cache) = &self.answer_cache {
+            if grounded {
+                cache.insert(&answer_scope, message, &response, &context_data).await;
+            }
+        }
+
+        let sources: HashSet<&str> = response_context.sources.iter().map(String::as_str).collect();
+        let outcome = AnswerOutcome {
+            session_id: session_id.to_string(),
+            tenant_id: response_context.tenant_id.clone(),
+            query: message.to_string(),
+            intent: Some(format!("{:?}", intent.intent_type)),
+            confidence,
+            citations: sources.len(),
+            cached: false,
+            experiment: experiment.map(|(assignment, _)| assignment),
+            context_downsize,
+        };
+        Ok((response, outcome))
+    }
+
+    /// Cut `context_data` to fit `model`'s context window, or, once the
+    /// model rejected a prompt as over `rejected_window` tokens, to fit
+    /// that; the cut is reported as a compression stage
+    fn fit_context(
+        &self,
+        model: &str,
+        message: &str,
+        overhead: usize,
+        context_data: &mut RetrievalResult,
+        rejected_window: Option<usize>,
+    ) -> Option<ContextDownsize> {
+        let span = ContextStage::Compression.span();
+        let started = Instant::now();
+        let (candidates_in, tokens_in) = (context_data.selected.len(), context_data.total_tokens);
+        let downsize = span.in_scope(|| match rejected_window {
+            Some(window) => Some(self.context_limits.refit(model, window, message, overhead, context_data)),
+            None => self.context_limits.fit(model, message, overhead, context_data),
+        })?;
+        let record = StageRecord::new(ContextStage::Compression, started.elapsed())
+            .with_candidates(candidates_in, context_data.selected.len())
+            .with_tokens(tokens_in, context_data.total_tokens);
+        self.observe_stage(&span, record);
+        Some(downsize)
+    }
+
+    /// The prompt of a turn: system prompt, retrieved passages, recent
+    /// history and the message
+    fn assemble_prompt(&self, system_prompt: &str, context_data: &RetrievalResult, history: &str, message: &str) -> String {
+        let span = ContextStage::Assembly.span();
+        let started = Instant::now();
+        let passages: Vec<String> = context_data
+            .selected
+            .iter()
+            .map(|scored| format!("[{}] {}", scored.item.metadata.source, scored.item.get_content()))
+            .collect();
+        let prompt = format!(
+            "{}\n\nContext:\n{}\n\nConversation:\n{}\n\nUser: {}",
+            system_prompt,
+            passages.join("\n"),
+            history,
+            message
+        );
+        let record = StageRecord::new(ContextStage::Assembly, started.elapsed())
+            .with_candidates(context_data.selected.len(), passages.len())
+            .with_tokens(context_data.total_tokens, self.estimate_tokens(&prompt));
+        self.observe_stage(&span, record);
+        prompt
     }
 
     /// Create a streaming response
     ///
+    /// Waits for the session's turn in progress, if any, and for a model
+    /// call slot; the response holds both until its stream finishes or is
+    /// dropped.
+    ///
     /// # Arguments
     ///
     /// * `request` - The message request to process
@@ -214,6 +1025,8 @@ impl ConversationManager {
     ) -> Result<StreamingResponse> {
         info!("Creating streaming response for session: {}", request.session_id);
 
+        let turn = self.turn_locks.acquire(&request.session_id).await;
+
         // Validate session exists
         {
             let mut session_mgr = self.session_manager.write().await;
@@ -222,17 +1035,57 @@ impl ConversationManager {
                 .ok_or_else(|| ConversationError::SessionNotFound(request.session_id.clone()))?;
         }
 
+        let strategy = self.check_budget(&request.session_id, &request.message).await?;
+        let model = self.turn_model(&request, strategy).await;
+
+        let slot = self.llm_slot().await?;
+
         // Create streaming response
-        let streaming_response = StreamingResponse::new(
+        let mut streaming_response = StreamingResponse::new(
             request.session_id.clone(),
             Arc::clone(&self.nlp_engine),
             Arc::clone(&self.context_engine),
             Arc::clone(&self.history_manager),
-        );
+        )
+        .with_counters(Arc::clone(&self.stream_counters))
+        .with_pricing(self.pricing.clone(), model)
+        .with_turn(turn);
+        if let Some((budgets, daily_tokens)) = self.session_budget(&request.session_id).await {
+            streaming_response = streaming_response.with_budget(Arc::clone(budgets), daily_tokens);
+        }
+        if let Some(slot) = slot {
+            streaming_response = streaming_response.with_slot(slot);
+        }
 
         Ok(streaming_response)
     }
 
+    /// Stream a response that its client can resume after disconnecting,
+    /// returning the stream's ID and its events
+    pub async fn start_resumable_stream(&self, request: MessageRequest) -> Result<(String, EventStream)> {
+        let session_id = request.session_id.clone();
+        let message = request.message.clone();
+        let mut response = self.create_streaming_response(request).await?;
+        let chunks = response.stream(message).await?;
+        Ok(self.resumable_streams.start(&session_id, chunks))
+    }
+
+    /// Continue stream `stream_id` of a session after event `after`, or from
+    /// its start
+    pub fn resume_stream(&self, session_id: &str, stream_id: &str, after: Option<usize>) -> Result<EventStream> {
+        self.resumable_streams.resume(session_id, stream_id, after)
+    }
+
+    /// Streamed responses started, completed and cancelled so far
+    pub fn stream_stats(&self) -> StreamStats {
+        self.stream_counters.stats()
+    }
+
+    /// Context windows used so far and the stale items among them
+    pub fn freshness_stats(&self) -> FreshnessStats {
+        self.freshness_counters.stats()
+    }
+
     /// Resolve references in a message
     ///
     /// Handles pronouns and references like "it", "that service", "the previous one"
@@ -329,28 +1182,1361 @@ impl ConversationManager {
         (text.len() / 4).max(1)
     }
 
-    /// Get session manager
-    pub fn session_manager(&self) -> Arc<RwLock<SessionManager>> {
-        Arc::clone(&self.session_manager)
+    /// Create a session, optionally assigning a persona
+    ///
+    /// # Arguments
+    ///
+    /// * `persona_id` - Persona to enforce for the session's lifetime
+    /// * `max_tokens` - Optional maximum tokens for the session
+    pub async fn create_session(
+        &self,
+        persona_id: Option<&str>,
+        max_tokens: Option<usize>,
+    ) -> Result<Session> {
+        if let Some(id) = persona_id {
+            if self.persona_registry.read().await.get(id).is_none() {
+                return Err(ConversationError::PersonaNotFound(id.to_string()));
+            }
+        }
+
+        let mut session_mgr = self.session_manager.write().await;
+        let session_id = session_mgr.create_session(max_tokens).id;
+        let session = session_mgr
+            .get_session_mut(&session_id)
+            .expect("session was just created");
+        session.persona_id = persona_id.map(str::to_string);
+        Ok(session.clone())
     }
 
-    /// Get history manager
-    pub fn history_manager(&self) -> Arc<RwLock<HistoryManager>> {
-        Arc::clone(&self.history_manager)
+    /// Get the persona assigned to a session
+    pub async fn session_persona(&self, session_id: &str) -> Option<Persona> {
+        let persona_id = {
+            let mut session_mgr = self.session_manager.write().await;
+            session_mgr.get_session(session_id)?.persona_id.clone()?
+        };
+        self.persona_registry.read().await.get(&persona_id).cloned()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Get the system prompt for a session: its persona's, followed by the
+    /// confirmed preferences of the user it belongs to
+    pub async fn system_prompt(&self, session_id: &str) -> Option<String> {
+        let persona_prompt = self.session_persona(session_id).await.map(|p| p.system_prompt);
+        let preferences = self
+            .session_preferences(session_id)
+            .await
+            .and_then(|p| p.prompt_instructions());
+        match (persona_prompt, preferences) {
+            (Some(prompt), Some(preferences)) => Some(format!("{}\n\n{}", prompt, preferences)),
+            (prompt, preferences) => prompt.or(preferences),
+        }
+    }
 
-    #[tokio::test]
-    async fn test_reference_resolution() {
-        // Test would go here
+    /// Record which user a session belongs to, so their preferences apply
+    /// to it and can be learned from it
+    pub async fn set_session_owner(&self, session_id: &str, tenant_id: &str, user_id: &str) -> Result<()> {
+        let mut session_mgr = self.session_manager.write().await;
+        let session = session_mgr
+            .get_session_mut(session_id)
+            .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?;
+        session.tenant_id = Some(tenant_id.to_string());
+        session.user_id = Some(user_id.to_string());
+        Ok(())
     }
 
-    #[tokio::test]
-    async fn test_message_processing() {
-        // Test would go here
+    /// Confirmed preferences of the user a session belongs to
+    pub async fn session_preferences(&self, session_id: &str) -> Option<UserPreferences> {
+        let (tenant_id, user_id) = self.session_owner(session_id).await?;
+        Some(self.preferences.get(&tenant_id, &user_id))
+    }
+
+    /// Get the store of users' preferences
+    pub fn preferences(&self) -> Arc<PreferenceStore> {
+        Arc::clone(&self.preferences)
+    }
+
+    /// Tenant and user a session belongs to, if it has an owner
+    pub async fn session_owner(&self, session_id: &str) -> Option<(String, String)> {
+        let mut session_mgr = self.session_manager.write().await;
+        let session = session_mgr.get_session(session_id)?;
+        Some((session.tenant_id.clone()?, session.user_id.clone()?))
+    }
+
+    /// Suggest preferences picked up from the message of a session's owner
+    async fn learn_preferences(&self, request: &MessageRequest) -> Vec<PreferenceSuggestion> {
+        let Some((tenant_id, user_id)) = self.session_owner(&request.session_id).await else {
+            return Vec::new();
+        };
+        let suggestions = self
+            .preferences
+            .observe(&tenant_id, &user_id, Some(&request.session_id), &request.message);
+        if !suggestions.is_empty() {
+            debug!("Suggesting {} preferences to {}", suggestions.len(), user_id);
+        }
+        suggestions
+    }
+
+    /// Check that the session's persona and its own allowlist permit
+    /// invoking `tool`
+    ///
+    /// Sessions without a persona or allowlist are unrestricted.
+    pub async fn authorize_tool(&self, session_id: &str, tool: &str) -> Result<()> {
+        match self.tool_policy(session_id).await?.check(tool) {
+            Ok(()) => Ok(()),
+            Err(ToolDenial::Persona(persona)) => {
+                warn!("Persona {} denied tool {} for session {}", persona, tool, session_id);
+                Err(ConversationError::ToolNotAllowed {
+                    tool: tool.to_string(),
+                    persona,
+                })
+            }
+            Err(ToolDenial::Session) => {
+                warn!("Session {} denied tool {}", session_id, tool);
+                Err(ConversationError::ToolNotAllowedInSession {
+                    tool: tool.to_string(),
+                    session: session_id.to_string(),
+                })
+            }
+        }
+    }
+
+    /// Invoke `tool` for a session through `run`, reusing the result of an
+    /// identical earlier call while it is fresh
+    ///
+    /// The session must be allowed the tool. `force_refresh` always runs
+    /// the tool and replaces any cached result. The call and its outcome
+    /// are recorded in the session's history, with the arguments redacted.
+    pub async fn call_tool<F, Fut>(
+        &self,
+        session_id: &str,
+        tool: &str,
+        args: &serde_json::Value,
+        force_refresh: bool,
+        run: F,
+    ) -> Result<ToolResult>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<serde_json::Value>>,
+    {
+        self.authorize_tool(session_id, tool).await?;
+        let result = self.tool_cache.call(session_id, tool, args, force_refresh, run).await;
+        self.record_tool_call(session_id, tool, args, &result).await;
+        result
+    }
+
+    /// Run code for the sandbox tool on `runner`
+    pub fn set_sandbox_runner(&self, runner: Arc<dyn SandboxRunner>) {
+        *self.sandbox_runner.write().unwrap_or_else(|e| e.into_inner()) = Some(runner);
+    }
+
+    /// Run `code` for a session through the sandbox tool
+    ///
+    /// The sandbox is created under the session's effective sandbox policy,
+    /// and the call goes through [`call_tool`](Self::call_tool), so the
+    /// session must be allowed the tool.
+    pub async fn execute_code(
+        &self,
+        session_id: &str,
+        runtime: &str,
+        code: &str,
+        force_refresh: bool,
+    ) -> Result<ToolResult> {
+        let runner = self
+            .sandbox_runner
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .ok_or_else(|| ConversationError::ToolUnavailable(SANDBOX_TOOL.to_string()))?;
+        let policy = self.tool_policy(session_id).await?.sandbox;
+
+        // The policy is part of the arguments, so results are never reused
+        // across policies
+        let args = serde_json::json!({ "runtime": runtime, "code": code, "sandbox": policy });
+        self.call_tool(session_id, SANDBOX_TOOL, &args, force_refresh, || async move {
+            runner.run(runtime, code, policy).await
+        })
+        .await
+    }
+
+    /// Add a tool call and its outcome to the session's history as a tool
+    /// message
+    async fn record_tool_call(
+        &self,
+        session_id: &str,
+        tool: &str,
+        args: &serde_json::Value,
+        result: &Result<ToolResult>,
+    ) {
+        let call_id = uuid::Uuid::new_v4().to_string();
+        let mut parts = vec![MessagePart::ToolCall {
+            call_id: call_id.clone(),
+            tool: tool.to_string(),
+            arguments: self.tool_redaction.redact_arguments(args),
+        }];
+        let content = match result {
+            Ok(result) => {
+                let output = self.tool_redaction.shorten(&result.value);
+                let sandbox = MessagePart::sandbox_output(&call_id, &output);
+                parts.push(MessagePart::ToolResult {
+                    call_id: call_id.clone(),
+                    tool: tool.to_string(),
+                    output,
+                    cached: result.cached,
+                    error: None,
+                });
+                parts.extend(sandbox);
+                format!("Called {}", tool)
+            }
+            Err(e) => {
+                parts.push(MessagePart::ToolResult {
+                    call_id,
+                    tool: tool.to_string(),
+                    output: serde_json::Value::Null,
+                    cached: false,
+                    error: Some(self.tool_redaction.shorten_text(&e.to_string())),
+                });
+                format!("Called {} (failed)", tool)
+            }
+        };
+
+        let message = ConversationMessage {
+            role: MessageRole::Tool,
+            content,
+            timestamp: chrono::Utc::now(),
+            token_count: self.estimate_tokens(&serde_json::to_string(&parts).unwrap_or_default()),
+            metadata: HashMap::new(),
+            usage: None,
+            model: None,
+            parts,
+        };
+        let mut history_mgr = self.history_manager.write().await;
+        if let Err(e) = history_mgr.append_message(session_id, message).await {
+            warn!("Failed to record tool call {} in session {}: {}", tool, session_id, e);
+        }
+    }
+
+    /// The tools and sandbox capabilities a session may use, combining its
+    /// persona's bounds with the session's own overrides
+    pub async fn tool_policy(&self, session_id: &str) -> Result<ToolPolicy> {
+        let session = self
+            .session_manager
+            .write()
+            .await
+            .get_session(session_id)
+            .cloned()
+            .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?;
+        let registry = self.persona_registry.read().await;
+        let persona = session.persona_id.as_deref().and_then(|id| registry.get(id));
+        Ok(ToolPolicy::resolve(persona, &session))
+    }
+
+    /// Set a session's own tool allowlist and sandbox policy
+    ///
+    /// These can only narrow what the session's persona allows. `None`
+    /// clears the override.
+    pub async fn set_tool_policy(
+        &self,
+        session_id: &str,
+        allowed_tools: Option<Vec<String>>,
+        sandbox: Option<SandboxPolicy>,
+    ) -> Result<ToolPolicy> {
+        {
+            let mut session_mgr = self.session_manager.write().await;
+            let session = session_mgr
+                .get_session_mut(session_id)
+                .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?;
+            session.allowed_tools = allowed_tools;
+            session.sandbox = sandbox;
+        }
+        info!("Updated tool policy for session {}", session_id);
+        self.tool_policy(session_id).await
+    }
+
+    /// Get the persona registry
+    pub fn persona_registry(&self) -> Arc<RwLock<PersonaRegistry>> {
+        Arc::clone(&self.persona_registry)
+    }
+
+    /// Get the context window diff between two turns of a session
+    ///
+    /// When `from_turn`/`to_turn` are omitted, the two most recent turns are
+    /// compared. Returns `None` if either turn is not retained.
+    pub fn context_window_diff(
+        &self,
+        session_id: &str,
+        from_turn: Option<u64>,
+        to_turn: Option<u64>,
+    ) -> Option<ContextWindowDiff> {
+        match (from_turn, to_turn) {
+            (None, None) => self.window_tracker.latest_diff(session_id),
+            (from, to) => {
+                let snapshots = self.window_tracker.snapshots(session_id);
+                let to = to.or_else(|| snapshots.last().map(|s| s.turn))?;
+                let from = from.unwrap_or_else(|| to.saturating_sub(1));
+                self.window_tracker.diff(session_id, from, to)
+            }
+        }
+    }
+
+    /// Get the retained context window snapshots for a session
+    pub fn context_window_snapshots(&self, session_id: &str) -> Vec<ContextWindowSnapshot> {
+        self.window_tracker.snapshots(session_id)
+    }
+
+    /// Delete a session along with its working memory and context window
+    /// snapshots; returns whether the session existed
+    pub async fn delete_session(&self, session_id: &str) -> bool {
+        let deleted = self.session_manager.write().await.delete_session(session_id).is_some();
+        self.forget_session(session_id);
+        deleted
+    }
+
+    /// Remove expired sessions along with their per-session state; returns
+    /// the number removed
+    pub async fn cleanup_expired_sessions(&self) -> usize {
+        let expired = self.session_manager.write().await.remove_expired();
+        for session_id in &expired {
+            self.forget_session(session_id);
+        }
+        expired.len()
+    }
+
+    fn forget_session(&self, session_id: &str) {
+        self.working_memory.clear(session_id);
+        self.window_tracker.clear(session_id);
+    }
+
+    /// Get the code edits proposed in the latest assistant response
+    ///
+    /// Returns an empty list when that response contains no unified diffs.
+    pub async fn proposed_edits(&self, session_id: &str) -> Result<Vec<FileEdit>> {
+        let history_mgr = self.history_manager.read().await;
+        let messages = history_mgr.get_all_messages(session_id).await?;
+        Ok(messages
+            .iter()
+            .rev()
+            .find(|m| m.role == MessageRole::Assistant)
+            .map(|m| extract_edits(&m.content))
+            .unwrap_or_default())
+    }
+
+    /// Every message of a session, tool calls and their results included
+    pub async fn session_history(&self, session_id: &str) -> Result<Vec<ConversationMessage>> {
+        self.history_manager.read().await.get_all_messages(session_id).await
+    }
+
+    /// Package the conversation into a handoff bundle for the humans taking
+    /// it over
+    ///
+    /// The bundle's summary is written by the model, or plainly described
+    /// when generating it fails.
+    pub async fn handoff_bundle(&self, session_id: &str) -> Result<HandoffBundle> {
+        let messages = self.history_manager.read().await.get_all_messages(session_id).await?;
+        let bundle = HandoffBundle::from_messages(session_id, &messages)
+            .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?;
+
+        let summary = match self.create_session(None, None).await {
+            Ok(session) => {
+                // Not a user's question, so its outcome is not observed
+                let summary = self.respond(&session.id, &bundle.summary_task(), None).await.map(|(summary, _)| summary);
+                self.session_manager.write().await.delete_session(&session.id);
+                summary
+            }
+            Err(e) => Err(e),
+        };
+        Ok(match summary {
+            Ok(summary) if !summary.trim().is_empty() => bundle.with_summary(summary),
+            Ok(_) => bundle,
+            Err(e) => {
+                warn!("Handoff summary for session {} failed, using the plain description: {}", session_id, e);
+                bundle
+            }
+        })
+    }
+
+    /// Warm the context for a message the user is still typing
+    pub async fn prefetch_context(&self, session_id: &str, partial_message: &str) -> Result<PrefetchOutcome> {
+        self.session_manager
+            .write()
+            .await
+            .get_session(session_id)
+            .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?;
+
+        // Resolve references the way the sent message will be, so the
+        // prefetched query matches the final one
+        let resolved = self.resolve_references(session_id, partial_message).await?;
+        let query = self.enhance_message_with_references(partial_message, &resolved);
+        self.prefetcher
+            .prefetch(&query)
+            .await
+            .map_err(|e| ConversationError::ContextError(e.to_string()))
+    }
+
+    /// Hit rate and latency savings of context prefetching
+    pub fn prefetch_stats(&self) -> PrefetchStats {
+        self.prefetcher.prefetch_stats()
+    }
+
+    /// How often turns reused their session's previous context window
+    pub fn window_cache_stats(&self) -> WindowCacheStats {
+        self.window_cache.stats()
+    }
+
+    /// Get the context engine backing retrieval
+    pub fn context_engine(&self) -> Arc<dyn ContextEngine> {
+        Arc::clone(&self.context_engine)
+    }
+
+    /// Get session manager
+    pub fn session_manager(&self) -> Arc<RwLock<SessionManager>> {
+        Arc::clone(&self.session_manager)
+    }
+
+    /// Get history manager
+    pub fn history_manager(&self) -> Arc<RwLock<HistoryManager>> {
+        Arc::clone(&self.history_manager)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reference_resolution() {
+        // Test would go here
+    }
+
+    #[tokio::test]
+    async fn test_message_processing() {
+        // Test would go here
+    }
+
+    #[tokio::test]
+    async fn test_persona_enforcement() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+
+        assert!(matches!(
+            manager.create_session(Some("nope"), None).await,
+            Err(ConversationError::PersonaNotFound(_))
+        ));
+
+        let session = manager.create_session(Some("code-reviewer"), None).await.unwrap();
+        assert_eq!(session.persona_id.as_deref(), Some("code-reviewer"));
+        assert!(manager.system_prompt(&session.id).await.unwrap().contains("code reviewer"));
+        assert!(manager.authorize_tool(&session.id, "file_read").await.is_ok());
+        assert!(matches!(
+            manager.authorize_tool(&session.id, "workflow_trigger").await,
+            Err(ConversationError::ToolNotAllowed { .. })
+        ));
+
+        let unrestricted = manager.create_session(None, None).await.unwrap();
+        assert!(manager.authorize_tool(&unrestricted.id, "workflow_trigger").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_session_tool_policy() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+        let session = manager.create_session(Some("code-reviewer"), None).await.unwrap();
+
+        let policy = manager
+            .set_tool_policy(
+                &session.id,
+                Some(vec!["sandbox_execute".to_string()]),
+                Some(SandboxPolicy::unrestricted()),
+            )
+            .await
+            .unwrap();
+        // The persona's locked-down sandbox cannot be widened by the session
+        assert_eq!(policy.sandbox, SandboxPolicy::locked_down());
+
+        assert!(manager.authorize_tool(&session.id, "sandbox_execute").await.is_ok());
+        assert!(matches!(
+            manager.authorize_tool(&session.id, "file_read").await,
+            Err(ConversationError::ToolNotAllowedInSession { .. })
+        ));
+        assert!(matches!(
+            manager.authorize_tool("unknown", "file_read").await,
+            Err(ConversationError::SessionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_are_authorized_and_memoized() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+        use serde_json::json;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+        let session = manager.create_session(Some("code-reviewer"), None).await.unwrap();
+        let args = json!({ "path": "src/lib.rs" });
+
+        let first = manager
+            .call_tool(&session.id, "file_read", &args, false, || async { Ok(json!("v1")) })
+            .await
+            .unwrap();
+        let second = manager
+            .call_tool(&session.id, "file_read", &args, false, || async { Ok(json!("v2")) })
+            .await
+            .unwrap();
+        assert!(!first.cached);
+        assert!(second.cached);
+        assert_eq!(second.value, json!("v1"));
+
+        let refreshed = manager
+            .call_tool(&session.id, "file_read", &args, true, || async { Ok(json!("v2")) })
+            .await
+            .unwrap();
+        assert_eq!((refreshed.cached, refreshed.value), (false, json!("v2")));
+
+        assert!(matches!(
+            manager
+                .call_tool(&session.id, "workflow_trigger", &json!({}), false, || async { Ok(json!(null)) })
+                .await,
+            Err(ConversationError::ToolNotAllowed { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_code_runs_under_the_session_sandbox_policy() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        #[derive(Default)]
+        struct RecordingRunner(std::sync::Mutex<Vec<SandboxPolicy>>);
+
+        #[async_trait]
+        impl SandboxRunner for RecordingRunner {
+            async fn run(&self, _runtime: &str, _code: &str, policy: SandboxPolicy) -> Result<serde_json::Value> {
+                self.0.lock().unwrap().push(policy);
+                Ok(serde_json::json!({ "stdout": "2\n", "exit_code": 0 }))
+            }
+        }
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+        let reviewer = manager.create_session(Some("code-reviewer"), None).await.unwrap();
+        let analyst = manager.create_session(Some("analyst"), None).await.unwrap();
+        assert!(matches!(
+            manager.execute_code(&reviewer.id, "python", "print(1 + 1)", false).await,
+            Err(ConversationError::ToolUnavailable(_))
+        ));
+
+        let runner = Arc::new(RecordingRunner::default());
+        manager.set_sandbox_runner(runner.clone());
+        manager.execute_code(&reviewer.id, "python", "print(1 + 1)", false).await.unwrap();
+        assert!(matches!(
+            manager.execute_code(&analyst.id, "python", "print(1 + 1)", false).await,
+            Err(ConversationError::ToolNotAllowed { .. })
+        ));
+        assert_eq!(*runner.0.lock().unwrap(), vec![SandboxPolicy::locked_down()]);
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_are_recorded_in_history() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+        use serde_json::json;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+        let session = manager.create_session(None, None).await.unwrap();
+
+        let args = json!({ "code": "print(6 * 7)", "api_token": "sk-live-123" });
+        manager
+            .call_tool(&session.id, "sandbox_execute", &args, false, || async {
+                Ok(json!({ "stdout": "42\n", "stderr": "", "exit_code": 0 }))
+            })
+            .await
+            .unwrap();
+        let failed = manager
+            .call_tool(&session.id, "file_read", &json!({ "path": "missing" }), false, || async {
+                Err(ConversationError::ToolError("not found".to_string()))
+            })
+            .await;
+        assert!(failed.is_err());
+
+        let history = manager.history_manager().read().await.get_all_messages(&session.id).await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].role, MessageRole::Tool);
+        let MessagePart::ToolCall { arguments, .. } = &history[0].parts[0] else {
+            panic!("expected a tool call");
+        };
+        assert_eq!(arguments["api_token"], crate::tool_transcript::REDACTED);
+        assert!(matches!(
+            &history[0].parts[2],
+            MessagePart::SandboxOutput { exit_code: Some(0), stdout, .. } if stdout == "42\n"
+        ));
+        assert!(matches!(&history[1].parts[1], MessagePart::ToolResult { error: Some(_), .. }));
+
+        let export = manager
+            .history_manager()
+            .read()
+            .await
+            .export_history(&session.id, crate::history::ExportFormat::Markdown)
+            .await
+            .unwrap();
+        assert!(export.contains("**Sandbox output** (exit code 0)"));
+        assert!(!export.contains("sk-live-123"));
+    }
+
+    #[tokio::test]
+    async fn test_responses_warn_about_superseded_context() {
+        use copilot_context::freshness::{DOCUMENT_KEY, VERSION_KEY};
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl, MemoryMetadata};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        for version in ["v1", "v2"] {
+            let mut metadata = MemoryMetadata::new("document", "wiki");
+            metadata.add_custom(DOCUMENT_KEY.to_string(), serde_json::json!("deploy.md"));
+            metadata.add_custom(VERSION_KEY.to_string(), serde_json::json!(version));
+            context_engine
+                .store("Deploy the billing service with helm".to_string(), metadata, 0.5)
+                .await
+                .unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+        let session_id = manager.session_manager.write().await.create_session(None).id;
+
+        let response = manager
+            .process_message(MessageRequest {
+                session_id,
+                message: "How do I deploy the billing service?".to_string(),
+                metadata: Default::default(),
+                model: None,
+            })
+            .await
+            .unwrap();
+
+        assert!(response.response.contains("Note: wiki may be outdated: a newer version was ingested"));
+        let stats = manager.freshness_stats();
+        assert_eq!((stats.windows, stats.windows_with_stale, stats.superseded_items), (1, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_context_window_diff_between_turns() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+
+        let session_id = {
+            let mut sessions = manager.session_manager.write().await;
+            sessions.create_session(None).id
+        };
+
+        assert!(manager.context_window_diff(&session_id, None, None).is_none());
+
+        for message in ["Show me CPU usage", "Now show memory usage"] {
+            manager
+                .process_message(MessageRequest {
+                    session_id: session_id.clone(),
+                    message: message.to_string(),
+                    metadata: Default::default(),
+                    model: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(manager.context_window_snapshots(&session_id).len(), 2);
+        let diff = manager.context_window_diff(&session_id, None, None).unwrap();
+        assert_eq!((diff.from_turn, diff.to_turn), (1, 2));
+        assert_eq!(
+            manager.context_window_diff(&session_id, Some(1), None).unwrap().to_turn,
+            2
+        );
+
+        assert!(manager.delete_session(&session_id).await);
+        assert!(manager.context_window_snapshots(&session_id).is_empty());
+        assert!(!manager.delete_session(&session_id).await);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_messages_do_not_interleave() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = Arc::new(ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine));
+        let session = manager.create_session(None, None).await.unwrap();
+
+        let turns: Vec<_> = (0..8)
+            .map(|i| {
+                let manager = Arc::clone(&manager);
+                let session_id = session.id.clone();
+                tokio::spawn(async move {
+                    manager
+                        .process_message(MessageRequest {
+                            session_id,
+                            message: format!("Show me CPU usage for host {}", i),
+                            metadata: Default::default(),
+                            model: None,
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for turn in turns {
+            turn.await.unwrap().unwrap();
+        }
+
+        let history = manager.history_manager.read().await;
+        let messages = history.get_all_messages(&session.id).await.unwrap();
+        assert_eq!(messages.len(), 16);
+        for pair in messages.chunks(2) {
+            assert_eq!(pair[0].role, MessageRole::User);
+            assert_eq!(pair[1].role, MessageRole::Assistant);
+        }
+        assert_eq!(manager.turn_locks.active(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_proposed_edits_from_latest_response() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+
+        let answer = |content: &str| ConversationMessage {
+            role: MessageRole::Assistant,
+            content: content.to_string(),
+            timestamp: chrono::Utc::now(),
+            token_count: 0,
+            metadata: Default::default(),
+            usage: None,
+            model: None,
+            parts: Vec::new(),
+        };
+        {
+            let mut history = manager.history_manager.write().await;
+            history
+                .append_message("s1", answer("```diff\n--- a/a.txt\n+++ b/a.txt\n@@ -1 +1 @@\n-a\n+b\n```"))
+                .await
+                .unwrap();
+        }
+        let edits = manager.proposed_edits("s1").await.unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].path, "a.txt");
+
+        {
+            let mut history = manager.history_manager.write().await;
+            history.append_message("s1", answer("Done, nothing else to change.")).await.unwrap();
+        }
+        assert!(manager.proposed_edits("s1").await.unwrap().is_empty());
+        assert!(manager.proposed_edits("unknown").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_handoff_bundle() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+        {
+            let mut history = manager.history_manager.write().await;
+            history
+                .append_message(
+                    "s1",
+                    ConversationMessage {
+                        role: MessageRole::User,
+                        content: "Why is the nightly backup failing?".to_string(),
+                        timestamp: chrono::Utc::now(),
+                        token_count: 0,
+                        metadata: Default::default(),
+                        usage: None,
+                        model: None,
+                        parts: Vec::new(),
+                    },
+                )
+                .await
+                .unwrap();
+        }
+
+        let bundle = manager.handoff_bundle("s1").await.unwrap();
+        assert_eq!(bundle.title, "Why is the nightly backup failing?");
+        assert_eq!(bundle.open_questions, vec!["Why is the nightly backup failing?"]);
+        assert!(!bundle.summary.is_empty());
+        // The scratch session used for the summary is gone
+        assert_eq!(manager.session_manager.read().await.session_count(), 0);
+        assert!(matches!(
+            manager.handoff_bundle("unknown").await,
+            Err(ConversationError::SessionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_preferences_learned_and_applied() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine)
+            .with_preferences(Arc::new(PreferenceStore::new().with_min_observations(1)));
+        let session = manager.create_session(Some("analyst"), None).await.unwrap();
+        let ask = |message: &str| MessageRequest {
+            session_id: session.id.clone(),
+            message: message.to_string(),
+            metadata: HashMap::new(),
+            model: None,
+        };
+
+        // Nothing is learned from sessions nobody owns
+        let response = manager.process_message(ask("Answer in French please")).await.unwrap();
+        assert!(response.preference_suggestions.is_empty());
+
+        manager.set_session_owner(&session.id, "acme", "alice").await.unwrap();
+        let response = manager.process_message(ask("Answer in French please")).await.unwrap();
+        assert_eq!(response.preference_suggestions.len(), 1);
+        assert!(!manager.system_prompt(&session.id).await.unwrap().contains("French"));
+
+        let preferences = manager.preferences();
+        preferences
+            .confirm("acme", "alice", &response.preference_suggestions[0].id)
+            .unwrap();
+        let prompt = manager.system_prompt(&session.id).await.unwrap();
+        assert!(prompt.starts_with("You are a data analyst"));
+        assert!(prompt.contains("Answer in French."));
+    }
+
+    #[tokio::test]
+    async fn test_token_budget_prefers_cheap_models_then_refuses() {
+        use crate::{ModelPricing, TokenBudgetConfig};
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let budgets = TokenBudgets::new(TokenBudgetConfig {
+            tenants: HashMap::from([("acme".to_string(), 1000)]),
+            economy_models: vec!["claude-3-haiku".to_string()],
+            ..Default::default()
+        });
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine)
+            .with_pricing(
+                PricingTable::new(ModelPricing::new(0.01, 0.03))
+                    .with_model("claude-3-haiku", ModelPricing::new(0.00025, 0.00125)),
+            )
+            .with_token_budgets(Arc::new(budgets));
+        let session = manager.create_session(None, None).await.unwrap();
+        manager.set_session_owner(&session.id, "acme", "alice").await.unwrap();
+
+        let mut models = Vec::new();
+        let exhausted = loop {
+            let request = MessageRequest {
+                session_id: session.id.clone(),
+                message: "How is the payments service doing?".to_string(),
+                metadata: HashMap::new(),
+                model: None,
+            };
+            match manager.process_message(request).await {
+                Ok(response) => models.push(response.model),
+                Err(e) => break e,
+            }
+            assert!(models.len() < 100, "budget never ran out");
+        };
+
+        assert_eq!(models[0], None);
+        assert_eq!(models.last().unwrap().as_deref(), Some("claude-3-haiku"));
+        match exhausted {
+            ConversationError::TokenBudgetExhausted { daily_tokens, resets_at, .. } => {
+                assert_eq!(daily_tokens, 1000);
+                assert!(resets_at > chrono::Utc::now());
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+        let status = manager.budget_status(&session.id).await.unwrap();
+        assert_eq!(status.strategy, TruncationStrategy::Minimal);
+
+        // Sessions of tenants without a budget are unaffected
+        let other = manager.create_session(None, None).await.unwrap();
+        assert!(manager.budget_status(&other.id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_responses_fail_over_between_models() {
+        use crate::{FallbackConfig, ProviderHealth};
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        struct Fixed(&'static str, bool);
+
+        #[async_trait]
+        impl JudgeModel for Fixed {
+            fn model(&self) -> &str {
+                self.0
+            }
+
+            async fn complete(&self, prompt: &str) -> Result<String> {
+                if self.1 {
+                    return Err(ConversationError::ModelUnavailable {
+                        model: self.0.to_string(),
+                        reason: "connection refused".to_string(),
+                    });
+                }
+                assert!(prompt.ends_with("User: Is the API up?"));
+                Ok(format!("{} says yes", self.0))
+            }
+        }
+
+        let chain = ModelFallbackChain::new(FallbackConfig {
+            unhealthy_after: 1,
+            ..Default::default()
+        })
+        .with_link("anthropic", Arc::new(Fixed("claude-3-opus", true)))
+        .with_link("local", Arc::new(Fixed("llama-3-8b", false)));
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine)
+            .with_model_chain(Arc::new(chain));
+        let session = manager.create_session(None, None).await.unwrap();
+
+        let response = manager
+            .process_message(MessageRequest {
+                session_id: session.id.clone(),
+                message: "Is the API up?".to_string(),
+                metadata: HashMap::new(),
+                model: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.response, "llama-3-8b says yes");
+        let stats = manager.model_chain().unwrap().stats();
+        assert_eq!(stats.active.as_deref(), Some("local"));
+        assert_eq!(stats.providers[0].health, ProviderHealth::Unhealthy);
+    }
+
+    #[tokio::test]
+    async fn test_experiment_variants_shape_answers_and_are_measured() {
+        use crate::{ExperimentSpec, FallbackConfig, Variant};
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        /// Answers with the first line of its prompt, the system prompt
+        struct Echo;
+
+        #[async_trait]
+        impl JudgeModel for Echo {
+            fn model(&self) -> &str {
+                "echo"
+            }
+
+            async fn complete(&self, prompt: &str) -> Result<String> {
+                Ok(prompt.lines().next().unwrap_or_default().to_string())
+            }
+        }
+
+        let chain = ModelFallbackChain::new(FallbackConfig::default()).with_link("local", Arc::new(Echo));
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine)
+            .with_model_chain(Arc::new(chain));
+        manager
+            .experiments()
+            .create(ExperimentSpec {
+                id: "brief-answers".to_string(),
+                description: String::new(),
+                variants: vec![
+                    Variant::new("control").with_weight(0),
+                    Variant::new("brief").with_prompt_template("Be brief.").with_model("claude-3-haiku"),
+                ],
+                tenants: vec!["acme".to_string()],
+            })
+            .unwrap();
+
+        let request = |session_id: &str| MessageRequest {
+            session_id: session_id.to_string(),
+            message: "Is the API up?".to_string(),
+            metadata: HashMap::new(),
+            model: None,
+        };
+        let session = manager.create_session(None, None).await.unwrap();
+        manager.set_session_owner(&session.id, "acme", "alice").await.unwrap();
+        let response = manager.process_message(request(&session.id)).await.unwrap();
+        assert_eq!(response.response, "Be brief.");
+        assert_eq!(response.model.as_deref(), Some("claude-3-haiku"));
+        assert!(manager.experiments().record_feedback(&session.id, true).is_some());
+
+        let results = manager.experiments().results("brief-answers").unwrap();
+        assert_eq!(results.variants[1].turns, 1);
+        assert_eq!(results.variants[1].positive_feedback, 1);
+
+        // Sessions of tenants not enrolled take no part
+        let other = manager.create_session(None, None).await.unwrap();
+        let response = manager.process_message(request(&other.id)).await.unwrap();
+        assert_ne!(response.response, "Be brief.");
+        assert_eq!(response.model, None);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_profiles_shape_answers() {
+        use crate::{FallbackConfig, PipelineProfile, PipelineProfiles};
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        struct Echo;
+
+        #[async_trait]
+        impl JudgeModel for Echo {
+            fn model(&self) -> &str {
+                "echo"
+            }
+
+            async fn complete(&self, prompt: &str) -> Result<String> {
+                Ok(prompt.lines().next().unwrap_or_default().to_string())
+            }
+        }
+
+        let profiles = PipelineProfiles {
+            tenants: HashMap::from([(
+                "acme".to_string(),
+                PipelineProfile {
+                    prompt_template: Some("Answer for Acme staff.".to_string()),
+                    models: vec!["claude-3-sonnet".to_string()],
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+        let chain = ModelFallbackChain::new(FallbackConfig::default()).with_link("local", Arc::new(Echo));
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine)
+            .with_model_chain(Arc::new(chain))
+            .with_profiles(Arc::new(profiles));
+
+        let session = manager.create_session(None, None).await.unwrap();
+        manager.set_session_owner(&session.id, "acme", "alice").await.unwrap();
+        let response = manager
+            .process_message(MessageRequest {
+                session_id: session.id.clone(),
+                message: "Is the API up?".to_string(),
+                metadata: HashMap::new(),
+                model: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.response, "Answer for Acme staff.");
+        assert_eq!(response.model.as_deref(), Some("claude-3-sonnet"));
+    }
+
+    #[tokio::test]
+    async fn test_message_usage_accumulates() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine)
+            .with_pricing(PricingTable::new(crate::ModelPricing::new(1.0, 2.0)));
+        let session = manager.create_session(None, None).await.unwrap();
+
+        let mut expected = TokenUsage::default();
+        for message in ["Show me CPU usage", "And memory?"] {
+            let response = manager
+                .process_message(MessageRequest {
+                    session_id: session.id.clone(),
+                    message: message.to_string(),
+                    metadata: Default::default(),
+                    model: None,
+                })
+                .await
+                .unwrap();
+            assert_eq!(response.usage.total_tokens, response.tokens_used);
+            let cost = (response.usage.prompt_tokens + 2 * response.usage.completion_tokens) as f64 / 1_000.0;
+            assert!((response.usage.cost_usd - cost).abs() < 1e-12);
+
+            expected += response.usage;
+            assert_eq!(response.session_usage, expected);
+        }
+        assert_eq!(manager.session_usage(&session.id).await, expected);
+    }
+
+    #[tokio::test]
+    async fn test_compare_models_and_vote() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+        let session = manager.create_session(None, None).await.unwrap();
+        let request = MessageRequest {
+            session_id: session.id.clone(),
+            message: "Why is latency up?".to_string(),
+            metadata: Default::default(),
+            model: None,
+        };
+
+        let comparison = manager
+            .compare_models(request.clone(), ["claude-3-opus".to_string(), "gpt-4".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(comparison.responses.len(), 2);
+
+        let history = manager.history_manager.read().await.get_all_messages(&session.id).await.unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[2].model.as_deref(), Some("gpt-4"));
+        assert_eq!(history[2].metadata["comparison_id"], comparison.id);
+
+        assert!(manager
+            .vote_comparison(&session.id, &comparison.id, "gpt-4o")
+            .await
+            .is_err());
+        manager
+            .vote_comparison(&session.id, &comparison.id, "gpt-4")
+            .await
+            .unwrap();
+        let stats = manager.model_preference_stats().await;
+        assert_eq!(stats["gpt-4"].preferred, 1);
+        assert_eq!(stats["claude-3-opus"].voted, 1);
+
+        let response = manager
+            .process_message(MessageRequest {
+                model: Some("claude-3-haiku".to_string()),
+                ..request
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.model.as_deref(), Some("claude-3-haiku"));
+    }
+
+    #[tokio::test]
+    async fn test_prefetched_context_is_used_for_the_message() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+        let session = manager.create_session(None, None).await.unwrap();
+
+        for partial in ["Why is", "Why is latency", "Why is latency up?"] {
+            manager.prefetch_context(&session.id, partial).await.unwrap();
+        }
+        assert!(manager.prefetch_context("missing", "Why").await.is_err());
+
+        manager
+            .process_message(MessageRequest {
+                session_id: session.id.clone(),
+                message: "Why is latency up?".to_string(),
+                metadata: Default::default(),
+                model: None,
+            })
+            .await
+            .unwrap();
+
+        let stats = manager.prefetch_stats();
+        assert_eq!(stats.prefetches, 3);
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 0);
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_turns_reuse_the_context_window() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl, MemoryMetadata};
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+        let session = manager.create_session(None, None).await.unwrap();
+
+        manager.generate_response(&session.id, "Why is latency up?").await.unwrap();
+        manager.generate_response(&session.id, "Why is latency  up?").await.unwrap();
+        assert_eq!(manager.window_cache_stats().hits, 1);
+
+        // New content invalidates the window
+        manager
+            .context_engine()
+            .store("Latency rose after the 14:00 deploy".to_string(), MemoryMetadata::new("doc", "incident"), 0.8)
+            .await
+            .unwrap();
+        manager.generate_response(&session.id, "Why is latency up?").await.unwrap();
+        let stats = manager.window_cache_stats();
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+        assert_eq!(manager.prefetch_stats().misses, 2);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_questions_are_answered_from_the_cache() {
+        use crate::answer_cache::AnswerCache;
+        use copilot_context::freshness::{DOCUMENT_KEY, VERSION_KEY};
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl, MemoryMetadata, MockEmbeddingProvider};
+        use copilot_nlp::NlpEngineImpl;
+
+        let runbook = |version: &str| {
+            let mut metadata = MemoryMetadata::new("document", "wiki");
+            metadata.add_custom(DOCUMENT_KEY.to_string(), serde_json::json!("rollback.md"));
+            metadata.add_custom(VERSION_KEY.to_string(), serde_json::json!(version));
+            metadata
+        };
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        context_engine
+            .store("Roll back a release with helm rollback".to_string(), runbook("v1"), 0.5)
+            .await
+            .unwrap();
+        let cache = Arc::new(AnswerCache::new(Arc::new(MockEmbeddingProvider::new(32))));
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine)
+            .with_answer_cache(cache);
+        let first = manager.create_session(None, None).await.unwrap();
+        let second = manager.create_session(None, None).await.unwrap();
+
+        let answer = manager.generate_response(&first.id, "How do I roll back a release?").await.unwrap();
+        let cached = manager.generate_response(&second.id, "how do I roll back a release").await.unwrap();
+        assert!(cached.starts_with(&answer));
+        assert!(cached.contains("Note: based on sources as of"));
+
+        // Re-ingesting the runbook makes the next answer fresh
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        manager
+            .context_engine()
+            .store("Roll back a release with helm rollback --wait".to_string(), runbook("v2"), 0.5)
+            .await
+            .unwrap();
+        let fresh = manager.generate_response(&second.id, "How do I roll back a release?").await.unwrap();
+        assert!(!fresh.contains("based on sources as of"));
+        let stats = manager.answer_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.invalidations), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_answer_outcomes_are_observed() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl, MemoryMetadata};
+        use copilot_nlp::NlpEngineImpl;
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<AnswerOutcome>>);
+
+        impl AnswerObserver for Recorder {
+            fn observe(&self, outcome: &AnswerOutcome) {
+                self.0.lock().unwrap().push(outcome.clone());
+            }
+        }
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        context_engine
+            .store("Expense reports are due by the fifth".to_string(), MemoryMetadata::new("doc", "finance"), 0.5)
+            .await
+            .unwrap();
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+        let recorder = Arc::new(Recorder::default());
+        manager.set_answer_observer(recorder.clone());
+        let session = manager.create_session(None, None).await.unwrap();
+
+        manager.generate_response(&session.id, "When are expense reports due?").await.unwrap();
+        manager.generate_response(&session.id, "Where is the parking garage?").await.unwrap();
+
+        let outcomes = recorder.0.lock().unwrap();
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes[0].query, "When are expense reports due?");
+        assert_eq!((outcomes[0].citations, outcomes[0].confidence), (1, None));
+        assert!(outcomes[0].intent.is_some());
+        assert_eq!(outcomes[1].citations, 0);
+    }
+
+    #[tokio::test]
+    async fn test_context_stages_are_observed() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl, MemoryMetadata};
+        use copilot_nlp::NlpEngineImpl;
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<StageRecord>>);
+
+        impl ContextStageObserver for Recorder {
+            fn record(&self, record: &StageRecord) {
+                self.0.lock().unwrap().push(record.clone());
+            }
+        }
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        context_engine
+            .store("The VPN client is in the software portal".to_string(), MemoryMetadata::new("doc", "it"), 0.5)
+            .await
+            .unwrap();
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+        let recorder = Arc::new(Recorder::default());
+        manager.set_stage_observer(recorder.clone());
+        let session = manager.create_session(None, None).await.unwrap();
+
+        manager.generate_response(&session.id, "Where do I get the VPN client?").await.unwrap();
+        manager.generate_response(&session.id, "Where do I get the VPN client?").await.unwrap();
+
+        // The repeated question reuses the session's window
+        let records = recorder.0.lock().unwrap();
+        let stages: Vec<_> = records.iter().map(|record| (record.stage, record.cache_hit)).collect();
+        assert_eq!(
+            stages,
+            vec![(ContextStage::Retrieval, Some(false)), (ContextStage::Retrieval, Some(true))]
+        );
+        assert_eq!(records[1].candidates_out, 1);
+    }
+
+    #[tokio::test]
+    async fn test_context_is_cut_to_fit_the_model() {
+        use crate::{ContextLimits, FallbackConfig};
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl, MemoryMetadata};
+        use copilot_nlp::NlpEngineImpl;
+
+        /// Rejects prompts of more words than its window
+        struct Windowed(usize);
+
+        #[async_trait]
+        impl JudgeModel for Windowed {
+            fn model(&self) -> &str {
+                "llama-3-8b"
+            }
+
+            async fn complete(&self, prompt: &str) -> Result<String> {
+                let used = prompt.split_whitespace().count();
+                if used > self.0 {
+                    return Err(ConversationError::TokenLimitExceeded { used, limit: self.0 });
+                }
+                Ok(format!("Answered from {} words", used))
+            }
+        }
+
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        for runbook in 0..3 {
+            let content = format!("Runbook {}: deploy the billing service with helm and watch the rollout. ", runbook);
+            context_engine
+                .store(content.repeat(40), MemoryMetadata::new("document", "wiki"), 0.5)
+                .await
+                .unwrap();
+        }
+        let chain = ModelFallbackChain::new(FallbackConfig::default()).with_link("local", Arc::new(Windowed(1_000)));
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine)
+            .with_model_chain(Arc::new(chain))
+            .with_context_limits(ContextLimits::new(64));
+        let session = manager.create_session(None, None).await.unwrap();
+        let request = || MessageRequest {
+            session_id: session.id.clone(),
+            message: "deploy billing service helm rollout".to_string(),
+            metadata: HashMap::new(),
+            model: None,
+        };
+
+        // The window is unknown until the model rejects the prompt
+        let response = manager.process_message(request()).await.unwrap();
+        assert!(response.response.starts_with("Answered from"));
+        let downsize = response.context_downsize.unwrap();
+        assert!(downsize.retried);
+        assert_eq!((downsize.model.as_str(), downsize.window), ("llama-3-8b", 1_000));
+        assert!(downsize.tokens_after < downsize.tokens_before);
+
+        // Then the context is cut before the model sees it
+        let response = manager.process_message(request()).await.unwrap();
+        assert!(!response.context_downsize.unwrap().retried);
+        let history = manager.session_history(&session.id).await.unwrap();
+        assert!(history[3].metadata["context_downsized"].contains("1000-token window of llama-3-8b"));
+        let stats = manager.context_fit_stats();
+        assert_eq!((stats.downsizes, stats.retries), (2, 1));
     }
 }
@@ -0,0 +1,487 @@
+//! Model fallback chains
+//!
+//! A [`ModelFallbackChain`] puts several models behind one [`JudgeModel`],
+//! in order of preference, e.g. a hosted primary, a second provider and a
+//! local model. A call goes to the active model and moves down the chain
+//! when a provider is out ([`ConversationError::ModelUnavailable`]) or rate
+//! limits it ([`ConversationError::ModelRateLimited`]); other errors are the
+//! caller's and are returned as is. A provider failing several calls in a
+//! row is marked unhealthy and skipped, and a rate-limited one is skipped
+//! until it may be called again.
+//!
+//! Failover is sticky: once the chain has moved down it stays there, and
+//! returns to a preferred provider only after that provider has passed
+//! several health probes in a row, so a flapping provider does not bounce
+//! traffic back and forth. [`ModelFallbackChain::probe`] runs one round of
+//! probes; [`ModelFallbackChain::spawn_probes`] runs them in the background.
+
+use crate::eval::JudgeModel;
+use crate::{ConversationError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// When providers count as unhealthy and when they are trusted again
+#[derive(Debug, Clone)]
+pub struct FallbackConfig {
+    /// Failed calls in a row after which a provider is marked unhealthy
+    pub unhealthy_after: u32,
+    /// Successful probes in a row after which the chain returns to a
+    /// provider
+    pub recover_after: u32,
+    /// Interval between probes of a provider
+    pub probe_interval: Duration,
+    /// How long a rate-limited provider is skipped when it does not say
+    pub rate_limit_cooldown: Duration,
+    /// Prompt health probes send
+    pub probe_prompt: String,
+}
+
+impl Default for FallbackConfig {
+    fn default() -> Self {
+        Self {
+            unhealthy_after: 3,
+            recover_after: 2,
+            probe_interval: Duration::from_secs(30),
+            rate_limit_cooldown: Duration::from_secs(30),
+            probe_prompt: "Reply with OK.".to_string(),
+        }
+    }
+}
+
+/// Whether a provider is taking calls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderHealth {
+    Healthy,
+    RateLimited,
+    Unhealthy,
+}
+
+#[derive(Debug, Default)]
+struct LinkState {
+    failures_in_row: u32,
+    probes_passed: u32,
+    unhealthy: bool,
+    cooling_until: Option<Instant>,
+    last_probe: Option<Instant>,
+    last_error: Option<String>,
+}
+
+impl LinkState {
+    fn health(&self, now: Instant) -> ProviderHealth {
+        if self.unhealthy {
+            ProviderHealth::Unhealthy
+        } else if self.cooling_until.is_some_and(|until| until > now) {
+            ProviderHealth::RateLimited
+        } else {
+            ProviderHealth::Healthy
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct LinkCounters {
+    requests: AtomicU64,
+    failures: AtomicU64,
+    rate_limited: AtomicU64,
+    failovers: AtomicU64,
+}
+
+struct Link {
+    provider: String,
+    model: Arc<dyn JudgeModel>,
+    state: Mutex<LinkState>,
+    counters: LinkCounters,
+}
+
+impl Link {
+    fn state(&self) -> std::sync::MutexGuard<'_, LinkState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn succeeded(&self) {
+        let mut state = self.state();
+        state.failures_in_row = 0;
+        state.unhealthy = false;
+        state.cooling_until = None;
+    }
+
+    fn failed(&self, error: &ConversationError, config: &FallbackConfig) {
+        let mut state = self.state();
+        state.last_error = Some(error.to_string());
+        state.probes_passed = 0;
+        self.counters.failures.fetch_add(1, Ordering::Relaxed);
+        if let ConversationError::ModelRateLimited { retry_after, .. } = error {
+            self.counters.rate_limited.fetch_add(1, Ordering::Relaxed);
+            state.cooling_until = Some(Instant::now() + retry_after.unwrap_or(config.rate_limit_cooldown));
+            return;
+        }
+        state.failures_in_row += 1;
+        if state.failures_in_row >= config.unhealthy_after && !state.unhealthy {
+            warn!("Model provider {} is unhealthy: {}", self.provider, error);
+            state.unhealthy = true;
+        }
+    }
+}
+
+/// Whether an error means the provider cannot answer right now, rather
+/// than that the request is at fault
+pub fn is_failover_error(error: &ConversationError) -> bool {
+    matches!(
+        error,
+        ConversationError::ModelUnavailable { .. } | ConversationError::ModelRateLimited { .. }
+    )
+}
+
+/// Models tried in order of preference, failing over between providers
+pub struct ModelFallbackChain {
+    links: Vec<Link>,
+    config: FallbackConfig,
+    active: AtomicUsize,
+}
+
+impl ModelFallbackChain {
+    pub fn new(config: FallbackConfig) -> Self {
+        Self {
+            links: Vec::new(),
+            config,
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    /// Add `model` of `provider` as the next fallback
+    pub fn with_link(mut self, provider: impl Into<String>, model: Arc<dyn JudgeModel>) -> Self {
+        self.links.push(Link {
+            provider: provider.into(),
+            model,
+            state: Mutex::new(LinkState::default()),
+            counters: LinkCounters::default(),
+        });
+        self
+    }
+
+    pub fn config(&self) -> &FallbackConfig {
+        &self.config
+    }
+
+    /// The provider calls go to first
+    pub fn active_provider(&self) -> Option<&str> {
+        self.links.get(self.active.load(Ordering::Relaxed)).map(|link| link.provider.as_str())
+    }
+
+    /// Probe the providers preferred over the active one and the unhealthy
+    /// ones that are due, and return to the most preferred provider that
+    /// has passed enough probes in a row
+    pub async fn probe(&self) {
+        let active = self.active.load(Ordering::Relaxed);
+        let now = Instant::now();
+        for (index, link) in self.links.iter().enumerate() {
+            let due = {
+                let state = link.state();
+                let wanted = index < active || state.unhealthy;
+                let cooling = state.cooling_until.is_some_and(|until| until > now);
+                let waited = !state
+                    .last_probe
+                    .is_some_and(|last| now.duration_since(last) < self.config.probe_interval);
+                wanted && !cooling && waited
+            };
+            if !due {
+                continue;
+            }
+            link.state().last_probe = Some(now);
+
+            match link.model.complete(&self.config.probe_prompt).await {
+                Ok(_) => {
+                    let mut state = link.state();
+                    state.probes_passed += 1;
+                    if state.probes_passed >= self.config.recover_after && state.unhealthy {
+                        info!("Model provider {} recovered", link.provider);
+                        state.unhealthy = false;
+                        state.failures_in_row = 0;
+                    }
+                }
+                Err(e) => link.failed(&e, &self.config),
+            }
+        }
+
+        let recovered = self.links[..active.min(self.links.len())]
+            .iter()
+            .position(|link| link.state().probes_passed >= self.config.recover_after);
+        if let Some(index) = recovered {
+            info!(
+                "Returning model calls from {} to {}",
+                self.links[active].provider, self.links[index].provider
+            );
+            self.links[index].succeeded();
+            self.active.store(index, Ordering::Relaxed);
+        }
+    }
+
+    /// Run [`Self::probe`] every probe interval until the chain is dropped
+    pub fn spawn_probes(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let chain = Arc::downgrade(self);
+        let interval = self.config.probe_interval;
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                let Some(chain) = chain.upgrade() else {
+                    return;
+                };
+                chain.probe().await;
+            }
+        })
+    }
+
+    /// Counters and health of each provider
+    pub fn stats(&self) -> FallbackStats {
+        let active = self.active.load(Ordering::Relaxed);
+        let now = Instant::now();
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        FallbackStats {
+            active: self.links.get(active).map(|link| link.provider.clone()),
+            providers: self
+                .links
+                .iter()
+                .enumerate()
+                .map(|(index, link)| {
+                    let state = link.state();
+                    ProviderStats {
+                        provider: link.provider.clone(),
+                        model: link.model.model().to_string(),
+                        health: state.health(now),
+                        active: index == active,
+                        requests: load(&link.counters.requests),
+                        failures: load(&link.counters.failures),
+                        rate_limited: load(&link.counters.rate_limited),
+                        failovers: load(&link.counters.failovers),
+                        last_error: state.last_error.clone(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Links to try: the active one and those after it, then as a last
+    /// resort the preferred ones not yet proven healthy again
+    fn order(&self) -> impl Iterator<Item = usize> {
+        let active = self.active.load(Ordering::Relaxed).min(self.links.len());
+        (active..self.links.len()).chain(0..active)
+    }
+}
+
+#[async_trait]
+impl JudgeModel for ModelFallbackChain {
+    /// The active provider's model
+    fn model(&self) -> &str {
+        self.links
+            .get(self.active.load(Ordering::Relaxed))
+            .map_or("", |link| link.model.model())
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String> {
+        let active = self.active.load(Ordering::Relaxed);
+        let mut last_error = None;
+        for index in self.order() {
+            let link = &self.links[index];
+            if link.state().health(Instant::now()) != ProviderHealth::Healthy {
+                continue;
+            }
+            link.counters.requests.fetch_add(1, Ordering::Relaxed);
+            match link.model.complete(prompt).await {
+                Ok(text) => {
+                    link.succeeded();
+                    if index != active {
+                        info!("Model calls failed over to {}", link.provider);
+                        self.active.store(index, Ordering::Relaxed);
+                    }
+                    return Ok(text);
+                }
+                Err(e) if is_failover_error(&e) => {
+                    warn!("Model provider {} failed, trying the next: {}", link.provider, e);
+                    link.failed(&e, &self.config);
+                    link.counters.failovers.fetch_add(1, Ordering::Relaxed);
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| ConversationError::ModelUnavailable {
+            model: self.model().to_string(),
+            reason: "every provider in the fallback chain is unavailable".to_string(),
+        }))
+    }
+}
+
+/// Counters and health of one provider of a chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderStats {
+    pub provider: String,
+    pub model: String,
+    pub health: ProviderHealth,
+    /// Whether calls go to this provider first
+    pub active: bool,
+    pub requests: u64,
+    pub failures: u64,
+    pub rate_limited: u64,
+    /// Calls that failed over from this provider to the next
+    pub failovers: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Name, type and help of a per-provider metric, and its value
+type ProviderMetric = (&'static str, &'static str, &'static str, fn(&ProviderStats) -> f64);
+
+/// Counters and health of a chain's providers
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FallbackStats {
+    pub active: Option<String>,
+    pub providers: Vec<ProviderStats>,
+}
+
+impl FallbackStats {
+    /// Prometheus text exposition of the counters, labelled by provider
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        let metrics: [ProviderMetric; 6] = [
+            ("requests_total", "counter", "Calls sent to a model provider", |p| p.requests as f64),
+            ("failures_total", "counter", "Calls a model provider failed", |p| p.failures as f64),
+            ("rate_limited_total", "counter", "Calls a model provider rate limited", |p| p.rate_limited as f64),
+            ("failovers_total", "counter", "Calls failed over from a model provider", |p| p.failovers as f64),
+            ("provider_healthy", "gauge", "Whether a model provider takes calls", |p| {
+                f64::from(u8::from(p.health == ProviderHealth::Healthy))
+            }),
+            ("provider_active", "gauge", "Whether calls go to a model provider first", |p| {
+                f64::from(u8::from(p.active))
+            }),
+        ];
+        for (name, kind, help, value) in metrics {
+            let _ = writeln!(out, "# HELP {prefix}_model_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_model_{name} {kind}");
+            for provider in &self.providers {
+                let _ = writeln!(
+                    out,
+                    "{prefix}_model_{name}{{provider=\"{}\",model=\"{}\"}} {}",
+                    provider.provider,
+                    provider.model,
+                    value(provider)
+                );
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    /// Answers with its name, or fails while `down`
+    struct Scripted {
+        name: &'static str,
+        down: AtomicBool,
+        rate_limited: AtomicBool,
+    }
+
+    impl Scripted {
+        fn new(name: &'static str) -> Arc<Self> {
+            Arc::new(Self {
+                name,
+                down: AtomicBool::new(false),
+                rate_limited: AtomicBool::new(false),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl JudgeModel for Scripted {
+        fn model(&self) -> &str {
+            self.name
+        }
+
+        async fn complete(&self, _prompt: &str) -> Result<String> {
+            if self.rate_limited.load(Ordering::Relaxed) {
+                return Err(ConversationError::ModelRateLimited {
+                    model: self.name.to_string(),
+                    retry_after: Some(Duration::from_secs(60)),
+                });
+            }
+            if self.down.load(Ordering::Relaxed) {
+                return Err(ConversationError::ModelUnavailable {
+                    model: self.name.to_string(),
+                    reason: "503".to_string(),
+                });
+            }
+            Ok(self.name.to_string())
+        }
+    }
+
+    fn chain(primary: &Arc<Scripted>, secondary: &Arc<Scripted>, local: &Arc<Scripted>) -> ModelFallbackChain {
+        ModelFallbackChain::new(FallbackConfig {
+            unhealthy_after: 2,
+            recover_after: 2,
+            probe_interval: Duration::ZERO,
+            ..Default::default()
+        })
+        .with_link("anthropic", primary.clone())
+        .with_link("bedrock", secondary.clone())
+        .with_link("local", local.clone())
+    }
+
+    #[tokio::test]
+    async fn test_outage_fails_over_and_recovers_only_after_probes() {
+        let (primary, secondary, local) = (Scripted::new("opus"), Scripted::new("sonnet"), Scripted::new("llama"));
+        let chain = chain(&primary, &secondary, &local);
+        assert_eq!(chain.complete("hi").await.unwrap(), "opus");
+
+        primary.down.store(true, Ordering::Relaxed);
+        assert_eq!(chain.complete("hi").await.unwrap(), "sonnet");
+        assert_eq!(chain.active_provider(), Some("bedrock"));
+
+        // Sticky: the primary is back but not yet proven
+        primary.down.store(false, Ordering::Relaxed);
+        assert_eq!(chain.complete("hi").await.unwrap(), "sonnet");
+        chain.probe().await;
+        assert_eq!(chain.active_provider(), Some("bedrock"));
+        chain.probe().await;
+        assert_eq!(chain.active_provider(), Some("anthropic"));
+        assert_eq!(chain.complete("hi").await.unwrap(), "opus");
+
+        let stats = chain.stats();
+        assert_eq!(stats.providers[0].failovers, 1);
+        assert_eq!(stats.providers[1].requests, 2);
+        assert!(stats
+            .render_prometheus("copilot")
+            .contains("copilot_model_failovers_total{provider=\"anthropic\",model=\"opus\"} 1"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limits_and_outages_fall_through_to_local() {
+        let (primary, secondary, local) = (Scripted::new("opus"), Scripted::new("sonnet"), Scripted::new("llama"));
+        let chain = chain(&primary, &secondary, &local);
+
+        primary.rate_limited.store(true, Ordering::Relaxed);
+        secondary.down.store(true, Ordering::Relaxed);
+        assert_eq!(chain.complete("hi").await.unwrap(), "llama");
+        assert_eq!(chain.stats().providers[0].health, ProviderHealth::RateLimited);
+
+        // A second failure marks the secondary unhealthy; probing it while
+        // it is still down keeps it so
+        primary.rate_limited.store(false, Ordering::Relaxed);
+        chain.probe().await;
+        assert_eq!(chain.stats().providers[1].health, ProviderHealth::Unhealthy);
+
+        local.down.store(true, Ordering::Relaxed);
+        assert!(matches!(
+            chain.complete("hi").await,
+            Err(ConversationError::ModelUnavailable { .. })
+        ));
+    }
+}
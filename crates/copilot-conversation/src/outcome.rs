@@ -0,0 +1,69 @@
+//! Outcomes of answered questions
+//!
+//! Every answer the manager generates is summed up as an [`AnswerOutcome`]:
+//! how many sources it drew on and, when answers are scored, how well it is
+//! grounded in them. An [`AnswerObserver`] set on the manager receives each
+//! one, e.g. to find the questions the corpus cannot answer; a
+//! [`CompositeAnswerObserver`] passes them to several.
+
+use crate::context_limits::ContextDownsize;
+use crate::experiments::ExperimentAssignment;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// How a question was answered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnswerOutcome {
+    pub session_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub query: String,
+    /// Intent classified for the question; cached answers have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intent: Option<String>,
+    /// Groundedness score of the answer, if answers are scored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// Distinct sources the answer drew on
+    pub citations: usize,
+    /// Whether the answer was served from the answer cache
+    #[serde(default)]
+    pub cached: bool,
+    /// Experiment variant the answer was generated with; cached answers
+    /// have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experiment: Option<ExperimentAssignment>,
+    /// How the retrieved context was cut to fit the model, if it was
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_downsize: Option<ContextDownsize>,
+}
+
+/// Receives the outcome of each answer
+pub trait AnswerObserver: Send + Sync {
+    fn observe(&self, outcome: &AnswerOutcome);
+}
+
+/// Passes each outcome to several observers, in order
+#[derive(Default)]
+pub struct CompositeAnswerObserver {
+    observers: Vec<Arc<dyn AnswerObserver>>,
+}
+
+impl CompositeAnswerObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_observer(mut self, observer: Arc<dyn AnswerObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+}
+
+impl AnswerObserver for CompositeAnswerObserver {
+    fn observe(&self, outcome: &AnswerOutcome) {
+        for observer in &self.observers {
+            observer.observe(outcome);
+        }
+    }
+}
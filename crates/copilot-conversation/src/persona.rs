@@ -0,0 +1,324 @@
+//! Conversation personas
+//!
+//! A persona bundles the system prompt, tool allowlist, sandbox policy,
+//! retrieval sources and model preferences that apply to a conversation. Personas are assigned when
+//! a session is created and enforced by the conversation manager, so clients
+//! cannot widen a persona's tools or sources from the request side.
+
+use crate::{ConversationError, Result};
+use chrono::{DateTime, Utc};
+use copilot_context::retrieval::RetrievalResult;
+use copilot_core::SandboxPolicy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Wildcard entry that allows every tool or source
+pub const ALLOW_ALL: &str = "*";
+
+/// Model preferences for a persona
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelPreferences {
+    /// Preferred models, in order of preference
+    #[serde(default)]
+    pub preferred_models: Vec<String>,
+    /// Sampling temperature override
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Maximum output tokens per response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<usize>,
+}
+
+/// A conversation persona
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Persona {
+    /// Unique persona identifier (e.g. "sre-assistant")
+    pub id: String,
+    /// Display name
+    pub name: String,
+    /// Short description
+    #[serde(default)]
+    pub description: String,
+    /// System prompt prepended to every conversation using this persona
+    pub system_prompt: String,
+    /// Tools the persona may invoke; `"*"` allows every tool
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+    /// Context sources retrieval may draw from; empty or `"*"` allows all
+    #[serde(default)]
+    pub retrieval_sources: Vec<String>,
+    /// Model preferences
+    #[serde(default)]
+    pub model: ModelPreferences,
+    /// Capabilities of the sandbox the persona's tools run in
+    #[serde(default)]
+    pub sandbox: SandboxPolicy,
+    /// Whether this is a built-in persona (read-only)
+    #[serde(default)]
+    pub builtin: bool,
+    /// Last modification time
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Persona {
+    /// Create a persona with the given ID, name and system prompt
+    pub fn new(id: impl Into<String>, name: impl Into<String>, system_prompt: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            description: String::new(),
+            system_prompt: system_prompt.into(),
+            allowed_tools: Vec::new(),
+            retrieval_sources: Vec::new(),
+            model: ModelPreferences::default(),
+            sandbox: SandboxPolicy::default(),
+            builtin: false,
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn with_tools(mut self, tools: &[&str]) -> Self {
+        self.allowed_tools = tools.iter().map(|t| t.to_string()).collect();
+        self
+    }
+
+    pub fn with_sources(mut self, sources: &[&str]) -> Self {
+        self.retrieval_sources = sources.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    pub fn with_model(mut self, model: ModelPreferences) -> Self {
+        self.model = model;
+        self
+    }
+
+    pub fn with_sandbox(mut self, sandbox: SandboxPolicy) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Validate the persona definition
+    pub fn validate(&self) -> Result<()> {
+        if self.id.trim().is_empty() {
+            return Err(ConversationError::InvalidPersona("id must not be empty".to_string()));
+        }
+        if self.system_prompt.trim().is_empty() {
+            return Err(ConversationError::InvalidPersona(format!(
+                "persona {} must have a system prompt",
+                self.id
+            )));
+        }
+        if let Some(temperature) = self.model.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                return Err(ConversationError::InvalidPersona(format!(
+                    "temperature must be in [0, 2], got {}",
+                    temperature
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the persona may invoke `tool`
+    pub fn allows_tool(&self, tool: &str) -> bool {
+        self.allowed_tools.iter().any(|t| t == ALLOW_ALL || t == tool)
+    }
+
+    /// Whether retrieval may use context from `source`
+    pub fn allows_source(&self, source: &str) -> bool {
+        self.retrieval_sources.is_empty()
+            || self.retrieval_sources.iter().any(|s| s == ALLOW_ALL || s == source)
+    }
+
+    /// Remove context items from sources this persona may not use
+    pub fn filter_context(&self, result: &mut RetrievalResult) {
+        result.selected.retain(|s| self.allows_source(&s.item.metadata.source));
+        result.rejected.retain(|s| self.allows_source(&s.item.metadata.source));
+        result.total_tokens = result.selected.iter().map(|s| s.item.token_count).sum();
+    }
+}
+
+/// Built-in personas shipped with the server
+pub fn builtin_personas() -> Vec<Persona> {
+    vec![
+        Persona::new(
+            "sre-assistant",
+            "SRE Assistant",
+            "You are a site reliability engineer. Diagnose incidents from metrics, logs and \
+             traces, state your confidence, and prefer reversible mitigations.",
+        )
+        .with_description("Incident triage and operational troubleshooting")
+        .with_tools(&["metrics_query", "log_search", "trace_lookup", "incident_lookup", "workflow_trigger"])
+        .with_sources(&["metrics", "logs", "traces", "runbooks", "incidents"])
+        .with_model(ModelPreferences {
+            temperature: Some(0.2),
+            ..Default::default()
+        }),
+        Persona::new(
+            "code-reviewer",
+            "Code Reviewer",
+            "You are a meticulous code reviewer. Point out correctness, security and \
+             maintainability issues with concrete suggestions; do not rewrite unrelated code.",
+        )
+        .with_description("Review diffs and source files")
+        .with_tools(&["code_search", "file_read", "sandbox_execute"])
+        .with_sources(&["code", "documentation"])
+        .with_sandbox(SandboxPolicy::locked_down())
+        .with_model(ModelPreferences {
+            temperature: Some(0.1),
+            ..Default::default()
+        }),
+        Persona::new(
+            "analyst",
+            "Analyst",
+            "You are a data analyst. Answer with numbers where possible, show the queries \
+             you used, and call out gaps in the data.",
+        )
+        .with_description("Metrics, cost and usage analysis")
+        .with_tools(&["metrics_query", "sql_query"])
+        .with_sources(&["metrics", "analytics", "documentation"])
+        .with_model(ModelPreferences {
+            temperature: Some(0.3),
+            ..Default::default()
+        }),
+    ]
+    .into_iter()
+    .map(|mut p| {
+        p.builtin = true;
+        p
+    })
+    .collect()
+}
+
+/// Registry of available personas
+pub struct PersonaRegistry {
+    personas: HashMap<String, Persona>,
+}
+
+impl PersonaRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self {
+            personas: HashMap::new(),
+        }
+    }
+
+    /// Create a registry pre-populated with the built-in personas
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        for persona in builtin_personas() {
+            registry.personas.insert(persona.id.clone(), persona);
+        }
+        registry
+    }
+
+    /// Get a persona by ID
+    pub fn get(&self, id: &str) -> Option<&Persona> {
+        self.personas.get(id)
+    }
+
+    /// List all personas, sorted by ID
+    pub fn list(&self) -> Vec<Persona> {
+        let mut personas: Vec<_> = self.personas.values().cloned().collect();
+        personas.sort_by(|a, b| a.id.cmp(&b.id));
+        personas
+    }
+
+    /// Create or replace a custom persona
+    pub fn upsert(&mut self, mut persona: Persona) -> Result<Persona> {
+        persona.validate()?;
+        if self.personas.get(&persona.id).is_some_and(|p| p.builtin) {
+            return Err(ConversationError::InvalidPersona(format!(
+                "built-in persona {} cannot be modified",
+                persona.id
+            )));
+        }
+
+        persona.builtin = false;
+        persona.updated_at = Utc::now();
+        self.personas.insert(persona.id.clone(), persona.clone());
+        Ok(persona)
+    }
+
+    /// Remove a custom persona
+    pub fn remove(&mut self, id: &str) -> Result<Persona> {
+        match self.personas.get(id) {
+            None => Err(ConversationError::PersonaNotFound(id.to_string())),
+            Some(p) if p.builtin => Err(ConversationError::InvalidPersona(format!(
+                "built-in persona {} cannot be deleted",
+                id
+            ))),
+            Some(_) => Ok(self.personas.remove(id).expect("persona exists")),
+        }
+    }
+}
+
+impl Default for PersonaRegistry {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtins_present_and_read_only() {
+        let mut registry = PersonaRegistry::with_builtins();
+        let ids: Vec<_> = registry.list().into_iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec!["analyst", "code-reviewer", "sre-assistant"]);
+
+        assert!(registry.remove("analyst").is_err());
+        let overwrite = Persona::new("analyst", "Analyst", "Say anything");
+        assert!(registry.upsert(overwrite).is_err());
+    }
+
+    #[test]
+    fn test_custom_persona_lifecycle() {
+        let mut registry = PersonaRegistry::with_builtins();
+
+        // Clients cannot smuggle in a built-in flag
+        let mut persona = Persona::new("support", "Support", "Be helpful").with_tools(&["ticket_lookup"]);
+        persona.builtin = true;
+        let stored = registry.upsert(persona).unwrap();
+        assert!(!stored.builtin);
+
+        assert!(registry.remove("support").is_ok());
+        assert!(matches!(
+            registry.remove("support"),
+            Err(ConversationError::PersonaNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(Persona::new("", "Empty", "prompt").validate().is_err());
+        assert!(Persona::new("x", "X", "  ").validate().is_err());
+
+        let hot = Persona::new("x", "X", "prompt").with_model(ModelPreferences {
+            temperature: Some(5.0),
+            ..Default::default()
+        });
+        assert!(hot.validate().is_err());
+    }
+
+    #[test]
+    fn test_tool_and_source_policy() {
+        let sre = PersonaRegistry::with_builtins().get("sre-assistant").cloned().unwrap();
+        assert!(sre.allows_tool("log_search"));
+        assert!(!sre.allows_tool("sandbox_execute"));
+        assert!(sre.allows_source("runbooks"));
+        assert!(!sre.allows_source("code"));
+
+        let open = Persona::new("open", "Open", "prompt").with_tools(&[ALLOW_ALL]);
+        assert!(open.allows_tool("anything"));
+        assert!(open.allows_source("anything"));
+    }
+}
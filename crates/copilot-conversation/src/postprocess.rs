@@ -0,0 +1,530 @@
+//! Response post-processing
+//!
+//! A [`PostProcessor`] runs chat responses through an ordered chain of
+//! [`ResponseStage`]s before they are recorded and returned, so deployments
+//! can lint markdown, tag code blocks, drop dubious links, filter phrases or
+//! cite sources without patching handlers. Chains are usually built from a
+//! [`PostProcessingConfig`]; custom stages implement the trait and are added
+//! with [`PostProcessor::with_stage`].
+//!
+//! A stage that fails is skipped with a warning, so a misbehaving stage
+//! never costs the user their answer. Streamed responses are sent as they
+//! are generated and are not post-processed.
+
+use crate::{ConversationError, Result};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use tracing::warn;
+
+/// What a stage may know about the response it processes
+#[derive(Debug, Clone, Default)]
+pub struct ResponseContext {
+    pub session_id: String,
+    /// Tenant owning the session, if known
+    pub tenant_id: Option<String>,
+    /// The user's message
+    pub query: String,
+    /// Sources of the context the response was generated from, most
+    /// relevant first
+    pub sources: Vec<String>,
+    /// Sources with context that failed signature verification
+    pub untrusted_sources: Vec<String>,
+}
+
+/// One step of the post-processing chain
+pub trait ResponseStage: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    /// The response after this stage
+    fn process(&self, response: String, context: &ResponseContext) -> Result<String>;
+}
+
+/// Ordered chain of response stages
+#[derive(Clone, Default)]
+pub struct PostProcessor {
+    stages: Vec<Arc<dyn ResponseStage>>,
+}
+
+impl PostProcessor {
+    /// A chain without stages, which returns responses unchanged
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the chain a configuration describes
+    pub fn from_config(config: &PostProcessingConfig) -> Result<Self> {
+        let mut processor = Self::new();
+        for stage in &config.stages {
+            processor.stages.push(stage.build()?);
+        }
+        Ok(processor)
+    }
+
+    /// Append `stage` to the chain
+    pub fn with_stage(mut self, stage: Arc<dyn ResponseStage>) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    pub fn stage_names(&self) -> Vec<&str> {
+        self.stages.iter().map(|stage| stage.name()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Run `response` through every stage in order
+    pub fn process(&self, response: String, context: &ResponseContext) -> String {
+        self.stages.iter().fold(response, |response, stage| {
+            match stage.process(response.clone(), context) {
+                Ok(processed) => processed,
+                Err(e) => {
+                    warn!("Post-processing stage {} failed, skipping it: {}", stage.name(), e);
+                    response
+                }
+            }
+        })
+    }
+}
+
+impl std::fmt::Debug for PostProcessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostProcessor").field("stages", &self.stage_names()).finish()
+    }
+}
+
+/// Stages to run on chat responses, in order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PostProcessingConfig {
+    #[serde(default)]
+    pub stages: Vec<StageConfig>,
+}
+
+impl PostProcessingConfig {
+    /// Read a configuration from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// A built-in stage and its settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum StageConfig {
+    MarkdownLint,
+    CodeLanguage {
+        /// Language for blocks that match no known language
+        #[serde(default = "default_language")]
+        fallback: String,
+    },
+    LinkValidation {
+        #[serde(default = "default_schemes")]
+        allowed_schemes: Vec<String>,
+        /// Domains whose links are removed, including their subdomains
+        #[serde(default)]
+        blocked_domains: Vec<String>,
+    },
+    BannedPhrases {
+        phrases: Vec<String>,
+        #[serde(default = "default_replacement")]
+        replacement: String,
+    },
+    Citations {
+        #[serde(default = "default_max_citations")]
+        max_sources: usize,
+    },
+}
+
+fn default_language() -> String {
+    "text".to_string()
+}
+
+fn default_schemes() -> Vec<String> {
+    vec!["http".to_string(), "https".to_string(), "mailto".to_string()]
+}
+
+fn default_replacement() -> String {
+    "[removed]".to_string()
+}
+
+fn default_max_citations() -> usize {
+    5
+}
+
+impl StageConfig {
+    fn build(&self) -> Result<Arc<dyn ResponseStage>> {
+        Ok(match self {
+            Self::MarkdownLint => Arc::new(MarkdownLint),
+            Self::CodeLanguage { fallback } => Arc::new(CodeLanguageTagger::new(fallback.clone())),
+            Self::LinkValidation { allowed_schemes, blocked_domains } => Arc::new(LinkValidator {
+                allowed_schemes: allowed_schemes.clone(),
+                blocked_domains: blocked_domains.clone(),
+            }),
+            Self::BannedPhrases { phrases, replacement } => {
+                Arc::new(BannedPhraseFilter::new(phrases, replacement.clone())?)
+            }
+            Self::Citations { max_sources } => Arc::new(CitationInserter { max_sources: *max_sources }),
+        })
+    }
+}
+
+/// Splits markdown into code fences and the prose between them
+fn map_prose(response: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let mut out = Vec::new();
+    let mut prose = Vec::new();
+    let mut in_code = false;
+    for line in response.lines() {
+        if line.trim_start().starts_with("```") {
+            if !in_code && !prose.is_empty() {
+                out.push(f(&prose.join("\n")));
+                prose.clear();
+            }
+            in_code = !in_code;
+            out.push(line.to_string());
+        } else if in_code {
+            out.push(line.to_string());
+        } else {
+            prose.push(line);
+        }
+    }
+    if !prose.is_empty() {
+        out.push(f(&prose.join("\n")));
+    }
+    out.join("\n")
+}
+
+/// Fixes common markdown slips: trailing whitespace, runs of blank lines,
+/// headings without a space after `#` and unclosed code fences
+pub struct MarkdownLint;
+
+impl ResponseStage for MarkdownLint {
+    fn name(&self) -> &str {
+        "markdown_lint"
+    }
+
+    fn process(&self, response: String, _context: &ResponseContext) -> Result<String> {
+        static HEADING: OnceLock<Regex> = OnceLock::new();
+        static BLANK_RUN: OnceLock<Regex> = OnceLock::new();
+        let heading = HEADING.get_or_init(|| Regex::new(r"(?m)^(#{1,6})([^#\s])").unwrap());
+        let blank_run = BLANK_RUN.get_or_init(|| Regex::new(r"\n{3,}").unwrap());
+
+        let trimmed: Vec<&str> = response.lines().map(str::trim_end).collect();
+        let mut linted = map_prose(&trimmed.join("\n"), |prose| {
+            let prose = heading.replace_all(prose, "$1 $2");
+            blank_run.replace_all(&prose, "\n\n").into_owned()
+        });
+
+        let fences = linted.lines().filter(|line| line.trim_start().starts_with("```")).count();
+        if fences % 2 == 1 {
+            linted.push_str("\n```");
+        }
+        Ok(linted.trim_end().to_string())
+    }
+}
+
+/// Adds a language to code fences that have none, guessed from the code
+pub struct CodeLanguageTagger {
+    fallback: String,
+}
+
+impl CodeLanguageTagger {
+    pub fn new(fallback: impl Into<String>) -> Self {
+        Self { fallback: fallback.into() }
+    }
+
+    fn guess(&self, code: &str) -> String {
+        let trimmed = code.trim();
+        let has_line = |prefixes: &[&str]| {
+            code.lines().any(|line| prefixes.iter().any(|p| line.trim_start().starts_with(p)))
+        };
+        let language = if (trimmed.starts_with('{') || trimmed.starts_with('['))
+            && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+        {
+            "json"
+        } else if has_line(&["fn ", "pub fn ", "use ", "impl ", "let mut ", "#[derive"]) {
+            "rust"
+        } else if has_line(&["def ", "import ", "from ", "class "]) && code.contains(':') {
+            "python"
+        } else if has_line(&["func ", "package "]) {
+            "go"
+        } else if has_line(&["const ", "function ", "export ", "interface "]) || code.contains("=>") {
+            "typescript"
+        } else if has_line(&["SELECT ", "INSERT ", "UPDATE ", "CREATE TABLE"]) {
+            "sql"
+        } else if has_line(&["$ ", "#!/", "cargo ", "npm ", "kubectl ", "docker ", "git ", "curl "]) {
+            "bash"
+        } else if code.lines().filter(|l| !l.trim().is_empty()).all(|l| l.contains(": ") || l.trim_end().ends_with(':') || l.trim_start().starts_with("- ")) {
+            "yaml"
+        } else {
+            &self.fallback
+        };
+        language.to_string()
+    }
+}
+
+impl ResponseStage for CodeLanguageTagger {
+    fn name(&self) -> &str {
+        "code_language"
+    }
+
+    fn process(&self, response: String, _context: &ResponseContext) -> Result<String> {
+        let lines: Vec<&str> = response.lines().collect();
+        let mut out = Vec::with_capacity(lines.len());
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i];
+            if line.trim() == "```" {
+                // An untagged opening fence; find its closing fence
+                let end = lines[i + 1..].iter().position(|l| l.trim_start().starts_with("```"));
+                let code = match end {
+                    Some(end) => lines[i + 1..i + 1 + end].join("\n"),
+                    None => lines[i + 1..].join("\n"),
+                };
+                out.push(format!("{}{}", line, self.guess(&code)));
+                let block_end = end.map_or(lines.len(), |end| i + 2 + end);
+                out.extend(lines[i + 1..block_end].iter().map(|l| l.to_string()));
+                i = block_end;
+            } else if line.trim_start().starts_with("```") {
+                // Already tagged; copy the block through
+                let end = lines[i + 1..].iter().position(|l| l.trim_start().starts_with("```"));
+                let block_end = end.map_or(lines.len(), |end| i + 2 + end);
+                out.extend(lines[i..block_end].iter().map(|l| l.to_string()));
+                i = block_end;
+            } else {
+                out.push(line.to_string());
+                i += 1;
+            }
+        }
+        Ok(out.join("\n"))
+    }
+}
+
+/// Replaces markdown links that do not parse, use a disallowed scheme or
+/// point at a blocked domain with their text
+pub struct LinkValidator {
+    pub allowed_schemes: Vec<String>,
+    pub blocked_domains: Vec<String>,
+}
+
+impl LinkValidator {
+    fn is_valid(&self, target: &str) -> bool {
+        let Ok(url) = url::Url::parse(target) else {
+            return false;
+        };
+        if !self.allowed_schemes.iter().any(|s| s.eq_ignore_ascii_case(url.scheme())) {
+            return false;
+        }
+        if url.scheme() == "mailto" {
+            return true;
+        }
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        !self.blocked_domains.iter().any(|domain| {
+            host.eq_ignore_ascii_case(domain) || host.to_ascii_lowercase().ends_with(&format!(".{}", domain.to_ascii_lowercase()))
+        })
+    }
+}
+
+impl ResponseStage for LinkValidator {
+    fn name(&self) -> &str {
+        "link_validation"
+    }
+
+    fn process(&self, response: String, _context: &ResponseContext) -> Result<String> {
+        static LINK: OnceLock<Regex> = OnceLock::new();
+        let link = LINK.get_or_init(|| Regex::new(r"\[([^\]]+)\]\(([^)\s]+)\)").unwrap());
+        Ok(map_prose(&response, |prose| {
+            link.replace_all(prose, |caps: &Captures| {
+                if self.is_valid(&caps[2]) {
+                    caps[0].to_string()
+                } else {
+                    caps[1].to_string()
+                }
+            })
+            .into_owned()
+        }))
+    }
+}
+
+/// Replaces banned phrases, ignoring case, wherever they appear as whole
+/// words
+pub struct BannedPhraseFilter {
+    pattern: Option<Regex>,
+    replacement: String,
+}
+
+impl BannedPhraseFilter {
+    pub fn new(phrases: &[String], replacement: impl Into<String>) -> Result<Self> {
+        let phrases: Vec<String> = phrases
+            .iter()
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| regex::escape(p.trim()))
+            .collect();
+        let pattern = if phrases.is_empty() {
+            None
+        } else {
+            let alternation = format!(r"(?i)\b(?:{})\b", phrases.join("|"));
+            Some(Regex::new(&alternation).map_err(|e| ConversationError::PostProcessingError(e.to_string()))?)
+        };
+        Ok(Self { pattern, replacement: replacement.into() })
+    }
+}
+
+impl ResponseStage for BannedPhraseFilter {
+    fn name(&self) -> &str {
+        "banned_phrases"
+    }
+
+    fn process(&self, response: String, _context: &ResponseContext) -> Result<String> {
+        Ok(match &self.pattern {
+            Some(pattern) => pattern.replace_all(&response, self.replacement.as_str()).into_owned(),
+            None => response,
+        })
+    }
+}
+
+/// Appends the sources of the response's context as a numbered list,
+/// labeling sources whose content failed signature verification
+pub struct CitationInserter {
+    pub max_sources: usize,
+}
+
+impl ResponseStage for CitationInserter {
+    fn name(&self) -> &str {
+        "citations"
+    }
+
+    fn process(&self, mut response: String, context: &ResponseContext) -> Result<String> {
+        let mut sources: Vec<&str> = Vec::new();
+        for source in &context.sources {
+            if !sources.contains(&source.as_str()) {
+                sources.push(source);
+            }
+        }
+        sources.truncate(self.max_sources);
+        if sources.is_empty() {
+            return Ok(response);
+        }
+
+        response.push_str("\n\nSources:");
+        for (i, source) in sources.iter().enumerate() {
+            response.push_str(&format!("\n{}. {}", i + 1, source));
+            if context.untrusted_sources.iter().any(|untrusted| untrusted == source) {
+                response.push_str(" (unverified)");
+            }
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(stage: &dyn ResponseStage, response: &str) -> String {
+        stage.process(response.to_string(), &ResponseContext::default()).unwrap()
+    }
+
+    #[test]
+    fn test_markdown_lint() {
+        let response = "##Setup   \n\n\n\nRun this:\n```bash\n#not a heading\n\n\n\ncargo build";
+        assert_eq!(
+            run(&MarkdownLint, response),
+            "## Setup\n\nRun this:\n```bash\n#not a heading\n\n\n\ncargo build\n```"
+        );
+    }
+
+    #[test]
+    fn test_code_language_tagging() {
+        let tagger = CodeLanguageTagger::new("text");
+        let response = "```\nfn main() {}\n```\n\n```python\nprint(1)\n```\n\n```\n$ cargo test\n```\n\n```\n{\"a\": 1}\n```\n\n```\n???\n```";
+        assert_eq!(
+            run(&tagger, response),
+            "```rust\nfn main() {}\n```\n\n```python\nprint(1)\n```\n\n```bash\n$ cargo test\n```\n\n```json\n{\"a\": 1}\n```\n\n```text\n???\n```"
+        );
+    }
+
+    #[test]
+    fn test_link_validation() {
+        let validator = LinkValidator {
+            allowed_schemes: default_schemes(),
+            blocked_domains: vec!["evil.example".to_string()],
+        };
+        let response = "See [docs](https://docs.rs/tokio), [this](javascript:void), \
+                        [that](https://cdn.evil.example/x), [bad](../relative) and [mail](mailto:ops@example.com).";
+        assert_eq!(
+            run(&validator, response),
+            "See [docs](https://docs.rs/tokio), this, that, bad and [mail](mailto:ops@example.com)."
+        );
+        assert_eq!(run(&validator, "```\n[x](javascript:y)\n```"), "```\n[x](javascript:y)\n```");
+    }
+
+    #[test]
+    fn test_banned_phrases() {
+        let filter = BannedPhraseFilter::new(&["as an AI".to_string(), "guaranteed".to_string()], "[removed]").unwrap();
+        assert_eq!(
+            run(&filter, "As an AI, this fix is Guaranteed; not unguaranteed."),
+            "[removed], this fix is [removed]; not unguaranteed."
+        );
+    }
+
+    #[test]
+    fn test_configured_chain_runs_in_order() {
+        let config: PostProcessingConfig = serde_json::from_str(
+            r#"{"stages": [
+                {"stage": "banned_phrases", "phrases": ["obviously"]},
+                {"stage": "markdown_lint"},
+                {"stage": "citations", "max_sources": 2}
+            ]}"#,
+        )
+        .unwrap();
+        let processor = PostProcessor::from_config(&config).unwrap();
+        assert_eq!(processor.stage_names(), vec!["banned_phrases", "markdown_lint", "citations"]);
+
+        let context = ResponseContext {
+            sources: vec!["runbook.md".to_string(), "runbook.md".to_string(), "wiki/deploy".to_string(), "chat".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            processor.process("Obviously, restart it.  \n\n\n".to_string(), &context),
+            "[removed], restart it.\n\nSources:\n1. runbook.md\n2. wiki/deploy"
+        );
+    }
+
+    #[test]
+    fn test_citations_label_untrusted_sources() {
+        let context = ResponseContext {
+            sources: vec!["runbook.md".to_string(), "pastebin".to_string()],
+            untrusted_sources: vec!["pastebin".to_string()],
+            ..Default::default()
+        };
+        let stage = CitationInserter { max_sources: 5 };
+        assert_eq!(
+            stage.process("Restart it.".to_string(), &context).unwrap(),
+            "Restart it.\n\nSources:\n1. runbook.md\n2. pastebin (unverified)"
+        );
+    }
+
+    #[test]
+    fn test_failing_stage_is_skipped() {
+        struct Broken;
+        impl ResponseStage for Broken {
+            fn name(&self) -> &str {
+                "broken"
+            }
+            fn process(&self, _response: String, _context: &ResponseContext) -> Result<String> {
+                Err(ConversationError::PostProcessingError("boom".to_string()))
+            }
+        }
+
+        let processor = PostProcessor::new()
+            .with_stage(Arc::new(Broken))
+            .with_stage(Arc::new(MarkdownLint));
+        assert_eq!(processor.process("#Done ".to_string(), &ResponseContext::default()), "# Done");
+    }
+}
@@ -0,0 +1,522 @@
+//! Per-user preference memory
+//!
+//! Preferences such as verbosity, answer language, the default time range
+//! and favorite services are picked up from what users say in their
+//! conversations, but a learned preference is only a
+//! [`PreferenceSuggestion`] until the user confirms it. Confirmed
+//! preferences are added to the system prompt of the user's sessions. Users
+//! can view, edit and delete their preferences at any time, dismiss
+//! suggestions (which are then not offered again), and switch learning off
+//! altogether.
+
+use crate::{ConversationError, Result};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{OnceLock, RwLock};
+use uuid::Uuid;
+
+/// How many times a preference has to show up before it is suggested
+pub const DEFAULT_MIN_OBSERVATIONS: usize = 2;
+
+/// How long answers should be
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    Brief,
+    Normal,
+    Detailed,
+}
+
+/// Preferences a user has confirmed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserPreferences {
+    /// How long answers should be
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verbosity: Option<Verbosity>,
+    /// Language to answer in (e.g. "Spanish")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Time range to use when a question gives none (e.g. "24h", "7d")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_time_range: Option<String>,
+    /// Services the user works on most
+    #[serde(default)]
+    pub favorite_services: Vec<String>,
+    /// Whether preferences may be learned from the user's messages
+    #[serde(default = "default_learning")]
+    pub learning: bool,
+    /// Last change, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+fn default_learning() -> bool {
+    true
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        Self {
+            verbosity: None,
+            language: None,
+            default_time_range: None,
+            favorite_services: Vec::new(),
+            learning: true,
+            updated_at: None,
+        }
+    }
+}
+
+impl UserPreferences {
+    /// Whether no preference is set
+    pub fn is_empty(&self) -> bool {
+        self.verbosity.is_none()
+            && self.language.is_none()
+            && self.default_time_range.is_none()
+            && self.favorite_services.is_empty()
+    }
+
+    /// Validate user-supplied preferences
+    pub fn validate(&self) -> Result<()> {
+        if self.language.as_deref().is_some_and(|l| l.trim().is_empty()) {
+            return Err(ConversationError::InvalidPreference(
+                "language must not be empty".to_string(),
+            ));
+        }
+        if let Some(range) = &self.default_time_range {
+            if !time_range_pattern().is_match(range) {
+                return Err(ConversationError::InvalidPreference(format!(
+                    "default time range must look like 30m, 24h, 7d or 2w, got {:?}",
+                    range
+                )));
+            }
+        }
+        if self.favorite_services.iter().any(|s| s.trim().is_empty()) {
+            return Err(ConversationError::InvalidPreference(
+                "favorite services must not be empty".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the preference is already in place
+    pub fn contains(&self, preference: &LearnedPreference) -> bool {
+        match preference {
+            LearnedPreference::Verbosity(v) => self.verbosity == Some(*v),
+            LearnedPreference::Language(l) => self.language.as_deref() == Some(l),
+            LearnedPreference::DefaultTimeRange(r) => self.default_time_range.as_deref() == Some(r),
+            LearnedPreference::FavoriteService(s) => self.favorite_services.contains(s),
+        }
+    }
+
+    /// Apply a confirmed preference
+    pub fn apply(&mut self, preference: &LearnedPreference) {
+        match preference {
+            LearnedPreference::Verbosity(v) => self.verbosity = Some(*v),
+            LearnedPreference::Language(l) => self.language = Some(l.clone()),
+            LearnedPreference::DefaultTimeRange(r) => self.default_time_range = Some(r.clone()),
+            LearnedPreference::FavoriteService(s) => {
+                if !self.favorite_services.contains(s) {
+                    self.favorite_services.push(s.clone());
+                }
+            }
+        }
+        self.updated_at = Some(Utc::now());
+    }
+
+    /// Instructions for the system prompt, if any preference is set
+    pub fn prompt_instructions(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut lines = vec!["The user has asked for the following:".to_string()];
+        match self.verbosity {
+            Some(Verbosity::Brief) => lines.push("- Keep answers brief.".to_string()),
+            Some(Verbosity::Detailed) => {
+                lines.push("- Give detailed answers with the reasoning behind them.".to_string())
+            }
+            Some(Verbosity::Normal) | None => {}
+        }
+        if let Some(language) = &self.language {
+            lines.push(format!("- Answer in {}.", language));
+        }
+        if let Some(range) = &self.default_time_range {
+            lines.push(format!("- When a question gives no time range, use the last {}.", range));
+        }
+        if !self.favorite_services.is_empty() {
+            lines.push(format!(
+                "- They mostly work on: {}.",
+                self.favorite_services.join(", ")
+            ));
+        }
+        Some(lines.join("\n"))
+    }
+}
+
+/// A preference picked up from a message
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "key", content = "value", rename_all = "snake_case")]
+pub enum LearnedPreference {
+    Verbosity(Verbosity),
+    Language(String),
+    DefaultTimeRange(String),
+    FavoriteService(String),
+}
+
+/// A learned preference awaiting the user's confirmation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreferenceSuggestion {
+    pub id: String,
+    #[serde(flatten)]
+    pub preference: LearnedPreference,
+    /// Message the preference was last seen in
+    pub evidence: String,
+    /// Session of that message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub suggested_at: DateTime<Utc>,
+}
+
+/// Preferences picked up from a message
+pub fn learn(message: &str) -> Vec<LearnedPreference> {
+    let lower = message.to_lowercase();
+    let mut learned = Vec::new();
+
+    const BRIEF: &[&str] = &["be brief", "keep it short", "shorter answer", "tl;dr", "more concise", "just the answer"];
+    const DETAILED: &[&str] = &["more detail", "in detail", "in depth", "step by step", "more thorough"];
+    if BRIEF.iter().any(|cue| lower.contains(cue)) {
+        learned.push(LearnedPreference::Verbosity(Verbosity::Brief));
+    } else if DETAILED.iter().any(|cue| lower.contains(cue)) {
+        learned.push(LearnedPreference::Verbosity(Verbosity::Detailed));
+    }
+
+    if let Some(captures) = language_pattern().captures(&lower) {
+        let language = &captures[1];
+        if LANGUAGES.contains(&language) {
+            learned.push(LearnedPreference::Language(capitalize(language)));
+        }
+    }
+
+    if let Some(range) = time_range(&lower) {
+        learned.push(LearnedPreference::DefaultTimeRange(range));
+    }
+
+    let mut services = HashSet::new();
+    for captures in service_pattern().captures_iter(&lower) {
+        let service = captures[1].to_string();
+        if !NOT_SERVICES.contains(&service.as_str()) && services.insert(service.clone()) {
+            learned.push(LearnedPreference::FavoriteService(service));
+        }
+    }
+
+    learned
+}
+
+const LANGUAGES: &[&str] = &[
+    "english", "spanish", "french", "german", "portuguese", "italian", "dutch", "polish",
+    "japanese", "chinese", "korean", "hindi",
+];
+
+const NOT_SERVICES: &[&str] = &[
+    "a", "an", "the", "this", "that", "which", "each", "every", "any", "my", "our", "your",
+    "their", "its", "one", "same", "other", "new", "web",
+];
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// The time range a message asks about, normalized to e.g. "24h"
+fn time_range(lower: &str) -> Option<String> {
+    let captures = relative_range_pattern().captures(lower)?;
+    let count = captures.get(1).map_or("1", |m| m.as_str());
+    let unit = match captures[2].chars().next()? {
+        'm' => 'm',
+        'h' => 'h',
+        'd' => 'd',
+        _ => 'w',
+    };
+    Some(format!("{}{}", count, unit))
+}
+
+fn language_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\b(?:answer|reply|respond|write|talk|speak)(?:\s+to\s+me)?\s+in\s+([a-z]+)\b").unwrap()
+    })
+}
+
+fn relative_range_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\b(?:last|past)\s+(?:(\d+)\s*)?(minutes?|mins?|hours?|hrs?|days?|weeks?)\b").unwrap()
+    })
+}
+
+fn time_range_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^\d+[mhdw]$").unwrap())
+}
+
+fn service_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\b([a-z0-9][a-z0-9_-]*)\s+service\b").unwrap())
+}
+
+/// What is remembered about one user
+#[derive(Debug, Default)]
+struct Profile {
+    confirmed: UserPreferences,
+    pending: Vec<PreferenceSuggestion>,
+    observations: HashMap<LearnedPreference, usize>,
+    dismissed: HashSet<LearnedPreference>,
+}
+
+/// Preferences per tenant and user
+///
+/// Observations only turn into suggestions; nothing is added to a user's
+/// preferences without [`PreferenceStore::confirm`] or
+/// [`PreferenceStore::set`].
+#[derive(Debug)]
+pub struct PreferenceStore {
+    profiles: RwLock<HashMap<(String, String), Profile>>,
+    min_observations: usize,
+}
+
+impl PreferenceStore {
+    pub fn new() -> Self {
+        Self {
+            profiles: RwLock::new(HashMap::new()),
+            min_observations: DEFAULT_MIN_OBSERVATIONS,
+        }
+    }
+
+    /// Suggest preferences after they have been seen `min_observations` times
+    pub fn with_min_observations(mut self, min_observations: usize) -> Self {
+        self.min_observations = min_observations.max(1);
+        self
+    }
+
+    /// Learn from a user's message; returns the suggestions it produced
+    ///
+    /// Preferences the user already has, has been asked about or has
+    /// dismissed are not suggested again.
+    pub fn observe(
+        &self,
+        tenant_id: &str,
+        user_id: &str,
+        session_id: Option<&str>,
+        message: &str,
+    ) -> Vec<PreferenceSuggestion> {
+        let learned = learn(message);
+        if learned.is_empty() {
+            return Vec::new();
+        }
+
+        let mut profiles = self.profiles.write().unwrap();
+        let profile = profiles.entry(key(tenant_id, user_id)).or_default();
+        if !profile.confirmed.learning {
+            return Vec::new();
+        }
+
+        let mut suggestions = Vec::new();
+        for preference in learned {
+            if profile.confirmed.contains(&preference) || profile.dismissed.contains(&preference) {
+                continue;
+            }
+            if let Some(pending) = profile.pending.iter_mut().find(|s| s.preference == preference) {
+                pending.evidence = message.to_string();
+                continue;
+            }
+
+            let seen = profile.observations.entry(preference.clone()).or_default();
+            *seen += 1;
+            if *seen >= self.min_observations {
+                profile.observations.remove(&preference);
+                let suggestion = PreferenceSuggestion {
+                    id: Uuid::new_v4().to_string(),
+                    preference,
+                    evidence: message.to_string(),
+                    session_id: session_id.map(str::to_string),
+                    suggested_at: Utc::now(),
+                };
+                profile.pending.push(suggestion.clone());
+                suggestions.push(suggestion);
+            }
+        }
+        suggestions
+    }
+
+    /// A user's confirmed preferences
+    pub fn get(&self, tenant_id: &str, user_id: &str) -> UserPreferences {
+        self.profiles
+            .read()
+            .unwrap()
+            .get(&key(tenant_id, user_id))
+            .map(|p| p.confirmed.clone())
+            .unwrap_or_default()
+    }
+
+    /// Suggestions awaiting the user's confirmation, oldest first
+    pub fn pending(&self, tenant_id: &str, user_id: &str) -> Vec<PreferenceSuggestion> {
+        self.profiles
+            .read()
+            .unwrap()
+            .get(&key(tenant_id, user_id))
+            .map(|p| p.pending.clone())
+            .unwrap_or_default()
+    }
+
+    /// Accept a suggestion into the user's preferences
+    pub fn confirm(&self, tenant_id: &str, user_id: &str, suggestion_id: &str) -> Result<UserPreferences> {
+        let mut profiles = self.profiles.write().unwrap();
+        let profile = profiles
+            .get_mut(&key(tenant_id, user_id))
+            .ok_or_else(|| ConversationError::PreferenceNotFound(suggestion_id.to_string()))?;
+        let suggestion = take_pending(profile, suggestion_id)?;
+        profile.confirmed.apply(&suggestion.preference);
+        Ok(profile.confirmed.clone())
+    }
+
+    /// Reject a suggestion; it will not be suggested again
+    pub fn dismiss(&self, tenant_id: &str, user_id: &str, suggestion_id: &str) -> Result<()> {
+        let mut profiles = self.profiles.write().unwrap();
+        let profile = profiles
+            .get_mut(&key(tenant_id, user_id))
+            .ok_or_else(|| ConversationError::PreferenceNotFound(suggestion_id.to_string()))?;
+        let suggestion = take_pending(profile, suggestion_id)?;
+        profile.dismissed.insert(suggestion.preference);
+        Ok(())
+    }
+
+    /// Replace a user's preferences
+    ///
+    /// Switching learning off also drops pending suggestions and what has
+    /// been observed so far.
+    pub fn set(&self, tenant_id: &str, user_id: &str, mut preferences: UserPreferences) -> Result<UserPreferences> {
+        preferences.validate()?;
+        preferences.updated_at = Some(Utc::now());
+
+        let mut profiles = self.profiles.write().unwrap();
+        let profile = profiles.entry(key(tenant_id, user_id)).or_default();
+        if !preferences.learning {
+            profile.pending.clear();
+            profile.observations.clear();
+        }
+        profile.confirmed = preferences.clone();
+        Ok(preferences)
+    }
+
+    /// Forget everything about a user: preferences, suggestions,
+    /// observations and dismissals; returns whether anything was stored
+    pub fn delete(&self, tenant_id: &str, user_id: &str) -> bool {
+        self.profiles
+            .write()
+            .unwrap()
+            .remove(&key(tenant_id, user_id))
+            .is_some()
+    }
+}
+
+impl Default for PreferenceStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn key(tenant_id: &str, user_id: &str) -> (String, String) {
+    (tenant_id.to_string(), user_id.to_string())
+}
+
+fn take_pending(profile: &mut Profile, suggestion_id: &str) -> Result<PreferenceSuggestion> {
+    let index = profile
+        .pending
+        .iter()
+        .position(|s| s.id == suggestion_id)
+        .ok_or_else(|| ConversationError::PreferenceNotFound(suggestion_id.to_string()))?;
+    Ok(profile.pending.remove(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_learn_from_message() {
+        let learned = learn("Be brief and answer in spanish: errors on the checkout service over the last 24 hours?");
+        assert_eq!(
+            learned,
+            vec![
+                LearnedPreference::Verbosity(Verbosity::Brief),
+                LearnedPreference::Language("Spanish".to_string()),
+                LearnedPreference::DefaultTimeRange("24h".to_string()),
+                LearnedPreference::FavoriteService("checkout".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            learn("what changed in the past week?"),
+            vec![LearnedPreference::DefaultTimeRange("1w".to_string())]
+        );
+        assert!(learn("restart the service in kubernetes").is_empty());
+    }
+
+    #[test]
+    fn test_suggestions_need_confirmation() {
+        let store = PreferenceStore::new();
+        assert!(store.observe("acme", "alice", Some("s1"), "latency of the payments service?").is_empty());
+        let suggestions = store.observe("acme", "alice", Some("s2"), "errors in the payments service");
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].session_id.as_deref(), Some("s2"));
+
+        // Nothing is remembered until the user confirms
+        assert!(store.get("acme", "alice").is_empty());
+        assert!(store.observe("acme", "alice", None, "payments service again").is_empty());
+        assert_eq!(store.pending("acme", "alice").len(), 1);
+
+        let preferences = store.confirm("acme", "alice", &suggestions[0].id).unwrap();
+        assert_eq!(preferences.favorite_services, vec!["payments"]);
+        assert!(store.pending("acme", "alice").is_empty());
+        assert!(store.get("acme", "bob").is_empty());
+        assert!(matches!(
+            store.confirm("acme", "alice", &suggestions[0].id),
+            Err(ConversationError::PreferenceNotFound(_))
+        ));
+
+        let instructions = preferences.prompt_instructions().unwrap();
+        assert!(instructions.contains("payments"));
+    }
+
+    #[test]
+    fn test_dismiss_learning_off_and_delete() {
+        let store = PreferenceStore::new().with_min_observations(1);
+        let suggestion = store.observe("acme", "alice", None, "keep it short").remove(0);
+        store.dismiss("acme", "alice", &suggestion.id).unwrap();
+        assert!(store.observe("acme", "alice", None, "keep it short").is_empty());
+
+        let preferences = UserPreferences {
+            learning: false,
+            language: Some("German".to_string()),
+            ..Default::default()
+        };
+        store.set("acme", "alice", preferences).unwrap();
+        assert!(store.observe("acme", "alice", None, "look at the search service").is_empty());
+
+        let invalid = UserPreferences {
+            default_time_range: Some("yesterday".to_string()),
+            ..Default::default()
+        };
+        assert!(store.set("acme", "alice", invalid).is_err());
+
+        assert!(store.delete("acme", "alice"));
+        assert_eq!(store.get("acme", "alice"), UserPreferences::default());
+        assert!(!store.delete("acme", "alice"));
+    }
+}
@@ -0,0 +1,161 @@
+//! Per-tenant and per-persona pipeline profiles
+//!
+//! A [`PipelineProfile`] sets how a conversation's turns are answered: the
+//! system prompt template, the models to route to and which retrieved
+//! context is kept. [`PipelineProfiles`] holds the defaults and the profiles
+//! of personas and tenants; a session's profile is the defaults, overlaid
+//! with its persona's profile and then its tenant's, each setting only what
+//! it names.
+
+use crate::experiments::{RetrievalOverrides, SYSTEM_PROMPT_PLACEHOLDER};
+use crate::persona::ALLOW_ALL;
+use copilot_context::retrieval::RetrievalResult;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Which retrieved context a profile keeps
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetrievalProfile {
+    /// Sources context may come from; empty or `"*"` allows all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    #[serde(default, flatten)]
+    pub limits: RetrievalOverrides,
+}
+
+impl RetrievalProfile {
+    pub fn allows_source(&self, source: &str) -> bool {
+        self.sources.is_empty() || self.sources.iter().any(|s| s == ALLOW_ALL || s == source)
+    }
+
+    /// Drop context from sources the profile does not allow, then apply its
+    /// limits
+    pub fn apply(&self, result: &mut RetrievalResult) {
+        if !self.sources.is_empty() {
+            result.selected.retain(|s| self.allows_source(&s.item.metadata.source));
+            result.rejected.retain(|s| self.allows_source(&s.item.metadata.source));
+            result.total_tokens = result.selected.iter().map(|s| s.item.token_count).sum();
+        }
+        self.limits.apply(result);
+    }
+}
+
+/// How a conversation's turns are answered
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PipelineProfile {
+    /// System prompt; `{system_prompt}` is replaced with the persona's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_template: Option<String>,
+    /// Models to answer with, preferred first; near a token budget the
+    /// cheapest of them is used
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub retrieval: RetrievalProfile,
+}
+
+impl PipelineProfile {
+    /// This profile with the settings `other` names replacing its own
+    pub fn overlay(mut self, other: &PipelineProfile) -> Self {
+        if other.prompt_template.is_some() {
+            self.prompt_template = other.prompt_template.clone();
+        }
+        if !other.models.is_empty() {
+            self.models = other.models.clone();
+        }
+        if !other.retrieval.sources.is_empty() {
+            self.retrieval.sources = other.retrieval.sources.clone();
+        }
+        if other.retrieval.limits.max_items.is_some() {
+            self.retrieval.limits.max_items = other.retrieval.limits.max_items;
+        }
+        if other.retrieval.limits.min_score.is_some() {
+            self.retrieval.limits.min_score = other.retrieval.limits.min_score;
+        }
+        self
+    }
+
+    /// The system prompt for a session whose persona's prompt is
+    /// `system_prompt`
+    pub fn system_prompt(&self, system_prompt: &str) -> String {
+        match &self.prompt_template {
+            Some(template) => template.replace(SYSTEM_PROMPT_PLACEHOLDER, system_prompt),
+            None => system_prompt.to_string(),
+        }
+    }
+}
+
+/// Default, persona and tenant pipeline profiles
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PipelineProfiles {
+    #[serde(default)]
+    pub defaults: PipelineProfile,
+    #[serde(default)]
+    pub personas: HashMap<String, PipelineProfile>,
+    #[serde(default)]
+    pub tenants: HashMap<String, PipelineProfile>,
+}
+
+impl PipelineProfiles {
+    /// The profile of a session of `tenant_id` using `persona_id`
+    pub fn resolve(&self, tenant_id: Option<&str>, persona_id: Option<&str>) -> PipelineProfile {
+        let mut profile = self.defaults.clone();
+        if let Some(persona) = persona_id.and_then(|id| self.personas.get(id)) {
+            profile = profile.overlay(persona);
+        }
+        if let Some(tenant) = tenant_id.and_then(|id| self.tenants.get(id)) {
+            profile = profile.overlay(tenant);
+        }
+        profile
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_overrides_persona_overrides_defaults() {
+        let profiles = PipelineProfiles {
+            defaults: PipelineProfile {
+                prompt_template: Some("{system_prompt}\nCite your sources.".to_string()),
+                models: vec!["claude-3-sonnet".to_string()],
+                retrieval: RetrievalProfile {
+                    sources: Vec::new(),
+                    limits: RetrievalOverrides {
+                        max_items: Some(8),
+                        min_score: None,
+                    },
+                },
+            },
+            personas: HashMap::from([(
+                "analyst".to_string(),
+                PipelineProfile {
+                    models: vec!["claude-3-opus".to_string()],
+                    retrieval: RetrievalProfile {
+                        sources: vec!["metrics".to_string()],
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )]),
+            tenants: HashMap::from([(
+                "acme".to_string(),
+                PipelineProfile {
+                    models: vec!["claude-3-haiku".to_string()],
+                    ..Default::default()
+                },
+            )]),
+        };
+
+        let profile = profiles.resolve(Some("acme"), Some("analyst"));
+        assert_eq!(profile.models, vec!["claude-3-haiku".to_string()]);
+        assert_eq!(profile.retrieval.sources, vec!["metrics".to_string()]);
+        assert_eq!(profile.retrieval.limits.max_items, Some(8));
+        assert_eq!(profile.system_prompt("Be precise."), "Be precise.\nCite your sources.");
+        assert!(!profile.retrieval.allows_source("logs"));
+
+        assert_eq!(profiles.resolve(None, Some("analyst")).models, vec!["claude-3-opus".to_string()]);
+        assert_eq!(profiles.resolve(Some("globex"), None), profiles.defaults);
+    }
+}
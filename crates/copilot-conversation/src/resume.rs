@@ -0,0 +1,360 @@
+//! Resumable streams
+//!
+//! A resumable stream is driven by a task of its own rather than by the
+//! connection reading it, and its chunks are buffered under a stream ID. A
+//! client that disconnects mid-reply reconnects with the ID and the index of
+//! the last event it received and continues from there; the reply is not
+//! generated again. Generation is cancelled (recording the partial reply, as
+//! for any dropped [`StreamingResponse`](crate::StreamingResponse) stream)
+//! only once no client has been attached for the resume grace period, and a
+//! finished stream stays resumable for the same period.
+
+use crate::{streaming::ChunkType, ConversationError, Result, StreamChunk};
+use futures::stream::{Stream, StreamExt};
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+/// How long streams can be resumed and how much of them is kept
+#[derive(Debug, Clone)]
+pub struct ResumeConfig {
+    /// How long generation continues without an attached client, and how
+    /// long a finished stream can still be resumed
+    pub grace: Duration,
+    /// Events kept per stream; older ones can no longer be resumed from
+    pub max_buffered_events: usize,
+}
+
+impl Default for ResumeConfig {
+    fn default() -> Self {
+        Self {
+            grace: Duration::from_secs(30),
+            max_buffered_events: 4096,
+        }
+    }
+}
+
+/// A chunk of a resumable stream and its position in it
+#[derive(Debug, Clone)]
+pub struct StreamEvent {
+    pub stream_id: String,
+    /// Position of the chunk in the stream, counting from 0
+    pub index: usize,
+    pub chunk: StreamChunk,
+}
+
+impl StreamEvent {
+    /// The event ID clients resume after, e.g. as SSE `Last-Event-ID`
+    pub fn id(&self) -> String {
+        format!("{}:{}", self.stream_id, self.index)
+    }
+}
+
+/// Parse an event ID into its stream ID and index
+pub fn parse_event_id(id: &str) -> Option<(&str, usize)> {
+    let (stream_id, index) = id.rsplit_once(':')?;
+    Some((stream_id, index.parse().ok()?))
+}
+
+/// The events of a stream attached to by a client, ending after the final
+/// chunk
+pub type EventStream = Pin<Box<dyn Stream<Item = StreamEvent> + Send>>;
+
+struct Buffer {
+    /// Index of the first buffered chunk
+    first: usize,
+    chunks: VecDeque<StreamChunk>,
+    finished: bool,
+    attached: usize,
+    detached_since: Option<Instant>,
+}
+
+struct BufferedStream {
+    session_id: String,
+    buffer: Mutex<Buffer>,
+    /// Total chunks pushed, waking attached clients
+    pushed: watch::Sender<usize>,
+}
+
+impl BufferedStream {
+    fn push(&self, chunk: StreamChunk, max_buffered: usize) {
+        let total = {
+            let mut buffer = self.buffer.lock().unwrap();
+            buffer.finished |= chunk.is_final;
+            buffer.chunks.push_back(chunk);
+            while buffer.chunks.len() > max_buffered {
+                buffer.chunks.pop_front();
+                buffer.first += 1;
+            }
+            buffer.first + buffer.chunks.len()
+        };
+        self.pushed.send_replace(total);
+    }
+
+    fn finish(&self) {
+        self.buffer.lock().unwrap().finished = true;
+        self.pushed.send_modify(|_| {});
+    }
+
+    fn detached_for(&self) -> Option<Duration> {
+        let buffer = self.buffer.lock().unwrap();
+        buffer.detached_since.map(|since| since.elapsed())
+    }
+}
+
+/// Decrements the attached count of a stream when a client goes away
+struct Attachment(Arc<BufferedStream>);
+
+impl Attachment {
+    fn new(stream: Arc<BufferedStream>) -> Self {
+        {
+            let mut buffer = stream.buffer.lock().unwrap();
+            buffer.attached += 1;
+            buffer.detached_since = None;
+        }
+        Self(stream)
+    }
+}
+
+impl Drop for Attachment {
+    fn drop(&mut self) {
+        let mut buffer = self.0.buffer.lock().unwrap();
+        buffer.attached -= 1;
+        if buffer.attached == 0 {
+            buffer.detached_since = Some(Instant::now());
+        }
+    }
+}
+
+/// Streams that clients can resume after disconnecting
+#[derive(Clone, Default)]
+pub struct ResumableStreams {
+    streams: Arc<Mutex<HashMap<String, Arc<BufferedStream>>>>,
+    config: ResumeConfig,
+}
+
+impl ResumableStreams {
+    pub fn new(config: ResumeConfig) -> Self {
+        Self {
+            streams: Arc::default(),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &ResumeConfig {
+        &self.config
+    }
+
+    /// Streams generating or still resumable
+    pub fn len(&self) -> usize {
+        self.streams.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Generate `chunks` in the background under a new stream ID, buffering
+    /// them for clients, and attach the first client to it
+    pub fn start(
+        &self,
+        session_id: &str,
+        mut chunks: Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>,
+    ) -> (String, EventStream) {
+        let stream_id = Uuid::new_v4().to_string();
+        let stream = Arc::new(BufferedStream {
+            session_id: session_id.to_string(),
+            buffer: Mutex::new(Buffer {
+                first: 0,
+                chunks: VecDeque::new(),
+                finished: false,
+                attached: 0,
+                detached_since: Some(Instant::now()),
+            }),
+            pushed: watch::channel(0).0,
+        });
+        self.streams
+            .lock()
+            .unwrap()
+            .insert(stream_id.clone(), Arc::clone(&stream));
+        let events = attach(&stream_id, Arc::clone(&stream), 0);
+
+        let streams = Arc::clone(&self.streams);
+        let config = self.config.clone();
+        let id = stream_id.clone();
+        tokio::spawn(async move {
+            let period = config.grace.clamp(Duration::from_millis(10), Duration::from_secs(1));
+            let mut check = tokio::time::interval(period);
+            loop {
+                tokio::select! {
+                    chunk = chunks.next() => match chunk {
+                        Some(Ok(chunk)) => stream.push(chunk, config.max_buffered_events),
+                        Some(Err(e)) => stream.push(error_chunk(&e), config.max_buffered_events),
+                        None => break,
+                    },
+                    _ = check.tick() => {
+                        if stream.detached_for().is_some_and(|detached| detached >= config.grace) {
+                            info!("No client resumed stream {} within {:?}, cancelling it", id, config.grace);
+                            break;
+                        }
+                    }
+                }
+            }
+            // Dropping the chunks cancels generation if it is still running
+            drop(chunks);
+            stream.finish();
+
+            tokio::time::sleep(config.grace).await;
+            streams.lock().unwrap().remove(&id);
+            debug!("Stream {} is no longer resumable", id);
+        });
+
+        (stream_id, events)
+    }
+
+    /// Attach to stream `stream_id` of session `session_id`, continuing after
+    /// event `after` or from the start
+    pub fn resume(&self, session_id: &str, stream_id: &str, after: Option<usize>) -> Result<EventStream> {
+        let stream = self
+            .streams
+            .lock()
+            .unwrap()
+            .get(stream_id)
+            .filter(|stream| stream.session_id == session_id)
+            .cloned()
+            .ok_or_else(|| ConversationError::StreamNotFound(stream_id.to_string()))?;
+
+        let next = after.map_or(0, |after| after + 1);
+        let first = stream.buffer.lock().unwrap().first;
+        if next < first {
+            return Err(ConversationError::StreamNotResumable(format!(
+                "events of stream {} before {} are no longer buffered",
+                stream_id, first
+            )));
+        }
+        Ok(attach(stream_id, stream, next))
+    }
+}
+
+/// The events of `stream` from index `next` on
+fn attach(stream_id: &str, stream: Arc<BufferedStream>, mut next: usize) -> EventStream {
+    let stream_id = stream_id.to_string();
+    let attachment = Attachment::new(stream);
+    Box::pin(async_stream::stream! {
+        let stream = Arc::clone(&attachment.0);
+        let _attachment = attachment;
+        let mut pushed = stream.pushed.subscribe();
+        loop {
+            pushed.borrow_and_update();
+            let (events, finished) = {
+                let buffer = stream.buffer.lock().unwrap();
+                // Events dropped from the buffer while the client lagged are skipped
+                next = next.max(buffer.first);
+                let events: Vec<StreamEvent> = buffer
+                    .chunks
+                    .iter()
+                    .skip(next - buffer.first)
+                    .enumerate()
+                    .map(|(i, chunk)| StreamEvent {
+                        stream_id: stream_id.clone(),
+                        index: next + i,
+                        chunk: chunk.clone(),
+                    })
+                    .collect();
+                (events, buffer.finished)
+            };
+            next += events.len();
+            for event in events {
+                let is_final = event.chunk.is_final;
+                yield event;
+                if is_final {
+                    return;
+                }
+            }
+            if finished || pushed.changed().await.is_err() {
+                return;
+            }
+        }
+    })
+}
+
+fn error_chunk(error: &ConversationError) -> StreamChunk {
+    StreamChunk {
+        chunk_type: ChunkType::Error,
+        content: error.to_string(),
+        sequence: 0,
+        is_final: false,
+        metadata: HashMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(count: usize, delay: Duration) -> Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>> {
+        Box::pin(async_stream::stream! {
+            for sequence in 0..count {
+                tokio::time::sleep(delay).await;
+                yield Ok(StreamChunk {
+                    chunk_type: if sequence + 1 == count { ChunkType::Done } else { ChunkType::Token },
+                    content: sequence.to_string(),
+                    sequence,
+                    is_final: sequence + 1 == count,
+                    metadata: HashMap::new(),
+                });
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_disconnected_client_resumes_without_regenerating() {
+        let streams = ResumableStreams::new(ResumeConfig {
+            grace: Duration::from_secs(5),
+            ..Default::default()
+        });
+        let (stream_id, mut events) = streams.start("s1", chunks(6, Duration::from_millis(5)));
+
+        let first = events.next().await.unwrap();
+        let second = events.next().await.unwrap();
+        assert_eq!(parse_event_id(&second.id()), Some((stream_id.as_str(), 1)));
+        assert_eq!(first.chunk.content, "0");
+        // The client disconnects; generation carries on
+        drop(events);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        let resumed: Vec<StreamEvent> = streams.resume("s1", &stream_id, Some(1)).unwrap().collect().await;
+        let contents: Vec<&str> = resumed.iter().map(|e| e.chunk.content.as_str()).collect();
+        assert_eq!(contents, ["2", "3", "4", "5"]);
+        assert!(resumed.last().unwrap().chunk.is_final);
+
+        assert!(matches!(streams.resume("s2", &stream_id, None), Err(ConversationError::StreamNotFound(_))));
+        assert!(streams.resume("s1", "unknown", None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_stream_is_cancelled_after_grace() {
+        let streams = ResumableStreams::new(ResumeConfig {
+            grace: Duration::from_millis(100),
+            max_buffered_events: 2,
+        });
+
+        // Only the last events stay resumable
+        let (short, _attached) = streams.start("s1", chunks(6, Duration::from_millis(1)));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(matches!(streams.resume("s1", &short, None), Err(ConversationError::StreamNotResumable(_))));
+        let tail: Vec<StreamEvent> = streams.resume("s1", &short, Some(3)).unwrap().collect().await;
+        assert_eq!(tail.iter().map(|e| e.index).collect::<Vec<_>>(), [4, 5]);
+
+        // Nobody resumes the long stream, so it stops well before its 5s
+        let (long, events) = streams.start("s1", chunks(1000, Duration::from_millis(5)));
+        drop(events);
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        assert!(matches!(streams.resume("s1", &long, None), Err(ConversationError::StreamNotFound(_))));
+        assert!(streams.is_empty());
+    }
+}
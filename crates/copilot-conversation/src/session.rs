@@ -1,6 +1,7 @@
 //! Session management for conversation tracking
 
 use crate::{Result, ConversationError};
+use copilot_core::SandboxPolicy;
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -33,6 +34,22 @@ pub struct Session {
     pub total_tokens: usize,
     /// Maximum tokens allowed for this session
     pub max_tokens: usize,
+    /// Persona assigned to this session, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona_id: Option<String>,
+    /// Tools this conversation may call, on top of the persona's allowlist;
+    /// `None` leaves the persona's allowlist as is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_tools: Option<Vec<String>>,
+    /// Sandbox capabilities for this conversation, narrowed by the persona's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxPolicy>,
+    /// Tenant of the user the session belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    /// User the session belongs to, whose preferences apply to it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
     /// Session metadata
     #[serde(default)]
     pub metadata: HashMap<String, String>,
@@ -49,6 +66,11 @@ impl Session {
             last_accessed: now,
             total_tokens: 0,
             max_tokens,
+            persona_id: None,
+            allowed_tools: None,
+            sandbox: None,
+            tenant_id: None,
+            user_id: None,
             metadata: HashMap::new(),
         }
     }
@@ -63,6 +85,11 @@ impl Session {
             last_accessed: now,
             total_tokens: 0,
             max_tokens,
+            persona_id: None,
+            allowed_tools: None,
+            sandbox: None,
+            tenant_id: None,
+            user_id: None,
             metadata: HashMap::new(),
         }
     }
@@ -241,20 +268,25 @@ impl SessionManager {
     ///
     /// Returns the number of sessions removed
     pub fn cleanup_expired(&mut self) -> usize {
+        self.remove_expired().len()
+    }
+
+    /// Remove expired sessions, returning their IDs
+    pub fn remove_expired(&mut self) -> Vec<String> {
         let expire_duration = Duration::seconds(self.config.timeout_seconds);
-        let before_count = self.sessions.len();
+        let mut removed = Vec::new();
 
         self.sessions.retain(|id, session| {
             let expired = session.is_expired(expire_duration);
             if expired {
                 info!("Removing expired session: {}", id);
+                removed.push(id.clone());
             }
             !expired
         });
 
-        let removed = before_count - self.sessions.len();
-        if removed > 0 {
-            info!("Cleaned up {} expired sessions", removed);
+        if !removed.is_empty() {
+            info!("Cleaned up {} expired sessions", removed.len());
         }
         removed
     }
@@ -1,15 +1,34 @@
 //! Response streaming with Server-Sent Events (SSE) support
-
-use crate::{history::HistoryManager, Result, ConversationError};
+//!
+//! A stream stops generating as soon as its client goes away: servers drop
+//! the stream when a write to the client fails, and dropping it (or calling
+//! [`StreamingResponse::cancel`]) cancels generation and every tool execution
+//! holding one of its [cancellation tokens](StreamingResponse::cancellation_token).
+//! The tokens streamed until then are recorded in the session history as a
+//! partial assistant message, and [`StreamCounters`] counts the cancellations.
+//! Streams started through [`ResumableStreams`](crate::ResumableStreams) are
+//! dropped only once their client has not come back for a grace period.
+
+use crate::{
+    history::{ConversationMessage, HistoryManager, MessageRole},
+    token_budget::TokenBudgets,
+    turn_lock::TurnGuard,
+    usage::PricingTable,
+    Result, ConversationError,
+};
 use copilot_context::ContextEngine;
+use copilot_core::FairPermit;
 use copilot_nlp::NlpEngine;
 use futures::stream::Stream;
 use serde::{Deserialize, Serialize};
+use std::fmt::Write;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
 use tokio::time::{sleep, Duration};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info};
 
 /// A chunk of streaming response
@@ -57,6 +76,59 @@ pub struct StreamStatistics {
     pub tokens_per_second: f64,
 }
 
+/// Counts of streamed responses by outcome
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreamStats {
+    pub started: u64,
+    pub completed: u64,
+    /// Streams stopped before their final chunk, mostly by client disconnects
+    pub cancelled: u64,
+    /// Tokens generated for streams that were then cancelled
+    pub cancelled_tokens: u64,
+}
+
+impl StreamStats {
+    /// Prometheus text exposition of the counters
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        for (name, help, value) in [
+            ("started_total", "Streamed responses started", self.started),
+            ("completed_total", "Streamed responses sent to the end", self.completed),
+            ("cancelled_total", "Streamed responses cancelled before the end", self.cancelled),
+            ("cancelled_tokens_total", "Tokens generated for cancelled streams", self.cancelled_tokens),
+        ] {
+            let _ = writeln!(out, "# HELP {prefix}_stream_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_stream_{name} counter");
+            let _ = writeln!(out, "{prefix}_stream_{name} {value}");
+        }
+        out
+    }
+}
+
+/// Stream outcome counters shared by every stream of a manager
+#[derive(Debug, Default)]
+pub struct StreamCounters {
+    started: AtomicU64,
+    completed: AtomicU64,
+    cancelled: AtomicU64,
+    cancelled_tokens: AtomicU64,
+}
+
+impl StreamCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        StreamStats {
+            started: self.started.load(Ordering::Relaxed),
+            completed: self.completed.load(Ordering::Relaxed),
+            cancelled: self.cancelled.load(Ordering::Relaxed),
+            cancelled_tokens: self.cancelled_tokens.load(Ordering::Relaxed),
+        }
+    }
+}
+
 /// Streaming response handler
 pub struct StreamingResponse {
     session_id: String,
@@ -66,6 +138,113 @@ pub struct StreamingResponse {
     start_time: Option<Instant>,
     first_token_time: Option<Instant>,
     token_count: usize,
+    cancel: CancellationToken,
+    counters: Arc<StreamCounters>,
+    pricing: PricingTable,
+    model: Option<String>,
+    budget: Option<(Arc<TokenBudgets>, usize)>,
+    turn: Option<TurnGuard>,
+    slot: Option<FairPermit>,
+}
+
+/// Tracks one stream's output; if dropped before [`Self::finish`] the
+/// stream was cancelled, and the partial output is recorded as such
+struct StreamGuard {
+    session_id: String,
+    history_manager: Arc<RwLock<HistoryManager>>,
+    cancel: CancellationToken,
+    counters: Arc<StreamCounters>,
+    pricing: PricingTable,
+    model: Option<String>,
+    prompt_tokens: usize,
+    text: String,
+    tokens: usize,
+    finished: bool,
+    budget: Option<(Arc<TokenBudgets>, usize)>,
+    turn: Option<TurnGuard>,
+    slot: Option<FairPermit>,
+}
+
+impl StreamGuard {
+    fn push(&mut self, token: &str) {
+        self.text.push_str(token);
+        self.tokens += 1;
+    }
+
+    /// Take the tokens streamed so far from the session's budget
+    fn charge_budget(&mut self) {
+        if let Some((budgets, daily_tokens)) = self.budget.take() {
+            budgets.charge(&self.session_id, daily_tokens, self.prompt_tokens + self.tokens);
+        }
+    }
+
+    fn message(&self, cancelled: bool) -> ConversationMessage {
+        let mut metadata = std::collections::HashMap::new();
+        if cancelled {
+            metadata.insert("cancelled".to_string(), "true".to_string());
+        }
+        ConversationMessage {
+            role: MessageRole::Assistant,
+            content: self.text.clone(),
+            timestamp: chrono::Utc::now(),
+            token_count: self.tokens,
+            metadata,
+            usage: Some(self.pricing.usage(self.model.as_deref(), self.prompt_tokens, self.tokens)),
+            model: self.model.clone(),
+            parts: Vec::new(),
+        }
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        self.finished = true;
+        self.slot = None;
+        self.counters.completed.fetch_add(1, Ordering::Relaxed);
+        self.charge_budget();
+        let message = self.message(false);
+        let appended = self
+            .history_manager
+            .write()
+            .await
+            .append_message(&self.session_id, message)
+            .await;
+        self.turn = None;
+        appended
+    }
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        // Stop tool executions started for this stream
+        self.cancel.cancel();
+        self.counters.cancelled.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .cancelled_tokens
+            .fetch_add(self.tokens as u64, Ordering::Relaxed);
+        self.charge_budget();
+        info!(
+            "Stream for session {} cancelled after {} tokens",
+            self.session_id, self.tokens
+        );
+
+        let message = self.message(true);
+        let session_id = std::mem::take(&mut self.session_id);
+        let history_manager = Arc::clone(&self.history_manager);
+        // The turn ends once the partial reply is recorded
+        let turn = self.turn.take();
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                let _ = history_manager
+                    .write()
+                    .await
+                    .append_message(&session_id, message)
+                    .await;
+                drop(turn);
+            });
+        }
+    }
 }
 
 impl StreamingResponse {
@@ -84,9 +263,59 @@ impl StreamingResponse {
             start_time: None,
             first_token_time: None,
             token_count: 0,
+            cancel: CancellationToken::new(),
+            counters: Arc::new(StreamCounters::new()),
+            pricing: PricingTable::default(),
+            model: None,
+            budget: None,
+            turn: None,
+            slot: None,
         }
     }
 
+    /// Count this stream's outcome in shared counters
+    pub fn with_counters(mut self, counters: Arc<StreamCounters>) -> Self {
+        self.counters = counters;
+        self
+    }
+
+    /// Price the recorded usage as `model` under `pricing`
+    pub fn with_pricing(mut self, pricing: PricingTable, model: Option<String>) -> Self {
+        self.pricing = pricing;
+        self.model = model;
+        self
+    }
+
+    /// Charge the streamed tokens to the session's daily budget of
+    /// `daily_tokens` under `budgets`
+    pub fn with_budget(mut self, budgets: Arc<TokenBudgets>, daily_tokens: usize) -> Self {
+        self.budget = Some((budgets, daily_tokens));
+        self
+    }
+
+    /// Hold the session's turn until the stream finishes or is cancelled
+    pub fn with_turn(mut self, turn: TurnGuard) -> Self {
+        self.turn = Some(turn);
+        self
+    }
+
+    /// Hold a model call slot until the stream finishes or is cancelled
+    pub fn with_slot(mut self, slot: FairPermit) -> Self {
+        self.slot = Some(slot);
+        self
+    }
+
+    /// A token cancelled when the stream is, for tool executions started
+    /// on the stream's behalf to stop with it
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.child_token()
+    }
+
+    /// Stop generating; the stream ends after the chunk in flight
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
     /// Start streaming response
     ///
     /// # Arguments
@@ -98,21 +327,41 @@ impl StreamingResponse {
     ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>> {
         info!("Starting streaming response for session: {}", self.session_id);
         self.start_time = Some(Instant::now());
+        self.counters.started.fetch_add(1, Ordering::Relaxed);
 
         // Create the stream
         let session_id = self.session_id.clone();
         let nlp_engine = Arc::clone(&self.nlp_engine);
         let context_engine = Arc::clone(&self.context_engine);
-        let _history_manager = Arc::clone(&self.history_manager);
+        let cancel = self.cancel.clone();
+        let mut guard = StreamGuard {
+            session_id: self.session_id.clone(),
+            history_manager: Arc::clone(&self.history_manager),
+            cancel: self.cancel.clone(),
+            counters: Arc::clone(&self.counters),
+            pricing: self.pricing.clone(),
+            model: self.model.clone(),
+            prompt_tokens: (message.len() / 4).max(1),
+            text: String::new(),
+            tokens: 0,
+            finished: false,
+            budget: self.budget.take(),
+            turn: self.turn.take(),
+            slot: self.slot.take(),
+        };
 
         // In a real implementation, this would stream from an LLM
         // For now, we'll simulate streaming
         let stream = async_stream::stream! {
             // Simulate first token latency optimization (target <500ms)
             let first_token_delay = Duration::from_millis(350);
-            sleep(first_token_delay).await;
+            tokio::select! {
+                _ = cancel.cancelled() => return,
+                _ = sleep(first_token_delay) => {}
+            }
 
             // First token
+            guard.push("I");
             yield Ok(StreamChunk {
                 chunk_type: ChunkType::Token,
                 content: "I".to_string(),
@@ -141,8 +390,12 @@ impl StreamingResponse {
 
             for (idx, token) in response_tokens.iter().enumerate() {
                 // Simulate token generation delay
-                sleep(Duration::from_millis(50)).await;
+                tokio::select! {
+                    _ = cancel.cancelled() => return,
+                    _ = sleep(Duration::from_millis(50)) => {}
+                }
 
+                guard.push(token);
                 yield Ok(StreamChunk {
                     chunk_type: ChunkType::Token,
                     content: token.to_string(),
@@ -152,6 +405,10 @@ impl StreamingResponse {
                 });
             }
 
+            if let Err(e) = guard.finish().await {
+                yield Err(e);
+            }
+
             // Final chunk
             yield Ok(StreamChunk {
                 chunk_type: ChunkType::Done,
@@ -333,15 +590,13 @@ mod tests {
         let context_config = ContextEngineConfig::default();
         let context_engine = ContextEngineImpl::new(context_config).unwrap();
 
-        let mut response = StreamingResponse {
-            session_id: "test".to_string(),
-            nlp_engine: Arc::new(NlpEngineImpl::default()),
-            context_engine: Arc::new(context_engine),
-            history_manager: Arc::new(RwLock::new(HistoryManager::new())),
-            start_time: Some(Instant::now()),
-            first_token_time: None,
-            token_count: 0,
-        };
+        let mut response = StreamingResponse::new(
+            "test".to_string(),
+            Arc::new(NlpEngineImpl::default()),
+            Arc::new(context_engine),
+            Arc::new(RwLock::new(HistoryManager::new())),
+        );
+        response.start_time = Some(Instant::now());
 
         response.record_first_token();
         response.increment_token_count();
@@ -350,4 +605,39 @@ mod tests {
         assert!(stats.time_to_first_token_ms >= 0);
         assert_eq!(stats.token_count, 1);
     }
+
+    #[tokio::test]
+    async fn test_dropped_stream_is_cancelled_and_recorded() {
+        use futures::StreamExt;
+
+        let history = Arc::new(RwLock::new(HistoryManager::new()));
+        let counters = Arc::new(StreamCounters::new());
+        let mut response = StreamingResponse::new(
+            "s1".to_string(),
+            Arc::new(NlpEngineImpl::default()),
+            Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap()),
+            history.clone(),
+        )
+        .with_counters(counters.clone());
+        let tool = response.cancellation_token();
+
+        let mut stream = response.stream("disk usage".to_string()).await.unwrap();
+        for _ in 0..3 {
+            stream.next().await.unwrap().unwrap();
+        }
+        // The client disconnects
+        drop(stream);
+
+        assert!(tool.is_cancelled());
+        let stats = counters.stats();
+        assert_eq!((stats.started, stats.completed, stats.cancelled), (1, 0, 1));
+        assert_eq!(stats.cancelled_tokens, 3);
+        assert!(stats.render_prometheus("copilot").contains("copilot_stream_cancelled_total 1"));
+
+        tokio::task::yield_now().await;
+        let messages = history.read().await.get_all_messages("s1").await.unwrap();
+        assert_eq!(messages[0].content, "I understand your");
+        assert_eq!(messages[0].metadata["cancelled"], "true");
+        assert_eq!(messages[0].usage.unwrap().completion_tokens, 3);
+    }
 }
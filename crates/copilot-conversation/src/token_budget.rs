@@ -0,0 +1,361 @@
+//! Daily token budgets per conversation
+//!
+//! Each conversation draws its tokens from a bucket holding one day's
+//! budget and refilling at that budget per day, so a conversation can spend
+//! its whole allowance in a burst but not more than it per rolling day.
+//! Budgets are set per tenant and per persona; when both apply the smaller
+//! one does. As a bucket runs low the conversation is answered more cheaply:
+//! its retrieved context is compressed harder, less history is sent and the
+//! cheapest of its candidate models is preferred. A turn the bucket cannot
+//! cover is refused with the time it can be sent again, instead of failing
+//! half-way.
+
+use crate::{ConversationError, PricingTable, Result};
+use chrono::{DateTime, Utc};
+use copilot_context::{PromptCompressionConfig, PromptCompressor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Seconds over which a bucket refills completely
+const REFILL_SECS: f64 = 24.0 * 60.0 * 60.0;
+
+/// How a conversation's prompt is cut down to save tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncationStrategy {
+    /// The last 10 messages and retrieved context as is
+    Standard,
+    /// The last 6 messages and retrieved context compressed to half
+    Compressed,
+    /// The last 2 messages and retrieved context compressed to a quarter
+    Minimal,
+}
+
+impl TruncationStrategy {
+    /// History messages sent with a turn
+    pub fn history_messages(self) -> usize {
+        match self {
+            TruncationStrategy::Standard => 10,
+            TruncationStrategy::Compressed => 6,
+            TruncationStrategy::Minimal => 2,
+        }
+    }
+
+    /// Share of retrieved context kept, if it is compressed
+    pub fn compression_ratio(self) -> Option<f64> {
+        match self {
+            TruncationStrategy::Standard => None,
+            TruncationStrategy::Compressed => Some(0.5),
+            TruncationStrategy::Minimal => Some(0.25),
+        }
+    }
+}
+
+/// Daily token budgets by tenant and persona
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenBudgetConfig {
+    /// Budget of conversations neither their tenant nor persona sets one
+    /// for; unset leaves them unlimited
+    #[serde(default)]
+    pub default_daily_tokens: Option<usize>,
+    #[serde(default)]
+    pub tenants: HashMap<String, usize>,
+    #[serde(default)]
+    pub personas: HashMap<String, usize>,
+    /// Models to fall back to near the budget, besides the persona's own
+    #[serde(default)]
+    pub economy_models: Vec<String>,
+    /// Share (0-1) of the budget used above which context is compressed
+    /// and cheaper models are preferred
+    #[serde(default = "default_compress_above")]
+    pub compress_above: f64,
+    /// Share (0-1) of the budget used above which prompts are cut to the
+    /// minimum
+    #[serde(default = "default_minimal_above")]
+    pub minimal_above: f64,
+}
+
+fn default_compress_above() -> f64 {
+    0.8
+}
+
+fn default_minimal_above() -> f64 {
+    0.95
+}
+
+impl Default for TokenBudgetConfig {
+    fn default() -> Self {
+        Self {
+            default_daily_tokens: None,
+            tenants: HashMap::new(),
+            personas: HashMap::new(),
+            economy_models: Vec::new(),
+            compress_above: default_compress_above(),
+            minimal_above: default_minimal_above(),
+        }
+    }
+}
+
+impl TokenBudgetConfig {
+    /// Read a configuration from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let config: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let valid = |share: f64| (0.0..=1.0).contains(&share);
+        if !valid(self.compress_above) || !valid(self.minimal_above) || self.compress_above > self.minimal_above {
+            return Err(ConversationError::InvalidTokenBudget(
+                "thresholds must satisfy 0 <= compress_above <= minimal_above <= 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Daily budget of a conversation of `tenant_id` with `persona_id`, if
+    /// it has one
+    pub fn daily_tokens(&self, tenant_id: Option<&str>, persona_id: Option<&str>) -> Option<usize> {
+        let tenant = tenant_id.and_then(|tenant_id| self.tenants.get(tenant_id)).copied();
+        let persona = persona_id.and_then(|persona_id| self.personas.get(persona_id)).copied();
+        match (tenant, persona) {
+            (Some(tenant), Some(persona)) => Some(tenant.min(persona)),
+            (None, None) => self.default_daily_tokens,
+            (tenant, persona) => tenant.or(persona),
+        }
+    }
+}
+
+/// Where a conversation stands against its budget
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetStatus {
+    pub daily_tokens: usize,
+    /// Tokens the conversation can spend now
+    pub available: usize,
+    /// When the bucket is full again
+    pub full_at: DateTime<Utc>,
+    pub strategy: TruncationStrategy,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: usize,
+    tokens: f64,
+    updated: DateTime<Utc>,
+}
+
+impl Bucket {
+    fn refill(&mut self, capacity: usize, now: DateTime<Utc>) {
+        let elapsed = (now - self.updated).num_milliseconds().max(0) as f64 / 1000.0;
+        self.capacity = capacity;
+        self.tokens = (self.tokens + elapsed * rate(capacity)).min(capacity as f64);
+        self.updated = now;
+    }
+
+    /// When the bucket holds `tokens` again
+    fn holds_at(&self, tokens: f64, now: DateTime<Utc>) -> DateTime<Utc> {
+        let missing = (tokens.min(self.capacity as f64) - self.tokens).max(0.0);
+        now + chrono::Duration::milliseconds((missing / rate(self.capacity) * 1000.0).ceil() as i64)
+    }
+}
+
+/// Tokens per second a bucket of `capacity` refills at
+fn rate(capacity: usize) -> f64 {
+    capacity.max(1) as f64 / REFILL_SECS
+}
+
+/// Token buckets of conversations with a budget
+pub struct TokenBudgets {
+    config: TokenBudgetConfig,
+    buckets: Mutex<HashMap<String, Bucket>>,
+    compressors: HashMap<TruncationStrategy, PromptCompressor>,
+}
+
+impl TokenBudgets {
+    pub fn new(config: TokenBudgetConfig) -> Self {
+        let compressors = [TruncationStrategy::Compressed, TruncationStrategy::Minimal]
+            .into_iter()
+            .filter_map(|strategy| {
+                let config = PromptCompressionConfig {
+                    ratio: strategy.compression_ratio()?,
+                    ..PromptCompressionConfig::default()
+                };
+                Some((strategy, PromptCompressor::new(config).ok()?))
+            })
+            .collect();
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+            compressors,
+        }
+    }
+
+    pub fn config(&self) -> &TokenBudgetConfig {
+        &self.config
+    }
+
+    /// Compressor for the retrieved context of turns answered with `strategy`
+    pub fn compressor(&self, strategy: TruncationStrategy) -> Option<&PromptCompressor> {
+        self.compressors.get(&strategy)
+    }
+
+    /// Where `conversation` stands against a budget of `daily_tokens`
+    pub fn status(&self, conversation: &str, daily_tokens: usize) -> BudgetStatus {
+        self.status_at(conversation, daily_tokens, Utc::now())
+    }
+
+    /// Check that `conversation` can spend `needed` tokens on a turn; fails
+    /// with the time the turn fits otherwise
+    pub fn check(&self, conversation: &str, daily_tokens: usize, needed: usize) -> Result<BudgetStatus> {
+        self.check_at(conversation, daily_tokens, needed, Utc::now())
+    }
+
+    /// Take the tokens a turn used from `conversation`'s bucket
+    pub fn charge(&self, conversation: &str, daily_tokens: usize, tokens: usize) {
+        self.charge_at(conversation, daily_tokens, tokens, Utc::now())
+    }
+
+    /// Forget a conversation's bucket, e.g. when the conversation ends
+    pub fn remove(&self, conversation: &str) {
+        self.buckets.lock().unwrap_or_else(|e| e.into_inner()).remove(conversation);
+    }
+
+    /// The cheapest of `candidates` and the configured economy models under
+    /// `pricing`
+    pub fn cheapest_model(&self, pricing: &PricingTable, candidates: &[String]) -> Option<String> {
+        candidates
+            .iter()
+            .chain(&self.config.economy_models)
+            .map(|model| {
+                let price = pricing.pricing_for(Some(model));
+                (model, price.prompt_per_1k_usd + price.completion_per_1k_usd)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(model, _)| model.clone())
+    }
+
+    fn with_bucket<T>(
+        &self,
+        conversation: &str,
+        daily_tokens: usize,
+        now: DateTime<Utc>,
+        f: impl FnOnce(&mut Bucket) -> T,
+    ) -> T {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets.entry(conversation.to_string()).or_insert_with(|| Bucket {
+            capacity: daily_tokens,
+            tokens: daily_tokens as f64,
+            updated: now,
+        });
+        bucket.refill(daily_tokens, now);
+        f(bucket)
+    }
+
+    fn strategy(&self, bucket: &Bucket) -> TruncationStrategy {
+        let used = 1.0 - bucket.tokens / bucket.capacity.max(1) as f64;
+        if used >= self.config.minimal_above {
+            TruncationStrategy::Minimal
+        } else if used >= self.config.compress_above {
+            TruncationStrategy::Compressed
+        } else {
+            TruncationStrategy::Standard
+        }
+    }
+
+    fn status_at(&self, conversation: &str, daily_tokens: usize, now: DateTime<Utc>) -> BudgetStatus {
+        self.with_bucket(conversation, daily_tokens, now, |bucket| BudgetStatus {
+            daily_tokens,
+            available: bucket.tokens.max(0.0) as usize,
+            full_at: bucket.holds_at(daily_tokens as f64, now),
+            strategy: self.strategy(bucket),
+        })
+    }
+
+    fn check_at(
+        &self,
+        conversation: &str,
+        daily_tokens: usize,
+        needed: usize,
+        now: DateTime<Utc>,
+    ) -> Result<BudgetStatus> {
+        let status = self.status_at(conversation, daily_tokens, now);
+        let needed = needed.max(1);
+        if status.available >= needed {
+            return Ok(status);
+        }
+        let resets_at = self.with_bucket(conversation, daily_tokens, now, |bucket| bucket.holds_at(needed as f64, now));
+        Err(ConversationError::TokenBudgetExhausted {
+            conversation: conversation.to_string(),
+            daily_tokens,
+            available: status.available,
+            resets_at,
+        })
+    }
+
+    fn charge_at(&self, conversation: &str, daily_tokens: usize, tokens: usize, now: DateTime<Utc>) {
+        // A turn may overrun what was left; the debt is repaid by refilling
+        self.with_bucket(conversation, daily_tokens, now, |bucket| bucket.tokens -= tokens as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ModelPricing;
+
+    #[test]
+    fn test_budget_resolves_by_tenant_and_persona() {
+        let config: TokenBudgetConfig = serde_json::from_str(
+            r#"{"default_daily_tokens": 1000, "tenants": {"acme": 500}, "personas": {"sre": 800, "intern": 200}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.daily_tokens(Some("acme"), Some("sre")), Some(500));
+        assert_eq!(config.daily_tokens(Some("acme"), Some("intern")), Some(200));
+        assert_eq!(config.daily_tokens(Some("globex"), Some("sre")), Some(800));
+        assert_eq!(config.daily_tokens(None, None), Some(1000));
+        assert!(TokenBudgetConfig::default().daily_tokens(Some("acme"), None).is_none());
+    }
+
+    #[test]
+    fn test_bucket_tightens_then_refuses_until_refilled() {
+        let budgets = TokenBudgets::new(TokenBudgetConfig::default());
+        let start = Utc::now();
+        assert_eq!(budgets.check_at("c1", 1000, 10, start).unwrap().strategy, TruncationStrategy::Standard);
+
+        budgets.charge_at("c1", 1000, 850, start);
+        assert_eq!(budgets.status_at("c1", 1000, start).strategy, TruncationStrategy::Compressed);
+        budgets.charge_at("c1", 1000, 120, start);
+        assert_eq!(budgets.status_at("c1", 1000, start).strategy, TruncationStrategy::Minimal);
+
+        // 30 tokens are left; a 100 token turn fits once 70 more refill
+        match budgets.check_at("c1", 1000, 100, start) {
+            Err(ConversationError::TokenBudgetExhausted { available, resets_at, .. }) => {
+                assert_eq!(available, 30);
+                let wait = (resets_at - start).num_seconds();
+                assert!((6040..=6050).contains(&wait), "waited {}s", wait);
+            }
+            other => panic!("unexpected: {:?}", other),
+        }
+        let later = start + chrono::Duration::hours(2);
+        assert!(budgets.check_at("c1", 1000, 100, later).is_ok());
+        assert!(budgets.check_at("c2", 1000, 100, start).is_ok());
+    }
+
+    #[test]
+    fn test_cheapest_model_includes_economy_models() {
+        let budgets = TokenBudgets::new(TokenBudgetConfig {
+            economy_models: vec!["claude-3-haiku".to_string()],
+            ..Default::default()
+        });
+        let pricing = PricingTable::new(ModelPricing::new(0.01, 0.03))
+            .with_model("claude-3-opus", ModelPricing::new(0.015, 0.075))
+            .with_model("claude-3-sonnet", ModelPricing::new(0.003, 0.015))
+            .with_model("claude-3-haiku", ModelPricing::new(0.00025, 0.00125));
+        let candidates = vec!["claude-3-opus".to_string(), "claude-3-sonnet".to_string()];
+        assert_eq!(budgets.cheapest_model(&pricing, &candidates).as_deref(), Some("claude-3-haiku"));
+        assert!(TokenBudgets::new(TokenBudgetConfig::default()).cheapest_model(&pricing, &[]).is_none());
+    }
+}
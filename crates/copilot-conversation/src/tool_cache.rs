@@ -0,0 +1,339 @@
+//! Memoization of tool calls within a conversation
+//!
+//! Agents often call the same tool with the same arguments several times in
+//! one conversation, e.g. re-running a metrics query while reasoning about
+//! it. [`ToolCache`] keeps each successful result per conversation, keyed by
+//! the tool and its canonicalized arguments, for the tool's TTL. Tools with
+//! side effects should be given a TTL of zero so they always run. A caller
+//! that needs fresh data passes `force_refresh`, which runs the tool and
+//! replaces the cached result. Failed calls are never cached.
+
+use crate::Result;
+use copilot_core::determinism::{Clock, SystemClock};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::debug;
+
+/// TTLs of cached tool results
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolCacheConfig {
+    /// TTL of tools without their own
+    pub default_ttl: Duration,
+    /// TTL by tool name; zero disables caching of that tool
+    pub ttls: HashMap<String, Duration>,
+    /// Results kept per conversation; the oldest is dropped first
+    pub max_entries_per_session: usize,
+}
+
+impl Default for ToolCacheConfig {
+    fn default() -> Self {
+        Self {
+            default_ttl: Duration::from_secs(60),
+            ttls: HashMap::new(),
+            max_entries_per_session: 256,
+        }
+    }
+}
+
+impl ToolCacheConfig {
+    /// Keep results of `tool` for `ttl`
+    pub fn with_ttl(mut self, tool: impl Into<String>, ttl: Duration) -> Self {
+        self.ttls.insert(tool.into(), ttl);
+        self
+    }
+
+    pub fn ttl(&self, tool: &str) -> Duration {
+        self.ttls.get(tool).copied().unwrap_or(self.default_ttl)
+    }
+}
+
+/// Cache statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Calls that skipped the cache with `force_refresh`
+    pub refreshes: u64,
+    pub entries: usize,
+}
+
+/// A tool's result and whether it came from the cache
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolResult {
+    pub value: Value,
+    pub cached: bool,
+    /// How long ago the result was produced
+    pub age_ms: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: Value,
+    stored_at: Duration,
+    expires_at: Duration,
+}
+
+/// Successful tool results by conversation, tool and arguments
+#[derive(Debug)]
+pub struct ToolCache {
+    config: ToolCacheConfig,
+    clock: Arc<dyn Clock>,
+    sessions: Mutex<HashMap<String, HashMap<String, Entry>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    refreshes: AtomicU64,
+}
+
+impl Default for ToolCache {
+    fn default() -> Self {
+        Self::new(ToolCacheConfig::default())
+    }
+}
+
+impl ToolCache {
+    pub fn new(config: ToolCacheConfig) -> Self {
+        Self {
+            config,
+            clock: Arc::new(SystemClock),
+            sessions: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            refreshes: AtomicU64::new(0),
+        }
+    }
+
+    /// Measure expiry on `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn config(&self) -> &ToolCacheConfig {
+        &self.config
+    }
+
+    /// Cache key of `tool` called with `args`; arguments that differ only
+    /// in key order share a key
+    pub fn key(tool: &str, args: &Value) -> String {
+        format!("{}\0{}", tool, canonical(args))
+    }
+
+    /// The result of `run`, or the cached result of an identical earlier
+    /// call in the session unless `force_refresh` is set
+    pub async fn call<F, Fut>(
+        &self,
+        session_id: &str,
+        tool: &str,
+        args: &Value,
+        force_refresh: bool,
+        run: F,
+    ) -> Result<ToolResult>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Value>>,
+    {
+        let ttl = self.config.ttl(tool);
+        if ttl.is_zero() {
+            return Ok(ToolResult { value: run().await?, cached: false, age_ms: 0 });
+        }
+
+        let key = Self::key(tool, args);
+        if force_refresh {
+            self.refreshes.fetch_add(1, Ordering::Relaxed);
+            debug!("Refreshing {} for session {}", tool, session_id);
+        } else if let Some(result) = self.get(session_id, &key) {
+            debug!("Serving {} for session {} from cache", tool, session_id);
+            return Ok(result);
+        }
+
+        let value = run().await?;
+        self.insert(session_id, key, value.clone(), ttl);
+        Ok(ToolResult { value, cached: false, age_ms: 0 })
+    }
+
+    fn get(&self, session_id: &str, key: &str) -> Option<ToolResult> {
+        let now = self.clock.monotonic();
+        let result = self.lock().get_mut(session_id).and_then(|entries| match entries.get(key) {
+            Some(entry) if entry.expires_at > now => Some(ToolResult {
+                value: entry.value.clone(),
+                cached: true,
+                age_ms: now.saturating_sub(entry.stored_at).as_millis() as u64,
+            }),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        });
+        let counter = if result.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    fn insert(&self, session_id: &str, key: String, value: Value, ttl: Duration) {
+        let now = self.clock.monotonic();
+        let mut sessions = self.lock();
+        let entries = sessions.entry(session_id.to_string()).or_default();
+        entries.retain(|_, entry| entry.expires_at > now);
+        if entries.len() >= self.config.max_entries_per_session.max(1) && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, Entry { value, stored_at: now, expires_at: now + ttl });
+    }
+
+    /// Drop a conversation's cached results
+    pub fn invalidate_session(&self, session_id: &str) {
+        self.lock().remove(session_id);
+    }
+
+    /// Drop expired results, returning how many were removed
+    pub fn purge_expired(&self) -> usize {
+        let now = self.clock.monotonic();
+        let mut sessions = self.lock();
+        let mut removed = 0;
+        sessions.retain(|_, entries| {
+            let before = entries.len();
+            entries.retain(|_, entry| entry.expires_at > now);
+            removed += before - entries.len();
+            !entries.is_empty()
+        });
+        removed
+    }
+
+    pub fn stats(&self) -> ToolCacheStats {
+        ToolCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            refreshes: self.refreshes.load(Ordering::Relaxed),
+            entries: self.lock().values().map(HashMap::len).sum(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, HashMap<String, Entry>>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// `value` as JSON with object keys sorted
+fn canonical(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let fields: Vec<String> = keys
+                .into_iter()
+                .map(|key| format!("{}:{}", Value::String(key.clone()), canonical(&map[key])))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => format!("[{}]", items.iter().map(canonical).collect::<Vec<_>>().join(",")),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ConversationError;
+    use copilot_core::FrozenClock;
+    use serde_json::json;
+    use std::sync::atomic::AtomicUsize;
+
+    async fn metrics(cache: &ToolCache, session: &str, args: Value, refresh: bool, calls: &AtomicUsize) -> ToolResult {
+        cache
+            .call(session, "metrics_query", &args, refresh, || async {
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                Ok(json!({ "run": n }))
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_identical_calls_are_memoized_per_session() {
+        let clock = Arc::new(FrozenClock::at_epoch());
+        let cache = ToolCache::new(ToolCacheConfig::default()).with_clock(clock.clone());
+        let calls = AtomicUsize::new(0);
+        let args = json!({ "query": "rate(http_errors[5m])", "range": { "start": 1, "end": 2 } });
+        let reordered = json!({ "range": { "end": 2, "start": 1 }, "query": "rate(http_errors[5m])" });
+
+        assert!(!metrics(&cache, "s1", args.clone(), false, &calls).await.cached);
+        clock.advance(chrono::Duration::seconds(5));
+        let cached = metrics(&cache, "s1", reordered, false, &calls).await;
+        assert!(cached.cached);
+        assert_eq!(cached.age_ms, 5_000);
+        assert_eq!(cached.value, json!({ "run": 0 }));
+
+        // Other conversations and other arguments miss
+        assert!(!metrics(&cache, "s2", args.clone(), false, &calls).await.cached);
+        assert!(!metrics(&cache, "s1", json!({ "query": "up" }), false, &calls).await.cached);
+
+        // A forced refresh runs the tool and replaces the result
+        let refreshed = metrics(&cache, "s1", args.clone(), true, &calls).await;
+        assert_eq!(refreshed.value, json!({ "run": 3 }));
+        assert_eq!(metrics(&cache, "s1", args.clone(), false, &calls).await.value, json!({ "run": 3 }));
+
+        // Results expire after the TTL
+        clock.advance(chrono::Duration::seconds(61));
+        assert!(!metrics(&cache, "s1", args, false, &calls).await.cached);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.refreshes), (2, 4, 1));
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_uncached_tools_and_failures_always_run() {
+        let cache = ToolCache::new(ToolCacheConfig::default().with_ttl("workflow_trigger", Duration::ZERO));
+        let calls = AtomicUsize::new(0);
+        for _ in 0..2 {
+            let result = cache
+                .call("s1", "workflow_trigger", &json!({}), false, || async {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Ok(json!("started"))
+                })
+                .await
+                .unwrap();
+            assert!(!result.cached);
+        }
+        for _ in 0..2 {
+            let result = cache
+                .call("s1", "metrics_query", &json!({}), false, || async {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Err(ConversationError::ToolError("timeout".to_string()))
+                })
+                .await;
+            assert!(result.is_err());
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_sessions_keep_their_newest_entries() {
+        let clock = Arc::new(FrozenClock::at_epoch());
+        let config = ToolCacheConfig { max_entries_per_session: 2, ..Default::default() };
+        let cache = ToolCache::new(config).with_clock(clock.clone());
+        let calls = AtomicUsize::new(0);
+        for query in ["a", "b", "c"] {
+            metrics(&cache, "s1", json!({ "query": query }), false, &calls).await;
+            clock.advance(chrono::Duration::seconds(1));
+        }
+        assert_eq!(cache.stats().entries, 2);
+        assert!(!metrics(&cache, "s1", json!({ "query": "a" }), false, &calls).await.cached);
+
+        cache.invalidate_session("s1");
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
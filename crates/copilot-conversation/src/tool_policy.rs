@@ -0,0 +1,117 @@
+//! Effective tool policy of a conversation
+//!
+//! A conversation's persona sets the outer bounds on which tools may run and
+//! what their sandbox may do; the conversation itself can only narrow them.
+//! The resolved [`ToolPolicy`] is what the tool execution layer enforces:
+//! code for the [`SANDBOX_TOOL`] runs through a [`SandboxRunner`] in a
+//! sandbox created under the policy's [`SandboxPolicy`].
+
+use crate::persona::{Persona, ALLOW_ALL};
+use crate::session::Session;
+use crate::Result;
+use async_trait::async_trait;
+use copilot_core::SandboxPolicy;
+use serde::{Deserialize, Serialize};
+
+/// Tool through which conversations run code in a sandbox
+pub const SANDBOX_TOOL: &str = "sandbox_execute";
+
+/// Runs code for conversations' [`SANDBOX_TOOL`] calls
+#[async_trait]
+pub trait SandboxRunner: Send + Sync {
+    /// Run `code` with `runtime` in a sandbox created under `policy`
+    async fn run(&self, runtime: &str, code: &str, policy: SandboxPolicy) -> Result<serde_json::Value>;
+}
+
+/// Tools and sandbox capabilities a conversation may use
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolPolicy {
+    /// Persona the bounds come from, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona_id: Option<String>,
+    /// Tools the persona allows; `None` when there is no persona
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona_tools: Option<Vec<String>>,
+    /// Tools the conversation allows; `None` when not narrowed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_tools: Option<Vec<String>>,
+    /// Sandbox capabilities both the persona and the conversation allow
+    pub sandbox: SandboxPolicy,
+}
+
+/// Why a tool call was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolDenial {
+    /// Outside the persona's allowlist
+    Persona(String),
+    /// Outside the conversation's allowlist
+    Session,
+}
+
+impl ToolPolicy {
+    /// Combine a session's overrides with its persona's bounds
+    pub fn resolve(persona: Option<&Persona>, session: &Session) -> Self {
+        let persona_sandbox = persona.map(|p| p.sandbox).unwrap_or_default();
+        Self {
+            persona_id: persona.map(|p| p.id.clone()),
+            persona_tools: persona.map(|p| p.allowed_tools.clone()),
+            session_tools: session.allowed_tools.clone(),
+            sandbox: persona_sandbox.intersect(&session.sandbox.unwrap_or_default()),
+        }
+    }
+
+    /// Check whether `tool` may be called
+    pub fn check(&self, tool: &str) -> std::result::Result<(), ToolDenial> {
+        if let (Some(persona), Some(tools)) = (&self.persona_id, &self.persona_tools) {
+            if !allows(tools, tool) {
+                return Err(ToolDenial::Persona(persona.clone()));
+            }
+        }
+        match &self.session_tools {
+            Some(tools) if !allows(tools, tool) => Err(ToolDenial::Session),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether `tool` may be called
+    pub fn allows_tool(&self, tool: &str) -> bool {
+        self.check(tool).is_ok()
+    }
+}
+
+fn allows(tools: &[String], tool: &str) -> bool {
+    tools.iter().any(|t| t == ALLOW_ALL || t == tool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_narrows_persona() {
+        let persona = Persona::new("p", "P", "prompt")
+            .with_tools(&["file_read", "sandbox_execute"])
+            .with_sandbox(SandboxPolicy {
+                network: false,
+                package_installs: true,
+            });
+        let mut session = Session::new(1000);
+        session.allowed_tools = Some(vec!["sandbox_execute".to_string(), "log_search".to_string()]);
+        session.sandbox = Some(SandboxPolicy::unrestricted());
+
+        let policy = ToolPolicy::resolve(Some(&persona), &session);
+        assert!(policy.allows_tool("sandbox_execute"));
+        assert_eq!(policy.check("file_read"), Err(ToolDenial::Session));
+        assert_eq!(policy.check("log_search"), Err(ToolDenial::Persona("p".to_string())));
+        assert!(!policy.sandbox.network);
+        assert!(policy.sandbox.package_installs);
+    }
+
+    #[test]
+    fn test_unrestricted_without_persona_or_overrides() {
+        let session = Session::new(1000);
+        let policy = ToolPolicy::resolve(None, &session);
+        assert!(policy.allows_tool("anything"));
+        assert_eq!(policy.sandbox, SandboxPolicy::unrestricted());
+    }
+}
@@ -0,0 +1,296 @@
+//! Tool execution transcripts
+//!
+//! Every tool call made through the conversation manager is recorded in the
+//! conversation's history as a [`MessageRole::Tool`](crate::MessageRole)
+//! message whose [`MessagePart`]s hold the call and its result. Arguments go
+//! through the [`ToolRedaction`] policy first, so credentials passed to a
+//! tool never reach the history. Results that carry a sandbox run's
+//! `stdout`, `stderr` and `exit_code` get a part of their own for the run's
+//! output. Exports and the history API include the parts, so a transcript
+//! shows what the assistant did as well as what it said.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Replacement for redacted argument values
+pub const REDACTED: &str = "[redacted]";
+
+/// Argument names redacted by default; a name matches when it contains one
+const SENSITIVE_KEYS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "authorization",
+    "credential",
+    "private_key",
+    "access_key",
+];
+
+/// A structured part of a message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessagePart {
+    /// A tool invoked by the assistant
+    ToolCall {
+        call_id: String,
+        tool: String,
+        /// Arguments, redacted
+        arguments: Value,
+    },
+    /// What a tool returned
+    ToolResult {
+        call_id: String,
+        tool: String,
+        #[serde(default)]
+        output: Value,
+        /// Whether the result was reused from an earlier identical call
+        #[serde(default)]
+        cached: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// Output of code a tool ran in the sandbox
+    SandboxOutput {
+        call_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+        #[serde(default)]
+        stdout: String,
+        #[serde(default)]
+        stderr: String,
+    },
+}
+
+impl MessagePart {
+    /// The tool call the part belongs to
+    pub fn call_id(&self) -> &str {
+        match self {
+            MessagePart::ToolCall { call_id, .. }
+            | MessagePart::ToolResult { call_id, .. }
+            | MessagePart::SandboxOutput { call_id, .. } => call_id,
+        }
+    }
+
+    /// The sandbox output carried by a tool's result, if it has any
+    pub fn sandbox_output(call_id: &str, output: &Value) -> Option<Self> {
+        let text = |key: &str| output.get(key).and_then(Value::as_str).map(String::from);
+        let (stdout, stderr) = (text("stdout"), text("stderr"));
+        if stdout.is_none() && stderr.is_none() {
+            return None;
+        }
+        Some(MessagePart::SandboxOutput {
+            call_id: call_id.to_string(),
+            exit_code: output.get("exit_code").and_then(Value::as_i64).map(|code| code as i32),
+            stdout: stdout.unwrap_or_default(),
+            stderr: stderr.unwrap_or_default(),
+        })
+    }
+
+    /// Apply `f` to every piece of text in the part, e.g. to anonymize it
+    pub fn map_text(&mut self, f: &mut impl FnMut(&str) -> String) {
+        match self {
+            MessagePart::ToolCall { arguments, .. } => map_strings(arguments, f),
+            MessagePart::ToolResult { output, error, .. } => {
+                map_strings(output, f);
+                if let Some(error) = error {
+                    *error = f(error);
+                }
+            }
+            MessagePart::SandboxOutput { stdout, stderr, .. } => {
+                *stdout = f(stdout);
+                *stderr = f(stderr);
+            }
+        }
+    }
+
+    /// The part as a Markdown block
+    pub fn to_markdown(&self) -> String {
+        match self {
+            MessagePart::ToolCall { call_id, tool, arguments } => format!(
+                "**Tool call** `{}` ({})\n\n```json\n{}\n```\n",
+                tool,
+                call_id,
+                pretty(arguments)
+            ),
+            MessagePart::ToolResult { tool, error: Some(error), .. } => {
+                format!("**Tool error** `{}`: {}\n", tool, error)
+            }
+            MessagePart::ToolResult { tool, output, cached, .. } => format!(
+                "**Tool result** `{}`{}\n\n```json\n{}\n```\n",
+                tool,
+                if *cached { " (cached)" } else { "" },
+                pretty(output)
+            ),
+            MessagePart::SandboxOutput { exit_code, stdout, stderr, .. } => {
+                let mut out = match exit_code {
+                    Some(code) => format!("**Sandbox output** (exit code {})\n", code),
+                    None => "**Sandbox output**\n".to_string(),
+                };
+                for (stream, text) in [("stdout", stdout), ("stderr", stderr)] {
+                    if !text.is_empty() {
+                        out.push_str(&format!("\n{}:\n\n```\n{}\n```\n", stream, text.trim_end()));
+                    }
+                }
+                out
+            }
+        }
+    }
+
+    /// The part as plain text lines
+    pub fn to_text(&self) -> String {
+        match self {
+            MessagePart::ToolCall { tool, arguments, .. } => format!("> call {} {}", tool, arguments),
+            MessagePart::ToolResult { tool, error: Some(error), .. } => format!("< {} failed: {}", tool, error),
+            MessagePart::ToolResult { tool, output, cached, .. } => {
+                format!("< {}{} {}", tool, if *cached { " (cached)" } else { "" }, output)
+            }
+            MessagePart::SandboxOutput { exit_code, stdout, stderr, .. } => {
+                let mut out = format!("$ exit {}", exit_code.map_or("?".to_string(), |code| code.to_string()));
+                for line in stdout.lines() {
+                    out.push_str(&format!("\n  | {}", line));
+                }
+                for line in stderr.lines() {
+                    out.push_str(&format!("\n  ! {}", line));
+                }
+                out
+            }
+        }
+    }
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+fn map_strings(value: &mut Value, f: &mut impl FnMut(&str) -> String) {
+    match value {
+        Value::String(text) => *text = f(text),
+        Value::Array(items) => items.iter_mut().for_each(|item| map_strings(item, f)),
+        Value::Object(map) => map.values_mut().for_each(|item| map_strings(item, f)),
+        _ => {}
+    }
+}
+
+/// Which tool arguments are kept out of conversation history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolRedaction {
+    /// Argument names to redact, lowercase; a name matches when it contains
+    /// one of them
+    pub keys: Vec<String>,
+    /// Longest string kept in recorded arguments and outputs, in characters
+    pub max_text_chars: usize,
+}
+
+impl Default for ToolRedaction {
+    fn default() -> Self {
+        Self {
+            keys: SENSITIVE_KEYS.iter().map(|key| key.to_string()).collect(),
+            max_text_chars: 4000,
+        }
+    }
+}
+
+impl ToolRedaction {
+    /// Also redact arguments whose name contains `key`
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.keys.push(key.to_lowercase());
+        self
+    }
+
+    pub fn with_max_text_chars(mut self, max_text_chars: usize) -> Self {
+        self.max_text_chars = max_text_chars;
+        self
+    }
+
+    fn is_sensitive(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.keys.iter().any(|key| name.contains(key.as_str()))
+    }
+
+    /// `arguments` with the values of sensitive names, at any depth,
+    /// replaced and long strings shortened
+    pub fn redact_arguments(&self, arguments: &Value) -> Value {
+        match arguments {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(name, value)| {
+                        let value = if self.is_sensitive(name) {
+                            Value::String(REDACTED.to_string())
+                        } else {
+                            self.redact_arguments(value)
+                        };
+                        (name.clone(), value)
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.iter().map(|item| self.redact_arguments(item)).collect()),
+            other => self.shorten(other),
+        }
+    }
+
+    /// `output` with long strings shortened
+    pub fn shorten(&self, output: &Value) -> Value {
+        let mut output = output.clone();
+        map_strings(&mut output, &mut |text| self.shorten_text(text));
+        output
+    }
+
+    /// `text` cut to the longest string kept, marking the cut
+    pub fn shorten_text(&self, text: &str) -> String {
+        match text.char_indices().nth(self.max_text_chars) {
+            Some((end, _)) => format!("{}… [truncated]", &text[..end]),
+            None => text.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sensitive_arguments_are_redacted() {
+        let redaction = ToolRedaction::default().with_key("ssn");
+        let arguments = json!({
+            "url": "https://status.example.com",
+            "headers": {"Authorization": "Bearer abc", "Accept": "application/json"},
+            "users": [{"name": "ana", "SSN": "123-45-6789"}],
+            "db_password": "hunter2",
+        });
+        assert_eq!(
+            redaction.redact_arguments(&arguments),
+            json!({
+                "url": "https://status.example.com",
+                "headers": {"Authorization": REDACTED, "Accept": "application/json"},
+                "users": [{"name": "ana", "SSN": REDACTED}],
+                "db_password": REDACTED,
+            })
+        );
+
+        let short = ToolRedaction::default().with_max_text_chars(4);
+        assert_eq!(short.shorten(&json!(["abcdef", 7])), json!(["abcd… [truncated]", 7]));
+    }
+
+    #[test]
+    fn test_parts_round_trip_and_render() {
+        let output = json!({"stdout": "42\n", "stderr": "", "exit_code": 0});
+        let sandbox = MessagePart::sandbox_output("call-1", &output).unwrap();
+        assert_eq!(
+            serde_json::to_value(&sandbox).unwrap(),
+            json!({"type": "sandbox_output", "call_id": "call-1", "exit_code": 0, "stdout": "42\n", "stderr": ""})
+        );
+        assert!(MessagePart::sandbox_output("call-1", &json!({"rows": 3})).is_none());
+        assert_eq!(sandbox.to_text(), "$ exit 0\n  | 42");
+
+        let failed: MessagePart = serde_json::from_value(
+            json!({"type": "tool_result", "call_id": "call-2", "tool": "file_read", "error": "not found"}),
+        )
+        .unwrap();
+        assert_eq!(failed.call_id(), "call-2");
+        assert_eq!(failed.to_markdown(), "**Tool error** `file_read`: not found\n");
+    }
+}
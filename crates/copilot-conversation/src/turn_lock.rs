@@ -0,0 +1,98 @@
+//! Per-session turn serialization
+//!
+//! A turn reads the history, generates a reply and writes both messages
+//! back. Two turns in the same session running at once would interleave
+//! those writes, so each turn holds its session's lock from start to
+//! finish; turns in other sessions are unaffected. Waiting turns run in
+//! arrival order.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OwnedMutexGuard;
+
+type SessionLock = Arc<tokio::sync::Mutex<()>>;
+
+/// Locks held by in-progress turns, one per session
+#[derive(Debug, Default)]
+pub struct TurnLocks {
+    locks: Arc<Mutex<HashMap<String, SessionLock>>>,
+}
+
+impl TurnLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait for the session's earlier turns to finish and start a new one;
+    /// the turn lasts until the guard is dropped
+    pub async fn acquire(&self, session_id: &str) -> TurnGuard {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap();
+            Arc::clone(locks.entry(session_id.to_string()).or_default())
+        };
+        TurnGuard {
+            guard: Some(lock.lock_owned().await),
+            session_id: session_id.to_string(),
+            locks: Arc::clone(&self.locks),
+        }
+    }
+
+    /// Sessions with a turn in progress or waiting
+    pub fn active(&self) -> usize {
+        self.locks.lock().unwrap().len()
+    }
+}
+
+/// An in-progress turn; dropping it lets the session's next turn start
+#[derive(Debug)]
+pub struct TurnGuard {
+    guard: Option<OwnedMutexGuard<()>>,
+    session_id: String,
+    locks: Arc<Mutex<HashMap<String, SessionLock>>>,
+}
+
+impl Drop for TurnGuard {
+    fn drop(&mut self) {
+        let Some(guard) = self.guard.take() else {
+            return;
+        };
+        let mut locks = self.locks.lock().unwrap();
+        // Only the map and this guard refer to the lock, so no turn is
+        // waiting and the entry can go
+        if Arc::strong_count(OwnedMutexGuard::mutex(&guard)) == 2 {
+            locks.remove(&self.session_id);
+        }
+        drop(guard);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_turns_in_a_session_wait_for_each_other() {
+        let locks = Arc::new(TurnLocks::new());
+        let first = locks.acquire("s1").await;
+
+        let waiting = {
+            let locks = Arc::clone(&locks);
+            tokio::spawn(async move { locks.acquire("s1").await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        // Other sessions are not blocked
+        drop(locks.acquire("s2").await);
+
+        drop(first);
+        let second = tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(locks.active(), 1);
+        drop(second);
+        assert_eq!(locks.active(), 0);
+    }
+}
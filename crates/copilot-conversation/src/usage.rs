@@ -0,0 +1,170 @@
+//! Token usage and cost accounting
+//!
+//! Every assistant message records the prompt and completion tokens it took
+//! and what they cost under the [`PricingTable`]; session totals are the sum
+//! over the session's messages.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::{Add, AddAssign};
+
+/// Tokens and cost of one message or a whole session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub total_tokens: usize,
+    /// Cost in US dollars
+    pub cost_usd: f64,
+}
+
+impl TokenUsage {
+    pub fn new(prompt_tokens: usize, completion_tokens: usize, cost_usd: f64) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            cost_usd,
+        }
+    }
+}
+
+impl Add for TokenUsage {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self += other;
+        self
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cost_usd += other.cost_usd;
+    }
+}
+
+impl std::iter::Sum for TokenUsage {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// Price of a model per thousand tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub prompt_per_1k_usd: f64,
+    pub completion_per_1k_usd: f64,
+}
+
+impl ModelPricing {
+    pub fn new(prompt_per_1k_usd: f64, completion_per_1k_usd: f64) -> Self {
+        Self {
+            prompt_per_1k_usd,
+            completion_per_1k_usd,
+        }
+    }
+
+    /// Cost in US dollars of a prompt/completion pair
+    pub fn cost(&self, prompt_tokens: usize, completion_tokens: usize) -> f64 {
+        (prompt_tokens as f64 * self.prompt_per_1k_usd
+            + completion_tokens as f64 * self.completion_per_1k_usd)
+            / 1_000.0
+    }
+}
+
+/// Per-model prices; models without an entry use the default price
+#[derive(Debug, Clone)]
+pub struct PricingTable {
+    models: HashMap<String, ModelPricing>,
+    default: ModelPricing,
+}
+
+impl PricingTable {
+    /// An empty table that prices every model at `default`
+    pub fn new(default: ModelPricing) -> Self {
+        Self {
+            models: HashMap::new(),
+            default,
+        }
+    }
+
+    /// Set the price of a model
+    pub fn with_model(mut self, model: impl Into<String>, pricing: ModelPricing) -> Self {
+        self.models.insert(model.into(), pricing);
+        self
+    }
+
+    /// Price of a model, matching dated variants such as
+    /// `claude-3-sonnet-20240229` to their `claude-3-sonnet` entry
+    pub fn pricing_for(&self, model: Option<&str>) -> ModelPricing {
+        let Some(model) = model else {
+            return self.default;
+        };
+        if let Some(pricing) = self.models.get(model) {
+            return *pricing;
+        }
+        self.models
+            .iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, pricing)| *pricing)
+            .unwrap_or(self.default)
+    }
+
+    /// Usage of one exchange with `model`
+    pub fn usage(
+        &self,
+        model: Option<&str>,
+        prompt_tokens: usize,
+        completion_tokens: usize,
+    ) -> TokenUsage {
+        let cost = self
+            .pricing_for(model)
+            .cost(prompt_tokens, completion_tokens);
+        TokenUsage::new(prompt_tokens, completion_tokens, cost)
+    }
+}
+
+impl Default for PricingTable {
+    /// List prices of common models; unknown models are priced like
+    /// `claude-3-sonnet`, the default engine model
+    fn default() -> Self {
+        let sonnet = ModelPricing::new(0.003, 0.015);
+        Self::new(sonnet)
+            .with_model("claude-3-opus", ModelPricing::new(0.015, 0.075))
+            .with_model("claude-3-sonnet", sonnet)
+            .with_model("claude-3-haiku", ModelPricing::new(0.000_25, 0.001_25))
+            .with_model("gpt-4", ModelPricing::new(0.03, 0.06))
+            .with_model("gpt-4o", ModelPricing::new(0.005, 0.015))
+            .with_model("gpt-3.5-turbo", ModelPricing::new(0.000_5, 0.001_5))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pricing_lookup_and_cost() {
+        let table = PricingTable::default();
+        let usage = table.usage(Some("claude-3-opus-20240229"), 1_000, 2_000);
+        assert_eq!(usage.total_tokens, 3_000);
+        assert!((usage.cost_usd - (0.015 + 0.15)).abs() < 1e-9);
+
+        // The longest matching prefix wins
+        assert_eq!(table.pricing_for(Some("gpt-4o-mini")), ModelPricing::new(0.005, 0.015));
+        assert_eq!(table.pricing_for(Some("unknown")), table.pricing_for(None));
+    }
+
+    #[test]
+    fn test_usage_sums() {
+        let total: TokenUsage = [TokenUsage::new(10, 5, 0.25), TokenUsage::new(1, 2, 0.5)]
+            .into_iter()
+            .sum();
+        assert_eq!(total, TokenUsage::new(11, 7, 0.75));
+    }
+}
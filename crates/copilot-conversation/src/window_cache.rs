@@ -0,0 +1,140 @@
+//! Reuse of assembled context windows between turns
+//!
+//! Retrieving, reranking, compressing and filtering context for a message
+//! is the slowest part of a turn, yet a follow-up that repeats the inputs of
+//! the previous turn (a retry, or the same question after a clarification)
+//! would assemble the same window. [`WindowCache`] keeps each
+//! conversation's latest window, keyed by a hash of its retrieval inputs
+//! and the corpus version it was assembled from; any ingestion or memory
+//! write bumps the version, so a window never outlives the content it was
+//! built from. The caller's access is part of the inputs, so a window is
+//! not reused after the caller's roles or groups change.
+
+use copilot_context::retrieval::RetrievalResult;
+use copilot_core::Principal;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Window cache effectiveness
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Conversations with a cached window
+    pub entries: usize,
+}
+
+struct CachedWindow {
+    inputs: u64,
+    corpus_version: u64,
+    result: RetrievalResult,
+}
+
+/// Latest assembled context window of each conversation
+#[derive(Default)]
+pub struct WindowCache {
+    windows: Mutex<HashMap<String, CachedWindow>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl WindowCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hash of the inputs a window is assembled from for the current
+    /// principal; messages differing only in whitespace share it
+    pub fn inputs_hash(message: &str, persona_id: Option<&str>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for word in message.split_whitespace() {
+            word.hash(&mut hasher);
+        }
+        persona_id.hash(&mut hasher);
+        Principal::current().map(|p| p.access_key()).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The conversation's window, if it was assembled from the same inputs
+    /// and corpus version
+    pub fn get(&self, session_id: &str, inputs: u64, corpus_version: u64) -> Option<RetrievalResult> {
+        let result = self
+            .lock()
+            .get(session_id)
+            .filter(|window| window.inputs == inputs && window.corpus_version == corpus_version)
+            .map(|window| window.result.clone());
+        let counter = if result.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    /// Replace the conversation's window
+    pub fn insert(&self, session_id: &str, inputs: u64, corpus_version: u64, result: RetrievalResult) {
+        self.lock().insert(
+            session_id.to_string(),
+            CachedWindow {
+                inputs,
+                corpus_version,
+                result,
+            },
+        );
+    }
+
+    pub fn invalidate_session(&self, session_id: &str) {
+        self.lock().remove(session_id);
+    }
+
+    pub fn stats(&self) -> WindowCacheStats {
+        WindowCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.lock().len(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, CachedWindow>> {
+        self.windows.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(total_tokens: usize) -> RetrievalResult {
+        RetrievalResult {
+            selected: Vec::new(),
+            rejected: Vec::new(),
+            candidate_ids: Vec::new(),
+            total_tokens,
+            target_tokens: 100,
+            max_tokens: 200,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_windows_match_inputs_and_corpus_version() {
+        let cache = WindowCache::new();
+        let inputs = WindowCache::inputs_hash("why is checkout slow", None);
+        assert_eq!(inputs, WindowCache::inputs_hash(" why is  checkout slow ", None));
+        assert_ne!(inputs, WindowCache::inputs_hash("why is checkout slow", Some("sre")));
+        let scoped = Principal::new("u")
+            .with_roles(["user"])
+            .scope(async { WindowCache::inputs_hash("why is checkout slow", None) });
+        assert_ne!(inputs, scoped.await);
+
+        cache.insert("s1", inputs, 3, window(42));
+        assert_eq!(cache.get("s1", inputs, 3).unwrap().total_tokens, 42);
+        assert!(cache.get("s1", inputs, 4).is_none());
+        assert!(cache.get("s1", WindowCache::inputs_hash("and payments?", None), 3).is_none());
+        assert!(cache.get("s2", inputs, 3).is_none());
+
+        cache.invalidate_session("s1");
+        assert!(cache.get("s1", inputs, 3).is_none());
+        assert_eq!(cache.stats(), WindowCacheStats { hits: 1, misses: 4, entries: 0 });
+    }
+}
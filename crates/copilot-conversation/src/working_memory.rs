@@ -0,0 +1,241 @@
+//! Per-conversation working memory
+//!
+//! Plans, partial results and notes that tools and workflow steps hand to
+//! each other during a conversation are kept as named [`WorkingNote`]s
+//! rather than in its history. Unlike the multi-agent
+//! [`copilot_context::Scratchpad`], which lives for one workflow step, a
+//! conversation's notes last as long as the conversation. Notes are hidden
+//! from the user unless written as visible, and each conversation has a
+//! token budget of its own: notes never compete with retrieved context for
+//! the context window, and a write that would exceed the budget fails
+//! instead of silently dropping earlier notes.
+
+use crate::{ConversationError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Tokens each conversation's notes may hold by default
+pub const DEFAULT_WORKING_MEMORY_BUDGET: usize = 4_000;
+
+/// What a note holds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteKind {
+    Plan,
+    PartialResult,
+    #[default]
+    Note,
+}
+
+/// One named note
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkingNote {
+    pub key: String,
+    pub kind: NoteKind,
+    pub content: String,
+    /// Estimated tokens of `content`
+    pub tokens: usize,
+    /// Tool or workflow step that last wrote the note
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub written_by: Option<String>,
+    /// Whether the note may be shown to the user
+    #[serde(default)]
+    pub visible: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl WorkingNote {
+    pub fn new(key: impl Into<String>, kind: NoteKind, content: impl Into<String>) -> Self {
+        let content = content.into();
+        Self {
+            key: key.into(),
+            kind,
+            tokens: estimate_tokens(&content),
+            content,
+            written_by: None,
+            visible: false,
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn with_writer(mut self, writer: impl Into<String>) -> Self {
+        self.written_by = Some(writer.into());
+        self
+    }
+
+    /// Allow the note to be shown to the user
+    pub fn visible(mut self) -> Self {
+        self.visible = true;
+        self
+    }
+}
+
+/// A conversation's notes and how much of its budget they use
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkingMemoryView {
+    pub session_id: String,
+    pub notes: Vec<WorkingNote>,
+    pub used_tokens: usize,
+    pub budget_tokens: usize,
+}
+
+/// Working notes of all conversations
+#[derive(Debug)]
+pub struct WorkingMemory {
+    budget: usize,
+    sessions: RwLock<HashMap<String, Vec<WorkingNote>>>,
+}
+
+impl Default for WorkingMemory {
+    fn default() -> Self {
+        Self::new(DEFAULT_WORKING_MEMORY_BUDGET)
+    }
+}
+
+impl WorkingMemory {
+    /// Notes of at most `budget` tokens per conversation
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            sessions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Add or replace the note with `note.key`
+    ///
+    /// Fails with [`ConversationError::TokenLimitExceeded`] when the
+    /// conversation's notes would exceed the budget.
+    pub fn write(&self, session_id: &str, mut note: WorkingNote) -> Result<WorkingNote> {
+        note.tokens = estimate_tokens(&note.content);
+        note.updated_at = Utc::now();
+        let mut sessions = self.write_lock();
+        let notes = sessions.entry(session_id.to_string()).or_default();
+        let used: usize = notes.iter().filter(|e| e.key != note.key).map(|e| e.tokens).sum();
+        if used + note.tokens > self.budget {
+            return Err(ConversationError::TokenLimitExceeded {
+                used: used + note.tokens,
+                limit: self.budget,
+            });
+        }
+        match notes.iter_mut().find(|e| e.key == note.key) {
+            Some(existing) => *existing = note.clone(),
+            None => notes.push(note.clone()),
+        }
+        Ok(note)
+    }
+
+    pub fn read(&self, session_id: &str, key: &str) -> Option<WorkingNote> {
+        self.read_lock()
+            .get(session_id)?
+            .iter()
+            .find(|e| e.key == key)
+            .cloned()
+    }
+
+    /// Remove a note, returning whether it existed
+    pub fn remove(&self, session_id: &str, key: &str) -> bool {
+        let mut sessions = self.write_lock();
+        let Some(notes) = sessions.get_mut(session_id) else {
+            return false;
+        };
+        let before = notes.len();
+        notes.retain(|e| e.key != key);
+        before != notes.len()
+    }
+
+    /// Drop a conversation's notes
+    pub fn clear(&self, session_id: &str) {
+        self.write_lock().remove(session_id);
+    }
+
+    /// A conversation's notes in the order they were first written;
+    /// hidden notes are left out unless `include_hidden` is set
+    pub fn view(&self, session_id: &str, include_hidden: bool) -> WorkingMemoryView {
+        let sessions = self.read_lock();
+        let all = sessions.get(session_id).map(Vec::as_slice).unwrap_or_default();
+        WorkingMemoryView {
+            session_id: session_id.to_string(),
+            notes: all.iter().filter(|e| include_hidden || e.visible).cloned().collect(),
+            used_tokens: all.iter().map(|e| e.tokens).sum(),
+            budget_tokens: self.budget,
+        }
+    }
+
+    /// The notes as a prompt section for the model, or `None` when there
+    /// are none
+    pub fn prompt_section(&self, session_id: &str) -> Option<String> {
+        let sessions = self.read_lock();
+        let notes = sessions.get(session_id).filter(|notes| !notes.is_empty())?;
+        let mut section = String::from("Working notes (not shown to the user):");
+        for note in notes {
+            section.push_str(&format!("\n[{:?}: {}]\n{}", note.kind, note.key, note.content));
+        }
+        Some(section)
+    }
+
+    fn read_lock(&self) -> RwLockReadGuard<'_, HashMap<String, Vec<WorkingNote>>> {
+        self.sessions.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write_lock(&self) -> RwLockWriteGuard<'_, HashMap<String, Vec<WorkingNote>>> {
+        self.sessions.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// ~4 characters per token, as elsewhere in the crate
+fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notes_are_hidden_by_default() {
+        let memory = WorkingMemory::default();
+        memory.write("s1", WorkingNote::new("plan", NoteKind::Plan, "1. query metrics\n2. compare"))
+            .unwrap();
+        memory.write(
+            "s1",
+            WorkingNote::new("summary", NoteKind::PartialResult, "p99 doubled at 14:02")
+                .with_writer("metrics_query")
+                .visible(),
+        )
+        .unwrap();
+
+        let visible = memory.view("s1", false);
+        assert_eq!(visible.notes.len(), 1);
+        assert_eq!(visible.notes[0].key, "summary");
+        assert_eq!(memory.view("s1", true).notes.len(), 2);
+        assert!(memory.view("s2", true).notes.is_empty());
+
+        let section = memory.prompt_section("s1").unwrap();
+        assert!(section.contains("query metrics") && section.contains("p99 doubled"));
+        assert!(memory.prompt_section("s2").is_none());
+
+        assert!(memory.remove("s1", "plan"));
+        assert!(memory.read("s1", "plan").is_none());
+    }
+
+    #[test]
+    fn test_writes_stay_within_the_budget() {
+        let memory = WorkingMemory::new(10);
+        memory.write("s1", WorkingNote::new("a", NoteKind::Note, "x".repeat(24))).unwrap();
+        assert!(matches!(
+            memory.write("s1", WorkingNote::new("b", NoteKind::Note, "x".repeat(24))),
+            Err(ConversationError::TokenLimitExceeded { used: 12, limit: 10 })
+        ));
+
+        // Replacing a note only counts its new size
+        memory.write("s1", WorkingNote::new("a", NoteKind::Note, "x".repeat(40))).unwrap();
+        let view = memory.view("s1", true);
+        assert_eq!((view.notes.len(), view.used_tokens), (1, 10));
+    }
+}
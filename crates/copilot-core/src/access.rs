@@ -0,0 +1,177 @@
+//! Access control on data.
+//!
+//! Data may carry an [`Acl`] naming the roles and groups allowed to read
+//! it; context items keep theirs in the [`ACL_KEY`] metadata field. The
+//! [`Principal`] a task works for is task-local, set for each API request
+//! from the caller's credentials in the same way as
+//! [`crate::DataResidency`]; code outside any scope is not restricted.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+
+/// Metadata key holding the ACL of an item.
+pub const ACL_KEY: &str = "acl";
+
+tokio::task_local! {
+    static CURRENT: Principal;
+}
+
+/// The caller a task works for.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Principal {
+    pub user_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+impl Principal {
+    pub fn new(user_id: impl Into<String>) -> Self {
+        Self {
+            user_id: user_id.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_tenant(mut self, tenant_id: impl Into<String>) -> Self {
+        self.tenant_id = Some(tenant_id.into());
+        self
+    }
+
+    pub fn with_roles<S: Into<String>>(mut self, roles: impl IntoIterator<Item = S>) -> Self {
+        self.roles.extend(roles.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_groups<S: Into<String>>(mut self, groups: impl IntoIterator<Item = S>) -> Self {
+        self.groups.extend(groups.into_iter().map(Into::into));
+        self
+    }
+
+    /// Run `f` on behalf of this principal.
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        CURRENT.scope(self, f).await
+    }
+
+    /// The principal of the running task, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+
+    /// Key shared by principals that ACLs treat alike: the same roles
+    /// (ignoring case) and groups.
+    pub fn access_key(&self) -> String {
+        let mut labels: Vec<String> = self
+            .roles
+            .iter()
+            .map(|role| format!("role:{}", role.to_lowercase()))
+            .chain(self.groups.iter().map(|group| format!("group:{}", group)))
+            .collect();
+        labels.sort();
+        labels.dedup();
+        labels.join(",")
+    }
+}
+
+/// Roles and groups allowed to read something; an ACL naming neither
+/// admits no one.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Acl {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+}
+
+impl Acl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_role(mut self, role: impl Into<String>) -> Self {
+        self.roles.push(role.into());
+        self
+    }
+
+    pub fn with_group(mut self, group: impl Into<String>) -> Self {
+        self.groups.push(group.into());
+        self
+    }
+
+    /// Whether `principal` holds one of the roles (ignoring case) or is in
+    /// one of the groups.
+    pub fn permits(&self, principal: &Principal) -> bool {
+        let role = self
+            .roles
+            .iter()
+            .any(|role| principal.roles.iter().any(|held| held.eq_ignore_ascii_case(role)));
+        role || self.groups.iter().any(|group| principal.groups.contains(group))
+    }
+}
+
+impl FromStr for Acl {
+    type Err = String;
+
+    /// Comma-separated `role:<name>` and `group:<name>` entries, e.g.
+    /// `role:admin,group:finance`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut acl = Acl::new();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            acl = match entry.split_once(':') {
+                Some(("role", role)) if !role.trim().is_empty() => acl.with_role(role.trim()),
+                Some(("group", group)) if !group.trim().is_empty() => acl.with_group(group.trim()),
+                _ => return Err(format!("{} is not role:<name> or group:<name>", entry)),
+            };
+        }
+        Ok(acl)
+    }
+}
+
+impl fmt::Display for Acl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<String> = self
+            .roles
+            .iter()
+            .map(|role| format!("role:{}", role))
+            .chain(self.groups.iter().map(|group| format!("group:{}", group)))
+            .collect();
+        write!(f, "{}", entries.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acl_permits_roles_and_groups() {
+        let acl: Acl = "role:Admin, group:finance".parse().unwrap();
+        assert_eq!(acl.to_string(), "role:Admin,group:finance");
+
+        assert!(acl.permits(&Principal::new("a").with_roles(["admin"])));
+        assert!(acl.permits(&Principal::new("b").with_roles(["user"]).with_groups(["finance"])));
+        assert!(!acl.permits(&Principal::new("c").with_roles(["user"]).with_groups(["Finance"])));
+        assert!(!Acl::new().permits(&Principal::new("d").with_roles(["admin"])));
+
+        assert!("team:finance".parse::<Acl>().is_err());
+        assert!("role:".parse::<Acl>().is_err());
+
+        let a = Principal::new("a").with_roles(["User", "admin"]).with_groups(["ops"]);
+        let b = Principal::new("b").with_groups(["ops"]).with_roles(["admin", "user"]);
+        assert_eq!(a.access_key(), b.access_key());
+        assert_ne!(a.access_key(), Principal::new("c").with_roles(["admin"]).access_key());
+    }
+
+    #[tokio::test]
+    async fn test_principal_is_task_local() {
+        assert!(Principal::current().is_none());
+        let principal = Principal::new("alice").with_tenant("acme");
+        let current = principal.clone().scope(async { Principal::current() }).await;
+        assert_eq!(current, Some(principal));
+    }
+}
@@ -1,5 +1,8 @@
 use config::{Config, ConfigError, Environment, File};
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
 use std::time::Duration;
 
 /// Main application configuration
@@ -19,19 +22,34 @@ impl AppConfig {
     }
 
     /// Load configuration from environment with custom prefix
+    ///
+    /// Nested settings are overridden with `<PREFIX>__<SECTION>__<KEY>`
+    /// (e.g. `APP__DATABASE__MAX_CONNECTIONS`); secret settings can also be
+    /// read from files, see [`SECRET_SETTINGS`].
     pub fn load_from_env(prefix: &str) -> Result<Self, ConfigError> {
-        let builder = Config::builder()
-            .add_source(
-                Environment::with_prefix(prefix)
-                    .separator("__")
-                    .try_parsing(true),
-            )
+        Self::load_layered(None, prefix, None)
+    }
+
+    /// Load configuration from file with environment overrides
+    pub fn load_from_file(path: &str) -> Result<Self, ConfigError> {
+        Self::load_layered(Some(path), "APP", None)
+    }
+
+    /// Defaults, then the file at `path`, then environment overrides, then
+    /// secrets read from `_FILE` variables. `env` replaces the process
+    /// environment when given.
+    fn load_layered(
+        path: Option<&str>,
+        prefix: &str,
+        env: Option<HashMap<String, String>>,
+    ) -> Result<Self, ConfigError> {
+        let mut builder = Config::builder()
             .set_default("database.url", "postgres://localhost/copilot")?
             .set_default("database.max_connections", 10)?
             .set_default("database.min_connections", 2)?
             .set_default("redis.url", "redis://localhost")?
             .set_default("redis.max_connections", 10)?
-            .set_default("auth.jwt_secret", "development-secret-change-in-production")?
+            .set_default("auth.jwt_secret", DEV_JWT_SECRET)?
             .set_default("auth.token_expiry_seconds", 3600)?
             .set_default("auth.issuer", "llm-copilot-agent")?
             .set_default("auth.audience", "copilot-api")?
@@ -44,18 +62,207 @@ impl AppConfig {
             .set_default("server.port", 8080)?
             .set_default("server.workers", 4)?;
 
-        let config = builder.build()?;
-        config.try_deserialize()
+        if let Some(path) = path {
+            builder = builder.add_source(File::with_name(path));
+        }
+        builder = builder.add_source(
+            Environment::with_prefix(prefix)
+                .separator("__")
+                .try_parsing(true)
+                .source(env.clone()),
+        );
+
+        let lookup = |name: &str| match &env {
+            Some(env) => env.get(name).cloned(),
+            None => std::env::var(name).ok(),
+        };
+        for key in SECRET_SETTINGS {
+            if let Some(value) = secret_with(&env_var_name(prefix, key), lookup)? {
+                builder = builder.set_override(*key, value)?;
+            }
+        }
+
+        builder.build()?.try_deserialize()
     }
 
-    /// Load configuration from file with environment overrides
-    pub fn load_from_file(path: &str) -> Result<Self, ConfigError> {
-        let builder = Config::builder()
-            .add_source(File::with_name(path))
-            .add_source(Environment::with_prefix("APP").separator("__"));
+    /// Check settings that would only fail once used
+    pub fn validate(&self) -> ConfigReport {
+        let mut report = ConfigReport::default();
+
+        check_url(&mut report, "database.url", &self.database.url, &["postgres", "postgresql"]);
+        if self.database.max_connections == 0 {
+            report.invalid("database.max_connections", "must be at least 1");
+        } else if self.database.min_connections > self.database.max_connections {
+            report.invalid("database.min_connections", "exceeds database.max_connections");
+        }
+        check_url(&mut report, "redis.url", &self.redis.url, &["redis", "rediss"]);
+
+        if self.auth.jwt_secret.is_empty() {
+            report.missing("auth.jwt_secret", "required to sign tokens");
+        } else if self.auth.jwt_secret == DEV_JWT_SECRET {
+            report.invalid("auth.jwt_secret", "still the development default");
+        }
+
+        if self.llm.api_key.is_empty() {
+            report.missing("llm.api_key", "required to call the LLM provider");
+        }
+        if self.llm.max_tokens == 0 {
+            report.invalid("llm.max_tokens", "must be at least 1");
+        }
+        if !(0.0..=2.0).contains(&self.llm.temperature) {
+            report.invalid("llm.temperature", "must be between 0 and 2");
+        }
+
+        if self.server.port == 0 {
+            report.invalid("server.port", "must not be 0");
+        }
+        match (&self.server.tls_cert_path, &self.server.tls_key_path) {
+            (Some(_), None) => report.missing("server.tls_key_path", "required with server.tls_cert_path"),
+            (None, Some(_)) => report.missing("server.tls_cert_path", "required with server.tls_key_path"),
+            (Some(cert), Some(key)) => {
+                for (setting, path) in [("server.tls_cert_path", cert), ("server.tls_key_path", key)] {
+                    if !Path::new(path).is_file() {
+                        report.invalid(setting, format!("{} does not exist", path));
+                    }
+                }
+            }
+            (None, None) => {}
+        }
+
+        report
+    }
+}
+
+/// JWT secret used when none is configured
+const DEV_JWT_SECRET: &str = "development-secret-change-in-production";
+
+/// Settings that hold credentials. Besides `<PREFIX>__<SECTION>__<KEY>`,
+/// each can be read from the file named by `<PREFIX>__<SECTION>__<KEY>_FILE`
+/// (e.g. `APP__AUTH__JWT_SECRET_FILE=/run/secrets/jwt`), so secrets can be
+/// mounted from Kubernetes or Docker secrets instead of passed in the
+/// environment.
+pub const SECRET_SETTINGS: &[&str] = &["database.url", "redis.url", "auth.jwt_secret", "llm.api_key"];
+
+/// Environment variable that overrides `key`, e.g. `APP__AUTH__JWT_SECRET`
+pub fn env_var_name(prefix: &str, key: &str) -> String {
+    format!("{}__{}", prefix, key.replace('.', "__")).to_uppercase()
+}
+
+/// Value of the environment variable `name`, or the contents of the file
+/// named by `<name>_FILE` with trailing newlines removed. Setting both is an
+/// error.
+pub fn secret_from_env(name: &str) -> Result<Option<String>, ConfigError> {
+    secret_with(name, |var| std::env::var(var).ok())
+}
+
+fn secret_with(name: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<Option<String>, ConfigError> {
+    let file_var = format!("{}_FILE", name);
+    match (lookup(name), lookup(&file_var)) {
+        (Some(_), Some(_)) => Err(ConfigError::Message(format!(
+            "{} and {} are both set; use one",
+            name, file_var
+        ))),
+        (value, None) => Ok(value),
+        (None, Some(path)) => std::fs::read_to_string(&path)
+            .map(|contents| Some(contents.trim_end_matches(['\r', '\n']).to_string()))
+            .map_err(|e| ConfigError::Message(format!("{}: cannot read {}: {}", file_var, path, e))),
+    }
+}
+
+/// For each variable in `names` that is only given as `<name>_FILE`, set it
+/// from the file so code reading the plain variable sees the secret. Call at
+/// startup before other threads read the environment.
+pub fn export_secret_files(names: &[&str]) -> ConfigReport {
+    let mut report = ConfigReport::default();
+    for name in names {
+        if std::env::var_os(format!("{}_FILE", name)).is_none() {
+            continue;
+        }
+        match secret_from_env(name) {
+            Ok(Some(value)) => std::env::set_var(name, value),
+            Ok(None) => {}
+            Err(e) => report.invalid(*name, e.to_string()),
+        }
+    }
+    report
+}
+
+fn check_url(report: &mut ConfigReport, setting: &str, url: &str, schemes: &[&str]) {
+    match url.split_once("://") {
+        _ if url.is_empty() => report.missing(setting, "required"),
+        Some((scheme, rest)) if schemes.contains(&scheme) && !rest.is_empty() => {}
+        _ => report.invalid(setting, format!("expected a {}:// URL", schemes[0])),
+    }
+}
+
+/// What is wrong with a setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigProblem {
+    Missing,
+    Invalid,
+}
+
+/// A setting that is missing or invalid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// Setting or environment variable name
+    pub setting: String,
+    pub problem: ConfigProblem,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problem = match self.problem {
+            ConfigProblem::Missing => "missing",
+            ConfigProblem::Invalid => "invalid",
+        };
+        write!(f, "{} is {}: {}", self.setting, problem, self.message)
+    }
+}
+
+/// Result of validating configuration at startup
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigReport {
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl ConfigReport {
+    /// Record a setting that is required but not set
+    pub fn missing(&mut self, setting: impl Into<String>, message: impl Into<String>) {
+        self.push(setting.into(), ConfigProblem::Missing, message.into());
+    }
+
+    /// Record a setting whose value cannot be used
+    pub fn invalid(&mut self, setting: impl Into<String>, message: impl Into<String>) {
+        self.push(setting.into(), ConfigProblem::Invalid, message.into());
+    }
+
+    /// Append the issues of another report
+    pub fn merge(&mut self, other: ConfigReport) {
+        self.issues.extend(other.issues);
+    }
 
-        let config = builder.build()?;
-        config.try_deserialize()
+    /// Whether no issues were found
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    fn push(&mut self, setting: String, problem: ConfigProblem, message: String) {
+        self.issues.push(ConfigIssue { setting, problem, message });
+    }
+}
+
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_ok() {
+            return write!(f, "configuration is valid");
+        }
+        write!(f, "{} configuration problem(s):", self.issues.len())?;
+        for issue in &self.issues {
+            write!(f, "\n  - {}", issue)?;
+        }
+        Ok(())
     }
 }
 
@@ -387,6 +594,69 @@ mod tests {
         assert!(config.workers > 0);
     }
 
+    fn env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_load_with_overrides_and_secret_files() {
+        let secret_path = std::env::temp_dir().join(format!("copilot-jwt-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&secret_path, "from-file-secret-0123456789abcdef\n").unwrap();
+
+        let config = AppConfig::load_layered(
+            None,
+            "TEST",
+            Some(env(&[
+                ("TEST__DATABASE__MAX_CONNECTIONS", "25"),
+                ("TEST__LLM__API_KEY", "sk-env"),
+                ("TEST__AUTH__JWT_SECRET_FILE", secret_path.to_str().unwrap()),
+            ])),
+        )
+        .unwrap();
+        std::fs::remove_file(&secret_path).unwrap();
+
+        assert_eq!(config.database.max_connections, 25);
+        assert_eq!(config.llm.api_key, "sk-env");
+        assert_eq!(config.auth.jwt_secret, "from-file-secret-0123456789abcdef");
+        assert!(config.validate().is_ok());
+
+        let both = AppConfig::load_layered(
+            None,
+            "TEST",
+            Some(env(&[("TEST__LLM__API_KEY", "a"), ("TEST__LLM__API_KEY_FILE", "/nonexistent")])),
+        );
+        assert!(both.unwrap_err().to_string().contains("both set"));
+
+        let unreadable = AppConfig::load_layered(None, "TEST", Some(env(&[("TEST__LLM__API_KEY_FILE", "/nonexistent")])));
+        assert!(unreadable.unwrap_err().to_string().contains("cannot read /nonexistent"));
+    }
+
+    #[test]
+    fn test_validation_report() {
+        let mut config = AppConfig::load_layered(None, "TEST", Some(HashMap::new())).unwrap();
+        config.database.url = "mysql://localhost/copilot".to_string();
+        config.server.tls_cert_path = Some("/etc/tls/cert.pem".to_string());
+
+        let report = config.validate();
+        let settings: Vec<(&str, ConfigProblem)> =
+            report.issues.iter().map(|i| (i.setting.as_str(), i.problem)).collect();
+        assert_eq!(
+            settings,
+            vec![
+                ("database.url", ConfigProblem::Invalid),
+                ("auth.jwt_secret", ConfigProblem::Invalid),
+                ("llm.api_key", ConfigProblem::Missing),
+                ("server.tls_key_path", ConfigProblem::Missing),
+            ]
+        );
+        assert!(report.to_string().starts_with("4 configuration problem(s):\n  - database.url is invalid"));
+    }
+
+    #[test]
+    fn test_env_var_name() {
+        assert_eq!(env_var_name("APP", "auth.jwt_secret"), "APP__AUTH__JWT_SECRET");
+    }
+
     #[test]
     fn test_server_tls_config() {
         let config = ServerConfig::new()
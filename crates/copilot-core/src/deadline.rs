@@ -0,0 +1,159 @@
+//! Request deadlines.
+//!
+//! Each inbound request runs inside a [`Deadline`] scope. Retrieval,
+//! reranking, model calls and adapter requests read [`Deadline::current`]
+//! and cut their work short when it runs out (skipping a rerank, dropping a
+//! slow sub-query, abandoning a retry) instead of holding the request until
+//! the outer HTTP timeout. Code outside any scope has no deadline.
+//!
+//! The deadline is task-local: tasks spawned inside a scope do not inherit
+//! it and must be wrapped with [`Deadline::scope`] themselves.
+
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Header a client sets to shorten (or, up to the server's maximum, extend)
+/// the time a request may take, e.g. `2500`, `2500ms` or `2.5s`.
+pub const DEADLINE_HEADER: &str = "x-request-timeout";
+
+tokio::task_local! {
+    static CURRENT: Deadline;
+}
+
+/// The instant by which a request must be answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    at: Instant,
+}
+
+/// Returned when work is abandoned because its deadline passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeadlineExceeded;
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("request deadline exceeded")
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
+impl Deadline {
+    /// A deadline `budget` from now.
+    pub fn after(budget: Duration) -> Self {
+        Self {
+            at: Instant::now() + budget,
+        }
+    }
+
+    pub fn at(&self) -> Instant {
+        self.at
+    }
+
+    /// Time left; zero once passed.
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.at
+    }
+
+    /// Run `f` with this deadline as the current one. A deadline already in
+    /// effect is kept if it is earlier.
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        let deadline = match Self::current() {
+            Some(outer) if outer.at < self.at => outer,
+            _ => self,
+        };
+        CURRENT.scope(deadline, f).await
+    }
+
+    /// The deadline of the running task, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(|deadline| *deadline).ok()
+    }
+
+    /// `timeout`, shortened to the current deadline.
+    pub fn clamp(timeout: Duration) -> Duration {
+        Self::current().map_or(timeout, |deadline| deadline.remaining().min(timeout))
+    }
+
+    /// Whether the current deadline, if any, leaves less than `needed`.
+    pub fn too_close(needed: Duration) -> bool {
+        Self::current().is_some_and(|deadline| deadline.remaining() < needed)
+    }
+
+    /// Run `f` unless the current deadline passes first.
+    pub async fn run<F: Future>(f: F) -> Result<F::Output, DeadlineExceeded> {
+        match Self::current() {
+            Some(deadline) => tokio::time::timeout_at(deadline.at, f)
+                .await
+                .map_err(|_| DeadlineExceeded),
+            None => Ok(f.await),
+        }
+    }
+
+    /// Parse a [`DEADLINE_HEADER`] value: milliseconds, optionally suffixed
+    /// with `ms`, or seconds suffixed with `s`.
+    pub fn parse_timeout(value: &str) -> Option<Duration> {
+        let value = value.trim();
+        if let Some(ms) = value.strip_suffix("ms") {
+            return ms.trim().parse().ok().map(Duration::from_millis);
+        }
+        if let Some(secs) = value.strip_suffix('s') {
+            return secs
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                .map(Duration::from_secs_f64);
+        }
+        value.parse().ok().map(Duration::from_millis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timeout() {
+        assert_eq!(Deadline::parse_timeout("2500"), Some(Duration::from_millis(2500)));
+        assert_eq!(Deadline::parse_timeout("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(Deadline::parse_timeout(" 2.5s "), Some(Duration::from_millis(2500)));
+        assert_eq!(Deadline::parse_timeout("-1s"), None);
+        assert_eq!(Deadline::parse_timeout("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn test_scope_keeps_the_earlier_deadline() {
+        assert!(Deadline::current().is_none());
+        assert_eq!(Deadline::clamp(Duration::from_secs(5)), Duration::from_secs(5));
+
+        let outer = Deadline::after(Duration::from_millis(100));
+        outer
+            .scope(async {
+                assert_eq!(Deadline::current(), Some(outer));
+                Deadline::after(Duration::from_secs(60))
+                    .scope(async { assert_eq!(Deadline::current(), Some(outer)) })
+                    .await;
+                assert!(Deadline::clamp(Duration::from_secs(5)) <= Duration::from_millis(100));
+                assert!(Deadline::too_close(Duration::from_secs(1)));
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_at_the_deadline() {
+        let result = Deadline::after(Duration::from_millis(50))
+            .scope(Deadline::run(tokio::time::sleep(Duration::from_secs(10))))
+            .await;
+        assert_eq!(result, Err(DeadlineExceeded));
+
+        let result = Deadline::run(async { 7 }).await;
+        assert_eq!(result, Ok(7));
+    }
+}
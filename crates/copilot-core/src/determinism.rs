@@ -0,0 +1,308 @@
+//! Deterministic time, randomness and identifiers.
+//!
+//! Code that reads the time, draws random numbers or mints IDs can take them
+//! from a [`Determinism`] instead of calling `Utc::now()`, `thread_rng()` or
+//! `Uuid::new_v4()` directly. [`Determinism::system`] uses the real sources;
+//! [`Determinism::seeded`] freezes the clock at a fixed instant and derives
+//! every random value and UUID from the seed, so tests, benchmarks and
+//! evaluations produce the same output byte for byte on every run.
+//!
+//! Setting `COPILOT_SEED` makes [`Determinism::from_env`] return a seeded
+//! instance, so a benchmark or evaluation run can be pinned without code
+//! changes.
+
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Environment variable holding the seed for [`Determinism::from_env`].
+pub const SEED_ENV_VAR: &str = "COPILOT_SEED";
+
+/// Source of the current time.
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Current wall-clock time.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Time since an arbitrary fixed point, for measuring durations.
+    fn monotonic(&self) -> Duration;
+}
+
+/// The system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn monotonic(&self) -> Duration {
+        static ORIGIN: OnceLock<Instant> = OnceLock::new();
+        ORIGIN.get_or_init(Instant::now).elapsed()
+    }
+}
+
+/// A clock that only moves when told to.
+#[derive(Debug)]
+pub struct FrozenClock {
+    origin: DateTime<Utc>,
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl FrozenClock {
+    pub fn new(at: DateTime<Utc>) -> Self {
+        Self {
+            origin: at,
+            now: Mutex::new(at),
+        }
+    }
+
+    /// Frozen at [`FrozenClock::EPOCH`].
+    pub fn at_epoch() -> Self {
+        Self::new(Self::EPOCH)
+    }
+
+    /// 2024-01-01T00:00:00Z, the start time of seeded runs.
+    pub const EPOCH: DateTime<Utc> = DateTime::from_timestamp_nanos(1_704_067_200_000_000_000);
+
+    /// Move the clock to `at`.
+    pub fn set(&self, at: DateTime<Utc>) {
+        *self.lock() = at;
+    }
+
+    /// Move the clock forward by `by`.
+    pub fn advance(&self, by: chrono::Duration) {
+        *self.lock() += by;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DateTime<Utc>> {
+        self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for FrozenClock {
+    fn default() -> Self {
+        Self::at_epoch()
+    }
+}
+
+impl Clock for FrozenClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.lock()
+    }
+
+    fn monotonic(&self) -> Duration {
+        (self.now() - self.origin).to_std().unwrap_or_default()
+    }
+}
+
+/// Measures elapsed time on a [`Clock`].
+#[derive(Debug, Clone)]
+pub struct Stopwatch {
+    clock: Arc<dyn Clock>,
+    started: Duration,
+}
+
+impl Stopwatch {
+    pub fn start(clock: Arc<dyn Clock>) -> Self {
+        let started = clock.monotonic();
+        Self { clock, started }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.clock.monotonic().saturating_sub(self.started)
+    }
+}
+
+/// Source of unique identifiers.
+pub trait IdGenerator: Send + Sync + fmt::Debug {
+    fn next_uuid(&self) -> Uuid;
+}
+
+/// Random (v4) UUIDs from the operating system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomIds;
+
+impl IdGenerator for RandomIds {
+    fn next_uuid(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+/// Version 4 UUIDs drawn from a seeded generator, the same sequence for the
+/// same seed.
+#[derive(Debug)]
+pub struct SeededIds {
+    rng: Mutex<StdRng>,
+}
+
+impl SeededIds {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl IdGenerator for SeededIds {
+    fn next_uuid(&self) -> Uuid {
+        let mut bytes = [0u8; 16];
+        self.rng
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .fill_bytes(&mut bytes);
+        uuid::Builder::from_random_bytes(bytes).into_uuid()
+    }
+}
+
+/// Clock, ID generator and random number seed used by a component.
+#[derive(Debug, Clone)]
+pub struct Determinism {
+    clock: Arc<dyn Clock>,
+    ids: Arc<dyn IdGenerator>,
+    seed: Option<u64>,
+}
+
+impl Default for Determinism {
+    fn default() -> Self {
+        Self::system()
+    }
+}
+
+impl Determinism {
+    /// The system clock, random UUIDs and entropy-seeded generators.
+    pub fn system() -> Self {
+        Self {
+            clock: Arc::new(SystemClock),
+            ids: Arc::new(RandomIds),
+            seed: None,
+        }
+    }
+
+    /// A clock frozen at [`FrozenClock::EPOCH`], and UUIDs and random
+    /// generators derived from `seed`.
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            clock: Arc::new(FrozenClock::at_epoch()),
+            ids: Arc::new(SeededIds::new(seed)),
+            seed: Some(seed),
+        }
+    }
+
+    /// Seeded from `COPILOT_SEED` when it is set, the system otherwise.
+    pub fn from_env() -> Self {
+        std::env::var(SEED_ENV_VAR)
+            .ok()
+            .and_then(|seed| seed.trim().parse().ok())
+            .map_or_else(Self::system, Self::seeded)
+    }
+
+    /// Use `clock` instead, e.g. a [`FrozenClock`] the test advances.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The seed, when deterministic.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.seed.is_some()
+    }
+
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    pub fn ids(&self) -> Arc<dyn IdGenerator> {
+        self.ids.clone()
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    pub fn next_uuid(&self) -> Uuid {
+        self.ids.next_uuid()
+    }
+
+    pub fn stopwatch(&self) -> Stopwatch {
+        Stopwatch::start(self.clock.clone())
+    }
+
+    /// A random number generator for one consumer.
+    ///
+    /// Seeded runs give each `stream` its own sequence derived from the seed,
+    /// so adding draws in one component does not shift the values another
+    /// one sees.
+    pub fn rng(&self, stream: &str) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed ^ fnv1a(stream.as_bytes())),
+            None => StdRng::from_entropy(),
+        }
+    }
+}
+
+/// FNV-1a, a hash that is stable across platforms and releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_seeded_runs_repeat() {
+        let first = Determinism::seeded(7);
+        let second = Determinism::seeded(7);
+
+        assert_eq!(first.now(), FrozenClock::EPOCH);
+        assert_eq!(first.now(), second.now());
+        let ids: Vec<_> = (0..3).map(|_| first.next_uuid()).collect();
+        assert_eq!(ids, (0..3).map(|_| second.next_uuid()).collect::<Vec<_>>());
+        assert_eq!(ids[0].get_version_num(), 4);
+        assert_ne!(ids[0], ids[1]);
+
+        let draws = |d: &Determinism, stream| d.rng(stream).gen::<[u64; 4]>();
+        assert_eq!(draws(&first, "retrieval"), draws(&second, "retrieval"));
+        assert_ne!(draws(&first, "retrieval"), draws(&first, "workflow"));
+        assert_ne!(draws(&first, "retrieval"), draws(&Determinism::seeded(8), "retrieval"));
+        assert_ne!(Determinism::seeded(8).next_uuid(), Determinism::seeded(7).next_uuid());
+    }
+
+    #[test]
+    fn test_frozen_clock_and_stopwatch() {
+        let clock = Arc::new(FrozenClock::at_epoch());
+        let determinism = Determinism::seeded(1).with_clock(clock.clone());
+        let stopwatch = determinism.stopwatch();
+        assert_eq!(stopwatch.elapsed(), Duration::ZERO);
+
+        clock.advance(chrono::Duration::milliseconds(1500));
+        assert_eq!(determinism.now(), FrozenClock::EPOCH + chrono::Duration::milliseconds(1500));
+        assert_eq!(stopwatch.elapsed(), Duration::from_millis(1500));
+
+        let unix_epoch = DateTime::from_timestamp(0, 0).unwrap();
+        clock.set(unix_epoch);
+        assert_eq!(determinism.now(), unix_epoch);
+        assert_eq!(stopwatch.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_system_is_not_seeded() {
+        let system = Determinism::system();
+        assert!(!system.is_deterministic());
+        assert_ne!(system.next_uuid(), system.next_uuid());
+        assert!(Determinism::seeded(3).is_deterministic());
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+    }
+}
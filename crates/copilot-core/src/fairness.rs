@@ -0,0 +1,632 @@
+//! Weighted fair queuing between tenants.
+//!
+//! Expensive shared resources (model calls, sandbox slots, embedding
+//! throughput) hand out work through a [`FairQueue`]. Each queued request
+//! gets a virtual finish time that advances by `cost / weight` for its
+//! tenant, and the earliest finish time goes next. A tenant with a deep
+//! backlog therefore only gets its weighted share while others are waiting,
+//! and a tenant that was idle cannot bank credit to burst past them later.
+//!
+//! The tenant a task works for is a task-local [`TenantShare`], set for each
+//! API request in the same way as [`crate::Deadline`]. Work outside any
+//! scope is queued under [`UNATTRIBUTED_TENANT`] with weight 1. Waits are
+//! counted per tenant in [`TenantQueueStats`].
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+use crate::Deadline;
+
+/// Tenant that work outside a [`TenantShare`] scope is queued under.
+pub const UNATTRIBUTED_TENANT: &str = "unattributed";
+
+/// Smallest weight a tenant can have; keeps finish times finite.
+const MIN_WEIGHT: f64 = 0.01;
+
+tokio::task_local! {
+    static CURRENT: TenantShare;
+}
+
+/// The tenant a task works for and its weight in shared queues.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TenantShare {
+    pub tenant_id: String,
+    pub weight: f64,
+}
+
+impl TenantShare {
+    pub fn new(tenant_id: impl Into<String>, weight: f64) -> Self {
+        Self {
+            tenant_id: tenant_id.into(),
+            weight: weight.max(MIN_WEIGHT),
+        }
+    }
+
+    /// Run `f` on behalf of this tenant.
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        CURRENT.scope(self, f).await
+    }
+
+    /// The tenant of the running task, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+
+    /// The tenant of the running task, or [`UNATTRIBUTED_TENANT`].
+    pub fn current_or_unattributed() -> Self {
+        Self::current().unwrap_or_else(|| Self::new(UNATTRIBUTED_TENANT, 1.0))
+    }
+}
+
+/// Queue weights by tenant tier, e.g. `free=1,standard=2,pro=4`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FairnessWeights {
+    tiers: HashMap<String, f64>,
+    default: f64,
+}
+
+impl Default for FairnessWeights {
+    fn default() -> Self {
+        Self {
+            tiers: [
+                ("anonymous", 0.5),
+                ("free", 1.0),
+                ("standard", 2.0),
+                ("pro", 4.0),
+                ("enterprise", 8.0),
+                ("unlimited", 8.0),
+            ]
+            .into_iter()
+            .map(|(tier, weight)| (tier.to_string(), weight))
+            .collect(),
+            default: 1.0,
+        }
+    }
+}
+
+impl FairnessWeights {
+    /// Give tenants of `tier` `weight`
+    pub fn with_weight(mut self, tier: impl Into<String>, weight: f64) -> Self {
+        self.tiers.insert(tier.into().to_lowercase(), weight.max(MIN_WEIGHT));
+        self
+    }
+
+    /// Weight of a tier; tiers not listed get 1.
+    pub fn weight(&self, tier: &str) -> f64 {
+        self.tiers.get(&tier.to_lowercase()).copied().unwrap_or(self.default)
+    }
+}
+
+impl FromStr for FairnessWeights {
+    type Err = String;
+
+    /// Comma-separated `tier=weight` pairs, overriding the defaults.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = Self::default();
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (tier, weight) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("{} is not tier=weight", pair))?;
+            let weight: f64 = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("weight of {} is not a number", tier.trim()))?;
+            if !weight.is_finite() || weight <= 0.0 {
+                return Err(format!("weight of {} must be positive", tier.trim()));
+            }
+            weights = weights.with_weight(tier.trim(), weight);
+        }
+        Ok(weights)
+    }
+}
+
+/// Queueing counters of one tenant on one resource.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TenantQueueStats {
+    pub tenant_id: String,
+    /// Requests waiting now
+    pub queued: usize,
+    /// Requests served, with or without waiting
+    pub granted: u64,
+    /// Requests that gave up waiting
+    pub abandoned: u64,
+    /// Time served requests spent queued
+    pub wait_seconds_total: f64,
+    /// Longest time a served request spent queued
+    pub max_wait_seconds: f64,
+}
+
+impl TenantQueueStats {
+    /// Add the counters of the same tenant on another queue
+    pub fn merge(&mut self, other: &TenantQueueStats) {
+        self.queued += other.queued;
+        self.granted += other.granted;
+        self.abandoned += other.abandoned;
+        self.wait_seconds_total += other.wait_seconds_total;
+        self.max_wait_seconds = self.max_wait_seconds.max(other.max_wait_seconds);
+    }
+}
+
+/// Render per-tenant queue counters of `resource` in the Prometheus text
+/// exposition format.
+pub fn render_queue_stats(prefix: &str, resource: &str, stats: &[TenantQueueStats]) -> String {
+    let mut out = String::new();
+    for (i, (name, kind, help)) in [
+        ("queued", "gauge", "Requests waiting for a shared resource"),
+        ("granted_total", "counter", "Requests served by a shared resource"),
+        ("abandoned_total", "counter", "Requests that gave up waiting for a shared resource"),
+        ("wait_seconds_total", "counter", "Time spent waiting for a shared resource"),
+        ("max_wait_seconds", "gauge", "Longest wait for a shared resource"),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let _ = writeln!(out, "# HELP {prefix}_fair_queue_{name} {help}");
+        let _ = writeln!(out, "# TYPE {prefix}_fair_queue_{name} {kind}");
+        for tenant in stats {
+            let value = [
+                tenant.queued as f64,
+                tenant.granted as f64,
+                tenant.abandoned as f64,
+                tenant.wait_seconds_total,
+                tenant.max_wait_seconds,
+            ][i];
+            let _ = writeln!(
+                out,
+                "{prefix}_fair_queue_{name}{{resource=\"{resource}\",tenant=\"{}\"}} {value}",
+                tenant.tenant_id
+            );
+        }
+    }
+    out
+}
+
+/// Position of a request in a [`FairQueue`], for removing it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ticket {
+    finish: f64,
+    seq: u64,
+}
+
+impl Eq for Ticket {}
+
+impl Ord for Ticket {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.finish.total_cmp(&other.finish).then(self.seq.cmp(&other.seq))
+    }
+}
+
+impl PartialOrd for Ticket {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+struct Entry<T> {
+    tenant_id: String,
+    queued_at: Instant,
+    item: T,
+}
+
+#[derive(Default)]
+struct TenantState {
+    /// Virtual finish time of the tenant's last request
+    finish: f64,
+    stats: TenantQueueStats,
+}
+
+/// A request taken off a [`FairQueue`].
+#[derive(Debug)]
+pub struct Dequeued<T> {
+    pub tenant_id: String,
+    pub item: T,
+    pub waited: Duration,
+}
+
+/// Requests of many tenants, served in weighted fair order.
+pub struct FairQueue<T> {
+    entries: BTreeMap<Ticket, Entry<T>>,
+    tenants: HashMap<String, TenantState>,
+    virtual_time: f64,
+    seq: u64,
+}
+
+impl<T> Default for FairQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FairQueue<T> {
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            tenants: HashMap::new(),
+            virtual_time: 0.0,
+            seq: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Advance `share`'s virtual time by `cost` and return the finish time.
+    fn charge(&mut self, share: &TenantShare, cost: f64) -> f64 {
+        let virtual_time = self.virtual_time;
+        let tenant = self.tenants.entry(share.tenant_id.clone()).or_insert_with(|| TenantState {
+            finish: 0.0,
+            stats: TenantQueueStats {
+                tenant_id: share.tenant_id.clone(),
+                ..Default::default()
+            },
+        });
+        tenant.finish = tenant.finish.max(virtual_time) + cost.max(0.0) / share.weight;
+        tenant.finish
+    }
+
+    fn tenant(&mut self, tenant_id: &str) -> Option<&mut TenantQueueStats> {
+        self.tenants.get_mut(tenant_id).map(|tenant| &mut tenant.stats)
+    }
+
+    /// Count a request served without queueing, charging its tenant as if
+    /// it had been queued.
+    pub fn grant(&mut self, share: &TenantShare, cost: f64) {
+        self.charge(share, cost);
+        if let Some(stats) = self.tenant(&share.tenant_id) {
+            stats.granted += 1;
+        }
+    }
+
+    /// Queue `item` for `share` at `cost` (e.g. 1 per call, or tokens).
+    pub fn push(&mut self, share: &TenantShare, cost: f64, item: T) -> Ticket {
+        let ticket = Ticket {
+            finish: self.charge(share, cost),
+            seq: self.seq,
+        };
+        self.seq += 1;
+        if let Some(stats) = self.tenant(&share.tenant_id) {
+            stats.queued += 1;
+        }
+        self.entries.insert(
+            ticket,
+            Entry {
+                tenant_id: share.tenant_id.clone(),
+                queued_at: Instant::now(),
+                item,
+            },
+        );
+        ticket
+    }
+
+    /// The next item in fair order.
+    pub fn pop(&mut self) -> Option<Dequeued<T>> {
+        let (ticket, entry) = self.entries.pop_first()?;
+        self.virtual_time = self.virtual_time.max(ticket.finish);
+        let waited = entry.queued_at.elapsed();
+        if let Some(stats) = self.tenant(&entry.tenant_id) {
+            stats.queued -= 1;
+            stats.granted += 1;
+            stats.wait_seconds_total += waited.as_secs_f64();
+            stats.max_wait_seconds = stats.max_wait_seconds.max(waited.as_secs_f64());
+        }
+        Some(Dequeued {
+            tenant_id: entry.tenant_id,
+            item: entry.item,
+            waited,
+        })
+    }
+
+    /// The next item without taking it.
+    pub fn peek(&self) -> Option<&T> {
+        self.entries.first_key_value().map(|(_, entry)| &entry.item)
+    }
+
+    /// Take a request out of the queue unserved, if it is still queued.
+    pub fn remove(&mut self, ticket: Ticket) -> Option<T> {
+        let entry = self.entries.remove(&ticket)?;
+        if let Some(stats) = self.tenant(&entry.tenant_id) {
+            stats.queued -= 1;
+            stats.abandoned += 1;
+        }
+        Some(entry.item)
+    }
+
+    /// Counters of every tenant seen, by tenant ID.
+    pub fn stats(&self) -> Vec<TenantQueueStats> {
+        let mut stats: Vec<TenantQueueStats> = self.tenants.values().map(|t| t.stats.clone()).collect();
+        stats.sort_by(|a, b| a.tenant_id.cmp(&b.tenant_id));
+        stats
+    }
+}
+
+/// Returned when a request gives up waiting for a shared resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueTimeout {
+    pub resource: String,
+    pub waited: Duration,
+}
+
+impl fmt::Display for QueueTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "gave up waiting for {} after {:?}", self.resource, self.waited)
+    }
+}
+
+impl std::error::Error for QueueTimeout {}
+
+struct SchedulerState {
+    in_use: usize,
+    waiting: FairQueue<oneshot::Sender<()>>,
+}
+
+/// A fixed number of slots of a shared resource, handed out to tenants in
+/// weighted fair order.
+pub struct FairScheduler {
+    resource: String,
+    capacity: usize,
+    max_wait: Duration,
+    state: Mutex<SchedulerState>,
+}
+
+impl FairScheduler {
+    /// `capacity` concurrent slots of `resource`, e.g. `llm`
+    pub fn new(resource: impl Into<String>, capacity: usize) -> Self {
+        Self {
+            resource: resource.into(),
+            capacity: capacity.max(1),
+            max_wait: Duration::from_secs(60),
+            state: Mutex::new(SchedulerState {
+                in_use: 0,
+                waiting: FairQueue::new(),
+            }),
+        }
+    }
+
+    /// Give up after waiting `max_wait` (or the request deadline, if earlier)
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    pub fn resource(&self) -> &str {
+        &self.resource
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Slots taken now
+    pub fn in_use(&self) -> usize {
+        self.state.lock().unwrap().in_use
+    }
+
+    /// Wait for a slot on behalf of the current [`TenantShare`]. `cost` is
+    /// how much of the tenant's share the request uses, e.g. 1 per call.
+    pub async fn acquire(self: &Arc<Self>, cost: f64) -> Result<FairPermit, QueueTimeout> {
+        let share = TenantShare::current_or_unattributed();
+        let (ticket, receiver) = {
+            let mut state = self.state.lock().unwrap();
+            if state.in_use < self.capacity && state.waiting.is_empty() {
+                state.in_use += 1;
+                state.waiting.grant(&share, cost);
+                return Ok(self.permit(share.tenant_id));
+            }
+            let (sender, receiver) = oneshot::channel();
+            (state.waiting.push(&share, cost, sender), receiver)
+        };
+
+        let started = Instant::now();
+        let mut waiter = Waiter {
+            scheduler: self,
+            ticket,
+            receiver,
+            granted: false,
+        };
+        match tokio::time::timeout(Deadline::clamp(self.max_wait), &mut waiter.receiver).await {
+            Ok(Ok(())) => {
+                waiter.granted = true;
+                Ok(self.permit(share.tenant_id))
+            }
+            // The sender is only dropped unsent when the queue is, so treat
+            // that like a timeout
+            _ => Err(QueueTimeout {
+                resource: self.resource.clone(),
+                waited: started.elapsed(),
+            }),
+        }
+    }
+
+    fn permit(self: &Arc<Self>, tenant_id: String) -> FairPermit {
+        FairPermit {
+            scheduler: self.clone(),
+            tenant_id,
+        }
+    }
+
+    /// Hand a freed slot to the next waiter, or return it
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        while let Some(next) = state.waiting.pop() {
+            if next.item.send(()).is_ok() {
+                return;
+            }
+        }
+        state.in_use -= 1;
+    }
+
+    /// Per-tenant queue counters
+    pub fn stats(&self) -> Vec<TenantQueueStats> {
+        self.state.lock().unwrap().waiting.stats()
+    }
+
+    /// Render the per-tenant counters in the Prometheus text exposition
+    /// format
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        render_queue_stats(prefix, &self.resource, &self.stats())
+    }
+}
+
+/// Takes a queued request back out when the caller stops waiting.
+struct Waiter<'a> {
+    scheduler: &'a FairScheduler,
+    ticket: Ticket,
+    receiver: oneshot::Receiver<()>,
+    granted: bool,
+}
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        if self.granted {
+            return;
+        }
+        let removed = self.scheduler.state.lock().unwrap().waiting.remove(self.ticket).is_some();
+        if !removed {
+            // Granted a slot while giving up; pass it on
+            self.receiver.close();
+            if self.receiver.try_recv().is_ok() {
+                self.scheduler.release();
+            }
+        }
+    }
+}
+
+/// A slot of a [`FairScheduler`], returned when dropped.
+pub struct FairPermit {
+    scheduler: Arc<FairScheduler>,
+    tenant_id: String,
+}
+
+impl FairPermit {
+    pub fn tenant_id(&self) -> &str {
+        &self.tenant_id
+    }
+}
+
+impl fmt::Debug for FairPermit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FairPermit")
+            .field("resource", &self.scheduler.resource)
+            .field("tenant_id", &self.tenant_id)
+            .finish()
+    }
+}
+
+impl Drop for FairPermit {
+    fn drop(&mut self) {
+        self.scheduler.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weights_from_str() {
+        let weights: FairnessWeights = "free=1, pro=6".parse().unwrap();
+        assert_eq!(weights.weight("pro"), 6.0);
+        assert_eq!(weights.weight("Enterprise"), 8.0);
+        assert_eq!(weights.weight("custom"), 1.0);
+        assert!("pro".parse::<FairnessWeights>().is_err());
+        assert!("pro=0".parse::<FairnessWeights>().is_err());
+    }
+
+    #[test]
+    fn test_queue_serves_tenants_by_weight() {
+        let noisy = TenantShare::new("noisy", 1.0);
+        let pro = TenantShare::new("pro", 2.0);
+        let mut queue = FairQueue::new();
+        for i in 0..6 {
+            queue.push(&noisy, 1.0, format!("noisy {}", i));
+        }
+        for i in 0..3 {
+            queue.push(&pro, 1.0, format!("pro {}", i));
+        }
+
+        // The pro tenant, queued last, is not stuck behind the backlog and
+        // gets two turns for each of the noisy tenant's
+        let order: Vec<String> = std::iter::from_fn(|| queue.pop()).map(|d| d.item).take(6).collect();
+        assert_eq!(order, ["pro 0", "noisy 0", "pro 1", "pro 2", "noisy 1", "noisy 2"]);
+
+        let stats = queue.stats();
+        assert_eq!(stats[0].tenant_id, "noisy");
+        assert_eq!((stats[0].queued, stats[0].granted), (3, 3));
+        assert_eq!((stats[1].queued, stats[1].granted), (0, 3));
+    }
+
+    #[test]
+    fn test_idle_tenants_do_not_bank_credit() {
+        let busy = TenantShare::new("busy", 1.0);
+        let idle = TenantShare::new("idle", 1.0);
+        let mut queue = FairQueue::new();
+        for i in 0..4 {
+            queue.push(&busy, 1.0, format!("busy {}", i));
+        }
+        queue.pop();
+        queue.pop();
+        for i in 0..3 {
+            queue.push(&idle, 1.0, format!("idle {}", i));
+        }
+
+        let order: Vec<String> = std::iter::from_fn(|| queue.pop()).map(|d| d.item).collect();
+        assert_eq!(order, ["busy 2", "idle 0", "busy 3", "idle 1", "idle 2"]);
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_hands_slots_out_fairly() {
+        let scheduler = Arc::new(FairScheduler::new("llm", 1));
+        let held = scheduler.acquire(1.0).await.unwrap();
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut waiting = Vec::new();
+        for (tenant, weight) in [("noisy", 1.0), ("noisy", 1.0), ("noisy", 1.0), ("quiet", 1.0)] {
+            let (scheduler, order) = (scheduler.clone(), order.clone());
+            waiting.push(tokio::spawn(TenantShare::new(tenant, weight).scope(async move {
+                let _permit = scheduler.acquire(1.0).await.unwrap();
+                order.lock().unwrap().push(tenant);
+            })));
+            tokio::task::yield_now().await;
+        }
+        drop(held);
+        for task in waiting {
+            task.await.unwrap();
+        }
+
+        assert_eq!(*order.lock().unwrap(), ["noisy", "quiet", "noisy", "noisy"]);
+        assert_eq!(scheduler.in_use(), 0);
+        let rendered = scheduler.render_prometheus("copilot");
+        assert!(rendered.contains("copilot_fair_queue_granted_total{resource=\"llm\",tenant=\"noisy\"} 3"));
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_gives_up_at_the_deadline() {
+        let scheduler = Arc::new(FairScheduler::new("sandbox", 1).with_max_wait(Duration::from_secs(10)));
+        let held = scheduler.acquire(1.0).await.unwrap();
+
+        let err = Deadline::after(Duration::from_millis(20))
+            .scope(TenantShare::new("acme", 1.0).scope(scheduler.acquire(1.0)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.resource, "sandbox");
+
+        // The abandoned request no longer holds a place in the queue
+        let acme = scheduler.stats().into_iter().find(|s| s.tenant_id == "acme").unwrap();
+        assert_eq!((acme.queued, acme.abandoned), (0, 1));
+        drop(held);
+        assert_eq!(scheduler.in_use(), 0);
+        let _again = scheduler.acquire(1.0).await.unwrap();
+    }
+}
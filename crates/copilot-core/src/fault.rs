@@ -0,0 +1,387 @@
+//! Fault injection for resilience testing.
+//!
+//! A [`FaultInjector`] holds rules that add latency, fail calls or drop
+//! connections for a share of the calls to matching targets. Targets are
+//! `<component>/<endpoint>` strings such as `router/api/v1/route` or
+//! `workflow/deploy`, and a rule's pattern may use `*` wildcards.
+//!
+//! Adapters, resilience policies and workflow steps consult
+//! [`FaultInjector::global`] before each call, so circuit breakers, retries
+//! and failure handling can be exercised against the failures they exist
+//! for. Nothing is injected until rules are installed, e.g. with
+//! `copilot-server --fault-injection` in a test environment. The injector
+//! draws from [`Determinism::rng`], so a seeded run injects the same faults
+//! in the same order.
+
+use crate::determinism::Determinism;
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Duration;
+
+/// Environment variable holding the rules of [`FaultInjector::global`].
+pub const FAULTS_ENV_VAR: &str = "COPILOT_FAULTS";
+
+/// Faults injected into calls to the targets matching one pattern.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FaultRule {
+    /// Target pattern; `*` matches any run of characters
+    pub target: String,
+    /// Delay added before the call, in milliseconds
+    pub latency_ms: u64,
+    /// Share of calls that are delayed, from 0 to 1
+    pub latency_rate: f64,
+    /// Share of calls that fail as if the service returned an error
+    pub error_rate: f64,
+    /// Share of calls whose connection is dropped
+    pub drop_rate: f64,
+}
+
+impl Default for FaultRule {
+    fn default() -> Self {
+        Self {
+            target: "*".to_string(),
+            latency_ms: 0,
+            latency_rate: 1.0,
+            error_rate: 0.0,
+            drop_rate: 0.0,
+        }
+    }
+}
+
+impl FaultRule {
+    /// A rule for `target` that injects nothing until configured.
+    pub fn new(target: impl Into<String>) -> Self {
+        Self {
+            target: target.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Delay `rate` of the calls by `latency`.
+    pub fn with_latency(mut self, latency: Duration, rate: f64) -> Self {
+        self.latency_ms = latency.as_millis() as u64;
+        self.latency_rate = rate;
+        self
+    }
+
+    /// Fail `rate` of the calls.
+    pub fn with_error_rate(mut self, rate: f64) -> Self {
+        self.error_rate = rate;
+        self
+    }
+
+    /// Drop the connection of `rate` of the calls.
+    pub fn with_drop_rate(mut self, rate: f64) -> Self {
+        self.drop_rate = rate;
+        self
+    }
+
+    /// Check that the target is set and every rate is between 0 and 1.
+    pub fn validate(&self) -> Result<(), FaultSpecError> {
+        if self.target.is_empty() {
+            return Err(FaultSpecError("with an empty target".to_string()));
+        }
+        let rates = [self.latency_rate, self.error_rate, self.drop_rate];
+        if rates.iter().any(|rate| !(0.0..=1.0).contains(rate)) {
+            return Err(FaultSpecError(format!("{}: rates must be between 0 and 1", self.target)));
+        }
+        Ok(())
+    }
+
+    /// Whether the rule applies to `target`.
+    pub fn matches(&self, target: &str) -> bool {
+        wildcard_match(self.target.as_bytes(), target.as_bytes())
+    }
+
+    /// Parse `;`-separated rules, e.g.
+    /// `router/*:latency=200ms@50%,error=10%;incident/*:drop=5%`.
+    pub fn parse_list(spec: &str) -> Result<Vec<Self>, FaultSpecError> {
+        spec.split(';')
+            .map(str::trim)
+            .filter(|rule| !rule.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+}
+
+impl FromStr for FaultRule {
+    type Err = FaultSpecError;
+
+    /// `<target>:<effect>,<effect>...` where an effect is
+    /// `latency=<ms|Ns|Nms>[@<rate>]`, `error=<rate>` or `drop=<rate>`, and a
+    /// rate is a fraction (`0.1`) or a percentage (`10%`).
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| FaultSpecError(format!("{spec}: {reason}"));
+        let (target, effects) = spec
+            .rsplit_once(':')
+            .ok_or_else(|| invalid("expected <target>:<effect>,..."))?;
+        let target = target.trim();
+        if target.is_empty() {
+            return Err(invalid("empty target"));
+        }
+
+        let mut rule = Self::new(target);
+        for effect in effects.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (kind, value) = effect
+                .split_once('=')
+                .ok_or_else(|| invalid("expected <effect>=<value>"))?;
+            let rate = |value| parse_rate(value).ok_or_else(|| invalid("invalid rate"));
+            match kind.trim() {
+                "latency" => {
+                    let (latency, latency_rate) = match value.split_once('@') {
+                        Some((latency, latency_rate)) => (latency, rate(latency_rate)?),
+                        None => (value, 1.0),
+                    };
+                    let latency = parse_latency(latency).ok_or_else(|| invalid("invalid latency"))?;
+                    rule = rule.with_latency(latency, latency_rate);
+                }
+                "error" => rule.error_rate = rate(value)?,
+                "drop" => rule.drop_rate = rate(value)?,
+                other => return Err(invalid(&format!("unknown effect '{other}'"))),
+            }
+        }
+        Ok(rule)
+    }
+}
+
+/// A fault specification that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid fault rule {0}")]
+pub struct FaultSpecError(String);
+
+/// A failure injected into a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fault {
+    /// The call fails as if the service returned an error
+    Error,
+    /// The connection is dropped before a response arrives
+    Drop,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::Error => write!(f, "injected error"),
+            Fault::Drop => write!(f, "injected connection drop"),
+        }
+    }
+}
+
+/// What to do to one call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Injection {
+    /// Wait this long before making the call
+    pub delay: Option<Duration>,
+    /// Fail the call instead of making it
+    pub fault: Option<Fault>,
+}
+
+/// Counts of injected faults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaultStats {
+    pub delayed: u64,
+    pub errors: u64,
+    pub drops: u64,
+}
+
+/// Decides which calls fail, are dropped or slowed down.
+#[derive(Debug)]
+pub struct FaultInjector {
+    rules: RwLock<Vec<FaultRule>>,
+    rng: Mutex<StdRng>,
+    delayed: AtomicU64,
+    errors: AtomicU64,
+    drops: AtomicU64,
+}
+
+impl Default for FaultInjector {
+    fn default() -> Self {
+        Self::new(&Determinism::system())
+    }
+}
+
+impl FaultInjector {
+    /// An injector without rules, drawing from `determinism`.
+    pub fn new(determinism: &Determinism) -> Self {
+        Self {
+            rules: RwLock::new(Vec::new()),
+            rng: Mutex::new(determinism.rng("faults")),
+            delayed: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            drops: AtomicU64::new(0),
+        }
+    }
+
+    /// An injector with `rules` installed.
+    pub fn with_rules(self, rules: Vec<FaultRule>) -> Self {
+        self.set_rules(rules);
+        self
+    }
+
+    /// The process-wide injector used by adapters, resilience policies and
+    /// workflow steps. It starts with the rules in `COPILOT_FAULTS`, if any.
+    pub fn global() -> Arc<FaultInjector> {
+        static GLOBAL: OnceLock<Arc<FaultInjector>> = OnceLock::new();
+        GLOBAL
+            .get_or_init(|| {
+                let rules = std::env::var(FAULTS_ENV_VAR)
+                    .ok()
+                    .and_then(|spec| FaultRule::parse_list(&spec).ok())
+                    .unwrap_or_default();
+                Arc::new(FaultInjector::new(&Determinism::from_env()).with_rules(rules))
+            })
+            .clone()
+    }
+
+    /// Replace the rules; the first rule matching a target applies.
+    pub fn set_rules(&self, rules: Vec<FaultRule>) {
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = rules;
+    }
+
+    /// Remove all rules, turning injection off.
+    pub fn clear(&self) {
+        self.set_rules(Vec::new());
+    }
+
+    pub fn rules(&self) -> Vec<FaultRule> {
+        self.rules.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.rules.read().unwrap_or_else(|e| e.into_inner()).is_empty()
+    }
+
+    /// Decide what happens to the next call to `target`.
+    pub fn roll(&self, target: &str) -> Injection {
+        let rules = self.rules.read().unwrap_or_else(|e| e.into_inner());
+        let Some(rule) = rules.iter().find(|rule| rule.matches(target)) else {
+            return Injection::default();
+        };
+
+        let mut rng = self.rng.lock().unwrap_or_else(|e| e.into_inner());
+        let mut injection = Injection::default();
+        if rule.latency_ms > 0 && rng.gen_bool(rule.latency_rate.clamp(0.0, 1.0)) {
+            injection.delay = Some(Duration::from_millis(rule.latency_ms));
+            self.delayed.fetch_add(1, Ordering::Relaxed);
+        }
+        if rng.gen_bool(rule.drop_rate.clamp(0.0, 1.0)) {
+            injection.fault = Some(Fault::Drop);
+            self.drops.fetch_add(1, Ordering::Relaxed);
+        } else if rng.gen_bool(rule.error_rate.clamp(0.0, 1.0)) {
+            injection.fault = Some(Fault::Error);
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        injection
+    }
+
+    pub fn stats(&self) -> FaultStats {
+        FaultStats {
+            delayed: self.delayed.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            drops: self.drops.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A rate as a fraction (`0.25`) or a percentage (`25%`), between 0 and 1.
+fn parse_rate(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let rate = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().ok()? / 100.0,
+        None => value.parse::<f64>().ok()?,
+    };
+    (0.0..=1.0).contains(&rate).then_some(rate)
+}
+
+/// A latency in milliseconds (`200`, `200ms`) or seconds (`2s`).
+fn parse_latency(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Some(ms) = value.strip_suffix("ms") {
+        ms.trim().parse().ok().map(Duration::from_millis)
+    } else if let Some(secs) = value.strip_suffix('s') {
+        secs.trim().parse::<f64>().ok().filter(|s| *s >= 0.0).map(Duration::from_secs_f64)
+    } else {
+        value.parse().ok().map(Duration::from_millis)
+    }
+}
+
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| wildcard_match(rest, &text[skip..])),
+        Some((c, rest)) => text.first() == Some(c) && wildcard_match(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = FaultRule::parse_list(
+            "router/*:latency=200ms@50%,error=10%; incident/api/v2/*:drop=0.05 ;workflow/deploy:latency=2s",
+        )
+        .unwrap();
+
+        assert_eq!(
+            rules,
+            vec![
+                FaultRule::new("router/*")
+                    .with_latency(Duration::from_millis(200), 0.5)
+                    .with_error_rate(0.1),
+                FaultRule::new("incident/api/v2/*").with_drop_rate(0.05),
+                FaultRule::new("workflow/deploy").with_latency(Duration::from_secs(2), 1.0),
+            ]
+        );
+        assert!("router/*".parse::<FaultRule>().is_err());
+        assert!("router/*:error=150%".parse::<FaultRule>().is_err());
+        assert!("router/*:timeout=1".parse::<FaultRule>().is_err());
+        assert!(":error=1".parse::<FaultRule>().is_err());
+        assert!(rules.iter().all(|rule| rule.validate().is_ok()));
+        assert!(FaultRule::new("*").with_error_rate(1.5).validate().is_err());
+    }
+
+    #[test]
+    fn test_first_matching_rule_applies() {
+        let faults = FaultInjector::new(&Determinism::seeded(1)).with_rules(vec![
+            FaultRule::new("router/api/v1/route").with_drop_rate(1.0),
+            FaultRule::new("router/*").with_error_rate(1.0),
+            FaultRule::new("*/health").with_latency(Duration::from_millis(50), 1.0),
+        ]);
+
+        assert_eq!(faults.roll("router/api/v1/route").fault, Some(Fault::Drop));
+        assert_eq!(faults.roll("router/api/v1/models").fault, Some(Fault::Error));
+        assert_eq!(
+            faults.roll("incident/health"),
+            Injection { delay: Some(Duration::from_millis(50)), fault: None }
+        );
+        assert_eq!(faults.roll("incident/api/v2/incidents"), Injection::default());
+        assert_eq!(faults.stats(), FaultStats { delayed: 1, errors: 1, drops: 1 });
+
+        faults.clear();
+        assert!(!faults.is_enabled());
+        assert_eq!(faults.roll("router/api/v1/route"), Injection::default());
+    }
+
+    #[test]
+    fn test_rates_are_reproducible_with_a_seed() {
+        let rules = vec![FaultRule::new("*").with_error_rate(0.3)];
+        let outcomes = |seed| {
+            let faults = FaultInjector::new(&Determinism::seeded(seed)).with_rules(rules.clone());
+            (0..1000).map(|_| faults.roll("router/api").fault.is_some()).collect::<Vec<_>>()
+        };
+
+        let first = outcomes(5);
+        assert_eq!(first, outcomes(5));
+        let failed = first.iter().filter(|failed| **failed).count();
+        assert!((240..360).contains(&failed), "{failed} of 1000 calls failed");
+    }
+}
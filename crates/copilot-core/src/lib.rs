@@ -1,7 +1,15 @@
+pub mod access;
 pub mod cache;
 pub mod config;
+pub mod deadline;
+pub mod determinism;
 pub mod error;
+pub mod fairness;
 pub mod events;
+pub mod fault;
+pub mod privacy;
+pub mod residency;
+pub mod sandbox;
 pub mod traits;
 pub mod types;
 
@@ -9,6 +17,17 @@ pub mod types;
 pub use config::*;
 pub use error::*;
 pub use types::*;
+pub use access::{Acl, Principal, ACL_KEY};
+pub use deadline::{Deadline, DeadlineExceeded, DEADLINE_HEADER};
+pub use fairness::{FairPermit, FairQueue, FairScheduler, FairnessWeights, QueueTimeout, TenantQueueStats, TenantShare};
+pub use determinism::{Clock, Determinism, FrozenClock, IdGenerator, Stopwatch, SystemClock};
+pub use fault::{Fault, FaultInjector, FaultRule, Injection};
+pub use privacy::{PromptLogPolicy, PromptLogging};
+pub use residency::{
+    DataResidency, OutOfRegion, RegionRouter, ResidencyLog, ResidencyMode, ResidencyOperation, ResidencyPolicy,
+    ResidencyReport, ResidencyViolation, TenantRegion, REGION_KEY,
+};
+pub use sandbox::{SandboxPolicy, PACKAGE_REGISTRIES};
 
 // Re-export cache module items (simpler API)
 pub use cache::Cache as SimpleCache;
@@ -17,4 +36,6 @@ pub use cache::Cache as SimpleCache;
 pub use events::{Event, EventPublisher as EventPublisherSimple, EventSubscriber};
 
 // Re-export traits module items (more comprehensive interfaces)
-pub use traits::{Cache, EventPublisher, HealthCheck, HealthStatus, Repository, Transaction};
+pub use traits::{
+    Cache, DedupStore, EventPublisher, HealthCheck, HealthStatus, LeaseStore, Repository, Transaction,
+};
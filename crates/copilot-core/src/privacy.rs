@@ -0,0 +1,192 @@
+//! Privacy modes for logging prompts and responses.
+//!
+//! Server logs, analytics events and audit records all pass prompt and
+//! response text through a [`PromptLogging`] mode, so a tenant that disables
+//! content retention gets the same treatment everywhere while lengths,
+//! token counts and latencies are still recorded.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// How much of a prompt or response may be retained.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptLogging {
+    /// Keep the text verbatim
+    #[default]
+    Full,
+    /// Keep a SHA-256 fingerprint of the text, enough to spot repeats
+    Hashed,
+    /// Keep only metadata such as length and token counts
+    MetadataOnly,
+    /// Keep no per-prompt record at all
+    Off,
+}
+
+impl PromptLogging {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Hashed => "hashed",
+            Self::MetadataOnly => "metadata_only",
+            Self::Off => "off",
+        }
+    }
+
+    /// The form of `content` that may be retained, if any.
+    pub fn redact(&self, content: &str) -> Option<String> {
+        match self {
+            Self::Full => Some(content.to_string()),
+            Self::Hashed => Some(fingerprint(content)),
+            Self::MetadataOnly | Self::Off => None,
+        }
+    }
+
+    /// Whether a per-prompt record (with or without content) may be kept.
+    pub fn records_prompts(&self) -> bool {
+        !matches!(self, Self::Off)
+    }
+
+    /// Fields describing `content` in a structured record: the retained
+    /// form under `key` and its length under `{key}_chars`.
+    pub fn fields(&self, key: &str, content: &str) -> HashMap<String, serde_json::Value> {
+        let mut fields = HashMap::new();
+        if !self.records_prompts() {
+            return fields;
+        }
+        if let Some(retained) = self.redact(content) {
+            fields.insert(key.to_string(), retained.into());
+        }
+        fields.insert(format!("{}_chars", key), content.chars().count().into());
+        fields
+    }
+}
+
+impl fmt::Display for PromptLogging {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PromptLogging {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "full" => Ok(Self::Full),
+            "hashed" => Ok(Self::Hashed),
+            "metadata_only" | "metadata" => Ok(Self::MetadataOnly),
+            "off" | "none" => Ok(Self::Off),
+            other => Err(format!("unknown prompt logging mode: {}", other)),
+        }
+    }
+}
+
+/// `sha256:`-prefixed hex digest of `content`.
+pub fn fingerprint(content: &str) -> String {
+    format!("sha256:{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Prompt logging modes per tenant, with a default for everyone else.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptLogPolicy {
+    /// Mode for tenants without an override
+    #[serde(default)]
+    pub default: PromptLogging,
+    /// Per-tenant overrides
+    #[serde(default)]
+    pub tenants: HashMap<String, PromptLogging>,
+}
+
+impl PromptLogPolicy {
+    pub fn new(default: PromptLogging) -> Self {
+        Self {
+            default,
+            tenants: HashMap::new(),
+        }
+    }
+
+    pub fn with_tenant(mut self, tenant_id: impl Into<String>, mode: PromptLogging) -> Self {
+        self.tenants.insert(tenant_id.into(), mode);
+        self
+    }
+
+    /// Override tenants' modes from `tenant=mode` entries, e.g. `acme=off`.
+    pub fn with_entries<S: AsRef<str>>(mut self, entries: &[S]) -> Result<Self, String> {
+        for entry in entries {
+            let entry = entry.as_ref();
+            let (tenant, mode) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("{} is not tenant=mode", entry))?;
+            let tenant = tenant.trim();
+            if tenant.is_empty() {
+                return Err(format!("{} has no tenant", entry));
+            }
+            let mode = mode.trim().parse().map_err(|e| format!("{}: {}", tenant, e))?;
+            self = self.with_tenant(tenant, mode);
+        }
+        Ok(self)
+    }
+
+    /// The mode that applies to `tenant_id`.
+    pub fn for_tenant(&self, tenant_id: &str) -> PromptLogging {
+        self.tenants.get(tenant_id).copied().unwrap_or(self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_by_mode() {
+        let prompt = "deploy the payments service";
+        assert_eq!(PromptLogging::Full.redact(prompt).as_deref(), Some(prompt));
+
+        let hashed = PromptLogging::Hashed.redact(prompt).unwrap();
+        assert!(hashed.starts_with("sha256:"));
+        assert_eq!(hashed.len(), "sha256:".len() + 64);
+        assert_eq!(Some(hashed), PromptLogging::Hashed.redact(prompt));
+
+        assert_eq!(PromptLogging::MetadataOnly.redact(prompt), None);
+        assert_eq!(PromptLogging::Off.redact(prompt), None);
+        assert!(PromptLogging::MetadataOnly.records_prompts());
+        assert!(!PromptLogging::Off.records_prompts());
+    }
+
+    #[test]
+    fn test_fields_by_mode() {
+        let full = PromptLogging::Full.fields("prompt", "hello");
+        assert_eq!(full["prompt"], "hello");
+        assert_eq!(full["prompt_chars"], 5);
+
+        let metadata = PromptLogging::MetadataOnly.fields("prompt", "hello");
+        assert!(!metadata.contains_key("prompt"));
+        assert_eq!(metadata["prompt_chars"], 5);
+
+        assert!(PromptLogging::Off.fields("prompt", "hello").is_empty());
+    }
+
+    #[test]
+    fn test_parse_and_policy() {
+        assert_eq!("metadata-only".parse(), Ok(PromptLogging::MetadataOnly));
+        assert_eq!("OFF".parse(), Ok(PromptLogging::Off));
+        assert!("verbose".parse::<PromptLogging>().is_err());
+
+        let policy = PromptLogPolicy::new(PromptLogging::Hashed)
+            .with_tenant("acme", PromptLogging::Off);
+        assert_eq!(policy.for_tenant("acme"), PromptLogging::Off);
+        assert_eq!(policy.for_tenant("other"), PromptLogging::Hashed);
+
+        let policy = PromptLogPolicy::default()
+            .with_entries(&["acme=off", " globex = hashed"])
+            .unwrap();
+        assert_eq!(policy.for_tenant("acme"), PromptLogging::Off);
+        assert_eq!(policy.for_tenant("globex"), PromptLogging::Hashed);
+        assert!(PromptLogPolicy::default().with_entries(&["acme"]).is_err());
+        assert!(PromptLogPolicy::default().with_entries(&["acme=verbose"]).is_err());
+    }
+}
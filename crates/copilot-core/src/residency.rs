@@ -0,0 +1,504 @@
+//! Data residency.
+//!
+//! Each tenant has a home region and a [`ResidencyMode`]. Context written
+//! on a tenant's behalf is tagged with the region it was written in (the
+//! [`REGION_KEY`] metadata field), and storage operations are routed to the
+//! endpoints of the tenant's region through a [`RegionRouter`]. A `strict`
+//! tenant's data must stay in its region: retrieving items tagged with
+//! another region is refused, as is writing to a store outside the region.
+//! Every refusal is a [`ResidencyViolation`], logged and kept in a
+//! [`ResidencyLog`] for governance reports.
+//!
+//! The residency of the tenant a task works for is task-local, set for each
+//! API request in the same way as [`crate::Deadline`]; code outside any
+//! scope is not fenced.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Metadata key holding the region data was written in.
+pub const REGION_KEY: &str = "region";
+
+/// Region of a deployment that does not configure one.
+pub const DEFAULT_REGION: &str = "default";
+
+/// Violations kept by a [`ResidencyLog`] unless configured otherwise.
+const DEFAULT_LOG_CAPACITY: usize = 1000;
+
+tokio::task_local! {
+    static CURRENT: DataResidency;
+}
+
+/// Whether a tenant's data may leave its region.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResidencyMode {
+    /// Data is written in the home region but may be read from anywhere
+    #[default]
+    Unrestricted,
+    /// Data is only read from and written to the home region
+    Strict,
+}
+
+impl ResidencyMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResidencyMode::Unrestricted => "unrestricted",
+            ResidencyMode::Strict => "strict",
+        }
+    }
+}
+
+impl FromStr for ResidencyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "unrestricted" => Ok(ResidencyMode::Unrestricted),
+            "strict" => Ok(ResidencyMode::Strict),
+            other => Err(format!("unknown residency mode '{}' (unrestricted or strict)", other)),
+        }
+    }
+}
+
+/// A tenant's home region and mode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantRegion {
+    pub region: String,
+    #[serde(default)]
+    pub mode: ResidencyMode,
+}
+
+impl FromStr for TenantRegion {
+    type Err = String;
+
+    /// `region` or `region:mode`, e.g. `eu:strict`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (region, mode) = match s.split_once(':') {
+            Some((region, mode)) => (region, mode.parse()?),
+            None => (s, ResidencyMode::default()),
+        };
+        let region = region.trim().to_lowercase();
+        if region.is_empty() {
+            return Err("region is empty".to_string());
+        }
+        Ok(Self { region, mode })
+    }
+}
+
+/// The residency of the tenant a task works for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataResidency {
+    pub tenant_id: String,
+    pub region: String,
+    pub mode: ResidencyMode,
+    /// Region untagged data is taken to be in: the deployment's default,
+    /// where everything lived before data was tagged
+    pub default_region: String,
+}
+
+impl DataResidency {
+    /// Whether data in `data_region` (untagged when `None`) may be used.
+    pub fn permits(&self, data_region: Option<&str>) -> bool {
+        let data_region = data_region.unwrap_or(&self.default_region);
+        self.mode == ResidencyMode::Unrestricted || data_region.eq_ignore_ascii_case(&self.region)
+    }
+
+    /// Run `f` on behalf of this tenant.
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        CURRENT.scope(self, f).await
+    }
+
+    /// The residency of the running task, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+
+    /// A violation by this tenant of `operation` on `resource` in
+    /// `data_region`.
+    pub fn violation(
+        &self,
+        operation: ResidencyOperation,
+        resource: impl Into<String>,
+        data_region: Option<&str>,
+    ) -> ResidencyViolation {
+        ResidencyViolation {
+            at: Utc::now(),
+            tenant_id: self.tenant_id.clone(),
+            home_region: self.region.clone(),
+            data_region: data_region.unwrap_or(&self.default_region).to_string(),
+            operation,
+            resource: resource.into(),
+        }
+    }
+}
+
+/// Home regions per tenant, with the deployment's default region for
+/// everyone else.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResidencyPolicy {
+    pub default_region: String,
+    #[serde(default)]
+    pub tenants: HashMap<String, TenantRegion>,
+}
+
+impl Default for ResidencyPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_REGION)
+    }
+}
+
+impl ResidencyPolicy {
+    pub fn new(default_region: impl Into<String>) -> Self {
+        Self {
+            default_region: default_region.into().to_lowercase(),
+            tenants: HashMap::new(),
+        }
+    }
+
+    /// Pin `tenant_id` to a region.
+    pub fn with_tenant(mut self, tenant_id: impl Into<String>, region: TenantRegion) -> Self {
+        self.tenants.insert(tenant_id.into(), region);
+        self
+    }
+
+    /// Pin tenants from `tenant=region[:mode]` entries, e.g.
+    /// `acme=eu:strict`.
+    pub fn with_entries<S: AsRef<str>>(mut self, entries: &[S]) -> Result<Self, String> {
+        for entry in entries {
+            let entry = entry.as_ref();
+            let (tenant, region) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("{} is not tenant=region[:mode]", entry))?;
+            let tenant = tenant.trim();
+            if tenant.is_empty() {
+                return Err(format!("{} has no tenant", entry));
+            }
+            let region = region.parse().map_err(|e| format!("{}: {}", tenant, e))?;
+            self = self.with_tenant(tenant, region);
+        }
+        Ok(self)
+    }
+
+    /// The residency that applies to `tenant_id`.
+    pub fn for_tenant(&self, tenant_id: &str) -> DataResidency {
+        let (region, mode) = match self.tenants.get(tenant_id) {
+            Some(pin) => (pin.region.clone(), pin.mode),
+            None => (self.default_region.clone(), ResidencyMode::Unrestricted),
+        };
+        DataResidency {
+            tenant_id: tenant_id.to_string(),
+            region,
+            mode,
+            default_region: self.default_region.clone(),
+        }
+    }
+}
+
+/// Returned when no store of a `strict` tenant's region is configured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutOfRegion {
+    pub region: String,
+}
+
+impl fmt::Display for OutOfRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no storage endpoint in region {}, and data may not leave it", self.region)
+    }
+}
+
+impl std::error::Error for OutOfRegion {}
+
+/// Storage endpoints by region, with a default endpoint for the rest.
+pub struct RegionRouter<T> {
+    default: Option<Arc<T>>,
+    regions: BTreeMap<String, Arc<T>>,
+}
+
+impl<T> Default for RegionRouter<T> {
+    fn default() -> Self {
+        Self {
+            default: None,
+            regions: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> Clone for RegionRouter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            default: self.default.clone(),
+            regions: self.regions.clone(),
+        }
+    }
+}
+
+impl<T> RegionRouter<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Route the default region, and regions without an endpoint of their
+    /// own, to `endpoint`
+    pub fn with_default(mut self, endpoint: Arc<T>) -> Self {
+        self.default = Some(endpoint);
+        self
+    }
+
+    pub fn with_region(mut self, region: impl Into<String>, endpoint: Arc<T>) -> Self {
+        self.regions.insert(region.into().to_lowercase(), endpoint);
+        self
+    }
+
+    /// Regions with an endpoint of their own
+    pub fn regions(&self) -> impl Iterator<Item = &str> {
+        self.regions.keys().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.default.is_none() && self.regions.is_empty()
+    }
+
+    /// The endpoint `residency`'s data is stored at: its region's, or the
+    /// default one unless that would move a `strict` tenant's data out of
+    /// its region. `None` when nothing is configured.
+    pub fn route(&self, residency: &DataResidency) -> Result<Option<&Arc<T>>, OutOfRegion> {
+        if let Some(endpoint) = self.regions.get(&residency.region.to_lowercase()) {
+            return Ok(Some(endpoint));
+        }
+        let in_default_region = residency.region.eq_ignore_ascii_case(&residency.default_region);
+        if self.default.is_some() && residency.mode == ResidencyMode::Strict && !in_default_region {
+            return Err(OutOfRegion {
+                region: residency.region.clone(),
+            });
+        }
+        Ok(self.default.as_ref())
+    }
+}
+
+/// What was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResidencyOperation {
+    /// Context retrieved from another region
+    Retrieve,
+    /// Data written to a store outside the region
+    Write,
+    /// Data read from a store outside the region
+    Read,
+}
+
+impl ResidencyOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResidencyOperation::Retrieve => "retrieve",
+            ResidencyOperation::Write => "write",
+            ResidencyOperation::Read => "read",
+        }
+    }
+}
+
+/// A cross-region operation refused by a tenant's residency policy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResidencyViolation {
+    pub at: DateTime<Utc>,
+    pub tenant_id: String,
+    pub home_region: String,
+    pub data_region: String,
+    pub operation: ResidencyOperation,
+    /// What was refused, e.g. the source of a context item or an object key
+    pub resource: String,
+}
+
+/// A tenant's residency and the violations recorded for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResidencyReport {
+    pub tenant_id: String,
+    pub region: String,
+    pub mode: ResidencyMode,
+    /// Violations since the server started
+    pub violations: u64,
+    /// Violations by the region the data was in
+    pub by_data_region: BTreeMap<String, u64>,
+    /// Violations by operation
+    pub by_operation: BTreeMap<ResidencyOperation, u64>,
+    /// Most recent violations kept, newest first
+    pub recent: Vec<ResidencyViolation>,
+}
+
+#[derive(Default)]
+struct Counts {
+    total: u64,
+    by_data_region: BTreeMap<String, u64>,
+    by_operation: BTreeMap<ResidencyOperation, u64>,
+}
+
+#[derive(Default)]
+struct LogState {
+    recent: VecDeque<ResidencyViolation>,
+    tenants: HashMap<String, Counts>,
+}
+
+/// Recent residency violations and per-tenant counts.
+pub struct ResidencyLog {
+    capacity: usize,
+    state: Mutex<LogState>,
+}
+
+impl Default for ResidencyLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResidencyLog {
+    pub fn new() -> Self {
+        Self {
+            capacity: DEFAULT_LOG_CAPACITY,
+            state: Mutex::new(LogState::default()),
+        }
+    }
+
+    /// Keep at most `capacity` violations; counts cover all of them
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    pub fn record(&self, violation: ResidencyViolation) {
+        warn!(
+            tenant_id = %violation.tenant_id,
+            home_region = %violation.home_region,
+            data_region = %violation.data_region,
+            operation = violation.operation.as_str(),
+            resource = %violation.resource,
+            "Refused cross-region data access"
+        );
+        let mut state = self.state.lock().unwrap();
+        let counts = state.tenants.entry(violation.tenant_id.clone()).or_default();
+        counts.total += 1;
+        *counts.by_data_region.entry(violation.data_region.clone()).or_default() += 1;
+        *counts.by_operation.entry(violation.operation).or_default() += 1;
+        if state.recent.len() == self.capacity {
+            state.recent.pop_front();
+        }
+        state.recent.push_back(violation);
+    }
+
+    /// Violations of all tenants since the server started
+    pub fn total(&self) -> u64 {
+        self.state.lock().unwrap().tenants.values().map(|c| c.total).sum()
+    }
+
+    /// `residency`'s tenant's violations, with up to `limit` recent ones
+    pub fn report(&self, residency: &DataResidency, limit: usize) -> ResidencyReport {
+        let state = self.state.lock().unwrap();
+        let counts = state.tenants.get(&residency.tenant_id);
+        ResidencyReport {
+            tenant_id: residency.tenant_id.clone(),
+            region: residency.region.clone(),
+            mode: residency.mode,
+            violations: counts.map_or(0, |c| c.total),
+            by_data_region: counts.map(|c| c.by_data_region.clone()).unwrap_or_default(),
+            by_operation: counts.map(|c| c.by_operation.clone()).unwrap_or_default(),
+            recent: state
+                .recent
+                .iter()
+                .rev()
+                .filter(|v| v.tenant_id == residency.tenant_id)
+                .take(limit)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ResidencyPolicy {
+        ResidencyPolicy::new("us")
+            .with_entries(&["acme=eu:strict", "globex=EU"])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_policy_entries() {
+        let policy = policy();
+        let acme = policy.for_tenant("acme");
+        assert_eq!((acme.region.as_str(), acme.mode), ("eu", ResidencyMode::Strict));
+        assert_eq!(policy.for_tenant("globex").mode, ResidencyMode::Unrestricted);
+        assert_eq!(policy.for_tenant("initech").region, "us");
+
+        assert!(ResidencyPolicy::default().with_entries(&["acme"]).is_err());
+        assert!(ResidencyPolicy::default().with_entries(&["acme=eu:sometimes"]).is_err());
+        assert!(ResidencyPolicy::default().with_entries(&["=eu"]).is_err());
+    }
+
+    #[test]
+    fn test_strict_tenants_only_use_their_region() {
+        let policy = policy();
+        let acme = policy.for_tenant("acme");
+        assert!(acme.permits(Some("eu")));
+        assert!(!acme.permits(Some("us")));
+        // Untagged data is in the default region
+        assert!(!acme.permits(None));
+
+        let globex = policy.for_tenant("globex");
+        assert!(globex.permits(Some("us")));
+    }
+
+    #[test]
+    fn test_router_keeps_strict_data_in_region() {
+        let router = RegionRouter::new()
+            .with_default(Arc::new("s3://us"))
+            .with_region("ap", Arc::new("s3://ap"));
+        let policy = policy().with_tenant("hooli", "ap:strict".parse().unwrap());
+
+        assert_eq!(**router.route(&policy.for_tenant("hooli")).unwrap().unwrap(), "s3://ap");
+        assert_eq!(**router.route(&policy.for_tenant("globex")).unwrap().unwrap(), "s3://us");
+        assert_eq!(**router.route(&policy.for_tenant("initech")).unwrap().unwrap(), "s3://us");
+        assert_eq!(
+            router.route(&policy.for_tenant("acme")).unwrap_err(),
+            OutOfRegion { region: "eu".to_string() }
+        );
+
+        let unconfigured: RegionRouter<&str> = RegionRouter::new();
+        assert!(unconfigured.route(&policy.for_tenant("acme")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_log_reports_per_tenant() {
+        let policy = policy();
+        let acme = policy.for_tenant("acme");
+        let log = ResidencyLog::new().with_capacity(2);
+        log.record(acme.violation(ResidencyOperation::Retrieve, "file:///us/runbook.md", Some("us")));
+        log.record(acme.violation(ResidencyOperation::Write, "attachments/acme", None));
+        log.record(policy.for_tenant("globex").violation(ResidencyOperation::Read, "exports/globex", Some("ap")));
+
+        let report = log.report(&acme, 10);
+        assert_eq!(report.violations, 2);
+        assert_eq!(report.by_data_region["us"], 2);
+        assert_eq!(report.by_operation[&ResidencyOperation::Retrieve], 1);
+        // The oldest violation fell out of the log but is still counted
+        assert_eq!(report.recent.len(), 1);
+        assert_eq!(report.recent[0].operation, ResidencyOperation::Write);
+        assert_eq!(log.total(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_scope() {
+        assert!(DataResidency::current().is_none());
+        let acme = policy().for_tenant("acme");
+        acme.clone()
+            .scope(async move { assert_eq!(DataResidency::current(), Some(acme)) })
+            .await;
+    }
+}
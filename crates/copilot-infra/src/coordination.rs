@@ -0,0 +1,225 @@
+//! Distributed leases for leader election
+//!
+//! Two [`LeaseStore`] backends are provided:
+//! - [`RedisLeaseStore`] keeps each lease as a key with a TTL, owned by the
+//!   holder that set it.
+//! - [`PgAdvisoryLeaseStore`] takes a Postgres session advisory lock on a
+//!   dedicated connection. The lock lives as long as that connection, so
+//!   failover happens when the holder's session ends rather than on a TTL.
+
+use async_trait::async_trait;
+use copilot_core::{AppError, AppResult, LeaseStore};
+use redis::{aio::ConnectionManager, Client, Script};
+use sha2::{Digest, Sha256};
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgPool, Postgres};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::{InfraError, Result};
+
+/// Take the lease if free, or refresh it if already ours
+const ACQUIRE_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1])
+if current == false then
+    redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+    return 1
+end
+if current == ARGV[1] then
+    redis.call('PEXPIRE', KEYS[1], ARGV[2])
+    return 1
+end
+return 0
+"#;
+
+const RENEW_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    redis.call('PEXPIRE', KEYS[1], ARGV[2])
+    return 1
+end
+return 0
+"#;
+
+const RELEASE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+fn dependency_error(service: &str, err: impl std::fmt::Display) -> AppError {
+    AppError::dependency_failure(service, err.to_string())
+}
+
+/// Redis-backed leases
+#[derive(Clone)]
+pub struct RedisLeaseStore {
+    connection: ConnectionManager,
+    key_prefix: String,
+}
+
+impl RedisLeaseStore {
+    pub async fn new(url: &str) -> Result<Self> {
+        info!("Connecting lease store to Redis at {}", url);
+
+        let client = Client::open(url).map_err(InfraError::Cache)?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .map_err(InfraError::Cache)?;
+
+        Ok(Self {
+            connection,
+            key_prefix: "copilot:lease:".to_string(),
+        })
+    }
+
+    pub fn with_key_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.key_prefix = prefix.into();
+        self
+    }
+
+    fn key(&self, lease: &str) -> String {
+        format!("{}{}", self.key_prefix, lease)
+    }
+
+    async fn run_script(
+        &self,
+        script: &str,
+        lease: &str,
+        holder: &str,
+        ttl: Duration,
+    ) -> AppResult<i64> {
+        let mut conn = self.connection.clone();
+        Script::new(script)
+            .key(self.key(lease))
+            .arg(holder)
+            .arg(ttl.as_millis() as u64)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| dependency_error("redis", e))
+    }
+}
+
+#[async_trait]
+impl LeaseStore for RedisLeaseStore {
+    async fn try_acquire(&self, lease: &str, holder: &str, ttl: Duration) -> AppResult<bool> {
+        Ok(self.run_script(ACQUIRE_SCRIPT, lease, holder, ttl).await? == 1)
+    }
+
+    async fn renew(&self, lease: &str, holder: &str, ttl: Duration) -> AppResult<bool> {
+        Ok(self.run_script(RENEW_SCRIPT, lease, holder, ttl).await? == 1)
+    }
+
+    async fn release(&self, lease: &str, holder: &str) -> AppResult<()> {
+        self.run_script(RELEASE_SCRIPT, lease, holder, Duration::ZERO)
+            .await
+            .map(|_| ())
+    }
+}
+
+/// Postgres advisory-lock leases
+///
+/// Each held lease pins one pooled connection, so size the pool accordingly.
+/// The TTL passed by callers is ignored: the lock is held until released or
+/// until the connection drops.
+pub struct PgAdvisoryLeaseStore {
+    pool: PgPool,
+    held: Mutex<HashMap<String, PoolConnection<Postgres>>>,
+}
+
+impl PgAdvisoryLeaseStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            held: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Advisory lock key for a lease name
+    pub fn lock_key(lease: &str) -> i64 {
+        let digest = Sha256::digest(lease.as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        i64::from_be_bytes(bytes)
+    }
+
+    /// Check that the session holding a lock is still alive
+    async fn session_alive(conn: &mut PoolConnection<Postgres>) -> bool {
+        sqlx::query("SELECT 1").execute(&mut **conn).await.is_ok()
+    }
+}
+
+#[async_trait]
+impl LeaseStore for PgAdvisoryLeaseStore {
+    async fn try_acquire(&self, lease: &str, holder: &str, ttl: Duration) -> AppResult<bool> {
+        if self.held.lock().await.contains_key(lease) {
+            return self.renew(lease, holder, ttl).await;
+        }
+
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| dependency_error("postgres", e))?;
+        let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(Self::lock_key(lease))
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| dependency_error("postgres", e))?;
+
+        if acquired {
+            debug!(lease = %lease, holder = %holder, "Acquired advisory lock");
+            self.held.lock().await.insert(lease.to_string(), conn);
+        }
+        Ok(acquired)
+    }
+
+    async fn renew(&self, lease: &str, _holder: &str, _ttl: Duration) -> AppResult<bool> {
+        let mut held = self.held.lock().await;
+        let Some(conn) = held.get_mut(lease) else {
+            return Ok(false);
+        };
+        if Self::session_alive(conn).await {
+            return Ok(true);
+        }
+
+        // The session is gone and Postgres has released the lock with it;
+        // close the connection instead of returning it to the pool
+        warn!(lease = %lease, "Advisory lock session lost");
+        if let Some(conn) = held.remove(lease) {
+            drop(conn.detach());
+        }
+        Ok(false)
+    }
+
+    async fn release(&self, lease: &str, _holder: &str) -> AppResult<()> {
+        let Some(mut conn) = self.held.lock().await.remove(lease) else {
+            return Ok(());
+        };
+
+        let unlocked = sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(Self::lock_key(lease))
+            .execute(&mut *conn)
+            .await;
+        if let Err(e) = unlocked {
+            // Closing the session releases the lock regardless
+            warn!(lease = %lease, error = %e, "Failed to unlock advisory lock, closing session");
+            drop(conn.detach());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_key_is_stable() {
+        let key = PgAdvisoryLeaseStore::lock_key("workflow-scheduler");
+        assert_eq!(key, PgAdvisoryLeaseStore::lock_key("workflow-scheduler"));
+        assert_ne!(key, PgAdvisoryLeaseStore::lock_key("workflow-triggers"));
+    }
+}
@@ -0,0 +1,349 @@
+//! Field-level encryption for conversation text at rest
+//!
+//! Values are sealed with AES-256-GCM under the active key of a [`KeyRing`]
+//! and stored as `enc:v1:<key id>:<base64 nonce + ciphertext>`. Retired keys
+//! stay in the ring so rows written before a rotation still decrypt, and
+//! values without the prefix pass through untouched, so existing plaintext
+//! rows keep working until [`encrypt_existing_rows`] rewrites them.
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::{InfraError, Result};
+
+const PREFIX: &str = "enc:v1:";
+
+/// Source of key material, such as environment variables or a vault
+#[async_trait]
+pub trait SecretsProvider: Send + Sync {
+    /// Fetch a secret by name, `None` if it is not set
+    async fn get_secret(&self, name: &str) -> Result<Option<String>>;
+}
+
+/// Reads secrets from environment variables of the same name
+#[derive(Debug, Clone, Default)]
+pub struct EnvSecretsProvider;
+
+#[async_trait]
+impl SecretsProvider for EnvSecretsProvider {
+    async fn get_secret(&self, name: &str) -> Result<Option<String>> {
+        Ok(std::env::var(name).ok())
+    }
+}
+
+/// Encryption keys by id, one of which encrypts new values
+pub struct KeyRing {
+    active: String,
+    keys: HashMap<String, LessSafeKey>,
+}
+
+impl KeyRing {
+    /// Parse `id:base64key[,id:base64key...]`, where each key is 32 bytes and
+    /// the first entry is the active key. Rotate by prepending a new key.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut active = None;
+        let mut keys = HashMap::new();
+
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (id, encoded) = entry.split_once(':').ok_or_else(|| {
+                InfraError::Encryption("Key entries must be id:base64key".to_string())
+            })?;
+            if id.is_empty() || id.contains(':') {
+                return Err(InfraError::Encryption(format!("Invalid key id: {:?}", id)));
+            }
+            let bytes = BASE64
+                .decode(encoded)
+                .map_err(|e| InfraError::Encryption(format!("Key {} is not base64: {}", id, e)))?;
+            let key = UnboundKey::new(&AES_256_GCM, &bytes)
+                .map_err(|_| InfraError::Encryption(format!("Key {} must be 32 bytes", id)))?;
+
+            active.get_or_insert_with(|| id.to_string());
+            keys.insert(id.to_string(), LessSafeKey::new(key));
+        }
+
+        let active = active.ok_or_else(|| InfraError::Encryption("Key ring is empty".to_string()))?;
+        Ok(Self { active, keys })
+    }
+
+    /// Id of the key that encrypts new values
+    pub fn active_key_id(&self) -> &str {
+        &self.active
+    }
+}
+
+/// Encrypts and decrypts stored text with a reloadable [`KeyRing`]
+pub struct FieldCipher {
+    keys: RwLock<Arc<KeyRing>>,
+    secrets: Option<(Arc<dyn SecretsProvider>, String)>,
+    rng: SystemRandom,
+}
+
+impl FieldCipher {
+    /// A cipher over a fixed key ring
+    pub fn new(keys: KeyRing) -> Self {
+        Self {
+            keys: RwLock::new(Arc::new(keys)),
+            secrets: None,
+            rng: SystemRandom::new(),
+        }
+    }
+
+    /// A cipher whose key ring is the secret `name`, re-read by [`reload`]
+    ///
+    /// [`reload`]: FieldCipher::reload
+    pub async fn from_secrets(provider: Arc<dyn SecretsProvider>, name: &str) -> Result<Self> {
+        let keys = load_key_ring(provider.as_ref(), name).await?;
+        Ok(Self {
+            keys: RwLock::new(Arc::new(keys)),
+            secrets: Some((provider, name.to_string())),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// Re-read the key ring from the secrets provider to pick up a rotation
+    pub async fn reload(&self) -> Result<()> {
+        let Some((provider, name)) = &self.secrets else {
+            return Ok(());
+        };
+        let keys = load_key_ring(provider.as_ref(), name).await?;
+        info!("Reloaded encryption keys, active key {}", keys.active_key_id());
+        *self.keys.write().unwrap() = Arc::new(keys);
+        Ok(())
+    }
+
+    fn key_ring(&self) -> Arc<KeyRing> {
+        self.keys.read().unwrap().clone()
+    }
+
+    /// Id of the key that encrypts new values
+    pub fn active_key_id(&self) -> String {
+        self.key_ring().active.clone()
+    }
+
+    /// Seal `plaintext` under the active key
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let ring = self.key_ring();
+        let key = &ring.keys[&ring.active];
+
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| InfraError::Encryption("Failed to generate nonce".to_string()))?;
+
+        let mut sealed = plaintext.as_bytes().to_vec();
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(ring.active.as_bytes()),
+            &mut sealed,
+        )
+        .map_err(|_| InfraError::Encryption("Failed to encrypt value".to_string()))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&sealed);
+        Ok(format!("{}{}:{}", PREFIX, ring.active, BASE64.encode(payload)))
+    }
+
+    /// Open a stored value; values that were never encrypted are returned as is
+    pub fn decrypt(&self, stored: &str) -> Result<String> {
+        let Some((key_id, encoded)) = parse_stored(stored) else {
+            return Ok(stored.to_string());
+        };
+
+        let ring = self.key_ring();
+        let key = ring
+            .keys
+            .get(key_id)
+            .ok_or_else(|| InfraError::Encryption(format!("Unknown encryption key: {}", key_id)))?;
+
+        let mut payload = BASE64
+            .decode(encoded)
+            .map_err(|e| InfraError::Encryption(format!("Malformed encrypted value: {}", e)))?;
+        if payload.len() < NONCE_LEN {
+            return Err(InfraError::Encryption("Encrypted value is truncated".to_string()));
+        }
+        let mut sealed = payload.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&payload)
+            .map_err(|_| InfraError::Encryption("Malformed nonce".to_string()))?;
+
+        let plaintext = key
+            .open_in_place(nonce, Aad::from(key_id.as_bytes()), &mut sealed)
+            .map_err(|_| InfraError::Encryption("Failed to decrypt value".to_string()))?;
+        String::from_utf8(plaintext.to_vec())
+            .map_err(|e| InfraError::Encryption(format!("Decrypted value is not UTF-8: {}", e)))
+    }
+
+    /// Whether a stored value is plaintext or sealed under a retired key
+    pub fn needs_reencryption(&self, stored: &str) -> bool {
+        match parse_stored(stored) {
+            Some((key_id, _)) => key_id != self.key_ring().active,
+            None => true,
+        }
+    }
+}
+
+async fn load_key_ring(provider: &dyn SecretsProvider, name: &str) -> Result<KeyRing> {
+    let spec = provider
+        .get_secret(name)
+        .await?
+        .ok_or_else(|| InfraError::Encryption(format!("Secret {} is not set", name)))?;
+    KeyRing::parse(&spec)
+}
+
+fn parse_stored(stored: &str) -> Option<(&str, &str)> {
+    stored.strip_prefix(PREFIX)?.split_once(':')
+}
+
+/// A text column holding encrypted values, keyed by a UUID primary key
+#[derive(Debug, Clone, Copy)]
+pub struct EncryptedColumn {
+    pub table: &'static str,
+    pub column: &'static str,
+}
+
+/// `messages.content`, written by [`MessageRepository`](super::MessageRepository)
+pub const MESSAGE_CONTENT: EncryptedColumn = EncryptedColumn {
+    table: "messages",
+    column: "content",
+};
+
+/// Encrypt plaintext rows of `column` and re-encrypt rows sealed under
+/// retired keys, `batch_size` rows at a time; returns the rows rewritten.
+///
+/// Safe to re-run: rows already under the active key are skipped.
+pub async fn encrypt_existing_rows(
+    pool: &PgPool,
+    cipher: &FieldCipher,
+    column: EncryptedColumn,
+    batch_size: i64,
+) -> Result<u64> {
+    let select = format!(
+        "SELECT id, {col} FROM {table} WHERE id > $1 ORDER BY id LIMIT $2",
+        col = column.column,
+        table = column.table
+    );
+    let update = format!(
+        "UPDATE {table} SET {col} = $1 WHERE id = $2 AND {col} = $3",
+        col = column.column,
+        table = column.table
+    );
+
+    let mut last_id = Uuid::nil();
+    let mut rewritten = 0;
+    loop {
+        let rows: Vec<(Uuid, String)> = sqlx::query_as(&select)
+            .bind(last_id)
+            .bind(batch_size)
+            .fetch_all(pool)
+            .await?;
+        let Some((id, _)) = rows.last() else {
+            break;
+        };
+        last_id = *id;
+
+        let mut tx = pool.begin().await?;
+        for (id, stored) in &rows {
+            if !cipher.needs_reencryption(stored) {
+                continue;
+            }
+            let sealed = cipher.encrypt(&cipher.decrypt(stored)?)?;
+            // Rows changed since they were read are left for the next run
+            let result = sqlx::query(&update)
+                .bind(sealed)
+                .bind(id)
+                .bind(stored)
+                .execute(&mut *tx)
+                .await?;
+            rewritten += result.rows_affected();
+        }
+        tx.commit().await?;
+        debug!("Encrypted {}.{} up to id={}", column.table, column.column, last_id);
+    }
+
+    info!(
+        "Encrypted {} rows of {}.{} under key {}",
+        rewritten,
+        column.table,
+        column.column,
+        cipher.active_key_id()
+    );
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> String {
+        BASE64.encode([byte; 32])
+    }
+
+    struct StaticSecrets(RwLock<String>);
+
+    #[async_trait]
+    impl SecretsProvider for StaticSecrets {
+        async fn get_secret(&self, _name: &str) -> Result<Option<String>> {
+            Ok(Some(self.0.read().unwrap().clone()))
+        }
+    }
+
+    #[test]
+    fn test_round_trip_and_plaintext_passthrough() {
+        let cipher = FieldCipher::new(KeyRing::parse(&format!("k1:{}", key(1))).unwrap());
+
+        let sealed = cipher.encrypt("the deploy key is in vault").unwrap();
+        assert!(sealed.starts_with("enc:v1:k1:"));
+        assert_ne!(sealed, cipher.encrypt("the deploy key is in vault").unwrap());
+        assert_eq!(cipher.decrypt(&sealed).unwrap(), "the deploy key is in vault");
+
+        assert_eq!(cipher.decrypt("legacy plaintext").unwrap(), "legacy plaintext");
+        assert!(cipher.needs_reencryption("legacy plaintext"));
+        assert!(!cipher.needs_reencryption(&sealed));
+    }
+
+    #[test]
+    fn test_tampered_value_is_rejected() {
+        let cipher = FieldCipher::new(KeyRing::parse(&format!("k1:{}", key(1))).unwrap());
+        let sealed = cipher.encrypt("hello").unwrap();
+
+        let (head, payload) = sealed.rsplit_once(':').unwrap();
+        let mut bytes = BASE64.decode(payload).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        let tampered = format!("{}:{}", head, BASE64.encode(bytes));
+
+        assert!(cipher.decrypt(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_key_ring_validation() {
+        assert!(KeyRing::parse("").is_err());
+        assert!(KeyRing::parse("k1").is_err());
+        assert!(KeyRing::parse(&format!("k1:{}", BASE64.encode([0u8; 16]))).is_err());
+
+        let ring = KeyRing::parse(&format!("k2:{}, k1:{}", key(2), key(1))).unwrap();
+        assert_eq!(ring.active_key_id(), "k2");
+    }
+
+    #[tokio::test]
+    async fn test_rotation_via_secrets_provider() {
+        let secrets = Arc::new(StaticSecrets(RwLock::new(format!("k1:{}", key(1)))));
+        let cipher = FieldCipher::from_secrets(secrets.clone(), "COPILOT_ENCRYPTION_KEYS")
+            .await
+            .unwrap();
+        let old = cipher.encrypt("before rotation").unwrap();
+
+        *secrets.0.write().unwrap() = format!("k2:{},k1:{}", key(2), key(1));
+        cipher.reload().await.unwrap();
+
+        assert_eq!(cipher.active_key_id(), "k2");
+        assert_eq!(cipher.decrypt(&old).unwrap(), "before rotation");
+        assert!(cipher.needs_reencryption(&old));
+        assert!(cipher.encrypt("after rotation").unwrap().starts_with("enc:v1:k2:"));
+    }
+}
@@ -244,6 +244,36 @@ fn get_migrations() -> Vec<Migration> {
             DROP TABLE IF EXISTS workflows;
             "#,
         ),
+
+        // Migration 5: Create shared webhook delivery queue
+        Migration::new(
+            5,
+            "create_webhook_delivery_queue_table",
+            r#"
+            CREATE TABLE webhook_delivery_queue (
+                id TEXT PRIMARY KEY,
+                endpoint_id TEXT NOT NULL,
+                event JSONB NOT NULL,
+                sequence BIGINT NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                enqueued_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                available_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                receipt TEXT,
+                visible_at TIMESTAMP WITH TIME ZONE,
+                worker_id TEXT
+            );
+            CREATE UNIQUE INDEX idx_webhook_delivery_queue_endpoint_sequence
+                ON webhook_delivery_queue(endpoint_id, sequence);
+            CREATE TABLE webhook_endpoint_sequences (
+                endpoint_id TEXT PRIMARY KEY,
+                last_sequence BIGINT NOT NULL
+            );
+            "#,
+            r#"
+            DROP TABLE IF EXISTS webhook_endpoint_sequences;
+            DROP TABLE IF EXISTS webhook_delivery_queue;
+            "#,
+        ),
     ]
 }
 
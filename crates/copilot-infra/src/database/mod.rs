@@ -1,9 +1,16 @@
 pub mod pool;
 pub mod repositories;
 pub mod migrations;
+pub mod webhook_queue;
+pub mod encryption;
 
 pub use pool::{create_pool, PgPoolConfig};
 pub use repositories::{
     SessionRepository, ConversationRepository, MessageRepository, WorkflowRepository,
 };
 pub use migrations::{run_migrations, rollback_migrations, Migration};
+pub use webhook_queue::PgDeliveryQueue;
+pub use encryption::{
+    encrypt_existing_rows, EncryptedColumn, EnvSecretsProvider, FieldCipher, KeyRing,
+    SecretsProvider, MESSAGE_CONTENT,
+};
@@ -85,6 +85,19 @@ pub async fn create_pool(config: &PgPoolConfig) -> Result<PgPool> {
     Ok(pool)
 }
 
+/// Creates a connection pool that connects on first use, so callers can
+/// start while the database is still down
+pub fn create_lazy_pool(config: &PgPoolConfig) -> Result<PgPool> {
+    PgPoolOptions::new()
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(config.connect_timeout)
+        .idle_timeout(config.idle_timeout)
+        .max_lifetime(config.max_lifetime)
+        .connect_lazy(&config.database_url)
+        .map_err(InfraError::Database)
+}
+
 /// Checks the health of a database connection pool
 pub async fn check_pool_health(pool: &PgPool) -> Result<()> {
     sqlx::query("SELECT 1")
@@ -1,9 +1,11 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::sync::Arc;
 use uuid::Uuid;
 use tracing::{debug, error, info};
 
+use super::encryption::FieldCipher;
 use crate::{InfraError, Result};
 
 // ============================================================================
@@ -310,14 +312,47 @@ pub struct MessageRecord {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct MessageRepository {
     pool: PgPool,
+    cipher: Option<Arc<FieldCipher>>,
+}
+
+impl std::fmt::Debug for MessageRepository {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MessageRepository")
+            .field("encrypted", &self.cipher.is_some())
+            .finish()
+    }
 }
 
 impl MessageRepository {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self { pool, cipher: None }
+    }
+
+    /// Encrypt message content at rest; reads still accept plaintext rows
+    pub fn with_encryption(mut self, cipher: Arc<FieldCipher>) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    fn seal(&self, content: &str) -> Result<String> {
+        match &self.cipher {
+            Some(cipher) => cipher.encrypt(content),
+            None => Ok(content.to_string()),
+        }
+    }
+
+    fn open(&self, mut message: MessageRecord) -> Result<MessageRecord> {
+        if let Some(cipher) = &self.cipher {
+            message.content = cipher.decrypt(&message.content)?;
+        }
+        Ok(message)
+    }
+
+    fn open_all(&self, messages: Vec<MessageRecord>) -> Result<Vec<MessageRecord>> {
+        messages.into_iter().map(|m| self.open(m)).collect()
     }
 
     pub async fn create(
@@ -339,14 +374,14 @@ impl MessageRepository {
         .bind(Uuid::new_v4())
         .bind(conversation_id)
         .bind(role)
-        .bind(content)
+        .bind(self.seal(content)?)
         .bind(metadata)
         .bind(Utc::now())
         .fetch_one(&self.pool)
         .await?;
 
         info!("Message created: id={}", message.id);
-        Ok(message)
+        self.open(message)
     }
 
     pub async fn find_by_id(&self, id: Uuid) -> Result<MessageRecord> {
@@ -362,7 +397,7 @@ impl MessageRepository {
         .await?
         .ok_or_else(|| InfraError::NotFound(format!("Message not found: {}", id)))?;
 
-        Ok(message)
+        self.open(message)
     }
 
     pub async fn find_by_conversation_id(&self, conversation_id: Uuid) -> Result<Vec<MessageRecord>> {
@@ -380,7 +415,7 @@ impl MessageRepository {
         .await?;
 
         debug!("Found {} messages for conversation_id={}", messages.len(), conversation_id);
-        Ok(messages)
+        self.open_all(messages)
     }
 
     pub async fn find_by_conversation_id_paginated(
@@ -409,7 +444,7 @@ impl MessageRepository {
         .await?;
 
         debug!("Found {} messages", messages.len());
-        Ok(messages)
+        self.open_all(messages)
     }
 
     pub async fn count_by_conversation_id(&self, conversation_id: Uuid) -> Result<i64> {
@@ -0,0 +1,197 @@
+//! Postgres-backed webhook delivery queue
+//!
+//! Lets every server replica run a [`copilot_webhook::DeliveryWorker`] against
+//! one `webhook_delivery_queue` table. Claims use `FOR UPDATE SKIP LOCKED`, so
+//! concurrent workers never lease the same row, and only the oldest row of
+//! each endpoint is eligible, so an endpoint sees its deliveries in order.
+//! Lease times are taken from the database clock to keep replicas with
+//! skewed clocks consistent.
+//!
+//! Sequences are counted per endpoint in `webhook_endpoint_sequences`.
+//! Taking the next one locks the endpoint's counter row until the enqueue
+//! commits, so an endpoint's rows commit in sequence order and a worker
+//! never delivers a later event while an earlier one is still uncommitted.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use copilot_webhook::{
+    new_delivery_id, stale_receipt, ClaimedDelivery, DeliveryQueue, QueuedDelivery,
+    Result as WebhookResult, WebhookError, WebhookEvent,
+};
+use sqlx::postgres::{PgPool, PgRow};
+use sqlx::Row;
+use tracing::debug;
+
+/// Queue an event behind the endpoint's others, taking the endpoint's next
+/// sequence
+const ENQUEUE_SQL: &str = r#"
+WITH next AS (
+    INSERT INTO webhook_endpoint_sequences (endpoint_id, last_sequence)
+    VALUES ($2, 1)
+    ON CONFLICT (endpoint_id)
+    DO UPDATE SET last_sequence = webhook_endpoint_sequences.last_sequence + 1
+    RETURNING last_sequence
+)
+INSERT INTO webhook_delivery_queue (id, endpoint_id, event, sequence, attempts, enqueued_at, available_at)
+SELECT $1, $2, $3, next.last_sequence, 0, now(), now()
+FROM next
+RETURNING id, endpoint_id, event, sequence, attempts, enqueued_at, available_at
+"#;
+
+/// Lease the head row of up to `$1` endpoints whose head is ready
+const CLAIM_SQL: &str = r#"
+WITH heads AS (
+    SELECT DISTINCT ON (endpoint_id) id
+    FROM webhook_delivery_queue
+    ORDER BY endpoint_id, sequence
+),
+ready AS (
+    SELECT q.id
+    FROM webhook_delivery_queue q
+    JOIN heads h ON h.id = q.id
+    WHERE q.available_at <= now()
+      AND (q.visible_at IS NULL OR q.visible_at <= now())
+    ORDER BY q.available_at
+    LIMIT $1
+    FOR UPDATE OF q SKIP LOCKED
+)
+UPDATE webhook_delivery_queue q
+SET receipt = gen_random_uuid()::text,
+    visible_at = now() + make_interval(secs => $2),
+    worker_id = $3
+FROM ready
+WHERE q.id = ready.id
+RETURNING q.id, q.endpoint_id, q.event, q.sequence, q.attempts,
+          q.enqueued_at, q.available_at, q.receipt, q.visible_at
+"#;
+
+fn queue_error(err: sqlx::Error) -> WebhookError {
+    WebhookError::Queue(err.to_string())
+}
+
+/// Delivery queue shared through Postgres
+#[derive(Clone)]
+pub struct PgDeliveryQueue {
+    pool: PgPool,
+}
+
+impl PgDeliveryQueue {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    fn delivery_from_row(row: &PgRow) -> std::result::Result<QueuedDelivery, sqlx::Error> {
+        let event: serde_json::Value = row.try_get("event")?;
+        let event: WebhookEvent =
+            serde_json::from_value(event).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+
+        Ok(QueuedDelivery {
+            id: row.try_get("id")?,
+            endpoint_id: row.try_get("endpoint_id")?,
+            event,
+            sequence: row.try_get::<i64, _>("sequence")? as u64,
+            attempts: row.try_get::<i32, _>("attempts")? as u32,
+            enqueued_at: row.try_get("enqueued_at")?,
+            available_at: row.try_get("available_at")?,
+        })
+    }
+
+    fn claim_from_row(row: &PgRow) -> std::result::Result<ClaimedDelivery, sqlx::Error> {
+        Ok(ClaimedDelivery {
+            delivery: Self::delivery_from_row(row)?,
+            receipt: row.try_get("receipt")?,
+            visible_at: row.try_get("visible_at")?,
+        })
+    }
+}
+
+#[async_trait]
+impl DeliveryQueue for PgDeliveryQueue {
+    async fn enqueue(
+        &self,
+        endpoint_id: &str,
+        event: WebhookEvent,
+    ) -> WebhookResult<QueuedDelivery> {
+        let payload =
+            serde_json::to_value(&event).map_err(|e| WebhookError::Serialization(e.to_string()))?;
+
+        let row = sqlx::query(ENQUEUE_SQL)
+            .bind(new_delivery_id())
+            .bind(endpoint_id)
+            .bind(payload)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(queue_error)?;
+
+        Self::delivery_from_row(&row).map_err(queue_error)
+    }
+
+    async fn claim(
+        &self,
+        worker_id: &str,
+        max: usize,
+        visibility_timeout: Duration,
+    ) -> WebhookResult<Vec<ClaimedDelivery>> {
+        let rows = sqlx::query(CLAIM_SQL)
+            .bind(max as i64)
+            .bind(visibility_timeout.num_milliseconds() as f64 / 1000.0)
+            .bind(worker_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(queue_error)?;
+
+        debug!(worker_id = %worker_id, claimed = rows.len(), "Claimed webhook deliveries");
+        rows.iter()
+            .map(Self::claim_from_row)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(queue_error)
+    }
+
+    async fn ack(&self, claim: &ClaimedDelivery) -> WebhookResult<()> {
+        let result =
+            sqlx::query("DELETE FROM webhook_delivery_queue WHERE id = $1 AND receipt = $2")
+                .bind(&claim.delivery.id)
+                .bind(&claim.receipt)
+                .execute(&self.pool)
+                .await
+                .map_err(queue_error)?;
+
+        if result.rows_affected() == 0 {
+            return Err(stale_receipt(claim));
+        }
+        Ok(())
+    }
+
+    async fn nack(&self, claim: &ClaimedDelivery, retry_at: DateTime<Utc>) -> WebhookResult<()> {
+        let result = sqlx::query(
+            r#"
+            UPDATE webhook_delivery_queue
+            SET attempts = attempts + 1,
+                available_at = $3,
+                receipt = NULL,
+                visible_at = NULL,
+                worker_id = NULL
+            WHERE id = $1 AND receipt = $2
+            "#,
+        )
+        .bind(&claim.delivery.id)
+        .bind(&claim.receipt)
+        .bind(retry_at)
+        .execute(&self.pool)
+        .await
+        .map_err(queue_error)?;
+
+        if result.rows_affected() == 0 {
+            return Err(stale_receipt(claim));
+        }
+        Ok(())
+    }
+
+    async fn depth(&self) -> WebhookResult<usize> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM webhook_delivery_queue")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(queue_error)?;
+        Ok(count as usize)
+    }
+}
@@ -0,0 +1,78 @@
+//! Redis-backed deduplication of redelivered events
+//!
+//! [`RedisDedupStore`] records each key with `SET NX PX`, so of all the
+//! replicas receiving the same webhook retry or NATS redelivery, only the
+//! first within the key's TTL gets to process it.
+
+use async_trait::async_trait;
+use copilot_core::{AppError, AppResult, DedupStore};
+use redis::{aio::ConnectionManager, Client};
+use std::time::Duration;
+use tracing::info;
+
+use crate::{InfraError, Result};
+
+fn dependency_error(err: impl std::fmt::Display) -> AppError {
+    AppError::dependency_failure("redis", err.to_string())
+}
+
+/// Redis-backed dedup keys
+#[derive(Clone)]
+pub struct RedisDedupStore {
+    connection: ConnectionManager,
+    key_prefix: String,
+}
+
+impl RedisDedupStore {
+    pub async fn new(url: &str) -> Result<Self> {
+        info!("Connecting dedup store to Redis at {}", url);
+
+        let client = Client::open(url).map_err(InfraError::Cache)?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .map_err(InfraError::Cache)?;
+
+        Ok(Self {
+            connection,
+            key_prefix: "copilot:dedup:".to_string(),
+        })
+    }
+
+    pub fn with_key_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.key_prefix = prefix.into();
+        self
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.key_prefix, key)
+    }
+}
+
+#[async_trait]
+impl DedupStore for RedisDedupStore {
+    async fn record(&self, key: &str, ttl: Duration) -> AppResult<bool> {
+        let mut conn = self.connection.clone();
+        // Redis rejects a zero expiry
+        let ttl_ms = ttl.as_millis().max(1) as u64;
+        let set: Option<String> = redis::cmd("SET")
+            .arg(self.key(key))
+            .arg(1)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_ms)
+            .query_async(&mut conn)
+            .await
+            .map_err(dependency_error)?;
+        Ok(set.is_some())
+    }
+
+    async fn forget(&self, key: &str) -> AppResult<()> {
+        let mut conn = self.connection.clone();
+        redis::cmd("DEL")
+            .arg(self.key(key))
+            .query_async::<_, i64>(&mut conn)
+            .await
+            .map_err(dependency_error)?;
+        Ok(())
+    }
+}
@@ -196,6 +196,110 @@ impl HealthCheck for NatsHealthCheck {
     }
 }
 
+// ============================================================================
+// Endpoint Health Checks
+// ============================================================================
+
+/// Pings Redis over a fresh connection, so it can probe a server that was
+/// down when the process started
+pub struct RedisPingHealthCheck {
+    client: redis::Client,
+}
+
+impl RedisPingHealthCheck {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+        })
+    }
+}
+
+#[async_trait]
+impl HealthCheck for RedisPingHealthCheck {
+    async fn check(&self) -> Result<HealthCheckResult> {
+        let ping = async {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+            redis::cmd("PING").query_async::<_, String>(&mut conn).await
+        };
+        match ping.await {
+            Ok(_) => Ok(HealthCheckResult::healthy()),
+            Err(e) => Ok(HealthCheckResult::unhealthy(format!("Redis connection failed: {}", e))),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "redis"
+    }
+}
+
+/// Checks that a TCP listener accepts connections
+pub struct TcpHealthCheck {
+    name: String,
+    address: String,
+}
+
+impl TcpHealthCheck {
+    pub fn new(name: impl Into<String>, address: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            address: address.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for TcpHealthCheck {
+    async fn check(&self) -> Result<HealthCheckResult> {
+        match tokio::net::TcpStream::connect(&self.address).await {
+            Ok(_) => Ok(HealthCheckResult::healthy()),
+            Err(e) => Ok(HealthCheckResult::unhealthy(format!(
+                "Cannot connect to {}: {}",
+                self.address, e
+            ))),
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Checks that an HTTP endpoint answers with a success status
+pub struct HttpHealthCheck {
+    name: String,
+    url: String,
+    client: reqwest::Client,
+}
+
+impl HttpHealthCheck {
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for HttpHealthCheck {
+    async fn check(&self) -> Result<HealthCheckResult> {
+        match self.client.get(&self.url).send().await {
+            Ok(response) if response.status().is_success() => Ok(HealthCheckResult::healthy()),
+            Ok(response) => Ok(HealthCheckResult::unhealthy(format!(
+                "{} answered {}",
+                self.url,
+                response.status()
+            ))),
+            Err(e) => Ok(HealthCheckResult::unhealthy(format!("{} is unreachable: {}", self.url, e))),
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
 // ============================================================================
 // Composite Health Checker
 // ============================================================================
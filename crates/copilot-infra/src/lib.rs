@@ -1,28 +1,46 @@
 pub mod database;
 pub mod cache;
+pub mod coordination;
+pub mod dedup;
 pub mod messaging;
 pub mod health;
 pub mod resilience;
 pub mod metrics;
+pub mod storage;
+pub mod startup;
 
 pub use database::{
-    pool::{create_pool, PgPoolConfig},
+    pool::{create_lazy_pool, create_pool, PgPoolConfig},
     repositories::{
         SessionRepository, ConversationRepository, MessageRepository, WorkflowRepository,
     },
     migrations::{run_migrations, rollback_migrations, Migration},
+    webhook_queue::PgDeliveryQueue,
+    encryption::{
+        encrypt_existing_rows, EncryptedColumn, EnvSecretsProvider, FieldCipher, KeyRing,
+        SecretsProvider, MESSAGE_CONTENT,
+    },
 };
 
 pub use cache::redis::{RedisCache, RedisCacheConfig};
 pub use cache::memory::{MemoryCache, MemoryCacheConfig};
 pub use cache::response::{CachedResponse, ResponseCacheConfig, CacheKeyBuilder, CacheControl, ResponseCache};
 
+pub use coordination::{PgAdvisoryLeaseStore, RedisLeaseStore};
+pub use dedup::RedisDedupStore;
+
 pub use messaging::nats::{NatsPublisher, NatsConfig, NatsSubscriber};
+pub use messaging::redis_streams::{
+    RedisStreamConsumer, RedisStreamPublisher, RedisStreamsConfig, StreamDelivery,
+};
 
 pub use health::{
     DatabaseHealthCheck, RedisHealthCheck, NatsHealthCheck, CompositeHealthChecker, HealthStatus,
+    HttpHealthCheck, RedisPingHealthCheck, TcpHealthCheck,
 };
 
+pub use startup::{check_for_url, ComponentStatus, Criticality, DependencyMonitor, ReadinessReport};
+
 pub use resilience::{
     CircuitBreaker, CircuitBreakerConfig, CircuitBreakerState,
     RetryPolicy, RetryConfig, ExponentialBackoff, FixedDelay,
@@ -31,6 +49,11 @@ pub use resilience::{
     ResilienceBuilder, ResilienceError,
 };
 
+pub use storage::{
+    LifecyclePolicy, LifecycleReport, LifecycleRule, LocalObjectStore, ObjectClass, ObjectMeta, ObjectStorage,
+    ObjectStore, PresignMethod, PresignRequest, PresignedQuery, PresignedUrl, S3Config, S3ObjectStore, StoredObject,
+};
+
 pub use metrics::{
     PrometheusMetrics, MetricsConfig, MetricsHandle, HttpMetrics, DatabaseMetrics,
     CacheMetrics, CircuitBreakerMetrics, MetricsCollector, SystemMetrics,
@@ -59,6 +82,12 @@ pub enum InfraError {
     #[error("Migration error: {0}")]
     Migration(String),
 
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    #[error("Object storage error: {0}")]
+    Storage(String),
+
     #[error("Not found: {0}")]
     NotFound(String),
 
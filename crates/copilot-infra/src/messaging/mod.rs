@@ -1,3 +1,5 @@
 pub mod nats;
+pub mod redis_streams;
 
 pub use nats::{NatsPublisher, NatsConfig, NatsSubscriber};
+pub use redis_streams::{RedisStreamConsumer, RedisStreamPublisher, RedisStreamsConfig, StreamDelivery};
@@ -0,0 +1,663 @@
+//! Redis Streams event bus
+//!
+//! A fallback for deployments without NATS. Events are appended to a single
+//! stream with `XADD`; each service reads it through its own consumer group,
+//! so every group sees every event while consumers inside a group share the
+//! work. Entries stay pending until acknowledged, and entries left pending by
+//! a crashed consumer are claimed by a live one once they have been idle for
+//! [`RedisStreamsConfig::claim_idle`]. Entries that keep failing are moved to
+//! a dead-letter stream after [`RedisStreamsConfig::max_deliveries`].
+
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, Client, Value};
+use std::collections::VecDeque;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+use copilot_core::events::{Event, EventPublisher, EventSubscriber};
+use crate::{InfraError, Result};
+
+const TYPE_FIELD: &str = "type";
+const EVENT_FIELD: &str = "event";
+
+#[derive(Debug, Clone)]
+pub struct RedisStreamsConfig {
+    pub url: String,
+    pub stream: String,
+    /// Approximate cap on stream length; `None` keeps everything
+    pub max_len: Option<usize>,
+    pub batch_size: usize,
+    pub block: Duration,
+    pub claim_idle: Duration,
+    pub max_deliveries: u64,
+}
+
+impl Default for RedisStreamsConfig {
+    fn default() -> Self {
+        Self {
+            url: String::from("redis://127.0.0.1:6379"),
+            stream: String::from("copilot:events"),
+            max_len: Some(100_000),
+            batch_size: 32,
+            block: Duration::from_secs(5),
+            claim_idle: Duration::from_secs(60),
+            max_deliveries: 5,
+        }
+    }
+}
+
+impl RedisStreamsConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_stream(mut self, stream: impl Into<String>) -> Self {
+        self.stream = stream.into();
+        self
+    }
+
+    pub fn with_max_len(mut self, max_len: Option<usize>) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_block(mut self, block: Duration) -> Self {
+        self.block = block;
+        self
+    }
+
+    pub fn with_claim_idle(mut self, idle: Duration) -> Self {
+        self.claim_idle = idle;
+        self
+    }
+
+    pub fn with_max_deliveries(mut self, max: u64) -> Self {
+        self.max_deliveries = max.max(1);
+        self
+    }
+
+    /// Stream that receives entries which exceeded `max_deliveries`
+    pub fn dead_letter_stream(&self) -> String {
+        format!("{}:dead", self.stream)
+    }
+}
+
+async fn connect(url: &str) -> Result<ConnectionManager> {
+    let client = Client::open(url).map_err(InfraError::Cache)?;
+    ConnectionManager::new(client).await.map_err(|e| {
+        error!("Failed to connect to Redis: {}", e);
+        InfraError::Messaging(format!("Failed to connect to Redis: {}", e))
+    })
+}
+
+fn messaging_error(op: &str, err: impl std::fmt::Display) -> InfraError {
+    InfraError::Messaging(format!("{} failed: {}", op, err))
+}
+
+fn xadd_cmd(config: &RedisStreamsConfig, stream: &str, event: &Event) -> Result<redis::Cmd> {
+    let mut cmd = redis::cmd("XADD");
+    cmd.arg(stream);
+    if let Some(max_len) = config.max_len {
+        cmd.arg("MAXLEN").arg("~").arg(max_len);
+    }
+    cmd.arg("*")
+        .arg(TYPE_FIELD)
+        .arg(&event.event_type)
+        .arg(EVENT_FIELD)
+        .arg(serde_json::to_vec(event)?);
+    Ok(cmd)
+}
+
+#[derive(Clone)]
+pub struct RedisStreamPublisher {
+    connection: ConnectionManager,
+    config: RedisStreamsConfig,
+}
+
+impl RedisStreamPublisher {
+    pub async fn new(config: RedisStreamsConfig) -> Result<Self> {
+        info!("Connecting event publisher to Redis stream {}", config.stream);
+        let connection = connect(&config.url).await?;
+        Ok(Self { connection, config })
+    }
+
+    pub fn config(&self) -> &RedisStreamsConfig {
+        &self.config
+    }
+
+    pub async fn health_check(&self) -> Result<()> {
+        let mut conn = self.connection.clone();
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut conn)
+            .await
+            .map(|_| ())
+            .map_err(|e| {
+                warn!("Redis stream health check failed: {}", e);
+                InfraError::HealthCheck(format!("Redis is not reachable: {}", e))
+            })
+    }
+}
+
+#[async_trait]
+impl EventPublisher for RedisStreamPublisher {
+    type Error = InfraError;
+
+    async fn publish(&self, event: &Event) -> Result<()> {
+        debug!("Publishing event {} to {}", event.event_type, self.config.stream);
+
+        let mut conn = self.connection.clone();
+        xadd_cmd(&self.config, &self.config.stream, event)?
+            .query_async::<_, String>(&mut conn)
+            .await
+            .map(|_| ())
+            .map_err(|e| messaging_error("XADD", e))
+    }
+
+    async fn publish_batch(&self, events: &[Event]) -> Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        debug!("Publishing batch of {} events to {}", events.len(), self.config.stream);
+
+        let mut pipe = redis::pipe();
+        for event in events {
+            pipe.add_command(xadd_cmd(&self.config, &self.config.stream, event)?);
+        }
+        let mut conn = self.connection.clone();
+        pipe.query_async::<_, Vec<String>>(&mut conn)
+            .await
+            .map(|_| ())
+            .map_err(|e| messaging_error("XADD", e))
+    }
+}
+
+/// One stream entry handed to a consumer
+#[derive(Debug, Clone)]
+pub struct StreamDelivery {
+    pub id: String,
+    pub event: Event,
+}
+
+/// A raw stream entry; `fields` is `None` when the entry was trimmed away
+/// while still pending
+#[derive(Debug, Clone, PartialEq)]
+struct StreamEntry {
+    id: String,
+    fields: Option<Vec<(String, Vec<u8>)>>,
+}
+
+impl StreamEntry {
+    fn field(&self, name: &str) -> Option<&[u8]> {
+        self.fields
+            .as_ref()?
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_slice())
+    }
+}
+
+/// A pending entry as reported by `XPENDING`
+#[derive(Debug, Clone, PartialEq)]
+struct PendingEntry {
+    id: String,
+    consumer: String,
+    idle: Duration,
+    deliveries: u64,
+}
+
+fn as_string(value: &Value) -> Option<String> {
+    match value {
+        Value::Data(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+        Value::Status(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn as_u64(value: &Value) -> Option<u64> {
+    match value {
+        Value::Int(n) => u64::try_from(*n).ok(),
+        Value::Data(bytes) => std::str::from_utf8(bytes).ok()?.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Parse `[id, [field, value, ...]]` pairs as returned by `XCLAIM` and
+/// inside each stream of an `XREADGROUP` reply
+fn parse_entries(value: &Value) -> Vec<StreamEntry> {
+    let Value::Bulk(items) = value else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            let Value::Bulk(parts) = item else {
+                return None;
+            };
+            let id = as_string(parts.first()?)?;
+            let fields = match parts.get(1) {
+                Some(Value::Bulk(kv)) => Some(
+                    kv.as_chunks::<2>()
+                        .0
+                        .iter()
+                        .filter_map(|[key, value]| {
+                            let key = as_string(key)?;
+                            let Value::Data(value) = value else {
+                                return None;
+                            };
+                            Some((key, value.clone()))
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            };
+            Some(StreamEntry { id, fields })
+        })
+        .collect()
+}
+
+/// Parse an `XREADGROUP` reply, which is nil on timeout
+fn parse_read_reply(value: &Value) -> Vec<StreamEntry> {
+    let Value::Bulk(streams) = value else {
+        return Vec::new();
+    };
+    streams
+        .iter()
+        .flat_map(|stream| match stream {
+            Value::Bulk(parts) => parts.get(1).map(parse_entries).unwrap_or_default(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// Parse the extended form of `XPENDING`
+fn parse_pending(value: &Value) -> Vec<PendingEntry> {
+    let Value::Bulk(items) = value else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            let Value::Bulk(parts) = item else {
+                return None;
+            };
+            Some(PendingEntry {
+                id: as_string(parts.first()?)?,
+                consumer: as_string(parts.get(1)?)?,
+                idle: Duration::from_millis(as_u64(parts.get(2)?)?),
+                deliveries: as_u64(parts.get(3)?)?,
+            })
+        })
+        .collect()
+}
+
+/// Consumer-group reader for the event stream
+///
+/// Through [`EventSubscriber::next`], an entry is acknowledged when the
+/// following one is requested, so an event whose handler crashes stays
+/// pending and is redelivered. Use [`next_delivery`](Self::next_delivery)
+/// and [`ack`](Self::ack) for explicit control.
+pub struct RedisStreamConsumer {
+    connection: ConnectionManager,
+    config: RedisStreamsConfig,
+    group: String,
+    consumer: String,
+    patterns: RwLock<Vec<String>>,
+    buffer: VecDeque<StreamDelivery>,
+    unacked: Option<String>,
+    last_claim: Option<Instant>,
+}
+
+impl RedisStreamConsumer {
+    /// Join `group` as `consumer`, creating the group (and stream) if needed.
+    /// A new group starts with entries published after its creation.
+    pub async fn new(
+        config: RedisStreamsConfig,
+        group: impl Into<String>,
+        consumer: impl Into<String>,
+    ) -> Result<Self> {
+        let group = group.into();
+        let consumer = consumer.into();
+        info!(
+            "Joining Redis stream {} as {} in group {}",
+            config.stream, consumer, group
+        );
+
+        let mut connection = connect(&config.url).await?;
+        let created = redis::cmd("XGROUP")
+            .arg("CREATE")
+            .arg(&config.stream)
+            .arg(&group)
+            .arg("$")
+            .arg("MKSTREAM")
+            .query_async::<_, ()>(&mut connection)
+            .await;
+        match created {
+            Ok(()) => info!("Created consumer group {} on {}", group, config.stream),
+            Err(e) if e.code() == Some("BUSYGROUP") => {}
+            Err(e) => return Err(messaging_error("XGROUP CREATE", e)),
+        }
+
+        Ok(Self {
+            connection,
+            config,
+            group,
+            consumer,
+            patterns: RwLock::new(Vec::new()),
+            buffer: VecDeque::new(),
+            unacked: None,
+            last_claim: None,
+        })
+    }
+
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    pub fn consumer(&self) -> &str {
+        &self.consumer
+    }
+
+    /// Whether an event passes the subscribed patterns; with no patterns
+    /// every event is delivered. Entries filtered out are acknowledged, as
+    /// the stream is shared by all event types.
+    fn wanted(&self, event: &Event) -> bool {
+        let patterns = self.patterns.read().unwrap_or_else(|e| e.into_inner());
+        patterns.is_empty() || patterns.iter().any(|p| event.matches(p))
+    }
+
+    /// Acknowledge entries so they leave the group's pending list
+    pub async fn ack(&mut self, ids: &[&str]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        redis::cmd("XACK")
+            .arg(&self.config.stream)
+            .arg(&self.group)
+            .arg(ids)
+            .query_async::<_, u64>(&mut self.connection)
+            .await
+            .map(|_| ())
+            .map_err(|e| messaging_error("XACK", e))
+    }
+
+    /// Take over entries other consumers left pending for longer than
+    /// `claim_idle`, dead-lettering those that exhausted their deliveries.
+    /// Returns the number of entries claimed.
+    pub async fn claim_stale(&mut self) -> Result<usize> {
+        self.last_claim = Some(Instant::now());
+
+        let reply: Value = redis::cmd("XPENDING")
+            .arg(&self.config.stream)
+            .arg(&self.group)
+            .arg("IDLE")
+            .arg(self.config.claim_idle.as_millis() as u64)
+            .arg("-")
+            .arg("+")
+            .arg(self.config.batch_size)
+            .query_async(&mut self.connection)
+            .await
+            .map_err(|e| messaging_error("XPENDING", e))?;
+
+        let (exhausted, stale): (Vec<_>, Vec<_>) = parse_pending(&reply)
+            .into_iter()
+            .partition(|p| p.deliveries >= self.config.max_deliveries);
+
+        for entry in exhausted {
+            self.dead_letter(&entry).await?;
+        }
+        if stale.is_empty() {
+            return Ok(0);
+        }
+
+        let mut cmd = redis::cmd("XCLAIM");
+        cmd.arg(&self.config.stream)
+            .arg(&self.group)
+            .arg(&self.consumer)
+            .arg(self.config.claim_idle.as_millis() as u64);
+        for entry in &stale {
+            debug!(
+                "Claiming {} from {} after {:?} idle",
+                entry.id, entry.consumer, entry.idle
+            );
+            cmd.arg(&entry.id);
+        }
+        let reply: Value = cmd
+            .query_async(&mut self.connection)
+            .await
+            .map_err(|e| messaging_error("XCLAIM", e))?;
+
+        let claimed = self.accept(parse_entries(&reply)).await?;
+        if claimed > 0 {
+            info!("Claimed {} stale entries from {}", claimed, self.config.stream);
+        }
+        Ok(claimed)
+    }
+
+    /// Copy a poisoned entry to the dead-letter stream and acknowledge it
+    async fn dead_letter(&mut self, entry: &PendingEntry) -> Result<()> {
+        warn!(
+            "Dead-lettering {} after {} deliveries",
+            entry.id, entry.deliveries
+        );
+        let reply: Value = redis::cmd("XRANGE")
+            .arg(&self.config.stream)
+            .arg(&entry.id)
+            .arg(&entry.id)
+            .query_async(&mut self.connection)
+            .await
+            .map_err(|e| messaging_error("XRANGE", e))?;
+
+        if let Some(payload) = parse_entries(&reply)
+            .first()
+            .and_then(|e| e.field(EVENT_FIELD).map(<[u8]>::to_vec))
+        {
+            redis::cmd("XADD")
+                .arg(self.config.dead_letter_stream())
+                .arg("*")
+                .arg("id")
+                .arg(&entry.id)
+                .arg("group")
+                .arg(&self.group)
+                .arg("deliveries")
+                .arg(entry.deliveries)
+                .arg(EVENT_FIELD)
+                .arg(payload)
+                .query_async::<_, String>(&mut self.connection)
+                .await
+                .map_err(|e| messaging_error("XADD", e))?;
+        }
+        self.ack(&[&entry.id]).await
+    }
+
+    /// Buffer wanted entries and acknowledge the rest; returns how many
+    /// were buffered
+    async fn accept(&mut self, entries: Vec<StreamEntry>) -> Result<usize> {
+        let mut skipped = Vec::new();
+        let mut accepted = 0;
+        for entry in entries {
+            let event = entry
+                .field(EVENT_FIELD)
+                .map(serde_json::from_slice::<Event>);
+            match event {
+                Some(Ok(event)) if self.wanted(&event) => {
+                    self.buffer.push_back(StreamDelivery { id: entry.id, event });
+                    accepted += 1;
+                }
+                Some(Err(e)) => {
+                    warn!("Dropping undecodable entry {}: {}", entry.id, e);
+                    skipped.push(entry.id);
+                }
+                _ => skipped.push(entry.id),
+            }
+        }
+        let ids: Vec<&str> = skipped.iter().map(String::as_str).collect();
+        self.ack(&ids).await?;
+        Ok(accepted)
+    }
+
+    /// Wait for the next entry; the caller must [`ack`](Self::ack) it once
+    /// handled
+    pub async fn next_delivery(&mut self) -> Result<StreamDelivery> {
+        loop {
+            if let Some(delivery) = self.buffer.pop_front() {
+                return Ok(delivery);
+            }
+
+            let claim_due = self
+                .last_claim
+                .map_or(true, |at| at.elapsed() >= self.config.claim_idle);
+            if claim_due && self.claim_stale().await? > 0 {
+                continue;
+            }
+
+            let reply: Value = redis::cmd("XREADGROUP")
+                .arg("GROUP")
+                .arg(&self.group)
+                .arg(&self.consumer)
+                .arg("COUNT")
+                .arg(self.config.batch_size)
+                .arg("BLOCK")
+                .arg(self.config.block.as_millis() as u64)
+                .arg("STREAMS")
+                .arg(&self.config.stream)
+                .arg(">")
+                .query_async(&mut self.connection)
+                .await
+                .map_err(|e| messaging_error("XREADGROUP", e))?;
+            self.accept(parse_read_reply(&reply)).await?;
+        }
+    }
+}
+
+#[async_trait]
+impl EventSubscriber for RedisStreamConsumer {
+    type Error = InfraError;
+
+    async fn subscribe(&self, pattern: &str) -> Result<()> {
+        let mut patterns = self.patterns.write().unwrap_or_else(|e| e.into_inner());
+        if !patterns.iter().any(|p| p == pattern) {
+            patterns.push(pattern.to_string());
+        }
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, pattern: &str) -> Result<()> {
+        self.patterns
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|p| p != pattern);
+        Ok(())
+    }
+
+    async fn next(&mut self) -> Result<Option<Event>> {
+        if let Some(id) = self.unacked.take() {
+            self.ack(&[&id]).await?;
+        }
+        let delivery = self.next_delivery().await?;
+        self.unacked = Some(delivery.id);
+        Ok(Some(delivery.event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(s: &str) -> Value {
+        Value::Data(s.as_bytes().to_vec())
+    }
+
+    fn entry(id: &str, event: &Event) -> Value {
+        Value::Bulk(vec![
+            data(id),
+            Value::Bulk(vec![
+                data(TYPE_FIELD),
+                data(&event.event_type),
+                data(EVENT_FIELD),
+                Value::Data(serde_json::to_vec(event).unwrap()),
+            ]),
+        ])
+    }
+
+    #[test]
+    fn test_config_builder() {
+        let config = RedisStreamsConfig::new("redis://localhost:6379")
+            .with_stream("test:events")
+            .with_max_len(None)
+            .with_batch_size(0)
+            .with_claim_idle(Duration::from_secs(10))
+            .with_max_deliveries(3);
+
+        assert_eq!(config.stream, "test:events");
+        assert_eq!(config.max_len, None);
+        assert_eq!(config.batch_size, 1);
+        assert_eq!(config.claim_idle, Duration::from_secs(10));
+        assert_eq!(config.max_deliveries, 3);
+        assert_eq!(config.dead_letter_stream(), "test:events:dead");
+    }
+
+    #[test]
+    fn test_parse_read_reply() {
+        let event = Event::new("workflow.completed", serde_json::json!({"id": 1}));
+        let reply = Value::Bulk(vec![Value::Bulk(vec![
+            data("copilot:events"),
+            Value::Bulk(vec![entry("1-0", &event), entry("2-0", &event)]),
+        ])]);
+
+        let entries = parse_read_reply(&reply);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].id, "2-0");
+        assert_eq!(entries[0].field(TYPE_FIELD), Some("workflow.completed".as_bytes()));
+
+        let decoded: Event = serde_json::from_slice(entries[0].field(EVENT_FIELD).unwrap()).unwrap();
+        assert_eq!(decoded.id, event.id);
+
+        assert!(parse_read_reply(&Value::Nil).is_empty());
+    }
+
+    #[test]
+    fn test_parse_claimed_entry_without_fields() {
+        // Entries trimmed from the stream while pending come back without fields
+        let reply = Value::Bulk(vec![Value::Bulk(vec![data("3-0"), Value::Nil])]);
+
+        let entries = parse_entries(&reply);
+        assert_eq!(entries, vec![StreamEntry { id: "3-0".into(), fields: None }]);
+        assert!(entries[0].field(EVENT_FIELD).is_none());
+    }
+
+    #[test]
+    fn test_parse_pending() {
+        let reply = Value::Bulk(vec![
+            Value::Bulk(vec![data("1-0"), data("worker-a"), Value::Int(90_000), Value::Int(2)]),
+            Value::Bulk(vec![data("2-0"), data("worker-b"), Value::Int(61_000), Value::Int(5)]),
+        ]);
+
+        let pending = parse_pending(&reply);
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].consumer, "worker-a");
+        assert_eq!(pending[0].idle, Duration::from_secs(90));
+        assert_eq!(pending[1].deliveries, 5);
+    }
+
+    #[test]
+    fn test_xadd_command_caps_stream() {
+        let config = RedisStreamsConfig::default().with_max_len(Some(1000));
+        let event = Event::new("webhook.delivered", serde_json::json!({}));
+
+        let packed = String::from_utf8_lossy(
+            &xadd_cmd(&config, &config.stream, &event).unwrap().get_packed_command(),
+        )
+        .into_owned();
+        assert!(packed.contains("MAXLEN\r\n$1\r\n~\r\n$4\r\n1000"));
+        assert!(packed.contains("webhook.delivered"));
+    }
+}
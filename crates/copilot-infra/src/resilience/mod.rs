@@ -1,6 +1,8 @@
 //! Resilience patterns for fault-tolerant services
 //!
 //! Provides circuit breaker and retry policies for handling transient failures.
+//! Faults injected with [`ResilienceBuilder::with_faults`] exercise these
+//! policies in test environments.
 
 pub mod circuit_breaker;
 pub mod retry;
@@ -12,7 +14,9 @@ pub use retry::{RetryPolicy, RetryConfig, ExponentialBackoff, FixedDelay};
 pub use bulkhead::{Bulkhead, BulkheadConfig};
 pub use timeout::{TimeoutPolicy, TimeoutError};
 
+use copilot_core::fault::{Fault, FaultInjector};
 use std::future::Future;
+use std::sync::Arc;
 
 /// A resilient operation that combines multiple resilience patterns
 pub type ResilienceResult<T, E> = std::result::Result<T, ResilienceError<E>>;
@@ -59,9 +63,17 @@ pub struct ResilienceBuilder<E> {
     retry_policy: Option<RetryPolicy>,
     bulkhead: Option<Bulkhead>,
     timeout: Option<std::time::Duration>,
+    faults: Option<FaultHook<E>>,
     _marker: std::marker::PhantomData<E>,
 }
 
+/// Faults injected into the operations of a [`ResilienceBuilder`]
+struct FaultHook<E> {
+    injector: Arc<FaultInjector>,
+    target: String,
+    to_error: fn(Fault) -> E,
+}
+
 impl<E: std::error::Error + Clone + Send + 'static> ResilienceBuilder<E> {
     /// Create a new resilience builder
     pub fn new() -> Self {
@@ -70,6 +82,7 @@ impl<E: std::error::Error + Clone + Send + 'static> ResilienceBuilder<E> {
             retry_policy: None,
             bulkhead: None,
             timeout: None,
+            faults: None,
             _marker: std::marker::PhantomData,
         }
     }
@@ -98,6 +111,22 @@ impl<E: std::error::Error + Clone + Send + 'static> ResilienceBuilder<E> {
         self
     }
 
+    /// Subject each attempt to the faults `injector` has for `target`,
+    /// reporting injected failures as `to_error(fault)`
+    pub fn with_faults(
+        mut self,
+        injector: Arc<FaultInjector>,
+        target: impl Into<String>,
+        to_error: fn(Fault) -> E,
+    ) -> Self {
+        self.faults = Some(FaultHook {
+            injector,
+            target: target.into(),
+            to_error,
+        });
+        self
+    }
+
     /// Execute an operation with the configured resilience patterns
     pub async fn execute<F, Fut, T>(&self, operation: F) -> ResilienceResult<T, E>
     where
@@ -146,7 +175,21 @@ impl<E: std::error::Error + Clone + Send + 'static> ResilienceBuilder<E> {
         Fut: Future<Output = Result<T, E>> + Send,
         T: Send,
     {
-        let fut = operation();
+        let injection = self
+            .faults
+            .as_ref()
+            .map(|hook| (hook.injector.roll(&hook.target), hook.to_error));
+        let fut = async {
+            if let Some((injection, to_error)) = injection {
+                if let Some(delay) = injection.delay {
+                    tokio::time::sleep(delay).await;
+                }
+                if let Some(fault) = injection.fault {
+                    return Err(to_error(fault));
+                }
+            }
+            operation().await
+        };
 
         if let Some(timeout_duration) = self.timeout {
             match tokio::time::timeout(timeout_duration, fut).await {
@@ -223,3 +266,65 @@ impl<E: std::error::Error + Clone + Send + 'static> Default for ResilienceBuilde
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_core::{Determinism, FaultRule};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[derive(Debug, Clone, thiserror::Error)]
+    #[error("{0}")]
+    struct TestError(String);
+
+    fn injector(rule: FaultRule) -> Arc<FaultInjector> {
+        Arc::new(FaultInjector::new(&Determinism::seeded(1)).with_rules(vec![rule]))
+    }
+
+    #[tokio::test]
+    async fn test_injected_faults_are_retried_and_trip_the_breaker() {
+        let faults = injector(FaultRule::new("redis/*").with_error_rate(1.0));
+        let breaker = CircuitBreaker::new(
+            CircuitBreakerConfig::new("redis")
+                .with_failure_threshold(1)
+                .with_minimum_requests(1),
+        );
+        let builder = ResilienceBuilder::new()
+            .with_retry(RetryPolicy::new(RetryConfig {
+                max_retries: 2,
+                initial_delay: Duration::from_millis(1),
+                ..Default::default()
+            }))
+            .with_circuit_breaker(breaker)
+            .with_faults(faults.clone(), "redis/get", |fault| TestError(fault.to_string()));
+        let calls = AtomicUsize::new(0);
+
+        let result = builder
+            .execute(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, TestError>(())
+            })
+            .await;
+
+        assert!(matches!(result, Err(ResilienceError::RetriesExhausted(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(faults.stats().errors, 3);
+        assert!(matches!(
+            builder.execute(|| async { Ok::<_, TestError>(()) }).await,
+            Err(ResilienceError::CircuitOpen)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_injected_latency_counts_against_the_timeout() {
+        let faults = injector(FaultRule::new("*").with_latency(Duration::from_millis(200), 1.0));
+        let builder = ResilienceBuilder::new()
+            .with_timeout(Duration::from_millis(20))
+            .with_faults(faults, "postgres/query", |fault| TestError(fault.to_string()));
+
+        let result = builder.execute(|| async { Ok::<_, TestError>(()) }).await;
+
+        assert!(matches!(result, Err(ResilienceError::Timeout)));
+    }
+}
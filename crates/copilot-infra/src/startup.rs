@@ -0,0 +1,368 @@
+//! Startup dependency probing and degraded-mode tracking
+//!
+//! A [`DependencyMonitor`] probes the backing services (Postgres, Redis,
+//! NATS, the vector store) before the server starts taking traffic and keeps
+//! re-probing them afterwards. Required dependencies gate readiness; optional
+//! ones only mark the server as degraded while the components that use them
+//! run on their fallback. Components follow a dependency through
+//! [`DependencyMonitor::watch`] to switch back once it recovers.
+
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::health::{
+    DatabaseHealthCheck, HealthCheck, HealthStatus, HttpHealthCheck, RedisPingHealthCheck, TcpHealthCheck,
+};
+use crate::{InfraError, Result};
+
+/// What happens while a dependency is down
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Criticality {
+    /// The server is not ready without it
+    Required,
+    /// The server keeps serving, with the described fallback
+    Optional { fallback: String },
+}
+
+impl Criticality {
+    pub fn optional(fallback: impl Into<String>) -> Self {
+        Self::Optional {
+            fallback: fallback.into(),
+        }
+    }
+}
+
+/// Last known state of one dependency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentStatus {
+    pub name: String,
+    pub required: bool,
+    pub status: HealthStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Fallback in effect while an optional dependency is down
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<String>,
+    /// When the dependency entered its current status
+    pub since: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+impl ComponentStatus {
+    pub fn is_available(&self) -> bool {
+        !self.status.is_unhealthy()
+    }
+}
+
+/// Readiness of the server as a whole
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadinessReport {
+    /// Every required dependency is available
+    pub ready: bool,
+    /// Some optional dependency is down or a dependency reports degraded
+    pub degraded: bool,
+    pub components: Vec<ComponentStatus>,
+}
+
+impl ReadinessReport {
+    /// `ready`, `degraded` or `not_ready`
+    pub fn status(&self) -> &'static str {
+        match (self.ready, self.degraded) {
+            (false, _) => "not_ready",
+            (true, true) => "degraded",
+            (true, false) => "ready",
+        }
+    }
+}
+
+struct Dependency {
+    name: String,
+    check: Arc<dyn HealthCheck>,
+    criticality: Criticality,
+    status: parking_lot::Mutex<ComponentStatus>,
+    available: watch::Sender<bool>,
+}
+
+impl Dependency {
+    fn record(&self, status: HealthStatus, message: Option<String>) {
+        let now = Utc::now();
+        let available = !status.is_unhealthy();
+        let was_available = *self.available.borrow();
+        let first_probe = {
+            let mut current = self.status.lock();
+            let first_probe = current.checked_at.is_none();
+            if current.status != status {
+                current.since = now;
+            }
+            current.fallback = match (&self.criticality, available) {
+                (Criticality::Optional { fallback }, false) => Some(fallback.clone()),
+                _ => None,
+            };
+            current.status = status;
+            current.message = message.clone();
+            current.checked_at = Some(now);
+            first_probe
+        };
+
+        // Log transitions only, so a dependency that stays down does not
+        // warn on every probe
+        match (was_available, available) {
+            (false, true) if first_probe => info!(dependency = %self.name, "Dependency available"),
+            (false, true) => info!(dependency = %self.name, "Dependency recovered"),
+            (true, false) | (false, false) if was_available || first_probe => match &self.criticality {
+                Criticality::Required => warn!(
+                    dependency = %self.name,
+                    error = message.as_deref().unwrap_or_default(),
+                    "Required dependency unavailable; not ready"
+                ),
+                Criticality::Optional { fallback } => warn!(
+                    dependency = %self.name,
+                    error = message.as_deref().unwrap_or_default(),
+                    "Dependency unavailable; degraded: {}",
+                    fallback
+                ),
+            },
+            _ => {}
+        }
+        self.available.send_if_modified(|current| {
+            let changed = *current != available;
+            *current = available;
+            changed
+        });
+    }
+}
+
+/// Probes dependencies and tracks which are available
+pub struct DependencyMonitor {
+    dependencies: Vec<Dependency>,
+    probe_timeout: Duration,
+}
+
+impl Default for DependencyMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DependencyMonitor {
+    pub fn new() -> Self {
+        Self {
+            dependencies: Vec::new(),
+            probe_timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_probe_timeout(mut self, timeout: Duration) -> Self {
+        self.probe_timeout = timeout;
+        self
+    }
+
+    /// Track a dependency; it counts as down until first probed
+    pub fn with_dependency(
+        mut self,
+        name: impl Into<String>,
+        check: Arc<dyn HealthCheck>,
+        criticality: Criticality,
+    ) -> Self {
+        let name = name.into();
+        let status = ComponentStatus {
+            name: name.clone(),
+            required: criticality == Criticality::Required,
+            status: HealthStatus::Unhealthy,
+            message: Some("not probed yet".to_string()),
+            fallback: None,
+            since: Utc::now(),
+            checked_at: None,
+        };
+        self.dependencies.push(Dependency {
+            name,
+            check,
+            criticality,
+            status: parking_lot::Mutex::new(status),
+            available: watch::channel(false).0,
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dependencies.is_empty()
+    }
+
+    fn dependency(&self, name: &str) -> Option<&Dependency> {
+        self.dependencies.iter().find(|d| d.name == name)
+    }
+
+    /// Whether a dependency answered its last probe; `false` for unknown names
+    pub fn is_available(&self, name: &str) -> bool {
+        self.dependency(name).is_some_and(|d| *d.available.borrow())
+    }
+
+    /// Follow a dependency's availability, e.g. to leave a fallback once it
+    /// recovers
+    pub fn watch(&self, name: &str) -> Option<watch::Receiver<bool>> {
+        self.dependency(name).map(|d| d.available.subscribe())
+    }
+
+    /// Probe every dependency concurrently and return the resulting report
+    pub async fn probe_all(&self) -> ReadinessReport {
+        join_all(self.dependencies.iter().map(|dependency| async move {
+            match tokio::time::timeout(self.probe_timeout, dependency.check.check()).await {
+                Ok(Ok(result)) => dependency.record(result.status, result.message),
+                Ok(Err(e)) => dependency.record(HealthStatus::Unhealthy, Some(e.to_string())),
+                Err(_) => dependency.record(
+                    HealthStatus::Unhealthy,
+                    Some(format!("no answer within {:?}", self.probe_timeout)),
+                ),
+            }
+        }))
+        .await;
+        self.report()
+    }
+
+    /// Report from the last probes, without probing
+    pub fn report(&self) -> ReadinessReport {
+        let components: Vec<ComponentStatus> =
+            self.dependencies.iter().map(|d| d.status.lock().clone()).collect();
+        ReadinessReport {
+            ready: components.iter().all(|c| !c.required || c.is_available()),
+            degraded: components
+                .iter()
+                .any(|c| !c.status.is_healthy() && (!c.required || c.is_available())),
+            components,
+        }
+    }
+
+    /// Re-probe every `interval` so dependencies that come back are picked
+    /// up without a restart
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                self.probe_all().await;
+            }
+        })
+    }
+}
+
+/// Health check for a dependency URL, chosen by its scheme: `postgres://`,
+/// `redis://`, `nats://` (a TCP connect) or `http(s)://` (a GET that must
+/// succeed, e.g. a vector store's health endpoint). Nothing is contacted
+/// until the first check.
+pub fn check_for_url(url: &str) -> Result<Arc<dyn HealthCheck>> {
+    let (scheme, rest) = url
+        .split_once("://")
+        .ok_or_else(|| InfraError::Configuration(format!("{} is not a URL", url)))?;
+    Ok(match scheme {
+        "postgres" | "postgresql" => {
+            let pool = PgPoolOptions::new()
+                .max_connections(1)
+                .acquire_timeout(Duration::from_secs(5))
+                .connect_lazy(url)?;
+            Arc::new(DatabaseHealthCheck::new(pool))
+        }
+        "redis" | "rediss" => Arc::new(RedisPingHealthCheck::new(url)?),
+        "nats" | "tls" => {
+            let address = rest.rsplit('@').next().unwrap_or(rest).trim_end_matches('/');
+            let address = if address.contains(':') {
+                address.to_string()
+            } else {
+                format!("{}:4222", address)
+            };
+            Arc::new(TcpHealthCheck::new("nats", address))
+        }
+        "http" | "https" => Arc::new(HttpHealthCheck::new("http", url)),
+        other => {
+            return Err(InfraError::Configuration(format!(
+                "unsupported dependency scheme {}://",
+                other
+            )))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::health::HealthCheckResult;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct Switch(AtomicBool);
+
+    #[async_trait]
+    impl HealthCheck for Switch {
+        async fn check(&self) -> Result<HealthCheckResult> {
+            Ok(if self.0.load(Ordering::SeqCst) {
+                HealthCheckResult::healthy()
+            } else {
+                HealthCheckResult::unhealthy("connection refused")
+            })
+        }
+
+        fn name(&self) -> &str {
+            "switch"
+        }
+    }
+
+    fn switch(up: bool) -> Arc<Switch> {
+        Arc::new(Switch(AtomicBool::new(up)))
+    }
+
+    #[tokio::test]
+    async fn test_optional_dependency_degrades() {
+        let redis = switch(false);
+        let monitor = DependencyMonitor::new()
+            .with_dependency("postgres", switch(true), Criticality::Required)
+            .with_dependency("redis", redis.clone(), Criticality::optional("no semantic cache"));
+
+        let report = monitor.probe_all().await;
+        assert!(report.ready);
+        assert!(report.degraded);
+        assert_eq!(report.status(), "degraded");
+        let component = &report.components[1];
+        assert_eq!(component.fallback.as_deref(), Some("no semantic cache"));
+        assert_eq!(component.message.as_deref(), Some("connection refused"));
+        assert!(!monitor.is_available("redis"));
+
+        // Recovery is picked up by the next probe and announced to watchers
+        let mut watch = monitor.watch("redis").unwrap();
+        redis.0.store(true, Ordering::SeqCst);
+        let report = monitor.probe_all().await;
+        assert_eq!(report.status(), "ready");
+        assert!(report.components[1].fallback.is_none());
+        assert!(watch.has_changed().unwrap());
+        assert!(*watch.borrow_and_update());
+    }
+
+    #[tokio::test]
+    async fn test_required_dependency_blocks_readiness() {
+        let monitor = DependencyMonitor::new()
+            .with_dependency("postgres", switch(false), Criticality::Required);
+
+        assert!(!monitor.report().ready);
+        let report = monitor.probe_all().await;
+        assert_eq!(report.status(), "not_ready");
+        assert!(report.components[0].fallback.is_none());
+        assert!(report.components[0].checked_at.is_some());
+    }
+
+    #[test]
+    fn test_check_for_url() {
+        assert_eq!(check_for_url("nats://user:pw@bus.internal").unwrap().name(), "nats");
+        assert_eq!(check_for_url("redis://localhost:6379").unwrap().name(), "redis");
+        assert_eq!(check_for_url("http://qdrant:6333/healthz").unwrap().name(), "http");
+        assert!(check_for_url("ftp://example.com").is_err());
+        assert!(check_for_url("localhost:5432").is_err());
+    }
+}
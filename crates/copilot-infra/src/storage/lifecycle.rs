@@ -0,0 +1,133 @@
+//! Object expiry
+//!
+//! A [`LifecyclePolicy`] maps key prefixes to how long objects under them
+//! are kept. Applying it lists each prefix and deletes objects older than
+//! their rule allows; the longest matching prefix wins, so a tenant- or
+//! class-specific rule can override a broader one.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::debug;
+
+use super::{ObjectClass, ObjectStore};
+use crate::Result;
+
+/// Keep objects under `prefix` for `expire_after`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifecycleRule {
+    pub prefix: String,
+    pub expire_after: Duration,
+}
+
+/// Retention rules by key prefix
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifecyclePolicy {
+    rules: Vec<LifecycleRule>,
+}
+
+/// Outcome of applying a lifecycle policy
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifecycleReport {
+    /// Objects looked at
+    pub scanned: usize,
+    /// Keys of the objects deleted
+    pub expired: Vec<String>,
+}
+
+impl LifecyclePolicy {
+    /// A policy that keeps everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Each [`ObjectClass`] kept for its default retention
+    pub fn defaults() -> Self {
+        ObjectClass::ALL.iter().fold(Self::new(), |policy, class| {
+            policy.with_rule(format!("{}/", class.prefix()), class.default_retention())
+        })
+    }
+
+    /// Add a rule, replacing any rule for the same prefix
+    pub fn with_rule(mut self, prefix: impl Into<String>, expire_after: Duration) -> Self {
+        let prefix = prefix.into();
+        self.rules.retain(|rule| rule.prefix != prefix);
+        self.rules.push(LifecycleRule { prefix, expire_after });
+        self
+    }
+
+    pub fn rules(&self) -> &[LifecycleRule] {
+        &self.rules
+    }
+
+    /// The rule governing `key`: the one with the longest matching prefix
+    pub fn rule_for(&self, key: &str) -> Option<&LifecycleRule> {
+        self.rules
+            .iter()
+            .filter(|rule| key.starts_with(&rule.prefix))
+            .max_by_key(|rule| rule.prefix.len())
+    }
+
+    /// Delete the objects in `store` that have expired as of `now`
+    pub async fn apply(&self, store: &dyn ObjectStore, now: DateTime<Utc>) -> Result<LifecycleReport> {
+        let mut report = LifecycleReport::default();
+        // Nested prefixes list the same objects; only sweep the outermost
+        let roots = self.rules.iter().filter(|rule| {
+            !self
+                .rules
+                .iter()
+                .any(|other| other.prefix != rule.prefix && rule.prefix.starts_with(&other.prefix))
+        });
+
+        for root in roots {
+            for object in store.list(&root.prefix).await? {
+                report.scanned += 1;
+                let Some(rule) = self.rule_for(&object.key) else { continue };
+                let age = (now - object.last_modified).to_std().unwrap_or_default();
+                if age >= rule.expire_after {
+                    store.delete(&object.key).await?;
+                    report.expired.push(object.key);
+                }
+            }
+        }
+        debug!(
+            scanned = report.scanned,
+            expired = report.expired.len(),
+            "Applied object lifecycle policy"
+        );
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalObjectStore;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    #[tokio::test]
+    async fn test_expiry_by_longest_prefix() {
+        let root = std::env::temp_dir().join(format!("copilot-lifecycle-{}", uuid::Uuid::new_v4()));
+        let store = LocalObjectStore::new(root, "http://localhost/objects", b"key").unwrap();
+        for key in ["exports/acme/a", "exports/vip/b", "attachments/acme/c", "other/d"] {
+            store.put(key, b"x".to_vec(), "text/plain").await.unwrap();
+        }
+
+        let policy = LifecyclePolicy::defaults().with_rule("exports/vip/", 30 * DAY);
+        assert_eq!(policy.rule_for("exports/vip/b").unwrap().expire_after, 30 * DAY);
+        assert_eq!(policy.rule_for("exports/acme/a").unwrap().expire_after, DAY);
+        assert!(policy.rule_for("other/d").is_none());
+
+        let report = policy.apply(&store, Utc::now()).await.unwrap();
+        assert!(report.expired.is_empty());
+
+        let later = Utc::now() + chrono::Duration::days(2);
+        let report = policy.apply(&store, later).await.unwrap();
+        assert_eq!(report.scanned, 3);
+        assert_eq!(report.expired, vec!["exports/acme/a".to_string()]);
+        assert!(store.head("exports/acme/a").await.unwrap().is_none());
+        assert!(store.head("exports/vip/b").await.unwrap().is_some());
+        assert!(store.head("other/d").await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,311 @@
+//! Object storage in a local directory
+//!
+//! Objects live under `<root>/objects/<key>` with their content type and
+//! ETag in a sidecar file under `<root>/meta/`. Writes go to `<root>/tmp/`
+//! first and are renamed into place, so readers never see a partial
+//! object. Presigned URLs point at the server's object route and carry an
+//! HMAC over the method, key, expiry and required digest; the route checks
+//! them with [`LocalObjectStore::verify`].
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use super::{encode_uri, sha256_hex, validate_key, ObjectMeta, ObjectStore, PresignMethod, PresignRequest, PresignedUrl};
+use crate::{InfraError, Result};
+
+/// Query parameters of a presigned local URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PresignedQuery {
+    /// Expiry as a Unix timestamp
+    pub expires: i64,
+    /// Digest the uploaded body must have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub signature: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Sidecar {
+    content_type: Option<String>,
+    etag: Option<String>,
+}
+
+/// Objects in a directory on local disk
+pub struct LocalObjectStore {
+    root: PathBuf,
+    base_url: String,
+    signing_key: hmac::Key,
+}
+
+impl LocalObjectStore {
+    /// Store objects under `root`; presigned URLs start with `base_url` and
+    /// are signed with `signing_key`
+    pub fn new(root: impl Into<PathBuf>, base_url: impl Into<String>, signing_key: &[u8]) -> Result<Self> {
+        let root = root.into();
+        for dir in ["objects", "meta", "tmp"] {
+            std::fs::create_dir_all(root.join(dir))
+                .map_err(|e| io_error(&format!("create {}", root.join(dir).display()), e))?;
+        }
+        Ok(Self {
+            root,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            signing_key: hmac::Key::new(hmac::HMAC_SHA256, signing_key),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Check a presigned URL's signature and expiry for `method` on `key`
+    pub fn verify(&self, key: &str, method: PresignMethod, query: &PresignedQuery, now: DateTime<Utc>) -> Result<()> {
+        validate_key(key)?;
+        let signature = hex::decode(&query.signature)
+            .map_err(|_| InfraError::Storage("Malformed presigned URL signature".to_string()))?;
+        let message = string_to_sign(method, key, query.expires, query.sha256.as_deref());
+        hmac::verify(&self.signing_key, message.as_bytes(), &signature)
+            .map_err(|_| InfraError::Storage("Invalid presigned URL signature".to_string()))?;
+        if now.timestamp() > query.expires {
+            return Err(InfraError::Storage("Presigned URL has expired".to_string()));
+        }
+        Ok(())
+    }
+
+    fn object_path(&self, key: &str) -> PathBuf {
+        self.root.join("objects").join(key)
+    }
+
+    fn sidecar_path(&self, key: &str) -> PathBuf {
+        self.root.join("meta").join(format!("{}.json", key))
+    }
+
+    async fn meta(&self, key: &str, metadata: std::fs::Metadata) -> ObjectMeta {
+        let sidecar: Sidecar = match tokio::fs::read(self.sidecar_path(key)).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+            Err(_) => Sidecar::default(),
+        };
+        ObjectMeta {
+            key: key.to_string(),
+            size: metadata.len(),
+            content_type: sidecar.content_type,
+            last_modified: metadata.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now()),
+            etag: sidecar.etag,
+        }
+    }
+}
+
+fn string_to_sign(method: PresignMethod, key: &str, expires: i64, sha256: Option<&str>) -> String {
+    format!("{}\n{}\n{}\n{}", method, key, expires, sha256.unwrap_or_default())
+}
+
+fn io_error(action: &str, error: std::io::Error) -> InfraError {
+    InfraError::Storage(format!("Failed to {}: {}", action, error))
+}
+
+async fn write_atomic(tmp_dir: &Path, path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| io_error(&format!("create {}", parent.display()), e))?;
+    }
+    let tmp = tmp_dir.join(uuid::Uuid::new_v4().to_string());
+    tokio::fs::write(&tmp, data)
+        .await
+        .map_err(|e| io_error(&format!("write {}", tmp.display()), e))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .map_err(|e| io_error(&format!("move object into {}", path.display()), e))
+}
+
+#[async_trait]
+impl ObjectStore for LocalObjectStore {
+    fn backend(&self) -> &'static str {
+        "local"
+    }
+
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<ObjectMeta> {
+        validate_key(key)?;
+        let tmp_dir = self.root.join("tmp");
+        let sidecar = Sidecar {
+            content_type: Some(content_type.to_string()),
+            etag: Some(sha256_hex(&data)),
+        };
+        write_atomic(&tmp_dir, &self.sidecar_path(key), &serde_json::to_vec(&sidecar)?).await?;
+        write_atomic(&tmp_dir, &self.object_path(key), &data).await?;
+
+        self.head(key)
+            .await?
+            .ok_or_else(|| InfraError::Storage(format!("Object {} vanished after writing", key)))
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        validate_key(key)?;
+        match tokio::fs::read(self.object_path(key)).await {
+            Ok(data) => Ok(data),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(InfraError::NotFound(format!("Object {}", key))),
+            Err(e) => Err(io_error(&format!("read {}", key), e)),
+        }
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<ObjectMeta>> {
+        validate_key(key)?;
+        match tokio::fs::metadata(self.object_path(key)).await {
+            Ok(metadata) if metadata.is_file() => Ok(Some(self.meta(key, metadata).await)),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error(&format!("stat {}", key), e)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        validate_key(key)?;
+        for path in [self.object_path(key), self.sidecar_path(key)] {
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(io_error(&format!("delete {}", key), e)),
+            }
+        }
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectMeta>> {
+        let objects = self.root.join("objects");
+        // Start from the deepest directory the prefix names
+        let start = match prefix.rfind('/') {
+            Some(end) => objects.join(&prefix[..end]),
+            None => objects.clone(),
+        };
+
+        let mut found = Vec::new();
+        let mut pending = vec![start];
+        while let Some(dir) = pending.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(io_error(&format!("list {}", dir.display()), e)),
+            };
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .map_err(|e| io_error(&format!("list {}", dir.display()), e))?
+            {
+                let metadata = entry
+                    .metadata()
+                    .await
+                    .map_err(|e| io_error(&format!("stat {}", entry.path().display()), e))?;
+                if metadata.is_dir() {
+                    pending.push(entry.path());
+                    continue;
+                }
+                let Ok(relative) = entry.path().strip_prefix(&objects).map(Path::to_path_buf) else { continue };
+                let key = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                if key.starts_with(prefix) {
+                    found.push(self.meta(&key, metadata).await);
+                }
+            }
+        }
+        found.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(found)
+    }
+
+    fn presign(&self, key: &str, request: &PresignRequest) -> Result<PresignedUrl> {
+        validate_key(key)?;
+        let expires_at = Utc::now()
+            + chrono::Duration::from_std(request.expires_in)
+                .map_err(|_| InfraError::Storage("Presigned URL lifetime too long".to_string()))?;
+        let expires = expires_at.timestamp();
+        let sha256 = request.content_sha256.as_deref();
+
+        let message = string_to_sign(request.method, key, expires, sha256);
+        let signature = hex::encode(hmac::sign(&self.signing_key, message.as_bytes()).as_ref());
+        let mut url = format!("{}/{}?expires={}", self.base_url, encode_uri(key, false), expires);
+        if let Some(sha256) = sha256 {
+            url.push_str(&format!("&sha256={}", sha256));
+        }
+        url.push_str(&format!("&signature={}", signature));
+
+        Ok(PresignedUrl {
+            url,
+            method: request.method,
+            expires_at,
+            headers: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn store() -> LocalObjectStore {
+        let root = std::env::temp_dir().join(format!("copilot-local-store-{}", uuid::Uuid::new_v4()));
+        LocalObjectStore::new(root, "http://localhost:8080/api/v1/objects/", b"signing-key").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_put_get_list_delete() {
+        let store = store();
+        let meta = store.put("artifacts/acme/out.txt", b"hello".to_vec(), "text/plain").await.unwrap();
+        assert_eq!(meta.size, 5);
+        assert_eq!(meta.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(meta.etag, Some(sha256_hex(b"hello")));
+
+        store.put("artifacts/acme/nested/log.txt", b"log".to_vec(), "text/plain").await.unwrap();
+        store.put("artifacts/globex/out.txt", b"other".to_vec(), "text/plain").await.unwrap();
+        assert_eq!(store.get("artifacts/acme/out.txt").await.unwrap(), b"hello");
+
+        let keys: Vec<String> = store.list("artifacts/acme/").await.unwrap().into_iter().map(|m| m.key).collect();
+        assert_eq!(keys, vec!["artifacts/acme/nested/log.txt", "artifacts/acme/out.txt"]);
+        assert_eq!(store.list("artifacts/a").await.unwrap().len(), 2);
+        assert!(store.list("missing/").await.unwrap().is_empty());
+
+        store.delete("artifacts/acme/out.txt").await.unwrap();
+        store.delete("artifacts/acme/out.txt").await.unwrap();
+        assert!(matches!(store.get("artifacts/acme/out.txt").await, Err(InfraError::NotFound(_))));
+        assert!(store.put("../escape", b"x".to_vec(), "text/plain").await.is_err());
+    }
+
+    #[test]
+    fn test_presigned_urls() {
+        let store = store();
+        let url = store
+            .presign(
+                "attachments/acme/a b.txt",
+                &PresignRequest::put(Duration::from_secs(60)).with_content_sha256("ab12"),
+            )
+            .unwrap();
+        assert!(url
+            .url
+            .starts_with("http://localhost:8080/api/v1/objects/attachments/acme/a%20b.txt?expires="));
+
+        let query: PresignedQuery = serde_json::from_value(serde_json::json!({
+            "expires": url.expires_at.timestamp(),
+            "sha256": "ab12",
+            "signature": url.url.rsplit("signature=").next().unwrap(),
+        }))
+        .unwrap();
+        let now = Utc::now();
+        store.verify("attachments/acme/a b.txt", PresignMethod::Put, &query, now).unwrap();
+
+        // The signature covers the method, key and digest, and expires
+        assert!(store.verify("attachments/acme/a b.txt", PresignMethod::Get, &query, now).is_err());
+        assert!(store.verify("attachments/acme/other", PresignMethod::Put, &query, now).is_err());
+        let tampered = PresignedQuery {
+            sha256: Some("cd34".to_string()),
+            ..query.clone()
+        };
+        assert!(store.verify("attachments/acme/a b.txt", PresignMethod::Put, &tampered, now).is_err());
+        let later = now + chrono::Duration::minutes(2);
+        assert!(store.verify("attachments/acme/a b.txt", PresignMethod::Put, &query, later).is_err());
+    }
+}
@@ -0,0 +1,408 @@
+//! Object storage for chat attachments, sandbox artifacts and export bundles
+//!
+//! Two [`ObjectStore`] backends are provided:
+//! - [`S3ObjectStore`] talks to AWS S3 or an S3-compatible store such as
+//!   MinIO, signing requests with AWS Signature Version 4.
+//! - [`LocalObjectStore`] keeps objects in a directory, for development and
+//!   single-node deployments. Its presigned URLs point back at the server,
+//!   which checks them with [`LocalObjectStore::verify`].
+//!
+//! [`ObjectStorage`] sits on top of either: it files objects under
+//! content-addressed keys per [`ObjectClass`] and tenant, so identical
+//! uploads are stored once, hands out presigned URLs, and expires objects
+//! according to a [`LifecyclePolicy`].
+
+pub mod lifecycle;
+pub mod local;
+pub mod s3;
+
+pub use lifecycle::{LifecyclePolicy, LifecycleReport, LifecycleRule};
+pub use local::{LocalObjectStore, PresignedQuery};
+pub use s3::{S3Config, S3ObjectStore};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::{InfraError, Result};
+
+/// Longest key accepted, in bytes (the S3 limit)
+pub const MAX_KEY_LEN: usize = 1024;
+
+/// HTTP method a presigned URL is good for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PresignMethod {
+    Get,
+    Put,
+}
+
+impl PresignMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PresignMethod::Get => "GET",
+            PresignMethod::Put => "PUT",
+        }
+    }
+}
+
+impl fmt::Display for PresignMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What a presigned URL should allow
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresignRequest {
+    pub method: PresignMethod,
+    pub expires_in: Duration,
+    /// Hex SHA-256 the uploaded body must have; only for uploads
+    pub content_sha256: Option<String>,
+}
+
+impl PresignRequest {
+    pub fn get(expires_in: Duration) -> Self {
+        Self {
+            method: PresignMethod::Get,
+            expires_in,
+            content_sha256: None,
+        }
+    }
+
+    pub fn put(expires_in: Duration) -> Self {
+        Self {
+            method: PresignMethod::Put,
+            expires_in,
+            content_sha256: None,
+        }
+    }
+
+    /// Only accept an upload whose body hashes to `sha256`
+    pub fn with_content_sha256(mut self, sha256: impl Into<String>) -> Self {
+        self.content_sha256 = Some(sha256.into());
+        self
+    }
+}
+
+/// A URL that grants one method on one object until it expires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PresignedUrl {
+    pub url: String,
+    pub method: PresignMethod,
+    pub expires_at: DateTime<Utc>,
+    /// Headers the client must send with the request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+}
+
+/// A stored object's metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectMeta {
+    pub key: String,
+    pub size: u64,
+    pub content_type: Option<String>,
+    pub last_modified: DateTime<Utc>,
+    pub etag: Option<String>,
+}
+
+/// Blob storage addressed by key
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    /// Backend name, for logs and health output
+    fn backend(&self) -> &'static str;
+
+    /// Store `data` under `key`, replacing any existing object
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<ObjectMeta>;
+
+    /// Read an object; [`InfraError::NotFound`] if there is none
+    async fn get(&self, key: &str) -> Result<Vec<u8>>;
+
+    /// An object's metadata, if it exists
+    async fn head(&self, key: &str) -> Result<Option<ObjectMeta>>;
+
+    /// Delete an object; deleting a missing object succeeds
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Every object whose key starts with `prefix`
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectMeta>>;
+
+    /// A URL that lets a client read or write `key` directly
+    fn presign(&self, key: &str, request: &PresignRequest) -> Result<PresignedUrl>;
+}
+
+/// Reject keys that are empty, too long, absolute, or could escape a
+/// directory on disk
+pub fn validate_key(key: &str) -> Result<()> {
+    let invalid = |reason: &str| Err(InfraError::Storage(format!("Invalid object key '{}': {}", key, reason)));
+    if key.is_empty() {
+        return invalid("empty");
+    }
+    if key.len() > MAX_KEY_LEN {
+        return invalid("longer than 1024 bytes");
+    }
+    if key.chars().any(|c| c.is_control() || c == '\\') {
+        return invalid("contains control characters or backslashes");
+    }
+    if key.split('/').any(|segment| segment.is_empty() || segment == "." || segment == "..") {
+        return invalid("empty, '.' or '..' path segment");
+    }
+    Ok(())
+}
+
+/// Percent-encode everything but unreserved characters, and `/` unless
+/// `encode_slash`
+pub(crate) fn encode_uri(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Hex SHA-256 of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// What an object is for; each class has its own key prefix and retention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectClass {
+    /// Files attached to chat messages
+    Attachment,
+    /// Files produced by code run in a sandbox
+    SandboxArtifact,
+    /// Export bundles, such as context exports
+    ExportBundle,
+}
+
+impl ObjectClass {
+    pub const ALL: [ObjectClass; 3] = [ObjectClass::Attachment, ObjectClass::SandboxArtifact, ObjectClass::ExportBundle];
+
+    /// Key prefix objects of this class are stored under
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            ObjectClass::Attachment => "attachments",
+            ObjectClass::SandboxArtifact => "artifacts",
+            ObjectClass::ExportBundle => "exports",
+        }
+    }
+
+    /// How long objects of this class are kept by default
+    pub fn default_retention(&self) -> Duration {
+        const DAY: u64 = 24 * 60 * 60;
+        match self {
+            ObjectClass::Attachment => Duration::from_secs(90 * DAY),
+            ObjectClass::SandboxArtifact => Duration::from_secs(7 * DAY),
+            ObjectClass::ExportBundle => Duration::from_secs(DAY),
+        }
+    }
+}
+
+/// Content-addressed key of an object with hex SHA-256 `sha256`
+pub fn content_key(class: ObjectClass, tenant_id: &str, sha256: &str) -> Result<String> {
+    if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(InfraError::Storage(format!("Invalid SHA-256 digest '{}'", sha256)));
+    }
+    if tenant_id.contains('/') {
+        return Err(InfraError::Storage(format!("Invalid tenant ID '{}' in object key", tenant_id)));
+    }
+    let key = format!("{}/{}/sha256/{}", class.prefix(), tenant_id, sha256.to_ascii_lowercase());
+    validate_key(&key)?;
+    Ok(key)
+}
+
+/// An object stored through [`ObjectStorage`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredObject {
+    pub key: String,
+    pub class: ObjectClass,
+    pub sha256: String,
+    pub size: u64,
+    pub content_type: String,
+    /// The same content was already stored, so nothing was written
+    pub deduplicated: bool,
+}
+
+/// Content-addressed storage of attachments, artifacts and export bundles
+pub struct ObjectStorage {
+    store: Arc<dyn ObjectStore>,
+    lifecycle: LifecyclePolicy,
+    url_ttl: Duration,
+}
+
+impl ObjectStorage {
+    pub fn new(store: Arc<dyn ObjectStore>) -> Self {
+        Self {
+            store,
+            lifecycle: LifecyclePolicy::defaults(),
+            url_ttl: Duration::from_secs(15 * 60),
+        }
+    }
+
+    pub fn with_lifecycle(mut self, lifecycle: LifecyclePolicy) -> Self {
+        self.lifecycle = lifecycle;
+        self
+    }
+
+    /// How long presigned URLs stay valid
+    pub fn with_url_ttl(mut self, ttl: Duration) -> Self {
+        self.url_ttl = ttl;
+        self
+    }
+
+    pub fn store(&self) -> &Arc<dyn ObjectStore> {
+        &self.store
+    }
+
+    pub fn lifecycle(&self) -> &LifecyclePolicy {
+        &self.lifecycle
+    }
+
+    /// Store `data` under its content-addressed key, unless it is there
+    /// already
+    pub async fn put(
+        &self,
+        class: ObjectClass,
+        tenant_id: &str,
+        data: Vec<u8>,
+        content_type: &str,
+    ) -> Result<StoredObject> {
+        let sha256 = sha256_hex(&data);
+        let key = content_key(class, tenant_id, &sha256)?;
+        let size = data.len() as u64;
+
+        let deduplicated = self.store.head(&key).await?.is_some_and(|meta| meta.size == size);
+        if !deduplicated {
+            self.store.put(&key, data, content_type).await?;
+        }
+        debug!(key = %key, size, deduplicated, "Stored object");
+        Ok(StoredObject {
+            key,
+            class,
+            sha256,
+            size,
+            content_type: content_type.to_string(),
+            deduplicated,
+        })
+    }
+
+    /// Read the object of `class` with digest `sha256`
+    pub async fn get(&self, class: ObjectClass, tenant_id: &str, sha256: &str) -> Result<Vec<u8>> {
+        self.store.get(&content_key(class, tenant_id, sha256)?).await
+    }
+
+    /// A URL the client can upload content with digest `sha256` to; the
+    /// store rejects a body with any other digest
+    pub fn presign_upload(&self, class: ObjectClass, tenant_id: &str, sha256: &str) -> Result<(String, PresignedUrl)> {
+        let key = content_key(class, tenant_id, sha256)?;
+        let request = PresignRequest::put(self.url_ttl).with_content_sha256(sha256.to_ascii_lowercase());
+        let url = self.store.presign(&key, &request)?;
+        Ok((key, url))
+    }
+
+    /// A URL the client can download `key` from
+    pub fn presign_download(&self, key: &str) -> Result<PresignedUrl> {
+        self.store.presign(key, &PresignRequest::get(self.url_ttl))
+    }
+
+    /// Delete objects the lifecycle policy says have expired
+    pub async fn expire(&self) -> Result<LifecycleReport> {
+        self.lifecycle.apply(self.store.as_ref(), Utc::now()).await
+    }
+
+    /// Apply the lifecycle policy every `interval` in the background
+    pub fn spawn_lifecycle(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                match self.expire().await {
+                    Ok(report) if !report.expired.is_empty() => {
+                        info!(
+                            backend = self.store.backend(),
+                            expired = report.expired.len(),
+                            "Expired stored objects"
+                        );
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!(backend = self.store.backend(), "Object lifecycle sweep failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store() -> Arc<LocalObjectStore> {
+        let root = std::env::temp_dir().join(format!("copilot-objects-{}", uuid::Uuid::new_v4()));
+        Arc::new(LocalObjectStore::new(root, "http://localhost:8080/api/v1/objects", b"signing-key").unwrap())
+    }
+
+    #[test]
+    fn test_key_validation() {
+        assert!(validate_key("attachments/acme/sha256/abc").is_ok());
+        for key in ["", "/etc/passwd", "a/../b", "a//b", "a/./b", "trailing/", "back\\slash", "nul\0"] {
+            assert!(validate_key(key).is_err(), "{:?} should be rejected", key);
+        }
+        assert!(validate_key(&"k".repeat(MAX_KEY_LEN + 1)).is_err());
+
+        let digest = sha256_hex(b"hello");
+        assert_eq!(
+            content_key(ObjectClass::SandboxArtifact, "acme", &digest).unwrap(),
+            format!("artifacts/acme/sha256/{}", digest)
+        );
+        assert!(content_key(ObjectClass::Attachment, "acme", "not-a-digest").is_err());
+        assert!(content_key(ObjectClass::Attachment, "..", &digest).is_err());
+        assert!(content_key(ObjectClass::Attachment, "acme/other", &digest).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_content_addressed_storage_deduplicates() {
+        let storage = ObjectStorage::new(temp_store());
+
+        let first = storage
+            .put(ObjectClass::Attachment, "acme", b"report".to_vec(), "text/plain")
+            .await
+            .unwrap();
+        assert!(!first.deduplicated);
+        assert_eq!(first.key, format!("attachments/acme/sha256/{}", sha256_hex(b"report")));
+
+        let second = storage
+            .put(ObjectClass::Attachment, "acme", b"report".to_vec(), "text/plain")
+            .await
+            .unwrap();
+        assert!(second.deduplicated);
+        assert_eq!(second.key, first.key);
+
+        let other_tenant = storage
+            .put(ObjectClass::Attachment, "globex", b"report".to_vec(), "text/plain")
+            .await
+            .unwrap();
+        assert!(!other_tenant.deduplicated);
+
+        let data = storage.get(ObjectClass::Attachment, "acme", &first.sha256).await.unwrap();
+        assert_eq!(data, b"report");
+        assert_eq!(storage.store().list("attachments/").await.unwrap().len(), 2);
+
+        let (key, url) = storage
+            .presign_upload(ObjectClass::ExportBundle, "acme", &sha256_hex(b"bundle"))
+            .unwrap();
+        assert!(key.starts_with("exports/acme/sha256/"));
+        assert_eq!(url.method, PresignMethod::Put);
+    }
+}
@@ -0,0 +1,292 @@
+//! Cold-start corpus bootstrap
+//!
+//! [`CorpusBootstrap`] ingests every page of a [`WikiExport`] in one go, so a
+//! new deployment starts out with its team's existing documentation rather
+//! than an empty knowledge base. Pages go through the ingestion pipeline,
+//! which should carry a [`SummaryStage`](crate::SummaryStage); their chunks,
+//! tagged with the page's space and breadcrumb, and their summary, as one more
+//! chunk, go to the sink. With [`CorpusBootstrap::with_index`] pages are also
+//! indexed hierarchically. The [`BootstrapReport`] tells how much of the
+//! export made it into the knowledge base, overall and per space.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{info, warn};
+
+use copilot_context::HierarchicalIndex;
+
+use crate::pipeline::{IngestionPipeline, ProcessedChunk};
+use crate::processors::compute_hash;
+use crate::rules::TAGS_KEY;
+use crate::streaming::ChunkSink;
+use crate::summaries::index_hierarchically;
+use crate::wiki::{WikiExport, WikiPage};
+
+/// Space reported for pages that belong to none
+pub const NO_SPACE: &str = "(none)";
+
+/// Failures listed in a report; later ones are only counted
+const MAX_LISTED_FAILURES: usize = 50;
+
+/// How much of one space was ingested
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpaceCoverage {
+    pub space: String,
+    pub pages: usize,
+    pub ingested: usize,
+    pub summarized: usize,
+    pub chunks: usize,
+}
+
+/// A page that could not be ingested
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BootstrapFailure {
+    pub page: String,
+    pub error: String,
+}
+
+/// What a bootstrap ingested
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BootstrapReport {
+    /// Pages processed so far
+    pub pages: usize,
+    pub ingested: usize,
+    /// Pages without text, such as ones holding only attachments
+    pub empty: usize,
+    /// Pages an ingestion rule left out
+    pub skipped: usize,
+    pub failed: usize,
+    /// Chunks handed to the sink, summaries included
+    pub chunks: usize,
+    /// Ingested pages that got a summary
+    pub summarized: usize,
+    /// Share of the pages with text that were ingested
+    pub coverage: f64,
+    /// Share of the ingested pages that got a summary
+    pub summary_coverage: f64,
+    pub spaces: Vec<SpaceCoverage>,
+    /// The first failures
+    #[serde(default)]
+    pub failures: Vec<BootstrapFailure>,
+    pub processing_time_ms: u64,
+}
+
+impl BootstrapReport {
+    fn space(&mut self, page: &WikiPage) -> &mut SpaceCoverage {
+        let name = page.space.as_deref().unwrap_or(NO_SPACE);
+        let index = match self.spaces.iter().position(|space| space.space == name) {
+            Some(index) => index,
+            None => {
+                self.spaces.push(SpaceCoverage { space: name.to_string(), ..Default::default() });
+                self.spaces.len() - 1
+            }
+        };
+        &mut self.spaces[index]
+    }
+
+    fn fail(&mut self, page: &WikiPage, error: impl Into<String>) {
+        self.failed += 1;
+        if self.failures.len() < MAX_LISTED_FAILURES {
+            self.failures.push(BootstrapFailure { page: page.document_name(), error: error.into() });
+        }
+    }
+
+    /// Work out the coverage ratios once every page is processed
+    pub fn finish(&mut self, processing_time_ms: u64) {
+        let eligible = self.pages - self.empty - self.skipped;
+        self.coverage = if eligible == 0 { 1.0 } else { self.ingested as f64 / eligible as f64 };
+        self.summary_coverage =
+            if self.ingested == 0 { 0.0 } else { self.summarized as f64 / self.ingested as f64 };
+        self.spaces.sort_by(|a, b| a.space.cmp(&b.space));
+        self.processing_time_ms = processing_time_ms;
+    }
+}
+
+/// Ingests whole wiki exports
+pub struct CorpusBootstrap {
+    pipeline: Arc<IngestionPipeline>,
+    sink: Arc<dyn ChunkSink>,
+    index: Option<Arc<HierarchicalIndex>>,
+}
+
+impl CorpusBootstrap {
+    pub fn new(pipeline: Arc<IngestionPipeline>, sink: Arc<dyn ChunkSink>) -> Self {
+        Self { pipeline, sink, index: None }
+    }
+
+    /// Also index each page's summary and chunks hierarchically
+    pub fn with_index(mut self, index: Arc<HierarchicalIndex>) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Ingest every page of `export`, attributing the content to `source`
+    pub async fn run(&self, export: &WikiExport, source: &str) -> BootstrapReport {
+        let started = Instant::now();
+        let mut report = BootstrapReport::default();
+        for page in &export.pages {
+            self.ingest_page(page, source, &mut report).await;
+        }
+        report.finish(started.elapsed().as_millis() as u64);
+        info!(
+            pages = report.pages,
+            ingested = report.ingested,
+            failed = report.failed,
+            chunks = report.chunks,
+            "Bootstrapped corpus from wiki export"
+        );
+        report
+    }
+
+    /// Ingest one page, counting the outcome in `report`
+    pub async fn ingest_page(&self, page: &WikiPage, source: &str, report: &mut BootstrapReport) {
+        report.pages += 1;
+        report.space(page).pages += 1;
+        if page.body.trim().is_empty() {
+            report.empty += 1;
+            return;
+        }
+
+        let result = self.pipeline.ingest(page.to_document(source)).await;
+        if result.skipped_by.is_some() {
+            report.skipped += 1;
+            return;
+        }
+        if !result.success {
+            warn!(page = %page.document_name(), error = ?result.error, "Failed to ingest wiki page");
+            report.fail(page, result.error.clone().unwrap_or_else(|| "ingestion failed".to_string()));
+            return;
+        }
+
+        let metadata = page.to_metadata();
+        let mut chunks = result.chunks.clone();
+        let summary = result.summary.as_ref().filter(|summary| !summary.is_empty());
+        if let Some(summary) = summary {
+            chunks.push(summary_chunk(page, &result.document_id, &summary.text()));
+        }
+        for chunk in &mut chunks {
+            chunk.metadata.extend(metadata.clone());
+        }
+
+        let mut stored = 0;
+        for chunk in chunks {
+            if let Err(e) = self.sink.accept(chunk).await {
+                warn!(page = %page.document_name(), error = %e, "Failed to store wiki page chunk");
+                report.chunks += stored;
+                report.fail(page, e.to_string());
+                return;
+            }
+            stored += 1;
+        }
+        if let Some(index) = &self.index {
+            if let Err(e) = index_hierarchically(index, &result).await {
+                warn!(page = %page.document_name(), error = %e, "Failed to index wiki page");
+            }
+        }
+
+        report.ingested += 1;
+        report.chunks += stored;
+        let space = report.space(page);
+        space.ingested += 1;
+        space.chunks += stored;
+        if summary.is_some() {
+            report.summarized += 1;
+            report.space(page).summarized += 1;
+        }
+    }
+}
+
+/// The page's summary as a chunk of its own, so plain retrieval can match it
+fn summary_chunk(page: &WikiPage, document_id: &str, summary: &str) -> ProcessedChunk {
+    let content = format!("{} (summary): {}", page.title, summary);
+    let mut tags = vec![serde_json::json!("wiki"), serde_json::json!("summary")];
+    tags.extend(page.labels.iter().map(|label| serde_json::json!(label)));
+    ProcessedChunk {
+        id: format!("{}_summary", document_id),
+        document_id: document_id.to_string(),
+        content_hash: compute_hash(&content),
+        content,
+        metadata: [
+            ("summary".to_string(), serde_json::json!(true)),
+            (TAGS_KEY.to_string(), serde_json::Value::Array(tags)),
+        ]
+        .into_iter()
+        .collect(),
+        embedding: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::PipelineConfig;
+    use crate::summaries::{ExtractiveSummarizer, SummaryStage};
+    use crate::wiki::WikiFormat;
+    use crate::Result;
+    use async_trait::async_trait;
+    use copilot_context::{HierarchicalConfig, HybridSearchConfig, MockEmbeddingProvider};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct CollectingSink(Mutex<Vec<ProcessedChunk>>);
+
+    #[async_trait]
+    impl ChunkSink for CollectingSink {
+        async fn accept(&self, chunk: ProcessedChunk) -> Result<()> {
+            self.0.lock().unwrap().push(chunk);
+            Ok(())
+        }
+    }
+
+    fn page(id: &str, space: Option<&str>, body: &str) -> WikiPage {
+        WikiPage {
+            id: id.to_string(),
+            title: format!("Page {}", id),
+            space: space.map(String::from),
+            path: Vec::new(),
+            body: body.to_string(),
+            labels: Vec::new(),
+            updated_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_reports_coverage() {
+        let mut pipeline = IngestionPipeline::with_defaults(PipelineConfig::default()).unwrap();
+        pipeline.insert_stage(1, Arc::new(SummaryStage::new(Arc::new(ExtractiveSummarizer::new())).with_min_chars(0)));
+        let sink = Arc::new(CollectingSink::default());
+        let index = Arc::new(HierarchicalIndex::new(
+            HierarchicalConfig::default(),
+            HybridSearchConfig::default(),
+            Arc::new(MockEmbeddingProvider::new(32)),
+        ));
+        let bootstrap = CorpusBootstrap::new(Arc::new(pipeline), sink.clone()).with_index(index.clone());
+
+        let export = WikiExport {
+            format: WikiFormat::Markdown,
+            pages: vec![
+                page("deploys", Some("Engineering"), "Deploy with make release. Watch the canary for ten minutes."),
+                page("attachments", Some("Engineering"), "  "),
+                page("pto", Some("People"), "Request time off in the HR portal. Managers approve requests."),
+            ],
+        };
+        let report = bootstrap.run(&export, "wiki").await;
+
+        assert_eq!((report.pages, report.ingested, report.empty, report.failed), (3, 2, 1, 0));
+        assert_eq!(report.summarized, 2);
+        assert_eq!(report.coverage, 1.0);
+        assert_eq!(report.summary_coverage, 1.0);
+        assert_eq!(report.spaces.iter().map(|s| s.space.as_str()).collect::<Vec<_>>(), vec!["Engineering", "People"]);
+        assert_eq!(report.spaces[0].pages, 2);
+        assert_eq!(report.spaces[0].ingested, 1);
+
+        let chunks = sink.0.lock().unwrap().clone();
+        assert_eq!(report.chunks, chunks.len());
+        let summary = chunks.iter().find(|c| c.id == "wiki:deploys_summary").expect("summary chunk");
+        assert!(summary.content.starts_with("Page deploys (summary): "));
+        assert_eq!(summary.metadata["wiki_space"], "Engineering");
+        assert!(chunks.iter().all(|c| c.metadata["document"] == "Engineering/Page deploys" || c.metadata["wiki_space"] == "People"));
+        assert!(index.summary_text("wiki:pto").await.is_some());
+    }
+}
@@ -0,0 +1,180 @@
+//! Chunk-level change detection
+//!
+//! Re-ingesting a document usually changes a few of its chunks. With a
+//! [`ChunkStore`] set ([`IngestionPipeline::with_chunk_store`](crate::IngestionPipeline::with_chunk_store)),
+//! the pipeline diffs the new chunks against the stored version by content
+//! hash:
+//!
+//! - an unchanged chunk keeps the ID it had, even when edits elsewhere moved
+//!   it, so citations and caches keyed by chunk ID stay valid, and keeps its
+//!   stored embedding instead of being embedded again
+//! - a changed chunk gets an ID no stored chunk had, so nothing keyed by a
+//!   stored chunk's ID ever sees different content under it
+//! - chunks the new version no longer has are reported as removed, for the
+//!   caller to delete downstream
+
+use async_trait::async_trait;
+use copilot_context::Embedding;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::RwLock;
+
+use crate::pipeline::ProcessedChunk;
+use crate::Result;
+
+/// A chunk of the stored version of a document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredChunk {
+    pub id: String,
+    pub content_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Embedding>,
+}
+
+impl From<&ProcessedChunk> for StoredChunk {
+    fn from(chunk: &ProcessedChunk) -> Self {
+        Self {
+            id: chunk.id.clone(),
+            content_hash: chunk.content_hash.clone(),
+            embedding: chunk.embedding.clone(),
+        }
+    }
+}
+
+/// Keeps the last ingested version of each document's chunks
+#[async_trait]
+pub trait ChunkStore: Send + Sync {
+    /// Chunks of the stored version of a document, in order; empty when it
+    /// was never ingested
+    async fn load(&self, document_id: &str) -> Result<Vec<StoredChunk>>;
+
+    /// Replace the stored version of a document
+    async fn save(&self, document_id: &str, chunks: Vec<StoredChunk>) -> Result<()>;
+}
+
+/// In-process [`ChunkStore`]
+#[derive(Default)]
+pub struct MemoryChunkStore {
+    documents: RwLock<HashMap<String, Vec<StoredChunk>>>,
+}
+
+impl MemoryChunkStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ChunkStore for MemoryChunkStore {
+    async fn load(&self, document_id: &str) -> Result<Vec<StoredChunk>> {
+        Ok(self.documents.read().unwrap().get(document_id).cloned().unwrap_or_default())
+    }
+
+    async fn save(&self, document_id: &str, chunks: Vec<StoredChunk>) -> Result<()> {
+        self.documents.write().unwrap().insert(document_id.to_string(), chunks);
+        Ok(())
+    }
+}
+
+/// How a document's chunks changed since its stored version
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkDiff {
+    /// Chunks that are new or whose content changed
+    pub changed: usize,
+    /// Chunks identical to a stored one, which kept its ID
+    pub unchanged: usize,
+    /// IDs of stored chunks the new version no longer has
+    pub removed: Vec<String>,
+}
+
+/// Diff `chunks` against the `previous` version of their document
+///
+/// Unchanged chunks take over the stored chunk's ID and, when they have
+/// none of their own, its embedding. Duplicate chunks pair up with stored
+/// duplicates in order.
+pub fn diff_chunks(previous: &[StoredChunk], chunks: &mut [ProcessedChunk]) -> ChunkDiff {
+    let mut stored: HashMap<&str, VecDeque<&StoredChunk>> = HashMap::new();
+    for chunk in previous {
+        stored.entry(chunk.content_hash.as_str()).or_default().push_back(chunk);
+    }
+
+    let mut diff = ChunkDiff::default();
+    let mut taken: HashSet<String> = previous.iter().map(|chunk| chunk.id.clone()).collect();
+    let mut matched = vec![false; chunks.len()];
+    for (chunk, matched) in chunks.iter_mut().zip(matched.iter_mut()) {
+        let Some(previous) = stored.get_mut(chunk.content_hash.as_str()).and_then(VecDeque::pop_front) else {
+            continue;
+        };
+        set_id(chunk, previous.id.clone());
+        if chunk.embedding.is_none() {
+            chunk.embedding = previous.embedding.clone();
+        }
+        *matched = true;
+        diff.unchanged += 1;
+    }
+
+    // Positional IDs of changed chunks may have belonged to a stored chunk
+    for (index, chunk) in chunks.iter_mut().enumerate().filter(|(index, _)| !matched[*index]) {
+        if taken.contains(&chunk.id) {
+            let hash = &chunk.content_hash[..chunk.content_hash.len().min(12)];
+            let mut id = format!("{}_{}", chunk.document_id, hash);
+            if taken.contains(&id) {
+                id = format!("{}_{}", id, index);
+            }
+            set_id(chunk, id);
+        }
+        taken.insert(chunk.id.clone());
+        diff.changed += 1;
+    }
+
+    diff.removed = stored.into_values().flatten().map(|chunk| chunk.id.clone()).collect();
+    diff.removed.sort();
+    diff
+}
+
+fn set_id(chunk: &mut ProcessedChunk, id: String) {
+    chunk.metadata.insert("chunk_id".to_string(), serde_json::json!(id));
+    chunk.id = id;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &str, hash: &str) -> ProcessedChunk {
+        ProcessedChunk {
+            id: id.to_string(),
+            document_id: "doc".to_string(),
+            content: hash.to_string(),
+            content_hash: hash.to_string(),
+            metadata: HashMap::new(),
+            embedding: None,
+        }
+    }
+
+    fn stored(id: &str, hash: &str) -> StoredChunk {
+        StoredChunk {
+            id: id.to_string(),
+            content_hash: hash.to_string(),
+            embedding: Some(vec![1.0]),
+        }
+    }
+
+    #[test]
+    fn test_unchanged_chunks_keep_their_ids_when_moved() {
+        let previous = vec![stored("doc_0", "aaa"), stored("doc_1", "bbb"), stored("doc_2", "ccc")];
+        // A chunk inserted at the front shifts the others, and "ccc" was edited
+        let mut chunks = vec![chunk("doc_0", "new"), chunk("doc_1", "aaa"), chunk("doc_2", "bbb"), chunk("doc_3", "ddd")];
+
+        let diff = diff_chunks(&previous, &mut chunks);
+        assert_eq!(diff.unchanged, 2);
+        assert_eq!(diff.changed, 2);
+        assert_eq!(diff.removed, vec!["doc_2".to_string()]);
+
+        let ids: Vec<_> = chunks.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["doc_new", "doc_0", "doc_1", "doc_3"]);
+        assert_eq!(chunks[1].metadata["chunk_id"], serde_json::json!("doc_0"));
+        assert!(chunks[1].embedding.is_some());
+        assert!(chunks[3].embedding.is_none());
+    }
+}
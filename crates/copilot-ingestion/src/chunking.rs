@@ -60,7 +60,7 @@ impl Chunk {
 }
 
 /// Chunking strategy
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum ChunkingStrategy {
     /// Fixed size chunks with overlap
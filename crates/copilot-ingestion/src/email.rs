@@ -0,0 +1,678 @@
+//! Email parsing
+//!
+//! Turns a raw RFC 5322 message into an [`Email`]: decoded headers, the
+//! readable body, attachments, and the thread it belongs to. Each email
+//! becomes one [`Document`] for its body plus one per text attachment, all
+//! carrying the same thread metadata so answers can cite the conversation a
+//! chunk came from.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use encoding_rs::{Encoding, UTF_8};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use crate::pipeline::{Document, DocumentMetadata};
+use crate::{IngestionError, Result};
+
+/// Prefixes mail clients add to the subject of replies and forwards
+const REPLY_PREFIXES: &[&str] = &["re:", "fw:", "fwd:", "aw:", "wg:", "sv:"];
+
+/// A file attached to an email
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+impl EmailAttachment {
+    /// Whether the attachment holds text the extractors can read
+    pub fn is_text(&self) -> bool {
+        is_text_type(&self.content_type)
+            || is_text_type(mime_guess::from_path(&self.filename).first_or_octet_stream().essence_str())
+    }
+}
+
+fn is_text_type(content_type: &str) -> bool {
+    content_type.starts_with("text/")
+        || matches!(
+            content_type,
+            "application/json" | "application/xml" | "application/x-yaml" | "application/yaml"
+        )
+}
+
+/// A parsed email message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    /// `Message-ID` without angle brackets
+    pub message_id: String,
+    /// Message this one replies to
+    pub in_reply_to: Option<String>,
+    /// Earlier messages in the thread, oldest first
+    pub references: Vec<String>,
+    pub subject: String,
+    pub from: String,
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub date: Option<DateTime<Utc>>,
+    /// Plain text body (HTML bodies are reduced to text)
+    pub body: String,
+    pub attachments: Vec<EmailAttachment>,
+}
+
+/// Thread metadata attached to every document and chunk of an email
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmailThreadInfo {
+    pub message_id: String,
+    pub thread_id: String,
+    pub subject: String,
+    pub from: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_reply_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<DateTime<Utc>>,
+}
+
+impl EmailThreadInfo {
+    /// The metadata as `email_*` entries
+    pub fn to_metadata(&self) -> HashMap<String, serde_json::Value> {
+        let mut metadata = HashMap::new();
+        metadata.insert("email_message_id".to_string(), serde_json::json!(self.message_id));
+        metadata.insert("email_thread_id".to_string(), serde_json::json!(self.thread_id));
+        metadata.insert("email_subject".to_string(), serde_json::json!(self.subject));
+        metadata.insert("email_from".to_string(), serde_json::json!(self.from));
+        if let Some(in_reply_to) = &self.in_reply_to {
+            metadata.insert("email_in_reply_to".to_string(), serde_json::json!(in_reply_to));
+        }
+        if let Some(date) = &self.date {
+            metadata.insert("email_date".to_string(), serde_json::json!(date.to_rfc3339()));
+        }
+        metadata
+    }
+}
+
+impl Email {
+    /// Parse a raw message
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let entity = Entity::parse(raw);
+        if entity.headers.is_empty() {
+            return Err(IngestionError::ExtractionFailed("Email has no headers".to_string()));
+        }
+
+        let mut parts = Parts::default();
+        parts.collect(&entity);
+        let body = if !parts.text.is_empty() {
+            parts.text.join("\n\n")
+        } else {
+            parts.html.iter().map(|html| html_to_text(html)).collect::<Vec<_>>().join("\n\n")
+        };
+
+        let message_id = entity
+            .header("message-id")
+            .and_then(|v| message_ids(v).into_iter().next())
+            .unwrap_or_else(|| {
+                // Without an ID, derive a stable one so re-polls deduplicate
+                let seed = format!(
+                    "{}|{}|{}",
+                    entity.header("from").unwrap_or_default(),
+                    entity.header("date").unwrap_or_default(),
+                    entity.header("subject").unwrap_or_default()
+                );
+                format!("{}@generated", &hex::encode(Sha256::digest(seed.as_bytes()))[..32])
+            });
+
+        Ok(Self {
+            message_id,
+            in_reply_to: entity.header("in-reply-to").and_then(|v| message_ids(v).into_iter().next()),
+            references: entity.header("references").map(message_ids).unwrap_or_default(),
+            subject: entity.header("subject").map(decode_words).unwrap_or_default(),
+            from: entity.header("from").map(decode_words).unwrap_or_default(),
+            to: entity.header("to").map(address_list).unwrap_or_default(),
+            cc: entity.header("cc").map(address_list).unwrap_or_default(),
+            date: entity.header("date").and_then(parse_date),
+            body,
+            attachments: parts.attachments,
+        })
+    }
+
+    /// The first message of the thread this email belongs to
+    pub fn thread_id(&self) -> &str {
+        self.references
+            .first()
+            .or(self.in_reply_to.as_ref())
+            .unwrap_or(&self.message_id)
+    }
+
+    /// Subject without reply and forward prefixes
+    pub fn normalized_subject(&self) -> &str {
+        let mut subject = self.subject.trim();
+        while let Some(prefix) = REPLY_PREFIXES
+            .iter()
+            .find(|p| subject.get(..p.len()).is_some_and(|s| s.eq_ignore_ascii_case(p)))
+        {
+            subject = subject[prefix.len()..].trim_start();
+        }
+        subject
+    }
+
+    pub fn thread_info(&self) -> EmailThreadInfo {
+        EmailThreadInfo {
+            message_id: self.message_id.clone(),
+            thread_id: self.thread_id().to_string(),
+            subject: self.normalized_subject().to_string(),
+            from: self.from.clone(),
+            in_reply_to: self.in_reply_to.clone(),
+            date: self.date,
+        }
+    }
+
+    /// One document for the body and one per text attachment
+    ///
+    /// The body document starts with the sender, date and subject so that
+    /// chunks retrieved on their own still say where they came from.
+    pub fn to_documents(&self, source: &str) -> Vec<Document> {
+        let thread = self.thread_info().to_metadata();
+        let with_thread = |mut metadata: DocumentMetadata| {
+            metadata.custom.extend(thread.clone());
+            metadata.with_source(source)
+        };
+
+        let mut text = format!("From: {}\n", self.from);
+        if !self.to.is_empty() {
+            text.push_str(&format!("To: {}\n", self.to.join(", ")));
+        }
+        if let Some(date) = &self.date {
+            text.push_str(&format!("Date: {}\n", date.to_rfc2822()));
+        }
+        text.push_str(&format!("Subject: {}\n\n{}", self.subject, self.body.trim()));
+
+        let body_id = format!("email:{}", self.message_id);
+        let content = text.into_bytes();
+        let mut documents = vec![Document::new(
+            body_id.clone(),
+            content.clone(),
+            with_thread(DocumentMetadata::new("text/plain", content.len())),
+        )];
+
+        for attachment in self.attachments.iter().filter(|a| a.is_text()) {
+            let metadata = DocumentMetadata::new(attachment.content_type.clone(), attachment.content.len())
+                .with_filename(attachment.filename.clone())
+                .with_custom("email_attachment", serde_json::json!(true));
+            documents.push(Document::new(
+                format!("{}/{}", body_id, attachment.filename),
+                attachment.content.clone(),
+                with_thread(metadata),
+            ));
+        }
+        documents
+    }
+}
+
+/// Text bodies, HTML bodies and attachments found in a message
+#[derive(Default)]
+struct Parts {
+    text: Vec<String>,
+    html: Vec<String>,
+    attachments: Vec<EmailAttachment>,
+}
+
+impl Parts {
+    fn collect(&mut self, entity: &Entity) {
+        let (content_type, params) = entity.content_type();
+        if content_type.starts_with("multipart/") {
+            if let Some(boundary) = params.get("boundary") {
+                let alternative = content_type == "multipart/alternative";
+                for part in split_multipart(entity.body, boundary) {
+                    let part = Entity::parse(part);
+                    // Of the alternatives, only the plain text one is needed
+                    // when there is one
+                    if alternative && !self.text.is_empty() && part.content_type().0 == "text/html" {
+                        continue;
+                    }
+                    self.collect(&part);
+                }
+            }
+            return;
+        }
+
+        let filename = entity.filename().or_else(|| params.get("name").cloned());
+        let inline = !entity
+            .header("content-disposition")
+            .is_some_and(|d| d.trim_start().to_ascii_lowercase().starts_with("attachment"));
+        let data = entity.decoded_body();
+
+        match (content_type.as_str(), filename) {
+            ("text/plain", None) if inline => self.text.push(decode_charset(&data, params.get("charset"))),
+            ("text/html", None) if inline => self.html.push(decode_charset(&data, params.get("charset"))),
+            (_, Some(filename)) => self.attachments.push(EmailAttachment {
+                filename,
+                content_type,
+                content: data,
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// A MIME entity: headers and raw body
+struct Entity<'a> {
+    headers: Vec<(String, String)>,
+    body: &'a [u8],
+}
+
+impl<'a> Entity<'a> {
+    fn parse(raw: &'a [u8]) -> Self {
+        let (head, body) = match find(raw, b"\r\n\r\n") {
+            Some(i) => (&raw[..i], &raw[i + 4..]),
+            None => match find(raw, b"\n\n") {
+                Some(i) => (&raw[..i], &raw[i + 2..]),
+                None => (raw, &raw[raw.len()..]),
+            },
+        };
+
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for line in String::from_utf8_lossy(head).lines() {
+            if line.starts_with([' ', '\t']) {
+                // Folded continuation of the previous header
+                if let Some((_, value)) = headers.last_mut() {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+            } else if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+            }
+        }
+        Self { headers, body }
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Lower-cased MIME type and its parameters, defaulting to `text/plain`
+    fn content_type(&self) -> (String, HashMap<String, String>) {
+        match self.header("content-type") {
+            Some(value) => {
+                let (mime, params) = parse_params(value);
+                (mime.to_ascii_lowercase(), params)
+            }
+            None => ("text/plain".to_string(), HashMap::new()),
+        }
+    }
+
+    fn filename(&self) -> Option<String> {
+        let (_, params) = parse_params(self.header("content-disposition")?);
+        params.get("filename").cloned()
+    }
+
+    fn decoded_body(&self) -> Vec<u8> {
+        let encoding = self
+            .header("content-transfer-encoding")
+            .map(|e| e.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        match encoding.as_str() {
+            "base64" => {
+                let compact: Vec<u8> = self.body.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+                BASE64.decode(compact).unwrap_or_default()
+            }
+            "quoted-printable" => decode_quoted_printable(self.body, false),
+            _ => self.body.to_vec(),
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// The bodies of a multipart entity's parts
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut offset = 0;
+
+    for line in body.split_inclusive(|&b| b == b'\n') {
+        let trimmed = line.strip_suffix(b"\n").unwrap_or(line);
+        let trimmed = trimmed.strip_suffix(b"\r").unwrap_or(trimmed);
+        if let Some(rest) = trimmed.strip_prefix(delimiter.as_bytes()) {
+            if let Some(begin) = start {
+                // The line break before a delimiter belongs to the delimiter
+                let mut end = offset;
+                if body[..end].ends_with(b"\n") {
+                    end -= 1;
+                }
+                if body[..end].ends_with(b"\r") {
+                    end -= 1;
+                }
+                parts.push(&body[begin..end.max(begin)]);
+            }
+            if rest.starts_with(b"--") {
+                return parts;
+            }
+            start = Some(offset + line.len());
+        }
+        offset += line.len();
+    }
+    parts
+}
+
+/// `type/subtype; name=value; name="quoted value"`
+///
+/// RFC 2231 extended values (`name*=utf-8''...`) are decoded under the plain
+/// name.
+fn parse_params(value: &str) -> (String, HashMap<String, String>) {
+    let mut segments = split_unquoted(value, ';').into_iter();
+    let head = segments.next().unwrap_or_default().trim().to_string();
+    let mut params = HashMap::new();
+    for segment in segments {
+        let Some((name, value)) = segment.split_once('=') else {
+            continue;
+        };
+        let name = name.trim().to_ascii_lowercase();
+        let value = value.trim();
+        if let Some(name) = name.strip_suffix('*') {
+            let encoded = value.splitn(3, '\'').nth(2).unwrap_or(value);
+            params.insert(name.to_string(), percent_decode(encoded));
+        } else {
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .map(|v| v.replace("\\\"", "\""))
+                .unwrap_or_else(|| value.to_string());
+            params.insert(name, decode_words(&value));
+        }
+    }
+    (head, params)
+}
+
+fn split_unquoted(value: &str, separator: char) -> Vec<String> {
+    let mut segments = vec![String::new()];
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                segments.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(segment) = segments.last_mut() {
+            segment.push(c);
+        }
+    }
+    segments
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Decode quoted-printable; in headers (`header`), `_` stands for a space
+fn decode_quoted_printable(data: &[u8], header: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            b'=' => {
+                let hex = data
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                if let Some(byte) = hex {
+                    out.push(byte);
+                    i += 3;
+                } else if data[i + 1..].starts_with(b"\r\n") {
+                    // Soft line break
+                    i += 3;
+                } else if data[i + 1..].starts_with(b"\n") {
+                    i += 2;
+                } else {
+                    out.push(b'=');
+                    i += 1;
+                }
+            }
+            b'_' if header => {
+                out.push(b' ');
+                i += 1;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+fn decode_charset(data: &[u8], charset: Option<&String>) -> String {
+    let encoding = charset
+        .and_then(|c| Encoding::for_label(c.trim().as_bytes()))
+        .unwrap_or(UTF_8);
+    encoding.decode(data).0.into_owned()
+}
+
+/// Decode RFC 2047 encoded words (`=?charset?B?...?=`) in a header value
+fn decode_words(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let decoded = rest[start + 2..].split_once("?=").and_then(|(word, tail)| {
+            let mut fields = word.splitn(3, '?');
+            let (charset, encoding, text) = (fields.next()?, fields.next()?, fields.next()?);
+            let bytes = match encoding.to_ascii_uppercase().as_str() {
+                "B" => BASE64.decode(text).ok()?,
+                "Q" => decode_quoted_printable(text.as_bytes(), true),
+                _ => return None,
+            };
+            // Drop any RFC 2231 language suffix (charset*lang)
+            let charset = charset.split('*').next().unwrap_or(charset).to_string();
+            Some((decode_charset(&bytes, Some(&charset)), tail))
+        });
+        match decoded {
+            Some((text, tail)) => {
+                // Whitespace between adjacent encoded words is not content
+                let between = &rest[..start];
+                if !(after_word && between.trim().is_empty()) {
+                    out.push_str(between);
+                }
+                out.push_str(&text);
+                rest = tail;
+                after_word = true;
+            }
+            None => {
+                out.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                after_word = false;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// IDs in angle brackets, in order
+fn message_ids(value: &str) -> Vec<String> {
+    let ids: Vec<String> = value
+        .split('<')
+        .skip(1)
+        .filter_map(|s| s.split_once('>').map(|(id, _)| id.trim().to_string()))
+        .filter(|id| !id.is_empty())
+        .collect();
+    if ids.is_empty() && !value.trim().is_empty() {
+        vec![value.trim().to_string()]
+    } else {
+        ids
+    }
+}
+
+fn address_list(value: &str) -> Vec<String> {
+    split_unquoted(&decode_words(value), ',')
+        .into_iter()
+        .map(|a| a.trim().to_string())
+        .filter(|a| !a.is_empty())
+        .collect()
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    // Strip trailing comments such as "(UTC)"
+    let value = value.split('(').next().unwrap_or(value).trim();
+    DateTime::parse_from_rfc2822(value).ok().map(|d| d.with_timezone(&Utc))
+}
+
+/// Reduce an HTML body to readable text
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let tag = &rest[start + 1..];
+        let end = tag.find('>').map(|i| i + 1).unwrap_or(tag.len());
+        let name = tag[..end]
+            .trim_start_matches('/')
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        rest = &tag[end..];
+
+        if matches!(name.as_str(), "script" | "style") && !tag.starts_with('/') {
+            let close = format!("</{}", name);
+            let skipped = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+            rest = &rest[skipped..];
+        } else if !tag.starts_with('/') && matches!(name.as_str(), "br" | "p" | "div" | "tr" | "li" | "h1" | "h2" | "h3") {
+            text.push('\n');
+        }
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+    lines.join("\n").split("\n\n\n").collect::<Vec<_>>().join("\n\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPLY: &str = "Message-ID: <reply-2@acme.com>\r\n\
+        In-Reply-To: <reply-1@acme.com>\r\n\
+        References: <root@acme.com>\r\n <reply-1@acme.com>\r\n\
+        From: =?UTF-8?Q?Jos=C3=A9_P=C3=A9rez?= <jose@customer.io>\r\n\
+        To: support@acme.com, \"Ops, Team\" <ops@acme.com>\r\n\
+        Date: Tue, 3 Sep 2024 10:15:00 +0200 (CEST)\r\n\
+        Subject: RE: Re: Export =?UTF-8?B?ZmFpbHM=?=\r\n\
+        MIME-Version: 1.0\r\n\
+        Content-Type: multipart/mixed; boundary=\"outer\"\r\n\
+        \r\n\
+        --outer\r\n\
+        Content-Type: multipart/alternative; boundary=inner\r\n\
+        \r\n\
+        --inner\r\n\
+        Content-Type: text/plain; charset=utf-8\r\n\
+        Content-Transfer-Encoding: quoted-printable\r\n\
+        \r\n\
+        The export still fails with a =\r\n\
+        timeout =E2=80=94 logs attached.\r\n\
+        --inner\r\n\
+        Content-Type: text/html\r\n\
+        \r\n\
+        <p>The export still fails</p>\r\n\
+        --inner--\r\n\
+        --outer\r\n\
+        Content-Type: text/plain; name=\"export.log\"\r\n\
+        Content-Disposition: attachment; filename=\"export.log\"\r\n\
+        Content-Transfer-Encoding: base64\r\n\
+        \r\n\
+        RVJST1IgdGltZW91dCBhZnRlciAzMHM=\r\n\
+        --outer\r\n\
+        Content-Type: image/png\r\n\
+        Content-Disposition: attachment; filename*=utf-8''screen%20shot.png\r\n\
+        Content-Transfer-Encoding: base64\r\n\
+        \r\n\
+        iVBORw0KGgo=\r\n\
+        --outer--\r\n";
+
+    #[test]
+    fn test_parse_multipart_reply() {
+        let email = Email::parse(REPLY.as_bytes()).unwrap();
+        assert_eq!(email.message_id, "reply-2@acme.com");
+        assert_eq!(email.thread_id(), "root@acme.com");
+        assert_eq!(email.references, vec!["root@acme.com", "reply-1@acme.com"]);
+        assert_eq!(email.from, "José Pérez <jose@customer.io>");
+        assert_eq!(email.to, vec!["support@acme.com", "\"Ops, Team\" <ops@acme.com>"]);
+        assert_eq!(email.subject, "RE: Re: Export fails");
+        assert_eq!(email.normalized_subject(), "Export fails");
+        assert_eq!(email.date.unwrap().to_rfc3339(), "2024-09-03T08:15:00+00:00");
+        assert_eq!(email.body, "The export still fails with a timeout — logs attached.");
+
+        let names: Vec<_> = email.attachments.iter().map(|a| a.filename.as_str()).collect();
+        assert_eq!(names, vec!["export.log", "screen shot.png"]);
+        assert_eq!(email.attachments[0].content, b"ERROR timeout after 30s");
+    }
+
+    #[test]
+    fn test_documents_carry_thread_metadata() {
+        let email = Email::parse(REPLY.as_bytes()).unwrap();
+        let documents = email.to_documents("imap:support");
+
+        // The image attachment is skipped
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0].id, "email:reply-2@acme.com");
+        assert_eq!(documents[1].id, "email:reply-2@acme.com/export.log");
+        let body = String::from_utf8(documents[0].content.clone()).unwrap();
+        assert!(body.starts_with("From: José Pérez <jose@customer.io>\n"));
+        assert!(body.ends_with("Subject: RE: Re: Export fails\n\nThe export still fails with a timeout — logs attached."));
+        for document in &documents {
+            assert_eq!(document.metadata.source.as_deref(), Some("imap:support"));
+            assert_eq!(document.metadata.custom["email_thread_id"], "root@acme.com");
+            assert_eq!(document.metadata.custom["email_subject"], "Export fails");
+        }
+    }
+
+    #[test]
+    fn test_html_only_message() {
+        let raw = "From: a@b.c\nSubject: Hi\nContent-Type: text/html\n\n\
+            <html><style>p{}</style><body><p>Line&nbsp;one</p><p>A &amp; B</p></body></html>";
+        let email = Email::parse(raw.as_bytes()).unwrap();
+        assert_eq!(email.body, "Line one\nA & B");
+        assert!(email.message_id.ends_with("@generated"));
+        assert_eq!(email.thread_id(), email.message_id);
+    }
+}
@@ -9,32 +9,71 @@
 //! - Multi-format document processing (text, markdown, JSON, code)
 //! - Intelligent text chunking with overlap
 //! - Content deduplication using fingerprinting
+//! - Chunk-level diffing of re-ingested documents, re-embedding only changed chunks
 //! - Metadata extraction and enrichment
 //! - Pipeline-based processing with configurable stages
 //! - Async streaming support for large documents
+//! - IMAP mailbox connector with email thread metadata
+//! - Quarantine of documents carrying secrets, malware or prompt injection
+//! - Signed ingestion from trusted sources, labeling unverified content
+//! - Bulk indexing into OpenSearch or Elasticsearch clusters
+//! - Declarative rules routing documents to chunking strategies, tags and
+//!   authority weights, or skipping them
+//! - Per-document summaries and keywords for hierarchical retrieval
+//! - One-step corpus bootstrap from Confluence or markdown wiki exports
 
+pub mod bootstrap;
+pub mod chunk_diff;
 pub mod chunking;
+pub mod email;
 pub mod extractors;
+pub mod imap;
 pub mod pipeline;
 pub mod processors;
+pub mod rules;
+pub mod safety;
+pub mod signing;
+pub mod streaming;
+pub mod summaries;
+pub mod wiki;
 
 // Re-exports
+pub use bootstrap::{BootstrapFailure, BootstrapReport, CorpusBootstrap, SpaceCoverage};
+pub use chunk_diff::{ChunkDiff, ChunkStore, MemoryChunkStore, StoredChunk};
 pub use chunking::{
     ChunkingStrategy, ChunkingConfig, TextChunker,
     Chunk, ChunkMetadata,
 };
+pub use email::{Email, EmailAttachment, EmailThreadInfo};
 pub use extractors::{
     TextExtractor, ExtractorRegistry, ExtractionResult,
     PlainTextExtractor, MarkdownExtractor, JsonExtractor,
 };
+pub use imap::{ImapConfig, ImapConnector, ImapCursor, ImapPollSummary, ImapSecurity};
 pub use pipeline::{
-    IngestionPipeline, PipelineConfig, PipelineStage,
+    EmbeddingStage, IngestionPipeline, PipelineConfig, PipelineStage,
     IngestionResult, Document, DocumentMetadata,
 };
 pub use processors::{
     ContentProcessor, ProcessorChain, DeduplicationProcessor,
     MetadataEnricher, ContentNormalizer,
 };
+pub use rules::{IngestionRule, IngestionRules, RuleConditions, RuleOutcome, RulesStage, RulesetConfig};
+pub use safety::{
+    Quarantine, QuarantinedDocument, SafetyCategory, SafetyFinding, SafetyScanner, SafetySink,
+    SafetyStage,
+};
+pub use signing::{
+    PayloadCheck, Provenance, SignatureAlgorithm, SignatureClaim, SignatureGate, TrustedSigners,
+};
+pub use streaming::{
+    ChunkSink, ContextEngineSink, SearchIndexSink, StreamingConfig, StreamingIngestor,
+    StreamingSummary,
+};
+pub use summaries::{
+    index_hierarchically, DocumentSummarizer, ExtractiveSummarizer, LlmSummarizer, SummaryModel, SummaryStage,
+};
+pub use wiki::{WikiExport, WikiFormat, WikiPage};
 
 /// Error types for ingestion operations
 #[derive(Debug, thiserror::Error)]
@@ -65,6 +104,16 @@ pub enum IngestionError {
 
     #[error("Validation error: {0}")]
     ValidationError(String),
+
+    #[error("Connector error: {0}")]
+    ConnectorError(String),
+
+    #[error("Document quarantined for review: {0}")]
+    Quarantined(String),
+
+    /// An ingestion rule leaves the document out; holds the rule's name
+    #[error("Document skipped by ingestion rule {0}")]
+    Skipped(String),
 }
 
 pub type Result<T> = std::result::Result<T, IngestionError>;
@@ -4,6 +4,7 @@
 //! chunking, processing, and output.
 
 use async_trait::async_trait;
+use copilot_context::{DocumentSummary, Embedding, EmbeddingProvider};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
@@ -11,9 +12,11 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
-use crate::chunking::{Chunk, ChunkingConfig, TextChunker};
+use crate::chunk_diff::{diff_chunks, ChunkStore, StoredChunk};
+use crate::chunking::{Chunk, ChunkingConfig, ChunkingStrategy, TextChunker};
 use crate::extractors::{ExtractorRegistry, ExtractionResult};
 use crate::processors::{ContentProcessor, ProcessedContent, ProcessorChain};
+use crate::rules::TAGS_KEY;
 use crate::{IngestionError, Result};
 
 /// Document metadata
@@ -135,6 +138,21 @@ pub struct IngestionResult {
     pub success: bool,
     /// Error message if failed
     pub error: Option<String>,
+    /// Chunks new or changed since the stored version of the document
+    #[serde(default)]
+    pub changed_chunks: usize,
+    /// Chunks identical to the stored version's, which kept their IDs
+    #[serde(default)]
+    pub unchanged_chunks: usize,
+    /// IDs of the stored version's chunks this version no longer has
+    #[serde(default)]
+    pub removed_chunk_ids: Vec<String>,
+    /// Ingestion rule that left the document out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped_by: Option<String>,
+    /// Summary of the document, when the pipeline has a summary stage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<DocumentSummary>,
 }
 
 /// A processed chunk ready for storage/embedding
@@ -150,6 +168,9 @@ pub struct ProcessedChunk {
     pub content_hash: String,
     /// Chunk metadata
     pub metadata: HashMap<String, serde_json::Value>,
+    /// Chunk embedding, when the pipeline has an embedding stage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Embedding>,
 }
 
 impl From<ProcessedContent> for ProcessedChunk {
@@ -174,6 +195,7 @@ impl From<ProcessedContent> for ProcessedChunk {
             content: content.text,
             content_hash: content.content_hash,
             metadata: content.metadata,
+            embedding: None,
         }
     }
 }
@@ -196,12 +218,22 @@ pub struct PipelineContext {
     pub extracted_text: Option<String>,
     /// Extraction result
     pub extraction_result: Option<ExtractionResult>,
+    /// Chunking strategy chosen for this document, overriding the chunking
+    /// stage's
+    pub chunking_strategy: Option<ChunkingStrategy>,
     /// Generated chunks
     pub chunks: Vec<Chunk>,
     /// Processed chunks
     pub processed_chunks: Vec<ProcessedContent>,
     /// Warnings collected during processing
     pub warnings: Vec<String>,
+    /// Embeddings of processed chunks by content hash
+    pub embeddings: HashMap<String, Embedding>,
+    /// Chunks of the stored version of the document, when the pipeline has
+    /// a chunk store
+    pub previous_chunks: Vec<StoredChunk>,
+    /// Summary of the whole document
+    pub summary: Option<DocumentSummary>,
     /// Stage-specific data
     pub stage_data: HashMap<String, serde_json::Value>,
 }
@@ -212,9 +244,13 @@ impl PipelineContext {
             document,
             extracted_text: None,
             extraction_result: None,
+            chunking_strategy: None,
             chunks: Vec::new(),
             processed_chunks: Vec::new(),
             warnings: Vec::new(),
+            embeddings: HashMap::new(),
+            previous_chunks: Vec::new(),
+            summary: None,
             stage_data: HashMap::new(),
         }
     }
@@ -281,13 +317,14 @@ impl PipelineStage for ExtractionStage {
 
 /// Chunking stage
 pub struct ChunkingStage {
+    config: ChunkingConfig,
     chunker: TextChunker,
 }
 
 impl ChunkingStage {
     pub fn new(config: ChunkingConfig) -> Result<Self> {
-        let chunker = TextChunker::new(config)?;
-        Ok(Self { chunker })
+        let chunker = TextChunker::new(config.clone())?;
+        Ok(Self { config, chunker })
     }
 }
 
@@ -309,7 +346,10 @@ impl PipelineStage for ChunkingStage {
             .as_ref()
             .ok_or_else(|| IngestionError::PipelineError("No extracted text available".to_string()))?;
 
-        let chunks = self.chunker.chunk(&context.document.id, text)?;
+        let chunks = match context.chunking_strategy {
+            Some(strategy) => TextChunker::new(self.config.clone().with_strategy(strategy))?.chunk(&context.document.id, text)?,
+            None => self.chunker.chunk(&context.document.id, text)?,
+        };
 
         debug!(
             document_id = %context.document.id,
@@ -364,6 +404,68 @@ impl PipelineStage for ProcessingStage {
     }
 }
 
+/// Embedding stage
+///
+/// Give it the background side of the cache queries use
+/// ([`CachedEmbeddingProvider::background`](copilot_context::CachedEmbeddingProvider::background)),
+/// so content embedded here is not embedded again at query time or when it
+/// is re-ingested unchanged, and large imports queue behind queries.
+///
+/// Chunks unchanged since the stored version of the document reuse its
+/// embeddings; only changed chunks are sent to the provider.
+pub struct EmbeddingStage {
+    provider: Arc<dyn EmbeddingProvider>,
+}
+
+impl EmbeddingStage {
+    pub fn new(provider: Arc<dyn EmbeddingProvider>) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl PipelineStage for EmbeddingStage {
+    fn name(&self) -> &'static str {
+        "embedding"
+    }
+
+    async fn process(&self, context: &mut PipelineContext) -> Result<()> {
+        for previous in &context.previous_chunks {
+            if let Some(embedding) = &previous.embedding {
+                context.embeddings.insert(previous.content_hash.clone(), embedding.clone());
+            }
+        }
+
+        let changed: Vec<&ProcessedContent> = context
+            .processed_chunks
+            .iter()
+            .filter(|c| !context.embeddings.contains_key(&c.content_hash))
+            .collect();
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        let texts: Vec<&str> = changed.iter().map(|c| c.text.as_str()).collect();
+        let embeddings = self
+            .provider
+            .embed_batch(&texts)
+            .await
+            .map_err(|e| IngestionError::PipelineError(format!("Embedding failed: {}", e)))?;
+        let hashes: Vec<String> = changed.iter().map(|c| c.content_hash.clone()).collect();
+        for (hash, embedding) in hashes.into_iter().zip(embeddings) {
+            context.embeddings.insert(hash, embedding);
+        }
+
+        debug!(
+            document_id = %context.document.id,
+            embedded = texts.len(),
+            reused = context.processed_chunks.len() - texts.len(),
+            "Chunks embedded"
+        );
+        Ok(())
+    }
+}
+
 /// Pipeline configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PipelineConfig {
@@ -393,6 +495,7 @@ pub struct IngestionPipeline {
     config: PipelineConfig,
     stages: Vec<Arc<dyn PipelineStage>>,
     stats: Arc<RwLock<PipelineStats>>,
+    chunk_store: Option<Arc<dyn ChunkStore>>,
 }
 
 /// Pipeline statistics
@@ -400,6 +503,7 @@ pub struct IngestionPipeline {
 pub struct PipelineStats {
     pub documents_processed: u64,
     pub documents_failed: u64,
+    pub documents_skipped: u64,
     pub chunks_created: u64,
     pub total_processing_time_ms: u64,
     pub bytes_processed: u64,
@@ -411,9 +515,17 @@ impl IngestionPipeline {
             config,
             stages: Vec::new(),
             stats: Arc::new(RwLock::new(PipelineStats::default())),
+            chunk_store: None,
         })
     }
 
+    /// Diff re-ingested documents against the versions kept in `store`; see
+    /// [`chunk_diff`](crate::chunk_diff)
+    pub fn with_chunk_store(mut self, store: Arc<dyn ChunkStore>) -> Self {
+        self.chunk_store = Some(store);
+        self
+    }
+
     /// Create with default stages
     pub fn with_defaults(config: PipelineConfig) -> Result<Self> {
         let mut pipeline = Self::new(config.clone())?;
@@ -428,6 +540,12 @@ impl IngestionPipeline {
         self.stages.push(stage);
     }
 
+    /// Insert a pipeline stage at `index`, e.g. 0 to run it before the
+    /// default stages
+    pub fn insert_stage(&mut self, index: usize, stage: Arc<dyn PipelineStage>) {
+        self.stages.insert(index.min(self.stages.len()), stage);
+    }
+
     /// Ingest a single document
     pub async fn ingest(&self, document: Document) -> IngestionResult {
         let start = std::time::Instant::now();
@@ -448,14 +566,45 @@ impl IngestionPipeline {
                     "Document too large: {} bytes (max {})",
                     doc_size, self.config.max_document_size
                 )),
+                changed_chunks: 0,
+                unchanged_chunks: 0,
+                removed_chunk_ids: Vec::new(),
+                skipped_by: None,
+                summary: None,
             };
         }
 
         let mut context = PipelineContext::new(document);
+        if let Some(store) = &self.chunk_store {
+            match store.load(&document_id).await {
+                Ok(previous) => context.previous_chunks = previous,
+                Err(e) => context.add_warning(format!("Stored version unavailable, re-ingesting every chunk: {}", e)),
+            }
+        }
 
         // Run through stages
         for stage in &self.stages {
-            if let Err(e) = stage.process(&mut context).await {
+            let outcome = stage.process(&mut context).await;
+            if let Err(IngestionError::Skipped(rule)) = outcome {
+                self.stats.write().await.documents_skipped += 1;
+                debug!(document_id = %document_id, rule = %rule, "Document skipped by ingestion rule");
+
+                return IngestionResult {
+                    document_id,
+                    chunk_count: 0,
+                    chunks: Vec::new(),
+                    extraction_metadata: HashMap::new(),
+                    processing_time_ms: start.elapsed().as_millis() as u64,
+                    warnings: context.warnings,
+                    success: true,
+                    error: None,
+                    changed_chunks: 0,
+                    unchanged_chunks: 0,
+                    removed_chunk_ids: Vec::new(),
+                    skipped_by: Some(rule),
+                    summary: None,
+                };
+            } else if let Err(e) = outcome {
                 // Update stats
                 let mut stats = self.stats.write().await;
                 stats.documents_failed += 1;
@@ -476,18 +625,38 @@ impl IngestionPipeline {
                     warnings: context.warnings,
                     success: false,
                     error: Some(e.to_string()),
+                    changed_chunks: 0,
+                    unchanged_chunks: 0,
+                    removed_chunk_ids: Vec::new(),
+                    skipped_by: None,
+                    summary: None,
                 };
             }
         }
 
         let processing_time_ms = start.elapsed().as_millis() as u64;
 
-        // Convert processed content to chunks
-        let chunks: Vec<ProcessedChunk> = context
+        // Convert processed content to chunks, carrying the tags rules added
+        let mut embeddings = context.embeddings;
+        let tags = context.document.metadata.custom.get(TAGS_KEY).cloned();
+        let mut chunks: Vec<ProcessedChunk> = context
             .processed_chunks
             .into_iter()
-            .map(ProcessedChunk::from)
+            .map(|content| {
+                let mut chunk = ProcessedChunk::from(content);
+                chunk.embedding = embeddings.remove(&chunk.content_hash);
+                if let Some(tags) = &tags {
+                    chunk.metadata.insert(TAGS_KEY.to_string(), tags.clone());
+                }
+                chunk
+            })
             .collect();
+        let diff = diff_chunks(&context.previous_chunks, &mut chunks);
+        if let Some(store) = &self.chunk_store {
+            if let Err(e) = store.save(&document_id, chunks.iter().map(StoredChunk::from).collect()).await {
+                context.warnings.push(format!("Failed to store chunk versions: {}", e));
+            }
+        }
 
         // Extract metadata
         let extraction_metadata = context
@@ -507,6 +676,9 @@ impl IngestionPipeline {
         info!(
             document_id = %document_id,
             chunk_count = chunks.len(),
+            changed = diff.changed,
+            unchanged = diff.unchanged,
+            removed = diff.removed.len(),
             processing_time_ms = processing_time_ms,
             "Document ingested"
         );
@@ -520,6 +692,11 @@ impl IngestionPipeline {
             warnings: context.warnings,
             success: true,
             error: None,
+            changed_chunks: diff.changed,
+            unchanged_chunks: diff.unchanged,
+            removed_chunk_ids: diff.removed,
+            skipped_by: None,
+            summary: context.summary,
         }
     }
 
@@ -536,12 +713,14 @@ impl IngestionPipeline {
                 let stages = self.stages.clone();
                 let stats = self.stats.clone();
                 let config = self.config.clone();
+                let chunk_store = self.chunk_store.clone();
 
                 handles.push(tokio::spawn(async move {
                     let pipeline = IngestionPipeline {
                         config,
                         stages,
                         stats,
+                        chunk_store,
                     };
                     pipeline.ingest(doc).await
                 }));
@@ -560,6 +739,11 @@ impl IngestionPipeline {
                             warnings: Vec::new(),
                             success: false,
                             error: Some(format!("Task failed: {}", e)),
+                            changed_chunks: 0,
+                            unchanged_chunks: 0,
+                            removed_chunk_ids: Vec::new(),
+                            skipped_by: None,
+                            summary: None,
                         });
                     }
                 }
@@ -749,6 +933,78 @@ mod tests {
         assert_eq!(chunk.content, "test content");
     }
 
+    #[tokio::test]
+    async fn test_embedding_stage_shares_cache() {
+        use copilot_context::{CachedEmbeddingProvider, EmbeddingCacheConfig, MockEmbeddingProvider};
+
+        let cache = Arc::new(CachedEmbeddingProvider::new(
+            Arc::new(MockEmbeddingProvider::new(8)),
+            EmbeddingCacheConfig::default(),
+        ));
+        let mut pipeline = IngestionPipeline::with_defaults(PipelineConfig::default()).unwrap();
+        pipeline.add_stage(Arc::new(EmbeddingStage::new(cache.clone())));
+
+        let doc = Document::from_text("doc1", "Checkout latency rose after the deploy.");
+        let result = pipeline.ingest(doc.clone()).await;
+        assert!(result.success);
+        assert!(result.chunks.iter().all(|c| c.embedding.as_ref().is_some_and(|e| e.len() == 8)));
+        let misses = cache.stats().misses;
+
+        // Unchanged content is not embedded again, at ingestion or query time
+        pipeline.ingest(doc).await;
+        cache.embed(&result.chunks[0].content).await.unwrap();
+        assert_eq!(cache.stats().misses, misses);
+    }
+
+    #[tokio::test]
+    async fn test_reingestion_only_embeds_changed_chunks() {
+        use crate::chunk_diff::MemoryChunkStore;
+        use crate::chunking::ChunkingStrategy;
+        use copilot_context::{CachedEmbeddingProvider, EmbeddingCacheConfig, MockEmbeddingProvider};
+
+        let cache = Arc::new(CachedEmbeddingProvider::new(
+            Arc::new(MockEmbeddingProvider::new(8)),
+            EmbeddingCacheConfig::default(),
+        ));
+        let config = PipelineConfig {
+            chunking: ChunkingConfig {
+                min_chunk_size: 1,
+                ..ChunkingConfig::default()
+                    .with_strategy(ChunkingStrategy::Paragraph)
+                    .with_chunk_size(8)
+                    .with_overlap(0)
+            },
+            ..Default::default()
+        };
+        let mut pipeline = IngestionPipeline::with_defaults(config)
+            .unwrap()
+            .with_chunk_store(Arc::new(MemoryChunkStore::new()));
+        pipeline.add_stage(Arc::new(EmbeddingStage::new(cache.clone())));
+
+        let paragraphs = [
+            "Checkout latency rose after the Tuesday deploy went out.",
+            "The payments team rolled back the cache change on Wednesday.",
+            "Latency returned to normal within an hour of the rollback.",
+        ];
+        let first = pipeline.ingest(Document::from_text("doc1", paragraphs.join("\n\n"))).await;
+        assert!(first.success);
+        assert_eq!((first.changed_chunks, first.unchanged_chunks), (3, 0));
+        let lookups = cache.stats().misses + cache.stats().memory_hits;
+
+        // A paragraph inserted at the front moves the others and edits nothing
+        let edited = format!("A new summary paragraph opens the incident report.\n\n{}", paragraphs.join("\n\n"));
+        let second = pipeline.ingest(Document::from_text("doc1", edited)).await;
+        assert!(second.success);
+        assert_eq!((second.changed_chunks, second.unchanged_chunks), (1, 3));
+        assert!(second.removed_chunk_ids.is_empty());
+        let first_ids: Vec<_> = first.chunks.iter().map(|c| c.id.as_str()).collect();
+        let second_ids: Vec<_> = second.chunks[1..].iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(first_ids, second_ids);
+        assert!(!first_ids.contains(&second.chunks[0].id.as_str()));
+        assert!(second.chunks.iter().all(|c| c.embedding.is_some()));
+        assert_eq!(cache.stats().misses + cache.stats().memory_hits, lookups + 1);
+    }
+
     #[tokio::test]
     async fn test_custom_stage() {
         let custom = CustomStage::new("custom", |ctx| {
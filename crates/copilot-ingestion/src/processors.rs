@@ -4,6 +4,7 @@
 //! deduplication, and metadata enrichment.
 
 use async_trait::async_trait;
+use copilot_context::chunk_merge::{END_OFFSET_KEY, START_OFFSET_KEY};
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 use std::collections::{HashMap, HashSet};
@@ -46,7 +47,7 @@ impl ProcessedContent {
 }
 
 /// Compute SHA-256 hash of content
-fn compute_hash(content: &str) -> String {
+pub(crate) fn compute_hash(content: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(content.as_bytes());
     hex::encode(hasher.finalize())
@@ -470,7 +471,9 @@ impl ProcessorChain {
         let content = ProcessedContent::new(&chunk.content)
             .with_metadata("chunk_id", serde_json::json!(chunk.id))
             .with_metadata("document_id", serde_json::json!(chunk.document_id))
-            .with_metadata("chunk_index", serde_json::json!(chunk.metadata.index));
+            .with_metadata("chunk_index", serde_json::json!(chunk.metadata.index))
+            .with_metadata(START_OFFSET_KEY, serde_json::json!(chunk.metadata.start_offset))
+            .with_metadata(END_OFFSET_KEY, serde_json::json!(chunk.metadata.end_offset));
 
         self.process(content).await
     }
@@ -0,0 +1,443 @@
+//! Declarative ingestion rules
+//!
+//! An [`IngestionRules`] ruleset routes and enriches documents before they
+//! are chunked. It is read from a YAML file:
+//!
+//! ```yaml
+//! rules:
+//!   - name: skip-drafts
+//!     match: { path: "**/drafts/**" }
+//!     skip: true
+//!   - name: official-docs
+//!     match:
+//!       source: "https://docs.example.com/*"
+//!       mime: "text/markdown"
+//!     chunking: section
+//!     tags: [docs, official]
+//!     authority: 2.0
+//!   - name: payments-runbooks
+//!     match: { metadata: { team: payments } }
+//!     tags: [payments]
+//! ```
+//!
+//! A rule matches when every condition it names holds: `source`, `mime` and
+//! `path` (the filename) are glob patterns, where `*` stays within one path
+//! segment of a filename and `**` spans several, and `metadata` compares
+//! custom document metadata for equality. Every matching rule applies, in
+//! file order: tags add up, the first rule to name a chunking strategy or an
+//! authority weight wins, and a matching `skip` rule stops ingestion there.
+//!
+//! [`RulesStage`] applies the ruleset as the first stage of an
+//! [`IngestionPipeline`](crate::IngestionPipeline). [`IngestionRules::reload`]
+//! swaps in an edited file without a restart.
+
+use async_trait::async_trait;
+use copilot_context::authority::MAX_WEIGHT;
+use copilot_context::ContextEngine;
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+use crate::chunking::ChunkingStrategy;
+use crate::pipeline::{DocumentMetadata, PipelineContext, PipelineStage};
+use crate::{IngestionError, Result};
+
+/// Document and chunk metadata key holding the tags rules added
+pub const TAGS_KEY: &str = "tags";
+
+/// A glob pattern, kept as written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct GlobPattern(Pattern);
+
+impl GlobPattern {
+    fn matches(&self, text: &str, options: MatchOptions) -> bool {
+        self.0.matches_with(text, options)
+    }
+}
+
+impl TryFrom<String> for GlobPattern {
+    type Error = String;
+
+    fn try_from(pattern: String) -> std::result::Result<Self, String> {
+        Pattern::new(&pattern)
+            .map(Self)
+            .map_err(|e| format!("Invalid glob {:?}: {}", pattern, e))
+    }
+}
+
+impl From<GlobPattern> for String {
+    fn from(pattern: GlobPattern) -> Self {
+        pattern.0.as_str().to_string()
+    }
+}
+
+/// Conditions a document must meet for a rule to apply; an empty set
+/// matches every document
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuleConditions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<GlobPattern>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime: Option<GlobPattern>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<GlobPattern>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl RuleConditions {
+    /// Whether a document with `metadata` meets every condition
+    pub fn matches(&self, metadata: &DocumentMetadata) -> bool {
+        let anywhere = MatchOptions::new();
+        let within_segments = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        let glob = |pattern: &Option<GlobPattern>, value: Option<&str>, options| match pattern {
+            Some(pattern) => value.is_some_and(|value| pattern.matches(value, options)),
+            None => true,
+        };
+
+        glob(&self.source, metadata.source.as_deref(), anywhere)
+            && glob(&self.mime, Some(&metadata.content_type), anywhere)
+            && glob(&self.path, metadata.filename.as_deref(), within_segments)
+            && self
+                .metadata
+                .iter()
+                .all(|(key, expected)| metadata.custom.get(key) == Some(expected))
+    }
+}
+
+/// One rule of a ruleset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngestionRule {
+    pub name: String,
+    #[serde(default, rename = "match")]
+    pub conditions: RuleConditions,
+    /// Leave matching documents out
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skip: bool,
+    /// Chunking strategy of matching documents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunking: Option<ChunkingStrategy>,
+    /// Tags added to matching documents' chunks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Authority weight of matching documents' sources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authority: Option<f64>,
+}
+
+impl IngestionRule {
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Rule name must not be empty".to_string());
+        }
+        if !self.skip && self.chunking.is_none() && self.tags.is_empty() && self.authority.is_none() {
+            return Err(format!("Rule {} has no action", self.name));
+        }
+        if self.tags.iter().any(|tag| tag.trim().is_empty()) {
+            return Err(format!("Rule {} has an empty tag", self.name));
+        }
+        if let Some(weight) = self.authority.filter(|w| !(0.0..=MAX_WEIGHT).contains(w)) {
+            return Err(format!(
+                "Rule {}: authority weight must be in [0, {}], got {}",
+                self.name, MAX_WEIGHT, weight
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The rules of a ruleset file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RulesetConfig {
+    #[serde(default)]
+    pub rules: Vec<IngestionRule>,
+}
+
+impl RulesetConfig {
+    /// Parse and validate a YAML ruleset
+    pub fn from_yaml(text: &str) -> Result<Self> {
+        let config: Self = serde_yaml::from_str(text).map_err(|e| IngestionError::ValidationError(e.to_string()))?;
+        config.validate().map_err(IngestionError::ValidationError)?;
+        Ok(config)
+    }
+
+    /// Read a ruleset from a YAML file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_yaml(&text).map_err(|e| {
+            IngestionError::ValidationError(format!("Ingestion rules {}: {}", path.display(), e))
+        })
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        self.rules.iter().try_for_each(IngestionRule::validate)
+    }
+
+    /// What the rules do to a document with `metadata`
+    pub fn evaluate(&self, metadata: &DocumentMetadata) -> RuleOutcome {
+        let mut outcome = RuleOutcome::default();
+        for rule in self.rules.iter().filter(|rule| rule.conditions.matches(metadata)) {
+            outcome.matched.push(rule.name.clone());
+            if rule.skip {
+                outcome.skipped_by = Some(rule.name.clone());
+                break;
+            }
+            if outcome.chunking.is_none() {
+                outcome.chunking = rule.chunking;
+            }
+            if outcome.authority.is_none() {
+                outcome.authority = rule.authority;
+            }
+            for tag in &rule.tags {
+                if !outcome.tags.contains(tag) {
+                    outcome.tags.push(tag.clone());
+                }
+            }
+        }
+        outcome
+    }
+}
+
+/// What a ruleset does to one document
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuleOutcome {
+    /// Names of the rules that matched, in order
+    pub matched: Vec<String>,
+    /// The rule that left the document out
+    pub skipped_by: Option<String>,
+    pub chunking: Option<ChunkingStrategy>,
+    pub tags: Vec<String>,
+    pub authority: Option<f64>,
+}
+
+/// A ruleset shared by pipelines and reloadable
+#[derive(Debug, Clone, Default)]
+pub struct IngestionRules {
+    config: Arc<RwLock<Arc<RulesetConfig>>>,
+    source: Option<PathBuf>,
+}
+
+impl IngestionRules {
+    /// A ruleset of `config`'s rules
+    pub fn new(config: RulesetConfig) -> Result<Self> {
+        config.validate().map_err(IngestionError::ValidationError)?;
+        Ok(Self {
+            config: Arc::new(RwLock::new(Arc::new(config))),
+            source: None,
+        })
+    }
+
+    /// A ruleset read from a YAML file, which [`reload`](Self::reload) re-reads
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let config = RulesetConfig::load(&path)?;
+        Ok(Self {
+            config: Arc::new(RwLock::new(Arc::new(config))),
+            source: Some(path),
+        })
+    }
+
+    /// The rules in force
+    pub fn config(&self) -> Arc<RulesetConfig> {
+        self.config.read().expect("ingestion rules poisoned").clone()
+    }
+
+    /// What the rules in force do to a document with `metadata`
+    pub fn evaluate(&self, metadata: &DocumentMetadata) -> RuleOutcome {
+        self.config().evaluate(metadata)
+    }
+
+    /// Re-read the ruleset file; on error the rules in force stay
+    pub fn reload(&self) -> Result<()> {
+        let Some(path) = &self.source else {
+            return Ok(());
+        };
+        let config = RulesetConfig::load(path)?;
+        info!(rules = config.rules.len(), "Reloaded ingestion rules from {}", path.display());
+        *self.config.write().expect("ingestion rules poisoned") = Arc::new(config);
+        Ok(())
+    }
+
+    /// Reload the ruleset file whenever it changes, checking every
+    /// `check_every`
+    pub fn spawn_reload(&self, check_every: Duration) -> Option<tokio::task::JoinHandle<()>> {
+        let path = self.source.clone()?;
+        let rules = self.clone();
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        Some(tokio::spawn(async move {
+            let mut seen: Option<SystemTime> = modified(&path);
+            let mut ticks = tokio::time::interval(check_every);
+            loop {
+                ticks.tick().await;
+                let current = modified(&path);
+                if current == seen {
+                    continue;
+                }
+                seen = current;
+                if let Err(e) = rules.reload() {
+                    warn!(error = %e, "Keeping the ingestion rules in force");
+                }
+            }
+        }))
+    }
+}
+
+/// Applies a ruleset to each document; add it before chunking
+pub struct RulesStage {
+    rules: IngestionRules,
+    authority: Option<Arc<dyn ContextEngine>>,
+}
+
+impl RulesStage {
+    pub fn new(rules: IngestionRules) -> Self {
+        Self { rules, authority: None }
+    }
+
+    /// Set rules' authority weights on `engine`, for the document's source
+    pub fn with_authority(mut self, engine: Arc<dyn ContextEngine>) -> Self {
+        self.authority = Some(engine);
+        self
+    }
+}
+
+#[async_trait]
+impl PipelineStage for RulesStage {
+    fn name(&self) -> &'static str {
+        "rules"
+    }
+
+    async fn process(&self, context: &mut PipelineContext) -> Result<()> {
+        let outcome = self.rules.evaluate(&context.document.metadata);
+        if outcome.matched.is_empty() {
+            return Ok(());
+        }
+        debug!(document_id = %context.document.id, rules = ?outcome.matched, "Ingestion rules matched");
+
+        if let Some(rule) = outcome.skipped_by {
+            return Err(IngestionError::Skipped(rule));
+        }
+        if outcome.chunking.is_some() {
+            context.chunking_strategy = outcome.chunking;
+        }
+        if !outcome.tags.is_empty() {
+            let custom = &mut context.document.metadata.custom;
+            let mut tags: Vec<serde_json::Value> = match custom.remove(TAGS_KEY) {
+                Some(serde_json::Value::Array(tags)) => tags,
+                _ => Vec::new(),
+            };
+            for tag in outcome.tags {
+                let tag = serde_json::json!(tag);
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            custom.insert(TAGS_KEY.to_string(), serde_json::Value::Array(tags));
+        }
+        if let (Some(weight), Some(engine)) = (outcome.authority, &self.authority) {
+            match &context.document.metadata.source {
+                Some(source) => engine
+                    .set_source_weight(source, weight)
+                    .map_err(|e| IngestionError::PipelineError(e.to_string()))?,
+                None => context.add_warning("Authority rule matched a document without a source"),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+rules:
+  - name: skip-drafts
+    match: { path: "**/drafts/**" }
+    skip: true
+  - name: official-docs
+    match:
+      source: "https://docs.example.com/*"
+      mime: "text/*"
+    chunking: section
+    tags: [docs, official]
+    authority: 2.0
+  - name: payments
+    match: { metadata: { team: payments } }
+    chunking: paragraph
+    tags: [payments, docs]
+  - name: top-level-notes
+    match: { path: "*.txt" }
+    tags: [notes]
+"#;
+
+    fn document(source: &str, filename: &str) -> DocumentMetadata {
+        DocumentMetadata::new("text/markdown", 0).with_source(source).with_filename(filename)
+    }
+
+    #[test]
+    fn test_matching_rules_combine_in_order() {
+        let rules = RulesetConfig::from_yaml(RULES).unwrap();
+
+        let docs = document("https://docs.example.com/guide", "guide.md")
+            .with_custom("team", serde_json::json!("payments"));
+        let outcome = rules.evaluate(&docs);
+        assert_eq!(outcome.matched, vec!["official-docs", "payments"]);
+        assert_eq!(outcome.chunking, Some(ChunkingStrategy::Section));
+        assert_eq!(outcome.tags, vec!["docs", "official", "payments"]);
+        assert_eq!(outcome.authority, Some(2.0));
+
+        let draft = document("https://docs.example.com/guide", "team/drafts/guide.md");
+        assert_eq!(rules.evaluate(&draft).skipped_by.as_deref(), Some("skip-drafts"));
+
+        // `*` stays within one segment of a filename
+        assert_eq!(rules.evaluate(&document("upload", "notes.txt")).tags, vec!["notes"]);
+        assert!(rules.evaluate(&document("upload", "team/notes.txt")).matched.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stage_routes_tags_and_skips_documents() {
+        use crate::chunking::ChunkingConfig;
+        use crate::pipeline::{Document, IngestionPipeline, PipelineConfig};
+
+        let rules = IngestionRules::new(RulesetConfig::from_yaml(RULES).unwrap()).unwrap();
+        let config = PipelineConfig {
+            chunking: ChunkingConfig {
+                min_chunk_size: 1,
+                ..ChunkingConfig::default().with_chunk_size(4).with_overlap(0)
+            },
+            ..Default::default()
+        };
+        let mut pipeline = IngestionPipeline::with_defaults(config).unwrap();
+        pipeline.insert_stage(0, Arc::new(RulesStage::new(rules)));
+
+        let text = "# Setup\n\nInstall the agent.\n\n# Usage\n\nRun the agent.";
+        let metadata = document("https://docs.example.com/setup", "setup.md").with_custom("team", serde_json::json!("infra"));
+        let result = pipeline.ingest(Document::new("setup", text.as_bytes().to_vec(), metadata)).await;
+        assert!(result.success);
+        assert_eq!(result.chunk_count, 2);
+        assert!(result.chunks.iter().all(|c| c.metadata[TAGS_KEY] == serde_json::json!(["docs", "official"])));
+
+        let draft = document("upload", "drafts/setup.md");
+        let result = pipeline.ingest(Document::new("draft", text.as_bytes().to_vec(), draft)).await;
+        assert!(result.success);
+        assert_eq!(result.skipped_by.as_deref(), Some("skip-drafts"));
+        assert!(result.chunks.is_empty());
+        assert_eq!(pipeline.stats().await.documents_skipped, 1);
+    }
+
+    #[test]
+    fn test_invalid_rulesets_are_rejected() {
+        assert!(RulesetConfig::from_yaml("rules:\n  - name: noop\n    match: { mime: text/* }\n").is_err());
+        assert!(RulesetConfig::from_yaml("rules:\n  - name: bad\n    match: { path: \"[\" }\n    skip: true\n").is_err());
+        assert!(RulesetConfig::from_yaml("rules:\n  - name: heavy\n    authority: 50\n").is_err());
+        assert!(RulesetConfig::from_yaml("rules:\n  - name: x\n    chunking: bogus\n").is_err());
+    }
+}
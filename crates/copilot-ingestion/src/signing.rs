@@ -0,0 +1,398 @@
+//! Signed ingestion
+//!
+//! Trusted sources sign what they upload, so content slipped into the
+//! knowledge base some other way can be told apart from theirs. A source
+//! names itself in [`SIGNER_HEADER`] and puts a hex signature in
+//! [`SIGNATURE_HEADER`]: an HMAC-SHA256 of the raw payload with a shared
+//! secret, or an Ed25519 signature of the payload's SHA-256 digest. Both can
+//! be checked as the upload streams in.
+//!
+//! Once [`TrustedSigners`] are configured, a streamed document's chunks are
+//! held by a [`SignatureGate`] until the whole payload has arrived and been
+//! checked, then stored with a [`Trust`] label: verified if a trusted source
+//! signed exactly these bytes, untrusted if the payload is unsigned, signed
+//! by an unknown source, or was modified after signing.
+
+use async_trait::async_trait;
+use copilot_context::provenance::{SIGNER_KEY, TRUST_KEY};
+use copilot_context::Trust;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use crate::pipeline::ProcessedChunk;
+use crate::streaming::ChunkSink;
+use crate::{IngestionError, Result};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Header naming the source that signed an upload
+pub const SIGNER_HEADER: &str = "x-copilot-signer";
+
+/// Header carrying the hex signature of an upload
+pub const SIGNATURE_HEADER: &str = "x-copilot-signature";
+
+/// How a trusted source signs its payloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureAlgorithm {
+    HmacSha256,
+    Ed25519,
+}
+
+#[derive(Clone)]
+enum SignerKey {
+    Hmac(Vec<u8>),
+    Ed25519(VerifyingKey),
+}
+
+impl SignerKey {
+    fn algorithm(&self) -> SignatureAlgorithm {
+        match self {
+            Self::Hmac(_) => SignatureAlgorithm::HmacSha256,
+            Self::Ed25519(_) => SignatureAlgorithm::Ed25519,
+        }
+    }
+}
+
+/// Keys of the sources whose signatures are trusted
+#[derive(Clone, Default)]
+pub struct TrustedSigners {
+    keys: HashMap<String, SignerKey>,
+}
+
+impl std::fmt::Debug for TrustedSigners {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrustedSigners").field("signers", &self.signers()).finish()
+    }
+}
+
+impl TrustedSigners {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust payloads `signer` signs with a shared secret
+    pub fn with_hmac(mut self, signer: impl Into<String>, secret: impl AsRef<[u8]>) -> Self {
+        self.keys.insert(signer.into(), SignerKey::Hmac(secret.as_ref().to_vec()));
+        self
+    }
+
+    /// Trust payloads signed with the key matching a 32-byte Ed25519
+    /// public key
+    pub fn with_ed25519(mut self, signer: impl Into<String>, public_key: &[u8]) -> Result<Self> {
+        let signer = signer.into();
+        let key = <[u8; 32]>::try_from(public_key)
+            .ok()
+            .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+            .ok_or_else(|| IngestionError::ValidationError(format!("Invalid Ed25519 public key for {}", signer)))?;
+        self.keys.insert(signer, SignerKey::Ed25519(key));
+        Ok(self)
+    }
+
+    /// Parse `name=hmac:<secret>` and `name=ed25519:<hex public key>` entries
+    pub fn from_entries<S: AsRef<str>>(entries: &[S]) -> Result<Self> {
+        entries.iter().try_fold(Self::new(), |signers, entry| {
+            let entry = entry.as_ref();
+            let invalid = || {
+                IngestionError::ValidationError(format!(
+                    "expected name=hmac:<secret> or name=ed25519:<hex key>, got {}",
+                    entry
+                ))
+            };
+            let (signer, key) = entry.split_once('=').ok_or_else(invalid)?;
+            let signer = signer.trim();
+            if signer.is_empty() {
+                return Err(invalid());
+            }
+            match key.split_once(':') {
+                Some(("hmac", secret)) if !secret.is_empty() => Ok(signers.with_hmac(signer, secret)),
+                Some(("ed25519", public_key)) => {
+                    let public_key = hex::decode(public_key.trim()).map_err(|_| invalid())?;
+                    signers.with_ed25519(signer, &public_key)
+                }
+                _ => Err(invalid()),
+            }
+        })
+    }
+
+    /// Whether signed ingestion is enabled
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Every trusted source and how it signs
+    pub fn signers(&self) -> BTreeMap<String, SignatureAlgorithm> {
+        self.keys
+            .iter()
+            .map(|(signer, key)| (signer.clone(), key.algorithm()))
+            .collect()
+    }
+
+    /// Start checking a payload against the signature claimed for it
+    pub fn check(&self, claim: Option<&SignatureClaim>) -> PayloadCheck {
+        let Some(claim) = claim else {
+            return PayloadCheck::failed(None, "payload is not signed");
+        };
+        let signer = Some(claim.signer.clone());
+        let Some(key) = self.keys.get(&claim.signer) else {
+            return PayloadCheck::failed(signer, "signer is not trusted");
+        };
+        let Ok(signature) = hex::decode(claim.signature.trim()) else {
+            return PayloadCheck::failed(signer, "signature is not hex");
+        };
+
+        let state = match key {
+            SignerKey::Hmac(secret) => CheckState::Hmac {
+                mac: HmacSha256::new_from_slice(secret).expect("HMAC can accept any key length"),
+                expected: signature,
+            },
+            SignerKey::Ed25519(key) => match Signature::from_slice(&signature) {
+                Ok(signature) => CheckState::Ed25519 { digest: Sha256::new(), key: *key, signature },
+                Err(_) => return PayloadCheck::failed(signer, "malformed Ed25519 signature"),
+            },
+        };
+        PayloadCheck { state, signer }
+    }
+}
+
+/// Signature a request claims for its payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureClaim {
+    pub signer: String,
+    /// Hex-encoded signature
+    pub signature: String,
+}
+
+impl SignatureClaim {
+    pub fn new(signer: impl Into<String>, signature: impl Into<String>) -> Self {
+        Self {
+            signer: signer.into(),
+            signature: signature.into(),
+        }
+    }
+}
+
+/// Whether a payload's signature checked out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub trust: Trust,
+    /// Source the payload claims to be signed by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    /// Why the payload is untrusted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+enum CheckState {
+    Hmac { mac: HmacSha256, expected: Vec<u8> },
+    Ed25519 { digest: Sha256, key: VerifyingKey, signature: Signature },
+    Failed(String),
+}
+
+/// Signature check fed with a payload as it streams in
+pub struct PayloadCheck {
+    state: CheckState,
+    signer: Option<String>,
+}
+
+impl PayloadCheck {
+    fn failed(signer: Option<String>, reason: &str) -> Self {
+        Self {
+            state: CheckState::Failed(reason.to_string()),
+            signer,
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        match &mut self.state {
+            CheckState::Hmac { mac, .. } => mac.update(bytes),
+            CheckState::Ed25519 { digest, .. } => digest.update(bytes),
+            CheckState::Failed(_) => {}
+        }
+    }
+
+    /// Verify the signature against everything fed so far
+    pub fn finish(self) -> Provenance {
+        let result = match self.state {
+            CheckState::Hmac { mac, expected } => {
+                mac.verify_slice(&expected).map_err(|_| "signature mismatch".to_string())
+            }
+            CheckState::Ed25519 { digest, key, signature } => key
+                .verify(&digest.finalize(), &signature)
+                .map_err(|_| "signature mismatch".to_string()),
+            CheckState::Failed(reason) => Err(reason),
+        };
+
+        match result {
+            Ok(()) => Provenance {
+                trust: Trust::Verified,
+                signer: self.signer,
+                reason: None,
+            },
+            Err(reason) => Provenance {
+                trust: Trust::Untrusted,
+                signer: self.signer,
+                reason: Some(reason),
+            },
+        }
+    }
+}
+
+/// Holds a document's chunks until its signature has been checked
+pub struct SignatureGate {
+    inner: Arc<dyn ChunkSink>,
+    held: Mutex<Vec<ProcessedChunk>>,
+}
+
+impl SignatureGate {
+    pub fn new(inner: Arc<dyn ChunkSink>) -> Self {
+        Self {
+            inner,
+            held: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Label the held chunks with the payload's provenance and pass them on,
+    /// returning how many were passed on
+    pub async fn release(&self, provenance: &Provenance) -> Result<usize> {
+        if let Some(reason) = &provenance.reason {
+            warn!(signer = ?provenance.signer, "Storing untrusted document: {}", reason);
+        }
+
+        let held = std::mem::take(&mut *self.held.lock().expect("signature gate poisoned"));
+        let count = held.len();
+        for mut chunk in held {
+            chunk
+                .metadata
+                .insert(TRUST_KEY.to_string(), serde_json::json!(provenance.trust));
+            if let Some(signer) = &provenance.signer {
+                chunk.metadata.insert(SIGNER_KEY.to_string(), serde_json::json!(signer));
+            }
+            self.inner.accept(chunk).await?;
+        }
+        Ok(count)
+    }
+}
+
+#[async_trait]
+impl ChunkSink for SignatureGate {
+    async fn accept(&self, chunk: ProcessedChunk) -> Result<()> {
+        self.held.lock().expect("signature gate poisoned").push(chunk);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const PAYLOAD: &[u8] = b"Rotate the billing database credentials every 90 days.";
+
+    fn checked(signers: &TrustedSigners, claim: Option<SignatureClaim>, payload: &[u8]) -> Provenance {
+        let mut check = signers.check(claim.as_ref());
+        // Split the payload to check it the way uploads arrive
+        let (head, tail) = payload.split_at(payload.len() / 2);
+        check.update(head);
+        check.update(tail);
+        check.finish()
+    }
+
+    #[test]
+    fn test_hmac_signatures() {
+        let signers = TrustedSigners::new().with_hmac("docs-bot", "s3cret");
+        let mut mac = HmacSha256::new_from_slice(b"s3cret").unwrap();
+        mac.update(PAYLOAD);
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        let verified = checked(&signers, Some(SignatureClaim::new("docs-bot", &signature)), PAYLOAD);
+        assert_eq!(verified.trust, Trust::Verified);
+        assert_eq!(verified.signer.as_deref(), Some("docs-bot"));
+
+        let tampered = checked(&signers, Some(SignatureClaim::new("docs-bot", &signature)), b"Never rotate them.");
+        assert_eq!(tampered.trust, Trust::Untrusted);
+        assert_eq!(tampered.reason.as_deref(), Some("signature mismatch"));
+
+        let unknown = checked(&signers, Some(SignatureClaim::new("intruder", &signature)), PAYLOAD);
+        assert_eq!(unknown.reason.as_deref(), Some("signer is not trusted"));
+
+        let unsigned = checked(&signers, None, PAYLOAD);
+        assert_eq!((unsigned.trust, unsigned.signer), (Trust::Untrusted, None));
+    }
+
+    #[test]
+    fn test_ed25519_signatures_cover_the_digest() {
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let signers = TrustedSigners::new()
+            .with_ed25519("release-pipeline", signing_key.verifying_key().as_bytes())
+            .unwrap();
+        assert_eq!(signers.signers()["release-pipeline"], SignatureAlgorithm::Ed25519);
+
+        let signature = hex::encode(signing_key.sign(&Sha256::digest(PAYLOAD)).to_bytes());
+        let claim = SignatureClaim::new("release-pipeline", signature);
+        assert_eq!(checked(&signers, Some(claim.clone()), PAYLOAD).trust, Trust::Verified);
+        assert_eq!(checked(&signers, Some(claim), b"tampered").trust, Trust::Untrusted);
+
+        assert!(TrustedSigners::new().with_ed25519("short", &[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_parse_entries() {
+        let public_key = hex::encode(SigningKey::from_bytes(&[7; 32]).verifying_key().as_bytes());
+        let signers =
+            TrustedSigners::from_entries(&["docs-bot=hmac:s3cret".to_string(), format!("release=ed25519:{}", public_key)])
+                .unwrap();
+        assert_eq!(
+            signers.signers().into_iter().collect::<Vec<_>>(),
+            vec![
+                ("docs-bot".to_string(), SignatureAlgorithm::HmacSha256),
+                ("release".to_string(), SignatureAlgorithm::Ed25519),
+            ]
+        );
+
+        for entry in ["docs-bot", "=hmac:s3cret", "docs-bot=hmac:", "docs-bot=rsa:abc", "release=ed25519:zz"] {
+            assert!(TrustedSigners::from_entries(&[entry]).is_err(), "{}", entry);
+        }
+        assert!(TrustedSigners::from_entries::<&str>(&[]).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_gate_labels_chunks_on_release() {
+        struct Collect(Mutex<Vec<ProcessedChunk>>);
+
+        #[async_trait]
+        impl ChunkSink for Collect {
+            async fn accept(&self, chunk: ProcessedChunk) -> Result<()> {
+                self.0.lock().unwrap().push(chunk);
+                Ok(())
+            }
+        }
+
+        let stored = Arc::new(Collect(Mutex::new(Vec::new())));
+        let gate = SignatureGate::new(stored.clone());
+        gate.accept(ProcessedChunk {
+            id: "doc_0".to_string(),
+            document_id: "doc".to_string(),
+            content: "content".to_string(),
+            content_hash: "hash".to_string(),
+            metadata: HashMap::new(),
+            embedding: None,
+        })
+        .await
+        .unwrap();
+        assert!(stored.0.lock().unwrap().is_empty());
+
+        let provenance = checked(&TrustedSigners::new().with_hmac("docs-bot", "s3cret"), None, PAYLOAD);
+        assert_eq!(gate.release(&provenance).await.unwrap(), 1);
+
+        let stored = stored.0.lock().unwrap();
+        assert_eq!(stored[0].metadata[TRUST_KEY], "untrusted");
+        assert!(!stored[0].metadata.contains_key(SIGNER_KEY));
+    }
+}
@@ -0,0 +1,626 @@
+//! Streaming ingestion
+//!
+//! Ingests a document as its bytes arrive instead of buffering the whole
+//! upload. Incoming bytes are decoded incrementally, text is accumulated into
+//! a bounded window, and each full window is cut at a natural boundary
+//! (paragraph, line or word), chunked, processed and handed to a
+//! [`ChunkSink`]. Only text-like content types can be streamed; structured
+//! formats such as JSON need the full document and go through
+//! [`IngestionPipeline`](crate::IngestionPipeline) instead.
+//!
+//! A signed upload's chunks are held until the whole payload has been
+//! checked; see [`signing`](crate::signing).
+
+use async_trait::async_trait;
+use copilot_context::freshness::{DOCUMENT_KEY, VERSION_KEY};
+use copilot_context::{ContextEngine, MemoryMetadata, OpenSearchBackend, SearchDocument, Trust};
+use copilot_core::REGION_KEY;
+use encoding_rs::{Decoder, UTF_8};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, info};
+
+use crate::chunking::{Chunk, ChunkMetadata, ChunkingConfig, TextChunker};
+use crate::pipeline::{DocumentMetadata, ProcessedChunk};
+use crate::processors::ProcessorChain;
+use crate::rules::TAGS_KEY;
+use crate::signing::{PayloadCheck, SignatureGate};
+use crate::{IngestionError, Result};
+
+/// Receives chunks as soon as they are produced
+#[async_trait]
+pub trait ChunkSink: Send + Sync {
+    async fn accept(&self, chunk: ProcessedChunk) -> Result<()>;
+}
+
+/// Stores chunks in a context engine so they become retrievable
+pub struct ContextEngineSink {
+    engine: Arc<dyn ContextEngine>,
+    source: String,
+    document: Option<String>,
+    region: Option<String>,
+    importance: f64,
+}
+
+impl ContextEngineSink {
+    pub fn new(engine: Arc<dyn ContextEngine>, source: impl Into<String>) -> Self {
+        Self {
+            engine,
+            source: source.into(),
+            document: None,
+            region: None,
+            importance: 0.5,
+        }
+    }
+
+    /// Name the document the chunks come from, so ingesting it again
+    /// supersedes these chunks
+    pub fn with_document(mut self, document: impl Into<String>) -> Self {
+        self.document = Some(document.into());
+        self
+    }
+
+    pub fn with_importance(mut self, importance: f64) -> Self {
+        self.importance = importance.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Tag the chunks with the region they are stored in
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+}
+
+#[async_trait]
+impl ChunkSink for ContextEngineSink {
+    async fn accept(&self, chunk: ProcessedChunk) -> Result<()> {
+        let mut metadata = chunk_metadata(&chunk, &self.source, self.document.as_deref());
+        if let Some(region) = &self.region {
+            metadata.add_custom(REGION_KEY.to_string(), serde_json::json!(region));
+        }
+        self.engine
+            .store(chunk.content, metadata, self.importance)
+            .await
+            .map_err(|e| IngestionError::PipelineError(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Context metadata for a chunk of a document from `source`
+fn chunk_metadata(chunk: &ProcessedChunk, source: &str, document: Option<&str>) -> MemoryMetadata {
+    let mut metadata = MemoryMetadata::new("document", source);
+    // Keep whatever the producer attached (e.g. email thread metadata)
+    for (key, value) in &chunk.metadata {
+        if key == TAGS_KEY {
+            let tags = value.as_array().into_iter().flatten().filter_map(|tag| tag.as_str());
+            metadata.tags.extend(tags.map(String::from));
+            continue;
+        }
+        metadata.add_custom(key.clone(), value.clone());
+    }
+    if let Some(document) = document {
+        metadata.add_custom(DOCUMENT_KEY.to_string(), serde_json::json!(document));
+    }
+    metadata.add_custom(VERSION_KEY.to_string(), serde_json::json!(chunk.document_id));
+    metadata.add_custom("chunk_id".to_string(), serde_json::json!(chunk.id));
+    metadata.add_custom("content_hash".to_string(), serde_json::json!(chunk.content_hash));
+    metadata
+}
+
+/// Bulk-indexes chunks into an OpenSearch or Elasticsearch cluster
+///
+/// Chunks are buffered and sent once a batch fills up; call
+/// [`flush`](Self::flush) when the document is done. Chunks the pipeline
+/// already embedded keep their vector.
+pub struct SearchIndexSink {
+    backend: Arc<OpenSearchBackend>,
+    source: String,
+    document: Option<String>,
+    batch_size: usize,
+    pending: Mutex<Vec<SearchDocument>>,
+}
+
+impl SearchIndexSink {
+    pub fn new(backend: Arc<OpenSearchBackend>, source: impl Into<String>) -> Self {
+        let batch_size = backend.config().bulk_batch_size;
+        Self {
+            backend,
+            source: source.into(),
+            document: None,
+            batch_size,
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Name the document the chunks come from
+    pub fn with_document(mut self, document: impl Into<String>) -> Self {
+        self.document = Some(document.into());
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Chunks waiting for the next bulk request
+    pub async fn pending(&self) -> usize {
+        self.pending.lock().await.len()
+    }
+
+    /// Index every buffered chunk
+    pub async fn flush(&self) -> Result<()> {
+        let batch = std::mem::take(&mut *self.pending.lock().await);
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let report = self
+            .backend
+            .index_documents(batch)
+            .await
+            .map_err(|e| IngestionError::PipelineError(e.to_string()))?;
+        if let Some((id, reason)) = report.failed.first() {
+            return Err(IngestionError::PipelineError(format!(
+                "Search index rejected {} chunks (first {}: {})",
+                report.failed.len(),
+                id,
+                reason
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChunkSink for SearchIndexSink {
+    async fn accept(&self, chunk: ProcessedChunk) -> Result<()> {
+        let metadata = chunk_metadata(&chunk, &self.source, self.document.as_deref());
+        let mut document = SearchDocument::new(chunk.id, chunk.content, metadata);
+        document.embedding = chunk.embedding;
+
+        let full = {
+            let mut pending = self.pending.lock().await;
+            pending.push(document);
+            pending.len() >= self.batch_size
+        };
+        if full {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Streaming ingestion configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamingConfig {
+    /// Chunking configuration applied to each window
+    pub chunking: ChunkingConfig,
+    /// Maximum document size in bytes
+    pub max_document_size: usize,
+    /// Window size, in chunks, accumulated before chunking
+    pub window_chunks: usize,
+}
+
+impl Default for StreamingConfig {
+    fn default() -> Self {
+        Self {
+            chunking: ChunkingConfig::default(),
+            max_document_size: 50 * 1024 * 1024, // 50MB
+            window_chunks: 8,
+        }
+    }
+}
+
+impl StreamingConfig {
+    /// Window size in bytes (chunk size is in tokens, ~4 chars per token)
+    fn window_bytes(&self) -> usize {
+        (self.chunking.chunk_size * 4 * self.window_chunks.max(1)).max(1)
+    }
+}
+
+/// Summary of a completed streaming ingestion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamingSummary {
+    pub document_id: String,
+    pub bytes_received: u64,
+    pub chunk_count: usize,
+    pub processing_time_ms: u64,
+    pub warnings: Vec<String>,
+    /// Whether the payload's signature checked out, when signed ingestion
+    /// is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trust: Option<Trust>,
+    /// Source the payload claims to be signed by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+}
+
+/// Whether a content type can be ingested incrementally
+pub fn is_streamable(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    mime.starts_with("text/")
+        || matches!(
+            mime,
+            "application/x-ndjson" | "application/x-yaml" | "application/yaml" | "application/xml"
+        )
+}
+
+/// Incremental ingestion of a single document
+pub struct StreamingIngestor {
+    document_id: String,
+    config: StreamingConfig,
+    chunker: TextChunker,
+    chain: Arc<ProcessorChain>,
+    sink: Arc<dyn ChunkSink>,
+    decoder: Decoder,
+    pending: String,
+    consumed_chars: usize,
+    bytes_received: u64,
+    chunk_count: usize,
+    warnings: Vec<String>,
+    started: std::time::Instant,
+    signature: Option<(PayloadCheck, Arc<SignatureGate>)>,
+}
+
+impl StreamingIngestor {
+    pub fn new(
+        document_id: impl Into<String>,
+        metadata: &DocumentMetadata,
+        config: StreamingConfig,
+        chain: Arc<ProcessorChain>,
+        sink: Arc<dyn ChunkSink>,
+    ) -> Result<Self> {
+        if !is_streamable(&metadata.content_type) {
+            return Err(IngestionError::UnsupportedType(format!(
+                "{} cannot be ingested as a stream",
+                metadata.content_type
+            )));
+        }
+
+        Ok(Self {
+            document_id: document_id.into(),
+            chunker: TextChunker::new(config.chunking.clone())?,
+            config,
+            chain,
+            sink,
+            decoder: UTF_8.new_decoder(),
+            pending: String::new(),
+            consumed_chars: 0,
+            bytes_received: 0,
+            chunk_count: 0,
+            warnings: Vec::new(),
+            started: std::time::Instant::now(),
+            signature: None,
+        })
+    }
+
+    /// Check the payload with `check` and hold chunks until it has been
+    /// checked, then store them labeled verified or untrusted
+    pub fn with_signature(mut self, check: PayloadCheck) -> Self {
+        let gate = Arc::new(SignatureGate::new(self.sink.clone()));
+        self.sink = gate.clone();
+        self.signature = Some((check, gate));
+        self
+    }
+
+    pub fn document_id(&self) -> &str {
+        &self.document_id
+    }
+
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Chunks emitted so far
+    pub fn chunk_count(&self) -> usize {
+        self.chunk_count
+    }
+
+    /// Feed the next piece of the upload, returning the number of chunks emitted
+    pub async fn push(&mut self, bytes: &[u8]) -> Result<usize> {
+        self.bytes_received += bytes.len() as u64;
+        if self.bytes_received > self.config.max_document_size as u64 {
+            return Err(IngestionError::ValidationError(format!(
+                "Document too large: more than {} bytes",
+                self.config.max_document_size
+            )));
+        }
+
+        if let Some((check, _)) = &mut self.signature {
+            check.update(bytes);
+        }
+        self.decode(bytes, false);
+
+        let window = self.config.window_bytes();
+        let mut emitted = 0;
+        while self.pending.len() >= window {
+            let split = split_point(&self.pending, window);
+            let remainder = self.pending.split_off(split);
+            let segment = std::mem::replace(&mut self.pending, remainder);
+            emitted += self.emit(&segment).await?;
+        }
+        Ok(emitted)
+    }
+
+    /// Flush remaining text and return the ingestion summary
+    pub async fn finish(mut self) -> Result<StreamingSummary> {
+        self.decode(&[], true);
+        let rest = std::mem::take(&mut self.pending);
+        self.emit(&rest).await?;
+
+        let provenance = match self.signature.take() {
+            Some((check, gate)) => {
+                let provenance = check.finish();
+                gate.release(&provenance).await?;
+                Some(provenance)
+            }
+            None => None,
+        };
+
+        let summary = StreamingSummary {
+            document_id: self.document_id,
+            bytes_received: self.bytes_received,
+            chunk_count: self.chunk_count,
+            processing_time_ms: self.started.elapsed().as_millis() as u64,
+            warnings: self.warnings,
+            trust: provenance.as_ref().map(|p| p.trust),
+            signer: provenance.and_then(|p| p.signer),
+        };
+
+        info!(
+            document_id = %summary.document_id,
+            bytes = summary.bytes_received,
+            chunk_count = summary.chunk_count,
+            "Streamed document ingested"
+        );
+        Ok(summary)
+    }
+
+    /// Consume a byte stream to completion
+    pub async fn ingest_stream<S, B, E>(mut self, stream: S) -> Result<StreamingSummary>
+    where
+        S: Stream<Item = std::result::Result<B, E>>,
+        B: AsRef<[u8]>,
+        E: std::fmt::Display,
+    {
+        futures::pin_mut!(stream);
+        while let Some(piece) = stream.next().await {
+            let piece = piece.map_err(|e| IngestionError::PipelineError(format!("Upload stream failed: {}", e)))?;
+            self.push(piece.as_ref()).await?;
+        }
+        self.finish().await
+    }
+
+    fn decode(&mut self, bytes: &[u8], last: bool) {
+        let needed = self
+            .decoder
+            .max_utf8_buffer_length(bytes.len())
+            .unwrap_or(bytes.len() * 3 + 4);
+        self.pending.reserve(needed);
+
+        let (_, _, had_errors) = self.decoder.decode_to_string(bytes, &mut self.pending, last);
+        if had_errors && !self.warnings.iter().any(|w| w.starts_with("Invalid UTF-8")) {
+            self.warnings
+                .push("Invalid UTF-8 sequences were replaced".to_string());
+        }
+    }
+
+    /// Chunk, process and sink one window of text
+    async fn emit(&mut self, segment: &str) -> Result<usize> {
+        let base = self.consumed_chars;
+        self.consumed_chars += segment.chars().count();
+
+        let chunks = self.chunker.chunk(&self.document_id, segment)?;
+        let mut emitted = 0;
+
+        for chunk in chunks {
+            // Re-index so chunk IDs and offsets are unique across windows
+            let chunk = Chunk::new(
+                self.document_id.clone(),
+                chunk.content,
+                ChunkMetadata {
+                    index: self.chunk_count,
+                    start_offset: base + chunk.metadata.start_offset,
+                    end_offset: base + chunk.metadata.end_offset,
+                    ..chunk.metadata
+                },
+            );
+
+            match self.chain.process_chunk(&chunk).await {
+                Ok(processed) => {
+                    self.sink.accept(ProcessedChunk::from(processed)).await?;
+                    self.chunk_count += 1;
+                    emitted += 1;
+                }
+                Err(IngestionError::DuplicateDocument(_)) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        debug!(document_id = %self.document_id, emitted, "Window ingested");
+        Ok(emitted)
+    }
+}
+
+/// Byte index at which to cut `text`, preferring paragraph, line and word
+/// boundaries within the first `window` bytes
+fn split_point(text: &str, window: usize) -> usize {
+    let mut limit = window.min(text.len());
+    while !text.is_char_boundary(limit) {
+        limit -= 1;
+    }
+    let head = &text[..limit];
+
+    for separator in ["\n\n", "\n", " "] {
+        if let Some(pos) = head.rfind(separator) {
+            if pos > 0 {
+                return pos + separator.len();
+            }
+        }
+    }
+    if limit == 0 {
+        // A single character wider than the window
+        text.char_indices().nth(1).map(|(i, _)| i).unwrap_or(text.len())
+    } else {
+        limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::Mutex;
+
+    #[derive(Default)]
+    struct CollectingSink {
+        chunks: Mutex<Vec<ProcessedChunk>>,
+    }
+
+    #[async_trait]
+    impl ChunkSink for CollectingSink {
+        async fn accept(&self, chunk: ProcessedChunk) -> Result<()> {
+            self.chunks.lock().await.push(chunk);
+            Ok(())
+        }
+    }
+
+    fn config() -> StreamingConfig {
+        StreamingConfig {
+            chunking: ChunkingConfig::default().with_chunk_size(16).with_overlap(2),
+            max_document_size: 64 * 1024,
+            window_chunks: 2,
+        }
+    }
+
+    fn ingestor(sink: Arc<CollectingSink>, config: StreamingConfig) -> StreamingIngestor {
+        StreamingIngestor::new(
+            "doc",
+            &DocumentMetadata::new("text/plain", 0),
+            config,
+            Arc::new(ProcessorChain::new()),
+            sink,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_chunks_emitted_while_streaming() {
+        let sink = Arc::new(CollectingSink::default());
+        let mut ingestor = ingestor(sink.clone(), config());
+
+        let paragraph = "The quick brown fox jumps over the lazy dog again and again.\n\n";
+        let mut emitted_early = 0;
+        for _ in 0..10 {
+            emitted_early += ingestor.push(paragraph.as_bytes()).await.unwrap();
+        }
+        assert!(emitted_early > 0, "chunks should be emitted before the upload ends");
+
+        let summary = ingestor.finish().await.unwrap();
+        assert_eq!(summary.bytes_received, (paragraph.len() * 10) as u64);
+
+        let chunks = sink.chunks.lock().await;
+        assert_eq!(chunks.len(), summary.chunk_count);
+        let ids: std::collections::HashSet<_> = chunks.iter().map(|c| c.id.clone()).collect();
+        assert_eq!(ids.len(), chunks.len(), "chunk IDs must be unique across windows");
+    }
+
+    #[tokio::test]
+    async fn test_signed_upload_is_held_until_verified() {
+        use crate::signing::{SignatureClaim, TrustedSigners};
+        use hmac::{Hmac, Mac};
+
+        let text = "The quick brown fox jumps over the lazy dog again and again.\n\n".repeat(10);
+        let mut mac = Hmac::<sha2::Sha256>::new_from_slice(b"s3cret").unwrap();
+        mac.update(text.as_bytes());
+        let claim = SignatureClaim::new("docs-bot", hex::encode(mac.finalize().into_bytes()));
+        let signers = TrustedSigners::new().with_hmac("docs-bot", "s3cret");
+
+        let sink = Arc::new(CollectingSink::default());
+        let mut ingestor = ingestor(sink.clone(), config()).with_signature(signers.check(Some(&claim)));
+        assert!(ingestor.push(text.as_bytes()).await.unwrap() > 0);
+        assert!(sink.chunks.lock().await.is_empty(), "chunks must wait for the signature check");
+
+        let summary = ingestor.finish().await.unwrap();
+        assert_eq!(summary.trust, Some(Trust::Verified));
+        assert_eq!(summary.signer.as_deref(), Some("docs-bot"));
+        let chunks = sink.chunks.lock().await;
+        assert_eq!(chunks.len(), summary.chunk_count);
+        assert!(chunks.iter().all(|c| c.metadata["trust"] == "verified"));
+    }
+
+    #[tokio::test]
+    async fn test_multibyte_split_across_pushes() {
+        let sink = Arc::new(CollectingSink::default());
+        let mut ingestor = ingestor(sink.clone(), config());
+
+        let text = "héllo wörld ünïcode ".repeat(4);
+        let bytes = text.as_bytes();
+        // Split in the middle of "é" (2 bytes)
+        ingestor.push(&bytes[..2]).await.unwrap();
+        ingestor.push(&bytes[2..]).await.unwrap();
+        let summary = ingestor.finish().await.unwrap();
+
+        assert!(summary.warnings.is_empty());
+        let joined: String = sink.chunks.lock().await.iter().map(|c| c.content.clone()).collect();
+        assert!(joined.contains("héllo"));
+    }
+
+    #[tokio::test]
+    async fn test_size_limit_and_content_type() {
+        let sink = Arc::new(CollectingSink::default());
+        let mut ingestor = ingestor(
+            sink.clone(),
+            StreamingConfig {
+                max_document_size: 8,
+                ..config()
+            },
+        );
+        assert!(ingestor.push(b"0123456789").await.is_err());
+
+        let json = StreamingIngestor::new(
+            "doc",
+            &DocumentMetadata::new("application/json", 0),
+            config(),
+            Arc::new(ProcessorChain::new()),
+            sink,
+        );
+        assert!(matches!(json, Err(IngestionError::UnsupportedType(_))));
+    }
+
+    #[tokio::test]
+    async fn test_search_index_sink_buffers_batches() {
+        use copilot_context::{MockEmbeddingProvider, OpenSearchConfig, SearchFlavor};
+
+        // Nothing listens here, so a bulk request fails
+        let config = OpenSearchConfig::new("http://127.0.0.1:1", "context", SearchFlavor::OpenSearch, 8);
+        let backend = OpenSearchBackend::new(config, Arc::new(MockEmbeddingProvider::new(8))).unwrap();
+        let sink = SearchIndexSink::new(Arc::new(backend), "upload").with_batch_size(3);
+
+        for id in ["c1", "c2"] {
+            let chunk = ProcessedChunk {
+                id: id.to_string(),
+                document_id: "doc".to_string(),
+                content: "some text".to_string(),
+                content_hash: String::new(),
+                metadata: Default::default(),
+                embedding: None,
+            };
+            sink.accept(chunk).await.unwrap();
+        }
+        assert_eq!(sink.pending().await, 2);
+
+        assert!(sink.flush().await.is_err());
+        assert_eq!(sink.pending().await, 0);
+        sink.flush().await.unwrap();
+    }
+
+    #[test]
+    fn test_split_point_prefers_boundaries() {
+        assert_eq!(split_point("ab\n\ncd ef", 8), 4);
+        assert_eq!(split_point("abc def ghi", 9), 8);
+        assert_eq!(split_point("abcdefgh", 4), 4);
+    }
+}
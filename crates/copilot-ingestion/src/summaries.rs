@@ -0,0 +1,330 @@
+//! Per-document summaries for hierarchical retrieval
+//!
+//! [`SummaryStage`] summarizes each document once its text is extracted,
+//! leaving a short summary and keyword list on the
+//! [`IngestionResult`](crate::IngestionResult). [`index_hierarchically`]
+//! stores that summary as the document's parent node in a
+//! [`HierarchicalIndex`], above the chunks the pipeline produced, so that
+//! retrieval matches summaries first and then expands to their chunks.
+//!
+//! [`LlmSummarizer`] asks a language model for the summary;
+//! [`ExtractiveSummarizer`] needs no model and takes the leading sentences
+//! and most frequent terms instead.
+
+use async_trait::async_trait;
+use copilot_context::{DocumentSummary, HierarchicalIndex};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+use crate::pipeline::{IngestionResult, PipelineContext, PipelineStage};
+use crate::{IngestionError, Result};
+
+/// A language model that completes a prompt
+#[async_trait]
+pub trait SummaryModel: Send + Sync {
+    async fn complete(&self, prompt: &str) -> Result<String>;
+}
+
+/// Summarizes a document's text
+#[async_trait]
+pub trait DocumentSummarizer: Send + Sync {
+    async fn summarize(&self, text: &str) -> Result<DocumentSummary>;
+}
+
+/// Summarizes documents with a language model
+pub struct LlmSummarizer {
+    model: Arc<dyn SummaryModel>,
+    max_input_chars: usize,
+    max_keywords: usize,
+}
+
+impl LlmSummarizer {
+    pub fn new(model: Arc<dyn SummaryModel>) -> Self {
+        Self {
+            model,
+            max_input_chars: 12_000,
+            max_keywords: 10,
+        }
+    }
+
+    /// Characters of the document sent to the model; the rest is cut
+    pub fn with_max_input_chars(mut self, max_input_chars: usize) -> Self {
+        self.max_input_chars = max_input_chars;
+        self
+    }
+
+    pub fn with_max_keywords(mut self, max_keywords: usize) -> Self {
+        self.max_keywords = max_keywords;
+        self
+    }
+
+    fn prompt(&self, text: &str) -> String {
+        let excerpt: String = text.chars().take(self.max_input_chars).collect();
+        format!(
+            "Summarize the document below in two or three sentences, then list up to {} keywords it is about.\n\
+             Answer in exactly this format:\n\
+             Summary: <summary>\n\
+             Keywords: <keyword>, <keyword>, ...\n\n\
+             Document:\n{}",
+            self.max_keywords, excerpt
+        )
+    }
+
+    /// Parse a `Summary:` / `Keywords:` answer; an answer without the
+    /// labels is taken as the summary
+    fn parse(&self, answer: &str) -> DocumentSummary {
+        let mut summary = Vec::new();
+        let mut keywords = Vec::new();
+        let mut in_summary = false;
+        for line in answer.lines().map(str::trim) {
+            if let Some(rest) = strip_label(line, "summary:") {
+                summary.push(rest);
+                in_summary = true;
+            } else if let Some(rest) = strip_label(line, "keywords:") {
+                keywords.extend(rest.split(',').map(str::trim).filter(|k| !k.is_empty()).map(String::from));
+                in_summary = false;
+            } else if in_summary && !line.is_empty() {
+                summary.push(line);
+            }
+        }
+        if summary.is_empty() && keywords.is_empty() {
+            summary.push(answer.trim());
+        }
+
+        let mut seen = HashSet::new();
+        keywords.retain(|keyword| seen.insert(keyword.to_lowercase()));
+        keywords.truncate(self.max_keywords);
+        DocumentSummary::new(summary.join(" ").trim()).with_keywords(keywords)
+    }
+}
+
+fn strip_label<'a>(line: &'a str, label: &str) -> Option<&'a str> {
+    let head = line.get(..label.len())?;
+    head.eq_ignore_ascii_case(label).then(|| line[label.len()..].trim())
+}
+
+#[async_trait]
+impl DocumentSummarizer for LlmSummarizer {
+    async fn summarize(&self, text: &str) -> Result<DocumentSummary> {
+        let answer = self.model.complete(&self.prompt(text)).await?;
+        Ok(self.parse(&answer))
+    }
+}
+
+const STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "been", "but", "by", "can",
+    "do", "does", "each", "for", "from", "has", "have", "how", "if", "in", "into", "is", "it", "its", "may", "more",
+    "must", "no", "not", "of", "on", "or", "other", "our", "should", "so", "such", "than", "that", "the", "their",
+    "them", "then", "there", "these", "they", "this", "to", "was", "we", "were", "what", "when", "which", "while",
+    "will", "with", "would", "you", "your",
+];
+
+/// Summarizes documents without a model: the leading sentences, and the
+/// terms used most often
+#[derive(Debug, Clone)]
+pub struct ExtractiveSummarizer {
+    sentences: usize,
+    max_keywords: usize,
+}
+
+impl Default for ExtractiveSummarizer {
+    fn default() -> Self {
+        Self {
+            sentences: 3,
+            max_keywords: 8,
+        }
+    }
+}
+
+impl ExtractiveSummarizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_sentences(mut self, sentences: usize) -> Self {
+        self.sentences = sentences;
+        self
+    }
+
+    pub fn with_max_keywords(mut self, max_keywords: usize) -> Self {
+        self.max_keywords = max_keywords;
+        self
+    }
+
+    fn leading_sentences(&self, text: &str) -> String {
+        let mut summary = Vec::new();
+        for sentence in text.split_inclusive(['.', '!', '?', '\n']) {
+            let sentence = sentence.trim();
+            // Skip headings and list markers, which rarely say what a document is about
+            if sentence.split_whitespace().count() < 4 {
+                continue;
+            }
+            summary.push(sentence);
+            if summary.len() == self.sentences {
+                break;
+            }
+        }
+        summary.join(" ")
+    }
+
+    fn keywords(&self, text: &str) -> Vec<String> {
+        let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+        let words = text
+            .split(|c: char| !c.is_alphanumeric() && c != '-' && c != '_')
+            .map(|word| word.trim_matches(['-', '_']).to_lowercase())
+            .filter(|word| word.chars().count() > 2 && !STOPWORDS.contains(&word.as_str()))
+            .filter(|word| !word.chars().all(|c| c.is_ascii_digit()));
+        for (position, word) in words.enumerate() {
+            counts.entry(word).or_insert((0, position)).0 += 1;
+        }
+
+        let mut ranked: Vec<(String, (usize, usize))> = counts.into_iter().collect();
+        // Most frequent first; ties go to the term used first
+        ranked.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.1 .1.cmp(&b.1 .1)));
+        ranked.into_iter().take(self.max_keywords).map(|(word, _)| word).collect()
+    }
+}
+
+#[async_trait]
+impl DocumentSummarizer for ExtractiveSummarizer {
+    async fn summarize(&self, text: &str) -> Result<DocumentSummary> {
+        Ok(DocumentSummary::new(self.leading_sentences(text)).with_keywords(self.keywords(text)))
+    }
+}
+
+/// Pipeline stage summarizing each document after extraction
+///
+/// A summarizer failure leaves the document without a summary and adds a
+/// warning; it does not fail ingestion.
+pub struct SummaryStage {
+    summarizer: Arc<dyn DocumentSummarizer>,
+    min_chars: usize,
+}
+
+impl SummaryStage {
+    pub fn new(summarizer: Arc<dyn DocumentSummarizer>) -> Self {
+        Self {
+            summarizer,
+            min_chars: 200,
+        }
+    }
+
+    /// Documents shorter than this are not summarized; their single chunk
+    /// says as much as a summary would
+    pub fn with_min_chars(mut self, min_chars: usize) -> Self {
+        self.min_chars = min_chars;
+        self
+    }
+}
+
+#[async_trait]
+impl PipelineStage for SummaryStage {
+    fn name(&self) -> &'static str {
+        "summary"
+    }
+
+    async fn process(&self, context: &mut PipelineContext) -> Result<()> {
+        let Some(text) = context.extracted_text.as_deref() else {
+            return Ok(());
+        };
+        if text.trim().chars().count() < self.min_chars {
+            return Ok(());
+        }
+
+        match self.summarizer.summarize(text).await {
+            Ok(summary) if !summary.is_empty() => {
+                debug!(
+                    document_id = %context.document.id,
+                    keywords = summary.keywords.len(),
+                    "Document summarized"
+                );
+                context.summary = Some(summary);
+            }
+            Ok(_) => context.add_warning("Summarizer returned an empty summary"),
+            Err(e) => {
+                warn!(document_id = %context.document.id, error = %e, "Document summary failed");
+                context.add_warning(format!("Document not summarized: {}", e));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Index an ingested document into `index`: its summary as the parent node
+/// and its chunks beneath it
+pub async fn index_hierarchically(index: &HierarchicalIndex, result: &IngestionResult) -> Result<()> {
+    if !result.success || result.skipped_by.is_some() {
+        return Ok(());
+    }
+    let summary = result.summary.clone().unwrap_or_default();
+    let chunks: Vec<(String, String)> =
+        result.chunks.iter().map(|chunk| (chunk.id.clone(), chunk.content.clone())).collect();
+    index
+        .index_document(&result.document_id, &summary, &chunks)
+        .await
+        .map_err(|e| IngestionError::PipelineError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{Document, IngestionPipeline, PipelineConfig};
+    use copilot_context::{HierarchicalConfig, HybridSearchConfig, MockEmbeddingProvider};
+
+    struct CannedModel(&'static str);
+
+    #[async_trait]
+    impl SummaryModel for CannedModel {
+        async fn complete(&self, _prompt: &str) -> Result<String> {
+            Ok(self.0.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_llm_summary_is_parsed() {
+        let model = CannedModel("Summary: Explains how refunds are issued.\nIt covers partial refunds.\nKeywords: refunds, Payments, payments, ledger");
+        let summarizer = LlmSummarizer::new(Arc::new(model)).with_max_keywords(2);
+
+        let summary = summarizer.summarize("irrelevant").await.unwrap();
+        assert_eq!(summary.summary, "Explains how refunds are issued. It covers partial refunds.");
+        assert_eq!(summary.keywords, vec!["refunds".to_string(), "Payments".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_extractive_summary() {
+        let text = "# Caching\nThe cache stores rendered pages for five minutes. Cache entries are evicted when pages change. \
+                    Pages behind a login are never cached. Use the purge endpoint to clear the cache.";
+        let summary = ExtractiveSummarizer::new().with_sentences(2).with_max_keywords(2).summarize(text).await.unwrap();
+
+        assert_eq!(
+            summary.summary,
+            "The cache stores rendered pages for five minutes. Cache entries are evicted when pages change."
+        );
+        assert_eq!(summary.keywords, vec!["cache".to_string(), "pages".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_ingested_summaries_index_hierarchically() {
+        let mut pipeline = IngestionPipeline::with_defaults(PipelineConfig::default()).unwrap();
+        pipeline.insert_stage(1, Arc::new(SummaryStage::new(Arc::new(ExtractiveSummarizer::new())).with_min_chars(0)));
+
+        let result = pipeline
+            .ingest(Document::from_text("runbook", "Restart the ingestion workers when the queue backs up. Workers drain the queue on restart."))
+            .await;
+        assert!(result.success);
+        let summary = result.summary.clone().expect("summary");
+        assert!(summary.keywords.contains(&"queue".to_string()));
+
+        let index = HierarchicalIndex::new(
+            HierarchicalConfig::default(),
+            HybridSearchConfig::default(),
+            Arc::new(MockEmbeddingProvider::new(32)),
+        );
+        index_hierarchically(&index, &result).await.unwrap();
+        assert_eq!(index.summary_text("runbook").await, Some(summary.text()));
+
+        let found = index.search("restart workers queue", 3).await.unwrap();
+        assert!(found.iter().any(|r| r.document_id == "runbook" && r.document_score.is_some()));
+    }
+}
@@ -0,0 +1,632 @@
+//! Wiki exports
+//!
+//! Reads the pages of a wiki exported in bulk, for a
+//! [`CorpusBootstrap`](crate::bootstrap::CorpusBootstrap) to ingest in one go.
+//! Two export formats are understood:
+//!
+//! - Confluence space or site exports: the `entities.xml` object dump, on its
+//!   own or inside the export zip. Only current page versions are read, and
+//!   their storage-format XHTML is reduced to markdown.
+//! - Zips of markdown files, such as GitHub, GitLab or Wiki.js wiki exports.
+//!   The top-level directory is the page's space and the directories below
+//!   it its path; the title comes from front matter, the first heading or
+//!   the file name.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use tracing::warn;
+
+use copilot_context::freshness::DOCUMENT_KEY;
+
+use crate::pipeline::{Document, DocumentMetadata};
+use crate::rules::TAGS_KEY;
+use crate::{IngestionError, Result};
+
+/// Largest markdown page read from a zip; bigger entries are left out
+const MAX_PAGE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Largest `entities.xml` read from a Confluence export zip
+const MAX_ENTITIES_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// How deep a Confluence page tree is followed when building breadcrumbs
+const MAX_DEPTH: usize = 32;
+
+/// Where an export came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WikiFormat {
+    Confluence,
+    Markdown,
+}
+
+/// One page of a wiki export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WikiPage {
+    /// Confluence page id, or the file's path without extension
+    pub id: String,
+    pub title: String,
+    pub space: Option<String>,
+    /// Titles of the ancestor pages (or directories), outermost first
+    #[serde(default)]
+    pub path: Vec<String>,
+    /// Page content as markdown
+    pub body: String,
+    /// Confluence labels or front matter tags
+    #[serde(default)]
+    pub labels: Vec<String>,
+    pub updated_at: Option<String>,
+}
+
+impl WikiPage {
+    /// Name of the page within the wiki, e.g. `Engineering/Runbooks/Deploys`,
+    /// which later ingestions of the same page supersede
+    pub fn document_name(&self) -> String {
+        let mut parts: Vec<&str> = self.space.iter().map(String::as_str).collect();
+        parts.extend(self.path.iter().map(String::as_str));
+        parts.push(&self.title);
+        parts.join("/")
+    }
+
+    /// Metadata attached to every chunk of the page
+    pub fn to_metadata(&self) -> HashMap<String, serde_json::Value> {
+        let mut metadata = HashMap::new();
+        metadata.insert("wiki_page_id".to_string(), serde_json::json!(self.id));
+        metadata.insert("wiki_title".to_string(), serde_json::json!(self.title));
+        if let Some(space) = &self.space {
+            metadata.insert("wiki_space".to_string(), serde_json::json!(space));
+        }
+        if !self.path.is_empty() {
+            metadata.insert("wiki_path".to_string(), serde_json::json!(self.path.join(" / ")));
+        }
+        if let Some(updated_at) = &self.updated_at {
+            metadata.insert("wiki_updated_at".to_string(), serde_json::json!(updated_at));
+        }
+        metadata.insert(DOCUMENT_KEY.to_string(), serde_json::json!(self.document_name()));
+        metadata
+    }
+
+    /// The page as a markdown document, tagged `wiki` and with its labels
+    pub fn to_document(&self, source: &str) -> Document {
+        let body = self.body.trim();
+        let text = if body.starts_with("# ") {
+            body.to_string()
+        } else {
+            format!("# {}\n\n{}", self.title, body)
+        };
+        let content = text.into_bytes();
+
+        let mut tags = vec![serde_json::json!("wiki")];
+        tags.extend(self.labels.iter().map(|label| serde_json::json!(label)));
+        let mut metadata = DocumentMetadata::new("text/markdown", content.len())
+            .with_filename(format!("{}.md", self.document_name()))
+            .with_source(source)
+            .with_custom(TAGS_KEY, serde_json::Value::Array(tags));
+        metadata.custom.extend(self.to_metadata());
+
+        Document::new(format!("wiki:{}", self.id), content, metadata)
+    }
+}
+
+/// The pages of a wiki export
+#[derive(Debug, Clone)]
+pub struct WikiExport {
+    pub format: WikiFormat,
+    pub pages: Vec<WikiPage>,
+}
+
+impl WikiExport {
+    /// Parse an export, telling the format from its content: a zip holding
+    /// `entities.xml` or markdown files, or a bare `entities.xml`
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.starts_with(b"PK\x03\x04") {
+            return Self::from_zip(bytes);
+        }
+        let xml = std::str::from_utf8(bytes)
+            .map_err(|_| IngestionError::ValidationError("Wiki export is neither a zip nor XML".to_string()))?;
+        if !xml.contains("<hibernate-generic") && !xml.contains("<object class=\"Page\"") {
+            return Err(IngestionError::ValidationError(
+                "Wiki export is neither a zip nor a Confluence entities.xml".to_string(),
+            ));
+        }
+        Ok(Self::from_confluence_xml(xml))
+    }
+
+    /// Read a Confluence `entities.xml`
+    pub fn from_confluence_xml(xml: &str) -> Self {
+        let entities = entities(xml);
+        let of_class = |class: &'static str| entities.iter().filter(move |e| e.class == class);
+
+        let spaces: HashMap<&str, String> = of_class("Space")
+            .filter_map(|space| {
+                let name = space.text("name").or_else(|| space.text("key"))?;
+                Some((space.id.as_str(), name))
+            })
+            .collect();
+        let bodies: HashMap<String, String> = of_class("BodyContent")
+            .filter_map(|body| Some((body.reference("content")?, body.text("body")?)))
+            .collect();
+        let label_names: HashMap<&str, String> = of_class("Label")
+            .filter_map(|label| Some((label.id.as_str(), label.text("name")?)))
+            .collect();
+        let mut labels: HashMap<String, Vec<String>> = HashMap::new();
+        for labelling in of_class("Labelling") {
+            let name = labelling.reference("label").and_then(|id| label_names.get(id.as_str()));
+            if let (Some(name), Some(content)) = (name, labelling.reference("content")) {
+                labels.entry(content).or_default().push(name.clone());
+            }
+        }
+
+        // Historical versions point at the current page; drafts and trashed
+        // pages have another status
+        let current: HashMap<&str, &Entity> = of_class("Page")
+            .filter(|page| page.reference("originalVersion").is_none())
+            .filter(|page| matches!(page.text("contentStatus").as_deref(), None | Some("current")))
+            .map(|page| (page.id.as_str(), page))
+            .collect();
+
+        let mut pages: Vec<WikiPage> = current
+            .values()
+            .map(|page| {
+                let mut path = Vec::new();
+                let mut parent = page.reference("parent");
+                while let Some(ancestor) = parent.as_deref().and_then(|id| current.get(id)) {
+                    if path.len() >= MAX_DEPTH {
+                        break;
+                    }
+                    path.push(ancestor.text("title").unwrap_or_default());
+                    parent = ancestor.reference("parent");
+                }
+                path.reverse();
+
+                WikiPage {
+                    id: page.id.clone(),
+                    title: page.text("title").unwrap_or_else(|| format!("Page {}", page.id)),
+                    space: page.reference("space").and_then(|id| spaces.get(id.as_str()).cloned()),
+                    path,
+                    body: bodies.get(&page.id).map(|body| storage_to_markdown(body)).unwrap_or_default(),
+                    labels: labels.remove(&page.id).unwrap_or_default(),
+                    updated_at: page.text("lastModificationDate"),
+                }
+            })
+            .collect();
+        pages.sort_by_key(|page| page.document_name());
+
+        Self { format: WikiFormat::Confluence, pages }
+    }
+
+    /// Read a zip of markdown files, or a zipped Confluence export
+    pub fn from_zip(bytes: &[u8]) -> Result<Self> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(invalid_zip)?;
+
+        if let Ok(entry) = archive.by_name("entities.xml") {
+            let mut xml = String::new();
+            entry
+                .take(MAX_ENTITIES_BYTES)
+                .read_to_string(&mut xml)
+                .map_err(|e| IngestionError::ValidationError(format!("Unreadable entities.xml: {}", e)))?;
+            return Ok(Self::from_confluence_xml(&xml));
+        }
+
+        let mut pages = Vec::new();
+        for index in 0..archive.len() {
+            let entry = archive.by_index(index).map_err(invalid_zip)?;
+            // `enclosed_name` rejects absolute paths and `..`
+            let Some(path) = entry.enclosed_name().map(Path::to_path_buf) else {
+                continue;
+            };
+            if !entry.is_file() || !is_markdown(&path) || is_hidden(&path) {
+                continue;
+            }
+            if entry.size() > MAX_PAGE_BYTES {
+                warn!(path = %path.display(), size = entry.size(), "Skipping oversized wiki page");
+                continue;
+            }
+
+            let mut content = Vec::new();
+            entry.take(MAX_PAGE_BYTES).read_to_end(&mut content)?;
+            pages.push(markdown_page(&path, &String::from_utf8_lossy(&content)));
+        }
+        if pages.is_empty() {
+            return Err(IngestionError::ValidationError(
+                "Wiki export holds neither entities.xml nor markdown files".to_string(),
+            ));
+        }
+        pages.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(Self { format: WikiFormat::Markdown, pages })
+    }
+}
+
+fn invalid_zip(e: zip::result::ZipError) -> IngestionError {
+    IngestionError::ValidationError(format!("Invalid wiki export zip: {}", e))
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|ext| matches!(ext.as_str(), "md" | "markdown"))
+}
+
+/// Dot files and the resource forks macOS adds to zips
+fn is_hidden(path: &Path) -> bool {
+    path.components().any(|part| {
+        let part = part.as_os_str().to_string_lossy();
+        part.starts_with('.') || part == "__MACOSX"
+    })
+}
+
+fn markdown_page(path: &Path, text: &str) -> WikiPage {
+    let (front_matter, body) = split_front_matter(text);
+    let mut directories: Vec<String> = path
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .map(|part| part.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let space = (!directories.is_empty()).then(|| directories.remove(0));
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+
+    let title = front_matter
+        .get("title")
+        .and_then(|title| title.as_str())
+        .map(String::from)
+        .or_else(|| body.lines().find_map(|line| line.strip_prefix("# ")).map(|h| h.trim().to_string()))
+        .unwrap_or_else(|| stem.replace(['-', '_'], " "));
+    let labels = match front_matter.get("tags") {
+        Some(serde_yaml::Value::Sequence(tags)) => tags.iter().filter_map(|t| t.as_str()).map(String::from).collect(),
+        Some(serde_yaml::Value::String(tags)) => {
+            tags.split(',').map(str::trim).filter(|t| !t.is_empty()).map(String::from).collect()
+        }
+        _ => Vec::new(),
+    };
+
+    WikiPage {
+        id: path.with_extension("").to_string_lossy().replace('\\', "/"),
+        title,
+        space,
+        path: directories,
+        body: body.trim().to_string(),
+        labels,
+        updated_at: front_matter.get("date").and_then(|date| date.as_str()).map(String::from),
+    }
+}
+
+/// Split YAML front matter (`---` delimited) off a markdown page
+fn split_front_matter(text: &str) -> (serde_yaml::Value, &str) {
+    let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+        return (serde_yaml::Value::Null, text);
+    };
+    let Some(end) = rest.find("\n---") else {
+        return (serde_yaml::Value::Null, text);
+    };
+    let front_matter = serde_yaml::from_str(&rest[..end]).unwrap_or(serde_yaml::Value::Null);
+    let body = rest[end + 4..].split_once('\n').map_or("", |(_, body)| body);
+    (front_matter, body)
+}
+
+/// One `<object>` of a Confluence `entities.xml`
+struct Entity<'a> {
+    class: &'a str,
+    id: String,
+    properties: HashMap<&'a str, &'a str>,
+}
+
+impl Entity<'_> {
+    /// A plain property's value
+    fn text(&self, name: &str) -> Option<String> {
+        let raw = self.properties.get(name)?;
+        if !raw.contains("<![CDATA[") {
+            return Some(decode_entities(raw.trim())).filter(|text| !text.is_empty());
+        }
+        // Confluence splits CDATA sections around any `]]>` in the value
+        let mut text = String::new();
+        let mut rest = *raw;
+        while let Some(start) = rest.find("<![CDATA[") {
+            let inner = &rest[start + 9..];
+            let end = inner.find("]]>").unwrap_or(inner.len());
+            text.push_str(&inner[..end]);
+            rest = &inner[(end + 3).min(inner.len())..];
+        }
+        Some(text)
+    }
+
+    /// The id of the object a property refers to
+    fn reference(&self, name: &str) -> Option<String> {
+        id_of(self.properties.get(name)?)
+    }
+}
+
+fn id_of(xml: &str) -> Option<String> {
+    let start = xml.find("<id name=\"id\">")? + 14;
+    let end = xml[start..].find("</id>")?;
+    Some(decode_entities(xml[start..start + end].trim()))
+}
+
+fn entities(xml: &str) -> Vec<Entity<'_>> {
+    let mut entities = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find("<object class=\"") {
+        let object = &rest[start + 15..];
+        let (Some(class_end), Some(end)) = (object.find('"'), object.find("</object>")) else {
+            break;
+        };
+        let class = &object[..class_end];
+        let body = &object[class_end..end];
+        rest = &object[end + 9..];
+
+        let mut properties = HashMap::new();
+        let mut remaining = body;
+        while let Some(property) = remaining.find("<property name=\"") {
+            let property = &remaining[property + 16..];
+            let (Some(name_end), Some(tag_end)) = (property.find('"'), property.find('>')) else {
+                break;
+            };
+            if property[..tag_end].ends_with('/') {
+                remaining = &property[tag_end + 1..];
+                continue;
+            }
+            let value = &property[tag_end + 1..];
+            let Some(close) = value.find("</property>") else {
+                break;
+            };
+            properties.insert(&property[..name_end], &value[..close]);
+            remaining = &value[close + 11..];
+        }
+
+        // The object's own id comes before its properties
+        let id = id_of(body).unwrap_or_default();
+        entities.push(Entity { class, id, properties });
+    }
+    entities
+}
+
+/// Reduce Confluence storage-format XHTML to markdown
+fn storage_to_markdown(xhtml: &str) -> String {
+    let mut text = String::with_capacity(xhtml.len());
+    let mut rest = xhtml;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let tag = &rest[start + 1..];
+        // Code macro bodies
+        if let Some(cdata) = tag.strip_prefix("![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            text.push_str(&cdata[..end]);
+            rest = &cdata[(end + 3).min(cdata.len())..];
+            continue;
+        }
+        let end = tag.find('>').map(|i| i + 1).unwrap_or(tag.len());
+        let closing = tag.starts_with('/');
+        let name = tag[..end]
+            .trim_start_matches('/')
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == ':' || c == '-'))
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let self_closing = tag[..end].ends_with("/>");
+        rest = &tag[end..];
+
+        let heading = name
+            .strip_prefix('h')
+            .and_then(|level| level.parse::<usize>().ok())
+            .filter(|level| (1..=6).contains(level));
+        match (name.as_str(), closing) {
+            // Macro parameters (languages, colours, ...) are not content
+            ("ac:parameter", false) if !self_closing => {
+                let skipped = rest.find("</ac:parameter>").map(|i| i + 15).unwrap_or(rest.len());
+                rest = &rest[skipped..];
+            }
+            (_, false) if heading.is_some() => {
+                text.push_str("\n\n");
+                text.push_str(&"#".repeat(heading.unwrap_or(1)));
+                text.push(' ');
+            }
+            ("li", false) => text.push_str("\n- "),
+            ("br", _) | ("tr", true) => text.push('\n'),
+            ("td" | "th", true) => text.push_str(" | "),
+            ("p" | "div" | "pre" | "ul" | "ol" | "table" | "blockquote", true) => text.push_str("\n\n"),
+            (_, true) if heading.is_some() => text.push_str("\n\n"),
+            _ => {}
+        }
+    }
+    text.push_str(rest);
+
+    let text = decode_entities(&text);
+    let mut markdown = String::with_capacity(text.len());
+    let mut blank = false;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            blank = true;
+            continue;
+        }
+        if !markdown.is_empty() {
+            markdown.push_str(if blank { "\n\n" } else { "\n" });
+        }
+        markdown.push_str(line);
+        blank = false;
+    }
+    markdown
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        let entity = &rest[start + 1..];
+        let end = entity.find(';').filter(|&end| end <= 8);
+        let character = end.and_then(|end| match &entity[..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            code => {
+                let code = code.strip_prefix('#')?;
+                let value = match code.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => code.parse().ok()?,
+                };
+                char::from_u32(value)
+            }
+        });
+        match (character, end) {
+            (Some(character), Some(end)) => {
+                decoded.push(character);
+                rest = &entity[end + 1..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = entity;
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const ENTITIES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<hibernate-generic datetime="2026-09-30 10:00:00">
+<object class="Space" package="com.atlassian.confluence.spaces">
+<id name="id">1</id>
+<property name="name"><![CDATA[Engineering]]></property>
+<property name="key"><![CDATA[ENG]]></property>
+</object>
+<object class="Page" package="com.atlassian.confluence.pages">
+<id name="id">10</id>
+<property name="title"><![CDATA[Runbooks]]></property>
+<property name="space" class="Space" package="com.atlassian.confluence.spaces"><id name="id">1</id></property>
+<property name="contentStatus"><![CDATA[current]]></property>
+</object>
+<object class="Page" package="com.atlassian.confluence.pages">
+<id name="id">11</id>
+<property name="title"><![CDATA[Deploys]]></property>
+<property name="space" class="Space" package="com.atlassian.confluence.spaces"><id name="id">1</id></property>
+<property name="parent" class="Page" package="com.atlassian.confluence.pages"><id name="id">10</id></property>
+<property name="lastModificationDate">2026-09-01 08:30:00.000</property>
+<property name="contentStatus"><![CDATA[current]]></property>
+</object>
+<object class="Page" package="com.atlassian.confluence.pages">
+<id name="id">12</id>
+<property name="title"><![CDATA[Deploys]]></property>
+<property name="originalVersion" class="Page" package="com.atlassian.confluence.pages"><id name="id">11</id></property>
+<property name="contentStatus"><![CDATA[current]]></property>
+</object>
+<object class="BodyContent" package="com.atlassian.confluence.core">
+<id name="id">20</id>
+<property name="body"><![CDATA[<h2>Rollout</h2><p>Deploy with <code>make release</code> &amp; watch the canary.</p><ul><li>Staging first</li><li>Then production</li></ul><ac:structured-macro ac:name="code"><ac:parameter ac:name="language">bash</ac:parameter><ac:plain-text-body><![CDATA[make release]]]]><![CDATA[></ac:plain-text-body></ac:structured-macro>]]></property>
+<property name="content" class="Page" package="com.atlassian.confluence.pages"><id name="id">11</id></property>
+</object>
+<object class="Label" package="com.atlassian.confluence.labels">
+<id name="id">30</id>
+<property name="name"><![CDATA[runbook]]></property>
+</object>
+<object class="Labelling" package="com.atlassian.confluence.labels">
+<id name="id">31</id>
+<property name="label" class="Label" package="com.atlassian.confluence.labels"><id name="id">30</id></property>
+<property name="content" class="Page" package="com.atlassian.confluence.pages"><id name="id">11</id></property>
+</object>
+</hibernate-generic>
+"#;
+
+    fn zip(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_confluence_export() {
+        let export = WikiExport::parse(ENTITIES.as_bytes()).unwrap();
+        assert_eq!(export.format, WikiFormat::Confluence);
+        assert_eq!(export.pages.len(), 2);
+
+        assert_eq!(export.pages[0].document_name(), "Engineering/Runbooks");
+        assert!(export.pages[0].body.is_empty());
+
+        let page = &export.pages[1];
+        assert_eq!(page.document_name(), "Engineering/Runbooks/Deploys");
+        assert_eq!(page.labels, vec!["runbook".to_string()]);
+        assert_eq!(page.updated_at.as_deref(), Some("2026-09-01 08:30:00.000"));
+        assert_eq!(
+            page.body,
+            "## Rollout\n\nDeploy with make release & watch the canary.\n\n- Staging first\n- Then production\n\nmake release"
+        );
+    }
+
+    #[test]
+    fn test_zipped_confluence_export() {
+        let bytes = zip(&[("entities.xml", ENTITIES), ("exportDescriptor.properties", "spaceKey=ENG")]);
+        let export = WikiExport::parse(&bytes).unwrap();
+        assert_eq!(export.format, WikiFormat::Confluence);
+        assert_eq!(export.pages.len(), 2);
+    }
+
+    #[test]
+    fn test_markdown_export() {
+        let bytes = zip(&[
+            ("wiki/ops/On-Call-Rotation.md", "Swap shifts in the rota sheet."),
+            ("wiki/Home.md", "---\ntitle: Welcome\ntags: [start, guide]\n---\n# Welcome\n\nStart here."),
+            ("wiki/.git/HEAD.md", "ignored"),
+            ("__MACOSX/wiki/._Home.md", "ignored"),
+            ("wiki/logo.png", "ignored"),
+        ]);
+        let export = WikiExport::parse(&bytes).unwrap();
+        assert_eq!(export.format, WikiFormat::Markdown);
+        assert_eq!(export.pages.len(), 2);
+
+        let home = &export.pages[0];
+        assert_eq!(home.id, "wiki/Home");
+        assert_eq!(home.title, "Welcome");
+        assert_eq!(home.labels, vec!["start".to_string(), "guide".to_string()]);
+        assert_eq!(home.body, "# Welcome\n\nStart here.");
+
+        let on_call = &export.pages[1];
+        assert_eq!(on_call.title, "On Call Rotation");
+        assert_eq!(on_call.document_name(), "wiki/ops/On Call Rotation");
+    }
+
+    #[test]
+    fn test_page_document() {
+        let page = WikiPage {
+            id: "11".to_string(),
+            title: "Deploys".to_string(),
+            space: Some("Engineering".to_string()),
+            path: vec!["Runbooks".to_string()],
+            body: "Deploy with make release.".to_string(),
+            labels: vec!["runbook".to_string()],
+            updated_at: None,
+        };
+        let document = page.to_document("confluence");
+
+        assert_eq!(document.id, "wiki:11");
+        assert_eq!(document.content, b"# Deploys\n\nDeploy with make release.");
+        let custom = &document.metadata.custom;
+        assert_eq!(custom[TAGS_KEY], serde_json::json!(["wiki", "runbook"]));
+        assert_eq!(custom[DOCUMENT_KEY], "Engineering/Runbooks/Deploys");
+        assert_eq!(custom["wiki_path"], "Runbooks");
+    }
+
+    #[test]
+    fn test_rejects_other_content() {
+        assert!(WikiExport::parse(b"just some text").is_err());
+        assert!(WikiExport::parse(&zip(&[("notes.txt", "no markdown here")])).is_err());
+    }
+}
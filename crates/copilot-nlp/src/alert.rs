@@ -0,0 +1,566 @@
+//! Alert rule authoring.
+//!
+//! This module turns descriptions like "page me when checkout latency is
+//! above 500ms for 10 minutes" into Prometheus alerting rules, or Loki
+//! alerting rules when the description is about logs. Rule expressions are
+//! built from the queries the [`QueryTranslator`] produces plus the
+//! described threshold, and [`AlertRule::validate`] checks them before they
+//! are handed out.
+//!
+//! [`AlertBacktest`] replays a rule's `for` clause over the points at which
+//! its expression held in recent data, so users can see how often it would
+//! have fired before they apply it.
+
+use crate::dashboard::{brackets_balanced, metric_title, subject_before};
+use crate::entity::{Entity, EntityExtractor, EntityType};
+use crate::error::{NlpError, Result};
+use crate::intent::{Intent, IntentType};
+use crate::query::{QueryLanguage, QueryTranslator};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// `for` clause of rules whose description names none.
+const DEFAULT_FOR: &str = "5m";
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+lazy_static! {
+    /// "for 10 minutes", "for more than 2h"
+    static ref FOR_PATTERN: Regex = Regex::new(
+        r"(?i)\bfor\s+(?:more\s+than\s+|at\s+least\s+|over\s+|longer\s+than\s+)?(\d+)\s*(seconds?|secs?|s|minutes?|mins?|m|hours?|hrs?|h)\b"
+    ).unwrap();
+
+    /// "above 500ms", "> 5%", "below 10"
+    static ref THRESHOLD_PATTERN: Regex = Regex::new(
+        r"(?i)(>=|<=|>|<|\babove\b|\bover\b|\bexceeds?\b|\b(?:greater|more|higher)\s+than\b|\bbelow\b|\bunder\b|\b(?:less|lower|fewer)\s+than\b)\s*(\d+(?:\.\d+)?)\s*(%|percent\b|ms\b|seconds?\b|secs?\b|s\b|gb\b|mb\b|kb\b)?"
+    ).unwrap();
+
+    /// "logs", "error logs"; a level is part of the phrase, not its subject
+    static ref LOG_PATTERN: Regex =
+        Regex::new(r"(?i)\b(?:(?:critical|error|warning|info|debug)\s+)?logs?\b").unwrap();
+
+    static ref NAME_PATTERN: Regex = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap();
+
+    /// A comparison against a number, which makes an expression a condition
+    static ref COMPARISON_PATTERN: Regex = Regex::new(r"(?:[<>]=?|[=!]=)\s*-?\d").unwrap();
+
+    static ref DURATION_PATTERN: Regex = Regex::new(r"^(?:\d+[smhdwy])+$").unwrap();
+    static ref DURATION_PART: Regex = Regex::new(r"(\d+)([smhdwy])").unwrap();
+}
+
+/// A Prometheus-format alerting rule, as used by Prometheus and the Loki ruler.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    /// Alert name
+    pub alert: String,
+    /// Condition; the alert is pending while it returns series
+    pub expr: String,
+    /// How long the condition must hold before the alert fires
+    #[serde(rename = "for")]
+    pub for_duration: String,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+}
+
+impl AlertRule {
+    /// Returns the `for` clause in seconds, if it is a valid duration.
+    pub fn for_secs(&self) -> Option<i64> {
+        parse_duration(&self.for_duration)
+    }
+
+    /// Returns every problem that would make the ruler reject the rule.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if !NAME_PATTERN.is_match(&self.alert) {
+            problems.push(format!("alert name {:?} is not a valid identifier", self.alert));
+        }
+        if self.expr.trim().is_empty() {
+            problems.push("expression is empty".to_string());
+        } else {
+            if !brackets_balanced(&self.expr) {
+                problems.push("expression has unbalanced brackets".to_string());
+            }
+            if !COMPARISON_PATTERN.is_match(&self.expr) {
+                problems.push("expression has no threshold comparison".to_string());
+            }
+        }
+        if self.for_secs().is_none() {
+            problems.push(format!("for {:?} is not a duration", self.for_duration));
+        }
+        match self.labels.get("severity").map(String::as_str) {
+            Some("critical" | "warning" | "info") => {}
+            Some(other) => problems.push(format!("severity {:?} is not critical, warning or info", other)),
+            None => problems.push("severity label is missing".to_string()),
+        }
+
+        problems
+    }
+
+    /// Checks the rule, failing with every problem found.
+    pub fn validate(&self) -> Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(NlpError::validation(format!(
+                "Invalid alert rule: {}",
+                problems.join("; ")
+            )))
+        }
+    }
+}
+
+/// A generated alert rule and the language of its expression.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertDraft {
+    /// PromQL for Prometheus rules, LogQL for Loki rules
+    pub language: QueryLanguage,
+    pub rule: AlertRule,
+}
+
+/// Generates alert rules from natural language descriptions.
+pub struct AlertRuleGenerator {
+    extractor: EntityExtractor,
+    translator: QueryTranslator,
+}
+
+impl AlertRuleGenerator {
+    /// Creates a generator with the default translator.
+    pub fn new() -> Self {
+        Self {
+            extractor: EntityExtractor::new(),
+            translator: QueryTranslator::new(),
+        }
+    }
+
+    /// Recognizes the given service and metric names in descriptions.
+    pub fn with_context(mut self, known_services: Vec<String>, known_metrics: Vec<String>) -> Self {
+        self.extractor = EntityExtractor::with_context(known_services, known_metrics);
+        self
+    }
+
+    /// Translates rule queries with a custom translator.
+    pub fn with_translator(mut self, translator: QueryTranslator) -> Self {
+        self.translator = translator;
+        self
+    }
+
+    /// Generates an alert rule for a description.
+    ///
+    /// # Arguments
+    ///
+    /// * `description` - When the alert should fire, e.g.
+    ///   "checkout latency above 500ms for 10 minutes"
+    ///
+    /// # Returns
+    ///
+    /// A validated rule, or an error if the description names no threshold
+    /// or nothing to compare it with
+    pub fn generate(&self, description: &str) -> Result<AlertDraft> {
+        let lower = description.to_lowercase();
+
+        // Take the `for` clause out first, so "for over 10m" is no threshold
+        let (for_duration, rest) = match FOR_PATTERN.captures(description) {
+            Some(caps) => {
+                let unit = caps[2].to_lowercase();
+                let duration = format!("{}{}", &caps[1], &unit[..1]);
+                (duration, FOR_PATTERN.replace(description, "").into_owned())
+            }
+            None => (DEFAULT_FOR.to_string(), description.to_string()),
+        };
+        let threshold = Threshold::parse(&rest).ok_or_else(|| {
+            NlpError::query_translation("No threshold found; describe one like \"above 500ms\" or \"> 5%\"")
+        })?;
+
+        let entities = self.extractor.extract(&rest);
+        let find = |entity_type: EntityType| entities.iter().find(|e| e.entity_type == entity_type);
+        let metric = find(EntityType::Metric);
+        let service = find(EntityType::Service)
+            .map(|e| e.normalized_value.clone())
+            .or_else(|| {
+                let anchor = match metric {
+                    Some(metric) => metric.original_text.to_lowercase(),
+                    None => LOG_PATTERN.find(&lower)?.as_str().to_string(),
+                };
+                subject_before(&lower, &anchor)
+            });
+        let severity = match find(EntityType::Severity).map(|e| e.normalized_value.as_str()) {
+            _ if lower.contains("page") => "critical",
+            Some("critical" | "high") => "critical",
+            Some("info" | "debug") => "info",
+            _ => "warning",
+        };
+
+        let service_entity = service.as_ref().map(|service| {
+            Entity::new(EntityType::Service, service.clone(), service.clone(), service.clone(), 1.0)
+        });
+        let (language, subject, expr) = if LOG_PATTERN.is_match(&lower) {
+            let level = find(EntityType::Severity)
+                .map(|e| e.normalized_value.as_str())
+                .filter(|level| matches!(*level, "critical" | "error" | "warning" | "info" | "debug"))
+                .unwrap_or("error");
+            let mut query_entities = vec![Entity::new(
+                EntityType::Severity,
+                level.to_string(),
+                level.to_string(),
+                level.to_string(),
+                1.0,
+            )];
+            query_entities.extend(service_entity);
+            let query = self
+                .translator
+                .to_logql(&Intent::new(IntentType::AlertInvestigation, 1.0), &query_entities);
+            let expr = format!("{} {} {}", query, threshold.operator, format_number(threshold.value));
+            (QueryLanguage::LogQL, format!("{} logs", level), expr)
+        } else {
+            let metric = metric.ok_or_else(|| {
+                NlpError::query_translation(format!("No metric recognized in: {}", description))
+            })?;
+            if self.translator.metric_name(&metric.normalized_value).is_none() {
+                return Err(NlpError::query_translation(format!(
+                    "No PromQL mapping for metric: {}",
+                    metric.normalized_value
+                )));
+            }
+            let query = self.promql(metric, service_entity, &threshold);
+            let expr = format!(
+                "{} {} {}",
+                query,
+                threshold.operator,
+                format_number(threshold.scaled())
+            );
+            (
+                QueryLanguage::PromQL,
+                metric_title(&metric.normalized_value).to_lowercase(),
+                expr,
+            )
+        };
+
+        let direction = if threshold.operator.starts_with('>') { "High" } else { "Low" };
+        let alert = [service.as_deref().unwrap_or(""), subject.as_str(), direction]
+            .iter()
+            .flat_map(|part| part.split(|c: char| !c.is_ascii_alphanumeric()))
+            .map(capitalize)
+            .collect::<String>();
+        let summary = format!(
+            "{}{} {} for {}",
+            service.as_ref().map(|s| format!("{} ", s)).unwrap_or_default(),
+            subject,
+            threshold.text,
+            for_duration
+        );
+
+        let rule = AlertRule {
+            alert,
+            expr,
+            for_duration,
+            labels: BTreeMap::from([("severity".to_string(), severity.to_string())]),
+            annotations: BTreeMap::from([
+                ("summary".to_string(), summary),
+                ("description".to_string(), description.trim().to_string()),
+            ]),
+        };
+        rule.validate()?;
+        Ok(AlertDraft { language, rule })
+    }
+
+    /// PromQL the threshold is compared with.
+    fn promql(&self, metric: &Entity, service: Option<Entity>, threshold: &Threshold) -> String {
+        let mut entities = vec![metric.clone()];
+        entities.extend(service.clone());
+
+        if metric.normalized_value != "error_rate" {
+            return self
+                .translator
+                .to_promql(&Intent::new(IntentType::QueryMetrics, 1.0), &entities);
+        }
+
+        let errors = self
+            .translator
+            .to_promql(&Intent::new(IntentType::ErrorAnalysis, 1.0), &entities);
+        if !threshold.is_percent() {
+            return errors;
+        }
+        // A percentage is of all requests
+        let mut requests = vec![Entity::new(
+            EntityType::Metric,
+            "request_rate".to_string(),
+            "request_rate".to_string(),
+            "request_rate".to_string(),
+            1.0,
+        )];
+        requests.extend(service);
+        requests.push(Entity::new(
+            EntityType::Aggregation,
+            "sum".to_string(),
+            "sum".to_string(),
+            "sum".to_string(),
+            1.0,
+        ));
+        let total = self
+            .translator
+            .to_promql(&Intent::new(IntentType::QueryMetrics, 1.0), &requests);
+        format!("{} / {}", errors, total)
+    }
+}
+
+impl Default for AlertRuleGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A described threshold.
+struct Threshold {
+    operator: &'static str,
+    value: f64,
+    unit: Option<String>,
+    /// The words the threshold was described with
+    text: String,
+}
+
+impl Threshold {
+    fn parse(description: &str) -> Option<Self> {
+        let caps = THRESHOLD_PATTERN.captures(description)?;
+        let word = caps[1].to_lowercase();
+        let operator = match word.as_str() {
+            ">=" => ">=",
+            "<=" => "<=",
+            "<" | "below" | "under" => "<",
+            w if w.starts_with("less") || w.starts_with("lower") || w.starts_with("fewer") => "<",
+            _ => ">",
+        };
+        Some(Self {
+            operator,
+            value: caps[2].parse().ok()?,
+            unit: caps.get(3).map(|m| m.as_str().to_lowercase()),
+            text: caps[0].trim().to_string(),
+        })
+    }
+
+    fn is_percent(&self) -> bool {
+        matches!(self.unit.as_deref(), Some("%" | "percent"))
+    }
+
+    /// The value in the base unit of the metric: seconds, bytes or a ratio.
+    fn scaled(&self) -> f64 {
+        match self.unit.as_deref() {
+            Some("%" | "percent") => self.value / 100.0,
+            Some("ms") => self.value / 1000.0,
+            Some("kb") => self.value * 1024.0,
+            Some("mb") => self.value * 1024.0 * 1024.0,
+            Some("gb") => self.value * 1024.0 * 1024.0 * 1024.0,
+            _ => self.value,
+        }
+    }
+}
+
+fn format_number(value: f64) -> String {
+    // Round away binary noise such as 0.30000000000000004
+    let rounded = (value * 1e9).round() / 1e9;
+    format!("{}", rounded)
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Parses a Prometheus duration such as `5m` or `1h30m` into seconds.
+pub fn parse_duration(duration: &str) -> Option<i64> {
+    if !DURATION_PATTERN.is_match(duration) {
+        return None;
+    }
+    DURATION_PART.captures_iter(duration).try_fold(0i64, |total, caps| {
+        let value: i64 = caps[1].parse().ok()?;
+        let unit = match &caps[2] {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3_600,
+            "d" => 86_400,
+            "w" => 604_800,
+            _ => 31_536_000,
+        };
+        total.checked_add(value.checked_mul(unit)?)
+    })
+}
+
+/// How often a rule would have fired over a past window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertBacktest {
+    /// Length of the replayed window, in seconds
+    pub window_secs: i64,
+    /// Seconds between evaluations
+    pub step_secs: i64,
+    /// Label sets the rule would have fired for
+    pub alerting_series: usize,
+    /// Times the rule would have started firing, across label sets
+    pub firings: usize,
+    /// Expected firings per day
+    pub firings_per_day: f64,
+    /// Fraction of the window some label set would have been firing
+    pub firing_fraction: f64,
+    /// Unix time the rule last started firing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_fired_at: Option<i64>,
+}
+
+impl AlertBacktest {
+    /// Replays a rule's `for` clause over past evaluations.
+    ///
+    /// # Arguments
+    ///
+    /// * `series` - For each label set, the Unix times at which the rule's
+    ///   expression returned it
+    /// * `for_secs` - How long the expression must hold before the rule fires
+    /// * `step_secs` - Seconds between evaluations
+    /// * `window_secs` - Length of the replayed window
+    pub fn evaluate(series: &[Vec<i64>], for_secs: i64, step_secs: i64, window_secs: i64) -> Self {
+        let step_secs = step_secs.max(1);
+        let mut firings = 0;
+        let mut alerting_series = 0;
+        let mut last_fired_at: Option<i64> = None;
+        let mut firing_at = std::collections::BTreeSet::new();
+
+        for timestamps in series {
+            let mut timestamps = timestamps.clone();
+            timestamps.sort_unstable();
+            timestamps.dedup();
+
+            let mut fired = false;
+            // Consecutive evaluations form a pending run; a run fires once it
+            // has lasted `for_secs`
+            for run in timestamps.chunk_by(|a, b| b - a <= step_secs) {
+                let start = run[0];
+                let fires_at = start + for_secs;
+                if run[run.len() - 1] < fires_at {
+                    continue;
+                }
+                fired = true;
+                firings += 1;
+                last_fired_at = last_fired_at.max(Some(fires_at));
+                firing_at.extend(run.iter().copied().filter(|t| *t >= fires_at));
+            }
+            if fired {
+                alerting_series += 1;
+            }
+        }
+
+        let window_secs = window_secs.max(1);
+        Self {
+            window_secs,
+            step_secs,
+            alerting_series,
+            firings,
+            firings_per_day: firings as f64 * SECONDS_PER_DAY / window_secs as f64,
+            firing_fraction: (firing_at.len() as f64 * step_secs as f64 / window_secs as f64).min(1.0),
+            last_fired_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_rule() {
+        let draft = AlertRuleGenerator::new()
+            .generate("Page me when checkout latency is above 500ms for 10 minutes")
+            .unwrap();
+
+        assert_eq!(draft.language, QueryLanguage::PromQL);
+        let rule = draft.rule;
+        assert_eq!(rule.alert, "CheckoutLatencyHigh");
+        assert_eq!(rule.expr, "rate(http_request_duration_seconds{service=\"checkout\"}[5m]) > 0.5");
+        assert_eq!(rule.for_duration, "10m");
+        assert_eq!(rule.labels["severity"], "critical");
+        assert_eq!(rule.annotations["summary"], "checkout latency above 500ms for 10m");
+    }
+
+    #[test]
+    fn test_error_rate_percentage_is_a_ratio() {
+        let rule = AlertRuleGenerator::new()
+            .generate("alert if payment-service error rate > 5%")
+            .unwrap()
+            .rule;
+
+        assert_eq!(
+            rule.expr,
+            "sum(rate(http_requests_total{code=~\"5..\", service=\"payment-service\"}[5m])) \
+             / sum(rate(http_requests_total{service=\"payment-service\"}[5m])) > 0.05"
+        );
+        assert_eq!(rule.for_duration, DEFAULT_FOR);
+        assert_eq!(rule.labels["severity"], "warning");
+    }
+
+    #[test]
+    fn test_log_rule() {
+        let draft = AlertRuleGenerator::new()
+            .generate("warn when auth error logs exceed 100 for 15m")
+            .unwrap();
+
+        assert_eq!(draft.language, QueryLanguage::LogQL);
+        assert_eq!(draft.rule.alert, "AuthErrorLogsHigh");
+        assert_eq!(
+            draft.rule.expr,
+            "sum(count_over_time({service=\"auth\", level=\"error\"}[5m])) by (service) > 100"
+        );
+        assert_eq!(draft.rule.for_duration, "15m");
+    }
+
+    #[test]
+    fn test_rejects_descriptions_without_threshold() {
+        let generator = AlertRuleGenerator::new();
+        assert!(generator.generate("alert on checkout latency").is_err());
+        assert!(generator.generate("alert when happiness is below 3").is_err());
+    }
+
+    #[test]
+    fn test_validation() {
+        let mut rule = AlertRuleGenerator::new()
+            .generate("cpu above 90% for 5m")
+            .unwrap()
+            .rule;
+        assert_eq!(rule.expr, "rate(node_cpu_seconds_total[5m]) > 0.9");
+
+        rule.alert = "cpu high".to_string();
+        rule.expr = "rate(node_cpu_seconds_total[5m]".to_string();
+        rule.for_duration = "5 minutes".to_string();
+        rule.labels.clear();
+        assert_eq!(rule.problems().len(), 5, "{:?}", rule.problems());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("5m"), Some(300));
+        assert_eq!(parse_duration("1h30m"), Some(5_400));
+        assert_eq!(parse_duration("0s"), Some(0));
+        assert_eq!(parse_duration("5"), None);
+        assert_eq!(parse_duration("5 m"), None);
+    }
+
+    #[test]
+    fn test_backtest_applies_for_clause() {
+        let step = 60;
+        // Held for 3 evaluations (too short), then for 11 (fires at the 6th)
+        let short: Vec<i64> = (0..3).map(|i| i * step).collect();
+        let long: Vec<i64> = (10..21).map(|i| i * step).collect();
+        let series = vec![short.into_iter().chain(long).collect(), vec![]];
+
+        let backtest = AlertBacktest::evaluate(&series, 5 * step, step, 86_400);
+        assert_eq!(backtest.firings, 1);
+        assert_eq!(backtest.alerting_series, 1);
+        assert_eq!(backtest.last_fired_at, Some(15 * step));
+        assert_eq!(backtest.firings_per_day, 1.0);
+        assert!((backtest.firing_fraction - 6.0 * 60.0 / 86_400.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,455 @@
+//! Grafana dashboard generation.
+//!
+//! This module turns asks like "make me a dashboard for checkout latency and
+//! error rate" into a Grafana dashboard model with one time series panel per
+//! metric, each querying the PromQL the [`QueryTranslator`] produces. The
+//! model serializes to Grafana's dashboard JSON, and [`GrafanaDashboard::validate`]
+//! checks it before it is handed out.
+
+use crate::entity::{Entity, EntityExtractor, EntityType};
+use crate::error::{NlpError, Result};
+use crate::intent::{Intent, IntentType};
+use crate::query::QueryTranslator;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tracing::debug;
+
+/// Columns in a Grafana dashboard grid.
+const GRID_COLUMNS: u32 = 24;
+
+/// Panels placed side by side in each row.
+const PANELS_PER_ROW: u32 = 2;
+
+/// Height of generated panels, in grid units.
+const PANEL_HEIGHT: u32 = 8;
+
+/// Grafana dashboard schema version the model follows.
+const SCHEMA_VERSION: u32 = 39;
+
+/// Words that never name the service an ask is about.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "the", "for", "of", "on", "me", "my", "our", "show", "with", "dashboard",
+    "board", "make", "create", "build", "plus", "all", "to", "in", "alert", "when", "if", "page",
+    "notify", "us", "is", "goes", "gets",
+];
+
+/// A Grafana dashboard.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrafanaDashboard {
+    /// Unique identifier; Grafana assigns one when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub time: DashboardTime,
+    pub refresh: String,
+    pub schema_version: u32,
+    pub panels: Vec<Panel>,
+}
+
+/// Default time range of a dashboard, in Grafana's relative syntax.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DashboardTime {
+    pub from: String,
+    pub to: String,
+}
+
+/// A dashboard panel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Panel {
+    pub id: u32,
+    pub title: String,
+    #[serde(rename = "type")]
+    pub panel_type: String,
+    pub datasource: PanelDatasource,
+    pub grid_pos: GridPos,
+    pub targets: Vec<PanelTarget>,
+    pub field_config: FieldConfig,
+}
+
+/// Datasource a panel queries.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PanelDatasource {
+    #[serde(rename = "type")]
+    pub datasource_type: String,
+    pub uid: String,
+}
+
+/// Position and size of a panel in the dashboard grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridPos {
+    pub h: u32,
+    pub w: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl GridPos {
+    fn overlaps(&self, other: &GridPos) -> bool {
+        self.x < other.x + other.w
+            && other.x < self.x + self.w
+            && self.y < other.y + other.h
+            && other.y < self.y + self.h
+    }
+}
+
+/// A query of a panel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PanelTarget {
+    pub ref_id: String,
+    pub expr: String,
+    #[serde(default)]
+    pub legend_format: String,
+}
+
+/// Display settings of a panel's fields.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FieldConfig {
+    pub defaults: FieldDefaults,
+}
+
+/// Display settings applied to every field of a panel.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FieldDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+}
+
+impl GrafanaDashboard {
+    /// Returns every problem that would make Grafana reject or misrender
+    /// the dashboard.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.title.trim().is_empty() {
+            problems.push("dashboard title is empty".to_string());
+        }
+        if self.panels.is_empty() {
+            problems.push("dashboard has no panels".to_string());
+        }
+
+        let mut ids = HashSet::new();
+        for (i, panel) in self.panels.iter().enumerate() {
+            if !ids.insert(panel.id) {
+                problems.push(format!("panel id {} is used more than once", panel.id));
+            }
+            if panel.datasource.uid.trim().is_empty() {
+                problems.push(format!("panel {} has no datasource", panel.id));
+            }
+
+            let pos = panel.grid_pos;
+            if pos.w == 0 || pos.h == 0 || pos.x + pos.w > GRID_COLUMNS {
+                problems.push(format!("panel {} does not fit the {}-column grid", panel.id, GRID_COLUMNS));
+            }
+            if let Some(other) = self.panels[..i].iter().find(|other| other.grid_pos.overlaps(&pos)) {
+                problems.push(format!("panel {} overlaps panel {}", panel.id, other.id));
+            }
+
+            if panel.targets.is_empty() {
+                problems.push(format!("panel {} has no queries", panel.id));
+            }
+            let mut ref_ids = HashSet::new();
+            for target in &panel.targets {
+                if !ref_ids.insert(target.ref_id.as_str()) {
+                    problems.push(format!("panel {} reuses query ref {}", panel.id, target.ref_id));
+                }
+                if target.expr.trim().is_empty() {
+                    problems.push(format!("panel {} query {} is empty", panel.id, target.ref_id));
+                } else if !brackets_balanced(&target.expr) {
+                    problems.push(format!(
+                        "panel {} query {} has unbalanced brackets",
+                        panel.id, target.ref_id
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Checks the dashboard, failing with every problem found.
+    pub fn validate(&self) -> Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(NlpError::validation(format!(
+                "Invalid dashboard: {}",
+                problems.join("; ")
+            )))
+        }
+    }
+}
+
+/// Returns true if every bracket in a query closes in order, ignoring
+/// quoted label values.
+pub(crate) fn brackets_balanced(expr: &str) -> bool {
+    let mut open = Vec::new();
+    let mut quote = None;
+    for c in expr.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'' | '`') => quote = Some(c),
+            (None, '(' | '[' | '{') => open.push(c),
+            (None, ')') if open.pop() != Some('(') => return false,
+            (None, ']') if open.pop() != Some('[') => return false,
+            (None, '}') if open.pop() != Some('{') => return false,
+            _ => {}
+        }
+    }
+    open.is_empty() && quote.is_none()
+}
+
+/// Generates Grafana dashboards from natural language asks.
+pub struct DashboardGenerator {
+    extractor: EntityExtractor,
+    translator: QueryTranslator,
+    datasource_uid: String,
+}
+
+impl DashboardGenerator {
+    /// Creates a generator querying the `prometheus` datasource.
+    pub fn new() -> Self {
+        Self {
+            extractor: EntityExtractor::new(),
+            translator: QueryTranslator::new(),
+            datasource_uid: "prometheus".to_string(),
+        }
+    }
+
+    /// Recognizes the given service and metric names in asks.
+    pub fn with_context(mut self, known_services: Vec<String>, known_metrics: Vec<String>) -> Self {
+        self.extractor = EntityExtractor::with_context(known_services, known_metrics);
+        self
+    }
+
+    /// Translates panel queries with a custom translator.
+    pub fn with_translator(mut self, translator: QueryTranslator) -> Self {
+        self.translator = translator;
+        self
+    }
+
+    /// Sets the uid of the Prometheus datasource panels query.
+    pub fn with_datasource(mut self, uid: impl Into<String>) -> Self {
+        self.datasource_uid = uid.into();
+        self
+    }
+
+    /// Generates a dashboard for an ask.
+    ///
+    /// # Arguments
+    ///
+    /// * `ask` - What the dashboard should show, e.g. "checkout latency and error rate"
+    ///
+    /// # Returns
+    ///
+    /// A validated dashboard with one panel per recognized metric, or an
+    /// error if the ask names no metric the translator knows
+    pub fn generate(&self, ask: &str) -> Result<GrafanaDashboard> {
+        let entities = self.extractor.extract(ask);
+        let lower = ask.to_lowercase();
+
+        // Metrics in the order the ask mentions them, once each
+        let mut metrics: Vec<&Entity> = entities
+            .iter()
+            .filter(|e| e.entity_type == EntityType::Metric)
+            .collect();
+        metrics.sort_by_key(|e| lower.find(&e.original_text.to_lowercase()).unwrap_or(usize::MAX));
+        let mut seen = HashSet::new();
+        metrics.retain(|e| seen.insert(e.normalized_value.clone()));
+
+        let unsupported: Vec<&str> = metrics
+            .iter()
+            .filter(|e| self.translator.metric_name(&e.normalized_value).is_none())
+            .map(|e| e.normalized_value.as_str())
+            .collect();
+        metrics.retain(|e| self.translator.metric_name(&e.normalized_value).is_some());
+        if metrics.is_empty() {
+            return Err(NlpError::query_translation(if unsupported.is_empty() {
+                format!("No metrics recognized in: {}", ask)
+            } else {
+                format!("No PromQL mapping for metrics: {}", unsupported.join(", "))
+            }));
+        }
+        if !unsupported.is_empty() {
+            debug!("Skipping metrics without a PromQL mapping: {:?}", unsupported);
+        }
+
+        let service = entities
+            .iter()
+            .find(|e| e.entity_type == EntityType::Service)
+            .map(|e| e.normalized_value.clone())
+            .or_else(|| subject_before(&lower, &metrics[0].original_text.to_lowercase()));
+        let time_range = entities
+            .iter()
+            .find(|e| e.entity_type == EntityType::TimeRange)
+            .map(|e| e.normalized_value.clone());
+
+        let panels = metrics
+            .iter()
+            .enumerate()
+            .map(|(i, metric)| self.panel(i as u32, metric, service.as_deref()))
+            .collect();
+
+        let titles: Vec<String> = metrics.iter().map(|m| metric_title(&m.normalized_value)).collect();
+        let title = match &service {
+            Some(service) => format!("{}: {}", service, titles.join(", ")),
+            None => titles.join(", "),
+        };
+        let mut tags = vec!["generated".to_string()];
+        tags.extend(service.clone());
+
+        let dashboard = GrafanaDashboard {
+            uid: None,
+            title,
+            tags,
+            time: DashboardTime {
+                from: format!("now-{}", time_range.as_deref().unwrap_or("6h")),
+                to: "now".to_string(),
+            },
+            refresh: "30s".to_string(),
+            schema_version: SCHEMA_VERSION,
+            panels,
+        };
+        dashboard.validate()?;
+        Ok(dashboard)
+    }
+
+    fn panel(&self, index: u32, metric: &Entity, service: Option<&str>) -> Panel {
+        let intent_type = if metric.normalized_value == "error_rate" {
+            IntentType::ErrorAnalysis
+        } else {
+            IntentType::QueryMetrics
+        };
+        let mut entities = vec![metric.clone()];
+        if let Some(service) = service {
+            entities.push(Entity::new(
+                EntityType::Service,
+                service.to_string(),
+                service.to_string(),
+                service.to_string(),
+                1.0,
+            ));
+        }
+        let expr = self.translator.to_promql(&Intent::new(intent_type, 1.0), &entities);
+
+        let width = GRID_COLUMNS / PANELS_PER_ROW;
+        Panel {
+            id: index + 1,
+            title: metric_title(&metric.normalized_value),
+            panel_type: "timeseries".to_string(),
+            datasource: PanelDatasource {
+                datasource_type: "prometheus".to_string(),
+                uid: self.datasource_uid.clone(),
+            },
+            grid_pos: GridPos {
+                h: PANEL_HEIGHT,
+                w: width,
+                x: (index % PANELS_PER_ROW) * width,
+                y: (index / PANELS_PER_ROW) * PANEL_HEIGHT,
+            },
+            targets: vec![PanelTarget {
+                ref_id: "A".to_string(),
+                expr,
+                legend_format: if service.is_some() { String::new() } else { "{{service}}".to_string() },
+            }],
+            field_config: FieldConfig {
+                defaults: FieldDefaults {
+                    unit: metric_unit(&metric.normalized_value).map(str::to_string),
+                },
+            },
+        }
+    }
+}
+
+impl Default for DashboardGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The word just before the first metric, taken as the service an ask is
+/// about when no service was recognized ("checkout latency" -> "checkout").
+pub(crate) fn subject_before(ask: &str, metric: &str) -> Option<String> {
+    let before = &ask[..ask.find(metric)?];
+    let word = before
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .rfind(|w| !w.is_empty())?;
+    (!STOP_WORDS.contains(&word)).then(|| word.to_string())
+}
+
+pub(crate) fn metric_title(metric: &str) -> String {
+    match metric {
+        "cpu" | "qps" | "rps" => metric.to_uppercase(),
+        _ => {
+            let words = metric.replace('_', " ");
+            let mut chars = words.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => words,
+            }
+        }
+    }
+}
+
+/// Grafana unit for a metric's panel.
+fn metric_unit(metric: &str) -> Option<&'static str> {
+    match metric {
+        "latency" | "response_time" | "disk" => Some("s"),
+        "error_rate" | "request_rate" | "qps" | "rps" => Some("reqps"),
+        "cpu" => Some("percentunit"),
+        "memory" => Some("bytes"),
+        "network" => Some("Bps"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generates_panel_per_metric() {
+        let dashboard = DashboardGenerator::new()
+            .generate("Make me a dashboard for checkout latency and error rate over the last 24 hours")
+            .unwrap();
+
+        assert_eq!(dashboard.title, "checkout: Latency, Error rate");
+        assert_eq!(dashboard.time.from, "now-24h");
+        assert_eq!(dashboard.panels.len(), 2);
+
+        let latency = &dashboard.panels[0];
+        assert_eq!(latency.title, "Latency");
+        assert_eq!(latency.field_config.defaults.unit.as_deref(), Some("s"));
+        assert!(latency.targets[0].expr.contains("http_request_duration_seconds{service=\"checkout\"}"));
+
+        let errors = &dashboard.panels[1];
+        assert!(errors.targets[0].expr.contains("code=~\"5..\""));
+        assert_eq!((errors.grid_pos.x, errors.grid_pos.y), (12, 0));
+    }
+
+    #[test]
+    fn test_rejects_asks_without_known_metrics() {
+        let generator = DashboardGenerator::new();
+        assert!(generator.generate("make me a dashboard about happiness").is_err());
+        assert!(generator.generate("dashboard for throughput").is_err());
+    }
+
+    #[test]
+    fn test_validation_finds_problems() {
+        let mut dashboard = DashboardGenerator::new().generate("cpu and memory").unwrap();
+        assert!(dashboard.validate().is_ok());
+
+        dashboard.panels[1].id = 1;
+        dashboard.panels[1].grid_pos.x = 4;
+        dashboard.panels[1].targets[0].expr = "rate(up[5m]".to_string();
+        let problems = dashboard.problems();
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(dashboard.validate().is_err());
+    }
+}
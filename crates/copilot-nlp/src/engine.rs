@@ -55,7 +55,7 @@ impl NlpEngineImpl {
         Self {
             intent_classifier: IntentClassifier::new(),
             entity_extractor,
-            query_translator: QueryTranslator::new(),
+            query_translator: Self::translator_for(&context),
             context: Some(context),
         }
     }
@@ -72,10 +72,20 @@ impl NlpEngineImpl {
             context.available_services.clone(),
             context.available_metrics.clone(),
         );
+        self.query_translator = Self::translator_for(&context);
 
         self.context = Some(context);
     }
 
+    /// Query translator checking SQL against the context's catalog, if any.
+    fn translator_for(context: &NlpContext) -> QueryTranslator {
+        if context.sql_catalog.is_empty() {
+            QueryTranslator::new()
+        } else {
+            QueryTranslator::new().with_catalog(context.sql_catalog.clone())
+        }
+    }
+
     /// Gets the current context.
     pub fn context(&self) -> Option<&NlpContext> {
         self.context.as_ref()
@@ -224,7 +234,8 @@ impl NlpEngine for NlpEngineImpl {
         let translated_query = match target_language {
             QueryLanguage::PromQL => self.query_translator.to_promql(intent, entities),
             QueryLanguage::LogQL => self.query_translator.to_logql(intent, entities),
-            QueryLanguage::SQL => self.query_translator.to_sql(intent, entities),
+            // The trait returns text, so values are inlined as escaped literals
+            QueryLanguage::SQL => self.query_translator.to_sql(intent, entities)?.inline(),
             QueryLanguage::TraceQL => {
                 // TraceQL not yet implemented, return a placeholder
                 debug!("TraceQL translation not yet implemented");
@@ -318,6 +329,7 @@ mod tests {
             available_metrics: vec!["checkout_duration".to_string()],
             query_history: Vec::new(),
             custom_entities: std::collections::HashMap::new(),
+            sql_catalog: crate::SqlCatalog::new(),
         };
 
         let engine = NlpEngineImpl::with_context(context);
@@ -375,6 +387,7 @@ mod tests {
             available_metrics: vec!["test-metric".to_string()],
             query_history: Vec::new(),
             custom_entities: std::collections::HashMap::new(),
+            sql_catalog: crate::SqlCatalog::new(),
         };
 
         engine.update_context(context);
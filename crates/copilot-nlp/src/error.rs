@@ -54,6 +54,7 @@ impl NlpError {
 pub type Result<T> = std::result::Result<T, NlpError>;
 
 // Convert to copilot_core AppError
+#[cfg(feature = "engine")]
 impl From<NlpError> for copilot_core::AppError {
     fn from(err: NlpError) -> Self {
         match err {
@@ -10,6 +10,17 @@
 //! - **Intent Classification**: Identifies user intent from natural language with confidence scoring
 //! - **Entity Extraction**: Extracts entities like time ranges, services, metrics, and severity levels
 //! - **Query Translation**: Converts natural language to PromQL, LogQL, and SQL queries
+//! - **SQL Safety**: Catalog-checked, parameterized, read-only SQL
+//! - **Dashboard Generation**: Builds Grafana dashboards of translated PromQL panels
+//! - **Alert Authoring**: Builds Prometheus and Loki alerting rules and back-tests them
+//! - **Log Clustering**: Groups log lines into templates and flags anomalous patterns
+//! - **Relevance Scoring**: Ranks context snippets against a query
+//!
+//! ## Cargo Features
+//!
+//! - `engine` (default): the async [`NlpEngine`] trait and [`NlpEngineImpl`]
+//! - `wasm`: JavaScript bindings for `wasm32-unknown-unknown`; combine with
+//!   `--no-default-features` to leave out the async engine
 //!
 //! ## Example
 //!
@@ -27,27 +38,43 @@
 //! }
 //! ```
 
+pub mod alert;
+pub mod dashboard;
+#[cfg(feature = "engine")]
 pub mod engine;
 pub mod entity;
 pub mod error;
 pub mod intent;
+pub mod logs;
 pub mod query;
+pub mod scoring;
+pub mod sql;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
+#[cfg(feature = "engine")]
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
 pub use error::{NlpError, Result};
 use std::collections::HashMap;
 
+pub use alert::{AlertBacktest, AlertDraft, AlertRule, AlertRuleGenerator};
+pub use dashboard::{DashboardGenerator, GrafanaDashboard};
+#[cfg(feature = "engine")]
 pub use engine::NlpEngineImpl;
 pub use entity::{Entity, EntityExtractor, EntityType};
 pub use intent::{Intent, IntentClassifier, IntentType};
+pub use logs::{FindingKind, LogAnalysis, LogClusterer, LogFinding, LogPattern};
 pub use query::{QueryLanguage, QueryTranslator};
+pub use scoring::{RelevanceScorer, ScoredText};
+pub use sql::{ensure_read_only, SqlCatalog, SqlQuery};
 
 /// Main NLP engine trait for processing natural language queries.
 ///
 /// This trait defines the core NLP capabilities needed for the CoPilot Agent
 /// to understand and process user queries about observability data.
+#[cfg(feature = "engine")]
 #[async_trait]
 pub trait NlpEngine: Send + Sync {
     /// Classifies the user's intent from natural language input.
@@ -153,6 +180,10 @@ pub struct NlpContext {
     pub query_history: Vec<String>,
     /// Custom entity mappings
     pub custom_entities: HashMap<String, String>,
+    /// Tables and columns SQL translation may reference; the built-in
+    /// observability schema when empty
+    #[serde(default)]
+    pub sql_catalog: SqlCatalog,
 }
 
 impl Default for NlpContext {
@@ -162,11 +193,12 @@ impl Default for NlpContext {
             available_metrics: Vec::new(),
             query_history: Vec::new(),
             custom_entities: HashMap::new(),
+            sql_catalog: SqlCatalog::new(),
         }
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "engine"))]
 mod tests {
     use super::*;
 
@@ -0,0 +1,420 @@
+//! Log pattern clustering.
+//!
+//! This module groups log lines by template, Drain style: tokens holding
+//! digits (ids, durations, addresses, timestamps) are masked, lines are
+//! bucketed by token count and first token, and each line joins the most
+//! similar template in its bucket, turning the positions where they differ
+//! into wildcards. [`LogAnalysis`] ranks the resulting patterns and flags
+//! error patterns, patterns that only appeared recently and rare ones.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Placeholder for the variable parts of a template.
+pub const WILDCARD: &str = "<*>";
+
+/// Instructions for the model that summarizes an analysis.
+pub const LOG_SUMMARY_PROMPT: &str = "You triage application logs. Below are the most frequent \
+log patterns and the anomalies found among them. Summarize what the system was doing, which \
+errors matter and what likely caused them, and what to look at next. Be brief and concrete.";
+
+/// Default fraction of a template's tokens a line must share to join it.
+const DEFAULT_SIMILARITY: f64 = 0.5;
+
+/// Default number of patterns an analysis reports.
+const DEFAULT_TOP_PATTERNS: usize = 10;
+
+/// Streams shorter than this have no rare or new patterns.
+const MIN_LINES_FOR_ANOMALIES: usize = 20;
+
+lazy_static! {
+    static ref LEVEL_PATTERN: Regex =
+        Regex::new(r"(?i)\b(fatal|critical|error|err|warn|warning|info|debug|trace)\b").unwrap();
+}
+
+/// A log template and the lines that matched it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogPattern {
+    /// Template with variable tokens replaced by `<*>`
+    pub template: String,
+    /// Lines that matched the template
+    pub count: usize,
+    /// Share of all lines, from 0.0 to 1.0
+    pub share: f64,
+    /// Most severe level seen on a matching line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    /// 1-based number of the first matching line
+    pub first_line: usize,
+    /// 1-based number of the last matching line
+    pub last_line: usize,
+    /// The first matching line, unmasked
+    pub example: String,
+}
+
+impl LogPattern {
+    /// Returns true if the pattern logs errors.
+    pub fn is_error(&self) -> bool {
+        matches!(self.level.as_deref(), Some("error" | "fatal"))
+    }
+}
+
+/// Why a pattern is worth a look.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    /// Lines logged at error level or above
+    ErrorPattern,
+    /// First seen in the last tenth of the stream
+    NewPattern,
+    /// Seen only once in a long stream
+    RarePattern,
+}
+
+/// A pattern flagged as an anomaly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogFinding {
+    pub kind: FindingKind,
+    pub template: String,
+    pub count: usize,
+    /// What makes the pattern stand out
+    pub detail: String,
+}
+
+/// Patterns and anomalies of a log stream.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogAnalysis {
+    /// Non-empty lines analyzed
+    pub total_lines: usize,
+    /// Distinct templates found
+    pub pattern_count: usize,
+    /// Most frequent patterns, most frequent first
+    pub patterns: Vec<LogPattern>,
+    pub findings: Vec<LogFinding>,
+}
+
+impl LogAnalysis {
+    /// Clusters the lines of `text` and reports the default number of patterns.
+    pub fn from_text(text: &str) -> Self {
+        let mut clusterer = LogClusterer::new();
+        clusterer.add_lines(text.lines());
+        clusterer.analysis(DEFAULT_TOP_PATTERNS)
+    }
+
+    /// Plain description of the analysis, for when no summary is available.
+    pub fn describe(&self) -> String {
+        let mut text = format!(
+            "{} lines in {} patterns.",
+            self.total_lines, self.pattern_count
+        );
+        if let Some(top) = self.patterns.first() {
+            text.push_str(&format!(
+                " Most frequent ({:.0}%): {}",
+                top.share * 100.0,
+                top.template
+            ));
+        }
+        for finding in &self.findings {
+            text.push_str(&format!("\n{}: {}", finding.detail, finding.template));
+        }
+        text
+    }
+
+    /// Task handed to the summarizing model.
+    pub fn summary_task(&self) -> String {
+        let mut task = format!(
+            "{} log lines in {} patterns.\n\nTop patterns:",
+            self.total_lines, self.pattern_count
+        );
+        for pattern in &self.patterns {
+            task.push_str(&format!(
+                "\n- {}x [{}] {}",
+                pattern.count,
+                pattern.level.as_deref().unwrap_or("-"),
+                pattern.template
+            ));
+        }
+        if !self.findings.is_empty() {
+            task.push_str("\n\nAnomalies:");
+            for finding in &self.findings {
+                task.push_str(&format!("\n- {}: {}", finding.detail, finding.template));
+            }
+        }
+        task
+    }
+}
+
+/// A template being built up from lines.
+#[derive(Debug, Clone)]
+struct Cluster {
+    tokens: Vec<String>,
+    count: usize,
+    level: Option<&'static str>,
+    first_line: usize,
+    last_line: usize,
+    example: String,
+}
+
+/// Incrementally clusters log lines by template.
+#[derive(Debug, Clone)]
+pub struct LogClusterer {
+    similarity: f64,
+    clusters: Vec<Cluster>,
+    /// Clusters by token count and first token
+    buckets: HashMap<(usize, String), Vec<usize>>,
+    lines: usize,
+}
+
+impl LogClusterer {
+    /// Creates a clusterer with the default similarity threshold.
+    pub fn new() -> Self {
+        Self {
+            similarity: DEFAULT_SIMILARITY,
+            clusters: Vec::new(),
+            buckets: HashMap::new(),
+            lines: 0,
+        }
+    }
+
+    /// Sets the fraction of a template's tokens a line must share to join
+    /// it; higher values give more, narrower patterns.
+    pub fn with_similarity(mut self, similarity: f64) -> Self {
+        self.similarity = similarity.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Adds a line; blank lines are skipped.
+    pub fn add(&mut self, line: &str) {
+        let line = line.trim_end();
+        let tokens: Vec<String> = line.split_whitespace().map(mask).collect();
+        if tokens.is_empty() {
+            return;
+        }
+        self.lines += 1;
+        let level = level_of(line);
+
+        let key = (tokens.len(), tokens[0].clone());
+        let bucket = self.buckets.entry(key).or_default();
+        let best = bucket
+            .iter()
+            .map(|&i| (i, similarity(&self.clusters[i].tokens, &tokens)))
+            .filter(|(_, score)| *score >= self.similarity)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        match best {
+            Some((i, _)) => {
+                let cluster = &mut self.clusters[i];
+                for (template, token) in cluster.tokens.iter_mut().zip(&tokens) {
+                    if template != token {
+                        *template = WILDCARD.to_string();
+                    }
+                }
+                cluster.count += 1;
+                cluster.last_line = self.lines;
+                if severity(level) > severity(cluster.level) {
+                    cluster.level = level;
+                }
+            }
+            None => {
+                bucket.push(self.clusters.len());
+                self.clusters.push(Cluster {
+                    tokens,
+                    count: 1,
+                    level,
+                    first_line: self.lines,
+                    last_line: self.lines,
+                    example: line.to_string(),
+                });
+            }
+        }
+    }
+
+    /// Adds every line.
+    pub fn add_lines<'a>(&mut self, lines: impl IntoIterator<Item = &'a str>) {
+        for line in lines {
+            self.add(line);
+        }
+    }
+
+    /// Returns the number of non-empty lines added so far.
+    pub fn line_count(&self) -> usize {
+        self.lines
+    }
+
+    /// Ranks the patterns found so far and flags anomalies.
+    ///
+    /// # Arguments
+    ///
+    /// * `top` - How many of the most frequent patterns to report
+    ///
+    /// # Returns
+    ///
+    /// The analysis; findings cover every pattern, not only the top ones
+    pub fn analysis(&self, top: usize) -> LogAnalysis {
+        let total = self.lines;
+        let mut patterns: Vec<LogPattern> = self
+            .clusters
+            .iter()
+            .map(|cluster| LogPattern {
+                template: cluster.tokens.join(" "),
+                count: cluster.count,
+                share: cluster.count as f64 / total.max(1) as f64,
+                level: cluster.level.map(str::to_string),
+                first_line: cluster.first_line,
+                last_line: cluster.last_line,
+                example: cluster.example.clone(),
+            })
+            .collect();
+        patterns.sort_by(|a, b| b.count.cmp(&a.count).then(a.first_line.cmp(&b.first_line)));
+
+        let mut findings = Vec::new();
+        for pattern in patterns.iter().filter(|p| p.is_error()) {
+            findings.push(LogFinding {
+                kind: FindingKind::ErrorPattern,
+                template: pattern.template.clone(),
+                count: pattern.count,
+                detail: format!("{} error lines", pattern.count),
+            });
+        }
+        if total >= MIN_LINES_FOR_ANOMALIES {
+            // Patterns that only started at the end of the stream
+            let recent = total - total / 10;
+            for pattern in patterns.iter().filter(|p| p.first_line > recent) {
+                findings.push(LogFinding {
+                    kind: FindingKind::NewPattern,
+                    template: pattern.template.clone(),
+                    count: pattern.count,
+                    detail: format!("New in the last {} lines", total - recent),
+                });
+            }
+            for pattern in patterns.iter().filter(|p| p.count == 1 && p.first_line <= recent) {
+                findings.push(LogFinding {
+                    kind: FindingKind::RarePattern,
+                    template: pattern.template.clone(),
+                    count: pattern.count,
+                    detail: format!("Seen once at line {}", pattern.first_line),
+                });
+            }
+        }
+
+        let pattern_count = patterns.len();
+        patterns.truncate(top);
+        LogAnalysis {
+            total_lines: total,
+            pattern_count,
+            patterns,
+            findings,
+        }
+    }
+}
+
+impl Default for LogClusterer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Masks tokens holding digits, which are almost always variables.
+fn mask(token: &str) -> String {
+    if token.chars().any(|c| c.is_ascii_digit()) {
+        WILDCARD.to_string()
+    } else {
+        token.to_string()
+    }
+}
+
+/// Fraction of positions where a template and a line agree.
+fn similarity(template: &[String], tokens: &[String]) -> f64 {
+    let same = template.iter().zip(tokens).filter(|(a, b)| a == b).count();
+    same as f64 / tokens.len() as f64
+}
+
+/// The first level name on a line, normalized.
+fn level_of(line: &str) -> Option<&'static str> {
+    let level = LEVEL_PATTERN.captures(line)?[1].to_lowercase();
+    Some(match level.as_str() {
+        "fatal" | "critical" => "fatal",
+        "error" | "err" => "error",
+        "warn" | "warning" => "warn",
+        "info" => "info",
+        "debug" => "debug",
+        _ => "trace",
+    })
+}
+
+fn severity(level: Option<&str>) -> u8 {
+    match level {
+        Some("fatal") => 5,
+        Some("error") => 4,
+        Some("warn") => 3,
+        Some("info") => 2,
+        Some("debug") => 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream() -> String {
+        let mut lines = Vec::new();
+        for i in 0..30 {
+            lines.push(format!("2024-05-01T10:00:{:02}Z INFO request GET /orders/{} took {}ms", i, i * 7, 20 + i));
+            if i % 10 == 0 {
+                lines.push(format!("2024-05-01T10:00:{:02}Z WARN cache miss for key user-{}", i, i));
+            }
+        }
+        lines.push("2024-05-01T10:00:12Z DEBUG feature flags reloaded".to_string());
+        for i in 0..3 {
+            lines.push(format!("2024-05-01T10:01:0{}Z ERROR payment gateway timeout after 30s", i));
+        }
+        lines.join("\n")
+    }
+
+    #[test]
+    fn test_clusters_lines_by_template() {
+        let analysis = LogAnalysis::from_text(&stream());
+
+        assert_eq!(analysis.total_lines, 37);
+        assert_eq!(analysis.pattern_count, 4);
+        let top = &analysis.patterns[0];
+        assert_eq!(top.template, "<*> INFO request GET <*> took <*>");
+        assert_eq!(top.count, 30);
+        assert_eq!(top.level.as_deref(), Some("info"));
+        assert_eq!(analysis.patterns[1].template, "<*> WARN cache miss for key <*>");
+    }
+
+    #[test]
+    fn test_flags_anomalies() {
+        let analysis = LogAnalysis::from_text(&stream());
+        let kinds: Vec<(FindingKind, &str)> = analysis
+            .findings
+            .iter()
+            .map(|f| (f.kind, f.template.as_str()))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                (FindingKind::ErrorPattern, "<*> ERROR payment gateway timeout after <*>"),
+                (FindingKind::NewPattern, "<*> ERROR payment gateway timeout after <*>"),
+                (FindingKind::RarePattern, "<*> DEBUG feature flags reloaded"),
+            ]
+        );
+        assert!(analysis.summary_task().contains("3x [error]"));
+    }
+
+    #[test]
+    fn test_dissimilar_lines_stay_apart() {
+        let mut clusterer = LogClusterer::new().with_similarity(0.9);
+        clusterer.add_lines(["connected to db primary", "connected to db replica", "", "  "]);
+        let analysis = clusterer.analysis(5);
+
+        assert_eq!(clusterer.line_count(), 2);
+        assert_eq!(analysis.pattern_count, 2);
+        assert!(analysis.findings.is_empty());
+    }
+}
@@ -2,9 +2,13 @@
 //!
 //! This module provides translation from natural language queries and extracted
 //! entities into structured query languages like PromQL, LogQL, and SQL.
+//! SQL is checked against a [`SqlCatalog`] and emitted as a parameterized,
+//! read-only [`SqlQuery`].
 
 use crate::entity::{Entity, EntityType};
+use crate::error::Result;
 use crate::intent::{Intent, IntentType};
+use crate::sql::{ensure_read_only, quote_ident, SqlCatalog, SqlQuery};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tracing::{debug, trace};
@@ -34,6 +38,9 @@ impl QueryLanguage {
     }
 }
 
+/// Rows a SQL query returns unless configured otherwise.
+const DEFAULT_SQL_ROW_LIMIT: usize = 100;
+
 /// Query translator that converts natural language to structured queries.
 pub struct QueryTranslator {
     /// Default time range if none specified
@@ -42,6 +49,10 @@ pub struct QueryTranslator {
     metric_mappings: HashMap<String, String>,
     /// Custom label mappings
     label_mappings: HashMap<String, String>,
+    /// Tables and columns SQL queries may reference
+    catalog: SqlCatalog,
+    /// Maximum rows a SQL query returns
+    sql_row_limit: usize,
 }
 
 impl QueryTranslator {
@@ -51,6 +62,8 @@ impl QueryTranslator {
             default_time_range: "5m".to_string(),
             metric_mappings: Self::default_metric_mappings(),
             label_mappings: HashMap::new(),
+            catalog: SqlCatalog::observability(),
+            sql_row_limit: DEFAULT_SQL_ROW_LIMIT,
         }
     }
 
@@ -63,9 +76,28 @@ impl QueryTranslator {
             default_time_range: "5m".to_string(),
             metric_mappings,
             label_mappings,
+            catalog: SqlCatalog::observability(),
+            sql_row_limit: DEFAULT_SQL_ROW_LIMIT,
         }
     }
 
+    /// Sets the tables and columns SQL queries may reference.
+    pub fn with_catalog(mut self, catalog: SqlCatalog) -> Self {
+        self.catalog = catalog;
+        self
+    }
+
+    /// Sets the maximum rows a SQL query returns.
+    pub fn with_sql_row_limit(mut self, limit: usize) -> Self {
+        self.sql_row_limit = limit.max(1);
+        self
+    }
+
+    /// Returns the Prometheus metric a metric entity maps to, if any.
+    pub fn metric_name(&self, metric: &str) -> Option<&str> {
+        self.metric_mappings.get(metric).map(String::as_str)
+    }
+
     /// Returns default metric name mappings.
     fn default_metric_mappings() -> HashMap<String, String> {
         let mut mappings = HashMap::new();
@@ -201,7 +233,11 @@ impl QueryTranslator {
         }
     }
 
-    /// Translates a query to SQL.
+    /// Translates a query to parameterized SQL.
+    ///
+    /// Tables and columns are checked against the translator's catalog,
+    /// identifiers are quoted, entity values are bound as parameters, row
+    /// counts are capped and the result must pass [`ensure_read_only`].
     ///
     /// # Arguments
     ///
@@ -210,17 +246,17 @@ impl QueryTranslator {
     ///
     /// # Returns
     ///
-    /// A SQL query string
-    pub fn to_sql(&self, intent: &Intent, entities: &[Entity]) -> String {
+    /// The SQL query and its parameters, or an error if it would reference a
+    /// table or column that is not in the catalog
+    pub fn to_sql(&self, intent: &Intent, entities: &[Entity]) -> Result<SqlQuery> {
         trace!("Translating to SQL: intent={:?}", intent.intent_type);
 
         let service = self.get_entity_value(entities, EntityType::Service);
         let severity = self.get_entity_value(entities, EntityType::Severity);
         let metric = self.get_entity_value(entities, EntityType::Metric);
-        let aggregation = self.get_entity_value(entities, EntityType::Aggregation)
-            .unwrap_or("avg");
+        let aggregation = self.get_entity_value(entities, EntityType::Aggregation);
 
-        match intent.intent_type {
+        let query = match intent.intent_type {
             IntentType::QueryMetrics | IntentType::PerformanceAnalysis => {
                 self.build_sql_metrics_query(metric, service, aggregation)
             }
@@ -232,21 +268,25 @@ impl QueryTranslator {
             }
             _ => {
                 // Default: simple select
-                let mut conditions = Vec::new();
-
+                let table = self.catalog.require_table("metrics")?;
+                let timestamp = self.sql_column(table, "timestamp")?;
+                let mut filter = SqlFilter::default();
                 if let Some(svc) = service {
-                    conditions.push(format!("service = '{}'", svc));
+                    filter.push(self.sql_column(table, "service")?, svc);
                 }
 
-                let where_clause = if conditions.is_empty() {
-                    String::new()
-                } else {
-                    format!(" WHERE {}", conditions.join(" AND "))
-                };
-
-                format!("SELECT * FROM metrics{} ORDER BY timestamp DESC LIMIT 100", where_clause)
+                Ok(filter.query(format!(
+                    "SELECT * FROM {}{} ORDER BY {} DESC LIMIT {}",
+                    quote_ident(table),
+                    filter.where_clause(),
+                    timestamp,
+                    self.sql_row_limit
+                )))
             }
-        }
+        }?;
+
+        ensure_read_only(&query.sql)?;
+        Ok(query)
     }
 
     /// Helper function to get entity value by type.
@@ -441,81 +481,152 @@ impl QueryTranslator {
 
     // SQL query builders
 
+    /// Quoted catalog spelling of a column.
+    fn sql_column(&self, table: &str, column: &str) -> Result<String> {
+        self.catalog.require_column(table, column).map(quote_ident)
+    }
+
+    /// SQL aggregate function for an aggregation entity; AVG for anything SQL
+    /// has no plain aggregate for (e.g. rate, percentile).
+    fn sql_aggregate(aggregation: Option<&str>) -> &'static str {
+        match aggregation {
+            Some("sum") => "SUM",
+            Some("max") => "MAX",
+            Some("min") => "MIN",
+            Some("count") => "COUNT",
+            Some("avg") | None => "AVG",
+            Some(other) => {
+                debug!("No SQL aggregate for {}, using AVG", other);
+                "AVG"
+            }
+        }
+    }
+
+    /// Quoted value column for a metric: the metric's own column if the
+    /// table has one, otherwise the `value` column of rows filtered by `name`.
+    fn sql_metric_column(
+        &self,
+        table: &str,
+        metric: Option<&str>,
+        filter: &mut SqlFilter,
+    ) -> Result<String> {
+        let Some(metric) = metric else {
+            return self.sql_column(table, "value");
+        };
+        if let Some(column) = self.catalog.column(table, metric) {
+            return Ok(quote_ident(column));
+        }
+        if let Some(name) = self.catalog.column(table, "name") {
+            filter.push(quote_ident(name), metric);
+            return self.sql_column(table, "value");
+        }
+        self.sql_column(table, metric)
+    }
+
     fn build_sql_metrics_query(
         &self,
         metric: Option<&str>,
         service: Option<&str>,
-        aggregation: &str,
-    ) -> String {
-        let metric_col = metric.unwrap_or("value");
-        let mut conditions = Vec::new();
+        aggregation: Option<&str>,
+    ) -> Result<SqlQuery> {
+        let table = self.catalog.require_table("metrics")?;
+        let timestamp = self.sql_column(table, "timestamp")?;
+        let service_col = self.sql_column(table, "service")?;
+        let mut filter = SqlFilter::default();
+        let metric_col = self.sql_metric_column(table, metric, &mut filter)?;
 
         if let Some(svc) = service {
-            conditions.push(format!("service = '{}'", svc));
+            filter.push(service_col.clone(), svc);
         }
 
-        let where_clause = if conditions.is_empty() {
-            String::new()
-        } else {
-            format!(" WHERE {}", conditions.join(" AND "))
-        };
-
-        format!(
-            "SELECT timestamp, service, {}({}) as value FROM metrics{} GROUP BY timestamp, service ORDER BY timestamp DESC",
-            aggregation.to_uppercase(),
+        Ok(filter.query(format!(
+            "SELECT {ts}, {svc}, {}({}) AS \"value\" FROM {}{} GROUP BY {ts}, {svc} ORDER BY {ts} DESC LIMIT {}",
+            Self::sql_aggregate(aggregation),
             metric_col,
-            where_clause
-        )
+            quote_ident(table),
+            filter.where_clause(),
+            self.sql_row_limit,
+            ts = timestamp,
+            svc = service_col,
+        )))
     }
 
-    fn build_sql_logs_query(&self, service: Option<&str>, severity: Option<&str>) -> String {
-        let mut conditions = Vec::new();
+    fn build_sql_logs_query(&self, service: Option<&str>, severity: Option<&str>) -> Result<SqlQuery> {
+        let table = self.catalog.require_table("logs")?;
+        let columns = ["timestamp", "service", "level", "message"]
+            .iter()
+            .map(|column| self.sql_column(table, column))
+            .collect::<Result<Vec<_>>>()?;
+        let mut filter = SqlFilter::default();
 
         if let Some(svc) = service {
-            conditions.push(format!("service = '{}'", svc));
+            filter.push(columns[1].clone(), svc);
         }
 
         if let Some(sev) = severity {
-            conditions.push(format!("level = '{}'", sev));
+            filter.push(columns[2].clone(), sev);
         }
 
-        let where_clause = if conditions.is_empty() {
-            String::new()
-        } else {
-            format!(" WHERE {}", conditions.join(" AND "))
-        };
-
-        format!(
-            "SELECT timestamp, service, level, message FROM logs{} ORDER BY timestamp DESC LIMIT 100",
-            where_clause
-        )
+        Ok(filter.query(format!(
+            "SELECT {} FROM {}{} ORDER BY {} DESC LIMIT {}",
+            columns.join(", "),
+            quote_ident(table),
+            filter.where_clause(),
+            columns[0],
+            self.sql_row_limit
+        )))
     }
 
     fn build_sql_trend_query(
         &self,
         metric: Option<&str>,
         service: Option<&str>,
-        aggregation: &str,
-    ) -> String {
-        let metric_col = metric.unwrap_or("value");
-        let mut conditions = Vec::new();
+        aggregation: Option<&str>,
+    ) -> Result<SqlQuery> {
+        let table = self.catalog.require_table("metrics")?;
+        let timestamp = self.sql_column(table, "timestamp")?;
+        let mut filter = SqlFilter::default();
+        let metric_col = self.sql_metric_column(table, metric, &mut filter)?;
 
         if let Some(svc) = service {
-            conditions.push(format!("service = '{}'", svc));
+            filter.push(self.sql_column(table, "service")?, svc);
         }
 
-        let where_clause = if conditions.is_empty() {
+        Ok(filter.query(format!(
+            "SELECT DATE_TRUNC('hour', {}) AS \"hour\", {}({}) AS \"value\" FROM {}{} GROUP BY \"hour\" ORDER BY \"hour\" DESC LIMIT {}",
+            timestamp,
+            Self::sql_aggregate(aggregation),
+            metric_col,
+            quote_ident(table),
+            filter.where_clause(),
+            self.sql_row_limit
+        )))
+    }
+}
+
+/// Equality conditions on quoted columns, with values bound as parameters.
+#[derive(Default)]
+struct SqlFilter {
+    conditions: Vec<String>,
+    params: Vec<String>,
+}
+
+impl SqlFilter {
+    fn push(&mut self, column: String, value: &str) {
+        self.params.push(value.to_string());
+        self.conditions.push(format!("{} = ${}", column, self.params.len()));
+    }
+
+    fn where_clause(&self) -> String {
+        if self.conditions.is_empty() {
             String::new()
         } else {
-            format!(" WHERE {}", conditions.join(" AND "))
-        };
+            format!(" WHERE {}", self.conditions.join(" AND "))
+        }
+    }
 
-        format!(
-            "SELECT DATE_TRUNC('hour', timestamp) as hour, {}({}) as value FROM metrics{} GROUP BY hour ORDER BY hour DESC",
-            aggregation.to_uppercase(),
-            metric_col,
-            where_clause
-        )
+    fn query(&self, sql: String) -> SqlQuery {
+        SqlQuery { sql, params: self.params.clone() }
     }
 }
 
@@ -600,10 +711,49 @@ mod tests {
             create_test_entity(EntityType::Aggregation, "avg"),
         ];
 
-        let query = translator.to_sql(&intent, &entities);
-        assert!(query.contains("SELECT"));
-        assert!(query.contains("AVG"));
-        assert!(query.contains("web-service"));
+        let query = translator.to_sql(&intent, &entities).unwrap();
+        assert_eq!(
+            query.sql,
+            "SELECT \"timestamp\", \"service\", AVG(\"value\") AS \"value\" FROM \"metrics\" \
+             WHERE \"name\" = $1 AND \"service\" = $2 GROUP BY \"timestamp\", \"service\" \
+             ORDER BY \"timestamp\" DESC LIMIT 100"
+        );
+        assert_eq!(query.params, vec!["latency", "web-service"]);
+    }
+
+    #[test]
+    fn test_sql_uses_catalog_columns() {
+        let catalog = SqlCatalog::new()
+            .with_table("metrics", ["timestamp", "service", "latency"])
+            .with_table("logs", ["timestamp", "service", "level", "message"]);
+        let translator = QueryTranslator::new()
+            .with_catalog(catalog)
+            .with_sql_row_limit(20);
+
+        let trend = create_test_intent(IntentType::TrendAnalysis);
+        let query = translator
+            .to_sql(&trend, &[create_test_entity(EntityType::Metric, "latency")])
+            .unwrap();
+        assert!(query.sql.contains("AVG(\"latency\")"));
+        assert!(query.sql.ends_with("LIMIT 20"));
+
+        // Columns outside the catalog are rejected rather than invented
+        let err = translator
+            .to_sql(&trend, &[create_test_entity(EntityType::Metric, "cpu")])
+            .unwrap_err();
+        assert!(err.to_string().contains("Column cpu is not in table metrics"));
+    }
+
+    #[test]
+    fn test_sql_values_are_parameters() {
+        let translator = QueryTranslator::new();
+        let intent = create_test_intent(IntentType::SearchLogs);
+        let entities = vec![create_test_entity(EntityType::Service, "x'; DROP TABLE logs; --")];
+
+        let query = translator.to_sql(&intent, &entities).unwrap();
+        assert!(!query.sql.contains("DROP"));
+        assert_eq!(query.params, vec!["x'; DROP TABLE logs; --"]);
+        assert!(query.inline().contains("'x''; DROP TABLE logs; --'"));
     }
 
     #[test]
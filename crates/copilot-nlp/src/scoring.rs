@@ -0,0 +1,209 @@
+//! Lightweight relevance scoring.
+//!
+//! A dependency-free BM25-style scorer for ranking context snippets against a
+//! query. It is cheap enough to run client-side (see the `wasm` feature), so
+//! callers can pre-filter candidates before sending them to the server.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::entity::{EntityExtractor, EntityType};
+
+/// Words ignored when matching query terms.
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "how", "i", "in", "is", "it",
+    "me", "my", "of", "on", "or", "show", "that", "the", "to", "was", "what", "when", "where",
+    "which", "why", "with",
+];
+
+/// A scored candidate returned by [`RelevanceScorer::rank`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoredText {
+    /// Index of the candidate in the input
+    pub index: usize,
+    /// Relevance score (0.0 to 1.0)
+    pub score: f64,
+}
+
+/// Scores how relevant a text is to a query.
+///
+/// Scores combine saturated term frequency (BM25) with a boost for services
+/// and metrics named in the query, and are normalized to `0.0..=1.0`.
+pub struct RelevanceScorer {
+    /// Term frequency saturation
+    k1: f64,
+    /// Length normalization strength
+    b: f64,
+    /// Added for each query service or metric found in the text
+    entity_boost: f64,
+    /// Extracts services and metrics from queries
+    extractor: EntityExtractor,
+}
+
+impl RelevanceScorer {
+    /// Creates a scorer with standard BM25 parameters.
+    pub fn new() -> Self {
+        Self {
+            k1: 1.2,
+            b: 0.75,
+            entity_boost: 0.2,
+            extractor: EntityExtractor::new(),
+        }
+    }
+
+    /// Sets the boost added per matched service or metric.
+    pub fn with_entity_boost(mut self, boost: f64) -> Self {
+        self.entity_boost = boost;
+        self
+    }
+
+    /// Uses known service and metric names when extracting query entities.
+    pub fn with_context(mut self, known_services: Vec<String>, known_metrics: Vec<String>) -> Self {
+        self.extractor = EntityExtractor::with_context(known_services, known_metrics);
+        self
+    }
+
+    /// Scores a single text against a query.
+    pub fn score(&self, query: &str, text: &str) -> f64 {
+        self.rank(query, &[text])
+            .first()
+            .map(|scored| scored.score)
+            .unwrap_or(0.0)
+    }
+
+    /// Scores each candidate against a query, most relevant first.
+    ///
+    /// Term weights come from how many candidates contain each term, so
+    /// terms shared by every candidate count for less.
+    pub fn rank(&self, query: &str, texts: &[&str]) -> Vec<ScoredText> {
+        if texts.is_empty() {
+            return Vec::new();
+        }
+        let query_terms: HashSet<String> = tokenize(query).collect();
+
+        let documents: Vec<Vec<String>> = texts.iter().map(|t| tokenize(t).collect()).collect();
+        let average_len =
+            documents.iter().map(Vec::len).sum::<usize>() as f64 / documents.len() as f64;
+
+        let idf: HashMap<&str, f64> = query_terms
+            .iter()
+            .map(|term| {
+                let containing = documents.iter().filter(|d| d.contains(term)).count() as f64;
+                let n = documents.len() as f64;
+                let weight = (1.0 + (n - containing + 0.5) / (containing + 0.5)).ln();
+                (term.as_str(), weight)
+            })
+            .collect();
+        let max_score: f64 = idf.values().sum();
+
+        let entities: Vec<String> = self
+            .extractor
+            .extract(query)
+            .into_iter()
+            .filter(|e| matches!(e.entity_type, EntityType::Service | EntityType::Metric))
+            .map(|e| e.value.to_lowercase())
+            .collect();
+
+        let mut scored: Vec<ScoredText> = documents
+            .iter()
+            .zip(texts)
+            .enumerate()
+            .map(|(index, (terms, text))| {
+                let lexical = if max_score > 0.0 {
+                    self.bm25(terms, &idf, average_len) / max_score
+                } else {
+                    0.0
+                };
+
+                let lowered = text.to_lowercase();
+                let matched = entities
+                    .iter()
+                    .filter(|e| lowered.contains(e.as_str()))
+                    .count();
+                let score = (lexical + matched as f64 * self.entity_boost).min(1.0);
+
+                ScoredText { index, score }
+            })
+            .collect();
+
+        scored.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.index.cmp(&b.index)));
+        scored
+    }
+
+    /// BM25 score of a tokenized document, at most the sum of the weights.
+    fn bm25(&self, terms: &[String], idf: &HashMap<&str, f64>, average_len: f64) -> f64 {
+        let mut frequencies: HashMap<&str, usize> = HashMap::new();
+        for term in terms {
+            *frequencies.entry(term.as_str()).or_default() += 1;
+        }
+
+        let length_norm = if average_len > 0.0 {
+            1.0 - self.b + self.b * terms.len() as f64 / average_len
+        } else {
+            1.0
+        };
+
+        idf.iter()
+            .map(|(term, weight)| {
+                let tf = frequencies.get(term).copied().unwrap_or(0) as f64;
+                // Saturates towards the term weight as tf grows
+                weight * tf / (tf + self.k1 * length_norm)
+            })
+            .sum()
+    }
+}
+
+impl Default for RelevanceScorer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Splits text into lowercase terms, dropping stopwords.
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .filter(|word| !STOPWORDS.contains(&word.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_prefers_matching_text() {
+        let scorer = RelevanceScorer::new();
+        let ranked = scorer.rank(
+            "database connection errors",
+            &[
+                "CPU usage is stable across all nodes",
+                "Database connection pool exhausted, errors rising",
+                "Connection to cache restored",
+            ],
+        );
+
+        assert_eq!(ranked[0].index, 1);
+        assert_eq!(ranked[2].index, 0);
+        assert_eq!(ranked[2].score, 0.0);
+        assert!(ranked[0].score <= 1.0);
+    }
+
+    #[test]
+    fn test_entity_boost() {
+        let scorer =
+            RelevanceScorer::new().with_context(vec!["auth-service".to_string()], Vec::new());
+        let query = "latency for auth-service";
+
+        let with_service = scorer.score(query, "auth-service p99 latency rose");
+        let without = scorer.score(query, "payment p99 latency rose");
+        assert!(with_service > without);
+    }
+
+    #[test]
+    fn test_stopword_only_query_scores_zero() {
+        let scorer = RelevanceScorer::new();
+        assert_eq!(scorer.score("what is the", "the service is healthy"), 0.0);
+        assert!(scorer.rank("errors", &[]).is_empty());
+    }
+}
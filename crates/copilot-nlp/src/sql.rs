@@ -0,0 +1,247 @@
+//! SQL safety helpers.
+//!
+//! This module provides the table/column catalog SQL translation is checked
+//! against, identifier quoting, parameterized queries and a read-only guard
+//! that rejects anything but a single `SELECT` statement.
+
+use crate::error::{NlpError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Keywords that modify data, schema or session state.
+const FORBIDDEN_KEYWORDS: &[&str] = &[
+    "INSERT", "UPDATE", "DELETE", "MERGE", "UPSERT", "INTO", "DROP", "CREATE", "ALTER",
+    "TRUNCATE", "RENAME", "GRANT", "REVOKE", "COPY", "CALL", "EXEC", "EXECUTE", "DO", "LOCK",
+    "VACUUM", "ATTACH", "DETACH", "PRAGMA", "SET",
+];
+
+/// Tables and columns that SQL queries may reference.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SqlCatalog {
+    tables: BTreeMap<String, Vec<String>>,
+}
+
+impl SqlCatalog {
+    /// Creates an empty catalog.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the catalog of the built-in observability schema: a long
+    /// `metrics` table with one row per named sample and a `logs` table.
+    pub fn observability() -> Self {
+        Self::new()
+            .with_table("metrics", ["timestamp", "service", "name", "value"])
+            .with_table("logs", ["timestamp", "service", "level", "message"])
+    }
+
+    /// Adds a table and its columns.
+    pub fn with_table<I, S>(mut self, table: impl Into<String>, columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tables
+            .insert(table.into(), columns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Returns true if the catalog has no tables.
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Returns the catalog spelling of a table, matched case-insensitively.
+    pub fn table(&self, table: &str) -> Option<&str> {
+        self.tables
+            .keys()
+            .find(|name| name.eq_ignore_ascii_case(table))
+            .map(String::as_str)
+    }
+
+    /// Returns the catalog spelling of a column, matched case-insensitively.
+    pub fn column(&self, table: &str, column: &str) -> Option<&str> {
+        let table = self.table(table)?;
+        self.tables[table]
+            .iter()
+            .find(|name| name.eq_ignore_ascii_case(column))
+            .map(String::as_str)
+    }
+
+    /// Returns the catalog spelling of a table, or an error if it is unknown.
+    pub fn require_table(&self, table: &str) -> Result<&str> {
+        self.table(table).ok_or_else(|| {
+            NlpError::query_translation(format!(
+                "Table {} is not in the SQL catalog (known tables: {})",
+                table,
+                self.tables.keys().cloned().collect::<Vec<_>>().join(", ")
+            ))
+        })
+    }
+
+    /// Returns the catalog spelling of a column, or an error if it is unknown.
+    pub fn require_column(&self, table: &str, column: &str) -> Result<&str> {
+        let table = self.require_table(table)?;
+        self.column(table, column).ok_or_else(|| {
+            NlpError::query_translation(format!(
+                "Column {} is not in table {} (known columns: {})",
+                column,
+                table,
+                self.tables[table].join(", ")
+            ))
+        })
+    }
+}
+
+/// A SQL query whose values are bound as `$1`, `$2`, ... parameters.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SqlQuery {
+    /// The query text
+    pub sql: String,
+    /// Parameter values, in placeholder order
+    pub params: Vec<String>,
+}
+
+impl SqlQuery {
+    /// Returns the query with every parameter inlined as an escaped string
+    /// literal, for display.
+    pub fn inline(&self) -> String {
+        // Highest placeholders first, so `$1` does not clobber `$10`
+        self.params
+            .iter()
+            .enumerate()
+            .rev()
+            .fold(self.sql.clone(), |sql, (i, value)| {
+                sql.replace(&format!("${}", i + 1), &quote_literal(value))
+            })
+    }
+}
+
+/// Quotes an SQL identifier, doubling embedded quotes.
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quotes an SQL string literal, doubling embedded quotes.
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Rejects SQL that is not a single read-only `SELECT` (or `WITH ... SELECT`)
+/// statement.
+///
+/// String literals, quoted identifiers and comments are skipped, so a
+/// keyword inside them does not trip the guard.
+pub fn ensure_read_only(sql: &str) -> Result<()> {
+    let code = strip_literals(sql);
+    let code = code.trim().trim_end_matches(';');
+    if code.contains(';') {
+        return Err(NlpError::validation("SQL must be a single statement"));
+    }
+
+    let words: Vec<String> = code
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|w| !w.is_empty())
+        .map(str::to_ascii_uppercase)
+        .collect();
+    match words.first().map(String::as_str) {
+        Some("SELECT") | Some("WITH") => {}
+        _ => return Err(NlpError::validation("SQL must be a SELECT query")),
+    }
+    if let Some(keyword) = words.iter().find(|w| FORBIDDEN_KEYWORDS.contains(&w.as_str())) {
+        return Err(NlpError::validation(format!(
+            "SQL must be read-only, found {}",
+            keyword
+        )));
+    }
+    Ok(())
+}
+
+/// Replaces string literals, quoted identifiers and comments with spaces.
+fn strip_literals(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                // Doubled quotes inside are consumed as a close and reopen
+                while let Some(next) = chars.next() {
+                    if next == c {
+                        if chars.peek() == Some(&c) {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                out.push(' ');
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+                out.push(' ');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for next in chars.by_ref() {
+                    if prev == '*' && next == '/' {
+                        break;
+                    }
+                    prev = next;
+                }
+                out.push(' ');
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_lookup() {
+        let catalog = SqlCatalog::observability().with_table("Deploys", ["id", "Service"]);
+
+        assert_eq!(catalog.table("deploys"), Some("Deploys"));
+        assert_eq!(catalog.column("deploys", "service"), Some("Service"));
+        assert!(catalog.require_column("metrics", "cpu_percent").is_err());
+        assert!(catalog.require_table("users").is_err());
+    }
+
+    #[test]
+    fn test_quoting_and_inlining() {
+        assert_eq!(quote_ident("weird\"name"), "\"weird\"\"name\"");
+
+        let query = SqlQuery {
+            sql: "SELECT * FROM logs WHERE service = $1 AND level = $2".to_string(),
+            params: vec!["o'brien".to_string(), "error".to_string()],
+        };
+        assert_eq!(
+            query.inline(),
+            "SELECT * FROM logs WHERE service = 'o''brien' AND level = 'error'"
+        );
+    }
+
+    #[test]
+    fn test_read_only_guard() {
+        assert!(ensure_read_only("SELECT * FROM logs LIMIT 10;").is_ok());
+        assert!(ensure_read_only("WITH recent AS (SELECT 1) SELECT * FROM recent").is_ok());
+        assert!(ensure_read_only("SELECT * FROM logs WHERE message = 'DROP TABLE logs'").is_ok());
+        assert!(ensure_read_only("SELECT \"update\" FROM t -- delete later").is_ok());
+
+        assert!(ensure_read_only("DELETE FROM logs").is_err());
+        assert!(ensure_read_only("SELECT 1; DROP TABLE logs").is_err());
+        assert!(ensure_read_only("SELECT * INTO backup FROM logs").is_err());
+        assert!(ensure_read_only("WITH x AS (DELETE FROM logs RETURNING *) SELECT * FROM x").is_err());
+        assert!(ensure_read_only("SELECT * FROM logs FOR UPDATE").is_err());
+        assert!(ensure_read_only("/* SELECT */ TRUNCATE logs").is_err());
+    }
+}
@@ -0,0 +1,70 @@
+//! WebAssembly bindings.
+//!
+//! Exposes intent classification, entity extraction and relevance scoring to
+//! JavaScript so browser extensions and edge workers can pre-classify queries
+//! without a round trip. Build with:
+//!
+//! ```text
+//! cargo build -p copilot-nlp --target wasm32-unknown-unknown \
+//!     --no-default-features --features wasm
+//! ```
+//!
+//! Results are returned as plain JavaScript objects with the same shape as
+//! the serde representation of the Rust types.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::entity::EntityExtractor;
+use crate::intent::IntentClassifier;
+use crate::scoring::RelevanceScorer;
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    serde_wasm_bindgen::to_value(value).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Query pre-classifier for JavaScript callers.
+#[wasm_bindgen]
+pub struct QueryClassifier {
+    intents: IntentClassifier,
+    entities: EntityExtractor,
+    scorer: RelevanceScorer,
+}
+
+#[wasm_bindgen]
+impl QueryClassifier {
+    /// Creates a classifier, optionally seeded with known service and metric names.
+    #[wasm_bindgen(constructor)]
+    pub fn new(known_services: Option<Vec<String>>, known_metrics: Option<Vec<String>>) -> Self {
+        let services = known_services.unwrap_or_default();
+        let metrics = known_metrics.unwrap_or_default();
+        Self {
+            intents: IntentClassifier::new(),
+            entities: EntityExtractor::with_context(services.clone(), metrics.clone()),
+            scorer: RelevanceScorer::new().with_context(services, metrics),
+        }
+    }
+
+    /// Classifies a query, returning an `Intent` object.
+    #[wasm_bindgen(js_name = classifyIntent)]
+    pub fn classify_intent(&self, query: &str) -> Result<JsValue, JsError> {
+        to_js(&self.intents.classify(query))
+    }
+
+    /// Extracts entities from a query, returning an array of `Entity` objects.
+    #[wasm_bindgen(js_name = extractEntities)]
+    pub fn extract_entities(&self, query: &str) -> Result<JsValue, JsError> {
+        to_js(&self.entities.extract(query))
+    }
+
+    /// Scores one text against a query (0.0 to 1.0).
+    pub fn score(&self, query: &str, text: &str) -> f64 {
+        self.scorer.score(query, text)
+    }
+
+    /// Ranks texts against a query, returning `{ index, score }` objects, best first.
+    pub fn rank(&self, query: &str, texts: Vec<String>) -> Result<JsValue, JsError> {
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        to_js(&self.scorer.rank(query, &texts))
+    }
+}
@@ -0,0 +1,162 @@
+//! Context pipeline stage metrics
+//!
+//! Aggregates what each stage of the context pipeline (retrieval, reranking,
+//! compression, prompt assembly) did across requests: how often it ran and
+//! how long it took, how many candidates it took in and passed on, the tokens
+//! it saved and how often a cache answered it. Operators read it from
+//! `/metrics` to see where context latency and the token budget go; the
+//! stages of a single request are in its tracing spans.
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// What one run of a stage did
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StageSample {
+    pub latency_ms: f64,
+    pub candidates_in: u64,
+    pub candidates_out: u64,
+    pub tokens_saved: u64,
+    /// Whether a cache answered the stage, for stages served from one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_hit: Option<bool>,
+}
+
+/// Totals of one stage
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StageStats {
+    pub stage: String,
+    pub runs: u64,
+    pub total_latency_ms: f64,
+    pub max_latency_ms: f64,
+    pub candidates_in: u64,
+    pub candidates_out: u64,
+    pub tokens_saved: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+}
+
+impl StageStats {
+    pub fn avg_latency_ms(&self) -> f64 {
+        if self.runs == 0 {
+            0.0
+        } else {
+            self.total_latency_ms / self.runs as f64
+        }
+    }
+}
+
+/// Totals of every stage that ran, by stage name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContextPipelineStats {
+    pub stages: Vec<StageStats>,
+}
+
+impl ContextPipelineStats {
+    pub fn stage(&self, stage: &str) -> Option<&StageStats> {
+        self.stages.iter().find(|stats| stats.stage == stage)
+    }
+
+    /// Render the totals in the Prometheus text exposition format, one
+    /// series per stage
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        let metrics: [(&str, &str, &str, fn(&StageStats) -> f64); 8] = [
+            ("runs_total", "counter", "Context pipeline stage runs", |s| s.runs as f64),
+            ("latency_ms_total", "counter", "Time spent in context pipeline stages", |s| s.total_latency_ms),
+            ("latency_ms_max", "gauge", "Slowest run of context pipeline stages", |s| s.max_latency_ms),
+            ("candidates_in_total", "counter", "Candidates context pipeline stages took in", |s| {
+                s.candidates_in as f64
+            }),
+            ("candidates_out_total", "counter", "Candidates context pipeline stages passed on", |s| {
+                s.candidates_out as f64
+            }),
+            ("tokens_saved_total", "counter", "Tokens context pipeline stages removed", |s| s.tokens_saved as f64),
+            ("cache_hits_total", "counter", "Context pipeline stage runs answered by a cache", |s| {
+                s.cache_hits as f64
+            }),
+            ("cache_misses_total", "counter", "Context pipeline stage runs that missed a cache", |s| {
+                s.cache_misses as f64
+            }),
+        ];
+        for (name, kind, help, value) in metrics {
+            let _ = writeln!(out, "# HELP {prefix}_context_stage_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_context_stage_{name} {kind}");
+            for stats in &self.stages {
+                let _ = writeln!(out, "{prefix}_context_stage_{name}{{stage=\"{}\"}} {}", stats.stage, value(stats));
+            }
+        }
+        out
+    }
+}
+
+/// Per-stage totals of the context pipeline
+#[derive(Default)]
+pub struct ContextPipelineMetrics {
+    stages: RwLock<BTreeMap<String, StageStats>>,
+}
+
+impl ContextPipelineMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one run of `stage`
+    pub fn record(&self, stage: &str, sample: StageSample) {
+        let mut stages = self.stages.write();
+        let stats = stages.entry(stage.to_string()).or_insert_with(|| StageStats {
+            stage: stage.to_string(),
+            ..Default::default()
+        });
+        stats.runs += 1;
+        stats.total_latency_ms += sample.latency_ms;
+        stats.max_latency_ms = stats.max_latency_ms.max(sample.latency_ms);
+        stats.candidates_in += sample.candidates_in;
+        stats.candidates_out += sample.candidates_out;
+        stats.tokens_saved += sample.tokens_saved;
+        match sample.cache_hit {
+            Some(true) => stats.cache_hits += 1,
+            Some(false) => stats.cache_misses += 1,
+            None => {}
+        }
+    }
+
+    pub fn stats(&self) -> ContextPipelineStats {
+        ContextPipelineStats {
+            stages: self.stages.read().values().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_totals() {
+        let metrics = ContextPipelineMetrics::new();
+        let sample = |latency_ms, tokens_saved, cache_hit| StageSample {
+            latency_ms,
+            candidates_in: 12,
+            candidates_out: 4,
+            tokens_saved,
+            cache_hit,
+        };
+        metrics.record("retrieval", sample(8.0, 900, Some(false)));
+        metrics.record("retrieval", sample(2.0, 900, Some(true)));
+        metrics.record("compression", sample(1.5, 300, None));
+
+        let stats = metrics.stats();
+        let retrieval = stats.stage("retrieval").unwrap();
+        assert_eq!((retrieval.runs, retrieval.cache_hits, retrieval.cache_misses), (2, 1, 1));
+        assert_eq!(retrieval.avg_latency_ms(), 5.0);
+        assert_eq!(retrieval.max_latency_ms, 8.0);
+        assert_eq!(stats.stage("compression").unwrap().tokens_saved, 300);
+
+        let rendered = stats.render_prometheus("copilot");
+        assert!(rendered.contains("copilot_context_stage_runs_total{stage=\"retrieval\"} 2"));
+        assert!(rendered.contains("copilot_context_stage_tokens_saved_total{stage=\"compression\"} 300"));
+    }
+}
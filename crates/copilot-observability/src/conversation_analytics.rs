@@ -0,0 +1,474 @@
+//! Conversation intents and resolution
+//!
+//! Follows each conversation turn by turn: the intent classified for each
+//! user message, whether the user's goal was resolved and after how many
+//! turns, and whether the conversation was escalated to humans. The report
+//! aggregates these per intent, so product owners can see what users come
+//! for and how often the agent gets them there.
+//!
+//! A goal counts as resolved when the user confirms it through the API or,
+//! failing that, when a message reads as a confirmation ("thanks, that
+//! worked"); a later complaint ("still failing") withdraws an inferred
+//! resolution but not a confirmed one. Message text is only inspected for
+//! these cues, never kept, and conversations of tenants that log no
+//! prompts are not followed.
+
+use chrono::{DateTime, Utc};
+use copilot_core::PromptLogging;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Messages that read as the user's goal being met
+const RESOLVED_CUES: &[&str] = &[
+    "thanks", "thank you", "that worked", "that works", "it worked", "it works now", "works now", "that fixed",
+    "fixed it", "solved", "that helps", "that helped", "perfect", "got it", "all set", "resolved",
+];
+
+/// Messages that read as the goal still being open
+const UNRESOLVED_CUES: &[&str] = &[
+    "didn't work", "did not work", "doesn't work", "does not work", "not working", "still failing", "still broken",
+    "still getting", "still not", "didn't help", "doesn't help", "not helpful", "wrong", "that's not",
+];
+
+/// How a conversation's resolution was established
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionSource {
+    /// The user said so through the API
+    Confirmed,
+    /// A message read as a confirmation
+    Inferred,
+}
+
+/// Whether and when a conversation's goal was resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resolution {
+    pub resolved: bool,
+    pub source: ResolutionSource,
+    /// Turns the conversation took to get there
+    pub turns: usize,
+}
+
+/// Cue a user message gives about the conversation's goal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolutionCue {
+    Resolved,
+    Unresolved,
+}
+
+/// Resolution cue of a user message, if any; complaints win over thanks
+pub fn resolution_cue(message: &str) -> Option<ResolutionCue> {
+    let message = message.to_lowercase().replace('\u{2019}', "'");
+    if UNRESOLVED_CUES.iter().any(|cue| message.contains(cue)) {
+        return Some(ResolutionCue::Unresolved);
+    }
+    // Long messages with a passing "thanks" are usually new questions
+    let short = message.split_whitespace().count() <= 12;
+    (short && RESOLVED_CUES.iter().any(|cue| message.contains(cue))).then_some(ResolutionCue::Resolved)
+}
+
+/// Conversation analytics settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConversationAnalyticsConfig {
+    /// Conversations followed; the least recently active are dropped first
+    pub max_conversations: usize,
+}
+
+impl Default for ConversationAnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            max_conversations: 50_000,
+        }
+    }
+}
+
+/// What one intent's conversations came to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntentMetrics {
+    pub intent: String,
+    /// Conversations that opened with this intent
+    pub conversations: u64,
+    /// Turns classified with this intent, in any conversation
+    pub turns: u64,
+    pub resolved: u64,
+    pub escalated: u64,
+    /// Mean turns the resolved conversations took
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mean_turns_to_resolution: Option<f64>,
+}
+
+impl IntentMetrics {
+    pub fn resolution_rate(&self) -> f64 {
+        rate(self.resolved, self.conversations)
+    }
+
+    pub fn escalation_rate(&self) -> f64 {
+        rate(self.escalated, self.conversations)
+    }
+}
+
+fn rate(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+/// Intent distribution and resolution metrics of recent conversations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationReport {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub since: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    pub conversations: u64,
+    pub turns: u64,
+    pub resolved: u64,
+    pub confirmed_resolutions: u64,
+    pub inferred_resolutions: u64,
+    /// Conversations the user confirmed were not resolved
+    pub unresolved: u64,
+    pub escalated: u64,
+    pub resolution_rate: f64,
+    pub escalation_rate: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mean_turns_to_resolution: Option<f64>,
+    /// Intents by number of conversations
+    pub intents: Vec<IntentMetrics>,
+}
+
+/// Counters of conversation events recorded so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConversationAnalyticsStats {
+    pub turns: u64,
+    /// Conversations marked resolved, including resolutions later withdrawn
+    pub resolutions: u64,
+    pub escalations: u64,
+}
+
+impl ConversationAnalyticsStats {
+    /// Prometheus text exposition of the counters
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        for (name, help, value) in [
+            ("conversation_turns_total", "Conversation turns answered", self.turns),
+            ("conversation_resolutions_total", "Conversations marked resolved", self.resolutions),
+            ("conversation_escalations_total", "Conversations escalated to humans", self.escalations),
+        ] {
+            let _ = writeln!(out, "# HELP {}_{} {}", prefix, name, help);
+            let _ = writeln!(out, "# TYPE {}_{} counter", prefix, name);
+            let _ = writeln!(out, "{}_{} {}", prefix, name, value);
+        }
+        out
+    }
+}
+
+struct ConversationRecord {
+    tenant_id: Option<String>,
+    turns: usize,
+    /// Intents of the turns, in order
+    intents: Vec<String>,
+    resolution: Option<Resolution>,
+    escalated: bool,
+    last_active: DateTime<Utc>,
+}
+
+impl ConversationRecord {
+    fn resolved(&self) -> bool {
+        self.resolution.is_some_and(|r| r.resolved)
+    }
+}
+
+/// Per-conversation intents and outcomes
+#[derive(Default)]
+pub struct ConversationAnalytics {
+    config: ConversationAnalyticsConfig,
+    conversations: RwLock<HashMap<String, ConversationRecord>>,
+    stats: RwLock<ConversationAnalyticsStats>,
+}
+
+impl ConversationAnalytics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: ConversationAnalyticsConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn config(&self) -> &ConversationAnalyticsConfig {
+        &self.config
+    }
+
+    /// Record a turn of `session_id`: the user's `message` and its
+    /// classified `intent`
+    ///
+    /// A message confirming the previous answer resolves the conversation
+    /// at the previous turn and is not counted as a turn of its own.
+    pub fn record_turn(
+        &self,
+        session_id: &str,
+        tenant_id: Option<&str>,
+        intent: Option<&str>,
+        message: &str,
+        mode: PromptLogging,
+    ) {
+        if !mode.records_prompts() {
+            return;
+        }
+        let now = Utc::now();
+        let mut conversations = self.conversations.write();
+        if !conversations.contains_key(session_id) && conversations.len() >= self.config.max_conversations {
+            let oldest = conversations
+                .iter()
+                .min_by_key(|(_, record)| record.last_active)
+                .map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                conversations.remove(&oldest);
+            }
+        }
+        let record = conversations.entry(session_id.to_string()).or_insert_with(|| ConversationRecord {
+            tenant_id: tenant_id.map(str::to_string),
+            turns: 0,
+            intents: Vec::new(),
+            resolution: None,
+            escalated: false,
+            last_active: now,
+        });
+        record.last_active = now;
+
+        let confirmed = matches!(record.resolution, Some(Resolution { source: ResolutionSource::Confirmed, .. }));
+        match resolution_cue(message) {
+            Some(ResolutionCue::Resolved) if record.turns > 0 && !confirmed => {
+                let newly = !record.resolved();
+                record.resolution = Some(Resolution {
+                    resolved: true,
+                    source: ResolutionSource::Inferred,
+                    turns: record.turns,
+                });
+                drop(conversations);
+                self.stats.write().resolutions += newly as u64;
+                return;
+            }
+            Some(ResolutionCue::Unresolved) if !confirmed => record.resolution = None,
+            _ => {}
+        }
+
+        record.turns += 1;
+        if let Some(intent) = intent {
+            record.intents.push(intent.to_string());
+        }
+        self.stats.write().turns += 1;
+    }
+
+    /// Record the user's own word on whether the conversation's goal was
+    /// met; false if the conversation is not followed
+    pub fn confirm_resolution(&self, session_id: &str, resolved: bool) -> bool {
+        let mut conversations = self.conversations.write();
+        let Some(record) = conversations.get_mut(session_id) else {
+            return false;
+        };
+        let was_resolved = record.resolved();
+        record.resolution = Some(Resolution {
+            resolved,
+            source: ResolutionSource::Confirmed,
+            turns: record.turns,
+        });
+        record.last_active = Utc::now();
+
+        self.stats.write().resolutions += (resolved && !was_resolved) as u64;
+        true
+    }
+
+    /// Record that the conversation was handed to humans; false if it is
+    /// not followed
+    pub fn record_escalation(&self, session_id: &str) -> bool {
+        let mut conversations = self.conversations.write();
+        let Some(record) = conversations.get_mut(session_id) else {
+            return false;
+        };
+        if !record.escalated {
+            record.escalated = true;
+            self.stats.write().escalations += 1;
+        }
+        record.last_active = Utc::now();
+        true
+    }
+
+    /// Resolution of a conversation so far
+    pub fn resolution(&self, session_id: &str) -> Option<Resolution> {
+        self.conversations.read().get(session_id).and_then(|record| record.resolution)
+    }
+
+    pub fn stats(&self) -> ConversationAnalyticsStats {
+        *self.stats.read()
+    }
+
+    /// Metrics of the conversations active since `since`, in `tenant_id`
+    /// or in every tenant
+    pub fn report(&self, tenant_id: Option<&str>, since: DateTime<Utc>) -> ConversationReport {
+        let conversations = self.conversations.read();
+        let mut report = ConversationReport {
+            tenant_id: tenant_id.map(str::to_string),
+            since,
+            generated_at: Utc::now(),
+            conversations: 0,
+            turns: 0,
+            resolved: 0,
+            confirmed_resolutions: 0,
+            inferred_resolutions: 0,
+            unresolved: 0,
+            escalated: 0,
+            resolution_rate: 0.0,
+            escalation_rate: 0.0,
+            mean_turns_to_resolution: None,
+            intents: Vec::new(),
+        };
+        let mut intents: HashMap<&str, (IntentMetrics, usize)> = HashMap::new();
+        let mut resolution_turns = 0;
+
+        for record in conversations.values() {
+            if record.last_active < since || tenant_id.is_some_and(|t| record.tenant_id.as_deref() != Some(t)) {
+                continue;
+            }
+            report.conversations += 1;
+            report.turns += record.turns as u64;
+            report.escalated += record.escalated as u64;
+            match record.resolution {
+                Some(Resolution { resolved: true, source, turns }) => {
+                    report.resolved += 1;
+                    resolution_turns += turns;
+                    match source {
+                        ResolutionSource::Confirmed => report.confirmed_resolutions += 1,
+                        ResolutionSource::Inferred => report.inferred_resolutions += 1,
+                    }
+                }
+                Some(Resolution { resolved: false, .. }) => report.unresolved += 1,
+                None => {}
+            }
+
+            for (i, intent) in record.intents.iter().enumerate() {
+                let (metrics, turns_to_resolution) = intents.entry(intent).or_insert_with(|| {
+                    let metrics = IntentMetrics {
+                        intent: intent.clone(),
+                        conversations: 0,
+                        turns: 0,
+                        resolved: 0,
+                        escalated: 0,
+                        mean_turns_to_resolution: None,
+                    };
+                    (metrics, 0)
+                });
+                metrics.turns += 1;
+                // A conversation belongs to the intent it opened with
+                if i == 0 {
+                    metrics.conversations += 1;
+                    metrics.escalated += record.escalated as u64;
+                    if let Some(Resolution { resolved: true, turns, .. }) = record.resolution {
+                        metrics.resolved += 1;
+                        *turns_to_resolution += turns;
+                    }
+                }
+            }
+        }
+
+        report.resolution_rate = rate(report.resolved, report.conversations);
+        report.escalation_rate = rate(report.escalated, report.conversations);
+        report.mean_turns_to_resolution =
+            (report.resolved > 0).then(|| resolution_turns as f64 / report.resolved as f64);
+
+        report.intents = intents
+            .into_values()
+            .map(|(mut metrics, turns)| {
+                metrics.mean_turns_to_resolution = (metrics.resolved > 0).then(|| turns as f64 / metrics.resolved as f64);
+                metrics
+            })
+            .collect();
+        report.intents.sort_by(|a, b| {
+            b.conversations
+                .cmp(&a.conversations)
+                .then_with(|| b.turns.cmp(&a.turns))
+                .then_with(|| a.intent.cmp(&b.intent))
+        });
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_resolution_cues() {
+        assert_eq!(resolution_cue("Thanks, that worked!"), Some(ResolutionCue::Resolved));
+        assert_eq!(resolution_cue("Thanks, but it still doesn't work"), Some(ResolutionCue::Unresolved));
+        assert_eq!(resolution_cue("That didn’t help"), Some(ResolutionCue::Unresolved));
+        assert_eq!(resolution_cue("How do I restart the ingestion workers?"), None);
+        assert_eq!(
+            resolution_cue("Thanks. Now how do I configure the retention period for the audit logs in the EU region?"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_intents_and_resolution() {
+        let analytics = ConversationAnalytics::new();
+        let since = Utc::now() - Duration::days(1);
+        let turn = |session: &str, intent: &str, message: &str| {
+            analytics.record_turn(session, Some("acme"), Some(intent), message, PromptLogging::Full)
+        };
+
+        // Resolved after two turns, inferred from the thanks
+        turn("s1", "SearchLogs", "Find errors in auth-service");
+        turn("s1", "SearchLogs", "Only the last hour");
+        turn("s1", "GeneralQuery", "Thanks, that worked");
+        assert_eq!(
+            analytics.resolution("s1"),
+            Some(Resolution { resolved: true, source: ResolutionSource::Inferred, turns: 2 })
+        );
+
+        // Thanks withdrawn by a complaint, then escalated
+        turn("s2", "SearchLogs", "Show logs for billing");
+        turn("s2", "GeneralQuery", "thanks");
+        turn("s2", "ServiceHealth", "It still doesn't work");
+        assert_eq!(analytics.resolution("s2"), None);
+        assert!(analytics.record_escalation("s2"));
+
+        // Confirmed through the API, which a later complaint does not undo
+        turn("s3", "ServiceHealth", "Is the auth service healthy?");
+        assert!(analytics.confirm_resolution("s3", true));
+        turn("s3", "ServiceHealth", "That's not what I asked");
+        assert!(analytics.resolution("s3").is_some_and(|r| r.resolved && r.turns == 1));
+
+        analytics.record_turn("s4", Some("globex"), Some("SearchLogs"), "Find errors", PromptLogging::Full);
+        analytics.record_turn("s5", Some("acme"), Some("SearchLogs"), "Find errors", PromptLogging::Off);
+        assert!(!analytics.confirm_resolution("s5", true));
+
+        let report = analytics.report(Some("acme"), since);
+        assert_eq!((report.conversations, report.turns), (3, 6));
+        assert_eq!((report.resolved, report.confirmed_resolutions, report.inferred_resolutions), (2, 1, 1));
+        assert_eq!(report.escalated, 1);
+        assert_eq!(report.mean_turns_to_resolution, Some(1.5));
+        assert!((report.resolution_rate - 2.0 / 3.0).abs() < 1e-9);
+
+        let logs = &report.intents[0];
+        assert_eq!((logs.intent.as_str(), logs.conversations, logs.turns), ("SearchLogs", 2, 3));
+        assert_eq!((logs.resolved, logs.escalated), (1, 1));
+        assert_eq!(logs.mean_turns_to_resolution, Some(2.0));
+        assert_eq!(logs.escalation_rate(), 0.5);
+        assert_eq!(report.intents[1].intent, "ServiceHealth");
+        assert_eq!(report.intents[1].turns, 3);
+
+        assert_eq!(analytics.report(None, since).conversations, 4);
+        assert_eq!(analytics.report(Some("acme"), Utc::now() + Duration::hours(1)).conversations, 0);
+        assert_eq!(analytics.stats(), ConversationAnalyticsStats { turns: 7, resolutions: 3, escalations: 1 });
+        assert!(analytics.stats().render_prometheus("copilot").contains("copilot_conversation_escalations_total 1"));
+    }
+}
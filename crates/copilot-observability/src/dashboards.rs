@@ -3,6 +3,7 @@
 //! Provides data structures and aggregations for dashboards.
 
 use crate::analytics::{AnalyticsEventType, AnalyticsService};
+use crate::query_analytics::{KnowledgeGapReport, QueryAnalytics};
 use crate::sla::{SlaMonitor, SlaSummary};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
@@ -217,6 +218,7 @@ pub struct PerformanceTimelinePoint {
 pub struct DashboardService {
     analytics: Arc<AnalyticsService>,
     sla_monitor: Arc<SlaMonitor>,
+    queries: Arc<QueryAnalytics>,
 }
 
 impl DashboardService {
@@ -224,9 +226,25 @@ impl DashboardService {
         Self {
             analytics,
             sla_monitor,
+            queries: Arc::new(QueryAnalytics::new()),
         }
     }
 
+    /// Report knowledge gaps from the questions recorded in `queries`
+    pub fn with_query_analytics(mut self, queries: Arc<QueryAnalytics>) -> Self {
+        self.queries = queries;
+        self
+    }
+
+    /// Generate the knowledge gaps report of a tenant, or of every tenant
+    pub fn generate_knowledge_gaps(
+        &self,
+        tenant_id: Option<&str>,
+        time_range: DashboardTimeRange,
+    ) -> KnowledgeGapReport {
+        self.queries.report(tenant_id, time_range.start_time())
+    }
+
     /// Generate overview dashboard
     pub fn generate_overview(&self, time_range: DashboardTimeRange) -> OverviewDashboard {
         let counts = self.analytics.count_by_type(None);
@@ -414,6 +432,61 @@ impl GrafanaDashboardGenerator {
         })
     }
 
+    /// Generate Grafana dashboard JSON for question answering quality
+    pub fn generate_knowledge_gaps_dashboard() -> serde_json::Value {
+        serde_json::json!({
+            "title": "CoPilot Agent - Knowledge Gaps",
+            "uid": "copilot-knowledge-gaps",
+            "editable": true,
+            "panels": [
+                {
+                    "title": "Questions",
+                    "type": "stat",
+                    "gridPos": { "h": 4, "w": 8, "x": 0, "y": 0 },
+                    "targets": [{
+                        "expr": "sum(increase(copilot_queries_total[24h]))",
+                        "legendFormat": "Questions (24h)"
+                    }]
+                },
+                {
+                    "title": "Uncited Answers",
+                    "type": "stat",
+                    "gridPos": { "h": 4, "w": 8, "x": 8, "y": 0 },
+                    "targets": [{
+                        "expr": "sum(increase(copilot_queries_uncited_total[24h])) / sum(increase(copilot_queries_total[24h])) * 100",
+                        "legendFormat": "Uncited %"
+                    }]
+                },
+                {
+                    "title": "Low-Confidence Answers",
+                    "type": "stat",
+                    "gridPos": { "h": 4, "w": 8, "x": 16, "y": 0 },
+                    "targets": [{
+                        "expr": "sum(increase(copilot_queries_low_confidence_total[24h])) / sum(increase(copilot_queries_total[24h])) * 100",
+                        "legendFormat": "Low confidence %"
+                    }]
+                },
+                {
+                    "title": "Unanswered Questions",
+                    "type": "timeseries",
+                    "gridPos": { "h": 8, "w": 24, "x": 0, "y": 4 },
+                    "targets": [
+                        {
+                            "expr": "sum(rate(copilot_queries_uncited_total[15m]))",
+                            "legendFormat": "Uncited/s"
+                        },
+                        {
+                            "expr": "sum(rate(copilot_queries_low_confidence_total[15m]))",
+                            "legendFormat": "Low confidence/s"
+                        }
+                    ]
+                }
+            ],
+            "time": { "from": "now-7d", "to": "now" },
+            "refresh": "5m"
+        })
+    }
+
     /// Generate Grafana dashboard JSON for tenant
     pub fn generate_tenant_dashboard(tenant_id: &str) -> serde_json::Value {
         serde_json::json!({
@@ -610,5 +683,9 @@ mod tests {
 
         let tenant_dashboard = service.generate_tenant_dashboard("tenant-1", DashboardTimeRange::Last7Days);
         assert_eq!(tenant_dashboard.tenant_id, "tenant-1");
+
+        let gaps = service.generate_knowledge_gaps(Some("tenant-1"), DashboardTimeRange::Last7Days);
+        assert_eq!(gaps.total_queries, 0);
+        assert!(GrafanaDashboardGenerator::generate_knowledge_gaps_dashboard()["panels"].as_array().is_some());
     }
 }
@@ -6,18 +6,27 @@
 //! - Custom business metrics
 //! - Analytics dashboards data
 //! - SLA monitoring
+//! - Query topics and knowledge gaps in the corpus
+//! - Conversation intents, resolution and escalation rates
+//! - Latency, candidates and tokens saved per context pipeline stage
 
 pub mod tracing_setup;
 pub mod correlation;
 pub mod analytics;
 pub mod sla;
 pub mod dashboards;
+pub mod query_analytics;
+pub mod conversation_analytics;
+pub mod context_pipeline;
 
 pub use tracing_setup::*;
 pub use correlation::*;
 pub use analytics::*;
 pub use sla::*;
 pub use dashboards::*;
+pub use query_analytics::*;
+pub use conversation_analytics::*;
+pub use context_pipeline::*;
 
 use thiserror::Error;
 
@@ -0,0 +1,442 @@
+//! Query topics and knowledge gaps
+//!
+//! Records the questions users ask and how well they were answered: the
+//! groundedness score of the answer, when answers are scored, and how many
+//! sources it drew on. Questions are clustered into topics by their content
+//! words, and the topics whose questions often go unanswered (answered with
+//! low confidence or without any source) make up the knowledge gaps report,
+//! which tells documentation teams what content to add.
+//!
+//! Question text is kept only as far as the tenant's [`PromptLogging`] mode
+//! allows: under `Full` a question's words feed its topic and it may be
+//! quoted as an example; under `Hashed` and `MetadataOnly` it is counted
+//! without a topic; under `Off` it is not recorded at all.
+
+use chrono::{DateTime, Utc};
+use copilot_core::PromptLogging;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+/// Words too common to say what a question is about
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "are", "was", "were", "this", "that", "these", "those", "with", "from", "into", "about",
+    "what", "which", "who", "whom", "why", "how", "when", "where", "does", "did", "can", "could", "should",
+    "would", "will", "shall", "may", "might", "must", "have", "has", "had", "our", "your", "you", "their",
+    "there", "here", "its", "not", "any", "all", "some", "get", "use", "using", "need", "want", "please",
+    "tell", "show", "explain", "way", "best", "between", "than", "then", "also", "just", "more", "most",
+];
+
+/// How a question was answered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryOutcome {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub query: String,
+    /// Groundedness score of the answer, 0..=1, if it was scored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// Sources the answer drew on
+    pub citations: usize,
+    pub asked_at: DateTime<Utc>,
+}
+
+impl QueryOutcome {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            tenant_id: None,
+            query: query.into(),
+            confidence: None,
+            citations: 0,
+            asked_at: Utc::now(),
+        }
+    }
+
+    pub fn with_tenant(mut self, tenant_id: impl Into<String>) -> Self {
+        self.tenant_id = Some(tenant_id.into());
+        self
+    }
+
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = Some(confidence);
+        self
+    }
+
+    pub fn with_citations(mut self, citations: usize) -> Self {
+        self.citations = citations;
+        self
+    }
+}
+
+/// Query analytics settings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QueryAnalyticsConfig {
+    /// Confidence below which an answer counts as low-confidence
+    pub min_confidence: f64,
+    /// Share of a question's words a topic's top words must cover for the
+    /// question to join it
+    pub topic_overlap: f64,
+    /// Questions kept; the oldest are dropped first
+    pub max_queries: usize,
+    /// Unanswered questions quoted per topic
+    pub max_examples: usize,
+}
+
+impl Default for QueryAnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            min_confidence: 0.5,
+            topic_overlap: 0.5,
+            max_queries: 50_000,
+            max_examples: 5,
+        }
+    }
+}
+
+/// Questions about one topic and how well they were answered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryTopic {
+    /// The topic's most frequent words
+    pub label: String,
+    pub terms: Vec<String>,
+    pub queries: u64,
+    /// Questions answered with low confidence or without sources
+    pub unanswered: u64,
+    pub low_confidence: u64,
+    pub uncited: u64,
+    /// Mean confidence of the scored answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mean_confidence: Option<f64>,
+    /// Recent unanswered questions, newest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<String>,
+}
+
+impl QueryTopic {
+    /// Share of the topic's questions that went unanswered
+    pub fn unanswered_rate(&self) -> f64 {
+        if self.queries == 0 {
+            0.0
+        } else {
+            self.unanswered as f64 / self.queries as f64
+        }
+    }
+}
+
+/// Topics users ask about and the ones the corpus does not cover
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnowledgeGapReport {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub since: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    pub total_queries: u64,
+    pub unanswered_queries: u64,
+    /// Questions whose text the tenant does not retain, so have no topic
+    pub untopical_queries: u64,
+    /// Topics by number of questions
+    pub topics: Vec<QueryTopic>,
+    /// Topics with unanswered questions, most unanswered first
+    pub gaps: Vec<QueryTopic>,
+}
+
+/// Counters of questions recorded so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryAnalyticsStats {
+    pub queries: u64,
+    pub low_confidence: u64,
+    pub uncited: u64,
+}
+
+impl QueryAnalyticsStats {
+    /// Prometheus text exposition of the counters
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        for (name, help, value) in [
+            ("queries_total", "Questions answered", self.queries),
+            ("queries_low_confidence_total", "Questions answered with low confidence", self.low_confidence),
+            ("queries_uncited_total", "Questions answered without sources", self.uncited),
+        ] {
+            let _ = writeln!(out, "# HELP {}_{} {}", prefix, name, help);
+            let _ = writeln!(out, "# TYPE {}_{} counter", prefix, name);
+            let _ = writeln!(out, "{}_{} {}", prefix, name, value);
+        }
+        out
+    }
+}
+
+struct QueryRecord {
+    tenant_id: Option<String>,
+    /// The question, if it may be quoted
+    text: Option<String>,
+    terms: Vec<String>,
+    confidence: Option<f64>,
+    citations: usize,
+    asked_at: DateTime<Utc>,
+}
+
+/// Content words of a question, lowercased and without plural `s`
+pub fn query_terms(query: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|word| word.chars().count() > 2 && !STOPWORDS.contains(&word.as_str()))
+        .map(|word| match word.strip_suffix('s') {
+            Some(stem) if stem.chars().count() > 2 && !stem.ends_with('s') => stem.to_string(),
+            _ => word,
+        })
+        .filter(|term| seen.insert(term.clone()))
+        .collect()
+}
+
+/// A topic being assembled
+#[derive(Default)]
+struct Cluster {
+    term_counts: HashMap<String, u64>,
+    queries: u64,
+    unanswered: u64,
+    low_confidence: u64,
+    uncited: u64,
+    confidence_sum: f64,
+    scored: u64,
+    examples: Vec<(DateTime<Utc>, String)>,
+}
+
+impl Cluster {
+    /// The cluster's most frequent terms, ties broken alphabetically
+    fn top_terms(&self, n: usize) -> Vec<String> {
+        let mut terms: Vec<(&String, &u64)> = self.term_counts.iter().collect();
+        terms.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        terms.into_iter().take(n).map(|(term, _)| term.clone()).collect()
+    }
+
+    /// Share of `terms` among the cluster's top terms
+    fn overlap(&self, terms: &[String]) -> f64 {
+        let top = self.top_terms(5);
+        let shared = terms.iter().filter(|term| top.contains(term)).count();
+        shared as f64 / terms.len().min(top.len()).max(1) as f64
+    }
+
+    fn into_topic(mut self, max_examples: usize) -> QueryTopic {
+        let terms = self.top_terms(5);
+        self.examples.sort_by(|a, b| b.0.cmp(&a.0));
+        let mut examples: Vec<String> = Vec::new();
+        for (_, text) in self.examples {
+            if examples.len() < max_examples && !examples.contains(&text) {
+                examples.push(text);
+            }
+        }
+        QueryTopic {
+            label: terms.iter().take(3).cloned().collect::<Vec<_>>().join(" "),
+            terms,
+            queries: self.queries,
+            unanswered: self.unanswered,
+            low_confidence: self.low_confidence,
+            uncited: self.uncited,
+            mean_confidence: (self.scored > 0).then(|| self.confidence_sum / self.scored as f64),
+            examples,
+        }
+    }
+}
+
+/// Question outcomes, clustered into topics on demand
+#[derive(Default)]
+pub struct QueryAnalytics {
+    config: QueryAnalyticsConfig,
+    records: RwLock<VecDeque<QueryRecord>>,
+    stats: RwLock<QueryAnalyticsStats>,
+}
+
+impl QueryAnalytics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: QueryAnalyticsConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn config(&self) -> &QueryAnalyticsConfig {
+        &self.config
+    }
+
+    fn is_low_confidence(&self, confidence: Option<f64>) -> bool {
+        confidence.is_some_and(|confidence| confidence < self.config.min_confidence)
+    }
+
+    /// Record how a question was answered, retaining its text as `mode`
+    /// allows
+    pub fn record(&self, outcome: QueryOutcome, mode: PromptLogging) {
+        if !mode.records_prompts() {
+            return;
+        }
+        let low_confidence = self.is_low_confidence(outcome.confidence);
+        {
+            let mut stats = self.stats.write();
+            stats.queries += 1;
+            stats.low_confidence += low_confidence as u64;
+            stats.uncited += (outcome.citations == 0) as u64;
+        }
+
+        let full = mode == PromptLogging::Full;
+        let record = QueryRecord {
+            tenant_id: outcome.tenant_id,
+            terms: if full { query_terms(&outcome.query) } else { Vec::new() },
+            text: full.then_some(outcome.query),
+            confidence: outcome.confidence,
+            citations: outcome.citations,
+            asked_at: outcome.asked_at,
+        };
+        let mut records = self.records.write();
+        records.push_back(record);
+        while records.len() > self.config.max_queries {
+            records.pop_front();
+        }
+    }
+
+    pub fn stats(&self) -> QueryAnalyticsStats {
+        *self.stats.read()
+    }
+
+    /// Topics and knowledge gaps of the questions asked since `since`, in
+    /// `tenant_id` or in every tenant
+    pub fn report(&self, tenant_id: Option<&str>, since: DateTime<Utc>) -> KnowledgeGapReport {
+        let records = self.records.read();
+        let mut clusters: Vec<Cluster> = Vec::new();
+        let (mut total, mut unanswered, mut untopical) = (0, 0, 0);
+
+        for record in records.iter() {
+            if record.asked_at < since || tenant_id.is_some_and(|t| record.tenant_id.as_deref() != Some(t)) {
+                continue;
+            }
+            let low_confidence = self.is_low_confidence(record.confidence);
+            let uncited = record.citations == 0;
+            total += 1;
+            unanswered += (low_confidence || uncited) as u64;
+            if record.terms.is_empty() {
+                untopical += 1;
+                continue;
+            }
+
+            let best = clusters
+                .iter()
+                .enumerate()
+                .map(|(i, cluster)| (i, cluster.overlap(&record.terms)))
+                .filter(|(_, overlap)| *overlap >= self.config.topic_overlap)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            let cluster = match best {
+                Some((i, _)) => &mut clusters[i],
+                None => {
+                    clusters.push(Cluster::default());
+                    clusters.last_mut().unwrap()
+                }
+            };
+            for term in &record.terms {
+                *cluster.term_counts.entry(term.clone()).or_default() += 1;
+            }
+            cluster.queries += 1;
+            cluster.unanswered += (low_confidence || uncited) as u64;
+            cluster.low_confidence += low_confidence as u64;
+            cluster.uncited += uncited as u64;
+            if let Some(confidence) = record.confidence {
+                cluster.confidence_sum += confidence;
+                cluster.scored += 1;
+            }
+            if low_confidence || uncited {
+                if let Some(text) = &record.text {
+                    cluster.examples.push((record.asked_at, text.clone()));
+                }
+            }
+        }
+
+        let mut topics: Vec<QueryTopic> = clusters
+            .into_iter()
+            .map(|cluster| cluster.into_topic(self.config.max_examples))
+            .collect();
+        topics.sort_by(|a, b| b.queries.cmp(&a.queries).then_with(|| a.label.cmp(&b.label)));
+
+        let mut gaps: Vec<QueryTopic> = topics.iter().filter(|t| t.unanswered > 0).cloned().collect();
+        gaps.sort_by(|a, b| {
+            b.unanswered
+                .cmp(&a.unanswered)
+                .then_with(|| b.unanswered_rate().total_cmp(&a.unanswered_rate()))
+                .then_with(|| a.label.cmp(&b.label))
+        });
+
+        KnowledgeGapReport {
+            tenant_id: tenant_id.map(str::to_string),
+            since,
+            generated_at: Utc::now(),
+            total_queries: total,
+            unanswered_queries: unanswered,
+            untopical_queries: untopical,
+            topics,
+            gaps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_query_terms() {
+        assert_eq!(
+            query_terms("How do I rotate the API keys for our Postgres databases?"),
+            vec!["rotate", "api", "key", "postgre", "database"]
+        );
+        assert_eq!(query_terms("What is it?"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_topics_and_knowledge_gaps() {
+        let analytics = QueryAnalytics::new();
+        let since = Utc::now() - Duration::days(1);
+        let asked = |query: &str| QueryOutcome::new(query).with_tenant("acme");
+
+        analytics.record(asked("How do I rotate database credentials?").with_citations(2), PromptLogging::Full);
+        analytics.record(asked("Rotate credentials of the billing database").with_citations(0), PromptLogging::Full);
+        analytics.record(
+            asked("When are database credentials rotated?").with_citations(1).with_confidence(0.2),
+            PromptLogging::Full,
+        );
+        analytics.record(asked("Configure the VPN client").with_citations(3).with_confidence(0.9), PromptLogging::Full);
+        // Counted, but its words are not kept
+        analytics.record(asked("Configure the VPN on Linux").with_citations(0), PromptLogging::Hashed);
+        analytics.record(asked("Secret question").with_citations(0), PromptLogging::Off);
+        analytics.record(QueryOutcome::new("Rotate database credentials").with_tenant("globex"), PromptLogging::Full);
+
+        let report = analytics.report(Some("acme"), since);
+        assert_eq!((report.total_queries, report.unanswered_queries, report.untopical_queries), (5, 3, 1));
+        assert_eq!(report.topics.len(), 2);
+
+        let credentials = &report.topics[0];
+        assert_eq!(credentials.queries, 3);
+        assert_eq!(credentials.label, "credential database rotate");
+        assert_eq!((credentials.unanswered, credentials.low_confidence, credentials.uncited), (2, 1, 1));
+        assert_eq!(credentials.mean_confidence, Some(0.2));
+        assert_eq!(
+            credentials.examples,
+            vec!["When are database credentials rotated?", "Rotate credentials of the billing database"]
+        );
+
+        // The VPN topic's only unanswered question is not quoted or topical
+        assert_eq!(report.gaps.len(), 1);
+        assert_eq!(report.gaps[0].label, credentials.label);
+
+        assert_eq!(analytics.report(None, since).total_queries, 6);
+        assert_eq!(analytics.report(Some("acme"), Utc::now() + Duration::hours(1)).total_queries, 0);
+        assert_eq!(
+            analytics.stats(),
+            QueryAnalyticsStats { queries: 6, low_confidence: 1, uncited: 3 }
+        );
+        assert!(analytics.stats().render_prometheus("copilot").contains("copilot_queries_uncited_total 3"));
+    }
+}
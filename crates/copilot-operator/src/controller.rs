@@ -0,0 +1,456 @@
+//! Reconciles custom resources with the workflow engine
+//!
+//! One loop per resource kind lists the resources, loads them, then follows a
+//! watch until it can no longer be resumed (e.g. the resource version
+//! expired) and starts over with a fresh list. Statuses are only patched when
+//! they change, so the watch events caused by the operator's own patches
+//! settle immediately.
+
+use async_trait::async_trait;
+use copilot_workflow::triggers::{InMemoryTriggerRepository, TriggerWorkflowProvider};
+use copilot_workflow::{TriggerEvent, TriggerManager, WorkflowDefinition, WorkflowEngine, WorkflowTrigger};
+use futures::StreamExt;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+use crate::crd::{
+    Resource, ResourceKind, RunStatus, SyncPhase, SyncStatus, TriggerSpec, WorkflowSpec, TRIGGERS, WORKFLOWS,
+};
+use crate::kube::{KubeClient, WatchEvent};
+use crate::{OperatorError, Result};
+
+/// How often run status is written back to `Workflow` resources
+const RUN_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Delay before relisting after a failed list or watch
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Workflow definitions loaded from `Workflow` resources
+#[derive(Default)]
+struct SyncedWorkflows {
+    definitions: RwLock<HashMap<String, WorkflowDefinition>>,
+}
+
+#[async_trait]
+impl TriggerWorkflowProvider for SyncedWorkflows {
+    async fn get_workflow(&self, workflow_id: &str) -> copilot_workflow::Result<Option<WorkflowDefinition>> {
+        Ok(self.definitions.read().await.get(workflow_id).cloned())
+    }
+}
+
+/// Latest run of a workflow and the status last written for it
+struct TrackedRun {
+    execution_id: String,
+    reported: Option<RunStatus>,
+}
+
+/// Syncs `Workflow` and `Trigger` resources into a workflow engine
+pub struct Operator {
+    kube: KubeClient,
+    /// Watched namespace; all namespaces when `None`
+    namespace: Option<String>,
+    engine: Arc<WorkflowEngine>,
+    workflows: Arc<SyncedWorkflows>,
+    triggers: TriggerManager,
+    /// Last seen `Trigger` resources, re-synced when their workflow changes
+    trigger_resources: RwLock<HashMap<String, Resource<Value>>>,
+    /// Keyed by workflow key
+    runs: RwLock<HashMap<String, TrackedRun>>,
+}
+
+impl Operator {
+    /// Create an operator that runs workflows on `engine`
+    pub fn new(kube: KubeClient, engine: Arc<WorkflowEngine>) -> Self {
+        let workflows = Arc::new(SyncedWorkflows::default());
+        let triggers = TriggerManager::new(
+            Arc::new(InMemoryTriggerRepository::new()),
+            engine.clone(),
+            workflows.clone(),
+        );
+        Self {
+            kube,
+            namespace: None,
+            engine,
+            workflows,
+            triggers,
+            trigger_resources: RwLock::new(HashMap::new()),
+            runs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Only watch resources in `namespace`
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Sync resources and report runs until the task is dropped
+    pub async fn run(self: Arc<Self>) {
+        tokio::join!(
+            self.sync_loop(WORKFLOWS),
+            self.sync_loop(TRIGGERS),
+            self.report_loop(),
+        );
+    }
+
+    /// Start the workflows whose triggers match `event`; returns the
+    /// execution IDs
+    pub async fn handle_event(&self, event: TriggerEvent) -> Result<Vec<String>> {
+        let execution_ids = self.triggers.process_event(event).await?;
+        let mut runs = self.runs.write().await;
+        for execution_id in &execution_ids {
+            let state = self.engine.get_status(execution_id).await?;
+            runs.insert(
+                state.workflow_id,
+                TrackedRun {
+                    execution_id: execution_id.clone(),
+                    reported: None,
+                },
+            );
+        }
+        Ok(execution_ids)
+    }
+
+    /// Load a `Workflow` resource and report the outcome in its status
+    pub async fn apply_workflow(&self, resource: &Resource<Value>) {
+        let key = resource.key();
+        let status = match resource.parse::<WorkflowSpec>().and_then(|r| r.to_definition()) {
+            Ok(definition) => {
+                self.workflows.definitions.write().await.insert(key.clone(), definition);
+                debug!(workflow = %key, "Synced workflow");
+                SyncStatus::new(resource, SyncPhase::Ready, None)
+            }
+            Err(e) => {
+                warn!(workflow = %key, error = %e, "Rejected workflow");
+                self.workflows.definitions.write().await.remove(&key);
+                SyncStatus::new(resource, SyncPhase::Invalid, Some(e.to_string()))
+            }
+        };
+        self.write_status(WORKFLOWS, resource, &status).await;
+        self.resync_triggers_of(&key).await;
+    }
+
+    /// Forget a deleted `Workflow`
+    pub async fn remove_workflow(&self, key: &str) {
+        self.workflows.definitions.write().await.remove(key);
+        self.runs.write().await.remove(key);
+        info!(workflow = %key, "Removed workflow");
+        self.resync_triggers_of(key).await;
+    }
+
+    /// Register a `Trigger` resource and report the outcome in its status
+    pub async fn apply_trigger(&self, resource: &Resource<Value>) {
+        let key = resource.key();
+        self.trigger_resources.write().await.insert(key.clone(), resource.clone());
+
+        let status = match resource.parse::<TriggerSpec>().and_then(|r| r.to_trigger()) {
+            Ok(trigger) => {
+                let (phase, message) = self.trigger_phase(&trigger).await;
+                self.triggers
+                    .create(trigger)
+                    .await
+                    .map(|_| SyncStatus::new(resource, phase, message))
+                    .map_err(OperatorError::from)
+            }
+            Err(e) => Err(e),
+        };
+        let status = status.unwrap_or_else(|e| {
+            warn!(trigger = %key, error = %e, "Rejected trigger");
+            SyncStatus::new(resource, SyncPhase::Invalid, Some(e.to_string()))
+        });
+        if status.phase == SyncPhase::Invalid {
+            let _ = self.triggers.delete(&key).await;
+        }
+        self.write_status(TRIGGERS, resource, &status).await;
+    }
+
+    /// Forget a deleted `Trigger`
+    pub async fn remove_trigger(&self, key: &str) {
+        self.trigger_resources.write().await.remove(key);
+        if let Err(e) = self.triggers.delete(key).await {
+            warn!(trigger = %key, error = %e, "Failed to remove trigger");
+        }
+        info!(trigger = %key, "Removed trigger");
+    }
+
+    /// Write the status of runs that changed since the last report
+    pub async fn report_runs(&self) {
+        let pending: Vec<(String, String, Option<RunStatus>)> = self
+            .runs
+            .read()
+            .await
+            .iter()
+            .filter(|(_, run)| !run.reported.as_ref().is_some_and(RunStatus::is_terminal))
+            .map(|(key, run)| (key.clone(), run.execution_id.clone(), run.reported.clone()))
+            .collect();
+
+        for (key, execution_id, reported) in pending {
+            let status = match self.engine.get_status(&execution_id).await {
+                Ok(state) => RunStatus::from(&state),
+                Err(e) => {
+                    warn!(workflow = %key, execution_id = %execution_id, error = %e, "Failed to read run status");
+                    continue;
+                }
+            };
+            if reported.as_ref() == Some(&status) {
+                continue;
+            }
+            let Some((namespace, name)) = key.split_once('/') else {
+                continue;
+            };
+            let patch = serde_json::json!({ "lastRun": status });
+            if let Err(e) = self.kube.patch_status(WORKFLOWS, namespace, name, &patch).await {
+                warn!(workflow = %key, error = %e, "Failed to write run status");
+                continue;
+            }
+            if let Some(run) = self.runs.write().await.get_mut(&key) {
+                if run.execution_id == execution_id {
+                    run.reported = Some(status);
+                }
+            }
+        }
+    }
+
+    async fn trigger_phase(&self, trigger: &WorkflowTrigger) -> (SyncPhase, Option<String>) {
+        if !trigger.enabled {
+            (SyncPhase::Disabled, None)
+        } else if self.workflows.definitions.read().await.contains_key(&trigger.workflow_id) {
+            (SyncPhase::Ready, None)
+        } else {
+            (
+                SyncPhase::Pending,
+                Some(format!("Workflow {} is not ready", trigger.workflow_id)),
+            )
+        }
+    }
+
+    /// Re-apply the triggers that run `workflow_key`, so their phase follows
+    /// the workflow's
+    async fn resync_triggers_of(&self, workflow_key: &str) {
+        let resources: Vec<Resource<Value>> = self
+            .trigger_resources
+            .read()
+            .await
+            .values()
+            .filter(|r| {
+                r.parse::<TriggerSpec>()
+                    .is_ok_and(|r| r.workflow_key() == workflow_key)
+            })
+            .cloned()
+            .collect();
+        for resource in resources {
+            self.apply_trigger(&resource).await;
+        }
+    }
+
+    async fn write_status(&self, kind: ResourceKind, resource: &Resource<Value>, status: &SyncStatus) {
+        if resource.has_status(status) {
+            return;
+        }
+        let result = match serde_json::to_value(status) {
+            Ok(status) => {
+                self.kube
+                    .patch_status(kind, &resource.metadata.namespace, &resource.metadata.name, &status)
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            warn!(kind = kind.kind, resource = %resource.key(), error = %e, "Failed to write status");
+        }
+    }
+
+    async fn apply(&self, kind: ResourceKind, resource: &Resource<Value>) {
+        if kind == WORKFLOWS {
+            self.apply_workflow(resource).await
+        } else {
+            self.apply_trigger(resource).await
+        }
+    }
+
+    async fn remove(&self, kind: ResourceKind, key: &str) {
+        if kind == WORKFLOWS {
+            self.remove_workflow(key).await
+        } else {
+            self.remove_trigger(key).await
+        }
+    }
+
+    async fn synced_keys(&self, kind: ResourceKind) -> Vec<String> {
+        if kind == WORKFLOWS {
+            self.workflows.definitions.read().await.keys().cloned().collect()
+        } else {
+            self.trigger_resources.read().await.keys().cloned().collect()
+        }
+    }
+
+    async fn sync_loop(&self, kind: ResourceKind) {
+        loop {
+            if let Err(e) = self.sync(kind).await {
+                warn!(kind = kind.kind, error = %e, "Sync interrupted; relisting");
+            }
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+    }
+
+    /// List all resources of `kind`, then follow changes until the watch
+    /// fails
+    async fn sync(&self, kind: ResourceKind) -> Result<()> {
+        let namespace = self.namespace.as_deref();
+        let list = self.kube.list::<Value>(kind, namespace).await?;
+
+        let listed: HashSet<String> = list.items.iter().map(Resource::key).collect();
+        for key in self.synced_keys(kind).await {
+            if !listed.contains(&key) {
+                self.remove(kind, &key).await;
+            }
+        }
+        for resource in &list.items {
+            self.apply(kind, resource).await;
+        }
+        info!(kind = kind.kind, count = list.items.len(), "Synced resources");
+
+        let mut version = list.metadata.resource_version.unwrap_or_default();
+        loop {
+            let events = self.kube.watch::<Value>(kind, namespace, &version).await?;
+            futures::pin_mut!(events);
+            while let Some(event) = events.next().await {
+                match event? {
+                    WatchEvent::Added(resource) | WatchEvent::Modified(resource) => {
+                        version = resource.metadata.resource_version.clone().unwrap_or(version);
+                        self.apply(kind, &resource).await;
+                    }
+                    WatchEvent::Deleted(resource) => {
+                        version = resource.metadata.resource_version.clone().unwrap_or(version);
+                        self.remove(kind, &resource.key()).await;
+                    }
+                    WatchEvent::Bookmark(bookmark) => {
+                        version = bookmark.metadata.resource_version.unwrap_or(version);
+                    }
+                    WatchEvent::Error(status) => {
+                        return Err(OperatorError::Api {
+                            status: status.code.unwrap_or(500),
+                            message: status.message,
+                        });
+                    }
+                }
+            }
+            debug!(kind = kind.kind, "Watch closed; resuming");
+        }
+    }
+
+    async fn report_loop(&self) {
+        loop {
+            self.report_runs().await;
+            tokio::time::sleep(RUN_POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{Path, State};
+    use axum::routing::patch;
+    use axum::{Json, Router};
+    use copilot_workflow::{EventSource, WorkflowStatus};
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    type Patches = Arc<Mutex<Vec<(String, Value)>>>;
+
+    /// API server that records status patches
+    async fn fake_api() -> (KubeClient, Patches) {
+        let patches: Patches = Arc::default();
+        let app = Router::new()
+            .route(
+                "/apis/:group/:version/namespaces/:namespace/:plural/:name/status",
+                patch(
+                    |State(patches): State<Patches>,
+                     Path((_, _, _, plural, name)): Path<(String, String, String, String, String)>,
+                     Json(body): Json<Value>| async move {
+                        patches.lock().unwrap().push((format!("{}/{}", plural, name), body["status"].clone()));
+                        Json(json!({}))
+                    },
+                ),
+            )
+            .with_state(patches.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (KubeClient::new(format!("http://{}", addr)), patches)
+    }
+
+    fn workflow(steps: Value, status: Option<Value>) -> Resource<Value> {
+        serde_json::from_value(json!({
+            "metadata": { "name": "deploy", "namespace": "team-a", "generation": 2 },
+            "spec": { "steps": steps },
+            "status": status
+        }))
+        .unwrap()
+    }
+
+    fn trigger() -> Resource<Value> {
+        serde_json::from_value(json!({
+            "metadata": { "name": "on-push", "namespace": "team-a", "generation": 1 },
+            "spec": { "workflow": "deploy", "condition": { "type": "event_type", "value": "git.push" } }
+        }))
+        .unwrap()
+    }
+
+    fn wait_step() -> Value {
+        json!([{ "id": "wait", "name": "Wait", "step_type": "wait", "action": { "type": "wait", "duration_secs": 0 } }])
+    }
+
+    #[tokio::test]
+    async fn test_workflow_status_follows_spec() {
+        let (kube, patches) = fake_api().await;
+        let operator = Operator::new(kube, Arc::new(WorkflowEngine::new()));
+
+        operator.apply_workflow(&workflow(json!([]), None)).await;
+        operator.apply_workflow(&workflow(wait_step(), None)).await;
+        // Already reported; nothing to patch
+        let synced = json!({ "observedGeneration": 2, "phase": "Ready", "message": null });
+        operator.apply_workflow(&workflow(wait_step(), Some(synced))).await;
+
+        let patches = patches.lock().unwrap().clone();
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].0, "workflows/deploy");
+        assert_eq!(patches[0].1["phase"], "Invalid");
+        assert_eq!(patches[0].1["message"], "Workflow error: Invalid workflow definition: Workflow has no steps");
+        assert_eq!(patches[1].1, json!({ "observedGeneration": 2, "phase": "Ready", "message": null }));
+    }
+
+    #[tokio::test]
+    async fn test_trigger_runs_workflow_and_reports_run() {
+        let (kube, patches) = fake_api().await;
+        let operator = Operator::new(kube, Arc::new(WorkflowEngine::new()));
+
+        // The trigger waits for its workflow
+        operator.apply_trigger(&trigger()).await;
+        assert_eq!(patches.lock().unwrap()[0].1["phase"], "Pending");
+        operator.apply_workflow(&workflow(wait_step(), None)).await;
+        assert_eq!(patches.lock().unwrap().last().unwrap().0, "triggers/on-push");
+        assert_eq!(patches.lock().unwrap().last().unwrap().1["phase"], "Ready");
+
+        let event = TriggerEvent::new("git.push", EventSource::Webhook, json!({}));
+        let execution_ids = operator.handle_event(event).await.unwrap();
+        assert_eq!(execution_ids.len(), 1);
+
+        for _ in 0..50 {
+            operator.report_runs().await;
+            let last = patches.lock().unwrap().last().cloned().unwrap();
+            if last.1["lastRun"]["status"] == json!(WorkflowStatus::Completed) {
+                assert_eq!(last.0, "workflows/deploy");
+                assert_eq!(last.1["lastRun"]["executionId"], json!(execution_ids[0]));
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("run status was not reported");
+    }
+}
@@ -0,0 +1,311 @@
+//! Workflow and Trigger custom resources
+//!
+//! Specs use the same JSON layout as the workflow engine's definitions and
+//! triggers, so a definition exported from the API can be pasted into a
+//! resource unchanged. Statuses follow Kubernetes conventions (camelCase,
+//! `observedGeneration`, a `phase`).
+
+use chrono::{DateTime, Utc};
+use copilot_workflow::triggers::RateLimit;
+use copilot_workflow::{TriggerCondition, WorkflowDefinition, WorkflowState, WorkflowStatus, WorkflowStep, WorkflowTrigger};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{OperatorError, Result};
+
+/// API group of the custom resources
+pub const GROUP: &str = "copilot.llm-devops.io";
+
+/// API version of the custom resources
+pub const VERSION: &str = "v1alpha1";
+
+/// A namespaced custom resource type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceKind {
+    pub kind: &'static str,
+    /// Plural name used in API paths
+    pub plural: &'static str,
+}
+
+/// `Workflow` resources
+pub const WORKFLOWS: ResourceKind = ResourceKind {
+    kind: "Workflow",
+    plural: "workflows",
+};
+
+/// `Trigger` resources
+pub const TRIGGERS: ResourceKind = ResourceKind {
+    kind: "Trigger",
+    plural: "triggers",
+};
+
+/// The metadata fields the operator reads
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectMeta {
+    pub name: String,
+    #[serde(default)]
+    pub namespace: String,
+    #[serde(default)]
+    pub resource_version: Option<String>,
+    #[serde(default)]
+    pub generation: Option<i64>,
+    #[serde(default)]
+    pub uid: Option<String>,
+}
+
+/// A custom resource as returned by the API server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resource<S> {
+    pub metadata: ObjectMeta,
+    pub spec: S,
+    #[serde(default)]
+    pub status: Option<Value>,
+}
+
+impl<S> Resource<S> {
+    /// `<namespace>/<name>`, the id the resource is synced under
+    pub fn key(&self) -> String {
+        format!("{}/{}", self.metadata.namespace, self.metadata.name)
+    }
+
+    /// Whether the resource's status already reports `status`
+    pub fn has_status(&self, status: &SyncStatus) -> bool {
+        self.status
+            .as_ref()
+            .and_then(|current| serde_json::from_value::<SyncStatus>(current.clone()).ok())
+            .is_some_and(|current| current == *status)
+    }
+}
+
+impl Resource<Value> {
+    /// Parse the spec of a resource read without a schema
+    pub fn parse<S: DeserializeOwned>(&self) -> Result<Resource<S>> {
+        Ok(Resource {
+            metadata: self.metadata.clone(),
+            spec: serde_json::from_value(self.spec.clone())
+                .map_err(|e| OperatorError::InvalidResource(format!("Invalid spec: {}", e)))?,
+            status: self.status.clone(),
+        })
+    }
+}
+
+/// Desired state of a `Workflow`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowSpec {
+    #[serde(default)]
+    pub description: String,
+    pub steps: Vec<WorkflowStep>,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub metadata: HashMap<String, Value>,
+}
+
+/// A `Workflow` resource
+pub type WorkflowResource = Resource<WorkflowSpec>;
+
+impl WorkflowResource {
+    /// Validated workflow definition with id `<namespace>/<name>`
+    pub fn to_definition(&self) -> Result<WorkflowDefinition> {
+        let mut definition = WorkflowDefinition::new(&self.metadata.name, &self.spec.description).with_id(self.key());
+        definition.steps = self.spec.steps.clone();
+        definition.timeout_secs = self.spec.timeout_secs;
+        definition.metadata = self.spec.metadata.clone();
+        definition
+            .metadata
+            .insert("namespace".to_string(), Value::String(self.metadata.namespace.clone()));
+        definition.validate()?;
+        Ok(definition)
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Desired state of a `Trigger`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerSpec {
+    /// Name of the `Workflow` to run, in the trigger's namespace
+    pub workflow: String,
+    pub condition: TriggerCondition,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Event paths to workflow input names
+    #[serde(default)]
+    pub input_mapping: HashMap<String, String>,
+    #[serde(default)]
+    pub static_inputs: Value,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimit>,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub priority: Option<i32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A `Trigger` resource
+pub type TriggerResource = Resource<TriggerSpec>;
+
+impl TriggerResource {
+    /// Key of the `Workflow` the trigger runs
+    pub fn workflow_key(&self) -> String {
+        format!("{}/{}", self.metadata.namespace, self.spec.workflow)
+    }
+
+    /// Engine trigger with id `<namespace>/<name>`
+    pub fn to_trigger(&self) -> Result<WorkflowTrigger> {
+        if self.spec.workflow.is_empty() {
+            return Err(OperatorError::InvalidResource("spec.workflow is required".to_string()));
+        }
+        let mut trigger = WorkflowTrigger::new(&self.metadata.name, &self.workflow_key(), self.spec.condition.clone());
+        trigger.id = self.key();
+        trigger.enabled = self.spec.enabled;
+        trigger.input_mapping = self.spec.input_mapping.clone();
+        trigger.static_inputs = self.spec.static_inputs.clone();
+        trigger.rate_limit = self.spec.rate_limit.clone();
+        trigger.tenant_id = self.spec.tenant_id.clone();
+        trigger.tags = self.spec.tags.clone();
+        if let Some(priority) = self.spec.priority {
+            trigger.priority = priority;
+        }
+        Ok(trigger)
+    }
+}
+
+/// Outcome of syncing a resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncPhase {
+    /// Loaded into the engine
+    Ready,
+    /// A trigger whose workflow does not exist (yet)
+    Pending,
+    /// A trigger with `enabled: false`
+    Disabled,
+    /// Rejected; see the status message
+    Invalid,
+}
+
+impl fmt::Display for SyncPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Sync fields of a resource's status
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub observed_generation: Option<i64>,
+    pub phase: SyncPhase,
+    /// Serialized as `null` when absent so a merge patch clears an old message
+    pub message: Option<String>,
+}
+
+impl SyncStatus {
+    pub fn new<S>(resource: &Resource<S>, phase: SyncPhase, message: Option<String>) -> Self {
+        Self {
+            observed_generation: resource.metadata.generation,
+            phase,
+            message,
+        }
+    }
+}
+
+/// Latest run of a `Workflow`, reported as `status.lastRun`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunStatus {
+    pub execution_id: String,
+    pub status: WorkflowStatus,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+impl From<&WorkflowState> for RunStatus {
+    fn from(state: &WorkflowState) -> Self {
+        Self {
+            execution_id: state.execution_id.clone(),
+            status: state.status.clone(),
+            started_at: state.started_at,
+            completed_at: state.completed_at,
+            error: state.error.clone(),
+        }
+    }
+}
+
+impl RunStatus {
+    /// Whether the run has finished
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self.status,
+            WorkflowStatus::Completed | WorkflowStatus::Failed | WorkflowStatus::Cancelled
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn workflow(steps: Value) -> WorkflowResource {
+        serde_json::from_value(json!({
+            "apiVersion": "copilot.llm-devops.io/v1alpha1",
+            "kind": "Workflow",
+            "metadata": { "name": "deploy", "namespace": "team-a", "generation": 3, "resourceVersion": "101" },
+            "spec": { "description": "Deploy the service", "steps": steps, "timeout_secs": 600 }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_workflow_to_definition() {
+        let resource = workflow(json!([
+            { "id": "wait", "name": "Wait", "step_type": "wait", "action": { "type": "wait", "duration_secs": 0 } }
+        ]));
+        let definition = resource.to_definition().unwrap();
+        assert_eq!(definition.id, "team-a/deploy");
+        assert_eq!(definition.name, "deploy");
+        assert_eq!(definition.timeout_secs, Some(600));
+        assert_eq!(definition.metadata["namespace"], "team-a");
+
+        assert!(workflow(json!([])).to_definition().is_err());
+
+        let ready = SyncStatus::new(&resource, SyncPhase::Ready, None);
+        assert!(!resource.has_status(&ready));
+        let synced = WorkflowResource {
+            status: Some(json!({ "observedGeneration": 3, "phase": "Ready", "message": null, "lastRun": {} })),
+            ..resource
+        };
+        assert!(synced.has_status(&ready));
+        assert!(!synced.has_status(&SyncStatus { observed_generation: Some(4), ..ready }));
+    }
+
+    #[test]
+    fn test_trigger_to_trigger() {
+        let resource: TriggerResource = serde_json::from_value(json!({
+            "metadata": { "name": "on-push", "namespace": "team-a" },
+            "spec": {
+                "workflow": "deploy",
+                "condition": { "type": "event_type", "value": "git.push" },
+                "input_mapping": { "ref": "branch" },
+                "priority": 5
+            }
+        }))
+        .unwrap();
+        let trigger = resource.to_trigger().unwrap();
+        assert_eq!(trigger.id, "team-a/on-push");
+        assert_eq!(trigger.workflow_id, "team-a/deploy");
+        assert!(trigger.enabled);
+        assert_eq!(trigger.priority, 5);
+        assert_eq!(trigger.input_mapping["ref"], "branch");
+    }
+}
@@ -0,0 +1,275 @@
+//! Minimal Kubernetes API client
+//!
+//! Covers what the operator needs for its custom resources: list, watch and
+//! merge-patching the status subresource. Inside a pod it authenticates with
+//! the service account token, which is re-read on every request because the
+//! kubelet rotates it.
+
+use futures::Stream;
+use reqwest::{Certificate, Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::crd::{Resource, ResourceKind, GROUP, VERSION};
+use crate::{OperatorError, Result};
+
+/// Where Kubernetes mounts a pod's service account credentials
+const SERVICE_ACCOUNT_DIR: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+
+/// How long the API server keeps a watch open before ending it
+const WATCH_TIMEOUT_SECS: u64 = 290;
+
+/// Bearer token for API requests
+#[derive(Clone)]
+enum Credentials {
+    None,
+    Token(String),
+    /// Path to a token file that is re-read per request
+    TokenFile(PathBuf),
+}
+
+/// Client for the operator's custom resources
+#[derive(Clone)]
+pub struct KubeClient {
+    base_url: String,
+    credentials: Credentials,
+    http: reqwest::Client,
+}
+
+/// Response of a list request
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResourceList<S> {
+    pub items: Vec<Resource<S>>,
+    #[serde(default)]
+    pub metadata: ListMeta,
+}
+
+/// Metadata of a list or bookmark
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListMeta {
+    #[serde(default)]
+    pub resource_version: Option<String>,
+}
+
+/// Object of a `BOOKMARK` watch event
+#[derive(Debug, Clone, Deserialize)]
+pub struct Bookmark {
+    pub metadata: ListMeta,
+}
+
+/// A `Status` object the API server returns on failure
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApiStatus {
+    #[serde(default)]
+    pub code: Option<u16>,
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub message: String,
+}
+
+/// One line of a watch stream
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", content = "object", rename_all = "UPPERCASE")]
+pub enum WatchEvent<S> {
+    Added(Resource<S>),
+    Modified(Resource<S>),
+    Deleted(Resource<S>),
+    Bookmark(Bookmark),
+    /// e.g. `410 Gone` when the requested resource version has expired
+    Error(ApiStatus),
+}
+
+impl KubeClient {
+    /// Client for `base_url` without credentials, e.g. `kubectl proxy` at
+    /// `http://127.0.0.1:8001`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            credentials: Credentials::None,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Authenticate with a bearer token
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.credentials = Credentials::Token(token.into());
+        self
+    }
+
+    /// Client for the cluster the pod runs in, using its service account
+    pub fn in_cluster() -> Result<Self> {
+        let host = std::env::var("KUBERNETES_SERVICE_HOST")
+            .map_err(|_| OperatorError::Config("KUBERNETES_SERVICE_HOST is not set; not running in a cluster?".to_string()))?;
+        let port = std::env::var("KUBERNETES_SERVICE_PORT").unwrap_or_else(|_| "443".to_string());
+        let host = if host.contains(':') { format!("[{}]", host) } else { host };
+
+        let ca_path = format!("{}/ca.crt", SERVICE_ACCOUNT_DIR);
+        let ca = std::fs::read(&ca_path)
+            .map_err(|e| OperatorError::Config(format!("Failed to read {}: {}", ca_path, e)))?;
+        let http = reqwest::Client::builder()
+            .add_root_certificate(Certificate::from_pem(&ca)?)
+            .build()?;
+
+        Ok(Self {
+            base_url: format!("https://{}:{}", host, port),
+            credentials: Credentials::TokenFile(PathBuf::from(format!("{}/token", SERVICE_ACCOUNT_DIR))),
+            http,
+        })
+    }
+
+    /// Path of a resource collection, cluster-wide when `namespace` is `None`
+    pub fn collection_path(kind: ResourceKind, namespace: Option<&str>) -> String {
+        match namespace {
+            Some(namespace) => format!("/apis/{}/{}/namespaces/{}/{}", GROUP, VERSION, namespace, kind.plural),
+            None => format!("/apis/{}/{}/{}", GROUP, VERSION, kind.plural),
+        }
+    }
+
+    /// List resources of `kind`
+    pub async fn list<S: DeserializeOwned>(&self, kind: ResourceKind, namespace: Option<&str>) -> Result<ResourceList<S>> {
+        let response = self
+            .request(Method::GET, &Self::collection_path(kind, namespace))?
+            .send()
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    /// Watch resources of `kind` for changes after `resource_version`
+    ///
+    /// The stream ends when the server closes the watch (after a few
+    /// minutes); resume from the last seen resource version.
+    pub async fn watch<S: DeserializeOwned>(
+        &self,
+        kind: ResourceKind,
+        namespace: Option<&str>,
+        resource_version: &str,
+    ) -> Result<impl Stream<Item = Result<WatchEvent<S>>>> {
+        let response = self
+            .request(Method::GET, &Self::collection_path(kind, namespace))?
+            .query(&[
+                ("watch", "true"),
+                ("allowWatchBookmarks", "true"),
+                ("resourceVersion", resource_version),
+                ("timeoutSeconds", &WATCH_TIMEOUT_SECS.to_string()),
+            ])
+            .send()
+            .await?;
+        let response = check(response).await?;
+
+        Ok(futures::stream::unfold(Some((response, Vec::new())), |state| async move {
+            let (mut response, mut buffer) = state?;
+            loop {
+                if let Some(line) = next_line(&mut buffer) {
+                    if line.iter().all(u8::is_ascii_whitespace) {
+                        continue;
+                    }
+                    let event = serde_json::from_slice(&line).map_err(OperatorError::from);
+                    return Some((event, Some((response, buffer))));
+                }
+                match response.chunk().await {
+                    Ok(Some(chunk)) => buffer.extend_from_slice(&chunk),
+                    Ok(None) => return None,
+                    Err(e) => return Some((Err(e.into()), None)),
+                }
+            }
+        }))
+    }
+
+    /// Merge `status` into a resource's status subresource
+    pub async fn patch_status(
+        &self,
+        kind: ResourceKind,
+        namespace: &str,
+        name: &str,
+        status: &serde_json::Value,
+    ) -> Result<()> {
+        let path = format!("{}/{}/status", Self::collection_path(kind, Some(namespace)), name);
+        let response = self
+            .request(Method::PATCH, &path)?
+            .header(reqwest::header::CONTENT_TYPE, "application/merge-patch+json")
+            .body(serde_json::to_vec(&serde_json::json!({ "status": status }))?)
+            .send()
+            .await?;
+        check(response).await?;
+        Ok(())
+    }
+
+    fn request(&self, method: Method, path: &str) -> Result<RequestBuilder> {
+        let request = self.http.request(method, format!("{}{}", self.base_url, path));
+        Ok(match &self.credentials {
+            Credentials::None => request,
+            Credentials::Token(token) => request.bearer_auth(token),
+            Credentials::TokenFile(path) => {
+                let token = std::fs::read_to_string(path)
+                    .map_err(|e| OperatorError::Config(format!("Failed to read {}: {}", path.display(), e)))?;
+                request.bearer_auth(token.trim())
+            }
+        })
+    }
+}
+
+/// Turn an error response into `OperatorError::Api`
+async fn check(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<ApiStatus>(&body)
+        .ok()
+        .map(|s| s.message)
+        .filter(|m| !m.is_empty())
+        .unwrap_or(body);
+    Err(OperatorError::Api {
+        status: status.as_u16(),
+        message,
+    })
+}
+
+/// Take the next complete line out of `buffer`
+fn next_line(buffer: &mut Vec<u8>) -> Option<Vec<u8>> {
+    let end = buffer.iter().position(|&b| b == b'\n')?;
+    let mut line: Vec<u8> = buffer.drain(..=end).collect();
+    line.pop();
+    Some(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crd::WORKFLOWS;
+
+    #[test]
+    fn test_collection_path() {
+        assert_eq!(
+            KubeClient::collection_path(WORKFLOWS, Some("team-a")),
+            "/apis/copilot.llm-devops.io/v1alpha1/namespaces/team-a/workflows"
+        );
+        assert_eq!(
+            KubeClient::collection_path(WORKFLOWS, None),
+            "/apis/copilot.llm-devops.io/v1alpha1/workflows"
+        );
+    }
+
+    #[test]
+    fn test_watch_lines() {
+        let mut buffer = br#"{"type":"ADDED","object":{"metadata":{"name":"a","namespace":"ns","resourceVersion":"7"},"spec":{}}}
+{"type":"BOOKMARK","object":{"metadata":{"resourceVersion":"9"}}}
+{"type":"ERROR","object":{"kind":"Status","code":410,"reason":"Expired","message":"too old resource version"}}
+{"type":"MODI"#
+            .to_vec();
+
+        let mut events = Vec::new();
+        while let Some(line) = next_line(&mut buffer) {
+            events.push(serde_json::from_slice::<WatchEvent<serde_json::Value>>(&line).unwrap());
+        }
+        assert_eq!(buffer, br#"{"type":"MODI"#);
+
+        assert!(matches!(&events[0], WatchEvent::Added(r) if r.key() == "ns/a"));
+        assert!(matches!(&events[1], WatchEvent::Bookmark(b) if b.metadata.resource_version.as_deref() == Some("9")));
+        assert!(matches!(&events[2], WatchEvent::Error(s) if s.code == Some(410)));
+    }
+}
@@ -0,0 +1,63 @@
+//! Kubernetes operator for LLM CoPilot Agent workflows
+//!
+//! GitOps teams declare workflows and their triggers as custom resources and
+//! the operator keeps the workflow engine in sync with the cluster:
+//! - `Workflow` resources become workflow definitions (id `<namespace>/<name>`)
+//! - `Trigger` resources become event triggers for a `Workflow` in the same
+//!   namespace
+//! - Each resource's status subresource reports whether it was accepted, and
+//!   a `Workflow`'s status carries its most recent run
+//!
+//! Events are posted to the operator's `/events` endpoint and start the
+//! workflows whose triggers match.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use copilot_operator::{router, KubeClient, Operator};
+//!
+//! let operator = Arc::new(Operator::new(KubeClient::in_cluster()?, Arc::new(WorkflowEngine::new())));
+//! tokio::spawn(operator.clone().run());
+//! axum::serve(listener, router(operator)).await?;
+//! ```
+
+pub mod controller;
+pub mod crd;
+pub mod kube;
+pub mod routes;
+
+pub use controller::Operator;
+pub use crd::{
+    ResourceKind, RunStatus, SyncPhase, SyncStatus, TriggerResource, TriggerSpec, WorkflowResource,
+    WorkflowSpec, TRIGGERS, WORKFLOWS,
+};
+pub use kube::{KubeClient, WatchEvent};
+pub use routes::router;
+
+use copilot_workflow::WorkflowError;
+use thiserror::Error;
+
+/// Operator errors
+#[derive(Debug, Error)]
+pub enum OperatorError {
+    #[error("Kubernetes API error ({status}): {message}")]
+    Api { status: u16, message: String },
+
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error("Invalid resource: {0}")]
+    InvalidResource(String),
+
+    #[error("Workflow error: {0}")]
+    Workflow(#[from] WorkflowError),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Result type for operator operations
+pub type Result<T> = std::result::Result<T, OperatorError>;
@@ -0,0 +1,102 @@
+//! LLM CoPilot Agent Kubernetes operator
+//!
+//! Syncs `Workflow` and `Trigger` resources into a workflow engine, runs
+//! workflows for posted events and writes their status back to the cluster.
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use copilot_operator::{router, KubeClient, Operator};
+use copilot_workflow::WorkflowEngine;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+/// LLM CoPilot Agent Kubernetes operator
+#[derive(Parser, Debug)]
+#[command(name = "copilot-operator", version, about)]
+struct Args {
+    /// Namespace to watch; all namespaces when unset
+    #[arg(long, env = "WATCH_NAMESPACE")]
+    namespace: Option<String>,
+
+    /// API server URL (e.g. a `kubectl proxy`); the in-cluster service
+    /// account is used when unset
+    #[arg(long, env = "KUBE_API_URL")]
+    kube_api_url: Option<String>,
+
+    /// Address for the events and health endpoints
+    #[arg(long, env = "OPERATOR_LISTEN", default_value = "0.0.0.0:8090")]
+    listen: SocketAddr,
+
+    /// Log level (trace, debug, info, warn, error)
+    #[arg(long, env = "LOG_LEVEL", default_value = "info")]
+    log_level: String,
+
+    /// Log as JSON
+    #[arg(long, env = "JSON_LOGS")]
+    json_logs: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = Args::parse();
+    args.namespace = args.namespace.filter(|namespace| !namespace.is_empty());
+
+    let env_filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&args.log_level))
+        .context("Failed to create environment filter")?;
+    if args.json_logs {
+        tracing_subscriber::registry().with(env_filter).with(fmt::layer().json()).init();
+    } else {
+        tracing_subscriber::registry().with(env_filter).with(fmt::layer()).init();
+    }
+
+    let kube = match &args.kube_api_url {
+        Some(url) => KubeClient::new(url),
+        None => KubeClient::in_cluster().context("Failed to configure the in-cluster Kubernetes client")?,
+    };
+    let mut operator = Operator::new(kube, Arc::new(WorkflowEngine::new()));
+    if let Some(namespace) = &args.namespace {
+        operator = operator.with_namespace(namespace);
+    }
+    let operator = Arc::new(operator);
+
+    info!(
+        namespace = args.namespace.as_deref().unwrap_or("<all>"),
+        listen = %args.listen,
+        "Starting copilot operator"
+    );
+    tokio::spawn(operator.clone().run());
+
+    let listener = tokio::net::TcpListener::bind(args.listen)
+        .await
+        .with_context(|| format!("Failed to bind {}", args.listen))?;
+    axum::serve(listener, router(operator))
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .context("Server error")?;
+
+    info!("Operator stopped");
+    Ok(())
+}
+
+/// Resolves on Ctrl+C or SIGTERM (sent by the kubelet on pod shutdown)
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        if let Ok(mut signal) = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            signal.recv().await;
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
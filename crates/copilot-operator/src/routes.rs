@@ -0,0 +1,71 @@
+//! HTTP endpoints of the operator
+//!
+//! `POST /events` accepts trigger events (from webhooks, CI, other services)
+//! and starts the workflows whose `Trigger` resources match. `GET /healthz`
+//! serves the liveness and readiness probes.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use copilot_workflow::{EventSource, TriggerEvent};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::controller::Operator;
+
+/// Body of `POST /events`
+#[derive(Debug, Deserialize)]
+pub struct EventRequest {
+    pub event_type: String,
+    #[serde(default = "default_source")]
+    pub source: EventSource,
+    #[serde(default)]
+    pub payload: serde_json::Value,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+}
+
+fn default_source() -> EventSource {
+    EventSource::Api
+}
+
+impl From<EventRequest> for TriggerEvent {
+    fn from(request: EventRequest) -> Self {
+        let mut event = TriggerEvent::new(&request.event_type, request.source, request.payload);
+        event.metadata = request.metadata;
+        event.tenant_id = request.tenant_id;
+        event.correlation_id = request.correlation_id;
+        event
+    }
+}
+
+/// Router for the operator's endpoints
+pub fn router(operator: Arc<Operator>) -> Router {
+    Router::new()
+        .route("/events", post(events))
+        .route("/healthz", get(|| async { "ok" }))
+        .with_state(operator)
+}
+
+async fn events(State(operator): State<Arc<Operator>>, Json(request): Json<EventRequest>) -> Response {
+    let event = TriggerEvent::from(request);
+    let event_id = event.id.clone();
+    match operator.handle_event(event).await {
+        Ok(execution_ids) => Json(json!({ "event_id": event_id, "execution_ids": execution_ids })).into_response(),
+        Err(e) => {
+            warn!(event_id = %event_id, error = %e, "Failed to process event");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
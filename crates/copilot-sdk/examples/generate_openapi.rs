@@ -0,0 +1,14 @@
+//! Regenerate the OpenAPI document of the SDK contract
+//!
+//! Run with: cargo run -p copilot-sdk --example generate_openapi
+
+use copilot_sdk::contract::{Contract, OPENAPI_PATH};
+use std::path::Path;
+
+fn main() -> std::io::Result<()> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let path = root.join(OPENAPI_PATH);
+    std::fs::write(&path, Contract::new().openapi_json())?;
+    println!("Wrote {}", OPENAPI_PATH);
+    Ok(())
+}
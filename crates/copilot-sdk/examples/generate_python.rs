@@ -0,0 +1,14 @@
+//! Regenerate the Python SDK's wire models from the Rust models
+//!
+//! Run with: cargo run -p copilot-sdk --example generate_python
+
+use copilot_sdk::codegen::{python_models, PYTHON_MODELS_PATH};
+use std::path::Path;
+
+fn main() -> std::io::Result<()> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let path = root.join(PYTHON_MODELS_PATH);
+    std::fs::write(&path, python_models())?;
+    println!("Wrote {}", PYTHON_MODELS_PATH);
+    Ok(())
+}
@@ -0,0 +1,92 @@
+//! Local validation cache for conditional requests
+//!
+//! Stores the last body and ETag seen for each URL so repeated reads can send
+//! `If-None-Match` and reuse the cached body when the server answers
+//! `304 Not Modified`.
+
+use bytes::Bytes;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// A cached response body and its validator
+#[derive(Debug, Clone)]
+pub(crate) struct CachedResponse {
+    pub etag: String,
+    pub body: Bytes,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<String, CachedResponse>,
+    /// Insertion order, oldest first, for eviction
+    order: VecDeque<String>,
+}
+
+/// Bounded cache of response bodies keyed by URL
+#[derive(Debug)]
+pub(crate) struct ValidationCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+impl ValidationCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        self.state
+            .lock()
+            .expect("validation cache poisoned")
+            .entries
+            .get(url)
+            .cloned()
+    }
+
+    pub fn insert(&self, url: &str, etag: String, body: Bytes) {
+        let mut state = self.state.lock().expect("validation cache poisoned");
+        if state
+            .entries
+            .insert(url.to_string(), CachedResponse { etag, body })
+            .is_none()
+        {
+            state.order.push_back(url.to_string());
+        }
+        while state.order.len() > self.capacity {
+            if let Some(oldest) = state.order.pop_front() {
+                state.entries.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn remove(&self, url: &str) {
+        let mut state = self.state.lock().expect("validation cache poisoned");
+        if state.entries.remove(url).is_some() {
+            state.order.retain(|u| u != url);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eviction() {
+        let cache = ValidationCache::new(2);
+        cache.insert("a", "\"1\"".into(), Bytes::from_static(b"a"));
+        cache.insert("b", "\"2\"".into(), Bytes::from_static(b"b"));
+        cache.insert("a", "\"3\"".into(), Bytes::from_static(b"a2"));
+        cache.insert("c", "\"4\"".into(), Bytes::from_static(b"c"));
+
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.get("b").unwrap().etag, "\"2\"");
+        assert_eq!(cache.get("c").unwrap().body, Bytes::from_static(b"c"));
+
+        cache.remove("b");
+        assert!(cache.get("b").is_none());
+    }
+}
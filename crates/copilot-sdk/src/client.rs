@@ -1,23 +1,44 @@
 //! Copilot API client implementation
 
+use crate::cache::ValidationCache;
 use crate::error::{CopilotError, Result};
+use crate::limits::RateLimitInfo;
 use crate::models::*;
-use crate::streaming::{ChatStream, StreamEvent};
-use futures::StreamExt;
-use reqwest::{header, Client, Response, StatusCode};
+use crate::streaming::{
+    sse_data, sse_events, ChatStream, DashboardStream, ReplyChunk, ReplyStream, RunEventStream, SseEvent,
+    StreamEvent,
+};
+use futures::{Stream, StreamExt};
+use reqwest::{header, Client, RequestBuilder, Response, StatusCode};
 use secrecy::{ExposeSecret, Secret};
 use serde::de::DeserializeOwned;
+use serde::Deserialize;
 use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 use tracing::{debug, instrument};
 use url::Url;
 
+/// Response header naming the stream a streamed reply can be resumed from
+pub const STREAM_ID_HEADER: &str = "x-stream-id";
+
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// Reconnects in a row to a dropped reply stream before giving up
+pub const STREAM_RESUME_ATTEMPTS: u32 = 3;
+
+const STREAM_RESUME_BACKOFF: Duration = Duration::from_millis(250);
+
 /// Client for interacting with the Copilot API
 #[derive(Clone)]
 pub struct CopilotClient {
     http: Client,
     base_url: Url,
     api_key: Option<Secret<String>>,
+    cache: Option<Arc<ValidationCache>>,
+    /// Rate limit and quota state from the most recent response
+    rate_limit: Arc<RwLock<Option<RateLimitInfo>>>,
 }
 
 impl std::fmt::Debug for CopilotClient {
@@ -25,6 +46,7 @@ impl std::fmt::Debug for CopilotClient {
         f.debug_struct("CopilotClient")
             .field("base_url", &self.base_url)
             .field("api_key", &self.api_key.as_ref().map(|_| "[REDACTED]"))
+            .field("validation_cache", &self.cache.is_some())
             .finish()
     }
 }
@@ -36,6 +58,7 @@ pub struct CopilotClientBuilder {
     api_key: Option<String>,
     timeout: Option<Duration>,
     user_agent: Option<String>,
+    cache_capacity: Option<usize>,
 }
 
 impl CopilotClientBuilder {
@@ -68,6 +91,15 @@ impl CopilotClientBuilder {
         self
     }
 
+    /// Enable a local validation cache holding up to `capacity` responses
+    ///
+    /// Cached reads send `If-None-Match` and reuse the stored body when the
+    /// server answers `304 Not Modified`, which keeps polling cheap.
+    pub fn validation_cache(mut self, capacity: usize) -> Self {
+        self.cache_capacity = Some(capacity);
+        self
+    }
+
     /// Build the client
     pub fn build(self) -> Result<CopilotClient> {
         let base_url = self
@@ -102,6 +134,10 @@ impl CopilotClientBuilder {
             http,
             base_url,
             api_key: self.api_key.map(Secret::new),
+            cache: self
+                .cache_capacity
+                .map(|capacity| Arc::new(ValidationCache::new(capacity))),
+            rate_limit: Arc::default(),
         })
     }
 }
@@ -122,6 +158,14 @@ impl CopilotClient {
         &self.base_url
     }
 
+    /// Rate limit and quota state reported by the most recent API response
+    ///
+    /// Clients can check [`RateLimitInfo::wait_time`] before sending more
+    /// requests instead of waiting to be rejected.
+    pub fn rate_limit(&self) -> Option<RateLimitInfo> {
+        self.rate_limit.read().ok().and_then(|info| info.clone())
+    }
+
     /// Build a URL for an endpoint
     fn url(&self, path: &str) -> Result<Url> {
         self.base_url.join(path).map_err(CopilotError::Url)
@@ -134,6 +178,17 @@ impl CopilotClient {
             .map(|key| format!("Bearer {}", key.expose_secret()))
     }
 
+    /// Send a request, recording the rate limit state it reports
+    async fn send(&self, req: RequestBuilder) -> Result<Response> {
+        let response = req.send().await.map_err(CopilotError::Http)?;
+        if let Some(info) = RateLimitInfo::from_headers(response.headers()) {
+            if let Ok(mut rate_limit) = self.rate_limit.write() {
+                *rate_limit = Some(info);
+            }
+        }
+        Ok(response)
+    }
+
     /// Handle API response
     async fn handle_response<T: DeserializeOwned>(&self, response: Response) -> Result<T> {
         let status = response.status();
@@ -141,15 +196,18 @@ impl CopilotClient {
         if status.is_success() {
             response.json().await.map_err(CopilotError::Http)
         } else {
+            let retry_after = response
+                .headers()
+                .get(header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse().ok());
             let error_body = response.text().await.unwrap_or_default();
 
             match status {
                 StatusCode::UNAUTHORIZED => Err(CopilotError::Auth(error_body)),
                 StatusCode::NOT_FOUND => Err(CopilotError::NotFound(error_body)),
-                StatusCode::TOO_MANY_REQUESTS => {
-                    // Try to parse retry-after header
-                    Err(CopilotError::RateLimit { retry_after: None })
-                }
+                StatusCode::TOO_MANY_REQUESTS => Err(CopilotError::RateLimit { retry_after }),
+                StatusCode::UNPROCESSABLE_ENTITY => Err(unprocessable(error_body)),
                 _ if status.is_server_error() => Err(CopilotError::Server(error_body)),
                 _ => Err(CopilotError::Api {
                     status: status.as_u16(),
@@ -160,6 +218,70 @@ impl CopilotClient {
         }
     }
 
+    /// Handle a response wrapped in the server's `{ success, data, error }` envelope
+    async fn handle_envelope<T: DeserializeOwned>(&self, response: Response) -> Result<T> {
+        let envelope: Envelope<T> = self.handle_response(response).await?;
+        envelope.data.ok_or_else(|| {
+            CopilotError::Server(envelope.error.unwrap_or_else(|| "Empty response".to_string()))
+        })
+    }
+
+    /// Handle a `204 No Content` response
+    async fn handle_empty(&self, response: Response) -> Result<()> {
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            self.handle_response::<serde_json::Value>(response).await.map(|_| ())
+        }
+    }
+
+    /// GET a resource, revalidating against the local cache when enabled
+    async fn get_cached<T: DeserializeOwned>(&self, url: Url) -> Result<T> {
+        let Some(cache) = &self.cache else {
+            let mut req = self.http.get(url);
+            if let Some(auth) = self.auth_header() {
+                req = req.header(header::AUTHORIZATION, auth);
+            }
+            let response = self.send(req).await?;
+            return self.handle_response(response).await;
+        };
+
+        let key = url.to_string();
+        let cached = cache.get(&key);
+
+        let mut req = self.http.get(url);
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+        if let Some(cached) = &cached {
+            req = req.header(header::IF_NONE_MATCH, &cached.etag);
+        }
+
+        let response = self.send(req).await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                debug!("Validation cache hit for {}", key);
+                return Ok(serde_json::from_slice(&cached.body)?);
+            }
+        }
+        if !response.status().is_success() {
+            return self.handle_response(response).await;
+        }
+
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let body = response.bytes().await.map_err(CopilotError::Http)?;
+        let value = serde_json::from_slice(&body)?;
+        match etag {
+            Some(etag) => cache.insert(&key, etag, body),
+            None => cache.remove(&key),
+        }
+        Ok(value)
+    }
+
     // ===== Chat API =====
 
     /// Send a chat message
@@ -197,7 +319,7 @@ impl CopilotClient {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
+        let response = self.send(req).await?;
         self.handle_response(response).await
     }
 
@@ -239,7 +361,7 @@ impl CopilotClient {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
+        let response = self.send(req).await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -286,31 +408,15 @@ impl CopilotClient {
     /// List conversations
     #[instrument(skip(self))]
     pub async fn list_conversations(&self, limit: usize) -> Result<Vec<Conversation>> {
-        let mut req = self
-            .http
-            .get(self.url(&format!("/api/v1/conversations?limit={}", limit))?);
-
-        if let Some(auth) = self.auth_header() {
-            req = req.header(header::AUTHORIZATION, auth);
-        }
-
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let url = self.url(&format!("/api/v1/conversations?limit={}", limit))?;
+        self.get_cached(url).await
     }
 
     /// Get a specific conversation
     #[instrument(skip(self))]
     pub async fn get_conversation(&self, id: &str) -> Result<Conversation> {
-        let mut req = self
-            .http
-            .get(self.url(&format!("/api/v1/conversations/{}", id))?);
-
-        if let Some(auth) = self.auth_header() {
-            req = req.header(header::AUTHORIZATION, auth);
-        }
-
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let url = self.url(&format!("/api/v1/conversations/{}", id))?;
+        self.get_cached(url).await
     }
 
     /// Delete a conversation
@@ -324,7 +430,7 @@ impl CopilotClient {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
+        let response = self.send(req).await?;
 
         if response.status().is_success() {
             Ok(())
@@ -359,7 +465,7 @@ impl CopilotClient {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
+        let response = self.send(req).await?;
         self.handle_response(response).await
     }
 
@@ -372,14 +478,7 @@ impl CopilotClient {
             url.query_pairs_mut().append_pair("tag", &t);
         }
 
-        let mut req = self.http.get(url);
-
-        if let Some(auth) = self.auth_header() {
-            req = req.header(header::AUTHORIZATION, auth);
-        }
-
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        self.get_cached(url).await
     }
 
     /// Search context
@@ -388,11 +487,25 @@ impl CopilotClient {
         &self,
         query: &str,
         limit: usize,
+    ) -> Result<Vec<ContextSearchResult>> {
+        self.search_context_filtered(query, limit, &ContextSearchFilter::default())
+            .await
+    }
+
+    /// Search the context matching `filter`; the server applies the filter
+    /// before retrieval
+    #[instrument(skip(self))]
+    pub async fn search_context_filtered(
+        &self,
+        query: &str,
+        limit: usize,
+        filter: &ContextSearchFilter,
     ) -> Result<Vec<ContextSearchResult>> {
         let mut url = self.url("/api/v1/context/search")?;
         url.query_pairs_mut()
             .append_pair("q", query)
-            .append_pair("limit", &limit.to_string());
+            .append_pair("limit", &limit.to_string())
+            .extend_pairs(filter.query_pairs());
 
         let mut req = self.http.get(url);
 
@@ -400,8 +513,8 @@ impl CopilotClient {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
     }
 
     /// Clear context
@@ -419,7 +532,7 @@ impl CopilotClient {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
+        let response = self.send(req).await?;
 
         if response.status().is_success() {
             Ok(())
@@ -432,168 +545,220 @@ impl CopilotClient {
         }
     }
 
-    // ===== Workflow API =====
+    // ===== Ingestion API =====
 
-    /// List workflows
+    /// Stream a local file into the ingestion pipeline.
+    ///
+    /// The file is read and uploaded incrementally, so large files are never
+    /// loaded into memory. The job is returned once the server accepts the
+    /// upload; poll [`get_ingestion_job`](Self::get_ingestion_job) for its
+    /// outcome.
     #[instrument(skip(self))]
-    pub async fn list_workflows(&self) -> Result<Vec<WorkflowSummary>> {
-        let mut req = self.http.get(self.url("/api/v1/workflows")?);
+    pub async fn ingest_file(
+        &self,
+        path: impl AsRef<std::path::Path> + std::fmt::Debug,
+        source: Option<&str>,
+    ) -> Result<IngestionJob> {
+        let path = path.as_ref();
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|e| CopilotError::InvalidInput(format!("{}: {}", path.display(), e)))?;
+        let filename = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("upload")
+            .to_string();
+
+        let stream = tokio_util::io::ReaderStream::new(file);
+        self.ingest_stream(stream, &filename, content_type_for(&filename), source)
+            .await
+    }
+
+    /// Stream arbitrary bytes into the ingestion pipeline as a chunked upload;
+    /// the job is returned once the server accepts the upload
+    pub async fn ingest_stream<S, B, E>(
+        &self,
+        stream: S,
+        filename: &str,
+        content_type: &str,
+        source: Option<&str>,
+    ) -> Result<IngestionJob>
+    where
+        S: futures::Stream<Item = std::result::Result<B, E>> + Send + Sync + 'static,
+        B: 'static,
+        bytes::Bytes: From<B>,
+        E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        let mut url = self.url("/api/v1/ingest")?;
+        url.query_pairs_mut().append_pair("filename", filename);
+        if let Some(source) = source {
+            url.query_pairs_mut().append_pair("source", source);
+        }
+
+        let mut req = self
+            .http
+            .post(url)
+            .header(header::CONTENT_TYPE, content_type)
+            .body(reqwest::Body::wrap_stream(stream));
 
         if let Some(auth) = self.auth_header() {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
     }
 
-    /// Get a workflow
+    /// Get the status of an ingestion job
     #[instrument(skip(self))]
-    pub async fn get_workflow(&self, id: &str) -> Result<Workflow> {
+    pub async fn get_ingestion_job(&self, job_id: &str) -> Result<IngestionJob> {
         let mut req = self
             .http
-            .get(self.url(&format!("/api/v1/workflows/{}", id))?);
+            .get(self.url(&format!("/api/v1/ingest/jobs/{}", job_id))?);
 
         if let Some(auth) = self.auth_header() {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
     }
 
-    /// Start a workflow execution
-    #[instrument(skip(self, input))]
-    pub async fn start_workflow(
+    /// Bootstrap the knowledge base from a wiki export (admin only)
+    ///
+    /// `path` is a Confluence space export (zip or `entities.xml`) or a zip
+    /// of markdown files. Pages are ingested in the background; poll
+    /// [`get_bootstrap_job`](Self::get_bootstrap_job) for the coverage report.
+    #[instrument(skip(self))]
+    pub async fn bootstrap_corpus(
         &self,
-        workflow_id: &str,
-        input: HashMap<String, serde_json::Value>,
-    ) -> Result<WorkflowExecution> {
-        let body = serde_json::json!({
-            "input": input,
-        });
+        path: impl AsRef<std::path::Path> + std::fmt::Debug,
+        source: Option<&str>,
+    ) -> Result<BootstrapJob> {
+        let path = path.as_ref();
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|e| CopilotError::InvalidInput(format!("{}: {}", path.display(), e)))?;
 
+        let mut url = self.url("/api/v1/ingest/bootstrap")?;
+        if let Some(source) = source {
+            url.query_pairs_mut().append_pair("source", source);
+        }
         let mut req = self
             .http
-            .post(self.url(&format!("/api/v1/workflows/{}/run", workflow_id))?)
-            .json(&body);
+            .post(url)
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .body(reqwest::Body::wrap_stream(tokio_util::io::ReaderStream::new(file)));
 
         if let Some(auth) = self.auth_header() {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
     }
 
-    /// Get workflow execution status
+    /// Get a bootstrap job and its coverage report
     #[instrument(skip(self))]
-    pub async fn get_workflow_status(&self, execution_id: &str) -> Result<WorkflowStatus> {
+    pub async fn get_bootstrap_job(&self, job_id: &str) -> Result<BootstrapJob> {
         let mut req = self
             .http
-            .get(self.url(&format!("/api/v1/executions/{}", execution_id))?);
+            .get(self.url(&format!("/api/v1/ingest/bootstrap/{}", job_id))?);
 
         if let Some(auth) = self.auth_header() {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
     }
 
-    /// Cancel a workflow execution
+    /// List captured chat turns, newest first (admin only)
     #[instrument(skip(self))]
-    pub async fn cancel_workflow(&self, execution_id: &str) -> Result<()> {
+    pub async fn list_replays(&self, limit: usize) -> Result<Vec<ReplaySummary>> {
         let mut req = self
             .http
-            .post(self.url(&format!("/api/v1/executions/{}/cancel", execution_id))?);
+            .get(self.url("/api/v1/replays")?)
+            .query(&[("limit", limit)]);
 
         if let Some(auth) = self.auth_header() {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
 
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(CopilotError::Api {
-                status: response.status().as_u16(),
-                message: response.text().await.unwrap_or_default(),
-                code: None,
-            })
+    /// Get a captured chat turn to replay (admin only)
+    #[instrument(skip(self))]
+    pub async fn get_replay(&self, replay_id: &str) -> Result<ReplayCapture> {
+        let mut req = self
+            .http
+            .get(self.url(&format!("/api/v1/replays/{}", replay_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
         }
-    }
 
-    // ===== Sandbox API =====
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
 
-    /// List sandboxes
+    /// List documents held in quarantine for review (admin only)
     #[instrument(skip(self))]
-    pub async fn list_sandboxes(&self) -> Result<Vec<Sandbox>> {
-        let mut req = self.http.get(self.url("/api/v1/sandboxes")?);
+    pub async fn list_quarantine(&self) -> Result<Vec<QuarantinedDocument>> {
+        let mut req = self.http.get(self.url("/api/v1/ingest/quarantine")?);
 
         if let Some(auth) = self.auth_header() {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
     }
 
-    /// Get sandbox status
+    /// Get a quarantined document with its findings and held chunks (admin only)
     #[instrument(skip(self))]
-    pub async fn get_sandbox(&self, id: &str) -> Result<Sandbox> {
+    pub async fn get_quarantined_document(&self, document_id: &str) -> Result<QuarantinedDocument> {
         let mut req = self
             .http
-            .get(self.url(&format!("/api/v1/sandboxes/{}", id))?);
+            .get(self.url(&format!("/api/v1/ingest/quarantine/{}", document_id))?);
 
         if let Some(auth) = self.auth_header() {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
     }
 
-    /// Execute code in a sandbox
-    #[instrument(skip(self, code))]
-    pub async fn execute_code(
-        &self,
-        code: &str,
-        runtime: &str,
-        timeout: u64,
-    ) -> Result<ExecutionResult> {
-        let body = serde_json::json!({
-            "code": code,
-            "runtime": runtime,
-            "timeout": timeout,
-        });
-
+    /// Store a reviewed document's held chunks (admin only)
+    #[instrument(skip(self))]
+    pub async fn release_quarantined_document(&self, document_id: &str) -> Result<QuarantinedDocument> {
         let mut req = self
             .http
-            .post(self.url("/api/v1/sandbox/execute")?)
-            .json(&body);
+            .post(self.url(&format!("/api/v1/ingest/quarantine/{}/release", document_id))?);
 
         if let Some(auth) = self.auth_header() {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
     }
 
-    /// Destroy a sandbox
+    /// Drop a quarantined document's held chunks (admin only)
     #[instrument(skip(self))]
-    pub async fn destroy_sandbox(&self, id: &str) -> Result<()> {
+    pub async fn discard_quarantined_document(&self, document_id: &str) -> Result<()> {
         let mut req = self
             .http
-            .delete(self.url(&format!("/api/v1/sandboxes/{}", id))?);
+            .delete(self.url(&format!("/api/v1/ingest/quarantine/{}", document_id))?);
 
         if let Some(auth) = self.auth_header() {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
+        let response = self.send(req).await?;
 
         if response.status().is_success() {
             Ok(())
@@ -606,109 +771,1641 @@ impl CopilotClient {
         }
     }
 
-    // ===== Session API =====
-
-    /// Create a new chat session
+    /// Get how well answers are grounded in their context (admin only)
     #[instrument(skip(self))]
-    pub async fn create_session(&self, model: Option<String>) -> Result<Session> {
-        let body = serde_json::json!({
-            "model": model,
-        });
-
-        let mut req = self
-            .http
-            .post(self.url("/api/v1/sessions")?)
-            .json(&body);
+    pub async fn groundedness_stats(&self) -> Result<GroundednessStats> {
+        let mut req = self.http.get(self.url("/api/v1/groundedness/stats")?);
 
         if let Some(auth) = self.auth_header() {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
     }
 
-    /// Resume an existing session
+    /// List poorly grounded answers awaiting review (admin only)
     #[instrument(skip(self))]
-    pub async fn resume_session(&self, session_id: &str) -> Result<Session> {
-        let mut req = self
-            .http
-            .get(self.url(&format!("/api/v1/sessions/{}", session_id))?);
+    pub async fn list_groundedness_reviews(&self) -> Result<Vec<GroundednessReview>> {
+        let mut req = self.http.get(self.url("/api/v1/groundedness/reviews")?);
 
         if let Some(auth) = self.auth_header() {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
     }
 
-    /// Send a message in a session
-    #[instrument(skip(self, message))]
-    pub async fn send_message(
-        &self,
-        session_id: &str,
-        message: impl Into<String>,
-    ) -> Result<ChatResponse> {
-        let body = serde_json::json!({
-            "message": message.into(),
-        });
-
-        let mut req = self
-            .http
-            .post(self.url(&format!("/api/v1/sessions/{}/messages", session_id))?)
-            .json(&body);
+    /// Get the topics users asked about over the last `days` days (30 if
+    /// `None`) and the ones answered with low confidence or without sources
+    /// (admin only)
+    #[instrument(skip(self))]
+    pub async fn knowledge_gaps(&self, days: Option<u32>) -> Result<KnowledgeGapReport> {
+        let mut req = self.http.get(self.url("/api/v1/analytics/knowledge-gaps")?);
+        if let Some(days) = days {
+            req = req.query(&[("days", days)]);
+        }
 
         if let Some(auth) = self.auth_header() {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
     }
 
-    /// Get session history
+    /// Get the intents of the conversations of the last `days` days (30 if
+    /// `None`) and how often they were resolved or escalated (admin only)
     #[instrument(skip(self))]
-    pub async fn get_history(&self, session_id: &str) -> Result<Vec<Message>> {
-        let mut req = self
-            .http
-            .get(self.url(&format!("/api/v1/sessions/{}/history", session_id))?);
+    pub async fn conversation_analytics(&self, days: Option<u32>) -> Result<ConversationReport> {
+        let mut req = self.http.get(self.url("/api/v1/analytics/conversations")?);
+        if let Some(days) = days {
+            req = req.query(&[("days", days)]);
+        }
 
         if let Some(auth) = self.auth_header() {
             req = req.header(header::AUTHORIZATION, auth);
         }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
     }
 
-    // ===== Ask API =====
-
-    /// Send a single question (stateless)
-    #[instrument(skip(self, message, context))]
-    pub async fn ask(
-        &self,
-        message: impl Into<String>,
-        context: Option<&str>,
-        model: Option<String>,
-    ) -> Result<ChatResponse> {
-        let body = serde_json::json!({
-            "message": message.into(),
-            "context": context,
-            "model": model,
-        });
-
-        let mut req = self
-            .http
-            .post(self.url("/api/v1/ask")?)
-            .json(&body);
+    /// List A/B experiments (admin only)
+    #[instrument(skip(self))]
+    pub async fn list_experiments(&self) -> Result<Vec<Experiment>> {
+        self.experiment_request(reqwest::Method::GET, "/api/v1/experiments", None).await
+    }
 
-        if let Some(auth) = self.auth_header() {
-            req = req.header(header::AUTHORIZATION, auth);
-        }
+    /// Start an A/B experiment (admin only)
+    #[instrument(skip(self, spec))]
+    pub async fn create_experiment(&self, spec: &ExperimentSpec) -> Result<Experiment> {
+        self.experiment_request(reqwest::Method::POST, "/api/v1/experiments", Some(spec)).await
+    }
 
-        let response = req.send().await.map_err(CopilotError::Http)?;
-        self.handle_response(response).await
+    /// Get an experiment's metrics per variant and significance tests
+    /// against the control (admin only)
+    #[instrument(skip(self))]
+    pub async fn experiment_results(&self, experiment_id: &str) -> Result<ExperimentResults> {
+        let path = format!("/api/v1/experiments/{}", experiment_id);
+        self.experiment_request(reqwest::Method::GET, &path, None).await
+    }
+
+    /// Stop assigning sessions to an experiment (admin only)
+    #[instrument(skip(self))]
+    pub async fn stop_experiment(&self, experiment_id: &str) -> Result<Experiment> {
+        let path = format!("/api/v1/experiments/{}/stop", experiment_id);
+        self.experiment_request(reqwest::Method::POST, &path, None).await
+    }
+
+    /// Enroll the caller's tenant in an experiment, or withdraw it (admin
+    /// only)
+    #[instrument(skip(self))]
+    pub async fn set_experiment_enrollment(&self, experiment_id: &str, enrolled: bool) -> Result<Experiment> {
+        let path = format!("/api/v1/experiments/{}/enrollment", experiment_id);
+        let method = if enrolled { reqwest::Method::PUT } else { reqwest::Method::DELETE };
+        self.experiment_request(method, &path, None).await
+    }
+
+    async fn experiment_request<T: DeserializeOwned>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        spec: Option<&ExperimentSpec>,
+    ) -> Result<T> {
+        let mut req = self.http.request(method, self.url(path)?);
+        if let Some(spec) = spec {
+            req = req.json(spec);
+        }
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Take a reviewed answer off the review queue (admin only)
+    #[instrument(skip(self))]
+    pub async fn resolve_groundedness_review(&self, review_id: &str) -> Result<()> {
+        let mut req = self
+            .http
+            .delete(self.url(&format!("/api/v1/groundedness/reviews/{}", review_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(CopilotError::Api {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+                code: None,
+            })
+        }
+    }
+
+    /// Get the tenant's home region and the cross-region access refused by
+    /// its residency policy (admin only)
+    #[instrument(skip(self))]
+    pub async fn residency_report(&self, limit: usize) -> Result<ResidencyReport> {
+        let mut req = self
+            .http
+            .get(self.url("/api/v1/governance/residency")?)
+            .query(&[("limit", limit)]);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// List responses the tenant's code policy annotated or blocked, newest
+    /// first (admin only)
+    #[instrument(skip(self))]
+    pub async fn list_code_policy_decisions(&self, limit: usize) -> Result<Vec<CodePolicyDecision>> {
+        let mut req = self
+            .http
+            .get(self.url("/api/v1/governance/code-policy")?)
+            .query(&[("limit", limit)]);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    // ===== Bulk context API =====
+
+    /// Start a bulk operation over the context items matching `filter`
+    #[instrument(skip(self))]
+    pub async fn bulk_context(
+        &self,
+        operation: &BulkContextOperation,
+        filter: &ContextSearchFilter,
+    ) -> Result<BulkContextJob> {
+        let body = BulkContextRequest {
+            operation: operation.clone(),
+            filter: filter.to_json(),
+        };
+
+        let mut req = self.http.post(self.url("/api/v1/context/bulk")?).json(&body);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Get the progress of a bulk context job
+    #[instrument(skip(self))]
+    pub async fn get_bulk_context_job(&self, job_id: &str) -> Result<BulkContextJob> {
+        let mut req = self
+            .http
+            .get(self.url(&format!("/api/v1/context/bulk/jobs/{}", job_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Download the items of a finished export job as newline-delimited JSON
+    #[instrument(skip(self))]
+    pub async fn export_bulk_context_job(&self, job_id: &str) -> Result<String> {
+        let mut req = self
+            .http
+            .get(self.url(&format!("/api/v1/context/bulk/jobs/{}/export", job_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+
+        if response.status().is_success() {
+            Ok(response.text().await?)
+        } else {
+            Err(CopilotError::Api {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+                code: None,
+            })
+        }
+    }
+
+    /// Store the items of a finished export job as an export bundle and get
+    /// a link to download it
+    #[instrument(skip(self))]
+    pub async fn store_bulk_context_export(&self, job_id: &str) -> Result<ExportBundle> {
+        let mut req = self
+            .http
+            .post(self.url(&format!("/api/v1/context/bulk/jobs/{}/bundle", job_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    // ===== Files API =====
+
+    /// Get a URL to upload an attachment or sandbox artifact with digest
+    /// `sha256` to; no upload is needed if the content is already stored
+    #[instrument(skip(self))]
+    pub async fn create_file_upload(&self, class: &str, sha256: &str, content_type: &str) -> Result<FileUpload> {
+        let body = FileUploadRequest {
+            class: class.to_string(),
+            sha256: sha256.to_string(),
+            content_type: content_type.to_string(),
+        };
+
+        let mut req = self.http.post(self.url("/api/v1/files")?).json(&body);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Get a URL to download a stored file by class and digest
+    #[instrument(skip(self))]
+    pub async fn get_file_download(&self, class: &str, sha256: &str) -> Result<PresignedUrl> {
+        let mut req = self.http.get(self.url(&format!("/api/v1/files/{}/{}", class, sha256))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    // ===== Dashboard API =====
+
+    /// Generate a Grafana dashboard from a natural language ask, saving it
+    /// to the server's Grafana if `request.push` is set
+    #[instrument(skip(self))]
+    pub async fn generate_dashboard(&self, request: &DashboardRequest) -> Result<GeneratedDashboard> {
+        let mut req = self
+            .http
+            .post(self.url("/api/v1/dashboards/generate")?)
+            .json(request);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    // ===== Alert API =====
+
+    /// Generate an alert rule from a natural language description, with an
+    /// estimate of how often it would have fired recently
+    #[instrument(skip(self))]
+    pub async fn generate_alert_rule(&self, request: &AlertRuleRequest) -> Result<GeneratedAlertRule> {
+        let mut req = self
+            .http
+            .post(self.url("/api/v1/alerts/generate")?)
+            .json(request);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    // ===== Log API =====
+
+    /// Cluster log lines into patterns, flag anomalies and summarize them
+    #[instrument(skip(self, request))]
+    pub async fn analyze_logs(&self, request: &LogAnalysisRequest) -> Result<LogAnalysisReport> {
+        let mut req = self
+            .http
+            .post(self.url("/api/v1/logs/analyze")?)
+            .json(request);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    // ===== Workflow API =====
+
+    /// List workflows
+    #[instrument(skip(self))]
+    pub async fn list_workflows(&self) -> Result<Vec<WorkflowSummary>> {
+        let mut req = self.http.get(self.url("/api/v1/workflows")?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = req.send().await.map_err(CopilotError::Http)?;
+        self.handle_response(response).await
+    }
+
+    /// Get a workflow
+    #[instrument(skip(self))]
+    pub async fn get_workflow(&self, id: &str) -> Result<Workflow> {
+        let mut req = self
+            .http
+            .get(self.url(&format!("/api/v1/workflows/{}", id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_response(response).await
+    }
+
+    /// Start a workflow execution
+    #[instrument(skip(self, input))]
+    pub async fn start_workflow(
+        &self,
+        workflow_id: &str,
+        input: HashMap<String, serde_json::Value>,
+    ) -> Result<WorkflowExecution> {
+        let body = serde_json::json!({
+            "input": input,
+        });
+
+        let mut req = self
+            .http
+            .post(self.url(&format!("/api/v1/workflows/{}/run", workflow_id))?)
+            .json(&body);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_response(response).await
+    }
+
+    /// Get workflow execution status
+    #[instrument(skip(self))]
+    pub async fn get_workflow_status(&self, execution_id: &str) -> Result<WorkflowStatus> {
+        let mut req = self
+            .http
+            .get(self.url(&format!("/api/v1/executions/{}", execution_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_response(response).await
+    }
+
+    /// Cancel a workflow execution
+    #[instrument(skip(self))]
+    pub async fn cancel_workflow(&self, execution_id: &str) -> Result<()> {
+        let mut req = self
+            .http
+            .post(self.url(&format!("/api/v1/executions/{}/cancel", execution_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(CopilotError::Api {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+                code: None,
+            })
+        }
+    }
+
+    /// List the workflow templates that can be run, optionally of one
+    /// category
+    #[instrument(skip(self))]
+    pub async fn list_templates(&self, category: Option<&str>) -> Result<Vec<TemplateSummary>> {
+        let mut url = self.url("/api/v1/templates")?;
+        if let Some(category) = category {
+            url.query_pairs_mut().append_pair("category", category);
+        }
+
+        let mut req = self.http.get(url);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Get the parameters a workflow template is run with
+    #[instrument(skip(self))]
+    pub async fn template_parameters(&self, template_id: &str) -> Result<ParameterSchema> {
+        let mut req = self
+            .http
+            .get(self.url(&format!("/api/v1/templates/{}/parameters", template_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Validate parameters and start a run of a workflow template
+    ///
+    /// Rejected parameters fail with [`CopilotError::InvalidParameters`],
+    /// naming every offending field. A dry run only validates.
+    #[instrument(skip(self, params))]
+    pub async fn run_template(
+        &self,
+        template_id: &str,
+        params: &serde_json::Map<String, serde_json::Value>,
+        dry_run: bool,
+    ) -> Result<ManualRun> {
+        let body = serde_json::json!({
+            "params": params,
+            "dry_run": dry_run,
+        });
+
+        let mut req = self
+            .http
+            .post(self.url(&format!("/api/v1/templates/{}/runs", template_id))?)
+            .json(&body);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Stream the events of a run: a snapshot of its state, then each step
+    /// starting, retrying, completing or failing, until the run finishes
+    #[instrument(skip(self))]
+    pub async fn watch_run(&self, execution_id: &str) -> Result<RunEventStream> {
+        // Runs can take far longer than the client's request timeout
+        let mut req = self
+            .http
+            .get(self.url(&format!("/api/v1/runs/{}/events", execution_id))?)
+            .timeout(Duration::from_secs(24 * 60 * 60));
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            return match self.handle_response::<serde_json::Value>(response).await {
+                Err(e) => Err(e),
+                Ok(_) => Err(CopilotError::Api {
+                    status: status.as_u16(),
+                    message: format!("Events of run {} unavailable", execution_id),
+                    code: None,
+                }),
+            };
+        }
+
+        let events = sse_data(response.bytes_stream()).map(|data| {
+            let data = data?;
+            Ok(serde_json::from_str::<RunEvent>(&data)?)
+        });
+        Ok(Box::pin(events))
+    }
+
+    // ===== Schedule API =====
+
+    /// List workflow schedules
+    #[instrument(skip(self))]
+    pub async fn list_schedules(&self) -> Result<Vec<WorkflowSchedule>> {
+        let mut req = self.http.get(self.url("/api/v1/schedules")?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Get a workflow schedule
+    #[instrument(skip(self))]
+    pub async fn get_schedule(&self, schedule_id: &str) -> Result<WorkflowSchedule> {
+        let mut req = self
+            .http
+            .get(self.url(&format!("/api/v1/schedules/{}", schedule_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Pause a workflow schedule
+    #[instrument(skip(self))]
+    pub async fn pause_schedule(&self, schedule_id: &str) -> Result<WorkflowSchedule> {
+        let mut req = self
+            .http
+            .post(self.url(&format!("/api/v1/schedules/{}/pause", schedule_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Resume a paused workflow schedule; fire times that passed while it
+    /// was paused are not run
+    #[instrument(skip(self))]
+    pub async fn resume_schedule(&self, schedule_id: &str) -> Result<WorkflowSchedule> {
+        let mut req = self
+            .http
+            .post(self.url(&format!("/api/v1/schedules/{}/resume", schedule_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Preview the next `count` fire times of `schedule`, e.g.
+    /// `{ "type": "cron", "expression": "*/15 * * * *" }`
+    ///
+    /// Schedules that cannot fire, such as malformed cron expressions, are
+    /// rejected by the server with `400`.
+    #[instrument(skip(self, schedule))]
+    pub async fn preview_schedule(&self, schedule: &serde_json::Value, count: usize) -> Result<SchedulePreview> {
+        let body = serde_json::json!({
+            "schedule": schedule,
+            "count": count,
+        });
+
+        let mut req = self
+            .http
+            .post(self.url("/api/v1/schedules/preview")?)
+            .json(&body);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    // ===== Sandbox API =====
+
+    /// List sandboxes
+    #[instrument(skip(self))]
+    pub async fn list_sandboxes(&self) -> Result<Vec<Sandbox>> {
+        let mut req = self.http.get(self.url("/api/v1/sandboxes")?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_response(response).await
+    }
+
+    /// Get sandbox status
+    #[instrument(skip(self))]
+    pub async fn get_sandbox(&self, id: &str) -> Result<Sandbox> {
+        let mut req = self
+            .http
+            .get(self.url(&format!("/api/v1/sandboxes/{}", id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_response(response).await
+    }
+
+    /// Execute code in a sandbox
+    #[instrument(skip(self, code))]
+    pub async fn execute_code(
+        &self,
+        code: &str,
+        runtime: &str,
+        timeout: u64,
+    ) -> Result<ExecutionResult> {
+        let body = serde_json::json!({
+            "code": code,
+            "runtime": runtime,
+            "timeout": timeout,
+        });
+
+        let mut req = self
+            .http
+            .post(self.url("/api/v1/sandbox/execute")?)
+            .json(&body);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_response(response).await
+    }
+
+    /// Destroy a sandbox
+    #[instrument(skip(self))]
+    pub async fn destroy_sandbox(&self, id: &str) -> Result<()> {
+        let mut req = self
+            .http
+            .delete(self.url(&format!("/api/v1/sandboxes/{}", id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(CopilotError::Api {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+                code: None,
+            })
+        }
+    }
+
+    // ===== Session API =====
+
+    /// Create a new chat session
+    #[instrument(skip(self))]
+    pub async fn create_session(&self, model: Option<String>) -> Result<Session> {
+        let body = serde_json::json!({
+            "model": model,
+        });
+
+        let mut req = self
+            .http
+            .post(self.url("/api/v1/sessions")?)
+            .json(&body);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_response(response).await
+    }
+
+    /// Resume an existing session
+    #[instrument(skip(self))]
+    pub async fn resume_session(&self, session_id: &str) -> Result<Session> {
+        let mut req = self
+            .http
+            .get(self.url(&format!("/api/v1/sessions/{}", session_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_response(response).await
+    }
+
+    /// Send a message in a session
+    #[instrument(skip(self, message))]
+    pub async fn send_message(
+        &self,
+        session_id: &str,
+        message: impl Into<String>,
+    ) -> Result<ChatResponse> {
+        let message = SessionMessage {
+            message: message.into(),
+            ..Default::default()
+        };
+        self.send_session_message(session_id, &message).await
+    }
+
+    /// Send a message in a session with metadata, or to be answered by
+    /// another model than the session's
+    #[instrument(skip(self, message))]
+    pub async fn send_session_message(&self, session_id: &str, message: &SessionMessage) -> Result<ChatResponse> {
+        let mut req = self
+            .http
+            .post(self.url(&format!("/api/v1/sessions/{}/messages", session_id))?)
+            .json(message);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Stream the reply to a message in a session. A dropped connection is
+    /// resumed after the last event received, up to
+    /// [`STREAM_RESUME_ATTEMPTS`] times in a row, so the reply arrives whole
+    #[instrument(skip(self, message))]
+    pub async fn stream_session_message(&self, session_id: &str, message: &SessionMessage) -> Result<ReplyStream> {
+        let mut req = self
+            .http
+            .post(self.url(&format!("/api/v1/sessions/{}/messages/stream", session_id))?)
+            .json(message);
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.open_event_stream(req).await?;
+        let stream_id = response
+            .headers()
+            .get(STREAM_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| CopilotError::Stream("Reply stream has no stream ID".to_string()))?;
+        Ok(self.reply_stream(session_id, stream_id, None, response))
+    }
+
+    /// Resume a reply stream after event `last_event_id`, or from its start.
+    /// Streams stay resumable for a while after the server finishes them
+    #[instrument(skip(self))]
+    pub async fn resume_session_stream(
+        &self,
+        session_id: &str,
+        stream_id: &str,
+        last_event_id: Option<&str>,
+    ) -> Result<ReplyStream> {
+        let last_event_id = last_event_id.map(str::to_string);
+        let response = self.resume_request(session_id, stream_id, last_event_id.as_deref()).await?;
+        Ok(self.reply_stream(session_id, stream_id.to_string(), last_event_id, response))
+    }
+
+    async fn resume_request(&self, session_id: &str, stream_id: &str, last_event_id: Option<&str>) -> Result<Response> {
+        let mut req = self
+            .http
+            .get(self.url(&format!("/api/v1/sessions/{}/messages/stream/{}", session_id, stream_id))?);
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+        if let Some(id) = last_event_id {
+            req = req.header(LAST_EVENT_ID_HEADER, id);
+        }
+        self.open_event_stream(req).await
+    }
+
+    /// Send a request answered with server-sent events
+    async fn open_event_stream(&self, req: RequestBuilder) -> Result<Response> {
+        let response = self.send(req).await?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        let status = response.status();
+        match self.handle_response::<serde_json::Value>(response).await {
+            Err(e) => Err(e),
+            Ok(_) => Err(CopilotError::Api {
+                status: status.as_u16(),
+                message: "Event stream unavailable".to_string(),
+                code: None,
+            }),
+        }
+    }
+
+    /// The chunks of a reply stream, reconnecting when the connection drops
+    /// before the final chunk
+    fn reply_stream(
+        &self,
+        session_id: &str,
+        stream_id: String,
+        last_event_id: Option<String>,
+        response: Response,
+    ) -> ReplyStream {
+        struct State {
+            client: CopilotClient,
+            session_id: String,
+            stream_id: String,
+            last_event_id: Arc<Mutex<Option<String>>>,
+            events: Option<Pin<Box<dyn Stream<Item = Result<SseEvent>> + Send>>>,
+            failures: u32,
+            done: bool,
+        }
+
+        let last_event_id = Arc::new(Mutex::new(last_event_id));
+        let state = State {
+            client: self.clone(),
+            session_id: session_id.to_string(),
+            stream_id: stream_id.clone(),
+            last_event_id: Arc::clone(&last_event_id),
+            events: Some(Box::pin(sse_events(response.bytes_stream()))),
+            failures: 0,
+            done: false,
+        };
+
+        let chunks = futures::stream::unfold(state, |mut state| async move {
+            loop {
+                if state.done {
+                    return None;
+                }
+                let Some(events) = state.events.as_mut() else {
+                    let last_event_id = state.last_event_id.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                    tokio::time::sleep(STREAM_RESUME_BACKOFF * state.failures).await;
+                    match state
+                        .client
+                        .resume_request(&state.session_id, &state.stream_id, last_event_id.as_deref())
+                        .await
+                    {
+                        Ok(response) => state.events = Some(Box::pin(sse_events(response.bytes_stream()))),
+                        Err(e) => {
+                            state.failures += 1;
+                            if !e.is_retryable() || state.failures >= STREAM_RESUME_ATTEMPTS {
+                                state.done = true;
+                                return Some((Err(e), state));
+                            }
+                        }
+                    }
+                    continue;
+                };
+
+                let error = match events.next().await {
+                    Some(Ok(event)) => {
+                        state.failures = 0;
+                        if event.id.is_some() {
+                            *state.last_event_id.lock().unwrap_or_else(|e| e.into_inner()) = event.id.clone();
+                        }
+                        if event.event.as_deref() == Some("error") {
+                            return Some((Err(CopilotError::Stream(event.data)), state));
+                        }
+                        let chunk = serde_json::from_str::<ReplyChunk>(&event.data).map_err(CopilotError::from);
+                        state.done = matches!(chunk, Ok(ReplyChunk { is_final: true, .. }));
+                        return Some((chunk, state));
+                    }
+                    Some(Err(e)) => e,
+                    None => CopilotError::Stream("Reply stream ended before its final chunk".to_string()),
+                };
+                debug!("Reply stream {} interrupted, resuming: {}", state.stream_id, error);
+                state.events = None;
+                state.failures += 1;
+                if state.failures > STREAM_RESUME_ATTEMPTS {
+                    state.done = true;
+                    return Some((Err(error), state));
+                }
+            }
+        });
+
+        ReplyStream::new(stream_id, last_event_id, Box::pin(chunks))
+    }
+
+    /// Get session history
+    #[instrument(skip(self))]
+    pub async fn get_history(&self, session_id: &str) -> Result<Vec<Message>> {
+        let url = self.url(&format!("/api/v1/sessions/{}/history", session_id))?;
+        self.get_cached(url).await
+    }
+
+    /// Get the code edits proposed as unified diffs in the session's latest
+    /// response
+    #[instrument(skip(self))]
+    pub async fn proposed_edits(&self, session_id: &str) -> Result<ProposedEdits> {
+        let mut req = self
+            .http
+            .get(self.url(&format!("/api/v1/sessions/{}/edits", session_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Package a session into a handoff document and post it to the
+    /// server's incident channels or ticket systems
+    #[instrument(skip(self, request))]
+    pub async fn hand_off_session(&self, session_id: &str, request: &HandoffRequest) -> Result<HandoffResponse> {
+        let mut req = self
+            .http
+            .post(self.url(&format!("/api/v1/sessions/{}/handoff", session_id))?)
+            .json(request);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Record whether a session's goal was resolved, overriding what its
+    /// messages suggested
+    #[instrument(skip(self))]
+    pub async fn confirm_resolution(&self, session_id: &str, resolved: bool) -> Result<Resolution> {
+        let mut req = self
+            .http
+            .post(self.url(&format!("/api/v1/sessions/{}/resolution", session_id))?)
+            .json(&serde_json::json!({ "resolved": resolved }));
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Rate whether a session's answers helped; counted in the results of
+    /// the experiment the session takes part in, if any
+    #[instrument(skip(self))]
+    pub async fn submit_feedback(&self, session_id: &str, helpful: bool) -> Result<()> {
+        let mut req = self
+            .http
+            .post(self.url(&format!("/api/v1/sessions/{}/feedback", session_id))?)
+            .json(&serde_json::json!({ "helpful": helpful }));
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_empty(response).await
+    }
+
+    /// Warm the context for a message the user is still typing
+    ///
+    /// Call this as the input changes (debounced); the message sent with
+    /// [`send_message`](Self::send_message) is then usually answered from
+    /// the prefetched context.
+    #[instrument(skip(self, partial_message))]
+    pub async fn prefetch_context(
+        &self,
+        session_id: &str,
+        partial_message: impl Into<String>,
+    ) -> Result<PrefetchOutcome> {
+        let body = serde_json::json!({ "query": partial_message.into() });
+        let mut req = self
+            .http
+            .post(self.url(&format!("/api/v1/sessions/{}/prefetch", session_id))?)
+            .json(&body);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Get the context prefetch hit rate and latencies
+    #[instrument(skip(self))]
+    pub async fn prefetch_stats(&self) -> Result<PrefetchStats> {
+        let mut req = self.http.get(self.url("/api/v1/prefetch/stats")?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Get the embedding cache hit rates
+    #[instrument(skip(self))]
+    pub async fn embedding_cache_stats(&self) -> Result<EmbeddingCacheStats> {
+        let mut req = self.http.get(self.url("/api/v1/embeddings/cache")?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Embed and cache the context items matching `filter` ahead of queries
+    /// (admin only)
+    #[instrument(skip(self))]
+    pub async fn warm_embedding_cache(&self, filter: &ContextSearchFilter) -> Result<EmbeddingWarmup> {
+        let mut req = self
+            .http
+            .post(self.url("/api/v1/embeddings/cache/warmup")?)
+            .json(&filter.to_json());
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Bring the archived context items matching `filter` back into memory
+    /// ahead of queries (admin only)
+    #[instrument(skip(self))]
+    pub async fn warm_up_context(&self, filter: &ContextSearchFilter) -> Result<ContextWarmup> {
+        let mut req = self
+            .http
+            .post(self.url("/api/v1/context/archive/warmup")?)
+            .json(&filter.to_json());
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    // ===== Preferences API =====
+
+    /// Get the caller's preferences and the learned ones awaiting confirmation
+    #[instrument(skip(self))]
+    pub async fn get_preferences(&self) -> Result<PreferencesResponse> {
+        let mut req = self.http.get(self.url("/api/v1/preferences")?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Replace the caller's preferences
+    #[instrument(skip(self, preferences))]
+    pub async fn update_preferences(&self, preferences: &UserPreferences) -> Result<UserPreferences> {
+        let mut req = self.http.put(self.url("/api/v1/preferences")?).json(preferences);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Forget the caller's preferences, suggestions and dismissals
+    #[instrument(skip(self))]
+    pub async fn delete_preferences(&self) -> Result<()> {
+        self.delete_resource("/api/v1/preferences").await
+    }
+
+    /// Accept a learned preference
+    #[instrument(skip(self))]
+    pub async fn confirm_preference(&self, suggestion_id: &str) -> Result<UserPreferences> {
+        let mut req = self.http.post(
+            self.url(&format!("/api/v1/preferences/suggestions/{}/confirm", suggestion_id))?,
+        );
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Reject a learned preference so it is not suggested again
+    #[instrument(skip(self))]
+    pub async fn dismiss_preference(&self, suggestion_id: &str) -> Result<()> {
+        self.delete_resource(&format!("/api/v1/preferences/suggestions/{}", suggestion_id))
+            .await
+    }
+
+    async fn delete_resource(&self, path: &str) -> Result<()> {
+        let mut req = self.http.delete(self.url(path)?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(CopilotError::Api {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+                code: None,
+            })
+        }
+    }
+
+    /// Get the caller's consent to be impersonated by admins
+    #[instrument(skip(self))]
+    pub async fn get_impersonation_consent(&self) -> Result<ImpersonationConsent> {
+        let mut req = self.http.get(self.url("/api/v1/impersonation/consent")?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Let admins of the caller's tenant impersonate them for `hours` (24
+    /// by default)
+    #[instrument(skip(self))]
+    pub async fn grant_impersonation_consent(&self, hours: Option<u32>) -> Result<ImpersonationConsent> {
+        let mut req = self
+            .http
+            .put(self.url("/api/v1/impersonation/consent")?)
+            .json(&serde_json::json!({ "hours": hours }));
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Withdraw the caller's consent, ending impersonations of them
+    #[instrument(skip(self))]
+    pub async fn revoke_impersonation_consent(&self) -> Result<()> {
+        self.delete_resource("/api/v1/impersonation/consent").await
+    }
+
+    /// List impersonations in the caller's tenant, newest first (admin only)
+    #[instrument(skip(self))]
+    pub async fn list_impersonations(&self) -> Result<Vec<Impersonation>> {
+        let mut req = self.http.get(self.url("/api/v1/admin/impersonations")?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Start acting as a consenting user (admin only); a client built with
+    /// the returned token acts as the user until it expires or is ended
+    #[instrument(skip(self))]
+    pub async fn start_impersonation(&self, request: &ImpersonationRequest) -> Result<ImpersonationToken> {
+        let mut req = self.http.post(self.url("/api/v1/admin/impersonations")?).json(request);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// End an impersonation, invalidating its token (admin only)
+    #[instrument(skip(self))]
+    pub async fn end_impersonation(&self, impersonation_id: &str) -> Result<()> {
+        self.delete_resource(&format!("/api/v1/admin/impersonations/{}", impersonation_id))
+            .await
+    }
+
+    /// Get the tenant's compliance report over `period` (`2026`, `2026-Q3`,
+    /// `2026-09` or `2026-07-01..2026-10-01`; the last 90 days if `None`)
+    /// (admin only)
+    #[instrument(skip(self))]
+    pub async fn compliance_report(&self, period: Option<&str>) -> Result<ComplianceReport> {
+        let response = self.send(self.compliance_report_request(period, "json")?).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Get the tenant's compliance report over `period` as a markdown
+    /// document (admin only)
+    #[instrument(skip(self))]
+    pub async fn compliance_report_markdown(&self, period: Option<&str>) -> Result<String> {
+        let response = self.send(self.compliance_report_request(period, "markdown")?).await?;
+
+        if response.status().is_success() {
+            Ok(response.text().await?)
+        } else {
+            Err(CopilotError::Api {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+                code: None,
+            })
+        }
+    }
+
+    fn compliance_report_request(&self, period: Option<&str>, format: &str) -> Result<RequestBuilder> {
+        let mut req = self
+            .http
+            .get(self.url("/api/v1/admin/compliance-report")?)
+            .query(&[("format", format)]);
+        if let Some(period) = period {
+            req = req.query(&[("period", period)]);
+        }
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+        Ok(req)
+    }
+
+    /// List the tenant's access reviews in `period`, the last 90 days if
+    /// `None` (admin only)
+    #[instrument(skip(self))]
+    pub async fn list_access_reviews(&self, period: Option<&str>) -> Result<Vec<AccessReview>> {
+        let mut req = self.http.get(self.url("/api/v1/admin/access-reviews")?);
+        if let Some(period) = period {
+            req = req.query(&[("period", period)]);
+        }
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Record a review of a user's access, signed off by the caller (admin
+    /// only)
+    #[instrument(skip(self))]
+    pub async fn record_access_review(&self, review: &AccessReviewRequest) -> Result<AccessReview> {
+        let mut req = self.http.post(self.url("/api/v1/admin/access-reviews")?).json(review);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Download the tenant's users as CSV with `username`, `email`,
+    /// `roles`, `active`, `external_id` and `display_name` columns (admin
+    /// only)
+    #[instrument(skip(self))]
+    pub async fn export_users(&self) -> Result<String> {
+        let mut req = self.http.get(self.url("/api/v1/admin/users/export")?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        if response.status().is_success() {
+            Ok(response.text().await?)
+        } else {
+            Err(CopilotError::Api {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+                code: None,
+            })
+        }
+    }
+
+    /// Create or update the tenant's users from CSV in the format of
+    /// [`export_users`](Self::export_users), matched by username (admin
+    /// only)
+    #[instrument(skip(self, csv))]
+    pub async fn import_users(&self, csv: impl Into<String>) -> Result<UserImportReport> {
+        let mut req = self
+            .http
+            .post(self.url("/api/v1/admin/users/import")?)
+            .header(header::CONTENT_TYPE, "text/csv")
+            .body(csv.into());
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    // ===== Account API =====
+
+    /// Email the caller a link to verify their address
+    #[instrument(skip(self))]
+    pub async fn request_email_verification(&self) -> Result<()> {
+        let mut req = self.http.post(self.url("/api/v1/auth/email-verification/request")?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_empty(response).await
+    }
+
+    /// Verify an email address with the token mailed to it
+    #[instrument(skip(self, token))]
+    pub async fn verify_email(&self, token: &str) -> Result<()> {
+        let req = self
+            .http
+            .post(self.url("/api/v1/auth/email-verification")?)
+            .json(&serde_json::json!({ "token": token }));
+
+        let response = self.send(req).await?;
+        self.handle_empty(response).await
+    }
+
+    /// Email a password reset link to the account registered to `email`;
+    /// succeeds whether or not there is one
+    #[instrument(skip(self))]
+    pub async fn request_password_reset(&self, email: &str) -> Result<()> {
+        let req = self
+            .http
+            .post(self.url("/api/v1/auth/password-reset/request")?)
+            .json(&serde_json::json!({ "email": email }));
+
+        let response = self.send(req).await?;
+        self.handle_empty(response).await
+    }
+
+    /// Set a new password with the token of a password reset email
+    #[instrument(skip(self, token, new_password))]
+    pub async fn reset_password(&self, token: &str, new_password: &str) -> Result<()> {
+        let req = self
+            .http
+            .post(self.url("/api/v1/auth/password-reset")?)
+            .json(&serde_json::json!({ "token": token, "new_password": new_password }));
+
+        let response = self.send(req).await?;
+        self.handle_empty(response).await
+    }
+
+    /// Public keys that verify the tokens the server signs, for checking
+    /// them without the shared secret
+    #[instrument(skip(self))]
+    pub async fn jwks(&self) -> Result<JsonWebKeySet> {
+        let response = self.send(self.http.get(self.url("/.well-known/jwks.json")?)).await?;
+        self.handle_response(response).await
+    }
+
+    // ===== Webhook API =====
+
+    /// List the tenant's webhook endpoints (admin only)
+    #[instrument(skip(self))]
+    pub async fn list_webhooks(&self) -> Result<Vec<WebhookEndpoint>> {
+        let mut req = self.http.get(self.url("/api/v1/webhooks")?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Register a webhook endpoint (admin only); its signing secret is only
+    /// returned now
+    #[instrument(skip(self))]
+    pub async fn create_webhook(&self, request: &CreateWebhookRequest) -> Result<WebhookSecret> {
+        let mut req = self.http.post(self.url("/api/v1/webhooks")?).json(request);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Get a webhook endpoint (admin only)
+    #[instrument(skip(self))]
+    pub async fn get_webhook(&self, webhook_id: &str) -> Result<WebhookEndpoint> {
+        let mut req = self.http.get(self.url(&format!("/api/v1/webhooks/{}", webhook_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Change a webhook endpoint's URL, subscribed events, headers or state
+    /// (admin only)
+    #[instrument(skip(self))]
+    pub async fn update_webhook(
+        &self,
+        webhook_id: &str,
+        update: &WebhookEndpointUpdate,
+    ) -> Result<WebhookEndpoint> {
+        let mut req = self
+            .http
+            .put(self.url(&format!("/api/v1/webhooks/{}", webhook_id))?)
+            .json(update);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Remove a webhook endpoint (admin only)
+    #[instrument(skip(self))]
+    pub async fn delete_webhook(&self, webhook_id: &str) -> Result<()> {
+        self.delete_resource(&format!("/api/v1/webhooks/{}", webhook_id)).await
+    }
+
+    /// Give a webhook endpoint a new signing secret (admin only); deliveries
+    /// are signed with the old one too for `grace_hours` (24 by default)
+    #[instrument(skip(self))]
+    pub async fn rotate_webhook_secret(&self, webhook_id: &str, grace_hours: Option<u32>) -> Result<WebhookSecret> {
+        let mut req = self
+            .http
+            .post(self.url(&format!("/api/v1/webhooks/{}/secret", webhook_id))?)
+            .json(&serde_json::json!({ "grace_hours": grace_hours }));
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Send a test delivery to a webhook endpoint (admin only)
+    #[instrument(skip(self))]
+    pub async fn ping_webhook(&self, webhook_id: &str) -> Result<WebhookPingResult> {
+        let mut req = self.http.post(self.url(&format!("/api/v1/webhooks/{}/ping", webhook_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Get a webhook endpoint's delivery success and latency, overall and by
+    /// event type (admin only)
+    #[instrument(skip(self))]
+    pub async fn get_webhook_stats(&self, webhook_id: &str) -> Result<WebhookStats> {
+        let mut req = self.http.get(self.url(&format!("/api/v1/webhooks/{}/stats", webhook_id))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// List the payload schema versions webhook endpoints can be pinned to
+    #[instrument(skip(self))]
+    pub async fn list_webhook_schema_versions(&self) -> Result<Vec<WebhookSchemaVersion>> {
+        let mut req = self.http.get(self.url("/api/v1/webhook-schemas")?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Get the JSON Schemas of the webhook envelope and payloads in a version
+    #[instrument(skip(self))]
+    pub async fn get_webhook_schemas(&self, version: u32) -> Result<WebhookPayloadSchemas> {
+        let mut req = self.http.get(self.url(&format!("/api/v1/webhook-schemas/{}", version))?);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    // ===== Ask API =====
+
+    /// Send a single question (stateless)
+    #[instrument(skip(self, message, context))]
+    pub async fn ask(
+        &self,
+        message: impl Into<String>,
+        context: Option<&str>,
+        model: Option<String>,
+    ) -> Result<ChatResponse> {
+        let body = serde_json::json!({
+            "message": message.into(),
+            "context": context,
+            "model": model,
+        });
+
+        let mut req = self
+            .http
+            .post(self.url("/api/v1/ask")?)
+            .json(&body);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_response(response).await
+    }
+
+    // ===== CI Gate API =====
+
+    /// Run a workflow or benchmark gate and wait for its verdict
+    ///
+    /// A failing gate still returns `Ok`; check [`GateVerdict::passed`] or
+    /// report the verdict to GitHub as a check run.
+    #[instrument(skip(self, request), fields(head_sha = %request.context.head_sha))]
+    pub async fn run_github_gate(&self, request: &GateRequest) -> Result<GateVerdict> {
+        let mut req = self
+            .http
+            .post(self.url("/api/v1/gates/github")?)
+            // The server only answers once the gate has finished
+            .timeout(Duration::from_secs(request.timeout_secs.unwrap_or(600) + 30))
+            .json(request);
+
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    // ===== Dashboard API =====
+
+    /// Get a snapshot of server activity (admin only)
+    #[instrument(skip(self))]
+    pub async fn dashboard(&self) -> Result<DashboardSnapshot> {
+        let mut req = self.http.get(self.url("/api/v1/dashboard")?);
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        self.handle_envelope(response).await
+    }
+
+    /// Stream server activity snapshots, one every `interval` (admin only)
+    #[instrument(skip(self))]
+    pub async fn dashboard_stream(&self, interval: Duration) -> Result<DashboardStream> {
+        let mut url = self.url("/api/v1/dashboard/events")?;
+        url.query_pairs_mut()
+            .append_pair("interval_ms", &interval.as_millis().to_string());
+
+        // Long-lived, so the client's request timeout would cut it short;
+        // callers reconnect when the stream ends
+        let mut req = self.http.get(url).timeout(Duration::from_secs(24 * 60 * 60));
+        if let Some(auth) = self.auth_header() {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+
+        let response = self.send(req).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            return match self.handle_response::<serde_json::Value>(response).await {
+                Err(e) => Err(e),
+                Ok(_) => Err(CopilotError::Api {
+                    status: status.as_u16(),
+                    message: "Dashboard stream unavailable".to_string(),
+                    code: None,
+                }),
+            };
+        }
+
+        let snapshots = sse_data(response.bytes_stream()).map(|data| {
+            let data = data?;
+            Ok(serde_json::from_str::<DashboardSnapshot>(&data)?)
+        });
+        Ok(Box::pin(snapshots))
     }
 
     // ===== Health API =====
@@ -757,10 +2454,80 @@ impl CopilotClient {
     }
 }
 
+/// `{ success, data, error }` wrapper used by the ingestion and dashboard endpoints
+#[derive(Deserialize)]
+struct Envelope<T> {
+    data: Option<T>,
+    error: Option<String>,
+}
+
+/// Error body of a `422` response
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(default)]
+    message: String,
+    code: Option<String>,
+    details: Option<serde_json::Value>,
+}
+
+/// The field errors of a `422` response, or a plain API error when it has
+/// none
+fn unprocessable(body: String) -> CopilotError {
+    let Ok(error) = serde_json::from_str::<ErrorBody>(&body) else {
+        return CopilotError::Api {
+            status: 422,
+            message: body,
+            code: None,
+        };
+    };
+    match error.details.map(serde_json::from_value::<Vec<ParameterError>>) {
+        Some(Ok(fields)) if !fields.is_empty() => CopilotError::InvalidParameters(fields),
+        _ => CopilotError::Api {
+            status: 422,
+            message: error.message,
+            code: error.code,
+        },
+    }
+}
+
+/// Content type for an upload, guessed from the file extension
+fn content_type_for(filename: &str) -> &'static str {
+    let extension = filename.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("md" | "markdown") => "text/markdown",
+        Some("html" | "htm") => "text/html",
+        Some("csv") => "text/csv",
+        Some("xml") => "application/xml",
+        Some("yaml" | "yml") => "application/yaml",
+        Some("ndjson" | "jsonl") => "application/x-ndjson",
+        _ => "text/plain",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_content_type_for() {
+        assert_eq!(content_type_for("README.md"), "text/markdown");
+        assert_eq!(content_type_for("events.JSONL"), "application/x-ndjson");
+        assert_eq!(content_type_for("main.rs"), "text/plain");
+        assert_eq!(content_type_for("Makefile"), "text/plain");
+    }
+
+    #[test]
+    fn test_unprocessable_names_the_fields() {
+        let body = r#"{"code":"INVALID_PARAMETERS","message":"Invalid parameters","details":[{"field":"environment","message":"Missing required parameter: environment"}]}"#;
+        match unprocessable(body.to_string()) {
+            CopilotError::InvalidParameters(fields) => assert_eq!(fields[0].field, "environment"),
+            other => panic!("unexpected error: {}", other),
+        }
+
+        let plain = unprocessable("not json".to_string());
+        assert!(matches!(plain, CopilotError::Api { status: 422, .. }));
+    }
+
     #[test]
     fn test_builder() {
         let client = CopilotClient::builder()
@@ -773,6 +2540,181 @@ mod tests {
         assert_eq!(client.base_url().as_str(), "http://localhost:8080/");
     }
 
+    #[tokio::test]
+    async fn test_rate_limit_headers_are_recorded() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/workflows"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("x-ratelimit-limit", "300")
+                    .insert_header("x-ratelimit-remaining", "12")
+                    .insert_header("x-ratelimit-reset", "8")
+                    .insert_header("x-quota-api-calls-remaining", "99")
+                    .set_body_json(serde_json::json!([])),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/workflows"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("x-ratelimit-limit", "300")
+                    .insert_header("x-ratelimit-remaining", "0")
+                    .insert_header("retry-after", "3"),
+            )
+            .mount(&server)
+            .await;
+
+        let client = CopilotClient::new(server.uri()).unwrap();
+        assert!(client.rate_limit().is_none());
+
+        client.list_workflows().await.unwrap();
+        let info = client.rate_limit().unwrap();
+        assert_eq!(info.remaining, Some(12));
+        assert_eq!(info.quota("api-calls").unwrap().remaining, Some(99));
+
+        let err = client.list_workflows().await.unwrap_err();
+        assert!(matches!(err, CopilotError::RateLimit { retry_after: Some(3) }));
+        let info = client.rate_limit().unwrap();
+        assert!(info.is_exhausted());
+        assert_eq!(info.wait_time(), Some(Duration::from_secs(3)));
+    }
+
+    #[tokio::test]
+    async fn test_reply_stream_resumes_after_disconnect() {
+        use wiremock::matchers::{header as header_eq, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        fn event(index: usize, content: &str, is_final: bool) -> String {
+            format!(
+                "event: {}\ndata: {{\"type\":\"Token\",\"content\":\"{}\",\"sequence\":{},\"is_final\":{}}}\nid: s1:{}\n\n",
+                if is_final { "done" } else { "token" },
+                content,
+                index,
+                is_final,
+                index
+            )
+        }
+
+        let server = MockServer::start().await;
+        // The first connection drops after two events
+        Mock::given(method("POST"))
+            .and(path("/api/v1/sessions/abc/messages/stream"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("x-stream-id", "s1")
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(format!("{}{}", event(0, "Hel", false), event(1, "lo", false))),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/sessions/abc/messages/stream/s1"))
+            .and(header_eq("last-event-id", "s1:1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(format!("{}{}", event(2, " world", false), event(3, "", true))),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = CopilotClient::new(&server.uri()).unwrap();
+        let message = SessionMessage {
+            message: "hi".to_string(),
+            ..Default::default()
+        };
+        let stream = client.stream_session_message("abc", &message).await.unwrap();
+        assert_eq!(stream.stream_id(), "s1");
+        assert_eq!(stream.collect_content().await.unwrap(), "Hello world");
+    }
+
+    #[tokio::test]
+    async fn test_watch_run_streams_step_events() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let body = concat!(
+            "event: snapshot\n",
+            "data: {\"execution_id\":\"r1\",\"status\":\"running\",\"running_steps\":[\"build\"],",
+            "\"completed_steps\":0,\"failed_steps\":0,\"timestamp\":\"2024-01-01T00:00:00Z\"}\n\n",
+            "event: step\n",
+            "data: {\"execution_id\":\"r1\",\"kind\":\"step_retried\",\"step_id\":\"build\",\"attempt\":1,",
+            "\"error\":\"exit 1\",\"status\":\"running\",\"running_steps\":[\"build\"],",
+            "\"completed_steps\":0,\"failed_steps\":0,\"timestamp\":\"2024-01-01T00:00:01Z\"}\n\n",
+            ": keep-alive\n\n",
+            "event: step\n",
+            "data: {\"execution_id\":\"r1\",\"kind\":\"completed\",\"status\":\"completed\",\"running_steps\":[],",
+            "\"completed_steps\":1,\"failed_steps\":0,\"timestamp\":\"2024-01-01T00:00:02Z\"}\n\n",
+        );
+        Mock::given(method("GET"))
+            .and(path("/api/v1/runs/r1/events"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(body),
+            )
+            .mount(&server)
+            .await;
+
+        let client = CopilotClient::new(&server.uri()).unwrap();
+        let events: Vec<RunEvent> = client
+            .watch_run("r1")
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(events.len(), 3);
+        assert!(events[0].kind.is_none());
+        assert_eq!(events[1].kind.as_deref(), Some("step_retried"));
+        assert_eq!((events[1].attempt, events[1].error.as_deref()), (Some(1), Some("exit 1")));
+        assert!(!events[1].is_last() && events[2].is_last());
+        assert!(client.watch_run("missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validation_cache_reuses_body_on_not_modified() {
+        use wiremock::matchers::{header as header_eq, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/context"))
+            .and(header_eq("if-none-match", "\"abc\""))
+            .respond_with(ResponseTemplate::new(304).insert_header("etag", "\"abc\""))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/context"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"abc\"")
+                    .set_body_json(Vec::<ContextItem>::new()),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = CopilotClient::builder()
+            .base_url(server.uri())
+            .validation_cache(16)
+            .build()
+            .unwrap();
+
+        assert!(client.list_context(None).await.unwrap().is_empty());
+        assert!(client.list_context(None).await.unwrap().is_empty());
+    }
+
     #[test]
     fn test_url_building() {
         let client = CopilotClient::new("http://localhost:8080").unwrap();
@@ -0,0 +1,448 @@
+//! Python model generation
+//!
+//! Renders the SDK's wire models as pydantic classes for the Python SDK, so
+//! both clients deserialize exactly the same payloads. The output is checked
+//! in at `sdks/python/llm_copilot/generated.py`; regenerate it with
+//!
+//! ```text
+//! cargo run -p copilot-sdk --example generate_python
+//! ```
+//!
+//! and a unit test fails whenever the checked-in file falls behind the Rust
+//! models.
+
+use crate::models::*;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::Value;
+use std::fmt::Write;
+
+/// Path of the generated module, relative to the repository root
+pub const PYTHON_MODELS_PATH: &str = "sdks/python/llm_copilot/generated.py";
+
+/// Collects model schemas in the order their classes are emitted
+struct ModelSet {
+    generator: SchemaGenerator,
+    names: Vec<String>,
+}
+
+impl ModelSet {
+    fn new() -> Self {
+        Self {
+            generator: SchemaSettings::draft07().into_generator(),
+            names: Vec::new(),
+        }
+    }
+
+    /// Register a model; anything it references must be registered first
+    fn add<T: JsonSchema>(&mut self) -> &mut Self {
+        self.generator.subschema_for::<T>();
+        self.names.push(T::schema_name());
+        self
+    }
+}
+
+/// Wire models shared with the Python SDK, dependencies first
+fn models() -> ModelSet {
+    let mut set = ModelSet::new();
+    set.add::<FunctionCall>()
+        .add::<Usage>()
+        .add::<MessagePart>()
+        .add::<Message>()
+        .add::<ChatRequest>()
+        .add::<PreferenceKey>()
+        .add::<PreferenceSuggestion>()
+        .add::<ContextDownsize>()
+        .add::<ChatResponse>()
+        .add::<Conversation>()
+        .add::<Session>()
+        .add::<SessionMessage>()
+        .add::<WindowEntry>()
+        .add::<CapturedResponse>()
+        .add::<ReplayCapture>()
+        .add::<ReplaySummary>()
+        .add::<ContextItem>()
+        .add::<ContextSearchResult>()
+        .add::<WorkflowStep>()
+        .add::<Workflow>()
+        .add::<WorkflowSummary>()
+        .add::<WorkflowExecution>()
+        .add::<WorkflowStatus>()
+        .add::<TemplateDocs>()
+        .add::<TemplateSummary>()
+        .add::<TemplateParameter>()
+        .add::<ParameterSchema>()
+        .add::<ManualRun>()
+        .add::<RunEvent>()
+        .add::<ParameterError>()
+        .add::<WorkflowSchedule>()
+        .add::<SchedulePreview>()
+        .add::<Trust>()
+        .add::<IngestedDocument>()
+        .add::<IngestionJob>()
+        .add::<SpaceCoverage>()
+        .add::<BootstrapFailure>()
+        .add::<BootstrapReport>()
+        .add::<BootstrapJob>()
+        .add::<SafetyCategory>()
+        .add::<SafetyFinding>()
+        .add::<QuarantinedChunk>()
+        .add::<QuarantinedDocument>()
+        .add::<ClaimSupport>()
+        .add::<GroundednessScore>()
+        .add::<GroundednessReview>()
+        .add::<GroundednessStats>()
+        .add::<QueryTopic>()
+        .add::<KnowledgeGapReport>()
+        .add::<IntentMetrics>()
+        .add::<ConversationReport>()
+        .add::<Resolution>()
+        .add::<RetrievalOverrides>()
+        .add::<ExperimentVariant>()
+        .add::<ExperimentSpec>()
+        .add::<Experiment>()
+        .add::<MetricSummary>()
+        .add::<VariantResults>()
+        .add::<SignificanceTest>()
+        .add::<ExperimentResults>()
+        .add::<ResidencyViolation>()
+        .add::<ResidencyReport>()
+        .add::<CodeFinding>()
+        .add::<CodePolicyDecision>()
+        .add::<ImpersonationConsent>()
+        .add::<ImpersonationRequest>()
+        .add::<Impersonation>()
+        .add::<ImpersonationToken>()
+        .add::<ReportPeriod>()
+        .add::<AuditEventRecord>()
+        .add::<AuditEvidence>()
+        .add::<RoleEvidence>()
+        .add::<RetentionSetting>()
+        .add::<AccessReviewRequest>()
+        .add::<AccessReview>()
+        .add::<ComplianceReport>()
+        .add::<ImportFailure>()
+        .add::<UserImportReport>()
+        .add::<JsonWebKey>()
+        .add::<JsonWebKeySet>()
+        .add::<WebhookEndpoint>()
+        .add::<CreateWebhookRequest>()
+        .add::<WebhookEndpointUpdate>()
+        .add::<WebhookSecret>()
+        .add::<WebhookPingResult>()
+        .add::<WebhookEventTypeStats>()
+        .add::<WebhookStats>()
+        .add::<WebhookSchemaVersion>()
+        .add::<WebhookPayloadSchemas>()
+        .add::<BulkContextOperation>()
+        .add::<BulkContextRequest>()
+        .add::<BulkContextJob>()
+        .add::<FileUploadRequest>()
+        .add::<PresignedUrl>()
+        .add::<FileUpload>()
+        .add::<StoredObject>()
+        .add::<ExportBundle>()
+        .add::<DashboardRequest>()
+        .add::<PushedDashboard>()
+        .add::<GeneratedDashboard>()
+        .add::<AlertRuleRequest>()
+        .add::<AlertRule>()
+        .add::<AlertBacktest>()
+        .add::<GeneratedAlertRule>()
+        .add::<LogAnalysisRequest>()
+        .add::<LogPattern>()
+        .add::<LogFinding>()
+        .add::<LogAnalysisReport>()
+        .add::<Sandbox>()
+        .add::<ExecutionResult>()
+        .add::<ServiceHealth>()
+        .add::<HealthResponse>()
+        .add::<VersionInfo>()
+        .add::<TaskQueueStats>()
+        .add::<RecentError>()
+        .add::<DashboardSnapshot>()
+        .add::<DiffHunk>()
+        .add::<FileEdit>()
+        .add::<ProposedEdits>()
+        .add::<HandoffRequest>()
+        .add::<HandoffBundle>()
+        .add::<HandoffDelivery>()
+        .add::<HandoffResponse>()
+        .add::<PrefetchOutcome>()
+        .add::<PrefetchStats>()
+        .add::<EmbeddingCacheStats>()
+        .add::<EmbeddingWarmup>()
+        .add::<ContextWarmup>()
+        .add::<CheckRunContext>()
+        .add::<GateTarget>()
+        .add::<GateRequest>()
+        .add::<GateCheck>()
+        .add::<CheckOutput>()
+        .add::<GateVerdict>()
+        .add::<Verbosity>()
+        .add::<UserPreferences>()
+        .add::<PreferencesResponse>();
+    set
+}
+
+/// Render the generated Python module
+pub fn python_models() -> String {
+    let set = models();
+    let definitions = set.generator.definitions();
+
+    let mut classes = String::new();
+    let mut uses_any = false;
+    let mut uses_optional = false;
+    for name in &set.names {
+        let class = PythonClass::new(name, &definitions[name]);
+        uses_any |= class.body.contains("Any");
+        uses_optional |= class.body.contains("Optional[");
+        let _ = write!(classes, "\n\n{}", class.body);
+    }
+
+    let mut typing = Vec::new();
+    if uses_any {
+        typing.push("Any");
+    }
+    if uses_optional {
+        typing.push("Optional");
+    }
+
+    let mut out = String::new();
+    out.push_str("\"\"\"\n");
+    out.push_str("Wire models generated from the Rust SDK (crates/copilot-sdk/src/models.rs).\n\n");
+    out.push_str("Do not edit by hand; regenerate with:\n\n");
+    out.push_str("    cargo run -p copilot-sdk --example generate_python\n");
+    out.push_str("\"\"\"\n\n");
+    if !typing.is_empty() {
+        let _ = writeln!(out, "from typing import {}", typing.join(", "));
+    }
+    out.push_str("from pydantic import BaseModel, Field\n\n");
+    out.push_str("__all__ = [\n");
+    for name in &set.names {
+        let _ = writeln!(out, "    \"{}\",", name);
+    }
+    out.push_str("]\n");
+    out.push_str(&classes);
+    out
+}
+
+struct PythonClass {
+    body: String,
+}
+
+impl PythonClass {
+    fn new(name: &str, schema: &Schema) -> Self {
+        let mut body = format!("class {}(BaseModel):\n", name);
+        let Schema::Object(schema) = schema else {
+            body.push_str("    pass\n");
+            return Self { body };
+        };
+
+        let description = schema
+            .metadata
+            .as_ref()
+            .and_then(|m| m.description.as_ref());
+        if let Some(description) = description {
+            let _ = writeln!(body, "    \"\"\"{}\"\"\"\n", description.trim());
+        }
+
+        let Some(object) = schema.object.as_ref().filter(|o| !o.properties.is_empty()) else {
+            body.push_str("    pass\n");
+            return Self { body };
+        };
+
+        // Required fields first, then the defaulted ones, each in declaration order
+        let mut fields: Vec<_> = object.properties.iter().collect();
+        fields.sort_by_key(|(field, _)| !object.required.contains(*field));
+        for (field, property) in fields {
+            let property = serde_json::to_value(property).unwrap_or_default();
+            let line = field_line(field, &property, object.required.contains(field));
+            let _ = writeln!(body, "    {}", line);
+        }
+
+        Self { body }
+    }
+}
+
+/// Python keywords; fields named after one are renamed with a trailing
+/// underscore and aliased to their wire name
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+    "continue", "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if",
+    "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try",
+    "while", "with", "yield",
+];
+
+fn field_line(field: &str, schema: &Value, required: bool) -> String {
+    let mut ty = python_type(schema);
+    // The `Field(...)` argument giving the default, if there is one
+    let default = if required {
+        None
+    } else if ty.starts_with("Optional[") {
+        Some("default=None".to_string())
+    } else {
+        match schema.get("default") {
+            Some(Value::Array(items)) if items.is_empty() => Some("default_factory=list".to_string()),
+            Some(Value::Object(map)) if map.is_empty() => Some("default_factory=dict".to_string()),
+            Some(default) => Some(format!("default={}", python_literal(default))),
+            None => {
+                ty = format!("Optional[{}]", ty);
+                Some("default=None".to_string())
+            }
+        }
+    };
+
+    if PYTHON_KEYWORDS.contains(&field) {
+        let default = default.map(|arg| format!("{}, ", arg)).unwrap_or_default();
+        return format!("{}_: {} = Field({}alias=\"{}\")", field, ty, default, field);
+    }
+    match default {
+        None => format!("{}: {}", field, ty),
+        Some(arg) => match arg.strip_prefix("default=") {
+            Some(value) => format!("{}: {} = {}", field, ty, value),
+            None => format!("{}: {} = Field({})", field, ty, arg),
+        },
+    }
+}
+
+/// Python annotation for a JSON schema
+fn python_type(schema: &Value) -> String {
+    let object = match schema {
+        Value::Object(object) => object,
+        // `true` accepts anything, e.g. `serde_json::Value`
+        _ => return "Any".to_string(),
+    };
+
+    if let Some(reference) = object.get("$ref").and_then(Value::as_str) {
+        return reference.rsplit('/').next().unwrap_or("Any").to_string();
+    }
+    for key in ["allOf", "anyOf", "oneOf"] {
+        if let Some(variants) = object.get(key).and_then(Value::as_array) {
+            let (nulls, others): (Vec<_>, Vec<_>) = variants.iter().partition(|v| is_null(v));
+            let inner = match others.as_slice() {
+                [single] => python_type(single),
+                _ => "Any".to_string(),
+            };
+            return if nulls.is_empty() {
+                inner
+            } else {
+                format!("Optional[{}]", inner)
+            };
+        }
+    }
+
+    match object.get("type") {
+        Some(Value::String(ty)) => primitive_type(ty, object),
+        Some(Value::Array(types)) => {
+            let names: Vec<&str> = types.iter().filter_map(Value::as_str).collect();
+            let non_null: Vec<&str> = names.iter().copied().filter(|t| *t != "null").collect();
+            let inner = match non_null.as_slice() {
+                [single] => primitive_type(single, object),
+                _ => "Any".to_string(),
+            };
+            if names.contains(&"null") {
+                format!("Optional[{}]", inner)
+            } else {
+                inner
+            }
+        }
+        _ => "Any".to_string(),
+    }
+}
+
+fn primitive_type(ty: &str, object: &serde_json::Map<String, Value>) -> String {
+    match ty {
+        "string" => "str".to_string(),
+        "integer" => "int".to_string(),
+        "number" => "float".to_string(),
+        "boolean" => "bool".to_string(),
+        "array" => {
+            let items = object.get("items").map(python_type);
+            format!("list[{}]", items.unwrap_or_else(|| "Any".to_string()))
+        }
+        "object" => {
+            let values = object.get("additionalProperties").map(python_type);
+            format!("dict[str, {}]", values.unwrap_or_else(|| "Any".to_string()))
+        }
+        _ => "Any".to_string(),
+    }
+}
+
+fn is_null(schema: &Value) -> bool {
+    schema.get("type").and_then(Value::as_str) == Some("null")
+}
+
+fn python_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "None".to_string(),
+        Value::Bool(true) => "True".to_string(),
+        Value::Bool(false) => "False".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_python_is_current() {
+        let checked_in = include_str!("../../../sdks/python/llm_copilot/generated.py");
+        assert!(
+            checked_in == python_models(),
+            "{} is stale; run `cargo run -p copilot-sdk --example generate_python`",
+            PYTHON_MODELS_PATH
+        );
+    }
+
+    #[test]
+    fn test_every_model_is_registered() {
+        let set = models();
+        let mut derives_schema = false;
+        for line in include_str!("models.rs").lines() {
+            if line.starts_with("#[derive(") {
+                derives_schema = line.contains("JsonSchema");
+                continue;
+            }
+            let Some(declared) = line.strip_prefix("pub struct ").or_else(|| line.strip_prefix("pub enum ")) else {
+                continue;
+            };
+            let name: String = declared.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+            assert!(!derives_schema || set.names.contains(&name), "{} is not registered in models()", name);
+            derives_schema = false;
+        }
+        // Referenced models must be registered too, or their classes are missing
+        for name in set.generator.definitions().keys() {
+            assert!(set.names.contains(name), "{} is referenced but not registered", name);
+        }
+    }
+
+    #[test]
+    fn test_keyword_fields_are_aliased() {
+        assert_eq!(field_line("for", &serde_json::json!({"type": "string"}), true), "for_: str = Field(alias=\"for\")");
+        assert_eq!(
+            field_line("in", &serde_json::json!({"type": ["string", "null"]}), false),
+            "in_: Optional[str] = Field(default=None, alias=\"in\")"
+        );
+        assert_eq!(
+            field_line("as", &serde_json::json!({"type": "array", "items": {"type": "string"}, "default": []}), false),
+            "as_: list[str] = Field(default_factory=list, alias=\"as\")"
+        );
+    }
+
+    #[test]
+    fn test_field_rendering() {
+        let output = python_models();
+        assert!(output.contains("class ChatRequest(BaseModel):"));
+        assert!(output.contains("    message: str\n"));
+        assert!(output.contains("    temperature: Optional[float] = None\n"));
+        assert!(output.contains("    stream: bool = False\n"));
+        assert!(output.contains("    messages: list[Message] = Field(default_factory=list)\n"));
+        assert!(output
+            .contains("    services: dict[str, ServiceHealth] = Field(default_factory=dict)\n"));
+        assert!(output.contains("    type: str\n"));
+        assert!(output.contains("    config: Optional[Any] = None\n"));
+    }
+}
@@ -0,0 +1,748 @@
+//! The HTTP contract between the SDK and the server
+//!
+//! [`Contract`] lists every JSON endpoint the client calls, with the request
+//! and response schemas of the wire models in [`crate::models`]. It is
+//! rendered as an OpenAPI document checked in at `api/schemas/sdk-openapi.json`;
+//! regenerate it with
+//!
+//! ```text
+//! cargo run -p copilot-sdk --example generate_openapi
+//! ```
+//!
+//! and a unit test fails whenever the checked-in file falls behind the Rust
+//! models. The mock server (`mock` feature) answers from the same contract
+//! and rejects canned responses that break it. Streaming endpoints
+//! (`/chat/stream`, `/dashboard/events`) are not part of the contract.
+
+use crate::models::*;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+use std::fmt;
+
+/// Path of the OpenAPI document, relative to the repository root
+pub const OPENAPI_PATH: &str = "api/schemas/sdk-openapi.json";
+
+/// One endpoint of the contract
+#[derive(Debug, Clone)]
+pub struct Operation {
+    /// Name of the client method calling the endpoint
+    pub name: &'static str,
+    /// Upper-case HTTP method
+    pub method: &'static str,
+    /// Path template, e.g. `/api/v1/sessions/{session_id}`
+    pub path: &'static str,
+    pub summary: &'static str,
+    /// Schema of the JSON request body
+    pub request: Option<Value>,
+    /// Schema of the JSON response body; `None` for `204 No Content`
+    pub response: Option<Value>,
+}
+
+impl Operation {
+    /// Status of a successful response
+    pub fn status(&self) -> u16 {
+        if self.response.is_some() {
+            200
+        } else {
+            204
+        }
+    }
+
+    /// Whether `path` (without query string) is an instance of the template
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        if !self.method.eq_ignore_ascii_case(method) {
+            return false;
+        }
+        let mut template = self.path.split('/');
+        let mut segments = path.split('/');
+        loop {
+            match (template.next(), segments.next()) {
+                (None, None) => return true,
+                (Some(t), Some(s)) if t.starts_with('{') && !s.is_empty() => {}
+                (Some(t), Some(s)) if t == s => {}
+                _ => return false,
+            }
+        }
+    }
+
+    /// Names of the path parameters
+    pub fn parameters(&self) -> impl Iterator<Item = &'static str> {
+        self.path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+    }
+}
+
+/// A value that does not match its schema
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub struct ContractViolation {
+    /// JSON pointer to the offending value
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for ContractViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() { "/" } else { &self.pointer };
+        write!(f, "{}: {}", pointer, self.message)
+    }
+}
+
+/// The SDK's endpoints and the schemas of their payloads
+#[derive(Debug, Clone)]
+pub struct Contract {
+    operations: Vec<Operation>,
+    schemas: Map<String, Value>,
+}
+
+impl Default for Contract {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Contract {
+    pub fn new() -> Self {
+        let mut generator = SchemaSettings::openapi3().into_generator();
+        let operations = operations(&mut generator);
+        let schemas = generator
+            .take_definitions()
+            .into_iter()
+            .map(|(name, schema)| (name, serde_json::to_value(schema).unwrap_or_default()))
+            .collect();
+        Self { operations, schemas }
+    }
+
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    /// The operation serving `method path`; `path` may carry a query string
+    pub fn find(&self, method: &str, path: &str) -> Option<&Operation> {
+        let path = path.split('?').next().unwrap_or_default();
+        self.operations.iter().find(|op| op.matches(method, path))
+    }
+
+    /// Check a response body of `method path` against the contract
+    pub fn validate_response(&self, method: &str, path: &str, body: &Value) -> Result<(), ContractViolation> {
+        let op = self.find(method, path).ok_or_else(|| ContractViolation {
+            pointer: String::new(),
+            message: format!("{} {} is not part of the contract", method, path),
+        })?;
+        match &op.response {
+            Some(schema) => self.validate(schema, body),
+            None => Err(ContractViolation {
+                pointer: String::new(),
+                message: format!("{} {} responds without a body", op.method, op.path),
+            }),
+        }
+    }
+
+    /// Check `value` against `schema`, an OpenAPI 3.0 schema object that may
+    /// refer to the contract's component schemas
+    pub fn validate(&self, schema: &Value, value: &Value) -> Result<(), ContractViolation> {
+        self.check(schema, value, &mut String::new())
+    }
+
+    /// A minimal value matching `schema`: required properties only, one item
+    /// per array and placeholder scalars
+    pub fn example(&self, schema: &Value) -> Value {
+        let Some(schema) = schema.as_object() else {
+            return Value::Null;
+        };
+        if let Some(target) = self.reference(schema) {
+            return self.example(target);
+        }
+        if let Some(value) = schema.get("enum").and_then(|e| e.get(0)) {
+            return value.clone();
+        }
+        for key in ["allOf", "oneOf", "anyOf"] {
+            if let Some(first) = schema.get(key).and_then(|s| s.get(0)) {
+                return self.example(first);
+            }
+        }
+        match schema.get("type").and_then(Value::as_str) {
+            Some("object") => {
+                let properties = schema.get("properties").and_then(Value::as_object);
+                let mut object = Map::new();
+                for name in required(schema) {
+                    let property = properties.and_then(|p| p.get(name)).unwrap_or(&Value::Null);
+                    object.insert(name.to_string(), self.example(property));
+                }
+                Value::Object(object)
+            }
+            Some("array") => match schema.get("items") {
+                Some(items) => json!([self.example(items)]),
+                None => json!([]),
+            },
+            Some("string") => json!("string"),
+            Some("integer") => json!(schema.get("minimum").and_then(Value::as_i64).unwrap_or(0).max(0)),
+            Some("number") => json!(0.0),
+            Some("boolean") => json!(false),
+            _ => Value::Null,
+        }
+    }
+
+    /// The OpenAPI document describing the contract
+    pub fn openapi(&self) -> Value {
+        let mut paths = Map::new();
+        for op in &self.operations {
+            let mut operation = Map::new();
+            operation.insert("operationId".into(), json!(op.name));
+            operation.insert("summary".into(), json!(op.summary));
+            let parameters: Vec<Value> = op
+                .parameters()
+                .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }))
+                .collect();
+            if !parameters.is_empty() {
+                operation.insert("parameters".into(), json!(parameters));
+            }
+            if let Some(request) = &op.request {
+                operation.insert(
+                    "requestBody".into(),
+                    json!({ "required": true, "content": { "application/json": { "schema": request } } }),
+                );
+            }
+            let response = match &op.response {
+                Some(schema) => json!({ "200": {
+                    "description": "OK",
+                    "content": { "application/json": { "schema": schema } }
+                } }),
+                None => json!({ "204": { "description": "No Content" } }),
+            };
+            operation.insert("responses".into(), response);
+
+            let item = paths.entry(op.path).or_insert_with(|| json!({}));
+            item[op.method.to_ascii_lowercase()] = Value::Object(operation);
+        }
+
+        json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": "LLM CoPilot Agent SDK contract",
+                "version": crate::VERSION,
+                "description": "Endpoints called by the Rust SDK (crates/copilot-sdk). \
+                    Generated; regenerate with `cargo run -p copilot-sdk --example generate_openapi`."
+            },
+            "paths": paths,
+            "components": {
+                "schemas": self.schemas,
+                "securitySchemes": { "bearerAuth": { "type": "http", "scheme": "bearer" } }
+            },
+            "security": [{ "bearerAuth": [] }]
+        })
+    }
+
+    /// The OpenAPI document as checked in
+    pub fn openapi_json(&self) -> String {
+        let mut out = serde_json::to_string_pretty(&self.openapi()).unwrap_or_default();
+        out.push('\n');
+        out
+    }
+
+    fn reference<'a>(&'a self, schema: &Map<String, Value>) -> Option<&'a Value> {
+        let name = schema.get("$ref")?.as_str()?.strip_prefix("#/components/schemas/")?;
+        self.schemas.get(name)
+    }
+
+    fn check(&self, schema: &Value, value: &Value, pointer: &mut String) -> Result<(), ContractViolation> {
+        let violation = |pointer: &str, message: String| ContractViolation {
+            pointer: pointer.to_string(),
+            message,
+        };
+        let Some(schema) = schema.as_object() else {
+            return Ok(());
+        };
+        if let Some(reference) = schema.get("$ref") {
+            let target = self
+                .reference(schema)
+                .ok_or_else(|| violation(pointer, format!("unknown schema {}", reference)))?;
+            return self.check(target, value, pointer);
+        }
+        if value.is_null() && schema.get("nullable") == Some(&Value::Bool(true)) {
+            return Ok(());
+        }
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            for part in all {
+                self.check(part, value, pointer)?;
+            }
+        }
+        for key in ["oneOf", "anyOf"] {
+            if let Some(options) = schema.get(key).and_then(Value::as_array) {
+                if !options.iter().any(|option| self.check(option, value, &mut pointer.clone()).is_ok()) {
+                    return Err(violation(pointer, format!("matches none of the {} schemas", key)));
+                }
+            }
+        }
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+            if !allowed.contains(value) {
+                return Err(violation(pointer, format!("{} is not one of {}", value, Value::from(allowed.clone()))));
+            }
+        }
+
+        let Some(ty) = schema.get("type").and_then(Value::as_str) else {
+            return Ok(());
+        };
+        let type_matches = match ty {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "boolean" => value.is_boolean(),
+            _ => true,
+        };
+        if !type_matches {
+            return Err(violation(pointer, format!("expected {}, found {}", ty, value)));
+        }
+        if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+            if value.as_f64().is_some_and(|n| n < minimum) {
+                return Err(violation(pointer, format!("{} is below the minimum {}", value, minimum)));
+            }
+        }
+
+        if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+            for (index, item) in array.iter().enumerate() {
+                let len = pointer.len();
+                pointer.push_str(&format!("/{}", index));
+                self.check(items, item, pointer)?;
+                pointer.truncate(len);
+            }
+        }
+        if let Some(object) = value.as_object() {
+            for name in required(schema) {
+                if !object.contains_key(name) {
+                    return Err(violation(pointer, format!("missing required property '{}'", name)));
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, field) in object {
+                let field_schema = properties
+                    .and_then(|p| p.get(name))
+                    .or_else(|| schema.get("additionalProperties").filter(|a| a.is_object()));
+                if let Some(field_schema) = field_schema {
+                    let len = pointer.len();
+                    pointer.push_str(&format!("/{}", name));
+                    self.check(field_schema, field, pointer)?;
+                    pointer.truncate(len);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn required(schema: &Map<String, Value>) -> impl Iterator<Item = &str> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+fn schema<T: JsonSchema>(generator: &mut SchemaGenerator) -> Option<Value> {
+    serde_json::to_value(generator.subschema_for::<T>()).ok()
+}
+
+/// The `{ success, data, error }` wrapper of the ingestion, session and
+/// dashboard endpoints
+fn envelope<T: JsonSchema>(generator: &mut SchemaGenerator) -> Option<Value> {
+    Some(json!({
+        "type": "object",
+        "required": ["success", "data"],
+        "properties": {
+            "success": { "type": "boolean" },
+            "data": schema::<T>(generator),
+            "error": { "type": "string", "nullable": true }
+        }
+    }))
+}
+
+/// An inline request object; fields not listed in `required` are optional
+fn body(properties: Value, required: &[&str]) -> Option<Value> {
+    Some(json!({ "type": "object", "required": required, "properties": properties }))
+}
+
+fn operations(gen: &mut SchemaGenerator) -> Vec<Operation> {
+    let op = |name, method, path, summary, request, response| Operation {
+        name,
+        method,
+        path,
+        summary,
+        request,
+        response,
+    };
+    let string = json!({ "type": "string" });
+    let optional_string = json!({ "type": "string", "nullable": true });
+
+    vec![
+        op("chat", "POST", "/api/v1/chat", "Send a chat message",
+            schema::<ChatRequest>(gen), schema::<ChatResponse>(gen)),
+        op("list_conversations", "GET", "/api/v1/conversations", "List conversations",
+            None, schema::<Vec<Conversation>>(gen)),
+        op("get_conversation", "GET", "/api/v1/conversations/{conversation_id}", "Get a conversation",
+            None, schema::<Conversation>(gen)),
+        op("delete_conversation", "DELETE", "/api/v1/conversations/{conversation_id}", "Delete a conversation",
+            None, None),
+        op("add_context", "POST", "/api/v1/context", "Add context",
+            body(json!({
+                "source": string,
+                "content": string,
+                "tags": { "type": "array", "items": string }
+            }), &["source", "content", "tags"]),
+            schema::<ContextItem>(gen)),
+        op("list_context", "GET", "/api/v1/context", "List context items",
+            None, schema::<Vec<ContextItem>>(gen)),
+        op("clear_context", "DELETE", "/api/v1/context", "Clear context",
+            None, None),
+        op("search_context", "GET", "/api/v1/context/search", "Search context",
+            None, envelope::<Vec<ContextSearchResult>>(gen)),
+        op("bulk_context", "POST", "/api/v1/context/bulk", "Delete, retag, re-embed or export matching context items",
+            schema::<BulkContextRequest>(gen), envelope::<BulkContextJob>(gen)),
+        op("get_bulk_context_job", "GET", "/api/v1/context/bulk/jobs/{job_id}", "Get a bulk context job",
+            None, envelope::<BulkContextJob>(gen)),
+        op("export_bulk_context_job", "GET", "/api/v1/context/bulk/jobs/{job_id}/export",
+            "Download exported context items as newline-delimited JSON",
+            None, None),
+        op("store_bulk_context_export", "POST", "/api/v1/context/bulk/jobs/{job_id}/bundle",
+            "Store exported context items as an export bundle and link to it",
+            None, envelope::<ExportBundle>(gen)),
+        op("create_file_upload", "POST", "/api/v1/files",
+            "Get a presigned URL to upload an attachment or sandbox artifact",
+            schema::<FileUploadRequest>(gen), envelope::<FileUpload>(gen)),
+        op("get_file_download", "GET", "/api/v1/files/{class}/{sha256}",
+            "Get a presigned URL to download a stored file",
+            None, envelope::<PresignedUrl>(gen)),
+        op("generate_dashboard", "POST", "/api/v1/dashboards/generate",
+            "Generate a Grafana dashboard from a natural language ask",
+            schema::<DashboardRequest>(gen), envelope::<GeneratedDashboard>(gen)),
+        op("generate_alert_rule", "POST", "/api/v1/alerts/generate",
+            "Generate an alert rule from a natural language description and back-test it",
+            schema::<AlertRuleRequest>(gen), envelope::<GeneratedAlertRule>(gen)),
+        op("analyze_logs", "POST", "/api/v1/logs/analyze",
+            "Cluster log lines into patterns, flag anomalies and summarize them",
+            schema::<LogAnalysisRequest>(gen), envelope::<LogAnalysisReport>(gen)),
+        op("ingest_stream", "POST", "/api/v1/ingest", "Stream a document into the knowledge base",
+            None, envelope::<IngestionJob>(gen)),
+        op("get_ingestion_job", "GET", "/api/v1/ingest/jobs/{job_id}", "Get an ingestion job",
+            None, envelope::<IngestionJob>(gen)),
+        op("bootstrap_corpus", "POST", "/api/v1/ingest/bootstrap",
+            "Bootstrap the knowledge base from a Confluence or markdown wiki export",
+            None, envelope::<BootstrapJob>(gen)),
+        op("get_bootstrap_job", "GET", "/api/v1/ingest/bootstrap/{job_id}", "Get a bootstrap job and its coverage report",
+            None, envelope::<BootstrapJob>(gen)),
+        op("list_quarantine", "GET", "/api/v1/ingest/quarantine", "List documents held for review",
+            None, envelope::<Vec<QuarantinedDocument>>(gen)),
+        op("get_quarantined_document", "GET", "/api/v1/ingest/quarantine/{document_id}",
+            "Get a quarantined document",
+            None, envelope::<QuarantinedDocument>(gen)),
+        op("release_quarantined_document", "POST", "/api/v1/ingest/quarantine/{document_id}/release",
+            "Store a reviewed document's held chunks",
+            None, envelope::<QuarantinedDocument>(gen)),
+        op("discard_quarantined_document", "DELETE", "/api/v1/ingest/quarantine/{document_id}",
+            "Drop a quarantined document's held chunks",
+            None, None),
+        op("list_replays", "GET", "/api/v1/replays", "List captured chat turns, newest first",
+            None, envelope::<Vec<ReplaySummary>>(gen)),
+        op("get_replay", "GET", "/api/v1/replays/{replay_id}", "Get a captured chat turn to replay",
+            None, envelope::<ReplayCapture>(gen)),
+        op("get_groundedness_stats", "GET", "/api/v1/groundedness/stats",
+            "Get how well answers are grounded in their context",
+            None, envelope::<GroundednessStats>(gen)),
+        op("list_groundedness_reviews", "GET", "/api/v1/groundedness/reviews",
+            "List poorly grounded answers awaiting review",
+            None, envelope::<Vec<GroundednessReview>>(gen)),
+        op("resolve_groundedness_review", "DELETE", "/api/v1/groundedness/reviews/{review_id}",
+            "Take a reviewed answer off the review queue",
+            None, None),
+        op("get_knowledge_gaps", "GET", "/api/v1/analytics/knowledge-gaps",
+            "Get the topics users ask about and the ones answered with low confidence or without sources",
+            None, envelope::<KnowledgeGapReport>(gen)),
+        op("get_conversation_analytics", "GET", "/api/v1/analytics/conversations",
+            "Get the intents of recent conversations and how often they were resolved or escalated",
+            None, envelope::<ConversationReport>(gen)),
+        op("list_experiments", "GET", "/api/v1/experiments", "List A/B experiments",
+            None, envelope::<Vec<Experiment>>(gen)),
+        op("create_experiment", "POST", "/api/v1/experiments", "Start an A/B experiment",
+            schema::<ExperimentSpec>(gen), envelope::<Experiment>(gen)),
+        op("get_experiment_results", "GET", "/api/v1/experiments/{experiment_id}",
+            "Get an experiment's metrics per variant and significance tests against the control",
+            None, envelope::<ExperimentResults>(gen)),
+        op("stop_experiment", "POST", "/api/v1/experiments/{experiment_id}/stop",
+            "Stop assigning sessions to an experiment",
+            None, envelope::<Experiment>(gen)),
+        op("enroll_in_experiment", "PUT", "/api/v1/experiments/{experiment_id}/enrollment",
+            "Enroll the caller's tenant in an experiment",
+            None, envelope::<Experiment>(gen)),
+        op("withdraw_from_experiment", "DELETE", "/api/v1/experiments/{experiment_id}/enrollment",
+            "Withdraw the caller's tenant from an experiment",
+            None, envelope::<Experiment>(gen)),
+        op("get_residency_report", "GET", "/api/v1/governance/residency",
+            "Get the tenant's home region and refused cross-region access",
+            None, envelope::<ResidencyReport>(gen)),
+        op("list_code_policy_decisions", "GET", "/api/v1/governance/code-policy",
+            "List responses the tenant's code policy annotated or blocked",
+            None, envelope::<Vec<CodePolicyDecision>>(gen)),
+        op("list_workflows", "GET", "/api/v1/workflows", "List workflows",
+            None, schema::<Vec<WorkflowSummary>>(gen)),
+        op("get_workflow", "GET", "/api/v1/workflows/{workflow_id}", "Get a workflow",
+            None, schema::<Workflow>(gen)),
+        op("start_workflow", "POST", "/api/v1/workflows/{workflow_id}/run", "Start a workflow execution",
+            body(json!({ "input": { "type": "object", "additionalProperties": true } }), &["input"]),
+            schema::<WorkflowExecution>(gen)),
+        op("get_workflow_status", "GET", "/api/v1/executions/{execution_id}", "Get workflow execution status",
+            None, schema::<WorkflowStatus>(gen)),
+        op("cancel_workflow", "POST", "/api/v1/executions/{execution_id}/cancel", "Cancel a workflow execution",
+            None, None),
+        op("list_templates", "GET", "/api/v1/templates", "List workflow templates",
+            None, envelope::<Vec<TemplateSummary>>(gen)),
+        op("template_parameters", "GET", "/api/v1/templates/{template_id}/parameters",
+            "Get the parameters of a workflow template",
+            None, envelope::<ParameterSchema>(gen)),
+        op("run_template", "POST", "/api/v1/templates/{template_id}/runs", "Run a workflow template",
+            body(json!({
+                "params": { "type": "object", "additionalProperties": true },
+                "dry_run": { "type": "boolean" }
+            }), &["params"]),
+            envelope::<ManualRun>(gen)),
+        op("list_schedules", "GET", "/api/v1/schedules", "List workflow schedules",
+            None, envelope::<Vec<WorkflowSchedule>>(gen)),
+        op("get_schedule", "GET", "/api/v1/schedules/{schedule_id}", "Get a workflow schedule",
+            None, envelope::<WorkflowSchedule>(gen)),
+        op("pause_schedule", "POST", "/api/v1/schedules/{schedule_id}/pause", "Pause a workflow schedule",
+            None, envelope::<WorkflowSchedule>(gen)),
+        op("resume_schedule", "POST", "/api/v1/schedules/{schedule_id}/resume", "Resume a workflow schedule",
+            None, envelope::<WorkflowSchedule>(gen)),
+        op("preview_schedule", "POST", "/api/v1/schedules/preview", "Preview the fire times of a schedule",
+            body(json!({
+                "schedule": { "type": "object", "additionalProperties": true },
+                "count": { "type": "integer", "minimum": 1, "maximum": 100 }
+            }), &["schedule"]),
+            envelope::<SchedulePreview>(gen)),
+        op("list_sandboxes", "GET", "/api/v1/sandboxes", "List sandboxes",
+            None, schema::<Vec<Sandbox>>(gen)),
+        op("get_sandbox", "GET", "/api/v1/sandboxes/{sandbox_id}", "Get sandbox status",
+            None, schema::<Sandbox>(gen)),
+        op("execute_code", "POST", "/api/v1/sandbox/execute", "Execute code in a sandbox",
+            body(json!({
+                "code": string,
+                "runtime": string,
+                "timeout": { "type": "integer", "minimum": 0 }
+            }), &["code", "runtime", "timeout"]),
+            schema::<ExecutionResult>(gen)),
+        op("destroy_sandbox", "DELETE", "/api/v1/sandboxes/{sandbox_id}", "Destroy a sandbox",
+            None, None),
+        op("create_session", "POST", "/api/v1/sessions", "Create a session",
+            body(json!({ "model": optional_string }), &[]),
+            schema::<Session>(gen)),
+        op("resume_session", "GET", "/api/v1/sessions/{session_id}", "Get a session",
+            None, schema::<Session>(gen)),
+        op("send_message", "POST", "/api/v1/sessions/{session_id}/messages", "Send a message in a session",
+            schema::<SessionMessage>(gen), envelope::<ChatResponse>(gen)),
+        op("get_history", "GET", "/api/v1/sessions/{session_id}/history", "Get a session's messages",
+            None, schema::<Vec<Message>>(gen)),
+        op("proposed_edits", "GET", "/api/v1/sessions/{session_id}/edits", "Get the edits proposed in a session",
+            None, envelope::<ProposedEdits>(gen)),
+        op("hand_off_session", "POST", "/api/v1/sessions/{session_id}/handoff",
+            "Post a session's handoff document to incident channels or ticket systems",
+            schema::<HandoffRequest>(gen), envelope::<HandoffResponse>(gen)),
+        op("confirm_resolution", "POST", "/api/v1/sessions/{session_id}/resolution",
+            "Record whether a session's goal was resolved",
+            body(json!({ "resolved": { "type": "boolean" } }), &["resolved"]),
+            envelope::<Resolution>(gen)),
+        op("submit_session_feedback", "POST", "/api/v1/sessions/{session_id}/feedback",
+            "Rate whether a session's answers helped",
+            body(json!({ "helpful": { "type": "boolean" } }), &["helpful"]),
+            None),
+        op("prefetch_context", "POST", "/api/v1/sessions/{session_id}/prefetch", "Prefetch context for a partial message",
+            body(json!({ "query": string }), &["query"]),
+            envelope::<PrefetchOutcome>(gen)),
+        op("prefetch_stats", "GET", "/api/v1/prefetch/stats", "Get context prefetch statistics",
+            None, envelope::<PrefetchStats>(gen)),
+        op("embedding_cache_stats", "GET", "/api/v1/embeddings/cache", "Get embedding cache hit rates",
+            None, envelope::<EmbeddingCacheStats>(gen)),
+        op("warm_embedding_cache", "POST", "/api/v1/embeddings/cache/warmup",
+            "Embed and cache the context items matching a filter",
+            body(json!({
+                "tags": { "type": "array", "items": string },
+                "source_prefix": string,
+                "language": string,
+                "content_type": string,
+                "ingested_after": string,
+                "ingested_before": string,
+                "as_of": string,
+            }), &[]),
+            envelope::<EmbeddingWarmup>(gen)),
+        op("warm_up_context", "POST", "/api/v1/context/archive/warmup",
+            "Bring the archived context items matching a filter back into memory",
+            body(json!({
+                "tags": { "type": "array", "items": string },
+                "source_prefix": string,
+                "language": string,
+                "content_type": string,
+                "ingested_after": string,
+                "ingested_before": string,
+                "as_of": string,
+            }), &[]),
+            envelope::<ContextWarmup>(gen)),
+        op("get_preferences", "GET", "/api/v1/preferences",
+            "Get the caller's preferences and the learned ones awaiting confirmation",
+            None, envelope::<PreferencesResponse>(gen)),
+        op("update_preferences", "PUT", "/api/v1/preferences", "Replace the caller's preferences",
+            schema::<UserPreferences>(gen), envelope::<UserPreferences>(gen)),
+        op("delete_preferences", "DELETE", "/api/v1/preferences", "Forget the caller's preferences",
+            None, None),
+        op("confirm_preference", "POST", "/api/v1/preferences/suggestions/{suggestion_id}/confirm",
+            "Accept a learned preference",
+            None, envelope::<UserPreferences>(gen)),
+        op("dismiss_preference", "DELETE", "/api/v1/preferences/suggestions/{suggestion_id}",
+            "Reject a learned preference",
+            None, None),
+        op("get_impersonation_consent", "GET", "/api/v1/impersonation/consent",
+            "Get the caller's consent to be impersonated",
+            None, envelope::<ImpersonationConsent>(gen)),
+        op("grant_impersonation_consent", "PUT", "/api/v1/impersonation/consent",
+            "Let admins of the caller's tenant impersonate them for a while",
+            body(json!({ "hours": { "type": ["integer", "null"], "minimum": 1 } }), &[]),
+            envelope::<ImpersonationConsent>(gen)),
+        op("revoke_impersonation_consent", "DELETE", "/api/v1/impersonation/consent",
+            "Withdraw the caller's consent, ending impersonations of them",
+            None, None),
+        op("list_impersonations", "GET", "/api/v1/admin/impersonations",
+            "List impersonations in the caller's tenant",
+            None, envelope::<Vec<Impersonation>>(gen)),
+        op("start_impersonation", "POST", "/api/v1/admin/impersonations",
+            "Start acting as a consenting user",
+            schema::<ImpersonationRequest>(gen), envelope::<ImpersonationToken>(gen)),
+        op("end_impersonation", "DELETE", "/api/v1/admin/impersonations/{impersonation_id}",
+            "End an impersonation, invalidating its token",
+            None, None),
+        op("get_compliance_report", "GET", "/api/v1/admin/compliance-report",
+            "Get the tenant's compliance evidence over a period, as JSON or markdown",
+            None, envelope::<ComplianceReport>(gen)),
+        op("list_access_reviews", "GET", "/api/v1/admin/access-reviews",
+            "List the tenant's access reviews in a period",
+            None, envelope::<Vec<AccessReview>>(gen)),
+        op("record_access_review", "POST", "/api/v1/admin/access-reviews",
+            "Record a review of a user's access",
+            schema::<AccessReviewRequest>(gen), envelope::<AccessReview>(gen)),
+        op("export_users", "GET", "/api/v1/admin/users/export",
+            "Download the tenant's users as CSV",
+            None, None),
+        op("import_users", "POST", "/api/v1/admin/users/import",
+            "Create or update the tenant's users from CSV",
+            None, envelope::<UserImportReport>(gen)),
+        op("request_email_verification", "POST", "/api/v1/auth/email-verification/request",
+            "Email the caller a link to verify their address",
+            None, None),
+        op("verify_email", "POST", "/api/v1/auth/email-verification",
+            "Verify an email address with the token mailed to it",
+            body(json!({ "token": string }), &["token"]), None),
+        op("request_password_reset", "POST", "/api/v1/auth/password-reset/request",
+            "Email a password reset link to the account registered to an address",
+            body(json!({ "email": string }), &["email"]), None),
+        op("reset_password", "POST", "/api/v1/auth/password-reset",
+            "Set a new password with a password reset token",
+            body(json!({ "token": string, "new_password": string }), &["token", "new_password"]), None),
+        op("list_webhooks", "GET", "/api/v1/webhooks",
+            "List the tenant's webhook endpoints",
+            None, envelope::<Vec<WebhookEndpoint>>(gen)),
+        op("create_webhook", "POST", "/api/v1/webhooks",
+            "Register a webhook endpoint and get its signing secret",
+            schema::<CreateWebhookRequest>(gen), envelope::<WebhookSecret>(gen)),
+        op("get_webhook", "GET", "/api/v1/webhooks/{webhook_id}",
+            "Get a webhook endpoint",
+            None, envelope::<WebhookEndpoint>(gen)),
+        op("update_webhook", "PUT", "/api/v1/webhooks/{webhook_id}",
+            "Change a webhook endpoint's URL, subscribed events, headers or state",
+            schema::<WebhookEndpointUpdate>(gen), envelope::<WebhookEndpoint>(gen)),
+        op("delete_webhook", "DELETE", "/api/v1/webhooks/{webhook_id}",
+            "Remove a webhook endpoint",
+            None, None),
+        op("rotate_webhook_secret", "POST", "/api/v1/webhooks/{webhook_id}/secret",
+            "Give a webhook endpoint a new secret, signing with the old one too for a grace period",
+            body(json!({ "grace_hours": { "type": ["integer", "null"], "minimum": 0 } }), &[]),
+            envelope::<WebhookSecret>(gen)),
+        op("ping_webhook", "POST", "/api/v1/webhooks/{webhook_id}/ping",
+            "Send a test delivery to a webhook endpoint",
+            None, envelope::<WebhookPingResult>(gen)),
+        op("get_webhook_stats", "GET", "/api/v1/webhooks/{webhook_id}/stats",
+            "Get a webhook endpoint's delivery success and latency",
+            None, envelope::<WebhookStats>(gen)),
+        op("list_webhook_schema_versions", "GET", "/api/v1/webhook-schemas",
+            "List the payload schema versions webhook endpoints can be pinned to",
+            None, envelope::<Vec<WebhookSchemaVersion>>(gen)),
+        op("get_webhook_schemas", "GET", "/api/v1/webhook-schemas/{version}",
+            "Get the JSON Schemas of webhook payloads in a version",
+            None, envelope::<WebhookPayloadSchemas>(gen)),
+        op("ask", "POST", "/api/v1/ask", "Ask a single question",
+            body(json!({
+                "message": string,
+                "context": optional_string,
+                "model": optional_string
+            }), &["message"]),
+            schema::<ChatResponse>(gen)),
+        op("run_github_gate", "POST", "/api/v1/gates/github", "Run a CI gate",
+            schema::<GateRequest>(gen), envelope::<GateVerdict>(gen)),
+        op("dashboard", "GET", "/api/v1/dashboard", "Get a snapshot of server activity",
+            None, envelope::<DashboardSnapshot>(gen)),
+        op("health", "GET", "/health", "Check server health",
+            None, schema::<HealthResponse>(gen)),
+        op("jwks", "GET", "/.well-known/jwks.json", "Get the public keys that verify tokens the server signs",
+            None, schema::<JsonWebKeySet>(gen)),
+        op("version", "GET", "/version", "Get the server version",
+            None, schema::<VersionInfo>(gen)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_contract_is_current() {
+        let checked_in = include_str!("../../../api/schemas/sdk-openapi.json");
+        assert!(
+            checked_in == Contract::new().openapi_json(),
+            "{} is stale; run `cargo run -p copilot-sdk --example generate_openapi`",
+            OPENAPI_PATH
+        );
+    }
+
+    #[test]
+    fn test_examples_match_their_schemas() {
+        let contract = Contract::new();
+        for op in contract.operations() {
+            for schema in op.request.iter().chain(&op.response) {
+                let example = contract.example(schema);
+                assert_eq!(contract.validate(schema, &example), Ok(()), "{} {}", op.method, op.path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_validation_reports_the_offending_field() {
+        let contract = Contract::new();
+        let session = json!({ "id": "s-1", "created_at": "2024-01-01T00:00:00Z", "message_count": 2 });
+        assert_eq!(contract.validate_response("GET", "/api/v1/sessions/s-1?x=1", &session), Ok(()));
+
+        let wrong_type = json!({ "id": "s-1", "created_at": "now", "message_count": "two" });
+        let violation = contract
+            .validate_response("GET", "/api/v1/sessions/s-1", &wrong_type)
+            .unwrap_err();
+        assert_eq!(violation.pointer, "/message_count");
+
+        let nested = json!({ "success": true, "data": { "session_id": "s-1", "edits": [{ "path": "a.rs", "diff": "" }] } });
+        let violation = contract
+            .validate_response("GET", "/api/v1/sessions/s-1/edits", &nested)
+            .unwrap_err();
+        assert_eq!(violation.to_string(), "/data/edits/0: missing required property 'hunks'");
+
+        let outcome = json!({ "success": true, "data": "lukewarm" });
+        assert!(contract.validate_response("POST", "/api/v1/sessions/s-1/prefetch", &outcome).is_err());
+        assert!(contract.validate_response("GET", "/api/v1/unknown", &json!({})).is_err());
+        assert!(contract.find("GET", "/api/v1/sessions/").is_none());
+    }
+}
@@ -0,0 +1,240 @@
+//! Applying proposed code edits
+//!
+//! [`FileEdit::apply`] replays a unified diff against the current contents of
+//! a file. Each hunk is located by its context and removed lines, starting at
+//! the line the diff anchors it to and searching outwards, so an edit still
+//! lands when earlier parts of the file have moved. When those lines are no
+//! longer in the file the hunk is reported as an [`EditConflict`] instead of
+//! being applied.
+
+use crate::models::{DiffHunk, FileEdit};
+use std::fmt;
+
+/// An edit that does not match the current state of the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditConflict {
+    pub path: String,
+    /// Index of the hunk that failed, or `None` when the file itself is in the
+    /// wrong state (already exists, missing, ...)
+    pub hunk: Option<usize>,
+    pub reason: String,
+}
+
+impl fmt::Display for EditConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.hunk {
+            Some(hunk) => write!(f, "{} (hunk {}): {}", self.path, hunk + 1, self.reason),
+            None => write!(f, "{}: {}", self.path, self.reason),
+        }
+    }
+}
+
+impl std::error::Error for EditConflict {}
+
+impl DiffHunk {
+    /// Lines the hunk expects to find in the original file
+    pub fn old_text(&self) -> impl Iterator<Item = &str> {
+        self.lines
+            .iter()
+            .filter(|line| !line.starts_with('+'))
+            .map(|line| line.get(1..).unwrap_or(""))
+    }
+}
+
+impl FileEdit {
+    /// Apply the edit to the file's current contents (`None` if the file does
+    /// not exist)
+    ///
+    /// Returns the new contents, or `None` when the edit deletes the file.
+    /// Line endings and the final newline follow the original file.
+    pub fn apply(&self, original: Option<&str>) -> Result<Option<String>, EditConflict> {
+        let original = match (original, self.created) {
+            (Some(_), true) => return Err(self.conflict(None, "file already exists")),
+            (None, false) => return Err(self.conflict(None, "file does not exist")),
+            (None, true) => "",
+            (Some(text), false) => text,
+        };
+        let line_ending = if original.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let final_newline = original.is_empty() || original.ends_with('\n');
+        let source: Vec<&str> = original.lines().collect();
+
+        let mut output: Vec<&str> = Vec::with_capacity(source.len());
+        let mut cursor = 0;
+        // How far the file has drifted from the line numbers in the diff
+        let mut drift: isize = 0;
+
+        for (index, hunk) in self.hunks.iter().enumerate() {
+            let old: Vec<&str> = hunk.old_text().collect();
+            let position = if old.is_empty() {
+                // Pure insertion after line `old_start`
+                (hunk.old_start as isize + drift).clamp(cursor as isize, source.len() as isize)
+                    as usize
+            } else {
+                let anchor = (hunk.old_start as isize - 1 + drift).max(cursor as isize) as usize;
+                find_lines(&source, &old, cursor, anchor).ok_or_else(|| {
+                    self.conflict(
+                        Some(index),
+                        format!(
+                            "expected lines {}-{} no longer match the file",
+                            hunk.old_start,
+                            hunk.old_start + old.len().saturating_sub(1)
+                        ),
+                    )
+                })?
+            };
+            drift = position as isize - (hunk.old_start as isize - 1).max(0);
+
+            output.extend_from_slice(&source[cursor..position]);
+            let mut next = position;
+            for line in &hunk.lines {
+                let text = line.get(1..).unwrap_or("");
+                if line.starts_with('+') {
+                    output.push(text);
+                } else {
+                    // Keep the file's own version of context lines
+                    if !line.starts_with('-') {
+                        output.push(source[next]);
+                    }
+                    next += 1;
+                }
+            }
+            cursor = next;
+        }
+        output.extend_from_slice(&source[cursor..]);
+
+        if self.deleted {
+            if output.iter().any(|line| !line.trim().is_empty()) {
+                return Err(self.conflict(None, "file has content the deletion does not remove"));
+            }
+            return Ok(None);
+        }
+
+        let mut text = output.join(line_ending);
+        if final_newline && !output.is_empty() {
+            text.push_str(line_ending);
+        }
+        Ok(Some(text))
+    }
+
+    fn conflict(&self, hunk: Option<usize>, reason: impl Into<String>) -> EditConflict {
+        EditConflict {
+            path: self.path.clone(),
+            hunk,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Find `needle` in `source` at or after `from`, preferring the match closest
+/// to `anchor`; trailing whitespace is ignored
+fn find_lines(source: &[&str], needle: &[&str], from: usize, anchor: usize) -> Option<usize> {
+    if needle.len() > source.len() {
+        return None;
+    }
+    let last = source.len() - needle.len();
+    if from > last {
+        return None;
+    }
+    let matches = |at: usize| {
+        source[at..at + needle.len()]
+            .iter()
+            .zip(needle)
+            .all(|(a, b)| a.trim_end() == b.trim_end())
+    };
+
+    let anchor = anchor.clamp(from, last);
+    for distance in 0..=(last - from) {
+        let after = anchor + distance;
+        if after <= last && matches(after) {
+            return Some(after);
+        }
+        if let Some(before) = anchor.checked_sub(distance).filter(|at| *at >= from) {
+            if distance > 0 && matches(before) {
+                return Some(before);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(path: &str, hunks: Vec<(usize, &[&str])>) -> FileEdit {
+        FileEdit {
+            path: path.to_string(),
+            created: false,
+            deleted: false,
+            hunks: hunks
+                .into_iter()
+                .map(|(old_start, lines)| DiffHunk {
+                    old_start,
+                    old_lines: 0,
+                    new_start: old_start,
+                    new_lines: 0,
+                    section: String::new(),
+                    lines: lines.iter().map(|l| l.to_string()).collect(),
+                })
+                .collect(),
+            diff: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_apply_follows_shifted_lines() {
+        let fix = edit(
+            "src/lib.rs",
+            vec![(2, &[" fn add() {", "-    a - b", "+    a + b", " }"])],
+        );
+
+        let original = "// header\nfn add() {\n    a - b\n}\n";
+        assert_eq!(
+            fix.apply(Some(original)).unwrap().unwrap(),
+            "// header\nfn add() {\n    a + b\n}\n"
+        );
+
+        // Two lines were added above the hunk since the diff was written
+        let shifted = "// header\nuse std::fmt;\n\nfn add() {\n    a - b\n}";
+        assert_eq!(
+            fix.apply(Some(shifted)).unwrap().unwrap(),
+            "// header\nuse std::fmt;\n\nfn add() {\n    a + b\n}"
+        );
+
+        let crlf = "fn add() {\r\n    a - b\r\n}\r\n";
+        assert_eq!(
+            fix.apply(Some(crlf)).unwrap().unwrap(),
+            "fn add() {\r\n    a + b\r\n}\r\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_reports_conflicts() {
+        let fix = edit(
+            "src/lib.rs",
+            vec![(1, &[" fn add() {", "-    a - b", "+    a + b"])],
+        );
+        let conflict = fix.apply(Some("fn add() {\n    a * b\n}\n")).unwrap_err();
+        assert_eq!(conflict.hunk, Some(0));
+        assert!(conflict.to_string().starts_with("src/lib.rs (hunk 1)"));
+
+        assert_eq!(fix.apply(None).unwrap_err().hunk, None);
+    }
+
+    #[test]
+    fn test_create_and_delete() {
+        let mut create = edit("new.txt", vec![(0, &["+one", "+two"])]);
+        create.created = true;
+        assert_eq!(create.apply(None).unwrap().unwrap(), "one\ntwo\n");
+        assert!(create.apply(Some("exists")).is_err());
+
+        let mut delete = edit("old.txt", vec![(1, &["-one", "-two"])]);
+        delete.deleted = true;
+        assert_eq!(delete.apply(Some("one\ntwo\n")).unwrap(), None);
+        assert!(delete.apply(Some("one\ntwo\nthree\n")).is_err());
+    }
+}
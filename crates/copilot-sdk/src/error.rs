@@ -1,5 +1,6 @@
 //! Error types for the Copilot SDK
 
+use crate::models::ParameterError;
 use thiserror::Error;
 
 /// Result type alias for Copilot SDK operations
@@ -59,6 +60,13 @@ pub enum CopilotError {
     /// Invalid input
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+
+    /// Template parameters rejected by the server, one entry per field
+    #[error(
+        "Invalid parameters: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    InvalidParameters(Vec<ParameterError>),
 }
 
 impl CopilotError {
@@ -80,6 +88,7 @@ impl CopilotError {
             CopilotError::RateLimit { .. } => Some(429),
             CopilotError::NotFound(_) => Some(404),
             CopilotError::Auth(_) => Some(401),
+            CopilotError::InvalidParameters(_) => Some(422),
             _ => None,
         }
     }
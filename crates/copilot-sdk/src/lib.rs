@@ -22,15 +22,25 @@
 //! }
 //! ```
 
+mod cache;
 mod client;
+mod edits;
 mod error;
+mod limits;
 mod models;
 mod streaming;
 
-pub use client::{CopilotClient, CopilotClientBuilder};
+pub mod codegen;
+pub mod contract;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+
+pub use client::{CopilotClient, CopilotClientBuilder, STREAM_ID_HEADER, STREAM_RESUME_ATTEMPTS};
+pub use edits::EditConflict;
 pub use error::{CopilotError, Result};
+pub use limits::{QuotaInfo, RateLimitInfo};
 pub use models::*;
-pub use streaming::StreamEvent;
+pub use streaming::{DashboardStream, ReplyChunk, ReplyStream, RunEventStream, SseEvent, StreamEvent};
 
 /// SDK version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
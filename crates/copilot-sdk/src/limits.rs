@@ -0,0 +1,187 @@
+//! Rate limit and quota state reported by the server
+//!
+//! Every API response carries `X-RateLimit-*` headers for the caller's rate
+//! limit and `X-Quota-<Name>-*` headers for its periodic quotas. The client
+//! keeps the most recent values so callers can slow down before requests
+//! start failing with `429 Too Many Requests`.
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Usage of one tenant quota
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuotaInfo {
+    /// Allowance for the current period
+    pub limit: Option<u64>,
+    /// Allowance left in the current period
+    pub remaining: Option<u64>,
+    /// Usage so far in the current period
+    pub used: Option<u64>,
+    /// Seconds until the period ends
+    pub reset_secs: Option<u64>,
+}
+
+/// Rate limit and quota state from a response's headers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimitInfo {
+    /// Requests allowed per minute
+    pub limit: Option<u32>,
+    /// Requests that can be sent right away
+    pub remaining: Option<u32>,
+    /// Seconds until the full burst allowance is available again
+    pub reset_secs: Option<u64>,
+    /// Seconds to wait before retrying a rejected request
+    pub retry_after_secs: Option<u64>,
+    /// Quotas keyed by lower-case name (`api-calls`, `tokens`)
+    pub quotas: BTreeMap<String, QuotaInfo>,
+}
+
+impl RateLimitInfo {
+    /// Parse the rate limit and quota headers; `None` when there are none
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let mut info = Self {
+            retry_after_secs: parse(headers.get(RETRY_AFTER).and_then(|v| v.to_str().ok())),
+            ..Self::default()
+        };
+        let mut found = false;
+
+        for (name, value) in headers {
+            let Ok(value) = value.to_str() else { continue };
+            let name = name.as_str();
+
+            if let Some(field) = name.strip_prefix("x-ratelimit-") {
+                match field {
+                    "limit" => info.limit = parse(Some(value)),
+                    "remaining" => info.remaining = parse(Some(value)),
+                    "reset" => info.reset_secs = parse(Some(value)),
+                    _ => continue,
+                }
+                found = true;
+            } else if let Some(rest) = name.strip_prefix("x-quota-") {
+                let Some((quota, field)) = rest.rsplit_once('-') else { continue };
+                let entry = info.quotas.entry(quota.to_string()).or_default();
+                match field {
+                    "limit" => entry.limit = parse(Some(value)),
+                    "remaining" => entry.remaining = parse(Some(value)),
+                    "used" => entry.used = parse(Some(value)),
+                    "reset" => entry.reset_secs = parse(Some(value)),
+                    _ => continue,
+                }
+                found = true;
+            }
+        }
+
+        found.then_some(info)
+    }
+
+    /// Usage of a quota, e.g. `quota("api-calls")`
+    pub fn quota(&self, name: &str) -> Option<&QuotaInfo> {
+        self.quotas.get(name)
+    }
+
+    /// Whether the next request would be rejected
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == Some(0) || self.quotas.values().any(|quota| quota.remaining == Some(0))
+    }
+
+    /// How long to wait before sending the next request, if at all
+    ///
+    /// Uses `Retry-After` when the server sent one, otherwise the time for a
+    /// single request to be replenished once the burst allowance is used up,
+    /// or the end of the period of an exhausted quota.
+    pub fn wait_time(&self) -> Option<Duration> {
+        if let Some(retry_after) = self.retry_after_secs {
+            return Some(Duration::from_secs(retry_after));
+        }
+
+        let quota_reset = self
+            .quotas
+            .values()
+            .filter(|quota| quota.remaining == Some(0))
+            .filter_map(|quota| quota.reset_secs)
+            .max();
+        if let Some(reset) = quota_reset {
+            return Some(Duration::from_secs(reset));
+        }
+
+        match (self.remaining, self.limit) {
+            (Some(0), Some(limit)) if limit > 0 => Some(Duration::from_secs_f64(60.0 / limit as f64)),
+            _ => None,
+        }
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: Option<&str>) -> Option<T> {
+    value.and_then(|v| v.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_parse_rate_limit_and_quota_headers() {
+        let info = RateLimitInfo::from_headers(&headers(&[
+            ("x-ratelimit-limit", "300"),
+            ("x-ratelimit-remaining", "49"),
+            ("x-ratelimit-reset", "1"),
+            ("x-quota-api-calls-limit", "10000"),
+            ("x-quota-api-calls-remaining", "9000"),
+            ("x-quota-api-calls-used", "1000"),
+            ("x-quota-tokens-used", "52000"),
+            ("content-type", "application/json"),
+        ]))
+        .unwrap();
+
+        assert_eq!(info.limit, Some(300));
+        assert_eq!(info.remaining, Some(49));
+        assert_eq!(info.reset_secs, Some(1));
+        assert_eq!(info.retry_after_secs, None);
+        let api_calls = info.quota("api-calls").unwrap();
+        assert_eq!(api_calls.limit, Some(10000));
+        assert_eq!(api_calls.remaining, Some(9000));
+        assert_eq!(api_calls.used, Some(1000));
+        assert_eq!(info.quota("tokens").unwrap().used, Some(52000));
+        assert!(!info.is_exhausted());
+        assert_eq!(info.wait_time(), None);
+
+        assert!(RateLimitInfo::from_headers(&headers(&[("content-type", "text/plain")])).is_none());
+    }
+
+    #[test]
+    fn test_wait_time() {
+        let rejected = RateLimitInfo::from_headers(&headers(&[
+            ("x-ratelimit-limit", "300"),
+            ("x-ratelimit-remaining", "0"),
+            ("retry-after", "2"),
+        ]))
+        .unwrap();
+        assert!(rejected.is_exhausted());
+        assert_eq!(rejected.wait_time(), Some(Duration::from_secs(2)));
+
+        let drained = RateLimitInfo::from_headers(&headers(&[
+            ("x-ratelimit-limit", "300"),
+            ("x-ratelimit-remaining", "0"),
+        ]))
+        .unwrap();
+        assert_eq!(drained.wait_time(), Some(Duration::from_millis(200)));
+
+        let quota = RateLimitInfo::from_headers(&headers(&[
+            ("x-quota-api-calls-remaining", "0"),
+            ("x-quota-api-calls-reset", "3600"),
+        ]))
+        .unwrap();
+        assert!(quota.is_exhausted());
+        assert_eq!(quota.wait_time(), Some(Duration::from_secs(3600)));
+    }
+}
@@ -0,0 +1,251 @@
+//! Mock server for testing integrations (`mock` feature)
+//!
+//! [`MockCopilotServer`] answers every endpoint of the SDK [`Contract`] with
+//! a minimal valid response, so code built on [`CopilotClient`] can be tested
+//! without a running server. Canned responses are checked against the
+//! contract when they are registered:
+//!
+//! ```rust,no_run
+//! # async fn example() {
+//! use copilot_sdk::mock::MockCopilotServer;
+//! use serde_json::json;
+//!
+//! let server = MockCopilotServer::start().await;
+//! server
+//!     .respond_with("GET", "/api/v1/sessions/s-1", &json!({
+//!         "id": "s-1",
+//!         "created_at": "2024-01-01T00:00:00Z",
+//!         "message_count": 3,
+//!     }))
+//!     .await;
+//!
+//! let session = server.client().resume_session("s-1").await.unwrap();
+//! assert_eq!(session.message_count, 3);
+//! # }
+//! ```
+//!
+//! Add the SDK with the feature to your dev-dependencies:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! copilot-sdk = { version = "0.1", features = ["mock"] }
+//! ```
+
+use crate::contract::{Contract, Operation};
+use crate::CopilotClient;
+use serde::Serialize;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockBuilder, MockServer, Request, ResponseTemplate};
+
+/// Priority of the default responses; anything registered later wins
+const DEFAULT_PRIORITY: u8 = 10;
+
+/// An HTTP server that speaks the SDK contract
+pub struct MockCopilotServer {
+    server: MockServer,
+    contract: Contract,
+}
+
+impl MockCopilotServer {
+    /// Start a server answering every contract operation with an example
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+        let contract = Contract::new();
+
+        for op in contract.operations() {
+            let response = match &op.response {
+                Some(schema) => ResponseTemplate::new(op.status()).set_body_json(contract.example(schema)),
+                None => ResponseTemplate::new(op.status()),
+            };
+            matcher(op, op.path)
+                .respond_with(response)
+                .with_priority(DEFAULT_PRIORITY)
+                .mount(&server)
+                .await;
+        }
+
+        Self { server, contract }
+    }
+
+    /// Base URL of the server
+    pub fn uri(&self) -> String {
+        self.server.uri()
+    }
+
+    /// A client for the server
+    pub fn client(&self) -> CopilotClient {
+        CopilotClient::builder()
+            .base_url(self.uri())
+            .api_key(Some("mock-api-key".to_string()))
+            .build()
+            .expect("mock server URI is a valid base URL")
+    }
+
+    /// The contract the server answers from
+    pub fn contract(&self) -> &Contract {
+        &self.contract
+    }
+
+    /// The underlying wiremock server, for mocks beyond the contract
+    pub fn server(&self) -> &MockServer {
+        &self.server
+    }
+
+    /// Answer `method path` with `body`
+    ///
+    /// `path` is a concrete path such as `/api/v1/sessions/s-1` or a contract
+    /// template such as `/api/v1/sessions/{session_id}`. Bodies of endpoints
+    /// that use the `{ success, data }` envelope are wrapped in it.
+    ///
+    /// # Panics
+    ///
+    /// When the endpoint is not in the contract or the body does not match
+    /// its response schema.
+    pub async fn respond_with<T: Serialize>(&self, method: &str, path: &str, body: &T) {
+        let op = self.operation(method, path);
+        let body = serde_json::to_value(body).expect("response body serializes to JSON");
+        let body = match &op.response {
+            Some(schema) if is_envelope(schema) && !is_envelope_value(&body) => {
+                json!({ "success": true, "data": body, "error": null })
+            }
+            _ => body,
+        };
+        if let Err(violation) = self.contract.validate_response(method, path, &body) {
+            panic!("response for {} {} breaks the contract at {}", op.method, op.path, violation);
+        }
+
+        matcher(op, path)
+            .respond_with(ResponseTemplate::new(op.status()).set_body_json(body))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer `method path` with an error `status` and message
+    ///
+    /// # Panics
+    ///
+    /// When the endpoint is not in the contract.
+    pub async fn fail(&self, method: &str, path: &str, status: u16, message: &str) {
+        let op = self.operation(method, path);
+        matcher(op, path)
+            .respond_with(ResponseTemplate::new(status).set_body_string(message))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Requests received so far
+    pub async fn received_requests(&self) -> Vec<Request> {
+        self.server.received_requests().await.unwrap_or_default()
+    }
+
+    fn operation(&self, method: &str, path: &str) -> &Operation {
+        self.contract
+            .find(method, path)
+            .unwrap_or_else(|| panic!("{} {} is not part of the SDK contract", method, path))
+    }
+}
+
+/// Match requests for `op` at `path`, a concrete path or the template
+fn matcher(op: &Operation, target: &str) -> MockBuilder {
+    let builder = Mock::given(method(op.method));
+    if target.contains('{') {
+        let pattern = target
+            .split('/')
+            .map(|segment| if segment.starts_with('{') { "[^/]+".to_string() } else { regex_escape(segment) })
+            .collect::<Vec<_>>()
+            .join("/");
+        builder.and(path_regex(format!("^{}$", pattern)))
+    } else {
+        builder.and(path(target))
+    }
+}
+
+fn regex_escape(segment: &str) -> String {
+    segment
+        .chars()
+        .flat_map(|c| {
+            let escape = !c.is_ascii_alphanumeric() && c != '_' && c != '-';
+            escape.then_some('\\').into_iter().chain(std::iter::once(c))
+        })
+        .collect()
+}
+
+fn is_envelope(schema: &Value) -> bool {
+    schema.pointer("/properties/success").is_some() && schema.pointer("/properties/data").is_some()
+}
+
+fn is_envelope_value(body: &Value) -> bool {
+    body.get("success").is_some_and(Value::is_boolean) && body.get("data").is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CopilotError, PrefetchOutcome};
+
+    /// Every default response is served with the contract's status and body
+    #[tokio::test]
+    async fn test_default_responses_match_the_contract() {
+        let server = MockCopilotServer::start().await;
+        let contract = server.contract();
+        let http = reqwest::Client::new();
+
+        for op in contract.operations() {
+            let path = op
+                .path
+                .split('/')
+                .map(|segment| if segment.starts_with('{') { "mock-id" } else { segment })
+                .collect::<Vec<_>>()
+                .join("/");
+            let mut request = http.request(op.method.parse().unwrap(), format!("{}{}", server.uri(), path));
+            if let Some(schema) = &op.request {
+                request = request.json(&contract.example(schema));
+            }
+            let response = request.send().await.unwrap();
+
+            assert_eq!(response.status().as_u16(), op.status(), "{} {}", op.method, op.path);
+            if op.response.is_some() {
+                let body: Value = response.json().await.unwrap();
+                assert_eq!(contract.validate_response(op.method, &path, &body), Ok(()), "{} {}", op.method, op.path);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_against_the_mock() {
+        let server = MockCopilotServer::start().await;
+        let client = server.client();
+
+        client.create_session(None).await.unwrap();
+        client.send_message("s-1", "hello").await.unwrap();
+        client.list_workflows().await.unwrap();
+        client.cancel_workflow("exec-1").await.unwrap();
+        client.dashboard().await.unwrap();
+        client.health().await.unwrap();
+        assert_eq!(client.prefetch_context("s-1", "deploy").await.unwrap(), PrefetchOutcome::Warmed);
+
+        server
+            .respond_with("POST", "/api/v1/sessions/{session_id}/prefetch", &PrefetchOutcome::AlreadyWarm)
+            .await;
+        assert_eq!(client.prefetch_context("s-2", "deploy").await.unwrap(), PrefetchOutcome::AlreadyWarm);
+
+        server.fail("GET", "/api/v1/workflows/missing", 404, "no such workflow").await;
+        assert!(matches!(client.get_workflow("missing").await, Err(CopilotError::NotFound(_))));
+        client.get_workflow("other").await.unwrap();
+
+        let requests = server.received_requests().await;
+        assert_eq!(requests.len(), 10);
+        assert_eq!(requests[0].url.path(), "/api/v1/sessions");
+        assert_eq!(requests[1].url.path(), "/api/v1/sessions/s-1/messages");
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "missing required property 'message_count'")]
+    async fn test_responses_that_break_the_contract_are_rejected() {
+        let server = MockCopilotServer::start().await;
+        server
+            .respond_with("GET", "/api/v1/sessions/s-1", &json!({ "id": "s-1", "created_at": "now" }))
+            .await;
+    }
+}
@@ -1,10 +1,11 @@
 //! Data models for the Copilot SDK
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// Chat message
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Message {
     pub role: String,
     pub content: String,
@@ -12,17 +13,52 @@ pub struct Message {
     pub name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub function_call: Option<FunctionCall>,
+    /// Tokens and cost of producing this message (assistant messages)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// Tool calls, their results and sandbox output (tool messages)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<MessagePart>,
+}
+
+/// A tool call, its result or sandbox output recorded in a message
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MessagePart {
+    /// `tool_call`, `tool_result` or `sandbox_output`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Tool call the part belongs to
+    pub call_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    /// Arguments of a tool call, with sensitive values redacted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<serde_json::Value>,
+    /// What a tool returned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<serde_json::Value>,
+    /// Whether a tool result was reused from an earlier identical call
+    #[serde(default)]
+    pub cached: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdout: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr: Option<String>,
 }
 
 /// Function call in a message
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FunctionCall {
     pub name: String,
     pub arguments: String,
 }
 
 /// Chat request
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ChatRequest {
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -40,28 +76,58 @@ pub struct ChatRequest {
 }
 
 /// Chat response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ChatResponse {
     pub content: String,
     pub conversation_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+    /// Tokens and cost of this message
     #[serde(skip_serializing_if = "Option::is_none")]
     pub usage: Option<Usage>,
+    /// Running tokens and cost of the whole session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_usage: Option<Usage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub finish_reason: Option<String>,
+    /// Preferences learned from this message, for the user to confirm
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preference_suggestions: Vec<PreferenceSuggestion>,
+    /// How the retrieved context was cut to fit the model, if it was
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_downsize: Option<ContextDownsize>,
+}
+
+/// How a reply's retrieved context was cut to fit the model's context window
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ContextDownsize {
+    pub model: String,
+    /// Context window of the model, in tokens
+    pub window: usize,
+    /// Tokens the retrieved context was cut to
+    pub budget: usize,
+    pub tokens_before: usize,
+    pub tokens_after: usize,
+    /// Passages dropped because recompressing them was not enough
+    pub items_dropped: usize,
+    /// Whether the model rejected the prompt before it was cut
+    #[serde(default)]
+    pub retried: bool,
 }
 
 /// Token usage information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Cost in US dollars
+    #[serde(default)]
+    pub cost_usd: f64,
 }
 
 /// Conversation metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Conversation {
     pub id: String,
     pub created_at: String,
@@ -72,10 +138,13 @@ pub struct Conversation {
     pub model: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
+    /// Tokens and cost of the conversation so far
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
 /// Chat session
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Session {
     pub id: String,
     pub created_at: String,
@@ -86,10 +155,76 @@ pub struct Session {
     pub message_count: usize,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_activity: Option<String>,
+    /// Tokens and cost of the session so far
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+/// A message sent in a session, optionally answered with another model
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SessionMessage {
+    pub message: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+    /// Model to answer with instead of the session's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// An item of the context window a reply was generated from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WindowEntry {
+    pub id: String,
+    pub content_type: String,
+    pub source: String,
+    pub score: f64,
+    pub token_count: usize,
+    pub compressed: bool,
+}
+
+/// The reply recorded with a replay capture
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CapturedResponse {
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub usage: Usage,
+}
+
+/// A sanitized chat turn captured for replay
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReplayCapture {
+    pub id: String,
+    pub captured_at: String,
+    pub tenant_id: String,
+    pub session_id: String,
+    pub request: SessionMessage,
+    /// The reply, unless the turn failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<CapturedResponse>,
+    /// Why the turn failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub retrieval: Vec<WindowEntry>,
+    pub duration_ms: u64,
+}
+
+/// A replay capture as listed, without its text
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReplaySummary {
+    pub id: String,
+    pub captured_at: String,
+    pub session_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub message_chars: usize,
+    pub succeeded: bool,
+    pub duration_ms: u64,
 }
 
 /// Context item
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ContextItem {
     pub id: String,
     pub content: String,
@@ -102,7 +237,7 @@ pub struct ContextItem {
 }
 
 /// Context search result
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ContextSearchResult {
     pub id: String,
     pub snippet: String,
@@ -112,7 +247,7 @@ pub struct ContextSearchResult {
 }
 
 /// Workflow definition
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Workflow {
     pub id: String,
     pub name: String,
@@ -122,7 +257,7 @@ pub struct Workflow {
 }
 
 /// Workflow step definition
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WorkflowStep {
     pub id: String,
     pub name: String,
@@ -135,7 +270,7 @@ pub struct WorkflowStep {
 }
 
 /// Workflow summary for listing
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WorkflowSummary {
     pub id: String,
     pub name: String,
@@ -144,7 +279,7 @@ pub struct WorkflowSummary {
 }
 
 /// Workflow execution
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WorkflowExecution {
     pub id: String,
     pub workflow_id: String,
@@ -155,7 +290,7 @@ pub struct WorkflowExecution {
 }
 
 /// Workflow execution status
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WorkflowStatus {
     pub id: String,
     pub status: String,
@@ -172,8 +307,1176 @@ pub struct WorkflowStatus {
     pub output: Option<serde_json::Value>,
 }
 
+/// A parameter of a workflow template
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TemplateParameter {
+    pub name: String,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// `"string"`, `"number"`, `"boolean"`, `"array"`, `"object"`,
+    /// `"secret"` or `{ "select": { "options": [{ "value", "label" }] } }`
+    pub param_type: serde_json::Value,
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<serde_json::Value>,
+    /// `min`, `max`, `min_length`, `max_length` and `pattern` rules
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation: Option<serde_json::Value>,
+}
+
+/// Documentation shown next to a workflow template
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TemplateDocs {
+    /// When to use the template
+    pub when_to_use: String,
+    /// What each step does, in order
+    #[serde(default)]
+    pub steps: Vec<String>,
+    /// What must be set up before a run
+    #[serde(default)]
+    pub prerequisites: Vec<String>,
+}
+
+/// A workflow template that can be run
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TemplateSummary {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub category: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    pub version: String,
+    /// Parameters a run must be given values for
+    #[serde(default)]
+    pub required_parameters: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs: Option<TemplateDocs>,
+    #[serde(default)]
+    pub usage_count: u64,
+}
+
+/// Parameters a workflow template is run with
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ParameterSchema {
+    pub template_id: String,
+    pub name: String,
+    pub description: String,
+    pub parameters: Vec<TemplateParameter>,
+}
+
+/// A validated, and unless dry run, started template run
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ManualRun {
+    pub template_id: String,
+    /// Workflow execution, absent for dry runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_id: Option<String>,
+    /// Values the run uses, with defaults filled in
+    pub params: serde_json::Value,
+}
+
+/// Something that happened to a run, streamed while watching it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RunEvent {
+    pub execution_id: String,
+    /// `step_started`, `step_retried`, `step_completed`, `step_failed`,
+    /// `started`, `completed`, `failed` or `cancelled`; absent for the
+    /// snapshot a stream starts with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_id: Option<String>,
+    /// Attempt of the step that failed, for retries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempt: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Short rendering of the step's outputs, for completed steps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_summary: Option<String>,
+    /// Status of the run after the event
+    pub status: String,
+    #[serde(default)]
+    pub running_steps: Vec<String>,
+    #[serde(default)]
+    pub completed_steps: usize,
+    #[serde(default)]
+    pub failed_steps: usize,
+    pub timestamp: String,
+}
+
+impl RunEvent {
+    /// Whether the run has finished, so no event follows this one
+    pub fn is_last(&self) -> bool {
+        matches!(self.status.as_str(), "completed" | "failed" | "cancelled")
+    }
+}
+
+/// A template parameter the server rejected
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ParameterError {
+    /// Name of the offending parameter
+    pub field: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// A workflow schedule
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WorkflowSchedule {
+    pub id: String,
+    pub workflow_id: String,
+    /// `{ "type": "cron", "expression": "0 9 * * 1-5" }`, `interval`,
+    /// `daily`, `weekly`, `monthly` or `once`
+    pub schedule: serde_json::Value,
+    /// `false` while paused
+    pub enabled: bool,
+    /// `{ "policy": "skip" }`, `{ "policy": "run_once" }` or
+    /// `{ "policy": "catch_up", "max_runs": 10 }`
+    pub missed_runs: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_execution: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_execution: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paused_at: Option<String>,
+}
+
+/// Upcoming fire times of a schedule
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SchedulePreview {
+    pub fire_times: Vec<String>,
+}
+
+/// Summary of one streamed document
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IngestedDocument {
+    pub document_id: String,
+    pub bytes_received: u64,
+    pub chunk_count: usize,
+    pub processing_time_ms: u64,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Whether the upload's signature checked out, when the server has
+    /// trusted signers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trust: Option<Trust>,
+    /// Source the upload claims to be signed by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+}
+
+/// Whether ingested content was signed by a trusted source; untrusted
+/// content was unsigned, signed by an unknown source, or tampered with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Trust {
+    Verified,
+    Untrusted,
+}
+
+/// Streaming ingestion job
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IngestionJob {
+    pub id: String,
+    pub status: String,
+    pub bytes_received: u64,
+    pub chunk_count: usize,
+    #[serde(default)]
+    pub documents: Vec<IngestedDocument>,
+    /// Documents with chunks held in quarantine for review
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quarantined: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+}
+
+impl IngestionJob {
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status.as_str(), "completed" | "failed")
+    }
+}
+
+/// How much of one wiki space a bootstrap ingested
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SpaceCoverage {
+    pub space: String,
+    pub pages: usize,
+    pub ingested: usize,
+    pub summarized: usize,
+    pub chunks: usize,
+}
+
+/// A wiki page a bootstrap could not ingest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BootstrapFailure {
+    pub page: String,
+    pub error: String,
+}
+
+/// How much of a wiki export made it into the knowledge base
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BootstrapReport {
+    /// Pages processed so far
+    pub pages: usize,
+    pub ingested: usize,
+    /// Pages without text
+    pub empty: usize,
+    /// Pages an ingestion rule left out
+    pub skipped: usize,
+    pub failed: usize,
+    pub chunks: usize,
+    pub summarized: usize,
+    /// Share of the pages with text that were ingested, 0-1
+    pub coverage: f64,
+    /// Share of the ingested pages that got a summary, 0-1
+    pub summary_coverage: f64,
+    #[serde(default)]
+    pub spaces: Vec<SpaceCoverage>,
+    /// The first failures
+    #[serde(default)]
+    pub failures: Vec<BootstrapFailure>,
+    pub processing_time_ms: u64,
+}
+
+/// Knowledge base bootstrap from a wiki export
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BootstrapJob {
+    pub id: String,
+    pub source: String,
+    /// `confluence` or `markdown`
+    pub format: String,
+    pub status: String,
+    /// Pages in the export
+    pub total_pages: usize,
+    pub report: BootstrapReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+}
+
+impl BootstrapJob {
+    pub fn is_finished(&self) -> bool {
+        self.status != "running"
+    }
+}
+
+/// What a safety finding is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyCategory {
+    Secret,
+    Malware,
+    PromptInjection,
+}
+
+/// A safety rule that matched ingested content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SafetyFinding {
+    pub category: SafetyCategory,
+    /// e.g. `aws_access_key`
+    pub rule: String,
+    /// What matched; secrets are masked
+    pub excerpt: String,
+}
+
+/// A chunk held back from the knowledge base
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QuarantinedChunk {
+    pub id: String,
+    pub document_id: String,
+    pub content: String,
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+/// An ingested document held for review
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QuarantinedDocument {
+    pub document_id: String,
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<String>,
+    pub findings: Vec<SafetyFinding>,
+    pub chunks: Vec<QuarantinedChunk>,
+    pub quarantined_at: String,
+}
+
+/// Whether one claim of an answer is backed by its context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ClaimSupport {
+    pub claim: String,
+    pub supported: bool,
+    /// Source of the passage backing the claim
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// How well an answer is grounded in its retrieved context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct GroundednessScore {
+    /// Share of claims backed by the context, 0-1
+    pub score: f64,
+    pub claims: Vec<ClaimSupport>,
+    pub scorer: String,
+}
+
+/// A poorly grounded answer awaiting human review
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GroundednessReview {
+    pub id: String,
+    pub session_id: String,
+    pub query: String,
+    pub answer: String,
+    pub score: GroundednessScore,
+    /// Sources of the context the answer was generated from
+    pub sources: Vec<String>,
+    pub flagged_at: String,
+}
+
+/// Groundedness scores of answers so far
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct GroundednessStats {
+    pub answers: u64,
+    pub flagged: u64,
+    pub failures: u64,
+    pub mean_score: f64,
+    pub pending_reviews: usize,
+    pub review_threshold: f64,
+}
+
+/// Questions about one topic and how well they were answered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct QueryTopic {
+    /// The topic's most frequent words
+    pub label: String,
+    pub terms: Vec<String>,
+    pub queries: u64,
+    /// Questions answered with low confidence or without sources
+    pub unanswered: u64,
+    pub low_confidence: u64,
+    pub uncited: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mean_confidence: Option<f64>,
+    /// Recent unanswered questions, newest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<String>,
+}
+
+/// Topics a tenant's users ask about and the ones the corpus does not cover
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct KnowledgeGapReport {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub since: String,
+    pub generated_at: String,
+    pub total_queries: u64,
+    pub unanswered_queries: u64,
+    /// Questions whose text the tenant does not retain, so have no topic
+    pub untopical_queries: u64,
+    /// Topics by number of questions
+    pub topics: Vec<QueryTopic>,
+    /// Topics with unanswered questions, most unanswered first
+    pub gaps: Vec<QueryTopic>,
+}
+
+/// What one intent's conversations came to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct IntentMetrics {
+    pub intent: String,
+    /// Conversations that opened with this intent
+    pub conversations: u64,
+    /// Turns classified with this intent, in any conversation
+    pub turns: u64,
+    pub resolved: u64,
+    pub escalated: u64,
+    /// Mean turns the resolved conversations took
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mean_turns_to_resolution: Option<f64>,
+}
+
+/// Intent distribution and resolution metrics of recent conversations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ConversationReport {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub since: String,
+    pub generated_at: String,
+    pub conversations: u64,
+    pub turns: u64,
+    pub resolved: u64,
+    pub confirmed_resolutions: u64,
+    pub inferred_resolutions: u64,
+    /// Conversations the user confirmed were not resolved
+    pub unresolved: u64,
+    pub escalated: u64,
+    pub resolution_rate: f64,
+    pub escalation_rate: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mean_turns_to_resolution: Option<f64>,
+    /// Intents by number of conversations
+    pub intents: Vec<IntentMetrics>,
+}
+
+/// Whether and when a conversation's goal was resolved
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Resolution {
+    pub resolved: bool,
+    /// `confirmed` when the user said so through the API, `inferred` when a
+    /// message read as a confirmation
+    pub source: String,
+    /// Turns the conversation took to get there
+    pub turns: u64,
+}
+
+/// Changes an experiment variant makes to the retrieved context
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RetrievalOverrides {
+    /// Most context items kept, best scoring first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<usize>,
+    /// Items scoring below this are dropped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_score: Option<f64>,
+}
+
+impl RetrievalOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.max_items.is_none() && self.min_score.is_none()
+    }
+}
+
+/// One arm of an A/B experiment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ExperimentVariant {
+    pub name: String,
+    /// Share of sessions assigned, relative to the other variants' weights
+    #[serde(default = "default_variant_weight")]
+    pub weight: u32,
+    /// System prompt; `{system_prompt}` is replaced with the session's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_template: Option<String>,
+    /// Model to answer with instead of the session's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "RetrievalOverrides::is_empty")]
+    pub retrieval: RetrievalOverrides,
+}
+
+fn default_variant_weight() -> u32 {
+    1
+}
+
+/// An experiment to start
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ExperimentSpec {
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    /// Variants, the control first
+    pub variants: Vec<ExperimentVariant>,
+    /// Tenants taking part from the start; only the caller's may be given
+    #[serde(default)]
+    pub tenants: Vec<String>,
+}
+
+/// A running or finished A/B experiment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Experiment {
+    pub id: String,
+    pub description: String,
+    pub variants: Vec<ExperimentVariant>,
+    /// Tenants enrolled in the experiment
+    pub tenants: Vec<String>,
+    /// `running` or `stopped`
+    pub status: String,
+    pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopped_at: Option<String>,
+}
+
+/// Mean and spread of a metric over a variant's turns
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct MetricSummary {
+    pub count: u64,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+/// What one experiment variant's turns came to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct VariantResults {
+    pub variant: String,
+    pub turns: u64,
+    pub positive_feedback: u64,
+    pub negative_feedback: u64,
+    /// Share of feedback that was positive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub positive_rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<MetricSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<MetricSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub groundedness: Option<MetricSummary>,
+}
+
+/// A variant's difference from the control on one metric
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SignificanceTest {
+    /// `positive_feedback`, `latency_ms`, `cost_usd` or `groundedness`
+    pub metric: String,
+    pub variant: String,
+    pub control_value: f64,
+    pub variant_value: f64,
+    /// Variant minus control
+    pub difference: f64,
+    /// Two-sided p-value of the difference
+    pub p_value: f64,
+    pub significant: bool,
+}
+
+/// Per-variant metrics of an experiment and how each variant compares with
+/// the control
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ExperimentResults {
+    pub experiment: Experiment,
+    pub variants: Vec<VariantResults>,
+    /// Significance level the tests were judged at
+    pub alpha: f64,
+    pub tests: Vec<SignificanceTest>,
+}
+
+/// A cross-region access refused by a tenant's residency policy
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ResidencyViolation {
+    pub at: String,
+    pub tenant_id: String,
+    pub home_region: String,
+    pub data_region: String,
+    /// `retrieve`, `read` or `write`
+    pub operation: String,
+    /// What was refused, e.g. the source of a context item
+    pub resource: String,
+}
+
+/// A tenant's home region and the cross-region access refused for it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ResidencyReport {
+    pub tenant_id: String,
+    pub region: String,
+    /// `unrestricted` or `strict`
+    pub mode: String,
+    pub violations: u64,
+    pub by_data_region: BTreeMap<String, u64>,
+    pub by_operation: BTreeMap<String, u64>,
+    /// Most recent violations, newest first
+    pub recent: Vec<ResidencyViolation>,
+}
+
+/// A breach of a tenant's code policy in one code block of a response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CodeFinding {
+    /// `license_text`, `verbatim_match` or `disallowed_package`
+    pub kind: String,
+    /// Position of the code block in the response, from 1
+    pub block: u32,
+    pub detail: String,
+    /// Share of the block found in the matched reference snippet
+    #[serde(default)]
+    pub similarity: Option<f64>,
+}
+
+/// A response annotated or blocked by the tenant's code policy
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CodePolicyDecision {
+    pub session_id: String,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    /// `annotated` or `blocked`
+    pub outcome: String,
+    pub findings: Vec<CodeFinding>,
+    pub decided_at: String,
+}
+
+/// A user's consent to be impersonated by admins of their tenant
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ImpersonationConsent {
+    pub tenant_id: String,
+    pub user_id: String,
+    pub granted_at: String,
+    pub expires_at: String,
+}
+
+/// An admin's request to act as a consenting user
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ImpersonationRequest {
+    pub user_id: String,
+    /// Why the admin needs to act as the user; recorded with every action
+    pub reason: String,
+    /// Lifetime of the token; 15 minutes by default, at most an hour
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minutes: Option<u32>,
+}
+
+/// An impersonation of a user by an admin
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Impersonation {
+    pub id: String,
+    pub tenant_id: String,
+    pub admin: String,
+    pub user_id: String,
+    pub reason: String,
+    pub started_at: String,
+    pub expires_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<String>,
+    /// Requests made with the impersonation token
+    pub actions: u64,
+}
+
+/// A started impersonation and the token to act as the user with
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ImpersonationToken {
+    pub token: String,
+    pub impersonation: Impersonation,
+}
+
+/// The span of time a compliance report covers
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReportPeriod {
+    /// How the period was asked for, e.g. `2026-Q3`
+    pub label: String,
+    pub start: String,
+    /// Exclusive
+    pub end: String,
+}
+
+/// An entry of the audit trail
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AuditEventRecord {
+    pub id: String,
+    pub event_type: String,
+    /// `low`, `medium`, `high` or `critical`
+    pub severity: String,
+    pub timestamp: String,
+    #[serde(default)]
+    pub actor_id: Option<String>,
+    pub action: String,
+    /// `success`, `failure`, `error` or `unknown`
+    pub outcome: String,
+    #[serde(default)]
+    pub resource_type: Option<String>,
+    #[serde(default)]
+    pub resource_id: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Summary of the audit trail over a report's period
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AuditEvidence {
+    pub events: u64,
+    pub by_type: BTreeMap<String, u64>,
+    pub by_severity: BTreeMap<String, u64>,
+    /// Events that failed or errored, e.g. denied access
+    pub failures: u64,
+    /// High and critical events, newest first
+    pub notable: Vec<AuditEventRecord>,
+    /// Whether more events were logged than the report read
+    pub truncated: bool,
+}
+
+/// A role and the permissions it grants
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RoleEvidence {
+    pub role: String,
+    pub level: u8,
+    pub permissions: Vec<String>,
+}
+
+/// How long one kind of data is kept
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RetentionSetting {
+    pub name: String,
+    pub value: String,
+    pub description: String,
+}
+
+/// Request to record a review of a user's access
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AccessReviewRequest {
+    /// User whose access was reviewed
+    pub subject: String,
+    /// Roles the user held when reviewed
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// `retain`, `reduce` or `revoke`
+    pub decision: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// A reviewer's sign-off on one user's access
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AccessReview {
+    pub id: String,
+    pub tenant_id: String,
+    pub reviewer: String,
+    pub subject: String,
+    pub roles: Vec<String>,
+    /// `retain`, `reduce` or `revoke`
+    pub decision: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub reviewed_at: String,
+}
+
+/// A CSV row of a user import that failed
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ImportFailure {
+    /// Line the row starts on
+    pub line: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    pub error: String,
+}
+
+/// Outcome of a CSV user import
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UserImportReport {
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub failures: Vec<ImportFailure>,
+}
+
+/// A public key that verifies tokens the server signs (RFC 7517)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct JsonWebKey {
+    /// `RSA` or `OKP`
+    pub kty: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    /// `RS256` or `EdDSA`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    #[serde(rename = "use", default, skip_serializing_if = "Option::is_none")]
+    pub key_use: Option<String>,
+    /// RSA modulus (base64url)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    /// RSA exponent (base64url)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    /// Curve of an `OKP` key, e.g. `Ed25519`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    /// Public key of an `OKP` key (base64url)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+}
+
+/// The keys that verify tokens the server signs; empty unless the server
+/// signs with rotating keys
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct JsonWebKeySet {
+    pub keys: Vec<JsonWebKey>,
+}
+
+/// Evidence of a tenant's controls over a period, for SOC 2 style audits
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ComplianceReport {
+    pub tenant_id: String,
+    pub period: ReportPeriod,
+    pub generated_at: String,
+    pub generated_by: String,
+    pub audit: AuditEvidence,
+    pub rbac: Vec<RoleEvidence>,
+    pub retention: Vec<RetentionSetting>,
+    pub access_reviews: Vec<AccessReview>,
+    /// Gaps in the evidence an auditor would ask about
+    pub exceptions: Vec<String>,
+}
+
+/// A webhook endpoint, without its secrets or header values
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookEndpoint {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    /// Subscribed event types, e.g. `agent_task_completed`; empty means all
+    pub events: Vec<String>,
+    pub enabled: bool,
+    /// Names of the extra headers sent with every delivery
+    pub header_names: Vec<String>,
+    /// Pinned payload schema version; deliveries use the current one when
+    /// unpinned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+    /// Until when deliveries are also signed with the previous secret
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_secret_expires_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Request to register a webhook endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateWebhookRequest {
+    pub name: String,
+    pub url: String,
+    /// Event types to subscribe to; empty subscribes to all
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Extra headers sent with every delivery
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    /// Payload schema version to pin deliveries to; the current one when
+    /// omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+}
+
+/// Changes to a webhook endpoint; omitted fields are left unchanged
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookEndpointUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Event types to subscribe to; empty subscribes to all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<Vec<(String, String)>>,
+    /// Payload schema version to pin to; `Some(None)` unpins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<Option<u32>>,
+}
+
+/// A webhook endpoint's signing secret, only shown when it is created or
+/// rotated
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookSecret {
+    pub endpoint: WebhookEndpoint,
+    pub secret: String,
+}
+
+/// Outcome of a test delivery to a webhook endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookPingResult {
+    pub endpoint_id: String,
+    pub delivered: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<u16>,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Deliveries of one event type to a webhook endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookEventTypeStats {
+    pub event_type: String,
+    pub count: u64,
+    pub success_rate: f64,
+}
+
+/// Delivery success and latency of a webhook endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookStats {
+    pub endpoint_id: String,
+    pub total: u64,
+    pub delivered: u64,
+    pub failed: u64,
+    pub success_rate: f64,
+    pub avg_latency_ms: f64,
+    pub p95_latency_ms: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_delivery_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_status_code: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub by_event_type: Vec<WebhookEventTypeStats>,
+}
+
+/// A published webhook payload schema version
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookSchemaVersion {
+    pub version: u32,
+    /// What changed since the previous version
+    pub description: String,
+    /// Payload objects whose shape changed in this version
+    pub changed_objects: Vec<String>,
+    /// Whether deliveries to unpinned endpoints use this version
+    pub current: bool,
+}
+
+/// JSON Schemas of the webhook envelope (`event`) and every payload object
+/// in one version
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookPayloadSchemas {
+    pub version: u32,
+    pub schemas: BTreeMap<String, serde_json::Value>,
+}
+
+/// What a bulk context job does with each matching item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum BulkContextOperation {
+    Delete,
+    Retag {
+        #[serde(default)]
+        add: Vec<String>,
+        #[serde(default)]
+        remove: Vec<String>,
+    },
+    Reembed,
+    Export,
+}
+
+/// A bulk operation over the context items matching `filter`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BulkContextRequest {
+    #[serde(flatten)]
+    pub operation: BulkContextOperation,
+    /// Conditions the items must meet, see [`ContextSearchFilter::to_json`]
+    #[serde(default)]
+    pub filter: serde_json::Value,
+}
+
+/// Bulk context job and its progress
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BulkContextJob {
+    pub id: String,
+    /// `delete`, `retag`, `reembed` or `export`
+    pub operation: String,
+    pub status: String,
+    pub matched: usize,
+    pub processed: usize,
+    pub failed: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+}
+
+impl BulkContextJob {
+    pub fn is_finished(&self) -> bool {
+        self.status != "running"
+    }
+}
+
+/// Request for a URL to upload a file to object storage
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FileUploadRequest {
+    /// `attachment` or `sandbox_artifact`
+    pub class: String,
+    /// Hex SHA-256 of the file; the upload is rejected if the body differs
+    pub sha256: String,
+    pub content_type: String,
+}
+
+/// A URL granting one request on a stored object until it expires
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PresignedUrl {
+    pub url: String,
+    /// `GET` or `PUT`
+    pub method: String,
+    pub expires_at: String,
+    /// Headers the request must carry
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+}
+
+/// Where to upload a file, unless the same content is already stored
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FileUpload {
+    pub key: String,
+    pub sha256: String,
+    pub already_stored: bool,
+    /// Absent when the content is already stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload: Option<PresignedUrl>,
+}
+
+/// An object kept in object storage under its content digest
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StoredObject {
+    pub key: String,
+    /// `attachment`, `sandbox_artifact` or `export_bundle`
+    pub class: String,
+    pub sha256: String,
+    pub size: u64,
+    pub content_type: String,
+    /// The same content was already stored
+    pub deduplicated: bool,
+}
+
+/// An export bundle kept in object storage
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportBundle {
+    pub object: StoredObject,
+    pub download: PresignedUrl,
+}
+
+/// Request to generate a Grafana dashboard from a natural language ask
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DashboardRequest {
+    /// What the dashboard should show, e.g. "checkout latency and error rate"
+    pub ask: String,
+    /// Save the dashboard to the server's Grafana
+    #[serde(default)]
+    pub push: bool,
+    /// Grafana folder to save the dashboard in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder_uid: Option<String>,
+}
+
+/// Where Grafana saved a pushed dashboard
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PushedDashboard {
+    pub uid: String,
+    pub url: String,
+    pub version: u64,
+    pub status: String,
+}
+
+/// A generated Grafana dashboard
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GeneratedDashboard {
+    /// Grafana dashboard JSON model
+    pub dashboard: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pushed: Option<PushedDashboard>,
+}
+
+/// Request to generate an alert rule from a natural language description
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AlertRuleRequest {
+    /// When the alert should fire, e.g. "checkout latency above 500ms for 10 minutes"
+    pub description: String,
+    /// Replay the rule over recent data to estimate how often it fires
+    /// (the server defaults to true)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backtest: Option<bool>,
+    /// How far back to replay the rule, in hours (default 168)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lookback_hours: Option<u32>,
+}
+
+/// A Prometheus-format alerting rule
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AlertRule {
+    pub alert: String,
+    pub expr: String,
+    #[serde(rename = "for")]
+    pub for_duration: String,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    #[serde(default)]
+    pub annotations: HashMap<String, String>,
+}
+
+/// How often an alert rule would have fired over a past window
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AlertBacktest {
+    pub window_secs: i64,
+    pub step_secs: i64,
+    /// Label sets the rule would have fired for
+    pub alerting_series: usize,
+    pub firings: usize,
+    pub firings_per_day: f64,
+    /// Fraction of the window the rule would have been firing
+    pub firing_fraction: f64,
+    /// Unix time the rule last started firing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_fired_at: Option<i64>,
+}
+
+/// A generated alert rule, to review before applying it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GeneratedAlertRule {
+    /// `PromQL` for a Prometheus rule, `LogQL` for a Loki rule
+    pub language: String,
+    pub rule: AlertRule,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backtest: Option<AlertBacktest>,
+    /// Why the back-test could not run, if it failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backtest_error: Option<String>,
+}
+
+/// Request to cluster and summarize log lines
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LogAnalysisRequest {
+    /// Log text, one entry per line
+    pub logs: String,
+    /// How many of the most frequent patterns to report (default 10)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_patterns: Option<usize>,
+    /// Have the model summarize the patterns (the server defaults to true)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summarize: Option<bool>,
+}
+
+/// A log template and the lines that matched it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LogPattern {
+    /// Template with variable tokens replaced by `<*>`
+    pub template: String,
+    pub count: usize,
+    /// Share of all lines, from 0.0 to 1.0
+    pub share: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    pub first_line: usize,
+    pub last_line: usize,
+    pub example: String,
+}
+
+/// A log pattern flagged as an anomaly
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LogFinding {
+    /// `error_pattern`, `new_pattern` or `rare_pattern`
+    pub kind: String,
+    pub template: String,
+    pub count: usize,
+    pub detail: String,
+}
+
+/// Log patterns, anomalies and their summary
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LogAnalysisReport {
+    pub total_lines: usize,
+    pub pattern_count: usize,
+    /// Most frequent patterns, most frequent first
+    pub patterns: Vec<LogPattern>,
+    pub findings: Vec<LogFinding>,
+    pub summary: String,
+}
+
 /// Sandbox information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Sandbox {
     pub id: String,
     pub template: String,
@@ -184,7 +1487,7 @@ pub struct Sandbox {
 }
 
 /// Code execution result
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ExecutionResult {
     pub success: bool,
     pub stdout: String,
@@ -194,7 +1497,7 @@ pub struct ExecutionResult {
 }
 
 /// Health check response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct HealthResponse {
     pub status: String,
     pub version: String,
@@ -205,7 +1508,7 @@ pub struct HealthResponse {
 }
 
 /// Individual service health
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ServiceHealth {
     pub status: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -215,7 +1518,7 @@ pub struct ServiceHealth {
 }
 
 /// Version information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct VersionInfo {
     pub version: String,
     pub git_commit: Option<String>,
@@ -223,6 +1526,445 @@ pub struct VersionInfo {
     pub rust_version: Option<String>,
 }
 
+/// Task queue counts reported by the dashboard
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TaskQueueStats {
+    pub queued: usize,
+    /// Queued tasks per priority lane (`low`, `normal`, `high`, `critical`)
+    #[serde(default)]
+    pub queued_by_priority: HashMap<String, usize>,
+    pub running: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+}
+
+/// A request that failed with a server error
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecentError {
+    pub timestamp: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub duration_ms: u64,
+}
+
+/// Live server activity, as shown by `copilot top`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DashboardSnapshot {
+    pub timestamp: String,
+    pub uptime_secs: u64,
+    pub requests_total: u64,
+    pub errors_total: u64,
+    pub in_flight: u64,
+    pub requests_per_sec: f64,
+    /// Requests per second over the last minute, oldest first
+    #[serde(default)]
+    pub request_rate_history: Vec<u64>,
+    pub tasks: TaskQueueStats,
+    /// Application gauges such as `workflows_active` or `sandboxes_active`
+    #[serde(default)]
+    pub gauges: HashMap<String, f64>,
+    /// Newest first
+    #[serde(default)]
+    pub recent_errors: Vec<RecentError>,
+}
+
+/// One `@@ -a,b +c,d @@` section of a unified diff
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DiffHunk {
+    /// First line of the hunk in the original file (1-based)
+    pub old_start: usize,
+    pub old_lines: usize,
+    /// First line of the hunk in the edited file (1-based)
+    pub new_start: usize,
+    pub new_lines: usize,
+    /// Text after the closing `@@`, typically the enclosing function
+    #[serde(default)]
+    pub section: String,
+    /// Hunk body, each line prefixed with `' '`, `'-'` or `'+'`
+    pub lines: Vec<String>,
+}
+
+/// Changes to a single file proposed by the agent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct FileEdit {
+    /// Path relative to the project root
+    pub path: String,
+    #[serde(default)]
+    pub created: bool,
+    #[serde(default)]
+    pub deleted: bool,
+    pub hunks: Vec<DiffHunk>,
+    /// The unified diff for this file as it appeared in the response
+    pub diff: String,
+}
+
+/// Code edits proposed in a session's latest response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProposedEdits {
+    pub session_id: String,
+    #[serde(default)]
+    pub edits: Vec<FileEdit>,
+}
+
+/// Request to hand a session over to humans
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct HandoffRequest {
+    /// Handoff endpoints to post to, e.g. `incident` or `ticket`; empty posts
+    /// to every endpoint subscribed to handoffs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+    /// Notes for the people taking over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Build the handoff document without posting it
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// What a conversation established, for the humans taking it over
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HandoffBundle {
+    pub conversation_id: String,
+    pub title: String,
+    pub summary: String,
+    #[serde(default)]
+    pub findings: Vec<String>,
+    #[serde(default)]
+    pub citations: Vec<String>,
+    /// Shell commands run or proposed during the conversation
+    #[serde(default)]
+    pub commands: Vec<String>,
+    #[serde(default)]
+    pub open_questions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    pub message_count: usize,
+    pub started_at: String,
+    pub last_message_at: String,
+}
+
+/// Outcome of posting a handoff to one endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HandoffDelivery {
+    pub endpoint_id: String,
+    pub delivered: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A session's handoff bundle and where it was posted
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HandoffResponse {
+    pub bundle: HandoffBundle,
+    /// The bundle as a Markdown document
+    pub document: String,
+    /// Empty for dry runs
+    #[serde(default)]
+    pub deliveries: Vec<HandoffDelivery>,
+}
+
+/// How long answers should be
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    Brief,
+    Normal,
+    Detailed,
+}
+
+/// A user's confirmed preferences, applied to their sessions' prompts
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UserPreferences {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verbosity: Option<Verbosity>,
+    /// Language to answer in, e.g. `Spanish`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Time range used when a question gives none, e.g. `24h` or `7d`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_time_range: Option<String>,
+    #[serde(default)]
+    pub favorite_services: Vec<String>,
+    /// Whether preferences may be learned from the user's messages
+    #[serde(default = "default_true")]
+    pub learning: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        Self {
+            verbosity: None,
+            language: None,
+            default_time_range: None,
+            favorite_services: Vec::new(),
+            learning: true,
+            updated_at: None,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Which preference a suggestion is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PreferenceKey {
+    Verbosity,
+    Language,
+    DefaultTimeRange,
+    FavoriteService,
+}
+
+/// A preference learned from the user's messages, awaiting confirmation
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PreferenceSuggestion {
+    pub id: String,
+    pub key: PreferenceKey,
+    pub value: String,
+    /// Message the preference was last seen in
+    pub evidence: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub suggested_at: String,
+}
+
+/// The caller's preferences and the learned ones awaiting confirmation
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PreferencesResponse {
+    pub preferences: UserPreferences,
+    #[serde(default)]
+    pub suggestions: Vec<PreferenceSuggestion>,
+}
+
+/// What a context prefetch request did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PrefetchOutcome {
+    /// The partial message was retrieved and cached
+    Warmed,
+    /// Context for the partial message was already cached
+    AlreadyWarm,
+    /// The partial message was too short to be worth retrieving
+    TooShort,
+}
+
+/// How often prefetched context was used and the latency it saved
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PrefetchStats {
+    pub prefetches: u64,
+    pub prefetches_skipped: u64,
+    /// Retrievals answered from prefetched results
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+    pub avg_hit_latency_ms: f64,
+    pub avg_miss_latency_ms: f64,
+}
+
+/// Embedding cache hits by tier
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct EmbeddingCacheStats {
+    /// Lookups answered from the server's memory
+    pub memory_hits: u64,
+    /// Lookups answered from the shared (Redis) tier
+    pub shared_hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+    /// Failed reads and writes of the shared tier
+    pub shared_errors: u64,
+    /// Embeddings held in memory
+    pub entries: usize,
+}
+
+/// What an embedding cache warmup did
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct EmbeddingWarmup {
+    /// Texts looked at
+    pub texts: usize,
+    /// Texts that were already cached
+    pub already_cached: usize,
+    /// Texts embedded and cached
+    pub embedded: usize,
+}
+
+/// What a warmup of archived context did
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ContextWarmup {
+    /// Archived items brought back into memory
+    pub rehydrated: usize,
+}
+
+/// GitHub check run a gate reports on
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CheckRunContext {
+    /// `owner/repo`
+    pub repository: String,
+    pub head_sha: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pull_request: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<u64>,
+    /// Name of the check run; the server derives one from the target when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details_url: Option<String>,
+}
+
+impl CheckRunContext {
+    /// Context of the running GitHub Actions job, from its environment
+    ///
+    /// Returns `None` outside of Actions. For pull request events the check
+    /// is reported on the pull request's head commit rather than the merge
+    /// commit in `GITHUB_SHA`.
+    pub fn from_github_actions() -> Option<Self> {
+        let event = std::env::var("GITHUB_EVENT_PATH")
+            .ok()
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+        Self::from_github_vars(|name| std::env::var(name).ok(), event)
+    }
+
+    fn from_github_vars(
+        var: impl Fn(&str) -> Option<String>,
+        event: Option<serde_json::Value>,
+    ) -> Option<Self> {
+        let repository = var("GITHUB_REPOSITORY")?;
+        let pr_head_sha = event
+            .as_ref()
+            .and_then(|e| e.pointer("/pull_request/head/sha"))
+            .and_then(|sha| sha.as_str())
+            .map(str::to_string);
+        let head_sha = pr_head_sha.or_else(|| var("GITHUB_SHA"))?;
+        let pull_request = var("GITHUB_REF")
+            .and_then(|r| r.strip_prefix("refs/pull/").map(str::to_string))
+            .and_then(|r| r.split('/').next().and_then(|n| n.parse().ok()));
+        let head_branch = var("GITHUB_HEAD_REF")
+            .filter(|r| !r.is_empty())
+            .or_else(|| var("GITHUB_REF_NAME"));
+        let run_id = var("GITHUB_RUN_ID").and_then(|id| id.parse().ok());
+        let details_url = match (var("GITHUB_SERVER_URL"), run_id) {
+            (Some(server), Some(run_id)) => {
+                Some(format!("{}/{}/actions/runs/{}", server, repository, run_id))
+            }
+            _ => None,
+        };
+
+        Some(Self {
+            repository,
+            head_sha,
+            head_branch,
+            pull_request,
+            run_id,
+            check_name: None,
+            details_url,
+        })
+    }
+}
+
+/// What a gate runs: a named workflow (`kind = "workflow"`) or the
+/// benchmarks matching a filter (`kind = "benchmarks"`)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GateTarget {
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    /// Fail benchmarks slower than this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration_ms: Option<u64>,
+}
+
+impl GateTarget {
+    /// Run the workflow registered as `name`
+    pub fn workflow(name: impl Into<String>) -> Self {
+        Self {
+            kind: "workflow".to_string(),
+            name: Some(name.into()),
+            filter: None,
+            max_duration_ms: None,
+        }
+    }
+
+    /// Run every benchmark whose ID starts with `filter`
+    pub fn benchmarks(filter: impl Into<String>) -> Self {
+        Self {
+            kind: "benchmarks".to_string(),
+            name: None,
+            filter: Some(filter.into()),
+            max_duration_ms: None,
+        }
+    }
+
+    pub fn max_duration_ms(mut self, max_duration_ms: u64) -> Self {
+        self.max_duration_ms = Some(max_duration_ms);
+        self
+    }
+}
+
+/// Request to run a CI gate
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GateRequest {
+    pub context: CheckRunContext,
+    pub target: GateTarget,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+/// One workflow step or benchmark behind a gate verdict
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GateCheck {
+    pub name: String,
+    pub conclusion: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Check-run `output` object
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CheckOutput {
+    pub title: String,
+    pub summary: String,
+    pub text: String,
+}
+
+/// Outcome of a gate; apart from `checks`, the fields are those of GitHub's
+/// create-check-run request body
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GateVerdict {
+    pub name: String,
+    pub head_sha: String,
+    pub status: String,
+    /// `success`, `failure`, `neutral`, `skipped`, `timed_out` or `action_required`
+    pub conclusion: String,
+    pub started_at: String,
+    pub completed_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details_url: Option<String>,
+    pub external_id: String,
+    pub output: CheckOutput,
+    #[serde(default)]
+    pub checks: Vec<GateCheck>,
+}
+
+impl GateVerdict {
+    /// Whether a required status check with this conclusion lets a merge through
+    pub fn passed(&self) -> bool {
+        matches!(self.conclusion.as_str(), "success" | "neutral" | "skipped")
+    }
+}
+
 /// Chat options for configuring requests
 #[derive(Debug, Clone, Default)]
 pub struct ChatOptions {
@@ -263,3 +2005,169 @@ impl ChatOptions {
         self
     }
 }
+
+/// Filters narrowing a context search; unset filters match everything
+#[derive(Debug, Clone, Default)]
+pub struct ContextSearchFilter {
+    pub tags: Vec<String>,
+    pub source_prefix: Option<String>,
+    pub language: Option<String>,
+    pub content_type: Option<String>,
+    /// Earliest ingestion time (RFC 3339), inclusive
+    pub since: Option<String>,
+    /// Latest ingestion time (RFC 3339), exclusive
+    pub until: Option<String>,
+    /// Time (RFC 3339) to view the corpus as of, with only the document
+    /// versions current then
+    pub as_of: Option<String>,
+}
+
+impl ContextSearchFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn source_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.source_prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    pub fn since(mut self, time: impl Into<String>) -> Self {
+        self.since = Some(time.into());
+        self
+    }
+
+    pub fn until(mut self, time: impl Into<String>) -> Self {
+        self.until = Some(time.into());
+        self
+    }
+
+    pub fn as_of(mut self, time: impl Into<String>) -> Self {
+        self.as_of = Some(time.into());
+        self
+    }
+
+    /// The filter as the JSON object bulk operations take
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut filter = serde_json::Map::new();
+        if !self.tags.is_empty() {
+            filter.insert("tags".to_string(), serde_json::json!(self.tags));
+        }
+        let optional = [
+            ("source_prefix", &self.source_prefix),
+            ("language", &self.language),
+            ("content_type", &self.content_type),
+            ("ingested_after", &self.since),
+            ("ingested_before", &self.until),
+            ("as_of", &self.as_of),
+        ];
+        for (name, value) in optional {
+            if let Some(value) = value {
+                filter.insert(name.to_string(), serde_json::json!(value));
+            }
+        }
+        serde_json::Value::Object(filter)
+    }
+
+    /// Query parameters the server reads the filter from
+    pub fn query_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if !self.tags.is_empty() {
+            pairs.push(("tags", self.tags.join(",")));
+        }
+        let optional = [
+            ("source", &self.source_prefix),
+            ("language", &self.language),
+            ("type", &self.content_type),
+            ("since", &self.since),
+            ("until", &self.until),
+            ("as_of", &self.as_of),
+        ];
+        for (name, value) in optional {
+            if let Some(value) = value {
+                pairs.push((name, value.clone()));
+            }
+        }
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_run_context_from_pull_request_job() {
+        let vars: HashMap<&str, &str> = [
+            ("GITHUB_REPOSITORY", "acme/widgets"),
+            ("GITHUB_SHA", "merge-sha"),
+            ("GITHUB_REF", "refs/pull/17/merge"),
+            ("GITHUB_REF_NAME", "17/merge"),
+            ("GITHUB_HEAD_REF", "feature/gates"),
+            ("GITHUB_RUN_ID", "9001"),
+            ("GITHUB_SERVER_URL", "https://github.com"),
+        ]
+        .into_iter()
+        .collect();
+        let event = serde_json::json!({ "pull_request": { "head": { "sha": "head-sha" } } });
+
+        let context =
+            CheckRunContext::from_github_vars(|name| vars.get(name).map(|v| v.to_string()), Some(event))
+                .unwrap();
+        assert_eq!(context.head_sha, "head-sha");
+        assert_eq!(context.pull_request, Some(17));
+        assert_eq!(context.head_branch.as_deref(), Some("feature/gates"));
+        assert_eq!(
+            context.details_url.as_deref(),
+            Some("https://github.com/acme/widgets/actions/runs/9001")
+        );
+
+        assert!(CheckRunContext::from_github_vars(|_| None, None).is_none());
+    }
+
+    #[test]
+    fn test_context_search_filter_query_pairs() {
+        assert!(ContextSearchFilter::new().query_pairs().is_empty());
+        assert_eq!(ContextSearchFilter::new().to_json(), serde_json::json!({}));
+
+        let filter = ContextSearchFilter::new()
+            .tag("backend")
+            .tag("core")
+            .content_type("code")
+            .since("2026-01-01T00:00:00Z")
+            .as_of("2026-03-03T00:00:00Z");
+        assert_eq!(
+            filter.query_pairs(),
+            vec![
+                ("tags", "backend,core".to_string()),
+                ("type", "code".to_string()),
+                ("since", "2026-01-01T00:00:00Z".to_string()),
+                ("as_of", "2026-03-03T00:00:00Z".to_string()),
+            ]
+        );
+        assert_eq!(
+            filter.to_json(),
+            serde_json::json!({
+                "tags": ["backend", "core"],
+                "content_type": "code",
+                "ingested_after": "2026-01-01T00:00:00Z",
+                "as_of": "2026-03-03T00:00:00Z"
+            })
+        );
+    }
+}
@@ -4,6 +4,7 @@ use crate::error::{CopilotError, Result};
 use futures::Stream;
 use serde::{Deserialize, Serialize};
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
 
 /// Events received during streaming
@@ -73,6 +74,160 @@ impl Stream for ChatStream {
     }
 }
 
+/// A stream of server activity snapshots
+pub type DashboardStream =
+    Pin<Box<dyn Stream<Item = Result<crate::models::DashboardSnapshot>> + Send>>;
+
+/// A stream of the events of one run
+pub type RunEventStream = Pin<Box<dyn Stream<Item = Result<crate::models::RunEvent>> + Send>>;
+
+/// A server-sent event
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Split a server-sent event byte stream into the `data` payloads of its
+/// events, buffering events that span several chunks. Comments and
+/// keep-alives are skipped.
+pub fn sse_data<S, B, E>(bytes: S) -> impl Stream<Item = Result<String>> + Send
+where
+    S: Stream<Item = std::result::Result<B, E>> + Send + Unpin,
+    B: AsRef<[u8]>,
+    E: Into<CopilotError>,
+{
+    use futures::StreamExt;
+
+    sse_events(bytes).map(|event| event.map(|event| event.data))
+}
+
+/// Split a server-sent event byte stream into its events, buffering events
+/// that span several chunks. Comments and keep-alives are skipped.
+pub fn sse_events<S, B, E>(bytes: S) -> impl Stream<Item = Result<SseEvent>> + Send
+where
+    S: Stream<Item = std::result::Result<B, E>> + Send + Unpin,
+    B: AsRef<[u8]>,
+    E: Into<CopilotError>,
+{
+    futures::stream::unfold(
+        (bytes, String::new(), false),
+        |(mut bytes, mut buffer, mut ended)| async move {
+            use futures::StreamExt;
+
+            loop {
+                if let Some(end) = buffer.find("\n\n") {
+                    let block: String = buffer.drain(..end + 2).collect();
+                    let mut event = SseEvent::default();
+                    let mut data = Vec::new();
+                    for line in block.lines() {
+                        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+                        let value = value.strip_prefix(' ').unwrap_or(value);
+                        match field {
+                            "data" => data.push(value),
+                            "id" => event.id = Some(value.to_string()),
+                            "event" => event.event = Some(value.to_string()),
+                            _ => {}
+                        }
+                    }
+                    if data.is_empty() {
+                        continue;
+                    }
+                    event.data = data.join("\n");
+                    return Some((Ok(event), (bytes, buffer, ended)));
+                }
+                if ended {
+                    return None;
+                }
+                match bytes.next().await {
+                    Some(Ok(chunk)) => {
+                        buffer.push_str(
+                            &String::from_utf8_lossy(chunk.as_ref()).replace("\r\n", "\n"),
+                        );
+                    }
+                    Some(Err(e)) => {
+                        ended = true;
+                        return Some((Err(e.into()), (bytes, buffer, ended)));
+                    }
+                    None => {
+                        // A final event without its blank line still counts
+                        ended = true;
+                        if !buffer.trim().is_empty() {
+                            buffer.push_str("\n\n");
+                        }
+                    }
+                }
+            }
+        },
+    )
+}
+
+/// A chunk of a streamed session reply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplyChunk {
+    /// `Token`, `Thinking`, `Metadata`, `Error` or `Done`
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    pub content: String,
+    pub sequence: usize,
+    pub is_final: bool,
+    #[serde(default)]
+    pub metadata: std::collections::HashMap<String, String>,
+}
+
+/// A streamed session reply that reconnects by itself after a dropped
+/// connection, continuing after the last chunk received
+pub struct ReplyStream {
+    stream_id: String,
+    last_event_id: Arc<Mutex<Option<String>>>,
+    inner: Pin<Box<dyn Stream<Item = Result<ReplyChunk>> + Send>>,
+}
+
+impl ReplyStream {
+    pub(crate) fn new(
+        stream_id: String,
+        last_event_id: Arc<Mutex<Option<String>>>,
+        inner: Pin<Box<dyn Stream<Item = Result<ReplyChunk>> + Send>>,
+    ) -> Self {
+        Self {
+            stream_id,
+            last_event_id,
+            inner,
+        }
+    }
+
+    /// ID of the stream on the server, for resuming it later with
+    /// [`CopilotClient::resume_session_stream`](crate::CopilotClient::resume_session_stream)
+    pub fn stream_id(&self) -> &str {
+        &self.stream_id
+    }
+
+    /// ID of the last event received, to resume after
+    pub fn last_event_id(&self) -> Option<String> {
+        self.last_event_id.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Collect the reply's text
+    pub async fn collect_content(mut self) -> Result<String> {
+        use futures::StreamExt;
+
+        let mut content = String::new();
+        while let Some(chunk) = self.next().await {
+            content.push_str(&chunk?.content);
+        }
+        Ok(content)
+    }
+}
+
+impl Stream for ReplyStream {
+    type Item = Result<ReplyChunk>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
 /// Builder for creating mock streams (useful for testing)
 #[derive(Default)]
 pub struct MockStreamBuilder {
@@ -122,6 +277,20 @@ mod tests {
         assert_eq!(content, "Hello, world!");
     }
 
+    #[tokio::test]
+    async fn test_sse_data_reassembles_split_events() {
+        let chunks = vec![
+            Ok::<_, CopilotError>(": keep-alive\n\nevent: snapshot\ndata: {\"a\"".as_bytes()),
+            Ok(":1}\n\nda".as_bytes()),
+            Ok("ta: second".as_bytes()),
+        ];
+        let data: Vec<String> = sse_data(futures::stream::iter(chunks))
+            .map(|item| item.unwrap())
+            .collect()
+            .await;
+        assert_eq!(data, ["{\"a\":1}", "second"]);
+    }
+
     #[tokio::test]
     async fn test_stream_iteration() {
         let mut stream = MockStreamBuilder::new()
@@ -0,0 +1,230 @@
+//! Email verification and password reset tokens
+//!
+//! [`AccountTokens`] issues the tokens mailed to users to verify their email
+//! address or reset a forgotten password. A token is a signed, time-limited
+//! statement of its purpose and user, bound to the state it changes: a
+//! verification token names the address it verifies and a reset token a
+//! fingerprint of the password it replaces, so changing either voids the
+//! tokens outstanding for it. Tokens are signed with a key derived from the
+//! JWT secret and are not JWTs, so they never pass for access tokens.
+//!
+//! [`AuthService`](crate::AuthService) redeems each token once and hands the
+//! emails carrying them to an [`AccountMailer`].
+
+use crate::auth::User;
+use crate::error::{Result, SecurityError};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Context the token signing key is derived for
+const KEY_CONTEXT: &[u8] = b"copilot-account-tokens";
+
+/// What a token lets its holder do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountTokenPurpose {
+    VerifyEmail,
+    ResetPassword,
+}
+
+impl AccountTokenPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::VerifyEmail => "verify_email",
+            Self::ResetPassword => "reset_password",
+        }
+    }
+}
+
+/// How long tokens are valid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountTokenConfig {
+    pub verify_email_ttl_secs: i64,
+    pub reset_password_ttl_secs: i64,
+}
+
+impl Default for AccountTokenConfig {
+    fn default() -> Self {
+        Self {
+            verify_email_ttl_secs: 86_400,  // 1 day
+            reset_password_ttl_secs: 3_600, // 1 hour
+        }
+    }
+}
+
+/// The signed content of a token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountToken {
+    /// Token ID, recorded once the token is redeemed
+    pub jti: String,
+    /// User ID
+    pub sub: String,
+    pub purpose: AccountTokenPurpose,
+    /// The email address or password fingerprint the token is bound to
+    pub binding: String,
+    /// Expiration time (Unix timestamp)
+    pub exp: i64,
+}
+
+impl AccountToken {
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.exp, 0).unwrap_or_else(Utc::now)
+    }
+}
+
+/// An email carrying a token to a user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountEmail {
+    pub purpose: AccountTokenPurpose,
+    pub user_id: String,
+    pub username: String,
+    /// Address the email goes to
+    pub to: String,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Delivers account emails, e.g. through the notification subsystem
+#[async_trait::async_trait]
+pub trait AccountMailer: Send + Sync {
+    async fn send(&self, email: &AccountEmail) -> Result<()>;
+}
+
+/// Issues and checks email verification and password reset tokens
+#[derive(Clone)]
+pub struct AccountTokens {
+    key: Vec<u8>,
+    config: AccountTokenConfig,
+}
+
+impl std::fmt::Debug for AccountTokens {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccountTokens")
+            .field("key", &"[REDACTED]")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl AccountTokens {
+    /// Tokens signed with a key derived from `secret`
+    pub fn new(secret: &str, config: AccountTokenConfig) -> Self {
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(KEY_CONTEXT);
+        Self {
+            key: mac.finalize().into_bytes().to_vec(),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &AccountTokenConfig {
+        &self.config
+    }
+
+    /// What a token for `user` is bound to: the lowercased email address it
+    /// verifies, or a fingerprint of the password hash it replaces
+    pub fn binding(purpose: AccountTokenPurpose, user: &User) -> String {
+        match purpose {
+            AccountTokenPurpose::VerifyEmail => user.email.to_lowercase(),
+            AccountTokenPurpose::ResetPassword => {
+                hex::encode(&Sha256::digest(user.password_hash.as_bytes())[..16])
+            }
+        }
+    }
+
+    /// Issue a token for `user`
+    pub fn issue(&self, purpose: AccountTokenPurpose, user: &User) -> Result<(String, AccountToken)> {
+        let ttl = match purpose {
+            AccountTokenPurpose::VerifyEmail => self.config.verify_email_ttl_secs,
+            AccountTokenPurpose::ResetPassword => self.config.reset_password_ttl_secs,
+        };
+        let token = AccountToken {
+            jti: Uuid::new_v4().to_string(),
+            sub: user.id.clone(),
+            purpose,
+            binding: Self::binding(purpose, user),
+            exp: (Utc::now() + Duration::seconds(ttl)).timestamp(),
+        };
+        let payload = serde_json::to_vec(&token).map_err(|e| SecurityError::Internal(e.to_string()))?;
+        let payload = URL_SAFE_NO_PAD.encode(payload);
+        let signature = URL_SAFE_NO_PAD.encode(self.sign(payload.as_bytes()).finalize().into_bytes());
+        Ok((format!("{}.{}", payload, signature), token))
+    }
+
+    /// The content of a token issued for `purpose`, if it is authentic and
+    /// unexpired; whether it was already redeemed is up to the caller
+    pub fn verify(&self, token: &str, purpose: AccountTokenPurpose) -> Result<AccountToken> {
+        let invalid = || SecurityError::InvalidToken(format!("Not a valid {} token", purpose.as_str()));
+        let (payload, signature) = token.trim().split_once('.').ok_or_else(invalid)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+        self.sign(payload.as_bytes())
+            .verify_slice(&signature)
+            .map_err(|_| invalid())?;
+
+        let payload = URL_SAFE_NO_PAD.decode(payload).map_err(|_| invalid())?;
+        let token: AccountToken = serde_json::from_slice(&payload).map_err(|_| invalid())?;
+        if token.purpose != purpose {
+            return Err(invalid());
+        }
+        if token.exp <= Utc::now().timestamp() {
+            return Err(SecurityError::TokenExpired);
+        }
+        Ok(token)
+    }
+
+    fn sign(&self, payload: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(payload);
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_are_signed_and_bound_to_their_purpose() {
+        let tokens = AccountTokens::new("secret", AccountTokenConfig::default());
+        let user = User::new("alice", "Alice@example.com", "$argon2id$hash");
+
+        let (token, issued) = tokens.issue(AccountTokenPurpose::VerifyEmail, &user).unwrap();
+        assert_eq!(tokens.verify(&token, AccountTokenPurpose::VerifyEmail).unwrap(), issued);
+        assert_eq!(issued.binding, "alice@example.com");
+        assert!(tokens.verify(&token, AccountTokenPurpose::ResetPassword).is_err());
+
+        // Another secret, a tampered payload or an expired token is refused
+        let other = AccountTokens::new("other", AccountTokenConfig::default());
+        assert!(matches!(
+            other.verify(&token, AccountTokenPurpose::VerifyEmail),
+            Err(SecurityError::InvalidToken(_))
+        ));
+        let (payload, signature) = token.split_once('.').unwrap();
+        let forged = URL_SAFE_NO_PAD.encode(
+            String::from_utf8(URL_SAFE_NO_PAD.decode(payload).unwrap())
+                .unwrap()
+                .replace(&user.id, "someone-else"),
+        );
+        assert!(tokens
+            .verify(&format!("{}.{}", forged, signature), AccountTokenPurpose::VerifyEmail)
+            .is_err());
+        let expired = AccountTokens::new(
+            "secret",
+            AccountTokenConfig {
+                reset_password_ttl_secs: -1,
+                ..Default::default()
+            },
+        );
+        let (token, _) = expired.issue(AccountTokenPurpose::ResetPassword, &user).unwrap();
+        assert!(matches!(
+            expired.verify(&token, AccountTokenPurpose::ResetPassword),
+            Err(SecurityError::TokenExpired)
+        ));
+    }
+}
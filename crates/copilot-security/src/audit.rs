@@ -3,8 +3,9 @@
 //! Provides comprehensive audit logging for security-relevant events.
 
 use chrono::{DateTime, Utc};
+use copilot_core::PromptLogging;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::net::IpAddr;
 use tracing::{info, warn};
 use uuid::Uuid;
@@ -28,6 +29,7 @@ pub enum AuditEventType {
     AccessDenied,
     PermissionElevated,
     PermissionRevoked,
+    AccessReviewed,
 
     // User management
     UserCreated,
@@ -68,6 +70,11 @@ pub enum AuditEventType {
     DataExported,
     DataImported,
     DataPurged,
+
+    // Impersonation events
+    ImpersonationStarted,
+    ImpersonationEnded,
+    ImpersonatedAction,
 }
 
 impl AuditEventType {
@@ -85,7 +92,8 @@ impl AuditEventType {
             | AuditEventType::RateLimitExceeded
             | AuditEventType::UserDeleted
             | AuditEventType::ApiKeyRevoked
-            | AuditEventType::TokenRevoked => AuditSeverity::High,
+            | AuditEventType::TokenRevoked
+            | AuditEventType::ImpersonationStarted => AuditSeverity::High,
 
             // Medium severity
             AuditEventType::LoginSuccess
@@ -95,11 +103,14 @@ impl AuditEventType {
             | AuditEventType::UserUpdated
             | AuditEventType::RoleAssigned
             | AuditEventType::RoleRevoked
+            | AuditEventType::AccessReviewed
             | AuditEventType::ApiKeyCreated
             | AuditEventType::ResourceDeleted
             | AuditEventType::ConfigChanged
             | AuditEventType::DataExported
-            | AuditEventType::DataPurged => AuditSeverity::Medium,
+            | AuditEventType::DataPurged
+            | AuditEventType::ImpersonationEnded
+            | AuditEventType::ImpersonatedAction => AuditSeverity::Medium,
 
             // Low severity
             AuditEventType::TokenRefresh
@@ -141,6 +152,7 @@ impl AuditEventType {
             AuditEventType::AccessDenied => "access_denied",
             AuditEventType::PermissionElevated => "permission_elevated",
             AuditEventType::PermissionRevoked => "permission_revoked",
+            AuditEventType::AccessReviewed => "access_reviewed",
             AuditEventType::UserCreated => "user_created",
             AuditEventType::UserUpdated => "user_updated",
             AuditEventType::UserDeleted => "user_deleted",
@@ -169,6 +181,9 @@ impl AuditEventType {
             AuditEventType::DataExported => "data_exported",
             AuditEventType::DataImported => "data_imported",
             AuditEventType::DataPurged => "data_purged",
+            AuditEventType::ImpersonationStarted => "impersonation_started",
+            AuditEventType::ImpersonationEnded => "impersonation_ended",
+            AuditEventType::ImpersonatedAction => "impersonated_action",
         }
     }
 }
@@ -331,6 +346,14 @@ impl AuditEvent {
         self
     }
 
+    /// Attach prompt or response text, retained as `mode` allows
+    pub fn with_content(mut self, key: &str, content: &str, mode: PromptLogging) -> Self {
+        self.metadata.extend(mode.fields(key, content));
+        self.metadata
+            .insert("prompt_logging".to_string(), mode.as_str().into());
+        self
+    }
+
     /// Set description
     pub fn with_description(mut self, description: &str) -> Self {
         self.description = Some(description.to_string());
@@ -420,10 +443,12 @@ impl AuditLogger for TracingAuditLogger {
     }
 }
 
-/// In-memory audit logger (for testing)
+/// In-memory audit logger, for tests and for keeping recent events
+/// queryable next to a logger that is not
 #[derive(Debug, Default)]
 pub struct InMemoryAuditLogger {
-    events: std::sync::Arc<tokio::sync::RwLock<Vec<AuditEvent>>>,
+    events: std::sync::Arc<tokio::sync::RwLock<VecDeque<AuditEvent>>>,
+    capacity: Option<usize>,
 }
 
 impl InMemoryAuditLogger {
@@ -431,8 +456,14 @@ impl InMemoryAuditLogger {
         Self::default()
     }
 
+    /// Keep only the `capacity` most recent events
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity.max(1));
+        self
+    }
+
     pub async fn get_events(&self) -> Vec<AuditEvent> {
-        self.events.read().await.clone()
+        self.events.read().await.iter().cloned().collect()
     }
 
     pub async fn clear(&self) {
@@ -443,7 +474,11 @@ impl InMemoryAuditLogger {
 #[async_trait::async_trait]
 impl AuditLogger for InMemoryAuditLogger {
     async fn log(&self, event: AuditEvent) {
-        self.events.write().await.push(event);
+        let mut events = self.events.write().await;
+        if self.capacity.is_some_and(|capacity| events.len() >= capacity) {
+            events.pop_front();
+        }
+        events.push_back(event);
     }
 
     async fn query(
@@ -473,6 +508,21 @@ impl AuditLogger for InMemoryAuditLogger {
                         return false;
                     }
                 }
+                if let Some(ref resource_type) = filter.resource_type {
+                    if e.resource_type.as_ref() != Some(resource_type) {
+                        return false;
+                    }
+                }
+                if let Some(ref resource_id) = filter.resource_id {
+                    if e.resource_id.as_ref() != Some(resource_id) {
+                        return false;
+                    }
+                }
+                if let Some(ref tenant_id) = filter.tenant_id {
+                    if e.tenant_id.as_ref() != Some(tenant_id) {
+                        return false;
+                    }
+                }
                 if let Some(ref outcome) = filter.outcome {
                     if e.outcome != *outcome {
                         return false;
@@ -562,6 +612,21 @@ mod tests {
         assert_eq!(event.ip_address, Some("192.168.1.1".to_string()));
     }
 
+    #[test]
+    fn test_audit_event_content_privacy() {
+        let event = AuditEvent::new(AuditEventType::ResourceCreated, "chat.message")
+            .with_content("prompt", "rotate the prod keys", PromptLogging::Hashed);
+        let prompt = event.metadata["prompt"].as_str().unwrap();
+        assert!(prompt.starts_with("sha256:"));
+        assert_eq!(event.metadata["prompt_chars"], 20);
+        assert_eq!(event.metadata["prompt_logging"], "hashed");
+
+        let event = AuditEvent::new(AuditEventType::ResourceCreated, "chat.message")
+            .with_content("prompt", "rotate the prod keys", PromptLogging::Off);
+        assert!(!event.metadata.contains_key("prompt"));
+        assert!(!event.metadata.contains_key("prompt_chars"));
+    }
+
     #[tokio::test]
     async fn test_in_memory_logger() {
         let logger = InMemoryAuditLogger::new();
@@ -577,6 +642,23 @@ mod tests {
         assert_eq!(events[0].event_type, AuditEventType::LoginSuccess);
     }
 
+    #[tokio::test]
+    async fn test_in_memory_logger_capacity() {
+        let logger = InMemoryAuditLogger::new().with_capacity(2);
+        for tenant in ["acme", "globex", "acme"] {
+            logger
+                .log(AuditEvent::new(AuditEventType::DataExported, "context.export").with_tenant_id(tenant))
+                .await;
+        }
+        assert_eq!(logger.get_events().await.len(), 2);
+
+        let filter = AuditFilter {
+            tenant_id: Some("acme".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(logger.query(filter, 10, 0).await.len(), 1);
+    }
+
     #[tokio::test]
     async fn test_audit_query() {
         let logger = InMemoryAuditLogger::new();
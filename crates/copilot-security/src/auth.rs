@@ -1,7 +1,11 @@
 //! Authentication service
 //!
-//! Provides user authentication, registration, and token management.
+//! Provides user authentication, registration, and token management, and
+//! the email verification and password reset flows.
 
+use crate::account::{
+    AccountEmail, AccountMailer, AccountToken, AccountTokenConfig, AccountTokenPurpose, AccountTokens,
+};
 use crate::audit::{AuditEvent, AuditEventType, AuditLogger, AuditOutcome};
 use crate::error::{Result, SecurityError};
 use crate::jwt::{Claims, JwtConfig, JwtManager, TokenPair};
@@ -162,6 +166,8 @@ pub trait UserStore: Send + Sync {
     async fn update(&self, user: User) -> Result<User>;
     /// Delete a user
     async fn delete(&self, id: &str) -> Result<()>;
+    /// List users, of one tenant if given
+    async fn list(&self, tenant_id: Option<&str>) -> Result<Vec<User>>;
 }
 
 /// Token blacklist trait (for logout/revocation)
@@ -247,6 +253,15 @@ impl UserStore for InMemoryUserStore {
             Err(SecurityError::UserNotFound)
         }
     }
+
+    async fn list(&self, tenant_id: Option<&str>) -> Result<Vec<User>> {
+        let users = self.users.read().await;
+        Ok(users
+            .iter()
+            .filter(|u| tenant_id.is_none() || u.tenant_id.as_deref() == tenant_id)
+            .cloned()
+            .collect())
+    }
 }
 
 /// In-memory token blacklist
@@ -291,6 +306,8 @@ pub struct AuthServiceConfig {
     pub lockout_duration_secs: i64,
     /// Require email verification
     pub require_email_verification: bool,
+    /// Email verification and password reset token lifetimes
+    pub account_tokens: AccountTokenConfig,
 }
 
 impl Default for AuthServiceConfig {
@@ -301,6 +318,7 @@ impl Default for AuthServiceConfig {
             max_failed_attempts: 5,
             lockout_duration_secs: 900, // 15 minutes
             require_email_verification: false,
+            account_tokens: AccountTokenConfig::default(),
         }
     }
 }
@@ -314,6 +332,8 @@ pub struct AuthService {
     user_store: Arc<dyn UserStore>,
     token_blacklist: Arc<dyn TokenBlacklist>,
     audit_logger: Arc<dyn AuditLogger>,
+    account_tokens: AccountTokens,
+    mailer: Option<Arc<dyn AccountMailer>>,
 }
 
 impl AuthService {
@@ -327,6 +347,7 @@ impl AuthService {
         let jwt_manager = JwtManager::new(config.jwt_config.clone());
         let password_manager = PasswordManager::new(config.password_config.clone())?;
         let rbac_manager = RbacManager::new();
+        let account_tokens = AccountTokens::new(&config.jwt_config.secret, config.account_tokens);
 
         Ok(Self {
             config,
@@ -336,9 +357,28 @@ impl AuthService {
             user_store,
             token_blacklist,
             audit_logger,
+            account_tokens,
+            mailer: None,
         })
     }
 
+    /// Sign tokens with the current key of `keys` instead of the JWT secret
+    pub fn with_signing_keys(mut self, keys: crate::signing_keys::SigningKeys) -> Self {
+        self.jwt_manager = self.jwt_manager.with_signing_keys(keys);
+        self
+    }
+
+    /// Deliver verification and password reset emails through `mailer`
+    pub fn with_mailer(mut self, mailer: Arc<dyn AccountMailer>) -> Self {
+        self.mailer = Some(mailer);
+        self
+    }
+
+    /// Where verification and password reset emails are delivered, if anywhere
+    pub fn mailer(&self) -> Option<&Arc<dyn AccountMailer>> {
+        self.mailer.as_ref()
+    }
+
     /// Create with default in-memory stores (for testing)
     pub fn in_memory(config: AuthServiceConfig) -> Result<Self> {
         use crate::audit::TracingAuditLogger;
@@ -380,6 +420,14 @@ impl AuthService {
 
         self.audit_logger.log(event).await;
 
+        // Registration succeeds even if the verification email can't be sent;
+        // the user can ask for another
+        if self.mailer.is_some() && !user.email_verified {
+            if let Err(e) = self.send_account_email(AccountTokenPurpose::VerifyEmail, &user).await {
+                tracing::warn!(user_id = %user.id, error = %e, "Failed to send verification email");
+            }
+        }
+
         Ok(user)
     }
 
@@ -600,6 +648,162 @@ impl AuthService {
         Ok(())
     }
 
+    /// Email `user_id` a link to verify their address
+    pub async fn request_email_verification(&self, user_id: &str) -> Result<()> {
+        let user = self
+            .user_store
+            .find_by_id(user_id)
+            .await?
+            .ok_or(SecurityError::UserNotFound)?;
+        if user.email_verified {
+            return Err(SecurityError::InvalidRequest(
+                "Email is already verified".to_string(),
+            ));
+        }
+
+        self.send_account_email(AccountTokenPurpose::VerifyEmail, &user).await
+    }
+
+    /// Mark the address an email verification token was sent to as verified
+    pub async fn verify_email(&self, token: &str) -> Result<User> {
+        let (claims, user) = self.redeem(token, AccountTokenPurpose::VerifyEmail).await?;
+
+        let mut user = user;
+        user.email_verified = true;
+        user.updated_at = Utc::now();
+        let user = self.user_store.update(user).await?;
+        self.token_blacklist.blacklist(&claims.jti, claims.expires_at()).await?;
+
+        let mut event = AuditEvent::new(AuditEventType::UserUpdated, "auth.verify_email")
+            .with_actor(&user.id, "user")
+            .with_resource("user", &user.id)
+            .with_outcome(AuditOutcome::Success);
+        if let Some(tenant_id) = &user.tenant_id {
+            event = event.with_tenant_id(tenant_id);
+        }
+        self.audit_logger.log(event).await;
+
+        Ok(user)
+    }
+
+    /// Email the account registered to `email` a password reset link
+    ///
+    /// Succeeds whether or not there is such an account, so callers can't
+    /// use it to find out which addresses are registered.
+    pub async fn request_password_reset(&self, email: &str, ip: Option<&str>) -> Result<()> {
+        let user = self
+            .user_store
+            .find_by_email(email.trim())
+            .await?
+            .filter(|user| user.is_active);
+
+        let mut event = AuditEvent::new(AuditEventType::PasswordResetRequested, "auth.request_password_reset");
+        if let Some(ip) = ip {
+            event = event.with_ip_str(ip);
+        }
+        let Some(user) = user else {
+            let event = event
+                .with_outcome(AuditOutcome::Failure)
+                .with_description("No active account for the address");
+            self.audit_logger.log(event).await;
+            return Ok(());
+        };
+
+        let mut event = event
+            .with_actor(&user.id, "user")
+            .with_resource("user", &user.id);
+        if let Some(tenant_id) = &user.tenant_id {
+            event = event.with_tenant_id(tenant_id);
+        }
+        match self.send_account_email(AccountTokenPurpose::ResetPassword, &user).await {
+            Ok(()) => self.audit_logger.log(event.with_outcome(AuditOutcome::Success)).await,
+            Err(e) => {
+                tracing::warn!(user_id = %user.id, error = %e, "Failed to send password reset email");
+                let event = event
+                    .with_outcome(AuditOutcome::Error)
+                    .with_description(&e.to_string());
+                self.audit_logger.log(event).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set a new password with a password reset token
+    ///
+    /// Also lifts any lockout from failed logins.
+    pub async fn reset_password(&self, token: &str, new_password: &str, ip: Option<&str>) -> Result<()> {
+        let (claims, user) = self.redeem(token, AccountTokenPurpose::ResetPassword).await?;
+        let new_hash = self.password_manager.hash_password(new_password)?;
+
+        let mut user = user;
+        user.password_hash = new_hash;
+        user.failed_login_attempts = 0;
+        user.locked_until = None;
+        user.updated_at = Utc::now();
+        let user = self.user_store.update(user).await?;
+        self.token_blacklist.blacklist(&claims.jti, claims.expires_at()).await?;
+
+        let mut event = AuditEvent::new(AuditEventType::PasswordResetCompleted, "auth.reset_password")
+            .with_actor(&user.id, "user")
+            .with_resource("user", &user.id)
+            .with_outcome(AuditOutcome::Success);
+        if let Some(tenant_id) = &user.tenant_id {
+            event = event.with_tenant_id(tenant_id);
+        }
+        if let Some(ip) = ip {
+            event = event.with_ip_str(ip);
+        }
+        self.audit_logger.log(event).await;
+
+        Ok(())
+    }
+
+    /// Check an account token and load its user; a token is void once used
+    /// or once the address or password it is bound to changes
+    async fn redeem(
+        &self,
+        token: &str,
+        purpose: AccountTokenPurpose,
+    ) -> Result<(AccountToken, User)> {
+        let claims = self.account_tokens.verify(token, purpose)?;
+        if self.token_blacklist.is_blacklisted(&claims.jti).await {
+            return Err(SecurityError::TokenRevoked);
+        }
+
+        let user = self
+            .user_store
+            .find_by_id(&claims.sub)
+            .await?
+            .filter(|user| user.is_active)
+            .ok_or_else(|| SecurityError::InvalidToken("Account no longer exists".to_string()))?;
+        if AccountTokens::binding(purpose, &user) != claims.binding {
+            return Err(SecurityError::TokenRevoked);
+        }
+
+        Ok((claims, user))
+    }
+
+    /// Issue a token for `user` and mail it
+    async fn send_account_email(&self, purpose: AccountTokenPurpose, user: &User) -> Result<()> {
+        let mailer = self
+            .mailer
+            .as_ref()
+            .ok_or_else(|| SecurityError::Configuration("No email delivery configured".to_string()))?;
+        let (token, claims) = self.account_tokens.issue(purpose, user)?;
+
+        mailer
+            .send(&AccountEmail {
+                purpose,
+                user_id: user.id.clone(),
+                username: user.username.clone(),
+                to: user.email.clone(),
+                token,
+                expires_at: claims.expires_at(),
+            })
+            .await
+    }
+
     /// Handle failed login attempt
     async fn handle_failed_login(&self, user: &User) -> Result<()> {
         let mut updated_user = user.clone();
@@ -786,4 +990,69 @@ mod tests {
         let result = service.validate_token(&response.tokens.access_token).await;
         assert!(result.is_err());
     }
+
+    #[derive(Default)]
+    struct RecordingMailer {
+        sent: RwLock<Vec<AccountEmail>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AccountMailer for RecordingMailer {
+        async fn send(&self, email: &AccountEmail) -> Result<()> {
+            self.sent.write().await.push(email.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_email_verification_and_password_reset() {
+        let mailer = Arc::new(RecordingMailer::default());
+        let service = AuthService::in_memory(AuthServiceConfig::default())
+            .unwrap()
+            .with_mailer(mailer.clone());
+
+        let register_req = RegisterRequest {
+            username: "testuser".to_string(),
+            email: "test@example.com".to_string(),
+            password: "SecurePass123".to_string(),
+            tenant_id: None,
+        };
+        let user = service.register(register_req, None).await.unwrap();
+
+        // Registering mails a verification link, which works once
+        let verification = mailer.sent.read().await[0].clone();
+        assert_eq!(verification.purpose, AccountTokenPurpose::VerifyEmail);
+        assert_eq!(verification.to, "test@example.com");
+        assert!(service.reset_password(&verification.token, "NewSecurePass456", None).await.is_err());
+        assert!(service.verify_email(&verification.token).await.unwrap().email_verified);
+        assert!(matches!(
+            service.verify_email(&verification.token).await,
+            Err(SecurityError::TokenRevoked)
+        ));
+        assert!(service.request_email_verification(&user.id).await.is_err());
+
+        // Unknown addresses are accepted but mailed nothing
+        service.request_password_reset("nobody@example.com", None).await.unwrap();
+        assert_eq!(mailer.sent.read().await.len(), 1);
+
+        // Any password change voids outstanding reset tokens
+        service.request_password_reset("test@example.com", None).await.unwrap();
+        service.request_password_reset("test@example.com", None).await.unwrap();
+        let (first, second) = {
+            let sent = mailer.sent.read().await;
+            (sent[1].token.clone(), sent[2].token.clone())
+        };
+        assert!(service.reset_password(&first, "short", None).await.is_err());
+        service.reset_password(&first, "NewSecurePass456", None).await.unwrap();
+        assert!(matches!(
+            service.reset_password(&second, "OtherSecurePass789", None).await,
+            Err(SecurityError::TokenRevoked)
+        ));
+
+        let login_req = LoginRequest {
+            username_or_email: "testuser".to_string(),
+            password: "NewSecurePass456".to_string(),
+        };
+        assert!(service.login(login_req, None).await.is_ok());
+    }
 }
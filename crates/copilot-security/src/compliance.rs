@@ -0,0 +1,552 @@
+//! Compliance reports
+//!
+//! A [`ComplianceReport`] collects the evidence an auditor asks for in a
+//! SOC 2 style review of one tenant over a [`ReportPeriod`]: the audit
+//! trail, the roles and permissions in force, how long data is retained,
+//! and the periodic reviews of who has access. Gaps, such as a period
+//! without an access review, are listed as exceptions. Reports serialize to
+//! JSON and render to markdown.
+
+use crate::audit::{AuditEvent, AuditFilter, AuditLogger, AuditOutcome, AuditSeverity};
+use crate::rbac::{RbacManager, Role};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+/// Audit events read into one report; beyond this the report is marked
+/// truncated
+const MAX_AUDIT_EVENTS: usize = 100_000;
+
+/// Notable (high and critical) events listed in full
+const MAX_NOTABLE_EVENTS: usize = 100;
+
+/// The time span a report covers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportPeriod {
+    /// How the period was asked for, e.g. `2026-Q3`
+    pub label: String,
+    pub start: DateTime<Utc>,
+    /// Exclusive
+    pub end: DateTime<Utc>,
+}
+
+impl ReportPeriod {
+    pub fn new(label: impl Into<String>, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self {
+            label: label.into(),
+            start,
+            end,
+        }
+    }
+
+    /// The `days` days up to `now`
+    pub fn trailing_days(days: i64, now: DateTime<Utc>) -> Self {
+        Self::new(format!("last {} days", days), now - Duration::days(days), now)
+    }
+
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.start <= at && at < self.end
+    }
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).expect("midnight exists"))
+}
+
+fn month_start(year: i32, month: u32) -> Option<DateTime<Utc>> {
+    let (year, month) = if month > 12 { (year + 1, month - 12) } else { (year, month) };
+    NaiveDate::from_ymd_opt(year, month, 1).map(midnight)
+}
+
+impl FromStr for ReportPeriod {
+    type Err = String;
+
+    /// A year (`2026`), quarter (`2026-Q3`), month (`2026-09`) or date
+    /// range (`2026-07-01..2026-10-01`, end exclusive).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || format!("invalid period '{}': expected YYYY, YYYY-Qn, YYYY-MM or YYYY-MM-DD..YYYY-MM-DD", s);
+
+        if let Some((start, end)) = s.split_once("..") {
+            let date = |d: &str| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").map_err(|_| invalid());
+            let (start, end) = (midnight(date(start)?), midnight(date(end)?));
+            if end <= start {
+                return Err(format!("invalid period '{}': ends before it starts", s));
+            }
+            return Ok(Self::new(s, start, end));
+        }
+
+        let (year, rest) = s.split_once('-').map_or((s, None), |(year, rest)| (year, Some(rest)));
+        let year: i32 = year.parse().map_err(|_| invalid())?;
+        let (first_month, months) = match rest {
+            None => (1, 12),
+            Some(quarter) if quarter.starts_with(['Q', 'q']) => match quarter[1..].parse::<u32>() {
+                Ok(q @ 1..=4) => (3 * q - 2, 3),
+                _ => return Err(invalid()),
+            },
+            Some(month) => match month.parse::<u32>() {
+                Ok(m @ 1..=12) => (m, 1),
+                _ => return Err(invalid()),
+            },
+        };
+        let start = month_start(year, first_month).ok_or_else(invalid)?;
+        let end = month_start(year, first_month + months).ok_or_else(invalid)?;
+        Ok(Self::new(s, start, end))
+    }
+}
+
+/// Outcome of reviewing a user's access
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewDecision {
+    /// The access is still needed
+    Retain,
+    /// Some roles were removed
+    Reduce,
+    /// All access was removed
+    Revoke,
+}
+
+impl ReviewDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewDecision::Retain => "retain",
+            ReviewDecision::Reduce => "reduce",
+            ReviewDecision::Revoke => "revoke",
+        }
+    }
+}
+
+/// A reviewer's sign-off on one user's access
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessReview {
+    pub id: Uuid,
+    pub tenant_id: String,
+    pub reviewer: String,
+    /// User whose access was reviewed
+    pub subject: String,
+    /// Roles the user held when reviewed
+    pub roles: Vec<String>,
+    pub decision: ReviewDecision,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub reviewed_at: DateTime<Utc>,
+}
+
+impl AccessReview {
+    pub fn new(
+        tenant_id: impl Into<String>,
+        reviewer: impl Into<String>,
+        subject: impl Into<String>,
+        roles: Vec<String>,
+        decision: ReviewDecision,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            tenant_id: tenant_id.into(),
+            reviewer: reviewer.into(),
+            subject: subject.into(),
+            roles,
+            decision,
+            note: None,
+            reviewed_at: Utc::now(),
+        }
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+}
+
+/// Access reviews recorded so far
+#[derive(Debug, Default)]
+pub struct AccessReviewLog {
+    reviews: RwLock<Vec<AccessReview>>,
+}
+
+impl AccessReviewLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, review: AccessReview) {
+        self.reviews.write().expect("access reviews poisoned").push(review);
+    }
+
+    /// `tenant_id`'s reviews in `period`, oldest first
+    pub fn list(&self, tenant_id: &str, period: &ReportPeriod) -> Vec<AccessReview> {
+        self.reviews
+            .read()
+            .expect("access reviews poisoned")
+            .iter()
+            .filter(|review| review.tenant_id == tenant_id && period.contains(review.reviewed_at))
+            .cloned()
+            .collect()
+    }
+}
+
+/// How long one kind of data is kept
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionSetting {
+    /// What is retained, e.g. `exports` or `prompt_logging`
+    pub name: String,
+    /// The setting in force, e.g. `1 day` or `hashed`
+    pub value: String,
+    pub description: String,
+}
+
+impl RetentionSetting {
+    pub fn new(name: impl Into<String>, value: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// A role and the permissions it grants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleEvidence {
+    pub role: String,
+    pub level: u8,
+    pub permissions: Vec<String>,
+}
+
+/// Summary of the audit trail over the period
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditEvidence {
+    pub events: usize,
+    pub by_type: BTreeMap<String, usize>,
+    pub by_severity: BTreeMap<String, usize>,
+    /// Events that failed or errored, e.g. denied access
+    pub failures: usize,
+    /// High and critical events, newest first
+    pub notable: Vec<AuditEvent>,
+    /// Whether more events were logged than the report read
+    pub truncated: bool,
+}
+
+/// Evidence of one tenant's controls over a period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceReport {
+    pub tenant_id: String,
+    pub period: ReportPeriod,
+    pub generated_at: DateTime<Utc>,
+    pub generated_by: String,
+    pub audit: AuditEvidence,
+    pub rbac: Vec<RoleEvidence>,
+    pub retention: Vec<RetentionSetting>,
+    pub access_reviews: Vec<AccessReview>,
+    /// Gaps in the evidence an auditor would ask about
+    pub exceptions: Vec<String>,
+}
+
+/// Builds compliance reports from the audit trail, RBAC configuration and
+/// access reviews
+pub struct ComplianceReporter {
+    audit: Arc<dyn AuditLogger>,
+    rbac: Arc<RbacManager>,
+    reviews: Arc<AccessReviewLog>,
+}
+
+impl ComplianceReporter {
+    pub fn new(audit: Arc<dyn AuditLogger>) -> Self {
+        Self {
+            audit,
+            rbac: Arc::new(RbacManager::new()),
+            reviews: Arc::new(AccessReviewLog::new()),
+        }
+    }
+
+    /// Report the role permissions of `rbac` instead of the defaults
+    pub fn with_rbac(mut self, rbac: Arc<RbacManager>) -> Self {
+        self.rbac = rbac;
+        self
+    }
+
+    pub fn with_access_reviews(mut self, reviews: Arc<AccessReviewLog>) -> Self {
+        self.reviews = reviews;
+        self
+    }
+
+    pub fn access_reviews(&self) -> &Arc<AccessReviewLog> {
+        &self.reviews
+    }
+
+    /// Report on `tenant_id` over `period`, with the retention settings in
+    /// force
+    pub async fn generate(
+        &self,
+        tenant_id: &str,
+        period: ReportPeriod,
+        retention: Vec<RetentionSetting>,
+        generated_by: &str,
+    ) -> ComplianceReport {
+        let audit = self.audit_evidence(tenant_id, &period).await;
+        let access_reviews = self.reviews.list(tenant_id, &period);
+
+        let mut exceptions = Vec::new();
+        if audit.events == 0 {
+            exceptions.push("No audit events were recorded in the period".to_string());
+        }
+        if audit.truncated {
+            exceptions.push(format!("Only the first {} audit events were examined", MAX_AUDIT_EVENTS));
+        }
+        let critical = audit.by_severity.get(AuditSeverity::Critical.as_str()).copied().unwrap_or(0);
+        if critical > 0 {
+            exceptions.push(format!("{} critical security events were recorded", critical));
+        }
+        if access_reviews.is_empty() {
+            exceptions.push("No access reviews were completed in the period".to_string());
+        }
+        if retention.is_empty() {
+            exceptions.push("No retention settings are configured".to_string());
+        }
+
+        ComplianceReport {
+            tenant_id: tenant_id.to_string(),
+            period,
+            generated_at: Utc::now(),
+            generated_by: generated_by.to_string(),
+            audit,
+            rbac: self.rbac_evidence(),
+            retention,
+            access_reviews,
+            exceptions,
+        }
+    }
+
+    async fn audit_evidence(&self, tenant_id: &str, period: &ReportPeriod) -> AuditEvidence {
+        let filter = AuditFilter {
+            tenant_id: Some(tenant_id.to_string()),
+            start_time: Some(period.start),
+            end_time: Some(period.end),
+            ..Default::default()
+        };
+        let mut events = self.audit.query(filter, MAX_AUDIT_EVENTS + 1, 0).await;
+        // The end of a period is exclusive
+        events.retain(|event| period.contains(event.timestamp));
+
+        let mut evidence = AuditEvidence {
+            truncated: events.len() > MAX_AUDIT_EVENTS,
+            ..Default::default()
+        };
+        events.truncate(MAX_AUDIT_EVENTS);
+        evidence.events = events.len();
+        for event in &events {
+            *evidence.by_type.entry(event.event_type.as_str().to_string()).or_default() += 1;
+            *evidence.by_severity.entry(event.severity.as_str().to_string()).or_default() += 1;
+            if matches!(event.outcome, AuditOutcome::Failure | AuditOutcome::Error) {
+                evidence.failures += 1;
+            }
+        }
+        events.sort_by_key(|event| std::cmp::Reverse(event.timestamp));
+        evidence.notable = events
+            .into_iter()
+            .filter(|event| event.severity >= AuditSeverity::High)
+            .take(MAX_NOTABLE_EVENTS)
+            .collect();
+        evidence
+    }
+
+    fn rbac_evidence(&self) -> Vec<RoleEvidence> {
+        Role::ALL
+            .iter()
+            .map(|role| {
+                let mut permissions: Vec<String> =
+                    self.rbac.get_permissions(role).iter().map(|p| p.as_str().to_string()).collect();
+                permissions.sort();
+                RoleEvidence {
+                    role: role.as_str().to_string(),
+                    level: role.level(),
+                    permissions,
+                }
+            })
+            .collect()
+    }
+}
+
+impl ComplianceReport {
+    /// The report as a markdown document
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        let date = |at: DateTime<Utc>| at.format("%Y-%m-%d").to_string();
+        let _ = writeln!(md, "# Compliance report: {}", self.tenant_id);
+        let _ = writeln!(md);
+        let _ = writeln!(
+            md,
+            "Period {} ({} to {}, exclusive). Generated {} by {}.",
+            self.period.label,
+            date(self.period.start),
+            date(self.period.end),
+            self.generated_at.format("%Y-%m-%d %H:%M UTC"),
+            self.generated_by
+        );
+
+        let _ = writeln!(md, "\n## Exceptions\n");
+        if self.exceptions.is_empty() {
+            let _ = writeln!(md, "None.");
+        }
+        for exception in &self.exceptions {
+            let _ = writeln!(md, "- {}", exception);
+        }
+
+        let _ = writeln!(md, "\n## Audit trail\n");
+        let _ = writeln!(
+            md,
+            "{} events, {} failed{}.",
+            self.audit.events,
+            self.audit.failures,
+            if self.audit.truncated { " (truncated)" } else { "" }
+        );
+        if !self.audit.by_type.is_empty() {
+            let _ = writeln!(md, "\n| Event | Count |\n| --- | ---: |");
+            for (event_type, count) in &self.audit.by_type {
+                let _ = writeln!(md, "| {} | {} |", event_type, count);
+            }
+        }
+        if !self.audit.notable.is_empty() {
+            let _ = writeln!(md, "\n### High and critical events\n");
+            let _ = writeln!(md, "| Time | Severity | Event | Actor | Outcome |\n| --- | --- | --- | --- | --- |");
+            for event in &self.audit.notable {
+                let _ = writeln!(
+                    md,
+                    "| {} | {} | {} | {} | {:?} |",
+                    event.timestamp.format("%Y-%m-%d %H:%M"),
+                    event.severity.as_str(),
+                    event.event_type.as_str(),
+                    event.actor_id.as_deref().unwrap_or("-"),
+                    event.outcome
+                );
+            }
+        }
+
+        let _ = writeln!(md, "\n## Roles and permissions\n");
+        let _ = writeln!(md, "| Role | Level | Permissions |\n| --- | ---: | --- |");
+        for role in &self.rbac {
+            let _ = writeln!(md, "| {} | {} | {} |", role.role, role.level, role.permissions.join(", "));
+        }
+
+        let _ = writeln!(md, "\n## Retention\n");
+        let _ = writeln!(md, "| Data | Setting | Notes |\n| --- | --- | --- |");
+        for setting in &self.retention {
+            let _ = writeln!(md, "| {} | {} | {} |", setting.name, setting.value, setting.description);
+        }
+
+        let _ = writeln!(md, "\n## Access reviews\n");
+        if self.access_reviews.is_empty() {
+            let _ = writeln!(md, "None in the period.");
+        } else {
+            let _ = writeln!(md, "| Reviewed | Reviewer | User | Roles | Decision | Note |\n| --- | --- | --- | --- | --- | --- |");
+            for review in &self.access_reviews {
+                let _ = writeln!(
+                    md,
+                    "| {} | {} | {} | {} | {} | {} |",
+                    date(review.reviewed_at),
+                    review.reviewer,
+                    review.subject,
+                    review.roles.join(", "),
+                    review.decision.as_str(),
+                    review.note.as_deref().unwrap_or("")
+                );
+            }
+        }
+        md
+    }
+}
+
+/// Human-readable retention, e.g. `90 days`
+pub fn describe_retention(retention: std::time::Duration) -> String {
+    const DAY: u64 = 24 * 60 * 60;
+    let secs = retention.as_secs();
+    match secs {
+        s if s >= DAY && s % DAY == 0 => match s / DAY {
+            1 => "1 day".to_string(),
+            days => format!("{} days", days),
+        },
+        s if s >= 3600 && s % 3600 == 0 => format!("{} hours", s / 3600),
+        s => format!("{} seconds", s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditEventType, InMemoryAuditLogger};
+
+    #[test]
+    fn test_period_parsing() {
+        let q3: ReportPeriod = "2026-Q3".parse().unwrap();
+        assert_eq!(q3.start, Utc.with_ymd_and_hms(2026, 7, 1, 0, 0, 0).unwrap());
+        assert_eq!(q3.end, Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap());
+
+        let december: ReportPeriod = "2026-12".parse().unwrap();
+        assert_eq!(december.end, Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap());
+
+        let year: ReportPeriod = "2026".parse().unwrap();
+        assert_eq!(year.end, Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap());
+
+        let range: ReportPeriod = "2026-07-15..2026-08-15".parse().unwrap();
+        assert!(range.contains(Utc.with_ymd_and_hms(2026, 8, 14, 23, 0, 0).unwrap()));
+        assert!(!range.contains(range.end));
+
+        for invalid in ["2026-Q5", "2026-13", "last quarter", "2026-08-15..2026-07-15"] {
+            assert!(invalid.parse::<ReportPeriod>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_report_collects_evidence() {
+        let audit = Arc::new(InMemoryAuditLogger::new());
+        for (tenant, event_type, outcome) in [
+            ("acme", AuditEventType::LoginSuccess, AuditOutcome::Success),
+            ("acme", AuditEventType::AccessDenied, AuditOutcome::Failure),
+            ("globex", AuditEventType::DataExported, AuditOutcome::Success),
+        ] {
+            audit
+                .log(
+                    AuditEvent::new(event_type, "test")
+                        .with_tenant_id(tenant)
+                        .with_actor("alice", "user")
+                        .with_outcome(outcome),
+                )
+                .await;
+        }
+        let reporter = ComplianceReporter::new(audit);
+        let period = ReportPeriod::trailing_days(90, Utc::now() + Duration::minutes(1));
+
+        let report = reporter.generate("acme", period.clone(), Vec::new(), "auditor").await;
+        assert_eq!(report.audit.events, 2);
+        assert_eq!(report.audit.failures, 1);
+        assert_eq!(report.audit.notable.len(), 1);
+        assert_eq!(report.rbac[0].role, "super_admin");
+        assert!(report.exceptions.iter().any(|e| e.contains("access reviews")));
+        assert!(report.exceptions.iter().any(|e| e.contains("retention")));
+
+        reporter.access_reviews().record(
+            AccessReview::new("acme", "auditor", "bob", vec!["admin".to_string()], ReviewDecision::Reduce)
+                .with_note("no longer on call"),
+        );
+        let retention = vec![RetentionSetting::new("exports", "1 day", "Export bundles")];
+        let report = reporter.generate("acme", period, retention, "auditor").await;
+        assert!(report.exceptions.is_empty(), "{:?}", report.exceptions);
+
+        let markdown = report.to_markdown();
+        assert!(markdown.starts_with("# Compliance report: acme"));
+        assert!(markdown.contains("| access_denied | 1 |"));
+        assert!(markdown.contains("| bob | admin | reduce | no longer on call |"));
+    }
+
+    #[test]
+    fn test_describe_retention() {
+        assert_eq!(describe_retention(std::time::Duration::from_secs(90 * 86_400)), "90 days");
+        assert_eq!(describe_retention(std::time::Duration::from_secs(86_400)), "1 day");
+        assert_eq!(describe_retention(std::time::Duration::from_secs(7_200)), "2 hours");
+    }
+}
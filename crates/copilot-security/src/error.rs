@@ -64,10 +64,26 @@ pub enum SecurityError {
     #[error("User already exists: {0}")]
     UserAlreadyExists(String),
 
+    /// Service account not found
+    #[error("Service account not found")]
+    ServiceAccountNotFound,
+
+    /// Service account already exists
+    #[error("Service account already exists: {0}")]
+    ServiceAccountAlreadyExists(String),
+
     /// Invalid role
     #[error("Invalid role: {0}")]
     InvalidRole(String),
 
+    /// Provisioning group (role) not found
+    #[error("Group not found: {0}")]
+    GroupNotFound(String),
+
+    /// Malformed provisioning request or import
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
     /// Configuration error
     #[error("Security configuration error: {0}")]
     Configuration(String),
@@ -99,7 +115,11 @@ impl SecurityError {
             SecurityError::RateLimitExceeded { .. } => 429,
             SecurityError::UserNotFound => 404,
             SecurityError::UserAlreadyExists(_) => 409,
+            SecurityError::ServiceAccountNotFound => 404,
+            SecurityError::ServiceAccountAlreadyExists(_) => 409,
             SecurityError::InvalidRole(_) => 400,
+            SecurityError::GroupNotFound(_) => 404,
+            SecurityError::InvalidRequest(_) => 400,
             SecurityError::Configuration(_) => 500,
             SecurityError::Internal(_) => 500,
             SecurityError::Jwt(_) => 401,
@@ -123,7 +143,11 @@ impl SecurityError {
             SecurityError::RateLimitExceeded { .. } => "RATE_LIMIT_EXCEEDED",
             SecurityError::UserNotFound => "USER_NOT_FOUND",
             SecurityError::UserAlreadyExists(_) => "USER_ALREADY_EXISTS",
+            SecurityError::ServiceAccountNotFound => "SERVICE_ACCOUNT_NOT_FOUND",
+            SecurityError::ServiceAccountAlreadyExists(_) => "SERVICE_ACCOUNT_ALREADY_EXISTS",
             SecurityError::InvalidRole(_) => "INVALID_ROLE",
+            SecurityError::GroupNotFound(_) => "GROUP_NOT_FOUND",
+            SecurityError::InvalidRequest(_) => "INVALID_REQUEST",
             SecurityError::Configuration(_) => "CONFIGURATION_ERROR",
             SecurityError::Internal(_) => "INTERNAL_ERROR",
             SecurityError::Jwt(_) => "JWT_ERROR",
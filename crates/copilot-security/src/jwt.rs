@@ -1,8 +1,11 @@
 //! JWT token management
 //!
 //! Provides JWT token generation, validation, and refresh functionality.
+//! Tokens are signed with the shared secret (HS256) unless the manager is
+//! given rotating [`SigningKeys`].
 
 use crate::error::{Result, SecurityError};
+use crate::signing_keys::SigningKeys;
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
 use serde::{Deserialize, Serialize};
@@ -33,6 +36,9 @@ pub struct Claims {
     pub nbf: i64,
     /// Token type (access or refresh)
     pub token_type: TokenType,
+    /// Kind of principal the token was issued to
+    #[serde(default)]
+    pub principal_type: PrincipalType,
 }
 
 /// Token type enumeration
@@ -43,6 +49,17 @@ pub enum TokenType {
     Refresh,
 }
 
+/// Kind of principal a token was issued to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrincipalType {
+    /// A human user
+    #[default]
+    User,
+    /// A service account used by automation
+    Service,
+}
+
 /// Token pair containing access and refresh tokens
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenPair {
@@ -91,6 +108,7 @@ pub struct JwtManager {
     config: JwtConfig,
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
+    signing_keys: Option<SigningKeys>,
 }
 
 impl std::fmt::Debug for JwtManager {
@@ -99,6 +117,7 @@ impl std::fmt::Debug for JwtManager {
             .field("config", &self.config)
             .field("encoding_key", &"[REDACTED]")
             .field("decoding_key", &"[REDACTED]")
+            .field("signing_keys", &self.signing_keys)
             .finish()
     }
 }
@@ -113,9 +132,22 @@ impl JwtManager {
             config,
             encoding_key,
             decoding_key,
+            signing_keys: None,
         }
     }
 
+    /// Sign tokens with the current key of `keys` instead of the secret;
+    /// tokens must then name a key of the ring to validate
+    pub fn with_signing_keys(mut self, keys: SigningKeys) -> Self {
+        self.signing_keys = Some(keys);
+        self
+    }
+
+    /// The rotating signing keys, if tokens are signed with them
+    pub fn signing_keys(&self) -> Option<&SigningKeys> {
+        self.signing_keys.as_ref()
+    }
+
     /// Create a JWT manager from a secret string
     pub fn from_secret(secret: &str) -> Self {
         Self::new(JwtConfig {
@@ -138,6 +170,7 @@ impl JwtManager {
             username,
             roles.clone(),
             TokenType::Access,
+            PrincipalType::User,
             self.config.access_token_expiry_secs,
         )?;
 
@@ -147,6 +180,7 @@ impl JwtManager {
             username,
             roles,
             TokenType::Refresh,
+            PrincipalType::User,
             self.config.refresh_token_expiry_secs,
         )?;
 
@@ -159,7 +193,22 @@ impl JwtManager {
         })
     }
 
+    /// Generate an access token for a service account; service accounts
+    /// authenticate again instead of refreshing
+    pub fn generate_service_token(&self, account_id: &str, name: &str, roles: Vec<String>) -> Result<String> {
+        self.generate_token(
+            account_id,
+            "",
+            name,
+            roles,
+            TokenType::Access,
+            PrincipalType::Service,
+            self.config.access_token_expiry_secs,
+        )
+    }
+
     /// Generate a single token
+    #[allow(clippy::too_many_arguments)]
     fn generate_token(
         &self,
         user_id: &str,
@@ -167,6 +216,7 @@ impl JwtManager {
         username: &str,
         roles: Vec<String>,
         token_type: TokenType,
+        principal_type: PrincipalType,
         expiry_secs: i64,
     ) -> Result<String> {
         let now = Utc::now();
@@ -184,9 +234,13 @@ impl JwtManager {
             iat: now.timestamp(),
             nbf: now.timestamp(),
             token_type,
+            principal_type,
         };
 
-        let token = encode(&Header::default(), &claims, &self.encoding_key)?;
+        let token = match &self.signing_keys {
+            Some(keys) => keys.sign(&claims)?,
+            None => encode(&Header::default(), &claims, &self.encoding_key)?,
+        };
         Ok(token)
     }
 
@@ -196,6 +250,9 @@ impl JwtManager {
         validation.set_issuer(&[&self.config.issuer]);
         validation.set_audience(&[&self.config.audience]);
 
+        if let Some(keys) = &self.signing_keys {
+            return keys.verify(token, &validation);
+        }
         let token_data: TokenData<Claims> = decode(token, &self.decoding_key, &validation)?;
         Ok(token_data.claims)
     }
@@ -389,4 +446,30 @@ mod tests {
         let result = manager2.validate_access_token(&pair.access_token);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_signing_keys_replace_the_secret() {
+        use crate::signing_keys::KeyRotationConfig;
+
+        let keys = SigningKeys::generate(KeyRotationConfig::default()).unwrap();
+        let manager = create_test_manager().with_signing_keys(keys.clone());
+        let pair = manager
+            .generate_token_pair(
+                "user-123",
+                "test@example.com",
+                "testuser",
+                vec!["user".to_string()],
+            )
+            .unwrap();
+        assert!(jsonwebtoken::decode_header(&pair.access_token).unwrap().kid.is_some());
+
+        // Tokens from before a rotation stay valid; secret-signed ones don't
+        keys.rotate().unwrap();
+        assert_eq!(manager.validate_access_token(&pair.access_token).unwrap().sub, "user-123");
+        assert!(manager.refresh_tokens(&pair.refresh_token).is_ok());
+        let legacy = create_test_manager()
+            .generate_token_pair("user-123", "test@example.com", "testuser", vec![])
+            .unwrap();
+        assert!(manager.validate_access_token(&legacy.access_token).is_err());
+    }
 }
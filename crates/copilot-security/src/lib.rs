@@ -4,26 +4,41 @@
 //!
 //! This crate provides:
 //! - JWT-based authentication with access and refresh tokens
+//! - Rotating RS256/EdDSA signing keys published as a JWKS
 //! - Password hashing and verification (Argon2)
 //! - Role-based access control (RBAC)
 //! - API key management with scopes
+//! - Service accounts for automation, with client-secret or key-pair auth
 //! - Rate limiting
 //! - Audit logging
+//! - Compliance reports (SOC 2 style evidence)
+//! - SCIM 2.0 provisioning and bulk CSV import/export of users
+//! - Email verification and password reset with single-use signed tokens
 
 pub mod auth;
 pub mod jwt;
+pub mod signing_keys;
 pub mod password;
 pub mod rbac;
 pub mod api_key;
+pub mod service_account;
 pub mod rate_limit;
 pub mod audit;
+pub mod compliance;
+pub mod provisioning;
+pub mod account;
 pub mod error;
 
 pub use auth::*;
 pub use jwt::*;
+pub use signing_keys::*;
 pub use password::*;
 pub use rbac::*;
 pub use api_key::*;
+pub use service_account::*;
 pub use rate_limit::*;
 pub use audit::*;
+pub use compliance::*;
+pub use provisioning::*;
+pub use account::*;
 pub use error::{SecurityError, Result};
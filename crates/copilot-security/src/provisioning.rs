@@ -0,0 +1,1157 @@
+//! User provisioning
+//!
+//! [`UserProvisioning`] lets an enterprise IdP manage a tenant's users. SCIM
+//! 2.0 (RFC 7643/7644) Users map onto the [`UserStore`] and Groups onto the
+//! RBAC roles: a user's groups are its roles, so adding a member to the
+//! `admin` group makes them an admin. The same users can be imported and
+//! exported in bulk as CSV.
+//!
+//! Every operation is confined to one tenant. Provisioned users sign in
+//! through the IdP and have no password. `super_admin` is not exposed as a
+//! group, so it can never be granted by provisioning.
+
+use crate::audit::{AuditEvent, AuditEventType, AuditLogger, AuditOutcome};
+use crate::auth::{User, UserStore};
+use crate::error::{Result, SecurityError};
+use crate::rbac::Role;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use tracing::info;
+
+pub const SCIM_USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+pub const SCIM_GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+pub const SCIM_LIST_RESPONSE_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+pub const SCIM_PATCH_OP_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:PatchOp";
+pub const SCIM_ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+
+/// Page size of list requests that do not ask for one
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Largest page a list request can ask for
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// Key of a user's SCIM attributes in its metadata
+const SCIM_METADATA_KEY: &str = "scim";
+
+/// Columns of user CSV files
+pub const USER_CSV_COLUMNS: [&str; 6] = ["username", "email", "roles", "active", "external_id", "display_name"];
+
+fn user_schemas() -> Vec<String> {
+    vec![SCIM_USER_SCHEMA.to_string()]
+}
+
+fn group_schemas() -> Vec<String> {
+    vec![SCIM_GROUP_SCHEMA.to_string()]
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Components of a user's name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimName {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formatted: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub given_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family_name: Option<String>,
+}
+
+/// An email address of a user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScimEmail {
+    pub value: String,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+/// A reference to a user (a group member) or a group (a user's group)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScimMember {
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+}
+
+/// Resource metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimMeta {
+    pub resource_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// A SCIM User
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimUser {
+    #[serde(default = "user_schemas")]
+    pub schemas: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    pub user_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<ScimName>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emails: Vec<ScimEmail>,
+    #[serde(default = "default_true")]
+    pub active: bool,
+    /// The user's roles; read-only, changed through group membership
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<ScimMember>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ScimMeta>,
+}
+
+impl ScimUser {
+    pub fn new(user_name: impl Into<String>, email: impl Into<String>) -> Self {
+        Self {
+            schemas: user_schemas(),
+            id: None,
+            external_id: None,
+            user_name: user_name.into(),
+            name: None,
+            display_name: None,
+            emails: vec![ScimEmail {
+                value: email.into(),
+                kind: Some("work".to_string()),
+                primary: true,
+            }],
+            active: true,
+            groups: Vec::new(),
+            meta: None,
+        }
+    }
+
+    /// The primary email, or the first one
+    pub fn primary_email(&self) -> Option<&str> {
+        self.emails
+            .iter()
+            .find(|e| e.primary)
+            .or_else(|| self.emails.first())
+            .map(|e| e.value.as_str())
+    }
+}
+
+/// A SCIM Group: one of the RBAC roles and the tenant's users holding it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimGroup {
+    #[serde(default = "group_schemas")]
+    pub schemas: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub display_name: String,
+    #[serde(default)]
+    pub members: Vec<ScimMember>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ScimMeta>,
+}
+
+/// A page of resources
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListResponse<T> {
+    pub schemas: Vec<String>,
+    pub total_results: usize,
+    pub start_index: usize,
+    pub items_per_page: usize,
+    #[serde(rename = "Resources")]
+    pub resources: Vec<T>,
+}
+
+/// Query parameters of list requests
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListQuery {
+    /// `<attribute> eq "<value>"`, the filter IdPs use to look users and
+    /// groups up
+    #[serde(default)]
+    pub filter: Option<String>,
+    /// 1-based index of the first result
+    #[serde(default)]
+    pub start_index: Option<usize>,
+    #[serde(default)]
+    pub count: Option<usize>,
+}
+
+/// One operation of a PATCH request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScimPatchOperation {
+    /// `add`, `replace` or `remove`, in any case
+    pub op: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+}
+
+/// A PATCH request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScimPatchRequest {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(rename = "Operations")]
+    pub operations: Vec<ScimPatchOperation>,
+}
+
+/// A SCIM error response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimError {
+    pub schemas: Vec<String>,
+    /// HTTP status code, as a string
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scim_type: Option<String>,
+    pub detail: String,
+}
+
+impl From<&SecurityError> for ScimError {
+    fn from(err: &SecurityError) -> Self {
+        let scim_type = match err {
+            SecurityError::UserAlreadyExists(_) => Some("uniqueness"),
+            SecurityError::InvalidRequest(_) => Some("invalidValue"),
+            SecurityError::InvalidRole(_) => Some("invalidValue"),
+            _ => None,
+        };
+        Self {
+            schemas: vec![SCIM_ERROR_SCHEMA.to_string()],
+            status: err.status_code().to_string(),
+            scim_type: scim_type.map(str::to_string),
+            detail: err.to_string(),
+        }
+    }
+}
+
+/// What the SCIM endpoints support, for `/ServiceProviderConfig`
+pub fn service_provider_config() -> Value {
+    json!({
+        "schemas": ["urn:ietf:params:scim:schemas:core:2.0:ServiceProviderConfig"],
+        "patch": { "supported": true },
+        "bulk": { "supported": false, "maxOperations": 0, "maxPayloadSize": 0 },
+        "filter": { "supported": true, "maxResults": MAX_PAGE_SIZE },
+        "changePassword": { "supported": false },
+        "sort": { "supported": false },
+        "etag": { "supported": false },
+        "authenticationSchemes": [{
+            "type": "oauthbearertoken",
+            "name": "OAuth Bearer Token",
+            "description": "A bearer token with the admin scope",
+        }],
+    })
+}
+
+/// Outcome of a CSV import
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UserImportReport {
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub failures: Vec<ImportFailure>,
+}
+
+/// A CSV row that could not be imported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportFailure {
+    /// Line the row starts on
+    pub line: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    pub error: String,
+}
+
+/// SCIM attributes kept in a user's metadata
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScimProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    external_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<ScimName>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    display_name: Option<String>,
+}
+
+impl ScimProfile {
+    fn of(user: &User) -> Self {
+        user.metadata
+            .get(SCIM_METADATA_KEY)
+            .and_then(|profile| serde_json::from_value(profile.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    fn store(&self, user: &mut User) {
+        let profile = serde_json::to_value(self).unwrap_or_default();
+        match user.metadata.as_object_mut() {
+            Some(metadata) => {
+                metadata.insert(SCIM_METADATA_KEY.to_string(), profile);
+            }
+            None => user.metadata = json!({ SCIM_METADATA_KEY: profile }),
+        }
+    }
+}
+
+/// Roles exposed as groups
+fn group_roles() -> impl Iterator<Item = Role> {
+    Role::ALL.into_iter().filter(|role| *role != Role::SuperAdmin)
+}
+
+/// The role a group id or name stands for
+fn group_role(group: &str) -> Result<Role> {
+    Role::from_str(group)
+        .ok()
+        .filter(|role| *role != Role::SuperAdmin)
+        .ok_or_else(|| SecurityError::GroupNotFound(group.to_string()))
+}
+
+/// A role granted by provisioning
+fn grantable_role(role: &str) -> Result<Role> {
+    match Role::from_str(role.trim())? {
+        Role::SuperAdmin => Err(SecurityError::InvalidRole(format!(
+            "{} cannot be granted by provisioning",
+            role.trim()
+        ))),
+        role => Ok(role),
+    }
+}
+
+fn invalid(message: impl Into<String>) -> SecurityError {
+    SecurityError::InvalidRequest(message.into())
+}
+
+/// A parsed `<attribute> eq "<value>"` filter
+struct EqFilter {
+    attribute: String,
+    value: String,
+}
+
+impl EqFilter {
+    fn parse(filter: &str) -> Result<Self> {
+        let unsupported = || invalid(format!("Unsupported filter '{}': expected <attribute> eq \"<value>\"", filter));
+        let mut parts = filter.trim().splitn(3, char::is_whitespace);
+        let (Some(attribute), Some(op), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(unsupported());
+        };
+        if !op.eq_ignore_ascii_case("eq") {
+            return Err(unsupported());
+        }
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value)
+            .replace("\\\"", "\"");
+        Ok(Self {
+            attribute: attribute.to_lowercase(),
+            value,
+        })
+    }
+}
+
+/// The requested page of `items`
+fn page<T>(items: Vec<T>, query: &ScimListQuery) -> ScimListResponse<T> {
+    let total_results = items.len();
+    let start_index = query.start_index.unwrap_or(1).max(1);
+    let count = query.count.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let resources: Vec<T> = items.into_iter().skip(start_index - 1).take(count).collect();
+    ScimListResponse {
+        schemas: vec![SCIM_LIST_RESPONSE_SCHEMA.to_string()],
+        total_results,
+        start_index,
+        items_per_page: resources.len(),
+        resources,
+    }
+}
+
+/// Canonical name of a user attribute a PATCH may set
+fn user_attribute(name: &str) -> Option<&'static str> {
+    ["externalId", "userName", "name", "displayName", "emails", "active"]
+        .into_iter()
+        .find(|attribute| attribute.eq_ignore_ascii_case(name))
+}
+
+/// Set `path` of a user document to `value`; attributes the platform does
+/// not keep (e.g. enterprise extensions) are ignored
+fn set_user_attribute(doc: &mut Map<String, Value>, path: &str, value: Value) -> Result<()> {
+    // `emails[type eq "work"].value` and the like set the primary email
+    if let Some((attribute, _)) = path.split_once('[') {
+        if !attribute.eq_ignore_ascii_case("emails") {
+            return Ok(());
+        }
+        let Some(email) = value.as_str() else {
+            return Err(invalid(format!("{} must be a string", path)));
+        };
+        doc.insert("emails".to_string(), json!([{ "value": email, "type": "work", "primary": true }]));
+        return Ok(());
+    }
+
+    let (attribute, sub_attribute) = match path.split_once('.') {
+        Some((attribute, sub)) => (attribute, Some(sub)),
+        None => (path, None),
+    };
+    let Some(attribute) = user_attribute(attribute) else {
+        return Ok(());
+    };
+    // Some IdPs send booleans as strings
+    let value = match (attribute, value) {
+        ("active", Value::String(s)) => Value::Bool(s.eq_ignore_ascii_case("true")),
+        (_, value) => value,
+    };
+    match sub_attribute {
+        None => {
+            doc.insert(attribute.to_string(), value);
+        }
+        Some(sub) => {
+            let Some(sub) = ["formatted", "givenName", "familyName"]
+                .into_iter()
+                .find(|s| s.eq_ignore_ascii_case(sub))
+                .filter(|_| attribute == "name")
+            else {
+                return Ok(());
+            };
+            let name = doc.entry(attribute.to_string()).or_insert_with(|| json!({}));
+            if !name.is_object() {
+                *name = json!({});
+            }
+            if let Some(name) = name.as_object_mut() {
+                name.insert(sub.to_string(), value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Remove `path` from a user document
+fn remove_user_attribute(doc: &mut Map<String, Value>, path: &str) -> Result<()> {
+    match user_attribute(path) {
+        Some("userName" | "emails" | "active") => Err(invalid(format!("{} cannot be removed", path))),
+        Some(attribute) => {
+            doc.remove(attribute);
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Member ids in a PATCH value: `[{"value": "<id>"}, ...]`
+fn member_ids(value: Option<&Value>) -> Result<Vec<String>> {
+    let members: Vec<ScimMember> = match value {
+        Some(value) => serde_json::from_value(value.clone()).map_err(|e| invalid(format!("Invalid members: {}", e)))?,
+        None => Vec::new(),
+    };
+    Ok(members.into_iter().map(|m| m.value).collect())
+}
+
+/// Split CSV text into records with the line each starts on; quoted fields
+/// may hold commas, doubled quotes and line breaks
+fn csv_records(text: &str) -> Result<Vec<(usize, Vec<String>)>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    let mut start = 1;
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|f| !f.is_empty()) {
+                    records.push((start, std::mem::take(&mut record)));
+                }
+                line += 1;
+                start = line;
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(invalid(format!("Unterminated quoted field starting on line {}", start)));
+    }
+    record.push(field);
+    if record.iter().any(|f| !f.is_empty()) {
+        records.push((start, record));
+    }
+    Ok(records)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "" | "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        other => Err(invalid(format!("Invalid active value '{}'", other))),
+    }
+}
+
+/// A user row of a CSV import
+struct CsvUser {
+    username: String,
+    email: String,
+    roles: Option<Vec<String>>,
+    active: bool,
+    external_id: Option<String>,
+    display_name: Option<String>,
+}
+
+/// Maps SCIM and CSV provisioning onto a user store and RBAC roles
+pub struct UserProvisioning {
+    store: Arc<dyn UserStore>,
+    audit: Arc<dyn AuditLogger>,
+}
+
+impl UserProvisioning {
+    pub fn new(store: Arc<dyn UserStore>, audit: Arc<dyn AuditLogger>) -> Self {
+        Self { store, audit }
+    }
+
+    pub fn store(&self) -> &Arc<dyn UserStore> {
+        &self.store
+    }
+
+    /// The SCIM representation of a user
+    pub fn to_scim_user(user: &User) -> ScimUser {
+        let profile = ScimProfile::of(user);
+        let mut scim = ScimUser::new(&user.username, &user.email);
+        scim.id = Some(user.id.clone());
+        scim.external_id = profile.external_id;
+        scim.name = profile.name;
+        scim.display_name = profile.display_name;
+        scim.active = user.is_active;
+        scim.groups = user
+            .get_roles()
+            .into_iter()
+            .filter(|role| *role != Role::SuperAdmin)
+            .map(|role| ScimMember {
+                value: role.as_str().to_string(),
+                display: Some(role.as_str().to_string()),
+            })
+            .collect();
+        scim.meta = Some(ScimMeta {
+            resource_type: "User".to_string(),
+            created: Some(user.created_at),
+            last_modified: Some(user.updated_at),
+        });
+        scim
+    }
+
+    // -- Users
+
+    pub async fn list_users(&self, tenant_id: &str, query: &ScimListQuery) -> Result<ScimListResponse<ScimUser>> {
+        let filter = query.filter.as_deref().map(EqFilter::parse).transpose()?;
+        if let Some(filter) = &filter {
+            if !["id", "username", "externalid", "emails", "emails.value"].contains(&filter.attribute.as_str()) {
+                return Err(invalid(format!("Users cannot be filtered by {}", filter.attribute)));
+            }
+        }
+        let mut users = self.store.list(Some(tenant_id)).await?;
+        users.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        let users = users
+            .iter()
+            .map(Self::to_scim_user)
+            .filter(|user| match &filter {
+                None => true,
+                Some(filter) => match filter.attribute.as_str() {
+                    "id" => user.id.as_deref() == Some(filter.value.as_str()),
+                    "username" => user.user_name.eq_ignore_ascii_case(&filter.value),
+                    "externalid" => user.external_id.as_deref() == Some(filter.value.as_str()),
+                    "emails" | "emails.value" => user.emails.iter().any(|e| e.value.eq_ignore_ascii_case(&filter.value)),
+                    _ => false,
+                },
+            })
+            .collect();
+        Ok(page(users, query))
+    }
+
+    pub async fn get_user(&self, tenant_id: &str, id: &str) -> Result<ScimUser> {
+        Ok(Self::to_scim_user(&self.find(tenant_id, id).await?))
+    }
+
+    /// Provision a user with the `user` role
+    pub async fn create_user(&self, tenant_id: &str, scim: ScimUser, actor: &str) -> Result<ScimUser> {
+        let mut user = User::new("", "", "");
+        user.tenant_id = Some(tenant_id.to_string());
+        Self::apply(&mut user, &scim)?;
+        let user = self.store.create(user).await?;
+        self.log(tenant_id, actor, AuditEventType::UserCreated, "provision_user", &user.id).await;
+        info!("{} provisioned user {} in {}", actor, user.username, tenant_id);
+        Ok(Self::to_scim_user(&user))
+    }
+
+    /// Replace a user's attributes; its roles are kept
+    pub async fn replace_user(&self, tenant_id: &str, id: &str, scim: ScimUser, actor: &str) -> Result<ScimUser> {
+        let mut user = self.find(tenant_id, id).await?;
+        Self::apply(&mut user, &scim)?;
+        Ok(Self::to_scim_user(&self.save(tenant_id, user, actor).await?))
+    }
+
+    pub async fn patch_user(&self, tenant_id: &str, id: &str, patch: ScimPatchRequest, actor: &str) -> Result<ScimUser> {
+        let user = self.find(tenant_id, id).await?;
+        let Value::Object(mut doc) = serde_json::to_value(Self::to_scim_user(&user))
+            .map_err(|e| SecurityError::Internal(e.to_string()))?
+        else {
+            return Err(SecurityError::Internal("User is not an object".to_string()));
+        };
+
+        for operation in &patch.operations {
+            match (operation.op.to_lowercase().as_str(), operation.path.as_deref()) {
+                ("add" | "replace", Some(path)) => {
+                    let value = operation.value.clone().ok_or_else(|| invalid(format!("{} needs a value", path)))?;
+                    set_user_attribute(&mut doc, path, value)?;
+                }
+                ("add" | "replace", None) => {
+                    let Some(Value::Object(attributes)) = &operation.value else {
+                        return Err(invalid("An operation without a path needs an object value"));
+                    };
+                    for (path, value) in attributes {
+                        set_user_attribute(&mut doc, path, value.clone())?;
+                    }
+                }
+                ("remove", Some(path)) => remove_user_attribute(&mut doc, path)?,
+                ("remove", None) => return Err(invalid("remove needs a path")),
+                (op, _) => return Err(invalid(format!("Unknown operation '{}'", op))),
+            }
+        }
+
+        let scim: ScimUser = serde_json::from_value(Value::Object(doc)).map_err(|e| invalid(e.to_string()))?;
+        let mut user = user;
+        Self::apply(&mut user, &scim)?;
+        Ok(Self::to_scim_user(&self.save(tenant_id, user, actor).await?))
+    }
+
+    /// Deprovision a user
+    pub async fn delete_user(&self, tenant_id: &str, id: &str, actor: &str) -> Result<()> {
+        let user = self.find(tenant_id, id).await?;
+        self.store.delete(&user.id).await?;
+        self.log(tenant_id, actor, AuditEventType::UserDeleted, "deprovision_user", &user.id).await;
+        info!("{} deprovisioned user {} in {}", actor, user.username, tenant_id);
+        Ok(())
+    }
+
+    // -- Groups
+
+    pub async fn list_groups(&self, tenant_id: &str, query: &ScimListQuery) -> Result<ScimListResponse<ScimGroup>> {
+        let filter = query.filter.as_deref().map(EqFilter::parse).transpose()?;
+        if let Some(filter) = &filter {
+            if !["id", "displayname"].contains(&filter.attribute.as_str()) {
+                return Err(invalid(format!("Groups cannot be filtered by {}", filter.attribute)));
+            }
+        }
+        let users = self.store.list(Some(tenant_id)).await?;
+        let groups = group_roles()
+            .filter(|role| filter.as_ref().map_or(true, |f| role.as_str().eq_ignore_ascii_case(&f.value)))
+            .map(|role| Self::group(role, &users))
+            .collect();
+        Ok(page(groups, query))
+    }
+
+    pub async fn get_group(&self, tenant_id: &str, id: &str) -> Result<ScimGroup> {
+        let role = group_role(id)?;
+        Ok(Self::group(role, &self.store.list(Some(tenant_id)).await?))
+    }
+
+    /// Groups are the fixed roles: "creating" one named after a role sets
+    /// its members
+    pub async fn create_group(&self, tenant_id: &str, group: ScimGroup, actor: &str) -> Result<ScimGroup> {
+        let role = group_role(&group.display_name)
+            .map_err(|_| SecurityError::InvalidRole(format!("Groups must be named after a role, not {}", group.display_name)))?;
+        self.replace_group(tenant_id, role.as_str(), group, actor).await
+    }
+
+    /// Make exactly the listed users members of a group
+    pub async fn replace_group(&self, tenant_id: &str, id: &str, group: ScimGroup, actor: &str) -> Result<ScimGroup> {
+        let role = group_role(id)?;
+        let members: Vec<String> = group.members.into_iter().map(|m| m.value).collect();
+        self.set_members(tenant_id, role, &members, actor).await?;
+        self.get_group(tenant_id, id).await
+    }
+
+    pub async fn patch_group(&self, tenant_id: &str, id: &str, patch: ScimPatchRequest, actor: &str) -> Result<ScimGroup> {
+        let role = group_role(id)?;
+        for operation in &patch.operations {
+            let path = operation.path.as_deref().unwrap_or("members");
+            // `members[value eq "<id>"]` names the member to remove
+            let (attribute, member) = match path.split_once('[') {
+                Some((attribute, filter)) => {
+                    let filter = EqFilter::parse(filter.trim_end_matches(']'))?;
+                    (attribute, Some(filter.value))
+                }
+                None => (path, None),
+            };
+            if attribute.eq_ignore_ascii_case("displayName") {
+                continue;
+            }
+            if !attribute.eq_ignore_ascii_case("members") {
+                return Err(invalid(format!("Groups have no attribute {}", attribute)));
+            }
+
+            match operation.op.to_lowercase().as_str() {
+                "add" => {
+                    for member in member_ids(operation.value.as_ref())? {
+                        self.set_role(tenant_id, &member, role, true, actor).await?;
+                    }
+                }
+                "remove" => {
+                    let members = match member {
+                        Some(member) => vec![member],
+                        None => member_ids(operation.value.as_ref())?,
+                    };
+                    for member in members {
+                        self.set_role(tenant_id, &member, role, false, actor).await?;
+                    }
+                }
+                "replace" => {
+                    let members = member_ids(operation.value.as_ref())?;
+                    self.set_members(tenant_id, role, &members, actor).await?;
+                }
+                op => return Err(invalid(format!("Unknown operation '{}'", op))),
+            }
+        }
+        self.get_group(tenant_id, id).await
+    }
+
+    // -- CSV
+
+    /// The tenant's users as CSV with [`USER_CSV_COLUMNS`]
+    pub async fn export_csv(&self, tenant_id: &str) -> Result<String> {
+        let mut users = self.store.list(Some(tenant_id)).await?;
+        users.sort_by_key(|user| user.username.to_lowercase());
+
+        let mut csv = USER_CSV_COLUMNS.join(",");
+        csv.push('\n');
+        for user in &users {
+            let profile = ScimProfile::of(user);
+            let row = [
+                user.username.clone(),
+                user.email.clone(),
+                user.roles.join(";"),
+                user.is_active.to_string(),
+                profile.external_id.unwrap_or_default(),
+                profile.display_name.unwrap_or_default(),
+            ];
+            csv.push_str(&row.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+            csv.push('\n');
+        }
+        Ok(csv)
+    }
+
+    /// Create or update the users of a CSV file, matched by username.
+    /// `username` and `email` columns are required; `roles` are separated by
+    /// `;` and, when empty, default to `user` for new users and are kept for
+    /// existing ones. Rows that fail are reported and the rest imported.
+    pub async fn import_csv(&self, tenant_id: &str, csv: &str, actor: &str) -> Result<UserImportReport> {
+        let mut records = csv_records(csv.trim_start_matches('\u{feff}'))?.into_iter();
+        let Some((_, header)) = records.next() else {
+            return Err(invalid("The CSV file is empty"));
+        };
+        let header: Vec<String> = header.iter().map(|h| h.trim().to_lowercase()).collect();
+        if let Some(unknown) = header.iter().find(|h| !USER_CSV_COLUMNS.contains(&h.as_str())) {
+            return Err(invalid(format!("Unknown column '{}': expected {}", unknown, USER_CSV_COLUMNS.join(", "))));
+        }
+        for required in ["username", "email"] {
+            if !header.iter().any(|h| h == required) {
+                return Err(invalid(format!("The {} column is required", required)));
+            }
+        }
+
+        let mut report = UserImportReport::default();
+        for (line, record) in records {
+            let field = |column: &str| {
+                header
+                    .iter()
+                    .position(|h| h == column)
+                    .and_then(|i| record.get(i))
+                    .map(|f| f.trim().to_string())
+            };
+            let username = field("username").filter(|u| !u.is_empty());
+            let row = Self::csv_user(&field);
+            match row {
+                Ok(row) => match self.import_user(tenant_id, row, actor).await {
+                    Ok(Some(true)) => report.created += 1,
+                    Ok(Some(false)) => report.updated += 1,
+                    Ok(None) => report.unchanged += 1,
+                    Err(e) => report.failures.push(ImportFailure { line, username, error: e.to_string() }),
+                },
+                Err(e) => report.failures.push(ImportFailure { line, username, error: e.to_string() }),
+            }
+        }
+
+        self.audit
+            .log(
+                AuditEvent::new(AuditEventType::DataImported, "import_users")
+                    .with_actor(actor, "user")
+                    .with_tenant_id(tenant_id)
+                    .with_resource("users", tenant_id)
+                    .with_metadata("created", report.created)
+                    .with_metadata("updated", report.updated)
+                    .with_metadata("failed", report.failures.len())
+                    .with_outcome(AuditOutcome::Success),
+            )
+            .await;
+        info!(
+            "{} imported users into {}: {} created, {} updated, {} failed",
+            actor,
+            tenant_id,
+            report.created,
+            report.updated,
+            report.failures.len()
+        );
+        Ok(report)
+    }
+
+    fn csv_user(field: &dyn Fn(&str) -> Option<String>) -> Result<CsvUser> {
+        let username = field("username").filter(|u| !u.is_empty()).ok_or_else(|| invalid("username is empty"))?;
+        let email = field("email").filter(|e| !e.is_empty()).ok_or_else(|| invalid("email is empty"))?;
+        let roles = match field("roles").filter(|r| !r.is_empty()) {
+            Some(roles) => Some(
+                roles
+                    .split(';')
+                    .filter(|r| !r.trim().is_empty())
+                    .map(|r| grantable_role(r).map(|role| role.as_str().to_string()))
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            None => None,
+        };
+        Ok(CsvUser {
+            username,
+            email,
+            roles,
+            active: parse_bool(&field("active").unwrap_or_default())?,
+            external_id: field("external_id").filter(|v| !v.is_empty()),
+            display_name: field("display_name").filter(|v| !v.is_empty()),
+        })
+    }
+
+    /// Create (`Some(true)`), update (`Some(false)`) or leave (`None`) a
+    /// user from a CSV row
+    async fn import_user(&self, tenant_id: &str, row: CsvUser, actor: &str) -> Result<Option<bool>> {
+        let existing = self
+            .store
+            .find_by_username(&row.username)
+            .await?
+            .filter(|user| user.tenant_id.as_deref() == Some(tenant_id));
+        let created = existing.is_none();
+        let mut user = existing.unwrap_or_else(|| {
+            let mut user = User::new(&row.username, &row.email, "");
+            user.tenant_id = Some(tenant_id.to_string());
+            user
+        });
+        let before = (user.username.clone(), user.email.clone(), user.roles.clone(), user.is_active, ScimProfile::of(&user));
+
+        user.username = row.username;
+        user.email = row.email;
+        user.is_active = row.active;
+        if let Some(roles) = row.roles {
+            // Roles provisioning cannot grant are kept
+            let mut kept: Vec<String> = user
+                .get_roles()
+                .into_iter()
+                .filter(|r| *r == Role::SuperAdmin)
+                .map(|r| r.as_str().to_string())
+                .collect();
+            for role in roles {
+                if !kept.contains(&role) {
+                    kept.push(role);
+                }
+            }
+            user.roles = kept;
+        }
+        let mut profile = ScimProfile::of(&user);
+        profile.external_id = row.external_id.or(profile.external_id);
+        profile.display_name = row.display_name.or(profile.display_name);
+        profile.store(&mut user);
+
+        if created {
+            let user = self.store.create(user).await?;
+            self.log(tenant_id, actor, AuditEventType::UserCreated, "import_user", &user.id).await;
+            return Ok(Some(true));
+        }
+        if before == (user.username.clone(), user.email.clone(), user.roles.clone(), user.is_active, ScimProfile::of(&user)) {
+            return Ok(None);
+        }
+        let previous_roles = before.2;
+        let user = self.save(tenant_id, user, actor).await?;
+        for role in user.roles.iter().filter(|r| !previous_roles.contains(r)) {
+            self.log(tenant_id, actor, AuditEventType::RoleAssigned, role, &user.id).await;
+        }
+        for role in previous_roles.iter().filter(|r| !user.roles.contains(r)) {
+            self.log(tenant_id, actor, AuditEventType::RoleRevoked, role, &user.id).await;
+        }
+        Ok(Some(false))
+    }
+
+    // -- Helpers
+
+    /// A user of the tenant
+    async fn find(&self, tenant_id: &str, id: &str) -> Result<User> {
+        self.store
+            .find_by_id(id)
+            .await?
+            .filter(|user| user.tenant_id.as_deref() == Some(tenant_id))
+            .ok_or(SecurityError::UserNotFound)
+    }
+
+    /// Copy SCIM attributes onto a user
+    fn apply(user: &mut User, scim: &ScimUser) -> Result<()> {
+        let user_name = scim.user_name.trim();
+        if user_name.is_empty() {
+            return Err(invalid("userName must not be empty"));
+        }
+        let email = scim
+            .primary_email()
+            .or_else(|| user_name.contains('@').then_some(user_name))
+            .ok_or_else(|| invalid("A user needs an email address"))?;
+        user.username = user_name.to_string();
+        user.email = email.trim().to_string();
+        user.is_active = scim.active;
+        ScimProfile {
+            external_id: scim.external_id.clone(),
+            name: scim.name.clone(),
+            display_name: scim.display_name.clone(),
+        }
+        .store(user);
+        Ok(())
+    }
+
+    /// Store changes to an existing user, keeping usernames and emails
+    /// unique
+    async fn save(&self, tenant_id: &str, mut user: User, actor: &str) -> Result<User> {
+        let previous = self.store.find_by_id(&user.id).await?.ok_or(SecurityError::UserNotFound)?;
+        if let Some(other) = self.store.find_by_username(&user.username).await? {
+            if other.id != user.id {
+                return Err(SecurityError::UserAlreadyExists(user.username));
+            }
+        }
+        if let Some(other) = self.store.find_by_email(&user.email).await? {
+            if other.id != user.id {
+                return Err(SecurityError::UserAlreadyExists(user.email));
+            }
+        }
+        user.updated_at = Utc::now();
+        let user = self.store.update(user).await?;
+
+        let event_type = match (previous.is_active, user.is_active) {
+            (true, false) => AuditEventType::UserDisabled,
+            (false, true) => AuditEventType::UserEnabled,
+            _ => AuditEventType::UserUpdated,
+        };
+        self.log(tenant_id, actor, event_type, "update_user", &user.id).await;
+        Ok(user)
+    }
+
+    /// Grant or revoke a role of one of the tenant's users
+    async fn set_role(&self, tenant_id: &str, id: &str, role: Role, member: bool, actor: &str) -> Result<()> {
+        let mut user = self.find(tenant_id, id).await?;
+        let has_role = user.get_roles().contains(&role);
+        if has_role == member {
+            return Ok(());
+        }
+        if member {
+            user.roles.push(role.as_str().to_string());
+        } else {
+            user.roles.retain(|r| Role::from_str(r).ok() != Some(role));
+        }
+        user.updated_at = Utc::now();
+        let user = self.store.update(user).await?;
+        let event_type = if member { AuditEventType::RoleAssigned } else { AuditEventType::RoleRevoked };
+        self.log(tenant_id, actor, event_type, role.as_str(), &user.id).await;
+        Ok(())
+    }
+
+    /// Make exactly `members` hold `role` among the tenant's users
+    async fn set_members(&self, tenant_id: &str, role: Role, members: &[String], actor: &str) -> Result<()> {
+        for member in members {
+            self.find(tenant_id, member).await?;
+        }
+        for user in self.store.list(Some(tenant_id)).await? {
+            let member = members.contains(&user.id);
+            self.set_role(tenant_id, &user.id, role, member, actor).await?;
+        }
+        Ok(())
+    }
+
+    fn group(role: Role, users: &[User]) -> ScimGroup {
+        ScimGroup {
+            schemas: group_schemas(),
+            id: Some(role.as_str().to_string()),
+            display_name: role.as_str().to_string(),
+            members: users
+                .iter()
+                .filter(|user| user.get_roles().contains(&role))
+                .map(|user| ScimMember {
+                    value: user.id.clone(),
+                    display: Some(user.username.clone()),
+                })
+                .collect(),
+            meta: Some(ScimMeta {
+                resource_type: "Group".to_string(),
+                created: None,
+                last_modified: None,
+            }),
+        }
+    }
+
+    async fn log(&self, tenant_id: &str, actor: &str, event_type: AuditEventType, action: &str, user_id: &str) {
+        self.audit
+            .log(
+                AuditEvent::new(event_type, action)
+                    .with_actor(actor, "user")
+                    .with_tenant_id(tenant_id)
+                    .with_resource("user", user_id)
+                    .with_outcome(AuditOutcome::Success),
+            )
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::InMemoryAuditLogger;
+    use crate::auth::InMemoryUserStore;
+
+    fn provisioning() -> (UserProvisioning, Arc<InMemoryAuditLogger>) {
+        let audit = Arc::new(InMemoryAuditLogger::new());
+        (UserProvisioning::new(Arc::new(InMemoryUserStore::new()), audit.clone()), audit)
+    }
+
+    fn patch(operations: Value) -> ScimPatchRequest {
+        serde_json::from_value(json!({ "schemas": [SCIM_PATCH_OP_SCHEMA], "Operations": operations })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_scim_users_and_groups() {
+        let (provisioning, audit) = provisioning();
+        let alice: ScimUser = serde_json::from_value(json!({
+            "schemas": [SCIM_USER_SCHEMA],
+            "userName": "alice@acme.com",
+            "externalId": "00u1",
+            "name": { "givenName": "Alice", "familyName": "Smith" },
+            "emails": [{ "value": "alice@acme.com", "type": "work", "primary": true }],
+        }))
+        .unwrap();
+        let alice = provisioning.create_user("acme", alice, "idp").await.unwrap();
+        let id = alice.id.clone().unwrap();
+        assert_eq!(alice.groups[0].value, "user");
+        assert!(matches!(
+            provisioning.create_user("acme", ScimUser::new("alice@acme.com", "a@acme.com"), "idp").await,
+            Err(SecurityError::UserAlreadyExists(_))
+        ));
+
+        // IdPs look users up by userName before creating them
+        let query = ScimListQuery { filter: Some("userName eq \"ALICE@acme.com\"".to_string()), ..Default::default() };
+        assert_eq!(provisioning.list_users("acme", &query).await.unwrap().total_results, 1);
+        assert_eq!(provisioning.list_users("globex", &query).await.unwrap().total_results, 0);
+        assert!(provisioning.get_user("globex", &id).await.is_err());
+
+        // Deactivation as Azure AD sends it
+        let patched = provisioning
+            .patch_user("acme", &id, patch(json!([{ "op": "Replace", "value": { "active": "False", "name.givenName": "Ali" } }])), "idp")
+            .await
+            .unwrap();
+        assert!(!patched.active);
+        assert_eq!(patched.name.unwrap().given_name.as_deref(), Some("Ali"));
+        assert_eq!(patched.external_id.as_deref(), Some("00u1"));
+
+        // Group membership is the role
+        provisioning
+            .patch_group("acme", "admin", patch(json!([{ "op": "add", "path": "members", "value": [{ "value": id }] }])), "idp")
+            .await
+            .unwrap();
+        let admins = provisioning.get_group("acme", "admin").await.unwrap();
+        assert_eq!(admins.members[0].display.as_deref(), Some("alice@acme.com"));
+        let path = format!("members[value eq \"{}\"]", id);
+        provisioning
+            .patch_group("acme", "user", patch(json!([{ "op": "remove", "path": path }])), "idp")
+            .await
+            .unwrap();
+        let groups: Vec<String> = provisioning.get_user("acme", &id).await.unwrap().groups.into_iter().map(|g| g.value).collect();
+        assert_eq!(groups, vec!["admin"]);
+        assert!(matches!(provisioning.get_group("acme", "super_admin").await, Err(SecurityError::GroupNotFound(_))));
+
+        provisioning.delete_user("acme", &id, "idp").await.unwrap();
+        assert!(provisioning.get_user("acme", &id).await.is_err());
+        let events: Vec<AuditEventType> = audit.get_events().await.into_iter().map(|e| e.event_type).collect();
+        assert_eq!(
+            events,
+            vec![
+                AuditEventType::UserCreated,
+                AuditEventType::UserDisabled,
+                AuditEventType::RoleAssigned,
+                AuditEventType::RoleRevoked,
+                AuditEventType::UserDeleted,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csv_import_and_export() {
+        let (provisioning, _) = provisioning();
+        let csv = "username,email,roles,active,display_name\n\
+                   bob,bob@acme.com,admin;viewer,true,\"Bob, Jr.\"\n\
+                   carol,carol@acme.com,,no,\n\
+                   dave,,user,,\n\
+                   erin,erin@acme.com,super_admin,,\n";
+        let report = provisioning.import_csv("acme", csv, "admin").await.unwrap();
+        assert_eq!((report.created, report.updated), (2, 0));
+        let failed: Vec<(usize, &str)> = report.failures.iter().map(|f| (f.line, f.username.as_deref().unwrap())).collect();
+        assert_eq!(failed, vec![(4, "dave"), (5, "erin")]);
+
+        let export = provisioning.export_csv("acme").await.unwrap();
+        assert_eq!(
+            export,
+            "username,email,roles,active,external_id,display_name\n\
+             bob,bob@acme.com,admin;viewer,true,,\"Bob, Jr.\"\n\
+             carol,carol@acme.com,user,false,,\n"
+        );
+
+        // Importing the export changes nothing; changed rows are updated
+        let report = provisioning.import_csv("acme", &export, "admin").await.unwrap();
+        assert_eq!((report.created, report.updated, report.unchanged), (0, 0, 2));
+        let report = provisioning
+            .import_csv("acme", &export.replace("carol,carol@acme.com,user,false", "carol,carol@acme.com,user,true"), "admin")
+            .await
+            .unwrap();
+        assert_eq!((report.updated, report.unchanged), (1, 1));
+
+        assert!(provisioning.import_csv("acme", "name,email\nx,y\n", "admin").await.is_err());
+        assert!(provisioning.export_csv("globex").await.unwrap().lines().count() == 1);
+    }
+}
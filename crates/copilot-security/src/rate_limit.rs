@@ -6,13 +6,14 @@
 use crate::error::{Result, SecurityError};
 use governor::{
     clock::DefaultClock,
-    middleware::NoOpMiddleware,
+    middleware::{StateInformationMiddleware, StateSnapshot},
     state::{InMemoryState, NotKeyed},
-    Quota, RateLimiter,
+    NotUntil, Quota, RateLimiter,
 };
 use std::collections::HashMap;
 use std::num::NonZeroU32;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 /// Rate limit configuration
@@ -81,6 +82,18 @@ impl RateLimitTier {
         }
     }
 
+    /// Name of the tier, as accepted by [`RateLimitTier::from_str`]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitTier::Anonymous => "anonymous",
+            RateLimitTier::Free => "free",
+            RateLimitTier::Standard => "standard",
+            RateLimitTier::Pro => "pro",
+            RateLimitTier::Enterprise => "enterprise",
+            RateLimitTier::Unlimited => "unlimited",
+        }
+    }
+
     /// Get from string
     pub fn from_str(s: &str) -> Self {
         match s.to_lowercase().as_str() {
@@ -138,8 +151,48 @@ pub struct RateLimitResult {
     pub retry_after_secs: Option<u64>,
 }
 
+impl RateLimitResult {
+    /// Result for a request rejected with [`SecurityError::RateLimitExceeded`]
+    pub fn exceeded(limit: u32, retry_after_secs: u64) -> Self {
+        Self {
+            allowed: false,
+            remaining: 0,
+            limit,
+            reset_after_secs: retry_after_secs,
+            retry_after_secs: Some(retry_after_secs),
+        }
+    }
+
+    /// Result for an allowed request, from the limiter's state after it
+    fn allowed(limit: u32, snapshot: &StateSnapshot) -> Self {
+        let quota = snapshot.quota();
+        let remaining = snapshot.remaining_burst_capacity();
+        let used = quota.burst_size().get().saturating_sub(remaining);
+        Self {
+            allowed: true,
+            remaining,
+            limit,
+            reset_after_secs: ceil_secs(quota.replenish_interval() * used),
+            retry_after_secs: None,
+        }
+    }
+}
+
+/// Whole seconds, rounded up so clients never retry too early
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+/// Error for a rejected request
+fn exceeded_error(not_until: NotUntil<<DefaultClock as governor::clock::Clock>::Instant>) -> SecurityError {
+    let wait = not_until.wait_time_from(governor::clock::Clock::now(&DefaultClock::default()));
+    SecurityError::RateLimitExceeded {
+        retry_after_secs: ceil_secs(wait).max(1),
+    }
+}
+
 /// Simple in-memory rate limiter
-type SimpleRateLimiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock, NoOpMiddleware>;
+type SimpleRateLimiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock, StateInformationMiddleware>;
 
 /// Rate limiter manager
 pub struct RateLimitManager {
@@ -204,7 +257,7 @@ impl RateLimitManager {
         }
 
         // Create new limiter
-        let limiter = Arc::new(RateLimiter::direct(quota));
+        let limiter = Arc::new(RateLimiter::direct(quota).with_middleware::<StateInformationMiddleware>());
 
         // Store it
         {
@@ -250,26 +303,11 @@ impl RateLimitManager {
         let limiter = self.get_or_create_limiter(&key_str, quota).await;
 
         let rpm = tier.requests_per_minute().unwrap_or(60);
-        let burst = tier.burst_size().unwrap_or(10);
 
-        match limiter.check() {
-            Ok(()) => Ok(RateLimitResult {
-                allowed: true,
-                remaining: burst.saturating_sub(1),
-                limit: rpm,
-                reset_after_secs: 60,
-                retry_after_secs: None,
-            }),
-            Err(not_until) => {
-                let retry_after = not_until
-                    .wait_time_from(governor::clock::Clock::now(&DefaultClock::default()))
-                    .as_secs();
-
-                Err(SecurityError::RateLimitExceeded {
-                    retry_after_secs: retry_after,
-                })
-            }
-        }
+        limiter
+            .check()
+            .map(|snapshot| RateLimitResult::allowed(rpm, &snapshot))
+            .map_err(exceeded_error)
     }
 
     /// Check rate limit with custom values
@@ -296,24 +334,10 @@ impl RateLimitManager {
 
         let limiter = self.get_or_create_limiter(&key_str, quota).await;
 
-        match limiter.check() {
-            Ok(()) => Ok(RateLimitResult {
-                allowed: true,
-                remaining: burst.saturating_sub(1),
-                limit: rpm,
-                reset_after_secs: 60,
-                retry_after_secs: None,
-            }),
-            Err(not_until) => {
-                let retry_after = not_until
-                    .wait_time_from(governor::clock::Clock::now(&DefaultClock::default()))
-                    .as_secs();
-
-                Err(SecurityError::RateLimitExceeded {
-                    retry_after_secs: retry_after,
-                })
-            }
-        }
+        limiter
+            .check()
+            .map(|snapshot| RateLimitResult::allowed(rpm, &snapshot))
+            .map_err(exceeded_error)
     }
 
     /// Set a custom quota for a key
@@ -427,6 +451,33 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_remaining_and_reset() {
+        let manager = RateLimitManager::default_config();
+        let key = RateLimitKey::User("tenant-1".to_string());
+
+        // Anonymous: 10/min with a burst of 5, one cell every 6 seconds
+        let first = manager.check_limit(&key, &RateLimitTier::Anonymous).await.unwrap();
+        assert_eq!(first.limit, 10);
+        assert_eq!(first.remaining, 4);
+        assert_eq!(first.reset_after_secs, 6);
+
+        for _ in 0..4 {
+            manager.check_limit(&key, &RateLimitTier::Anonymous).await.unwrap();
+        }
+        match manager.check_limit(&key, &RateLimitTier::Anonymous).await {
+            Err(SecurityError::RateLimitExceeded { retry_after_secs }) => {
+                assert!((1..=6).contains(&retry_after_secs))
+            }
+            other => panic!("expected rate limit error, got {:?}", other),
+        }
+
+        let exceeded = RateLimitResult::exceeded(10, 6);
+        let headers = manager.get_headers(&exceeded);
+        assert!(headers.contains(&("X-RateLimit-Remaining".to_string(), "0".to_string())));
+        assert!(headers.contains(&("Retry-After".to_string(), "6".to_string())));
+    }
+
     #[test]
     fn test_rate_limit_tiers() {
         assert!(RateLimitTier::Anonymous.requests_per_minute() < RateLimitTier::Free.requests_per_minute());
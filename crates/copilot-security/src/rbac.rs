@@ -3,6 +3,7 @@
 //! Provides role and permission management for authorization.
 
 use crate::error::{Result, SecurityError};
+use copilot_core::Principal;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
@@ -25,6 +26,16 @@ pub enum Role {
 }
 
 impl Role {
+    /// Every role, most privileged first
+    pub const ALL: [Role; 6] = [
+        Role::SuperAdmin,
+        Role::Admin,
+        Role::User,
+        Role::Service,
+        Role::Viewer,
+        Role::Guest,
+    ];
+
     /// Get role from string
     pub fn from_str(s: &str) -> Result<Self> {
         match s.to_lowercase().as_str() {
@@ -402,6 +413,8 @@ pub struct AuthContext {
     pub permissions: HashSet<Permission>,
     /// Tenant ID (for multi-tenant)
     pub tenant_id: Option<String>,
+    /// Groups the user belongs to
+    pub groups: Vec<String>,
 }
 
 impl AuthContext {
@@ -412,6 +425,7 @@ impl AuthContext {
             roles,
             permissions: HashSet::new(),
             tenant_id: None,
+            groups: Vec::new(),
         }
     }
 
@@ -431,6 +445,23 @@ impl AuthContext {
         self
     }
 
+    /// Add the user's groups
+    pub fn with_groups(mut self, groups: Vec<String>) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    /// The principal data access is checked against
+    pub fn principal(&self) -> Principal {
+        let principal = Principal::new(self.user_id.clone())
+            .with_roles(self.roles.iter().map(Role::as_str))
+            .with_groups(self.groups.iter().cloned());
+        match &self.tenant_id {
+            Some(tenant_id) => principal.with_tenant(tenant_id.clone()),
+            None => principal,
+        }
+    }
+
     /// Add a direct permission
     pub fn with_permission(mut self, permission: Permission) -> Self {
         self.permissions.insert(permission);
@@ -528,6 +559,14 @@ mod tests {
 
         assert!(ctx.has_permission(&rbac, &Permission::ConversationsWrite));
         assert!(!ctx.has_permission(&rbac, &Permission::SystemAdmin));
+
+        let principal = ctx
+            .with_tenant("acme".to_string())
+            .with_groups(vec!["finance".to_string()])
+            .principal();
+        assert_eq!(principal.roles, vec!["user"]);
+        assert_eq!(principal.groups, vec!["finance"]);
+        assert_eq!(principal.tenant_id.as_deref(), Some("acme"));
     }
 
     #[test]
@@ -0,0 +1,440 @@
+//! Rotating asymmetric JWT signing keys
+//!
+//! [`SigningKeys`] holds a ring of RS256 or EdDSA key pairs. Tokens are
+//! signed with the newest key and name it in their `kid` header; a rotated
+//! out key still verifies tokens for an overlap window, which should cover
+//! the longest token lifetime, and is then dropped. The public halves of the
+//! keys still in use are published as a JWKS so other services can verify
+//! tokens without sharing a secret.
+//!
+//! Keys are generated in memory: tokens don't survive a restart, and
+//! replicas each sign with keys of their own.
+
+use crate::error::{Result, SecurityError};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use rsa::pkcs1::EncodeRsaPrivateKey;
+use rsa::traits::PublicKeyParts;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+/// Size of generated RSA keys in bits
+const RSA_KEY_BITS: usize = 2048;
+
+/// Algorithm of the signing keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SigningAlgorithm {
+    #[serde(rename = "RS256")]
+    Rs256,
+    #[serde(rename = "EdDSA")]
+    EdDsa,
+}
+
+impl SigningAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rs256 => "RS256",
+            Self::EdDsa => "EdDSA",
+        }
+    }
+
+    fn jwt_algorithm(&self) -> Algorithm {
+        match self {
+            Self::Rs256 => Algorithm::RS256,
+            Self::EdDsa => Algorithm::EdDSA,
+        }
+    }
+}
+
+impl fmt::Display for SigningAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SigningAlgorithm {
+    type Err = SecurityError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "rs256" => Ok(Self::Rs256),
+            "eddsa" | "ed25519" => Ok(Self::EdDsa),
+            _ => Err(SecurityError::Configuration(format!(
+                "Unsupported signing algorithm '{}': expected RS256 or EdDSA",
+                s
+            ))),
+        }
+    }
+}
+
+/// When signing keys are rotated and how long old ones stay valid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRotationConfig {
+    pub algorithm: SigningAlgorithm,
+    /// Age at which the signing key is replaced
+    pub rotation_interval_secs: i64,
+    /// How long a replaced key still verifies tokens; at least the longest
+    /// token lifetime, or tokens stop working before they expire
+    pub overlap_secs: i64,
+}
+
+impl Default for KeyRotationConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: SigningAlgorithm::EdDsa,
+            rotation_interval_secs: 2_592_000, // 30 days
+            overlap_secs: 604_800,             // 7 days, the refresh token lifetime
+        }
+    }
+}
+
+/// A signing key as listed to operators
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningKeyInfo {
+    pub kid: String,
+    pub algorithm: SigningAlgorithm,
+    pub created_at: DateTime<Utc>,
+    /// When the key stopped signing; absent for the current key
+    pub retired_at: Option<DateTime<Utc>>,
+    /// When the key stops verifying tokens; absent for the current key
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+struct SigningKey {
+    kid: String,
+    algorithm: SigningAlgorithm,
+    created_at: DateTime<Utc>,
+    retired_at: Option<DateTime<Utc>>,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    jwk: Jwk,
+}
+
+impl SigningKey {
+    fn generate(algorithm: SigningAlgorithm) -> Result<Self> {
+        let created_at = Utc::now();
+        let kid = format!(
+            "{}-{}",
+            created_at.format("%Y%m%d"),
+            &Uuid::new_v4().simple().to_string()[..8]
+        );
+        let common = CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(match algorithm {
+                SigningAlgorithm::Rs256 => KeyAlgorithm::RS256,
+                SigningAlgorithm::EdDsa => KeyAlgorithm::EdDSA,
+            }),
+            key_id: Some(kid.clone()),
+            ..Default::default()
+        };
+
+        let (encoding, decoding, parameters) = match algorithm {
+            SigningAlgorithm::Rs256 => {
+                let key =
+                    rsa::RsaPrivateKey::new(&mut rand::rngs::OsRng, RSA_KEY_BITS).map_err(|e| {
+                        SecurityError::Internal(format!("Failed to generate RSA key: {}", e))
+                    })?;
+                let der = key.to_pkcs1_der().map_err(|e| {
+                    SecurityError::Internal(format!("Failed to encode RSA key: {}", e))
+                })?;
+                let n = URL_SAFE_NO_PAD.encode(key.n().to_bytes_be());
+                let e = URL_SAFE_NO_PAD.encode(key.e().to_bytes_be());
+                (
+                    EncodingKey::from_rsa_der(der.as_bytes()),
+                    DecodingKey::from_rsa_components(&n, &e)?,
+                    AlgorithmParameters::RSA(RSAKeyParameters {
+                        key_type: RSAKeyType::RSA,
+                        n,
+                        e,
+                    }),
+                )
+            }
+            SigningAlgorithm::EdDsa => {
+                let rng = ring::rand::SystemRandom::new();
+                let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).map_err(|_| {
+                    SecurityError::Internal("Failed to generate Ed25519 key".to_string())
+                })?;
+                let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).map_err(|_| {
+                    SecurityError::Internal("Failed to load Ed25519 key".to_string())
+                })?;
+                let x = URL_SAFE_NO_PAD.encode(pair.public_key().as_ref());
+                (
+                    EncodingKey::from_ed_der(pkcs8.as_ref()),
+                    DecodingKey::from_ed_components(&x)?,
+                    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                        key_type: OctetKeyPairType::OctetKeyPair,
+                        curve: EllipticCurve::Ed25519,
+                        x,
+                    }),
+                )
+            }
+        };
+
+        Ok(Self {
+            kid,
+            algorithm,
+            created_at,
+            retired_at: None,
+            encoding,
+            decoding,
+            jwk: Jwk {
+                common,
+                algorithm: parameters,
+            },
+        })
+    }
+}
+
+/// A ring of rotating signing keys, shared by its clones
+#[derive(Clone)]
+pub struct SigningKeys {
+    config: KeyRotationConfig,
+    keys: Arc<RwLock<Vec<SigningKey>>>,
+}
+
+impl fmt::Debug for SigningKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKeys")
+            .field("config", &self.config)
+            .field("keys", &self.keys())
+            .finish()
+    }
+}
+
+impl SigningKeys {
+    /// A ring with a freshly generated signing key
+    pub fn generate(config: KeyRotationConfig) -> Result<Self> {
+        let key = SigningKey::generate(config.algorithm)?;
+        Ok(Self {
+            config,
+            keys: Arc::new(RwLock::new(vec![key])),
+        })
+    }
+
+    pub fn config(&self) -> &KeyRotationConfig {
+        &self.config
+    }
+
+    /// Replace the signing key with a new one, returning its ID; the old
+    /// key verifies tokens until the overlap window closes
+    pub fn rotate(&self) -> Result<String> {
+        let key = SigningKey::generate(self.config.algorithm)?;
+        let kid = key.kid.clone();
+        let now = Utc::now();
+
+        let mut keys = self.keys.write().expect("signing keys poisoned");
+        for old in keys.iter_mut().filter(|k| k.retired_at.is_none()) {
+            old.retired_at = Some(now);
+        }
+        keys.push(key);
+        let overlap = Duration::seconds(self.config.overlap_secs);
+        keys.retain(|k| !k.retired_at.is_some_and(|retired| retired + overlap <= now));
+        Ok(kid)
+    }
+
+    /// Rotate if the signing key has reached the rotation interval,
+    /// returning the new key's ID
+    pub fn rotate_if_due(&self) -> Result<Option<String>> {
+        let due = {
+            let keys = self.keys.read().expect("signing keys poisoned");
+            keys.iter().filter(|k| k.retired_at.is_none()).all(|k| {
+                k.created_at + Duration::seconds(self.config.rotation_interval_secs) <= Utc::now()
+            })
+        };
+        if due {
+            self.rotate().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Rotate on schedule, checking every `check_every`
+    pub fn spawn_rotation(&self, check_every: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        let keys = self.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(check_every);
+            loop {
+                ticks.tick().await;
+                match keys.rotate_if_due() {
+                    Ok(Some(kid)) => tracing::info!(kid = %kid, "Rotated JWT signing key"),
+                    Ok(None) => {}
+                    Err(e) => tracing::error!(error = %e, "Failed to rotate JWT signing key"),
+                }
+            }
+        })
+    }
+
+    /// Sign `claims` with the current key, naming it in the `kid` header
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String> {
+        let keys = self.keys.read().expect("signing keys poisoned");
+        let key = keys
+            .iter()
+            .rev()
+            .find(|k| k.retired_at.is_none())
+            .ok_or_else(|| SecurityError::Internal("No signing key".to_string()))?;
+        let mut header = Header::new(key.algorithm.jwt_algorithm());
+        header.kid = Some(key.kid.clone());
+        Ok(encode(&header, claims, &key.encoding)?)
+    }
+
+    /// Check a token against the key its `kid` header names; `validation`
+    /// sets the other checks, its algorithms are the key's
+    pub fn verify<T: DeserializeOwned>(&self, token: &str, validation: &Validation) -> Result<T> {
+        let kid = decode_header(token)?
+            .kid
+            .ok_or_else(|| SecurityError::InvalidToken("Token names no signing key".to_string()))?;
+        let (algorithm, decoding) = {
+            let keys = self.keys.read().expect("signing keys poisoned");
+            let key = keys
+                .iter()
+                .find(|k| k.kid == kid && self.verifies(k))
+                .ok_or_else(|| {
+                    SecurityError::InvalidToken(format!("Unknown signing key '{}'", kid))
+                })?;
+            (key.algorithm.jwt_algorithm(), key.decoding.clone())
+        };
+
+        let mut validation = validation.clone();
+        validation.algorithms = vec![algorithm];
+        Ok(decode::<T>(token, &decoding, &validation)?.claims)
+    }
+
+    /// The public keys that verify tokens, as a JWKS
+    pub fn jwks(&self) -> JwkSet {
+        let keys = self.keys.read().expect("signing keys poisoned");
+        JwkSet {
+            keys: keys
+                .iter()
+                .filter(|k| self.verifies(k))
+                .map(|k| k.jwk.clone())
+                .collect(),
+        }
+    }
+
+    /// The keys that verify tokens, newest first
+    pub fn keys(&self) -> Vec<SigningKeyInfo> {
+        let keys = self.keys.read().expect("signing keys poisoned");
+        let overlap = Duration::seconds(self.config.overlap_secs);
+        keys.iter()
+            .rev()
+            .filter(|k| self.verifies(k))
+            .map(|k| SigningKeyInfo {
+                kid: k.kid.clone(),
+                algorithm: k.algorithm,
+                created_at: k.created_at,
+                retired_at: k.retired_at,
+                expires_at: k.retired_at.map(|retired| retired + overlap),
+            })
+            .collect()
+    }
+
+    fn verifies(&self, key: &SigningKey) -> bool {
+        !key.retired_at.is_some_and(|retired| {
+            retired + Duration::seconds(self.config.overlap_secs) <= Utc::now()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct TestClaims {
+        sub: String,
+        exp: i64,
+    }
+
+    fn claims() -> TestClaims {
+        TestClaims {
+            sub: "user-1".to_string(),
+            exp: (Utc::now() + Duration::minutes(5)).timestamp(),
+        }
+    }
+
+    #[test]
+    fn test_rotated_keys_verify_until_the_overlap_closes() {
+        let keys = SigningKeys::generate(KeyRotationConfig::default()).unwrap();
+        let validation = Validation::new(Algorithm::EdDSA);
+
+        let old = keys.sign(&claims()).unwrap();
+        let old_kid = decode_header(&old).unwrap().kid.unwrap();
+        let new_kid = keys.rotate().unwrap();
+        let new = keys.sign(&claims()).unwrap();
+        assert_eq!(decode_header(&new).unwrap().kid.unwrap(), new_kid);
+        assert_eq!(decode_header(&new).unwrap().alg, Algorithm::EdDSA);
+
+        // Both verify and are published while the overlap lasts
+        assert_eq!(
+            keys.verify::<TestClaims>(&old, &validation).unwrap().sub,
+            "user-1"
+        );
+        assert!(keys.verify::<TestClaims>(&new, &validation).is_ok());
+        let jwks = keys.jwks();
+        assert!(jwks.find(&old_kid).is_some() && jwks.find(&new_kid).is_some());
+        assert_eq!(keys.keys()[0].kid, new_kid);
+        assert!(keys.keys()[1].expires_at.is_some());
+        assert_eq!(keys.rotate_if_due().unwrap(), None);
+
+        // Without an overlap, rotating drops the old key at once
+        let strict = SigningKeys::generate(KeyRotationConfig {
+            overlap_secs: 0,
+            rotation_interval_secs: 0,
+            ..Default::default()
+        })
+        .unwrap();
+        let token = strict.sign(&claims()).unwrap();
+        assert!(strict.rotate_if_due().unwrap().is_some());
+        assert!(matches!(
+            strict.verify::<TestClaims>(&token, &validation),
+            Err(SecurityError::InvalidToken(_))
+        ));
+        assert_eq!(strict.jwks().keys.len(), 1);
+
+        // Another ring's keys, and tokens without a key ID, are refused
+        let other = SigningKeys::generate(KeyRotationConfig::default()).unwrap();
+        assert!(other.verify::<TestClaims>(&new, &validation).is_err());
+        let unsigned = encode(
+            &Header::default(),
+            &claims(),
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        assert!(keys.verify::<TestClaims>(&unsigned, &validation).is_err());
+    }
+
+    #[test]
+    fn test_rs256_keys_are_published_as_rsa_jwks() {
+        let keys = SigningKeys::generate(KeyRotationConfig {
+            algorithm: SigningAlgorithm::Rs256,
+            ..Default::default()
+        })
+        .unwrap();
+        let token = keys.sign(&claims()).unwrap();
+        assert_eq!(decode_header(&token).unwrap().alg, Algorithm::RS256);
+
+        // The published key verifies the token on its own
+        let jwks = keys.jwks();
+        let decoding = DecodingKey::from_jwk(&jwks.keys[0]).unwrap();
+        let decoded =
+            decode::<TestClaims>(&token, &decoding, &Validation::new(Algorithm::RS256)).unwrap();
+        assert_eq!(decoded.claims.sub, "user-1");
+        assert_eq!(
+            "rs256".parse::<SigningAlgorithm>().unwrap(),
+            SigningAlgorithm::Rs256
+        );
+        assert!("HS256".parse::<SigningAlgorithm>().is_err());
+    }
+}
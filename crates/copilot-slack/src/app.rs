@@ -0,0 +1,449 @@
+//! The Slack app
+//!
+//! Each Slack thread is one conversation: the first question in a thread
+//! creates a session and every later message in that thread continues it.
+//! Answers are posted as a placeholder reply that is edited in place as the
+//! response streams in. Approval requests are posted as interactive messages
+//! whose buttons resolve the matching [`ApprovalGate`] request.
+
+use copilot_conversation::manager::MessageRequest;
+use copilot_conversation::streaming::ChunkType;
+use copilot_conversation::ConversationManager;
+use copilot_workflow::{ApprovalGate, ApprovalRequest};
+use dashmap::DashMap;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::blocks::{self, APPROVE_ACTION, DENY_ACTION};
+use crate::client::{SlackApi, SlackMessage};
+use crate::events::{Interaction, SlackEvent, SlashCommand};
+use crate::verify::SignatureVerifier;
+use crate::{Result, SlackError};
+
+const THINKING: &str = ":hourglass_flowing_sand: Thinking…";
+
+/// A Slack thread, which maps to one conversation session
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ThreadKey {
+    pub team_id: String,
+    pub channel: String,
+    pub thread_ts: String,
+}
+
+impl ThreadKey {
+    pub fn new(team_id: &str, channel: &str, thread_ts: &str) -> Self {
+        Self {
+            team_id: team_id.to_string(),
+            channel: channel.to_string(),
+            thread_ts: thread_ts.to_string(),
+        }
+    }
+}
+
+/// Where an approval request was posted
+#[derive(Debug, Clone)]
+struct PostedApproval {
+    channel: String,
+    ts: String,
+}
+
+/// Slack front end to the conversation manager and approval gate
+pub struct SlackApp {
+    api: Arc<dyn SlackApi>,
+    conversations: Arc<ConversationManager>,
+    approvals: ApprovalGate,
+    verifier: SignatureVerifier,
+    threads: DashMap<ThreadKey, String>,
+    posted_approvals: DashMap<String, PostedApproval>,
+    update_interval: Duration,
+}
+
+impl SlackApp {
+    pub fn new(api: Arc<dyn SlackApi>, conversations: Arc<ConversationManager>, signing_secret: &str) -> Self {
+        Self {
+            api,
+            conversations,
+            approvals: ApprovalGate::new(),
+            verifier: SignatureVerifier::new(signing_secret),
+            threads: DashMap::new(),
+            posted_approvals: DashMap::new(),
+            update_interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Resolve approvals on a shared gate (e.g. the workflow engine's)
+    pub fn with_approval_gate(mut self, approvals: ApprovalGate) -> Self {
+        self.approvals = approvals;
+        self
+    }
+
+    /// Minimum time between edits of a streaming answer; Slack rate-limits
+    /// `chat.update` to about one call per second per channel
+    pub fn with_update_interval(mut self, interval: Duration) -> Self {
+        self.update_interval = interval;
+        self
+    }
+
+    pub fn verifier(&self) -> &SignatureVerifier {
+        &self.verifier
+    }
+
+    pub fn approval_gate(&self) -> &ApprovalGate {
+        &self.approvals
+    }
+
+    /// Session of a thread, if the app has answered in it
+    pub fn thread_session(&self, thread: &ThreadKey) -> Option<String> {
+        self.threads.get(thread).map(|s| s.clone())
+    }
+
+    async fn session_for(&self, thread: &ThreadKey) -> Result<String> {
+        if let Some(session_id) = self.thread_session(thread) {
+            return Ok(session_id);
+        }
+        let session = self.conversations.create_session(None, None).await?;
+        // Another message in the same thread may have raced us here
+        Ok(self.threads.entry(thread.clone()).or_insert(session.id).clone())
+    }
+
+    /// React to an Events API event
+    ///
+    /// Mentions are answered in their thread (starting one if needed); plain
+    /// messages are answered only in threads the app is already part of.
+    pub fn handle_event(self: &Arc<Self>, team_id: &str, event: SlackEvent) {
+        if !event.is_from_user() {
+            return;
+        }
+        let thread = ThreadKey::new(team_id, &event.channel, event.reply_thread());
+        let wanted = match event.event_type.as_str() {
+            "app_mention" => true,
+            // Mentions also arrive as `message` events; answer those once
+            "message" => {
+                event.thread_ts.is_some() && !event.text.contains("<@") && self.threads.contains_key(&thread)
+            }
+            _ => false,
+        };
+        let question = strip_mentions(&event.text);
+        if !wanted || question.is_empty() {
+            return;
+        }
+
+        let app = Arc::clone(self);
+        tokio::spawn(async move {
+            if let Err(e) = app.answer(&thread, &question).await {
+                warn!(channel = %thread.channel, error = %e, "Failed to answer Slack message");
+            }
+        });
+    }
+
+    /// Handle a slash command, returning the immediate (ephemeral) reply
+    ///
+    /// `/copilot ask <question>` posts the question to the channel and answers
+    /// it in a thread, which then continues as a conversation.
+    pub fn handle_command(self: &Arc<Self>, command: SlashCommand) -> SlackMessage {
+        let text = command.text.trim();
+        let (subcommand, question) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let question = question.trim().to_string();
+
+        if !subcommand.eq_ignore_ascii_case("ask") || question.is_empty() {
+            return SlackMessage::text(format!(
+                "Usage: `{} ask <question>` — I'll answer in a thread you can keep replying in.",
+                command.command
+            ));
+        }
+
+        let reply = SlackMessage::text(format!("Looking into: {}", question));
+        let app = Arc::clone(self);
+        tokio::spawn(async move {
+            let asked = SlackMessage::text(format!("<@{}> asked: {}", command.user_id, question));
+            let result = match app.api.post_message(&command.channel_id, None, &asked).await {
+                Ok(ts) => {
+                    let thread = ThreadKey::new(&command.team_id, &command.channel_id, &ts);
+                    app.answer(&thread, &question).await.map(|_| ())
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!(channel = %command.channel_id, error = %e, "Failed to answer slash command");
+                let failed = SlackMessage::text(format!("Sorry, I couldn't answer that: {}", e));
+                let _ = app.api.respond(&command.response_url, &failed, false).await;
+            }
+        });
+        reply
+    }
+
+    /// Answer `question` in `thread`, streaming the response into a reply
+    pub async fn answer(&self, thread: &ThreadKey, question: &str) -> Result<String> {
+        let session_id = self.session_for(thread).await?;
+        let reply_ts = self
+            .api
+            .post_message(&thread.channel, Some(&thread.thread_ts), &SlackMessage::text(THINKING))
+            .await?;
+
+        match self.stream_answer(thread, &reply_ts, &session_id, question).await {
+            Ok(answer) => Ok(answer),
+            Err(e) => {
+                let failed = SlackMessage::text(format!(":warning: Sorry, something went wrong: {}", e));
+                let _ = self.api.update_message(&thread.channel, &reply_ts, &failed).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn stream_answer(&self, thread: &ThreadKey, reply_ts: &str, session_id: &str, question: &str) -> Result<String> {
+        let mut metadata = HashMap::new();
+        metadata.insert("source".to_string(), "slack".to_string());
+        metadata.insert("slack_channel".to_string(), thread.channel.clone());
+        metadata.insert("slack_thread_ts".to_string(), thread.thread_ts.clone());
+        let request = MessageRequest {
+            session_id: session_id.to_string(),
+            message: question.to_string(),
+            metadata,
+            model: None,
+        };
+
+        let mut response = self.conversations.create_streaming_response(request).await?;
+        let mut stream = response.stream(question.to_string()).await?;
+
+        let mut answer = String::new();
+        let mut shown = String::new();
+        let mut last_update = Instant::now();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            match chunk.chunk_type {
+                ChunkType::Token => answer.push_str(&chunk.content),
+                ChunkType::Error => return Err(SlackError::Conversation(chunk.content)),
+                ChunkType::Done => break,
+                ChunkType::Thinking | ChunkType::Metadata => continue,
+            }
+            if last_update.elapsed() >= self.update_interval && answer != shown {
+                let partial = SlackMessage::text(format!("{} …", answer));
+                self.api.update_message(&thread.channel, reply_ts, &partial).await?;
+                shown.clone_from(&answer);
+                last_update = Instant::now();
+            }
+        }
+
+        self.api
+            .update_message(&thread.channel, reply_ts, &SlackMessage::text(answer.clone()))
+            .await?;
+        debug!(session_id, channel = %thread.channel, "Answered in Slack");
+        Ok(answer)
+    }
+
+    /// Register `request` with the approval gate and post it to `channel`
+    /// with Approve and Deny buttons
+    pub async fn request_approval(&self, channel: &str, request: ApprovalRequest) -> Result<String> {
+        let message = blocks::approval_message(&request);
+        let approval_id = self.approvals.request_approval(request).await;
+        let ts = self.api.post_message(channel, None, &message).await?;
+        self.posted_approvals.insert(
+            approval_id.clone(),
+            PostedApproval {
+                channel: channel.to_string(),
+                ts,
+            },
+        );
+        Ok(approval_id)
+    }
+
+    /// Apply an Approve or Deny button press
+    pub async fn handle_interaction(&self, interaction: Interaction) -> Result<()> {
+        if interaction.interaction_type != "block_actions" {
+            return Ok(());
+        }
+        for action in &interaction.actions {
+            let approve = match action.action_id.as_str() {
+                APPROVE_ACTION => true,
+                DENY_ACTION => false,
+                _ => continue,
+            };
+            let Some(approval_id) = action.value.as_deref() else {
+                continue;
+            };
+
+            let approver = &interaction.user.id;
+            let decided = if approve {
+                self.approvals.approve(approval_id, approver, None).await
+            } else {
+                self.approvals.deny(approval_id, approver, None).await
+            };
+            if let Err(reason) = decided {
+                // Typically someone else already decided
+                if let Some(url) = &interaction.response_url {
+                    self.api.respond(url, &SlackMessage::text(reason.clone()), false).await?;
+                }
+                return Err(SlackError::Approval(reason));
+            }
+            info!(approval_id, approver = %approver, approve, "Approval decided in Slack");
+
+            let Some(request) = self.approvals.get_request(approval_id).await else {
+                continue;
+            };
+            let posted = self.posted_approvals.remove(approval_id).map(|(_, p)| p).or_else(|| {
+                Some(PostedApproval {
+                    channel: interaction.channel.as_ref()?.id.clone(),
+                    ts: interaction.message.as_ref()?.ts.clone(),
+                })
+            });
+            if let Some(posted) = posted {
+                self.api
+                    .update_message(&posted.channel, &posted.ts, &blocks::resolved_message(&request))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Remove `<@U123>` mentions from message text
+fn strip_mentions(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<@") {
+        out.push_str(&rest[..start]);
+        rest = rest[start..].split_once('>').map(|(_, tail)| tail).unwrap_or("");
+    }
+    out.push_str(rest);
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{BlockAction, InteractionUser};
+    use async_trait::async_trait;
+    use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+    use copilot_nlp::NlpEngineImpl;
+    use copilot_workflow::ApprovalStatus;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Call {
+        Post { channel: String, thread_ts: Option<String>, text: String },
+        Update { ts: String, text: String },
+    }
+
+    #[derive(Default)]
+    struct FakeSlack {
+        calls: Mutex<Vec<Call>>,
+    }
+
+    #[async_trait]
+    impl SlackApi for FakeSlack {
+        async fn post_message(&self, channel: &str, thread_ts: Option<&str>, message: &SlackMessage) -> Result<String> {
+            let mut calls = self.calls.lock().unwrap();
+            calls.push(Call::Post {
+                channel: channel.to_string(),
+                thread_ts: thread_ts.map(str::to_string),
+                text: message.text.clone(),
+            });
+            Ok(format!("100.{}", calls.len()))
+        }
+
+        async fn update_message(&self, _channel: &str, ts: &str, message: &SlackMessage) -> Result<()> {
+            self.calls.lock().unwrap().push(Call::Update {
+                ts: ts.to_string(),
+                text: message.text.clone(),
+            });
+            Ok(())
+        }
+
+        async fn respond(&self, _response_url: &str, _message: &SlackMessage, _in_channel: bool) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn app() -> (Arc<SlackApp>, Arc<FakeSlack>) {
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = Arc::new(ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine));
+        let slack = Arc::new(FakeSlack::default());
+        let app = SlackApp::new(slack.clone(), manager, "secret").with_update_interval(Duration::ZERO);
+        (Arc::new(app), slack)
+    }
+
+    #[test]
+    fn test_strip_mentions() {
+        assert_eq!(strip_mentions("<@U0BOT> why is   checkout slow?"), "why is checkout slow?");
+        assert_eq!(strip_mentions("ping <@U1> and <@U2|bob>"), "ping and");
+    }
+
+    #[tokio::test]
+    async fn test_answer_streams_into_thread_reply() {
+        let (app, slack) = app();
+        let thread = ThreadKey::new("T1", "C1", "111.1");
+
+        let answer = app.answer(&thread, "why is checkout slow").await.unwrap();
+        assert!(answer.contains("why is checkout slow"));
+
+        let calls = slack.calls.lock().unwrap().clone();
+        assert_eq!(
+            calls[0],
+            Call::Post {
+                channel: "C1".to_string(),
+                thread_ts: Some("111.1".to_string()),
+                text: THINKING.to_string(),
+            }
+        );
+        let updates: Vec<_> = calls[1..]
+            .iter()
+            .map(|c| match c {
+                Call::Update { ts, text } if ts == "100.1" => text.clone(),
+                other => panic!("unexpected call {:?}", other),
+            })
+            .collect();
+        assert!(updates.len() > 2, "answer should stream in several edits");
+        assert!(updates[0].ends_with(" …"));
+        assert_eq!(updates.last().unwrap(), &answer);
+
+        // Later messages in the thread continue the same conversation
+        let session = app.thread_session(&thread).unwrap();
+        assert_eq!(app.session_for(&thread).await.unwrap(), session);
+        let other = ThreadKey::new("T1", "C1", "222.2");
+        assert_ne!(app.session_for(&other).await.unwrap(), session);
+    }
+
+    #[tokio::test]
+    async fn test_approval_buttons_resolve_gate() {
+        let (app, slack) = app();
+        let request = ApprovalRequest::new("wf-1", "deploy", "Deploy to prod", "Ship v2", "ci", 600);
+        let approval_id = app.request_approval("C-ops", request).await.unwrap();
+        assert_eq!(app.approval_gate().check_approval(&approval_id).await, Some(ApprovalStatus::Pending));
+
+        let press = |action_id: &str| Interaction {
+            interaction_type: "block_actions".to_string(),
+            user: InteractionUser {
+                id: "U42".to_string(),
+                username: None,
+            },
+            channel: None,
+            message: None,
+            actions: vec![BlockAction {
+                action_id: action_id.to_string(),
+                value: Some(approval_id.clone()),
+            }],
+            response_url: None,
+        };
+        app.handle_interaction(press(DENY_ACTION)).await.unwrap();
+
+        let request = app.approval_gate().get_request(&approval_id).await.unwrap();
+        assert_eq!(request.status, ApprovalStatus::Denied);
+        assert_eq!(request.approver.as_deref(), Some("U42"));
+        assert_eq!(
+            slack.calls.lock().unwrap().last().unwrap(),
+            &Call::Update {
+                ts: "100.1".to_string(),
+                text: ":no_entry: Denied: Deploy to prod".to_string(),
+            }
+        );
+
+        // A second press on the stale message is refused
+        assert!(matches!(
+            app.handle_interaction(press(APPROVE_ACTION)).await,
+            Err(SlackError::Approval(_))
+        ));
+    }
+}
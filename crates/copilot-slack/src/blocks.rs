@@ -0,0 +1,112 @@
+//! Block Kit messages for approval requests
+//!
+//! A pending approval is posted with Approve and Deny buttons whose value is
+//! the approval ID. Once decided, the message is replaced with the outcome so
+//! nobody can press a stale button.
+
+use copilot_workflow::{ApprovalRequest, ApprovalStatus};
+use serde_json::{json, Value};
+
+use crate::client::SlackMessage;
+
+pub const APPROVE_ACTION: &str = "copilot_approval_approve";
+pub const DENY_ACTION: &str = "copilot_approval_deny";
+
+/// Slack's limit on the text of a section block
+const SECTION_TEXT_LIMIT: usize = 3000;
+
+/// Interactive message asking for a decision on `request`
+pub fn approval_message(request: &ApprovalRequest) -> SlackMessage {
+    let mut blocks = header_blocks(request);
+    blocks.push(json!({
+        "type": "actions",
+        "block_id": format!("approval:{}", request.id),
+        "elements": [
+            {
+                "type": "button",
+                "action_id": APPROVE_ACTION,
+                "style": "primary",
+                "text": { "type": "plain_text", "text": "Approve" },
+                "value": request.id,
+            },
+            {
+                "type": "button",
+                "action_id": DENY_ACTION,
+                "style": "danger",
+                "text": { "type": "plain_text", "text": "Deny" },
+                "value": request.id,
+            },
+        ],
+    }));
+    SlackMessage::text(format!("Approval requested: {}", request.title)).with_blocks(blocks)
+}
+
+/// Final state of an approval message, without buttons
+pub fn resolved_message(request: &ApprovalRequest) -> SlackMessage {
+    let outcome = match request.status {
+        ApprovalStatus::Approved => ":white_check_mark: Approved",
+        ApprovalStatus::Denied => ":no_entry: Denied",
+        ApprovalStatus::Timeout => ":hourglass: Timed out",
+        ApprovalStatus::Cancelled => ":heavy_minus_sign: Cancelled",
+        ApprovalStatus::Pending => ":hourglass_flowing_sand: Pending",
+    };
+    let by = request
+        .approver
+        .as_deref()
+        .map(|approver| format!(" by <@{}>", approver))
+        .unwrap_or_default();
+
+    let mut blocks = header_blocks(request);
+    blocks.push(json!({
+        "type": "context",
+        "elements": [{ "type": "mrkdwn", "text": format!("{}{}", outcome, by) }],
+    }));
+    SlackMessage::text(format!("{}: {}", outcome, request.title)).with_blocks(blocks)
+}
+
+fn header_blocks(request: &ApprovalRequest) -> Vec<Value> {
+    let mut description = request.description.clone();
+    if description.len() > SECTION_TEXT_LIMIT {
+        let mut cut = SECTION_TEXT_LIMIT - 1;
+        while !description.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        description.truncate(cut);
+        description.push('…');
+    }
+    let mut blocks = vec![json!({
+        "type": "section",
+        "text": { "type": "mrkdwn", "text": format!("*{}*\n{}", request.title, description) },
+    })];
+    blocks.push(json!({
+        "type": "context",
+        "elements": [{
+            "type": "mrkdwn",
+            "text": format!(
+                "Workflow `{}` · step `{}` · requested by {}",
+                request.workflow_id, request.step_id, request.requester
+            ),
+        }],
+    }));
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approval_buttons_carry_the_request_id() {
+        let request = ApprovalRequest::new("wf-1", "deploy", "Deploy to prod", "Ship v2", "ci", 600);
+        let message = approval_message(&request);
+        let blocks = message.blocks.unwrap();
+        let actions = &blocks.last().unwrap()["elements"];
+        assert_eq!(actions[0]["action_id"], APPROVE_ACTION);
+        assert_eq!(actions[1]["action_id"], DENY_ACTION);
+        assert_eq!(actions[0]["value"], request.id.as_str());
+
+        let resolved = resolved_message(&request.approve("U123", None));
+        let text = resolved.blocks.unwrap().last().unwrap()["elements"][0]["text"].clone();
+        assert_eq!(text, ":white_check_mark: Approved by <@U123>");
+    }
+}
@@ -0,0 +1,138 @@
+//! Slack Web API client
+//!
+//! Only the calls the app needs: posting and updating messages, and replying
+//! through a slash command or interaction `response_url`.
+
+use crate::{Result, SlackError};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::debug;
+
+/// A message to post or to replace an existing one with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SlackMessage {
+    /// Plain text; also the notification fallback when `blocks` are set
+    pub text: String,
+    /// Block Kit blocks
+    pub blocks: Option<Vec<Value>>,
+}
+
+impl SlackMessage {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            blocks: None,
+        }
+    }
+
+    pub fn with_blocks(mut self, blocks: Vec<Value>) -> Self {
+        self.blocks = Some(blocks);
+        self
+    }
+
+    fn payload(&self) -> Value {
+        let mut payload = json!({ "text": self.text });
+        if let Some(blocks) = &self.blocks {
+            payload["blocks"] = json!(blocks);
+        }
+        payload
+    }
+}
+
+/// Slack Web API operations used by the app
+#[async_trait]
+pub trait SlackApi: Send + Sync {
+    /// Post a message, in a thread when `thread_ts` is set; returns its `ts`
+    async fn post_message(&self, channel: &str, thread_ts: Option<&str>, message: &SlackMessage) -> Result<String>;
+
+    /// Replace the content of a posted message
+    async fn update_message(&self, channel: &str, ts: &str, message: &SlackMessage) -> Result<()>;
+
+    /// Reply through a `response_url`, visible only to the caller unless
+    /// `in_channel`
+    async fn respond(&self, response_url: &str, message: &SlackMessage, in_channel: bool) -> Result<()>;
+}
+
+/// [`SlackApi`] over HTTPS with a bot token
+pub struct SlackClient {
+    client: reqwest::Client,
+    token: String,
+    base_url: String,
+}
+
+impl SlackClient {
+    pub fn new(bot_token: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to create HTTP client");
+        Self {
+            client,
+            token: bot_token.into(),
+            base_url: "https://slack.com/api".to_string(),
+        }
+    }
+
+    /// Point at a different API host (e.g. a test server)
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    async fn call(&self, method: &str, payload: Value) -> Result<Value> {
+        debug!(method, "Slack API call");
+        let response: Value = self
+            .client
+            .post(format!("{}/{}", self.base_url, method))
+            .bearer_auth(&self.token)
+            .json(&payload)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        // Slack reports errors in the body with HTTP 200
+        if response["ok"].as_bool() != Some(true) {
+            let error = response["error"].as_str().unwrap_or("unknown_error");
+            return Err(SlackError::Api(format!("{}: {}", method, error)));
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl SlackApi for SlackClient {
+    async fn post_message(&self, channel: &str, thread_ts: Option<&str>, message: &SlackMessage) -> Result<String> {
+        let mut payload = message.payload();
+        payload["channel"] = json!(channel);
+        if let Some(thread_ts) = thread_ts {
+            payload["thread_ts"] = json!(thread_ts);
+        }
+        let response = self.call("chat.postMessage", payload).await?;
+        response["ts"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| SlackError::Api("chat.postMessage: no ts in response".to_string()))
+    }
+
+    async fn update_message(&self, channel: &str, ts: &str, message: &SlackMessage) -> Result<()> {
+        let mut payload = message.payload();
+        payload["channel"] = json!(channel);
+        payload["ts"] = json!(ts);
+        self.call("chat.update", payload).await?;
+        Ok(())
+    }
+
+    async fn respond(&self, response_url: &str, message: &SlackMessage, in_channel: bool) -> Result<()> {
+        let mut payload = message.payload();
+        payload["response_type"] = json!(if in_channel { "in_channel" } else { "ephemeral" });
+        self.client
+            .post(response_url)
+            .json(&payload)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+//! Payloads Slack sends to the app
+//!
+//! Events API callbacks arrive as JSON; slash commands and interactions
+//! arrive form-encoded, interactions with their JSON in a `payload` field.
+
+use serde::{Deserialize, Serialize};
+
+/// Events API request body
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventEnvelope {
+    /// Sent once when the request URL is configured
+    UrlVerification { challenge: String },
+    EventCallback {
+        #[serde(default)]
+        team_id: String,
+        event: SlackEvent,
+    },
+    #[serde(other)]
+    Unsupported,
+}
+
+/// An event from the Events API
+#[derive(Debug, Clone, Deserialize)]
+pub struct SlackEvent {
+    /// `app_mention`, `message`, ...
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// Set for edits, joins and other non-plain messages
+    #[serde(default)]
+    pub subtype: Option<String>,
+    #[serde(default)]
+    pub user: Option<String>,
+    /// Set when a bot (including this app) posted the message
+    #[serde(default)]
+    pub bot_id: Option<String>,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub channel: String,
+    pub ts: String,
+    /// Root of the thread the message is in
+    #[serde(default)]
+    pub thread_ts: Option<String>,
+}
+
+impl SlackEvent {
+    /// The thread a reply belongs in: the message's own thread, or a new one
+    /// rooted at the message
+    pub fn reply_thread(&self) -> &str {
+        self.thread_ts.as_deref().unwrap_or(&self.ts)
+    }
+
+    /// Whether a person wrote this message (not a bot, not an edit)
+    pub fn is_from_user(&self) -> bool {
+        self.bot_id.is_none() && self.subtype.is_none() && self.user.is_some()
+    }
+}
+
+/// A slash command invocation
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SlashCommand {
+    pub command: String,
+    #[serde(default)]
+    pub text: String,
+    pub user_id: String,
+    pub channel_id: String,
+    #[serde(default)]
+    pub team_id: String,
+    pub response_url: String,
+}
+
+/// Form body of an interaction request
+#[derive(Debug, Clone, Deserialize)]
+pub struct InteractionForm {
+    pub payload: String,
+}
+
+/// A Block Kit interaction
+#[derive(Debug, Clone, Deserialize)]
+pub struct Interaction {
+    #[serde(rename = "type")]
+    pub interaction_type: String,
+    pub user: InteractionUser,
+    #[serde(default)]
+    pub channel: Option<InteractionChannel>,
+    #[serde(default)]
+    pub message: Option<InteractionMessage>,
+    #[serde(default)]
+    pub actions: Vec<BlockAction>,
+    #[serde(default)]
+    pub response_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InteractionUser {
+    pub id: String,
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InteractionChannel {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InteractionMessage {
+    pub ts: String,
+}
+
+/// A button press or other block element action
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockAction {
+    pub action_id: String,
+    #[serde(default)]
+    pub value: Option<String>,
+}
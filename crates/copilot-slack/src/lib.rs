@@ -0,0 +1,64 @@
+//! Slack app integration for LLM CoPilot Agent
+//!
+//! This crate lets teams use the agent from Slack:
+//! - Events API: mention the app to ask a question; each Slack thread maps to
+//!   one conversation session
+//! - Slash commands: `/copilot ask <question>` starts a threaded conversation
+//! - Streaming answers, shown by editing the reply as tokens arrive
+//! - Approval requests as interactive Block Kit messages whose buttons
+//!   resolve the matching workflow `ApprovalGate` request
+//! - Request signature verification with replay protection
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use copilot_slack::{router, SlackApp, SlackClient};
+//!
+//! let app = SlackApp::new(Arc::new(SlackClient::new(bot_token)), conversations, &signing_secret)
+//!     .with_approval_gate(engine.approval_gate().clone());
+//! let slack_routes = router(Arc::new(app)); // nest under e.g. "/slack"
+//! ```
+
+pub mod app;
+pub mod blocks;
+pub mod client;
+pub mod events;
+pub mod routes;
+pub mod verify;
+
+pub use app::{SlackApp, ThreadKey};
+pub use client::{SlackApi, SlackClient, SlackMessage};
+pub use events::{EventEnvelope, Interaction, SlackEvent, SlashCommand};
+pub use routes::router;
+pub use verify::SignatureVerifier;
+
+use copilot_conversation::ConversationError;
+use thiserror::Error;
+
+/// Slack integration errors
+#[derive(Debug, Error)]
+pub enum SlackError {
+    #[error("Invalid request signature: {0}")]
+    InvalidSignature(String),
+
+    #[error("Slack API error: {0}")]
+    Api(String),
+
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Conversation error: {0}")]
+    Conversation(String),
+
+    #[error("Approval error: {0}")]
+    Approval(String),
+}
+
+impl From<ConversationError> for SlackError {
+    fn from(err: ConversationError) -> Self {
+        SlackError::Conversation(err.to_string())
+    }
+}
+
+/// Result type for Slack operations
+pub type Result<T> = std::result::Result<T, SlackError>;
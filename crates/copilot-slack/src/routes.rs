@@ -0,0 +1,154 @@
+//! HTTP endpoints Slack calls
+//!
+//! Mount the router where the Slack app's Event Subscriptions, Slash
+//! Commands and Interactivity request URLs point (`/events`, `/commands`,
+//! `/interactions`). Slack expects an answer within three seconds, so work
+//! that talks to the model runs in the background after the request is
+//! acknowledged.
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use serde_json::json;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::app::SlackApp;
+use crate::events::{EventEnvelope, Interaction, InteractionForm, SlashCommand};
+
+/// Set on redeliveries of events Slack thinks were not acknowledged in time
+const RETRY_HEADER: &str = "x-slack-retry-num";
+
+/// Router for the Slack request URLs
+pub fn router(app: Arc<SlackApp>) -> Router {
+    Router::new()
+        .route("/events", post(events))
+        .route("/commands", post(commands))
+        .route("/interactions", post(interactions))
+        .with_state(app)
+}
+
+fn verified(app: &SlackApp, headers: &HeaderMap, body: &[u8]) -> bool {
+    match app.verifier().verify(headers, body) {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(error = %e, "Rejected Slack request");
+            false
+        }
+    }
+}
+
+async fn events(State(app): State<Arc<SlackApp>>, headers: HeaderMap, body: Bytes) -> Response {
+    if !verified(&app, &headers, &body) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let envelope: EventEnvelope = match serde_json::from_slice(&body) {
+        Ok(envelope) => envelope,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    match envelope {
+        EventEnvelope::UrlVerification { challenge } => Json(json!({ "challenge": challenge })).into_response(),
+        // The first delivery is already being answered
+        EventEnvelope::EventCallback { .. } if headers.contains_key(RETRY_HEADER) => StatusCode::OK.into_response(),
+        EventEnvelope::EventCallback { team_id, event } => {
+            app.handle_event(&team_id, event);
+            StatusCode::OK.into_response()
+        }
+        EventEnvelope::Unsupported => StatusCode::OK.into_response(),
+    }
+}
+
+async fn commands(State(app): State<Arc<SlackApp>>, headers: HeaderMap, body: Bytes) -> Response {
+    if !verified(&app, &headers, &body) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let command: SlashCommand = match serde_urlencoded::from_bytes(&body) {
+        Ok(command) => command,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    let reply = app.handle_command(command);
+    Json(json!({ "response_type": "ephemeral", "text": reply.text })).into_response()
+}
+
+async fn interactions(State(app): State<Arc<SlackApp>>, headers: HeaderMap, body: Bytes) -> Response {
+    if !verified(&app, &headers, &body) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let interaction: Interaction = match serde_urlencoded::from_bytes::<InteractionForm>(&body)
+        .map_err(|e| e.to_string())
+        .and_then(|form| serde_json::from_str(&form.payload).map_err(|e| e.to_string()))
+    {
+        Ok(interaction) => interaction,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    tokio::spawn(async move {
+        if let Err(e) = app.handle_interaction(interaction).await {
+            warn!(error = %e, "Failed to handle Slack interaction");
+        }
+    });
+    StatusCode::OK.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::SlackClient;
+    use crate::verify::{SIGNATURE_HEADER, TIMESTAMP_HEADER};
+    use axum::body::Body;
+    use axum::http::Request;
+    use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+    use copilot_conversation::ConversationManager;
+    use copilot_nlp::NlpEngineImpl;
+    use tower::ServiceExt;
+
+    fn app() -> Arc<SlackApp> {
+        let context_engine = Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager = Arc::new(ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine));
+        Arc::new(SlackApp::new(Arc::new(SlackClient::new("xoxb-test")), manager, "secret"))
+    }
+
+    fn signed(app: &SlackApp, uri: &str, body: &str) -> Request<Body> {
+        let now = chrono::Utc::now().timestamp();
+        Request::post(uri)
+            .header(TIMESTAMP_HEADER, now.to_string())
+            .header(SIGNATURE_HEADER, app.verifier().sign(now, body.as_bytes()))
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_url_verification_and_signature_check() {
+        let app = app();
+        let body = r#"{"type":"url_verification","challenge":"3eZbrw1aBm2rZgRNFdxV2595E9CY3gmdALWMmHkvFXO7tYXAYM8P"}"#;
+
+        let response = router(app.clone()).oneshot(signed(&app, "/events", body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["challenge"], "3eZbrw1aBm2rZgRNFdxV2595E9CY3gmdALWMmHkvFXO7tYXAYM8P");
+
+        let unsigned = Request::post("/events").body(Body::from(body)).unwrap();
+        let response = router(app).oneshot(unsigned).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_slash_command_usage() {
+        let app = app();
+        let body = "command=%2Fcopilot&text=help&user_id=U1&channel_id=C1&team_id=T1&response_url=https%3A%2F%2Fhooks.slack.com%2Fx";
+        let response = router(app.clone()).oneshot(signed(&app, "/commands", body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["response_type"], "ephemeral");
+        assert!(json["text"].as_str().unwrap().starts_with("Usage: `/copilot ask <question>`"));
+    }
+}
@@ -12,6 +12,7 @@ use crate::{
 };
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use copilot_core::{PromptLogPolicy, PromptLogging};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -384,6 +385,31 @@ impl TenantRepository for InMemoryTenantRepository {
     }
 }
 
+/// Tenants read per page by [`load_prompt_log_policy`]
+const TENANT_PAGE_SIZE: usize = 500;
+
+/// Prompt logging policy defaulting to `default`, with the mode of every
+/// stored tenant that sets its own
+pub async fn load_prompt_log_policy(
+    repository: &dyn TenantRepository,
+    default: PromptLogging,
+) -> Result<PromptLogPolicy> {
+    let mut policy = PromptLogPolicy::new(default);
+    let mut offset = 0;
+    loop {
+        let tenants = repository.list(TENANT_PAGE_SIZE, offset).await?;
+        for tenant in &tenants {
+            if let Some(mode) = tenant.config.prompt_logging {
+                policy = policy.with_tenant(tenant.id.clone(), mode);
+            }
+        }
+        if tenants.len() < TENANT_PAGE_SIZE {
+            return Ok(policy);
+        }
+        offset += tenants.len();
+    }
+}
+
 /// Onboarding service for automating tenant setup
 pub struct OnboardingService {
     repository: Arc<dyn TenantRepository>,
@@ -690,6 +716,20 @@ mod tests {
         assert_eq!(result.progress.completion_percentage(), 100.0);
     }
 
+    #[tokio::test]
+    async fn test_prompt_log_policy_from_tenants() {
+        let repository = InMemoryTenantRepository::new();
+        let mut acme = Tenant::new("Acme", "acme", "owner", TenantTier::Free);
+        acme.set_prompt_logging(PromptLogging::Off);
+        let globex = Tenant::new("Globex", "globex", "owner", TenantTier::Free);
+        repository.create(&acme).await.unwrap();
+        repository.create(&globex).await.unwrap();
+
+        let policy = load_prompt_log_policy(&repository, PromptLogging::Hashed).await.unwrap();
+        assert_eq!(policy.for_tenant(&acme.id), PromptLogging::Off);
+        assert_eq!(policy.for_tenant(&globex.id), PromptLogging::Hashed);
+    }
+
     #[tokio::test]
     async fn test_trial_registration() {
         let service = OnboardingService::in_memory();
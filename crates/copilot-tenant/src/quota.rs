@@ -3,7 +3,7 @@
 //! Provides resource quota enforcement and tracking.
 
 use crate::{Result, Tenant, TenantError, TenantTier};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -34,6 +34,10 @@ pub enum QuotaType {
     ContextItems,
     /// Number of webhooks
     Webhooks,
+    /// Document bytes ingested per day
+    IngestedBytes,
+    /// Number of concurrent ingestion jobs
+    ConcurrentIngestions,
 }
 
 impl QuotaType {
@@ -49,6 +53,8 @@ impl QuotaType {
             Self::ApiKeys => "api_keys",
             Self::ContextItems => "context_items",
             Self::Webhooks => "webhooks",
+            Self::IngestedBytes => "ingested_bytes",
+            Self::ConcurrentIngestions => "concurrent_ingestions",
         }
     }
 
@@ -56,6 +62,27 @@ impl QuotaType {
     pub fn is_periodic(&self) -> bool {
         matches!(self, Self::ApiCalls | Self::Tokens)
     }
+
+    /// Check if this quota resets at midnight UTC
+    pub fn is_daily(&self) -> bool {
+        matches!(self, Self::IngestedBytes)
+    }
+
+    /// Prefix of the response headers reporting this quota (`X-Quota-Api-Calls`)
+    pub fn header_prefix(&self) -> String {
+        let words: Vec<String> = self
+            .as_str()
+            .split('_')
+            .map(|word| {
+                let mut chars = word.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                    .unwrap_or_default()
+            })
+            .collect();
+        format!("X-Quota-{}", words.join("-"))
+    }
 }
 
 /// Quota limit configuration
@@ -118,6 +145,13 @@ impl QuotaUsage {
             0.0
         };
 
+        let (period_start, period_end) = if quota_type.is_daily() {
+            let start = Utc::now().date_naive().and_hms_opt(0, 0, 0).map(|start| start.and_utc());
+            (start, start.map(|start| start + chrono::Duration::days(1)))
+        } else {
+            (None, None)
+        };
+
         Self {
             quota_type,
             current,
@@ -125,14 +159,33 @@ impl QuotaUsage {
             percentage,
             exceeded: current > limit,
             warning: percentage >= 80.0,
-            period_start: None,
-            period_end: None,
+            period_start,
+            period_end,
         }
     }
 
     pub fn remaining(&self) -> u64 {
         self.limit.saturating_sub(self.current)
     }
+
+    /// Response headers reporting this usage: `<prefix>-Limit`,
+    /// `<prefix>-Remaining`, `<prefix>-Used` and, when the period end is
+    /// known, `<prefix>-Reset` in seconds
+    pub fn headers(&self) -> Vec<(String, String)> {
+        let prefix = self.quota_type.header_prefix();
+        let mut headers = vec![
+            (format!("{}-Limit", prefix), self.limit.to_string()),
+            (format!("{}-Remaining", prefix), self.remaining().to_string()),
+            (format!("{}-Used", prefix), self.current.to_string()),
+        ];
+
+        if let Some(period_end) = self.period_end {
+            let reset = (period_end - Utc::now()).num_seconds().max(0);
+            headers.push((format!("{}-Reset", prefix), reset.to_string()));
+        }
+
+        headers
+    }
 }
 
 /// Tenant quota configuration
@@ -155,6 +208,11 @@ impl TenantQuotas {
             QuotaType::ConcurrentWorkflows,
             QuotaLimit::new(tier.default_max_concurrent_workflows() as u64),
         );
+        limits.insert(QuotaType::IngestedBytes, QuotaLimit::new(tier.default_daily_ingestion_bytes()));
+        limits.insert(
+            QuotaType::ConcurrentIngestions,
+            QuotaLimit::new(tier.default_max_concurrent_ingestions() as u64),
+        );
 
         // Add other default limits based on tier
         let (workflows, conversations, api_keys, context_items, webhooks) = match tier {
@@ -185,12 +243,38 @@ impl TenantQuotas {
     }
 }
 
+/// Threshold a tenant's usage crossed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaAlertLevel {
+    /// The warning threshold was reached
+    Warning,
+    /// The limit was reached; hard limits reject further usage
+    Exceeded,
+}
+
+/// Usage crossing a quota threshold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaAlert {
+    pub tenant_id: String,
+    pub level: QuotaAlertLevel,
+    /// Threshold crossed, as a fraction of the limit
+    pub threshold: f64,
+    pub usage: QuotaUsage,
+}
+
+type AlertHandler = Box<dyn Fn(&QuotaAlert) + Send + Sync>;
+
 /// Quota manager for tracking and enforcing quotas
 pub struct QuotaManager {
     /// Current usage per tenant
     usage: Arc<RwLock<HashMap<String, HashMap<QuotaType, u64>>>>,
     /// Quota configurations per tenant
     quotas: Arc<RwLock<HashMap<String, TenantQuotas>>>,
+    /// Day each tenant's daily quotas are counted for
+    days: Arc<RwLock<HashMap<String, NaiveDate>>>,
+    /// Threshold crossing handlers
+    alert_handlers: Arc<RwLock<Vec<AlertHandler>>>,
 }
 
 impl Default for QuotaManager {
@@ -204,9 +288,20 @@ impl QuotaManager {
         Self {
             usage: Arc::new(RwLock::new(HashMap::new())),
             quotas: Arc::new(RwLock::new(HashMap::new())),
+            days: Arc::new(RwLock::new(HashMap::new())),
+            alert_handlers: Arc::new(RwLock::new(Vec::new())),
         }
     }
 
+    /// Call `handler` whenever an increment takes a tenant's usage across a
+    /// quota's warning threshold or limit
+    pub fn on_alert<F>(&self, handler: F)
+    where
+        F: Fn(&QuotaAlert) + Send + Sync + 'static,
+    {
+        self.alert_handlers.write().push(Box::new(handler));
+    }
+
     /// Register quotas for a tenant
     pub fn register_tenant(&self, tenant: &Tenant) {
         let quotas = TenantQuotas::for_tier(tenant.tier);
@@ -219,11 +314,33 @@ impl QuotaManager {
     pub fn remove_tenant(&self, tenant_id: &str) {
         self.quotas.write().remove(tenant_id);
         self.usage.write().remove(tenant_id);
+        self.days.write().remove(tenant_id);
         debug!(tenant_id = %tenant_id, "Removed tenant quotas");
     }
 
+    /// Reset daily quotas for a tenant if they were counted for an earlier
+    /// day than `today`
+    fn roll_daily(&self, tenant_id: &str, today: NaiveDate) {
+        if self.days.read().get(tenant_id) == Some(&today) {
+            return;
+        }
+        if !self.quotas.read().contains_key(tenant_id) {
+            return;
+        }
+        let mut days = self.days.write();
+        let day = days.entry(tenant_id.to_string()).or_insert(today);
+        if *day != today {
+            *day = today;
+            if let Some(tenant_usage) = self.usage.write().get_mut(tenant_id) {
+                tenant_usage.retain(|quota_type, _| !quota_type.is_daily());
+            }
+            debug!(tenant_id = %tenant_id, day = %today, "Reset daily quotas");
+        }
+    }
+
     /// Get current usage for a quota
     pub fn get_usage(&self, tenant_id: &str, quota_type: QuotaType) -> Option<QuotaUsage> {
+        self.roll_daily(tenant_id, Utc::now().date_naive());
         let usage = self.usage.read();
         let quotas = self.quotas.read();
 
@@ -243,6 +360,7 @@ impl QuotaManager {
 
     /// Get all usage for a tenant
     pub fn get_all_usage(&self, tenant_id: &str) -> HashMap<QuotaType, QuotaUsage> {
+        self.roll_daily(tenant_id, Utc::now().date_naive());
         let usage = self.usage.read();
         let quotas = self.quotas.read();
 
@@ -266,6 +384,9 @@ impl QuotaManager {
 
     /// Check if a quota would be exceeded
     pub fn check_quota(&self, tenant_id: &str, quota_type: QuotaType, amount: u64) -> Result<()> {
+        if quota_type.is_daily() {
+            self.roll_daily(tenant_id, Utc::now().date_naive());
+        }
         let usage = self.usage.read();
         let quotas = self.quotas.read();
 
@@ -314,20 +435,54 @@ impl QuotaManager {
     pub fn increment(&self, tenant_id: &str, quota_type: QuotaType, amount: u64) -> Result<u64> {
         self.check_quota(tenant_id, quota_type, amount)?;
 
-        let mut usage = self.usage.write();
-        let tenant_usage = usage.entry(tenant_id.to_string()).or_default();
-        let current = tenant_usage.entry(quota_type).or_insert(0);
-        *current += amount;
+        let new_total = {
+            let mut usage = self.usage.write();
+            let tenant_usage = usage.entry(tenant_id.to_string()).or_default();
+            let current = tenant_usage.entry(quota_type).or_insert(0);
+            *current += amount;
+            *current
+        };
 
         debug!(
             tenant_id = %tenant_id,
             quota_type = ?quota_type,
             amount = amount,
-            new_total = *current,
+            new_total = new_total,
             "Incremented quota usage"
         );
 
-        Ok(*current)
+        if let Some(alert) = self.crossed_threshold(tenant_id, quota_type, new_total - amount, new_total) {
+            for handler in self.alert_handlers.read().iter() {
+                handler(&alert);
+            }
+        }
+
+        Ok(new_total)
+    }
+
+    /// The highest threshold usage moving from `before` to `after` crossed
+    fn crossed_threshold(&self, tenant_id: &str, quota_type: QuotaType, before: u64, after: u64) -> Option<QuotaAlert> {
+        let quotas = self.quotas.read();
+        let limit = quotas.get(tenant_id)?.limits.get(&quota_type)?;
+        if limit.limit == 0 {
+            return None;
+        }
+
+        let fraction = |value: u64| value as f64 / limit.limit as f64;
+        let (level, threshold) = if fraction(before) < 1.0 && fraction(after) >= 1.0 {
+            (QuotaAlertLevel::Exceeded, 1.0)
+        } else if fraction(before) < limit.warning_threshold && fraction(after) >= limit.warning_threshold {
+            (QuotaAlertLevel::Warning, limit.warning_threshold)
+        } else {
+            return None;
+        };
+
+        Some(QuotaAlert {
+            tenant_id: tenant_id.to_string(),
+            level,
+            threshold,
+            usage: QuotaUsage::new(quota_type, after, limit.limit),
+        })
     }
 
     /// Decrement usage for a quota
@@ -410,6 +565,28 @@ mod tests {
         assert_eq!(usage.remaining(), 200);
     }
 
+    #[test]
+    fn test_quota_usage_headers() {
+        assert_eq!(QuotaType::ApiCalls.header_prefix(), "X-Quota-Api-Calls");
+        assert_eq!(QuotaType::Tokens.header_prefix(), "X-Quota-Tokens");
+
+        let mut usage = QuotaUsage::new(QuotaType::ApiCalls, 800, 1000);
+        let headers = usage.headers();
+        assert_eq!(
+            headers,
+            vec![
+                ("X-Quota-Api-Calls-Limit".to_string(), "1000".to_string()),
+                ("X-Quota-Api-Calls-Remaining".to_string(), "200".to_string()),
+                ("X-Quota-Api-Calls-Used".to_string(), "800".to_string()),
+            ]
+        );
+
+        usage.period_end = Some(Utc::now() + chrono::Duration::hours(1));
+        let reset = usage.headers().pop().unwrap();
+        assert_eq!(reset.0, "X-Quota-Api-Calls-Reset");
+        assert!(reset.1.parse::<i64>().unwrap() > 3500);
+    }
+
     #[test]
     fn test_quota_exceeded() {
         let usage = QuotaUsage::new(QuotaType::ApiCalls, 1200, 1000);
@@ -488,6 +665,31 @@ mod tests {
         assert_eq!(manager.get_usage(&tenant.id, QuotaType::Users).unwrap().current, 5);
     }
 
+    #[test]
+    fn test_daily_quotas_reset_each_day() {
+        let manager = QuotaManager::new();
+        let tenant = Tenant::new("Test", "test", "owner", TenantTier::Free);
+
+        manager.register_tenant(&tenant);
+        manager.set_custom_limit(&tenant.id, QuotaType::IngestedBytes, 1000);
+        manager.increment(&tenant.id, QuotaType::IngestedBytes, 900).unwrap();
+        manager.increment(&tenant.id, QuotaType::Storage, 900).unwrap();
+        assert!(matches!(
+            manager.increment(&tenant.id, QuotaType::IngestedBytes, 200),
+            Err(TenantError::QuotaExceeded(_))
+        ));
+
+        let usage = manager.get_usage(&tenant.id, QuotaType::IngestedBytes).unwrap();
+        assert_eq!(usage.current, 900);
+        assert_eq!(usage.period_end.unwrap() - usage.period_start.unwrap(), chrono::Duration::days(1));
+
+        // The next day the daily budget is back; other quotas are kept
+        let tomorrow = Utc::now().date_naive() + chrono::Duration::days(1);
+        manager.roll_daily(&tenant.id, tomorrow);
+        assert_eq!(manager.usage.read()[&tenant.id].get(&QuotaType::IngestedBytes), None);
+        assert_eq!(manager.usage.read()[&tenant.id][&QuotaType::Storage], 900);
+    }
+
     #[test]
     fn test_get_all_usage() {
         let manager = QuotaManager::new();
@@ -503,4 +705,32 @@ mod tests {
         assert!(all_usage.contains_key(&QuotaType::Users));
         assert!(all_usage.contains_key(&QuotaType::Storage));
     }
+
+    #[test]
+    fn test_alerts_on_threshold_crossings() {
+        let manager = QuotaManager::new();
+        let tenant = create_test_tenant();
+        manager.register_tenant(&tenant);
+        manager.set_custom_limit(&tenant.id, QuotaType::ApiCalls, 100);
+
+        let alerts = Arc::new(RwLock::new(Vec::new()));
+        let seen = alerts.clone();
+        manager.on_alert(move |alert| seen.write().push(alert.clone()));
+
+        manager.increment(&tenant.id, QuotaType::ApiCalls, 70).unwrap();
+        assert!(alerts.read().is_empty());
+
+        manager.increment(&tenant.id, QuotaType::ApiCalls, 15).unwrap();
+        manager.increment(&tenant.id, QuotaType::ApiCalls, 5).unwrap();
+        manager.increment(&tenant.id, QuotaType::ApiCalls, 10).unwrap();
+        assert!(manager.increment(&tenant.id, QuotaType::ApiCalls, 1).is_err());
+
+        let alerts = alerts.read();
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].level, QuotaAlertLevel::Warning);
+        assert_eq!(alerts[0].threshold, 0.8);
+        assert_eq!(alerts[0].usage.current, 85);
+        assert_eq!(alerts[1].level, QuotaAlertLevel::Exceeded);
+        assert_eq!(alerts[1].usage.current, 100);
+    }
 }
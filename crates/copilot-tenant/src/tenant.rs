@@ -3,6 +3,7 @@
 //! Provides tenant data models and management functionality.
 
 use chrono::{DateTime, Utc};
+use copilot_core::{PromptLogging, TenantRegion};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -143,6 +144,30 @@ impl TenantTier {
             Self::Custom => u32::MAX,
         }
     }
+
+    /// Get default daily limit of ingested document bytes
+    pub fn default_daily_ingestion_bytes(&self) -> u64 {
+        match self {
+            Self::Free => 50 * 1024 * 1024,               // 50 MB
+            Self::Starter => 500 * 1024 * 1024,           // 500 MB
+            Self::Professional => 5 * 1024 * 1024 * 1024, // 5 GB
+            Self::Business => 50 * 1024 * 1024 * 1024,    // 50 GB
+            Self::Enterprise => 500 * 1024 * 1024 * 1024, // 500 GB
+            Self::Custom => u64::MAX,
+        }
+    }
+
+    /// Get default max concurrent ingestion jobs
+    pub fn default_max_concurrent_ingestions(&self) -> u32 {
+        match self {
+            Self::Free => 1,
+            Self::Starter => 2,
+            Self::Professional => 4,
+            Self::Business => 8,
+            Self::Enterprise => 16,
+            Self::Custom => u32::MAX,
+        }
+    }
 }
 
 /// Tenant configuration
@@ -162,6 +187,13 @@ pub struct TenantConfig {
     pub webhook_url: Option<String>,
     /// Webhook secret for signing
     pub webhook_secret: Option<String>,
+    /// How prompts and responses are retained in logs, analytics and audit
+    /// records; the deployment's default when unset
+    #[serde(default)]
+    pub prompt_logging: Option<PromptLogging>,
+    /// Home region of the tenant's data; the deployment's default region when unset
+    #[serde(default)]
+    pub residency: Option<TenantRegion>,
 }
 
 impl Default for TenantConfig {
@@ -174,6 +206,8 @@ impl Default for TenantConfig {
             settings: HashMap::new(),
             webhook_url: None,
             webhook_secret: None,
+            prompt_logging: None,
+            residency: None,
         }
     }
 }
@@ -335,6 +369,18 @@ impl Tenant {
         self.config.features.insert(feature.to_string(), false);
         self.updated_at = Utc::now();
     }
+
+    /// Set how prompts and responses are retained
+    pub fn set_prompt_logging(&mut self, mode: PromptLogging) {
+        self.config.prompt_logging = Some(mode);
+        self.updated_at = Utc::now();
+    }
+
+    /// Pin the tenant's data to a region
+    pub fn set_residency(&mut self, residency: Option<TenantRegion>) {
+        self.config.residency = residency;
+        self.updated_at = Utc::now();
+    }
 }
 
 /// Tenant member/user association
@@ -507,6 +553,36 @@ mod tests {
         assert!(!tenant.has_feature("advanced_analytics"));
     }
 
+    #[test]
+    fn test_prompt_logging_config() {
+        let mut tenant = Tenant::new("Test", "test", "owner", TenantTier::Free);
+        assert_eq!(tenant.config.prompt_logging, None);
+
+        tenant.set_prompt_logging(PromptLogging::Hashed);
+        assert_eq!(tenant.config.prompt_logging, Some(PromptLogging::Hashed));
+
+        // Configs stored before the setting existed follow the deployment
+        let mut stored = serde_json::to_value(&tenant.config).unwrap();
+        stored.as_object_mut().unwrap().remove("prompt_logging");
+        let config: TenantConfig = serde_json::from_value(stored).unwrap();
+        assert_eq!(config.prompt_logging, None);
+    }
+
+    #[test]
+    fn test_residency_config() {
+        let mut tenant = Tenant::new("Test", "test", "owner", TenantTier::Enterprise);
+        assert!(tenant.config.residency.is_none());
+
+        tenant.set_residency(Some("eu:strict".parse().unwrap()));
+        let stored = serde_json::to_value(&tenant.config).unwrap();
+        assert_eq!(stored["residency"], serde_json::json!({"region": "eu", "mode": "strict"}));
+
+        let mut stored = stored;
+        stored.as_object_mut().unwrap().remove("residency");
+        let config: TenantConfig = serde_json::from_value(stored).unwrap();
+        assert!(config.residency.is_none());
+    }
+
     #[test]
     fn test_tenant_role_permissions() {
         assert!(TenantRole::Owner.can_manage_users());
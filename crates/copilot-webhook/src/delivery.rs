@@ -578,6 +578,150 @@ impl DeliveryReportGenerator {
     }
 }
 
+/// Live delivery statistics of one endpoint
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EndpointDeliveryStats {
+    /// Endpoint ID
+    pub endpoint_id: String,
+    /// Completed deliveries
+    pub total: u64,
+    /// Successful deliveries
+    pub delivered: u64,
+    /// Failed deliveries
+    pub failed: u64,
+    /// Success rate (0.0 - 1.0)
+    pub success_rate: f64,
+    /// Average response time of the final attempts in ms
+    pub avg_latency_ms: f64,
+    /// P95 response time of the final attempts in ms
+    pub p95_latency_ms: f64,
+    /// When the last delivery completed
+    pub last_delivery_at: Option<DateTime<Utc>>,
+    /// HTTP status of the last delivery's final attempt
+    pub last_status_code: Option<u16>,
+    /// Error of the last failed delivery
+    pub last_error: Option<String>,
+    /// Event type breakdown
+    pub by_event_type: Vec<EventTypeStats>,
+}
+
+#[derive(Default)]
+struct EndpointSamples {
+    delivered: u64,
+    failed: u64,
+    latencies: VecDeque<u64>,
+    by_event_type: std::collections::HashMap<WebhookEventType, (u64, u64)>,
+    last_delivery_at: Option<DateTime<Utc>>,
+    last_status_code: Option<u16>,
+    last_error: Option<String>,
+}
+
+/// Per-endpoint success and latency statistics of completed deliveries
+pub struct EndpointStatsRecorder {
+    endpoints: DashMap<String, EndpointSamples>,
+    max_latency_samples: usize,
+}
+
+impl EndpointStatsRecorder {
+    pub fn new(max_latency_samples: usize) -> Self {
+        Self {
+            endpoints: DashMap::new(),
+            max_latency_samples,
+        }
+    }
+
+    /// Record a delivery; ones still pending or retrying are ignored
+    pub fn record(&self, delivery: &WebhookDelivery) {
+        let delivered = match delivery.status {
+            DeliveryStatus::Delivered => true,
+            DeliveryStatus::Failed => false,
+            _ => return,
+        };
+
+        let mut samples = self.endpoints.entry(delivery.endpoint_id.clone()).or_default();
+        if delivered {
+            samples.delivered += 1;
+        } else {
+            samples.failed += 1;
+        }
+        let by_type = samples.by_event_type.entry(delivery.event_type).or_insert((0, 0));
+        by_type.0 += 1;
+        if delivered {
+            by_type.1 += 1;
+        }
+
+        let last_attempt = delivery.last_attempt();
+        if let Some(attempt) = last_attempt {
+            samples.latencies.push_back(attempt.duration_ms);
+            if samples.latencies.len() > self.max_latency_samples {
+                samples.latencies.pop_front();
+            }
+        }
+        samples.last_delivery_at = delivery.completed_at.or(Some(Utc::now()));
+        samples.last_status_code = last_attempt.and_then(|a| a.status_code);
+        if !delivered {
+            samples.last_error = last_attempt.and_then(|a| {
+                a.error_message
+                    .clone()
+                    .or_else(|| a.status_code.map(|code| format!("HTTP {}", code)))
+            });
+        }
+    }
+
+    /// Current statistics of an endpoint, zeroed if it has no completed deliveries
+    pub fn stats(&self, endpoint_id: &str) -> EndpointDeliveryStats {
+        let mut stats = EndpointDeliveryStats {
+            endpoint_id: endpoint_id.to_string(),
+            ..Default::default()
+        };
+        let Some(samples) = self.endpoints.get(endpoint_id) else {
+            return stats;
+        };
+
+        stats.delivered = samples.delivered;
+        stats.failed = samples.failed;
+        stats.total = samples.delivered + samples.failed;
+        if stats.total > 0 {
+            stats.success_rate = stats.delivered as f64 / stats.total as f64;
+        }
+        if !samples.latencies.is_empty() {
+            stats.avg_latency_ms =
+                samples.latencies.iter().sum::<u64>() as f64 / samples.latencies.len() as f64;
+            let mut sorted: Vec<_> = samples.latencies.iter().copied().collect();
+            sorted.sort();
+            let p95_idx = (sorted.len() as f64 * 0.95) as usize;
+            stats.p95_latency_ms = sorted.get(p95_idx.min(sorted.len() - 1)).copied().unwrap_or(0) as f64;
+        }
+        stats.last_delivery_at = samples.last_delivery_at;
+        stats.last_status_code = samples.last_status_code;
+        stats.last_error = samples.last_error.clone();
+
+        let mut by_event_type: Vec<_> = samples
+            .by_event_type
+            .iter()
+            .map(|(event_type, (count, success))| EventTypeStats {
+                event_type: *event_type,
+                count: *count,
+                success_rate: *success as f64 / *count as f64,
+            })
+            .collect();
+        by_event_type.sort_by(|a, b| b.count.cmp(&a.count).then(a.event_type.as_str().cmp(b.event_type.as_str())));
+        stats.by_event_type = by_event_type;
+        stats
+    }
+
+    /// Forget an endpoint's statistics
+    pub fn remove(&self, endpoint_id: &str) {
+        self.endpoints.remove(endpoint_id);
+    }
+}
+
+impl Default for EndpointStatsRecorder {
+    fn default() -> Self {
+        Self::new(1000)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -642,6 +786,29 @@ mod tests {
         assert!((stats.success_rate - 0.5).abs() < 0.001);
     }
 
+    #[test]
+    fn test_endpoint_stats() {
+        let recorder = EndpointStatsRecorder::default();
+        recorder.record(&create_test_delivery("d1", "ep1", DeliveryStatus::Delivered));
+        recorder.record(&create_test_delivery("d2", "ep1", DeliveryStatus::Delivered));
+        let mut failed = create_test_delivery("d3", "ep1", DeliveryStatus::Failed);
+        failed.event_type = WebhookEventType::MessageCreated;
+        failed.attempts[0].status_code = Some(500);
+        recorder.record(&failed);
+        recorder.record(&create_test_delivery("d4", "ep1", DeliveryStatus::Retrying));
+
+        let stats = recorder.stats("ep1");
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.failed, 1);
+        assert!((stats.success_rate - 2.0 / 3.0).abs() < 0.001);
+        assert_eq!(stats.avg_latency_ms, 100.0);
+        assert_eq!(stats.last_error.as_deref(), Some("HTTP 500"));
+        assert_eq!(stats.by_event_type[0].event_type, WebhookEventType::ConversationCreated);
+        assert_eq!(stats.by_event_type[1].success_rate, 0.0);
+
+        assert_eq!(recorder.stats("ep2").total, 0);
+    }
+
     #[test]
     fn test_delivery_metrics() {
         let mut delivery = create_test_delivery("d1", "ep1", DeliveryStatus::Delivered);
@@ -3,12 +3,13 @@
 //! Defines all webhook event types and payloads.
 
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
 /// Webhook event types
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum WebhookEventType {
     // Conversation events
@@ -17,12 +18,25 @@ pub enum WebhookEventType {
     ConversationDeleted,
     MessageCreated,
     MessageUpdated,
+    ConversationHandoff,
 
     // Workflow events
     WorkflowStarted,
     WorkflowCompleted,
     WorkflowFailed,
     WorkflowStepCompleted,
+    WorkflowStepFailed,
+    WorkflowApprovalRequested,
+
+    // Sandbox events
+    SandboxCreated,
+    SandboxFailed,
+
+    // Long-running task events
+    IngestionCompleted,
+    IngestionFailed,
+    AgentTaskCompleted,
+    AgentTaskFailed,
 
     // User events
     UserCreated,
@@ -58,6 +72,8 @@ pub enum WebhookEventType {
     SystemAlert,
     QuotaWarning,
     QuotaExceeded,
+    SlaBreached,
+    Ping,
 
     // Custom events
     Custom,
@@ -71,10 +87,19 @@ impl WebhookEventType {
             Self::ConversationDeleted => "conversation.deleted",
             Self::MessageCreated => "message.created",
             Self::MessageUpdated => "message.updated",
+            Self::ConversationHandoff => "conversation.handoff",
             Self::WorkflowStarted => "workflow.started",
             Self::WorkflowCompleted => "workflow.completed",
             Self::WorkflowFailed => "workflow.failed",
             Self::WorkflowStepCompleted => "workflow.step.completed",
+            Self::WorkflowStepFailed => "workflow.step.failed",
+            Self::WorkflowApprovalRequested => "workflow.approval.requested",
+            Self::SandboxCreated => "sandbox.created",
+            Self::SandboxFailed => "sandbox.failed",
+            Self::IngestionCompleted => "ingestion.completed",
+            Self::IngestionFailed => "ingestion.failed",
+            Self::AgentTaskCompleted => "agent_task.completed",
+            Self::AgentTaskFailed => "agent_task.failed",
             Self::UserCreated => "user.created",
             Self::UserUpdated => "user.updated",
             Self::UserDeleted => "user.deleted",
@@ -98,6 +123,8 @@ impl WebhookEventType {
             Self::SystemAlert => "system.alert",
             Self::QuotaWarning => "quota.warning",
             Self::QuotaExceeded => "quota.exceeded",
+            Self::SlaBreached => "sla.breached",
+            Self::Ping => "webhook.ping",
             Self::Custom => "custom",
         }
     }
@@ -109,11 +136,19 @@ impl WebhookEventType {
             | Self::ConversationUpdated
             | Self::ConversationDeleted
             | Self::MessageCreated
-            | Self::MessageUpdated => "conversation",
+            | Self::MessageUpdated
+            | Self::ConversationHandoff => "conversation",
             Self::WorkflowStarted
             | Self::WorkflowCompleted
             | Self::WorkflowFailed
-            | Self::WorkflowStepCompleted => "workflow",
+            | Self::WorkflowStepCompleted
+            | Self::WorkflowStepFailed
+            | Self::WorkflowApprovalRequested => "workflow",
+            Self::SandboxCreated | Self::SandboxFailed => "sandbox",
+            Self::IngestionCompleted
+            | Self::IngestionFailed
+            | Self::AgentTaskCompleted
+            | Self::AgentTaskFailed => "task",
             Self::UserCreated
             | Self::UserUpdated
             | Self::UserDeleted
@@ -133,14 +168,18 @@ impl WebhookEventType {
             | Self::InvoiceCreated
             | Self::InvoicePaid
             | Self::PaymentFailed => "billing",
-            Self::SystemAlert | Self::QuotaWarning | Self::QuotaExceeded => "system",
+            Self::SystemAlert
+            | Self::QuotaWarning
+            | Self::QuotaExceeded
+            | Self::SlaBreached
+            | Self::Ping => "system",
             Self::Custom => "custom",
         }
     }
 }
 
 /// Webhook event payload
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WebhookEvent {
     /// Event ID
     pub id: String,
@@ -159,6 +198,9 @@ pub struct WebhookEvent {
     pub previous_data: Option<serde_json::Value>,
     /// Request ID that triggered this event
     pub request_id: Option<String>,
+    /// Payload schema version of `data`, set when the event is delivered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
 }
 
 impl WebhookEvent {
@@ -173,6 +215,7 @@ impl WebhookEvent {
             data,
             previous_data: None,
             request_id: None,
+            schema_version: None,
         }
     }
 
@@ -198,15 +241,23 @@ impl WebhookEvent {
 }
 
 /// Webhook event data wrapper
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "object")]
 pub enum WebhookEventData {
     #[serde(rename = "conversation")]
     Conversation(ConversationEventData),
     #[serde(rename = "message")]
     Message(MessageEventData),
+    #[serde(rename = "handoff")]
+    Handoff(HandoffEventData),
     #[serde(rename = "workflow")]
     Workflow(WorkflowEventData),
+    #[serde(rename = "approval")]
+    Approval(ApprovalEventData),
+    #[serde(rename = "sandbox")]
+    Sandbox(SandboxEventData),
+    #[serde(rename = "task")]
+    Task(TaskEventData),
     #[serde(rename = "user")]
     User(UserEventData),
     #[serde(rename = "tenant")]
@@ -219,14 +270,43 @@ pub enum WebhookEventData {
     Subscription(SubscriptionEventData),
     #[serde(rename = "invoice")]
     Invoice(InvoiceEventData),
+    #[serde(rename = "quota")]
+    Quota(QuotaEventData),
+    #[serde(rename = "sla")]
+    Sla(SlaEventData),
     #[serde(rename = "system")]
     System(SystemEventData),
     #[serde(rename = "custom")]
     Custom(CustomEventData),
 }
 
+impl WebhookEventData {
+    /// The `object` tag of the payload, which names its schema
+    pub fn object(&self) -> &'static str {
+        match self {
+            Self::Conversation(_) => "conversation",
+            Self::Message(_) => "message",
+            Self::Handoff(_) => "handoff",
+            Self::Workflow(_) => "workflow",
+            Self::Approval(_) => "approval",
+            Self::Sandbox(_) => "sandbox",
+            Self::Task(_) => "task",
+            Self::User(_) => "user",
+            Self::Tenant(_) => "tenant",
+            Self::ApiKey(_) => "api_key",
+            Self::Context(_) => "context",
+            Self::Subscription(_) => "subscription",
+            Self::Invoice(_) => "invoice",
+            Self::Quota(_) => "quota",
+            Self::Sla(_) => "sla",
+            Self::System(_) => "system",
+            Self::Custom(_) => "custom",
+        }
+    }
+}
+
 /// Conversation event data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ConversationEventData {
     pub id: String,
     pub user_id: String,
@@ -237,7 +317,7 @@ pub struct ConversationEventData {
 }
 
 /// Message event data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MessageEventData {
     pub id: String,
     pub conversation_id: String,
@@ -247,8 +327,23 @@ pub struct MessageEventData {
     pub created_at: DateTime<Utc>,
 }
 
+/// Conversation handed off to humans
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HandoffEventData {
+    pub conversation_id: String,
+    pub title: String,
+    pub summary: String,
+    /// Markdown handoff document, ready to post or attach
+    pub document: String,
+    /// Findings, citations, commands and open questions
+    pub bundle: serde_json::Value,
+    /// User who requested the handoff
+    pub requested_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
 /// Workflow event data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WorkflowEventData {
     pub id: String,
     pub workflow_id: String,
@@ -261,8 +356,48 @@ pub struct WorkflowEventData {
     pub output: Option<serde_json::Value>,
 }
 
+/// Approval requested by a workflow step
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ApprovalEventData {
+    pub id: String,
+    pub workflow_id: String,
+    pub step_id: String,
+    pub title: String,
+    pub description: String,
+    pub requester: String,
+    /// Approver group deciding, if the request names one
+    pub group: Option<String>,
+    pub timeout_secs: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Sandbox event data
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SandboxEventData {
+    /// Sandbox ID, absent when creation failed before one was assigned
+    pub id: Option<String>,
+    pub template: String,
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Long-running task event data (ingestion jobs, agent tasks)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TaskEventData {
+    pub id: String,
+    pub kind: String,
+    pub name: String,
+    pub status: String,
+    pub user_id: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: DateTime<Utc>,
+    pub error: Option<String>,
+    pub details: Option<serde_json::Value>,
+}
+
 /// User event data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct UserEventData {
     pub id: String,
     pub username: String,
@@ -272,7 +407,7 @@ pub struct UserEventData {
 }
 
 /// Tenant event data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TenantEventData {
     pub id: String,
     pub name: String,
@@ -284,7 +419,7 @@ pub struct TenantEventData {
 }
 
 /// API key event data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ApiKeyEventData {
     pub id: String,
     pub name: String,
@@ -295,7 +430,7 @@ pub struct ApiKeyEventData {
 }
 
 /// Context event data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ContextEventData {
     pub id: String,
     pub name: String,
@@ -305,7 +440,7 @@ pub struct ContextEventData {
 }
 
 /// Subscription event data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SubscriptionEventData {
     pub id: String,
     pub tenant_id: String,
@@ -316,7 +451,7 @@ pub struct SubscriptionEventData {
 }
 
 /// Invoice event data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct InvoiceEventData {
     pub id: String,
     pub tenant_id: String,
@@ -328,8 +463,32 @@ pub struct InvoiceEventData {
     pub period_end: DateTime<Utc>,
 }
 
+/// Quota threshold crossed by a tenant
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QuotaEventData {
+    pub tenant_id: String,
+    pub quota_type: String,
+    pub current: u64,
+    pub limit: u64,
+    pub percentage: f64,
+    /// Warning threshold crossed, as a fraction of the limit
+    pub threshold: f64,
+}
+
+/// SLA target breached
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SlaEventData {
+    pub metric: String,
+    pub target: f64,
+    pub current: f64,
+    pub status: String,
+    pub message: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+}
+
 /// System event data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SystemEventData {
     pub alert_type: String,
     pub severity: String,
@@ -338,7 +497,7 @@ pub struct SystemEventData {
 }
 
 /// Custom event data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CustomEventData {
     pub event_name: String,
     pub data: serde_json::Value,
@@ -352,6 +511,8 @@ mod tests {
     fn test_event_type_string() {
         assert_eq!(WebhookEventType::ConversationCreated.as_str(), "conversation.created");
         assert_eq!(WebhookEventType::WorkflowCompleted.as_str(), "workflow.completed");
+        assert_eq!(WebhookEventType::WorkflowApprovalRequested.as_str(), "workflow.approval.requested");
+        assert_eq!(WebhookEventType::SandboxFailed.as_str(), "sandbox.failed");
     }
 
     #[test]
@@ -359,6 +520,9 @@ mod tests {
         assert_eq!(WebhookEventType::ConversationCreated.category(), "conversation");
         assert_eq!(WebhookEventType::WorkflowStarted.category(), "workflow");
         assert_eq!(WebhookEventType::UserCreated.category(), "user");
+        assert_eq!(WebhookEventType::WorkflowStepFailed.category(), "workflow");
+        assert_eq!(WebhookEventType::SandboxCreated.category(), "sandbox");
+        assert_eq!(WebhookEventType::SlaBreached.category(), "system");
     }
 
     #[test]
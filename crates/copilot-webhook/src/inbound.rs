@@ -1,6 +1,15 @@
 //! Inbound webhook handling
 //!
 //! Provides handlers for receiving webhooks from external services.
+//!
+//! Each endpoint guards the trigger pipeline behind it: requests from
+//! addresses outside its [`allowed_ips`](InboundWebhookConfig::allowed_ips)
+//! are refused with 403, requests beyond its
+//! [`rate_limit`](InboundWebhookConfig::rate_limit) with 429 and a
+//! `Retry-After` header, and payloads larger than
+//! [`max_payload_bytes`](InboundWebhookConfig::max_payload_bytes) with 413,
+//! before anything is read past the limit. Rejections are counted per source
+//! and exported by [`InboundWebhookState::render_prometheus`].
 
 use crate::{
     signature::WebhookVerifier,
@@ -8,17 +17,23 @@ use crate::{
 };
 use async_trait::async_trait;
 use axum::{
-    body::Bytes,
-    extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    body::{Body, Bytes},
+    extract::{ConnectInfo, Path, State},
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
     routing::post,
     Router,
 };
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
@@ -42,6 +57,32 @@ pub struct InboundWebhookConfig {
     pub tenant_id: Option<String>,
     /// Creation timestamp
     pub created_at: DateTime<Utc>,
+    /// Largest payload accepted, in bytes
+    #[serde(default = "default_max_payload_bytes")]
+    pub max_payload_bytes: usize,
+    /// Most requests accepted per window; unlimited if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<InboundRateLimit>,
+    /// Addresses or CIDR ranges (e.g. "192.30.252.0/22") requests may come
+    /// from; any address if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_ips: Vec<String>,
+}
+
+/// Default cap on inbound payloads (1 MiB)
+pub const DEFAULT_MAX_PAYLOAD_BYTES: usize = 1024 * 1024;
+
+fn default_max_payload_bytes() -> usize {
+    DEFAULT_MAX_PAYLOAD_BYTES
+}
+
+/// Requests an inbound endpoint accepts per sliding window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InboundRateLimit {
+    /// Maximum requests per window
+    pub max_requests: u32,
+    /// Time window in seconds
+    pub window_seconds: u64,
 }
 
 impl InboundWebhookConfig {
@@ -56,6 +97,9 @@ impl InboundWebhookConfig {
             enabled: true,
             tenant_id: None,
             created_at: Utc::now(),
+            max_payload_bytes: DEFAULT_MAX_PAYLOAD_BYTES,
+            rate_limit: None,
+            allowed_ips: Vec::new(),
         }
     }
 
@@ -68,6 +112,59 @@ impl InboundWebhookConfig {
         self.tenant_id = Some(tenant_id.to_string());
         self
     }
+
+    pub fn with_max_payload_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_payload_bytes = max_bytes;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, max_requests: u32, window_seconds: u64) -> Self {
+        self.rate_limit = Some(InboundRateLimit {
+            max_requests,
+            window_seconds,
+        });
+        self
+    }
+
+    pub fn with_allowed_ips(mut self, allowed: &[&str]) -> Self {
+        self.allowed_ips = allowed.iter().map(|entry| entry.to_string()).collect();
+        self
+    }
+
+    /// Whether requests from `ip` are accepted; entries that are not valid
+    /// addresses or CIDR ranges match nothing
+    pub fn allows_ip(&self, ip: IpAddr) -> bool {
+        self.allowed_ips.is_empty() || self.allowed_ips.iter().any(|entry| ip_in_range(entry, ip))
+    }
+}
+
+/// Whether `ip` is the address `entry` or inside the CIDR range `entry`
+fn ip_in_range(entry: &str, ip: IpAddr) -> bool {
+    let (addr, prefix) = match entry.trim().split_once('/') {
+        Some((addr, prefix)) => match prefix.parse::<u32>() {
+            Ok(prefix) => (addr, Some(prefix)),
+            Err(_) => return false,
+        },
+        None => (entry.trim(), None),
+    };
+    let Ok(network) = addr.parse::<IpAddr>() else {
+        return false;
+    };
+    // IPv4 clients of a dual-stack listener show up as mapped IPv6 addresses
+    let ip = match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    };
+    let (network, ip, width) = match (network, ip) {
+        (IpAddr::V4(network), IpAddr::V4(ip)) => (u32::from(network) as u128, u32::from(ip) as u128, 32),
+        (IpAddr::V6(network), IpAddr::V6(ip)) => (u128::from(network), u128::from(ip), 128),
+        _ => return false,
+    };
+    match prefix.unwrap_or(width) {
+        0 => true,
+        bits if bits > width => false,
+        bits => network >> (width - bits) == ip >> (width - bits),
+    }
 }
 
 /// Received webhook payload
@@ -151,11 +248,38 @@ impl Default for WebhookHandlerRegistry {
     }
 }
 
+/// Requests to one source's endpoints, by outcome
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InboundWebhookStats {
+    pub source: String,
+    /// Requests passed on to handlers
+    pub accepted: u64,
+    /// Requests refused with 429
+    pub rate_limited: u64,
+    /// Requests refused with 413
+    pub payload_too_large: u64,
+    /// Requests refused because their address is not allowed
+    pub ip_rejected: u64,
+}
+
+#[derive(Debug, Default)]
+struct SourceCounters {
+    accepted: AtomicU64,
+    rate_limited: AtomicU64,
+    payload_too_large: AtomicU64,
+    ip_rejected: AtomicU64,
+}
+
 /// Shared state for webhook routes
 pub struct InboundWebhookState {
     configs: DashMap<String, InboundWebhookConfig>,
     handlers: Arc<WebhookHandlerRegistry>,
     event_sender: mpsc::Sender<InboundWebhook>,
+    /// Recent requests per endpoint, for rate limiting
+    requests: DashMap<String, VecDeque<Instant>>,
+    counters: DashMap<String, SourceCounters>,
+    /// Proxies in front of the server that append to `X-Forwarded-For`
+    trusted_proxies: usize,
 }
 
 impl InboundWebhookState {
@@ -167,9 +291,23 @@ impl InboundWebhookState {
             configs: DashMap::new(),
             handlers,
             event_sender,
+            requests: DashMap::new(),
+            counters: DashMap::new(),
+            trusted_proxies: 0,
         }
     }
 
+    /// Take the client address from `X-Forwarded-For` rather than the
+    /// connection, behind `hops` proxies that each append the address they
+    /// received the request from
+    ///
+    /// The client is the `hops`-th entry from the right; entries left of it
+    /// were sent by the client itself and are ignored.
+    pub fn with_trusted_proxies(mut self, hops: usize) -> Self {
+        self.trusted_proxies = hops;
+        self
+    }
+
     /// Register a webhook configuration
     pub fn register_config(&self, config: InboundWebhookConfig) {
         info!(
@@ -188,27 +326,122 @@ impl InboundWebhookState {
     /// Remove a config
     pub fn remove_config(&self, id: &str) {
         self.configs.remove(id);
+        self.requests.remove(id);
     }
 
     /// List all configs
     pub fn list_configs(&self) -> Vec<InboundWebhookConfig> {
         self.configs.iter().map(|c| c.clone()).collect()
     }
+
+    /// Address a request came from
+    fn client_ip(&self, peer: Option<SocketAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        if self.trusted_proxies == 0 {
+            return peer.map(|addr| addr.ip());
+        }
+        let forwarded: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect();
+        // Fewer entries than proxies: the header was not set by them
+        let client = forwarded.len().checked_sub(self.trusted_proxies)?;
+        forwarded[client].trim().parse().ok()
+    }
+
+    /// Admit a request to endpoint `config_id`, or how long until it would
+    /// be admitted
+    fn admit(&self, config_id: &str, limit: &InboundRateLimit) -> std::result::Result<(), Duration> {
+        let window = Duration::from_secs(limit.window_seconds);
+        let now = Instant::now();
+        let mut requests = self.requests.entry(config_id.to_string()).or_default();
+        while requests.front().is_some_and(|at| now.duration_since(*at) >= window) {
+            requests.pop_front();
+        }
+        if requests.len() >= limit.max_requests as usize {
+            let oldest = requests.front().copied().unwrap_or(now);
+            return Err(window.saturating_sub(now.duration_since(oldest)));
+        }
+        requests.push_back(now);
+        Ok(())
+    }
+
+    fn count(&self, source: &str, counter: fn(&SourceCounters) -> &AtomicU64) {
+        let counters = self.counters.entry(source.to_string()).or_default();
+        counter(&counters).fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Request outcomes per source, ordered by source
+    pub fn stats(&self) -> Vec<InboundWebhookStats> {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let mut stats: Vec<InboundWebhookStats> = self
+            .counters
+            .iter()
+            .map(|entry| InboundWebhookStats {
+                source: entry.key().clone(),
+                accepted: load(&entry.accepted),
+                rate_limited: load(&entry.rate_limited),
+                payload_too_large: load(&entry.payload_too_large),
+                ip_rejected: load(&entry.ip_rejected),
+            })
+            .collect();
+        stats.sort_by(|a, b| a.source.cmp(&b.source));
+        stats
+    }
+
+    /// Render the counters in the Prometheus text exposition format
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let stats = self.stats();
+        let mut out = String::new();
+        for (i, (name, help)) in [
+            ("accepted_total", "Inbound webhooks passed on to handlers"),
+            ("rate_limited_total", "Inbound webhooks refused for exceeding their rate limit"),
+            ("payload_too_large_total", "Inbound webhooks refused for exceeding their payload size limit"),
+            ("ip_rejected_total", "Inbound webhooks refused because their address is not allowed"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let _ = writeln!(out, "# HELP {prefix}_inbound_webhook_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_inbound_webhook_{name} counter");
+            for source in &stats {
+                let value = [source.accepted, source.rate_limited, source.payload_too_large, source.ip_rejected][i];
+                let _ = writeln!(out, "{prefix}_inbound_webhook_{name}{{source=\"{}\"}} {value}", source.source);
+            }
+        }
+        out
+    }
 }
 
 /// Create Axum router for inbound webhooks
+///
+/// IP allowlists need the peer address, so serve the router with
+/// `into_make_service_with_connect_info::<SocketAddr>()` unless the client
+/// address comes from `X-Forwarded-For` set by trusted proxies.
 pub fn create_webhook_router(state: Arc<InboundWebhookState>) -> Router {
     Router::new()
         .route("/webhooks/:config_id", post(handle_webhook))
         .with_state(state)
 }
 
+fn payload_too_large(state: &InboundWebhookState, config: &InboundWebhookConfig) -> axum::response::Response {
+    warn!(
+        config_id = %config.id,
+        max_bytes = config.max_payload_bytes,
+        "Webhook payload too large"
+    );
+    state.count(&config.source, |c| &c.payload_too_large);
+    (StatusCode::PAYLOAD_TOO_LARGE, "Payload too large").into_response()
+}
+
 /// Handle incoming webhook request
 async fn handle_webhook(
     State(state): State<Arc<InboundWebhookState>>,
     Path(config_id): Path<String>,
+    peer: Option<ConnectInfo<SocketAddr>>,
     headers: HeaderMap,
-    body: Bytes,
+    body: Body,
 ) -> impl IntoResponse {
     // Get config
     let config = match state.get_config(&config_id) {
@@ -225,6 +458,50 @@ async fn handle_webhook(
         return (StatusCode::FORBIDDEN, "Webhook disabled").into_response();
     }
 
+    // Check the source address
+    let remote_ip = state.client_ip(peer.map(|ConnectInfo(addr)| addr), &headers);
+    if !config.allowed_ips.is_empty() && !remote_ip.is_some_and(|ip| config.allows_ip(ip)) {
+        warn!(
+            config_id = %config_id,
+            remote_ip = ?remote_ip,
+            "Webhook from an address that is not allowed"
+        );
+        state.count(&config.source, |c| &c.ip_rejected);
+        return (StatusCode::FORBIDDEN, "Address not allowed").into_response();
+    }
+
+    // Check the rate limit
+    if let Some(limit) = &config.rate_limit {
+        if let Err(retry_after) = state.admit(&config.id, limit) {
+            warn!(config_id = %config_id, source = %config.source, "Webhook rate limit exceeded");
+            state.count(&config.source, |c| &c.rate_limited);
+            let retry_after = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                "Rate limit exceeded",
+            )
+                .into_response();
+        }
+    }
+
+    // Read the payload, no further than the size limit
+    let declared_len = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared_len.is_some_and(|len| len > config.max_payload_bytes as u64) {
+        return payload_too_large(&state, &config);
+    }
+    let body: Bytes = match Limited::new(body, config.max_payload_bytes).collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) if e.is::<LengthLimitError>() => return payload_too_large(&state, &config),
+        Err(e) => {
+            warn!(config_id = %config_id, error = %e, "Failed to read webhook payload");
+            return (StatusCode::BAD_REQUEST, "Failed to read payload").into_response();
+        }
+    };
+
     // Verify signature if present
     if let Some(signature) = headers.get(&config.signature_header) {
         let signature_str = match signature.to_str() {
@@ -268,7 +545,7 @@ async fn handle_webhook(
                     .map(|v| (k.as_str().to_string(), v.to_string()))
             })
             .collect(),
-        remote_addr: None,
+        remote_addr: remote_ip.map(|ip| ip.to_string()),
         received_at: Utc::now(),
         status: InboundWebhookStatus::Received,
         error: None,
@@ -281,6 +558,7 @@ async fn handle_webhook(
         source = %config.source,
         "Received inbound webhook"
     );
+    state.count(&config.source, |c| &c.accepted);
 
     // Process webhook
     if let Some(handler) = state.handlers.get(&config.source) {
@@ -564,4 +842,80 @@ mod tests {
         state.remove_config(&config_id);
         assert!(state.get_config(&config_id).is_none());
     }
+
+    #[test]
+    fn test_allowlist_matches_addresses_and_ranges() {
+        let config = InboundWebhookConfig::new("GitHub", "github", "secret")
+            .with_allowed_ips(&["192.30.252.0/22", "2606:50c0::/32", "203.0.113.7", "not-an-ip/8"]);
+        let allowed = |ip: &str| config.allows_ip(ip.parse().unwrap());
+
+        assert!(allowed("192.30.255.1"));
+        assert!(allowed("::ffff:192.30.252.9"));
+        assert!(allowed("2606:50c0:8000::154"));
+        assert!(allowed("203.0.113.7"));
+        assert!(!allowed("192.30.248.1"));
+        assert!(!allowed("203.0.113.8"));
+        assert!(InboundWebhookConfig::new("Any", "any", "secret").allows_ip("198.51.100.1".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_guards_refuse_with_403_413_and_429() {
+        let (tx, mut rx) = mpsc::channel(100);
+        let state = Arc::new(InboundWebhookState::new(Arc::new(WebhookHandlerRegistry::new()), tx));
+        let config = InboundWebhookConfig::new("Test", "test", "secret")
+            .with_max_payload_bytes(32)
+            .with_rate_limit(2, 60)
+            .with_allowed_ips(&["10.0.0.0/8"]);
+        let config_id = config.id.clone();
+        state.register_config(config);
+
+        let send = |peer: &str, body: &str| {
+            handle_webhook(
+                State(state.clone()),
+                Path(config_id.clone()),
+                Some(ConnectInfo(format!("{}:443", peer).parse().unwrap())),
+                HeaderMap::new(),
+                Body::from(body.to_string()),
+            )
+        };
+
+        assert_eq!(send("192.168.1.1", "{}").await.into_response().status(), StatusCode::FORBIDDEN);
+        assert_eq!(send("10.1.2.3", "{}").await.into_response().status(), StatusCode::OK);
+        assert_eq!(rx.recv().await.unwrap().remote_addr.as_deref(), Some("10.1.2.3"));
+
+        let oversized = format!("{{\"padding\": \"{}\"}}", "x".repeat(64));
+        assert_eq!(send("10.1.2.3", &oversized).await.into_response().status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let limited = send("10.1.2.3", "{}").await.into_response();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(limited.headers().get(header::RETRY_AFTER).is_some());
+
+        let stats = &state.stats()[0];
+        assert_eq!(
+            (stats.accepted, stats.ip_rejected, stats.payload_too_large, stats.rate_limited),
+            (1, 1, 1, 1)
+        );
+        assert!(state
+            .render_prometheus("copilot")
+            .contains("copilot_inbound_webhook_rate_limited_total{source=\"test\"} 1"));
+    }
+
+    #[test]
+    fn test_client_address_skips_entries_sent_by_the_client() {
+        let (tx, _rx) = mpsc::channel(1);
+        let registry = Arc::new(WebhookHandlerRegistry::new());
+        let peer = Some("10.0.0.1:443".parse().unwrap());
+        let mut headers = HeaderMap::new();
+        // The client claims an allowed address; two proxies append theirs
+        headers.insert("x-forwarded-for", "192.30.252.1, 203.0.113.9, 10.0.0.2".parse().unwrap());
+
+        let direct = InboundWebhookState::new(registry.clone(), tx.clone());
+        assert_eq!(direct.client_ip(peer, &headers), Some("10.0.0.1".parse().unwrap()));
+        let one_hop = InboundWebhookState::new(registry.clone(), tx.clone()).with_trusted_proxies(1);
+        assert_eq!(one_hop.client_ip(peer, &headers), Some("10.0.0.2".parse().unwrap()));
+        let two_hops = InboundWebhookState::new(registry.clone(), tx.clone()).with_trusted_proxies(2);
+        assert_eq!(two_hops.client_ip(peer, &headers), Some("203.0.113.9".parse().unwrap()));
+        let four_hops = InboundWebhookState::new(registry, tx).with_trusted_proxies(4);
+        assert_eq!(four_hops.client_ip(peer, &headers), None);
+    }
 }
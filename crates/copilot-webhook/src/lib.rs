@@ -2,9 +2,15 @@
 //!
 //! This crate provides webhook functionality:
 //! - Outbound webhooks for event notifications
-//! - Inbound webhooks for external triggers
+//! - Inbound webhooks for external triggers, with per-endpoint rate limits,
+//!   payload size caps and IP allowlists
 //! - Webhook signature verification
+//! - Versioned payload schemas, with endpoints pinnable to older versions
 //! - Retry policies and delivery tracking
+//! - Shared delivery queues for dispatch across replicas
+//! - Endpoint subscription editing, secret rotation with grace periods, test
+//!   pings and per-endpoint delivery statistics
+//! - Task completion notifications over webhooks and SMTP email
 //!
 //! # Features
 //!
@@ -50,15 +56,25 @@
 // Module order matters due to dependencies
 pub mod events;
 pub mod signature;
+pub mod schema;
 pub mod delivery;
+pub mod queue;
 pub mod outbound;
+pub mod worker;
 pub mod inbound;
+pub mod notification;
+pub mod smtp;
 
 pub use events::*;
 pub use signature::*;
+pub use schema::*;
 pub use delivery::*;
+pub use queue::*;
 pub use outbound::*;
+pub use worker::*;
 pub use inbound::*;
+pub use notification::*;
+pub use smtp::{SmtpConfig, SmtpNotifier, SmtpSecurity};
 
 use thiserror::Error;
 
@@ -91,6 +107,15 @@ pub enum WebhookError {
 
     #[error("Serialization error: {0}")]
     Serialization(String),
+
+    #[error("Delivery queue error: {0}")]
+    Queue(String),
+
+    #[error("Email delivery failed: {0}")]
+    Email(String),
+
+    #[error("Unknown payload schema version: {0}")]
+    UnknownSchemaVersion(u32),
 }
 
 pub type Result<T> = std::result::Result<T, WebhookError>;
@@ -0,0 +1,673 @@
+//! Notifications for long-running tasks
+//!
+//! When a workflow, ingestion job or agent task finishes, a
+//! [`TaskNotification`] is handed to the [`TaskNotifier`], which fans it out
+//! as a webhook event and, for recipients who opted in, as an email rendered
+//! from a [`MessageTemplate`]. Who hears about what is decided by
+//! [`NotificationPreferences`], set per tenant and overridden per user.
+
+use crate::{
+    events::{TaskEventData, WebhookEvent, WebhookEventData, WebhookEventType, WorkflowEventData},
+    outbound::WebhookDispatcher,
+    Result, WebhookError,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Webhook events a [`TaskNotifier`] can send
+pub const TASK_EVENT_TYPES: [WebhookEventType; 6] = [
+    WebhookEventType::WorkflowCompleted,
+    WebhookEventType::WorkflowFailed,
+    WebhookEventType::IngestionCompleted,
+    WebhookEventType::IngestionFailed,
+    WebhookEventType::AgentTaskCompleted,
+    WebhookEventType::AgentTaskFailed,
+];
+
+/// Kind of long-running task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    Workflow,
+    Ingestion,
+    AgentTask,
+}
+
+impl TaskKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Workflow => "workflow",
+            Self::Ingestion => "ingestion",
+            Self::AgentTask => "agent_task",
+        }
+    }
+
+    /// Human-readable name used in messages
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Workflow => "Workflow",
+            Self::Ingestion => "Ingestion job",
+            Self::AgentTask => "Agent task",
+        }
+    }
+}
+
+/// How a task ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskOutcome {
+    Completed,
+    Failed,
+}
+
+impl TaskOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// A finished task to notify about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskNotification {
+    pub task_id: String,
+    pub kind: TaskKind,
+    /// Display name (workflow name, task kind, uploaded file, ...)
+    pub name: String,
+    pub outcome: TaskOutcome,
+    pub tenant_id: String,
+    /// User who started the task, if known
+    pub user_id: Option<String>,
+    pub error: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: DateTime<Utc>,
+    /// Kind-specific details included in the webhook payload
+    pub details: Option<serde_json::Value>,
+}
+
+impl TaskNotification {
+    pub fn new(
+        kind: TaskKind,
+        task_id: impl Into<String>,
+        name: impl Into<String>,
+        outcome: TaskOutcome,
+        tenant_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            task_id: task_id.into(),
+            kind,
+            name: name.into(),
+            outcome,
+            tenant_id: tenant_id.into(),
+            user_id: None,
+            error: None,
+            started_at: None,
+            finished_at: Utc::now(),
+            details: None,
+        }
+    }
+
+    pub fn with_user(mut self, user_id: Option<&str>) -> Self {
+        self.user_id = user_id.map(str::to_string);
+        self
+    }
+
+    pub fn with_error(mut self, error: Option<&str>) -> Self {
+        self.error = error.map(str::to_string);
+        self
+    }
+
+    pub fn with_times(mut self, started_at: Option<DateTime<Utc>>, finished_at: DateTime<Utc>) -> Self {
+        self.started_at = started_at;
+        self.finished_at = finished_at;
+        self
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// Webhook event type for this notification
+    pub fn event_type(&self) -> WebhookEventType {
+        match (self.kind, self.outcome) {
+            (TaskKind::Workflow, TaskOutcome::Completed) => WebhookEventType::WorkflowCompleted,
+            (TaskKind::Workflow, TaskOutcome::Failed) => WebhookEventType::WorkflowFailed,
+            (TaskKind::Ingestion, TaskOutcome::Completed) => WebhookEventType::IngestionCompleted,
+            (TaskKind::Ingestion, TaskOutcome::Failed) => WebhookEventType::IngestionFailed,
+            (TaskKind::AgentTask, TaskOutcome::Completed) => WebhookEventType::AgentTaskCompleted,
+            (TaskKind::AgentTask, TaskOutcome::Failed) => WebhookEventType::AgentTaskFailed,
+        }
+    }
+
+    /// The notification as a tenant-scoped webhook event
+    ///
+    /// Workflows keep the existing workflow payload so current subscribers
+    /// of `workflow.*` events see no change.
+    pub fn to_webhook_event(&self) -> WebhookEvent {
+        let data = match self.kind {
+            TaskKind::Workflow => WebhookEventData::Workflow(WorkflowEventData {
+                id: self.task_id.clone(),
+                workflow_id: self
+                    .details
+                    .as_ref()
+                    .and_then(|d| d["workflow_id"].as_str())
+                    .unwrap_or(&self.task_id)
+                    .to_string(),
+                name: self.name.clone(),
+                status: self.outcome.as_str().to_string(),
+                current_step: None,
+                started_at: self.started_at.unwrap_or(self.finished_at),
+                completed_at: Some(self.finished_at),
+                error: self.error.clone(),
+                output: self.details.clone(),
+            }),
+            TaskKind::Ingestion | TaskKind::AgentTask => WebhookEventData::Task(TaskEventData {
+                id: self.task_id.clone(),
+                kind: self.kind.as_str().to_string(),
+                name: self.name.clone(),
+                status: self.outcome.as_str().to_string(),
+                user_id: self.user_id.clone(),
+                started_at: self.started_at,
+                finished_at: self.finished_at,
+                error: self.error.clone(),
+                details: self.details.clone(),
+            }),
+        };
+        WebhookEvent::new(self.event_type(), data).with_tenant(&self.tenant_id)
+    }
+
+    /// Values available to message templates as `{{name}}`
+    pub fn template_vars(&self) -> HashMap<&'static str, String> {
+        let duration = self
+            .started_at
+            .map(|started| format!("{}s", (self.finished_at - started).num_seconds().max(0)))
+            .unwrap_or_default();
+        HashMap::from([
+            ("task_id", self.task_id.clone()),
+            ("kind", self.kind.label().to_string()),
+            ("name", self.name.clone()),
+            ("status", self.outcome.as_str().to_string()),
+            ("tenant_id", self.tenant_id.clone()),
+            ("user_id", self.user_id.clone().unwrap_or_default()),
+            ("error", self.error.clone().unwrap_or_default()),
+            ("finished_at", self.finished_at.format("%Y-%m-%d %H:%M:%S UTC").to_string()),
+            ("duration", duration),
+        ])
+    }
+}
+
+/// Delivery channel for notifications
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    Webhook,
+    Email,
+}
+
+/// Which task notifications a recipient receives, and how
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    /// Channels to notify on
+    #[serde(default = "default_channels")]
+    pub channels: Vec<NotificationChannel>,
+    /// Address for the email channel
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Notify when tasks complete
+    #[serde(default = "enabled")]
+    pub on_completed: bool,
+    /// Notify when tasks fail
+    #[serde(default = "enabled")]
+    pub on_failed: bool,
+    /// Task kinds to notify about; empty means all
+    #[serde(default)]
+    pub kinds: Vec<TaskKind>,
+}
+
+fn default_channels() -> Vec<NotificationChannel> {
+    vec![NotificationChannel::Webhook]
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            channels: default_channels(),
+            email: None,
+            on_completed: true,
+            on_failed: true,
+            kinds: Vec::new(),
+        }
+    }
+}
+
+impl NotificationPreferences {
+    /// Turn every notification off
+    pub fn none() -> Self {
+        Self {
+            channels: Vec::new(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_email(mut self, address: impl Into<String>) -> Self {
+        self.email = Some(address.into());
+        if !self.channels.contains(&NotificationChannel::Email) {
+            self.channels.push(NotificationChannel::Email);
+        }
+        self
+    }
+
+    /// Whether `notification` should go out at all
+    pub fn wants(&self, notification: &TaskNotification) -> bool {
+        let outcome = match notification.outcome {
+            TaskOutcome::Completed => self.on_completed,
+            TaskOutcome::Failed => self.on_failed,
+        };
+        outcome && (self.kinds.is_empty() || self.kinds.contains(&notification.kind))
+    }
+
+    /// Whether `notification` should go out on `channel`
+    pub fn wants_on(&self, notification: &TaskNotification, channel: NotificationChannel) -> bool {
+        self.channels.contains(&channel) && self.wants(notification)
+    }
+}
+
+/// Notification preferences per tenant and per user
+///
+/// A user's own preferences win over their tenant's, which win over the
+/// default.
+#[derive(Debug, Default)]
+pub struct NotificationSubscriptions {
+    default: NotificationPreferences,
+    tenants: DashMap<String, NotificationPreferences>,
+    users: DashMap<(String, String), NotificationPreferences>,
+}
+
+impl NotificationSubscriptions {
+    pub fn new(default: NotificationPreferences) -> Self {
+        Self {
+            default,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_tenant(&self, tenant_id: &str, preferences: NotificationPreferences) {
+        self.tenants.insert(tenant_id.to_string(), preferences);
+    }
+
+    pub fn set_user(&self, tenant_id: &str, user_id: &str, preferences: NotificationPreferences) {
+        self.users
+            .insert((tenant_id.to_string(), user_id.to_string()), preferences);
+    }
+
+    /// Drop a user's own preferences so their tenant's apply again
+    pub fn clear_user(&self, tenant_id: &str, user_id: &str) {
+        self.users.remove(&(tenant_id.to_string(), user_id.to_string()));
+    }
+
+    /// Preferences that apply to a user (or the tenant when `user_id` is `None`)
+    pub fn resolve(&self, tenant_id: &str, user_id: Option<&str>) -> NotificationPreferences {
+        if let Some(user_id) = user_id {
+            if let Some(preferences) = self.users.get(&(tenant_id.to_string(), user_id.to_string())) {
+                return preferences.clone();
+            }
+        }
+        self.tenants
+            .get(tenant_id)
+            .map(|p| p.clone())
+            .unwrap_or_else(|| self.default.clone())
+    }
+}
+
+/// Subject and body of a notification message, with `{{name}}` placeholders
+/// filled from [`TaskNotification::template_vars`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageTemplate {
+    pub subject: String,
+    pub body: String,
+}
+
+impl MessageTemplate {
+    pub fn new(subject: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            subject: subject.into(),
+            body: body.into(),
+        }
+    }
+
+    /// Fill in the placeholders; unknown placeholders are left as written
+    pub fn render(&self, notification: &TaskNotification) -> RenderedMessage {
+        self.render_with(&notification.template_vars())
+    }
+
+    /// Fill in the placeholders from `vars`, for messages other than task
+    /// notifications
+    pub fn render_with(&self, vars: &HashMap<&'static str, String>) -> RenderedMessage {
+        RenderedMessage {
+            subject: fill(&self.subject, vars),
+            body: fill(&self.body, vars),
+        }
+    }
+}
+
+/// A rendered notification message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedMessage {
+    pub subject: String,
+    pub body: String,
+}
+
+fn fill(template: &str, vars: &HashMap<&'static str, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                match vars.get(name) {
+                    Some(value) => out.push_str(value),
+                    None => out.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Message templates per outcome, overridable per tenant
+#[derive(Debug, Clone)]
+pub struct NotificationTemplates {
+    defaults: HashMap<TaskOutcome, MessageTemplate>,
+    tenants: HashMap<String, HashMap<TaskOutcome, MessageTemplate>>,
+}
+
+impl Default for NotificationTemplates {
+    fn default() -> Self {
+        Self {
+            defaults: HashMap::from([
+                (
+                    TaskOutcome::Completed,
+                    MessageTemplate::new(
+                        "{{kind}} {{name}} completed",
+                        "{{kind}} {{name}} ({{task_id}}) completed at {{finished_at}} after {{duration}}.\n",
+                    ),
+                ),
+                (
+                    TaskOutcome::Failed,
+                    MessageTemplate::new(
+                        "{{kind}} {{name}} failed",
+                        "{{kind}} {{name}} ({{task_id}}) failed at {{finished_at}}.\n\nError: {{error}}\n",
+                    ),
+                ),
+            ]),
+            tenants: HashMap::new(),
+        }
+    }
+}
+
+impl NotificationTemplates {
+    pub fn with_template(mut self, outcome: TaskOutcome, template: MessageTemplate) -> Self {
+        self.defaults.insert(outcome, template);
+        self
+    }
+
+    pub fn with_tenant_template(
+        mut self,
+        tenant_id: impl Into<String>,
+        outcome: TaskOutcome,
+        template: MessageTemplate,
+    ) -> Self {
+        self.tenants
+            .entry(tenant_id.into())
+            .or_default()
+            .insert(outcome, template);
+        self
+    }
+
+    /// Render the message for `notification`, preferring the tenant's template
+    pub fn render(&self, notification: &TaskNotification) -> RenderedMessage {
+        self.tenants
+            .get(&notification.tenant_id)
+            .and_then(|templates| templates.get(&notification.outcome))
+            .or_else(|| self.defaults.get(&notification.outcome))
+            .map(|template| template.render(notification))
+            .unwrap_or_else(|| RenderedMessage {
+                subject: format!("{} {} {}", notification.kind.label(), notification.name, notification.outcome.as_str()),
+                body: String::new(),
+            })
+    }
+}
+
+/// An email ready to send
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Sends plain-text emails
+#[async_trait]
+pub trait EmailSender: Send + Sync {
+    async fn send(&self, message: &EmailMessage) -> Result<()>;
+}
+
+/// What was sent for one notification
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NotificationReport {
+    /// Webhook deliveries attempted
+    pub webhook_deliveries: usize,
+    /// Address emailed, if any
+    pub emailed: Option<String>,
+    /// Channel failures; these never fail the task itself
+    pub errors: Vec<String>,
+}
+
+/// Dispatches task notifications over webhooks and email
+pub struct TaskNotifier {
+    subscriptions: Arc<NotificationSubscriptions>,
+    templates: NotificationTemplates,
+    webhooks: Option<Arc<WebhookDispatcher>>,
+    email: Option<Arc<dyn EmailSender>>,
+}
+
+impl TaskNotifier {
+    pub fn new(subscriptions: Arc<NotificationSubscriptions>) -> Self {
+        Self {
+            subscriptions,
+            templates: NotificationTemplates::default(),
+            webhooks: None,
+            email: None,
+        }
+    }
+
+    pub fn with_webhooks(mut self, dispatcher: Arc<WebhookDispatcher>) -> Self {
+        self.webhooks = Some(dispatcher);
+        self
+    }
+
+    pub fn with_email(mut self, sender: Arc<dyn EmailSender>) -> Self {
+        self.email = Some(sender);
+        self
+    }
+
+    pub fn with_templates(mut self, templates: NotificationTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    pub fn subscriptions(&self) -> &Arc<NotificationSubscriptions> {
+        &self.subscriptions
+    }
+
+    /// Send `notification` on every channel its recipient subscribed to
+    pub async fn notify(&self, notification: &TaskNotification) -> NotificationReport {
+        let preferences = self
+            .subscriptions
+            .resolve(&notification.tenant_id, notification.user_id.as_deref());
+        let mut report = NotificationReport::default();
+        if !preferences.wants(notification) {
+            debug!(task_id = %notification.task_id, "Notification not wanted by recipient");
+            return report;
+        }
+
+        if let Some(dispatcher) = &self.webhooks {
+            if preferences.wants_on(notification, NotificationChannel::Webhook) {
+                match dispatcher.dispatch(notification.to_webhook_event()).await {
+                    Ok(deliveries) => report.webhook_deliveries = deliveries.len(),
+                    Err(e) => report.errors.push(format!("webhook: {}", e)),
+                }
+            }
+        }
+
+        if let Some(sender) = &self.email {
+            if preferences.wants_on(notification, NotificationChannel::Email) {
+                match &preferences.email {
+                    Some(address) => {
+                        let rendered = self.templates.render(notification);
+                        let message = EmailMessage {
+                            to: address.clone(),
+                            subject: rendered.subject,
+                            body: rendered.body,
+                        };
+                        match sender.send(&message).await {
+                            Ok(()) => report.emailed = Some(address.clone()),
+                            Err(e) => report.errors.push(format!("email: {}", e)),
+                        }
+                    }
+                    None => report
+                        .errors
+                        .push(WebhookError::Email("no email address on file".to_string()).to_string()),
+                }
+            }
+        }
+
+        for error in &report.errors {
+            warn!(task_id = %notification.task_id, error = %error, "Task notification failed");
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+
+    #[derive(Default)]
+    struct RecordingSender {
+        sent: Mutex<Vec<EmailMessage>>,
+    }
+
+    #[async_trait]
+    impl EmailSender for RecordingSender {
+        async fn send(&self, message: &EmailMessage) -> Result<()> {
+            self.sent.lock().push(message.clone());
+            Ok(())
+        }
+    }
+
+    fn failed_ingestion(user: Option<&str>) -> TaskNotification {
+        TaskNotification::new(TaskKind::Ingestion, "job-1", "report.pdf", TaskOutcome::Failed, "acme")
+            .with_user(user)
+            .with_error(Some("unsupported type"))
+    }
+
+    #[test]
+    fn test_templates_render_with_tenant_override() {
+        let templates = NotificationTemplates::default().with_tenant_template(
+            "acme",
+            TaskOutcome::Failed,
+            MessageTemplate::new("[acme] {{name}} {{status}}", "{{error}} {{unknown}}"),
+        );
+
+        let rendered = templates.render(&failed_ingestion(None));
+        assert_eq!(rendered.subject, "[acme] report.pdf failed");
+        assert_eq!(rendered.body, "unsupported type {{unknown}}");
+
+        let mut other = failed_ingestion(None);
+        other.tenant_id = "globex".to_string();
+        assert_eq!(templates.render(&other).subject, "Ingestion job report.pdf failed");
+    }
+
+    #[test]
+    fn test_user_preferences_override_tenant() {
+        let subscriptions = NotificationSubscriptions::default();
+        subscriptions.set_tenant("acme", NotificationPreferences::none());
+        subscriptions.set_user(
+            "acme",
+            "alice",
+            NotificationPreferences {
+                on_completed: false,
+                ..NotificationPreferences::default().with_email("alice@example.com")
+            },
+        );
+
+        let alice = subscriptions.resolve("acme", Some("alice"));
+        assert!(alice.wants_on(&failed_ingestion(Some("alice")), NotificationChannel::Email));
+        let mut completed = failed_ingestion(Some("alice"));
+        completed.outcome = TaskOutcome::Completed;
+        assert!(!alice.wants(&completed));
+
+        assert!(subscriptions.resolve("acme", Some("bob")).channels.is_empty());
+        assert_eq!(subscriptions.resolve("globex", None), NotificationPreferences::default());
+    }
+
+    #[tokio::test]
+    async fn test_notifier_emails_subscribed_user() {
+        let subscriptions = Arc::new(NotificationSubscriptions::default());
+        subscriptions.set_user(
+            "acme",
+            "alice",
+            NotificationPreferences::none().with_email("alice@example.com"),
+        );
+        let sender = Arc::new(RecordingSender::default());
+        let notifier = TaskNotifier::new(subscriptions).with_email(sender.clone());
+
+        let report = notifier.notify(&failed_ingestion(Some("alice"))).await;
+        assert_eq!(report.emailed.as_deref(), Some("alice@example.com"));
+        assert!(report.errors.is_empty());
+
+        let report = notifier.notify(&failed_ingestion(Some("bob"))).await;
+        assert_eq!(report, NotificationReport::default());
+
+        let sent = sender.sent.lock();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].subject, "Ingestion job report.pdf failed");
+        assert!(sent[0].body.contains("Error: unsupported type"));
+    }
+
+    #[test]
+    fn test_webhook_event_types() {
+        let event = failed_ingestion(Some("alice")).to_webhook_event();
+        assert_eq!(event.event_type, WebhookEventType::IngestionFailed);
+        assert_eq!(event.tenant_id.as_deref(), Some("acme"));
+        assert_eq!(event.to_json()["data"]["object"], "task");
+
+        let workflow = TaskNotification::new(TaskKind::Workflow, "run-1", "deploy", TaskOutcome::Completed, "acme")
+            .with_details(serde_json::json!({"workflow_id": "wf-9"}));
+        let json = workflow.to_webhook_event().to_json();
+        assert_eq!(json["type"], "workflow_completed");
+        assert_eq!(json["data"]["workflow_id"], "wf-9");
+    }
+}
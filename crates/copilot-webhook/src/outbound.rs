@@ -3,9 +3,11 @@
 //! Handles sending webhooks to external endpoints with retry support.
 
 use crate::{
-    delivery::{DeliveryAttempt, DeliveryStatus, WebhookDelivery},
-    events::{WebhookEvent, WebhookEventType},
-    signature::WebhookSigner,
+    delivery::{DeliveryAttempt, DeliveryStatus, EndpointDeliveryStats, EndpointStatsRecorder, WebhookDelivery},
+    events::{CustomEventData, WebhookEvent, WebhookEventData, WebhookEventType},
+    queue::{DeliveryQueue, QueuedDelivery},
+    schema::SchemaRegistry,
+    signature::{generate_webhook_secret, WebhookSigner},
     Result, WebhookError,
 };
 use async_trait::async_trait;
@@ -29,6 +31,12 @@ pub struct WebhookEndpoint {
     pub url: String,
     /// Secret for signature generation
     pub secret: String,
+    /// Secret being rotated out, still signed with until it expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_secret: Option<String>,
+    /// End of the previous secret's grace period
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_secret_expires_at: Option<DateTime<Utc>>,
     /// Events to subscribe to
     pub events: Vec<WebhookEventType>,
     /// Whether endpoint is active
@@ -43,6 +51,10 @@ pub struct WebhookEndpoint {
     pub updated_at: DateTime<Utc>,
     /// API version
     pub api_version: String,
+    /// Payload schema version deliveries are rendered in; the current one
+    /// when unpinned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
     /// Metadata
     pub metadata: serde_json::Value,
 }
@@ -56,6 +68,8 @@ impl WebhookEndpoint {
             name: name.to_string(),
             url: url.to_string(),
             secret: secret.to_string(),
+            previous_secret: None,
+            previous_secret_expires_at: None,
             events: Vec::new(),
             enabled: true,
             headers: Vec::new(),
@@ -63,6 +77,7 @@ impl WebhookEndpoint {
             created_at: now,
             updated_at: now,
             api_version: "2024-01-01".to_string(),
+            schema_version: None,
             metadata: serde_json::Value::Null,
         }
     }
@@ -82,10 +97,110 @@ impl WebhookEndpoint {
         self
     }
 
+    /// Pin deliveries to a payload schema version
+    pub fn with_schema_version(mut self, version: u32) -> Self {
+        self.schema_version = Some(version);
+        self
+    }
+
     /// Check if endpoint subscribes to an event type
     pub fn subscribes_to(&self, event_type: &WebhookEventType) -> bool {
         self.events.is_empty() || self.events.contains(event_type)
     }
+
+    /// Switch to a new secret, signing with the old one as well until
+    /// `grace` has passed
+    pub fn rotate_secret(&mut self, secret: &str, grace: Duration) {
+        let now = Utc::now();
+        if grace > Duration::zero() {
+            self.previous_secret = Some(std::mem::replace(&mut self.secret, secret.to_string()));
+            self.previous_secret_expires_at = Some(now + grace);
+        } else {
+            self.secret = secret.to_string();
+            self.previous_secret = None;
+            self.previous_secret_expires_at = None;
+        }
+        self.updated_at = now;
+    }
+
+    /// The previous secret, while its grace period lasts
+    pub fn active_previous_secret(&self) -> Option<&str> {
+        match (&self.previous_secret, self.previous_secret_expires_at) {
+            (Some(secret), Some(expires_at)) if expires_at > Utc::now() => Some(secret),
+            _ => None,
+        }
+    }
+
+    /// Signer for deliveries to this endpoint
+    pub fn signer(&self) -> WebhookSigner {
+        let signer = WebhookSigner::new(&self.secret);
+        match self.active_previous_secret() {
+            Some(previous) => signer.with_previous_secret(previous),
+            None => signer,
+        }
+    }
+
+    /// Apply an update, leaving the fields it omits unchanged
+    pub fn apply(&mut self, update: EndpointUpdate) -> Result<()> {
+        if let Some(url) = update.url {
+            validate_url(&url)?;
+            self.url = url;
+        }
+        if let Some(name) = update.name {
+            self.name = name;
+        }
+        if let Some(events) = update.events {
+            self.events = events;
+        }
+        if let Some(enabled) = update.enabled {
+            self.enabled = enabled;
+        }
+        if let Some(headers) = update.headers {
+            self.headers = headers;
+        }
+        if let Some(schema_version) = update.schema_version {
+            self.schema_version = schema_version;
+        }
+        self.updated_at = Utc::now();
+        Ok(())
+    }
+}
+
+/// Changes to a webhook endpoint; omitted fields are left unchanged
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EndpointUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Events to subscribe to; empty subscribes to all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<WebhookEventType>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<Vec<(String, String)>>,
+    /// Payload schema version to pin to; `null` unpins
+    #[serde(default, deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<Option<u32>>,
+}
+
+/// Tell a field set to `null` apart from an omitted one
+fn present<'de, D, T>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Check that a webhook URL is an absolute HTTP(S) URL
+pub fn validate_url(url: &str) -> Result<()> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| WebhookError::InvalidUrl(format!("{}: {}", url, e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(WebhookError::InvalidUrl(url.to_string()));
+    }
+    Ok(())
 }
 
 /// Retry configuration for webhook delivery
@@ -144,6 +259,9 @@ pub struct WebhookDispatcher {
     endpoints: Arc<DashMap<String, WebhookEndpoint>>,
     retry_config: RetryConfig,
     delivery_sender: mpsc::Sender<WebhookDelivery>,
+    stats: EndpointStatsRecorder,
+    schemas: Arc<SchemaRegistry>,
+    queue: Option<Arc<dyn DeliveryQueue>>,
 }
 
 impl WebhookDispatcher {
@@ -162,9 +280,30 @@ impl WebhookDispatcher {
             endpoints: Arc::new(DashMap::new()),
             retry_config,
             delivery_sender,
+            stats: EndpointStatsRecorder::default(),
+            schemas: Arc::new(SchemaRegistry::builtin()),
+            queue: None,
         }
     }
 
+    /// Render payloads with `schemas` instead of the built-in registry
+    pub fn with_schema_registry(mut self, schemas: Arc<SchemaRegistry>) -> Self {
+        self.schemas = schemas;
+        self
+    }
+
+    /// Hand [`dispatch`](Self::dispatch)ed events to `queue` for the
+    /// [`DeliveryWorker`](crate::DeliveryWorker)s to send
+    pub fn with_queue(mut self, queue: Arc<dyn DeliveryQueue>) -> Self {
+        self.queue = Some(queue);
+        self
+    }
+
+    /// Payload schema versions deliveries can be rendered in
+    pub fn schema_registry(&self) -> &Arc<SchemaRegistry> {
+        &self.schemas
+    }
+
     /// Register a webhook endpoint
     pub fn register_endpoint(&self, endpoint: WebhookEndpoint) {
         info!(endpoint_id = %endpoint.id, url = %endpoint.url, "Registering webhook endpoint");
@@ -175,6 +314,42 @@ impl WebhookDispatcher {
     pub fn unregister_endpoint(&self, endpoint_id: &str) {
         info!(endpoint_id = %endpoint_id, "Unregistering webhook endpoint");
         self.endpoints.remove(endpoint_id);
+        self.stats.remove(endpoint_id);
+    }
+
+    /// Update an endpoint's name, URL, subscriptions, headers or state
+    pub fn update_endpoint(&self, endpoint_id: &str, update: EndpointUpdate) -> Result<WebhookEndpoint> {
+        if let Some(Some(version)) = update.schema_version {
+            self.schemas.check_version(version)?;
+        }
+        let mut endpoint = self
+            .endpoints
+            .get_mut(endpoint_id)
+            .ok_or_else(|| WebhookError::NotFound(endpoint_id.to_string()))?;
+        endpoint.apply(update)?;
+        info!(endpoint_id = %endpoint_id, "Updated webhook endpoint");
+        Ok(endpoint.clone())
+    }
+
+    /// Give an endpoint a new secret, signing with the old one too until
+    /// `grace` has passed so its receiver can switch over
+    pub fn rotate_secret(&self, endpoint_id: &str, grace: Duration) -> Result<WebhookEndpoint> {
+        let mut endpoint = self
+            .endpoints
+            .get_mut(endpoint_id)
+            .ok_or_else(|| WebhookError::NotFound(endpoint_id.to_string()))?;
+        endpoint.rotate_secret(&generate_webhook_secret(), grace);
+        info!(
+            endpoint_id = %endpoint_id,
+            grace_secs = grace.num_seconds(),
+            "Rotated webhook endpoint secret"
+        );
+        Ok(endpoint.clone())
+    }
+
+    /// Delivery success and latency statistics of an endpoint
+    pub fn endpoint_stats(&self, endpoint_id: &str) -> EndpointDeliveryStats {
+        self.stats.stats(endpoint_id)
     }
 
     /// Get an endpoint by ID
@@ -196,30 +371,47 @@ impl WebhookDispatcher {
             .collect()
     }
 
+    /// Endpoints that should receive an event
+    pub fn matching_endpoints(&self, event: &WebhookEvent) -> Vec<WebhookEndpoint> {
+        self.endpoints
+            .iter()
+            .filter(|endpoint| {
+                // Skip disabled endpoints and those not subscribed to this event
+                if !endpoint.enabled || !endpoint.subscribes_to(&event.event_type) {
+                    return false;
+                }
+                // Check tenant filter
+                match endpoint.tenant_id {
+                    Some(ref tenant_id) => event.tenant_id.as_ref() == Some(tenant_id),
+                    None => true,
+                }
+            })
+            .map(|e| e.clone())
+            .collect()
+    }
+
     /// Dispatch an event to all subscribed endpoints
+    ///
+    /// With a [queue](Self::with_queue) the deliveries are enqueued and
+    /// returned as pending; otherwise, or while the queue is unreachable,
+    /// they are made inline.
     pub async fn dispatch(&self, event: WebhookEvent) -> Result<Vec<WebhookDelivery>> {
         let mut deliveries = Vec::new();
 
-        for endpoint_ref in self.endpoints.iter() {
-            let endpoint = endpoint_ref.clone();
-
-            // Skip disabled endpoints
-            if !endpoint.enabled {
-                continue;
-            }
-
-            // Check if endpoint subscribes to this event
-            if !endpoint.subscribes_to(&event.event_type) {
-                continue;
-            }
-
-            // Check tenant filter
-            if let Some(ref tenant_id) = endpoint.tenant_id {
-                if event.tenant_id.as_ref() != Some(tenant_id) {
-                    continue;
+        for endpoint in self.matching_endpoints(&event) {
+            if let Some(queue) = &self.queue {
+                match queue.enqueue(&endpoint.id, event.clone()).await {
+                    Ok(queued) => {
+                        deliveries.push(pending_delivery(&queued));
+                        continue;
+                    }
+                    Err(e) => warn!(
+                        endpoint_id = %endpoint.id,
+                        error = %e,
+                        "Failed to enqueue webhook delivery, delivering inline"
+                    ),
                 }
             }
-
             let delivery = self.deliver_to_endpoint(&endpoint, &event).await;
             deliveries.push(delivery);
         }
@@ -227,6 +419,91 @@ impl WebhookDispatcher {
         Ok(deliveries)
     }
 
+    /// Deliver an event to one endpoint, whatever events it subscribes to
+    pub async fn dispatch_to(&self, endpoint_id: &str, event: WebhookEvent) -> Result<WebhookDelivery> {
+        let endpoint = self
+            .get_endpoint(endpoint_id)
+            .ok_or_else(|| WebhookError::NotFound(endpoint_id.to_string()))?;
+        if !endpoint.enabled {
+            return Err(WebhookError::Disabled);
+        }
+        Ok(self.deliver_to_endpoint(&endpoint, &event).await)
+    }
+
+    /// Send a single `webhook.ping` delivery to an endpoint, without retries,
+    /// whether or not it is enabled or subscribed to pings
+    pub async fn ping(&self, endpoint_id: &str) -> Result<WebhookDelivery> {
+        let endpoint = self
+            .get_endpoint(endpoint_id)
+            .ok_or_else(|| WebhookError::NotFound(endpoint_id.to_string()))?;
+        let mut event = WebhookEvent::new(
+            WebhookEventType::Ping,
+            WebhookEventData::Custom(CustomEventData {
+                event_name: "ping".to_string(),
+                data: serde_json::json!({ "endpoint_id": endpoint.id }),
+            }),
+        );
+        event.tenant_id = endpoint.tenant_id.clone();
+        let payload = self.payload(&endpoint, &event);
+
+        let created_at = Utc::now();
+        let attempt = self.attempt_delivery(&endpoint, &payload, 0).await;
+        let status = match attempt.status {
+            DeliveryStatus::Delivered => DeliveryStatus::Delivered,
+            _ => DeliveryStatus::Failed,
+        };
+        let delivery = WebhookDelivery {
+            id: format!("whd_{}", Uuid::new_v4().to_string().replace('-', "")),
+            endpoint_id: endpoint.id.clone(),
+            event_id: event.id.clone(),
+            event_type: event.event_type,
+            status,
+            completed_at: Some(attempt.completed_at),
+            attempts: vec![attempt],
+            payload_size: payload.len(),
+            created_at,
+            next_retry_at: None,
+        };
+        self.record_delivery(delivery.clone()).await;
+        Ok(delivery)
+    }
+
+    /// Enqueue an event on a shared delivery queue instead of delivering inline
+    ///
+    /// Workers on any replica pick the deliveries up; see [`DeliveryWorker`](crate::DeliveryWorker).
+    pub async fn enqueue(
+        &self,
+        queue: &dyn DeliveryQueue,
+        event: WebhookEvent,
+    ) -> Result<Vec<QueuedDelivery>> {
+        let mut queued = Vec::new();
+        for endpoint in self.matching_endpoints(&event) {
+            queued.push(queue.enqueue(&endpoint.id, event.clone()).await?);
+        }
+        Ok(queued)
+    }
+
+    /// Serialize an event in the schema version an endpoint is pinned to
+    pub(crate) fn payload(&self, endpoint: &WebhookEndpoint, event: &WebhookEvent) -> Vec<u8> {
+        self.schemas
+            .render(event, endpoint.schema_version)
+            .or_else(|e| {
+                warn!(endpoint_id = %endpoint.id, error = %e, "Delivering in the current payload schema");
+                self.schemas.render(event, None)
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn retry_config(&self) -> &RetryConfig {
+        &self.retry_config
+    }
+
+    /// Send a delivery record to the tracking channel
+    pub(crate) async fn record_delivery(&self, delivery: WebhookDelivery) {
+        self.stats.record(&delivery);
+        let _ = self.delivery_sender.send(delivery).await;
+    }
+
     /// Deliver event to a specific endpoint
     async fn deliver_to_endpoint(
         &self,
@@ -234,7 +511,7 @@ impl WebhookDispatcher {
         event: &WebhookEvent,
     ) -> WebhookDelivery {
         let delivery_id = format!("whd_{}", Uuid::new_v4().to_string().replace('-', ""));
-        let payload = serde_json::to_vec(event).unwrap_or_default();
+        let payload = self.payload(endpoint, event);
 
         let mut delivery = WebhookDelivery {
             id: delivery_id.clone(),
@@ -296,20 +573,20 @@ impl WebhookDispatcher {
         }
 
         // Send delivery record to tracking channel
-        let _ = self.delivery_sender.send(delivery.clone()).await;
+        self.record_delivery(delivery.clone()).await;
 
         delivery
     }
 
     /// Attempt a single delivery
-    async fn attempt_delivery(
+    pub(crate) async fn attempt_delivery(
         &self,
         endpoint: &WebhookEndpoint,
         payload: &[u8],
         attempt_num: u32,
     ) -> DeliveryAttempt {
         let started_at = Utc::now();
-        let signer = WebhookSigner::new(&endpoint.secret);
+        let signer = endpoint.signer();
         let signature_headers = signer.get_headers(payload);
 
         let mut request = self
@@ -389,6 +666,22 @@ impl WebhookDispatcher {
     }
 }
 
+/// Delivery record for a delivery still waiting in the queue
+fn pending_delivery(queued: &QueuedDelivery) -> WebhookDelivery {
+    WebhookDelivery {
+        id: queued.id.clone(),
+        endpoint_id: queued.endpoint_id.clone(),
+        event_id: queued.event.id.clone(),
+        event_type: queued.event.event_type,
+        status: DeliveryStatus::Pending,
+        attempts: Vec::new(),
+        payload_size: 0,
+        created_at: queued.enqueued_at,
+        completed_at: None,
+        next_retry_at: None,
+    }
+}
+
 /// Webhook endpoint repository trait
 #[async_trait]
 pub trait WebhookEndpointRepository: Send + Sync {
@@ -572,6 +865,67 @@ mod tests {
         assert!(endpoint.subscribes_to(&WebhookEventType::UserCreated));
     }
 
+    #[test]
+    fn test_secret_rotation_grace_period() {
+        let mut endpoint = WebhookEndpoint::new("Test", "https://example.com", "old-secret");
+        endpoint.rotate_secret("new-secret", Duration::hours(1));
+        assert_eq!(endpoint.secret, "new-secret");
+        assert_eq!(endpoint.active_previous_secret(), Some("old-secret"));
+
+        let payload = b"payload";
+        let (signature, _) = endpoint.signer().sign_now(payload);
+        assert!(crate::WebhookVerifier::new("old-secret").verify(payload, &signature).is_ok());
+        assert!(crate::WebhookVerifier::new("new-secret").verify(payload, &signature).is_ok());
+
+        endpoint.previous_secret_expires_at = Some(Utc::now() - Duration::seconds(1));
+        assert_eq!(endpoint.active_previous_secret(), None);
+        let (signature, _) = endpoint.signer().sign_now(payload);
+        assert!(crate::WebhookVerifier::new("old-secret").verify(payload, &signature).is_err());
+
+        endpoint.rotate_secret("newest-secret", Duration::zero());
+        assert!(endpoint.previous_secret.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_endpoint() {
+        let (tx, _rx) = mpsc::channel(8);
+        let dispatcher = WebhookDispatcher::new(tx, RetryConfig::default());
+        let endpoint = WebhookEndpoint::new("Alerts", "https://example.com", "secret");
+        let id = endpoint.id.clone();
+        dispatcher.register_endpoint(endpoint);
+
+        let updated = dispatcher
+            .update_endpoint(
+                &id,
+                EndpointUpdate {
+                    events: Some(vec![WebhookEventType::SystemAlert]),
+                    enabled: Some(false),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(updated.events, vec![WebhookEventType::SystemAlert]);
+        assert!(!updated.enabled);
+        assert_eq!(updated.url, "https://example.com");
+
+        let unknown_schema = EndpointUpdate { schema_version: Some(Some(9)), ..Default::default() };
+        assert!(matches!(
+            dispatcher.update_endpoint(&id, unknown_schema),
+            Err(WebhookError::UnknownSchemaVersion(9))
+        ));
+        let pinned = EndpointUpdate { schema_version: Some(Some(1)), ..Default::default() };
+        assert_eq!(dispatcher.update_endpoint(&id, pinned).unwrap().schema_version, Some(1));
+        let unpinned: EndpointUpdate = serde_json::from_str(r#"{"schema_version": null}"#).unwrap();
+        assert_eq!(dispatcher.update_endpoint(&id, unpinned).unwrap().schema_version, None);
+
+        let bad_url = EndpointUpdate { url: Some("ftp://example.com".to_string()), ..Default::default() };
+        assert!(matches!(dispatcher.update_endpoint(&id, bad_url), Err(WebhookError::InvalidUrl(_))));
+        assert!(matches!(
+            dispatcher.update_endpoint("missing", EndpointUpdate::default()),
+            Err(WebhookError::NotFound(_))
+        ));
+    }
+
     #[test]
     fn test_retry_config_delay() {
         let config = RetryConfig {
@@ -607,4 +961,49 @@ mod tests {
         let deleted = repo.get(&endpoint.id).await.unwrap();
         assert!(deleted.is_none());
     }
+
+    #[tokio::test]
+    async fn test_dispatch_to_checks_the_endpoint() {
+        let (tx, _rx) = mpsc::channel(8);
+        let dispatcher = WebhookDispatcher::new(tx, RetryConfig::default());
+        let mut endpoint = WebhookEndpoint::new("Tickets", "https://example.com", "secret");
+        endpoint.enabled = false;
+        let id = endpoint.id.clone();
+        dispatcher.register_endpoint(endpoint);
+
+        let event = || {
+            WebhookEvent::new(
+                WebhookEventType::SystemAlert,
+                crate::events::WebhookEventData::Custom(crate::events::CustomEventData {
+                    event_name: "test".to_string(),
+                    data: serde_json::Value::Null,
+                }),
+            )
+        };
+        assert!(matches!(dispatcher.dispatch_to("missing", event()).await, Err(WebhookError::NotFound(_))));
+        assert!(matches!(dispatcher.dispatch_to(&id, event()).await, Err(WebhookError::Disabled)));
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_enqueues_with_a_queue() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let queue = Arc::new(crate::queue::InMemoryDeliveryQueue::new());
+        let dispatcher = WebhookDispatcher::new(tx, RetryConfig::default()).with_queue(queue.clone());
+        // Unroutable, so an inline delivery would fail
+        dispatcher.register_endpoint(WebhookEndpoint::new("Alerts", "http://127.0.0.1:9", "secret"));
+
+        let event = WebhookEvent::new(
+            WebhookEventType::SystemAlert,
+            WebhookEventData::Custom(CustomEventData {
+                event_name: "test".to_string(),
+                data: serde_json::Value::Null,
+            }),
+        );
+        let deliveries = dispatcher.dispatch(event).await.unwrap();
+
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status, DeliveryStatus::Pending);
+        assert_eq!(queue.depth().await.unwrap(), 1);
+        assert!(rx.try_recv().is_err());
+    }
 }
@@ -0,0 +1,312 @@
+//! Shared webhook delivery queue
+//!
+//! Lets several server instances pull deliveries from one queue. A claimed
+//! delivery is invisible to other workers until its visibility timeout
+//! expires, after which it is handed out again (at-least-once delivery).
+//!
+//! Deliveries for the same endpoint are handed out strictly in enqueue order:
+//! only the head of an endpoint's queue can be claimed, and it stays at the
+//! head while it is being retried.
+
+use crate::{events::WebhookEvent, Result, WebhookError};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+/// A delivery waiting in the queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedDelivery {
+    /// Delivery ID
+    pub id: String,
+    /// Target endpoint
+    pub endpoint_id: String,
+    /// Event to deliver
+    pub event: WebhookEvent,
+    /// Position in the endpoint's queue
+    pub sequence: u64,
+    /// Attempts made so far
+    pub attempts: u32,
+    /// When the delivery was enqueued
+    pub enqueued_at: DateTime<Utc>,
+    /// Earliest time the delivery may be claimed
+    pub available_at: DateTime<Utc>,
+}
+
+/// A delivery leased to a worker
+#[derive(Debug, Clone)]
+pub struct ClaimedDelivery {
+    pub delivery: QueuedDelivery,
+    /// Identifies this lease; acks with a stale receipt are rejected
+    pub receipt: String,
+    /// When the lease expires and the delivery becomes visible again
+    pub visible_at: DateTime<Utc>,
+}
+
+/// Delivery queue shared by webhook workers
+#[async_trait]
+pub trait DeliveryQueue: Send + Sync {
+    /// Append a delivery to an endpoint's queue
+    async fn enqueue(&self, endpoint_id: &str, event: WebhookEvent) -> Result<QueuedDelivery>;
+
+    /// Lease up to `max` deliveries, at most one per endpoint
+    async fn claim(
+        &self,
+        worker_id: &str,
+        max: usize,
+        visibility_timeout: Duration,
+    ) -> Result<Vec<ClaimedDelivery>>;
+
+    /// Remove a finished delivery from the queue
+    async fn ack(&self, claim: &ClaimedDelivery) -> Result<()>;
+
+    /// Return a delivery to the head of its queue for a retry at `retry_at`
+    async fn nack(&self, claim: &ClaimedDelivery, retry_at: DateTime<Utc>) -> Result<()>;
+
+    /// Number of queued deliveries
+    async fn depth(&self) -> Result<usize>;
+}
+
+/// Generate an ID for a new queued delivery
+pub fn new_delivery_id() -> String {
+    format!("whd_{}", Uuid::new_v4().simple())
+}
+
+/// Error for an ack or nack whose lease has expired or been re-claimed
+pub fn stale_receipt(claim: &ClaimedDelivery) -> WebhookError {
+    WebhookError::NotFound(format!(
+        "Delivery {} is no longer leased by this receipt",
+        claim.delivery.id
+    ))
+}
+
+#[derive(Debug)]
+struct Lease {
+    receipt: String,
+    visible_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct EndpointQueue {
+    items: VecDeque<QueuedDelivery>,
+    /// Lease on the head item, if any
+    lease: Option<Lease>,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    endpoints: HashMap<String, EndpointQueue>,
+    next_sequence: u64,
+}
+
+/// In-memory delivery queue shared by the workers of one process
+#[derive(Debug, Default)]
+pub struct InMemoryDeliveryQueue {
+    state: Mutex<QueueState>,
+}
+
+impl InMemoryDeliveryQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `f` on the head of the claim's endpoint queue if the lease still matches
+    fn with_leased_head<T>(
+        &self,
+        claim: &ClaimedDelivery,
+        f: impl FnOnce(&mut EndpointQueue) -> T,
+    ) -> Result<T> {
+        let mut state = self.state.lock();
+        let queue = state
+            .endpoints
+            .get_mut(&claim.delivery.endpoint_id)
+            .filter(|q| {
+                q.lease.as_ref().is_some_and(|l| l.receipt == claim.receipt)
+                    && q.items.front().is_some_and(|d| d.id == claim.delivery.id)
+            })
+            .ok_or_else(|| stale_receipt(claim))?;
+        Ok(f(queue))
+    }
+}
+
+#[async_trait]
+impl DeliveryQueue for InMemoryDeliveryQueue {
+    async fn enqueue(&self, endpoint_id: &str, event: WebhookEvent) -> Result<QueuedDelivery> {
+        let mut state = self.state.lock();
+        state.next_sequence += 1;
+        let now = Utc::now();
+        let delivery = QueuedDelivery {
+            id: new_delivery_id(),
+            endpoint_id: endpoint_id.to_string(),
+            event,
+            sequence: state.next_sequence,
+            attempts: 0,
+            enqueued_at: now,
+            available_at: now,
+        };
+        state
+            .endpoints
+            .entry(endpoint_id.to_string())
+            .or_default()
+            .items
+            .push_back(delivery.clone());
+        Ok(delivery)
+    }
+
+    async fn claim(
+        &self,
+        _worker_id: &str,
+        max: usize,
+        visibility_timeout: Duration,
+    ) -> Result<Vec<ClaimedDelivery>> {
+        let mut state = self.state.lock();
+        let now = Utc::now();
+
+        let mut ready: Vec<&mut EndpointQueue> = state
+            .endpoints
+            .values_mut()
+            .filter(|q| {
+                q.lease.as_ref().map_or(true, |l| l.visible_at <= now)
+                    && q.items.front().is_some_and(|d| d.available_at <= now)
+            })
+            .collect();
+        ready.sort_by_key(|q| q.items.front().map(|d| d.available_at));
+
+        let visible_at = now + visibility_timeout;
+        Ok(ready
+            .into_iter()
+            .take(max)
+            .filter_map(|queue| {
+                let receipt = Uuid::new_v4().to_string();
+                queue.lease = Some(Lease {
+                    receipt: receipt.clone(),
+                    visible_at,
+                });
+                queue.items.front().map(|delivery| ClaimedDelivery {
+                    delivery: delivery.clone(),
+                    receipt,
+                    visible_at,
+                })
+            })
+            .collect())
+    }
+
+    async fn ack(&self, claim: &ClaimedDelivery) -> Result<()> {
+        self.with_leased_head(claim, |queue| {
+            queue.items.pop_front();
+            queue.lease = None;
+        })
+    }
+
+    async fn nack(&self, claim: &ClaimedDelivery, retry_at: DateTime<Utc>) -> Result<()> {
+        self.with_leased_head(claim, |queue| {
+            if let Some(head) = queue.items.front_mut() {
+                head.attempts += 1;
+                head.available_at = retry_at;
+            }
+            queue.lease = None;
+        })
+    }
+
+    async fn depth(&self) -> Result<usize> {
+        Ok(self
+            .state
+            .lock()
+            .endpoints
+            .values()
+            .map(|q| q.items.len())
+            .sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{CustomEventData, WebhookEventData, WebhookEventType};
+
+    fn event() -> WebhookEvent {
+        WebhookEvent::new(
+            WebhookEventType::Custom,
+            WebhookEventData::Custom(CustomEventData {
+                event_name: "test".to_string(),
+                data: serde_json::json!({"n": 1}),
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_per_endpoint_ordering() {
+        let queue = InMemoryDeliveryQueue::new();
+        let first = queue.enqueue("we_a", event()).await.unwrap();
+        let second = queue.enqueue("we_a", event()).await.unwrap();
+        queue.enqueue("we_b", event()).await.unwrap();
+
+        // One delivery per endpoint, never the second one for we_a
+        let claims = queue.claim("w1", 10, Duration::seconds(30)).await.unwrap();
+        assert_eq!(claims.len(), 2);
+        let a = claims
+            .iter()
+            .find(|c| c.delivery.endpoint_id == "we_a")
+            .unwrap();
+        assert_eq!(a.delivery.id, first.id);
+        assert!(queue
+            .claim("w2", 10, Duration::seconds(30))
+            .await
+            .unwrap()
+            .is_empty());
+
+        queue.ack(a).await.unwrap();
+        let claims = queue.claim("w2", 10, Duration::seconds(30)).await.unwrap();
+        assert_eq!(claims.len(), 1);
+        assert_eq!(claims[0].delivery.id, second.id);
+        assert_eq!(queue.depth().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_visibility_timeout_and_stale_ack() {
+        let queue = InMemoryDeliveryQueue::new();
+        queue.enqueue("we_a", event()).await.unwrap();
+
+        let stale = queue
+            .claim("w1", 1, Duration::zero())
+            .await
+            .unwrap()
+            .remove(0);
+        let fresh = queue
+            .claim("w2", 1, Duration::seconds(30))
+            .await
+            .unwrap()
+            .remove(0);
+        assert_eq!(stale.delivery.id, fresh.delivery.id);
+
+        assert!(queue.ack(&stale).await.is_err());
+        queue.ack(&fresh).await.unwrap();
+        assert_eq!(queue.depth().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_nack_keeps_head_and_delays_retry() {
+        let queue = InMemoryDeliveryQueue::new();
+        queue.enqueue("we_a", event()).await.unwrap();
+        queue.enqueue("we_a", event()).await.unwrap();
+
+        let claim = queue
+            .claim("w1", 1, Duration::seconds(30))
+            .await
+            .unwrap()
+            .remove(0);
+        queue
+            .nack(&claim, Utc::now() + Duration::hours(1))
+            .await
+            .unwrap();
+
+        // The retried head blocks later deliveries to the same endpoint
+        assert!(queue
+            .claim("w1", 10, Duration::seconds(30))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,285 @@
+//! Webhook payload schema registry
+//!
+//! Payload schemas are versioned as a whole: each version may change the
+//! shape of some payload objects (`conversation`, `task`, ...) and registers
+//! how to turn the new shape back into the previous one. Deliveries carry
+//! the version their data follows, and endpoints pinned to an older version
+//! receive payloads downgraded to it.
+
+use crate::{
+    events::{
+        ApiKeyEventData, ApprovalEventData, ContextEventData, ConversationEventData, CustomEventData,
+        HandoffEventData, InvoiceEventData, MessageEventData, QuotaEventData, SandboxEventData, SlaEventData,
+        SubscriptionEventData, SystemEventData, TaskEventData, TenantEventData, UserEventData, WebhookEvent,
+        WorkflowEventData,
+    },
+    Result, WebhookError,
+};
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// Schema name of the event envelope, next to the payload objects
+pub const ENVELOPE_SCHEMA: &str = "event";
+
+/// Turns an object's data of one schema version into the previous version
+pub type Downgrade = fn(Value) -> Value;
+
+/// How one payload object changes in a new schema version
+pub struct ObjectChange {
+    /// Object tag, e.g. `task`
+    pub object: String,
+    /// JSON Schema of the object in the new version
+    pub schema: Value,
+    /// Conversion of the new shape into the previous version's
+    pub downgrade: Downgrade,
+}
+
+impl ObjectChange {
+    pub fn new(object: &str, schema: Value, downgrade: Downgrade) -> Self {
+        Self {
+            object: object.to_string(),
+            schema,
+            downgrade,
+        }
+    }
+}
+
+/// A published payload schema version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaVersionInfo {
+    pub version: u32,
+    /// What changed since the previous version
+    pub description: String,
+    /// Objects whose shape changed in this version
+    pub changed_objects: Vec<String>,
+    /// Whether deliveries to unpinned endpoints use this version
+    pub current: bool,
+}
+
+/// JSON Schemas of the envelope and every payload object in one version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayloadSchemas {
+    pub version: u32,
+    pub schemas: BTreeMap<String, Value>,
+}
+
+struct SchemaVersion {
+    info: SchemaVersionInfo,
+    schemas: BTreeMap<String, Value>,
+    downgrades: HashMap<String, Downgrade>,
+}
+
+/// Registry of the payload schema versions deliveries can be rendered in
+pub struct SchemaRegistry {
+    versions: Vec<SchemaVersion>,
+}
+
+impl SchemaRegistry {
+    /// The schemas of the payloads this crate defines, as version 1
+    pub fn builtin() -> Self {
+        let mut schemas = BTreeMap::new();
+        schemas.insert(ENVELOPE_SCHEMA.to_string(), to_value(schema_for!(WebhookEvent)));
+        schemas.insert("conversation".to_string(), object_schema::<ConversationEventData>("conversation"));
+        schemas.insert("message".to_string(), object_schema::<MessageEventData>("message"));
+        schemas.insert("handoff".to_string(), object_schema::<HandoffEventData>("handoff"));
+        schemas.insert("workflow".to_string(), object_schema::<WorkflowEventData>("workflow"));
+        schemas.insert("approval".to_string(), object_schema::<ApprovalEventData>("approval"));
+        schemas.insert("sandbox".to_string(), object_schema::<SandboxEventData>("sandbox"));
+        schemas.insert("task".to_string(), object_schema::<TaskEventData>("task"));
+        schemas.insert("user".to_string(), object_schema::<UserEventData>("user"));
+        schemas.insert("tenant".to_string(), object_schema::<TenantEventData>("tenant"));
+        schemas.insert("api_key".to_string(), object_schema::<ApiKeyEventData>("api_key"));
+        schemas.insert("context".to_string(), object_schema::<ContextEventData>("context"));
+        schemas.insert("subscription".to_string(), object_schema::<SubscriptionEventData>("subscription"));
+        schemas.insert("invoice".to_string(), object_schema::<InvoiceEventData>("invoice"));
+        schemas.insert("quota".to_string(), object_schema::<QuotaEventData>("quota"));
+        schemas.insert("sla".to_string(), object_schema::<SlaEventData>("sla"));
+        schemas.insert("system".to_string(), object_schema::<SystemEventData>("system"));
+        schemas.insert("custom".to_string(), object_schema::<CustomEventData>("custom"));
+
+        Self {
+            versions: vec![SchemaVersion {
+                info: SchemaVersionInfo {
+                    version: 1,
+                    description: "Initial payload schemas".to_string(),
+                    changed_objects: schemas.keys().cloned().collect(),
+                    current: true,
+                },
+                schemas,
+                downgrades: HashMap::new(),
+            }],
+        }
+    }
+
+    /// Add the next version, in which `changes` replace their objects'
+    /// schemas and every other object keeps its shape
+    pub fn with_version(mut self, description: &str, changes: Vec<ObjectChange>) -> Self {
+        let previous = self.versions.last().expect("registry has a version");
+        let mut schemas = previous.schemas.clone();
+        let mut downgrades = HashMap::new();
+        let mut changed_objects = Vec::new();
+        for change in changes {
+            schemas.insert(change.object.clone(), change.schema);
+            downgrades.insert(change.object.clone(), change.downgrade);
+            changed_objects.push(change.object);
+        }
+
+        let version = previous.info.version + 1;
+        for existing in &mut self.versions {
+            existing.info.current = false;
+        }
+        self.versions.push(SchemaVersion {
+            info: SchemaVersionInfo {
+                version,
+                description: description.to_string(),
+                changed_objects,
+                current: true,
+            },
+            schemas,
+            downgrades,
+        });
+        self
+    }
+
+    /// Version deliveries to unpinned endpoints use
+    pub fn current_version(&self) -> u32 {
+        self.versions.last().map(|v| v.info.version).unwrap_or(1)
+    }
+
+    /// Published versions, oldest first
+    pub fn versions(&self) -> Vec<SchemaVersionInfo> {
+        self.versions.iter().map(|v| v.info.clone()).collect()
+    }
+
+    /// JSON Schemas of a version
+    pub fn schemas(&self, version: u32) -> Option<PayloadSchemas> {
+        self.version(version).map(|v| PayloadSchemas {
+            version,
+            schemas: v.schemas.clone(),
+        })
+    }
+
+    /// Check that endpoints can be pinned to `version`
+    pub fn check_version(&self, version: u32) -> Result<()> {
+        match self.version(version) {
+            Some(_) => Ok(()),
+            None => Err(WebhookError::UnknownSchemaVersion(version)),
+        }
+    }
+
+    /// Serialize an event for delivery in schema version `pinned`, or the
+    /// current version when unpinned
+    pub fn render(&self, event: &WebhookEvent, pinned: Option<u32>) -> Result<Vec<u8>> {
+        let target = pinned.unwrap_or_else(|| self.current_version());
+        self.check_version(target)?;
+
+        let mut event = event.clone();
+        event.schema_version = Some(target);
+        let object = event.data.object();
+        let mut value = serde_json::to_value(&event).map_err(|e| WebhookError::Serialization(e.to_string()))?;
+
+        for version in self.versions.iter().rev().take_while(|v| v.info.version > target) {
+            if let Some(downgrade) = version.downgrades.get(object) {
+                if let Some(data) = value.get_mut("data") {
+                    *data = downgrade(data.take());
+                }
+            }
+        }
+
+        serde_json::to_vec(&value).map_err(|e| WebhookError::Serialization(e.to_string()))
+    }
+
+    fn version(&self, version: u32) -> Option<&SchemaVersion> {
+        self.versions.iter().find(|v| v.info.version == version)
+    }
+}
+
+impl Default for SchemaRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+fn to_value<T: Serialize>(schema: T) -> Value {
+    serde_json::to_value(schema).unwrap_or_default()
+}
+
+/// Schema of a payload object, including the `object` tag it is sent with
+fn object_schema<T: JsonSchema>(object: &str) -> Value {
+    let mut schema = to_value(schema_for!(T));
+    if let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) {
+        properties.insert("object".to_string(), serde_json::json!({ "const": object }));
+    }
+    if let Some(required) = schema.get_mut("required").and_then(Value::as_array_mut) {
+        required.insert(0, Value::String("object".to_string()));
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{WebhookEventData, WebhookEventType};
+
+    fn task_event() -> WebhookEvent {
+        WebhookEvent::new(
+            WebhookEventType::AgentTaskCompleted,
+            WebhookEventData::Task(TaskEventData {
+                id: "task-1".to_string(),
+                kind: "agent".to_string(),
+                name: "Summarize".to_string(),
+                status: "completed".to_string(),
+                user_id: None,
+                started_at: None,
+                finished_at: chrono::Utc::now(),
+                error: None,
+                details: None,
+            }),
+        )
+    }
+
+    fn drop_details(mut data: Value) -> Value {
+        if let Some(object) = data.as_object_mut() {
+            object.remove("details");
+        }
+        data
+    }
+
+    #[test]
+    fn test_builtin_schemas() {
+        let registry = SchemaRegistry::builtin();
+        assert_eq!(registry.current_version(), 1);
+
+        let schemas = registry.schemas(1).unwrap();
+        assert!(schemas.schemas.contains_key(ENVELOPE_SCHEMA));
+        let task = &schemas.schemas["task"];
+        assert_eq!(task["properties"]["object"]["const"], "task");
+        assert_eq!(task["required"][0], "object");
+        assert!(registry.schemas(2).is_none());
+    }
+
+    #[test]
+    fn test_render_downgrades_for_pinned_endpoints() {
+        let registry = SchemaRegistry::builtin().with_version(
+            "Task details added",
+            vec![ObjectChange::new("task", serde_json::json!({}), drop_details)],
+        );
+        assert_eq!(registry.current_version(), 2);
+        assert!(!registry.versions()[0].current);
+        assert_eq!(registry.versions()[1].changed_objects, vec!["task"]);
+
+        let event = task_event();
+        let current: Value = serde_json::from_slice(&registry.render(&event, None).unwrap()).unwrap();
+        assert_eq!(current["schema_version"], 2);
+        assert!(current["data"].get("details").is_some());
+
+        let pinned: Value = serde_json::from_slice(&registry.render(&event, Some(1)).unwrap()).unwrap();
+        assert_eq!(pinned["schema_version"], 1);
+        assert!(pinned["data"].get("details").is_none());
+        assert_eq!(pinned["data"]["object"], "task");
+
+        assert!(matches!(registry.render(&event, Some(3)), Err(WebhookError::UnknownSchemaVersion(3))));
+    }
+}
@@ -60,6 +60,7 @@ impl Default for SignatureConfig {
 /// Webhook signer for generating signatures
 pub struct WebhookSigner {
     secret: Vec<u8>,
+    previous_secret: Option<Vec<u8>>,
     config: SignatureConfig,
 }
 
@@ -68,6 +69,7 @@ impl WebhookSigner {
     pub fn new(secret: &str) -> Self {
         Self {
             secret: secret.as_bytes().to_vec(),
+            previous_secret: None,
             config: SignatureConfig::default(),
         }
     }
@@ -76,10 +78,18 @@ impl WebhookSigner {
     pub fn with_config(secret: &str, config: SignatureConfig) -> Self {
         Self {
             secret: secret.as_bytes().to_vec(),
+            previous_secret: None,
             config,
         }
     }
 
+    /// Also sign with a secret being rotated out, so receivers that have not
+    /// switched to the new secret yet still verify deliveries
+    pub fn with_previous_secret(mut self, secret: &str) -> Self {
+        self.previous_secret = Some(secret.as_bytes().to_vec());
+        self
+    }
+
     /// Generate a signature for a payload
     pub fn sign(&self, payload: &[u8], timestamp: DateTime<Utc>) -> String {
         let timestamp_str = timestamp.timestamp().to_string();
@@ -87,10 +97,17 @@ impl WebhookSigner {
 
         let signature = self.compute_signature(message.as_bytes());
 
-        format!(
+        let mut header = format!(
             "{}={},{}={}",
             "t", timestamp_str, self.config.algorithm.as_str(), signature
-        )
+        );
+        if let Some(previous) = &self.previous_secret {
+            let mut mac = HmacSha256::new_from_slice(previous).expect("HMAC can accept any key length");
+            mac.update(message.as_bytes());
+            let previous_signature = hex::encode(mac.finalize().into_bytes());
+            header.push_str(&format!(",{}={}", self.config.algorithm.as_str(), previous_signature));
+        }
+        header
     }
 
     /// Generate signature with current timestamp
@@ -306,6 +323,17 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_rotation_signs_with_both_secrets() {
+        let signer = WebhookSigner::new("new-secret").with_previous_secret("old-secret");
+        let payload = b"test payload";
+        let (signature, _) = signer.sign_now(payload);
+
+        assert!(WebhookVerifier::new("new-secret").verify(payload, &signature).is_ok());
+        assert!(WebhookVerifier::new("old-secret").verify(payload, &signature).is_ok());
+        assert!(WebhookVerifier::new("other-secret").verify(payload, &signature).is_err());
+    }
+
     #[test]
     fn test_get_headers() {
         let signer = WebhookSigner::new("test-secret");
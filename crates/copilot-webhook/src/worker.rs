@@ -0,0 +1,306 @@
+//! Webhook delivery workers
+//!
+//! Each server instance runs a [`DeliveryWorker`] against the shared
+//! [`DeliveryQueue`]. Throughput grows with the number of workers, while the
+//! queue's one-in-flight-per-endpoint rule keeps each endpoint's deliveries
+//! in order.
+
+use crate::{
+    delivery::{DeliveryAttempt, DeliveryStatus, WebhookDelivery},
+    outbound::WebhookDispatcher,
+    queue::{ClaimedDelivery, DeliveryQueue},
+    Result,
+};
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+/// Delivery worker settings
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    /// Identity of this worker, used for lease bookkeeping
+    pub worker_id: String,
+    /// Deliveries claimed per poll
+    pub batch_size: usize,
+    /// How long a claimed delivery stays hidden from other workers; must
+    /// exceed the HTTP timeout of a delivery attempt
+    pub visibility_timeout: Duration,
+    /// Wait between polls when the queue is empty
+    pub poll_interval: std::time::Duration,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self {
+            worker_id: format!("worker_{}", Uuid::new_v4().simple()),
+            batch_size: 16,
+            visibility_timeout: Duration::seconds(60),
+            poll_interval: std::time::Duration::from_secs(1),
+        }
+    }
+}
+
+/// What happened to a claimed delivery
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerOutcome {
+    Delivered,
+    /// Returned to the queue for another attempt at the given time
+    Retrying(DateTime<Utc>),
+    /// Dropped after a permanent failure or the last allowed attempt
+    Failed,
+    /// The endpoint no longer exists or is disabled
+    Skipped,
+}
+
+/// Pulls deliveries from a shared queue and sends them
+pub struct DeliveryWorker {
+    queue: Arc<dyn DeliveryQueue>,
+    dispatcher: Arc<WebhookDispatcher>,
+    config: WorkerConfig,
+    running: RwLock<bool>,
+}
+
+impl DeliveryWorker {
+    pub fn new(queue: Arc<dyn DeliveryQueue>, dispatcher: Arc<WebhookDispatcher>) -> Self {
+        Self {
+            queue,
+            dispatcher,
+            config: WorkerConfig::default(),
+            running: RwLock::new(false),
+        }
+    }
+
+    pub fn with_config(mut self, config: WorkerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Poll the queue until [`stop`](Self::stop) is called
+    pub async fn run(&self) {
+        {
+            let mut running = self.running.write().await;
+            if *running {
+                warn!(worker_id = %self.config.worker_id, "Delivery worker already running");
+                return;
+            }
+            *running = true;
+        }
+
+        info!(worker_id = %self.config.worker_id, "Starting webhook delivery worker");
+
+        while *self.running.read().await {
+            match self.process_batch().await {
+                Ok(0) => tokio::time::sleep(self.config.poll_interval).await,
+                Ok(_) => {}
+                Err(e) => {
+                    error!(worker_id = %self.config.worker_id, error = %e, "Failed to claim deliveries");
+                    tokio::time::sleep(self.config.poll_interval).await;
+                }
+            }
+        }
+
+        info!(worker_id = %self.config.worker_id, "Webhook delivery worker stopped");
+    }
+
+    /// Stop the worker after its current batch
+    pub async fn stop(&self) {
+        *self.running.write().await = false;
+    }
+
+    /// Claim and process one batch, returning the number of deliveries handled
+    ///
+    /// Claimed deliveries target distinct endpoints, so they are sent
+    /// concurrently.
+    pub async fn process_batch(&self) -> Result<usize> {
+        let claims = self
+            .queue
+            .claim(
+                &self.config.worker_id,
+                self.config.batch_size,
+                self.config.visibility_timeout,
+            )
+            .await?;
+
+        let count = claims.len();
+        futures::future::join_all(claims.iter().map(|claim| self.process(claim))).await;
+        Ok(count)
+    }
+
+    async fn process(&self, claim: &ClaimedDelivery) -> WorkerOutcome {
+        let queued = &claim.delivery;
+        let outcome = self.deliver(claim).await;
+
+        let settled = match outcome {
+            WorkerOutcome::Retrying(retry_at) => self.queue.nack(claim, retry_at).await,
+            _ => self.queue.ack(claim).await,
+        };
+
+        // Another worker re-claimed the delivery after our lease expired
+        if let Err(e) = settled {
+            warn!(delivery_id = %queued.id, error = %e, "Lost lease on webhook delivery");
+        }
+        outcome
+    }
+
+    async fn deliver(&self, claim: &ClaimedDelivery) -> WorkerOutcome {
+        let queued = &claim.delivery;
+        let retry_config = self.dispatcher.retry_config();
+        let endpoint = match self.dispatcher.get_endpoint(&queued.endpoint_id) {
+            Some(endpoint) if endpoint.enabled => endpoint,
+            // Endpoints registered through the API live on the replica that
+            // registered them, so leave the delivery for a worker there
+            None if queued.attempts < retry_config.max_attempts => {
+                debug!(
+                    delivery_id = %queued.id,
+                    endpoint_id = %queued.endpoint_id,
+                    "Endpoint unknown to this worker, returning delivery to the queue"
+                );
+                return WorkerOutcome::Retrying(Utc::now() + retry_config.calculate_delay(queued.attempts + 1));
+            }
+            _ => {
+                debug!(
+                    delivery_id = %queued.id,
+                    endpoint_id = %queued.endpoint_id,
+                    "Skipping delivery to missing or disabled endpoint"
+                );
+                return WorkerOutcome::Skipped;
+            }
+        };
+
+        let payload = self.dispatcher.payload(&endpoint, &queued.event);
+        let attempt = self
+            .dispatcher
+            .attempt_delivery(&endpoint, &payload, queued.attempts)
+            .await;
+
+        let outcome = match attempt.status {
+            DeliveryStatus::Delivered => WorkerOutcome::Delivered,
+            DeliveryStatus::Retrying if queued.attempts < retry_config.max_attempts => {
+                let delay = retry_config.calculate_delay(queued.attempts + 1);
+                WorkerOutcome::Retrying(Utc::now() + delay)
+            }
+            _ => {
+                warn!(
+                    delivery_id = %queued.id,
+                    endpoint_id = %queued.endpoint_id,
+                    attempts = queued.attempts + 1,
+                    "Webhook delivery failed"
+                );
+                WorkerOutcome::Failed
+            }
+        };
+
+        self.dispatcher
+            .record_delivery(delivery_record(claim, attempt, outcome, payload.len()))
+            .await;
+        outcome
+    }
+}
+
+/// Delivery record for the attempt made under this claim
+fn delivery_record(
+    claim: &ClaimedDelivery,
+    attempt: DeliveryAttempt,
+    outcome: WorkerOutcome,
+    payload_size: usize,
+) -> WebhookDelivery {
+    let queued = &claim.delivery;
+    let (status, next_retry_at) = match outcome {
+        WorkerOutcome::Delivered => (DeliveryStatus::Delivered, None),
+        WorkerOutcome::Retrying(retry_at) => (DeliveryStatus::Retrying, Some(retry_at)),
+        _ => (DeliveryStatus::Failed, None),
+    };
+    let completed_at = next_retry_at.is_none().then_some(attempt.completed_at);
+    WebhookDelivery {
+        id: queued.id.clone(),
+        endpoint_id: queued.endpoint_id.clone(),
+        event_id: queued.event.id.clone(),
+        event_type: queued.event.event_type,
+        status,
+        completed_at,
+        attempts: vec![attempt],
+        payload_size,
+        created_at: queued.enqueued_at,
+        next_retry_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{CustomEventData, WebhookEvent, WebhookEventData, WebhookEventType};
+    use crate::outbound::{RetryConfig, WebhookEndpoint};
+    use crate::queue::InMemoryDeliveryQueue;
+    use axum::{extract::State, routing::post, Router};
+    use parking_lot::Mutex;
+    use tokio::sync::mpsc;
+
+    fn event(n: u64) -> WebhookEvent {
+        WebhookEvent::new(
+            WebhookEventType::Custom,
+            WebhookEventData::Custom(CustomEventData {
+                event_name: "test".to_string(),
+                data: serde_json::json!({ "n": n }),
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_workers_deliver_in_order_without_duplicates() {
+        // Local receiver that records the sequence numbers it sees
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    |State(received): State<Arc<Mutex<Vec<u64>>>>, body: String| async move {
+                        let event: serde_json::Value = serde_json::from_str(&body).unwrap();
+                        received
+                            .lock()
+                            .push(event["data"]["data"]["n"].as_u64().unwrap());
+                    },
+                ),
+            )
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let (tx, mut rx) = mpsc::channel(100);
+        let dispatcher = Arc::new(WebhookDispatcher::new(tx, RetryConfig::default()));
+        let endpoint = WebhookEndpoint::new("Test", &format!("http://{}/hook", addr), "secret");
+        dispatcher.register_endpoint(endpoint);
+
+        let queue: Arc<dyn DeliveryQueue> = Arc::new(InMemoryDeliveryQueue::new());
+        for n in 0..5 {
+            dispatcher.enqueue(queue.as_ref(), event(n)).await.unwrap();
+        }
+
+        // Two workers, as if on two replicas, drain the queue together
+        let a = DeliveryWorker::new(queue.clone(), dispatcher.clone());
+        let b = DeliveryWorker::new(queue.clone(), dispatcher.clone());
+        while queue.depth().await.unwrap() > 0 {
+            let (x, y) = tokio::join!(a.process_batch(), b.process_batch());
+            assert!(x.unwrap() + y.unwrap() <= 1);
+        }
+
+        assert_eq!(*received.lock(), vec![0, 1, 2, 3, 4]);
+        for _ in 0..5 {
+            assert_eq!(rx.recv().await.unwrap().status, DeliveryStatus::Delivered);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_endpoint_is_left_for_another_worker() {
+        let (tx, _rx) = mpsc::channel(8);
+        let dispatcher = Arc::new(WebhookDispatcher::new(tx, RetryConfig::default()));
+        let queue: Arc<dyn DeliveryQueue> = Arc::new(InMemoryDeliveryQueue::new());
+        queue.enqueue("we_elsewhere", event(0)).await.unwrap();
+
+        let worker = DeliveryWorker::new(queue.clone(), dispatcher);
+        assert_eq!(worker.process_batch().await.unwrap(), 1);
+        assert_eq!(queue.depth().await.unwrap(), 1);
+    }
+}
@@ -1,9 +1,18 @@
 //! Approval gate implementation for workflow steps
-
+//!
+//! A request is decided by anyone, or under an [`ApprovalPolicy`] by the
+//! members of an [`ApproverGroup`]: it is approved once `quorum` members
+//! approve and denied once so many deny that the quorum is out of reach.
+//! A request still pending after the policy's escalation delay may also be
+//! decided by a secondary group. Members can hand their vote to someone else
+//! with a [`Delegation`]. Every vote, escalation and delegation is written
+//! to the `audit` log target and kept on the request.
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use uuid::Uuid;
 
 /// Status of an approval request
@@ -22,6 +31,161 @@ pub enum ApprovalStatus {
     Cancelled,
 }
 
+/// A named set of approvers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApproverGroup {
+    pub name: String,
+    pub members: Vec<String>,
+}
+
+impl ApproverGroup {
+    pub fn new<I, S>(name: impl Into<String>, members: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            members: members.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Opens a request to a secondary group when it stays pending too long
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Escalation {
+    /// Group that may decide once the request is escalated
+    pub group: String,
+    /// Seconds after creation before escalating
+    pub after_secs: u64,
+}
+
+/// Who decides a request and how many approvals it needs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    /// Group whose members decide; anyone may when unset
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Approvals needed, the M of M-of-N
+    #[serde(default = "default_quorum")]
+    pub quorum: usize,
+    #[serde(default)]
+    pub escalation: Option<Escalation>,
+}
+
+fn default_quorum() -> usize {
+    1
+}
+
+impl Default for ApprovalPolicy {
+    fn default() -> Self {
+        Self {
+            group: None,
+            quorum: default_quorum(),
+            escalation: None,
+        }
+    }
+}
+
+impl ApprovalPolicy {
+    /// `quorum` approvals from members of `group`
+    pub fn quorum(group: impl Into<String>, quorum: usize) -> Self {
+        Self {
+            group: Some(group.into()),
+            quorum: quorum.max(1),
+            escalation: None,
+        }
+    }
+
+    /// Let `group` decide too once the request is pending for `after_secs`
+    pub fn escalate_to(mut self, group: impl Into<String>, after_secs: u64) -> Self {
+        self.escalation = Some(Escalation {
+            group: group.into(),
+            after_secs,
+        });
+        self
+    }
+}
+
+/// A single approver's vote
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Vote {
+    Approve,
+    Deny,
+}
+
+/// A vote cast on a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalDecision {
+    /// Who voted
+    pub approver: String,
+    /// Member the approver voted for through a delegation
+    #[serde(default)]
+    pub on_behalf_of: Option<String>,
+    pub vote: Vote,
+    #[serde(default)]
+    pub message: Option<String>,
+    pub decided_at: DateTime<Utc>,
+}
+
+impl ApprovalDecision {
+    /// Member the vote counts for
+    pub fn principal(&self) -> &str {
+        self.on_behalf_of.as_deref().unwrap_or(&self.approver)
+    }
+}
+
+/// A member's vote handed to someone else
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Delegation {
+    pub id: String,
+    /// Member delegating
+    pub from: String,
+    /// Who votes in their place
+    pub to: String,
+    /// Group the delegation is limited to; every group when unset
+    #[serde(default)]
+    pub group: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl Delegation {
+    pub fn new(from: impl Into<String>, to: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            from: from.into(),
+            to: to.into(),
+            group: None,
+            created_at: Utc::now(),
+            expires_at: None,
+            revoked_at: None,
+        }
+    }
+
+    /// Limit the delegation to requests of `group`
+    pub fn for_group(mut self, group: impl Into<String>) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    pub fn until(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Whether the delegation lets `to` vote for `from` in `group` now
+    fn applies(&self, group: Option<&str>, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none()
+            && self.expires_at.map_or(true, |expires_at| now < expires_at)
+            && (self.group.is_none() || self.group.as_deref() == group)
+    }
+}
+
 /// Approval request information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApprovalRequest {
@@ -55,6 +219,15 @@ pub struct ApprovalRequest {
     /// Notification channels to use
     #[serde(default)]
     pub notification_channels: Vec<String>,
+    /// Who decides and how many approvals are needed
+    #[serde(default)]
+    pub policy: ApprovalPolicy,
+    /// Votes cast so far, oldest first
+    #[serde(default)]
+    pub decisions: Vec<ApprovalDecision>,
+    /// When the request was opened to the escalation group
+    #[serde(default)]
+    pub escalated_at: Option<DateTime<Utc>>,
 }
 
 impl ApprovalRequest {
@@ -82,6 +255,9 @@ impl ApprovalRequest {
             responded_at: None,
             response_message: None,
             notification_channels: Vec::new(),
+            policy: ApprovalPolicy::default(),
+            decisions: Vec::new(),
+            escalated_at: None,
         }
     }
 
@@ -97,6 +273,25 @@ impl ApprovalRequest {
         self
     }
 
+    /// Decide under `policy` instead of by the first response
+    pub fn with_policy(mut self, policy: ApprovalPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Whether the request is due for escalation
+    pub fn is_escalation_due(&self) -> bool {
+        let Some(escalation) = &self.policy.escalation else {
+            return false;
+        };
+        if self.status != ApprovalStatus::Pending || self.escalated_at.is_some() {
+            return false;
+        }
+
+        let elapsed = Utc::now().signed_duration_since(self.created_at).num_seconds() as u64;
+        elapsed >= escalation.after_secs
+    }
+
     /// Check if the approval has timed out
     pub fn is_timed_out(&self) -> bool {
         if self.status != ApprovalStatus::Pending {
@@ -148,6 +343,12 @@ impl ApprovalRequest {
 pub struct ApprovalGate {
     /// Pending approval requests
     requests: Arc<RwLock<HashMap<String, ApprovalRequest>>>,
+    /// Approver groups by name
+    groups: Arc<RwLock<HashMap<String, ApproverGroup>>>,
+    /// Delegations, including expired and revoked ones
+    delegations: Arc<RwLock<Vec<Delegation>>>,
+    /// Newly created requests
+    requested: broadcast::Sender<ApprovalRequest>,
 }
 
 impl Default for ApprovalGate {
@@ -161,13 +362,71 @@ impl ApprovalGate {
     pub fn new() -> Self {
         Self {
             requests: Arc::new(RwLock::new(HashMap::new())),
+            groups: Arc::new(RwLock::new(HashMap::new())),
+            delegations: Arc::new(RwLock::new(Vec::new())),
+            requested: broadcast::channel(256).0,
         }
     }
 
+    /// Register `group`, replacing a group of the same name
+    pub async fn register_group(&self, group: ApproverGroup) {
+        tracing::info!(
+            target: "audit",
+            group = %group.name,
+            members = group.members.len(),
+            "Approver group registered"
+        );
+        self.groups.write().await.insert(group.name.clone(), group);
+    }
+
+    /// The approver group called `name`
+    pub async fn group(&self, name: &str) -> Option<ApproverGroup> {
+        self.groups.read().await.get(name).cloned()
+    }
+
+    /// Record `delegation`, returning its ID
+    pub async fn delegate(&self, delegation: Delegation) -> String {
+        tracing::info!(
+            target: "audit",
+            delegation_id = %delegation.id,
+            from = %delegation.from,
+            to = %delegation.to,
+            group = delegation.group.as_deref().unwrap_or("*"),
+            "Approval delegated"
+        );
+        let id = delegation.id.clone();
+        self.delegations.write().await.push(delegation);
+        id
+    }
+
+    /// Revoke a delegation; the record is kept
+    pub async fn revoke_delegation(&self, delegation_id: &str) -> Result<(), String> {
+        let mut delegations = self.delegations.write().await;
+        let delegation = delegations
+            .iter_mut()
+            .find(|d| d.id == delegation_id)
+            .ok_or_else(|| format!("Delegation not found: {}", delegation_id))?;
+        delegation.revoked_at.get_or_insert_with(Utc::now);
+
+        tracing::info!(target: "audit", delegation_id = %delegation_id, "Approval delegation revoked");
+        Ok(())
+    }
+
+    /// Every delegation recorded, oldest first
+    pub async fn delegations(&self) -> Vec<Delegation> {
+        self.delegations.read().await.clone()
+    }
+
+    /// Subscribe to approval requests created from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ApprovalRequest> {
+        self.requested.subscribe()
+    }
+
     /// Request approval for a workflow step
     pub async fn request_approval(&self, request: ApprovalRequest) -> String {
         let id = request.id.clone();
         let mut requests = self.requests.write().await;
+        let _ = self.requested.send(request.clone());
         requests.insert(id.clone(), request);
 
         tracing::info!(
@@ -183,17 +442,7 @@ impl ApprovalGate {
         let mut requests = self.requests.write().await;
 
         if let Some(request) = requests.get_mut(approval_id) {
-            // Check for timeout
-            if request.is_timed_out() && request.status == ApprovalStatus::Pending {
-                let timed_out = std::mem::replace(request, request.clone().timeout());
-                *request = timed_out;
-
-                tracing::warn!(
-                    approval_id = %approval_id,
-                    "Approval request timed out"
-                );
-            }
-
+            Self::refresh(request);
             Some(request.status.clone())
         } else {
             None
@@ -227,76 +476,188 @@ impl ApprovalGate {
     }
 
     /// Approve a request
+    ///
+    /// Under a quorum policy this is one vote; the request stays pending
+    /// until enough members approve.
     pub async fn approve(
         &self,
         approval_id: &str,
         approver: impl Into<String>,
         message: Option<String>,
     ) -> Result<(), String> {
-        let mut requests = self.requests.write().await;
-
-        if let Some(request) = requests.get_mut(approval_id) {
-            if request.status != ApprovalStatus::Pending {
-                return Err(format!("Approval is not pending: {:?}", request.status));
-            }
-
-            let approved = std::mem::replace(
-                request,
-                request.clone().approve(approver, message),
-            );
-            *request = approved;
-
-            tracing::info!(
-                approval_id = %approval_id,
-                approver = %request.approver.as_ref().unwrap(),
-                "Approval granted"
-            );
-
-            Ok(())
-        } else {
-            Err(format!("Approval request not found: {}", approval_id))
-        }
+        self.vote(approval_id, approver.into(), Vote::Approve, message).await
     }
 
     /// Deny a request
+    ///
+    /// Under a quorum policy this is one vote; the request is denied once
+    /// the quorum can no longer be reached.
     pub async fn deny(
         &self,
         approval_id: &str,
         approver: impl Into<String>,
         message: Option<String>,
+    ) -> Result<(), String> {
+        self.vote(approval_id, approver.into(), Vote::Deny, message).await
+    }
+
+    async fn vote(
+        &self,
+        approval_id: &str,
+        approver: String,
+        vote: Vote,
+        message: Option<String>,
     ) -> Result<(), String> {
         let mut requests = self.requests.write().await;
+        let request = requests
+            .get_mut(approval_id)
+            .ok_or_else(|| format!("Approval request not found: {}", approval_id))?;
 
-        if let Some(request) = requests.get_mut(approval_id) {
-            if request.status != ApprovalStatus::Pending {
-                return Err(format!("Approval is not pending: {:?}", request.status));
+        Self::refresh(request);
+        if request.status != ApprovalStatus::Pending {
+            return Err(format!("Approval is not pending: {:?}", request.status));
+        }
+
+        let eligible = self.eligible(request).await?;
+        let on_behalf_of = match &eligible {
+            Some(members) if !members.contains(&approver) => {
+                Some(self.delegator(request, members, &approver).await.ok_or_else(|| {
+                    format!("{} may not decide approval {}", approver, approval_id)
+                })?)
             }
+            _ => None,
+        };
+        let principal = on_behalf_of.as_deref().unwrap_or(&approver);
+        if request.decisions.iter().any(|d| d.principal() == principal) {
+            return Err(format!("{} already decided approval {}", principal, approval_id));
+        }
 
-            let denied = std::mem::replace(
-                request,
-                request.clone().deny(approver, message),
-            );
-            *request = denied;
+        tracing::info!(
+            target: "audit",
+            approval_id = %approval_id,
+            workflow_id = %request.workflow_id,
+            step_id = %request.step_id,
+            approver = %approver,
+            on_behalf_of = on_behalf_of.as_deref().unwrap_or("-"),
+            vote = ?vote,
+            "Approval vote"
+        );
+        request.decisions.push(ApprovalDecision {
+            approver: approver.clone(),
+            on_behalf_of,
+            vote,
+            message: message.clone(),
+            decided_at: Utc::now(),
+        });
 
+        let approvals = request.decisions.iter().filter(|d| d.vote == Vote::Approve).count();
+        let denials = request.decisions.len() - approvals;
+        let quorum = request.policy.quorum.max(1);
+        let out_of_reach = match &eligible {
+            Some(members) => members.len().saturating_sub(denials) < quorum,
+            None => denials > 0,
+        };
+
+        if approvals >= quorum {
+            *request = request.clone().approve(approver, message);
+            tracing::info!(
+                target: "audit",
+                approval_id = %approval_id,
+                approver = %request.approver.as_ref().unwrap(),
+                approvals,
+                "Approval granted"
+            );
+        } else if out_of_reach {
+            *request = request.clone().deny(approver, message);
             tracing::warn!(
+                target: "audit",
                 approval_id = %approval_id,
                 approver = %request.approver.as_ref().unwrap(),
+                denials,
                 "Approval denied"
             );
+        }
 
-            Ok(())
-        } else {
-            Err(format!("Approval request not found: {}", approval_id))
+        Ok(())
+    }
+
+    /// Time out or escalate `request` when due
+    fn refresh(request: &mut ApprovalRequest) {
+        if request.is_timed_out() {
+            *request = request.clone().timeout();
+
+            tracing::warn!(
+                target: "audit",
+                approval_id = %request.id,
+                "Approval request timed out"
+            );
+        } else if request.is_escalation_due() {
+            request.escalated_at = Some(Utc::now());
+
+            tracing::warn!(
+                target: "audit",
+                approval_id = %request.id,
+                group = request.policy.escalation.as_ref().map_or("", |e| e.group.as_str()),
+                "Approval request escalated"
+            );
         }
     }
 
+    /// Members who may vote on `request`; `None` when anyone may
+    async fn eligible(&self, request: &ApprovalRequest) -> Result<Option<HashSet<String>>, String> {
+        let Some(group) = &request.policy.group else {
+            return Ok(None);
+        };
+
+        let groups = self.groups.read().await;
+        let mut names = vec![group];
+        if request.escalated_at.is_some() {
+            names.extend(request.policy.escalation.as_ref().map(|e| &e.group));
+        }
+
+        let mut members = HashSet::new();
+        for name in names {
+            let group = groups
+                .get(name)
+                .ok_or_else(|| format!("Approver group not found: {}", name))?;
+            members.extend(group.members.iter().cloned());
+        }
+        Ok(Some(members))
+    }
+
+    /// The eligible member `approver` votes for through a delegation
+    async fn delegator(
+        &self,
+        request: &ApprovalRequest,
+        members: &HashSet<String>,
+        approver: &str,
+    ) -> Option<String> {
+        let now = Utc::now();
+        let groups: Vec<Option<&str>> = std::iter::once(request.policy.group.as_deref())
+            .chain(
+                request
+                    .escalated_at
+                    .and(request.policy.escalation.as_ref())
+                    .map(|e| Some(e.group.as_str())),
+            )
+            .collect();
+
+        self.delegations
+            .read()
+            .await
+            .iter()
+            .filter(|d| d.to == approver && members.contains(&d.from))
+            .filter(|d| !request.decisions.iter().any(|v| v.principal() == d.from))
+            .find(|d| groups.iter().any(|group| d.applies(*group, now)))
+            .map(|d| d.from.clone())
+    }
+
     /// Cancel a request
     pub async fn cancel(&self, approval_id: &str) -> Result<(), String> {
         let mut requests = self.requests.write().await;
 
         if let Some(request) = requests.get_mut(approval_id) {
-            let cancelled = std::mem::replace(request, request.clone().cancel());
-            *request = cancelled;
+            *request = request.clone().cancel();
 
             tracing::info!(
                 approval_id = %approval_id,
@@ -397,6 +758,23 @@ mod tests {
         assert_eq!(denied.approver.as_ref().unwrap(), "reviewer1");
     }
 
+    #[tokio::test]
+    async fn test_gate_records_decisions() {
+        let gate = ApprovalGate::new();
+        let mut requested = gate.subscribe();
+        let request = ApprovalRequest::new("wf1", "step1", "Deploy", "Ship it", "user1", 3600);
+        let id = gate.request_approval(request).await;
+        assert_eq!(requested.recv().await.unwrap().id, id);
+
+        gate.deny(&id, "reviewer1", None).await.unwrap();
+        assert_eq!(gate.check_approval(&id).await, Some(ApprovalStatus::Denied));
+        assert!(gate.approve(&id, "reviewer2", None).await.is_err());
+
+        let expired = ApprovalRequest::new("wf1", "step2", "Deploy", "Ship it", "user1", 0);
+        let id = gate.request_approval(expired).await;
+        assert_eq!(gate.check_approval(&id).await, Some(ApprovalStatus::Timeout));
+    }
+
     #[tokio::test]
     async fn test_approval_timeout() {
         let request = ApprovalRequest::new(
@@ -411,4 +789,81 @@ mod tests {
         tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
         assert!(request.is_timed_out());
     }
+
+    async fn gate_with_groups() -> ApprovalGate {
+        let gate = ApprovalGate::new();
+        gate.register_group(ApproverGroup::new("sre", ["alice", "bob", "carol"])).await;
+        gate.register_group(ApproverGroup::new("leads", ["dave"])).await;
+        gate
+    }
+
+    fn deploy(policy: ApprovalPolicy) -> ApprovalRequest {
+        ApprovalRequest::new("wf1", "deploy", "Deploy", "Ship it", "ci", 3600).with_policy(policy)
+    }
+
+    #[tokio::test]
+    async fn test_quorum_needs_m_of_n_members() {
+        let gate = gate_with_groups().await;
+        let id = gate.request_approval(deploy(ApprovalPolicy::quorum("sre", 2))).await;
+
+        assert!(gate.approve(&id, "mallory", None).await.is_err());
+        gate.approve(&id, "alice", None).await.unwrap();
+        assert!(gate.approve(&id, "alice", None).await.is_err());
+        assert_eq!(gate.check_approval(&id).await, Some(ApprovalStatus::Pending));
+
+        gate.approve(&id, "bob", Some("LGTM".to_string())).await.unwrap();
+        let request = gate.get_request(&id).await.unwrap();
+        assert_eq!(request.status, ApprovalStatus::Approved);
+        assert_eq!(request.approver.as_deref(), Some("bob"));
+        assert_eq!(request.decisions.len(), 2);
+
+        // Two denials leave only one member, short of the quorum
+        let id = gate.request_approval(deploy(ApprovalPolicy::quorum("sre", 2))).await;
+        gate.deny(&id, "alice", None).await.unwrap();
+        assert_eq!(gate.check_approval(&id).await, Some(ApprovalStatus::Pending));
+        gate.deny(&id, "bob", None).await.unwrap();
+        assert_eq!(gate.check_approval(&id).await, Some(ApprovalStatus::Denied));
+    }
+
+    #[tokio::test]
+    async fn test_escalation_opens_the_request_to_the_secondary_group() {
+        let gate = gate_with_groups().await;
+        let waiting = gate
+            .request_approval(deploy(ApprovalPolicy::quorum("sre", 1).escalate_to("leads", 3600)))
+            .await;
+        assert!(gate.approve(&waiting, "dave", None).await.is_err());
+
+        let id = gate
+            .request_approval(deploy(ApprovalPolicy::quorum("sre", 1).escalate_to("leads", 0)))
+            .await;
+        assert_eq!(gate.check_approval(&id).await, Some(ApprovalStatus::Pending));
+        gate.approve(&id, "dave", None).await.unwrap();
+
+        let request = gate.get_request(&id).await.unwrap();
+        assert!(request.escalated_at.is_some());
+        assert_eq!(request.status, ApprovalStatus::Approved);
+    }
+
+    #[tokio::test]
+    async fn test_delegates_vote_for_members() {
+        let gate = gate_with_groups().await;
+        let delegation = gate.delegate(Delegation::new("alice", "erin").for_group("sre")).await;
+        gate.delegate(Delegation::new("bob", "frank").for_group("other")).await;
+
+        let id = gate.request_approval(deploy(ApprovalPolicy::quorum("sre", 2))).await;
+        gate.approve(&id, "erin", None).await.unwrap();
+        assert!(gate.approve(&id, "alice", None).await.is_err());
+        assert!(gate.approve(&id, "frank", None).await.is_err());
+        gate.approve(&id, "carol", None).await.unwrap();
+
+        let request = gate.get_request(&id).await.unwrap();
+        assert_eq!(request.status, ApprovalStatus::Approved);
+        assert_eq!(request.decisions[0].on_behalf_of.as_deref(), Some("alice"));
+        assert_eq!(request.decisions[0].principal(), "alice");
+
+        gate.revoke_delegation(&delegation).await.unwrap();
+        let id = gate.request_approval(deploy(ApprovalPolicy::quorum("sre", 1))).await;
+        assert!(gate.approve(&id, "erin", None).await.is_err());
+        assert!(gate.delegations().await[0].revoked_at.is_some());
+    }
 }
@@ -0,0 +1,200 @@
+//! Concurrency groups for workflow runs
+//!
+//! A workflow may declare a [`ConcurrencyGroup`] so that only one of its runs
+//! holds the group's key at a time, e.g. `deploy-production`. Keys are plain
+//! strings, so templates can derive them from parameters
+//! (`deploy-{{ environment }}`). When a run arrives while another holds the
+//! key, the group's [`ConcurrencyPolicy`] decides whether it waits, is
+//! dropped, or takes over.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// What happens to a run arriving while another holds the key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConcurrencyPolicy {
+    /// Wait for the runs ahead of it, in arrival order
+    #[default]
+    Queue,
+    /// Drop the new run
+    Skip,
+    /// Cancel the running and queued runs and start the new one after them
+    Replace,
+}
+
+/// Named lock a workflow's runs take while they execute
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConcurrencyGroup {
+    /// Runs sharing a key never run at the same time
+    pub key: String,
+    #[serde(default)]
+    pub policy: ConcurrencyPolicy,
+}
+
+impl ConcurrencyGroup {
+    pub fn new(key: impl Into<String>, policy: ConcurrencyPolicy) -> Self {
+        Self {
+            key: key.into(),
+            policy,
+        }
+    }
+
+    /// Queue runs on `key`
+    pub fn queue(key: impl Into<String>) -> Self {
+        Self::new(key, ConcurrencyPolicy::Queue)
+    }
+
+    /// Skip runs arriving while `key` is held
+    pub fn skip(key: impl Into<String>) -> Self {
+        Self::new(key, ConcurrencyPolicy::Skip)
+    }
+
+    /// Let new runs on `key` replace the current one
+    pub fn replace(key: impl Into<String>) -> Self {
+        Self::new(key, ConcurrencyPolicy::Replace)
+    }
+}
+
+/// Outcome of a run asking for its group's key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Admission {
+    /// The key was free; the run starts now
+    Run,
+    /// The run waits behind the holder and earlier arrivals
+    Queued,
+    /// The key is held by `holder` and the run is dropped
+    Skipped { holder: String },
+    /// The run waits for `cancel`, the holder, to stop; `superseded` runs
+    /// were removed from the queue
+    Replace { cancel: String, superseded: Vec<String> },
+}
+
+#[derive(Debug)]
+struct Slot {
+    holder: String,
+    queue: VecDeque<String>,
+}
+
+/// Holders and waiting runs of each concurrency key
+#[derive(Debug, Default)]
+pub struct ConcurrencyGroups {
+    slots: HashMap<String, Slot>,
+}
+
+impl ConcurrencyGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask for `group`'s key on behalf of `execution_id`
+    pub fn admit(&mut self, group: &ConcurrencyGroup, execution_id: &str) -> Admission {
+        let Some(slot) = self.slots.get_mut(&group.key) else {
+            self.slots.insert(
+                group.key.clone(),
+                Slot {
+                    holder: execution_id.to_string(),
+                    queue: VecDeque::new(),
+                },
+            );
+            return Admission::Run;
+        };
+
+        match group.policy {
+            ConcurrencyPolicy::Queue => {
+                slot.queue.push_back(execution_id.to_string());
+                Admission::Queued
+            }
+            ConcurrencyPolicy::Skip => Admission::Skipped {
+                holder: slot.holder.clone(),
+            },
+            ConcurrencyPolicy::Replace => {
+                let superseded = slot.queue.drain(..).collect();
+                slot.queue.push_back(execution_id.to_string());
+                Admission::Replace {
+                    cancel: slot.holder.clone(),
+                    superseded,
+                }
+            }
+        }
+    }
+
+    /// Give up `key` or a place in its queue, returning the run that holds
+    /// the key next, if any
+    pub fn release(&mut self, key: &str, execution_id: &str) -> Option<String> {
+        let slot = self.slots.get_mut(key)?;
+        if slot.holder != execution_id {
+            slot.queue.retain(|id| id != execution_id);
+            return None;
+        }
+
+        match slot.queue.pop_front() {
+            Some(next) => {
+                slot.holder = next.clone();
+                Some(next)
+            }
+            None => {
+                self.slots.remove(key);
+                None
+            }
+        }
+    }
+
+    /// Run holding `key`
+    pub fn holder(&self, key: &str) -> Option<&str> {
+        self.slots.get(key).map(|slot| slot.holder.as_str())
+    }
+
+    /// Runs waiting for `key`, in the order they will run
+    pub fn queued(&self, key: &str) -> Vec<String> {
+        self.slots
+            .get(key)
+            .map(|slot| slot.queue.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_runs_in_arrival_order() {
+        let mut groups = ConcurrencyGroups::new();
+        let group = ConcurrencyGroup::queue("deploy-prod");
+
+        assert_eq!(groups.admit(&group, "a"), Admission::Run);
+        assert_eq!(groups.admit(&group, "b"), Admission::Queued);
+        assert_eq!(groups.admit(&group, "c"), Admission::Queued);
+        assert_eq!(groups.queued("deploy-prod"), vec!["b", "c"]);
+
+        // Leaving the queue does not affect the holder
+        assert_eq!(groups.release("deploy-prod", "b"), None);
+        assert_eq!(groups.release("deploy-prod", "a").as_deref(), Some("c"));
+        assert_eq!(groups.holder("deploy-prod"), Some("c"));
+        assert_eq!(groups.release("deploy-prod", "c"), None);
+        assert_eq!(groups.holder("deploy-prod"), None);
+    }
+
+    #[test]
+    fn test_skip_and_replace() {
+        let mut groups = ConcurrencyGroups::new();
+
+        assert_eq!(groups.admit(&ConcurrencyGroup::skip("k"), "a"), Admission::Run);
+        assert_eq!(
+            groups.admit(&ConcurrencyGroup::skip("k"), "b"),
+            Admission::Skipped { holder: "a".to_string() }
+        );
+        assert_eq!(groups.admit(&ConcurrencyGroup::queue("k"), "c"), Admission::Queued);
+        assert_eq!(
+            groups.admit(&ConcurrencyGroup::replace("k"), "d"),
+            Admission::Replace {
+                cancel: "a".to_string(),
+                superseded: vec!["c".to_string()],
+            }
+        );
+        assert_eq!(groups.release("k", "a").as_deref(), Some("d"));
+        // Keys are independent
+        assert_eq!(groups.admit(&ConcurrencyGroup::skip("other"), "e"), Admission::Run);
+    }
+}
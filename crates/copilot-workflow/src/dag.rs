@@ -293,6 +293,19 @@ impl WorkflowDag {
             .unwrap_or_default()
     }
 
+    /// Get every step that must complete before a step, directly or
+    /// through other dependencies
+    pub fn get_ancestors(&self, step_id: &str) -> HashSet<String> {
+        let mut ancestors = HashSet::new();
+        let mut pending = self.get_dependencies(step_id);
+        while let Some(dep) = pending.pop() {
+            if ancestors.insert(dep.clone()) {
+                pending.extend(self.get_dependencies(&dep));
+            }
+        }
+        ancestors
+    }
+
     /// Get dependents of a step (steps that depend on this step)
     pub fn get_dependents(&self, step_id: &str) -> Vec<String> {
         let node = match self.step_to_node.get(step_id) {
@@ -1,16 +1,23 @@
 //! Workflow engine with state machine and execution control
 
 use crate::approval::{ApprovalGate, ApprovalRequest, ApprovalStatus};
+use crate::concurrency::{Admission, ConcurrencyGroup, ConcurrencyGroups};
 use crate::dag::WorkflowDag;
-use crate::execution::{DefaultStepExecutor, ExecutionContext, StepExecutor};
+use crate::execution::{DefaultStepExecutor, ExecutionContext, StepExecutor, StepRetry};
+use crate::expressions;
 use crate::step::{StepResult, StepState, WorkflowStep};
 use crate::{Result, WorkflowError};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, Mutex, RwLock};
 use uuid::Uuid;
 
+/// Longest output summary carried by step events, in characters
+const OUTPUT_SUMMARY_LEN: usize = 200;
+
 /// Status of a workflow execution
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -96,6 +103,53 @@ impl WorkflowState {
     }
 }
 
+/// What happened to a workflow execution
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowEventKind {
+    Started,
+    StepStarted,
+    /// A step failed and is about to be retried
+    StepRetried,
+    StepCompleted,
+    StepFailed,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl WorkflowEventKind {
+    /// Whether the event is the last one of its execution
+    pub fn is_terminal(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// Lifecycle event of a workflow execution, published to subscribers such
+/// as the event webhooks and run watchers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowEvent {
+    pub kind: WorkflowEventKind,
+    /// Name of the workflow definition
+    pub name: String,
+    /// Tenant that started the run, from the definition's `tenant_id`
+    /// metadata
+    pub tenant_id: Option<String>,
+    /// Step the event is about, for step events
+    pub step_id: Option<String>,
+    /// Attempt of the step that failed, for retries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempt: Option<u32>,
+    /// Why the step failed, for retries and step failures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Short rendering of the step's outputs, for completed steps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_summary: Option<String>,
+    /// Execution state when the event happened
+    pub state: WorkflowState,
+}
+
 /// Workflow definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowDefinition {
@@ -112,6 +166,9 @@ pub struct WorkflowDefinition {
     pub metadata: HashMap<String, serde_json::Value>,
     /// Maximum execution time in seconds
     pub timeout_secs: Option<u64>,
+    /// Group limiting how many runs execute at the same time
+    #[serde(default)]
+    pub concurrency: Option<ConcurrencyGroup>,
 }
 
 impl WorkflowDefinition {
@@ -124,6 +181,7 @@ impl WorkflowDefinition {
             steps: Vec::new(),
             metadata: HashMap::new(),
             timeout_secs: None,
+            concurrency: None,
         }
     }
 
@@ -145,6 +203,12 @@ impl WorkflowDefinition {
         self
     }
 
+    /// Run at most one execution at a time per `group` key
+    pub fn with_concurrency(mut self, group: ConcurrencyGroup) -> Self {
+        self.concurrency = Some(group);
+        self
+    }
+
     /// Validate the workflow definition
     pub fn validate(&self) -> Result<()> {
         if self.steps.is_empty() {
@@ -154,7 +218,24 @@ impl WorkflowDefinition {
         }
 
         // Create DAG to validate structure
-        WorkflowDag::new(self.steps.clone())?;
+        let dag = WorkflowDag::new(self.steps.clone())?;
+
+        // Steps may only read outputs of steps that finish before they start
+        for step in &self.steps {
+            let referenced = expressions::referenced_steps(&step.action).map_err(|e| {
+                WorkflowError::InvalidDefinition(format!("Step {}: {}", step.id, e))
+            })?;
+            if referenced.is_empty() {
+                continue;
+            }
+            let upstream = dag.get_ancestors(&step.id);
+            if let Some(missing) = referenced.iter().find(|id| !upstream.contains(*id)) {
+                return Err(WorkflowError::InvalidDefinition(format!(
+                    "Step {} reads outputs of step {}, which it does not depend on",
+                    step.id, missing
+                )));
+            }
+        }
 
         Ok(())
     }
@@ -169,6 +250,10 @@ pub struct WorkflowEngine {
     approval_gate: Arc<ApprovalGate>,
     /// Step executor
     executor: Arc<dyn StepExecutor>,
+    /// Holders and queues of concurrency groups
+    concurrency: Arc<Mutex<ConcurrencyGroups>>,
+    /// Lifecycle events of the executions
+    events: broadcast::Sender<WorkflowEvent>,
 }
 
 /// Internal workflow execution state
@@ -189,10 +274,13 @@ impl Default for WorkflowEngine {
 impl WorkflowEngine {
     /// Create a new workflow engine
     pub fn new() -> Self {
+        let approval_gate = Arc::new(ApprovalGate::new());
         Self {
             executions: Arc::new(RwLock::new(HashMap::new())),
-            approval_gate: Arc::new(ApprovalGate::new()),
-            executor: Arc::new(DefaultStepExecutor::new()),
+            executor: Arc::new(DefaultStepExecutor::new().with_approval_gate(approval_gate.clone())),
+            approval_gate,
+            concurrency: Arc::new(Mutex::new(ConcurrencyGroups::new())),
+            events: broadcast::channel(256).0,
         }
     }
 
@@ -202,6 +290,71 @@ impl WorkflowEngine {
             executions: Arc::new(RwLock::new(HashMap::new())),
             approval_gate: Arc::new(ApprovalGate::new()),
             executor,
+            concurrency: Arc::new(Mutex::new(ConcurrencyGroups::new())),
+            events: broadcast::channel(256).0,
+        }
+    }
+
+    /// Use `gate` for approvals, e.g. the one a custom executor requests
+    /// approvals on
+    pub fn with_approval_gate(mut self, gate: Arc<ApprovalGate>) -> Self {
+        self.approval_gate = gate;
+        self
+    }
+
+    /// Subscribe to lifecycle events of the executions started from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<WorkflowEvent> {
+        self.events.subscribe()
+    }
+
+    /// Current state of an execution and a subscription to the events
+    /// after it, so a watcher misses nothing in between
+    ///
+    /// The subscription carries the events of every execution; filter on
+    /// `state.execution_id`.
+    pub async fn watch(&self, execution_id: &str) -> Result<(WorkflowState, broadcast::Receiver<WorkflowEvent>)> {
+        let events = self.events.subscribe();
+        let state = self.get_status(execution_id).await?;
+        Ok((state, events))
+    }
+
+    /// Event about `execution`
+    fn event(kind: WorkflowEventKind, execution: &WorkflowExecution, step_id: Option<&str>) -> WorkflowEvent {
+        WorkflowEvent {
+            kind,
+            name: execution.definition.name.clone(),
+            tenant_id: Self::tenant_of(execution),
+            step_id: step_id.map(str::to_string),
+            attempt: None,
+            error: None,
+            output_summary: None,
+            state: execution.state.clone(),
+        }
+    }
+
+    fn tenant_of(execution: &WorkflowExecution) -> Option<String> {
+        execution
+            .definition
+            .metadata
+            .get("tenant_id")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    }
+
+    /// Publish an event about `execution`; nobody listening is fine
+    fn publish(&self, kind: WorkflowEventKind, execution: &WorkflowExecution, step_id: Option<&str>) {
+        let _ = self.events.send(Self::event(kind, execution, step_id));
+    }
+
+    /// Publish a retry of a step of execution `execution_id`
+    async fn publish_retry(&self, execution_id: &str, retry: StepRetry) {
+        let executions = self.executions.read().await;
+        if let Some(execution) = executions.get(execution_id) {
+            let _ = self.events.send(WorkflowEvent {
+                attempt: Some(retry.attempt),
+                error: retry.error,
+                ..Self::event(WorkflowEventKind::StepRetried, execution, Some(&retry.step_id))
+            });
         }
     }
 
@@ -232,13 +385,30 @@ impl WorkflowEngine {
 
         // Create execution
         let execution_id = Uuid::new_v4().to_string();
-        let mut state = WorkflowState::new(&workflow_id, &execution_id);
-        state.status = WorkflowStatus::Running;
-        state.started_at = Some(chrono::Utc::now());
+        let state = WorkflowState::new(&workflow_id, &execution_id);
 
         let context = ExecutionContext::new(&workflow_id, &execution_id);
         let cancel_flag = Arc::new(RwLock::new(false));
 
+        // Hold the groups until the execution is stored, so a releasing run
+        // cannot hand the key to an execution that does not exist yet
+        let mut groups = self.concurrency.lock().await;
+        let admission = match &definition.concurrency {
+            Some(group) => groups.admit(group, &execution_id),
+            None => Admission::Run,
+        };
+
+        if let Admission::Skipped { holder } = admission {
+            let key = definition.concurrency.map(|group| group.key).unwrap_or_default();
+            tracing::info!(
+                workflow_id = %workflow_id,
+                concurrency_key = %key,
+                holder = %holder,
+                "Workflow run skipped, concurrency group is held"
+            );
+            return Err(WorkflowError::ConcurrencyLimited { key, holder });
+        }
+
         let execution = WorkflowExecution {
             definition,
             dag,
@@ -253,15 +423,62 @@ impl WorkflowEngine {
             executions.insert(execution_id.clone(), execution);
         }
 
+        match admission {
+            Admission::Run => {
+                self.start_execution(&execution_id).await;
+                drop(groups);
+            }
+            Admission::Replace { cancel, superseded } => {
+                drop(groups);
+                tracing::info!(
+                    workflow_id = %workflow_id,
+                    execution_id = %execution_id,
+                    replaced = %cancel,
+                    "Workflow run queued, replacing the current run"
+                );
+                for id in superseded.iter().chain(std::iter::once(&cancel)) {
+                    if let Err(e) = self.cancel_workflow(id).await {
+                        tracing::warn!(execution_id = %id, error = %e, "Failed to cancel replaced run");
+                    }
+                }
+            }
+            _ => {
+                drop(groups);
+                tracing::info!(
+                    workflow_id = %workflow_id,
+                    execution_id = %execution_id,
+                    "Workflow run queued behind its concurrency group"
+                );
+            }
+        }
+
+        Ok(execution_id)
+    }
+
+    /// Start a pending execution, returning whether it was started
+    async fn start_execution(&self, execution_id: &str) -> bool {
+        let key = {
+            let mut executions = self.executions.write().await;
+            let Some(execution) = executions.get_mut(execution_id) else {
+                return false;
+            };
+            if execution.state.status != WorkflowStatus::Pending {
+                return false;
+            }
+            execution.state.status = WorkflowStatus::Running;
+            execution.state.started_at = Some(chrono::Utc::now());
+            self.publish(WorkflowEventKind::Started, execution, None);
+            execution.definition.concurrency.as_ref().map(|group| group.key.clone())
+        };
+
         tracing::info!(
-            workflow_id = %workflow_id,
             execution_id = %execution_id,
             "Workflow execution started"
         );
 
         // Spawn execution task
         let engine = self.clone();
-        let exec_id = execution_id.clone();
+        let exec_id = execution_id.to_string();
         tokio::spawn(async move {
             if let Err(e) = engine.run_workflow_loop(&exec_id).await {
                 tracing::error!(
@@ -270,9 +487,34 @@ impl WorkflowEngine {
                     "Workflow execution failed"
                 );
             }
+            if let Some(key) = key {
+                engine.release_concurrency(&key, &exec_id).await;
+            }
         });
 
-        Ok(execution_id)
+        true
+    }
+
+    /// Give up `execution_id`'s hold on, or place in, the `key` group and
+    /// start the next waiting run
+    ///
+    /// Boxed, as starting a run spawns a task that releases again.
+    fn release_concurrency<'a>(
+        &'a self,
+        key: &'a str,
+        execution_id: &'a str,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            let mut groups = self.concurrency.lock().await;
+            let mut next = groups.release(key, execution_id);
+            while let Some(id) = next {
+                if self.start_execution(&id).await {
+                    break;
+                }
+                // Cancelled while it waited
+                next = groups.release(key, &id);
+            }
+        })
     }
 
     /// Main workflow execution loop
@@ -292,6 +534,18 @@ impl WorkflowEngine {
                 break;
             }
 
+            // A step failing with `fail_on_error` ends the run
+            let failed = {
+                let executions = self.executions.read().await;
+                let execution = executions.get(execution_id)
+                    .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+                execution.state.status == WorkflowStatus::Failed
+            };
+
+            if failed {
+                break;
+            }
+
             // Get ready steps
             let ready_steps = {
                 let executions = self.executions.read().await;
@@ -325,8 +579,16 @@ impl WorkflowEngine {
                     let execution = executions.get(execution_id)
                         .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
 
+                    // Failed and skipped steps stay "ready" as they never complete
                     execution.state.running_steps.is_empty()
-                        && execution.dag.get_ready_steps(&execution.state.completed_steps).is_empty()
+                        && execution
+                            .dag
+                            .get_ready_steps(&execution.state.completed_steps)
+                            .iter()
+                            .all(|id| {
+                                execution.state.failed_steps.contains(id)
+                                    || execution.state.skipped_steps.contains(id)
+                            })
                 };
 
                 if is_complete {
@@ -371,6 +633,7 @@ impl WorkflowEngine {
             let execution = executions.get_mut(execution_id)
                 .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
             execution.state.running_steps.insert(step_id.to_string());
+            self.publish(WorkflowEventKind::StepStarted, execution, Some(step_id));
         }
 
         // Get step and context
@@ -388,6 +651,16 @@ impl WorkflowEngine {
             (step, execution.context.clone())
         };
 
+        // Publish retries as they happen; the hook lives only as long as the
+        // step, so it does not keep the engine alive through the execution
+        let engine = self.clone();
+        let run = execution_id.to_string();
+        let context = context.with_retry_hook(move |retry| {
+            let engine = engine.clone();
+            let run = run.clone();
+            async move { engine.publish_retry(&run, retry).await }
+        });
+
         // Execute step
         let result = self.executor.execute_step(&step, &context).await?;
 
@@ -399,6 +672,11 @@ impl WorkflowEngine {
 
             execution.state.running_steps.remove(step_id);
 
+            let failed = result.state == StepState::Failed;
+            let completed = result.state == StepState::Completed;
+            let output_summary = summarize_outputs(&result.outputs);
+            let error = result.error.clone();
+            let mut workflow_failed = false;
             match result.state {
                 StepState::Completed => {
                     execution.state.completed_steps.insert(step_id.to_string());
@@ -407,6 +685,7 @@ impl WorkflowEngine {
                     execution.state.failed_steps.insert(step_id.to_string());
 
                     if step.fail_on_error {
+                        workflow_failed = execution.state.status != WorkflowStatus::Failed;
                         execution.state.status = WorkflowStatus::Failed;
                         execution.state.error = result.error.clone();
                         execution.state.completed_at = Some(chrono::Utc::now());
@@ -419,6 +698,22 @@ impl WorkflowEngine {
             }
 
             execution.state.step_results.insert(step_id.to_string(), result);
+
+            if completed {
+                let _ = self.events.send(WorkflowEvent {
+                    output_summary,
+                    ..Self::event(WorkflowEventKind::StepCompleted, execution, Some(step_id))
+                });
+            }
+            if failed {
+                let _ = self.events.send(WorkflowEvent {
+                    error,
+                    ..Self::event(WorkflowEventKind::StepFailed, execution, Some(step_id))
+                });
+                if workflow_failed {
+                    self.publish(WorkflowEventKind::Failed, execution, Some(step_id));
+                }
+            }
         }
 
         Ok(())
@@ -432,6 +727,7 @@ impl WorkflowEngine {
 
         execution.state.status = WorkflowStatus::Completed;
         execution.state.completed_at = Some(chrono::Utc::now());
+        self.publish(WorkflowEventKind::Completed, execution, None);
 
         tracing::info!(
             execution_id = %execution_id,
@@ -449,6 +745,7 @@ impl WorkflowEngine {
 
         execution.state.status = WorkflowStatus::Cancelled;
         execution.state.completed_at = Some(chrono::Utc::now());
+        self.publish(WorkflowEventKind::Cancelled, execution, None);
 
         tracing::info!(
             execution_id = %execution_id,
@@ -502,11 +799,29 @@ impl WorkflowEngine {
 
     /// Cancel a workflow
     pub async fn cancel_workflow(&self, execution_id: &str) -> Result<()> {
-        let mut executions = self.executions.write().await;
-        let execution = executions.get_mut(execution_id)
-            .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+        let queued_on = {
+            let mut executions = self.executions.write().await;
+            let execution = executions.get_mut(execution_id)
+                .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+
+            *execution.cancel_flag.write().await = true;
+            // Stop running steps too, not just the scheduling of new ones
+            execution.context.cancel();
+
+            // A run still waiting for its concurrency group never starts
+            if execution.state.status == WorkflowStatus::Pending {
+                execution.state.status = WorkflowStatus::Cancelled;
+                execution.state.completed_at = Some(chrono::Utc::now());
+                self.publish(WorkflowEventKind::Cancelled, execution, None);
+                execution.definition.concurrency.as_ref().map(|group| group.key.clone())
+            } else {
+                None
+            }
+        };
 
-        *execution.cancel_flag.write().await = true;
+        if let Some(key) = queued_on {
+            self.release_concurrency(&key, execution_id).await;
+        }
 
         tracing::info!(
             execution_id = %execution_id,
@@ -525,12 +840,53 @@ impl WorkflowEngine {
         Ok(execution.state.clone())
     }
 
+    /// Tenant that started an execution, from its definition's `tenant_id`
+    /// metadata
+    pub async fn execution_tenant(&self, execution_id: &str) -> Result<Option<String>> {
+        let executions = self.executions.read().await;
+        let execution = executions.get(execution_id)
+            .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+
+        Ok(Self::tenant_of(execution))
+    }
+
+    /// Execution holding the concurrency group `key`
+    pub async fn concurrency_holder(&self, key: &str) -> Option<String> {
+        self.concurrency.lock().await.holder(key).map(str::to_string)
+    }
+
+    /// Executions waiting for the concurrency group `key`, in start order
+    pub async fn queued_executions(&self, key: &str) -> Vec<String> {
+        self.concurrency.lock().await.queued(key)
+    }
+
     /// Get approval gate
     pub fn approval_gate(&self) -> &ApprovalGate {
         &self.approval_gate
     }
 }
 
+/// Outputs of a step as `key=value` pairs in key order, cut to
+/// [`OUTPUT_SUMMARY_LEN`] characters; `None` when there are none
+fn summarize_outputs(outputs: &HashMap<String, serde_json::Value>) -> Option<String> {
+    if outputs.is_empty() {
+        return None;
+    }
+    let mut keys: Vec<&String> = outputs.keys().collect();
+    keys.sort();
+    let summary = keys
+        .into_iter()
+        .map(|key| format!("{}={}", key, outputs[key]))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if summary.chars().count() <= OUTPUT_SUMMARY_LEN {
+        return Some(summary);
+    }
+    let mut cut: String = summary.chars().take(OUTPUT_SUMMARY_LEN - 3).collect();
+    cut.push_str("...");
+    Some(cut)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -573,4 +929,201 @@ mod tests {
             WorkflowStatus::Running | WorkflowStatus::Completed
         ));
     }
+
+    fn deploy(group: ConcurrencyGroup) -> WorkflowDefinition {
+        WorkflowDefinition::new("Deploy", "Deploy to production")
+            .add_step(
+                WorkflowStep::new("wait", StepType::Action, StepAction::Wait { duration_secs: 1 })
+                    .with_id("wait"),
+            )
+            .with_concurrency(group)
+    }
+
+    async fn wait_for(engine: &WorkflowEngine, execution_id: &str, status: WorkflowStatus) {
+        for _ in 0..50 {
+            if engine.get_status(execution_id).await.unwrap().status == status {
+                return;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+        panic!("{} never reached {:?}", execution_id, status);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_queue_runs_one_at_a_time() {
+        let engine = WorkflowEngine::new();
+
+        let first = engine.execute_workflow(deploy(ConcurrencyGroup::queue("prod"))).await.unwrap();
+        let second = engine.execute_workflow(deploy(ConcurrencyGroup::queue("prod"))).await.unwrap();
+        let third = engine.execute_workflow(deploy(ConcurrencyGroup::queue("prod"))).await.unwrap();
+
+        assert_eq!(engine.get_status(&second).await.unwrap().status, WorkflowStatus::Pending);
+        assert_eq!(engine.concurrency_holder("prod").await.as_deref(), Some(first.as_str()));
+        assert_eq!(engine.queued_executions("prod").await, vec![second.clone(), third.clone()]);
+
+        // A cancelled run leaves the queue without running
+        engine.cancel_workflow(&third).await.unwrap();
+        assert_eq!(engine.get_status(&third).await.unwrap().status, WorkflowStatus::Cancelled);
+
+        wait_for(&engine, &first, WorkflowStatus::Completed).await;
+        wait_for(&engine, &second, WorkflowStatus::Running).await;
+        wait_for(&engine, &second, WorkflowStatus::Completed).await;
+        assert!(engine.get_status(&third).await.unwrap().started_at.is_none());
+        assert_eq!(engine.concurrency_holder("prod").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_skip_and_replace() {
+        let engine = WorkflowEngine::new();
+
+        let first = engine.execute_workflow(deploy(ConcurrencyGroup::skip("prod"))).await.unwrap();
+        let skipped = engine.execute_workflow(deploy(ConcurrencyGroup::skip("prod"))).await;
+        assert!(matches!(
+            skipped,
+            Err(WorkflowError::ConcurrencyLimited { ref holder, .. }) if *holder == first
+        ));
+
+        // Other keys are not affected
+        let staging = engine.execute_workflow(deploy(ConcurrencyGroup::skip("staging"))).await.unwrap();
+        assert_eq!(engine.get_status(&staging).await.unwrap().status, WorkflowStatus::Running);
+
+        let replacement = engine.execute_workflow(deploy(ConcurrencyGroup::replace("prod"))).await.unwrap();
+        wait_for(&engine, &first, WorkflowStatus::Cancelled).await;
+        wait_for(&engine, &replacement, WorkflowStatus::Running).await;
+        assert_eq!(engine.concurrency_holder("prod").await.as_deref(), Some(replacement.as_str()));
+    }
+
+    fn command(id: &str, args: &[&str]) -> WorkflowStep {
+        WorkflowStep::new(
+            id,
+            StepType::Action,
+            StepAction::Command {
+                command: "echo".to_string(),
+                args: args.iter().map(|arg| arg.to_string()).collect(),
+                env: HashMap::new(),
+            },
+        )
+        .with_id(id)
+    }
+
+    #[tokio::test]
+    async fn test_steps_read_outputs_of_their_dependencies() {
+        let unordered = WorkflowDefinition::new("Release", "Build and deploy")
+            .add_step(command("build", &[]))
+            .add_step(command("deploy", &["${{ steps.build.outputs.exit_code }}"]));
+        let err = unordered.validate().unwrap_err();
+        assert!(err.to_string().contains("Step deploy reads outputs of step build"));
+
+        let engine = WorkflowEngine::new();
+        let workflow = WorkflowDefinition::new("Release", "Build and deploy")
+            .add_step(command("build", &[]))
+            .add_step(command("deploy", &["--code=${{ steps.build.outputs.exit_code }}"]).with_dependency("build"));
+        let execution_id = engine.execute_workflow(workflow).await.unwrap();
+        wait_for(&engine, &execution_id, WorkflowStatus::Completed).await;
+
+        let workflow = WorkflowDefinition::new("Release", "Build and deploy")
+            .add_step(command("build", &[]))
+            .add_step(command("deploy", &["${{ steps.build.outputs.artifact_url }}"]).with_dependency("build"));
+        let execution_id = engine.execute_workflow(workflow).await.unwrap();
+        wait_for(&engine, &execution_id, WorkflowStatus::Failed).await;
+        let status = engine.get_status(&execution_id).await.unwrap();
+        let error = status.step_results["deploy"].error.clone().unwrap();
+        assert!(error.contains("steps.build.outputs has no artifact_url (available: exit_code, stderr, stdout)"));
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_events() {
+        let engine = WorkflowEngine::new();
+        let mut events = engine.subscribe();
+
+        let mut workflow = WorkflowDefinition::new("Release", "Build and deploy")
+            .add_step(command("build", &[]))
+            .add_step(command("deploy", &["${{ steps.build.outputs.artifact_url }}"]).with_dependency("build"));
+        workflow.metadata.insert("tenant_id".to_string(), serde_json::json!("tenant-1"));
+        let execution_id = engine.execute_workflow(workflow).await.unwrap();
+        wait_for(&engine, &execution_id, WorkflowStatus::Failed).await;
+        assert_eq!(engine.execution_tenant(&execution_id).await.unwrap().as_deref(), Some("tenant-1"));
+
+        let started = events.recv().await.unwrap();
+        assert_eq!(started.kind, WorkflowEventKind::Started);
+        assert_eq!(started.name, "Release");
+        assert_eq!(started.tenant_id.as_deref(), Some("tenant-1"));
+        assert_eq!(started.state.execution_id, execution_id);
+
+        let step_started = events.recv().await.unwrap();
+        assert_eq!(step_started.kind, WorkflowEventKind::StepStarted);
+        assert_eq!(step_started.step_id.as_deref(), Some("build"));
+        assert!(step_started.state.running_steps.contains("build"));
+
+        let step_completed = events.recv().await.unwrap();
+        assert_eq!(step_completed.kind, WorkflowEventKind::StepCompleted);
+        assert!(step_completed.output_summary.unwrap().starts_with("exit_code=0"));
+
+        let step_started = events.recv().await.unwrap();
+        assert_eq!(step_started.kind, WorkflowEventKind::StepStarted);
+        assert_eq!(step_started.step_id.as_deref(), Some("deploy"));
+
+        let step_failed = events.recv().await.unwrap();
+        assert_eq!(step_failed.kind, WorkflowEventKind::StepFailed);
+        assert_eq!(step_failed.step_id.as_deref(), Some("deploy"));
+        assert!(step_failed.error.is_some());
+        assert!(step_failed.state.step_results["deploy"].error.is_some());
+
+        let failed = events.recv().await.unwrap();
+        assert_eq!(failed.kind, WorkflowEventKind::Failed);
+        assert!(failed.kind.is_terminal());
+        assert_eq!(failed.state.status, WorkflowStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_watch_reports_retries_and_cancellation() {
+        let engine = WorkflowEngine::new();
+        let mut flaky = WorkflowStep::new("wait", StepType::Wait, StepAction::Wait { duration_secs: 60 })
+            .with_id("wait")
+            .with_timeout(1)
+            .with_retry(1);
+        flaky.fail_on_error = false;
+        let workflow = WorkflowDefinition::new("Soak", "Wait it out").add_step(flaky);
+        let execution_id = engine.execute_workflow(workflow).await.unwrap();
+
+        let (state, mut events) = engine.watch(&execution_id).await.unwrap();
+        assert_eq!(state.execution_id, execution_id);
+        assert!(engine.watch("missing").await.is_err());
+
+        let retried = loop {
+            let event = events.recv().await.unwrap();
+            if event.kind == WorkflowEventKind::StepRetried {
+                break event;
+            }
+        };
+        assert_eq!(retried.step_id.as_deref(), Some("wait"));
+        assert_eq!(retried.attempt, Some(1));
+        assert_eq!(retried.error.as_deref(), Some("Step timed out after 1 seconds"));
+
+        engine.cancel_workflow(&execution_id).await.unwrap();
+        let cancelled = loop {
+            let event = events.recv().await.unwrap();
+            if event.kind.is_terminal() {
+                break event;
+            }
+        };
+        assert_eq!(cancelled.kind, WorkflowEventKind::Cancelled);
+        assert_eq!(cancelled.state.status, WorkflowStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_output_summary_is_sorted_and_cut() {
+        assert_eq!(summarize_outputs(&HashMap::new()), None);
+
+        let outputs = HashMap::from([
+            ("stdout".to_string(), serde_json::json!("ok")),
+            ("exit_code".to_string(), serde_json::json!(0)),
+        ]);
+        assert_eq!(summarize_outputs(&outputs).unwrap(), "exit_code=0, stdout=\"ok\"");
+
+        let outputs = HashMap::from([("stdout".to_string(), serde_json::json!("x".repeat(500)))]);
+        let summary = summarize_outputs(&outputs).unwrap();
+        assert_eq!(summary.chars().count(), OUTPUT_SUMMARY_LEN);
+        assert!(summary.ends_with("..."));
+    }
 }
@@ -1,13 +1,32 @@
 //! Workflow execution engine with retry logic and timeout handling
 
-use crate::step::{StepAction, StepResult, StepState, WorkflowStep};
+use crate::approval::{ApprovalGate, ApprovalPolicy, ApprovalRequest, ApprovalStatus};
+use crate::expressions::ExpressionScope;
+use crate::orchestration::{
+    AgentRole, AgentToolExecutor, AgentTurnHandler, AgentTurnOutput, MultiAgentOrchestration, NoAgentTools,
+    PersonaLookup, RoleTools, SimulatedAgentHandler,
+};
+use crate::spike::{self, SpikeRequest, SPIKE_EXPLANATION_PROMPT};
+use crate::step::{CancellationReason, StepAction, StepResult, StepState, WorkflowStep};
+use crate::terraform::{self, PlanAnalysis, PlanRisk, PLAN_REVIEW_PROMPT};
 use crate::{Result, WorkflowError};
 use async_trait::async_trait;
+use copilot_adapters::llm_devops::policy_engine::Decision;
+use copilot_adapters::{ObservatoryAdapter, PolicyEngineAdapter};
+use copilot_context::{ContextEngine, Scratchpad};
+use copilot_core::FaultInjector;
+use copilot_nlp::logs::{LogClusterer, LOG_SUMMARY_PROMPT};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::time::{timeout, Duration};
+use tokio_util::sync::CancellationToken;
+
+/// How long a timed-out or cancelled step gets to stop before it is abandoned
+const DEFAULT_CANCELLATION_GRACE: Duration = Duration::from_secs(5);
 
 /// Configuration for retry behavior
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +63,27 @@ impl RetryConfig {
     }
 }
 
+/// A failed attempt of a step that is about to be retried
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepRetry {
+    pub step_id: String,
+    /// Attempt that failed, counting from 1
+    pub attempt: u32,
+    pub error: Option<String>,
+}
+
+type RetryCallback = dyn Fn(StepRetry) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync;
+
+/// Told about each retry of a step before its backoff starts
+#[derive(Clone)]
+struct RetryHook(Arc<RetryCallback>);
+
+impl std::fmt::Debug for RetryHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RetryHook")
+    }
+}
+
 /// Execution context for a workflow
 #[derive(Debug, Clone)]
 pub struct ExecutionContext {
@@ -55,6 +95,11 @@ pub struct ExecutionContext {
     state: Arc<RwLock<HashMap<String, serde_json::Value>>>,
     /// Step outputs
     outputs: Arc<RwLock<HashMap<String, HashMap<String, serde_json::Value>>>>,
+    /// Cancelled when the workflow, or the step this context belongs to, is
+    /// stopped
+    cancellation: CancellationToken,
+    /// Told about retries, e.g. to publish them as run events
+    on_retry: Option<RetryHook>,
 }
 
 impl ExecutionContext {
@@ -65,6 +110,51 @@ impl ExecutionContext {
             execution_id: execution_id.into(),
             state: Arc::new(RwLock::new(HashMap::new())),
             outputs: Arc::new(RwLock::new(HashMap::new())),
+            cancellation: CancellationToken::new(),
+            on_retry: None,
+        }
+    }
+
+    /// Await `hook` before each retry of a step run under this context
+    pub fn with_retry_hook<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(StepRetry) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_retry = Some(RetryHook(Arc::new(move |retry| Box::pin(hook(retry)))));
+        self
+    }
+
+    /// Report that a step failed and is about to be retried
+    pub async fn report_retry(&self, retry: StepRetry) {
+        if let Some(hook) = &self.on_retry {
+            (hook.0)(retry).await;
+        }
+    }
+
+    /// Token long-running operations should watch and stop on
+    ///
+    /// Pass it to sandbox executions and adapter calls so a timed-out or
+    /// cancelled step does not leave them running.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Cancel every step running under this context
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// A context sharing this one's state, whose token can be cancelled on
+    /// its own and is cancelled along with this one
+    pub fn child(&self) -> Self {
+        Self {
+            cancellation: self.cancellation.child_token(),
+            ..self.clone()
         }
     }
 
@@ -121,6 +211,9 @@ impl ExecutionContext {
 #[async_trait]
 pub trait StepExecutor: Send + Sync {
     /// Execute a workflow step
+    ///
+    /// Implementations should stop promptly once
+    /// [`ExecutionContext::cancellation_token`] is cancelled.
     async fn execute_step(
         &self,
         step: &WorkflowStep,
@@ -129,9 +222,27 @@ pub trait StepExecutor: Send + Sync {
 }
 
 /// Default step executor implementation
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct DefaultStepExecutor {
     retry_config: RetryConfig,
+    agent_handler: Arc<dyn AgentTurnHandler>,
+    agent_tools: Arc<dyn AgentToolExecutor>,
+    personas: Option<Arc<dyn PersonaLookup>>,
+    policy_engine: Option<Arc<dyn PolicyEngineAdapter>>,
+    approval_gate: Option<Arc<ApprovalGate>>,
+    approval_policy: ApprovalPolicy,
+    observatory: Option<Arc<dyn ObservatoryAdapter>>,
+    context_engine: Option<Arc<dyn ContextEngine>>,
+    faults: Arc<FaultInjector>,
+    cancellation_grace: Duration,
+}
+
+impl std::fmt::Debug for DefaultStepExecutor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DefaultStepExecutor")
+            .field("retry_config", &self.retry_config)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Default for DefaultStepExecutor {
@@ -145,12 +256,87 @@ impl DefaultStepExecutor {
     pub fn new() -> Self {
         Self {
             retry_config: RetryConfig::default(),
+            agent_handler: Arc::new(SimulatedAgentHandler),
+            agent_tools: Arc::new(NoAgentTools),
+            personas: None,
+            policy_engine: None,
+            approval_gate: None,
+            approval_policy: ApprovalPolicy::default(),
+            observatory: None,
+            context_engine: None,
+            faults: FaultInjector::global(),
+            cancellation_grace: DEFAULT_CANCELLATION_GRACE,
         }
     }
 
     /// Create with custom retry configuration
     pub fn with_retry_config(retry_config: RetryConfig) -> Self {
-        Self { retry_config }
+        Self {
+            retry_config,
+            ..Self::new()
+        }
+    }
+
+    /// Use `handler` to produce agent turns for multi-agent steps
+    pub fn with_agent_handler(mut self, handler: Arc<dyn AgentTurnHandler>) -> Self {
+        self.agent_handler = handler;
+        self
+    }
+
+    /// Run the tools agents call, within each role's allowlist, on `tools`
+    pub fn with_agent_tools(mut self, tools: Arc<dyn AgentToolExecutor>) -> Self {
+        self.agent_tools = tools;
+        self
+    }
+
+    /// Resolve the personas agent roles name from `personas`
+    pub fn with_personas(mut self, personas: Arc<dyn PersonaLookup>) -> Self {
+        self.personas = Some(personas);
+        self
+    }
+
+    /// Check Terraform plans against `policy_engine` before they are applied
+    pub fn with_policy_engine(mut self, policy_engine: Arc<dyn PolicyEngineAdapter>) -> Self {
+        self.policy_engine = Some(policy_engine);
+        self
+    }
+
+    /// Request approvals for risky Terraform plans on `gate`
+    pub fn with_approval_gate(mut self, gate: Arc<ApprovalGate>) -> Self {
+        self.approval_gate = Some(gate);
+        self
+    }
+
+    /// Decide the approvals this executor requests under `policy`
+    pub fn with_approval_policy(mut self, policy: ApprovalPolicy) -> Self {
+        self.approval_policy = policy;
+        self
+    }
+
+    /// Fetch the metrics, logs and traces that explain spikes from `observatory`
+    pub fn with_observatory(mut self, observatory: Arc<dyn ObservatoryAdapter>) -> Self {
+        self.observatory = Some(observatory);
+        self
+    }
+
+    /// Retrieve runbooks for spike explanations from `context_engine`
+    pub fn with_context_engine(mut self, context_engine: Arc<dyn ContextEngine>) -> Self {
+        self.context_engine = Some(context_engine);
+        self
+    }
+
+    /// Take the faults injected into `workflow/<step id>` from `faults`
+    /// instead of the global injector
+    pub fn with_faults(mut self, faults: Arc<FaultInjector>) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    /// Give timed-out or cancelled steps `grace` to stop before they are
+    /// abandoned
+    pub fn with_cancellation_grace(mut self, grace: Duration) -> Self {
+        self.cancellation_grace = grace;
+        self
     }
 
     /// Execute a step with retry logic
@@ -169,6 +355,10 @@ impl DefaultStepExecutor {
         let mut retry_count = 0;
 
         loop {
+            if context.is_cancelled() {
+                return Ok(StepResult::pending(step.id.clone()).cancel(CancellationReason::WorkflowCancelled));
+            }
+
             tracing::debug!(
                 step_id = %step.id,
                 attempt = retry_count + 1,
@@ -179,7 +369,9 @@ impl DefaultStepExecutor {
 
             match result {
                 Ok(step_result) => {
-                    if step_result.is_success() {
+                    if step_result.is_success()
+                        || step_result.cancellation == Some(CancellationReason::WorkflowCancelled)
+                    {
                         return Ok(step_result);
                     } else if retry_count < max_retries {
                         last_error = step_result.error.clone();
@@ -193,8 +385,15 @@ impl DefaultStepExecutor {
                             error = ?step_result.error,
                             "Step failed, retrying"
                         );
-
-                        tokio::time::sleep(backoff).await;
+                        context
+                            .report_retry(StepRetry {
+                                step_id: step.id.clone(),
+                                attempt: retry_count,
+                                error: step_result.error.clone(),
+                            })
+                            .await;
+
+                        backoff_unless_cancelled(backoff, context).await;
                     } else {
                         return Ok(step_result);
                     }
@@ -212,8 +411,15 @@ impl DefaultStepExecutor {
                             error = %e,
                             "Step execution error, retrying"
                         );
-
-                        tokio::time::sleep(backoff).await;
+                        context
+                            .report_retry(StepRetry {
+                                step_id: step.id.clone(),
+                                attempt: retry_count,
+                                error: Some(e.to_string()),
+                            })
+                            .await;
+
+                        backoff_unless_cancelled(backoff, context).await;
                     } else {
                         return Err(e);
                     }
@@ -231,9 +437,32 @@ impl DefaultStepExecutor {
         let mut result = StepResult::pending(step.id.clone());
         result.state = StepState::Running;
 
-        // Apply timeout if configured
+        let injection = self.faults.roll(&format!("workflow/{}", step.id));
+
+        // Operations of this attempt watch its own token, so a timeout stops
+        // them without cancelling the rest of the workflow
+        let step_context = context.child();
+        let context = &step_context;
+        let cancel = context.cancellation_token();
+
         let execution = async {
-            match &step.action {
+            if let Some(delay) = injection.delay {
+                tokio::time::sleep(delay).await;
+            }
+            if let Some(fault) = injection.fault {
+                return Err(WorkflowError::StepExecutionFailed {
+                    step_id: step.id.clone(),
+                    reason: fault.to_string(),
+                });
+            }
+            let action = ExpressionScope::from_context(context)
+                .await
+                .resolve_action(&step.action)
+                .map_err(|e| WorkflowError::StepExecutionFailed {
+                    step_id: step.id.clone(),
+                    reason: e.to_string(),
+                })?;
+            match &action {
                 StepAction::Command { command, args, env } => {
                     self.execute_command(command, args, env, context).await
                 }
@@ -247,29 +476,71 @@ impl DefaultStepExecutor {
                     self.execute_agent_invoke(agent_id, parameters, context).await
                 }
                 StepAction::Condition { expression, true_steps, false_steps } => {
-                    self.execute_condition(expression, true_steps, false_steps, context).await
+                    self.execute_condition(step, expression, true_steps, false_steps, context).await
                 }
                 StepAction::Wait { duration_secs } => {
-                    self.execute_wait(*duration_secs).await
+                    self.execute_wait(step, *duration_secs, context).await
                 }
                 StepAction::Custom { handler, parameters } => {
                     self.execute_custom(handler, parameters, context).await
                 }
+                StepAction::MultiAgent { task, orchestration } => {
+                    self.execute_multi_agent(task, orchestration, context).await
+                }
+                StepAction::TerraformPlan { plan, plan_step, approval_risk, approval_timeout_secs } => {
+                    self.execute_terraform_plan(
+                        step,
+                        plan.as_ref(),
+                        plan_step.as_deref(),
+                        *approval_risk,
+                        *approval_timeout_secs,
+                        context,
+                    )
+                    .await
+                }
+                StepAction::LogAnalysis { logs, logs_step, top_patterns } => {
+                    self.execute_log_analysis(step, logs.as_deref(), logs_step.as_deref(), *top_patterns, context)
+                        .await
+                }
+                StepAction::ExplainSpike { metric, service, start, end, related_metrics } => {
+                    let request = SpikeRequest::parse(metric, service.as_deref(), start, end, related_metrics)?;
+                    self.execute_explain_spike(step, request).await
+                }
+            }
+        };
+
+        tokio::pin!(execution);
+        let deadline = async {
+            match step.timeout_secs {
+                Some(timeout_secs) => tokio::time::sleep(Duration::from_secs(timeout_secs)).await,
+                None => std::future::pending().await,
             }
         };
 
-        let execution_result = if let Some(timeout_secs) = step.timeout_secs {
-            match timeout(Duration::from_secs(timeout_secs), execution).await {
-                Ok(r) => r,
-                Err(_) => {
-                    return Ok(result.fail(format!(
-                        "Step timed out after {} seconds",
-                        timeout_secs
-                    )));
+        // Apply timeout if configured
+        let outcome = tokio::select! {
+            r = &mut execution => Ok(r),
+            _ = deadline => Err(CancellationReason::Timeout {
+                after_secs: step.timeout_secs.unwrap_or_default(),
+            }),
+            _ = cancel.cancelled() => Err(CancellationReason::WorkflowCancelled),
+        };
+        let execution_result = match outcome {
+            Ok(r) => r,
+            Err(reason) => {
+                // Let the step's operations see the cancellation and clean
+                // up (kill sandboxes, abort requests) before giving up on them
+                cancel.cancel();
+                if timeout(self.cancellation_grace, &mut execution).await.is_err() {
+                    tracing::warn!(
+                        step_id = %step.id,
+                        grace_ms = self.cancellation_grace.as_millis(),
+                        "Step did not stop within the cancellation grace period, abandoning it"
+                    );
                 }
+                tracing::warn!(step_id = %step.id, %reason, "Step cancelled");
+                return Ok(result.cancel(reason));
             }
-        } else {
-            execution.await
         };
 
         match execution_result {
@@ -352,25 +623,46 @@ impl DefaultStepExecutor {
 
     async fn execute_condition(
         &self,
+        step: &WorkflowStep,
         expression: &str,
         true_steps: &[String],
         false_steps: &[String],
-        _context: &ExecutionContext,
+        context: &ExecutionContext,
     ) -> Result<HashMap<String, serde_json::Value>> {
-        tracing::info!(expression, "Evaluating condition");
+        let result = ExpressionScope::from_context(context)
+            .await
+            .evaluate_condition(expression)
+            .map_err(|e| WorkflowError::StepExecutionFailed {
+                step_id: step.id.clone(),
+                reason: e.to_string(),
+            })?;
+        tracing::info!(expression, result, "Evaluated condition");
 
-        // Mock implementation - always returns true
         let mut outputs = HashMap::new();
-        outputs.insert("condition_result".to_string(), serde_json::json!(true));
-        outputs.insert("next_steps".to_string(), serde_json::json!(true_steps));
+        outputs.insert("condition_result".to_string(), serde_json::json!(result));
+        let next_steps = if result { true_steps } else { false_steps };
+        outputs.insert("next_steps".to_string(), serde_json::json!(next_steps));
 
         Ok(outputs)
     }
 
-    async fn execute_wait(&self, duration_secs: u64) -> Result<HashMap<String, serde_json::Value>> {
+    async fn execute_wait(
+        &self,
+        step: &WorkflowStep,
+        duration_secs: u64,
+        context: &ExecutionContext,
+    ) -> Result<HashMap<String, serde_json::Value>> {
         tracing::info!(duration_secs, "Waiting");
 
-        tokio::time::sleep(Duration::from_secs(duration_secs)).await;
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(duration_secs)) => {}
+            _ = context.cancellation_token().cancelled() => {
+                return Err(WorkflowError::StepExecutionFailed {
+                    step_id: step.id.clone(),
+                    reason: "wait cancelled".to_string(),
+                });
+            }
+        }
 
         let mut outputs = HashMap::new();
         outputs.insert("waited_secs".to_string(), serde_json::json!(duration_secs));
@@ -392,6 +684,252 @@ impl DefaultStepExecutor {
 
         Ok(outputs)
     }
+
+    /// One turn by `role` on a fresh scratchpad
+    async fn single_turn(&self, role: &AgentRole, task: &str) -> Result<AgentTurnOutput> {
+        let tools = RoleTools::new(role, self.agent_tools.as_ref());
+        self.agent_handler.take_turn(role, task, &Scratchpad::new(), &tools).await
+    }
+
+    async fn execute_multi_agent(
+        &self,
+        task: &str,
+        orchestration: &MultiAgentOrchestration,
+        _context: &ExecutionContext,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        tracing::info!(roles = orchestration.roles.len(), "Executing multi-agent step");
+
+        let scratchpad = Scratchpad::new();
+        let outcome = orchestration
+            .run(
+                task,
+                self.agent_handler.clone(),
+                self.agent_tools.as_ref(),
+                self.personas.as_deref(),
+                &scratchpad,
+            )
+            .await?;
+
+        let mut outputs = HashMap::new();
+        outputs.insert("turns".to_string(), serde_json::to_value(&outcome.turns)?);
+        outputs.insert("termination".to_string(), serde_json::to_value(outcome.reason)?);
+        outputs.insert("scratchpad".to_string(), serde_json::to_value(&outcome.scratchpad)?);
+        outputs.insert(
+            "final_output".to_string(),
+            serde_json::json!(outcome.turns.last().map(|t| t.content.clone())),
+        );
+
+        Ok(outputs)
+    }
+
+    async fn execute_terraform_plan(
+        &self,
+        step: &WorkflowStep,
+        plan: Option<&serde_json::Value>,
+        plan_step: Option<&str>,
+        approval_risk: PlanRisk,
+        approval_timeout_secs: u64,
+        context: &ExecutionContext,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        let failed = |reason: String| WorkflowError::StepExecutionFailed {
+            step_id: step.id.clone(),
+            reason,
+        };
+
+        let plan = match (plan, plan_step) {
+            (Some(plan), _) => plan.clone(),
+            (None, Some(plan_step)) => context
+                .get_step_outputs(plan_step)
+                .await
+                .and_then(|outputs| terraform::plan_from_outputs(&outputs))
+                .ok_or_else(|| failed(format!("step {} produced no plan", plan_step)))?,
+            (None, None) => {
+                return Err(WorkflowError::InvalidDefinition(format!(
+                    "terraform_plan step {} needs a plan or a plan_step",
+                    step.id
+                )))
+            }
+        };
+        let analysis = PlanAnalysis::from_plan_json(&plan)?;
+        tracing::info!(
+            step_id = %step.id,
+            risk = %analysis.risk,
+            changes = analysis.changes.len(),
+            "Analyzed Terraform plan"
+        );
+
+        let reviewer = AgentRole::new("plan_reviewer", PLAN_REVIEW_PROMPT);
+        let summary = match self.single_turn(&reviewer, &analysis.review_task()).await {
+            Ok(turn) if !turn.content.trim().is_empty() => turn.content,
+            Ok(_) => analysis.describe(),
+            Err(e) => {
+                tracing::warn!(step_id = %step.id, error = %e, "Plan summary failed, using the plain description");
+                analysis.describe()
+            }
+        };
+
+        let mut outputs = HashMap::new();
+        outputs.insert("risk".to_string(), serde_json::to_value(analysis.risk)?);
+        outputs.insert("summary".to_string(), serde_json::json!(summary));
+        outputs.insert("analysis".to_string(), serde_json::to_value(&analysis)?);
+
+        // Fail closed: a plan is only applied once the policy engine has seen it
+        let mut policy_requires_approval = false;
+        if let Some(policy_engine) = &self.policy_engine {
+            let decision = policy_engine
+                .evaluate(analysis.policy_request(&context.workflow_id))
+                .await
+                .map_err(|e| failed(format!("policy evaluation failed: {}", e)))?;
+            if matches!(decision.decision, Decision::Deny) {
+                return Err(failed(format!(
+                    "plan denied by policy: {}",
+                    decision.reasons.join("; ")
+                )));
+            }
+            policy_requires_approval = terraform::requires_approval(&decision);
+            outputs.insert("policy".to_string(), serde_json::to_value(&decision)?);
+        }
+
+        let needs_approval = analysis.has_changes()
+            && (analysis.risk >= approval_risk || policy_requires_approval);
+        if needs_approval {
+            let gate = self
+                .approval_gate
+                .as_ref()
+                .ok_or_else(|| failed("plan needs approval but no approval gate is configured".to_string()))?;
+            let request = ApprovalRequest::new(
+                &context.workflow_id,
+                &step.id,
+                format!("Apply Terraform plan ({} risk)", analysis.risk),
+                format!("{}\n\n{}", summary, analysis.describe()),
+                "copilot",
+                approval_timeout_secs,
+            )
+            .with_context("analysis", serde_json::to_value(&analysis)?)
+            .with_policy(self.approval_policy.clone());
+            let approval_id = gate.request_approval(request).await;
+            outputs.insert("approval_id".to_string(), serde_json::json!(approval_id));
+
+            let status = tokio::select! {
+                status = gate.wait_for_decision(&approval_id, 500) => status.map_err(failed)?,
+                _ = context.cancellation_token().cancelled() => {
+                    return Err(failed(format!("cancelled while waiting for approval {}", approval_id)));
+                }
+            };
+            if status != ApprovalStatus::Approved {
+                return Err(match status {
+                    ApprovalStatus::Timeout => WorkflowError::ApprovalTimeout(approval_id),
+                    _ => WorkflowError::ApprovalDenied(approval_id),
+                });
+            }
+            if let Some(request) = gate.get_request(&approval_id).await {
+                outputs.insert("approved_by".to_string(), serde_json::json!(request.approver));
+                outputs.insert("approval_decisions".to_string(), serde_json::to_value(&request.decisions)?);
+                outputs.insert("approval_escalated".to_string(), serde_json::json!(request.escalated_at.is_some()));
+            }
+        }
+        outputs.insert("approved".to_string(), serde_json::json!(true));
+
+        Ok(outputs)
+    }
+
+    async fn execute_log_analysis(
+        &self,
+        step: &WorkflowStep,
+        logs: Option<&str>,
+        logs_step: Option<&str>,
+        top_patterns: usize,
+        context: &ExecutionContext,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        let logs = match (logs, logs_step) {
+            (Some(logs), _) => logs.to_string(),
+            (None, Some(logs_step)) => context
+                .get_step_outputs(logs_step)
+                .await
+                .and_then(|outputs| {
+                    ["logs", "stdout"]
+                        .iter()
+                        .find_map(|key| outputs.get(*key)?.as_str().map(str::to_string))
+                })
+                .ok_or_else(|| WorkflowError::StepExecutionFailed {
+                    step_id: step.id.clone(),
+                    reason: format!("step {} produced no logs", logs_step),
+                })?,
+            (None, None) => {
+                return Err(WorkflowError::InvalidDefinition(format!(
+                    "log_analysis step {} needs logs or a logs_step",
+                    step.id
+                )))
+            }
+        };
+
+        let mut clusterer = LogClusterer::new();
+        clusterer.add_lines(logs.lines());
+        let analysis = clusterer.analysis(top_patterns);
+        tracing::info!(
+            step_id = %step.id,
+            lines = analysis.total_lines,
+            patterns = analysis.pattern_count,
+            findings = analysis.findings.len(),
+            "Clustered logs"
+        );
+
+        let analyst = AgentRole::new("log_analyst", LOG_SUMMARY_PROMPT);
+        let summary = match self.single_turn(&analyst, &analysis.summary_task()).await {
+            Ok(turn) if !turn.content.trim().is_empty() => turn.content,
+            Ok(_) => analysis.describe(),
+            Err(e) => {
+                tracing::warn!(step_id = %step.id, error = %e, "Log summary failed, using the plain description");
+                analysis.describe()
+            }
+        };
+
+        let mut outputs = HashMap::new();
+        outputs.insert("summary".to_string(), serde_json::json!(summary));
+        outputs.insert("total_lines".to_string(), serde_json::json!(analysis.total_lines));
+        outputs.insert("patterns".to_string(), serde_json::to_value(&analysis.patterns)?);
+        outputs.insert("findings".to_string(), serde_json::to_value(&analysis.findings)?);
+
+        Ok(outputs)
+    }
+
+    /// Gather evidence for a metric spike and have an agent explain it
+    async fn execute_explain_spike(
+        &self,
+        step: &WorkflowStep,
+        request: SpikeRequest,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        let mut report = spike::gather_evidence(
+            request,
+            self.observatory.as_deref(),
+            self.context_engine.as_deref(),
+        )
+        .await;
+        tracing::info!(
+            step_id = %step.id,
+            metric = %report.request.metric,
+            evidence = report.evidence.len(),
+            gaps = report.gaps.len(),
+            "Gathered spike evidence"
+        );
+
+        let investigator = AgentRole::new("spike_investigator", SPIKE_EXPLANATION_PROMPT);
+        report.hypotheses = match self.single_turn(&investigator, &report.explanation_task()).await {
+            Ok(turn) if !turn.content.trim().is_empty() => turn.content,
+            Ok(_) => report.describe(),
+            Err(e) => {
+                tracing::warn!(step_id = %step.id, error = %e, "Spike explanation failed, using the evidence alone");
+                report.describe()
+            }
+        };
+
+        let mut outputs = HashMap::new();
+        outputs.insert("hypotheses".to_string(), serde_json::json!(report.hypotheses));
+        outputs.insert("evidence".to_string(), serde_json::to_value(&report.evidence)?);
+        outputs.insert("gaps".to_string(), serde_json::to_value(&report.gaps)?);
+        outputs.insert("report".to_string(), serde_json::to_value(&report)?);
+        Ok(outputs)
+    }
 }
 
 #[async_trait]
@@ -405,6 +943,14 @@ impl StepExecutor for DefaultStepExecutor {
     }
 }
 
+/// Sleep for `backoff`, returning early when `context` is cancelled
+async fn backoff_unless_cancelled(backoff: Duration, context: &ExecutionContext) {
+    tokio::select! {
+        _ = tokio::time::sleep(backoff) => {}
+        _ = context.cancellation_token().cancelled() => {}
+    }
+}
+
 /// Execute multiple steps in parallel
 pub async fn execute_parallel_steps(
     steps: Vec<WorkflowStep>,
@@ -442,6 +988,10 @@ pub async fn execute_parallel_steps(
 mod tests {
     use super::*;
     use crate::step::{StepType, StepAction};
+    use copilot_adapters::llm_devops::policy_engine::{
+        EvaluationRequest, EvaluationResponse, Policy,
+    };
+    use copilot_adapters::AdapterResult;
 
     #[tokio::test]
     async fn test_execution_context() {
@@ -467,6 +1017,257 @@ mod tests {
         assert_eq!(result.state, StepState::Completed);
     }
 
+    #[tokio::test]
+    async fn test_injected_step_faults_are_retried() {
+        use copilot_core::{Determinism, FaultRule};
+
+        let faults = Arc::new(
+            FaultInjector::new(&Determinism::seeded(1))
+                .with_rules(vec![FaultRule::new("workflow/deploy").with_error_rate(1.0)]),
+        );
+        let executor = DefaultStepExecutor::with_retry_config(RetryConfig {
+            initial_backoff_ms: 1,
+            ..Default::default()
+        })
+        .with_faults(faults.clone());
+        let retries = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reported = retries.clone();
+        let context = ExecutionContext::new("wf1", "exec1").with_retry_hook(move |retry| {
+            reported.lock().unwrap().push(retry);
+            async {}
+        });
+        let step = WorkflowStep::new("deploy", StepType::Action, StepAction::Wait { duration_secs: 0 })
+            .with_id("deploy")
+            .with_retry(2);
+
+        let result = executor.execute_step(&step, &context).await.unwrap();
+        assert_eq!(result.state, StepState::Failed);
+        assert!(result.error.unwrap().contains("injected error"));
+        assert_eq!(faults.stats().errors, 3);
+
+        // Both retries were reported, not the final failure
+        let retries = retries.lock().unwrap().clone();
+        assert_eq!(retries.iter().map(|retry| retry.attempt).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(retries.iter().all(|retry| retry.step_id == "deploy" && retry.error.is_some()));
+
+        let other = step.clone().with_id("build");
+        let result = executor.execute_step(&other, &context).await.unwrap();
+        assert_eq!(result.state, StepState::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_step_timeout_cancels_the_operation() {
+        let executor = DefaultStepExecutor::new();
+        let context = ExecutionContext::new("wf1", "exec1");
+        let step = WorkflowStep::new("slow", StepType::Wait, StepAction::Wait { duration_secs: 60 })
+            .with_timeout(1);
+
+        let started = std::time::Instant::now();
+        let result = executor.execute_step(&step, &context).await.unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(3));
+        assert_eq!(result.state, StepState::Failed);
+        assert_eq!(result.cancellation, Some(CancellationReason::Timeout { after_secs: 1 }));
+        assert_eq!(result.error.as_deref(), Some("Step timed out after 1 seconds"));
+        assert!(!context.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_workflow_stops_steps_without_retrying() {
+        let executor = DefaultStepExecutor::with_retry_config(RetryConfig {
+            initial_backoff_ms: 1,
+            ..Default::default()
+        });
+        let context = ExecutionContext::new("wf1", "exec1");
+        let step = WorkflowStep::new("slow", StepType::Wait, StepAction::Wait { duration_secs: 60 })
+            .with_retry(3);
+
+        let canceller = context.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+        let result = executor.execute_step(&step, &context).await.unwrap();
+
+        assert_eq!(result.cancellation, Some(CancellationReason::WorkflowCancelled));
+        assert_eq!(result.retry_count, 0);
+        assert!(context.child().is_cancelled());
+
+        let result = executor.execute_step(&step, &context).await.unwrap();
+        assert_eq!(result.cancellation, Some(CancellationReason::WorkflowCancelled));
+    }
+
+    #[tokio::test]
+    async fn test_multi_agent_step() {
+        let executor = DefaultStepExecutor::new();
+        let context = ExecutionContext::new("wf1", "exec1");
+
+        let action: StepAction = serde_json::from_value(serde_json::json!({
+            "type": "multi_agent",
+            "task": "add a health check endpoint",
+            "roles": [
+                { "name": "planner", "system_prompt": "Plan the work" },
+                { "name": "coder", "system_prompt": "Write the code" }
+            ],
+            "termination": { "type": "max_turns", "turns": 3 }
+        }))
+        .unwrap();
+        let step = WorkflowStep::new("collaborate", StepType::MultiAgent, action);
+
+        let result = executor.execute_step(&step, &context).await.unwrap();
+        assert_eq!(result.state, StepState::Completed);
+        assert_eq!(result.outputs["turns"].as_array().unwrap().len(), 3);
+        assert_eq!(result.outputs["termination"], serde_json::json!("condition_met"));
+    }
+
+    struct DenyDeletes;
+
+    #[async_trait]
+    impl PolicyEngineAdapter for DenyDeletes {
+        async fn create_policy(&self, policy: Policy) -> AdapterResult<Policy> {
+            Ok(policy)
+        }
+
+        async fn evaluate(&self, request: EvaluationRequest) -> AdapterResult<EvaluationResponse> {
+            let deletes = request.context["deletes"].as_u64().unwrap_or(0);
+            Ok(EvaluationResponse {
+                decision: if deletes > 0 { Decision::Deny } else { Decision::Allow },
+                matched_policies: vec!["no-deletes".to_string()],
+                reasons: vec!["deletes are not allowed".to_string()],
+                obligations: Vec::new(),
+            })
+        }
+
+        async fn list_policies(&self) -> AdapterResult<Vec<Policy>> {
+            Ok(Vec::new())
+        }
+
+        async fn update_policy(&self, policy: Policy) -> AdapterResult<Policy> {
+            Ok(policy)
+        }
+
+        async fn delete_policy(&self, _policy_id: &str) -> AdapterResult<()> {
+            Ok(())
+        }
+    }
+
+    fn plan_step(actions: &[&str]) -> WorkflowStep {
+        let action: StepAction = serde_json::from_value(serde_json::json!({
+            "type": "terraform_plan",
+            "plan": {
+                "resource_changes": [
+                    { "address": "aws_instance.web", "type": "aws_instance", "change": { "actions": actions } }
+                ]
+            },
+            "approval_risk": "medium"
+        }))
+        .unwrap();
+        WorkflowStep::new("review plan", StepType::Approval, action).with_id("review")
+    }
+
+    #[tokio::test]
+    async fn test_terraform_plan_step_waits_for_approval() {
+        let gate = Arc::new(ApprovalGate::new());
+        let executor = DefaultStepExecutor::new()
+            .with_policy_engine(Arc::new(DenyDeletes))
+            .with_approval_gate(gate.clone());
+        let context = ExecutionContext::new("wf1", "exec1");
+
+        // Creates only: below the approval threshold
+        let result = executor.execute_step(&plan_step(&["create"]), &context).await.unwrap();
+        assert_eq!(result.state, StepState::Completed);
+        assert_eq!(result.outputs["risk"], "low");
+        assert!(!result.outputs.contains_key("approval_id"));
+
+        // Updates need an approval
+        let approver = tokio::spawn({
+            let gate = gate.clone();
+            async move {
+                loop {
+                    if let Some(request) = gate.list_pending().await.pop() {
+                        gate.approve(&request.id, "alice", None).await.unwrap();
+                        break;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            }
+        });
+        let result = executor.execute_step(&plan_step(&["update"]), &context).await.unwrap();
+        approver.await.unwrap();
+        assert_eq!(result.state, StepState::Completed);
+        assert_eq!(result.outputs["approved_by"], "alice");
+        assert_eq!(result.outputs["approval_decisions"][0]["vote"], "approve");
+        assert_eq!(result.outputs["policy"]["decision"], "Allow");
+
+        // Deletes are stopped by policy before anyone is asked
+        let result = executor.execute_step(&plan_step(&["delete"]), &context).await.unwrap();
+        assert_eq!(result.state, StepState::Failed);
+        assert!(result.error.unwrap().contains("deletes are not allowed"));
+        assert!(gate.list_pending().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_log_analysis_step_reads_previous_step() {
+        let executor = DefaultStepExecutor::new();
+        let context = ExecutionContext::new("wf1", "exec1");
+        let logs = "INFO user 1 logged in\nINFO user 2 logged in\nERROR disk /dev/sda1 full";
+        context
+            .set_step_outputs("fetch", HashMap::from([("stdout".to_string(), serde_json::json!(logs))]))
+            .await;
+
+        let action: StepAction = serde_json::from_value(serde_json::json!({
+            "type": "log_analysis",
+            "logs_step": "fetch"
+        }))
+        .unwrap();
+        let step = WorkflowStep::new("analyze logs", StepType::Action, action).with_id("analyze");
+        let result = executor.execute_step(&step, &context).await.unwrap();
+
+        assert_eq!(result.state, StepState::Completed);
+        assert_eq!(result.outputs["total_lines"], 3);
+        assert_eq!(result.outputs["patterns"][0]["template"], "INFO user <*> logged in");
+        assert_eq!(result.outputs["findings"][0]["kind"], "error_pattern");
+        assert!(!result.outputs["summary"].as_str().unwrap().is_empty());
+
+        let action: StepAction = serde_json::from_value(serde_json::json!({"type": "log_analysis"})).unwrap();
+        let step = WorkflowStep::new("analyze logs", StepType::Action, action).with_id("analyze");
+        let result = executor.execute_step(&step, &context).await.unwrap();
+        assert_eq!(result.state, StepState::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_explain_spike_step_reports_gaps() {
+        let executor = DefaultStepExecutor::new();
+        let context = ExecutionContext::new("wf1", "exec1");
+
+        let action: StepAction = serde_json::from_value(serde_json::json!({
+            "type": "explain_spike",
+            "metric": "latency",
+            "service": "checkout",
+            "start": "2024-05-01T10:00:00Z",
+            "end": "2024-05-01T10:30:00Z"
+        }))
+        .unwrap();
+        let step = WorkflowStep::new("explain", StepType::Action, action).with_id("explain");
+        let result = executor.execute_step(&step, &context).await.unwrap();
+
+        assert_eq!(result.state, StepState::Completed);
+        assert_eq!(result.outputs["evidence"], serde_json::json!([]));
+        assert_eq!(result.outputs["gaps"].as_array().unwrap().len(), 2);
+        assert!(!result.outputs["hypotheses"].as_str().unwrap().is_empty());
+
+        let action: StepAction = serde_json::from_value(serde_json::json!({
+            "type": "explain_spike",
+            "metric": "latency",
+            "start": "2024-05-01T10:30:00Z",
+            "end": "2024-05-01T10:00:00Z"
+        }))
+        .unwrap();
+        let step = WorkflowStep::new("explain", StepType::Action, action).with_id("explain");
+        let result = executor.execute_step(&step, &context).await.unwrap();
+        assert_eq!(result.state, StepState::Failed);
+    }
+
     #[tokio::test]
     async fn test_retry_config() {
         let config = RetryConfig::default();
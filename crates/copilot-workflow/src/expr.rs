@@ -0,0 +1,1575 @@
+//! Sandboxed expression language
+//!
+//! A small CEL-like language shared by step conditions, trigger conditions,
+//! `${{ }}` step parameters and `{{ }}` template parameters:
+//!
+//! ```text
+//! steps.test.outputs.failures == 0 && state.branch in ["main", "release"]
+//! event.payload.ref.startsWith("refs/tags/") ? "release" : "snapshot"
+//! size(steps.scan.outputs.findings) > 0 || has(event.payload.force)
+//! ```
+//!
+//! Values are JSON values. Names are paths into the variables an evaluation
+//! is given (`a.b[0]["c"]`) and may contain `-`, as step IDs do, so a minus
+//! needs spaces around it to subtract. Functions are `size`, `has`, `int` and
+//! `string`; strings have `contains`, `startsWith`, `endsWith`, `matches`,
+//! `lower` and `upper` methods.
+//!
+//! Expressions cannot loop, call out or grow without bound: their length,
+//! nesting and the size of the values they build are limited, and `matches`
+//! runs on the linear-time `regex` engine with a size limit. [`Expr::check`]
+//! finds unknown names and type errors before anything is evaluated.
+
+use serde_json::{Number, Value};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use thiserror::Error;
+
+/// Longest accepted expression, in bytes
+pub const MAX_EXPRESSION_LEN: usize = 4096;
+/// Deepest accepted nesting of operators, calls and brackets
+pub const MAX_NESTING: usize = 64;
+/// Largest string, in bytes, or list, in items, an expression may build
+pub const MAX_VALUE_LEN: usize = 1 << 20;
+/// Largest compiled regex `matches` accepts, in bytes
+const MAX_REGEX_SIZE: usize = 1 << 20;
+
+/// Why an expression could not be parsed, checked or evaluated
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ExpressionError {
+    #[error("Invalid expression `{expression}`: {reason}")]
+    Syntax { expression: String, reason: String },
+
+    #[error("`{expression}`: unknown name {name}{}", available_hint("available", .available))]
+    UnknownName {
+        expression: String,
+        name: String,
+        available: Vec<String>,
+    },
+
+    #[error("`{expression}`: {reason}")]
+    Type { expression: String, reason: String },
+
+    #[error("`{expression}` exceeds a limit: {reason}")]
+    Limit { expression: String, reason: String },
+
+    #[error("`{expression}`: step {step} has no outputs{}", available_hint("steps with outputs", .available))]
+    StepNotRun {
+        expression: String,
+        step: String,
+        available: Vec<String>,
+    },
+
+    #[error("`{expression}`: {parent} has no {segment}{}", available_hint("available", .available))]
+    MissingPath {
+        expression: String,
+        parent: String,
+        segment: String,
+        available: Vec<String>,
+    },
+
+    #[error("`{expression}`: {parent} is {found}, so it has no {segment}")]
+    NotTraversable {
+        expression: String,
+        parent: String,
+        segment: String,
+        found: &'static str,
+    },
+}
+
+impl ExpressionError {
+    /// Whether the error is a name or path that does not exist, which
+    /// `has()` turns into `false`
+    fn is_missing(&self) -> bool {
+        matches!(
+            self,
+            Self::UnknownName { .. } | Self::StepNotRun { .. } | Self::MissingPath { .. } | Self::NotTraversable { .. }
+        )
+    }
+}
+
+fn available_hint(label: &str, available: &[String]) -> String {
+    if available.is_empty() {
+        String::new()
+    } else {
+        format!(" ({}: {})", label, available.join(", "))
+    }
+}
+
+/// Part of a path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Key(key) => write!(f, "{}", key),
+            Segment::Index(index) => write!(f, "[{}]", index),
+        }
+    }
+}
+
+/// Static type of an expression, as far as the checker can tell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Null,
+    Bool,
+    Number,
+    String,
+    List,
+    Map,
+    /// Only known once evaluated, e.g. a value read from a path
+    Dyn,
+}
+
+impl ValueType {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(_) => Self::Bool,
+            Value::Number(_) => Self::Number,
+            Value::String(_) => Self::String,
+            Value::Array(_) => Self::List,
+            Value::Object(_) => Self::Map,
+        }
+    }
+
+    /// Whether a value of this type may turn out to be `expected`
+    fn allows(self, expected: ValueType) -> bool {
+        self == expected || self == Self::Dyn
+    }
+
+    fn is_scalar(self) -> bool {
+        matches!(self, Self::Null | Self::Bool | Self::Number | Self::String)
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Null => "null",
+            Self::Bool => "a boolean",
+            Self::Number => "a number",
+            Self::String => "a string",
+            Self::List => "a list",
+            Self::Map => "an object",
+            Self::Dyn => "any value",
+        })
+    }
+}
+
+/// Name of the JSON type of `value`, e.g. `a number`
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "a list",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Names an expression may use, with their types
+#[derive(Debug, Clone, Default)]
+pub struct TypeEnv {
+    names: BTreeMap<String, ValueType>,
+}
+
+impl TypeEnv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare `name` as holding values of type `value_type`
+    pub fn with(mut self, name: impl Into<String>, value_type: ValueType) -> Self {
+        self.names.insert(name.into(), value_type);
+        self
+    }
+}
+
+/// Looks up the values of paths while an expression is evaluated
+pub trait Resolver {
+    /// Value at `segments` below the variable `root`
+    fn resolve(&self, expression: &str, root: &str, segments: &[Segment]) -> Result<Value, ExpressionError>;
+}
+
+/// Variables of an evaluation, by name
+#[derive(Debug, Clone, Default)]
+pub struct Bindings {
+    values: BTreeMap<String, Value>,
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind `name` to `value`
+    pub fn with(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.values.insert(name.into(), value);
+        self
+    }
+
+    /// Types of the bound values, to check expressions against
+    pub fn types(&self) -> TypeEnv {
+        TypeEnv {
+            names: self.values.iter().map(|(name, value)| (name.clone(), ValueType::of(value))).collect(),
+        }
+    }
+}
+
+impl Resolver for Bindings {
+    fn resolve(&self, expression: &str, root: &str, segments: &[Segment]) -> Result<Value, ExpressionError> {
+        let value = self.values.get(root).ok_or_else(|| ExpressionError::UnknownName {
+            expression: expression.to_string(),
+            name: root.to_string(),
+            available: self.values.keys().cloned().collect(),
+        })?;
+        traverse(expression, root.to_string(), value, segments)
+    }
+}
+
+/// Compile a user-supplied regex, bounding the size of the compiled program
+pub fn compile_regex(pattern: &str) -> Result<regex::Regex, regex::Error> {
+    regex::RegexBuilder::new(pattern)
+        .size_limit(MAX_REGEX_SIZE)
+        .dfa_size_limit(MAX_REGEX_SIZE)
+        .build()
+}
+
+/// Follow `segments` down from `value`, which is at `parent`
+pub fn traverse(
+    expression: &str,
+    mut parent: String,
+    value: &Value,
+    segments: &[Segment],
+) -> Result<Value, ExpressionError> {
+    let mut value = value;
+    for segment in segments {
+        value = match (segment, value) {
+            (Segment::Key(key), Value::Object(map)) => map.get(key).ok_or_else(|| ExpressionError::MissingPath {
+                expression: expression.to_string(),
+                parent: parent.clone(),
+                segment: segment.to_string(),
+                available: sorted_keys(map.keys()),
+            })?,
+            (Segment::Index(index), Value::Array(items)) => {
+                items.get(*index).ok_or_else(|| ExpressionError::MissingPath {
+                    expression: expression.to_string(),
+                    parent: parent.clone(),
+                    segment: segment.to_string(),
+                    available: vec![format!("{} items", items.len())],
+                })?
+            }
+            (_, other) => {
+                return Err(ExpressionError::NotTraversable {
+                    expression: expression.to_string(),
+                    parent,
+                    segment: segment.to_string(),
+                    found: type_name(other),
+                })
+            }
+        };
+        parent = match segment {
+            Segment::Key(key) => format!("{}.{}", parent, key),
+            Segment::Index(index) => format!("{}[{}]", parent, index),
+        };
+    }
+    Ok(value.clone())
+}
+
+/// Sorted copy of `keys`, for error hints
+pub fn sorted_keys<'a>(keys: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let mut keys: Vec<_> = keys.cloned().collect();
+    keys.sort();
+    keys
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnaryOp {
+    Not,
+    Neg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Or => "||",
+            Self::And => "&&",
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::In => "in",
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Rem => "%",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Size,
+    Has,
+    Int,
+    String,
+}
+
+impl Function {
+    const NAMES: &'static [&'static str] = &["has", "int", "size", "string"];
+
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "size" => Self::Size,
+            "has" => Self::Has,
+            "int" => Self::Int,
+            "string" => Self::String,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Method {
+    Contains,
+    StartsWith,
+    EndsWith,
+    Matches,
+    Lower,
+    Upper,
+}
+
+impl Method {
+    const NAMES: &'static [&'static str] = &["contains", "endsWith", "lower", "matches", "startsWith", "upper"];
+
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "contains" => Self::Contains,
+            "startsWith" => Self::StartsWith,
+            "endsWith" => Self::EndsWith,
+            "matches" => Self::Matches,
+            "lower" => Self::Lower,
+            "upper" => Self::Upper,
+            _ => return None,
+        })
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Self::Lower | Self::Upper => 0,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Literal(Value),
+    List(Vec<Node>),
+    /// A variable and the fixed keys and indexes below it
+    Path { root: String, segments: Vec<Segment> },
+    /// `.name` on something other than a path
+    Member { target: Box<Node>, name: String },
+    /// `[index]` with a computed index, or on something other than a path
+    Index { target: Box<Node>, index: Box<Node> },
+    Unary { op: UnaryOp, operand: Box<Node> },
+    Binary { op: BinaryOp, left: Box<Node>, right: Box<Node> },
+    Conditional { condition: Box<Node>, then: Box<Node>, otherwise: Box<Node> },
+    Call { function: Function, args: Vec<Node> },
+    Method { target: Box<Node>, method: Method, args: Vec<Node> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(Number),
+    Str(String),
+    Ident(String),
+    Punct(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Str(s) => write!(f, "{:?}", s),
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Punct(p) => write!(f, "{}", p),
+        }
+    }
+}
+
+const PUNCTUATION: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "+", "-", "*", "/", "%", "(", ")", "[", "]", ",", ".", "?",
+    ":",
+];
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if is_ident_start(c) {
+            let start = i;
+            while i < chars.len()
+                && (is_ident_char(chars[i])
+                    || (chars[i] == '-' && chars.get(i + 1).copied().is_some_and(is_ident_char)))
+            {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c.is_ascii_digit() {
+            let start = i;
+            let mut float = false;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            if chars.get(i) == Some(&'.') && chars.get(i + 1).is_some_and(char::is_ascii_digit) {
+                float = true;
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            if matches!(chars.get(i), Some('e' | 'E')) {
+                let mut j = i + 1;
+                if matches!(chars.get(j), Some('+' | '-')) {
+                    j += 1;
+                }
+                if chars.get(j).is_some_and(char::is_ascii_digit) {
+                    float = true;
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = if float {
+                text.parse::<f64>().ok().and_then(Number::from_f64)
+            } else {
+                text.parse::<i64>().ok().map(Number::from)
+            };
+            tokens.push(Token::Number(number.ok_or_else(|| format!("number {} is out of range", text))?));
+        } else if c == '"' || c == '\'' {
+            let quote = c;
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err("unterminated string".to_string()),
+                    Some(&c) if c == quote => {
+                        i += 1;
+                        break;
+                    }
+                    Some('\\') => {
+                        let escaped = match chars.get(i + 1) {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some('r') => '\r',
+                            Some(&c @ ('\\' | '"' | '\'')) => c,
+                            Some(c) => return Err(format!("unknown escape \\{}", c)),
+                            None => return Err("unterminated string".to_string()),
+                        };
+                        text.push(escaped);
+                        i += 2;
+                    }
+                    Some(&c) => {
+                        text.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            tokens.push(Token::Str(text));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let punct = PUNCTUATION
+                .iter()
+                .find(|p| rest.starts_with(**p))
+                .ok_or_else(|| format!("unexpected `{}`", c))?;
+            tokens.push(Token::Punct(punct));
+            i += punct.chars().count();
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn syntax(&self, reason: impl Into<String>) -> ExpressionError {
+        ExpressionError::Syntax {
+            expression: self.source.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        let found = self.peek_punct(punct);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<(), ExpressionError> {
+        if self.eat_punct(punct) {
+            return Ok(());
+        }
+        Err(match self.peek() {
+            Some(token) => self.syntax(format!("expected `{}`, found `{}`", punct, token)),
+            None => self.syntax(format!("expected `{}`", punct)),
+        })
+    }
+
+    /// Go one level deeper into the tree being built
+    fn descend(&mut self) -> Result<(), ExpressionError> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING {
+            return Err(ExpressionError::Limit {
+                expression: self.source.to_string(),
+                reason: format!("nested more than {} levels deep", MAX_NESTING),
+            });
+        }
+        Ok(())
+    }
+
+    /// Run `parse` one level deeper, restoring the level afterwards
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, ExpressionError>) -> Result<T, ExpressionError> {
+        let depth = self.depth;
+        self.descend()?;
+        let parsed = parse(self);
+        self.depth = depth;
+        parsed
+    }
+
+    fn expression(&mut self) -> Result<Node, ExpressionError> {
+        self.nested(|p| {
+            let condition = p.binary(0)?;
+            if !p.eat_punct("?") {
+                return Ok(condition);
+            }
+            let then = p.expression()?;
+            p.expect_punct(":")?;
+            let otherwise = p.expression()?;
+            Ok(Node::Conditional {
+                condition: Box::new(condition),
+                then: Box::new(then),
+                otherwise: Box::new(otherwise),
+            })
+        })
+    }
+
+    /// Binary operator at the next token, if it binds at least as tight as
+    /// `level`
+    fn binary_op(&self, level: usize) -> Option<(BinaryOp, usize)> {
+        let op = match self.peek()? {
+            Token::Punct("||") => (BinaryOp::Or, 0),
+            Token::Punct("&&") => (BinaryOp::And, 1),
+            Token::Punct("==") => (BinaryOp::Eq, 2),
+            Token::Punct("!=") => (BinaryOp::Ne, 2),
+            Token::Punct("<") => (BinaryOp::Lt, 2),
+            Token::Punct("<=") => (BinaryOp::Le, 2),
+            Token::Punct(">") => (BinaryOp::Gt, 2),
+            Token::Punct(">=") => (BinaryOp::Ge, 2),
+            Token::Ident(name) if name == "in" => (BinaryOp::In, 2),
+            Token::Punct("+") => (BinaryOp::Add, 3),
+            Token::Punct("-") => (BinaryOp::Sub, 3),
+            Token::Punct("*") => (BinaryOp::Mul, 4),
+            Token::Punct("/") => (BinaryOp::Div, 4),
+            Token::Punct("%") => (BinaryOp::Rem, 4),
+            _ => return None,
+        };
+        (op.1 >= level).then_some(op)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Node, ExpressionError> {
+        self.nested(|p| p.binary_chain(level))
+    }
+
+    /// Operators at `level` and tighter; each one nests the tree deeper
+    fn binary_chain(&mut self, level: usize) -> Result<Node, ExpressionError> {
+        let mut left = self.unary()?;
+        while let Some((op, op_level)) = self.binary_op(level) {
+            self.descend()?;
+            self.position += 1;
+            // Comparisons do not chain; the others are left-associative
+            let right = self.nested(|p| p.binary(op_level + 1))?;
+            left = Node::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+            if op_level == 2 && self.binary_op(2).is_some_and(|(_, next)| next == 2) {
+                return Err(self.syntax("comparisons cannot be chained, use && between them"));
+            }
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Node, ExpressionError> {
+        self.nested(|p| {
+            let op = if p.eat_punct("!") {
+                UnaryOp::Not
+            } else if p.eat_punct("-") {
+                UnaryOp::Neg
+            } else {
+                return p.postfix();
+            };
+            Ok(Node::Unary {
+                op,
+                operand: Box::new(p.unary()?),
+            })
+        })
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Node>, ExpressionError> {
+        let mut args = Vec::new();
+        if self.eat_punct(")") {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expression()?);
+            if self.eat_punct(")") {
+                return Ok(args);
+            }
+            self.expect_punct(",")?;
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Node, ExpressionError> {
+        self.nested(|p| p.postfix_chain())
+    }
+
+    /// Member accesses, indexes and method calls; all but fixed path
+    /// segments nest the tree deeper
+    fn postfix_chain(&mut self) -> Result<Node, ExpressionError> {
+        let mut node = self.primary()?;
+        loop {
+            if self.eat_punct(".") {
+                let name = match self.peek() {
+                    Some(Token::Ident(name)) => name.clone(),
+                    _ => return Err(self.syntax("expected a name after `.`")),
+                };
+                self.position += 1;
+                if self.eat_punct("(") {
+                    let method = Method::parse(&name).ok_or_else(|| {
+                        self.syntax(format!("unknown method {} (available: {})", name, Method::NAMES.join(", ")))
+                    })?;
+                    self.descend()?;
+                    let args = self.arguments()?;
+                    if args.len() != method.arity() {
+                        return Err(self.syntax(format!("{}() takes {} argument(s)", name, method.arity())));
+                    }
+                    node = Node::Method {
+                        target: Box::new(node),
+                        method,
+                        args,
+                    };
+                } else {
+                    node = match node {
+                        Node::Path { root, mut segments } => {
+                            segments.push(Segment::Key(name));
+                            Node::Path { root, segments }
+                        }
+                        target => {
+                            self.descend()?;
+                            Node::Member {
+                                target: Box::new(target),
+                                name,
+                            }
+                        }
+                    };
+                }
+            } else if self.eat_punct("[") {
+                let index = self.nested(|p| p.expression())?;
+                self.expect_punct("]")?;
+                let segment = match &index {
+                    Node::Literal(Value::Number(n)) => n.as_u64().map(|i| Segment::Index(i as usize)),
+                    Node::Literal(Value::String(key)) => Some(Segment::Key(key.clone())),
+                    _ => None,
+                };
+                node = match (node, segment) {
+                    (Node::Path { root, mut segments }, Some(segment)) => {
+                        segments.push(segment);
+                        Node::Path { root, segments }
+                    }
+                    (target, _) => {
+                        self.descend()?;
+                        Node::Index {
+                            target: Box::new(target),
+                            index: Box::new(index),
+                        }
+                    }
+                };
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node, ExpressionError> {
+        let token = self.peek().cloned().ok_or_else(|| self.syntax("unexpected end of expression"))?;
+        self.position += 1;
+        match token {
+            Token::Number(n) => Ok(Node::Literal(Value::Number(n))),
+            Token::Str(s) => Ok(Node::Literal(Value::String(s))),
+            Token::Ident(name) => match name.as_str() {
+                "true" => Ok(Node::Literal(Value::Bool(true))),
+                "false" => Ok(Node::Literal(Value::Bool(false))),
+                "null" => Ok(Node::Literal(Value::Null)),
+                "in" => Err(self.syntax("expected a value before `in`")),
+                _ if self.eat_punct("(") => {
+                    let function = Function::parse(&name).ok_or_else(|| {
+                        self.syntax(format!("unknown function {} (available: {})", name, Function::NAMES.join(", ")))
+                    })?;
+                    let args = self.arguments()?;
+                    if args.len() != 1 {
+                        return Err(self.syntax(format!("{}() takes 1 argument", name)));
+                    }
+                    if function == Function::Has && !matches!(args[0], Node::Path { .. } | Node::Member { .. } | Node::Index { .. }) {
+                        return Err(self.syntax("has() takes a path, e.g. has(event.payload.ref)"));
+                    }
+                    Ok(Node::Call { function, args })
+                }
+                _ => Ok(Node::Path {
+                    root: name,
+                    segments: Vec::new(),
+                }),
+            },
+            Token::Punct("(") => {
+                let node = self.expression()?;
+                self.expect_punct(")")?;
+                Ok(node)
+            }
+            Token::Punct("[") => {
+                let mut items = Vec::new();
+                if !self.eat_punct("]") {
+                    loop {
+                        items.push(self.expression()?);
+                        if self.eat_punct("]") {
+                            break;
+                        }
+                        self.expect_punct(",")?;
+                    }
+                }
+                Ok(Node::List(items))
+            }
+            other => Err(self.syntax(format!("unexpected `{}`", other))),
+        }
+    }
+}
+
+/// A parsed expression
+#[derive(Debug, Clone)]
+pub struct Expr {
+    source: String,
+    root: Node,
+}
+
+impl Expr {
+    /// Parse `source`
+    pub fn parse(source: &str) -> Result<Self, ExpressionError> {
+        let syntax = |reason: String| ExpressionError::Syntax {
+            expression: source.to_string(),
+            reason,
+        };
+        if source.len() > MAX_EXPRESSION_LEN {
+            return Err(ExpressionError::Limit {
+                expression: source.chars().take(64).collect::<String>() + "...",
+                reason: format!("longer than {} bytes", MAX_EXPRESSION_LEN),
+            });
+        }
+        let tokens = tokenize(source).map_err(syntax)?;
+        if tokens.is_empty() {
+            return Err(syntax("expected an expression".to_string()));
+        }
+
+        let mut parser = Parser {
+            source,
+            tokens,
+            position: 0,
+            depth: 0,
+        };
+        let root = parser.expression()?;
+        if let Some(token) = parser.peek() {
+            return Err(parser.syntax(format!("unexpected `{}`", token)));
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Variables the expression reads, with the fixed keys and indexes read
+    /// below them
+    pub fn paths(&self) -> Vec<(&str, &[Segment])> {
+        fn walk<'a>(node: &'a Node, paths: &mut Vec<(&'a str, &'a [Segment])>) {
+            match node {
+                Node::Literal(_) => {}
+                Node::Path { root, segments } => paths.push((root, segments)),
+                Node::List(items) | Node::Call { args: items, .. } => items.iter().for_each(|item| walk(item, paths)),
+                Node::Member { target, .. } | Node::Unary { operand: target, .. } => walk(target, paths),
+                Node::Index { target, index } => {
+                    walk(target, paths);
+                    walk(index, paths);
+                }
+                Node::Binary { left, right, .. } => {
+                    walk(left, paths);
+                    walk(right, paths);
+                }
+                Node::Conditional { condition, then, otherwise } => {
+                    walk(condition, paths);
+                    walk(then, paths);
+                    walk(otherwise, paths);
+                }
+                Node::Method { target, args, .. } => {
+                    walk(target, paths);
+                    args.iter().for_each(|arg| walk(arg, paths));
+                }
+            }
+        }
+
+        let mut paths = Vec::new();
+        walk(&self.root, &mut paths);
+        paths
+    }
+
+    /// Names the expression reads
+    pub fn names(&self) -> BTreeSet<&str> {
+        self.paths().into_iter().map(|(root, _)| root).collect()
+    }
+
+    /// Type the expression evaluates to, checking it only uses the names in
+    /// `env` and applies operators and functions to values they accept
+    pub fn check(&self, env: &TypeEnv) -> Result<ValueType, ExpressionError> {
+        self.check_node(&self.root, env)
+    }
+
+    /// Check the expression evaluates to a boolean
+    pub fn check_condition(&self, env: &TypeEnv) -> Result<(), ExpressionError> {
+        match self.check(env)? {
+            ValueType::Bool | ValueType::Dyn => Ok(()),
+            other => Err(self.type_error(format!("a condition must be a boolean, not {}", other))),
+        }
+    }
+
+    /// Evaluate the expression, reading variables from `resolver`
+    pub fn evaluate(&self, resolver: &dyn Resolver) -> Result<Value, ExpressionError> {
+        self.eval(&self.root, resolver)
+    }
+
+    /// Evaluate the expression as a condition
+    pub fn evaluate_condition(&self, resolver: &dyn Resolver) -> Result<bool, ExpressionError> {
+        match self.evaluate(resolver)? {
+            Value::Bool(result) => Ok(result),
+            other => Err(self.type_error(format!("a condition must be a boolean, not {}", type_name(&other)))),
+        }
+    }
+
+    fn type_error(&self, reason: impl Into<String>) -> ExpressionError {
+        ExpressionError::Type {
+            expression: self.source.clone(),
+            reason: reason.into(),
+        }
+    }
+
+    fn limit(&self, reason: impl Into<String>) -> ExpressionError {
+        ExpressionError::Limit {
+            expression: self.source.clone(),
+            reason: reason.into(),
+        }
+    }
+
+    fn expect_type(&self, actual: ValueType, expected: ValueType, what: &str) -> Result<(), ExpressionError> {
+        if actual.allows(expected) {
+            Ok(())
+        } else {
+            Err(self.type_error(format!("{} must be {}, not {}", what, expected, actual)))
+        }
+    }
+
+    fn check_node(&self, node: &Node, env: &TypeEnv) -> Result<ValueType, ExpressionError> {
+        Ok(match node {
+            Node::Literal(value) => ValueType::of(value),
+            Node::List(items) => {
+                for item in items {
+                    self.check_node(item, env)?;
+                }
+                ValueType::List
+            }
+            Node::Path { root, segments } => {
+                let root_type = *env.names.get(root).ok_or_else(|| ExpressionError::UnknownName {
+                    expression: self.source.clone(),
+                    name: root.clone(),
+                    available: env.names.keys().cloned().collect(),
+                })?;
+                match segments.first() {
+                    None => root_type,
+                    Some(segment) if root_type.is_scalar() => {
+                        return Err(ExpressionError::NotTraversable {
+                            expression: self.source.clone(),
+                            parent: root.clone(),
+                            segment: segment.to_string(),
+                            found: match root_type {
+                                ValueType::Null => "null",
+                                ValueType::Bool => "a boolean",
+                                ValueType::Number => "a number",
+                                _ => "a string",
+                            },
+                        })
+                    }
+                    Some(_) => ValueType::Dyn,
+                }
+            }
+            Node::Member { target, name } => {
+                let target_type = self.check_node(target, env)?;
+                if target_type.is_scalar() {
+                    return Err(self.type_error(format!("{} has no field {}", target_type, name)));
+                }
+                ValueType::Dyn
+            }
+            Node::Index { target, index } => {
+                let target_type = self.check_node(target, env)?;
+                let index_type = self.check_node(index, env)?;
+                if target_type.is_scalar() {
+                    return Err(self.type_error(format!("{} cannot be indexed", target_type)));
+                }
+                if !matches!(index_type, ValueType::Number | ValueType::String | ValueType::Dyn) {
+                    return Err(self.type_error(format!("an index must be a number or string, not {}", index_type)));
+                }
+                ValueType::Dyn
+            }
+            Node::Unary { op: UnaryOp::Not, operand } => {
+                self.expect_type(self.check_node(operand, env)?, ValueType::Bool, "the operand of !")?;
+                ValueType::Bool
+            }
+            Node::Unary { op: UnaryOp::Neg, operand } => {
+                self.expect_type(self.check_node(operand, env)?, ValueType::Number, "the operand of -")?;
+                ValueType::Number
+            }
+            Node::Binary { op, left, right } => {
+                let left = self.check_node(left, env)?;
+                let right = self.check_node(right, env)?;
+                self.check_binary(*op, left, right)?
+            }
+            Node::Conditional { condition, then, otherwise } => {
+                self.expect_type(self.check_node(condition, env)?, ValueType::Bool, "the condition of ?:")?;
+                let then = self.check_node(then, env)?;
+                let otherwise = self.check_node(otherwise, env)?;
+                if then == otherwise {
+                    then
+                } else {
+                    ValueType::Dyn
+                }
+            }
+            Node::Call { function, args } => {
+                let arg = self.check_node(&args[0], env)?;
+                match function {
+                    Function::Size => {
+                        if !matches!(arg, ValueType::String | ValueType::List | ValueType::Map | ValueType::Dyn) {
+                            return Err(self.type_error(format!("size() takes a string, list or object, not {}", arg)));
+                        }
+                        ValueType::Number
+                    }
+                    Function::Has => ValueType::Bool,
+                    Function::Int => {
+                        if matches!(arg, ValueType::List | ValueType::Map | ValueType::Null) {
+                            return Err(self.type_error(format!("int() takes a number, string or boolean, not {}", arg)));
+                        }
+                        ValueType::Number
+                    }
+                    Function::String => ValueType::String,
+                }
+            }
+            Node::Method { target, method, args } => {
+                self.expect_type(self.check_node(target, env)?, ValueType::String, "the target of a string method")?;
+                for arg in args {
+                    self.expect_type(self.check_node(arg, env)?, ValueType::String, "the argument of a string method")?;
+                }
+                if let (Method::Matches, [Node::Literal(Value::String(pattern))]) = (method, args.as_slice()) {
+                    self.regex(pattern)?;
+                }
+                match method {
+                    Method::Lower | Method::Upper => ValueType::String,
+                    _ => ValueType::Bool,
+                }
+            }
+        })
+    }
+
+    fn check_binary(&self, op: BinaryOp, left: ValueType, right: ValueType) -> Result<ValueType, ExpressionError> {
+        use ValueType::*;
+        let mismatch = || {
+            self.type_error(format!("{} cannot be applied to {} and {}", op.symbol(), left, right))
+        };
+        Ok(match op {
+            BinaryOp::Or | BinaryOp::And => {
+                self.expect_type(left, Bool, &format!("the left side of {}", op.symbol()))?;
+                self.expect_type(right, Bool, &format!("the right side of {}", op.symbol()))?;
+                Bool
+            }
+            BinaryOp::Eq | BinaryOp::Ne => Bool,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                let comparable = |t: ValueType| matches!(t, Number | String | Dyn);
+                if !comparable(left) || !comparable(right) || (left != Dyn && right != Dyn && left != right) {
+                    return Err(mismatch());
+                }
+                Bool
+            }
+            BinaryOp::In => {
+                match right {
+                    List | Map | Dyn => {}
+                    String if left.allows(String) => {}
+                    _ => return Err(mismatch()),
+                }
+                Bool
+            }
+            BinaryOp::Add => {
+                let addable = |t: ValueType| matches!(t, Number | String | List | Dyn);
+                if !addable(left) || !addable(right) || (left != Dyn && right != Dyn && left != right) {
+                    return Err(mismatch());
+                }
+                if left == Dyn {
+                    right
+                } else {
+                    left
+                }
+            }
+            BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => {
+                if !left.allows(Number) || !right.allows(Number) {
+                    return Err(mismatch());
+                }
+                Number
+            }
+        })
+    }
+
+    fn regex(&self, pattern: &str) -> Result<regex::Regex, ExpressionError> {
+        compile_regex(pattern).map_err(|e| self.type_error(format!("invalid regex {:?}: {}", pattern, e)))
+    }
+
+    fn eval(&self, node: &Node, resolver: &dyn Resolver) -> Result<Value, ExpressionError> {
+        Ok(match node {
+            Node::Literal(value) => value.clone(),
+            Node::List(items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| self.eval(item, resolver))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Node::Path { root, segments } => resolver.resolve(&self.source, root, segments)?,
+            Node::Member { target, name } => {
+                let target = self.eval(target, resolver)?;
+                traverse(&self.source, "value".to_string(), &target, &[Segment::Key(name.clone())])?
+            }
+            Node::Index { target, index } => {
+                let target = self.eval(target, resolver)?;
+                let segment = match self.eval(index, resolver)? {
+                    Value::String(key) => Segment::Key(key),
+                    Value::Number(n) => match n.as_u64() {
+                        Some(i) => Segment::Index(i as usize),
+                        None => return Err(self.type_error(format!("index {} is not a whole number", n))),
+                    },
+                    other => {
+                        return Err(self.type_error(format!("an index must be a number or string, not {}", type_name(&other))))
+                    }
+                };
+                traverse(&self.source, "value".to_string(), &target, &[segment])?
+            }
+            Node::Unary { op, operand } => match (op, self.eval(operand, resolver)?) {
+                (UnaryOp::Not, Value::Bool(b)) => Value::Bool(!b),
+                (UnaryOp::Neg, Value::Number(n)) => self.arithmetic(BinaryOp::Sub, &Number::from(0), &n)?,
+                (UnaryOp::Not, other) => {
+                    return Err(self.type_error(format!("the operand of ! must be a boolean, not {}", type_name(&other))))
+                }
+                (UnaryOp::Neg, other) => {
+                    return Err(self.type_error(format!("the operand of - must be a number, not {}", type_name(&other))))
+                }
+            },
+            Node::Binary { op: op @ (BinaryOp::And | BinaryOp::Or), left, right } => {
+                let short_circuit = *op == BinaryOp::Or;
+                if self.eval_bool(left, resolver, op)? == short_circuit {
+                    return Ok(Value::Bool(short_circuit));
+                }
+                Value::Bool(self.eval_bool(right, resolver, op)?)
+            }
+            Node::Binary { op, left, right } => {
+                let left = self.eval(left, resolver)?;
+                let right = self.eval(right, resolver)?;
+                self.binary(*op, left, right)?
+            }
+            Node::Conditional { condition, then, otherwise } => {
+                match self.eval(condition, resolver)? {
+                    Value::Bool(true) => self.eval(then, resolver)?,
+                    Value::Bool(false) => self.eval(otherwise, resolver)?,
+                    other => {
+                        return Err(self.type_error(format!(
+                            "the condition of ?: must be a boolean, not {}",
+                            type_name(&other)
+                        )))
+                    }
+                }
+            }
+            Node::Call { function: Function::Has, args } => match self.eval(&args[0], resolver) {
+                Ok(_) => Value::Bool(true),
+                Err(e) if e.is_missing() => Value::Bool(false),
+                Err(e) => return Err(e),
+            },
+            Node::Call { function, args } => {
+                let arg = self.eval(&args[0], resolver)?;
+                self.call(*function, arg)?
+            }
+            Node::Method { target, method, args } => {
+                let target = self.eval(target, resolver)?;
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg, resolver))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.method(*method, target, args)?
+            }
+        })
+    }
+
+    fn eval_bool(&self, node: &Node, resolver: &dyn Resolver, op: &BinaryOp) -> Result<bool, ExpressionError> {
+        match self.eval(node, resolver)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(self.type_error(format!(
+                "the operands of {} must be booleans, not {}",
+                op.symbol(),
+                type_name(&other)
+            ))),
+        }
+    }
+
+    fn binary(&self, op: BinaryOp, left: Value, right: Value) -> Result<Value, ExpressionError> {
+        let mismatch = |left: &Value, right: &Value| {
+            self.type_error(format!(
+                "{} cannot be applied to {} and {}",
+                op.symbol(),
+                type_name(left),
+                type_name(right)
+            ))
+        };
+        Ok(match op {
+            BinaryOp::Eq => Value::Bool(values_equal(&left, &right)),
+            BinaryOp::Ne => Value::Bool(!values_equal(&left, &right)),
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                let ordering = match (&left, &right) {
+                    (Value::Number(a), Value::Number(b)) => compare_numbers(a, b),
+                    (Value::String(a), Value::String(b)) => a.cmp(b),
+                    _ => return Err(mismatch(&left, &right)),
+                };
+                Value::Bool(match op {
+                    BinaryOp::Lt => ordering == Ordering::Less,
+                    BinaryOp::Le => ordering != Ordering::Greater,
+                    BinaryOp::Gt => ordering == Ordering::Greater,
+                    _ => ordering != Ordering::Less,
+                })
+            }
+            BinaryOp::In => Value::Bool(match (&left, &right) {
+                (item, Value::Array(items)) => items.iter().any(|candidate| values_equal(item, candidate)),
+                (Value::String(key), Value::Object(map)) => map.contains_key(key),
+                (Value::String(needle), Value::String(haystack)) => haystack.contains(needle.as_str()),
+                _ => return Err(mismatch(&left, &right)),
+            }),
+            BinaryOp::Add => match (left, right) {
+                (Value::Number(a), Value::Number(b)) => self.arithmetic(op, &a, &b)?,
+                (Value::String(mut a), Value::String(b)) => {
+                    if a.len() + b.len() > MAX_VALUE_LEN {
+                        return Err(self.limit(format!("builds a string longer than {} bytes", MAX_VALUE_LEN)));
+                    }
+                    a.push_str(&b);
+                    Value::String(a)
+                }
+                (Value::Array(mut a), Value::Array(b)) => {
+                    if a.len() + b.len() > MAX_VALUE_LEN {
+                        return Err(self.limit(format!("builds a list longer than {} items", MAX_VALUE_LEN)));
+                    }
+                    a.extend(b);
+                    Value::Array(a)
+                }
+                (left, right) => return Err(mismatch(&left, &right)),
+            },
+            BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => match (&left, &right) {
+                (Value::Number(a), Value::Number(b)) => self.arithmetic(op, a, b)?,
+                _ => return Err(mismatch(&left, &right)),
+            },
+            BinaryOp::And | BinaryOp::Or => unreachable!("evaluated with short-circuiting"),
+        })
+    }
+
+    /// Integer arithmetic while both sides are integers and the result
+    /// fits, floating point otherwise
+    fn arithmetic(&self, op: BinaryOp, a: &Number, b: &Number) -> Result<Value, ExpressionError> {
+        if let (Some(x), Some(y)) = (a.as_i64(), b.as_i64()) {
+            if matches!(op, BinaryOp::Div | BinaryOp::Rem) && y == 0 {
+                return Err(self.type_error("division by zero"));
+            }
+            let result = match op {
+                BinaryOp::Add => x.checked_add(y),
+                BinaryOp::Sub => x.checked_sub(y),
+                BinaryOp::Mul => x.checked_mul(y),
+                BinaryOp::Div => x.checked_div(y),
+                _ => x.checked_rem(y),
+            };
+            if let Some(result) = result {
+                return Ok(Value::Number(result.into()));
+            }
+        }
+
+        let (x, y) = (a.as_f64().unwrap_or(f64::NAN), b.as_f64().unwrap_or(f64::NAN));
+        if matches!(op, BinaryOp::Div | BinaryOp::Rem) && y == 0.0 {
+            return Err(self.type_error("division by zero"));
+        }
+        let result = match op {
+            BinaryOp::Add => x + y,
+            BinaryOp::Sub => x - y,
+            BinaryOp::Mul => x * y,
+            BinaryOp::Div => x / y,
+            _ => x % y,
+        };
+        Number::from_f64(result)
+            .map(Value::Number)
+            .ok_or_else(|| self.type_error(format!("{} {} {} is not a finite number", a, op.symbol(), b)))
+    }
+
+    fn call(&self, function: Function, arg: Value) -> Result<Value, ExpressionError> {
+        Ok(match (function, arg) {
+            (Function::Size, Value::String(s)) => Value::from(s.chars().count()),
+            (Function::Size, Value::Array(items)) => Value::from(items.len()),
+            (Function::Size, Value::Object(map)) => Value::from(map.len()),
+            (Function::Int, Value::Number(n)) => match n.as_i64() {
+                Some(i) => Value::from(i),
+                None => self.truncate(n.as_f64().unwrap_or(f64::NAN))?,
+            },
+            (Function::Int, Value::String(s)) => match s.trim().parse::<i64>() {
+                Ok(i) => Value::from(i),
+                Err(_) => match s.trim().parse::<f64>() {
+                    Ok(f) => self.truncate(f)?,
+                    Err(_) => return Err(self.type_error(format!("int() cannot convert {:?}", s))),
+                },
+            },
+            (Function::Int, Value::Bool(b)) => Value::from(b as i64),
+            (Function::String, Value::String(s)) => Value::String(s),
+            (Function::String, other @ (Value::Number(_) | Value::Bool(_) | Value::Null)) => {
+                Value::String(other.to_string())
+            }
+            (Function::String, other) => {
+                let text = other.to_string();
+                if text.len() > MAX_VALUE_LEN {
+                    return Err(self.limit(format!("builds a string longer than {} bytes", MAX_VALUE_LEN)));
+                }
+                Value::String(text)
+            }
+            (function, other) => {
+                let name = match function {
+                    Function::Size => "size",
+                    Function::Int => "int",
+                    _ => "has",
+                };
+                return Err(self.type_error(format!("{}() cannot take {}", name, type_name(&other))));
+            }
+        })
+    }
+
+    fn truncate(&self, value: f64) -> Result<Value, ExpressionError> {
+        if value.is_finite() && value.abs() < i64::MAX as f64 {
+            Ok(Value::from(value.trunc() as i64))
+        } else {
+            Err(self.type_error(format!("int() cannot convert {}", value)))
+        }
+    }
+
+    fn method(&self, method: Method, target: Value, args: Vec<Value>) -> Result<Value, ExpressionError> {
+        let Value::String(target) = target else {
+            return Err(self.type_error(format!("string methods cannot be called on {}", type_name(&target))));
+        };
+        let arg = match args.first() {
+            Some(Value::String(arg)) => Some(arg.as_str()),
+            Some(other) => {
+                return Err(self.type_error(format!("string methods take strings, not {}", type_name(other))))
+            }
+            None => None,
+        };
+        Ok(match (method, arg) {
+            (Method::Contains, Some(arg)) => Value::Bool(target.contains(arg)),
+            (Method::StartsWith, Some(arg)) => Value::Bool(target.starts_with(arg)),
+            (Method::EndsWith, Some(arg)) => Value::Bool(target.ends_with(arg)),
+            (Method::Matches, Some(pattern)) => Value::Bool(self.regex(pattern)?.is_match(&target)),
+            (Method::Lower, None) => Value::String(target.to_lowercase()),
+            (Method::Upper, None) => Value::String(target.to_uppercase()),
+            _ => return Err(self.type_error("wrong number of method arguments")),
+        })
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn compare_numbers(a: &Number, b: &Number) -> Ordering {
+    match (a.as_i64(), b.as_i64()) {
+        (Some(x), Some(y)) => x.cmp(&y),
+        _ => {
+            let (x, y) = (a.as_f64().unwrap_or(f64::NAN), b.as_f64().unwrap_or(f64::NAN));
+            x.partial_cmp(&y).unwrap_or(Ordering::Equal)
+        }
+    }
+}
+
+/// JSON equality, with `1 == 1.0`
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => compare_numbers(x, y) == Ordering::Equal,
+        (Value::Array(xs), Value::Array(ys)) => {
+            xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| values_equal(x, y))
+        }
+        (Value::Object(xs), Value::Object(ys)) => {
+            xs.len() == ys.len() && xs.iter().all(|(key, x)| ys.get(key).is_some_and(|y| values_equal(x, y)))
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bindings() -> Bindings {
+        Bindings::new()
+            .with(
+                "event",
+                json!({
+                    "type": "push",
+                    "payload": {"ref": "refs/tags/v1.2.0", "commits": [{"id": "a1"}, {"id": "b2"}], "size": 3},
+                }),
+            )
+            .with("environment", json!("production"))
+            .with("replicas", json!(3))
+            .with("my-step", json!({"ok": true}))
+    }
+
+    fn eval(source: &str) -> Result<Value, ExpressionError> {
+        let bindings = bindings();
+        let expr = Expr::parse(source)?;
+        expr.check(&bindings.types())?;
+        expr.evaluate(&bindings)
+    }
+
+    #[test]
+    fn test_operators_and_functions() {
+        assert_eq!(eval("1 + 2 * 3").unwrap(), json!(7));
+        assert_eq!(eval("(1 + 2) * 3 - 10 / 4").unwrap(), json!(7));
+        assert_eq!(eval("7 % 4 == 3 && !(1 > 2)").unwrap(), json!(true));
+        assert_eq!(eval("1 / 2.0").unwrap(), json!(0.5));
+        assert_eq!(eval("1 == 1.0").unwrap(), json!(true));
+        assert_eq!(eval("replicas - 1").unwrap(), json!(2));
+        assert_eq!(eval("'a' + \"b\"").unwrap(), json!("ab"));
+        assert_eq!(eval("[1] + [2, 3]").unwrap(), json!([1, 2, 3]));
+        assert_eq!(eval("environment in ['staging', 'production']").unwrap(), json!(true));
+        assert_eq!(eval("'ref' in event.payload").unwrap(), json!(true));
+        assert_eq!(eval("event.payload.ref.startsWith('refs/tags/')").unwrap(), json!(true));
+        assert_eq!(eval("event.payload.ref.matches('^refs/tags/v[0-9]+')").unwrap(), json!(true));
+        assert_eq!(eval("environment.upper()").unwrap(), json!("PRODUCTION"));
+        assert_eq!(eval("size(event.payload.commits) == 2").unwrap(), json!(true));
+        assert_eq!(eval("event.payload.commits[1].id").unwrap(), json!("b2"));
+        assert_eq!(eval("event['payload'][\"commits\"][replicas - 3].id").unwrap(), json!("a1"));
+        assert_eq!(eval("has(event.payload.force) || has(event.payload.ref)").unwrap(), json!(true));
+        assert_eq!(eval("replicas > 2 ? 'large' : 'small'").unwrap(), json!("large"));
+        assert_eq!(eval("int('42') + int(2.9) + int(true)").unwrap(), json!(45));
+        assert_eq!(eval("string(replicas) + 'x'").unwrap(), json!("3x"));
+        assert_eq!(eval("my-step.ok").unwrap(), json!(true));
+        // Short-circuiting skips the missing path
+        assert_eq!(eval("false && event.payload.missing").unwrap(), json!(false));
+    }
+
+    #[test]
+    fn test_check_finds_errors_before_evaluation() {
+        let env = bindings().types();
+        let check = |source: &str| Expr::parse(source).unwrap().check(&env).unwrap_err();
+
+        assert!(matches!(check("branch == 'main'"), ExpressionError::UnknownName { ref name, .. } if name == "branch"));
+        assert!(check("'a' + 1").to_string().contains("+ cannot be applied to a string and a number"));
+        assert!(check("!replicas").to_string().contains("the operand of ! must be a boolean, not a number"));
+        assert!(check("replicas.count").to_string().contains("replicas is a number, so it has no count"));
+        assert!(check("size(true)").to_string().contains("size() takes a string, list or object"));
+        assert!(check("environment.matches('(')").to_string().contains("invalid regex"));
+        assert!(matches!(
+            Expr::parse("replicas + 1").unwrap().check_condition(&env),
+            Err(ExpressionError::Type { .. })
+        ));
+        assert_eq!(Expr::parse("event.payload.size > 2").unwrap().check(&env).unwrap(), ValueType::Bool);
+    }
+
+    #[test]
+    fn test_syntax_and_runtime_errors() {
+        for source in ["", "1 +", "(1", "a.", "foo(1)", "a.bar()", "1 < 2 < 3", "'open", "a ? b", "#"] {
+            assert!(
+                matches!(Expr::parse(source), Err(ExpressionError::Syntax { .. })),
+                "{:?} should not parse",
+                source
+            );
+        }
+
+        assert!(eval("1 / 0").unwrap_err().to_string().contains("division by zero"));
+        assert!(eval("event.payload.missing").unwrap_err().to_string().contains("event.payload has no missing"));
+        assert!(matches!(eval("event.payload.size + event.type"), Err(ExpressionError::Type { .. })));
+        assert!(eval("9223372036854775807 + 1").is_ok());
+    }
+
+    #[test]
+    fn test_limits() {
+        let long = vec!["1"; MAX_EXPRESSION_LEN].join("+");
+        assert!(matches!(Expr::parse(&long), Err(ExpressionError::Limit { .. })));
+
+        let deep = format!("{}1{}", "(".repeat(MAX_NESTING + 1), ")".repeat(MAX_NESTING + 1));
+        assert!(matches!(Expr::parse(&deep), Err(ExpressionError::Limit { .. })));
+        let deep = "!".repeat(MAX_NESTING + 1) + "true";
+        assert!(matches!(Expr::parse(&deep), Err(ExpressionError::Limit { .. })));
+        let chain = vec!["1"; MAX_NESTING + 1].join(" + ");
+        assert!(matches!(Expr::parse(&chain), Err(ExpressionError::Limit { .. })));
+        let methods = "'a'".to_string() + &".lower()".repeat(MAX_NESTING + 1);
+        assert!(matches!(Expr::parse(&methods), Err(ExpressionError::Limit { .. })));
+
+        let big = Bindings::new().with("s", json!("x".repeat(MAX_VALUE_LEN / 2 + 1)));
+        let expr = Expr::parse("s + s").unwrap();
+        assert!(matches!(expr.evaluate(&big), Err(ExpressionError::Limit { .. })));
+    }
+
+    /// xorshift, so the fuzz tests are reproducible without extra crates
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+            items[(self.next() % items.len() as u64) as usize]
+        }
+    }
+
+    #[test]
+    fn test_fuzz_random_text_never_panics() {
+        let alphabet: Vec<char> = "ab1.0 ()[]'\"\\!=<>&|+-*/%?:,_-eE\u{e9}".chars().collect();
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+        let bindings = bindings();
+        let env = bindings.types();
+        for _ in 0..20_000 {
+            let len = (rng.next() % 24) as usize;
+            let source: String = (0..len).map(|_| alphabet[(rng.next() % alphabet.len() as u64) as usize]).collect();
+            if let Ok(expr) = Expr::parse(&source) {
+                let _ = expr.check(&env);
+                let _ = expr.evaluate(&bindings);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fuzz_generated_expressions_check_soundly() {
+        const ATOMS: &[&str] = &[
+            "1", "2.5", "0", "-3", "'a'", "\"refs/\"", "true", "false", "null", "[1, 'a']", "replicas", "environment",
+            "event", "event.payload", "event.payload.size", "event.payload.commits", "event.payload.commits[0].id",
+            "event.type", "missing",
+        ];
+        const BINARY: &[&str] = &["+", "-", "*", "/", "%", "==", "!=", "<", ">=", "&&", "||", "in"];
+
+        fn generate(rng: &mut Rng, depth: usize) -> String {
+            if depth == 0 || rng.next().is_multiple_of(3) {
+                return rng.pick(ATOMS).to_string();
+            }
+            match rng.next() % 6 {
+                0 => format!("!{}", generate(rng, depth - 1)),
+                1 => format!("({} ? {} : {})", generate(rng, depth - 1), generate(rng, depth - 1), generate(rng, depth - 1)),
+                2 => format!("size({})", generate(rng, depth - 1)),
+                3 => format!("has({})", rng.pick(&["event.payload.ref", "event.payload.force", "event.x.y"])),
+                4 => format!("{}.startsWith({})", generate(rng, depth - 1), generate(rng, depth - 1)),
+                _ => format!("({} {} {})", generate(rng, depth - 1), rng.pick(BINARY), generate(rng, depth - 1)),
+            }
+        }
+
+        let mut rng = Rng(42);
+        let bindings = bindings();
+        let env = bindings.types();
+        for _ in 0..5_000 {
+            let source = generate(&mut rng, 4);
+            let expr = Expr::parse(&source).unwrap_or_else(|e| panic!("{:?} did not parse: {}", source, e));
+            let Ok(checked) = expr.check(&env) else {
+                continue;
+            };
+            // Whatever evaluates has the type the checker promised
+            if let Ok(value) = expr.evaluate(&bindings) {
+                assert!(
+                    checked == ValueType::Dyn || checked == ValueType::of(&value),
+                    "{:?} checked as {:?} but evaluated to {}",
+                    source,
+                    checked,
+                    value
+                );
+            }
+        }
+    }
+}
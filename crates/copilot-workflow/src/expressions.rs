@@ -0,0 +1,539 @@
+//! Step parameter expressions
+//!
+//! Step parameters can reference the outputs of earlier steps and the shared
+//! state of the execution with `${{ ... }}` expressions, written in the
+//! [expression language](crate::expr):
+//!
+//! ```text
+//! ${{ steps.build.outputs.artifact_url }}
+//! ${{ steps.test.outputs.report.failures[0].name }}
+//! ${{ state.release_tag }}
+//! ${{ workflow.id }} / ${{ execution.id }}
+//! ${{ steps.test.outputs.failures == 0 ? "green" : "red" }}
+//! ```
+//!
+//! A parameter consisting of a single expression takes the value with its
+//! JSON type, so numbers, lists and objects pass through unchanged.
+//! Expressions embedded in text are rendered into it, strings as they are
+//! and other values as JSON. Condition steps evaluate their whole
+//! `expression` as one boolean expression.
+//!
+//! Templates use the same language in `{{ ... }}` placeholders, which read
+//! the template's parameters when it is instantiated and leave `${{ }}`
+//! expressions for the run.
+
+use crate::execution::ExecutionContext;
+use crate::expr::{self, Bindings, Expr, Resolver, Segment, TypeEnv, ValueType};
+use crate::step::StepAction;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+pub use crate::expr::ExpressionError;
+
+const OPEN: &str = "${{";
+const TEMPLATE_OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+
+/// Names `${{ }}` expressions may start with
+fn scope_types() -> TypeEnv {
+    TypeEnv::new()
+        .with("steps", ValueType::Map)
+        .with("state", ValueType::Map)
+        .with("workflow", ValueType::Map)
+        .with("execution", ValueType::Map)
+}
+
+/// Parse a `${{ }}` expression, checking its names and the shape of its
+/// paths
+fn parse_expression(source: &str) -> Result<Expr, ExpressionError> {
+    let expression = Expr::parse(source)?;
+    let syntax = |reason: String| ExpressionError::Syntax {
+        expression: source.to_string(),
+        reason,
+    };
+    for (root, segments) in expression.paths() {
+        let key = |index: usize| match segments.get(index) {
+            Some(Segment::Key(key)) => Some(key.as_str()),
+            _ => None,
+        };
+        match root {
+            "steps" => {
+                if key(0).is_none() || key(1) != Some("outputs") {
+                    return Err(syntax("expected steps.<step>.outputs.<name>".to_string()));
+                }
+                if segments.len() < 3 {
+                    return Err(syntax("expected an output name".to_string()));
+                }
+            }
+            "state" if segments.is_empty() => return Err(syntax("expected state.<key>".to_string())),
+            "workflow" | "execution" if key(0) != Some("id") || segments.len() != 1 => {
+                return Err(syntax(format!("expected {}.id", root)));
+            }
+            _ => {}
+        }
+    }
+    expression.check(&scope_types())?;
+    Ok(expression)
+}
+
+/// Steps whose outputs `expression` reads
+fn steps_of(expression: &Expr) -> impl Iterator<Item = String> + '_ {
+    expression.paths().into_iter().filter_map(|(root, segments)| match (root, segments.first()) {
+        ("steps", Some(Segment::Key(step))) => Some(step.clone()),
+        _ => None,
+    })
+}
+
+/// Text or expression pieces of a string
+enum Piece<'a> {
+    Text(&'a str),
+    Expression(&'a str),
+}
+
+/// End of the expression starting at `body`, skipping `}}` inside quoted
+/// strings
+fn find_close(body: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in body.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if body[i..].starts_with(CLOSE) => return Some(i),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Split `text` at `open ... }}` expressions; for `{{`, `${{` expressions
+/// are kept as text
+fn split<'a>(text: &'a str, open: &str) -> Result<Vec<Piece<'a>>, ExpressionError> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    let mut text_start = 0;
+    while let Some(found) = rest[text_start..].find(open) {
+        let start = text_start + found;
+        let body = &rest[start + open.len()..];
+        let end = find_close(body).ok_or_else(|| ExpressionError::Syntax {
+            expression: rest[start..].to_string(),
+            reason: format!("missing closing `{}`", CLOSE),
+        })?;
+        if open == TEMPLATE_OPEN && rest[..start].ends_with('$') {
+            // A run-time expression, passed through
+            text_start = start + open.len() + end + CLOSE.len();
+            continue;
+        }
+        if start > 0 {
+            pieces.push(Piece::Text(&rest[..start]));
+        }
+        pieces.push(Piece::Expression(body[..end].trim()));
+        rest = &body[end + CLOSE.len()..];
+        text_start = 0;
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest));
+    }
+    Ok(pieces)
+}
+
+/// `value` as rendered into text
+fn display(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+/// Strip the `${{ }}` around a condition written as one expression
+fn condition_source(expression: &str) -> &str {
+    let trimmed = expression.trim();
+    match trimmed.strip_prefix(OPEN).and_then(|rest| rest.strip_suffix(CLOSE)) {
+        Some(inner) if find_close(inner).is_none() => inner.trim(),
+        _ => trimmed,
+    }
+}
+
+/// Check a condition step's `expression` parses, reads only step outputs,
+/// state and IDs, and is a boolean
+pub fn parse_condition(expression: &str) -> Result<Expr, ExpressionError> {
+    let parsed = parse_expression(condition_source(expression))?;
+    parsed.check_condition(&scope_types())?;
+    Ok(parsed)
+}
+
+/// Check the `{{ }}` placeholders of a template string only use the
+/// parameters in `types`, in ways their types allow
+pub fn check_template(text: &str, types: &TypeEnv) -> Result<(), ExpressionError> {
+    for piece in split(text, TEMPLATE_OPEN)? {
+        if let Piece::Expression(source) = piece {
+            Expr::parse(source)?.check(types)?;
+        }
+    }
+    Ok(())
+}
+
+/// Render the `{{ }}` placeholders of a template string with `params`,
+/// leaving `${{ }}` expressions for the run; null values render empty
+pub fn render_template(text: &str, params: &Bindings, types: &TypeEnv) -> Result<String, ExpressionError> {
+    let mut rendered = String::with_capacity(text.len());
+    for piece in split(text, TEMPLATE_OPEN)? {
+        match piece {
+            Piece::Text(text) => rendered.push_str(text),
+            Piece::Expression(source) => {
+                let expression = Expr::parse(source)?;
+                expression.check(types)?;
+                match expression.evaluate(params)? {
+                    Value::Null => {}
+                    value => rendered.push_str(&display(value)),
+                }
+            }
+        }
+    }
+    Ok(rendered)
+}
+
+/// Values expressions can reference
+#[derive(Debug, Clone, Default)]
+pub struct ExpressionScope {
+    workflow_id: String,
+    execution_id: String,
+    steps: HashMap<String, HashMap<String, Value>>,
+    state: HashMap<String, Value>,
+}
+
+impl ExpressionScope {
+    pub fn new(workflow_id: impl Into<String>, execution_id: impl Into<String>) -> Self {
+        Self {
+            workflow_id: workflow_id.into(),
+            execution_id: execution_id.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Snapshot of the outputs and state of `context`
+    pub async fn from_context(context: &ExecutionContext) -> Self {
+        Self {
+            workflow_id: context.workflow_id.clone(),
+            execution_id: context.execution_id.clone(),
+            steps: context.get_all_outputs().await,
+            state: context.get_all_state().await,
+        }
+    }
+
+    pub fn with_step_outputs(mut self, step_id: impl Into<String>, outputs: HashMap<String, Value>) -> Self {
+        self.steps.insert(step_id.into(), outputs);
+        self
+    }
+
+    pub fn with_state(mut self, key: impl Into<String>, value: Value) -> Self {
+        self.state.insert(key.into(), value);
+        self
+    }
+
+    /// Render the expressions in `text` into it
+    pub fn render(&self, text: &str) -> Result<String, ExpressionError> {
+        let mut rendered = String::with_capacity(text.len());
+        for piece in split(text, OPEN)? {
+            match piece {
+                Piece::Text(text) => rendered.push_str(text),
+                Piece::Expression(source) => {
+                    rendered.push_str(&display(parse_expression(source)?.evaluate(self)?));
+                }
+            }
+        }
+        Ok(rendered)
+    }
+
+    /// Resolve the expressions in the strings of `value`; a string that is a
+    /// single expression becomes its value
+    pub fn resolve(&self, value: &Value) -> Result<Value, ExpressionError> {
+        match value {
+            Value::String(text) => {
+                if let [Piece::Expression(source)] = split(text, OPEN)?.as_slice() {
+                    return parse_expression(source)?.evaluate(self);
+                }
+                self.render(text).map(Value::String)
+            }
+            Value::Array(items) => items.iter().map(|item| self.resolve(item)).collect(),
+            Value::Object(map) => map
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), self.resolve(value)?)))
+                .collect::<Result<_, _>>()
+                .map(Value::Object),
+            other => Ok(other.clone()),
+        }
+    }
+
+    /// Evaluate a condition step's `expression`
+    pub fn evaluate_condition(&self, expression: &str) -> Result<bool, ExpressionError> {
+        parse_condition(expression)?.evaluate_condition(self)
+    }
+
+    /// `action` with every expression in its parameters resolved
+    pub fn resolve_action(&self, action: &StepAction) -> Result<StepAction, ExpressionError> {
+        let render_map = |map: &HashMap<String, String>| -> Result<HashMap<String, String>, ExpressionError> {
+            map.iter()
+                .map(|(key, value)| Ok((key.clone(), self.render(value)?)))
+                .collect()
+        };
+        let resolve_map = |map: &HashMap<String, Value>| -> Result<HashMap<String, Value>, ExpressionError> {
+            map.iter()
+                .map(|(key, value)| Ok((key.clone(), self.resolve(value)?)))
+                .collect()
+        };
+
+        Ok(match action {
+            StepAction::Command { command, args, env } => StepAction::Command {
+                command: self.render(command)?,
+                args: args.iter().map(|arg| self.render(arg)).collect::<Result<_, _>>()?,
+                env: render_map(env)?,
+            },
+            StepAction::Script { language, code } => StepAction::Script {
+                language: language.clone(),
+                code: self.render(code)?,
+            },
+            StepAction::HttpRequest { method, url, headers, body } => StepAction::HttpRequest {
+                method: method.clone(),
+                url: self.render(url)?,
+                headers: render_map(headers)?,
+                body: body.as_deref().map(|body| self.render(body)).transpose()?,
+            },
+            StepAction::AgentInvoke { agent_id, parameters } => StepAction::AgentInvoke {
+                agent_id: agent_id.clone(),
+                parameters: resolve_map(parameters)?,
+            },
+            // Evaluated as a whole by the step
+            StepAction::Condition { .. } => action.clone(),
+            StepAction::Custom { handler, parameters } => StepAction::Custom {
+                handler: handler.clone(),
+                parameters: resolve_map(parameters)?,
+            },
+            StepAction::MultiAgent { task, orchestration } => StepAction::MultiAgent {
+                task: self.render(task)?,
+                orchestration: orchestration.clone(),
+            },
+            StepAction::TerraformPlan { plan, plan_step, approval_risk, approval_timeout_secs } => {
+                StepAction::TerraformPlan {
+                    plan: plan.as_ref().map(|plan| self.resolve(plan)).transpose()?,
+                    plan_step: plan_step.clone(),
+                    approval_risk: *approval_risk,
+                    approval_timeout_secs: *approval_timeout_secs,
+                }
+            }
+            StepAction::LogAnalysis { logs, logs_step, top_patterns } => StepAction::LogAnalysis {
+                logs: logs.as_deref().map(|logs| self.render(logs)).transpose()?,
+                logs_step: logs_step.clone(),
+                top_patterns: *top_patterns,
+            },
+            StepAction::ExplainSpike { metric, service, start, end, related_metrics } => StepAction::ExplainSpike {
+                metric: self.render(metric)?,
+                service: service.as_deref().map(|service| self.render(service)).transpose()?,
+                start: self.render(start)?,
+                end: self.render(end)?,
+                related_metrics: related_metrics
+                    .iter()
+                    .map(|metric| self.render(metric))
+                    .collect::<Result<_, _>>()?,
+            },
+            StepAction::Wait { .. } => action.clone(),
+        })
+    }
+}
+
+impl Resolver for ExpressionScope {
+    fn resolve(&self, expression: &str, root: &str, segments: &[Segment]) -> Result<Value, ExpressionError> {
+        let key = |index: usize| match segments.get(index) {
+            Some(Segment::Key(key)) => Some(key.as_str()),
+            _ => None,
+        };
+        let missing = |parent: &str, segment: &Segment, available: Vec<String>| ExpressionError::MissingPath {
+            expression: expression.to_string(),
+            parent: parent.to_string(),
+            segment: segment.to_string(),
+            available,
+        };
+
+        match root {
+            "steps" => {
+                // Shapes are checked when parsing
+                let step = key(0).unwrap_or_default();
+                let outputs = self.steps.get(step).ok_or_else(|| ExpressionError::StepNotRun {
+                    expression: expression.to_string(),
+                    step: step.to_string(),
+                    available: expr::sorted_keys(self.steps.keys()),
+                })?;
+                let parent = format!("steps.{}.outputs", step);
+                let name = &segments[2];
+                let value = match name {
+                    Segment::Key(name) => outputs.get(name),
+                    Segment::Index(_) => None,
+                }
+                .ok_or_else(|| missing(&parent, name, expr::sorted_keys(outputs.keys())))?;
+                expr::traverse(expression, format!("{}.{}", parent, name), value, &segments[3..])
+            }
+            "state" => {
+                let name = &segments[0];
+                let value = match name {
+                    Segment::Key(name) => self.state.get(name),
+                    Segment::Index(_) => None,
+                }
+                .ok_or_else(|| missing("state", name, expr::sorted_keys(self.state.keys())))?;
+                expr::traverse(expression, format!("state.{}", name), value, &segments[1..])
+            }
+            "workflow" => Ok(Value::String(self.workflow_id.clone())),
+            _ => Ok(Value::String(self.execution_id.clone())),
+        }
+    }
+}
+
+/// Steps whose outputs the expressions in `action` read, checking the
+/// expressions parse
+pub fn referenced_steps(action: &StepAction) -> Result<BTreeSet<String>, ExpressionError> {
+    fn collect(value: &Value, steps: &mut BTreeSet<String>) -> Result<(), ExpressionError> {
+        match value {
+            Value::String(text) => {
+                for piece in split(text, OPEN)? {
+                    if let Piece::Expression(source) = piece {
+                        steps.extend(steps_of(&parse_expression(source)?));
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    collect(item, steps)?;
+                }
+            }
+            Value::Object(map) => {
+                for value in map.values() {
+                    collect(value, steps)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    let mut steps = BTreeSet::new();
+    if let StepAction::Condition { expression, .. } = action {
+        steps.extend(steps_of(&parse_condition(expression)?));
+        return Ok(steps);
+    }
+    // Actions always serialize; the map keys are plain strings
+    if let Ok(value) = serde_json::to_value(action) {
+        collect(&value, &mut steps)?;
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scope() -> ExpressionScope {
+        ExpressionScope::new("wf-1", "exec-1")
+            .with_step_outputs(
+                "build",
+                HashMap::from([
+                    ("artifact_url".to_string(), json!("s3://builds/app.tar.gz")),
+                    ("size".to_string(), json!(2048)),
+                    ("report".to_string(), json!({"warnings": [{"file": "main.rs"}]})),
+                ]),
+            )
+            .with_state("release", json!("v1.2.0"))
+    }
+
+    #[test]
+    fn test_resolution_keeps_types() {
+        let scope = scope();
+
+        assert_eq!(scope.resolve(&json!("${{ steps.build.outputs.size }}")).unwrap(), json!(2048));
+        assert_eq!(
+            scope.resolve(&json!({"files": ["${{steps.build.outputs.report.warnings[0].file}}"]})).unwrap(),
+            json!({"files": ["main.rs"]})
+        );
+        assert_eq!(
+            scope
+                .render("deploy ${{ steps.build.outputs.artifact_url }} (${{ steps.build.outputs.size }} bytes) as ${{ state.release }}")
+                .unwrap(),
+            "deploy s3://builds/app.tar.gz (2048 bytes) as v1.2.0"
+        );
+        assert_eq!(scope.render("${{ execution.id }}").unwrap(), "exec-1");
+        assert_eq!(scope.render("no expressions").unwrap(), "no expressions");
+    }
+
+    #[test]
+    fn test_errors_name_the_missing_part() {
+        let scope = scope();
+
+        let err = scope.render("${{ steps.build.outputs.artifact }}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`steps.build.outputs.artifact`: steps.build.outputs has no artifact (available: artifact_url, report, size)"
+        );
+        let err = scope.render("${{ steps.test.outputs.log }}").unwrap_err();
+        assert!(matches!(err, ExpressionError::StepNotRun { ref step, .. } if step == "test"));
+        let err = scope.render("${{ steps.build.outputs.size.bytes }}").unwrap_err();
+        assert!(err.to_string().contains("steps.build.outputs.size is a number"));
+        assert!(matches!(scope.render("${{ steps.build }}"), Err(ExpressionError::Syntax { .. })));
+        assert!(matches!(scope.render("${{ state.release"), Err(ExpressionError::Syntax { .. })));
+    }
+
+    #[test]
+    fn test_expressions_compute_values() {
+        let scope = scope();
+
+        assert_eq!(scope.resolve(&json!("${{ steps.build.outputs.size * 2 }}")).unwrap(), json!(4096));
+        assert_eq!(
+            scope.render("${{ state.release.startsWith('v1') ? 'legacy' : 'current' }} build").unwrap(),
+            "legacy build"
+        );
+        assert_eq!(scope.render("${{ '}}' + state.release }}").unwrap(), "}}v1.2.0");
+        assert!(scope.evaluate_condition("size(steps.build.outputs.report.warnings) > 0").unwrap());
+        assert!(!scope.evaluate_condition("${{ has(steps.build.outputs.signature) }}").unwrap());
+        assert!(matches!(
+            scope.evaluate_condition("steps.build.outputs.size"),
+            Err(ExpressionError::Type { .. })
+        ));
+        assert!(matches!(parse_condition("branch == 'main'"), Err(ExpressionError::UnknownName { .. })));
+        assert!(matches!(parse_condition("${{ 1 }} + 1"), Err(ExpressionError::Syntax { .. })));
+    }
+
+    #[test]
+    fn test_templates_leave_run_expressions() {
+        let params = Bindings::new().with("service", json!("checkout")).with("replicas", json!(null));
+        let types = TypeEnv::new().with("service", ValueType::String).with("replicas", ValueType::Number);
+
+        assert_eq!(
+            render_template("deploy {{service}}{{ replicas }} ${{ steps.build.outputs.url }}", &params, &types).unwrap(),
+            "deploy checkout ${{ steps.build.outputs.url }}"
+        );
+        assert!(check_template("{{ services }}", &types).is_err());
+        assert!(check_template("${{ anything }}", &types).is_ok());
+    }
+
+    #[test]
+    fn test_referenced_steps() {
+        let action = StepAction::HttpRequest {
+            method: "POST".to_string(),
+            url: "https://deploy.example.com/${{ steps.build.outputs.id }}".to_string(),
+            headers: HashMap::new(),
+            body: Some("${{ steps.sign.outputs.signature }} ${{ state.release }}".to_string()),
+        };
+
+        let steps: Vec<_> = referenced_steps(&action).unwrap().into_iter().collect();
+        assert_eq!(steps, vec!["build", "sign"]);
+
+        let condition = StepAction::Condition {
+            expression: "steps.test.outputs.passed && steps.scan-deps.outputs.findings == 0".to_string(),
+            true_steps: vec![],
+            false_steps: vec![],
+        };
+        let steps: Vec<_> = referenced_steps(&condition).unwrap().into_iter().collect();
+        assert_eq!(steps, vec!["scan-deps", "test"]);
+    }
+}
@@ -0,0 +1,340 @@
+//! Leader election for scheduler and trigger loops
+//!
+//! When several server replicas run side by side, only the replica holding a
+//! shared lease may fire schedules or process broadcast trigger events. The
+//! lease expires unless renewed, so a crashed leader is replaced within one
+//! TTL.
+
+use crate::{Result, WorkflowError};
+use async_trait::async_trait;
+use copilot_core::{AppResult, LeaseStore};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, RwLock};
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Leader election settings
+#[derive(Debug, Clone)]
+pub struct LeadershipConfig {
+    /// Name of the shared lease, e.g. `workflow-scheduler`
+    pub lease_name: String,
+    /// Identity of this replica
+    pub holder_id: String,
+    /// How long a lease lasts without renewal
+    pub ttl: Duration,
+    /// How often the lease is acquired or renewed; must be below `ttl`
+    pub renew_interval: Duration,
+}
+
+impl LeadershipConfig {
+    pub fn new(lease_name: &str) -> Self {
+        Self {
+            lease_name: lease_name.to_string(),
+            holder_id: Uuid::new_v4().to_string(),
+            ttl: Duration::from_secs(15),
+            renew_interval: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_holder_id(mut self, holder_id: &str) -> Self {
+        self.holder_id = holder_id.to_string();
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration, renew_interval: Duration) -> Self {
+        self.ttl = ttl;
+        self.renew_interval = renew_interval;
+        self
+    }
+}
+
+/// Leadership change counters
+#[derive(Debug, Default)]
+struct LeadershipCounters {
+    acquired: AtomicU64,
+    lost: AtomicU64,
+    errors: AtomicU64,
+}
+
+/// Point-in-time view of leadership metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct LeadershipMetrics {
+    pub lease_name: String,
+    pub holder_id: String,
+    pub is_leader: bool,
+    /// Times this replica became leader
+    pub acquired_total: u64,
+    /// Times this replica lost or gave up leadership
+    pub lost_total: u64,
+    /// Lease store calls that failed
+    pub errors_total: u64,
+}
+
+/// Maintains a lease and reports whether this replica is the leader
+pub struct LeaderElector {
+    store: Arc<dyn LeaseStore>,
+    config: LeadershipConfig,
+    leader: watch::Sender<bool>,
+    counters: LeadershipCounters,
+    running: RwLock<bool>,
+}
+
+impl LeaderElector {
+    pub fn new(store: Arc<dyn LeaseStore>, config: LeadershipConfig) -> Result<Self> {
+        if config.renew_interval >= config.ttl {
+            return Err(WorkflowError::InvalidDefinition(
+                "Lease renew interval must be shorter than its TTL".to_string(),
+            ));
+        }
+
+        let (leader, _) = watch::channel(false);
+        Ok(Self {
+            store,
+            config,
+            leader,
+            counters: LeadershipCounters::default(),
+            running: RwLock::new(false),
+        })
+    }
+
+    pub fn config(&self) -> &LeadershipConfig {
+        &self.config
+    }
+
+    /// Whether this replica currently holds the lease
+    pub fn is_leader(&self) -> bool {
+        *self.leader.borrow()
+    }
+
+    /// Watch leadership changes
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.leader.subscribe()
+    }
+
+    pub fn metrics(&self) -> LeadershipMetrics {
+        LeadershipMetrics {
+            lease_name: self.config.lease_name.clone(),
+            holder_id: self.config.holder_id.clone(),
+            is_leader: self.is_leader(),
+            acquired_total: self.counters.acquired.load(Ordering::Relaxed),
+            lost_total: self.counters.lost.load(Ordering::Relaxed),
+            errors_total: self.counters.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Acquire or renew the lease once
+    ///
+    /// A leader that cannot confirm its lease steps down immediately rather
+    /// than risk running alongside a new leader.
+    pub async fn tick(&self) -> bool {
+        let LeadershipConfig {
+            lease_name,
+            holder_id,
+            ttl,
+            ..
+        } = &self.config;
+
+        let held = if self.is_leader() {
+            self.store.renew(lease_name, holder_id, *ttl).await
+        } else {
+            self.store.try_acquire(lease_name, holder_id, *ttl).await
+        };
+
+        let held = match held {
+            Ok(held) => held,
+            Err(e) => {
+                self.counters.errors.fetch_add(1, Ordering::Relaxed);
+                warn!(lease = %lease_name, error = %e, "Lease store call failed");
+                false
+            }
+        };
+        self.set_leader(held);
+        held
+    }
+
+    /// Keep the lease until [`stop`](Self::stop) is called
+    pub async fn run(&self) {
+        {
+            let mut running = self.running.write().await;
+            if *running {
+                warn!(lease = %self.config.lease_name, "Leader election already running");
+                return;
+            }
+            *running = true;
+        }
+
+        info!(
+            lease = %self.config.lease_name,
+            holder = %self.config.holder_id,
+            "Starting leader election"
+        );
+
+        let mut interval = tokio::time::interval_at(Instant::now(), self.config.renew_interval);
+        loop {
+            interval.tick().await;
+            if !*self.running.read().await {
+                break;
+            }
+            self.tick().await;
+        }
+
+        if self.is_leader() {
+            if let Err(e) = self
+                .store
+                .release(&self.config.lease_name, &self.config.holder_id)
+                .await
+            {
+                warn!(lease = %self.config.lease_name, error = %e, "Failed to release lease");
+            }
+            self.set_leader(false);
+        }
+        info!(lease = %self.config.lease_name, "Leader election stopped");
+    }
+
+    /// Stop the election loop; the lease is released on its next tick
+    pub async fn stop(&self) {
+        *self.running.write().await = false;
+    }
+
+    fn set_leader(&self, leader: bool) {
+        let changed = self.leader.send_if_modified(|current| {
+            let changed = *current != leader;
+            *current = leader;
+            changed
+        });
+        if !changed {
+            return;
+        }
+
+        if leader {
+            self.counters.acquired.fetch_add(1, Ordering::Relaxed);
+            info!(
+                lease = %self.config.lease_name,
+                holder = %self.config.holder_id,
+                "Acquired leadership"
+            );
+        } else {
+            self.counters.lost.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                lease = %self.config.lease_name,
+                holder = %self.config.holder_id,
+                "Lost leadership"
+            );
+        }
+    }
+}
+
+/// In-memory lease store for single-process deployments and testing
+pub struct InMemoryLeaseStore {
+    leases: RwLock<HashMap<String, (String, Instant)>>,
+}
+
+impl InMemoryLeaseStore {
+    pub fn new() -> Self {
+        Self {
+            leases: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for InMemoryLeaseStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl LeaseStore for InMemoryLeaseStore {
+    async fn try_acquire(&self, lease: &str, holder: &str, ttl: Duration) -> AppResult<bool> {
+        let mut leases = self.leases.write().await;
+        let now = Instant::now();
+        match leases.get(lease) {
+            Some((current, expires)) if current != holder && *expires > now => Ok(false),
+            _ => {
+                leases.insert(lease.to_string(), (holder.to_string(), now + ttl));
+                Ok(true)
+            }
+        }
+    }
+
+    async fn renew(&self, lease: &str, holder: &str, ttl: Duration) -> AppResult<bool> {
+        let mut leases = self.leases.write().await;
+        let now = Instant::now();
+        match leases.get_mut(lease) {
+            Some((current, expires)) if current == holder && *expires > now => {
+                *expires = now + ttl;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn release(&self, lease: &str, holder: &str) -> AppResult<()> {
+        let mut leases = self.leases.write().await;
+        if leases
+            .get(lease)
+            .is_some_and(|(current, _)| current == holder)
+        {
+            leases.remove(lease);
+            debug!(lease = %lease, holder = %holder, "Released lease");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elector(store: &Arc<InMemoryLeaseStore>, holder: &str) -> LeaderElector {
+        let config = LeadershipConfig::new("scheduler")
+            .with_holder_id(holder)
+            .with_ttl(Duration::from_millis(100), Duration::from_millis(20));
+        LeaderElector::new(store.clone(), config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_single_leader_and_failover() {
+        let store = Arc::new(InMemoryLeaseStore::new());
+        let a = elector(&store, "replica-a");
+        let b = elector(&store, "replica-b");
+
+        assert!(a.tick().await);
+        assert!(!b.tick().await);
+
+        // Replica A stops renewing; B takes over once the lease expires
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(b.tick().await);
+        assert!(!a.tick().await);
+
+        let metrics = a.metrics();
+        assert_eq!(metrics.acquired_total, 1);
+        assert_eq!(metrics.lost_total, 1);
+        assert!(!metrics.is_leader);
+        assert!(b.is_leader());
+    }
+
+    #[tokio::test]
+    async fn test_release_allows_takeover() {
+        let store = Arc::new(InMemoryLeaseStore::new());
+        let a = elector(&store, "replica-a");
+        let b = elector(&store, "replica-b");
+
+        assert!(a.tick().await);
+        store.release("scheduler", "replica-a").await.unwrap();
+        assert!(b.tick().await);
+    }
+
+    #[test]
+    fn test_renew_interval_must_be_below_ttl() {
+        let config = LeadershipConfig::new("scheduler")
+            .with_ttl(Duration::from_secs(5), Duration::from_secs(5));
+        let store = Arc::new(InMemoryLeaseStore::new());
+        assert!(LeaderElector::new(store, config).is_err());
+    }
+}
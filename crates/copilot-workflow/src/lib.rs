@@ -3,34 +3,75 @@
 //! This crate provides a comprehensive workflow execution engine with:
 //! - DAG-based workflow definition and validation
 //! - Parallel and sequential step execution
-//! - Approval gates with timeout handling
+//! - A sandboxed, type-checked expression language for step and trigger
+//!   conditions, `${{ steps.<id>.outputs.<name> }}` step parameters and
+//!   template placeholders
+//! - Approval gates with timeout handling, quorums, escalation and delegation
 //! - State management and persistence
 //! - Retry logic with exponential backoff
-//! - Real-time workflow status tracking
-//! - Workflow versioning and rollback
-//! - Scheduled workflow execution
-//! - Event-driven workflow triggers
-//! - Workflow templates library
+//! - Real-time workflow status tracking and lifecycle event subscriptions
+//! - Workflow versioning, canary rollouts and rollback
+//! - Scheduled workflow execution with missed-run policies
+//! - Concurrency groups queueing, skipping or replacing overlapping runs
+//! - Event-driven workflow triggers, deduplicating redelivered events
+//! - Workflow templates library with a built-in DevOps seed pack
+//! - Multi-agent orchestration steps
+//! - Terraform/OpenTofu plan review with policy checks and approval
+//! - Log analysis steps clustering log lines into patterns and summarizing them
+//! - Metric spike explanations citing related signals and runbooks
+//! - Leader election for multi-replica scheduling
+//! - A test harness running workflows with mocked steps and approvals
 
 pub mod approval;
+pub mod concurrency;
 pub mod dag;
 pub mod engine;
 pub mod execution;
+pub mod expr;
+pub mod expressions;
+pub mod leadership;
+pub mod orchestration;
 pub mod step;
 pub mod versioning;
 pub mod scheduling;
+pub mod spike;
 pub mod triggers;
 pub mod templates;
-
-pub use approval::{ApprovalGate, ApprovalRequest, ApprovalStatus};
+pub mod terraform;
+pub mod testing;
+
+pub use approval::{
+    ApprovalDecision, ApprovalGate, ApprovalPolicy, ApprovalRequest, ApprovalStatus, ApproverGroup, Delegation,
+    Escalation, Vote,
+};
+pub use concurrency::{ConcurrencyGroup, ConcurrencyPolicy};
 pub use dag::{WorkflowDag, DagValidationError};
-pub use engine::{WorkflowEngine, WorkflowDefinition, WorkflowStatus, WorkflowState};
-pub use execution::{ExecutionContext, StepExecutor, RetryConfig};
-pub use step::{WorkflowStep, StepType, StepState, StepResult, StepAction};
-pub use versioning::{WorkflowVersion, VersionManager, VersionBump, VersionRepository};
-pub use scheduling::{Schedule, ScheduledWorkflow, WorkflowScheduler, ScheduleRepository};
-pub use triggers::{TriggerEvent, TriggerCondition, WorkflowTrigger, TriggerManager, EventBus, EventSource};
-pub use templates::{WorkflowTemplate, TemplateParameter, TemplateLibrary, TemplateBuilders};
+pub use engine::{WorkflowEngine, WorkflowDefinition, WorkflowEvent, WorkflowEventKind, WorkflowStatus, WorkflowState};
+pub use execution::{ExecutionContext, StepExecutor, StepRetry, RetryConfig};
+pub use expr::{Bindings, Expr, TypeEnv, ValueType};
+pub use expressions::{ExpressionError, ExpressionScope};
+pub use leadership::{InMemoryLeaseStore, LeaderElector, LeadershipConfig, LeadershipMetrics};
+pub use orchestration::{
+    AgentRole, AgentToolExecutor, AgentTurnHandler, AgentTurnOutput, MultiAgentOrchestration, NoAgentTools,
+    OrchestrationOutcome, PersonaLookup, RolePersona, RoleTools, SimulatedAgentHandler, TerminationCondition,
+    TerminationReason, TurnPolicy,
+};
+pub use step::{WorkflowStep, StepType, StepState, StepResult, StepAction, CancellationReason};
+pub use versioning::{
+    CanaryComparison, VersionBump, VersionManager, VersionRepository, VersionRunStats, WorkflowVersion,
+};
+pub use scheduling::{MissedRunPolicy, Schedule, ScheduledWorkflow, WorkflowScheduler, ScheduleRepository};
+pub use triggers::{
+    DedupConfig, EventBus, EventSource, InMemoryDedupStore, TriggerCondition, TriggerEvent, TriggerManager,
+    WorkflowTrigger,
+};
+pub use templates::{
+    ParameterError, ParameterType, ParameterValidation, TemplateBuilders, TemplateDocs, TemplateLibrary,
+    TemplateParameter, WorkflowTemplate,
+};
+pub use spike::{Evidence, EvidenceKind, MetricShift, SpikeReport, SpikeRequest};
+pub use terraform::{ChangeKind, PlanAnalysis, PlanRisk, ResourceChange};
+pub use testing::{MockStep, SimulatedApproval, StepCall, WorkflowTestHarness, WorkflowTestRun};
 
 use thiserror::Error;
 
@@ -54,6 +95,12 @@ pub enum WorkflowError {
     #[error("Workflow already running: {0}")]
     AlreadyRunning(String),
 
+    #[error("Concurrency group {key} is held by run {holder}")]
+    ConcurrencyLimited {
+        key: String,
+        holder: String,
+    },
+
     #[error("Workflow not running: {0}")]
     NotRunning(String),
 
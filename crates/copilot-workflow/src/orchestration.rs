@@ -0,0 +1,679 @@
+//! Multi-agent orchestration primitives
+//!
+//! Several agent roles (e.g. planner, coder, reviewer), each with its own
+//! persona and tool allowlist, take turns on a shared task. Every turn writes
+//! to a shared [`Scratchpad`] so later roles build on earlier work. A turn
+//! policy picks who speaks next and a termination condition decides when the
+//! collaboration is done.
+//!
+//! Agents reach tools only through the [`RoleTools`] handed to each turn,
+//! which refuses calls outside the role's allowlist before they run. Roles
+//! that name a persona take its prompt and tools from a [`PersonaLookup`].
+
+use crate::{Result, WorkflowError};
+use async_trait::async_trait;
+use copilot_context::{Scratchpad, ScratchpadEntry};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// Hard upper bound on turns, regardless of the configured termination
+pub const MAX_TURNS_LIMIT: u32 = 100;
+
+/// An agent participating in an orchestration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentRole {
+    /// Role name (e.g. "planner"), used as the scratchpad author
+    pub name: String,
+    /// Persona backing this role, if managed server-side
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona_id: Option<String>,
+    /// Instructions for the role
+    #[serde(default)]
+    pub system_prompt: String,
+    /// Tools this role may call; with a persona, empty means the persona's
+    /// tools and anything else is narrowed to them
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+}
+
+impl AgentRole {
+    pub fn new(name: impl Into<String>, system_prompt: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            persona_id: None,
+            system_prompt: system_prompt.into(),
+            allowed_tools: Vec::new(),
+        }
+    }
+
+    pub fn with_persona(mut self, persona_id: impl Into<String>) -> Self {
+        self.persona_id = Some(persona_id.into());
+        self
+    }
+
+    pub fn with_tools(mut self, tools: &[&str]) -> Self {
+        self.allowed_tools = tools.iter().map(|t| t.to_string()).collect();
+        self
+    }
+
+    /// Whether this role may call `tool`
+    pub fn allows_tool(&self, tool: &str) -> bool {
+        self.allowed_tools.iter().any(|t| t == "*" || t == tool)
+    }
+
+    /// This role with `persona`'s prompt and tools applied
+    ///
+    /// The persona's prompt comes first, followed by the role's own
+    /// instructions. A role can narrow the persona's tools but never widen
+    /// them.
+    pub fn with_persona_applied(&self, persona: &RolePersona) -> Self {
+        let system_prompt = match (persona.system_prompt.is_empty(), self.system_prompt.is_empty()) {
+            (_, true) => persona.system_prompt.clone(),
+            (true, false) => self.system_prompt.clone(),
+            (false, false) => format!("{}\n\n{}", persona.system_prompt, self.system_prompt),
+        };
+        let persona_allows = |tool: &str| persona.allowed_tools.iter().any(|t| t == "*" || t == tool);
+        let allowed_tools = if self.allowed_tools.is_empty() || self.allowed_tools.iter().any(|t| t == "*") {
+            persona.allowed_tools.clone()
+        } else {
+            self.allowed_tools.iter().filter(|t| persona_allows(t)).cloned().collect()
+        };
+
+        Self {
+            system_prompt,
+            allowed_tools,
+            ..self.clone()
+        }
+    }
+}
+
+/// Prompt and tools a persona contributes to the roles that name it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RolePersona {
+    pub system_prompt: String,
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+}
+
+/// Resolves the personas named by [`AgentRole::persona_id`]
+#[async_trait]
+pub trait PersonaLookup: Send + Sync {
+    /// The persona with `persona_id`, if it exists
+    async fn persona(&self, persona_id: &str) -> Option<RolePersona>;
+}
+
+/// Runs the tools agents call during their turns
+#[async_trait]
+pub trait AgentToolExecutor: Send + Sync {
+    async fn call_tool(&self, tool: &str, input: serde_json::Value) -> Result<serde_json::Value>;
+}
+
+/// Tool executor used when none is configured: every call fails
+#[derive(Debug, Clone, Default)]
+pub struct NoAgentTools;
+
+#[async_trait]
+impl AgentToolExecutor for NoAgentTools {
+    async fn call_tool(&self, tool: &str, _input: serde_json::Value) -> Result<serde_json::Value> {
+        Err(WorkflowError::StepExecutionFailed {
+            step_id: tool.to_string(),
+            reason: "no tool executor is configured for agents".to_string(),
+        })
+    }
+}
+
+/// A role's access to tools for one turn
+///
+/// Calls the role is not allowed to make are refused here, before they reach
+/// the executor.
+pub struct RoleTools<'a> {
+    role: &'a AgentRole,
+    executor: &'a dyn AgentToolExecutor,
+    calls: Mutex<Vec<String>>,
+    refused: Mutex<Option<String>>,
+}
+
+impl<'a> RoleTools<'a> {
+    pub fn new(role: &'a AgentRole, executor: &'a dyn AgentToolExecutor) -> Self {
+        Self {
+            role,
+            executor,
+            calls: Mutex::new(Vec::new()),
+            refused: Mutex::new(None),
+        }
+    }
+
+    /// Call `tool` on behalf of the role
+    pub async fn call(&self, tool: &str, input: serde_json::Value) -> Result<serde_json::Value> {
+        if !self.role.allows_tool(tool) {
+            self.refused.lock().unwrap().get_or_insert_with(|| tool.to_string());
+            return Err(disallowed_tool(self.role, tool));
+        }
+        self.calls.lock().unwrap().push(tool.to_string());
+        self.executor.call_tool(tool, input).await
+    }
+
+    /// Tools called so far, in order
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// First tool call that was refused, if any
+    pub fn refused(&self) -> Option<String> {
+        self.refused.lock().unwrap().clone()
+    }
+}
+
+fn disallowed_tool(role: &AgentRole, tool: &str) -> WorkflowError {
+    WorkflowError::StepExecutionFailed {
+        step_id: role.name.clone(),
+        reason: format!("agent role {} is not allowed to call tool {}", role.name, tool),
+    }
+}
+
+/// How the next speaker is chosen
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TurnPolicy {
+    /// Cycle through roles in declaration order
+    #[default]
+    RoundRobin,
+    /// Follow a fixed sequence of role names, repeating it
+    Sequence { order: Vec<String> },
+    /// Each turn may name the next role; falls back to round robin
+    Directed,
+}
+
+/// When the collaboration stops
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TerminationCondition {
+    /// Stop after a fixed number of turns
+    MaxTurns { turns: u32 },
+    /// Stop when a turn's output contains `keyword` (optionally only from `role`)
+    Keyword {
+        keyword: String,
+        #[serde(default)]
+        role: Option<String>,
+    },
+    /// Stop when any agent signals it is done
+    AgentSignal,
+    /// Stop when any of the nested conditions holds
+    Any { conditions: Vec<TerminationCondition> },
+}
+
+impl TerminationCondition {
+    fn is_met(&self, turns_taken: u32, last: &TurnRecord) -> bool {
+        match self {
+            TerminationCondition::MaxTurns { turns } => turns_taken >= *turns,
+            TerminationCondition::Keyword { keyword, role } => {
+                role.as_ref().map_or(true, |r| r == &last.role) && last.content.contains(keyword.as_str())
+            }
+            TerminationCondition::AgentSignal => last.done,
+            TerminationCondition::Any { conditions } => {
+                conditions.iter().any(|c| c.is_met(turns_taken, last))
+            }
+        }
+    }
+}
+
+/// What an agent produced on its turn
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentTurnOutput {
+    /// Contribution written to the scratchpad
+    pub content: String,
+    /// Requested next role (used by [`TurnPolicy::Directed`])
+    #[serde(default)]
+    pub next_role: Option<String>,
+    /// Whether the agent considers the task complete
+    #[serde(default)]
+    pub done: bool,
+}
+
+/// Produces a single agent turn (typically backed by an LLM call)
+///
+/// Tools are called through `tools`, never directly.
+#[async_trait]
+pub trait AgentTurnHandler: Send + Sync {
+    async fn take_turn(
+        &self,
+        role: &AgentRole,
+        task: &str,
+        scratchpad: &Scratchpad,
+        tools: &RoleTools<'_>,
+    ) -> Result<AgentTurnOutput>;
+}
+
+/// Record of a completed turn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnRecord {
+    pub turn: u32,
+    pub role: String,
+    pub content: String,
+    #[serde(default)]
+    pub tool_calls: Vec<String>,
+    #[serde(default)]
+    pub done: bool,
+}
+
+/// Why an orchestration stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TerminationReason {
+    /// The configured termination condition was met
+    ConditionMet,
+    /// The hard turn limit was reached first
+    TurnLimit,
+}
+
+/// Result of running an orchestration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrchestrationOutcome {
+    pub turns: Vec<TurnRecord>,
+    pub reason: TerminationReason,
+    pub scratchpad: Vec<ScratchpadEntry>,
+}
+
+/// Runs a group of agent roles against a shared task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiAgentOrchestration {
+    pub roles: Vec<AgentRole>,
+    #[serde(default)]
+    pub turn_policy: TurnPolicy,
+    pub termination: TerminationCondition,
+    /// Hard turn limit (capped at [`MAX_TURNS_LIMIT`])
+    #[serde(default = "default_max_turns")]
+    pub max_turns: u32,
+}
+
+fn default_max_turns() -> u32 {
+    20
+}
+
+impl MultiAgentOrchestration {
+    pub fn new(roles: Vec<AgentRole>, termination: TerminationCondition) -> Self {
+        Self {
+            roles,
+            turn_policy: TurnPolicy::default(),
+            termination,
+            max_turns: default_max_turns(),
+        }
+    }
+
+    pub fn with_turn_policy(mut self, policy: TurnPolicy) -> Self {
+        self.turn_policy = policy;
+        self
+    }
+
+    pub fn with_max_turns(mut self, max_turns: u32) -> Self {
+        self.max_turns = max_turns;
+        self
+    }
+
+    /// Validate the role set and turn policy
+    pub fn validate(&self) -> Result<()> {
+        if self.roles.is_empty() {
+            return Err(WorkflowError::InvalidDefinition(
+                "multi-agent orchestration requires at least one role".to_string(),
+            ));
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        for role in &self.roles {
+            if !seen.insert(role.name.as_str()) {
+                return Err(WorkflowError::InvalidDefinition(format!(
+                    "duplicate agent role: {}",
+                    role.name
+                )));
+            }
+        }
+
+        if let TurnPolicy::Sequence { order } = &self.turn_policy {
+            if order.is_empty() {
+                return Err(WorkflowError::InvalidDefinition(
+                    "turn sequence must not be empty".to_string(),
+                ));
+            }
+            if let Some(unknown) = order.iter().find(|r| !seen.contains(r.as_str())) {
+                return Err(WorkflowError::InvalidDefinition(format!(
+                    "turn sequence references unknown role: {}",
+                    unknown
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolve the personas named by the roles
+    ///
+    /// Fails when a role names a persona that does not exist, or when roles
+    /// name personas but there is nothing to look them up in.
+    pub async fn resolve_roles(&self, personas: Option<&dyn PersonaLookup>) -> Result<Vec<AgentRole>> {
+        let mut roles = Vec::with_capacity(self.roles.len());
+        for role in &self.roles {
+            let Some(persona_id) = &role.persona_id else {
+                roles.push(role.clone());
+                continue;
+            };
+            let persona = match personas {
+                Some(personas) => personas.persona(persona_id).await,
+                None => {
+                    return Err(WorkflowError::InvalidDefinition(format!(
+                        "agent role {} uses persona {} but no personas are available",
+                        role.name, persona_id
+                    )))
+                }
+            };
+            let persona = persona.ok_or_else(|| {
+                WorkflowError::InvalidDefinition(format!(
+                    "agent role {} uses unknown persona {}",
+                    role.name, persona_id
+                ))
+            })?;
+            roles.push(role.with_persona_applied(&persona));
+        }
+        Ok(roles)
+    }
+
+    /// Run the orchestration to completion
+    ///
+    /// Each role's tool calls go to `tools` only if the role allows them; a
+    /// refused call fails the run.
+    pub async fn run(
+        &self,
+        task: &str,
+        handler: Arc<dyn AgentTurnHandler>,
+        tools: &dyn AgentToolExecutor,
+        personas: Option<&dyn PersonaLookup>,
+        scratchpad: &Scratchpad,
+    ) -> Result<OrchestrationOutcome> {
+        self.validate()?;
+        let roles = self.resolve_roles(personas).await?;
+
+        let limit = self.max_turns.clamp(1, MAX_TURNS_LIMIT);
+        let mut turns: Vec<TurnRecord> = Vec::new();
+        let mut current = self.first_role();
+
+        while (turns.len() as u32) < limit {
+            let role = &roles[current];
+            let role_tools = RoleTools::new(role, tools);
+            let output = handler.take_turn(role, task, scratchpad, &role_tools).await?;
+
+            // The handler may have swallowed the refusal
+            if let Some(tool) = role_tools.refused() {
+                return Err(disallowed_tool(role, &tool));
+            }
+
+            scratchpad.write(role.name.clone(), output.content.clone());
+            let record = TurnRecord {
+                turn: turns.len() as u32 + 1,
+                role: role.name.clone(),
+                content: output.content,
+                tool_calls: role_tools.calls(),
+                done: output.done,
+            };
+            tracing::debug!(turn = record.turn, role = %record.role, "Agent turn completed");
+
+            let finished = self.termination.is_met(record.turn, &record);
+            turns.push(record);
+            if finished {
+                return Ok(self.outcome(turns, TerminationReason::ConditionMet, scratchpad));
+            }
+
+            current = self.next_role(current, turns.len(), output.next_role.as_deref());
+        }
+
+        tracing::warn!(limit, "Multi-agent orchestration hit turn limit");
+        Ok(self.outcome(turns, TerminationReason::TurnLimit, scratchpad))
+    }
+
+    fn outcome(
+        &self,
+        turns: Vec<TurnRecord>,
+        reason: TerminationReason,
+        scratchpad: &Scratchpad,
+    ) -> OrchestrationOutcome {
+        OrchestrationOutcome {
+            turns,
+            reason,
+            scratchpad: scratchpad.entries(),
+        }
+    }
+
+    fn role_index(&self, name: &str) -> Option<usize> {
+        self.roles.iter().position(|r| r.name == name)
+    }
+
+    fn first_role(&self) -> usize {
+        match &self.turn_policy {
+            TurnPolicy::Sequence { order } => self.role_index(&order[0]).unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn next_role(&self, current: usize, turns_taken: usize, requested: Option<&str>) -> usize {
+        let round_robin = (current + 1) % self.roles.len();
+        match &self.turn_policy {
+            TurnPolicy::RoundRobin => round_robin,
+            TurnPolicy::Sequence { order } => {
+                self.role_index(&order[turns_taken % order.len()]).unwrap_or(round_robin)
+            }
+            TurnPolicy::Directed => requested
+                .and_then(|name| self.role_index(name))
+                .unwrap_or(round_robin),
+        }
+    }
+}
+
+/// Handler used when no LLM backend is configured: each role records a
+/// placeholder contribution so workflows can be exercised end to end.
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedAgentHandler;
+
+#[async_trait]
+impl AgentTurnHandler for SimulatedAgentHandler {
+    async fn take_turn(
+        &self,
+        role: &AgentRole,
+        task: &str,
+        scratchpad: &Scratchpad,
+        _tools: &RoleTools<'_>,
+    ) -> Result<AgentTurnOutput> {
+        Ok(AgentTurnOutput {
+            content: format!(
+                "{} contribution #{} to: {}",
+                role.name,
+                scratchpad.entries_by(&role.name).len() + 1,
+                task
+            ),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roles() -> Vec<AgentRole> {
+        vec![
+            AgentRole::new("planner", "Break the task down"),
+            AgentRole::new("coder", "Write the code").with_tools(&["sandbox_execute"]),
+            AgentRole::new("reviewer", "Review the code"),
+        ]
+    }
+
+    /// Records the tools that actually ran
+    #[derive(Default)]
+    struct RecordingTools {
+        calls: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl AgentToolExecutor for RecordingTools {
+        async fn call_tool(&self, tool: &str, _input: serde_json::Value) -> Result<serde_json::Value> {
+            self.calls.lock().unwrap().push(tool.to_string());
+            Ok(serde_json::Value::Null)
+        }
+    }
+
+    /// Reviewer approves on its second turn; coder runs code each turn
+    struct ApprovingHandler;
+
+    #[async_trait]
+    impl AgentTurnHandler for ApprovingHandler {
+        async fn take_turn(
+            &self,
+            role: &AgentRole,
+            _task: &str,
+            scratchpad: &Scratchpad,
+            tools: &RoleTools<'_>,
+        ) -> Result<AgentTurnOutput> {
+            let previous = scratchpad.entries_by(&role.name).len();
+            Ok(match role.name.as_str() {
+                "coder" => {
+                    tools.call("sandbox_execute", serde_json::json!({ "code": "1 + 1" })).await?;
+                    AgentTurnOutput {
+                        content: "code".to_string(),
+                        ..Default::default()
+                    }
+                }
+                "reviewer" if previous >= 1 => AgentTurnOutput {
+                    content: "APPROVED".to_string(),
+                    ..Default::default()
+                },
+                _ => AgentTurnOutput {
+                    content: format!("{} note", role.name),
+                    ..Default::default()
+                },
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_until_keyword() {
+        let orchestration = MultiAgentOrchestration::new(
+            roles(),
+            TerminationCondition::Keyword {
+                keyword: "APPROVED".to_string(),
+                role: Some("reviewer".to_string()),
+            },
+        );
+        let pad = Scratchpad::new();
+        let tools = RecordingTools::default();
+
+        let outcome = orchestration
+            .run("task", Arc::new(ApprovingHandler), &tools, None, &pad)
+            .await
+            .unwrap();
+        assert_eq!(outcome.reason, TerminationReason::ConditionMet);
+        let order: Vec<_> = outcome.turns.iter().map(|t| t.role.as_str()).collect();
+        assert_eq!(order, vec!["planner", "coder", "reviewer", "planner", "coder", "reviewer"]);
+        assert_eq!(pad.len(), 6);
+        assert_eq!(outcome.turns[1].tool_calls, vec!["sandbox_execute"]);
+        assert_eq!(tools.calls.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sequence_policy_and_turn_limit() {
+        let orchestration = MultiAgentOrchestration::new(roles(), TerminationCondition::AgentSignal)
+            .with_turn_policy(TurnPolicy::Sequence {
+                order: vec!["coder".to_string(), "reviewer".to_string()],
+            })
+            .with_max_turns(4);
+
+        let outcome = orchestration
+            .run("task", Arc::new(SimulatedAgentHandler), &NoAgentTools, None, &Scratchpad::new())
+            .await
+            .unwrap();
+        assert_eq!(outcome.reason, TerminationReason::TurnLimit);
+        let order: Vec<_> = outcome.turns.iter().map(|t| t.role.as_str()).collect();
+        assert_eq!(order, vec!["coder", "reviewer", "coder", "reviewer"]);
+    }
+
+    #[tokio::test]
+    async fn test_disallowed_tool_is_refused_before_it_runs() {
+        /// Ignores the refusal and carries on
+        struct RogueHandler;
+
+        #[async_trait]
+        impl AgentTurnHandler for RogueHandler {
+            async fn take_turn(
+                &self,
+                _: &AgentRole,
+                _: &str,
+                _: &Scratchpad,
+                tools: &RoleTools<'_>,
+            ) -> Result<AgentTurnOutput> {
+                let _ = tools.call("workflow_trigger", serde_json::json!({})).await;
+                Ok(AgentTurnOutput {
+                    content: "deploying".to_string(),
+                    ..Default::default()
+                })
+            }
+        }
+
+        let orchestration =
+            MultiAgentOrchestration::new(roles(), TerminationCondition::MaxTurns { turns: 3 });
+        let tools = RecordingTools::default();
+        let result = orchestration
+            .run("task", Arc::new(RogueHandler), &tools, None, &Scratchpad::new())
+            .await;
+        assert!(matches!(result, Err(WorkflowError::StepExecutionFailed { .. })));
+        assert!(tools.calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_roles_take_their_persona() {
+        struct Personas;
+
+        #[async_trait]
+        impl PersonaLookup for Personas {
+            async fn persona(&self, persona_id: &str) -> Option<RolePersona> {
+                (persona_id == "sre").then(|| RolePersona {
+                    system_prompt: "You are an SRE.".to_string(),
+                    allowed_tools: vec!["query_metrics".to_string(), "sandbox_execute".to_string()],
+                })
+            }
+        }
+
+        let orchestration = MultiAgentOrchestration::new(
+            vec![
+                AgentRole::new("investigator", "Find the cause").with_persona("sre"),
+                AgentRole::new("fixer", "")
+                    .with_persona("sre")
+                    .with_tools(&["sandbox_execute", "workflow_trigger"]),
+            ],
+            TerminationCondition::AgentSignal,
+        );
+
+        let roles = orchestration.resolve_roles(Some(&Personas)).await.unwrap();
+        assert_eq!(roles[0].system_prompt, "You are an SRE.\n\nFind the cause");
+        assert_eq!(roles[0].allowed_tools, vec!["query_metrics", "sandbox_execute"]);
+        assert_eq!(roles[1].system_prompt, "You are an SRE.");
+        assert_eq!(roles[1].allowed_tools, vec!["sandbox_execute"]);
+
+        assert!(orchestration.resolve_roles(None).await.is_err());
+        let unknown = MultiAgentOrchestration::new(
+            vec![AgentRole::new("planner", "Plan").with_persona("missing")],
+            TerminationCondition::AgentSignal,
+        );
+        assert!(unknown.resolve_roles(Some(&Personas)).await.is_err());
+    }
+
+    #[test]
+    fn test_validation() {
+        let mut duplicate = roles();
+        duplicate.push(AgentRole::new("coder", "again"));
+        assert!(MultiAgentOrchestration::new(duplicate, TerminationCondition::AgentSignal)
+            .validate()
+            .is_err());
+
+        let bad_sequence = MultiAgentOrchestration::new(roles(), TerminationCondition::AgentSignal)
+            .with_turn_policy(TurnPolicy::Sequence {
+                order: vec!["tester".to_string()],
+            });
+        assert!(bad_sequence.validate().is_err());
+    }
+}
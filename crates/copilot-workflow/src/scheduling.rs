@@ -1,12 +1,22 @@
 //! Workflow scheduling
 //!
 //! Provides cron-based and time-based workflow scheduling.
-
-use crate::{engine::{WorkflowEngine, WorkflowDefinition}, Result, WorkflowError};
+//!
+//! Each schedule has a [`MissedRunPolicy`] deciding what happens to fire
+//! times that passed while the scheduler was down or not the leader: they
+//! are skipped, collapsed into a single run, or caught up one by one up to
+//! a cap.
+
+use crate::{
+    engine::{WorkflowEngine, WorkflowDefinition},
+    leadership::LeaderElector,
+    Result, WorkflowError,
+};
 use async_trait::async_trait;
+use copilot_core::{Clock, Determinism, SystemClock};
 use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
 use tokio::time::{interval_at, Instant};
@@ -48,18 +58,38 @@ pub enum Schedule {
         /// Time to run (in UTC)
         time: NaiveTime,
     },
-    /// Cron expression (simplified)
+    /// Cron expression
     Cron {
-        /// Cron expression string
+        /// Five fields: minute, hour, day of month, month and day of week
+        /// (0-7, Sunday being 0 or 7). Fields take `*`, values, ranges,
+        /// lists and steps, e.g. `*/15 9-17 * * 1-5`.
         expression: String,
     },
 }
 
+/// What happens to fire times that passed while the scheduler was not
+/// running
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum MissedRunPolicy {
+    /// Drop missed fire times and wait for the next one
+    Skip,
+    /// Run once for all missed fire times
+    #[default]
+    RunOnce,
+    /// Run for each missed fire time, oldest first, keeping only the latest
+    /// `max_runs`
+    CatchUp { max_runs: u32 },
+}
+
 impl Schedule {
     /// Calculate next execution time
     pub fn next_execution(&self) -> Option<DateTime<Utc>> {
-        let now = Utc::now();
+        self.next_execution_after(Utc::now())
+    }
 
+    /// Calculate the next execution time as of `now`
+    pub fn next_execution_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
         match self {
             Schedule::Once { at } => {
                 if *at > now {
@@ -144,10 +174,51 @@ impl Schedule {
                     Some(date.and_time(*time).and_utc())
                 })
             }
-            Schedule::Cron { expression } => {
-                // Simplified cron parsing - just support basic patterns
-                parse_simple_cron(expression, now)
+            Schedule::Cron { expression } => CronSpec::parse(expression).ok()?.next_after(now),
+        }
+    }
+
+    /// Fire time strictly after `time`
+    ///
+    /// Unlike [`next_execution_after`](Self::next_execution_after), an
+    /// interval schedule starting immediately does not fire at `time` itself.
+    pub fn fire_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Interval { interval_seconds, .. } => {
+                Some(time + Duration::seconds((*interval_seconds).max(1) as i64))
+            }
+            _ => self.next_execution_after(time),
+        }
+    }
+
+    /// The next `count` fire times as of `now`
+    pub fn preview(&self, now: DateTime<Utc>, count: usize) -> Vec<DateTime<Utc>> {
+        let mut times = Vec::with_capacity(count);
+        let mut next = self.next_execution_after(now);
+        while let Some(time) = next {
+            if times.len() == count {
+                break;
+            }
+            times.push(time);
+            next = self.fire_after(time);
+        }
+        times
+    }
+
+    /// Check the schedule can fire, e.g. that a cron expression parses
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| Err(WorkflowError::InvalidDefinition(reason.to_string()));
+        match self {
+            Schedule::Interval { interval_seconds: 0, .. } => invalid("Interval must be at least one second"),
+            Schedule::Daily { times, .. } if times.is_empty() => invalid("Daily schedule has no times"),
+            Schedule::Weekly { days, .. } if days.is_empty() => invalid("Weekly schedule has no days"),
+            Schedule::Monthly { days, .. } if days.is_empty() || days.iter().any(|d| !(1..=31).contains(d)) => {
+                invalid("Monthly schedule needs days between 1 and 31")
             }
+            Schedule::Cron { expression } => CronSpec::parse(expression)
+                .map(|_| ())
+                .map_err(|e| WorkflowError::InvalidDefinition(format!("Invalid cron expression `{}`: {}", expression, e))),
+            _ => Ok(()),
         }
     }
 
@@ -173,28 +244,117 @@ fn days_until_weekday(current: Weekday, target: Weekday) -> u32 {
     }
 }
 
-/// Simple cron expression parser
-fn parse_simple_cron(expression: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
-    // Very simplified: only support "minute hour * * *" format
-    let parts: Vec<&str> = expression.split_whitespace().collect();
-    if parts.len() < 2 {
-        return None;
-    }
+/// Parsed five-field cron expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CronSpec {
+    minutes: BTreeSet<u32>,
+    hours: BTreeSet<u32>,
+    days_of_month: BTreeSet<u32>,
+    months: BTreeSet<u32>,
+    days_of_week: BTreeSet<u32>,
+    /// Whether the day fields were restricted; when both are, a day matching
+    /// either fires
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+/// How far ahead to look for a matching day, so impossible dates such as
+/// `0 0 31 2 *` end the search
+const CRON_SEARCH_DAYS: i64 = 366 * 5;
+
+impl CronSpec {
+    fn parse(expression: &str) -> std::result::Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields.as_slice() else {
+            return Err(format!("expected 5 fields, got {}", fields.len()));
+        };
 
-    let minute: u32 = parts[0].parse().ok()?;
-    let hour: u32 = parts[1].parse().ok()?;
+        let mut days_of_week = parse_cron_field(dow, 0, 7, "day of week")?;
+        if days_of_week.remove(&7) {
+            days_of_week.insert(0);
+        }
 
-    let today = now.date_naive();
-    if let Some(time) = NaiveTime::from_hms_opt(hour, minute, 0) {
-        let candidate = today.and_time(time).and_utc();
-        if candidate > now {
-            return Some(candidate);
+        Ok(Self {
+            minutes: parse_cron_field(minute, 0, 59, "minute")?,
+            hours: parse_cron_field(hour, 0, 23, "hour")?,
+            days_of_month: parse_cron_field(dom, 1, 31, "day of month")?,
+            months: parse_cron_field(month, 1, 12, "month")?,
+            days_of_week,
+            dom_restricted: *dom != "*",
+            dow_restricted: *dow != "*",
+        })
+    }
+
+    fn matches_day(&self, date: chrono::NaiveDate) -> bool {
+        let dom = self.days_of_month.contains(&date.day());
+        let dow = self.days_of_week.contains(&date.weekday().num_days_from_sunday());
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
         }
-        // Tomorrow
-        return Some((today + Duration::days(1)).and_time(time).and_utc());
     }
 
-    None
+    fn next_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = now.date_naive();
+        (0..CRON_SEARCH_DAYS)
+            .map(|offset| today + Duration::days(offset))
+            .filter(|date| self.months.contains(&date.month()) && self.matches_day(*date))
+            .find_map(|date| {
+                self.hours.iter().find_map(|&hour| {
+                    self.minutes.iter().find_map(|&minute| {
+                        let candidate = date.and_time(NaiveTime::from_hms_opt(hour, minute, 0)?).and_utc();
+                        (candidate > now).then_some(candidate)
+                    })
+                })
+            })
+    }
+}
+
+/// Values of one cron field between `min` and `max`
+fn parse_cron_field(field: &str, min: u32, max: u32, name: &str) -> std::result::Result<BTreeSet<u32>, String> {
+    let number = |value: &str| -> std::result::Result<u32, String> {
+        let n: u32 = value
+            .parse()
+            .map_err(|_| format!("{} `{}` is not a number", name, value))?;
+        if n < min || n > max {
+            return Err(format!("{} {} is outside {}-{}", name, n, min, max));
+        }
+        Ok(n)
+    };
+
+    let mut values = BTreeSet::new();
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("{} step `{}` must be a positive number", name, step))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (number(start)?, number(end)?),
+                // `5/10` runs from 5 to the end of the range
+                None if step > 1 => (number(range)?, max),
+                None => {
+                    let n = number(range)?;
+                    (n, n)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("{} range {}-{} is reversed", name, start, end));
+        }
+        values.extend((start..=end).step_by(step as usize));
+    }
+    Ok(values)
 }
 
 /// Scheduled workflow configuration
@@ -212,8 +372,9 @@ pub struct ScheduledWorkflow {
     pub input: serde_json::Value,
     /// Maximum concurrent executions
     pub max_concurrent: u32,
-    /// Catch up missed executions
-    pub catch_up: bool,
+    /// What happens to fire times missed while the scheduler was down
+    #[serde(default)]
+    pub missed_runs: MissedRunPolicy,
     /// Timezone for display
     pub timezone: String,
     /// Created at
@@ -222,6 +383,9 @@ pub struct ScheduledWorkflow {
     pub last_execution: Option<DateTime<Utc>>,
     /// Next execution time
     pub next_execution: Option<DateTime<Utc>>,
+    /// When the schedule was paused, if it is
+    #[serde(default)]
+    pub paused_at: Option<DateTime<Utc>>,
     /// Tenant ID
     pub tenant_id: Option<String>,
     /// Tags
@@ -230,23 +394,27 @@ pub struct ScheduledWorkflow {
 
 impl ScheduledWorkflow {
     pub fn new(workflow_id: &str, schedule: Schedule) -> Self {
-        let next_execution = schedule.next_execution();
+        Self::new_with(workflow_id, schedule, &Determinism::system())
+    }
+
+    /// Create a schedule whose ID and timestamps come from `determinism`
+    pub fn new_with(workflow_id: &str, schedule: Schedule, determinism: &Determinism) -> Self {
+        let now = determinism.now();
+        let next_execution = schedule.next_execution_after(now);
 
         Self {
-            id: format!(
-                "sched_{}",
-                uuid::Uuid::new_v4().to_string().replace('-', "")
-            ),
+            id: format!("sched_{}", determinism.next_uuid().simple()),
             workflow_id: workflow_id.to_string(),
             schedule,
             enabled: true,
             input: serde_json::Value::Null,
             max_concurrent: 1,
-            catch_up: false,
+            missed_runs: MissedRunPolicy::default(),
             timezone: "UTC".to_string(),
-            created_at: Utc::now(),
+            created_at: now,
             last_execution: None,
             next_execution,
+            paused_at: None,
             tenant_id: None,
             tags: Vec::new(),
         }
@@ -267,9 +435,49 @@ impl ScheduledWorkflow {
         self
     }
 
+    pub fn with_missed_runs(mut self, policy: MissedRunPolicy) -> Self {
+        self.missed_runs = policy;
+        self
+    }
+
+    /// Fire times to run as of `now`, applying the missed run policy
+    ///
+    /// Fire times older than `grace` count as missed; the latest fire time
+    /// within it always runs.
+    pub fn due_runs(&self, now: DateTime<Utc>, grace: Duration) -> Vec<DateTime<Utc>> {
+        let Some(first) = self.next_execution.filter(|first| *first <= now) else {
+            return Vec::new();
+        };
+
+        // Enumerate at most as many fire times as the policy can run
+        let keep = match self.missed_runs {
+            MissedRunPolicy::CatchUp { max_runs } => max_runs.max(1) as usize,
+            _ => 1,
+        };
+        let mut due = std::collections::VecDeque::with_capacity(keep);
+        let mut next = Some(first);
+        while let Some(time) = next.filter(|time| *time <= now) {
+            if due.len() == keep {
+                due.pop_front();
+            }
+            due.push_back(time);
+            next = self.schedule.fire_after(time);
+        }
+
+        match self.missed_runs {
+            MissedRunPolicy::Skip => due.into_iter().filter(|time| now - *time <= grace).collect(),
+            MissedRunPolicy::RunOnce | MissedRunPolicy::CatchUp { .. } => due.into(),
+        }
+    }
+
     /// Update next execution time
     pub fn update_next_execution(&mut self) {
-        self.next_execution = self.schedule.next_execution();
+        self.update_next_execution_at(Utc::now());
+    }
+
+    /// Recalculate the next execution time as of `now`
+    pub fn update_next_execution_at(&mut self, now: DateTime<Utc>) {
+        self.next_execution = self.schedule.next_execution_after(now);
     }
 }
 
@@ -374,6 +582,8 @@ pub struct WorkflowScheduler {
     execution_sender: mpsc::Sender<ScheduledExecution>,
     poll_interval_seconds: u64,
     running: Arc<RwLock<bool>>,
+    leader: Option<Arc<LeaderElector>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl WorkflowScheduler {
@@ -386,16 +596,32 @@ impl WorkflowScheduler {
             execution_sender,
             poll_interval_seconds: 60,
             running: Arc::new(RwLock::new(false)),
+            leader: None,
+            clock: Arc::new(SystemClock),
         }
     }
 
+    /// Decide which schedules are due with `clock` instead of the system
+    /// clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn with_poll_interval(mut self, seconds: u64) -> Self {
         self.poll_interval_seconds = seconds;
         self
     }
 
+    /// Only fire schedules while this replica holds the scheduler lease
+    pub fn with_leader_election(mut self, leader: Arc<LeaderElector>) -> Self {
+        self.leader = Some(leader);
+        self
+    }
+
     /// Create a new schedule
     pub async fn create(&self, schedule: ScheduledWorkflow) -> Result<ScheduledWorkflow> {
+        schedule.schedule.validate()?;
         self.repository.save(&schedule).await?;
 
         info!(
@@ -408,31 +634,55 @@ impl WorkflowScheduler {
         Ok(schedule)
     }
 
-    /// Enable a schedule
-    pub async fn enable(&self, id: &str) -> Result<()> {
-        if let Some(mut schedule) = self.repository.get(id).await? {
-            schedule.enabled = true;
-            schedule.update_next_execution();
-            self.repository.update(&schedule).await?;
+    /// Get a schedule
+    pub async fn get(&self, id: &str) -> Result<ScheduledWorkflow> {
+        self.repository
+            .get(id)
+            .await?
+            .ok_or_else(|| WorkflowError::NotFound(id.to_string()))
+    }
 
-            info!(schedule_id = %id, "Enabled schedule");
-            Ok(())
-        } else {
-            Err(WorkflowError::NotFound(id.to_string()))
-        }
+    /// List all schedules
+    pub async fn list(&self) -> Result<Vec<ScheduledWorkflow>> {
+        let mut schedules = self.repository.list().await?;
+        schedules.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        Ok(schedules)
     }
 
-    /// Disable a schedule
-    pub async fn disable(&self, id: &str) -> Result<()> {
-        if let Some(mut schedule) = self.repository.get(id).await? {
+    /// Stop firing a schedule until it is resumed
+    pub async fn pause(&self, id: &str) -> Result<ScheduledWorkflow> {
+        let mut schedule = self.get(id).await?;
+        if schedule.enabled {
             schedule.enabled = false;
+            schedule.paused_at = Some(self.clock.now());
             self.repository.update(&schedule).await?;
+            info!(schedule_id = %id, "Paused schedule");
+        }
+        Ok(schedule)
+    }
 
-            info!(schedule_id = %id, "Disabled schedule");
-            Ok(())
-        } else {
-            Err(WorkflowError::NotFound(id.to_string()))
+    /// Resume a paused schedule from its next fire time; fire times that
+    /// passed while it was paused are not run
+    pub async fn resume(&self, id: &str) -> Result<ScheduledWorkflow> {
+        let mut schedule = self.get(id).await?;
+        if !schedule.enabled {
+            schedule.enabled = true;
+            schedule.paused_at = None;
+            schedule.update_next_execution_at(self.clock.now());
+            self.repository.update(&schedule).await?;
+            info!(schedule_id = %id, next = ?schedule.next_execution, "Resumed schedule");
         }
+        Ok(schedule)
+    }
+
+    /// Enable a schedule
+    pub async fn enable(&self, id: &str) -> Result<()> {
+        self.resume(id).await.map(|_| ())
+    }
+
+    /// Disable a schedule
+    pub async fn disable(&self, id: &str) -> Result<()> {
+        self.pause(id).await.map(|_| ())
     }
 
     /// Delete a schedule
@@ -470,6 +720,11 @@ impl WorkflowScheduler {
                 break;
             }
 
+            if self.leader.as_ref().is_some_and(|leader| !leader.is_leader()) {
+                debug!("Not the scheduler leader, skipping schedule check");
+                continue;
+            }
+
             if let Err(e) = self.check_and_execute().await {
                 error!(error = %e, "Error checking schedules");
             }
@@ -487,33 +742,49 @@ impl WorkflowScheduler {
 
     /// Check for due schedules and execute them
     async fn check_and_execute(&self) -> Result<()> {
-        let now = Utc::now();
+        let now = self.clock.now();
         let due_schedules = self.repository.list_due(now).await?;
 
         debug!(count = due_schedules.len(), "Checking due schedules");
 
-        for schedule in due_schedules {
-            // Send execution request
-            let execution = ScheduledExecution {
-                schedule_id: schedule.id.clone(),
-                workflow_id: schedule.workflow_id.clone(),
-                input: schedule.input.clone(),
-                scheduled_time: schedule.next_execution.unwrap_or(now),
-            };
+        // Fire times older than one poll were missed rather than late
+        let grace = Duration::seconds(self.poll_interval_seconds as i64);
 
-            if let Err(e) = self.execution_sender.send(execution).await {
-                error!(
+        for schedule in due_schedules {
+            let runs = schedule.due_runs(now, grace);
+            if runs.len() > 1 {
+                info!(
                     schedule_id = %schedule.id,
-                    error = %e,
-                    "Failed to send execution request"
+                    runs = runs.len(),
+                    "Catching up missed scheduled runs"
                 );
-                continue;
+            }
+            let ran = !runs.is_empty();
+
+            for scheduled_time in runs {
+                let execution = ScheduledExecution {
+                    schedule_id: schedule.id.clone(),
+                    workflow_id: schedule.workflow_id.clone(),
+                    input: schedule.input.clone(),
+                    scheduled_time,
+                };
+
+                if let Err(e) = self.execution_sender.send(execution).await {
+                    error!(
+                        schedule_id = %schedule.id,
+                        error = %e,
+                        "Failed to send execution request"
+                    );
+                    continue;
+                }
             }
 
             // Update schedule
             let mut updated = schedule.clone();
-            updated.last_execution = Some(now);
-            updated.update_next_execution();
+            if ran {
+                updated.last_execution = Some(now);
+            }
+            updated.update_next_execution_at(now);
 
             if let Err(e) = self.repository.update(&updated).await {
                 error!(
@@ -523,12 +794,21 @@ impl WorkflowScheduler {
                 );
             }
 
-            info!(
-                schedule_id = %schedule.id,
-                workflow_id = %schedule.workflow_id,
-                next = ?updated.next_execution,
-                "Triggered scheduled workflow"
-            );
+            if ran {
+                info!(
+                    schedule_id = %schedule.id,
+                    workflow_id = %schedule.workflow_id,
+                    next = ?updated.next_execution,
+                    "Triggered scheduled workflow"
+                );
+            } else {
+                info!(
+                    schedule_id = %schedule.id,
+                    missed_since = ?schedule.next_execution,
+                    next = ?updated.next_execution,
+                    "Skipped missed scheduled runs"
+                );
+            }
         }
 
         Ok(())
@@ -695,6 +975,46 @@ mod tests {
         assert!(repo.get(&schedule.id).await.unwrap().is_none());
     }
 
+    #[tokio::test]
+    async fn test_scheduling_with_a_frozen_clock_is_reproducible() {
+        use copilot_core::FrozenClock;
+
+        let run = || async {
+            let clock = Arc::new(FrozenClock::at_epoch());
+            let determinism = Determinism::seeded(42).with_clock(clock.clone());
+            let repo = Arc::new(InMemoryScheduleRepository::new());
+            let (tx, mut rx) = mpsc::channel(10);
+            let scheduler = WorkflowScheduler::new(repo.clone(), tx).with_clock(clock.clone());
+
+            let schedule = ScheduledWorkflow::new_with(
+                "wf-1",
+                Schedule::Interval {
+                    interval_seconds: 60,
+                    start_immediately: false,
+                },
+                &determinism,
+            );
+            let created = scheduler.create(schedule).await.unwrap();
+            assert_eq!(created.next_execution, Some(FrozenClock::EPOCH + Duration::seconds(60)));
+
+            // Nothing is due until the clock reaches the next execution
+            scheduler.check_and_execute().await.unwrap();
+            assert!(rx.try_recv().is_err());
+
+            clock.advance(Duration::seconds(60));
+            scheduler.check_and_execute().await.unwrap();
+            let execution = rx.try_recv().unwrap();
+            assert_eq!(execution.scheduled_time, FrozenClock::EPOCH + Duration::seconds(60));
+
+            let updated = repo.get(&created.id).await.unwrap().unwrap();
+            assert_eq!(updated.last_execution, Some(FrozenClock::EPOCH + Duration::seconds(60)));
+            assert_eq!(updated.next_execution, Some(FrozenClock::EPOCH + Duration::seconds(120)));
+            serde_json::to_string(&updated).unwrap()
+        };
+
+        assert_eq!(run().await, run().await);
+    }
+
     #[tokio::test]
     async fn test_scheduler_create() {
         let repo = Arc::new(InMemoryScheduleRepository::new());
@@ -713,4 +1033,100 @@ mod tests {
         assert!(created.enabled);
         assert!(created.next_execution.is_some());
     }
+
+    #[test]
+    fn test_cron_expressions() {
+        let now = DateTime::parse_from_rfc3339("2024-03-15T10:07:00Z").unwrap().with_timezone(&Utc);
+        let times = |expression: &str, count| {
+            Schedule::Cron { expression: expression.to_string() }
+                .preview(now, count)
+                .iter()
+                .map(|t| t.to_rfc3339())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            times("*/15 10-11 * * *", 3),
+            vec!["2024-03-15T10:15:00+00:00", "2024-03-15T10:30:00+00:00", "2024-03-15T10:45:00+00:00"]
+        );
+        // 2024-03-15 is a Friday; 7 is Sunday like 0
+        assert_eq!(
+            times("0 9 * * 1,7", 2),
+            vec!["2024-03-17T09:00:00+00:00", "2024-03-18T09:00:00+00:00"]
+        );
+        assert_eq!(times("30 6 1 */6 *", 1), vec!["2024-07-01T06:30:00+00:00"]);
+        assert!(times("0 0 30 2 *", 1).is_empty());
+
+        for invalid in ["0 9 * *", "61 * * * *", "* * * * mon", "5-1 * * * *", "*/0 * * * *"] {
+            let schedule = Schedule::Cron { expression: invalid.to_string() };
+            assert!(schedule.validate().is_err(), "{} should be invalid", invalid);
+        }
+    }
+
+    #[test]
+    fn test_missed_run_policies() {
+        let start = DateTime::parse_from_rfc3339("2024-03-15T00:00:00Z").unwrap().with_timezone(&Utc);
+        let hourly = Schedule::Interval {
+            interval_seconds: 3600,
+            start_immediately: true,
+        };
+        let mut schedule = ScheduledWorkflow::new("wf-1", hourly);
+        schedule.next_execution = Some(start);
+
+        // Down for five hours and a half: fire times 00:00 to 05:00 passed
+        let now = start + Duration::minutes(330);
+        let grace = Duration::minutes(1);
+
+        let skip = schedule.clone().with_missed_runs(MissedRunPolicy::Skip);
+        assert!(skip.due_runs(now, grace).is_empty());
+        assert_eq!(skip.due_runs(start + Duration::seconds(30), grace), vec![start]);
+
+        let once = schedule.clone().with_missed_runs(MissedRunPolicy::RunOnce);
+        assert_eq!(once.due_runs(now, grace), vec![start + Duration::hours(5)]);
+
+        let catch_up = schedule.with_missed_runs(MissedRunPolicy::CatchUp { max_runs: 3 });
+        assert_eq!(
+            catch_up.due_runs(now, grace),
+            vec![start + Duration::hours(3), start + Duration::hours(4), start + Duration::hours(5)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume() {
+        use copilot_core::FrozenClock;
+
+        let clock = Arc::new(FrozenClock::at_epoch());
+        let repo = Arc::new(InMemoryScheduleRepository::new());
+        let (tx, mut rx) = mpsc::channel(10);
+        let scheduler = WorkflowScheduler::new(repo, tx).with_clock(clock.clone());
+        let determinism = Determinism::seeded(7).with_clock(clock.clone());
+        let schedule = ScheduledWorkflow::new_with(
+            "wf-1",
+            Schedule::Interval {
+                interval_seconds: 60,
+                start_immediately: false,
+            },
+            &determinism,
+        )
+        .with_missed_runs(MissedRunPolicy::CatchUp { max_runs: 10 });
+        let created = scheduler.create(schedule).await.unwrap();
+
+        let paused = scheduler.pause(&created.id).await.unwrap();
+        assert_eq!(paused.paused_at, Some(FrozenClock::EPOCH));
+
+        // Fire times passing while paused are not caught up on resume
+        clock.advance(Duration::minutes(10));
+        scheduler.check_and_execute().await.unwrap();
+        let resumed = scheduler.resume(&created.id).await.unwrap();
+        assert!(resumed.paused_at.is_none());
+        assert_eq!(resumed.next_execution, Some(FrozenClock::EPOCH + Duration::minutes(11)));
+        scheduler.check_and_execute().await.unwrap();
+        assert!(rx.try_recv().is_err());
+
+        assert!(scheduler.pause("missing").await.is_err());
+        assert!(scheduler
+            .create(ScheduledWorkflow::new("wf-1", Schedule::Cron { expression: "bad".to_string() }))
+            .await
+            .is_err());
+    }
 }
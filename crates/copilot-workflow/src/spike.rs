@@ -0,0 +1,613 @@
+//! Metric spike explanation
+//!
+//! The `explain_spike` step action answers "why did this metric spike?".
+//! It compares the metric and a set of related metrics over the spike
+//! window against the window just before it, clusters the error logs and
+//! picks the slowest traces of the window through the observatory adapter,
+//! retrieves runbooks from the context engine and has an agent write causal
+//! hypotheses citing that evidence by id. Sources that cannot be reached are
+//! listed as gaps rather than failing the step.
+
+use crate::{Result, WorkflowError};
+use chrono::{DateTime, TimeZone, Utc};
+use copilot_adapters::traits::{LogsQuery, MetricsQuery, TracesQuery};
+use copilot_adapters::ObservatoryAdapter;
+use copilot_context::{ContextEngine, ContextFilter};
+use copilot_nlp::{Entity, EntityType, Intent, IntentType, LogClusterer, QueryTranslator};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Instructions for the agent that explains a spike
+pub const SPIKE_EXPLANATION_PROMPT: &str = "You investigate metric spikes. From the evidence \
+below, propose the most likely causes of the spike, most likely first. For each hypothesis, say \
+what happened, cite the evidence ids that support it (e.g. [E2]) and what would confirm or rule \
+it out. Point to the runbook steps that apply. Do not claim causes the evidence does not support.";
+
+/// Metrics compared with the spiking one when none are given
+pub const DEFAULT_RELATED_METRICS: &[&str] = &["request_rate", "error_rate", "latency", "cpu", "memory"];
+
+/// Related metrics count as moving with the spike from this change on
+const RELATED_CHANGE: f64 = 1.5;
+
+/// Evidence items kept per source
+const MAX_ITEMS_PER_SOURCE: usize = 3;
+
+/// The spike to explain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpikeRequest {
+    /// Metric name, e.g. `latency`, or a PromQL expression
+    pub metric: String,
+    pub service: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Metrics checked for moving with the spike
+    pub related_metrics: Vec<String>,
+}
+
+impl SpikeRequest {
+    /// Build a request from step fields, whose times are RFC 3339 strings
+    pub fn parse(
+        metric: &str,
+        service: Option<&str>,
+        start: &str,
+        end: &str,
+        related_metrics: &[String],
+    ) -> Result<Self> {
+        let time = |field: &str, value: &str| {
+            DateTime::parse_from_rfc3339(value.trim())
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(|e| WorkflowError::InvalidDefinition(format!("explain_spike {} {:?}: {}", field, value, e)))
+        };
+        let start = time("start", start)?;
+        let end = time("end", end)?;
+        if end <= start {
+            return Err(WorkflowError::InvalidDefinition(
+                "explain_spike end must be after start".to_string(),
+            ));
+        }
+        if metric.trim().is_empty() {
+            return Err(WorkflowError::InvalidDefinition("explain_spike needs a metric".to_string()));
+        }
+
+        let related_metrics = if related_metrics.is_empty() {
+            DEFAULT_RELATED_METRICS
+                .iter()
+                .filter(|m| **m != metric)
+                .map(|m| m.to_string())
+                .collect()
+        } else {
+            related_metrics.to_vec()
+        };
+        Ok(Self {
+            metric: metric.trim().to_string(),
+            service: service.map(str::trim).filter(|s| !s.is_empty()).map(str::to_string),
+            start,
+            end,
+            related_metrics,
+        })
+    }
+
+    /// The window before the spike, of the same length
+    pub fn baseline_start(&self) -> DateTime<Utc> {
+        self.start - (self.end - self.start)
+    }
+}
+
+/// How a series moved in the spike window compared with the window before
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricShift {
+    /// Mean before the window
+    pub baseline: f64,
+    /// Highest value in the window
+    pub peak: f64,
+    pub peak_at: DateTime<Utc>,
+    /// Peak over baseline; absent when the baseline is zero
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<f64>,
+}
+
+impl MetricShift {
+    /// Compare the samples of a series (Unix time, value) before and from
+    /// `window_start`; `None` without samples on both sides
+    pub fn from_samples(samples: &[(f64, String)], window_start: DateTime<Utc>) -> Option<Self> {
+        let split = window_start.timestamp() as f64;
+        let values = samples
+            .iter()
+            .filter_map(|(t, v)| v.parse::<f64>().ok().filter(|v| v.is_finite()).map(|v| (*t, v)));
+
+        let (mut baseline_sum, mut baseline_count) = (0.0, 0usize);
+        let mut peak: Option<(f64, f64)> = None;
+        for (t, v) in values {
+            if t < split {
+                baseline_sum += v;
+                baseline_count += 1;
+            } else if peak.map_or(true, |(_, p)| v > p) {
+                peak = Some((t, v));
+            }
+        }
+        let (peak_t, peak) = peak?;
+        if baseline_count == 0 {
+            return None;
+        }
+        let baseline = baseline_sum / baseline_count as f64;
+        Some(Self {
+            baseline,
+            peak,
+            peak_at: Utc.timestamp_opt(peak_t as i64, 0).single()?,
+            change: (baseline.abs() > f64::EPSILON).then(|| peak / baseline),
+        })
+    }
+
+    fn describe(&self) -> String {
+        let change = self
+            .change
+            .map(|c| format!("{:.1}x baseline", c))
+            .unwrap_or_else(|| "up from zero".to_string());
+        format!(
+            "peaked at {} at {} ({}, baseline {})",
+            round(self.peak),
+            self.peak_at.format("%H:%M:%S"),
+            change,
+            round(self.baseline)
+        )
+    }
+}
+
+fn round(value: f64) -> String {
+    format!("{}", (value * 1000.0).round() / 1000.0)
+}
+
+/// Where a piece of evidence comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvidenceKind {
+    Metric,
+    Logs,
+    Trace,
+    Runbook,
+}
+
+/// A piece of evidence the hypotheses can cite
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Evidence {
+    /// Citation id, `E1`, `E2`, ...
+    pub id: String,
+    pub kind: EvidenceKind,
+    pub summary: String,
+    /// Query, trace id or runbook source the evidence links to
+    pub source: String,
+}
+
+/// Evidence gathered for a spike and the hypotheses drawn from it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpikeReport {
+    pub request: SpikeRequest,
+    /// How the spiking metric itself moved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shift: Option<MetricShift>,
+    pub evidence: Vec<Evidence>,
+    /// Sources that could not be consulted, and why
+    pub gaps: Vec<String>,
+    /// Causal hypotheses citing the evidence
+    pub hypotheses: String,
+}
+
+impl SpikeReport {
+    fn new(request: SpikeRequest) -> Self {
+        Self {
+            request,
+            shift: None,
+            evidence: Vec::new(),
+            gaps: Vec::new(),
+            hypotheses: String::new(),
+        }
+    }
+
+    fn push(&mut self, kind: EvidenceKind, summary: String, source: String) {
+        let id = format!("E{}", self.evidence.len() + 1);
+        self.evidence.push(Evidence { id, kind, summary, source });
+    }
+
+    /// Plain description of the evidence, for when no hypotheses are available
+    pub fn describe(&self) -> String {
+        let subject = match &self.request.service {
+            Some(service) => format!("{} of {}", self.request.metric, service),
+            None => self.request.metric.clone(),
+        };
+        let mut text = match &self.shift {
+            Some(shift) => format!("{} {}.", subject, shift.describe()),
+            None => format!("No samples of {} around the spike.", subject),
+        };
+        for evidence in &self.evidence {
+            text.push_str(&format!("\n[{}] {}", evidence.id, evidence.summary));
+        }
+        for gap in &self.gaps {
+            text.push_str(&format!("\nNot consulted: {}", gap));
+        }
+        text
+    }
+
+    /// Task handed to the explaining agent
+    pub fn explanation_task(&self) -> String {
+        format!(
+            "Spike window: {} to {}.\n{}",
+            self.request.start.to_rfc3339(),
+            self.request.end.to_rfc3339(),
+            self.describe()
+        )
+    }
+}
+
+/// Gather metric, log, trace and runbook evidence for a spike. The report's
+/// hypotheses are left empty.
+pub async fn gather_evidence(
+    request: SpikeRequest,
+    observatory: Option<&dyn ObservatoryAdapter>,
+    context: Option<&dyn ContextEngine>,
+) -> SpikeReport {
+    let mut report = SpikeReport::new(request);
+    let request = report.request.clone();
+
+    match observatory {
+        Some(observatory) => {
+            gather_metrics(&mut report, &request, observatory).await;
+            gather_logs(&mut report, &request, observatory).await;
+            gather_traces(&mut report, &request, observatory).await;
+        }
+        None => report.gaps.push("metrics, logs and traces: no observatory configured".to_string()),
+    }
+    match context {
+        Some(context) => gather_runbooks(&mut report, &request, context).await,
+        None => report.gaps.push("runbooks: no context engine configured".to_string()),
+    }
+
+    report
+}
+
+async fn gather_metrics(report: &mut SpikeReport, request: &SpikeRequest, observatory: &dyn ObservatoryAdapter) {
+    let translator = QueryTranslator::new();
+    let step = ((request.end - request.start).num_seconds() / 60).max(15);
+    let fetch = |query: String| MetricsQuery {
+        query,
+        start_time: request.baseline_start(),
+        end_time: request.end,
+        step: Some(format!("{}s", step)),
+    };
+
+    let query = metric_query(&translator, &request.metric, request.service.as_deref());
+    match observatory.query_metrics(fetch(query.clone())).await {
+        Ok(response) => {
+            let shift = response
+                .data
+                .result
+                .iter()
+                .filter_map(|series| MetricShift::from_samples(&series.values, request.start))
+                .max_by(|a, b| a.peak.total_cmp(&b.peak));
+            if let Some(shift) = &shift {
+                report.push(
+                    EvidenceKind::Metric,
+                    format!("{} {}", request.metric, shift.describe()),
+                    query,
+                );
+            }
+            report.shift = shift;
+        }
+        Err(e) => report.gaps.push(format!("metric {}: {}", request.metric, e)),
+    }
+
+    // Related metrics that moved the most with the spike
+    let mut moved = Vec::new();
+    for metric in &request.related_metrics {
+        let query = metric_query(&translator, metric, request.service.as_deref());
+        match observatory.query_metrics(fetch(query.clone())).await {
+            Ok(response) => {
+                let series = response.data.result.iter().filter_map(|series| {
+                    let shift = MetricShift::from_samples(&series.values, request.start)?;
+                    Some((label_set(&series.metric), shift))
+                });
+                if let Some((labels, shift)) = series.max_by(|a, b| change(&a.1).total_cmp(&change(&b.1))) {
+                    if change(&shift) >= RELATED_CHANGE {
+                        moved.push((metric, labels, shift, query));
+                    }
+                }
+            }
+            Err(e) => report.gaps.push(format!("metric {}: {}", metric, e)),
+        }
+    }
+    moved.sort_by(|a, b| change(&b.2).total_cmp(&change(&a.2)));
+    for (metric, labels, shift, query) in moved.into_iter().take(MAX_ITEMS_PER_SOURCE) {
+        report.push(
+            EvidenceKind::Metric,
+            format!("{}{} {}", metric, labels, shift.describe()),
+            query,
+        );
+    }
+}
+
+async fn gather_logs(report: &mut SpikeReport, request: &SpikeRequest, observatory: &dyn ObservatoryAdapter) {
+    let query = match &request.service {
+        Some(service) => format!("{{service=\"{}\", level=\"error\"}}", service),
+        None => "{level=\"error\"}".to_string(),
+    };
+    let logs = observatory
+        .query_logs(LogsQuery {
+            query: query.clone(),
+            start_time: request.start,
+            end_time: request.end,
+            limit: Some(1000),
+        })
+        .await;
+    match logs {
+        Ok(logs) => {
+            let mut clusterer = LogClusterer::new();
+            clusterer.add_lines(logs.logs.iter().map(|entry| entry.message.as_str()));
+            let analysis = clusterer.analysis(MAX_ITEMS_PER_SOURCE);
+            for pattern in analysis.patterns {
+                report.push(
+                    EvidenceKind::Logs,
+                    format!(
+                        "{} error lines like \"{}\" (first at line {} of {})",
+                        pattern.count, pattern.template, pattern.first_line, analysis.total_lines
+                    ),
+                    query.clone(),
+                );
+            }
+        }
+        Err(e) => report.gaps.push(format!("logs: {}", e)),
+    }
+}
+
+async fn gather_traces(report: &mut SpikeReport, request: &SpikeRequest, observatory: &dyn ObservatoryAdapter) {
+    let Some(service) = &request.service else {
+        report.gaps.push("traces: no service given".to_string());
+        return;
+    };
+    let traces = observatory
+        .query_traces(TracesQuery {
+            service_name: Some(service.clone()),
+            operation_name: None,
+            start_time: request.start,
+            end_time: request.end,
+            tags: None,
+            limit: Some(20),
+        })
+        .await;
+    match traces {
+        Ok(response) => {
+            let mut traces = response.traces;
+            traces.sort_by_key(|trace| std::cmp::Reverse(trace.duration_ms));
+            for trace in traces.iter().take(MAX_ITEMS_PER_SOURCE) {
+                let mut summary = format!("Trace took {}ms", trace.duration_ms);
+                if let Some(span) = trace.spans.iter().max_by_key(|span| span.duration_ms) {
+                    summary.push_str(&format!(", slowest span {} ({}ms)", span.operation_name, span.duration_ms));
+                }
+                let errors = trace
+                    .spans
+                    .iter()
+                    .filter(|span| span.tags.get("error").is_some_and(|v| v == "true"))
+                    .count();
+                if errors > 0 {
+                    summary.push_str(&format!(", {} failed spans", errors));
+                }
+                report.push(EvidenceKind::Trace, summary, trace.trace_id.clone());
+            }
+        }
+        Err(e) => report.gaps.push(format!("traces: {}", e)),
+    }
+}
+
+async fn gather_runbooks(report: &mut SpikeReport, request: &SpikeRequest, context: &dyn ContextEngine) {
+    let query = format!(
+        "runbook {} spike {}",
+        request.metric,
+        request.service.as_deref().unwrap_or_default()
+    );
+    // Prefer documents tagged as runbooks, then anything relevant
+    let retrieved = match context
+        .retrieve_filtered(&query, &ContextFilter::new().with_tag("runbook"))
+        .await
+    {
+        Ok(result) if !result.selected.is_empty() => Ok(result),
+        _ => context.retrieve(&query).await,
+    };
+    match retrieved {
+        Ok(result) => {
+            for scored in result.selected.iter().take(MAX_ITEMS_PER_SOURCE) {
+                let title = scored
+                    .item
+                    .content
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .unwrap_or_default()
+                    .trim_start_matches('#')
+                    .trim();
+                let title: String = title.chars().take(120).collect();
+                report.push(
+                    EvidenceKind::Runbook,
+                    format!("Runbook: {}", title),
+                    scored.item.metadata.source.clone(),
+                );
+            }
+        }
+        Err(e) => report.gaps.push(format!("runbooks: {}", e)),
+    }
+}
+
+/// PromQL for a metric name the translator knows, or the metric as given
+fn metric_query(translator: &QueryTranslator, metric: &str, service: Option<&str>) -> String {
+    if translator.metric_name(metric).is_none() {
+        return metric.to_string();
+    }
+    let entity = |entity_type, value: &str| {
+        Entity::new(entity_type, value.to_string(), value.to_string(), value.to_string(), 1.0)
+    };
+    let mut entities = vec![entity(EntityType::Metric, metric)];
+    entities.extend(service.map(|service| entity(EntityType::Service, service)));
+    let intent = match metric {
+        "error_rate" => IntentType::ErrorAnalysis,
+        _ => IntentType::QueryMetrics,
+    };
+    translator.to_promql(&Intent::new(intent, 1.0), &entities)
+}
+
+fn change(shift: &MetricShift) -> f64 {
+    shift.change.unwrap_or(f64::MAX)
+}
+
+fn label_set(labels: &HashMap<String, String>) -> String {
+    let mut labels: Vec<String> = labels
+        .iter()
+        .filter(|(name, _)| name.as_str() != "__name__")
+        .map(|(name, value)| format!("{}=\"{}\"", name, value))
+        .collect();
+    if labels.is_empty() {
+        return String::new();
+    }
+    labels.sort();
+    format!("{{{}}}", labels.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_adapters::traits::{
+        DashboardPush, DashboardPushResponse, LogEntry, LogsResponse, MetricResult, MetricsData, MetricsResponse,
+        TracesResponse,
+    };
+    use copilot_adapters::{AdapterError, AdapterResult};
+
+    fn samples(values: &[(i64, f64)]) -> Vec<(f64, String)> {
+        values.iter().map(|(t, v)| (*t as f64, v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_metric_shift() {
+        let start = Utc.timestamp_opt(1_000, 0).unwrap();
+        let shift = MetricShift::from_samples(
+            &samples(&[(900, 1.0), (950, 3.0), (1_000, 4.0), (1_050, 8.0), (1_100, 5.0)]),
+            start,
+        )
+        .unwrap();
+
+        assert_eq!(shift.baseline, 2.0);
+        assert_eq!(shift.peak, 8.0);
+        assert_eq!(shift.peak_at.timestamp(), 1_050);
+        assert_eq!(shift.change, Some(4.0));
+
+        // No baseline to compare with
+        assert!(MetricShift::from_samples(&samples(&[(1_000, 4.0)]), start).is_none());
+        let from_zero = MetricShift::from_samples(&samples(&[(900, 0.0), (1_000, 4.0)]), start).unwrap();
+        assert_eq!(from_zero.change, None);
+    }
+
+    #[test]
+    fn test_request_parsing() {
+        let request = SpikeRequest::parse(
+            "latency",
+            Some(" "),
+            "2024-05-01T10:00:00Z",
+            "2024-05-01T10:30:00Z",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(request.service, None);
+        assert!(!request.related_metrics.contains(&"latency".to_string()));
+        assert_eq!(request.baseline_start().to_rfc3339(), "2024-05-01T09:30:00+00:00");
+
+        assert!(SpikeRequest::parse("latency", None, "2024-05-01T10:30:00Z", "2024-05-01T10:00:00Z", &[]).is_err());
+        assert!(SpikeRequest::parse("latency", None, "yesterday", "2024-05-01T10:00:00Z", &[]).is_err());
+    }
+
+    #[test]
+    fn test_metric_query() {
+        let translator = QueryTranslator::new();
+        assert_eq!(
+            metric_query(&translator, "latency", Some("checkout")),
+            "rate(http_request_duration_seconds{service=\"checkout\"}[5m])"
+        );
+        assert_eq!(metric_query(&translator, "sum(up)", Some("checkout")), "sum(up)");
+    }
+
+    /// Serves a latency spike with error logs; traces are unavailable
+    struct SpikingObservatory;
+
+    #[async_trait::async_trait]
+    impl ObservatoryAdapter for SpikingObservatory {
+        async fn query_metrics(&self, query: MetricsQuery) -> AdapterResult<MetricsResponse> {
+            let start = query.start_time.timestamp();
+            let (before, during) = match query.query.as_str() {
+                q if q.contains("duration") => (1.0, 6.0),
+                q if q.contains("code=~") => (2.0, 20.0),
+                _ => (10.0, 11.0),
+            };
+            let values = (0..4)
+                .map(|i| ((start + i * 900) as f64, if i < 2 { before } else { during }.to_string()))
+                .collect();
+            Ok(MetricsResponse {
+                status: "success".to_string(),
+                data: MetricsData {
+                    result_type: "matrix".to_string(),
+                    result: vec![MetricResult { metric: HashMap::new(), values }],
+                },
+            })
+        }
+
+        async fn query_logs(&self, query: LogsQuery) -> AdapterResult<LogsResponse> {
+            let logs: Vec<LogEntry> = (0..5)
+                .map(|i| LogEntry {
+                    timestamp: query.start_time,
+                    level: "error".to_string(),
+                    message: format!("connection to db-{} timed out", i),
+                    labels: HashMap::new(),
+                })
+                .collect();
+            Ok(LogsResponse { total_count: logs.len(), logs })
+        }
+
+        async fn query_log_metrics(&self, query: MetricsQuery) -> AdapterResult<MetricsResponse> {
+            self.query_metrics(query).await
+        }
+
+        async fn query_traces(&self, _query: TracesQuery) -> AdapterResult<TracesResponse> {
+            Err(AdapterError::ConnectionError("tempo unreachable".to_string()))
+        }
+
+        async fn push_dashboard(&self, _push: DashboardPush) -> AdapterResult<DashboardPushResponse> {
+            Err(AdapterError::RequestFailed("not supported".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gather_evidence() {
+        let request = SpikeRequest::parse(
+            "latency",
+            Some("checkout"),
+            "2024-05-01T10:00:00Z",
+            "2024-05-01T10:30:00Z",
+            &["error_rate".to_string(), "cpu".to_string()],
+        )
+        .unwrap();
+        let report = gather_evidence(request, Some(&SpikingObservatory), None).await;
+
+        assert_eq!(report.shift.as_ref().unwrap().change, Some(6.0));
+        let kinds: Vec<EvidenceKind> = report.evidence.iter().map(|e| e.kind).collect();
+        // The spiking metric, error_rate but not the flat cpu, one log pattern
+        assert_eq!(kinds, vec![EvidenceKind::Metric, EvidenceKind::Metric, EvidenceKind::Logs]);
+        assert!(report.evidence[1].summary.starts_with("error_rate"));
+        assert!(report.evidence[2].summary.contains("connection to <*> timed out"));
+        assert_eq!(report.evidence[2].id, "E3");
+        assert!(report.gaps.iter().any(|gap| gap.contains("tempo unreachable")));
+        assert!(report.gaps.iter().any(|gap| gap.starts_with("runbooks")));
+    }
+
+    #[tokio::test]
+    async fn test_gaps_without_sources() {
+        let request = SpikeRequest::parse("latency", None, "2024-05-01T10:00:00Z", "2024-05-01T10:30:00Z", &[]).unwrap();
+        let report = gather_evidence(request, None, None).await;
+
+        assert!(report.evidence.is_empty());
+        assert_eq!(report.gaps.len(), 2);
+        assert!(report.describe().starts_with("No samples of latency"));
+    }
+}
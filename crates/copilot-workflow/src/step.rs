@@ -1,5 +1,7 @@
 //! Workflow step definitions and state management
 
+use crate::orchestration::MultiAgentOrchestration;
+use crate::terraform::PlanRisk;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -18,6 +20,8 @@ pub enum StepType {
     Parallel,
     /// Wait/delay step
     Wait,
+    /// Several agent roles collaborating on a task
+    MultiAgent,
 }
 
 /// State of a workflow step
@@ -89,6 +93,81 @@ pub enum StepAction {
         #[serde(default)]
         parameters: HashMap<String, serde_json::Value>,
     },
+    /// Multi-agent collaboration on a shared scratchpad
+    MultiAgent {
+        task: String,
+        #[serde(flatten)]
+        orchestration: MultiAgentOrchestration,
+    },
+    /// Review a Terraform/OpenTofu plan before it is applied
+    TerraformPlan {
+        /// Plan JSON (`terraform show -json`)
+        #[serde(default)]
+        plan: Option<serde_json::Value>,
+        /// Step whose `plan` or `stdout` output holds the plan JSON
+        #[serde(default)]
+        plan_step: Option<String>,
+        /// Lowest risk that needs a human approval; plans without changes
+        /// never do
+        #[serde(default)]
+        approval_risk: PlanRisk,
+        /// How long to wait for the approval
+        #[serde(default = "default_approval_timeout_secs")]
+        approval_timeout_secs: u64,
+    },
+    /// Cluster log lines into patterns and summarize them
+    LogAnalysis {
+        /// Log text
+        #[serde(default)]
+        logs: Option<String>,
+        /// Step whose `logs` or `stdout` output holds the log text
+        #[serde(default)]
+        logs_step: Option<String>,
+        /// How many of the most frequent patterns to report
+        #[serde(default = "default_top_patterns")]
+        top_patterns: usize,
+    },
+    /// Explain a metric spike from related metrics, logs, traces and runbooks
+    ExplainSpike {
+        /// Metric name, e.g. `latency`, or a PromQL expression
+        metric: String,
+        #[serde(default)]
+        service: Option<String>,
+        /// Spike window start, RFC 3339
+        start: String,
+        /// Spike window end, RFC 3339
+        end: String,
+        /// Metrics checked for moving with the spike; a default set when empty
+        #[serde(default)]
+        related_metrics: Vec<String>,
+    },
+}
+
+fn default_approval_timeout_secs() -> u64 {
+    3600
+}
+
+fn default_top_patterns() -> usize {
+    10
+}
+
+/// Why a step was stopped before it finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum CancellationReason {
+    /// The step exceeded its `timeout_secs`
+    Timeout { after_secs: u64 },
+    /// The workflow was cancelled while the step ran
+    WorkflowCancelled,
+}
+
+impl std::fmt::Display for CancellationReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Timeout { after_secs } => write!(f, "Step timed out after {} seconds", after_secs),
+            Self::WorkflowCancelled => write!(f, "Workflow was cancelled"),
+        }
+    }
 }
 
 /// Result of step execution
@@ -111,6 +190,9 @@ pub struct StepResult {
     /// Number of retry attempts
     #[serde(default)]
     pub retry_count: u32,
+    /// Why the step was cancelled, if it was
+    #[serde(default)]
+    pub cancellation: Option<CancellationReason>,
 }
 
 impl StepResult {
@@ -124,6 +206,7 @@ impl StepResult {
             started_at: chrono::Utc::now(),
             completed_at: None,
             retry_count: 0,
+            cancellation: None,
         }
     }
 
@@ -143,6 +226,12 @@ impl StepResult {
         self
     }
 
+    /// Mark as failed because the step was cancelled
+    pub fn cancel(mut self, reason: CancellationReason) -> Self {
+        self.cancellation = Some(reason);
+        self.fail(reason.to_string())
+    }
+
     /// Mark as skipped
     pub fn skip(mut self) -> Self {
         self.state = StepState::Skipped;
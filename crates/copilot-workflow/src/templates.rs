@@ -4,6 +4,8 @@
 
 use crate::{
     engine::WorkflowDefinition,
+    expr::{self, Bindings, TypeEnv, ValueType},
+    expressions::{self, ExpressionError},
     step::{StepType, StepAction, WorkflowStep},
     Result, WorkflowError,
 };
@@ -34,6 +36,30 @@ pub struct TemplateParameter {
     pub validation: Option<ParameterValidation>,
 }
 
+/// A parameter that failed validation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParameterError {
+    /// Name of the offending parameter
+    pub field: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+impl ParameterError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
 /// Parameter types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -69,6 +95,17 @@ pub struct ParameterValidation {
     pub pattern: Option<String>,
 }
 
+/// Documentation shown next to a template
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateDocs {
+    /// When to use the template
+    pub when_to_use: String,
+    /// What each step does, in order
+    pub steps: Vec<String>,
+    /// What must be set up before a run, e.g. step handlers or approver groups
+    pub prerequisites: Vec<String>,
+}
+
 /// Workflow template
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowTemplate {
@@ -86,6 +123,9 @@ pub struct WorkflowTemplate {
     pub icon: Option<String>,
     /// Template parameters
     pub parameters: Vec<TemplateParameter>,
+    /// Usage documentation
+    #[serde(default)]
+    pub docs: Option<TemplateDocs>,
     /// Template definition (with placeholders)
     pub definition: WorkflowDefinition,
     /// Version
@@ -119,6 +159,7 @@ impl WorkflowTemplate {
             tags: Vec::new(),
             icon: None,
             parameters: Vec::new(),
+            docs: None,
             definition,
             version: "1.0.0".to_string(),
             author: None,
@@ -150,6 +191,11 @@ impl WorkflowTemplate {
         self
     }
 
+    pub fn with_docs(mut self, docs: TemplateDocs) -> Self {
+        self.docs = Some(docs);
+        self
+    }
+
     pub fn with_author(mut self, author: &str) -> Self {
         self.author = Some(author.to_string());
         self
@@ -167,24 +213,53 @@ impl WorkflowTemplate {
 
     /// Validate parameters
     pub fn validate_params(&self, params: &serde_json::Value) -> Result<()> {
+        match self.check_params(params).into_iter().next() {
+            Some(error) => Err(WorkflowError::InvalidDefinition(error.message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Check parameters, reporting every invalid one rather than the first
+    pub fn check_params(&self, params: &serde_json::Value) -> Vec<ParameterError> {
+        let mut errors = Vec::new();
         for param_def in &self.parameters {
             let value = params.get(&param_def.name);
 
             // Check required
             if param_def.required && value.is_none() {
-                return Err(WorkflowError::InvalidDefinition(format!(
-                    "Missing required parameter: {}",
-                    param_def.name
-                )));
+                errors.push(ParameterError::new(
+                    &param_def.name,
+                    format!("Missing required parameter: {}", param_def.name),
+                ));
+                continue;
             }
 
             // Validate if value present
             if let Some(value) = value {
-                self.validate_param_value(param_def, value)?;
+                if let Err(e) = self.validate_param_value(param_def, value) {
+                    let message = match e {
+                        WorkflowError::InvalidDefinition(message) => message,
+                        other => other.to_string(),
+                    };
+                    errors.push(ParameterError::new(&param_def.name, message));
+                }
             }
         }
 
-        Ok(())
+        errors
+    }
+
+    /// Parameters with defaults filled in for the ones not given
+    pub fn resolve_params(&self, params: &serde_json::Value) -> serde_json::Value {
+        let mut resolved = params.as_object().cloned().unwrap_or_default();
+        for param_def in &self.parameters {
+            if let Some(default) = &param_def.default_value {
+                resolved
+                    .entry(param_def.name.clone())
+                    .or_insert_with(|| default.clone());
+            }
+        }
+        serde_json::Value::Object(resolved)
     }
 
     /// Validate a single parameter value
@@ -225,7 +300,7 @@ impl WorkflowTemplate {
                     }
 
                     if let Some(ref pattern) = validation.pattern {
-                        let re = regex::Regex::new(pattern).map_err(|_| {
+                        let re = expr::compile_regex(pattern).map_err(|_| {
                             WorkflowError::InvalidDefinition("Invalid regex pattern".to_string())
                         })?;
                         if !re.is_match(s) {
@@ -311,6 +386,31 @@ impl WorkflowTemplate {
         Ok(())
     }
 
+    /// Types of the parameters, as `{{ }}` placeholders see them
+    pub fn parameter_types(&self) -> TypeEnv {
+        self.parameters.iter().fold(TypeEnv::new(), |types, param_def| {
+            let value_type = match param_def.param_type {
+                ParameterType::String | ParameterType::Secret | ParameterType::Select { .. } => ValueType::String,
+                ParameterType::Number => ValueType::Number,
+                ParameterType::Boolean => ValueType::Bool,
+                ParameterType::Array => ValueType::List,
+                ParameterType::Object => ValueType::Map,
+            };
+            types.with(&param_def.name, value_type)
+        })
+    }
+
+    /// Check every `{{ }}` placeholder of the definition parses and only
+    /// uses the template's parameters, in ways their types allow
+    pub fn check_placeholders(&self) -> Result<()> {
+        let types = self.parameter_types();
+        let mut definition = serde_json::to_value(&self.definition).map_err(WorkflowError::Serialization)?;
+        map_strings(&mut definition, &mut |text| {
+            expressions::check_template(text, &types).map(|_| text.to_string())
+        })
+        .map_err(|e| WorkflowError::InvalidDefinition(format!("Template {}: {}", self.id, e)))
+    }
+
     /// Instantiate the template with parameters
     pub fn instantiate(&self, params: &serde_json::Value) -> Result<WorkflowDefinition> {
         // Validate parameters
@@ -325,36 +425,24 @@ impl WorkflowTemplate {
             uuid::Uuid::new_v4().to_string().replace('-', "")
         );
 
-        // Replace placeholders in the definition
-        let definition_json = serde_json::to_string(&definition)
-            .map_err(|e| WorkflowError::Serialization(e))?;
-
-        let mut result = definition_json;
-
-        // Replace parameter placeholders
-        for param_def in &self.parameters {
-            let placeholder = format!("{{{{ {} }}}}", param_def.name);
-            let alt_placeholder = format!("{{{{{}}}}} ", param_def.name);
-
+        // Render the `{{ }}` placeholders with the parameters
+        let types = self.parameter_types();
+        let bindings = self.parameters.iter().fold(Bindings::new(), |bindings, param_def| {
             let value = params
                 .get(&param_def.name)
                 .or(param_def.default_value.as_ref())
-                .map(|v| {
-                    if v.is_string() {
-                        v.as_str().unwrap().to_string()
-                    } else {
-                        v.to_string()
-                    }
-                })
-                .unwrap_or_default();
-
-            result = result.replace(&placeholder, &value);
-            result = result.replace(&alt_placeholder, &value);
-        }
+                .cloned()
+                .unwrap_or(serde_json::Value::Null);
+            bindings.with(&param_def.name, value)
+        });
+        let mut definition_json = serde_json::to_value(&definition).map_err(WorkflowError::Serialization)?;
+        map_strings(&mut definition_json, &mut |text| {
+            expressions::render_template(text, &bindings, &types)
+        })
+        .map_err(|e| WorkflowError::InvalidDefinition(format!("Template {}: {}", self.id, e)))?;
 
-        // Parse back to definition
-        let instantiated: WorkflowDefinition = serde_json::from_str(&result)
-            .map_err(|e| WorkflowError::Serialization(e))?;
+        let instantiated: WorkflowDefinition =
+            serde_json::from_value(definition_json).map_err(WorkflowError::Serialization)?;
 
         debug!(
             template_id = %self.id,
@@ -366,6 +454,30 @@ impl WorkflowTemplate {
     }
 }
 
+/// Replace every string in `value`, object keys included, with `f` of it
+fn map_strings(
+    value: &mut serde_json::Value,
+    f: &mut impl FnMut(&str) -> std::result::Result<String, ExpressionError>,
+) -> std::result::Result<(), ExpressionError> {
+    match value {
+        serde_json::Value::String(text) => *text = f(text)?,
+        serde_json::Value::Array(items) => {
+            for item in items {
+                map_strings(item, f)?;
+            }
+        }
+        serde_json::Value::Object(map) => {
+            let entries = std::mem::take(map);
+            for (key, mut item) in entries {
+                map_strings(&mut item, f)?;
+                map.insert(f(&key)?, item);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 /// Template repository trait
 #[async_trait]
 pub trait TemplateRepository: Send + Sync {
@@ -392,6 +504,30 @@ impl InMemoryTemplateRepository {
             templates: RwLock::new(HashMap::new()),
         }
     }
+
+    /// Create a repository holding the [`builtin_templates`]
+    pub fn with_builtins() -> Self {
+        Self {
+            templates: RwLock::new(
+                builtin_templates()
+                    .into_iter()
+                    .map(|template| (template.id.clone(), template))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Templates shipped with the engine, under stable ids
+pub fn builtin_templates() -> Vec<WorkflowTemplate> {
+    vec![
+        TemplateBuilders::explain_spike(),
+        TemplateBuilders::deploy_with_approval(),
+        TemplateBuilders::rollback(),
+        TemplateBuilders::database_migration_gate(),
+        TemplateBuilders::incident_triage(),
+        TemplateBuilders::dependency_update(),
+    ]
 }
 
 impl Default for InMemoryTemplateRepository {
@@ -499,6 +635,7 @@ impl TemplateLibrary {
 
     /// Create a new template
     pub async fn create(&self, template: WorkflowTemplate) -> Result<WorkflowTemplate> {
+        template.check_placeholders()?;
         self.repository.save(&template).await?;
 
         info!(
@@ -575,6 +712,23 @@ impl TemplateLibrary {
         Ok(definition)
     }
 
+    /// Save the [`builtin_templates`], replacing earlier versions of them
+    /// but keeping their usage counts
+    pub async fn install_builtin(&self) -> Result<Vec<WorkflowTemplate>> {
+        let mut installed = Vec::new();
+        for mut template in builtin_templates() {
+            if let Some(existing) = self.repository.get(&template.id).await? {
+                template.usage_count = existing.usage_count;
+                template.created_at = existing.created_at;
+            }
+            self.repository.save(&template).await?;
+            installed.push(template);
+        }
+
+        info!(count = installed.len(), "Installed built-in workflow templates");
+        Ok(installed)
+    }
+
     /// Delete a template
     pub async fn delete(&self, id: &str) -> Result<()> {
         self.repository.delete(id).await?;
@@ -618,6 +772,7 @@ impl TemplateBuilders {
             steps: workflow_steps,
             metadata: HashMap::new(),
             timeout_secs: None,
+            concurrency: None,
         };
 
         let mut template = WorkflowTemplate::new(name, description, definition)
@@ -711,6 +866,7 @@ impl TemplateBuilders {
             steps,
             metadata: HashMap::new(),
             timeout_secs: None,
+            concurrency: None,
         };
 
         WorkflowTemplate::new(name, description, definition)
@@ -733,6 +889,476 @@ impl TemplateBuilders {
     }
 }
 
+impl TemplateBuilders {
+    /// Id of the [`TemplateBuilders::explain_spike`] template
+    pub const EXPLAIN_SPIKE_ID: &'static str = "explain-spike";
+
+    /// Create the "explain this spike" template: gathers related metrics,
+    /// error logs, slow traces and runbooks for a metric spike and has an
+    /// agent propose causes citing them
+    pub fn explain_spike() -> WorkflowTemplate {
+        let string_param = |name: &str, label: &str, description: &str, required: bool| TemplateParameter {
+            name: name.to_string(),
+            label: label.to_string(),
+            description: Some(description.to_string()),
+            param_type: ParameterType::String,
+            required,
+            default_value: (!required).then(|| serde_json::json!("")),
+            validation: None,
+        };
+
+        let definition = WorkflowDefinition {
+            id: "explain-spike".to_string(),
+            name: "Explain {{ metric }} spike".to_string(),
+            description: "Explain a metric spike from related signals and runbooks".to_string(),
+            steps: vec![WorkflowStep::new(
+                "Explain spike",
+                StepType::Action,
+                StepAction::ExplainSpike {
+                    metric: "{{ metric }}".to_string(),
+                    service: Some("{{ service }}".to_string()),
+                    start: "{{ start }}".to_string(),
+                    end: "{{ end }}".to_string(),
+                    related_metrics: Vec::new(),
+                },
+            )
+            .with_id("explain")
+            .with_timeout(300)],
+            metadata: HashMap::new(),
+            timeout_secs: None,
+            concurrency: None,
+        };
+
+        let mut template = WorkflowTemplate::new(
+            "Explain this spike",
+            "Gather related metrics, error logs, slow traces and runbooks for a metric spike and propose its causes",
+            definition,
+        )
+        .with_category("Observability")
+        .with_tags(vec!["observability".to_string(), "incident".to_string(), "spike".to_string()])
+        .with_icon("📈")
+        .with_author("system")
+        .make_public()
+        .with_parameter(string_param("metric", "Metric", "Metric name, e.g. latency, or a PromQL expression", true))
+        .with_parameter(string_param("service", "Service", "Service the metric belongs to", false))
+        .with_parameter(string_param("start", "Start", "Spike window start, RFC 3339", true))
+        .with_parameter(string_param("end", "End", "Spike window end, RFC 3339", true));
+        template.id = Self::EXPLAIN_SPIKE_ID.to_string();
+        template
+    }
+}
+
+/// DevOps seed pack: deploys, rollbacks, migrations, incidents and
+/// dependency updates
+///
+/// Steps call custom handlers (`deploy`, `health_check`, ...) that
+/// deployments register on their step executor, so the templates stay
+/// independent of any one CI/CD system.
+impl TemplateBuilders {
+    /// Id of the [`TemplateBuilders::deploy_with_approval`] template
+    pub const DEPLOY_WITH_APPROVAL_ID: &'static str = "deploy-with-approval";
+    /// Id of the [`TemplateBuilders::rollback`] template
+    pub const ROLLBACK_ID: &'static str = "rollback";
+    /// Id of the [`TemplateBuilders::database_migration_gate`] template
+    pub const DATABASE_MIGRATION_GATE_ID: &'static str = "database-migration-gate";
+    /// Id of the [`TemplateBuilders::incident_triage`] template
+    pub const INCIDENT_TRIAGE_ID: &'static str = "incident-triage";
+    /// Id of the [`TemplateBuilders::dependency_update`] template
+    pub const DEPENDENCY_UPDATE_ID: &'static str = "dependency-update";
+
+    /// Deploy a version of a service once an approver signs off on the plan,
+    /// then check its health
+    pub fn deploy_with_approval() -> WorkflowTemplate {
+        let steps = vec![
+            Self::handler_step("plan", "Plan deployment", "deployment_plan", &["service", "version", "environment"]),
+            WorkflowStep::new("Approve deployment", StepType::Approval, StepAction::Wait { duration_secs: 0 })
+                .with_id("approve")
+                .with_dependency("plan")
+                .with_timeout(86400)
+                .with_metadata("approvers", serde_json::json!("{{ approvers }}")),
+            Self::handler_step("deploy", "Deploy", "deploy", &["service", "version", "environment"])
+                .with_dependency("approve"),
+            Self::handler_step("verify", "Check health", "health_check", &["service", "environment"])
+                .with_dependency("deploy")
+                .with_retry(3),
+        ];
+
+        Self::devops_template(
+            Self::DEPLOY_WITH_APPROVAL_ID,
+            "Deploy with approval",
+            "Deploy {{ service }} {{ version }} to {{ environment }}",
+            "Plan a deployment, wait for an approver, deploy and check the service's health",
+            steps,
+        )
+        .with_tags(vec!["deploy".to_string(), "approval".to_string(), "release".to_string()])
+        .with_icon("🚀")
+        .with_parameter(Self::text_param("service", "Service", "Service to deploy", None))
+        .with_parameter(Self::text_param("version", "Version", "Version, tag or image digest to deploy", None))
+        .with_parameter(Self::environment_param())
+        .with_parameter(Self::text_param(
+            "approvers",
+            "Approvers",
+            "Approver group signing off on the deployment",
+            Some("release-managers"),
+        ))
+        .with_docs(TemplateDocs {
+            when_to_use: "Releasing a new version of a service to an environment where a human signs off first."
+                .to_string(),
+            steps: vec![
+                "plan: summarize what changes between the running and the new version".to_string(),
+                "approve: wait up to 24 hours for the approver group".to_string(),
+                "deploy: roll the new version out".to_string(),
+                "verify: check the service's health, retrying up to 3 times".to_string(),
+            ],
+            prerequisites: vec![
+                "deployment_plan, deploy and health_check step handlers".to_string(),
+                "An approver group matching the approvers parameter".to_string(),
+            ],
+        })
+    }
+
+    /// Roll a service back to a known good version and confirm it is healthy
+    pub fn rollback() -> WorkflowTemplate {
+        let steps = vec![
+            Self::handler_step("capture", "Capture current release", "capture_release_state", &["service", "environment"]),
+            Self::handler_step("rollback", "Roll back", "rollback", &["service", "environment", "target_version"])
+                .with_dependency("capture"),
+            Self::handler_step("verify", "Check health", "health_check", &["service", "environment"])
+                .with_dependency("rollback")
+                .with_retry(3),
+            Self::handler_step("notify", "Announce rollback", "notify", &["channel", "reason"])
+                .with_dependency("verify")
+                .with_fail_on_error(false),
+        ];
+
+        Self::devops_template(
+            Self::ROLLBACK_ID,
+            "Rollback",
+            "Roll {{ service }} back to {{ target_version }} in {{ environment }}",
+            "Roll a service back to a known good version, check its health and announce the rollback",
+            steps,
+        )
+        .with_tags(vec!["rollback".to_string(), "deploy".to_string(), "incident".to_string()])
+        .with_icon("⏪")
+        .with_parameter(Self::text_param("service", "Service", "Service to roll back", None))
+        .with_parameter(Self::environment_param())
+        .with_parameter(Self::text_param("target_version", "Target Version", "Known good version to return to", None))
+        .with_parameter(Self::text_param("reason", "Reason", "Why the service is rolled back", None))
+        .with_parameter(Self::text_param(
+            "channel",
+            "Channel",
+            "Channel the rollback is announced in",
+            Some("#deployments"),
+        ))
+        .with_docs(TemplateDocs {
+            when_to_use: "A release misbehaves and the previous version is known to work.".to_string(),
+            steps: vec![
+                "capture: record the running release so the rollback can be undone".to_string(),
+                "rollback: deploy the target version".to_string(),
+                "verify: check the service's health, retrying up to 3 times".to_string(),
+                "notify: announce the rollback and its reason; failures do not fail the run".to_string(),
+            ],
+            prerequisites: vec!["capture_release_state, rollback, health_check and notify step handlers".to_string()],
+        })
+    }
+
+    /// Back up a database, dry-run a migration and apply it once approved
+    pub fn database_migration_gate() -> WorkflowTemplate {
+        let steps = vec![
+            Self::handler_step("backup", "Back up database", "database_backup", &["database", "environment"]),
+            Self::handler_step("dry_run", "Dry-run migration", "migration_dry_run", &["database", "migration"])
+                .with_dependency("backup"),
+            WorkflowStep::new("Approve migration", StepType::Approval, StepAction::Wait { duration_secs: 0 })
+                .with_id("approve")
+                .with_dependency("dry_run")
+                .with_timeout(86400)
+                .with_metadata("approvers", serde_json::json!("{{ approvers }}")),
+            Self::handler_step("apply", "Apply migration", "apply_migration", &["database", "migration", "environment"])
+                .with_dependency("approve")
+                .with_timeout(3600),
+            Self::handler_step("verify", "Check schema", "schema_check", &["database", "migration"])
+                .with_dependency("apply"),
+        ];
+
+        Self::devops_template(
+            Self::DATABASE_MIGRATION_GATE_ID,
+            "Database migration gate",
+            "Migrate {{ database }} to {{ migration }} in {{ environment }}",
+            "Back up a database, dry-run a schema migration, and apply it once an approver has reviewed the dry run",
+            steps,
+        )
+        .with_tags(vec!["database".to_string(), "migration".to_string(), "approval".to_string()])
+        .with_icon("🗄️")
+        .with_parameter(Self::text_param("database", "Database", "Database to migrate", None))
+        .with_parameter(Self::text_param("migration", "Migration", "Migration version or name to migrate to", None))
+        .with_parameter(Self::environment_param())
+        .with_parameter(Self::text_param(
+            "approvers",
+            "Approvers",
+            "Approver group reviewing the dry run",
+            Some("dba"),
+        ))
+        .with_docs(TemplateDocs {
+            when_to_use: "Schema changes that lock tables or rewrite data, where a reviewer should see the dry run first."
+                .to_string(),
+            steps: vec![
+                "backup: take a backup the migration can be restored from".to_string(),
+                "dry_run: run the migration in a transaction that is rolled back".to_string(),
+                "approve: wait up to 24 hours for the approver group".to_string(),
+                "apply: run the migration, for at most an hour".to_string(),
+                "verify: compare the resulting schema with the migration's".to_string(),
+            ],
+            prerequisites: vec![
+                "database_backup, migration_dry_run, apply_migration and schema_check step handlers".to_string(),
+                "An approver group matching the approvers parameter".to_string(),
+            ],
+        })
+    }
+
+    /// Gather logs and metrics for an incident and have an agent propose a
+    /// severity, likely causes and next steps
+    pub fn incident_triage() -> WorkflowTemplate {
+        let steps = vec![
+            Self::handler_step("collect_logs", "Collect logs", "fetch_logs", &["service", "start", "end"]),
+            WorkflowStep::new(
+                "Cluster log patterns",
+                StepType::Action,
+                StepAction::LogAnalysis {
+                    logs: None,
+                    logs_step: Some("collect_logs".to_string()),
+                    top_patterns: 10,
+                },
+            )
+            .with_id("analyze_logs")
+            .with_dependency("collect_logs"),
+            WorkflowStep::new(
+                "Explain metric spike",
+                StepType::Action,
+                StepAction::ExplainSpike {
+                    metric: "{{ metric }}".to_string(),
+                    service: Some("{{ service }}".to_string()),
+                    start: "{{ start }}".to_string(),
+                    end: "{{ end }}".to_string(),
+                    related_metrics: Vec::new(),
+                },
+            )
+            .with_id("explain")
+            .with_timeout(300)
+            .with_fail_on_error(false),
+            WorkflowStep::new(
+                "Triage",
+                StepType::Action,
+                StepAction::AgentInvoke {
+                    agent_id: "incident-triage".to_string(),
+                    parameters: Self::placeholders(&["service", "summary", "severity"]),
+                },
+            )
+            .with_id("triage")
+            .with_dependencies(vec!["analyze_logs".to_string(), "explain".to_string()]),
+            Self::handler_step("notify", "Post triage", "notify", &["channel", "severity"])
+                .with_dependency("triage")
+                .with_fail_on_error(false),
+        ];
+
+        Self::devops_template(
+            Self::INCIDENT_TRIAGE_ID,
+            "Incident triage",
+            "Triage {{ severity }} incident in {{ service }}",
+            "Cluster an incident's logs, explain the metric spike and have an agent propose causes and next steps",
+            steps,
+        )
+        .with_tags(vec!["incident".to_string(), "triage".to_string(), "observability".to_string()])
+        .with_icon("🚨")
+        .with_parameter(Self::text_param("service", "Service", "Affected service", None))
+        .with_parameter(Self::text_param("summary", "Summary", "What is going wrong, as reported", None))
+        .with_parameter(Self::select_param(
+            "severity",
+            "Severity",
+            "Reported severity; the triage may propose another",
+            &["sev1", "sev2", "sev3", "sev4"],
+            "sev2",
+        ))
+        .with_parameter(Self::text_param("start", "Start", "Incident window start, RFC 3339", None))
+        .with_parameter(Self::text_param("end", "End", "Incident window end, RFC 3339", None))
+        .with_parameter(Self::text_param(
+            "metric",
+            "Metric",
+            "Metric that shows the incident, e.g. error_rate or a PromQL expression",
+            Some("error_rate"),
+        ))
+        .with_parameter(Self::text_param(
+            "channel",
+            "Channel",
+            "Channel the triage is posted in",
+            Some("#incidents"),
+        ))
+        .with_docs(TemplateDocs {
+            when_to_use: "The first minutes of an incident, to get the evidence and a proposed severity in one place."
+                .to_string(),
+            steps: vec![
+                "collect_logs: fetch the service's logs for the incident window".to_string(),
+                "analyze_logs: cluster the log lines into their 10 most frequent patterns".to_string(),
+                "explain: relate the metric spike to other signals and runbooks; may fail without failing the run"
+                    .to_string(),
+                "triage: have the incident-triage agent propose a severity, causes and next steps".to_string(),
+                "notify: post the triage; failures do not fail the run".to_string(),
+            ],
+            prerequisites: vec![
+                "fetch_logs and notify step handlers".to_string(),
+                "An incident-triage agent".to_string(),
+                "An observatory for the spike explanation".to_string(),
+            ],
+        })
+    }
+
+    /// Update dependencies, test and audit them, and open a pull request
+    pub fn dependency_update() -> WorkflowTemplate {
+        let steps = vec![
+            Self::handler_step(
+                "update",
+                "Update dependencies",
+                "update_dependencies",
+                &["repository", "ecosystem", "packages", "branch"],
+            ),
+            Self::handler_step("test", "Run tests", "run_tests", &["repository", "branch"])
+                .with_dependency("update")
+                .with_timeout(3600),
+            Self::handler_step("audit", "Audit dependencies", "security_audit", &["repository", "ecosystem", "branch"])
+                .with_dependency("update"),
+            Self::handler_step("open_pr", "Open pull request", "open_pull_request", &["repository", "branch", "reviewers"])
+                .with_dependencies(vec!["test".to_string(), "audit".to_string()]),
+        ];
+
+        Self::devops_template(
+            Self::DEPENDENCY_UPDATE_ID,
+            "Dependency update",
+            "Update {{ ecosystem }} dependencies of {{ repository }}",
+            "Update a repository's dependencies on a branch, run its tests and a security audit, and open a pull request",
+            steps,
+        )
+        .with_tags(vec!["dependencies".to_string(), "maintenance".to_string(), "security".to_string()])
+        .with_icon("📦")
+        .with_parameter(Self::text_param("repository", "Repository", "Repository to update, e.g. org/service", None))
+        .with_parameter(Self::select_param(
+            "ecosystem",
+            "Ecosystem",
+            "Package manager whose dependencies are updated",
+            &["cargo", "npm", "pip", "go"],
+            "cargo",
+        ))
+        .with_parameter(Self::text_param(
+            "packages",
+            "Packages",
+            "Space-separated packages to update; all when empty",
+            Some(""),
+        ))
+        .with_parameter(Self::text_param("branch", "Branch", "Branch the updates are committed to", Some("deps/update")))
+        .with_parameter(Self::text_param("reviewers", "Reviewers", "Reviewers requested on the pull request", Some("")))
+        .with_docs(TemplateDocs {
+            when_to_use: "Routine dependency bumps, or updating one package after a security advisory.".to_string(),
+            steps: vec![
+                "update: update the packages and commit the lockfile to the branch".to_string(),
+                "test: run the repository's tests on the branch, for at most an hour".to_string(),
+                "audit: check the updated dependencies for known vulnerabilities".to_string(),
+                "open_pr: open a pull request once tests and audit pass".to_string(),
+            ],
+            prerequisites: vec![
+                "update_dependencies, run_tests, security_audit and open_pull_request step handlers".to_string(),
+            ],
+        })
+    }
+
+    /// Public, system-authored template of the DevOps category under `id`
+    fn devops_template(
+        id: &str,
+        name: &str,
+        run_name: &str,
+        description: &str,
+        steps: Vec<WorkflowStep>,
+    ) -> WorkflowTemplate {
+        let definition = WorkflowDefinition {
+            id: id.to_string(),
+            name: run_name.to_string(),
+            description: description.to_string(),
+            steps,
+            metadata: HashMap::new(),
+            timeout_secs: None,
+            concurrency: None,
+        };
+
+        let mut template = WorkflowTemplate::new(name, description, definition)
+            .with_category("DevOps")
+            .with_author("system")
+            .make_public();
+        template.id = id.to_string();
+        template
+    }
+
+    /// Step calling the custom `handler` with the named parameters
+    fn handler_step(id: &str, name: &str, handler: &str, params: &[&str]) -> WorkflowStep {
+        WorkflowStep::new(
+            name,
+            StepType::Action,
+            StepAction::Custom {
+                handler: handler.to_string(),
+                parameters: Self::placeholders(params),
+            },
+        )
+        .with_id(id)
+    }
+
+    /// `{{ param }}` placeholder values by parameter name
+    fn placeholders(params: &[&str]) -> HashMap<String, serde_json::Value> {
+        params
+            .iter()
+            .map(|param| (param.to_string(), serde_json::json!(format!("{{{{ {} }}}}", param))))
+            .collect()
+    }
+
+    /// String parameter, required when it has no default
+    fn text_param(name: &str, label: &str, description: &str, default: Option<&str>) -> TemplateParameter {
+        TemplateParameter {
+            name: name.to_string(),
+            label: label.to_string(),
+            description: Some(description.to_string()),
+            param_type: ParameterType::String,
+            required: default.is_none(),
+            default_value: default.map(|value| serde_json::json!(value)),
+            validation: None,
+        }
+    }
+
+    fn select_param(name: &str, label: &str, description: &str, options: &[&str], default: &str) -> TemplateParameter {
+        TemplateParameter {
+            name: name.to_string(),
+            label: label.to_string(),
+            description: Some(description.to_string()),
+            param_type: ParameterType::Select {
+                options: options
+                    .iter()
+                    .map(|option| SelectOption {
+                        value: option.to_string(),
+                        label: option.to_string(),
+                    })
+                    .collect(),
+            },
+            required: false,
+            default_value: Some(serde_json::json!(default)),
+            validation: None,
+        }
+    }
+
+    fn environment_param() -> TemplateParameter {
+        Self::select_param(
+            "environment",
+            "Environment",
+            "Environment to act on",
+            &["staging", "production"],
+            "production",
+        )
+    }
+}
+
 impl Default for ParameterValidation {
     fn default() -> Self {
         Self {
@@ -748,6 +1374,7 @@ impl Default for ParameterValidation {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dag::WorkflowDag;
 
     fn create_test_template() -> WorkflowTemplate {
         let definition = WorkflowDefinition {
@@ -762,6 +1389,7 @@ mod tests {
             .with_id("step-1")],
             metadata: HashMap::new(),
             timeout_secs: None,
+            concurrency: None,
         };
 
         WorkflowTemplate::new("Test Template", "A test template", definition)
@@ -822,6 +1450,23 @@ mod tests {
         assert!(template.validate_params(&params).is_err());
     }
 
+    #[test]
+    fn test_check_params_reports_every_field() {
+        let template = create_test_template();
+
+        let errors = template.check_params(&serde_json::json!({
+            "workflow_name": "ab",
+            "step_name": 7
+        }));
+        let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["workflow_id", "workflow_name", "step_name"]);
+        assert_eq!(errors[2].to_string(), "step_name: Parameter step_name must be a string");
+
+        let resolved = template.resolve_params(&serde_json::json!({ "workflow_id": "x" }));
+        assert_eq!(resolved["step_name"], "Default Step");
+        assert_eq!(resolved["workflow_id"], "x");
+    }
+
     #[test]
     fn test_template_instantiation() {
         let template = create_test_template();
@@ -868,6 +1513,44 @@ mod tests {
         assert_eq!(template.definition.steps.len(), 3);
     }
 
+    #[test]
+    fn test_instantiation_escapes_values() {
+        let template = create_test_template();
+
+        let definition = template
+            .instantiate(&serde_json::json!({
+                "workflow_id": "my-workflow",
+                "workflow_name": "Say \"hi\" \\o/"
+            }))
+            .unwrap();
+
+        assert_eq!(definition.name, "Say \"hi\" \\o/");
+    }
+
+    #[tokio::test]
+    async fn test_builtin_explain_spike() {
+        let library = TemplateLibrary::new(Arc::new(InMemoryTemplateRepository::with_builtins()));
+        let template = library.get(TemplateBuilders::EXPLAIN_SPIKE_ID).await.unwrap().unwrap();
+        assert!(template.is_public);
+
+        let params = serde_json::json!({
+            "metric": "rate(http_requests_total{code=~\"5..\"}[5m])",
+            "start": "2024-05-01T10:00:00Z",
+            "end": "2024-05-01T10:30:00Z"
+        });
+        let definition = template.instantiate(&params).unwrap();
+
+        match &definition.steps[0].action {
+            StepAction::ExplainSpike { metric, service, start, .. } => {
+                assert_eq!(metric, "rate(http_requests_total{code=~\"5..\"}[5m])");
+                assert_eq!(service.as_deref(), Some(""));
+                assert_eq!(start, "2024-05-01T10:00:00Z");
+            }
+            action => panic!("unexpected action {:?}", action),
+        }
+        assert!(template.instantiate(&serde_json::json!({ "metric": "latency" })).is_err());
+    }
+
     #[test]
     fn test_builder_approval() {
         let template = TemplateBuilders::approval_workflow("Request Approval", "Approve requests");
@@ -875,4 +1558,86 @@ mod tests {
         assert_eq!(template.category, "Approval");
         assert!(template.definition.steps.iter().any(|s| s.step_type == StepType::Approval));
     }
+
+    #[test]
+    fn test_devops_templates_instantiate() {
+        let required = serde_json::json!({
+            "service": "checkout",
+            "version": "v1.4.2",
+            "target_version": "v1.4.1",
+            "reason": "Error rate doubled",
+            "database": "orders",
+            "migration": "20240501_add_index",
+            "summary": "Checkout returns 502s",
+            "start": "2024-05-01T10:00:00Z",
+            "end": "2024-05-01T10:30:00Z",
+            "repository": "acme/checkout"
+        });
+
+        for template in builtin_templates() {
+            assert!(template.is_public, "{}", template.id);
+            if template.id == TemplateBuilders::EXPLAIN_SPIKE_ID {
+                continue;
+            }
+            assert_eq!(template.category, "DevOps");
+            let docs = template.docs.as_ref().unwrap();
+            assert_eq!(docs.steps.len(), template.definition.steps.len(), "{}", template.id);
+
+            let params: serde_json::Map<_, _> = template
+                .parameters
+                .iter()
+                .filter(|p| p.required)
+                .map(|p| (p.name.clone(), required[&p.name].clone()))
+                .collect();
+            let definition = template.instantiate(&serde_json::Value::Object(params)).unwrap();
+            let json = serde_json::to_string(&definition).unwrap();
+            assert!(!json.contains("{{"), "{} left a placeholder: {}", template.id, json);
+            WorkflowDag::new(definition.steps).unwrap();
+        }
+
+        let deploy = TemplateBuilders::deploy_with_approval()
+            .instantiate(&serde_json::json!({ "service": "checkout", "version": "v2" }))
+            .unwrap();
+        assert_eq!(deploy.name, "Deploy checkout v2 to production");
+        assert_eq!(deploy.steps[1].metadata["approvers"], "release-managers");
+    }
+
+    #[test]
+    fn test_placeholders_are_expressions() {
+        let mut template = TemplateBuilders::deploy_with_approval();
+        template.definition.description =
+            "{{ environment == 'production' ? 'Careful: ' : '' }}{{ service.upper() }} ${{ steps.deploy.outputs.url }}"
+                .to_string();
+        template.check_placeholders().unwrap();
+
+        let params = serde_json::json!({ "service": "checkout", "version": "v2" });
+        let definition = template.instantiate(&params).unwrap();
+        assert_eq!(definition.description, "Careful: CHECKOUT ${{ steps.deploy.outputs.url }}");
+
+        template.definition.description = "{{ servce }}".to_string();
+        let err = template.check_placeholders().unwrap_err();
+        assert!(err.to_string().contains("unknown name servce"));
+        template.definition.description = "{{ version + 1 }}".to_string();
+        assert!(template.instantiate(&params).is_err());
+
+        for template in builtin_templates() {
+            template.check_placeholders().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_install_builtin() {
+        let library = TemplateLibrary::new(Arc::new(InMemoryTemplateRepository::new()));
+        let installed = library.install_builtin().await.unwrap();
+        assert_eq!(installed.len(), builtin_templates().len());
+
+        let params = serde_json::json!({ "repository": "acme/checkout" });
+        library.instantiate(TemplateBuilders::DEPENDENCY_UPDATE_ID, params).await.unwrap();
+        library.install_builtin().await.unwrap();
+
+        let template = library.get(TemplateBuilders::DEPENDENCY_UPDATE_ID).await.unwrap().unwrap();
+        assert_eq!(template.usage_count, 1);
+        assert_eq!(library.list().await.unwrap().len(), installed.len());
+        assert!(library.get_categories().await.unwrap().contains(&"DevOps".to_string()));
+    }
 }
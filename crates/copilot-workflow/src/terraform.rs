@@ -0,0 +1,324 @@
+//! Terraform / OpenTofu plan analysis
+//!
+//! The `terraform_plan` step action reviews a plan (the JSON written by
+//! `terraform show -json` or `tofu show -json`) before it is applied. The
+//! plan's resource changes are classified, destroys and IAM changes are
+//! called out, an agent summarizes the risk, the configured policy engine is
+//! consulted and, when the plan is risky enough, the step waits for a human
+//! approval. Steps that apply the plan should depend on the review step so
+//! they only run once it has passed.
+
+use crate::{Result, WorkflowError};
+use copilot_adapters::llm_devops::policy_engine::{EvaluationRequest, EvaluationResponse, Subject};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Instructions for the agent that summarizes a plan
+pub const PLAN_REVIEW_PROMPT: &str = "You review infrastructure changes before they are applied. \
+Summarize the risk of this Terraform plan for the engineer approving it: call out every resource \
+that is destroyed or replaced, every IAM or access change, and anything that could cause downtime \
+or data loss. Be brief and concrete.";
+
+/// What a plan does to one resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Create,
+    Update,
+    Delete,
+    /// Destroyed and created again (either order)
+    Replace,
+    Read,
+    NoOp,
+}
+
+impl ChangeKind {
+    fn from_actions(actions: &[String]) -> Option<Self> {
+        let actions: Vec<&str> = actions.iter().map(String::as_str).collect();
+        match actions.as_slice() {
+            ["create"] => Some(ChangeKind::Create),
+            ["update"] => Some(ChangeKind::Update),
+            ["delete"] => Some(ChangeKind::Delete),
+            ["delete", "create"] | ["create", "delete"] => Some(ChangeKind::Replace),
+            ["read"] => Some(ChangeKind::Read),
+            ["no-op"] => Some(ChangeKind::NoOp),
+            _ => None,
+        }
+    }
+
+    fn is_change(self) -> bool {
+        !matches!(self, ChangeKind::Read | ChangeKind::NoOp)
+    }
+}
+
+/// How risky applying a plan is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanRisk {
+    /// Only creates resources
+    #[default]
+    Low,
+    /// Updates resources in place
+    Medium,
+    /// Destroys or replaces resources, or changes IAM
+    High,
+}
+
+impl fmt::Display for PlanRisk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanRisk::Low => write!(f, "low"),
+            PlanRisk::Medium => write!(f, "medium"),
+            PlanRisk::High => write!(f, "high"),
+        }
+    }
+}
+
+/// A resource the plan changes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceChange {
+    /// e.g. `module.db.aws_db_instance.main`
+    pub address: String,
+    /// e.g. `aws_db_instance`
+    pub resource_type: String,
+    pub kind: ChangeKind,
+    /// Whether the resource grants or defines access
+    pub iam: bool,
+}
+
+/// Classified resource changes of a plan
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlanAnalysis {
+    /// Changed resources; reads and no-ops are left out
+    pub changes: Vec<ResourceChange>,
+    pub creates: usize,
+    pub updates: usize,
+    pub deletes: usize,
+    pub replaces: usize,
+    /// Addresses of destroyed or replaced resources
+    pub deletions: Vec<String>,
+    /// Addresses of changed IAM resources
+    pub iam_changes: Vec<String>,
+    pub risk: PlanRisk,
+}
+
+impl PlanAnalysis {
+    /// Analyze a plan in `terraform show -json` format; the plan may also be
+    /// given as a JSON string
+    pub fn from_plan_json(plan: &serde_json::Value) -> Result<Self> {
+        if let Some(text) = plan.as_str() {
+            return Self::from_plan_json(&serde_json::from_str(text)?);
+        }
+
+        #[derive(Deserialize)]
+        struct Plan {
+            #[serde(default)]
+            resource_changes: Option<Vec<RawChange>>,
+        }
+        #[derive(Deserialize)]
+        struct RawChange {
+            address: String,
+            #[serde(rename = "type")]
+            resource_type: String,
+            change: RawActions,
+        }
+        #[derive(Deserialize)]
+        struct RawActions {
+            actions: Vec<String>,
+        }
+
+        let plan: Plan = serde_json::from_value(plan.clone())?;
+        let raw_changes = plan.resource_changes.ok_or_else(|| {
+            WorkflowError::InvalidDefinition("Terraform plan has no resource_changes".to_string())
+        })?;
+
+        let mut analysis = PlanAnalysis::default();
+        for raw in raw_changes {
+            let kind = ChangeKind::from_actions(&raw.change.actions).ok_or_else(|| {
+                WorkflowError::InvalidDefinition(format!(
+                    "Unknown plan actions for {}: {:?}",
+                    raw.address, raw.change.actions
+                ))
+            })?;
+            if !kind.is_change() {
+                continue;
+            }
+            let iam = is_iam_resource(&raw.resource_type);
+            match kind {
+                ChangeKind::Create => analysis.creates += 1,
+                ChangeKind::Update => analysis.updates += 1,
+                ChangeKind::Delete => analysis.deletes += 1,
+                ChangeKind::Replace => analysis.replaces += 1,
+                ChangeKind::Read | ChangeKind::NoOp => {}
+            }
+            if matches!(kind, ChangeKind::Delete | ChangeKind::Replace) {
+                analysis.deletions.push(raw.address.clone());
+            }
+            if iam {
+                analysis.iam_changes.push(raw.address.clone());
+            }
+            analysis.changes.push(ResourceChange {
+                address: raw.address,
+                resource_type: raw.resource_type,
+                kind,
+                iam,
+            });
+        }
+
+        analysis.risk = if !analysis.deletions.is_empty() || !analysis.iam_changes.is_empty() {
+            PlanRisk::High
+        } else if analysis.updates > 0 {
+            PlanRisk::Medium
+        } else {
+            PlanRisk::Low
+        };
+        Ok(analysis)
+    }
+
+    /// Whether applying the plan changes anything
+    pub fn has_changes(&self) -> bool {
+        !self.changes.is_empty()
+    }
+
+    /// Plain-text summary, used when no agent summary is available
+    pub fn describe(&self) -> String {
+        let mut text = format!(
+            "Plan: {} to add, {} to change, {} to destroy, {} to replace. Risk: {}.",
+            self.creates, self.updates, self.deletes, self.replaces, self.risk
+        );
+        if !self.deletions.is_empty() {
+            text.push_str(&format!("\nDestroyed or replaced: {}", self.deletions.join(", ")));
+        }
+        if !self.iam_changes.is_empty() {
+            text.push_str(&format!("\nIAM changes: {}", self.iam_changes.join(", ")));
+        }
+        text
+    }
+
+    /// Task handed to the reviewing agent
+    pub fn review_task(&self) -> String {
+        let mut task = format!("{}\n\nResource changes:", self.describe());
+        for change in &self.changes {
+            task.push_str(&format!("\n- {:?} {}", change.kind, change.address));
+            if change.iam {
+                task.push_str(" (IAM)");
+            }
+        }
+        task
+    }
+
+    /// Policy engine request for applying the plan
+    pub fn policy_request(&self, workflow_id: &str) -> EvaluationRequest {
+        let mut context = HashMap::new();
+        context.insert("risk".to_string(), serde_json::json!(self.risk));
+        context.insert("creates".to_string(), serde_json::json!(self.creates));
+        context.insert("updates".to_string(), serde_json::json!(self.updates));
+        context.insert("deletes".to_string(), serde_json::json!(self.deletes));
+        context.insert("replaces".to_string(), serde_json::json!(self.replaces));
+        context.insert("deletions".to_string(), serde_json::json!(self.deletions));
+        context.insert("iam_changes".to_string(), serde_json::json!(self.iam_changes));
+        let resource_types: Vec<&str> = self.changes.iter().map(|c| c.resource_type.as_str()).collect();
+        context.insert("resource_types".to_string(), serde_json::json!(resource_types));
+
+        EvaluationRequest {
+            resource: "terraform_plan".to_string(),
+            action: "apply".to_string(),
+            context,
+            subject: Subject {
+                subject_type: "workflow".to_string(),
+                id: workflow_id.to_string(),
+                attributes: HashMap::new(),
+            },
+        }
+    }
+}
+
+/// Whether a policy decision asks for a human to approve the apply
+pub fn requires_approval(response: &EvaluationResponse) -> bool {
+    response
+        .obligations
+        .iter()
+        .any(|o| o.action.eq_ignore_ascii_case("require_approval"))
+}
+
+/// Find plan JSON among a previous step's outputs: a `plan` output, or the
+/// `stdout` of a command that ran `terraform show -json`
+pub fn plan_from_outputs(outputs: &HashMap<String, serde_json::Value>) -> Option<serde_json::Value> {
+    if let Some(plan) = outputs.get("plan") {
+        return Some(plan.clone());
+    }
+    outputs
+        .get("stdout")
+        .and_then(|stdout| stdout.as_str())
+        .filter(|stdout| !stdout.trim().is_empty())
+        .map(|stdout| serde_json::Value::String(stdout.to_string()))
+}
+
+/// Resource types that grant, define or bind access
+fn is_iam_resource(resource_type: &str) -> bool {
+    const PREFIXES: &[&str] = &[
+        "aws_iam_",
+        "azurerm_role_",
+        "azuread_",
+        "kubernetes_role",
+        "kubernetes_cluster_role",
+    ];
+    PREFIXES.iter().any(|p| resource_type.starts_with(p))
+        || (resource_type.starts_with("google_") && resource_type.contains("_iam_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn plan() -> serde_json::Value {
+        json!({
+            "format_version": "1.2",
+            "resource_changes": [
+                { "address": "aws_s3_bucket.logs", "type": "aws_s3_bucket", "change": { "actions": ["create"] } },
+                { "address": "aws_instance.web", "type": "aws_instance", "change": { "actions": ["update"] } },
+                { "address": "aws_db_instance.main", "type": "aws_db_instance", "change": { "actions": ["delete", "create"] } },
+                { "address": "aws_iam_role_policy.ci", "type": "aws_iam_role_policy", "change": { "actions": ["update"] } },
+                { "address": "data.aws_caller_identity.me", "type": "aws_caller_identity", "change": { "actions": ["read"] } },
+                { "address": "aws_sqs_queue.jobs", "type": "aws_sqs_queue", "change": { "actions": ["no-op"] } }
+            ]
+        })
+    }
+
+    #[test]
+    fn test_plan_analysis() {
+        let analysis = PlanAnalysis::from_plan_json(&plan()).unwrap();
+        assert_eq!(analysis.changes.len(), 4);
+        assert_eq!((analysis.creates, analysis.updates, analysis.deletes, analysis.replaces), (1, 2, 0, 1));
+        assert_eq!(analysis.deletions, vec!["aws_db_instance.main"]);
+        assert_eq!(analysis.iam_changes, vec!["aws_iam_role_policy.ci"]);
+        assert_eq!(analysis.risk, PlanRisk::High);
+
+        // Accepted as the string a command step captured
+        let from_text = PlanAnalysis::from_plan_json(&json!(plan().to_string())).unwrap();
+        assert_eq!(from_text.changes, analysis.changes);
+
+        let request = analysis.policy_request("wf-1");
+        assert_eq!(request.action, "apply");
+        assert_eq!(request.context["risk"], "high");
+    }
+
+    #[test]
+    fn test_plan_risk_levels() {
+        let only = |actions: serde_json::Value, resource_type: &str| {
+            PlanAnalysis::from_plan_json(&json!({
+                "resource_changes": [{ "address": "x.y", "type": resource_type, "change": { "actions": actions } }]
+            }))
+            .unwrap()
+        };
+        assert_eq!(only(json!(["create"]), "aws_instance").risk, PlanRisk::Low);
+        assert_eq!(only(json!(["update"]), "aws_instance").risk, PlanRisk::Medium);
+        assert_eq!(only(json!(["delete"]), "aws_instance").risk, PlanRisk::High);
+        assert_eq!(only(json!(["create"]), "google_project_iam_member").risk, PlanRisk::High);
+        assert!(!only(json!(["no-op"]), "aws_instance").has_changes());
+
+        assert!(PlanAnalysis::from_plan_json(&json!({ "format_version": "1.2" })).is_err());
+    }
+}
@@ -0,0 +1,551 @@
+//! Test harness for workflow definitions
+//!
+//! [`WorkflowTestHarness`] runs a [`WorkflowDefinition`] on a real
+//! [`WorkflowEngine`] with scripted step handlers, so workflow authors can
+//! test ordering, data flow between steps, retries, failures and approval
+//! outcomes without running commands, calling agents or waiting on people.
+
+use crate::approval::{ApprovalGate, ApprovalRequest, ApprovalStatus};
+use crate::engine::{WorkflowDefinition, WorkflowEngine, WorkflowState, WorkflowStatus};
+use crate::execution::{ExecutionContext, StepExecutor};
+use crate::expressions::ExpressionScope;
+use crate::step::{CancellationReason, StepAction, StepResult, StepState, StepType, WorkflowStep};
+use crate::{Result, WorkflowError};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long a harness run may take before it is cancelled
+const DEFAULT_RUN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Scripted result of one attempt of a step
+#[derive(Debug, Clone)]
+pub struct MockStep {
+    outcome: MockOutcome,
+    delay: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+enum MockOutcome {
+    Complete(HashMap<String, serde_json::Value>),
+    Fail(String),
+}
+
+impl MockStep {
+    /// Complete with the fields of `outputs`, a JSON object
+    pub fn outputs(outputs: serde_json::Value) -> Self {
+        let outputs = match outputs {
+            serde_json::Value::Object(map) => map.into_iter().collect(),
+            serde_json::Value::Null => HashMap::new(),
+            other => HashMap::from([("result".to_string(), other)]),
+        };
+        Self {
+            outcome: MockOutcome::Complete(outputs),
+            delay: None,
+        }
+    }
+
+    /// Complete without outputs
+    pub fn succeed() -> Self {
+        Self::outputs(serde_json::Value::Null)
+    }
+
+    /// Fail with `error`
+    pub fn fail(error: impl Into<String>) -> Self {
+        Self {
+            outcome: MockOutcome::Fail(error.into()),
+            delay: None,
+        }
+    }
+
+    /// Take `delay` before finishing, e.g. to run into the step's timeout
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+}
+
+/// Scripted decision on an approval step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulatedApproval {
+    Approve { approver: String },
+    Deny { approver: String },
+    Timeout,
+}
+
+/// One attempt of a step during a harness run
+#[derive(Debug, Clone)]
+pub struct StepCall {
+    pub step_id: String,
+    /// 1 for the first attempt
+    pub attempt: u32,
+    /// Action with its `${{ }}` expressions resolved
+    pub action: StepAction,
+}
+
+/// Runs a workflow definition with mocked step handlers
+///
+/// Steps run in the engine's dependency order with their retry and timeout
+/// settings, but each attempt takes the next scripted [`MockStep`]; the last
+/// one repeats. Approval steps request an approval on the engine's gate and
+/// get the scripted [`SimulatedApproval`], approved by `test` when none is
+/// scripted. Other steps without mocks complete without outputs, or run on
+/// the fallback executor when one is set.
+pub struct WorkflowTestHarness {
+    definition: WorkflowDefinition,
+    mocks: HashMap<String, Vec<MockStep>>,
+    approvals: HashMap<String, SimulatedApproval>,
+    fallback: Option<Arc<dyn StepExecutor>>,
+    timeout: Duration,
+}
+
+impl WorkflowTestHarness {
+    /// Harness running `definition`
+    pub fn new(definition: WorkflowDefinition) -> Self {
+        Self {
+            definition,
+            mocks: HashMap::new(),
+            approvals: HashMap::new(),
+            fallback: None,
+            timeout: DEFAULT_RUN_TIMEOUT,
+        }
+    }
+
+    /// Script the next attempt of `step_id`
+    pub fn mock_step(mut self, step_id: impl Into<String>, mock: MockStep) -> Self {
+        self.mocks.entry(step_id.into()).or_default().push(mock);
+        self
+    }
+
+    /// Have `approver` approve the approval step `step_id`
+    pub fn approve(mut self, step_id: impl Into<String>, approver: impl Into<String>) -> Self {
+        self.approvals.insert(step_id.into(), SimulatedApproval::Approve { approver: approver.into() });
+        self
+    }
+
+    /// Have `approver` deny the approval step `step_id`
+    pub fn deny(mut self, step_id: impl Into<String>, approver: impl Into<String>) -> Self {
+        self.approvals.insert(step_id.into(), SimulatedApproval::Deny { approver: approver.into() });
+        self
+    }
+
+    /// Let the approval of step `step_id` time out
+    pub fn time_out(mut self, step_id: impl Into<String>) -> Self {
+        self.approvals.insert(step_id.into(), SimulatedApproval::Timeout);
+        self
+    }
+
+    /// Run steps without mocks on `executor` instead of completing them
+    pub fn with_fallback(mut self, executor: Arc<dyn StepExecutor>) -> Self {
+        self.fallback = Some(executor);
+        self
+    }
+
+    /// Cancel the run and fail if it takes longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Run the workflow until it completes, fails or is cancelled
+    pub async fn run(self) -> Result<WorkflowTestRun> {
+        let gate = Arc::new(ApprovalGate::new());
+        let executor = Arc::new(ScriptedExecutor {
+            mocks: self.mocks,
+            approvals: self.approvals,
+            fallback: self.fallback,
+            gate: gate.clone(),
+            calls: Mutex::new(Vec::new()),
+        });
+        let engine = WorkflowEngine::with_executor(executor.clone()).with_approval_gate(gate.clone());
+
+        let workflow_id = self.definition.id.clone();
+        let execution_id = engine.execute_workflow(self.definition).await?;
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        let state = loop {
+            let state = engine.get_status(&execution_id).await?;
+            if state.is_terminal() {
+                break state;
+            }
+            if tokio::time::Instant::now() >= deadline {
+                engine.cancel_workflow(&execution_id).await?;
+                return Err(WorkflowError::Timeout(format!(
+                    "workflow {} did not finish within {:?}",
+                    workflow_id, self.timeout
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+
+        let mut approvals = gate.list_for_workflow(&workflow_id).await;
+        approvals.sort_by_key(|request| request.created_at);
+        let calls = executor.calls.lock().expect("calls lock poisoned").clone();
+
+        Ok(WorkflowTestRun { state, calls, approvals })
+    }
+}
+
+/// Outcome of a harness run, with assertions for tests
+///
+/// The `assert_*` methods panic with a description of what differed and
+/// return `self`, so they can be chained.
+#[derive(Debug, Clone)]
+pub struct WorkflowTestRun {
+    /// Final state of the execution
+    pub state: WorkflowState,
+    /// Step attempts in the order they started
+    pub calls: Vec<StepCall>,
+    /// Approvals requested by approval steps, oldest first
+    pub approvals: Vec<ApprovalRequest>,
+}
+
+impl WorkflowTestRun {
+    /// Steps in the order their first attempt started
+    pub fn execution_order(&self) -> Vec<&str> {
+        self.calls
+            .iter()
+            .filter(|call| call.attempt == 1)
+            .map(|call| call.step_id.as_str())
+            .collect()
+    }
+
+    /// How many times `step_id` was attempted
+    pub fn attempts(&self, step_id: &str) -> usize {
+        self.calls.iter().filter(|call| call.step_id == step_id).count()
+    }
+
+    /// Output `name` of step `step_id`
+    pub fn output(&self, step_id: &str, name: &str) -> Option<&serde_json::Value> {
+        self.state.step_results.get(step_id)?.outputs.get(name)
+    }
+
+    /// Action the last attempt of `step_id` ran, expressions resolved
+    pub fn action(&self, step_id: &str) -> Option<&StepAction> {
+        self.calls.iter().rev().find(|call| call.step_id == step_id).map(|call| &call.action)
+    }
+
+    /// Assert the workflow ended in `status`
+    pub fn assert_status(&self, status: WorkflowStatus) -> &Self {
+        assert_eq!(
+            self.state.status, status,
+            "workflow ended {:?} instead of {:?} (error: {:?})",
+            self.state.status, status, self.state.error
+        );
+        self
+    }
+
+    /// Assert the workflow completed
+    pub fn assert_completed(&self) -> &Self {
+        self.assert_status(WorkflowStatus::Completed)
+    }
+
+    /// Assert the workflow failed with an error containing `error`
+    pub fn assert_failed_with(&self, error: &str) -> &Self {
+        self.assert_status(WorkflowStatus::Failed);
+        let actual = self.state.error.as_deref().unwrap_or_default();
+        assert!(actual.contains(error), "workflow failed with {:?}, not {:?}", actual, error);
+        self
+    }
+
+    /// Assert step `step_id` ended in `state`
+    pub fn assert_step(&self, step_id: &str, state: StepState) -> &Self {
+        let actual = self.state.step_results.get(step_id).map(|result| &result.state);
+        assert_eq!(actual, Some(&state), "step {} ended {:?} instead of {:?}", step_id, actual, state);
+        self
+    }
+
+    /// Assert `steps` started in this order, other steps in between allowed
+    pub fn assert_order(&self, steps: &[&str]) -> &Self {
+        let order = self.execution_order();
+        let positions: Vec<Option<usize>> = steps
+            .iter()
+            .map(|step| order.iter().position(|id| id == step))
+            .collect();
+        let in_order = positions.iter().all(Option::is_some) && positions.windows(2).all(|w| w[0] < w[1]);
+        assert!(in_order, "steps ran in order {:?}, expected {:?}", order, steps);
+        self
+    }
+
+    /// Assert step `step_id` was never attempted
+    pub fn assert_not_run(&self, step_id: &str) -> &Self {
+        assert_eq!(self.attempts(step_id), 0, "step {} ran, expected it not to", step_id);
+        self
+    }
+
+    /// Assert step `step_id` was attempted `attempts` times
+    pub fn assert_attempts(&self, step_id: &str, attempts: usize) -> &Self {
+        let actual = self.attempts(step_id);
+        assert_eq!(actual, attempts, "step {} was attempted {} times, not {}", step_id, actual, attempts);
+        self
+    }
+
+    /// Assert output `name` of step `step_id` is `value`
+    pub fn assert_output(&self, step_id: &str, name: &str, value: serde_json::Value) -> &Self {
+        assert_eq!(self.output(step_id, name), Some(&value), "output {} of step {}", name, step_id);
+        self
+    }
+}
+
+/// Step executor playing back the harness's scripts
+struct ScriptedExecutor {
+    mocks: HashMap<String, Vec<MockStep>>,
+    approvals: HashMap<String, SimulatedApproval>,
+    fallback: Option<Arc<dyn StepExecutor>>,
+    gate: Arc<ApprovalGate>,
+    calls: Mutex<Vec<StepCall>>,
+}
+
+impl ScriptedExecutor {
+    /// Record the attempt and resolve the step's expressions
+    async fn record(&self, step: &WorkflowStep, attempt: u32, context: &ExecutionContext) -> Result<StepAction> {
+        let resolved = ExpressionScope::from_context(context).await.resolve_action(&step.action);
+        let action = resolved.as_ref().unwrap_or(&step.action).clone();
+        self.calls.lock().expect("calls lock poisoned").push(StepCall {
+            step_id: step.id.clone(),
+            attempt,
+            action: action.clone(),
+        });
+        resolved.map_err(|e| WorkflowError::StepExecutionFailed {
+            step_id: step.id.clone(),
+            reason: e.to_string(),
+        })
+    }
+
+    /// Play back one attempt of a mocked step
+    async fn play(&self, step: &WorkflowStep, mock: &MockStep, context: &ExecutionContext) -> StepResult {
+        let result = StepResult::pending(step.id.clone());
+        let finished = async {
+            if let Some(delay) = mock.delay {
+                tokio::time::sleep(delay).await;
+            }
+        };
+        let deadline = async {
+            match step.timeout_secs {
+                Some(timeout_secs) => tokio::time::sleep(Duration::from_secs(timeout_secs)).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            _ = finished => {}
+            _ = deadline => {
+                return result.cancel(CancellationReason::Timeout {
+                    after_secs: step.timeout_secs.unwrap_or_default(),
+                });
+            }
+            _ = context.cancellation_token().cancelled() => {
+                return result.cancel(CancellationReason::WorkflowCancelled);
+            }
+        }
+
+        match &mock.outcome {
+            MockOutcome::Complete(outputs) => {
+                context.set_step_outputs(&step.id, outputs.clone()).await;
+                result.complete(outputs.clone())
+            }
+            MockOutcome::Fail(error) => result.fail(error.clone()),
+        }
+    }
+
+    /// Request an approval and decide it as scripted
+    async fn approval(&self, step: &WorkflowStep, context: &ExecutionContext) -> Result<HashMap<String, serde_json::Value>> {
+        let decision = self
+            .approvals
+            .get(&step.id)
+            .cloned()
+            .unwrap_or(SimulatedApproval::Approve { approver: "test".to_string() });
+        let timeout_secs = match decision {
+            SimulatedApproval::Timeout => 0,
+            _ => step.timeout_secs.unwrap_or(3600),
+        };
+        let request = ApprovalRequest::new(
+            &context.workflow_id,
+            &step.id,
+            format!("Approve {}", step.name),
+            format!("Simulated approval of step {}", step.id),
+            "test",
+            timeout_secs,
+        );
+        let approval_id = self.gate.request_approval(request).await;
+
+        let decided = match &decision {
+            SimulatedApproval::Approve { approver } => self.gate.approve(&approval_id, approver, None).await,
+            SimulatedApproval::Deny { approver } => self.gate.deny(&approval_id, approver, None).await,
+            SimulatedApproval::Timeout => Ok(()),
+        };
+        decided.map_err(|reason| WorkflowError::StepExecutionFailed {
+            step_id: step.id.clone(),
+            reason,
+        })?;
+
+        let status = self
+            .gate
+            .wait_for_decision(&approval_id, 10)
+            .await
+            .map_err(|reason| WorkflowError::StepExecutionFailed {
+                step_id: step.id.clone(),
+                reason,
+            })?;
+        match status {
+            ApprovalStatus::Approved => {}
+            ApprovalStatus::Timeout => return Err(WorkflowError::ApprovalTimeout(approval_id)),
+            _ => return Err(WorkflowError::ApprovalDenied(approval_id)),
+        }
+
+        let mut outputs = HashMap::new();
+        outputs.insert("approval_id".to_string(), serde_json::json!(approval_id));
+        outputs.insert("approved".to_string(), serde_json::json!(true));
+        if let SimulatedApproval::Approve { approver } = decision {
+            outputs.insert("approved_by".to_string(), serde_json::json!(approver));
+        }
+        Ok(outputs)
+    }
+
+    /// Run one attempt of `step`
+    async fn attempt(&self, step: &WorkflowStep, attempt: u32, context: &ExecutionContext) -> Result<StepResult> {
+        if let Err(e) = self.record(step, attempt, context).await {
+            return Ok(StepResult::pending(step.id.clone()).fail(e.to_string()));
+        }
+
+        if let Some(script) = self.mocks.get(&step.id) {
+            let index = (attempt as usize - 1).min(script.len() - 1);
+            return Ok(self.play(step, &script[index], context).await);
+        }
+
+        if step.step_type == StepType::Approval {
+            let result = StepResult::pending(step.id.clone());
+            return Ok(match self.approval(step, context).await {
+                Ok(outputs) => {
+                    context.set_step_outputs(&step.id, outputs.clone()).await;
+                    result.complete(outputs)
+                }
+                Err(e) => result.fail(e.to_string()),
+            });
+        }
+
+        match &self.fallback {
+            Some(fallback) => fallback.execute_step(step, context).await,
+            None => {
+                context.set_step_outputs(&step.id, HashMap::new()).await;
+                Ok(StepResult::pending(step.id.clone()).complete(HashMap::new()))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl StepExecutor for ScriptedExecutor {
+    async fn execute_step(&self, step: &WorkflowStep, context: &ExecutionContext) -> Result<StepResult> {
+        let max_attempts = if step.retry_enabled { step.max_retries + 1 } else { 1 };
+
+        let mut attempt = 1;
+        loop {
+            let mut result = self.attempt(step, attempt, context).await?;
+            let cancelled = result.cancellation == Some(CancellationReason::WorkflowCancelled);
+            if result.is_success() || cancelled || attempt >= max_attempts {
+                result.retry_count = attempt - 1;
+                return Ok(result);
+            }
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn step(id: &str, step_type: StepType, action: StepAction) -> WorkflowStep {
+        WorkflowStep::new(id, step_type, action).with_id(id)
+    }
+
+    fn custom(id: &str, parameters: serde_json::Value) -> WorkflowStep {
+        let parameters = serde_json::from_value(parameters).unwrap();
+        step(id, StepType::Action, StepAction::Custom { handler: id.to_string(), parameters })
+    }
+
+    fn release() -> WorkflowDefinition {
+        WorkflowDefinition::new("Release", "Build, approve and deploy")
+            .add_step(custom("build", json!({})))
+            .add_step(
+                step("sign-off", StepType::Approval, StepAction::Custom { handler: "approval".to_string(), parameters: HashMap::new() })
+                    .with_dependency("build"),
+            )
+            .add_step(
+                custom("deploy", json!({ "artifact": "${{ steps.build.outputs.artifact }}" }))
+                    .with_dependency("sign-off")
+                    .with_retry(2),
+            )
+    }
+
+    #[tokio::test]
+    async fn test_scripted_steps_run_in_order() {
+        let run = WorkflowTestHarness::new(release())
+            .mock_step("build", MockStep::outputs(json!({ "artifact": "app-1.2.tar" })))
+            .mock_step("deploy", MockStep::fail("connection refused"))
+            .mock_step("deploy", MockStep::outputs(json!({ "url": "https://app.example.com" })))
+            .approve("sign-off", "alice")
+            .run()
+            .await
+            .unwrap();
+
+        run.assert_completed()
+            .assert_order(&["build", "sign-off", "deploy"])
+            .assert_attempts("deploy", 2)
+            .assert_output("sign-off", "approved_by", json!("alice"))
+            .assert_output("deploy", "url", json!("https://app.example.com"));
+        match run.action("deploy") {
+            Some(StepAction::Custom { parameters, .. }) => assert_eq!(parameters["artifact"], json!("app-1.2.tar")),
+            other => panic!("unexpected action {:?}", other),
+        }
+        assert_eq!(run.approvals.len(), 1);
+        assert_eq!(run.approvals[0].approver.as_deref(), Some("alice"));
+    }
+
+    #[tokio::test]
+    async fn test_denied_and_timed_out_approvals_fail_the_run() {
+        let run = WorkflowTestHarness::new(release()).deny("sign-off", "bob").run().await.unwrap();
+        run.assert_failed_with("Approval denied")
+            .assert_step("sign-off", StepState::Failed)
+            .assert_not_run("deploy");
+
+        let run = WorkflowTestHarness::new(release()).time_out("sign-off").run().await.unwrap();
+        run.assert_failed_with("Approval timeout").assert_not_run("deploy");
+        assert_eq!(run.approvals[0].status, ApprovalStatus::Timeout);
+    }
+
+    #[tokio::test]
+    async fn test_delays_run_into_step_timeouts() {
+        let workflow = WorkflowDefinition::new("Slow", "A step that hangs")
+            .add_step(custom("probe", json!({})).with_timeout(1).with_fail_on_error(false))
+            .add_step(custom("report", json!({})));
+
+        let run = WorkflowTestHarness::new(workflow)
+            .mock_step("probe", MockStep::succeed().with_delay(Duration::from_secs(5)))
+            .run()
+            .await
+            .unwrap();
+
+        run.assert_completed()
+            .assert_step("probe", StepState::Failed)
+            .assert_step("report", StepState::Completed);
+        assert_eq!(
+            run.state.step_results["probe"].cancellation,
+            Some(CancellationReason::Timeout { after_secs: 1 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_timeout_cancels_the_workflow() {
+        let workflow = WorkflowDefinition::new("Stuck", "Never finishes").add_step(custom("hang", json!({})));
+        let err = WorkflowTestHarness::new(workflow)
+            .mock_step("hang", MockStep::succeed().with_delay(Duration::from_secs(60)))
+            .with_timeout(Duration::from_millis(200))
+            .run()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, WorkflowError::Timeout(_)));
+    }
+}
@@ -1,13 +1,30 @@
 //! Event-driven workflow triggers
 //!
 //! Provides event-based workflow triggering with pattern matching and filtering.
-
-use crate::{engine::{WorkflowEngine, WorkflowDefinition}, Result, WorkflowError};
+//!
+//! Webhook senders retry and NATS redelivers, so the same event can arrive
+//! more than once. [`TriggerManager`] remembers the IDs of the events it
+//! processed for a [`DedupConfig::window`], and optionally a hash of their
+//! content, in a [`DedupStore`]; a redelivery within the window starts no
+//! runs. Replicas sharing a Redis-backed store deduplicate across the
+//! cluster.
+
+use crate::{
+    engine::{WorkflowEngine, WorkflowDefinition},
+    expr::{self, Bindings, Expr, ExpressionError, Segment, TypeEnv, ValueType},
+    leadership::LeaderElector,
+    versioning::VersionManager,
+    Result, WorkflowError,
+};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use copilot_core::{AppResult, DedupStore};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, error, info, warn};
 
@@ -83,6 +100,42 @@ impl TriggerEvent {
         self.correlation_id = Some(correlation_id.to_string());
         self
     }
+
+    /// SHA-256 of the event's type, source, tenant and payload, the same
+    /// for redeliveries that were given a new ID
+    pub fn content_hash(&self) -> String {
+        let content = serde_json::json!({
+            "type": self.event_type,
+            "source": self.source,
+            "tenant_id": self.tenant_id,
+            "payload": self.payload,
+        });
+        format!("{:x}", Sha256::digest(content.to_string().as_bytes()))
+    }
+
+    /// Bindings for trigger expressions, which see the event as `event`
+    pub fn bindings(&self) -> Bindings {
+        let source = match &self.source {
+            EventSource::Custom(name) => name.clone(),
+            other => serde_json::to_value(other)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default(),
+        };
+        Bindings::new().with(
+            "event",
+            serde_json::json!({
+                "id": self.id,
+                "type": self.event_type,
+                "source": source,
+                "payload": self.payload,
+                "metadata": self.metadata,
+                "tenant_id": self.tenant_id,
+                "correlation_id": self.correlation_id,
+                "timestamp": self.timestamp.to_rfc3339(),
+            }),
+        )
+    }
 }
 
 /// Condition for trigger matching
@@ -109,6 +162,9 @@ pub enum TriggerCondition {
     Not { condition: Box<TriggerCondition> },
     /// Match using regex on event type
     EventTypePattern { pattern: String },
+    /// Match a boolean expression over `event`, e.g.
+    /// `event.type.startsWith('git.') && event.payload.ref == 'refs/heads/main'`
+    Expression { expression: String },
 }
 
 impl TriggerCondition {
@@ -118,12 +174,12 @@ impl TriggerCondition {
             TriggerCondition::EventType { value } => event.event_type == *value,
             TriggerCondition::EventSource { value } => &event.source == value,
             TriggerCondition::PayloadField { path, value } => {
-                get_json_path(&event.payload, path)
-                    .map(|v| v == value)
+                payload_field(&event.payload, path)
+                    .map(|v| v == *value)
                     .unwrap_or(false)
             }
             TriggerCondition::PayloadFieldExists { path } => {
-                get_json_path(&event.payload, path).is_some()
+                payload_field(&event.payload, path).is_some()
             }
             TriggerCondition::Metadata { key, value } => {
                 event.metadata.get(key).map(|v| v == value).unwrap_or(false)
@@ -139,29 +195,54 @@ impl TriggerCondition {
             }
             TriggerCondition::Not { condition } => !condition.matches(event),
             TriggerCondition::EventTypePattern { pattern } => {
-                regex::Regex::new(pattern)
+                expr::compile_regex(pattern)
                     .map(|re| re.is_match(&event.event_type))
                     .unwrap_or(false)
             }
+            TriggerCondition::Expression { expression } => {
+                match Expr::parse(expression).and_then(|e| e.evaluate_condition(&event.bindings())) {
+                    Ok(matched) => matched,
+                    Err(e) => {
+                        debug!(event_id = %event.id, error = %e, "Trigger expression did not evaluate");
+                        false
+                    }
+                }
+            }
         }
     }
-}
-
-/// Get value at JSON path (simplified dot notation)
-fn get_json_path<'a>(value: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
-    let parts: Vec<&str> = path.split('.').collect();
-    let mut current = value;
 
-    for part in parts {
-        // Handle array index
-        if let Ok(index) = part.parse::<usize>() {
-            current = current.get(index)?;
-        } else {
-            current = current.get(part)?;
+    /// Check that expressions type-check as conditions and patterns compile
+    pub fn validate(&self) -> std::result::Result<(), ExpressionError> {
+        match self {
+            TriggerCondition::All { conditions } | TriggerCondition::Any { conditions } => {
+                conditions.iter().try_for_each(TriggerCondition::validate)
+            }
+            TriggerCondition::Not { condition } => condition.validate(),
+            TriggerCondition::EventTypePattern { pattern } => expr::compile_regex(pattern)
+                .map(|_| ())
+                .map_err(|e| ExpressionError::Syntax {
+                    expression: pattern.clone(),
+                    reason: e.to_string(),
+                }),
+            TriggerCondition::Expression { expression } => {
+                let types = TypeEnv::new().with("event", ValueType::Map);
+                Expr::parse(expression)?.check_condition(&types)
+            }
+            _ => Ok(()),
         }
     }
+}
 
-    Some(current)
+/// Value at a dotted payload path such as `order.items.0.id`
+fn payload_field(payload: &serde_json::Value, path: &str) -> Option<serde_json::Value> {
+    let segments: Vec<Segment> = path
+        .split('.')
+        .map(|part| match part.parse::<usize>() {
+            Ok(index) => Segment::Index(index),
+            Err(_) => Segment::Key(part.to_string()),
+        })
+        .collect();
+    expr::traverse(path, "payload".to_string(), payload, &segments).ok()
 }
 
 /// Workflow trigger configuration
@@ -258,12 +339,12 @@ impl WorkflowTrigger {
 
         // Apply input mappings
         for (event_path, input_name) in &self.input_mapping {
-            if let Some(value) = get_json_path(&event.payload, event_path) {
+            if let Some(value) = payload_field(&event.payload, event_path) {
                 if let serde_json::Value::Object(ref mut map) = input {
-                    map.insert(input_name.clone(), value.clone());
+                    map.insert(input_name.clone(), value);
                 } else {
                     let mut map = serde_json::Map::new();
-                    map.insert(input_name.clone(), value.clone());
+                    map.insert(input_name.clone(), value);
                     input = serde_json::Value::Object(map);
                 }
             }
@@ -373,6 +454,63 @@ impl TriggerRepository for InMemoryTriggerRepository {
     }
 }
 
+/// How redelivered events are recognized
+#[derive(Debug, Clone)]
+pub struct DedupConfig {
+    /// How long an event is remembered after its first delivery
+    pub window: Duration,
+    /// Also drop events with the same content as one seen in the window,
+    /// for senders that give each retry a new ID
+    pub content_hash: bool,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60 * 60),
+            content_hash: false,
+        }
+    }
+}
+
+/// In-memory dedup store for single-process deployments and testing
+pub struct InMemoryDedupStore {
+    keys: RwLock<HashMap<String, Instant>>,
+}
+
+impl InMemoryDedupStore {
+    pub fn new() -> Self {
+        Self {
+            keys: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for InMemoryDedupStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl DedupStore for InMemoryDedupStore {
+    async fn record(&self, key: &str, ttl: Duration) -> AppResult<bool> {
+        let mut keys = self.keys.write().await;
+        let now = Instant::now();
+        keys.retain(|_, expires| *expires > now);
+        if keys.contains_key(key) {
+            return Ok(false);
+        }
+        keys.insert(key.to_string(), now + ttl);
+        Ok(true)
+    }
+
+    async fn forget(&self, key: &str) -> AppResult<()> {
+        self.keys.write().await.remove(key);
+        Ok(())
+    }
+}
+
 /// Rate limiter state
 struct RateLimiterState {
     executions: Vec<DateTime<Utc>>,
@@ -390,7 +528,11 @@ pub struct TriggerManager {
     repository: Arc<dyn TriggerRepository>,
     engine: Arc<WorkflowEngine>,
     provider: Arc<dyn TriggerWorkflowProvider>,
+    versions: Option<Arc<VersionManager>>,
     rate_limiter: RwLock<HashMap<String, RateLimiterState>>,
+    dedup: Arc<dyn DedupStore>,
+    dedup_config: DedupConfig,
+    duplicates: AtomicU64,
 }
 
 impl TriggerManager {
@@ -403,12 +545,40 @@ impl TriggerManager {
             repository,
             engine,
             provider,
+            versions: None,
             rate_limiter: RwLock::new(HashMap::new()),
+            dedup: Arc::new(InMemoryDedupStore::new()),
+            dedup_config: DedupConfig::default(),
+            duplicates: AtomicU64::new(0),
         }
     }
 
+    /// Remember processed events in `store`, e.g. Redis shared by all
+    /// replicas, instead of this process's memory
+    pub fn with_dedup(mut self, store: Arc<dyn DedupStore>, config: DedupConfig) -> Self {
+        self.dedup = store;
+        self.dedup_config = config;
+        self
+    }
+
+    /// Events dropped as redeliveries
+    pub fn duplicate_events(&self) -> u64 {
+        self.duplicates.load(Ordering::Relaxed)
+    }
+
+    /// Run the version `versions` routes to, so canaries get their share of
+    /// triggered runs; workflows without versions still come from the
+    /// provider
+    pub fn with_versions(mut self, versions: Arc<VersionManager>) -> Self {
+        self.versions = Some(versions);
+        self
+    }
+
     /// Create a new trigger
     pub async fn create(&self, trigger: WorkflowTrigger) -> Result<WorkflowTrigger> {
+        trigger.condition.validate().map_err(|e| {
+            WorkflowError::InvalidDefinition(format!("Trigger {}: {}", trigger.name, e))
+        })?;
         self.repository.save(&trigger).await?;
 
         info!(
@@ -452,8 +622,29 @@ impl TriggerManager {
     }
 
     /// Process an event and trigger matching workflows
+    ///
+    /// A redelivery of an event processed within the dedup window triggers
+    /// nothing.
     pub async fn process_event(&self, event: TriggerEvent) -> Result<Vec<String>> {
-        let triggers = self.repository.list_enabled().await?;
+        let dedup_keys = self.dedup_keys(&event);
+        if !self.first_delivery(&event, &dedup_keys).await {
+            self.duplicates.fetch_add(1, Ordering::Relaxed);
+            info!(event_id = %event.id, event_type = %event.event_type, "Dropped redelivered trigger event");
+            return Ok(Vec::new());
+        }
+
+        let triggers = match self.repository.list_enabled().await {
+            Ok(triggers) => triggers,
+            Err(e) => {
+                // Nothing ran, so let a redelivery try again
+                for key in &dedup_keys {
+                    if let Err(e) = self.dedup.forget(key).await {
+                        warn!(key = %key, error = %e, "Failed to forget trigger event");
+                    }
+                }
+                return Err(e);
+            }
+        };
         let mut triggered_workflows = Vec::new();
 
         debug!(
@@ -494,7 +685,26 @@ impl TriggerManager {
             let _input = trigger.build_input(&event);
 
             // Get workflow definition
-            let definition = match self.provider.get_workflow(&trigger.workflow_id).await {
+            let version = match &self.versions {
+                Some(versions) => match versions.route(&trigger.workflow_id).await {
+                    Ok(version) => version,
+                    Err(e) => {
+                        error!(
+                            trigger_id = %trigger.id,
+                            error = %e,
+                            "Failed to route workflow version"
+                        );
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            let version_id = version.as_ref().map(|v| v.id.clone());
+            let definition = match version {
+                Some(version) => Ok(Some(version.definition)),
+                None => self.provider.get_workflow(&trigger.workflow_id).await,
+            };
+            let definition = match definition {
                 Ok(Some(def)) => def,
                 Ok(None) => {
                     error!(
@@ -521,8 +731,12 @@ impl TriggerManager {
                         workflow_id = %trigger.workflow_id,
                         run_id = %run_id,
                         event_id = %event.id,
+                        version_id = version_id.as_deref().unwrap_or("-"),
                         "Triggered workflow from event"
                     );
+                    if let (Some(versions), Some(version_id)) = (&self.versions, &version_id) {
+                        versions.track_run(&run_id, version_id).await;
+                    }
                     triggered_workflows.push(run_id);
 
                     // Record execution for rate limiting
@@ -543,6 +757,34 @@ impl TriggerManager {
         Ok(triggered_workflows)
     }
 
+    /// Keys an event is remembered under: its ID and, if configured, its
+    /// content, both scoped to its tenant
+    fn dedup_keys(&self, event: &TriggerEvent) -> Vec<String> {
+        let tenant = event.tenant_id.as_deref().unwrap_or("-");
+        let mut keys = vec![format!("trigger-event:{}:{}", tenant, event.id)];
+        if self.dedup_config.content_hash {
+            keys.push(format!("trigger-content:{}:{}", tenant, event.content_hash()));
+        }
+        keys
+    }
+
+    /// Record the event's keys; false if any was already recorded
+    ///
+    /// An unreachable store lets the event through: a possible duplicate
+    /// run is better than a lost one.
+    async fn first_delivery(&self, event: &TriggerEvent, keys: &[String]) -> bool {
+        let mut first = true;
+        for key in keys {
+            match self.dedup.record(key, self.dedup_config.window).await {
+                Ok(recorded) => first &= recorded,
+                Err(e) => {
+                    warn!(event_id = %event.id, error = %e, "Dedup store unavailable, processing event");
+                }
+            }
+        }
+        first
+    }
+
     /// Check if execution is within rate limit
     async fn check_rate_limit(&self, trigger_id: &str, rate_limit: &RateLimit) -> bool {
         let mut limiter = self.rate_limiter.write().await;
@@ -580,7 +822,10 @@ impl EventBus {
         let (sender, receiver) = mpsc::channel(buffer_size);
         (
             Self { sender },
-            EventBusProcessor { receiver },
+            EventBusProcessor {
+                receiver,
+                leader: None,
+            },
         )
     }
 
@@ -604,9 +849,19 @@ impl EventBus {
 /// Event bus processor
 pub struct EventBusProcessor {
     receiver: mpsc::Receiver<TriggerEvent>,
+    leader: Option<Arc<LeaderElector>>,
 }
 
 impl EventBusProcessor {
+    /// Only process events while this replica holds the trigger lease
+    ///
+    /// Use this when every replica receives the same event stream, so each
+    /// event fires its triggers exactly once across the cluster.
+    pub fn with_leader_election(mut self, leader: Arc<LeaderElector>) -> Self {
+        self.leader = Some(leader);
+        self
+    }
+
     /// Run the processor with a trigger manager
     pub async fn run(mut self, manager: Arc<TriggerManager>) {
         info!("Starting event bus processor");
@@ -618,6 +873,11 @@ impl EventBusProcessor {
                 "Processing event from bus"
             );
 
+            if self.leader.as_ref().is_some_and(|leader| !leader.is_leader()) {
+                debug!(event_id = %event.id, "Not the trigger leader, skipping event");
+                continue;
+            }
+
             if let Err(e) = manager.process_event(event).await {
                 error!(error = %e, "Error processing event");
             }
@@ -719,6 +979,84 @@ mod tests {
         assert!(condition.matches(&event));
     }
 
+    #[test]
+    fn test_expression_condition() {
+        let event = TriggerEvent::new(
+            "git.push",
+            EventSource::Webhook,
+            serde_json::json!({"ref": "refs/heads/main", "commits": [{"id": "a1"}, {"id": "b2"}]}),
+        )
+        .with_metadata("repo", "checkout");
+
+        let matches = |expression: &str| {
+            TriggerCondition::Expression { expression: expression.to_string() }.matches(&event)
+        };
+        assert!(matches("event.type.startsWith('git.') && event.payload.ref == 'refs/heads/main'"));
+        assert!(matches("event.source == 'webhook' && size(event.payload.commits) > 1"));
+        assert!(matches("event.metadata.repo in ['checkout', 'cart'] && !has(event.payload.forced)"));
+        assert!(!matches("event.payload.commits[0].id == 'b2'"));
+        // Runtime errors do not match rather than fail the event
+        assert!(!matches("event.payload.missing == 'x'"));
+        assert!(!matches("event.payload.ref + 1 == 2"));
+    }
+
+    #[test]
+    fn test_condition_validation() {
+        let valid = TriggerCondition::All {
+            conditions: vec![
+                TriggerCondition::EventTypePattern { pattern: "^git\\.".to_string() },
+                TriggerCondition::Expression { expression: "event.payload.ref.endsWith('/main')".to_string() },
+            ],
+        };
+        assert!(valid.validate().is_ok());
+
+        let invalid = [
+            TriggerCondition::Expression { expression: "event.type ==".to_string() },
+            TriggerCondition::Expression { expression: "payload.ref == 'main'".to_string() },
+            TriggerCondition::Expression { expression: "event.type + 'x'".to_string() },
+            TriggerCondition::Not {
+                condition: Box::new(TriggerCondition::EventTypePattern { pattern: "(".to_string() }),
+            },
+        ];
+        for condition in invalid {
+            assert!(condition.validate().is_err(), "{:?}", condition);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_rejects_invalid_conditions() {
+        struct NoWorkflows;
+
+        #[async_trait]
+        impl TriggerWorkflowProvider for NoWorkflows {
+            async fn get_workflow(&self, _workflow_id: &str) -> Result<Option<WorkflowDefinition>> {
+                Ok(None)
+            }
+        }
+
+        let manager = TriggerManager::new(
+            Arc::new(InMemoryTriggerRepository::new()),
+            Arc::new(WorkflowEngine::new()),
+            Arc::new(NoWorkflows),
+        );
+        let trigger = WorkflowTrigger::new(
+            "Broken",
+            "wf-1",
+            TriggerCondition::Expression { expression: "event.typ == 'push'".to_string() },
+        );
+        // `event` is a map, so a misspelt field is only caught at match time
+        assert!(manager.create(trigger).await.is_ok());
+
+        let trigger = WorkflowTrigger::new(
+            "Broken",
+            "wf-1",
+            TriggerCondition::Expression { expression: "evnt.type == 'push'".to_string() },
+        );
+        let err = manager.create(trigger).await.unwrap_err();
+        assert!(matches!(err, WorkflowError::InvalidDefinition(_)));
+        assert!(err.to_string().contains("unknown name evnt"));
+    }
+
     #[test]
     fn test_input_mapping() {
         let trigger = WorkflowTrigger::new(
@@ -748,6 +1086,60 @@ mod tests {
         assert!(input["_event_id"].is_string());
     }
 
+    #[tokio::test]
+    async fn test_redelivered_events_start_no_runs() {
+        use crate::{StepAction, StepType, WorkflowStep};
+
+        struct OneWorkflow;
+
+        #[async_trait]
+        impl TriggerWorkflowProvider for OneWorkflow {
+            async fn get_workflow(&self, _workflow_id: &str) -> Result<Option<WorkflowDefinition>> {
+                let wait = WorkflowStep::new("wait", StepType::Action, StepAction::Wait { duration_secs: 0 });
+                Ok(Some(WorkflowDefinition::new("Deploy", "Deploy on push").add_step(wait.with_id("wait"))))
+            }
+        }
+
+        let store = Arc::new(InMemoryDedupStore::new());
+        let manager = TriggerManager::new(
+            Arc::new(InMemoryTriggerRepository::new()),
+            Arc::new(WorkflowEngine::new()),
+            Arc::new(OneWorkflow),
+        )
+        .with_dedup(
+            store.clone(),
+            DedupConfig {
+                content_hash: true,
+                ..Default::default()
+            },
+        );
+        manager
+            .create(WorkflowTrigger::new(
+                "Deploy",
+                "wf-1",
+                TriggerCondition::EventType { value: "push".to_string() },
+            ))
+            .await
+            .unwrap();
+
+        let push = TriggerEvent::new("push", EventSource::Webhook, serde_json::json!({"sha": "abc"}));
+        assert_eq!(manager.process_event(push.clone()).await.unwrap().len(), 1);
+        // A webhook retry, then the same push delivered under a new ID
+        assert!(manager.process_event(push.clone()).await.unwrap().is_empty());
+        let resent = TriggerEvent::new("push", EventSource::Webhook, serde_json::json!({"sha": "abc"}));
+        assert!(manager.process_event(resent).await.unwrap().is_empty());
+        assert_eq!(manager.duplicate_events(), 2);
+
+        let next = TriggerEvent::new("push", EventSource::Webhook, serde_json::json!({"sha": "def"}));
+        assert_eq!(manager.process_event(next).await.unwrap().len(), 1);
+
+        // Keys expire with the window
+        assert!(store.record("key", Duration::from_millis(10)).await.unwrap());
+        assert!(!store.record("key", Duration::from_millis(10)).await.unwrap());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(store.record("key", Duration::from_millis(10)).await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_trigger_repository() {
         let repo = InMemoryTriggerRepository::new();
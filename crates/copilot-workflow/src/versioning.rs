@@ -1,8 +1,14 @@
 //! Workflow versioning and rollback
 //!
 //! Provides version control for workflow definitions with rollback capabilities.
+//!
+//! A new version can also be published as a canary: it stays inactive while a
+//! set percentage of trigger-initiated runs use it instead of the active
+//! version. Run outcomes of both are collected from the engine for
+//! comparison, and the canary is then promoted or rolled back.
 
-use crate::{engine::WorkflowDefinition, Result, WorkflowError};
+use crate::engine::{WorkflowDefinition, WorkflowEngine, WorkflowStatus};
+use crate::{Result, WorkflowError};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -42,6 +48,10 @@ pub struct WorkflowVersion {
     pub parent_version_id: Option<String>,
     /// Tags for categorization
     pub tags: Vec<String>,
+    /// Percentage of trigger-initiated runs using this version while it is
+    /// a canary
+    #[serde(default)]
+    pub canary_percent: Option<u8>,
 }
 
 impl WorkflowVersion {
@@ -65,6 +75,7 @@ impl WorkflowVersion {
             is_deprecated: false,
             parent_version_id: None,
             tags: Vec::new(),
+            canary_percent: None,
         }
     }
 
@@ -115,6 +126,82 @@ impl WorkflowVersion {
         self.tags = tags;
         self
     }
+
+    /// Whether this version is being rolled out as a canary
+    pub fn is_canary(&self) -> bool {
+        self.canary_percent.is_some() && !self.is_deprecated
+    }
+}
+
+/// Outcomes of the finished runs of one version
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VersionRunStats {
+    pub runs: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+    pub cancelled: u64,
+    /// Sum of run durations, for the mean
+    pub total_duration_ms: u64,
+}
+
+impl VersionRunStats {
+    /// Share of finished runs that succeeded, between 0 and 1
+    pub fn success_rate(&self) -> f64 {
+        if self.runs == 0 {
+            0.0
+        } else {
+            self.succeeded as f64 / self.runs as f64
+        }
+    }
+
+    /// Mean run duration in milliseconds
+    pub fn mean_duration_ms(&self) -> f64 {
+        if self.runs == 0 {
+            0.0
+        } else {
+            self.total_duration_ms as f64 / self.runs as f64
+        }
+    }
+
+    fn record(&mut self, status: WorkflowStatus, duration_ms: u64) {
+        self.runs += 1;
+        self.total_duration_ms += duration_ms;
+        match status {
+            WorkflowStatus::Completed => self.succeeded += 1,
+            WorkflowStatus::Cancelled => self.cancelled += 1,
+            _ => self.failed += 1,
+        }
+    }
+}
+
+/// Active version and canary of a workflow side by side
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanaryComparison {
+    pub workflow_id: String,
+    pub stable_version: String,
+    pub canary_version: String,
+    pub canary_percent: u8,
+    pub stable: VersionRunStats,
+    pub canary: VersionRunStats,
+}
+
+impl CanaryComparison {
+    /// Canary success rate minus stable success rate; negative when the
+    /// canary does worse
+    pub fn success_rate_delta(&self) -> f64 {
+        self.canary.success_rate() - self.stable.success_rate()
+    }
+}
+
+/// Routing counters, tracked runs and outcomes of canary rollouts
+#[derive(Debug, Default)]
+struct RolloutState {
+    /// Runs routed so far, by workflow
+    routed: HashMap<String, u64>,
+    /// Version each unfinished run uses, by execution
+    runs: HashMap<String, String>,
+    /// Outcomes by version ID
+    stats: HashMap<String, VersionRunStats>,
 }
 
 /// Version comparison result
@@ -293,11 +380,15 @@ impl VersionRepository for InMemoryVersionRepository {
 /// Workflow version manager
 pub struct VersionManager {
     repository: Arc<dyn VersionRepository>,
+    rollout: RwLock<RolloutState>,
 }
 
 impl VersionManager {
     pub fn new(repository: Arc<dyn VersionRepository>) -> Self {
-        Self { repository }
+        Self {
+            repository,
+            rollout: RwLock::new(RolloutState::default()),
+        }
     }
 
     /// Create initial version for a workflow
@@ -472,6 +563,214 @@ impl VersionManager {
             connection_changes: Vec::new(), // Simplified
         })
     }
+
+    /// Publish `definition` as a canary of the active version, used by
+    /// `percent` percent of trigger-initiated runs
+    pub async fn publish_canary(
+        &self,
+        workflow_id: &str,
+        definition: WorkflowDefinition,
+        version_type: VersionBump,
+        percent: u8,
+    ) -> Result<WorkflowVersion> {
+        check_percent(percent)?;
+        let stable = self
+            .repository
+            .get_active(workflow_id)
+            .await?
+            .ok_or_else(|| WorkflowError::NotFound(format!("No active version of workflow {}", workflow_id)))?;
+        if let Some(canary) = self.canary(workflow_id).await? {
+            return Err(WorkflowError::InvalidDefinition(format!(
+                "Workflow {} already has canary {}",
+                workflow_id, canary.version
+            )));
+        }
+
+        let mut canary = match version_type {
+            VersionBump::Major => stable.next_major(definition),
+            VersionBump::Minor => stable.next_minor(definition),
+            VersionBump::Patch => stable.next_patch(definition),
+        };
+        canary.is_active = false;
+        canary.canary_percent = Some(percent);
+        self.repository.save(&canary).await?;
+
+        info!(
+            workflow_id = %workflow_id,
+            stable = %stable.version,
+            canary = %canary.version,
+            percent = percent,
+            "Published canary workflow version"
+        );
+
+        Ok(canary)
+    }
+
+    /// Canary being rolled out for `workflow_id`
+    pub async fn canary(&self, workflow_id: &str) -> Result<Option<WorkflowVersion>> {
+        let versions = self.repository.list_versions(workflow_id).await?;
+        Ok(versions.into_iter().find(WorkflowVersion::is_canary))
+    }
+
+    /// Change the share of runs the canary of `workflow_id` gets
+    pub async fn set_canary_percent(&self, workflow_id: &str, percent: u8) -> Result<WorkflowVersion> {
+        check_percent(percent)?;
+        let mut canary = self.require_canary(workflow_id).await?;
+        canary.canary_percent = Some(percent);
+        self.repository.update(&canary).await?;
+
+        info!(
+            workflow_id = %workflow_id,
+            canary = %canary.version,
+            percent = percent,
+            "Changed canary percentage"
+        );
+
+        Ok(canary)
+    }
+
+    /// Version the next trigger-initiated run of `workflow_id` uses
+    ///
+    /// Runs are spread evenly: with a 25% canary every fourth run uses it.
+    pub async fn route(&self, workflow_id: &str) -> Result<Option<WorkflowVersion>> {
+        let Some(stable) = self.repository.get_active(workflow_id).await? else {
+            return Ok(None);
+        };
+        let Some(canary) = self.canary(workflow_id).await? else {
+            return Ok(Some(stable));
+        };
+
+        let percent = u64::from(canary.canary_percent.unwrap_or_default());
+        let mut rollout = self.rollout.write().await;
+        let routed = rollout.routed.entry(workflow_id.to_string()).or_default();
+        let use_canary = (*routed + 1) * percent / 100 > *routed * percent / 100;
+        *routed += 1;
+
+        Ok(Some(if use_canary { canary } else { stable }))
+    }
+
+    /// Count the outcome of `execution_id` towards `version_id` once it
+    /// finishes
+    pub async fn track_run(&self, execution_id: &str, version_id: &str) {
+        let mut rollout = self.rollout.write().await;
+        rollout
+            .runs
+            .insert(execution_id.to_string(), version_id.to_string());
+    }
+
+    /// Record the outcomes of tracked runs that finished on `engine`,
+    /// returning how many were recorded
+    pub async fn collect_outcomes(&self, engine: &WorkflowEngine) -> usize {
+        let runs: Vec<(String, String)> = {
+            let rollout = self.rollout.read().await;
+            rollout.runs.iter().map(|(e, v)| (e.clone(), v.clone())).collect()
+        };
+
+        let mut recorded = 0;
+        for (execution_id, version_id) in runs {
+            let state = match engine.get_status(&execution_id).await {
+                Ok(state) if state.is_terminal() => state,
+                Ok(_) => continue,
+                Err(_) => {
+                    // The engine no longer knows the run, nothing to count
+                    self.rollout.write().await.runs.remove(&execution_id);
+                    continue;
+                }
+            };
+            let duration_ms = match (state.started_at, state.completed_at) {
+                (Some(started), Some(completed)) => (completed - started).num_milliseconds().max(0) as u64,
+                _ => 0,
+            };
+
+            let mut rollout = self.rollout.write().await;
+            rollout.runs.remove(&execution_id);
+            rollout
+                .stats
+                .entry(version_id)
+                .or_default()
+                .record(state.status, duration_ms);
+            recorded += 1;
+        }
+        recorded
+    }
+
+    /// Outcomes recorded for `version_id`
+    pub async fn run_stats(&self, version_id: &str) -> VersionRunStats {
+        let rollout = self.rollout.read().await;
+        rollout.stats.get(version_id).cloned().unwrap_or_default()
+    }
+
+    /// Compare the canary of `workflow_id` with the active version
+    pub async fn compare_canary(&self, workflow_id: &str) -> Result<CanaryComparison> {
+        let canary = self.require_canary(workflow_id).await?;
+        let stable = self
+            .repository
+            .get_active(workflow_id)
+            .await?
+            .ok_or_else(|| WorkflowError::NotFound(format!("No active version of workflow {}", workflow_id)))?;
+
+        Ok(CanaryComparison {
+            workflow_id: workflow_id.to_string(),
+            stable: self.run_stats(&stable.id).await,
+            canary: self.run_stats(&canary.id).await,
+            stable_version: stable.version,
+            canary_version: canary.version,
+            canary_percent: canary.canary_percent.unwrap_or_default(),
+        })
+    }
+
+    /// Make the canary of `workflow_id` the active version for all runs
+    pub async fn promote_canary(&self, workflow_id: &str) -> Result<WorkflowVersion> {
+        let mut canary = self.require_canary(workflow_id).await?;
+        canary.canary_percent = None;
+        self.repository.update(&canary).await?;
+        self.activate(workflow_id, &canary.id).await?;
+        self.rollout.write().await.routed.remove(workflow_id);
+
+        info!(
+            workflow_id = %workflow_id,
+            version = %canary.version,
+            "Promoted canary workflow version"
+        );
+
+        canary.is_active = true;
+        Ok(canary)
+    }
+
+    /// Stop the canary of `workflow_id` and send all runs to the active
+    /// version again, returning the deprecated canary
+    pub async fn rollback_canary(&self, workflow_id: &str) -> Result<WorkflowVersion> {
+        let canary = self.require_canary(workflow_id).await?;
+        self.deprecate(&canary.id).await?;
+        self.rollout.write().await.routed.remove(workflow_id);
+
+        warn!(
+            workflow_id = %workflow_id,
+            version = %canary.version,
+            "Rolled back canary workflow version"
+        );
+
+        self.repository
+            .get(&canary.id)
+            .await?
+            .ok_or_else(|| WorkflowError::NotFound(canary.id.clone()))
+    }
+
+    async fn require_canary(&self, workflow_id: &str) -> Result<WorkflowVersion> {
+        self.canary(workflow_id)
+            .await?
+            .ok_or_else(|| WorkflowError::NotFound(format!("No canary for workflow {}", workflow_id)))
+    }
+}
+
+fn check_percent(percent: u8) -> Result<()> {
+    if percent > 100 {
+        return Err(WorkflowError::InvalidDefinition(format!(
+            "Canary percentage must be at most 100, got {}",
+            percent
+        )));
+    }
+    Ok(())
 }
 
 /// Version bump type
@@ -499,6 +798,7 @@ mod tests {
             ).with_id("step-1")],
             metadata: Default::default(),
             timeout_secs: None,
+            concurrency: None,
         }
     }
 
@@ -585,4 +885,105 @@ mod tests {
         assert_eq!(history[1].version, "1.1.0");
         assert_eq!(history[2].version, "1.0.0");
     }
+
+    #[tokio::test]
+    async fn test_canary_routing_and_rollback() {
+        let manager = VersionManager::new(Arc::new(InMemoryVersionRepository::new()));
+        let definition = create_test_definition();
+        let stable = manager.create_initial("wf-1", definition.clone()).await.unwrap();
+
+        let canary = manager
+            .publish_canary("wf-1", definition.clone(), VersionBump::Minor, 25)
+            .await
+            .unwrap();
+        assert_eq!(canary.version, "1.1.0");
+        assert!(!canary.is_active);
+        assert!(manager
+            .publish_canary("wf-1", definition, VersionBump::Minor, 10)
+            .await
+            .is_err());
+        assert!(manager.set_canary_percent("wf-1", 101).await.is_err());
+
+        let mut routed = Vec::new();
+        for _ in 0..8 {
+            routed.push(manager.route("wf-1").await.unwrap().unwrap().id);
+        }
+        assert_eq!(routed.iter().filter(|id| **id == canary.id).count(), 2);
+
+        let rolled_back = manager.rollback_canary("wf-1").await.unwrap();
+        assert!(rolled_back.is_deprecated);
+        assert!(manager.canary("wf-1").await.unwrap().is_none());
+        assert_eq!(manager.route("wf-1").await.unwrap().unwrap().id, stable.id);
+    }
+
+    #[tokio::test]
+    async fn test_triggered_canary_runs_are_compared_and_promoted() {
+        use crate::triggers::{
+            EventSource, InMemoryTriggerRepository, TriggerCondition, TriggerEvent, TriggerManager,
+            TriggerWorkflowProvider, WorkflowTrigger,
+        };
+
+        struct NoWorkflows;
+
+        #[async_trait]
+        impl TriggerWorkflowProvider for NoWorkflows {
+            async fn get_workflow(&self, _workflow_id: &str) -> Result<Option<WorkflowDefinition>> {
+                Ok(None)
+            }
+        }
+
+        let manager = Arc::new(VersionManager::new(Arc::new(InMemoryVersionRepository::new())));
+        let mut definition = create_test_definition();
+        definition.steps[0].action = StepAction::Wait { duration_secs: 0 };
+        let stable = manager.create_initial("wf-1", definition.clone()).await.unwrap();
+        let canary = manager
+            .publish_canary("wf-1", definition, VersionBump::Patch, 50)
+            .await
+            .unwrap();
+
+        let engine = Arc::new(WorkflowEngine::new());
+        let triggers = TriggerManager::new(
+            Arc::new(InMemoryTriggerRepository::new()),
+            engine.clone(),
+            Arc::new(NoWorkflows),
+        )
+        .with_versions(manager.clone());
+        triggers
+            .create(WorkflowTrigger::new(
+                "Deploy",
+                "wf-1",
+                TriggerCondition::EventType { value: "push".to_string() },
+            ))
+            .await
+            .unwrap();
+        for _ in 0..4 {
+            let runs = triggers
+                .process_event(TriggerEvent::new("push", EventSource::Webhook, serde_json::json!({})))
+                .await
+                .unwrap();
+            assert_eq!(runs.len(), 1);
+        }
+
+        let mut recorded = 0;
+        for _ in 0..50 {
+            recorded += manager.collect_outcomes(&engine).await;
+            if recorded == 4 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(recorded, 4);
+
+        let comparison = manager.compare_canary("wf-1").await.unwrap();
+        assert_eq!(comparison.stable_version, stable.version);
+        assert_eq!(comparison.canary_version, "1.0.1");
+        assert_eq!(comparison.stable.runs, 2);
+        assert_eq!(comparison.canary.succeeded, 2);
+        assert_eq!(comparison.success_rate_delta(), 0.0);
+
+        let promoted = manager.promote_canary("wf-1").await.unwrap();
+        assert_eq!(promoted.id, canary.id);
+        assert_eq!(manager.route("wf-1").await.unwrap().unwrap().id, canary.id);
+        assert!(manager.compare_canary("wf-1").await.is_err());
+    }
 }
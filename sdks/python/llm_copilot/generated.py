@@ -0,0 +1,1531 @@
+"""
+Wire models generated from the Rust SDK (crates/copilot-sdk/src/models.rs).
+
+Do not edit by hand; regenerate with:
+
+    cargo run -p copilot-sdk --example generate_python
+"""
+
+from typing import Any, Optional
+from pydantic import BaseModel, Field
+
+__all__ = [
+    "FunctionCall",
+    "Usage",
+    "MessagePart",
+    "Message",
+    "ChatRequest",
+    "PreferenceKey",
+    "PreferenceSuggestion",
+    "ContextDownsize",
+    "ChatResponse",
+    "Conversation",
+    "Session",
+    "SessionMessage",
+    "WindowEntry",
+    "CapturedResponse",
+    "ReplayCapture",
+    "ReplaySummary",
+    "ContextItem",
+    "ContextSearchResult",
+    "WorkflowStep",
+    "Workflow",
+    "WorkflowSummary",
+    "WorkflowExecution",
+    "WorkflowStatus",
+    "TemplateDocs",
+    "TemplateSummary",
+    "TemplateParameter",
+    "ParameterSchema",
+    "ManualRun",
+    "RunEvent",
+    "ParameterError",
+    "WorkflowSchedule",
+    "SchedulePreview",
+    "Trust",
+    "IngestedDocument",
+    "IngestionJob",
+    "SpaceCoverage",
+    "BootstrapFailure",
+    "BootstrapReport",
+    "BootstrapJob",
+    "SafetyCategory",
+    "SafetyFinding",
+    "QuarantinedChunk",
+    "QuarantinedDocument",
+    "ClaimSupport",
+    "GroundednessScore",
+    "GroundednessReview",
+    "GroundednessStats",
+    "QueryTopic",
+    "KnowledgeGapReport",
+    "IntentMetrics",
+    "ConversationReport",
+    "Resolution",
+    "RetrievalOverrides",
+    "ExperimentVariant",
+    "ExperimentSpec",
+    "Experiment",
+    "MetricSummary",
+    "VariantResults",
+    "SignificanceTest",
+    "ExperimentResults",
+    "ResidencyViolation",
+    "ResidencyReport",
+    "CodeFinding",
+    "CodePolicyDecision",
+    "ImpersonationConsent",
+    "ImpersonationRequest",
+    "Impersonation",
+    "ImpersonationToken",
+    "ReportPeriod",
+    "AuditEventRecord",
+    "AuditEvidence",
+    "RoleEvidence",
+    "RetentionSetting",
+    "AccessReviewRequest",
+    "AccessReview",
+    "ComplianceReport",
+    "ImportFailure",
+    "UserImportReport",
+    "JsonWebKey",
+    "JsonWebKeySet",
+    "WebhookEndpoint",
+    "CreateWebhookRequest",
+    "WebhookEndpointUpdate",
+    "WebhookSecret",
+    "WebhookPingResult",
+    "WebhookEventTypeStats",
+    "WebhookStats",
+    "WebhookSchemaVersion",
+    "WebhookPayloadSchemas",
+    "BulkContextOperation",
+    "BulkContextRequest",
+    "BulkContextJob",
+    "FileUploadRequest",
+    "PresignedUrl",
+    "FileUpload",
+    "StoredObject",
+    "ExportBundle",
+    "DashboardRequest",
+    "PushedDashboard",
+    "GeneratedDashboard",
+    "AlertRuleRequest",
+    "AlertRule",
+    "AlertBacktest",
+    "GeneratedAlertRule",
+    "LogAnalysisRequest",
+    "LogPattern",
+    "LogFinding",
+    "LogAnalysisReport",
+    "Sandbox",
+    "ExecutionResult",
+    "ServiceHealth",
+    "HealthResponse",
+    "VersionInfo",
+    "TaskQueueStats",
+    "RecentError",
+    "DashboardSnapshot",
+    "DiffHunk",
+    "FileEdit",
+    "ProposedEdits",
+    "HandoffRequest",
+    "HandoffBundle",
+    "HandoffDelivery",
+    "HandoffResponse",
+    "PrefetchOutcome",
+    "PrefetchStats",
+    "EmbeddingCacheStats",
+    "EmbeddingWarmup",
+    "ContextWarmup",
+    "CheckRunContext",
+    "GateTarget",
+    "GateRequest",
+    "GateCheck",
+    "CheckOutput",
+    "GateVerdict",
+    "Verbosity",
+    "UserPreferences",
+    "PreferencesResponse",
+]
+
+
+class FunctionCall(BaseModel):
+    """Function call in a message"""
+
+    name: str
+    arguments: str
+
+
+class Usage(BaseModel):
+    """Token usage information"""
+
+    prompt_tokens: int
+    completion_tokens: int
+    total_tokens: int
+    cost_usd: float = 0.0
+
+
+class MessagePart(BaseModel):
+    """A tool call, its result or sandbox output recorded in a message"""
+
+    type: str
+    call_id: str
+    tool: Optional[str] = None
+    arguments: Optional[Any] = None
+    output: Optional[Any] = None
+    cached: bool = False
+    error: Optional[str] = None
+    exit_code: Optional[int] = None
+    stdout: Optional[str] = None
+    stderr: Optional[str] = None
+
+
+class Message(BaseModel):
+    """Chat message"""
+
+    role: str
+    content: str
+    name: Optional[str] = None
+    function_call: Optional[FunctionCall] = None
+    usage: Optional[Usage] = None
+    parts: list[MessagePart] = Field(default_factory=list)
+
+
+class ChatRequest(BaseModel):
+    """Chat request"""
+
+    message: str
+    conversation_id: Optional[str] = None
+    model: Optional[str] = None
+    system_prompt: Optional[str] = None
+    temperature: Optional[float] = None
+    max_tokens: Optional[int] = None
+    stream: bool = False
+
+
+class PreferenceKey(BaseModel):
+    """Which preference a suggestion is about"""
+
+    pass
+
+
+class PreferenceSuggestion(BaseModel):
+    """A preference learned from the user's messages, awaiting confirmation"""
+
+    id: str
+    key: PreferenceKey
+    value: str
+    evidence: str
+    suggested_at: str
+    session_id: Optional[str] = None
+
+
+class ContextDownsize(BaseModel):
+    """How a reply's retrieved context was cut to fit the model's context window"""
+
+    model: str
+    window: int
+    budget: int
+    tokens_before: int
+    tokens_after: int
+    items_dropped: int
+    retried: bool = False
+
+
+class ChatResponse(BaseModel):
+    """Chat response"""
+
+    content: str
+    conversation_id: str
+    model: Optional[str] = None
+    usage: Optional[Usage] = None
+    session_usage: Optional[Usage] = None
+    finish_reason: Optional[str] = None
+    preference_suggestions: Optional[list[PreferenceSuggestion]] = None
+    context_downsize: Optional[ContextDownsize] = None
+
+
+class Conversation(BaseModel):
+    """Conversation metadata"""
+
+    id: str
+    created_at: str
+    message_count: int
+    messages: list[Message] = Field(default_factory=list)
+    model: Optional[str] = None
+    title: Optional[str] = None
+    usage: Optional[Usage] = None
+
+
+class Session(BaseModel):
+    """Chat session"""
+
+    id: str
+    created_at: str
+    message_count: int
+    model: Optional[str] = None
+    system_prompt: Optional[str] = None
+    last_activity: Optional[str] = None
+    usage: Optional[Usage] = None
+
+
+class SessionMessage(BaseModel):
+    """A message sent in a session, optionally answered with another model"""
+
+    message: str
+    metadata: Optional[dict[str, str]] = None
+    model: Optional[str] = None
+
+
+class WindowEntry(BaseModel):
+    """An item of the context window a reply was generated from"""
+
+    id: str
+    content_type: str
+    source: str
+    score: float
+    token_count: int
+    compressed: bool
+
+
+class CapturedResponse(BaseModel):
+    """The reply recorded with a replay capture"""
+
+    content: str
+    usage: Usage
+    model: Optional[str] = None
+
+
+class ReplayCapture(BaseModel):
+    """A sanitized chat turn captured for replay"""
+
+    id: str
+    captured_at: str
+    tenant_id: str
+    session_id: str
+    request: SessionMessage
+    duration_ms: int
+    response: Optional[CapturedResponse] = None
+    error: Optional[str] = None
+    retrieval: list[WindowEntry] = Field(default_factory=list)
+
+
+class ReplaySummary(BaseModel):
+    """A replay capture as listed, without its text"""
+
+    id: str
+    captured_at: str
+    session_id: str
+    message_chars: int
+    succeeded: bool
+    duration_ms: int
+    model: Optional[str] = None
+
+
+class ContextItem(BaseModel):
+    """Context item"""
+
+    id: str
+    content: str
+    size: int
+    created_at: str
+    source: Optional[str] = None
+    tags: list[str] = Field(default_factory=list)
+
+
+class ContextSearchResult(BaseModel):
+    """Context search result"""
+
+    id: str
+    snippet: str
+    score: float
+    source: Optional[str] = None
+
+
+class WorkflowStep(BaseModel):
+    """Workflow step definition"""
+
+    id: str
+    name: str
+    type: str
+    dependencies: list[str] = Field(default_factory=list)
+    config: Optional[Any] = None
+
+
+class Workflow(BaseModel):
+    """Workflow definition"""
+
+    id: str
+    name: str
+    description: str
+    version: str
+    steps: list[WorkflowStep]
+
+
+class WorkflowSummary(BaseModel):
+    """Workflow summary for listing"""
+
+    id: str
+    name: str
+    version: str
+    step_count: int
+
+
+class WorkflowExecution(BaseModel):
+    """Workflow execution"""
+
+    id: str
+    workflow_id: str
+    status: str
+    started_at: str
+    ended_at: Optional[str] = None
+
+
+class WorkflowStatus(BaseModel):
+    """Workflow execution status"""
+
+    id: str
+    status: str
+    current_step: str
+    progress: Optional[int] = None
+    started_at: Optional[str] = None
+    ended_at: Optional[str] = None
+    error: Optional[str] = None
+    output: Optional[Any] = None
+
+
+class TemplateDocs(BaseModel):
+    """Documentation shown next to a workflow template"""
+
+    when_to_use: str
+    steps: list[str] = Field(default_factory=list)
+    prerequisites: list[str] = Field(default_factory=list)
+
+
+class TemplateSummary(BaseModel):
+    """A workflow template that can be run"""
+
+    id: str
+    name: str
+    description: str
+    category: str
+    version: str
+    tags: list[str] = Field(default_factory=list)
+    icon: Optional[str] = None
+    required_parameters: list[str] = Field(default_factory=list)
+    docs: Optional[TemplateDocs] = None
+    usage_count: int = 0
+
+
+class TemplateParameter(BaseModel):
+    """A parameter of a workflow template"""
+
+    name: str
+    label: str
+    param_type: Any
+    required: bool
+    description: Optional[str] = None
+    default_value: Optional[Any] = None
+    validation: Optional[Any] = None
+
+
+class ParameterSchema(BaseModel):
+    """Parameters a workflow template is run with"""
+
+    template_id: str
+    name: str
+    description: str
+    parameters: list[TemplateParameter]
+
+
+class ManualRun(BaseModel):
+    """A validated, and unless dry run, started template run"""
+
+    template_id: str
+    params: Any
+    execution_id: Optional[str] = None
+
+
+class RunEvent(BaseModel):
+    """Something that happened to a run, streamed while watching it"""
+
+    execution_id: str
+    status: str
+    timestamp: str
+    kind: Optional[str] = None
+    step_id: Optional[str] = None
+    attempt: Optional[int] = None
+    error: Optional[str] = None
+    output_summary: Optional[str] = None
+    running_steps: list[str] = Field(default_factory=list)
+    completed_steps: int = 0
+    failed_steps: int = 0
+
+
+class ParameterError(BaseModel):
+    """A template parameter the server rejected"""
+
+    field: str
+    message: str
+
+
+class WorkflowSchedule(BaseModel):
+    """A workflow schedule"""
+
+    id: str
+    workflow_id: str
+    schedule: Any
+    enabled: bool
+    missed_runs: Any
+    last_execution: Optional[str] = None
+    next_execution: Optional[str] = None
+    paused_at: Optional[str] = None
+
+
+class SchedulePreview(BaseModel):
+    """Upcoming fire times of a schedule"""
+
+    fire_times: list[str]
+
+
+class Trust(BaseModel):
+    """Whether ingested content was signed by a trusted source; untrusted content was unsigned, signed by an unknown source, or tampered with"""
+
+    pass
+
+
+class IngestedDocument(BaseModel):
+    """Summary of one streamed document"""
+
+    document_id: str
+    bytes_received: int
+    chunk_count: int
+    processing_time_ms: int
+    warnings: list[str] = Field(default_factory=list)
+    trust: Optional[Trust] = None
+    signer: Optional[str] = None
+
+
+class IngestionJob(BaseModel):
+    """Streaming ingestion job"""
+
+    id: str
+    status: str
+    bytes_received: int
+    chunk_count: int
+    created_at: str
+    documents: list[IngestedDocument] = Field(default_factory=list)
+    quarantined: Optional[list[str]] = None
+    error: Optional[str] = None
+    finished_at: Optional[str] = None
+
+
+class SpaceCoverage(BaseModel):
+    """How much of one wiki space a bootstrap ingested"""
+
+    space: str
+    pages: int
+    ingested: int
+    summarized: int
+    chunks: int
+
+
+class BootstrapFailure(BaseModel):
+    """A wiki page a bootstrap could not ingest"""
+
+    page: str
+    error: str
+
+
+class BootstrapReport(BaseModel):
+    """How much of a wiki export made it into the knowledge base"""
+
+    pages: int
+    ingested: int
+    empty: int
+    skipped: int
+    failed: int
+    chunks: int
+    summarized: int
+    coverage: float
+    summary_coverage: float
+    processing_time_ms: int
+    spaces: list[SpaceCoverage] = Field(default_factory=list)
+    failures: list[BootstrapFailure] = Field(default_factory=list)
+
+
+class BootstrapJob(BaseModel):
+    """Knowledge base bootstrap from a wiki export"""
+
+    id: str
+    source: str
+    format: str
+    status: str
+    total_pages: int
+    report: BootstrapReport
+    created_at: str
+    error: Optional[str] = None
+    finished_at: Optional[str] = None
+
+
+class SafetyCategory(BaseModel):
+    """What a safety finding is about"""
+
+    pass
+
+
+class SafetyFinding(BaseModel):
+    """A safety rule that matched ingested content"""
+
+    category: SafetyCategory
+    rule: str
+    excerpt: str
+
+
+class QuarantinedChunk(BaseModel):
+    """A chunk held back from the knowledge base"""
+
+    id: str
+    document_id: str
+    content: str
+    metadata: dict[str, Any] = Field(default_factory=dict)
+
+
+class QuarantinedDocument(BaseModel):
+    """An ingested document held for review"""
+
+    document_id: str
+    source: str
+    findings: list[SafetyFinding]
+    chunks: list[QuarantinedChunk]
+    quarantined_at: str
+    document: Optional[str] = None
+
+
+class ClaimSupport(BaseModel):
+    """Whether one claim of an answer is backed by its context"""
+
+    claim: str
+    supported: bool
+    source: Optional[str] = None
+
+
+class GroundednessScore(BaseModel):
+    """How well an answer is grounded in its retrieved context"""
+
+    score: float
+    claims: list[ClaimSupport]
+    scorer: str
+
+
+class GroundednessReview(BaseModel):
+    """A poorly grounded answer awaiting human review"""
+
+    id: str
+    session_id: str
+    query: str
+    answer: str
+    score: GroundednessScore
+    sources: list[str]
+    flagged_at: str
+
+
+class GroundednessStats(BaseModel):
+    """Groundedness scores of answers so far"""
+
+    answers: int
+    flagged: int
+    failures: int
+    mean_score: float
+    pending_reviews: int
+    review_threshold: float
+
+
+class QueryTopic(BaseModel):
+    """Questions about one topic and how well they were answered"""
+
+    label: str
+    terms: list[str]
+    queries: int
+    unanswered: int
+    low_confidence: int
+    uncited: int
+    mean_confidence: Optional[float] = None
+    examples: Optional[list[str]] = None
+
+
+class KnowledgeGapReport(BaseModel):
+    """Topics a tenant's users ask about and the ones the corpus does not cover"""
+
+    since: str
+    generated_at: str
+    total_queries: int
+    unanswered_queries: int
+    untopical_queries: int
+    topics: list[QueryTopic]
+    gaps: list[QueryTopic]
+    tenant_id: Optional[str] = None
+
+
+class IntentMetrics(BaseModel):
+    """What one intent's conversations came to"""
+
+    intent: str
+    conversations: int
+    turns: int
+    resolved: int
+    escalated: int
+    mean_turns_to_resolution: Optional[float] = None
+
+
+class ConversationReport(BaseModel):
+    """Intent distribution and resolution metrics of recent conversations"""
+
+    since: str
+    generated_at: str
+    conversations: int
+    turns: int
+    resolved: int
+    confirmed_resolutions: int
+    inferred_resolutions: int
+    unresolved: int
+    escalated: int
+    resolution_rate: float
+    escalation_rate: float
+    intents: list[IntentMetrics]
+    tenant_id: Optional[str] = None
+    mean_turns_to_resolution: Optional[float] = None
+
+
+class Resolution(BaseModel):
+    """Whether and when a conversation's goal was resolved"""
+
+    resolved: bool
+    source: str
+    turns: int
+
+
+class RetrievalOverrides(BaseModel):
+    """Changes an experiment variant makes to the retrieved context"""
+
+    max_items: Optional[int] = None
+    min_score: Optional[float] = None
+
+
+class ExperimentVariant(BaseModel):
+    """One arm of an A/B experiment"""
+
+    name: str
+    weight: int = 1
+    prompt_template: Optional[str] = None
+    model: Optional[str] = None
+    retrieval: Optional[RetrievalOverrides] = None
+
+
+class ExperimentSpec(BaseModel):
+    """An experiment to start"""
+
+    id: str
+    variants: list[ExperimentVariant]
+    description: str = ""
+    tenants: list[str] = Field(default_factory=list)
+
+
+class Experiment(BaseModel):
+    """A running or finished A/B experiment"""
+
+    id: str
+    description: str
+    variants: list[ExperimentVariant]
+    tenants: list[str]
+    status: str
+    created_at: str
+    stopped_at: Optional[str] = None
+
+
+class MetricSummary(BaseModel):
+    """Mean and spread of a metric over a variant's turns"""
+
+    count: int
+    mean: float
+    std_dev: float
+
+
+class VariantResults(BaseModel):
+    """What one experiment variant's turns came to"""
+
+    variant: str
+    turns: int
+    positive_feedback: int
+    negative_feedback: int
+    positive_rate: Optional[float] = None
+    latency_ms: Optional[MetricSummary] = None
+    cost_usd: Optional[MetricSummary] = None
+    groundedness: Optional[MetricSummary] = None
+
+
+class SignificanceTest(BaseModel):
+    """A variant's difference from the control on one metric"""
+
+    metric: str
+    variant: str
+    control_value: float
+    variant_value: float
+    difference: float
+    p_value: float
+    significant: bool
+
+
+class ExperimentResults(BaseModel):
+    """Per-variant metrics of an experiment and how each variant compares with the control"""
+
+    experiment: Experiment
+    variants: list[VariantResults]
+    alpha: float
+    tests: list[SignificanceTest]
+
+
+class ResidencyViolation(BaseModel):
+    """A cross-region access refused by a tenant's residency policy"""
+
+    at: str
+    tenant_id: str
+    home_region: str
+    data_region: str
+    operation: str
+    resource: str
+
+
+class ResidencyReport(BaseModel):
+    """A tenant's home region and the cross-region access refused for it"""
+
+    tenant_id: str
+    region: str
+    mode: str
+    violations: int
+    by_data_region: dict[str, int]
+    by_operation: dict[str, int]
+    recent: list[ResidencyViolation]
+
+
+class CodeFinding(BaseModel):
+    """A breach of a tenant's code policy in one code block of a response"""
+
+    kind: str
+    block: int
+    detail: str
+    similarity: Optional[float] = None
+
+
+class CodePolicyDecision(BaseModel):
+    """A response annotated or blocked by the tenant's code policy"""
+
+    session_id: str
+    outcome: str
+    findings: list[CodeFinding]
+    decided_at: str
+    tenant_id: Optional[str] = None
+
+
+class ImpersonationConsent(BaseModel):
+    """A user's consent to be impersonated by admins of their tenant"""
+
+    tenant_id: str
+    user_id: str
+    granted_at: str
+    expires_at: str
+
+
+class ImpersonationRequest(BaseModel):
+    """An admin's request to act as a consenting user"""
+
+    user_id: str
+    reason: str
+    minutes: Optional[int] = None
+
+
+class Impersonation(BaseModel):
+    """An impersonation of a user by an admin"""
+
+    id: str
+    tenant_id: str
+    admin: str
+    user_id: str
+    reason: str
+    started_at: str
+    expires_at: str
+    actions: int
+    ended_at: Optional[str] = None
+
+
+class ImpersonationToken(BaseModel):
+    """A started impersonation and the token to act as the user with"""
+
+    token: str
+    impersonation: Impersonation
+
+
+class ReportPeriod(BaseModel):
+    """The span of time a compliance report covers"""
+
+    label: str
+    start: str
+    end: str
+
+
+class AuditEventRecord(BaseModel):
+    """An entry of the audit trail"""
+
+    id: str
+    event_type: str
+    severity: str
+    timestamp: str
+    action: str
+    outcome: str
+    actor_id: Optional[str] = None
+    resource_type: Optional[str] = None
+    resource_id: Optional[str] = None
+    description: Optional[str] = None
+
+
+class AuditEvidence(BaseModel):
+    """Summary of the audit trail over a report's period"""
+
+    events: int
+    by_type: dict[str, int]
+    by_severity: dict[str, int]
+    failures: int
+    notable: list[AuditEventRecord]
+    truncated: bool
+
+
+class RoleEvidence(BaseModel):
+    """A role and the permissions it grants"""
+
+    role: str
+    level: int
+    permissions: list[str]
+
+
+class RetentionSetting(BaseModel):
+    """How long one kind of data is kept"""
+
+    name: str
+    value: str
+    description: str
+
+
+class AccessReviewRequest(BaseModel):
+    """Request to record a review of a user's access"""
+
+    subject: str
+    decision: str
+    roles: list[str] = Field(default_factory=list)
+    note: Optional[str] = None
+
+
+class AccessReview(BaseModel):
+    """A reviewer's sign-off on one user's access"""
+
+    id: str
+    tenant_id: str
+    reviewer: str
+    subject: str
+    roles: list[str]
+    decision: str
+    reviewed_at: str
+    note: Optional[str] = None
+
+
+class ComplianceReport(BaseModel):
+    """Evidence of a tenant's controls over a period, for SOC 2 style audits"""
+
+    tenant_id: str
+    period: ReportPeriod
+    generated_at: str
+    generated_by: str
+    audit: AuditEvidence
+    rbac: list[RoleEvidence]
+    retention: list[RetentionSetting]
+    access_reviews: list[AccessReview]
+    exceptions: list[str]
+
+
+class ImportFailure(BaseModel):
+    """A CSV row of a user import that failed"""
+
+    line: int
+    error: str
+    username: Optional[str] = None
+
+
+class UserImportReport(BaseModel):
+    """Outcome of a CSV user import"""
+
+    created: int
+    updated: int
+    unchanged: int
+    failures: list[ImportFailure]
+
+
+class JsonWebKey(BaseModel):
+    """A public key that verifies tokens the server signs (RFC 7517)"""
+
+    kty: str
+    kid: Optional[str] = None
+    alg: Optional[str] = None
+    use: Optional[str] = None
+    n: Optional[str] = None
+    e: Optional[str] = None
+    crv: Optional[str] = None
+    x: Optional[str] = None
+
+
+class JsonWebKeySet(BaseModel):
+    """The keys that verify tokens the server signs; empty unless the server signs with rotating keys"""
+
+    keys: list[JsonWebKey]
+
+
+class WebhookEndpoint(BaseModel):
+    """A webhook endpoint, without its secrets or header values"""
+
+    id: str
+    name: str
+    url: str
+    events: list[str]
+    enabled: bool
+    header_names: list[str]
+    created_at: str
+    updated_at: str
+    schema_version: Optional[int] = None
+    previous_secret_expires_at: Optional[str] = None
+
+
+class CreateWebhookRequest(BaseModel):
+    """Request to register a webhook endpoint"""
+
+    name: str
+    url: str
+    events: list[str] = Field(default_factory=list)
+    headers: list[list[Any]] = Field(default_factory=list)
+    schema_version: Optional[int] = None
+
+
+class WebhookEndpointUpdate(BaseModel):
+    """Changes to a webhook endpoint; omitted fields are left unchanged"""
+
+    name: Optional[str] = None
+    url: Optional[str] = None
+    events: Optional[list[str]] = None
+    enabled: Optional[bool] = None
+    headers: Optional[list[list[Any]]] = None
+    schema_version: Optional[int] = None
+
+
+class WebhookSecret(BaseModel):
+    """A webhook endpoint's signing secret, only shown when it is created or rotated"""
+
+    endpoint: WebhookEndpoint
+    secret: str
+
+
+class WebhookPingResult(BaseModel):
+    """Outcome of a test delivery to a webhook endpoint"""
+
+    endpoint_id: str
+    delivered: bool
+    duration_ms: int
+    status_code: Optional[int] = None
+    error: Optional[str] = None
+
+
+class WebhookEventTypeStats(BaseModel):
+    """Deliveries of one event type to a webhook endpoint"""
+
+    event_type: str
+    count: int
+    success_rate: float
+
+
+class WebhookStats(BaseModel):
+    """Delivery success and latency of a webhook endpoint"""
+
+    endpoint_id: str
+    total: int
+    delivered: int
+    failed: int
+    success_rate: float
+    avg_latency_ms: float
+    p95_latency_ms: float
+    by_event_type: list[WebhookEventTypeStats]
+    last_delivery_at: Optional[str] = None
+    last_status_code: Optional[int] = None
+    last_error: Optional[str] = None
+
+
+class WebhookSchemaVersion(BaseModel):
+    """A published webhook payload schema version"""
+
+    version: int
+    description: str
+    changed_objects: list[str]
+    current: bool
+
+
+class WebhookPayloadSchemas(BaseModel):
+    """JSON Schemas of the webhook envelope (`event`) and every payload object in one version"""
+
+    version: int
+    schemas: dict[str, Any]
+
+
+class BulkContextOperation(BaseModel):
+    """What a bulk context job does with each matching item"""
+
+    pass
+
+
+class BulkContextRequest(BaseModel):
+    """A bulk operation over the context items matching `filter`"""
+
+    filter: Any = None
+
+
+class BulkContextJob(BaseModel):
+    """Bulk context job and its progress"""
+
+    id: str
+    operation: str
+    status: str
+    matched: int
+    processed: int
+    failed: int
+    created_at: str
+    error: Optional[str] = None
+    finished_at: Optional[str] = None
+
+
+class FileUploadRequest(BaseModel):
+    """Request for a URL to upload a file to object storage"""
+
+    class_: str = Field(alias="class")
+    sha256: str
+    content_type: str
+
+
+class PresignedUrl(BaseModel):
+    """A URL granting one request on a stored object until it expires"""
+
+    url: str
+    method: str
+    expires_at: str
+    headers: Optional[list[list[Any]]] = None
+
+
+class FileUpload(BaseModel):
+    """Where to upload a file, unless the same content is already stored"""
+
+    key: str
+    sha256: str
+    already_stored: bool
+    upload: Optional[PresignedUrl] = None
+
+
+class StoredObject(BaseModel):
+    """An object kept in object storage under its content digest"""
+
+    key: str
+    class_: str = Field(alias="class")
+    sha256: str
+    size: int
+    content_type: str
+    deduplicated: bool
+
+
+class ExportBundle(BaseModel):
+    """An export bundle kept in object storage"""
+
+    object: StoredObject
+    download: PresignedUrl
+
+
+class DashboardRequest(BaseModel):
+    """Request to generate a Grafana dashboard from a natural language ask"""
+
+    ask: str
+    push: bool = False
+    folder_uid: Optional[str] = None
+
+
+class PushedDashboard(BaseModel):
+    """Where Grafana saved a pushed dashboard"""
+
+    uid: str
+    url: str
+    version: int
+    status: str
+
+
+class GeneratedDashboard(BaseModel):
+    """A generated Grafana dashboard"""
+
+    dashboard: Any
+    pushed: Optional[PushedDashboard] = None
+
+
+class AlertRuleRequest(BaseModel):
+    """Request to generate an alert rule from a natural language description"""
+
+    description: str
+    backtest: Optional[bool] = None
+    lookback_hours: Optional[int] = None
+
+
+class AlertRule(BaseModel):
+    """A Prometheus-format alerting rule"""
+
+    alert: str
+    expr: str
+    for_: str = Field(alias="for")
+    labels: dict[str, str] = Field(default_factory=dict)
+    annotations: dict[str, str] = Field(default_factory=dict)
+
+
+class AlertBacktest(BaseModel):
+    """How often an alert rule would have fired over a past window"""
+
+    window_secs: int
+    step_secs: int
+    alerting_series: int
+    firings: int
+    firings_per_day: float
+    firing_fraction: float
+    last_fired_at: Optional[int] = None
+
+
+class GeneratedAlertRule(BaseModel):
+    """A generated alert rule, to review before applying it"""
+
+    language: str
+    rule: AlertRule
+    backtest: Optional[AlertBacktest] = None
+    backtest_error: Optional[str] = None
+
+
+class LogAnalysisRequest(BaseModel):
+    """Request to cluster and summarize log lines"""
+
+    logs: str
+    top_patterns: Optional[int] = None
+    summarize: Optional[bool] = None
+
+
+class LogPattern(BaseModel):
+    """A log template and the lines that matched it"""
+
+    template: str
+    count: int
+    share: float
+    first_line: int
+    last_line: int
+    example: str
+    level: Optional[str] = None
+
+
+class LogFinding(BaseModel):
+    """A log pattern flagged as an anomaly"""
+
+    kind: str
+    template: str
+    count: int
+    detail: str
+
+
+class LogAnalysisReport(BaseModel):
+    """Log patterns, anomalies and their summary"""
+
+    total_lines: int
+    pattern_count: int
+    patterns: list[LogPattern]
+    findings: list[LogFinding]
+    summary: str
+
+
+class Sandbox(BaseModel):
+    """Sandbox information"""
+
+    id: str
+    template: str
+    status: str
+    created_at: str
+    last_activity: Optional[str] = None
+
+
+class ExecutionResult(BaseModel):
+    """Code execution result"""
+
+    success: bool
+    stdout: str
+    stderr: str
+    exit_code: int
+    duration_ms: int
+
+
+class ServiceHealth(BaseModel):
+    """Individual service health"""
+
+    status: str
+    latency_ms: Optional[int] = None
+    message: Optional[str] = None
+
+
+class HealthResponse(BaseModel):
+    """Health check response"""
+
+    status: str
+    version: str
+    uptime: Optional[int] = None
+    services: dict[str, ServiceHealth] = Field(default_factory=dict)
+
+
+class VersionInfo(BaseModel):
+    """Version information"""
+
+    version: str
+    git_commit: Optional[str] = None
+    build_time: Optional[str] = None
+    rust_version: Optional[str] = None
+
+
+class TaskQueueStats(BaseModel):
+    """Task queue counts reported by the dashboard"""
+
+    queued: int
+    running: int
+    completed: int
+    failed: int
+    cancelled: int
+    queued_by_priority: dict[str, int] = Field(default_factory=dict)
+
+
+class RecentError(BaseModel):
+    """A request that failed with a server error"""
+
+    timestamp: str
+    method: str
+    path: str
+    status: int
+    duration_ms: int
+
+
+class DashboardSnapshot(BaseModel):
+    """Live server activity, as shown by `copilot top`"""
+
+    timestamp: str
+    uptime_secs: int
+    requests_total: int
+    errors_total: int
+    in_flight: int
+    requests_per_sec: float
+    tasks: TaskQueueStats
+    request_rate_history: list[int] = Field(default_factory=list)
+    gauges: dict[str, float] = Field(default_factory=dict)
+    recent_errors: list[RecentError] = Field(default_factory=list)
+
+
+class DiffHunk(BaseModel):
+    """One `@@ -a,b +c,d @@` section of a unified diff"""
+
+    old_start: int
+    old_lines: int
+    new_start: int
+    new_lines: int
+    lines: list[str]
+    section: str = ""
+
+
+class FileEdit(BaseModel):
+    """Changes to a single file proposed by the agent"""
+
+    path: str
+    hunks: list[DiffHunk]
+    diff: str
+    created: bool = False
+    deleted: bool = False
+
+
+class ProposedEdits(BaseModel):
+    """Code edits proposed in a session's latest response"""
+
+    session_id: str
+    edits: list[FileEdit] = Field(default_factory=list)
+
+
+class HandoffRequest(BaseModel):
+    """Request to hand a session over to humans"""
+
+    targets: Optional[list[str]] = None
+    notes: Optional[str] = None
+    dry_run: bool = False
+
+
+class HandoffBundle(BaseModel):
+    """What a conversation established, for the humans taking it over"""
+
+    conversation_id: str
+    title: str
+    summary: str
+    message_count: int
+    started_at: str
+    last_message_at: str
+    findings: list[str] = Field(default_factory=list)
+    citations: list[str] = Field(default_factory=list)
+    commands: list[str] = Field(default_factory=list)
+    open_questions: list[str] = Field(default_factory=list)
+    notes: Optional[str] = None
+
+
+class HandoffDelivery(BaseModel):
+    """Outcome of posting a handoff to one endpoint"""
+
+    endpoint_id: str
+    delivered: bool
+    error: Optional[str] = None
+
+
+class HandoffResponse(BaseModel):
+    """A session's handoff bundle and where it was posted"""
+
+    bundle: HandoffBundle
+    document: str
+    deliveries: list[HandoffDelivery] = Field(default_factory=list)
+
+
+class PrefetchOutcome(BaseModel):
+    """What a context prefetch request did"""
+
+    pass
+
+
+class PrefetchStats(BaseModel):
+    """How often prefetched context was used and the latency it saved"""
+
+    prefetches: int
+    prefetches_skipped: int
+    hits: int
+    misses: int
+    hit_rate: float
+    avg_hit_latency_ms: float
+    avg_miss_latency_ms: float
+
+
+class EmbeddingCacheStats(BaseModel):
+    """Embedding cache hits by tier"""
+
+    memory_hits: int
+    shared_hits: int
+    misses: int
+    hit_rate: float
+    shared_errors: int
+    entries: int
+
+
+class EmbeddingWarmup(BaseModel):
+    """What an embedding cache warmup did"""
+
+    texts: int
+    already_cached: int
+    embedded: int
+
+
+class ContextWarmup(BaseModel):
+    """What a warmup of archived context did"""
+
+    rehydrated: int
+
+
+class CheckRunContext(BaseModel):
+    """GitHub check run a gate reports on"""
+
+    repository: str
+    head_sha: str
+    head_branch: Optional[str] = None
+    pull_request: Optional[int] = None
+    run_id: Optional[int] = None
+    check_name: Optional[str] = None
+    details_url: Optional[str] = None
+
+
+class GateTarget(BaseModel):
+    """What a gate runs: a named workflow (`kind = "workflow"`) or the benchmarks matching a filter (`kind = "benchmarks"`)"""
+
+    kind: str
+    name: Optional[str] = None
+    filter: Optional[str] = None
+    max_duration_ms: Optional[int] = None
+
+
+class GateRequest(BaseModel):
+    """Request to run a CI gate"""
+
+    context: CheckRunContext
+    target: GateTarget
+    timeout_secs: Optional[int] = None
+
+
+class GateCheck(BaseModel):
+    """One workflow step or benchmark behind a gate verdict"""
+
+    name: str
+    conclusion: str
+    duration_ms: Optional[int] = None
+    message: Optional[str] = None
+
+
+class CheckOutput(BaseModel):
+    """Check-run `output` object"""
+
+    title: str
+    summary: str
+    text: str
+
+
+class GateVerdict(BaseModel):
+    """Outcome of a gate; apart from `checks`, the fields are those of GitHub's create-check-run request body"""
+
+    name: str
+    head_sha: str
+    status: str
+    conclusion: str
+    started_at: str
+    completed_at: str
+    external_id: str
+    output: CheckOutput
+    details_url: Optional[str] = None
+    checks: list[GateCheck] = Field(default_factory=list)
+
+
+class Verbosity(BaseModel):
+    """How long answers should be"""
+
+    pass
+
+
+class UserPreferences(BaseModel):
+    """A user's confirmed preferences, applied to their sessions' prompts"""
+
+    verbosity: Optional[Verbosity] = None
+    language: Optional[str] = None
+    default_time_range: Optional[str] = None
+    favorite_services: list[str] = Field(default_factory=list)
+    learning: bool = True
+    updated_at: Optional[str] = None
+
+
+class PreferencesResponse(BaseModel):
+    """The caller's preferences and the learned ones awaiting confirmation"""
+
+    preferences: UserPreferences
+    suggestions: list[PreferenceSuggestion] = Field(default_factory=list)
@@ -0,0 +1,29 @@
+"""Tests for the models generated from the Rust SDK."""
+
+from llm_copilot.generated import ChatResponse, Conversation, HealthResponse
+
+
+def test_chat_response_from_server_payload():
+    """Generated models accept the payloads the Rust SDK deserializes."""
+    response = ChatResponse.model_validate(
+        {
+            "content": "Hello!",
+            "conversation_id": "conv-1",
+            "usage": {"prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5},
+        }
+    )
+
+    assert response.usage is not None
+    assert response.usage.total_tokens == 5
+    assert response.finish_reason is None
+
+
+def test_defaults_match_serde_defaults():
+    """Fields with serde defaults are optional on the Python side too."""
+    conversation = Conversation.model_validate(
+        {"id": "conv-1", "created_at": "2024-01-01T00:00:00Z", "message_count": 0}
+    )
+    health = HealthResponse.model_validate({"status": "ok", "version": "1.0.0"})
+
+    assert conversation.messages == []
+    assert health.services == {}